@@ -1,30 +1,50 @@
 use actix::prelude::*;
-use oauth2_events::{AuthEvent, EventBusHandle, EventEnvelope, EventSeverity, EventType};
-use oauth2_observability::annotate_span_with_trace_ids;
-use oauth2_ports::DynStorage;
-use rand::Rng;
+use oauth2_events::EventBusHandle;
+use oauth2_observability::{annotate_span_with_trace_ids, semconv};
+use oauth2_ports::{
+    AuthService, CodeRedemption, DynStorage, DynUserAuthenticator, NewAuthorizationCode,
+};
 use tracing::Instrument;
 
-use oauth2_core::{AuthorizationCode, OAuth2Error};
+use oauth2_core::{
+    AuthorizationCode, ContextBinding, ContextTolerance, OAuth2Error, Redactor, User,
+};
 
+use crate::deadline::{Deadline, DeadlineResponse};
+use crate::security_events::SecurityEvents;
+
+/// Runs [`AuthService`] operations for actor messages.
 pub struct AuthActor {
-    db: DynStorage,
-    event_bus: Option<EventBusHandle>,
+    service: AuthService,
 }
 
 impl AuthActor {
     pub fn new(db: DynStorage) -> Self {
-        Self {
-            db,
-            event_bus: None,
-        }
+        Self::from_service(AuthService::new(db))
     }
 
     pub fn with_events(db: DynStorage, event_bus: EventBusHandle) -> Self {
-        Self {
-            db,
-            event_bus: Some(event_bus),
-        }
+        Self::from_service(AuthService::new(db).with_events(event_bus))
+    }
+
+    pub fn from_service(service: AuthService) -> Self {
+        Self { service }
+    }
+
+    /// Check passwords with `authenticator` instead of the hashes in storage.
+    pub fn with_authenticator(self, authenticator: DynUserAuthenticator) -> Self {
+        Self::from_service(self.service.with_authenticator(authenticator))
+    }
+
+    /// Record the requesting context on new codes and reject redemption from a context
+    /// `tolerance` does not accept.
+    pub fn with_context_binding(self, enabled: bool, tolerance: ContextTolerance) -> Self {
+        Self::from_service(self.service.with_context_binding(enabled, tolerance))
+    }
+
+    /// The service the actor runs, for callers outside the actor system.
+    pub fn service(&self) -> &AuthService {
+        &self.service
     }
 }
 
@@ -32,6 +52,7 @@ impl Actor for AuthActor {
     type Context = Context<Self>;
 }
 
+/// [`AuthService::create_authorization_code`].
 #[derive(Message)]
 #[rtype(result = "Result<AuthorizationCode, OAuth2Error>")]
 pub struct CreateAuthorizationCode {
@@ -41,16 +62,21 @@ pub struct CreateAuthorizationCode {
     pub scope: String,
     pub code_challenge: Option<String>,
     pub code_challenge_method: Option<String>,
+    /// Context of the authorize request; recorded only when binding is enabled.
+    pub context: ContextBinding,
+    /// Tenant the authorize request was addressed to; only it can redeem the code.
+    pub tenant_id: Option<String>,
     pub span: tracing::Span,
+    pub deadline: Deadline,
 }
 
 impl Handler<CreateAuthorizationCode> for AuthActor {
     type Result = ResponseFuture<Result<AuthorizationCode, OAuth2Error>>;
 
     fn handle(&mut self, msg: CreateAuthorizationCode, _: &mut Self::Context) -> Self::Result {
-        let db = self.db.clone();
-        let event_bus = self.event_bus.clone();
+        let service = self.service.clone();
 
+        let deadline = msg.deadline;
         let parent_span = msg.span.clone();
         let actor_span = tracing::info_span!(
             parent: &parent_span,
@@ -58,47 +84,27 @@ impl Handler<CreateAuthorizationCode> for AuthActor {
             trace_id = tracing::field::Empty,
             span_id = tracing::field::Empty,
             client_id = %msg.client_id,
-            user_id = %msg.user_id
+            enduser.id = %semconv::enduser_id(&msg.user_id)
         );
         annotate_span_with_trace_ids(&actor_span);
 
-        Box::pin(
-            async move {
-                let code = generate_code();
-                let auth_code = AuthorizationCode::new(
-                    code,
-                    msg.client_id.clone(),
-                    msg.user_id.clone(),
-                    msg.redirect_uri.clone(),
-                    msg.scope.clone(),
-                    msg.code_challenge,
-                    msg.code_challenge_method,
-                );
-
-                db.save_authorization_code(&auth_code).await?;
-
-                // Emit event
-                if let Some(event_bus) = event_bus {
-                    let event = AuthEvent::new(
-                        EventType::AuthorizationCodeCreated,
-                        EventSeverity::Info,
-                        Some(msg.user_id.clone()),
-                        Some(msg.client_id.clone()),
-                    )
-                    .with_metadata("scope", msg.scope)
-                    .with_metadata("redirect_uri", msg.redirect_uri);
-
-                    let envelope = EventEnvelope::from_current_span(event, "oauth2_server");
-                    event_bus.publish_best_effort(envelope);
-                }
-
-                Ok(auth_code)
-            }
-            .instrument(actor_span),
+        let request = NewAuthorizationCode {
+            client_id: msg.client_id,
+            user_id: msg.user_id,
+            redirect_uri: msg.redirect_uri,
+            scope: msg.scope,
+            code_challenge: msg.code_challenge,
+            code_challenge_method: msg.code_challenge_method,
+            context: msg.context,
+            tenant_id: msg.tenant_id,
+        };
+        deadline.response(
+            async move { service.create_authorization_code(request).await }.instrument(actor_span),
         )
     }
 }
 
+/// [`AuthService::validate_authorization_code`].
 #[derive(Message)]
 #[rtype(result = "Result<AuthorizationCode, OAuth2Error>")]
 pub struct ValidateAuthorizationCode {
@@ -106,87 +112,58 @@ pub struct ValidateAuthorizationCode {
     pub client_id: String,
     pub redirect_uri: Option<String>,
     pub code_verifier: Option<String>,
+    /// Context of the token request, compared against the one recorded on the code.
+    pub context: ContextBinding,
+    /// Tenant the token request was addressed to; codes of other tenants are not found.
+    pub tenant_id: Option<String>,
+    /// Reports replayed codes and missing verifiers with the caller's IP and user agent.
+    pub security: SecurityEvents,
     pub span: tracing::Span,
+    pub deadline: Deadline,
 }
 
+/// [`AuthService::mark_authorization_code_used`].
 #[derive(Message)]
 #[rtype(result = "Result<(), OAuth2Error>")]
 pub struct MarkAuthorizationCodeUsed {
     pub code: String,
     pub span: tracing::Span,
+    pub deadline: Deadline,
 }
 
 impl Handler<ValidateAuthorizationCode> for AuthActor {
     type Result = ResponseFuture<Result<AuthorizationCode, OAuth2Error>>;
 
     fn handle(&mut self, msg: ValidateAuthorizationCode, _: &mut Self::Context) -> Self::Result {
-        let db = self.db.clone();
-        let event_bus = self.event_bus.clone();
+        let service = self.service.clone();
 
+        let deadline = msg.deadline;
         let parent_span = msg.span.clone();
-        let code_prefix = msg.code.chars().take(12).collect::<String>();
         let actor_span = tracing::info_span!(
             parent: &parent_span,
             "actor.auth.validate_authorization_code",
             trace_id = tracing::field::Empty,
             span_id = tracing::field::Empty,
             client_id = %msg.client_id,
-            code_prefix = %code_prefix,
+            code = %Redactor::current().token(&msg.code),
             code_len = msg.code.len()
         );
         annotate_span_with_trace_ids(&actor_span);
 
-        Box::pin(
+        let security = msg.security;
+        let request = CodeRedemption {
+            code: msg.code,
+            client_id: msg.client_id,
+            redirect_uri: msg.redirect_uri,
+            code_verifier: msg.code_verifier,
+            context: msg.context,
+            tenant_id: msg.tenant_id,
+        };
+        deadline.response(
             async move {
-                let auth_code = db
-                    .get_authorization_code(&msg.code)
-                    .await?
-                    .ok_or_else(|| OAuth2Error::invalid_grant("Authorization code not found"))?;
-
-                if !auth_code.is_valid() {
-                    // Emit expired event
-                    if let Some(event_bus) = &event_bus {
-                        let event = AuthEvent::new(
-                            EventType::AuthorizationCodeExpired,
-                            EventSeverity::Warning,
-                            Some(auth_code.user_id.clone()),
-                            Some(auth_code.client_id.clone()),
-                        );
-                        let envelope = EventEnvelope::from_current_span(event, "oauth2_server");
-                        event_bus.publish_best_effort(envelope);
-                    }
-
-                    return Err(OAuth2Error::invalid_grant(
-                        "Authorization code is expired or used",
-                    ));
-                }
-
-                if auth_code.client_id != msg.client_id {
-                    return Err(OAuth2Error::invalid_grant("Client ID mismatch"));
-                }
-
-                // OAuth 2.1 removes redirect_uri from the authorization_code token request.
-                // For backward compatibility (OAuth 2.0 clients), we still accept it and
-                // enforce it when provided.
-                if let Some(redirect_uri) = msg.redirect_uri {
-                    if auth_code.redirect_uri != redirect_uri {
-                        return Err(OAuth2Error::invalid_grant("Redirect URI mismatch"));
-                    }
-                }
-
-                // Validate PKCE if present
-                if let Some(challenge) = &auth_code.code_challenge {
-                    let verifier = msg
-                        .code_verifier
-                        .ok_or_else(|| OAuth2Error::invalid_grant("Code verifier required"))?;
-
-                    let method = auth_code.code_challenge_method.as_deref().unwrap_or("S256");
-                    if !validate_pkce(challenge, &verifier, method) {
-                        return Err(OAuth2Error::invalid_grant("Invalid code verifier"));
-                    }
-                }
-
-                Ok(auth_code)
+                service
+                    .validate_authorization_code(request, &security)
+                    .await
             }
             .instrument(actor_span),
         )
@@ -197,85 +174,71 @@ impl Handler<MarkAuthorizationCodeUsed> for AuthActor {
     type Result = ResponseFuture<Result<(), OAuth2Error>>;
 
     fn handle(&mut self, msg: MarkAuthorizationCodeUsed, _: &mut Self::Context) -> Self::Result {
-        let db = self.db.clone();
-        let event_bus = self.event_bus.clone();
+        let service = self.service.clone();
 
+        let deadline = msg.deadline;
         let parent_span = msg.span.clone();
-        let code_prefix = msg.code.chars().take(12).collect::<String>();
         let actor_span = tracing::info_span!(
             parent: &parent_span,
             "actor.auth.mark_authorization_code_used",
             trace_id = tracing::field::Empty,
             span_id = tracing::field::Empty,
-            code_prefix = %code_prefix,
+            code = %Redactor::current().token(&msg.code),
             code_len = msg.code.len()
         );
         annotate_span_with_trace_ids(&actor_span);
 
-        Box::pin(
-            async move {
-                // Idempotent in storage implementations: marking an already-used code used again
-                // should be safe.
-                let auth_code = db
-                    .get_authorization_code(&msg.code)
-                    .await?
-                    .ok_or_else(|| OAuth2Error::invalid_grant("Authorization code not found"))?;
-
-                db.mark_authorization_code_used(&msg.code).await?;
-
-                // Emit validated/consumed event
-                if let Some(event_bus) = event_bus {
-                    let event = AuthEvent::new(
-                        EventType::AuthorizationCodeValidated,
-                        EventSeverity::Info,
-                        Some(auth_code.user_id.clone()),
-                        Some(auth_code.client_id.clone()),
-                    );
-                    let envelope = EventEnvelope::from_current_span(event, "oauth2_server");
-                    event_bus.publish_best_effort(envelope);
-                }
-
-                Ok(())
-            }
-            .instrument(actor_span),
+        deadline.response(
+            async move { service.mark_authorization_code_used(&msg.code).await }
+                .instrument(actor_span),
         )
     }
 }
 
-fn generate_code() -> String {
-    let mut rng = rand::rng();
-    let code: String = (0..32)
-        .map(|_| {
-            let idx = rng.random_range(0..62);
-            match idx {
-                0..=25 => (b'a' + idx) as char,
-                26..=51 => (b'A' + (idx - 26)) as char,
-                _ => (b'0' + (idx - 52)) as char,
-            }
-        })
-        .collect();
-    code
+/// [`AuthService::authenticate_user`]: check a user's password for the Resource Owner
+/// Password Credentials grant.
+#[derive(Message)]
+#[rtype(result = "Result<User, OAuth2Error>")]
+pub struct AuthenticateUser {
+    pub username: String,
+    pub password: String,
+    /// Client the user is signing in to, for the emitted event.
+    pub client_id: String,
+    /// Tenant the request was addressed to; users of other tenants are unknown.
+    pub tenant_id: Option<String>,
+    pub span: tracing::Span,
+    pub deadline: Deadline,
 }
 
-fn validate_pkce(challenge: &str, verifier: &str, method: &str) -> bool {
-    // RFC 7636: code_verifier length MUST be between 43 and 128 characters.
-    // We validate this early so short verifiers can't be used to weaken PKCE.
-    if verifier.len() < 43 || verifier.len() > 128 {
-        return false;
-    }
+impl Handler<AuthenticateUser> for AuthActor {
+    type Result = ResponseFuture<Result<User, OAuth2Error>>;
+
+    fn handle(&mut self, msg: AuthenticateUser, _: &mut Self::Context) -> Self::Result {
+        let service = self.service.clone();
 
-    match method {
-        // Only S256 is supported (OAuth 2.0 Security BCP guidance).
-        "S256" => {
-            use base64::{engine::general_purpose, Engine as _};
-            use sha2::{Digest, Sha256};
+        let deadline = msg.deadline;
+        let parent_span = msg.span.clone();
+        let actor_span = tracing::info_span!(
+            parent: &parent_span,
+            "actor.auth.authenticate_user",
+            trace_id = tracing::field::Empty,
+            span_id = tracing::field::Empty,
+            client_id = %msg.client_id
+        );
+        annotate_span_with_trace_ids(&actor_span);
 
-            let mut hasher = Sha256::new();
-            hasher.update(verifier.as_bytes());
-            let result = hasher.finalize();
-            let encoded = general_purpose::URL_SAFE_NO_PAD.encode(result);
-            challenge == encoded
-        }
-        _ => false,
+        deadline.response(
+            async move {
+                service
+                    .authenticate_user(
+                        &msg.username,
+                        &msg.password,
+                        &msg.client_id,
+                        msg.tenant_id.as_deref(),
+                    )
+                    .await
+            }
+            .instrument(actor_span),
+        )
     }
 }
@@ -1,30 +1,57 @@
 use actix::prelude::*;
-use oauth2_events::{AuthEvent, EventBusHandle, EventEnvelope, EventSeverity, EventType};
+use oauth2_events::EventBusHandle;
 use oauth2_observability::annotate_span_with_trace_ids;
-use oauth2_ports::DynStorage;
-use rand::Rng;
+use oauth2_ports::{
+    AuthorizedClientCredentials, ClientCredentials, ClientService, DynStorage, SecurityEvents,
+};
 use tracing::Instrument;
 
-use oauth2_core::{Client, ClientRegistration, OAuth2Error};
+use oauth2_core::{
+    Client, ClientRegistration, ClientUpdateRequest, OAuth2Error, RedirectUriChangePolicy,
+    ServiceAccount,
+};
 
+use crate::deadline::{Deadline, DeadlineResponse};
+
+pub(crate) use oauth2_ports::generate_secret;
+pub use oauth2_ports::RegisteredClient;
+
+/// Runs [`ClientService`] operations for actor messages.
 pub struct ClientActor {
-    db: DynStorage,
-    event_bus: Option<EventBusHandle>,
+    service: ClientService,
 }
 
 impl ClientActor {
     pub fn new(db: DynStorage) -> Self {
-        Self {
-            db,
-            event_bus: None,
-        }
+        Self::from_service(ClientService::new(db))
     }
 
     pub fn with_events(db: DynStorage, event_bus: EventBusHandle) -> Self {
-        Self {
-            db,
-            event_bus: Some(event_bus),
-        }
+        Self::from_service(ClientService::new(db).with_events(event_bus))
+    }
+
+    pub fn from_service(service: ClientService) -> Self {
+        Self { service }
+    }
+
+    /// Hold back redirect URI changes made through registration updates (see
+    /// [`RedirectUriChangePolicy`]). `delay_secs` only applies to `Delayed`.
+    pub fn with_redirect_uri_changes(
+        self,
+        policy: RedirectUriChangePolicy,
+        delay_secs: u64,
+    ) -> Self {
+        Self::from_service(self.service.with_redirect_uri_changes(policy, delay_secs))
+    }
+
+    /// Refuse `admin_scope` in registrations (see [`ClientService::with_admin_scope`]).
+    pub fn with_admin_scope(self, admin_scope: impl Into<String>) -> Self {
+        Self::from_service(self.service.with_admin_scope(admin_scope))
+    }
+
+    /// The service the actor runs, for callers outside the actor system.
+    pub fn service(&self) -> &ClientService {
+        &self.service
     }
 }
 
@@ -32,20 +59,24 @@ impl Actor for ClientActor {
     type Context = Context<Self>;
 }
 
+/// [`ClientService::register`].
 #[derive(Message)]
-#[rtype(result = "Result<Client, OAuth2Error>")]
+#[rtype(result = "Result<RegisteredClient, OAuth2Error>")]
 pub struct RegisterClient {
     pub registration: ClientRegistration,
+    /// Tenant the client is registered in; `None` for the default tenant.
+    pub tenant_id: Option<String>,
     pub span: tracing::Span,
+    pub deadline: Deadline,
 }
 
 impl Handler<RegisterClient> for ClientActor {
-    type Result = ResponseFuture<Result<Client, OAuth2Error>>;
+    type Result = ResponseFuture<Result<RegisteredClient, OAuth2Error>>;
 
     fn handle(&mut self, msg: RegisterClient, _: &mut Self::Context) -> Self::Result {
-        let db = self.db.clone();
-        let event_bus = self.event_bus.clone();
+        let service = self.service.clone();
 
+        let deadline = msg.deadline;
         let parent_span = msg.span.clone();
         let actor_span = tracing::info_span!(
             parent: &parent_span,
@@ -57,58 +88,31 @@ impl Handler<RegisterClient> for ClientActor {
         );
         annotate_span_with_trace_ids(&actor_span);
 
-        Box::pin(
-            async move {
-                // Generate client credentials
-                let client_id = format!("client_{}", uuid::Uuid::new_v4());
-                let client_secret = generate_secret();
-
-                let client = Client::new(
-                    client_id.clone(),
-                    client_secret,
-                    msg.registration.redirect_uris,
-                    msg.registration.grant_types,
-                    msg.registration.scope.clone(),
-                    msg.registration.client_name.clone(),
-                );
-
-                db.save_client(&client).await?;
-
-                // Emit event
-                if let Some(event_bus) = event_bus {
-                    let event = AuthEvent::new(
-                        EventType::ClientRegistered,
-                        EventSeverity::Info,
-                        None,
-                        Some(client_id),
-                    )
-                    .with_metadata("client_name", msg.registration.client_name)
-                    .with_metadata("scope", msg.registration.scope);
-
-                    let envelope = EventEnvelope::from_current_span(event, "oauth2_server");
-                    event_bus.publish_best_effort(envelope);
-                }
-
-                Ok(client)
-            }
-            .instrument(actor_span),
+        deadline.response(
+            async move { service.register(msg.registration, msg.tenant_id).await }
+                .instrument(actor_span),
         )
     }
 }
 
+/// [`ClientService::get`].
 #[derive(Message)]
 #[rtype(result = "Result<Client, OAuth2Error>")]
 pub struct GetClient {
     pub client_id: String,
+    /// Tenant the request was addressed to; clients of other tenants are not found.
+    pub tenant_id: Option<String>,
     pub span: tracing::Span,
+    pub deadline: Deadline,
 }
 
 impl Handler<GetClient> for ClientActor {
     type Result = ResponseFuture<Result<Client, OAuth2Error>>;
 
     fn handle(&mut self, msg: GetClient, _: &mut Self::Context) -> Self::Result {
-        let db = self.db.clone();
+        let service = self.service.clone();
 
+        let deadline = msg.deadline;
         let parent_span = msg.span.clone();
         let actor_span = tracing::info_span!(
             parent: &parent_span,
@@ -119,32 +123,64 @@ impl Handler<GetClient> for ClientActor {
         );
         annotate_span_with_trace_ids(&actor_span);
 
-        Box::pin(
-            async move {
-                db.get_client(&msg.client_id)
-                    .await?
-                    .ok_or_else(|| OAuth2Error::invalid_client("Client not found"))
-            }
-            .instrument(actor_span),
+        deadline.response(
+            async move { service.get(&msg.client_id, msg.tenant_id.as_deref()).await }
+                .instrument(actor_span),
+        )
+    }
+}
+
+/// [`ClientService::get_service_account`]: the service account backed by a client, if any.
+#[derive(Message)]
+#[rtype(result = "Result<Option<ServiceAccount>, OAuth2Error>")]
+pub struct GetServiceAccount {
+    pub client_id: String,
+    pub span: tracing::Span,
+    pub deadline: Deadline,
+}
+
+impl Handler<GetServiceAccount> for ClientActor {
+    type Result = ResponseFuture<Result<Option<ServiceAccount>, OAuth2Error>>;
+
+    fn handle(&mut self, msg: GetServiceAccount, _: &mut Self::Context) -> Self::Result {
+        let service = self.service.clone();
+
+        let deadline = msg.deadline;
+        let parent_span = msg.span.clone();
+        let actor_span = tracing::info_span!(
+            parent: &parent_span,
+            "actor.client.get_service_account",
+            trace_id = tracing::field::Empty,
+            span_id = tracing::field::Empty,
+            client_id = %msg.client_id
+        );
+        annotate_span_with_trace_ids(&actor_span);
+
+        deadline.response(
+            async move { service.get_service_account(&msg.client_id).await }.instrument(actor_span),
         )
     }
 }
 
+/// [`ClientService::validate`].
 #[derive(Message)]
 #[rtype(result = "Result<bool, OAuth2Error>")]
 pub struct ValidateClient {
     pub client_id: String,
     pub client_secret: String,
+    /// Tenant the request was addressed to; clients of other tenants are not found.
+    pub tenant_id: Option<String>,
     pub span: tracing::Span,
+    pub deadline: Deadline,
 }
 
 impl Handler<ValidateClient> for ClientActor {
     type Result = ResponseFuture<Result<bool, OAuth2Error>>;
 
     fn handle(&mut self, msg: ValidateClient, _: &mut Self::Context) -> Self::Result {
-        let db = self.db.clone();
-        let event_bus = self.event_bus.clone();
+        let service = self.service.clone();
 
+        let deadline = msg.deadline;
         let parent_span = msg.span.clone();
         let actor_span = tracing::info_span!(
             parent: &parent_span,
@@ -155,53 +191,397 @@ impl Handler<ValidateClient> for ClientActor {
         );
         annotate_span_with_trace_ids(&actor_span);
 
-        Box::pin(
+        deadline.response(
+            async move {
+                service
+                    .validate(&msg.client_id, &msg.client_secret, msg.tenant_id.as_deref())
+                    .await
+            }
+            .instrument(actor_span),
+        )
+    }
+}
+
+/// [`ClientService::authenticate`]: the client calling an endpoint such as introspection.
+#[derive(Message)]
+#[rtype(result = "Result<Client, OAuth2Error>")]
+pub struct AuthenticateClient {
+    pub client_id: String,
+    pub client_secret: String,
+    /// Tenant the request was addressed to; clients of other tenants are not found.
+    pub tenant_id: Option<String>,
+    /// Reports failures with the caller's IP and user agent.
+    pub security: SecurityEvents,
+    /// Path of the endpoint, for the failure event.
+    pub endpoint: String,
+    pub span: tracing::Span,
+    pub deadline: Deadline,
+}
+
+impl Handler<AuthenticateClient> for ClientActor {
+    type Result = ResponseFuture<Result<Client, OAuth2Error>>;
+
+    fn handle(&mut self, msg: AuthenticateClient, _: &mut Self::Context) -> Self::Result {
+        let service = self.service.clone();
+
+        let deadline = msg.deadline;
+        let parent_span = msg.span.clone();
+        let actor_span = tracing::info_span!(
+            parent: &parent_span,
+            "actor.client.authenticate",
+            trace_id = tracing::field::Empty,
+            span_id = tracing::field::Empty,
+            client_id = %msg.client_id
+        );
+        annotate_span_with_trace_ids(&actor_span);
+
+        deadline.response(
             async move {
-                let client = db
-                    .get_client(&msg.client_id)
-                    .await?
-                    .ok_or_else(|| OAuth2Error::invalid_client("Client not found"))?;
-
-                // Use constant-time comparison to prevent timing attacks
-                use subtle::ConstantTimeEq;
-                let secret_match = client
-                    .client_secret
-                    .as_bytes()
-                    .ct_eq(msg.client_secret.as_bytes())
-                    .into();
-
-                // Emit event
-                if let Some(event_bus) = event_bus {
-                    let event = AuthEvent::new(
-                        EventType::ClientValidated,
-                        EventSeverity::Info,
-                        None,
-                        Some(msg.client_id),
+                service
+                    .authenticate(
+                        &msg.client_id,
+                        &msg.client_secret,
+                        msg.tenant_id.as_deref(),
+                        &msg.security,
+                        &msg.endpoint,
                     )
-                    .with_metadata("success", if secret_match { "true" } else { "false" });
+                    .await
+            }
+            .instrument(actor_span),
+        )
+    }
+}
+
+/// [`ClientService::authenticate_secret`]: authenticate a client at the token endpoint.
+#[derive(Message)]
+#[rtype(result = "Result<(), OAuth2Error>")]
+pub struct AuthenticateClientSecret {
+    pub client: Client,
+    pub client_secret: Option<String>,
+    pub span: tracing::Span,
+    pub deadline: Deadline,
+}
+
+impl Handler<AuthenticateClientSecret> for ClientActor {
+    type Result = ResponseFuture<Result<(), OAuth2Error>>;
+
+    fn handle(&mut self, msg: AuthenticateClientSecret, _: &mut Self::Context) -> Self::Result {
+        let service = self.service.clone();
+
+        let deadline = msg.deadline;
+        let parent_span = msg.span.clone();
+        let actor_span = tracing::info_span!(
+            parent: &parent_span,
+            "actor.client.authenticate_secret",
+            trace_id = tracing::field::Empty,
+            span_id = tracing::field::Empty,
+            client_id = %msg.client.client_id
+        );
+        annotate_span_with_trace_ids(&actor_span);
+
+        deadline.response(
+            async move {
+                service
+                    .authenticate_secret(&msg.client, msg.client_secret.as_deref())
+                    .await
+            }
+            .instrument(actor_span),
+        )
+    }
+}
+
+/// [`ClientService::authorize_client_credentials`].
+#[derive(Message)]
+#[rtype(result = "Result<AuthorizedClientCredentials, OAuth2Error>")]
+pub struct AuthorizeClientCredentials {
+    pub client: Client,
+    pub credentials: ClientCredentials,
+    /// Audience client assertions must be addressed to.
+    pub token_endpoint: String,
+    pub span: tracing::Span,
+    pub deadline: Deadline,
+}
+
+impl Handler<AuthorizeClientCredentials> for ClientActor {
+    type Result = ResponseFuture<Result<AuthorizedClientCredentials, OAuth2Error>>;
 
-                    let envelope = EventEnvelope::from_current_span(event, "oauth2_server");
-                    event_bus.publish_best_effort(envelope);
-                }
+    fn handle(&mut self, msg: AuthorizeClientCredentials, _: &mut Self::Context) -> Self::Result {
+        let service = self.service.clone();
+
+        let deadline = msg.deadline;
+        let parent_span = msg.span.clone();
+        let actor_span = tracing::info_span!(
+            parent: &parent_span,
+            "actor.client.authorize_client_credentials",
+            trace_id = tracing::field::Empty,
+            span_id = tracing::field::Empty,
+            client_id = %msg.client.client_id
+        );
+        annotate_span_with_trace_ids(&actor_span);
 
-                Ok(secret_match)
+        deadline.response(
+            async move {
+                service
+                    .authorize_client_credentials(&msg.client, msg.credentials, &msg.token_endpoint)
+                    .await
             }
             .instrument(actor_span),
         )
     }
 }
 
-fn generate_secret() -> String {
-    let mut rng = rand::rng();
-    let secret: String = (0..32)
-        .map(|_| {
-            let idx = rng.random_range(0..62);
-            match idx {
-                0..=25 => (b'a' + idx) as char,
-                26..=51 => (b'A' + (idx - 26)) as char,
-                _ => (b'0' + (idx - 52)) as char,
+/// [`ClientService::read_registration`]: read a client's registration (RFC 7592 section
+/// 2.1).
+#[derive(Message)]
+#[rtype(result = "Result<Client, OAuth2Error>")]
+pub struct ReadClientRegistration {
+    pub client_id: String,
+    pub registration_access_token: String,
+    /// Tenant the request was addressed to; clients of other tenants are not found.
+    pub tenant_id: Option<String>,
+    pub span: tracing::Span,
+    pub deadline: Deadline,
+}
+
+impl Handler<ReadClientRegistration> for ClientActor {
+    type Result = ResponseFuture<Result<Client, OAuth2Error>>;
+
+    fn handle(&mut self, msg: ReadClientRegistration, _: &mut Self::Context) -> Self::Result {
+        let service = self.service.clone();
+
+        let deadline = msg.deadline;
+        let parent_span = msg.span.clone();
+        let actor_span = tracing::info_span!(
+            parent: &parent_span,
+            "actor.client.read_registration",
+            trace_id = tracing::field::Empty,
+            span_id = tracing::field::Empty,
+            client_id = %msg.client_id
+        );
+        annotate_span_with_trace_ids(&actor_span);
+
+        deadline.response(
+            async move {
+                service
+                    .read_registration(
+                        &msg.client_id,
+                        &msg.registration_access_token,
+                        msg.tenant_id.as_deref(),
+                    )
+                    .await
             }
-        })
-        .collect();
-    secret
+            .instrument(actor_span),
+        )
+    }
+}
+
+/// [`ClientService::update_registration`]: replace a client's registered metadata (RFC 7592
+/// section 2.2).
+#[derive(Message)]
+#[rtype(result = "Result<Client, OAuth2Error>")]
+pub struct UpdateClientRegistration {
+    pub client_id: String,
+    pub registration_access_token: String,
+    pub update: ClientUpdateRequest,
+    /// Tenant the request was addressed to; clients of other tenants are not found.
+    pub tenant_id: Option<String>,
+    pub span: tracing::Span,
+    pub deadline: Deadline,
+}
+
+impl Handler<UpdateClientRegistration> for ClientActor {
+    type Result = ResponseFuture<Result<Client, OAuth2Error>>;
+
+    fn handle(&mut self, msg: UpdateClientRegistration, _: &mut Self::Context) -> Self::Result {
+        let service = self.service.clone();
+
+        let deadline = msg.deadline;
+        let parent_span = msg.span.clone();
+        let actor_span = tracing::info_span!(
+            parent: &parent_span,
+            "actor.client.update_registration",
+            trace_id = tracing::field::Empty,
+            span_id = tracing::field::Empty,
+            client_id = %msg.client_id
+        );
+        annotate_span_with_trace_ids(&actor_span);
+
+        deadline.response(
+            async move {
+                service
+                    .update_registration(
+                        &msg.client_id,
+                        &msg.registration_access_token,
+                        msg.tenant_id.as_deref(),
+                        msg.update,
+                    )
+                    .await
+            }
+            .instrument(actor_span),
+        )
+    }
+}
+
+/// [`ClientService::approve_redirect_uri_change`]: apply a client's held-back redirect URIs
+/// now (admin approval).
+#[derive(Message)]
+#[rtype(result = "Result<Client, OAuth2Error>")]
+pub struct ApproveRedirectUriChange {
+    pub client_id: String,
+    /// Tenant of the client; `None` for the default tenant.
+    pub tenant_id: Option<String>,
+    pub span: tracing::Span,
+    pub deadline: Deadline,
+}
+
+impl Handler<ApproveRedirectUriChange> for ClientActor {
+    type Result = ResponseFuture<Result<Client, OAuth2Error>>;
+
+    fn handle(&mut self, msg: ApproveRedirectUriChange, _: &mut Self::Context) -> Self::Result {
+        let service = self.service.clone();
+
+        let deadline = msg.deadline;
+        let parent_span = msg.span.clone();
+        let actor_span = tracing::info_span!(
+            parent: &parent_span,
+            "actor.client.approve_redirect_uris",
+            trace_id = tracing::field::Empty,
+            span_id = tracing::field::Empty,
+            client_id = %msg.client_id
+        );
+        annotate_span_with_trace_ids(&actor_span);
+
+        deadline.response(
+            async move {
+                service
+                    .approve_redirect_uri_change(&msg.client_id, msg.tenant_id.as_deref())
+                    .await
+            }
+            .instrument(actor_span),
+        )
+    }
+}
+
+/// [`ClientService::reject_redirect_uri_change`]: discard a client's held-back redirect
+/// URIs (admin rejection).
+#[derive(Message)]
+#[rtype(result = "Result<Client, OAuth2Error>")]
+pub struct RejectRedirectUriChange {
+    pub client_id: String,
+    /// Tenant of the client; `None` for the default tenant.
+    pub tenant_id: Option<String>,
+    pub span: tracing::Span,
+    pub deadline: Deadline,
+}
+
+impl Handler<RejectRedirectUriChange> for ClientActor {
+    type Result = ResponseFuture<Result<Client, OAuth2Error>>;
+
+    fn handle(&mut self, msg: RejectRedirectUriChange, _: &mut Self::Context) -> Self::Result {
+        let service = self.service.clone();
+
+        let deadline = msg.deadline;
+        let parent_span = msg.span.clone();
+        let actor_span = tracing::info_span!(
+            parent: &parent_span,
+            "actor.client.reject_redirect_uris",
+            trace_id = tracing::field::Empty,
+            span_id = tracing::field::Empty,
+            client_id = %msg.client_id
+        );
+        annotate_span_with_trace_ids(&actor_span);
+
+        deadline.response(
+            async move {
+                service
+                    .reject_redirect_uri_change(&msg.client_id, msg.tenant_id.as_deref())
+                    .await
+            }
+            .instrument(actor_span),
+        )
+    }
+}
+
+/// [`ClientService::delete_registration`]: deprovision a client (RFC 7592 section 2.3).
+#[derive(Message)]
+#[rtype(result = "Result<(), OAuth2Error>")]
+pub struct DeleteClientRegistration {
+    pub client_id: String,
+    pub registration_access_token: String,
+    /// Tenant the request was addressed to; clients of other tenants are not found.
+    pub tenant_id: Option<String>,
+    pub span: tracing::Span,
+    pub deadline: Deadline,
+}
+
+impl Handler<DeleteClientRegistration> for ClientActor {
+    type Result = ResponseFuture<Result<(), OAuth2Error>>;
+
+    fn handle(&mut self, msg: DeleteClientRegistration, _: &mut Self::Context) -> Self::Result {
+        let service = self.service.clone();
+
+        let deadline = msg.deadline;
+        let parent_span = msg.span.clone();
+        let actor_span = tracing::info_span!(
+            parent: &parent_span,
+            "actor.client.delete_registration",
+            trace_id = tracing::field::Empty,
+            span_id = tracing::field::Empty,
+            client_id = %msg.client_id
+        );
+        annotate_span_with_trace_ids(&actor_span);
+
+        deadline.response(
+            async move {
+                service
+                    .delete_registration(
+                        &msg.client_id,
+                        &msg.registration_access_token,
+                        msg.tenant_id.as_deref(),
+                    )
+                    .await
+            }
+            .instrument(actor_span),
+        )
+    }
+}
+
+/// [`ClientService::delete`]: delete a client, with its tokens, authorization codes and
+/// service account (admin).
+#[derive(Message)]
+#[rtype(result = "Result<Client, OAuth2Error>")]
+pub struct DeleteClient {
+    pub client_id: String,
+    /// Tenant of the client; `None` for the default tenant.
+    pub tenant_id: Option<String>,
+    pub span: tracing::Span,
+    pub deadline: Deadline,
+}
+
+impl Handler<DeleteClient> for ClientActor {
+    type Result = ResponseFuture<Result<Client, OAuth2Error>>;
+
+    fn handle(&mut self, msg: DeleteClient, _: &mut Self::Context) -> Self::Result {
+        let service = self.service.clone();
+
+        let deadline = msg.deadline;
+        let parent_span = msg.span.clone();
+        let actor_span = tracing::info_span!(
+            parent: &parent_span,
+            "actor.client.delete",
+            trace_id = tracing::field::Empty,
+            span_id = tracing::field::Empty,
+            client_id = %msg.client_id
+        );
+        annotate_span_with_trace_ids(&actor_span);
+
+        deadline.response(
+            async move {
+                service
+                    .delete(&msg.client_id, msg.tenant_id.as_deref())
+                    .await
+            }
+            .instrument(actor_span),
+        )
+    }
 }
@@ -1,57 +1,168 @@
 use actix::prelude::*;
-use oauth2_events::{AuthEvent, EventBusHandle, EventEnvelope, EventSeverity, EventType};
-use oauth2_observability::annotate_span_with_trace_ids;
-use oauth2_ports::DynStorage;
+use oauth2_events::EventBusHandle;
+use oauth2_observability::{annotate_span_with_trace_ids, semconv};
+use oauth2_ports::{
+    DynClaimsEnricher, DynClock, DynStorage, DynTokenIssuancePolicy, Introspection, IssueToken,
+    RefreshTokenRequest, TokenService,
+};
 use tracing::Instrument;
 
-use oauth2_core::{Claims, OAuth2Error, Token};
+use oauth2_core::{
+    Claims, Client, ContextBinding, ContextTolerance, IssuerKeys, OAuth2Error, Redactor,
+    TenantContext, Token, TokenMetadata,
+};
 
+use crate::deadline::{Deadline, DeadlineResponse};
+
+pub use oauth2_ports::{ACCESS_TOKEN_TTL_SECS, REFRESH_TOKEN_TTL_SECS};
+
+/// Runs [`TokenService`] operations for actor messages.
 pub struct TokenActor {
-    db: DynStorage,
-    jwt_secret: String,
-    event_bus: Option<EventBusHandle>,
+    service: TokenService,
 }
 
 impl TokenActor {
     pub fn new(db: DynStorage, jwt_secret: String) -> Self {
-        Self {
-            db,
-            jwt_secret,
-            event_bus: None,
-        }
+        Self::from_service(TokenService::new(db, IssuerKeys::from_secret(jwt_secret)))
     }
 
     pub fn with_events(db: DynStorage, jwt_secret: String, event_bus: EventBusHandle) -> Self {
+        Self::new(db, jwt_secret).map(|service| service.with_events(event_bus))
+    }
+
+    pub fn from_service(service: TokenService) -> Self {
+        Self { service }
+    }
+
+    fn map(self, f: impl FnOnce(TokenService) -> TokenService) -> Self {
         Self {
-            db,
-            jwt_secret,
-            event_bus: Some(event_bus),
+            service: f(self.service),
         }
     }
+
+    /// Replace the signing keys, e.g. to use a custom issuer or accept a legacy one.
+    pub fn with_issuer_keys(self, keys: IssuerKeys) -> Self {
+        self.map(|service| service.with_issuer_keys(keys))
+    }
+
+    /// Revoke the whole grant when an access token is revoked, not just that token.
+    /// Revoking a refresh token always revokes its grant.
+    pub fn with_revocation_cascade(self, enabled: bool) -> Self {
+        self.map(|service| service.with_revocation_cascade(enabled))
+    }
+
+    /// Run `enrichers`, in order, on every access token before it is signed.
+    pub fn with_claims_enrichers(self, enrichers: Vec<DynClaimsEnricher>) -> Self {
+        self.map(|service| service.with_claims_enrichers(enrichers))
+    }
+
+    /// Check `policies`, in order, before every token is issued; the first error denies it.
+    pub fn with_issuance_policies(self, policies: Vec<DynTokenIssuancePolicy>) -> Self {
+        self.map(|service| service.with_issuance_policies(policies))
+    }
+
+    /// Time source for the `iat`/`exp` of issued tokens, e.g. a fixed clock in tests.
+    pub fn with_clock(self, clock: DynClock) -> Self {
+        self.map(|service| service.with_clock(clock))
+    }
+
+    /// Record the requesting context on new refresh tokens and reject refreshes from a
+    /// context `tolerance` does not accept.
+    pub fn with_context_binding(self, enabled: bool, tolerance: ContextTolerance) -> Self {
+        self.map(|service| service.with_context_binding(enabled, tolerance))
+    }
+
+    /// The service the actor runs, for callers outside the actor system.
+    pub fn service(&self) -> &TokenService {
+        &self.service
+    }
 }
 
 impl Actor for TokenActor {
     type Context = Context<Self>;
 }
 
+/// [`TokenService::issue`].
 #[derive(Message)]
 #[rtype(result = "Result<Token, OAuth2Error>")]
 pub struct CreateToken {
+    /// Grant the token is issued under, as seen by issuance policies.
+    pub grant_type: String,
     pub user_id: Option<String>,
     pub client_id: String,
     pub scope: String,
     pub include_refresh: bool,
+    /// Tags persisted with the token for later lookup.
+    pub metadata: TokenMetadata,
+    /// Grant the token continues (refresh token rotation); `None` starts a new grant.
+    pub grant_id: Option<String>,
+    /// Authorization code to burn in the same transaction that saves the token, so a code
+    /// is exchanged at most once and never burned without issuing a token.
+    pub consume_code: Option<String>,
+    /// Refresh token to revoke in the same transaction that saves the token, so a refresh
+    /// token is rotated at most once. Losing a race for it counts as refresh token reuse.
+    pub redeem_refresh_token: Option<String>,
+    /// Tenant issuing the token, signed with its keys; `None` for the default tenant.
+    pub tenant: Option<TenantContext>,
+    /// Context the refresh token is bound to when binding is enabled; grants default it
+    /// to the token request's.
+    pub context: Option<ContextBinding>,
     pub span: tracing::Span,
+    pub deadline: Deadline,
+}
+
+impl CreateToken {
+    /// A token for the default tenant without a refresh token, in the current span and
+    /// request deadline; set the other fields to change that.
+    pub fn new(
+        grant_type: impl Into<String>,
+        client_id: impl Into<String>,
+        user_id: Option<String>,
+        scope: impl Into<String>,
+    ) -> Self {
+        Self {
+            grant_type: grant_type.into(),
+            user_id,
+            client_id: client_id.into(),
+            scope: scope.into(),
+            include_refresh: false,
+            metadata: TokenMetadata::default(),
+            grant_id: None,
+            consume_code: None,
+            redeem_refresh_token: None,
+            tenant: None,
+            context: None,
+            span: tracing::Span::current(),
+            deadline: Deadline::current(),
+        }
+    }
+}
+
+impl From<CreateToken> for IssueToken {
+    fn from(msg: CreateToken) -> Self {
+        Self {
+            grant_type: msg.grant_type,
+            user_id: msg.user_id,
+            client_id: msg.client_id,
+            scope: msg.scope,
+            include_refresh: msg.include_refresh,
+            metadata: msg.metadata,
+            grant_id: msg.grant_id,
+            consume_code: msg.consume_code,
+            redeem_refresh_token: msg.redeem_refresh_token,
+            tenant: msg.tenant,
+            context: msg.context,
+        }
+    }
 }
 
 impl Handler<CreateToken> for TokenActor {
     type Result = ResponseFuture<Result<Token, OAuth2Error>>;
 
     fn handle(&mut self, msg: CreateToken, _: &mut Self::Context) -> Self::Result {
-        let db = self.db.clone();
-        let jwt_secret = self.jwt_secret.clone();
-        let event_bus = self.event_bus.clone();
+        let service = self.service.clone();
 
+        let deadline = msg.deadline;
         let parent_span = msg.span.clone();
         let actor_span = tracing::info_span!(
             parent: &parent_span,
@@ -59,214 +170,257 @@ impl Handler<CreateToken> for TokenActor {
             trace_id = tracing::field::Empty,
             span_id = tracing::field::Empty,
             client_id = %msg.client_id,
-            user_id = %msg.user_id.as_deref().unwrap_or(""),
+            enduser.id = %msg.user_id.as_deref().map(semconv::enduser_id).unwrap_or_default(),
             include_refresh = msg.include_refresh
         );
         annotate_span_with_trace_ids(&actor_span);
 
-        Box::pin(
-            async move {
-                let subject = msg.user_id.clone().unwrap_or_else(|| msg.client_id.clone());
-
-                // Create access token
-                let access_claims = Claims::new(
-                    subject.clone(),
-                    msg.client_id.clone(),
-                    msg.scope.clone(),
-                    3600, // 1 hour
-                );
-                let access_token = access_claims
-                    .encode(&jwt_secret)
-                    .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))?;
-
-                // Create refresh token if requested
-                let refresh_token = if msg.include_refresh {
-                    let refresh_claims = Claims::new(
-                        subject,
-                        msg.client_id.clone(),
-                        msg.scope.clone(),
-                        2592000, // 30 days
-                    );
-                    Some(
-                        refresh_claims
-                            .encode(&jwt_secret)
-                            .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))?,
-                    )
-                } else {
-                    None
-                };
-
-                let token = Token::new(
-                    access_token,
-                    refresh_token,
-                    msg.client_id.clone(),
-                    msg.user_id.clone(),
-                    msg.scope.clone(),
-                    3600,
-                );
-
-                db.save_token(&token).await?;
-
-                // Emit event
-                if let Some(event_bus) = event_bus {
-                    let event = AuthEvent::new(
-                        EventType::TokenCreated,
-                        EventSeverity::Info,
-                        msg.user_id,
-                        Some(msg.client_id),
-                    )
-                    .with_metadata("scope", msg.scope)
-                    .with_metadata("has_refresh_token", msg.include_refresh.to_string());
+        deadline.response(async move { service.issue(msg.into()).await }.instrument(actor_span))
+    }
+}
 
-                    let envelope = EventEnvelope::from_current_span(event, "oauth2_server");
-                    event_bus.publish_best_effort(envelope);
-                }
+/// [`TokenService::preview_claims`]: the access token claims `CreateToken` would issue,
+/// without signing or saving a token.
+#[derive(Message)]
+#[rtype(result = "Result<Claims, OAuth2Error>")]
+pub struct PreviewClaims {
+    pub grant_type: String,
+    pub user_id: Option<String>,
+    pub client_id: String,
+    pub scope: String,
+    pub metadata: TokenMetadata,
+    pub span: tracing::Span,
+    pub deadline: Deadline,
+}
 
-                Ok(token)
-            }
-            .instrument(actor_span),
-        )
+impl Handler<PreviewClaims> for TokenActor {
+    type Result = ResponseFuture<Result<Claims, OAuth2Error>>;
+
+    fn handle(&mut self, msg: PreviewClaims, _: &mut Self::Context) -> Self::Result {
+        let service = self.service.clone();
+
+        let deadline = msg.deadline;
+        let parent_span = msg.span.clone();
+        let actor_span = tracing::info_span!(
+            parent: &parent_span,
+            "actor.token.preview_claims",
+            trace_id = tracing::field::Empty,
+            span_id = tracing::field::Empty,
+            client_id = %msg.client_id
+        );
+        annotate_span_with_trace_ids(&actor_span);
+
+        let request = IssueToken {
+            metadata: msg.metadata,
+            ..IssueToken::new(msg.grant_type, msg.client_id, msg.user_id, msg.scope)
+        };
+        deadline
+            .response(async move { service.preview_claims(request).await }.instrument(actor_span))
     }
 }
 
+/// [`TokenService::validate`].
 #[derive(Message)]
 #[rtype(result = "Result<Token, OAuth2Error>")]
 pub struct ValidateToken {
     pub token: String,
     pub span: tracing::Span,
+    pub deadline: Deadline,
 }
 
 impl Handler<ValidateToken> for TokenActor {
     type Result = ResponseFuture<Result<Token, OAuth2Error>>;
 
     fn handle(&mut self, msg: ValidateToken, _: &mut Self::Context) -> Self::Result {
-        let db = self.db.clone();
-        let event_bus = self.event_bus.clone();
+        let service = self.service.clone();
+
+        let deadline = msg.deadline;
         let parent_span = msg.span.clone();
-        let raw_token = msg.token;
-        let token_prefix = raw_token.trim().chars().take(12).collect::<String>();
         let actor_span = tracing::info_span!(
             parent: &parent_span,
             "actor.token.validate",
             trace_id = tracing::field::Empty,
             span_id = tracing::field::Empty,
-            token_prefix = %token_prefix,
-            token_len = raw_token.len()
+            token = %Redactor::current().token(msg.token.trim()),
+            token_len = msg.token.len()
         );
         annotate_span_with_trace_ids(&actor_span);
 
-        Box::pin(
-            async move {
-                // Be forgiving about whitespace and callers that accidentally include a Bearer prefix.
-                let token_trimmed = raw_token.trim();
-                let token_normalized = token_trimmed
-                    .strip_prefix("Bearer ")
-                    .unwrap_or(token_trimmed)
-                    .trim();
-
-                let token_prefix = token_normalized.chars().take(20).collect::<String>();
-                tracing::info!(
-                    token_len = token_normalized.len(),
-                    token_prefix = %token_prefix,
-                    "ValidateToken called"
-                );
-
-                let token = db
-                    .get_token_by_access_token(token_normalized)
-                    .await?
-                    .ok_or_else(|| OAuth2Error::invalid_grant("Token not found"))?;
-
-                if !token.is_valid() {
-                    tracing::warn!(
-                        revoked = token.revoked,
-                        expires_at = %token.expires_at,
-                        now = %chrono::Utc::now(),
-                        token_len = token_normalized.len(),
-                        token_prefix = %token_prefix,
-                        "Token is not valid (expired or revoked)"
-                    );
-                    // Emit expired/invalid event
-                    if let Some(event_bus) = &event_bus {
-                        let event = AuthEvent::new(
-                            EventType::TokenExpired,
-                            EventSeverity::Warning,
-                            token.user_id.clone(),
-                            Some(token.client_id.clone()),
-                        );
-                        let envelope = EventEnvelope::from_current_span(event, "oauth2_server");
-                        event_bus.publish_best_effort(envelope);
-                    }
-
-                    return Err(OAuth2Error::invalid_grant("Token is expired or revoked"));
-                }
-
-                // Emit validated event
-                if let Some(event_bus) = event_bus {
-                    let event = AuthEvent::new(
-                        EventType::TokenValidated,
-                        EventSeverity::Info,
-                        token.user_id.clone(),
-                        Some(token.client_id.clone()),
-                    );
-                    let envelope = EventEnvelope::from_current_span(event, "oauth2_server");
-                    event_bus.publish_best_effort(envelope);
-                }
-
-                Ok(token)
-            }
-            .instrument(actor_span),
+        deadline.response(async move { service.validate(&msg.token).await }.instrument(actor_span))
+    }
+}
+
+/// [`TokenService::check_refresh_token`]: check a refresh token presented for rotation,
+/// returning its token row so the caller can issue the replacement under the same grant.
+/// The replacement's [`CreateToken`] revokes it through `redeem_refresh_token`.
+///
+/// A refresh token that was already redeemed or revoked is treated as stolen and the
+/// whole grant is revoked (refresh token reuse detection).
+#[derive(Message)]
+#[rtype(result = "Result<Token, OAuth2Error>")]
+pub struct RotateRefreshToken {
+    pub refresh_token: String,
+    pub client_id: String,
+    /// Scope requested for the new token; must be within the original grant.
+    pub scope: Option<String>,
+    /// Tenant the request was addressed to; `None` for the default tenant.
+    pub tenant: Option<TenantContext>,
+    /// Context of the token request, compared against the one recorded on the token.
+    pub context: ContextBinding,
+    pub span: tracing::Span,
+    pub deadline: Deadline,
+}
+
+impl RotateRefreshToken {
+    fn into_parts(self) -> (RefreshTokenRequest, tracing::Span, Deadline) {
+        (
+            RefreshTokenRequest {
+                refresh_token: self.refresh_token,
+                client_id: self.client_id,
+                scope: self.scope,
+                tenant: self.tenant,
+                context: self.context,
+            },
+            self.span,
+            self.deadline,
+        )
+    }
+}
+
+impl Handler<RotateRefreshToken> for TokenActor {
+    type Result = ResponseFuture<Result<Token, OAuth2Error>>;
+
+    fn handle(&mut self, msg: RotateRefreshToken, _: &mut Self::Context) -> Self::Result {
+        let service = self.service.clone();
+        let (request, parent_span, deadline) = msg.into_parts();
+        let actor_span = tracing::info_span!(
+            parent: &parent_span,
+            "actor.token.rotate_refresh_token",
+            trace_id = tracing::field::Empty,
+            span_id = tracing::field::Empty,
+            client_id = %request.client_id
+        );
+        annotate_span_with_trace_ids(&actor_span);
+
+        deadline.response(
+            async move { service.check_refresh_token(&request).await }.instrument(actor_span),
         )
     }
 }
 
+/// [`TokenService::refresh`]: rotate a refresh token, issuing its replacement under the
+/// same grant. Takes the same fields as [`RotateRefreshToken`].
+#[derive(Message)]
+#[rtype(result = "Result<Token, OAuth2Error>")]
+pub struct RefreshToken(pub RotateRefreshToken);
+
+impl Handler<RefreshToken> for TokenActor {
+    type Result = ResponseFuture<Result<Token, OAuth2Error>>;
+
+    fn handle(&mut self, msg: RefreshToken, _: &mut Self::Context) -> Self::Result {
+        let service = self.service.clone();
+        let (request, parent_span, deadline) = msg.0.into_parts();
+        let actor_span = tracing::info_span!(
+            parent: &parent_span,
+            "actor.token.refresh",
+            trace_id = tracing::field::Empty,
+            span_id = tracing::field::Empty,
+            client_id = %request.client_id
+        );
+        annotate_span_with_trace_ids(&actor_span);
+
+        deadline.response(async move { service.refresh(request).await }.instrument(actor_span))
+    }
+}
+
+/// [`TokenService::revoke`].
 #[derive(Message)]
 #[rtype(result = "Result<(), OAuth2Error>")]
 pub struct RevokeToken {
     pub token: String,
+    /// RFC 7009 `token_type_hint`: which kind of token to look up first. Unknown hints
+    /// are ignored.
+    pub token_type_hint: Option<String>,
+    /// Only revoke tokens issued to this client; `None` allows any token.
+    pub client_id: Option<String>,
+    /// Tenant the request was addressed to; tokens of other tenants are treated as
+    /// unknown.
+    pub tenant_id: Option<String>,
     pub span: tracing::Span,
+    pub deadline: Deadline,
 }
 
 impl Handler<RevokeToken> for TokenActor {
     type Result = ResponseFuture<Result<(), OAuth2Error>>;
 
     fn handle(&mut self, msg: RevokeToken, _: &mut Self::Context) -> Self::Result {
-        let db = self.db.clone();
-        let event_bus = self.event_bus.clone();
+        let service = self.service.clone();
 
+        let deadline = msg.deadline;
         let parent_span = msg.span.clone();
-        let token_prefix = msg.token.trim().chars().take(12).collect::<String>();
         let actor_span = tracing::info_span!(
             parent: &parent_span,
             "actor.token.revoke",
             trace_id = tracing::field::Empty,
             span_id = tracing::field::Empty,
-            token_prefix = %token_prefix,
+            token = %Redactor::current().token(msg.token.trim()),
+            token_len = msg.token.len(),
+            token_type_hint = msg.token_type_hint.as_deref().unwrap_or("none")
+        );
+        annotate_span_with_trace_ids(&actor_span);
+
+        deadline.response(
+            async move {
+                service
+                    .revoke(
+                        &msg.token,
+                        msg.token_type_hint.as_deref(),
+                        msg.client_id.as_deref(),
+                        msg.tenant_id.as_deref(),
+                    )
+                    .await
+            }
+            .instrument(actor_span),
+        )
+    }
+}
+
+/// [`TokenService::introspect`]: token introspection (RFC 7662) on behalf of the
+/// authenticated `caller`.
+#[derive(Message)]
+#[rtype(result = "Result<Introspection, OAuth2Error>")]
+pub struct IntrospectToken {
+    pub caller: Client,
+    pub token: String,
+    /// Tenant the request was addressed to; tokens of other tenants are inactive.
+    pub tenant: Option<TenantContext>,
+    pub span: tracing::Span,
+    pub deadline: Deadline,
+}
+
+impl Handler<IntrospectToken> for TokenActor {
+    type Result = ResponseFuture<Result<Introspection, OAuth2Error>>;
+
+    fn handle(&mut self, msg: IntrospectToken, _: &mut Self::Context) -> Self::Result {
+        let service = self.service.clone();
+
+        let deadline = msg.deadline;
+        let parent_span = msg.span.clone();
+        let actor_span = tracing::info_span!(
+            parent: &parent_span,
+            "actor.token.introspect",
+            trace_id = tracing::field::Empty,
+            span_id = tracing::field::Empty,
+            caller = %msg.caller.client_id,
             token_len = msg.token.len()
         );
         annotate_span_with_trace_ids(&actor_span);
 
-        Box::pin(
+        deadline.response(
             async move {
-                // Get token info before revoking for event
-                let token_info = db.get_token_by_access_token(&msg.token).await?;
-
-                db.revoke_token(&msg.token).await?;
-
-                // Emit revoked event
-                if let Some(event_bus) = event_bus {
-                    if let Some(token) = token_info {
-                        let event = AuthEvent::new(
-                            EventType::TokenRevoked,
-                            EventSeverity::Info,
-                            token.user_id,
-                            Some(token.client_id),
-                        );
-                        let envelope = EventEnvelope::from_current_span(event, "oauth2_server");
-                        event_bus.publish_best_effort(envelope);
-                    }
-                }
-
-                Ok(())
+                Ok(service
+                    .introspect(&msg.caller, &msg.token, msg.tenant.as_ref())
+                    .await)
             }
             .instrument(actor_span),
         )
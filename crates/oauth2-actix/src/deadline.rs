@@ -0,0 +1,31 @@
+//! Request deadlines.
+//!
+//! [`RequestTimeout`](crate::middleware::timeout::RequestTimeout) sets a deadline for each
+//! request. Handlers pass it on in their actor messages with [`Deadline::current`], and the
+//! actors run the shared services under it, so they stop working on the request once it
+//! has passed.
+
+use actix::ResponseFuture;
+use std::future::Future;
+
+use oauth2_core::OAuth2Error;
+
+pub use oauth2_ports::Deadline;
+
+/// [`Deadline::run`] as the result of an actor's message handler.
+pub trait DeadlineResponse {
+    fn response<T, F>(self, fut: F) -> ResponseFuture<Result<T, OAuth2Error>>
+    where
+        T: 'static,
+        F: Future<Output = Result<T, OAuth2Error>> + 'static;
+}
+
+impl DeadlineResponse for Deadline {
+    fn response<T, F>(self, fut: F) -> ResponseFuture<Result<T, OAuth2Error>>
+    where
+        T: 'static,
+        F: Future<Output = Result<T, OAuth2Error>> + 'static,
+    {
+        Box::pin(self.run(fut))
+    }
+}
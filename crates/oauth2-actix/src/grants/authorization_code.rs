@@ -0,0 +1,79 @@
+use async_trait::async_trait;
+
+use oauth2_core::{Client, GrantType, OAuth2Error, Token};
+
+use super::{GrantContext, GrantHandler, TokenRequest};
+use crate::actors::{CreateToken, ValidateAuthorizationCode};
+use crate::deadline::Deadline;
+
+/// Authorization code grant (RFC 6749 section 4.1), with PKCE.
+pub(super) struct AuthorizationCodeGrant {
+    /// Issue refresh tokens to clients registered for them (`grants.refresh_token`).
+    pub(super) issue_refresh: bool,
+}
+
+#[async_trait]
+impl GrantHandler for AuthorizationCodeGrant {
+    fn grant_type(&self) -> &str {
+        GrantType::AuthorizationCode.as_str()
+    }
+
+    async fn handle(
+        &self,
+        mut request: TokenRequest,
+        client: Client,
+        grant: &GrantContext,
+    ) -> Result<Token, OAuth2Error> {
+        let code = request
+            .take_param("code")
+            .ok_or_else(|| OAuth2Error::invalid_request("Missing code"))?;
+
+        let redirect_uri = request.take_param("redirect_uri");
+        if matches!(redirect_uri.as_deref(), Some("")) {
+            return Err(OAuth2Error::invalid_request(
+                "redirect_uri must not be empty",
+            ));
+        }
+
+        // Validate authorization code
+        let auth_code = grant
+            .auth_actor
+            .send(ValidateAuthorizationCode {
+                code: code.clone(),
+                client_id: request.client_id().to_string(),
+                redirect_uri,
+                code_verifier: request.take_param("code_verifier"),
+                context: grant.binding.clone(),
+                tenant_id: grant.tenant_id().map(str::to_string),
+                security: grant.security.clone(),
+                span: tracing::Span::current(),
+                deadline: Deadline::current(),
+            })
+            .await
+            .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
+
+        // Public clients have no secret; PKCE, required at /oauth/authorize, binds the code.
+        grant
+            .authenticate_client(&client, request.take_param("client_secret"))
+            .await?;
+
+        // Only consume (burn) the authorization code after we've authenticated/authorized
+        // the client. This prevents invalid_client errors from exhausting valid codes. The
+        // code is burned in the transaction that saves the token, so concurrent exchanges
+        // cannot both succeed.
+        grant
+            .issue(CreateToken {
+                include_refresh: self.issue_refresh
+                    && client.supports_grant_type(GrantType::RefreshToken),
+                metadata: request.take_metadata(),
+                consume_code: Some(code),
+                ..CreateToken::new(
+                    self.grant_type(),
+                    auth_code.client_id,
+                    Some(auth_code.user_id),
+                    auth_code.scope,
+                )
+            })
+            .await
+    }
+}
@@ -0,0 +1,65 @@
+use async_trait::async_trait;
+
+use oauth2_core::{Client, GrantType, OAuth2Error, Token};
+use oauth2_ports::{AuthorizedClientCredentials, ClientCredentials};
+
+use super::{GrantContext, GrantHandler, TokenRequest};
+use crate::actors::{AuthorizeClientCredentials, CreateToken};
+use crate::deadline::Deadline;
+
+/// Client credentials grant (RFC 6749 section 4.4): a token for the client itself.
+pub(super) struct ClientCredentialsGrant;
+
+#[async_trait]
+impl GrantHandler for ClientCredentialsGrant {
+    fn grant_type(&self) -> &str {
+        GrantType::ClientCredentials.as_str()
+    }
+
+    async fn handle(
+        &self,
+        mut request: TokenRequest,
+        client: Client,
+        grant: &GrantContext,
+    ) -> Result<Token, OAuth2Error> {
+        // Either the client secret or, for service accounts with a registered key, an
+        // RFC 7523 client assertion.
+        let credentials = ClientCredentials {
+            client_secret: request.take_param("client_secret"),
+            client_assertion_type: request.take_param("client_assertion_type"),
+            client_assertion: request.take_param("client_assertion"),
+            scope: request.take_param("scope"),
+        };
+        let AuthorizedClientCredentials {
+            scope,
+            service_account,
+        } = grant
+            .client_actor
+            .send(AuthorizeClientCredentials {
+                client: client.clone(),
+                credentials,
+                token_endpoint: grant.token_endpoint.clone(),
+                span: tracing::Span::current(),
+                deadline: Deadline::current(),
+            })
+            .await
+            .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
+
+        // Create token (no user, client-only)
+        let token = grant
+            .issue(CreateToken {
+                metadata: request.take_metadata(),
+                ..CreateToken::new(self.grant_type(), client.client_id.clone(), None, scope)
+            })
+            .await?;
+
+        if let Some(account) = &service_account {
+            grant
+                .metrics
+                .oauth_service_account_tokens_issued_total
+                .with_label_values(&[account.name.as_str()])
+                .inc();
+        }
+        Ok(token)
+    }
+}
@@ -0,0 +1,244 @@
+//! Grant types of the token endpoint.
+//!
+//! Every `grant_type` is served by a [`GrantHandler`] looked up in the [`GrantRegistry`].
+//! The built-in grants are registered by [`GrantRegistry::builtin`]; downstream crates add
+//! their own (e.g. token exchange) with [`GrantRegistry::with_handler`], usually through the
+//! server builder.
+
+use actix::Addr;
+use actix_web::web;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use oauth2_config::GrantsConfig;
+use oauth2_core::{
+    tenant_id, Client, ContextBinding, GrantType, OAuth2Error, TenantContext, Token, TokenMetadata,
+};
+use oauth2_observability::Metrics;
+use oauth2_ports::DynStorage;
+
+use crate::actors::{AuthActor, AuthenticateClientSecret, ClientActor, CreateToken, TokenActor};
+use crate::deadline::Deadline;
+use crate::security_events::SecurityEvents;
+
+mod authorization_code;
+mod client_credentials;
+mod password;
+mod refresh_token;
+
+use authorization_code::AuthorizationCodeGrant;
+use client_credentials::ClientCredentialsGrant;
+use password::PasswordGrant;
+use refresh_token::RefreshTokenGrant;
+
+/// Issues tokens for one `grant_type`.
+#[async_trait]
+pub trait GrantHandler: Send + Sync {
+    /// The `grant_type` parameter value this handler serves.
+    fn grant_type(&self) -> &str;
+
+    /// Issue a token for `request`. `client` is registered for this grant type but not yet
+    /// authenticated; see [`GrantContext::authenticate_client`].
+    async fn handle(
+        &self,
+        request: TokenRequest,
+        client: Client,
+        grant: &GrantContext,
+    ) -> Result<Token, OAuth2Error>;
+}
+
+pub type DynGrantHandler = Arc<dyn GrantHandler>;
+
+/// The grant types the token endpoint accepts, in the order they are advertised.
+#[derive(Clone, Default)]
+pub struct GrantRegistry {
+    handlers: Vec<DynGrantHandler>,
+}
+
+impl GrantRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The built-in grants enabled in `grants`.
+    pub fn builtin(grants: &GrantsConfig) -> Self {
+        // Password and refresh_token are off unless enabled (OAuth 2.0 Security BCP).
+        GrantType::ALL
+            .into_iter()
+            .filter(|grant_type| grants.is_enabled(*grant_type))
+            .map(|grant_type| -> DynGrantHandler {
+                match grant_type {
+                    GrantType::AuthorizationCode => Arc::new(AuthorizationCodeGrant {
+                        issue_refresh: grants.refresh_token,
+                    }),
+                    GrantType::ClientCredentials => Arc::new(ClientCredentialsGrant),
+                    GrantType::RefreshToken => Arc::new(RefreshTokenGrant),
+                    GrantType::Password => Arc::new(PasswordGrant {
+                        issue_refresh: grants.refresh_token,
+                    }),
+                }
+            })
+            .fold(Self::new(), Self::with_handler)
+    }
+
+    /// Serve `handler`'s grant type with it, replacing any handler registered for it.
+    pub fn with_handler(mut self, handler: DynGrantHandler) -> Self {
+        match self
+            .handlers
+            .iter_mut()
+            .find(|registered| registered.grant_type() == handler.grant_type())
+        {
+            Some(registered) => *registered = handler,
+            None => self.handlers.push(handler),
+        }
+        self
+    }
+
+    pub fn get(&self, grant_type: &str) -> Option<&DynGrantHandler> {
+        self.handlers
+            .iter()
+            .find(|handler| handler.grant_type() == grant_type)
+    }
+
+    /// Accepted grant types, as advertised in discovery metadata.
+    pub fn grant_types(&self) -> Vec<&str> {
+        self.handlers
+            .iter()
+            .map(|handler| handler.grant_type())
+            .collect()
+    }
+}
+
+/// The registry a handler was given, or the built-in grants enabled in `grants` for apps
+/// mounting handlers directly.
+pub(crate) fn registry_or_builtin(
+    registry: Option<web::Data<GrantRegistry>>,
+    grants: Option<&web::Data<GrantsConfig>>,
+) -> Arc<GrantRegistry> {
+    registry.map(web::Data::into_inner).unwrap_or_else(|| {
+        let grants = grants
+            .map(|grants| grants.get_ref().clone())
+            .unwrap_or_default();
+        Arc::new(GrantRegistry::builtin(&grants))
+    })
+}
+
+/// A token request form, parsed and checked for duplicate parameters.
+pub struct TokenRequest {
+    grant_type: String,
+    client_id: String,
+    metadata: TokenMetadata,
+    params: HashMap<String, String>,
+}
+
+impl TokenRequest {
+    /// Fails on a missing `grant_type` or `client_id`, or malformed `metadata`.
+    pub fn from_params(mut params: HashMap<String, String>) -> Result<Self, OAuth2Error> {
+        let grant_type = params
+            .remove("grant_type")
+            .ok_or_else(|| OAuth2Error::invalid_request("Missing grant_type"))?;
+        let client_id = params
+            .remove("client_id")
+            .ok_or_else(|| OAuth2Error::invalid_request("Missing client_id"))?;
+        let metadata = params
+            .remove("metadata")
+            .map(|json| TokenMetadata::from_json(&json))
+            .transpose()?
+            .unwrap_or_default();
+        Ok(Self {
+            grant_type,
+            client_id,
+            metadata,
+            params,
+        })
+    }
+
+    pub fn grant_type(&self) -> &str {
+        &self.grant_type
+    }
+
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    /// Tags to attach to the issued token (`metadata`, a JSON object).
+    pub fn metadata(&self) -> &TokenMetadata {
+        &self.metadata
+    }
+
+    pub fn take_metadata(&mut self) -> TokenMetadata {
+        std::mem::take(&mut self.metadata)
+    }
+
+    /// Any other form parameter, e.g. `scope` or a grant specific one.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(String::as_str)
+    }
+
+    pub fn take_param(&mut self, name: &str) -> Option<String> {
+        self.params.remove(name)
+    }
+}
+
+/// What a [`GrantHandler`] can use to issue a token.
+pub struct GrantContext {
+    pub(crate) storage: Option<DynStorage>,
+    pub(crate) token_actor: Addr<TokenActor>,
+    pub(crate) client_actor: Addr<ClientActor>,
+    pub(crate) auth_actor: Addr<AuthActor>,
+    pub(crate) metrics: web::Data<Metrics>,
+    pub(crate) tenant: Option<TenantContext>,
+    /// Context of the calling user agent, for authorization code and refresh token
+    /// binding.
+    pub(crate) binding: ContextBinding,
+    /// Public URL of the token endpoint, the audience of client assertions.
+    pub(crate) token_endpoint: String,
+    pub(crate) security: SecurityEvents,
+}
+
+impl GrantContext {
+    /// Fails when the application registered no `DynStorage`; the server always does.
+    pub fn storage(&self) -> Result<&DynStorage, OAuth2Error> {
+        self.storage.as_ref().ok_or_else(|| {
+            OAuth2Error::new("server_error", Some("storage is not available to grants"))
+        })
+    }
+
+    /// Tenant the request was made to; `None` for the default tenant.
+    pub fn tenant_id(&self) -> Option<&str> {
+        tenant_id(self.tenant.as_ref())
+    }
+
+    /// Authenticate a confidential client by its secret. Public and native clients have
+    /// none and are accepted without one.
+    pub async fn authenticate_client(
+        &self,
+        client: &Client,
+        client_secret: Option<String>,
+    ) -> Result<(), OAuth2Error> {
+        self.client_actor
+            .send(AuthenticateClientSecret {
+                client: client.clone(),
+                client_secret,
+                span: tracing::Span::current(),
+                deadline: Deadline::current(),
+            })
+            .await
+            .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))?
+    }
+
+    /// Issue `token` for the request's tenant, after the token issuance policies. A
+    /// refresh token is bound to the request's context unless `token` names another.
+    pub async fn issue(&self, mut token: CreateToken) -> Result<Token, OAuth2Error> {
+        token.tenant = self.tenant.clone();
+        token.context.get_or_insert_with(|| self.binding.clone());
+        let token = self
+            .token_actor
+            .send(token)
+            .await
+            .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
+        self.metrics.oauth_token_issued_total.inc();
+        Ok(token)
+    }
+}
@@ -0,0 +1,74 @@
+use async_trait::async_trait;
+
+use oauth2_core::{Client, GrantType, OAuth2Error, Token};
+use oauth2_ports::validate_scope_subset;
+
+use super::{GrantContext, GrantHandler, TokenRequest};
+use crate::actors::{AuthenticateUser, CreateToken};
+use crate::deadline::Deadline;
+
+/// Resource Owner Password Credentials (RFC 6749 section 4.3); only registered when
+/// `grants.password` is enabled.
+pub(super) struct PasswordGrant {
+    /// Issue refresh tokens to clients registered for them (`grants.refresh_token`).
+    pub(super) issue_refresh: bool,
+}
+
+#[async_trait]
+impl GrantHandler for PasswordGrant {
+    fn grant_type(&self) -> &str {
+        GrantType::Password.as_str()
+    }
+
+    async fn handle(
+        &self,
+        mut request: TokenRequest,
+        client: Client,
+        grant: &GrantContext,
+    ) -> Result<Token, OAuth2Error> {
+        let (Some(username), Some(password)) = (
+            request.take_param("username"),
+            request.take_param("password"),
+        ) else {
+            return Err(OAuth2Error::invalid_request(
+                "username and password are required",
+            ));
+        };
+
+        grant
+            .authenticate_client(&client, request.take_param("client_secret"))
+            .await?;
+
+        let scope = request
+            .take_param("scope")
+            .unwrap_or_else(|| client.scope.clone());
+        validate_scope_subset(&scope, &client.scope)?;
+
+        let user = grant
+            .auth_actor
+            .send(AuthenticateUser {
+                username,
+                password,
+                client_id: client.client_id.clone(),
+                tenant_id: grant.tenant_id().map(str::to_string),
+                span: tracing::Span::current(),
+                deadline: Deadline::current(),
+            })
+            .await
+            .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
+
+        grant
+            .issue(CreateToken {
+                include_refresh: self.issue_refresh
+                    && client.supports_grant_type(GrantType::RefreshToken),
+                metadata: request.take_metadata(),
+                ..CreateToken::new(
+                    self.grant_type(),
+                    client.client_id.clone(),
+                    Some(user.id),
+                    scope,
+                )
+            })
+            .await
+    }
+}
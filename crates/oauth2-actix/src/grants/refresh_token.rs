@@ -0,0 +1,49 @@
+use async_trait::async_trait;
+
+use oauth2_core::{Client, GrantType, OAuth2Error, Token};
+
+use super::{GrantContext, GrantHandler, TokenRequest};
+use crate::actors::{RefreshToken, RotateRefreshToken};
+use crate::deadline::Deadline;
+
+/// Refresh token grant (RFC 6749 section 6). The presented refresh token is rotated: it is
+/// revoked and a new access and refresh token are issued under the same grant.
+pub(super) struct RefreshTokenGrant;
+
+#[async_trait]
+impl GrantHandler for RefreshTokenGrant {
+    fn grant_type(&self) -> &str {
+        GrantType::RefreshToken.as_str()
+    }
+
+    async fn handle(
+        &self,
+        mut request: TokenRequest,
+        client: Client,
+        grant: &GrantContext,
+    ) -> Result<Token, OAuth2Error> {
+        let refresh_token = request
+            .take_param("refresh_token")
+            .ok_or_else(|| OAuth2Error::invalid_request("Missing refresh_token"))?;
+
+        grant
+            .authenticate_client(&client, request.take_param("client_secret"))
+            .await?;
+
+        let token = grant
+            .token_actor
+            .send(RefreshToken(RotateRefreshToken {
+                refresh_token,
+                client_id: client.client_id.clone(),
+                scope: request.take_param("scope"),
+                tenant: grant.tenant.clone(),
+                context: grant.binding.clone(),
+                span: tracing::Span::current(),
+                deadline: Deadline::current(),
+            }))
+            .await
+            .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
+        grant.metrics.oauth_token_issued_total.inc();
+        Ok(token)
+    }
+}
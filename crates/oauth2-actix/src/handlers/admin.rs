@@ -1,59 +1,476 @@
-use actix_web::{web, HttpResponse, Result};
-use serde::Serialize;
+use actix::Addr;
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Result};
+use serde::{Deserialize, Serialize};
 
-use oauth2_observability::Metrics;
-use oauth2_ports::DynStorage;
+use oauth2_config::EffectiveConfig;
+use oauth2_core::{
+    hash_password, validate_password, AdminRole, Client, ClientMetadata, Group, IssuerKeys,
+    OAuth2Error, Role, ServiceAccount, Tenant, Token, TokenMetadata, User,
+};
+use oauth2_observability::{BuildInfo, HealthRegistry, HealthReport, Metrics};
+use oauth2_ports::{DynStorage, PageRequest, TokenMetadataQuery, UserListQuery};
+
+use crate::actors::{
+    generate_secret, ApproveRedirectUriChange, ClientActor, DeleteClient, RejectRedirectUriChange,
+};
+use crate::deadline::Deadline;
+use crate::middleware::admin_rbac::{AdminPrincipal, AdminScope};
+use crate::middleware::bearer::{AuthenticatedToken, BearerToken};
+
+/// Scope granting full access to the admin API; see [`AdminRole`] for narrower roles.
+pub const ADMIN_SCOPE: &str = "admin";
+
+const MAX_USER_PAGE_SIZE: u32 = 200;
+const MAX_TOKEN_PAGE_SIZE: u32 = 200;
 
 #[derive(Serialize)]
 pub struct DashboardData {
-    pub total_clients: i64,
-    pub total_users: i64,
-    pub total_tokens: i64,
-    pub active_tokens: i64,
+    pub total_clients: u64,
+    pub total_users: u64,
+    pub total_tokens: u64,
+    pub active_tokens: u64,
+}
+
+/// Page of a listing, e.g. `?page=2&per_page=50`. Pages are numbered from 1.
+#[derive(Debug, Deserialize)]
+pub struct PageParams {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+impl PageParams {
+    fn page_request(&self) -> PageRequest {
+        let defaults = PageRequest::default();
+        PageRequest::new(
+            self.page.unwrap_or(defaults.page),
+            self.per_page.unwrap_or(defaults.per_page),
+        )
+    }
+}
+
+/// Tenant of the client a request addresses, e.g. `?tenant=acme`; absent for the default
+/// tenant. Client ids are only unique within a tenant.
+#[derive(Debug, Deserialize)]
+pub struct TenantParams {
+    pub tenant: Option<String>,
 }
 
 #[derive(Serialize)]
 pub struct ClientInfo {
     pub client_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
     pub name: String,
     pub created_at: String,
+    #[serde(flatten)]
+    pub metadata: ClientMetadata,
 }
 
+#[derive(Serialize)]
+pub struct ClientPage {
+    pub clients: Vec<ClientInfo>,
+    pub page: u32,
+    pub per_page: u32,
+    pub total: u64,
+}
+
+impl From<&Client> for ClientInfo {
+    fn from(client: &Client) -> Self {
+        Self {
+            client_id: client.client_id.clone(),
+            tenant_id: client.tenant_id.clone(),
+            name: client.name.clone(),
+            created_at: client.created_at.to_rfc3339(),
+            metadata: client.metadata(),
+        }
+    }
+}
+
+/// Token as exposed by the admin API (never includes the token values).
 #[derive(Serialize)]
 pub struct TokenInfo {
     pub id: String,
     pub client_id: String,
-    pub user_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
     pub scope: String,
+    pub metadata: TokenMetadata,
+    pub created_at: String,
     pub expires_at: String,
     pub revoked: bool,
 }
 
+impl From<Token> for TokenInfo {
+    fn from(token: Token) -> Self {
+        Self {
+            id: token.id,
+            client_id: token.client_id,
+            user_id: token.user_id,
+            scope: token.scope,
+            metadata: token.metadata,
+            created_at: token.created_at.to_rfc3339(),
+            expires_at: token.expires_at.to_rfc3339(),
+            revoked: token.revoked,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TokenPage {
+    pub tokens: Vec<TokenInfo>,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+/// Tokens of one client or user, newest first.
+#[derive(Serialize)]
+pub struct TokenListPage {
+    pub tokens: Vec<TokenInfo>,
+    pub page: u32,
+    pub per_page: u32,
+}
+
+impl TokenListPage {
+    fn new(tokens: Vec<Token>, page: PageRequest) -> Self {
+        Self {
+            tokens: tokens.into_iter().map(TokenInfo::from).collect(),
+            page: page.page,
+            per_page: page.per_page,
+        }
+    }
+}
+
+/// Metadata tag to match, e.g. `key=deployment&value=canary`.
+#[derive(Debug, Deserialize)]
+pub struct ListTokensParams {
+    pub key: Option<String>,
+    pub value: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevokeTokensByMetadataRequest {
+    pub key: String,
+    pub value: String,
+}
+
+/// User as exposed by the admin API (never includes the password hash).
+#[derive(Serialize)]
+pub struct UserInfo {
+    pub id: String,
+    pub username: String,
+    pub email: String,
+    pub email_verified: bool,
+    pub enabled: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<User> for UserInfo {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            username: user.username,
+            email: user.email,
+            email_verified: user.email_verified,
+            enabled: user.enabled,
+            created_at: user.created_at.to_rfc3339(),
+            updated_at: user.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct UserPage {
+    pub users: Vec<UserInfo>,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateUserRequest {
+    pub username: String,
+    pub password: String,
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListUsersParams {
+    pub search: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub password: String,
+}
+
+/// Body of role and group creation.
+#[derive(Debug, Deserialize)]
+pub struct CreateRoleRequest {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// Roles assigned to a user, replaced as a whole by `PUT`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserRoles {
+    pub roles: Vec<String>,
+}
+
+/// Groups a user belongs to, replaced as a whole by `PUT`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserGroups {
+    pub groups: Vec<String>,
+}
+
+/// Service account as exposed by the admin API.
+#[derive(Serialize)]
+pub struct ServiceAccountInfo {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub client_id: String,
+    pub scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    pub enabled: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<ServiceAccount> for ServiceAccountInfo {
+    fn from(account: ServiceAccount) -> Self {
+        Self {
+            id: account.id,
+            name: account.name,
+            description: account.description,
+            client_id: account.client_id,
+            scope: account.scope,
+            public_key: account.public_key,
+            enabled: account.enabled,
+            created_at: account.created_at.to_rfc3339(),
+            updated_at: account.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Tenant as exposed by the admin API; its signing secret never leaves the server.
+#[derive(Serialize)]
+pub struct TenantInfo {
+    pub id: String,
+    pub name: String,
+    /// Path prefix of the tenant's endpoints.
+    pub path: String,
+    pub enabled: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<Tenant> for TenantInfo {
+    fn from(tenant: Tenant) -> Self {
+        Self {
+            path: tenant.path_prefix(),
+            id: tenant.id,
+            name: tenant.name,
+            enabled: tenant.enabled,
+            created_at: tenant.created_at.to_rfc3339(),
+            updated_at: tenant.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTenantRequest {
+    pub id: String,
+    pub name: Option<String>,
+}
+
+/// Response to service account creation; the secret is only ever shown here.
+#[derive(Serialize)]
+pub struct CreatedServiceAccount {
+    #[serde(flatten)]
+    pub account: ServiceAccountInfo,
+    /// Omitted for key-pair accounts, which authenticate with client assertions only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateResourceServerRequest {
+    pub name: String,
+}
+
+/// Response to resource server creation; the secret is only ever shown here.
+#[derive(Serialize)]
+pub struct CreatedResourceServer {
+    pub client_id: String,
+    pub client_secret: String,
+    pub name: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateServiceAccountRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub scope: String,
+    /// PEM public key; when set the account authenticates with `private_key_jwt`.
+    pub public_key: Option<String>,
+}
+
+/// Partial update; absent fields are left unchanged.
+#[derive(Debug, Deserialize)]
+pub struct UpdateServiceAccountRequest {
+    pub description: Option<String>,
+    pub scope: Option<String>,
+    pub public_key: Option<String>,
+}
+
+async fn load_user(db: &DynStorage, user_id: &str) -> Result<User, OAuth2Error> {
+    db.get_user(user_id)
+        .await?
+        .ok_or_else(|| OAuth2Error::not_found("User not found"))
+}
+
+async fn load_service_account(db: &DynStorage, id: &str) -> Result<ServiceAccount, OAuth2Error> {
+    db.get_service_account(id)
+        .await?
+        .ok_or_else(|| OAuth2Error::not_found("Service account not found"))
+}
+
+fn validate_service_account_scope(scope: &str) -> Result<String, OAuth2Error> {
+    let scope = scope.split_whitespace().collect::<Vec<_>>().join(" ");
+    if scope.is_empty() {
+        return Err(OAuth2Error::invalid_scope("scope must not be empty"));
+    }
+    Ok(scope)
+}
+
+/// Trim a PEM key and check it is usable; an empty string clears the key.
+fn normalize_public_key(public_key: &str) -> Result<Option<String>, OAuth2Error> {
+    let public_key = public_key.trim();
+    if public_key.is_empty() {
+        return Ok(None);
+    }
+    ServiceAccount::validate_public_key(public_key)?;
+    Ok(Some(public_key.to_string()))
+}
+
 /// Admin dashboard - shows overview statistics
-pub async fn dashboard(_db: web::Data<DynStorage>) -> Result<HttpResponse> {
-    // In a real implementation, fetch actual stats from storage.
+pub async fn dashboard(db: web::Data<DynStorage>) -> Result<HttpResponse, OAuth2Error> {
     let data = DashboardData {
-        total_clients: 0,
-        total_users: 0,
-        total_tokens: 0,
-        active_tokens: 0,
+        total_clients: db.count_clients().await?,
+        total_users: db.count_users().await?,
+        total_tokens: db.count_tokens().await?,
+        active_tokens: db.count_active_tokens().await?,
     };
 
     Ok(HttpResponse::Ok().json(data))
 }
 
-/// List all registered clients
-pub async fn list_clients(_db: web::Data<DynStorage>) -> Result<HttpResponse> {
-    // In a real implementation, fetch from storage.
-    let clients: Vec<ClientInfo> = vec![];
-    Ok(HttpResponse::Ok().json(clients))
+/// List registered clients, ordered by client ID
+pub async fn list_clients(
+    params: web::Query<PageParams>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let page = params.page_request();
+    let clients = db.list_clients(page).await?;
+    let total = db.count_clients().await?;
+
+    Ok(HttpResponse::Ok().json(ClientPage {
+        clients: clients.iter().map(ClientInfo::from).collect(),
+        page: page.page,
+        per_page: page.per_page,
+        total,
+    }))
+}
+
+/// List tokens issued to a client, newest first
+pub async fn list_client_tokens(
+    client_id: web::Path<String>,
+    params: web::Query<PageParams>,
+    tenant: web::Query<TenantParams>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    if db
+        .get_client(&client_id, tenant.tenant.as_deref())
+        .await?
+        .is_none()
+    {
+        return Err(OAuth2Error::not_found("Client not found"));
+    }
+    let page = params.page_request();
+    let tokens = db.list_tokens_by_client(&client_id, page).await?;
+
+    Ok(HttpResponse::Ok().json(TokenListPage::new(tokens, page)))
+}
+
+/// List tokens issued to a user, newest first
+pub async fn list_user_tokens(
+    user_id: web::Path<String>,
+    params: web::Query<PageParams>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let user = load_user(&db, &user_id).await?;
+    let page = params.page_request();
+    let tokens = db.list_tokens_by_user(&user.id, page).await?;
+
+    Ok(HttpResponse::Ok().json(TokenListPage::new(tokens, page)))
 }
 
-/// List all active tokens
-pub async fn list_tokens(_db: web::Data<DynStorage>) -> Result<HttpResponse> {
-    // In a real implementation, fetch from storage.
-    let tokens: Vec<TokenInfo> = vec![];
-    Ok(HttpResponse::Ok().json(tokens))
+/// List tokens tagged with a metadata key/value, newest first
+pub async fn list_tokens(
+    params: web::Query<ListTokensParams>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let params = params.into_inner();
+    let (key, value) = match (params.key, params.value) {
+        (Some(key), Some(value)) => (key, value),
+        _ => {
+            return Err(OAuth2Error::invalid_request(
+                "key and value are required to find tokens",
+            ))
+        }
+    };
+    TokenMetadata::validate_key(&key)?;
+
+    let mut query = TokenMetadataQuery::new(key, value);
+    query.limit = params
+        .limit
+        .unwrap_or(query.limit)
+        .clamp(1, MAX_TOKEN_PAGE_SIZE);
+    query.offset = params.offset.unwrap_or(query.offset);
+
+    let tokens = db.find_tokens_by_metadata(&query).await?;
+
+    Ok(HttpResponse::Ok().json(TokenPage {
+        tokens: tokens.into_iter().map(TokenInfo::from).collect(),
+        limit: query.limit,
+        offset: query.offset,
+    }))
+}
+
+/// Revoke every token tagged with a metadata key/value
+pub async fn revoke_tokens_by_metadata(
+    req: web::Json<RevokeTokensByMetadataRequest>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let req = req.into_inner();
+    TokenMetadata::validate_key(&req.key)?;
+
+    let revoked = db.revoke_tokens_by_metadata(&req.key, &req.value).await?;
+
+    tracing::info!(
+        metadata.key = %req.key,
+        metadata.value = %req.value,
+        revoked,
+        "Tokens revoked by metadata"
+    );
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "revoked": revoked })))
 }
 
 /// Revoke a token by ID (admin function)
@@ -71,17 +488,640 @@ pub async fn admin_revoke_token(
     })))
 }
 
-/// Delete a client (admin function)
+/// Delete a client along with its tokens, authorization codes and service account
 pub async fn delete_client(
-    _client_id: web::Path<String>,
-    _db: web::Data<DynStorage>,
-) -> Result<HttpResponse> {
-    // In a real implementation, delete client and associated tokens
+    client_id: web::Path<String>,
+    tenant: web::Query<TenantParams>,
+    client_actor: web::Data<Addr<ClientActor>>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let client = client_actor
+        .send(DeleteClient {
+            client_id: client_id.into_inner(),
+            tenant_id: tenant.into_inner().tenant,
+            span: tracing::Span::current(),
+            deadline: Deadline::current(),
+        })
+        .await
+        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
+
+    tracing::info!(client_id = %client.client_id, name = %client.name, "Client deleted");
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Create a `resource_server` client that introspects tokens with its own credentials
+pub async fn create_resource_server(
+    req: HttpRequest,
+    body: web::Json<CreateResourceServerRequest>,
+    issuer_keys: web::Data<IssuerKeys>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    require_admin(&req, &issuer_keys, &db, AdminRole::SecurityAdmin).await?;
+    let name = body.name.trim();
+    if name.is_empty() {
+        return Err(OAuth2Error::invalid_request("name must not be empty"));
+    }
+
+    let client_secret = generate_secret();
+    let mut client = Client::resource_server(
+        format!("rs_{}", uuid::Uuid::new_v4()),
+        String::new(),
+        name.to_string(),
+    );
+    client.set_client_secret(&client_secret)?;
+    db.save_client(&client).await?;
+
+    tracing::info!(client_id = %client.client_id, name = %client.name, "Resource server created");
+    Ok(HttpResponse::Created().json(CreatedResourceServer {
+        client_id: client.client_id,
+        client_secret,
+        name: client.name,
+        created_at: client.created_at.to_rfc3339(),
+    }))
+}
+
+/// Delete a resource server; its introspection requests fail with `invalid_client` from then on
+pub async fn delete_resource_server(
+    req: HttpRequest,
+    client_id: web::Path<String>,
+    issuer_keys: web::Data<IssuerKeys>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    require_admin(&req, &issuer_keys, &db, AdminRole::SecurityAdmin).await?;
+    let client = db
+        .get_client(&client_id, None)
+        .await?
+        .filter(Client::is_resource_server)
+        .ok_or_else(|| OAuth2Error::not_found("Resource server not found"))?;
+    db.delete_client(&client.client_id, None).await?;
+
+    tracing::info!(client_id = %client.client_id, name = %client.name, "Resource server deleted");
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// List tenants
+pub async fn list_tenants(db: web::Data<DynStorage>) -> Result<HttpResponse, OAuth2Error> {
+    let tenants = db.list_tenants().await?;
+    Ok(HttpResponse::Ok().json(
+        tenants
+            .into_iter()
+            .map(TenantInfo::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Create a tenant with a freshly generated signing secret
+pub async fn create_tenant(
+    body: web::Json<CreateTenantRequest>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let body = body.into_inner();
+    Tenant::validate_id(&body.id)?;
+    if db.get_tenant(&body.id).await?.is_some() {
+        return Err(OAuth2Error::invalid_request("tenant already exists"));
+    }
+
+    let name = body
+        .name
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| body.id.clone());
+    let tenant = Tenant::new(body.id, name, generate_secret());
+    db.save_tenant(&tenant).await?;
+
+    tracing::info!(tenant = %tenant.id, "Tenant created");
+    Ok(HttpResponse::Created().json(TenantInfo::from(tenant)))
+}
+
+/// Apply a client's redirect URI change held back by `security.redirect_uri_changes`.
+pub async fn approve_redirect_uris(
+    req: HttpRequest,
+    client_id: web::Path<String>,
+    tenant: web::Query<TenantParams>,
+    client_actor: web::Data<Addr<ClientActor>>,
+    issuer_keys: web::Data<IssuerKeys>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    require_admin(&req, &issuer_keys, &db, AdminRole::SecurityAdmin).await?;
+    let client = client_actor
+        .send(ApproveRedirectUriChange {
+            client_id: client_id.into_inner(),
+            tenant_id: tenant.into_inner().tenant,
+            span: tracing::Span::current(),
+            deadline: Deadline::current(),
+        })
+        .await
+        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
     Ok(HttpResponse::Ok().json(serde_json::json!({
-        "message": "Client deleted successfully"
+        "client_id": client.client_id,
+        "redirect_uris": client.get_redirect_uris(),
     })))
 }
 
+/// Discard a client's redirect URI change held back by `security.redirect_uri_changes`.
+pub async fn reject_redirect_uris(
+    req: HttpRequest,
+    client_id: web::Path<String>,
+    tenant: web::Query<TenantParams>,
+    client_actor: web::Data<Addr<ClientActor>>,
+    issuer_keys: web::Data<IssuerKeys>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    require_admin(&req, &issuer_keys, &db, AdminRole::SecurityAdmin).await?;
+    let client = client_actor
+        .send(RejectRedirectUriChange {
+            client_id: client_id.into_inner(),
+            tenant_id: tenant.into_inner().tenant,
+            span: tracing::Span::current(),
+            deadline: Deadline::current(),
+        })
+        .await
+        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "client_id": client.client_id,
+        "redirect_uris": client.get_redirect_uris(),
+    })))
+}
+
+/// Require an active bearer token granting at least `required` (see [`AdminRole`]).
+///
+/// Behind [`crate::middleware::admin_rbac::RequireAdminRole`] the principal it already authenticated is reused, so the
+/// token is only verified once per request.
+pub(crate) async fn require_admin(
+    req: &HttpRequest,
+    issuer_keys: &IssuerKeys,
+    db: &DynStorage,
+    required: AdminRole,
+) -> Result<AdminPrincipal, OAuth2Error> {
+    let authenticated = req.extensions().get::<AdminPrincipal>().cloned();
+    let principal = match authenticated {
+        Some(principal) => principal,
+        None => authenticate_admin(req, issuer_keys, db, required).await?,
+    };
+
+    if !principal.role.allows(required) {
+        tracing::warn!(
+            subject = %principal.subject,
+            role = %principal.role,
+            required = %required,
+            path = %req.path(),
+            "Admin request denied"
+        );
+        return Err(
+            OAuth2Error::insufficient_scope(&format!("{required} role required"))
+                .with_required_scope(required.scope()),
+        );
+    }
+    Ok(principal)
+}
+
+async fn authenticate_admin(
+    req: &HttpRequest,
+    issuer_keys: &IssuerKeys,
+    db: &DynStorage,
+    required: AdminRole,
+) -> Result<AdminPrincipal, OAuth2Error> {
+    let BearerToken(raw) = BearerToken::from_request_headers(req)?;
+    // The admin API belongs to the default tenant.
+    let AuthenticatedToken { claims, .. } =
+        AuthenticatedToken::validate(&raw, issuer_keys, db, None).await?;
+
+    let admin_scope = req
+        .app_data::<web::Data<AdminScope>>()
+        .map(|scope| scope.get_ref().clone())
+        .unwrap_or_default();
+    let role =
+        AdminRole::from_claims_with_admin_scope(&claims, &admin_scope.0).ok_or_else(|| {
+            OAuth2Error::insufficient_scope("admin scope required")
+                .with_required_scope(required.scope())
+        })?;
+    Ok(AdminPrincipal {
+        subject: claims.sub,
+        role,
+    })
+}
+
+/// Sanitized effective configuration, resolved backends, and compiled features
+pub async fn effective_config(
+    req: HttpRequest,
+    effective: web::Data<EffectiveConfig>,
+    issuer_keys: web::Data<IssuerKeys>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    require_admin(&req, &issuer_keys, &db, AdminRole::Viewer).await?;
+    Ok(HttpResponse::Ok().json(effective.get_ref()))
+}
+
+/// Create a user with an Argon2-hashed password
+pub async fn create_user(
+    req: web::Json<CreateUserRequest>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let req = req.into_inner();
+    let username = req.username.trim();
+    let email = req.email.trim();
+
+    if username.is_empty() {
+        return Err(OAuth2Error::invalid_request("username must not be empty"));
+    }
+    if !email.contains('@') {
+        return Err(OAuth2Error::invalid_request(
+            "email must be a valid address",
+        ));
+    }
+    validate_password(&req.password)?;
+
+    let user = User::new(
+        username.to_string(),
+        hash_password(&req.password)?,
+        email.to_string(),
+    );
+    db.save_user(&user).await?;
+
+    tracing::info!(user_id = %user.id, username = %user.username, "User created");
+    Ok(HttpResponse::Created().json(UserInfo::from(user)))
+}
+
+/// List users, optionally filtered by a username/email search term
+pub async fn list_users(
+    params: web::Query<ListUsersParams>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let params = params.into_inner();
+    let defaults = UserListQuery::default();
+    let query = UserListQuery {
+        search: params
+            .search
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty()),
+        limit: params
+            .limit
+            .unwrap_or(defaults.limit)
+            .clamp(1, MAX_USER_PAGE_SIZE),
+        offset: params.offset.unwrap_or(defaults.offset),
+    };
+
+    let users = db.list_users(&query).await?;
+
+    Ok(HttpResponse::Ok().json(UserPage {
+        users: users.into_iter().map(UserInfo::from).collect(),
+        limit: query.limit,
+        offset: query.offset,
+    }))
+}
+
+/// Get a single user
+pub async fn get_user(
+    user_id: web::Path<String>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let user = load_user(&db, &user_id).await?;
+    Ok(HttpResponse::Ok().json(UserInfo::from(user)))
+}
+
+async fn set_user_enabled(
+    db: &DynStorage,
+    user_id: &str,
+    enabled: bool,
+) -> Result<HttpResponse, OAuth2Error> {
+    let mut user = load_user(db, user_id).await?;
+    user.enabled = enabled;
+    user.updated_at = chrono::Utc::now();
+    db.update_user(&user).await?;
+
+    tracing::info!(user_id = %user.id, enabled, "User enabled state changed");
+    Ok(HttpResponse::Ok().json(UserInfo::from(user)))
+}
+
+/// Enable a user
+pub async fn enable_user(
+    user_id: web::Path<String>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    set_user_enabled(&db, &user_id, true).await
+}
+
+/// Disable a user
+pub async fn disable_user(
+    user_id: web::Path<String>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    set_user_enabled(&db, &user_id, false).await
+}
+
+/// Replace a user's password
+pub async fn change_user_password(
+    user_id: web::Path<String>,
+    req: web::Json<ChangePasswordRequest>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    validate_password(&req.password)?;
+
+    let mut user = load_user(&db, &user_id).await?;
+    user.password_hash = hash_password(&req.password)?;
+    user.updated_at = chrono::Utc::now();
+    db.update_user(&user).await?;
+
+    tracing::info!(user_id = %user.id, "User password changed");
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Delete a user together with their tokens and authorization codes
+pub async fn delete_user(
+    user_id: web::Path<String>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let user = load_user(&db, &user_id).await?;
+    db.delete_user(&user.id).await?;
+
+    tracing::info!(user_id = %user.id, username = %user.username, "User deleted");
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// List roles
+pub async fn list_roles(db: web::Data<DynStorage>) -> Result<HttpResponse, OAuth2Error> {
+    Ok(HttpResponse::Ok().json(db.list_roles().await?))
+}
+
+/// Create a role
+pub async fn create_role(
+    body: web::Json<CreateRoleRequest>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let body = body.into_inner();
+    Role::validate_name(&body.name)?;
+    if db.list_roles().await?.iter().any(|r| r.name == body.name) {
+        return Err(OAuth2Error::invalid_request("role already exists"));
+    }
+
+    let role = Role::new(body.name, body.description.filter(|d| !d.trim().is_empty()));
+    db.save_role(&role).await?;
+
+    tracing::info!(role = %role.name, "Role created");
+    Ok(HttpResponse::Created().json(role))
+}
+
+/// Delete a role, removing it from every user
+pub async fn delete_role(
+    name: web::Path<String>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    if !db.list_roles().await?.iter().any(|r| r.name == *name) {
+        return Err(OAuth2Error::not_found("Role not found"));
+    }
+    db.delete_role(&name).await?;
+
+    tracing::info!(role = %name, "Role deleted");
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// List groups
+pub async fn list_groups(db: web::Data<DynStorage>) -> Result<HttpResponse, OAuth2Error> {
+    Ok(HttpResponse::Ok().json(db.list_groups().await?))
+}
+
+/// Create a group
+pub async fn create_group(
+    body: web::Json<CreateRoleRequest>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let body = body.into_inner();
+    Group::validate_name(&body.name)?;
+    if db.list_groups().await?.iter().any(|g| g.name == body.name) {
+        return Err(OAuth2Error::invalid_request("group already exists"));
+    }
+
+    let group = Group::new(body.name, body.description.filter(|d| !d.trim().is_empty()));
+    db.save_group(&group).await?;
+
+    tracing::info!(group = %group.name, "Group created");
+    Ok(HttpResponse::Created().json(group))
+}
+
+/// Delete a group, removing every user from it
+pub async fn delete_group(
+    name: web::Path<String>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    if !db.list_groups().await?.iter().any(|g| g.name == *name) {
+        return Err(OAuth2Error::not_found("Group not found"));
+    }
+    db.delete_group(&name).await?;
+
+    tracing::info!(group = %name, "Group deleted");
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Roles assigned to a user
+pub async fn get_user_roles(
+    user_id: web::Path<String>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let user = load_user(&db, &user_id).await?;
+    let roles = db.list_user_roles(&user.id).await?;
+    Ok(HttpResponse::Ok().json(UserRoles { roles }))
+}
+
+/// Replace the roles assigned to a user; every role must exist
+pub async fn set_user_roles(
+    user_id: web::Path<String>,
+    body: web::Json<UserRoles>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let user = load_user(&db, &user_id).await?;
+    let known: Vec<String> = db.list_roles().await?.into_iter().map(|r| r.name).collect();
+    check_known("role", &known, &body.roles)?;
+    db.set_user_roles(&user.id, &body.roles).await?;
+
+    let roles = db.list_user_roles(&user.id).await?;
+    tracing::info!(user_id = %user.id, roles = ?roles, "User roles changed");
+    Ok(HttpResponse::Ok().json(UserRoles { roles }))
+}
+
+/// Groups a user belongs to
+pub async fn get_user_groups(
+    user_id: web::Path<String>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let user = load_user(&db, &user_id).await?;
+    let groups = db.list_user_groups(&user.id).await?;
+    Ok(HttpResponse::Ok().json(UserGroups { groups }))
+}
+
+/// Replace the groups a user belongs to; every group must exist
+pub async fn set_user_groups(
+    user_id: web::Path<String>,
+    body: web::Json<UserGroups>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let user = load_user(&db, &user_id).await?;
+    let known: Vec<String> = db
+        .list_groups()
+        .await?
+        .into_iter()
+        .map(|g| g.name)
+        .collect();
+    check_known("group", &known, &body.groups)?;
+    db.set_user_groups(&user.id, &body.groups).await?;
+
+    let groups = db.list_user_groups(&user.id).await?;
+    tracing::info!(user_id = %user.id, groups = ?groups, "User groups changed");
+    Ok(HttpResponse::Ok().json(UserGroups { groups }))
+}
+
+fn check_known(kind: &str, known: &[String], names: &[String]) -> Result<(), OAuth2Error> {
+    match names.iter().find(|name| !known.contains(name)) {
+        Some(unknown) => Err(OAuth2Error::invalid_request(&format!(
+            "unknown {kind} '{unknown}'"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Create a service account and its backing `client_credentials` client
+pub async fn create_service_account(
+    req: web::Json<CreateServiceAccountRequest>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let req = req.into_inner();
+    let name = req.name.trim();
+    if name.is_empty() {
+        return Err(OAuth2Error::invalid_request("name must not be empty"));
+    }
+    let scope = validate_service_account_scope(&req.scope)?;
+    let public_key = match req.public_key.as_deref() {
+        Some(key) => normalize_public_key(key)?,
+        None => None,
+    };
+
+    let client_id = format!("sa_{}", uuid::Uuid::new_v4());
+    let client_secret = generate_secret();
+    let mut client = Client::new(
+        client_id.clone(),
+        String::new(),
+        Vec::new(),
+        vec!["client_credentials".to_string()],
+        scope.clone(),
+        name.to_string(),
+    );
+    client.set_client_secret(&client_secret)?;
+    db.save_client(&client).await?;
+
+    let account = ServiceAccount::new(name.to_string(), client_id.clone(), scope)
+        .with_description(req.description.filter(|d| !d.trim().is_empty()))
+        .with_public_key(public_key);
+    if let Err(err) = db.save_service_account(&account).await {
+        // Most likely a duplicate name; don't leave an orphaned client behind.
+        if let Err(cleanup) = db.delete_client(&client_id, None).await {
+            tracing::warn!(client_id = %client_id, error = %cleanup, "Failed to remove client of unsaved service account");
+        }
+        return Err(err);
+    }
+
+    tracing::info!(service_account_id = %account.id, name = %account.name, client_id = %client_id, "Service account created");
+    let key_pair_only = account.public_key.is_some();
+    Ok(HttpResponse::Created().json(CreatedServiceAccount {
+        account: ServiceAccountInfo::from(account),
+        client_secret: (!key_pair_only).then_some(client_secret),
+    }))
+}
+
+/// List service accounts
+pub async fn list_service_accounts(db: web::Data<DynStorage>) -> Result<HttpResponse, OAuth2Error> {
+    let accounts = db.list_service_accounts().await?;
+    Ok(HttpResponse::Ok().json(
+        accounts
+            .into_iter()
+            .map(ServiceAccountInfo::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Get a single service account
+pub async fn get_service_account(
+    id: web::Path<String>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let account = load_service_account(&db, &id).await?;
+    Ok(HttpResponse::Ok().json(ServiceAccountInfo::from(account)))
+}
+
+/// Update a service account's description, scopes or public key
+pub async fn update_service_account(
+    id: web::Path<String>,
+    req: web::Json<UpdateServiceAccountRequest>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let req = req.into_inner();
+    let mut account = load_service_account(&db, &id).await?;
+
+    if let Some(description) = req.description {
+        let description = description.trim();
+        account.description = (!description.is_empty()).then(|| description.to_string());
+    }
+    if let Some(scope) = req.scope.as_deref() {
+        account.scope = validate_service_account_scope(scope)?;
+    }
+    if let Some(public_key) = req.public_key.as_deref() {
+        account.public_key = normalize_public_key(public_key)?;
+    }
+    account.updated_at = chrono::Utc::now();
+    db.update_service_account(&account).await?;
+
+    // Keep the backing client's scope aligned for anything that inspects clients directly.
+    if let Some(mut client) = db.get_client(&account.client_id, None).await? {
+        if client.scope != account.scope {
+            client.scope = account.scope.clone();
+            client.updated_at = account.updated_at;
+            db.update_client(&client).await?;
+        }
+    }
+
+    tracing::info!(service_account_id = %account.id, scope = %account.scope, "Service account updated");
+    Ok(HttpResponse::Ok().json(ServiceAccountInfo::from(account)))
+}
+
+async fn set_service_account_enabled(
+    db: &DynStorage,
+    id: &str,
+    enabled: bool,
+) -> Result<HttpResponse, OAuth2Error> {
+    let mut account = load_service_account(db, id).await?;
+    account.enabled = enabled;
+    account.updated_at = chrono::Utc::now();
+    db.update_service_account(&account).await?;
+
+    tracing::info!(service_account_id = %account.id, enabled, "Service account enabled state changed");
+    Ok(HttpResponse::Ok().json(ServiceAccountInfo::from(account)))
+}
+
+/// Enable a service account
+pub async fn enable_service_account(
+    id: web::Path<String>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    set_service_account_enabled(&db, &id, true).await
+}
+
+/// Disable a service account; it can no longer obtain tokens
+pub async fn disable_service_account(
+    id: web::Path<String>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    set_service_account_enabled(&db, &id, false).await
+}
+
+/// Delete a service account together with its client and tokens
+pub async fn delete_service_account(
+    id: web::Path<String>,
+    db: web::Data<DynStorage>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let account = load_service_account(&db, &id).await?;
+    db.delete_client(&account.client_id, None).await?;
+
+    tracing::info!(service_account_id = %account.id, name = %account.name, "Service account deleted");
+    Ok(HttpResponse::NoContent().finish())
+}
+
 /// Get system metrics
 pub async fn system_metrics(metrics: web::Data<Metrics>) -> Result<HttpResponse> {
     let buffer = oauth2_observability::encode_prometheus_text(&metrics.registry)
@@ -101,16 +1141,31 @@ pub async fn health() -> Result<HttpResponse> {
     })))
 }
 
-/// Readiness check endpoint
-pub async fn readiness(db: web::Data<DynStorage>) -> Result<HttpResponse> {
-    db.healthcheck()
-        .await
-        .map_err(actix_web::error::ErrorServiceUnavailable)?;
+/// Version, commit, build time and enabled features of the running binary.
+pub async fn version(build: web::Data<BuildInfo>) -> HttpResponse {
+    HttpResponse::Ok().json(build.get_ref())
+}
 
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "status": "ready",
-        "checks": {
-            "database": "ok"
-        }
-    })))
+fn health_response(report: HealthReport) -> HttpResponse {
+    if report.status.is_available() {
+        HttpResponse::Ok().json(report)
+    } else {
+        HttpResponse::ServiceUnavailable().json(report)
+    }
+}
+
+/// Liveness probe: the process is up. Runs no component checks.
+pub async fn liveness(registry: web::Data<HealthRegistry>) -> HttpResponse {
+    health_response(registry.liveness())
+}
+
+/// Readiness probe: every registered component check, with per-component status and
+/// latency. `503` when a critical component is unhealthy.
+pub async fn readiness(registry: web::Data<HealthRegistry>) -> HttpResponse {
+    health_response(registry.readiness().await)
+}
+
+/// Startup probe: `503` until the server has finished starting, then as readiness.
+pub async fn startup(registry: web::Data<HealthRegistry>) -> HttpResponse {
+    health_response(registry.startup().await)
 }
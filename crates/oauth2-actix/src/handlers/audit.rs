@@ -0,0 +1,70 @@
+use actix_web::{http::header, web, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use oauth2_core::{AuditRecord, OAuth2Error};
+use oauth2_ports::{AuditQuery, DynAuditSink};
+
+const MAX_AUDIT_PAGE_SIZE: u32 = 1000;
+
+/// Filters of `GET /admin/audit`, e.g.
+/// `?since=2024-05-01T00:00:00Z&actor=user_123&action=login&limit=50`.
+#[derive(Debug, Deserialize)]
+pub struct AuditParams {
+    /// RFC 3339 time; records at or after it.
+    pub since: Option<String>,
+    /// RFC 3339 time; records before it.
+    pub until: Option<String>,
+    pub actor: Option<String>,
+    /// `login`, `consent`, `token_issued` or `admin_change`.
+    pub action: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditList {
+    pub records: Vec<AuditRecord>,
+}
+
+fn parse_time(name: &str, value: Option<&str>) -> Result<Option<DateTime<Utc>>, OAuth2Error> {
+    value
+        .map(|value| {
+            DateTime::parse_from_rfc3339(value)
+                .map(|time| time.with_timezone(&Utc))
+                .map_err(|_| {
+                    OAuth2Error::invalid_request(&format!("{name} must be an RFC 3339 time"))
+                })
+        })
+        .transpose()
+}
+
+/// `GET /admin/audit`: audit records matching the filters, newest first.
+pub async fn list_audit_records(
+    params: web::Query<AuditParams>,
+    sink: web::Data<DynAuditSink>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let params = params.into_inner();
+    let query = AuditQuery {
+        since: parse_time("since", params.since.as_deref())?,
+        until: parse_time("until", params.until.as_deref())?,
+        actor: params
+            .actor
+            .map(|actor| actor.trim().to_string())
+            .filter(|actor| !actor.is_empty()),
+        action: params.action.as_deref().map(str::parse).transpose()?,
+        limit: params
+            .limit
+            .unwrap_or(AuditQuery::default().limit)
+            .clamp(1, MAX_AUDIT_PAGE_SIZE),
+    };
+    if let (Some(since), Some(until)) = (query.since, query.until) {
+        if since >= until {
+            return Err(OAuth2Error::invalid_request("since must be before until"));
+        }
+    }
+
+    let records = sink.query(&query).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .json(AuditList { records }))
+}
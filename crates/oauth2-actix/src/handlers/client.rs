@@ -1,103 +1,248 @@
 use actix::Addr;
-use actix_web::{web, HttpResponse, Result};
-
-use crate::actors::{ClientActor, RegisterClient};
-use oauth2_core::{ClientCredentials, ClientRegistration, OAuth2Error};
-
-fn validate_redirect_uri(uri: &str) -> Result<(), OAuth2Error> {
-    let uri = uri.trim();
-    if uri.is_empty() {
-        return Err(OAuth2Error::invalid_request(
-            "redirect_uri must not be empty",
-        ));
-    }
+use actix_web::{http::header, web, HttpRequest, HttpResponse, Result};
+
+use crate::actors::{
+    ClientActor, DeleteClientRegistration, ReadClientRegistration, RegisterClient,
+    UpdateClientRegistration,
+};
+use crate::deadline::Deadline;
+use crate::grants::{registry_or_builtin, GrantRegistry};
+use oauth2_config::GrantsConfig;
+use oauth2_core::{
+    ClientInformationResponse, ClientRegistration, ClientType, ClientUpdateRequest, IssuerUrls,
+    OAuth2Error, RedirectUri, RequestOrigin, TenantContext,
+};
+
+/// JSON extractor config for the registration endpoints: malformed metadata, including
+/// redirect URIs that fail [`RedirectUri`] parsing, is reported as an OAuth2
+/// `invalid_request` error rather than actix's plain-text 400.
+pub fn registration_json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(|err, _req| {
+        let description = match &err {
+            actix_web::error::JsonPayloadError::Deserialize(e) => e.to_string(),
+            other => other.to_string(),
+        };
+        OAuth2Error::invalid_request(&description).into()
+    })
+}
 
-    // OAuth 2.0 requires redirection URIs to be absolute and MUST NOT include fragments.
-    // Keep this validation intentionally simple and conservative.
-    if uri.contains('#') {
-        return Err(OAuth2Error::invalid_request(
-            "redirect_uri must not contain a fragment",
-        ));
-    }
-    if uri.contains('\r') || uri.contains('\n') {
+fn validate_grant_types(
+    grant_types: &[String],
+    registry: &GrantRegistry,
+) -> Result<(), OAuth2Error> {
+    if grant_types.is_empty() {
         return Err(OAuth2Error::invalid_request(
-            "redirect_uri contains invalid characters",
+            "grant_types must not be empty",
         ));
     }
 
-    let lower = uri.to_ascii_lowercase();
-    if lower.starts_with("javascript:") || lower.starts_with("data:") {
-        return Err(OAuth2Error::invalid_request(
-            "redirect_uri uses a disallowed URI scheme",
-        ));
-    }
-
-    // Minimal absolute-URI check.
-    if !uri.contains("://") {
-        return Err(OAuth2Error::invalid_request(
-            "redirect_uri must be an absolute URI",
-        ));
+    // Keep registration honest: only allow grant types the token endpoint accepts, so
+    // refresh and password grants follow server configuration ('implicit' is never one).
+    if let Some(gt) = grant_types.iter().find(|gt| registry.get(gt).is_none()) {
+        return Err(OAuth2Error::invalid_request(&format!(
+            "unsupported or disabled grant_type '{gt}' in registration"
+        )));
     }
 
     Ok(())
 }
 
-fn validate_grant_types(grant_types: &[String]) -> Result<(), OAuth2Error> {
-    // Keep registration honest: only allow grant types that the server actually supports.
-    // (prevents clients from registering for 'implicit' / 'refresh_token' etc.)
-    const SUPPORTED: [&str; 2] = ["authorization_code", "client_credentials"];
-
-    if grant_types.is_empty() {
+/// Client type independent checks; the actor checks the rest against the stored type.
+/// Each redirect URI was already validated when it was parsed.
+fn validate_client_metadata(
+    redirect_uris: &[RedirectUri],
+    grant_types: &[String],
+    scope: &str,
+    registry: &GrantRegistry,
+) -> Result<(), OAuth2Error> {
+    validate_grant_types(grant_types, registry)?;
+
+    if redirect_uris.is_empty() {
         return Err(OAuth2Error::invalid_request(
-            "grant_types must not be empty",
+            "redirect_uris must not be empty",
         ));
     }
 
-    for gt in grant_types {
-        if !SUPPORTED.contains(&gt.as_str()) {
-            return Err(OAuth2Error::invalid_request(
-                "unsupported or disabled grant_type in registration",
-            ));
-        }
+    if scope.trim().is_empty() {
+        return Err(OAuth2Error::invalid_request("scope must not be empty"));
     }
 
     Ok(())
 }
 
+/// Absolute URL of the RFC 7592 client configuration endpoint for `client_id`.
+fn registration_client_uri(
+    req: &HttpRequest,
+    issuer_urls: Option<&IssuerUrls>,
+    client_id: &str,
+) -> String {
+    issuer_urls.cloned().unwrap_or_default().url(
+        &RequestOrigin::from(req),
+        &format!("/oauth/register/{client_id}"),
+    )
+}
+
+/// Extract the registration access token from `Authorization: Bearer <token>`.
+fn registration_access_token(req: &HttpRequest) -> Result<String, OAuth2Error> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| OAuth2Error::invalid_token("Missing registration access token"))
+}
+
 /// Register a new OAuth2 client
+///
+/// The response includes the client secret (none for public and native clients) and a
+/// registration access token for managing the registration via
+/// `/oauth/register/{client_id}`. Neither is retrievable later.
 pub async fn register_client(
+    req: HttpRequest,
     registration: web::Json<ClientRegistration>,
     client_actor: web::Data<Addr<ClientActor>>,
+    issuer_urls: Option<web::Data<IssuerUrls>>,
+    grants: Option<web::Data<GrantsConfig>>,
+    registry: Option<web::Data<GrantRegistry>>,
+    tenant: Option<web::ReqData<TenantContext>>,
 ) -> Result<HttpResponse, OAuth2Error> {
     // Validate registration input early (OWASP OAuth guidance: strict redirect URI handling).
     let reg: &ClientRegistration = &registration;
-    validate_grant_types(&reg.grant_types)?;
-
-    if reg.redirect_uris.is_empty() {
+    validate_client_metadata(
+        &reg.redirect_uris,
+        &reg.grant_types,
+        &reg.scope,
+        &registry_or_builtin(registry, grants.as_ref()),
+    )?;
+    if reg.client_type == ClientType::ResourceServer {
         return Err(OAuth2Error::invalid_request(
-            "redirect_uris must not be empty",
+            "resource_server clients are created by an administrator",
         ));
     }
-    for uri in &reg.redirect_uris {
-        validate_redirect_uri(uri)?;
-    }
-
-    if reg.scope.trim().is_empty() {
-        return Err(OAuth2Error::invalid_request("scope must not be empty"));
-    }
+    reg.client_type
+        .validate_metadata(&reg.redirect_uris, &reg.grant_types)?;
+    reg.metadata.validate(reg.client_type)?;
 
-    let client = client_actor
+    let registered = client_actor
         .send(RegisterClient {
             registration: registration.into_inner(),
+            tenant_id: tenant.map(|tenant| tenant.id().to_string()),
+            span: tracing::Span::current(),
+            deadline: Deadline::current(),
+        })
+        .await
+        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
+
+    let client = registered.client;
+    let mut response = ClientInformationResponse::from_client(
+        &client,
+        registration_client_uri(
+            &req,
+            issuer_urls.as_ref().map(|urls| urls.get_ref()),
+            &client.client_id,
+        ),
+    );
+    response.client_secret = registered.client_secret;
+    response.registration_access_token = Some(registered.registration_access_token);
+
+    Ok(HttpResponse::Created().json(response))
+}
+
+/// Read the current registration of a client (RFC 7592 section 2.1)
+pub async fn get_client_configuration(
+    req: HttpRequest,
+    client_id: web::Path<String>,
+    client_actor: web::Data<Addr<ClientActor>>,
+    issuer_urls: Option<web::Data<IssuerUrls>>,
+    tenant: Option<web::ReqData<TenantContext>>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let client = client_actor
+        .send(ReadClientRegistration {
+            client_id: client_id.into_inner(),
+            registration_access_token: registration_access_token(&req)?,
+            tenant_id: tenant.map(|tenant| tenant.id().to_string()),
             span: tracing::Span::current(),
+            deadline: Deadline::current(),
         })
         .await
         .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
 
-    let credentials = ClientCredentials {
-        client_id: client.client_id,
-        client_secret: client.client_secret,
-    };
+    Ok(
+        HttpResponse::Ok().json(ClientInformationResponse::from_client(
+            &client,
+            registration_client_uri(
+                &req,
+                issuer_urls.as_ref().map(|urls| urls.get_ref()),
+                &client.client_id,
+            ),
+        )),
+    )
+}
+
+/// Replace the registered metadata of a client (RFC 7592 section 2.2)
+#[allow(clippy::too_many_arguments)] // actix extractors
+pub async fn update_client_configuration(
+    req: HttpRequest,
+    client_id: web::Path<String>,
+    update: web::Json<ClientUpdateRequest>,
+    client_actor: web::Data<Addr<ClientActor>>,
+    issuer_urls: Option<web::Data<IssuerUrls>>,
+    grants: Option<web::Data<GrantsConfig>>,
+    registry: Option<web::Data<GrantRegistry>>,
+    tenant: Option<web::ReqData<TenantContext>>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let token = registration_access_token(&req)?;
+    let update = update.into_inner();
+    validate_client_metadata(
+        &update.redirect_uris,
+        &update.grant_types,
+        &update.scope,
+        &registry_or_builtin(registry, grants.as_ref()),
+    )?;
+
+    let client = client_actor
+        .send(UpdateClientRegistration {
+            client_id: client_id.into_inner(),
+            registration_access_token: token,
+            update,
+            tenant_id: tenant.map(|tenant| tenant.id().to_string()),
+            span: tracing::Span::current(),
+            deadline: Deadline::current(),
+        })
+        .await
+        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
+
+    Ok(
+        HttpResponse::Ok().json(ClientInformationResponse::from_client(
+            &client,
+            registration_client_uri(
+                &req,
+                issuer_urls.as_ref().map(|urls| urls.get_ref()),
+                &client.client_id,
+            ),
+        )),
+    )
+}
+
+/// Deprovision a client and everything issued to it (RFC 7592 section 2.3)
+pub async fn delete_client_configuration(
+    req: HttpRequest,
+    client_id: web::Path<String>,
+    client_actor: web::Data<Addr<ClientActor>>,
+    tenant: Option<web::ReqData<TenantContext>>,
+) -> Result<HttpResponse, OAuth2Error> {
+    client_actor
+        .send(DeleteClientRegistration {
+            client_id: client_id.into_inner(),
+            registration_access_token: registration_access_token(&req)?,
+            tenant_id: tenant.map(|tenant| tenant.id().to_string()),
+            span: tracing::Span::current(),
+            deadline: Deadline::current(),
+        })
+        .await
+        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
 
-    Ok(HttpResponse::Created().json(credentials))
+    Ok(HttpResponse::NoContent().finish())
 }
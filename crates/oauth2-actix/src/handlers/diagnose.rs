@@ -0,0 +1,262 @@
+use actix::Addr;
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use oauth2_config::GrantsConfig;
+use oauth2_core::{AdminRole, Claims, GrantType, IssuerKeys, OAuth2Error, TokenMetadata};
+use oauth2_ports::{validate_scope_subset, DynStorage};
+
+use super::admin::require_admin;
+use crate::actors::{PreviewClaims, TokenActor, ACCESS_TOKEN_TTL_SECS, REFRESH_TOKEN_TTL_SECS};
+use crate::deadline::Deadline;
+
+/// Token request to simulate. Client and user credentials are not needed: the diagnosis
+/// covers policy, not authentication.
+#[derive(Debug, Deserialize)]
+pub struct DiagnoseTokenRequest {
+    pub client_id: String,
+    /// Tenant of the client; omitted for the default tenant.
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    pub grant_type: String,
+    /// Requested scope; omitted means the grant's default, as at the token endpoint.
+    pub scope: Option<String>,
+    /// Resource owner for user grants.
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub metadata: TokenMetadata,
+}
+
+/// One policy check in the order the token endpoint applies it.
+#[derive(Debug, Serialize)]
+pub struct DiagnosticCheck {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+    /// Error code the token endpoint would return when this check fails.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// What the token endpoint would decide for a request, and why.
+#[derive(Debug, Serialize)]
+pub struct TokenDiagnosis {
+    pub allowed: bool,
+    pub checks: Vec<DiagnosticCheck>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_token_ttl_secs: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token_ttl_secs: Option<i64>,
+    /// Access token claims, including those added by claims enrichers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub claims: Option<Claims>,
+}
+
+#[derive(Default)]
+struct Trace {
+    checks: Vec<DiagnosticCheck>,
+}
+
+impl Trace {
+    fn pass(&mut self, name: &'static str, detail: impl Into<String>) {
+        self.checks.push(DiagnosticCheck {
+            name,
+            passed: true,
+            detail: detail.into(),
+            error: None,
+        });
+    }
+
+    fn fail(&mut self, name: &'static str, error: OAuth2Error) {
+        self.checks.push(DiagnosticCheck {
+            name,
+            passed: false,
+            detail: error.error_description.clone().unwrap_or_default(),
+            error: Some(error.error),
+        });
+    }
+}
+
+/// Simulate a token request and return the decision trace. Nothing is signed, persisted
+/// or published; the checks stop at the first one the token endpoint would reject.
+pub async fn diagnose_token(
+    req: HttpRequest,
+    body: web::Json<DiagnoseTokenRequest>,
+    issuer_keys: web::Data<IssuerKeys>,
+    db: web::Data<DynStorage>,
+    token_actor: web::Data<Addr<TokenActor>>,
+    grants: Option<web::Data<GrantsConfig>>,
+) -> Result<HttpResponse, OAuth2Error> {
+    require_admin(&req, &issuer_keys, &db, AdminRole::Operator).await?;
+
+    let request = body.into_inner();
+    let grants = grants
+        .as_ref()
+        .map(|grants| grants.get_ref().clone())
+        .unwrap_or_default();
+
+    let mut trace = Trace::default();
+    let scope = evaluate(&request, &grants, &db, &mut trace).await?;
+
+    let mut diagnosis = TokenDiagnosis {
+        allowed: false,
+        checks: Vec::new(),
+        scope: None,
+        access_token_ttl_secs: None,
+        refresh_token_ttl_secs: None,
+        claims: None,
+    };
+    if let Some((scope, issues_refresh)) = scope {
+        let preview = token_actor
+            .send(PreviewClaims {
+                grant_type: request.grant_type.clone(),
+                user_id: request.user_id.clone(),
+                client_id: request.client_id.clone(),
+                scope: scope.clone(),
+                metadata: request.metadata,
+                span: tracing::Span::current(),
+                deadline: Deadline::current(),
+            })
+            .await
+            .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))?;
+        match preview {
+            Ok(claims) => {
+                trace.pass(
+                    "claims",
+                    "issuance policies and claims enrichers accepted the token",
+                );
+                diagnosis.allowed = true;
+                diagnosis.scope = Some(claims.scope.clone());
+                diagnosis.access_token_ttl_secs = Some(ACCESS_TOKEN_TTL_SECS);
+                diagnosis.refresh_token_ttl_secs = issues_refresh.then_some(REFRESH_TOKEN_TTL_SECS);
+                diagnosis.claims = Some(claims);
+            }
+            Err(error) => {
+                trace.fail("claims", error);
+            }
+        }
+    }
+    diagnosis.checks = trace.checks;
+
+    tracing::info!(
+        client_id = %request.client_id,
+        grant_type = %request.grant_type,
+        allowed = diagnosis.allowed,
+        "Token request diagnosed"
+    );
+    Ok(HttpResponse::Ok().json(diagnosis))
+}
+
+/// Run the policy checks. Returns the granted scope and whether a refresh token would be
+/// issued, or `None` once a check fails. Storage errors are returned as is.
+async fn evaluate(
+    request: &DiagnoseTokenRequest,
+    grants: &GrantsConfig,
+    db: &DynStorage,
+    trace: &mut Trace,
+) -> Result<Option<(String, bool)>, OAuth2Error> {
+    let grant_type = match request.grant_type.parse::<GrantType>() {
+        Ok(grant_type) => grant_type,
+        Err(error) => {
+            trace.fail("grant_type", error);
+            return Ok(None);
+        }
+    };
+    if !grants.is_enabled(grant_type) {
+        trace.fail(
+            "grant_type",
+            OAuth2Error::unsupported_grant_type("Grant type disabled"),
+        );
+        return Ok(None);
+    }
+    trace.pass("grant_type", format!("{grant_type} is enabled"));
+
+    let Some(client) = db
+        .get_client(&request.client_id, request.tenant_id.as_deref())
+        .await?
+    else {
+        trace.fail("client", OAuth2Error::invalid_client("Client not found"));
+        return Ok(None);
+    };
+    trace.pass("client", format!("client '{}' exists", client.name));
+
+    if !client.supports_grant_type(grant_type) {
+        trace.fail(
+            "client_grant_type",
+            OAuth2Error::unauthorized_client(&format!("Client is not allowed to use {grant_type}")),
+        );
+        return Ok(None);
+    }
+    trace.pass(
+        "client_grant_type",
+        format!("client grant_types: {}", client.get_grant_types().join(" ")),
+    );
+
+    // Client credentials tokens for service accounts carry the account's scopes.
+    let mut allowed_scope = client.scope.clone();
+    let mut default_scope = match grant_type {
+        GrantType::ClientCredentials | GrantType::AuthorizationCode => "read".to_string(),
+        GrantType::RefreshToken | GrantType::Password => client.scope.clone(),
+    };
+    if grant_type == GrantType::ClientCredentials {
+        if let Some(account) = db
+            .get_service_account_by_client_id(&client.client_id)
+            .await?
+        {
+            if !account.enabled {
+                trace.fail(
+                    "service_account",
+                    OAuth2Error::invalid_client("Service account is disabled"),
+                );
+                return Ok(None);
+            }
+            let method = if account.public_key.is_some() {
+                "client_assertion"
+            } else {
+                "client_secret"
+            };
+            trace.pass(
+                "service_account",
+                format!(
+                    "service account '{}' authenticates with {method}",
+                    account.name
+                ),
+            );
+            allowed_scope = account.scope.clone();
+            default_scope = account.scope;
+        }
+    }
+
+    if grant_type.is_user_grant() {
+        let Some(user_id) = request.user_id.as_deref() else {
+            trace.fail(
+                "user",
+                OAuth2Error::invalid_request("user_id is required to simulate a user grant"),
+            );
+            return Ok(None);
+        };
+        let user = db.get_user(user_id).await?;
+        if !user.as_ref().is_some_and(|user| user.enabled) {
+            trace.fail(
+                "user",
+                OAuth2Error::invalid_grant("User does not exist or is disabled"),
+            );
+            return Ok(None);
+        }
+        trace.pass("user", format!("user '{user_id}' is enabled"));
+    }
+
+    let scope = request.scope.clone().unwrap_or(default_scope);
+    if let Err(error) = validate_scope_subset(&scope, &allowed_scope) {
+        trace.fail("scope", error);
+        return Ok(None);
+    }
+    trace.pass("scope", format!("'{scope}' is within '{allowed_scope}'"));
+
+    let issues_refresh = grant_type != GrantType::ClientCredentials
+        && grants.refresh_token
+        && client.supports_grant_type(GrantType::RefreshToken);
+    Ok(Some((scope, issues_refresh)))
+}
@@ -1,67 +1,353 @@
 use actix::Addr;
 use actix_web::{web, HttpRequest, HttpResponse, Result};
-use serde::Serialize;
-use std::collections::HashMap;
+use async_trait::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::Mutex;
 
-use oauth2_events::{event_actor::GetPluginHealth, EventBusHandle, EventEnvelope};
+use oauth2_core::{AdminRole, OAuth2Error};
+use oauth2_events::{
+    dedupe_by_idempotency_key,
+    event_actor::{EventActor, GetPluginHealth, GetPluginNames, ReplayEvent},
+    DynEventReplaySource, EventBusHandle, EventEnvelope, EventType, LiveEventStream, ReplayRange,
+};
+use oauth2_observability::{HealthCheck, Metrics};
+use oauth2_ports::{DynIdempotencyBackend, IdempotencyBackend};
 
-/// Best-effort in-memory idempotency store for `/events/ingest`.
+use crate::middleware::admin_rbac::AdminScope;
+use crate::middleware::bearer::AuthenticatedToken;
+use crate::security_events::SecurityEvents;
+
+/// Best-effort idempotency store for `/events/ingest`.
 ///
-/// Phase 1 semantics:
+/// Semantics:
 /// - Dedupes by effective idempotency key (header preferred; else `envelope.idempotency_key`; else `event.id`).
 /// - TTL-based eviction; no persistence.
-///
-/// Phase 2+ should replace this with a persistent inbox/outbox.
+/// - With a shared [`IdempotencyBackend`] (e.g. Redis), keys are deduplicated across replicas.
+///   If the backend errors, the check degrades to the local per-replica map instead of failing ingest.
 #[derive(Clone)]
 pub struct IdempotencyStore {
     ttl: Duration,
-    max_entries: usize,
-    inner: Arc<Mutex<HashMap<String, Instant>>>,
+    local: InMemoryIdempotencyBackend,
+    shared: Option<DynIdempotencyBackend>,
+    metrics: Option<Metrics>,
 }
 
 impl IdempotencyStore {
     pub fn new(ttl: Duration) -> Self {
         Self {
             ttl,
+            local: InMemoryIdempotencyBackend::new(),
+            shared: None,
+            metrics: None,
+        }
+    }
+
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.local = self.local.with_max_entries(max_entries);
+        self
+    }
+
+    /// Deduplicate through a shared backend, keeping the local map as a fallback.
+    pub fn with_backend(mut self, backend: DynIdempotencyBackend) -> Self {
+        self.shared = Some(backend);
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.local = self.local.with_metrics(metrics.clone());
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Name of the backend deduplicating keys while it is reachable.
+    pub fn backend_name(&self) -> &'static str {
+        self.shared
+            .as_ref()
+            .map_or(self.local.backend_name(), |shared| shared.backend_name())
+    }
+
+    /// Returns `true` if the key was already present (duplicate), else records it and returns `false`.
+    pub async fn is_duplicate_and_record(&self, key: &str) -> bool {
+        if let Some(shared) = &self.shared {
+            match shared.check_and_record(key, self.ttl).await {
+                Ok(duplicate) => {
+                    self.record_check(shared.backend_name(), duplicate);
+                    return duplicate;
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        backend = shared.backend_name(),
+                        error = %e,
+                        "idempotency backend unavailable; falling back to local store"
+                    );
+                    if let Some(metrics) = &self.metrics {
+                        metrics.events_idempotency_fallbacks_total.inc();
+                    }
+                }
+            }
+        }
+
+        let duplicate = self.local.record_locally(key, self.ttl).await;
+        self.record_check(self.local.backend_name(), duplicate);
+        duplicate
+    }
+
+    fn record_check(&self, backend: &str, duplicate: bool) {
+        if let Some(metrics) = &self.metrics {
+            let result = if duplicate { "duplicate" } else { "recorded" };
+            metrics
+                .events_idempotency_checks_total
+                .with_label_values(&[backend, result])
+                .inc();
+        }
+    }
+}
+
+/// [`IdempotencyBackend`] in process memory; each replica only sees its own keys.
+///
+/// Holds at most `max_entries` keys (100 000 by default) and forgets all of them when full.
+#[derive(Clone)]
+pub struct InMemoryIdempotencyBackend {
+    max_entries: usize,
+    inner: Arc<Mutex<HashMap<String, (Instant, Duration)>>>,
+    metrics: Option<Metrics>,
+}
+
+impl Default for InMemoryIdempotencyBackend {
+    fn default() -> Self {
+        Self {
             max_entries: 100_000,
             inner: Arc::new(Mutex::new(HashMap::new())),
+            metrics: None,
         }
     }
+}
+
+impl InMemoryIdempotencyBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
     pub fn with_max_entries(mut self, max_entries: usize) -> Self {
         self.max_entries = max_entries;
         self
     }
 
-    /// Returns `true` if the key was already present (duplicate), else records it and returns `false`.
-    pub async fn is_duplicate_and_record(&self, key: &str) -> bool {
+    /// Report the number of held keys as `events_idempotency_local_entries`.
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    async fn record_locally(&self, key: &str, ttl: Duration) -> bool {
         let now = Instant::now();
         let mut guard = self.inner.lock().await;
 
         // Prune expired entries opportunistically.
         if !guard.is_empty() {
-            let ttl = self.ttl;
-            guard.retain(|_, ts| now.duration_since(*ts) <= ttl);
+            guard.retain(|_, (recorded, ttl)| now.duration_since(*recorded) <= *ttl);
+        }
+
+        let duplicate = if guard.contains_key(key) {
+            true
+        } else {
+            if guard.len() >= self.max_entries {
+                tracing::warn!(
+                    max_entries = self.max_entries,
+                    current_entries = guard.len(),
+                    "idempotency cache full; clearing (best-effort)"
+                );
+                guard.clear();
+            }
+
+            guard.insert(key.to_string(), (now, ttl));
+            false
+        };
+
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .events_idempotency_local_entries
+                .set(guard.len() as i64);
         }
 
-        if guard.contains_key(key) {
-            return true;
+        duplicate
+    }
+}
+
+#[async_trait]
+impl IdempotencyBackend for InMemoryIdempotencyBackend {
+    fn backend_name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn check_and_record(&self, key: &str, ttl: Duration) -> Result<bool, OAuth2Error> {
+        Ok(self.record_locally(key, ttl).await)
+    }
+}
+
+/// Scope letting a token publish through [`ingest`].
+pub use oauth2_ports::EVENTS_INGEST_SCOPE;
+
+/// Header carrying a producer's signature of an [`ingest`] request:
+/// `producer=<id>,t=<unix seconds>,v1=<hex HMAC-SHA256 of "<t>.<body>">`.
+pub const EVENT_SIGNATURE_HEADER: &str = "X-Event-Signature";
+
+/// How far a signature's timestamp may be from the server's clock.
+const SIGNATURE_TOLERANCE_SECS: i64 = 5 * 60;
+
+/// Who may call [`ingest`]: bearer tokens granted [`EVENTS_INGEST_SCOPE`] (or an admin
+/// role of at least `operator`), and configured producers signing the request body with a
+/// shared secret (see [`EVENT_SIGNATURE_HEADER`]).
+///
+/// Each caller, by producer or client id, has its own per-minute quota. Counters live in
+/// memory, so each replica enforces the quota separately. Without registered `IngestAuth`,
+/// ingest accepts tokens only, without a quota.
+#[derive(Clone, Default)]
+pub struct IngestAuth {
+    open: bool,
+    producers: HashMap<String, String>,
+    limit: Option<u32>,
+    windows: Arc<std::sync::Mutex<HashMap<String, (Instant, u32)>>>,
+}
+
+impl IngestAuth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept requests without credentials, e.g. when a network policy already restricts
+    /// who can reach the endpoint.
+    pub fn open() -> Self {
+        Self {
+            open: true,
+            ..Self::default()
         }
+    }
 
-        if guard.len() >= self.max_entries {
-            tracing::warn!(
-                max_entries = self.max_entries,
-                current_entries = guard.len(),
-                "idempotency cache full; clearing (best-effort)"
-            );
-            guard.clear();
+    /// Accept requests signed with `secret` as producer `id`.
+    pub fn with_producer(mut self, id: impl Into<String>, secret: impl Into<String>) -> Self {
+        self.producers.insert(id.into(), secret.into());
+        self
+    }
+
+    /// Limit each caller to `limit` requests per minute.
+    pub fn with_rate_limit_per_minute(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// The producer or client id `req` authenticates as; `None` when the endpoint is open.
+    async fn authenticate(
+        &self,
+        req: &HttpRequest,
+        body: &[u8],
+    ) -> Result<Option<String>, OAuth2Error> {
+        if self.open {
+            return Ok(None);
+        }
+        if let Some(signature) = req.headers().get(EVENT_SIGNATURE_HEADER) {
+            let signature = signature.to_str().unwrap_or_default();
+            return match self.verify_signature(signature, body, Utc::now().timestamp()) {
+                Ok(producer) => Ok(Some(producer)),
+                Err(reason) => {
+                    tracing::warn!(reason, "Rejected signed event ingest");
+                    SecurityEvents::for_request(req).publish(
+                        EventType::ClientAuthenticationFailed,
+                        None,
+                        None,
+                        &[("endpoint", req.path()), ("reason", reason)],
+                    );
+                    Err(OAuth2Error::invalid_client("Invalid event signature"))
+                }
+            };
         }
 
-        guard.insert(key.to_string(), now);
-        false
+        let token = AuthenticatedToken::from_http_request(req).await?;
+        if token.has_scope(EVENTS_INGEST_SCOPE) {
+            return Ok(Some(token.token.client_id));
+        }
+        // Operators could ingest before the dedicated scope existed.
+        let admin_scope = req
+            .app_data::<web::Data<AdminScope>>()
+            .map(|scope| scope.get_ref().clone())
+            .unwrap_or_default();
+        if AdminRole::from_claims_with_admin_scope(&token.claims, &admin_scope.0)
+            .is_some_and(|role| role.allows(AdminRole::Operator))
+        {
+            return Ok(Some(token.token.client_id));
+        }
+        Err(OAuth2Error::insufficient_scope(&format!(
+            "Token lacks the '{EVENTS_INGEST_SCOPE}' scope"
+        ))
+        .with_required_scope(EVENTS_INGEST_SCOPE))
+    }
+
+    /// The producer that signed `body` with the [`EVENT_SIGNATURE_HEADER`] value `header`,
+    /// or why the signature is rejected.
+    fn verify_signature(
+        &self,
+        header: &str,
+        body: &[u8],
+        now: i64,
+    ) -> Result<String, &'static str> {
+        let (mut producer, mut timestamp, mut signature) = (None, None, None);
+        for part in header.split(',') {
+            match part.trim().split_once('=') {
+                Some(("producer", value)) => producer = Some(value),
+                Some(("t", value)) => timestamp = Some(value),
+                Some(("v1", value)) => signature = hex::decode(value).ok(),
+                _ => {}
+            }
+        }
+        let (Some(producer), Some(timestamp), Some(signature)) = (producer, timestamp, signature)
+        else {
+            return Err("malformed_signature");
+        };
+        let secret = self.producers.get(producer).ok_or("unknown_producer")?;
+        let signed_at: i64 = timestamp.parse().map_err(|_| "malformed_signature")?;
+        if (now - signed_at).abs() > SIGNATURE_TOLERANCE_SECS {
+            return Err("expired_signature");
+        }
+
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+        mac.update(timestamp.as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        mac.verify_slice(&signature)
+            .map_err(|_| "invalid_signature")?;
+        Ok(producer.to_string())
+    }
+
+    /// Count a request by `caller`. Over quota, returns how long until the window resets.
+    fn check_quota(&self, caller: &str) -> Result<(), Duration> {
+        let Some(limit) = self.limit else {
+            return Ok(());
+        };
+        let window = Duration::from_secs(60);
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        if windows.len() >= 10_000 {
+            windows.retain(|_, (started, _)| now.duration_since(*started) < window);
+        }
+
+        let (started, count) = windows.entry(caller.to_string()).or_insert((now, 0));
+        if now.duration_since(*started) >= window {
+            *started = now;
+            *count = 0;
+        }
+        if *count >= limit {
+            return Err(window - now.duration_since(*started));
+        }
+        *count += 1;
+        Ok(())
     }
 }
 
@@ -74,18 +360,46 @@ struct IngestResponse {
 
 /// Ingest an externally-produced event envelope.
 ///
-/// Best practice for callers: set `Idempotency-Key` header.
+/// Callers authenticate as described on [`IngestAuth`]; those over their quota get
+/// `429 rate_limit_exceeded`. Best practice for callers: set `Idempotency-Key` header.
+///
+/// Ingested envelopes are relayed unsigned: any signature they arrive with is dropped and
+/// the server's signing key is not applied, so consumers cannot mistake them for events
+/// the server produced.
 pub async fn ingest(
     req: HttpRequest,
-    envelope: web::Json<EventEnvelope>,
+    body: web::Bytes,
+    auth: Option<web::Data<IngestAuth>>,
     idempotency: web::Data<IdempotencyStore>,
     event_bus: Option<web::Data<EventBusHandle>>,
 ) -> Result<HttpResponse> {
+    let auth = auth.map(|auth| auth.get_ref().clone()).unwrap_or_default();
+    let caller = auth.authenticate(&req, &body).await?;
+    if let Some(Err(retry_after)) = caller.as_deref().map(|caller| auth.check_quota(caller)) {
+        tracing::warn!(caller = caller.as_deref(), "Event ingest quota exceeded");
+        SecurityEvents::for_request(&req).publish(
+            EventType::RateLimitExceeded,
+            caller.as_deref(),
+            None,
+            &[("limit", "event_ingest")],
+        );
+        return Ok(HttpResponse::TooManyRequests()
+            .insert_header((
+                actix_web::http::header::RETRY_AFTER,
+                retry_after.as_secs().max(1).to_string(),
+            ))
+            .json(OAuth2Error::rate_limit_exceeded(
+                "Event ingest quota exceeded; retry later",
+            )));
+    }
+
     let Some(event_bus) = event_bus else {
         return Ok(HttpResponse::ServiceUnavailable().json(serde_json::json!({
             "error": "eventing_disabled"
         })));
     };
+    let mut envelope: EventEnvelope = serde_json::from_slice(&body)
+        .map_err(|e| OAuth2Error::invalid_request(&format!("Invalid event envelope: {e}")))?;
 
     let header_idempotency_key = req
         .headers()
@@ -94,7 +408,7 @@ pub async fn ingest(
         .map(|s| s.trim().to_string())
         .filter(|s| !s.is_empty());
 
-    let mut envelope = envelope.into_inner();
+    envelope.signature = None;
     if let Some(k) = header_idempotency_key {
         envelope = envelope.with_idempotency_key(k);
     }
@@ -110,7 +424,7 @@ pub async fn ingest(
         }));
     }
 
-    event_bus.publish_best_effort(envelope);
+    event_bus.without_signing().publish_best_effort(envelope);
 
     Ok(HttpResponse::Accepted().json(IngestResponse {
         status: "accepted",
@@ -119,6 +433,215 @@ pub async fn ingest(
     }))
 }
 
+/// Filters for [`stream`], each a comma-separated list of event type names.
+#[derive(Deserialize)]
+pub struct EventStreamQuery {
+    /// Only stream these event types.
+    event_types: Option<String>,
+    /// Don't stream these event types.
+    exclude: Option<String>,
+}
+
+/// How often an idle stream sends a comment, so proxies don't close it.
+const STREAM_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Stream events as they are published, as Server-Sent Events.
+///
+/// Each event is sent with its type as the SSE event name, its id as the SSE id and the
+/// envelope as JSON data, after the configured event filter. Events published while no
+/// one is connected are not replayed. A client that falls behind gets a `lagged` event
+/// with the number of events it missed.
+pub async fn stream(
+    query: web::Query<EventStreamQuery>,
+    live_stream: Option<web::Data<LiveEventStream>>,
+) -> Result<HttpResponse> {
+    let Some(live_stream) = live_stream else {
+        return Ok(HttpResponse::ServiceUnavailable().json(serde_json::json!({
+            "error": "eventing_disabled"
+        })));
+    };
+    let include = query
+        .event_types
+        .as_deref()
+        .map(parse_event_types)
+        .transpose()?;
+    let exclude = query
+        .exclude
+        .as_deref()
+        .map(parse_event_types)
+        .transpose()?
+        .unwrap_or_default();
+
+    let state = (live_stream.subscribe(), include, exclude);
+    let body = futures::stream::unfold(state, |(mut receiver, include, exclude)| async move {
+        loop {
+            let frame = match tokio::time::timeout(STREAM_KEEP_ALIVE, receiver.recv()).await {
+                Err(_) => ": keep-alive\n\n".to_string(),
+                Ok(Ok(envelope)) => {
+                    let event_type = &envelope.event.event_type;
+                    if include
+                        .as_ref()
+                        .is_some_and(|types| !types.contains(event_type))
+                        || exclude.contains(event_type)
+                    {
+                        continue;
+                    }
+                    match serde_json::to_string(&envelope) {
+                        Ok(data) => format!(
+                            "id: {}\nevent: {}\ndata: {data}\n\n",
+                            envelope.event.id,
+                            event_type.as_str()
+                        ),
+                        Err(e) => {
+                            tracing::warn!(error = %e, "Failed to serialize streamed event");
+                            continue;
+                        }
+                    }
+                }
+                Ok(Err(RecvError::Lagged(skipped))) => {
+                    format!("event: lagged\ndata: {{\"skipped\":{skipped}}}\n\n")
+                }
+                Ok(Err(RecvError::Closed)) => return None,
+            };
+            let frame = Ok::<_, actix_web::Error>(web::Bytes::from(frame));
+            return Some((frame, (receiver, include, exclude)));
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((actix_web::http::header::CACHE_CONTROL, "no-cache"))
+        // Tell nginx not to buffer the stream.
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(body))
+}
+
+/// Event types named in a comma-separated list, failing on unknown names.
+fn parse_event_types(names: &str) -> Result<HashSet<EventType>, OAuth2Error> {
+    names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            serde_json::from_value(serde_json::Value::String(name.to_string()))
+                .map_err(|_| OAuth2Error::invalid_request(&format!("Unknown event type: {name}")))
+        })
+        .collect()
+}
+
+/// Most events one replay request delivers.
+const MAX_REPLAY_EVENTS: usize = 10_000;
+
+/// Body of [`replay`]: a range (`from`/`to` times, `from_id`/`to_id` Redis Streams ids, or
+/// a Kafka `partition` with `from_offset`/`to_offset`) and where to deliver it.
+#[derive(Deserialize)]
+pub struct ReplayRequest {
+    #[serde(flatten)]
+    range: ReplayRange,
+    /// Plugins to deliver to, by name; every plugin when absent.
+    plugins: Option<Vec<String>>,
+    /// Most events to replay, up to 10 000.
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct ReplayResponse {
+    source: String,
+    read: usize,
+    duplicates: usize,
+    replayed: usize,
+    failed: usize,
+    errors: Vec<String>,
+}
+
+/// Read events back from the configured backend and deliver them again to some or all
+/// plugins, oldest first.
+///
+/// Envelopes are delivered unchanged, so consumers can skip ones they already handled by
+/// idempotency key. Envelopes repeating an earlier key in the range are delivered once.
+pub async fn replay(
+    body: web::Json<ReplayRequest>,
+    source: Option<web::Data<DynEventReplaySource>>,
+    event_actor: Option<web::Data<Addr<EventActor>>>,
+) -> Result<HttpResponse> {
+    let Some(event_actor) = event_actor else {
+        return Ok(HttpResponse::ServiceUnavailable().json(serde_json::json!({
+            "error": "eventing_disabled"
+        })));
+    };
+    let Some(source) = source else {
+        return Ok(HttpResponse::ServiceUnavailable().json(serde_json::json!({
+            "error": "replay_unavailable",
+            "error_description": "The event backend keeps no events to replay"
+        })));
+    };
+    let ReplayRequest {
+        range,
+        plugins,
+        limit,
+    } = body.into_inner();
+
+    if let ReplayRange::Time { from, to } = &range {
+        if from > to {
+            return Err(OAuth2Error::invalid_request("from is after to").into());
+        }
+    }
+    if let Some(plugins) = &plugins {
+        let known = event_actor
+            .send(GetPluginNames)
+            .await
+            .map_err(actix_web::error::ErrorServiceUnavailable)?;
+        if let Some(unknown) = plugins.iter().find(|name| !known.contains(name)) {
+            return Err(OAuth2Error::invalid_request(&format!("Unknown plugin: {unknown}")).into());
+        }
+    }
+
+    let limit = limit.unwrap_or(MAX_REPLAY_EVENTS).min(MAX_REPLAY_EVENTS);
+    let envelopes = source.read(&range, limit).await.map_err(|e| {
+        tracing::warn!(source = source.name(), error = %e, "Event replay read failed");
+        OAuth2Error::new("temporarily_unavailable", Some(&e))
+    })?;
+    let read = envelopes.len();
+    let envelopes = dedupe_by_idempotency_key(envelopes);
+    let duplicates = read - envelopes.len();
+
+    let mut replayed = 0;
+    let mut errors = Vec::new();
+    for envelope in envelopes {
+        let delivered = event_actor
+            .send(ReplayEvent {
+                envelope,
+                plugins: plugins.clone(),
+            })
+            .await
+            .map_err(actix_web::error::ErrorServiceUnavailable)?;
+        match delivered {
+            Ok(()) => replayed += 1,
+            Err(e) => errors.push(e),
+        }
+    }
+    tracing::info!(
+        source = source.name(),
+        read,
+        duplicates,
+        replayed,
+        failed = errors.len(),
+        "Replayed events"
+    );
+
+    let failed = errors.len();
+    // Enough to diagnose a failing plugin without echoing every event.
+    errors.truncate(10);
+    Ok(HttpResponse::Ok().json(ReplayResponse {
+        source: source.name().to_string(),
+        read,
+        duplicates,
+        replayed,
+        failed,
+        errors,
+    }))
+}
+
 #[derive(Serialize)]
 struct PluginHealth {
     name: String,
@@ -151,3 +674,91 @@ pub async fn health(
         "plugins": plugins
     })))
 }
+
+/// Readiness check over every event plugin, via [`GetPluginHealth`]. Fails listing the
+/// plugins that reported unhealthy.
+pub struct EventPluginsHealthCheck(pub Addr<oauth2_events::event_actor::EventActor>);
+
+#[async_trait::async_trait]
+impl HealthCheck for EventPluginsHealthCheck {
+    async fn check(&self) -> Result<(), String> {
+        let statuses = self
+            .0
+            .send(GetPluginHealth)
+            .await
+            .map_err(|e| e.to_string())?;
+        let unhealthy: Vec<String> = statuses
+            .into_iter()
+            .filter(|(_, healthy)| !healthy)
+            .map(|(name, _)| name)
+            .collect();
+        if unhealthy.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("unhealthy event plugins: {}", unhealthy.join(", ")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Shared backend double: a set of keys, or always-failing when `down`.
+    #[derive(Default)]
+    struct FakeBackend {
+        down: bool,
+        keys: std::sync::Mutex<HashSet<String>>,
+    }
+
+    #[async_trait]
+    impl IdempotencyBackend for FakeBackend {
+        fn backend_name(&self) -> &'static str {
+            "fake"
+        }
+
+        async fn check_and_record(&self, key: &str, _ttl: Duration) -> Result<bool, OAuth2Error> {
+            if self.down {
+                return Err(OAuth2Error::new("server_error", Some("backend down")));
+            }
+            Ok(!self.keys.lock().unwrap().insert(key.to_string()))
+        }
+    }
+
+    #[actix_web::test]
+    async fn shared_backend_dedupes_across_replicas() {
+        let backend: DynIdempotencyBackend = Arc::new(FakeBackend::default());
+        let metrics = Metrics::new().unwrap();
+        let replica_a = IdempotencyStore::new(Duration::from_secs(60))
+            .with_backend(backend.clone())
+            .with_metrics(metrics.clone());
+        let replica_b = IdempotencyStore::new(Duration::from_secs(60)).with_backend(backend);
+
+        assert!(!replica_a.is_duplicate_and_record("k1").await);
+        assert!(replica_b.is_duplicate_and_record("k1").await);
+        assert_eq!(
+            metrics
+                .events_idempotency_checks_total
+                .with_label_values(&["fake", "recorded"])
+                .get(),
+            1
+        );
+    }
+
+    #[actix_web::test]
+    async fn falls_back_to_local_store_when_backend_is_down() {
+        let backend: DynIdempotencyBackend = Arc::new(FakeBackend {
+            down: true,
+            ..FakeBackend::default()
+        });
+        let metrics = Metrics::new().unwrap();
+        let store = IdempotencyStore::new(Duration::from_secs(60))
+            .with_backend(backend)
+            .with_metrics(metrics.clone());
+
+        assert!(!store.is_duplicate_and_record("k1").await);
+        assert!(store.is_duplicate_and_record("k1").await);
+        assert_eq!(metrics.events_idempotency_fallbacks_total.get(), 2);
+        assert_eq!(metrics.events_idempotency_local_entries.get(), 1);
+    }
+}
@@ -1,6 +1,9 @@
 pub mod admin;
+pub mod audit;
 pub mod client;
+pub mod diagnose;
 pub mod events;
 pub mod oauth;
+pub mod sessions;
 pub mod token;
 pub mod wellknown;
@@ -1,41 +1,28 @@
 use actix::Addr;
+use actix_session::Session;
+use actix_web::http::Method;
 use actix_web::{web, HttpRequest, HttpResponse, Result};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use url::{form_urlencoded, Url};
 
 use oauth2_observability::Metrics;
 
-use crate::actors::{
-    AuthActor, ClientActor, CreateAuthorizationCode, CreateToken, GetClient,
-    MarkAuthorizationCodeUsed, TokenActor, ValidateAuthorizationCode, ValidateClient,
+use crate::actors::{AuthActor, ClientActor, CreateAuthorizationCode, GetClient, TokenActor};
+use crate::deadline::Deadline;
+use crate::grants::{registry_or_builtin, GrantContext, GrantRegistry, TokenRequest};
+use crate::middleware::cors::allow_client_origins;
+use crate::security_events::SecurityEvents;
+use oauth2_config::GrantsConfig;
+use oauth2_core::{
+    is_silent_prompt, tenant_id, AuditAction, AuditRecord, Client, ContextBinding, GrantType,
+    IssuerKeys, IssuerUrls, OAuth2Error, RequestOrigin, ResponseType, ScopeSet, TenantContext,
+    Token, TokenResponse, TrustedProxies,
+};
+use oauth2_events::EventType;
+use oauth2_ports::{
+    generate_secret, record_audit, validate_scope_subset, DynAuditSink, DynStorage,
 };
-use oauth2_core::{OAuth2Error, TokenResponse};
-
-fn validate_scope_subset(requested: &str, allowed: &str) -> Result<(), OAuth2Error> {
-    let allowed_scopes: Vec<&str> = allowed
-        .split_whitespace()
-        .filter(|s| !s.is_empty())
-        .collect();
-    let requested_scopes: Vec<&str> = requested
-        .split_whitespace()
-        .filter(|s| !s.is_empty())
-        .collect();
-
-    if requested_scopes.is_empty() {
-        return Err(OAuth2Error::invalid_scope("scope must not be empty"));
-    }
-
-    let all_allowed = requested_scopes.iter().all(|s| allowed_scopes.contains(s));
-
-    if !all_allowed {
-        return Err(OAuth2Error::invalid_scope(
-            "requested scope exceeds client permissions",
-        ));
-    }
-
-    Ok(())
-}
 
 fn no_store_headers(mut resp: HttpResponse) -> HttpResponse {
     resp.headers_mut().insert(
@@ -69,6 +56,16 @@ fn auth_response_security_headers(mut resp: HttpResponse) -> HttpResponse {
     resp
 }
 
+/// Context of the calling user agent, for authorization code binding.
+fn request_context(req: &HttpRequest) -> ContextBinding {
+    let user_agent = req
+        .headers()
+        .get(actix_web::http::header::USER_AGENT)
+        .and_then(|v| v.to_str().ok());
+    let ip = TrustedProxies::client_ip_of(req).map(|ip| ip.to_string());
+    ContextBinding::new(user_agent, ip.as_deref())
+}
+
 fn ensure_no_duplicate_query_params(req: &HttpRequest) -> Result<(), OAuth2Error> {
     let mut seen: HashSet<String> = HashSet::new();
     for (k, _v) in form_urlencoded::parse(req.query_string().as_bytes()) {
@@ -99,50 +96,329 @@ fn parse_form_no_dupes(body: &web::Bytes) -> Result<HashMap<String, String>, OAu
 
 #[derive(Debug, Deserialize)]
 pub struct AuthorizeQuery {
-    #[allow(dead_code)] // OAuth2 spec field, will be validated in future
-    response_type: String,
+    response_type: Option<String>,
     client_id: String,
     redirect_uri: String,
     scope: Option<String>,
     state: Option<String>,
     code_challenge: Option<String>,
     code_challenge_method: Option<String>,
+    prompt: Option<String>,
+    login_hint: Option<String>,
+}
+
+/// Session entry remembering, per client, the scopes the signed-in user has approved.
+/// `prompt=none` requests are only answered within them.
+const CONSENTS_KEY: &str = "consents";
+
+/// Session entry holding the consent page last shown, which only its own form can answer.
+const PENDING_CONSENT_KEY: &str = "pending_consent";
+
+/// A consent page awaiting the user's decision.
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingConsent {
+    token: String,
+    client_id: String,
+    scope: String,
+}
+
+/// The user's answer on the consent page, posted back to the authorize URL it was shown
+/// for.
+#[derive(Debug, Deserialize)]
+pub struct ConsentForm {
+    /// `approve`, or anything else to deny.
+    decision: String,
+    consent_token: String,
+}
+
+/// What the authorize endpoint answers a valid request with.
+enum Authorization {
+    /// Redirect back to the client with this code.
+    Code(String),
+    /// Ask the signed-in user to approve the client first.
+    ConsentPage(HttpResponse),
+}
+
+/// [`CONSENTS_KEY`] of a tenant: each tenant's clients are approved separately.
+fn consents_key(tenant_id: Option<&str>) -> String {
+    match tenant_id {
+        Some(tenant_id) => format!("{CONSENTS_KEY}:{tenant_id}"),
+        None => CONSENTS_KEY.to_string(),
+    }
+}
+
+/// Redirect the user agent back to the client with `params` (and `state`, if any) appended
+/// to the already-verified redirect URI.
+///
+/// Every response also carries `iss` (RFC 9207) so clients talking to several
+/// authorization servers can detect mix-up attacks.
+fn authorization_redirect(
+    mut redirect_uri: Url,
+    params: &[(&str, &str)],
+    state: Option<&str>,
+    issuer: &str,
+) -> HttpResponse {
+    {
+        let mut qp = redirect_uri.query_pairs_mut();
+        for (name, value) in params {
+            qp.append_pair(name, value);
+        }
+        if let Some(state) = state {
+            qp.append_pair("state", state);
+        }
+        qp.append_pair("iss", issuer);
+    }
+
+    auth_response_security_headers(no_store_headers(
+        HttpResponse::Found()
+            .append_header(("Location", redirect_uri.to_string()))
+            .finish(),
+    ))
+}
+
+/// RFC 6749 section 4.1.2.1 error response. Internal error details stay in the logs.
+fn authorization_error_redirect(
+    redirect_uri: Url,
+    error: &OAuth2Error,
+    state: Option<&str>,
+    issuer: &str,
+) -> HttpResponse {
+    let description = match error.error.as_str() {
+        "server_error" => Some("The authorization server encountered an unexpected error"),
+        _ => error.error_description.as_deref(),
+    };
+    let mut params = vec![("error", error.error.as_str())];
+    if let Some(description) = description {
+        params.push(("error_description", description));
+    }
+    authorization_redirect(redirect_uri, &params, state, issuer)
 }
 
 /// OAuth2 authorize endpoint
 /// Initiates the authorization code flow
+///
+/// Until the client and its redirect URI are verified, errors are returned to the user
+/// agent as JSON; redirecting to an unverified URI would make this an open redirector.
+/// Every later error is reported to the client by redirecting with `error` and `state`.
+///
+/// Codes are issued to the session's signed-in user once they have approved the client
+/// for the requested scope: until then the user gets a consent page showing the client's
+/// registered metadata, answered through [`authorize_decision`]. Without a signed-in user
+/// the client receives `login_required`. With `prompt=none`
+/// (silent authentication, e.g. SPA token renewal) codes are issued without any UI, or the
+/// client receives `login_required`, `consent_required` or `interaction_required`.
+#[allow(clippy::too_many_arguments)] // actix extractors
 pub async fn authorize(
     req: HttpRequest,
     query: web::Query<AuthorizeQuery>,
+    session: Session,
+    storage: Option<web::Data<DynStorage>>,
+    audit: Option<web::Data<DynAuditSink>>,
     auth_actor: web::Data<Addr<AuthActor>>,
     client_actor: web::Data<Addr<ClientActor>>,
     metrics: web::Data<Metrics>,
+    issuer_urls: Option<web::Data<IssuerUrls>>,
+    tenant: Option<web::ReqData<TenantContext>>,
+    security: SecurityEvents,
+) -> Result<HttpResponse, OAuth2Error> {
+    handle_authorize(
+        req,
+        query,
+        None,
+        session,
+        storage,
+        audit,
+        auth_actor,
+        client_actor,
+        metrics,
+        issuer_urls,
+        tenant,
+        security,
+    )
+    .await
+}
+
+/// The consent page's form: the same authorize request, with the user's decision. A
+/// denial sends the client `access_denied`.
+#[allow(clippy::too_many_arguments)] // actix extractors
+pub async fn authorize_decision(
+    req: HttpRequest,
+    query: web::Query<AuthorizeQuery>,
+    form: web::Form<ConsentForm>,
+    session: Session,
+    storage: Option<web::Data<DynStorage>>,
+    audit: Option<web::Data<DynAuditSink>>,
+    auth_actor: web::Data<Addr<AuthActor>>,
+    client_actor: web::Data<Addr<ClientActor>>,
+    metrics: web::Data<Metrics>,
+    issuer_urls: Option<web::Data<IssuerUrls>>,
+    tenant: Option<web::ReqData<TenantContext>>,
+    security: SecurityEvents,
+) -> Result<HttpResponse, OAuth2Error> {
+    handle_authorize(
+        req,
+        query,
+        Some(form.into_inner()),
+        session,
+        storage,
+        audit,
+        auth_actor,
+        client_actor,
+        metrics,
+        issuer_urls,
+        tenant,
+        security,
+    )
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_authorize(
+    req: HttpRequest,
+    query: web::Query<AuthorizeQuery>,
+    decision: Option<ConsentForm>,
+    session: Session,
+    storage: Option<web::Data<DynStorage>>,
+    audit: Option<web::Data<DynAuditSink>>,
+    auth_actor: web::Data<Addr<AuthActor>>,
+    client_actor: web::Data<Addr<ClientActor>>,
+    metrics: web::Data<Metrics>,
+    issuer_urls: Option<web::Data<IssuerUrls>>,
+    tenant: Option<web::ReqData<TenantContext>>,
+    security: SecurityEvents,
 ) -> Result<HttpResponse, OAuth2Error> {
     // OAuch: reject duplicate parameters (prevents ambiguous parsing).
     ensure_no_duplicate_query_params(&req)?;
-
-    // Only Authorization Code flow is supported.
-    if query.response_type != "code" {
-        return Err(OAuth2Error::invalid_request("Unsupported response_type"));
-    }
+    let tenant = tenant.map(web::ReqData::into_inner);
 
     // Validate client and redirect_uri to prevent open redirect / code exfiltration.
     let client = client_actor
         .send(GetClient {
             client_id: query.client_id.clone(),
+            tenant_id: tenant_id(tenant.as_ref()).map(str::to_string),
             span: tracing::Span::current(),
+            deadline: Deadline::current(),
         })
         .await
         .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
 
-    if !client.supports_grant_type("authorization_code") {
-        return Err(OAuth2Error::unauthorized_client(
-            "Client is not allowed to use authorization_code",
+    if !client.validate_redirect_uri(&query.redirect_uri) {
+        security.publish(
+            EventType::InvalidRedirectUriAttempt,
+            Some(&client.client_id),
+            None,
+            &[("redirect_uri", &query.redirect_uri)],
+        );
+        return Err(OAuth2Error::invalid_request("Invalid redirect_uri"));
+    }
+
+    let redirect_uri = Url::parse(&query.redirect_uri)
+        .map_err(|_| OAuth2Error::invalid_request("Invalid redirect_uri"))?;
+    if redirect_uri.fragment().is_some() {
+        return Err(OAuth2Error::invalid_request(
+            "redirect_uri must not contain a fragment",
         ));
     }
 
-    if !client.validate_redirect_uri(&query.redirect_uri) {
-        return Err(OAuth2Error::invalid_request("Invalid redirect_uri"));
+    // Must match the `issuer` advertised by discovery.
+    let issuer = issuer_urls
+        .as_ref()
+        .map(|urls| urls.get_ref().clone())
+        .unwrap_or_default()
+        .for_tenant(tenant_id(tenant.as_ref()))
+        .base_url(&RequestOrigin::from(&req));
+
+    // Codes are only issued against S256 challenges; asking without one, or with the
+    // `plain` method, is an attempt to obtain a code an interceptor could redeem.
+    if query.code_challenge.is_none() || query.code_challenge_method.as_deref() != Some("S256") {
+        security.publish(
+            EventType::PkceDowngradeAttempt,
+            Some(&client.client_id),
+            None,
+            &[(
+                "code_challenge_method",
+                query.code_challenge_method.as_deref().unwrap_or("none"),
+            )],
+        );
+    }
+
+    let storage = storage.as_ref().map(|storage| storage.get_ref());
+    let issued = match issue_authorization_code(
+        &req,
+        &query,
+        decision.as_ref(),
+        &client,
+        &auth_actor,
+        &session,
+        storage,
+        tenant_id(tenant.as_ref()),
+    )
+    .await
+    {
+        Ok(Authorization::ConsentPage(page)) => return Ok(page),
+        Ok(Authorization::Code(code)) => Ok(code),
+        Err(error) => Err(error),
+    };
+    let mut record = AuditRecord::from_result(AuditAction::Consent, &issued)
+        .with_client(query.client_id.clone())
+        .with_request(&req);
+    if let Some(user_id) = session.get::<String>("user_id").unwrap_or(None) {
+        record = record.with_actor(user_id);
+    }
+    record_audit(audit.as_ref().map(|audit| audit.get_ref()), record).await;
+
+    match issued {
+        Ok(code) => {
+            metrics.oauth_authorization_codes_issued.inc();
+            Ok(authorization_redirect(
+                redirect_uri,
+                &[("code", &code)],
+                query.state.as_deref(),
+                &issuer,
+            ))
+        }
+        Err(error) => {
+            tracing::debug!(
+                client_id = %query.client_id,
+                error = %error,
+                "Authorization request rejected; redirecting to client"
+            );
+            Ok(authorization_error_redirect(
+                redirect_uri,
+                &error,
+                query.state.as_deref(),
+                &issuer,
+            ))
+        }
+    }
+}
+
+/// Validate the rest of an authorization request for a verified client and issue a code,
+/// or ask for consent.
+#[allow(clippy::too_many_arguments)]
+async fn issue_authorization_code(
+    req: &HttpRequest,
+    query: &AuthorizeQuery,
+    decision: Option<&ConsentForm>,
+    client: &Client,
+    auth_actor: &Addr<AuthActor>,
+    session: &Session,
+    storage: Option<&DynStorage>,
+    tenant_id: Option<&str>,
+) -> Result<Authorization, OAuth2Error> {
+    let silent = is_silent_prompt(query.prompt.as_deref())?;
+
+    // Only Authorization Code flow is supported.
+    let response_type: ResponseType = query
+        .response_type
+        .as_deref()
+        .ok_or_else(|| OAuth2Error::invalid_request("Missing response_type"))?
+        .parse()?;
+
+    if !client.supports_grant_type(response_type.grant_type()) {
+        return Err(OAuth2Error::unauthorized_client(
+            "Client is not allowed to use authorization_code",
+        ));
     }
 
     // Require PKCE (S256 only). This follows OAuth 2.0 Security BCP guidance.
@@ -165,15 +441,60 @@ pub async fn authorize(
         ));
     }
 
-    // In a real implementation, this would show a consent page
-    // For now, we'll auto-approve with a mock user
-    let user_id = "user_123".to_string(); // Mock user
-
     let scope = query.scope.clone().unwrap_or_else(|| "read".to_string());
 
     // Enforce that requested scopes are within the client's allowed scope set.
     validate_scope_subset(&scope, &client.scope)?;
 
+    let Some(user_id) = session_user(session, storage, tenant_id).await? else {
+        return Err(OAuth2Error::login_required("No signed-in session"));
+    };
+
+    let consents_key = consents_key(tenant_id);
+    let mut consents: HashMap<String, ScopeSet> = session
+        .get(&consents_key)
+        .unwrap_or(None)
+        .unwrap_or_default();
+    let approved = consents
+        .get(&query.client_id)
+        .is_some_and(|granted| ScopeSet::parse(&scope).is_subset(granted));
+    if silent {
+        check_silent_user(storage, &user_id, query.login_hint.as_deref()).await?;
+        if !approved {
+            return Err(OAuth2Error::consent_required(
+                "The user has not approved this client for the requested scope",
+            ));
+        }
+    } else if !approved {
+        let Some(decision) = decision else {
+            return consent_page(req, session, client, &scope).map(Authorization::ConsentPage);
+        };
+        let pending = session
+            .remove_as::<PendingConsent>(PENDING_CONSENT_KEY)
+            .and_then(Result::ok)
+            .filter(|pending| {
+                pending.token == decision.consent_token
+                    && pending.client_id == query.client_id
+                    && pending.scope == scope
+            });
+        if pending.is_none() {
+            return Err(OAuth2Error::access_denied(
+                "The consent request is no longer valid",
+            ));
+        }
+        if decision.decision != "approve" {
+            return Err(OAuth2Error::access_denied(
+                "The user denied the authorization request",
+            ));
+        }
+        // Later silent requests rely on this approval.
+        let granted = consents.entry(query.client_id.clone()).or_default();
+        *granted = granted.union(&ScopeSet::parse(&scope));
+        session
+            .insert(consents_key, &consents)
+            .map_err(|e| OAuth2Error::new("session_error", Some(&e.to_string())))?;
+    }
+
     let auth_code = auth_actor
         .send(CreateAuthorizationCode {
             client_id: query.client_id.clone(),
@@ -182,254 +503,364 @@ pub async fn authorize(
             scope,
             code_challenge: query.code_challenge.clone(),
             code_challenge_method: query.code_challenge_method.clone(),
+            context: request_context(req),
+            tenant_id: tenant_id.map(str::to_string),
             span: tracing::Span::current(),
+            deadline: Deadline::current(),
         })
         .await
         .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
 
-    metrics.oauth_authorization_codes_issued.inc();
+    Ok(Authorization::Code(auth_code.code))
+}
 
-    // Redirect back to client with code (and optional state) while safely preserving existing query.
-    let mut url = Url::parse(&query.redirect_uri)
-        .map_err(|_| OAuth2Error::invalid_request("Invalid redirect_uri"))?;
-    if url.fragment().is_some() {
-        return Err(OAuth2Error::invalid_request(
-            "redirect_uri must not contain a fragment",
-        ));
-    }
-    {
-        let mut qp = url.query_pairs_mut();
-        qp.append_pair("code", &auth_code.code);
-        if let Some(state) = &query.state {
-            qp.append_pair("state", state);
-        }
-    }
+/// Ask the signed-in user to approve `client` for `scope`, showing the metadata it
+/// registered (RFC 7591 section 2). The form posts back to this authorize URL.
+fn consent_page(
+    req: &HttpRequest,
+    session: &Session,
+    client: &Client,
+    scope: &str,
+) -> Result<HttpResponse, OAuth2Error> {
+    let pending = PendingConsent {
+        token: generate_secret(),
+        client_id: client.client_id.clone(),
+        scope: scope.to_string(),
+    };
+    session
+        .insert(PENDING_CONSENT_KEY, &pending)
+        .map_err(|e| OAuth2Error::new("session_error", Some(&e.to_string())))?;
+
+    let metadata = client.metadata();
+    let name = escape_html(&client.name);
+    let logo = metadata
+        .logo_uri
+        .map(|uri| format!(r#"<img src="{}" alt="" width="64">"#, escape_html(&uri)))
+        .unwrap_or_default();
+    let title = match &metadata.client_uri {
+        Some(uri) => format!(r#"<a href="{}">{name}</a>"#, escape_html(uri)),
+        None => name.clone(),
+    };
+    let scopes: String = ScopeSet::parse(scope)
+        .iter()
+        .map(|scope| format!("<li>{}</li>", escape_html(scope)))
+        .collect();
+    let links: Vec<String> = [
+        (&metadata.policy_uri, "Privacy policy"),
+        (&metadata.tos_uri, "Terms of service"),
+    ]
+    .into_iter()
+    .filter_map(|(uri, label)| {
+        uri.as_ref()
+            .map(|uri| format!(r#"<a href="{}">{label}</a>"#, escape_html(uri)))
+    })
+    .collect();
+    let contacts = match metadata.contacts.is_empty() {
+        true => String::new(),
+        false => format!(
+            "<p>Contact: {}</p>",
+            escape_html(&metadata.contacts.join(", "))
+        ),
+    };
 
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Authorize {name}</title>
+    <link rel="stylesheet" href="/static/css/admin.css">
+</head>
+<body>
+    <div class="container">
+        {logo}
+        <h1>{title} wants to access your account</h1>
+        <p>It is asking for:</p>
+        <ul>{scopes}</ul>
+        <p>{links}</p>
+        {contacts}
+        <form method="post" action="{action}">
+            <input type="hidden" name="consent_token" value="{token}">
+            <button type="submit" name="decision" value="approve" autofocus>Allow</button>
+            <button type="submit" name="decision" value="deny">Deny</button>
+        </form>
+    </div>
+</body>
+</html>"#,
+        links = links.join(" · "),
+        action = escape_html(&req.uri().to_string()),
+        token = escape_html(&pending.token),
+    );
     Ok(auth_response_security_headers(no_store_headers(
-        HttpResponse::Found()
-            .append_header(("Location", url.to_string()))
-            .finish(),
+        HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(html),
     )))
 }
 
-#[derive(Debug, Deserialize)]
-pub struct TokenRequest {
-    grant_type: String,
-    code: Option<String>,
-    redirect_uri: Option<String>,
-    client_id: String,
-    client_secret: Option<String>,
-    #[allow(dead_code)] // OAuth2 refresh token grant, planned for future
-    refresh_token: Option<String>,
-    #[allow(dead_code)] // OAuth2 password grant, intentionally disabled by default
-    username: Option<String>,
-    #[allow(dead_code)] // OAuth2 password grant, intentionally disabled by default
-    password: Option<String>,
-    scope: Option<String>,
-    code_verifier: Option<String>,
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#x27;")
 }
 
-/// OAuth2 token endpoint
-/// Exchanges authorization code for access token
-pub async fn token(
-    req: HttpRequest,
-    body: web::Bytes,
-    token_actor: web::Data<Addr<TokenActor>>,
-    client_actor: web::Data<Addr<ClientActor>>,
-    auth_actor: web::Data<Addr<AuthActor>>,
-    metrics: web::Data<Metrics>,
-) -> Result<HttpResponse, OAuth2Error> {
-    // OAuch: reject duplicate parameters (prevents parser differentials / smuggling).
-    ensure_no_duplicate_query_params(&req)?;
-    let form_map = parse_form_no_dupes(&body)?;
-
-    let form = TokenRequest {
-        grant_type: form_map
-            .get("grant_type")
-            .cloned()
-            .ok_or_else(|| OAuth2Error::invalid_request("Missing grant_type"))?,
-        code: form_map.get("code").cloned(),
-        redirect_uri: form_map.get("redirect_uri").cloned(),
-        client_id: form_map
-            .get("client_id")
-            .cloned()
-            .ok_or_else(|| OAuth2Error::invalid_request("Missing client_id"))?,
-        client_secret: form_map.get("client_secret").cloned(),
-        refresh_token: form_map.get("refresh_token").cloned(),
-        username: form_map.get("username").cloned(),
-        password: form_map.get("password").cloned(),
-        scope: form_map.get("scope").cloned(),
-        code_verifier: form_map.get("code_verifier").cloned(),
+/// The session's signed-in user, if they belong to the tenant the request was addressed to:
+/// signing in to one tenant does not sign the user agent in to the others.
+async fn session_user(
+    session: &Session,
+    storage: Option<&DynStorage>,
+    tenant_id: Option<&str>,
+) -> Result<Option<String>, OAuth2Error> {
+    let Some(user_id) = session.get::<String>("user_id").unwrap_or(None) else {
+        return Ok(None);
+    };
+    let in_tenant = match storage {
+        Some(storage) => storage
+            .get_user(&user_id)
+            .await?
+            .is_some_and(|user| user.tenant_id.as_deref() == tenant_id),
+        // Without storage the user's tenant is unknown; only the default tenant trusts it.
+        None => tenant_id.is_none(),
     };
+    Ok(in_tenant.then_some(user_id))
+}
 
-    match form.grant_type.as_str() {
-        "authorization_code" => {
-            handle_authorization_code_grant(form, token_actor, client_actor, auth_actor, metrics)
-                .await
-        }
-        "client_credentials" => {
-            handle_client_credentials_grant(form, token_actor, client_actor, metrics).await
-        }
-        // Password and refresh_token grants are intentionally disabled by default
-        // (OAuth 2.0 Security BCP).
-        "password" | "refresh_token" => {
-            Err(OAuth2Error::unsupported_grant_type("Grant type disabled"))
+/// `prompt=none` needs the session's user to still be able to sign in, and to be the
+/// account named by `login_hint`, if any: there is no page to sign in or switch accounts.
+async fn check_silent_user(
+    storage: Option<&DynStorage>,
+    user_id: &str,
+    login_hint: Option<&str>,
+) -> Result<(), OAuth2Error> {
+    let user = match storage {
+        Some(storage) => storage.get_user(user_id).await?,
+        None => None,
+    };
+    let Some(user) = user.filter(|user| user.enabled) else {
+        return Err(OAuth2Error::login_required(
+            "The signed-in user can no longer sign in",
+        ));
+    };
+    if let Some(hint) = login_hint {
+        if hint != user.username && !hint.eq_ignore_ascii_case(&user.email) {
+            return Err(OAuth2Error::interaction_required(
+                "The signed-in user is not the account named by login_hint",
+            ));
         }
-        _ => Err(OAuth2Error::unsupported_grant_type(&format!(
-            "Grant type '{}' not supported",
-            form.grant_type
-        ))),
     }
+    Ok(())
 }
 
-async fn handle_authorization_code_grant(
-    req: TokenRequest,
-    token_actor: web::Data<Addr<TokenActor>>,
+/// OpenID Connect RP-Initiated Logout endpoint (`end_session_endpoint`).
+///
+/// Accepts parameters as a query string (GET) or form (POST). `id_token_hint` must have
+/// been signed by this server, but may be expired. `post_logout_redirect_uri` must be one
+/// of the client's registered redirect URIs; the client comes from the hint or, without
+/// one, from `client_id`. The session is only destroyed once the request has validated.
+pub async fn end_session(
+    req: HttpRequest,
+    body: web::Bytes,
+    session: Session,
     client_actor: web::Data<Addr<ClientActor>>,
-    auth_actor: web::Data<Addr<AuthActor>>,
-    metrics: web::Data<Metrics>,
+    issuer_keys: web::Data<IssuerKeys>,
+    tenant: Option<web::ReqData<TenantContext>>,
+    security: SecurityEvents,
 ) -> Result<HttpResponse, OAuth2Error> {
-    let code = req
-        .code
-        .ok_or_else(|| OAuth2Error::invalid_request("Missing code"))?;
-
-    if matches!(req.redirect_uri.as_deref(), Some("")) {
-        return Err(OAuth2Error::invalid_request(
-            "redirect_uri must not be empty",
-        ));
-    }
-
-    // Validate authorization code
-    let auth_code = auth_actor
-        .send(ValidateAuthorizationCode {
-            code: code.clone(),
-            client_id: req.client_id.clone(),
-            redirect_uri: req.redirect_uri,
-            code_verifier: req.code_verifier,
-            span: tracing::Span::current(),
-        })
-        .await
-        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
-
-    // Validate client grant permissions + authenticate if required.
-    let client = client_actor
-        .send(GetClient {
-            client_id: req.client_id.clone(),
-            span: tracing::Span::current(),
-        })
-        .await
-        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
-
-    if !client.supports_grant_type("authorization_code") {
-        return Err(OAuth2Error::unauthorized_client(
-            "Client is not allowed to use authorization_code",
-        ));
-    }
+    let tenant = tenant.map(web::ReqData::into_inner);
+    let issuer_keys = match &tenant {
+        Some(tenant) => &tenant.keys,
+        None => issuer_keys.get_ref(),
+    };
+    let params = if req.method() == Method::POST {
+        parse_form_no_dupes(&body)?
+    } else {
+        ensure_no_duplicate_query_params(&req)?;
+        form_urlencoded::parse(req.query_string().as_bytes())
+            .into_owned()
+            .collect()
+    };
 
-    match req.client_secret {
-        Some(secret) => {
-            let ok = client_actor
-                .send(ValidateClient {
-                    client_id: req.client_id.clone(),
-                    client_secret: secret,
-                    span: tracing::Span::current(),
-                })
-                .await
-                .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
-
-            if !ok {
-                return Err(OAuth2Error::invalid_client("Invalid client_secret"));
-            }
+    let hinted_client = match params.get("id_token_hint") {
+        Some(hint) => {
+            let (claims, _) = issuer_keys
+                .verify_ignoring_expiry(hint)
+                .map_err(|_| OAuth2Error::invalid_request("Invalid id_token_hint"))?;
+            Some(claims.client_id.unwrap_or(claims.aud))
         }
-        None => {
-            // Require client authentication for the token endpoint.
-            return Err(OAuth2Error::invalid_client("Missing client_secret"));
+        None => None,
+    };
+    let client_id = match (hinted_client, params.get("client_id")) {
+        (Some(hinted), Some(given)) if &hinted != given => {
+            return Err(OAuth2Error::invalid_request(
+                "client_id does not match id_token_hint",
+            ))
         }
-    }
-
-    // Only consume (burn) the authorization code after we've authenticated/authorized the client.
-    // This prevents invalid_client errors from exhausting valid codes.
-    auth_actor
-        .send(MarkAuthorizationCodeUsed {
-            code,
-            span: tracing::Span::current(),
-        })
-        .await
-        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
+        (hinted, given) => hinted.or_else(|| given.cloned()),
+    };
 
-    // Create token
-    let token = token_actor
-        .send(CreateToken {
-            user_id: Some(auth_code.user_id),
-            client_id: auth_code.client_id,
-            scope: auth_code.scope,
-            include_refresh: false,
-            span: tracing::Span::current(),
-        })
-        .await
-        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
+    let redirect_uri =
+        match params.get("post_logout_redirect_uri") {
+            Some(uri) => {
+                let client_id = client_id.clone().ok_or_else(|| {
+                    OAuth2Error::invalid_request(
+                        "post_logout_redirect_uri requires id_token_hint or client_id",
+                    )
+                })?;
+                let client = client_actor
+                    .send(GetClient {
+                        client_id,
+                        tenant_id: tenant_id(tenant.as_ref()).map(str::to_string),
+                        span: tracing::Span::current(),
+                        deadline: Deadline::current(),
+                    })
+                    .await
+                    .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
+                if !client.validate_redirect_uri(uri) {
+                    security.publish(
+                        EventType::InvalidRedirectUriAttempt,
+                        Some(&client.client_id),
+                        None,
+                        &[("redirect_uri", uri)],
+                    );
+                    return Err(OAuth2Error::invalid_request(
+                        "Invalid post_logout_redirect_uri",
+                    ));
+                }
+                Some(Url::parse(uri).map_err(|_| {
+                    OAuth2Error::invalid_request("Invalid post_logout_redirect_uri")
+                })?)
+            }
+            None => None,
+        };
 
-    metrics.oauth_token_issued_total.inc();
+    session.purge();
+    tracing::info!(client_id = ?client_id, "End-user session ended");
 
-    Ok(no_store_headers(
-        HttpResponse::Ok().json(TokenResponse::from(token)),
-    ))
+    let response = match redirect_uri {
+        Some(mut redirect_uri) => {
+            if let Some(state) = params.get("state") {
+                redirect_uri.query_pairs_mut().append_pair("state", state);
+            }
+            HttpResponse::Found()
+                .append_header(("Location", redirect_uri.to_string()))
+                .finish()
+        }
+        None => HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body("<!DOCTYPE html><html><head><title>Signed out</title></head><body><p>You have been signed out.</p></body></html>"),
+    };
+    Ok(auth_response_security_headers(no_store_headers(response)))
 }
 
-async fn handle_client_credentials_grant(
-    req: TokenRequest,
+/// OAuth2 token endpoint
+/// Issues tokens through the handler registered for the requested `grant_type`
+#[allow(clippy::too_many_arguments)] // actix extractors
+pub async fn token(
+    req: HttpRequest,
+    body: web::Bytes,
     token_actor: web::Data<Addr<TokenActor>>,
     client_actor: web::Data<Addr<ClientActor>>,
+    auth_actor: web::Data<Addr<AuthActor>>,
     metrics: web::Data<Metrics>,
+    storage: Option<web::Data<DynStorage>>,
+    audit: Option<web::Data<DynAuditSink>>,
+    issuer_urls: Option<web::Data<IssuerUrls>>,
+    grants: Option<web::Data<GrantsConfig>>,
+    registry: Option<web::Data<GrantRegistry>>,
+    tenant: Option<web::ReqData<TenantContext>>,
+    security: SecurityEvents,
 ) -> Result<HttpResponse, OAuth2Error> {
-    // Validate client exists + grant permissions.
-    let client = client_actor
-        .send(GetClient {
-            client_id: req.client_id.clone(),
-            span: tracing::Span::current(),
-        })
-        .await
-        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
+    // OAuch: reject duplicate parameters (prevents parser differentials / smuggling).
+    ensure_no_duplicate_query_params(&req)?;
+    let tenant = tenant.map(web::ReqData::into_inner);
+    let request = TokenRequest::from_params(parse_form_no_dupes(&body)?)?;
+
+    let registry = registry_or_builtin(registry, grants.as_ref());
+    let client_id = request.client_id().to_string();
+    let grant_type = request.grant_type().to_string();
+    let issued: Result<Token, OAuth2Error> = async {
+        let handler = match registry.get(request.grant_type()) {
+            Some(handler) => handler.clone(),
+            None => {
+                // Known but disabled built-in grants get their own message.
+                request.grant_type().parse::<GrantType>()?;
+                return Err(OAuth2Error::unsupported_grant_type("Grant type disabled"));
+            }
+        };
+
+        let client = client_actor
+            .send(GetClient {
+                client_id: request.client_id().to_string(),
+                tenant_id: tenant_id(tenant.as_ref()).map(str::to_string),
+                span: tracing::Span::current(),
+                deadline: Deadline::current(),
+            })
+            .await
+            .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
+        allow_client_origins(&req, &client);
+        if !client
+            .get_grant_types()
+            .iter()
+            .any(|registered| registered == request.grant_type())
+        {
+            return Err(OAuth2Error::unauthorized_client(&format!(
+                "Client is not allowed to use {}",
+                request.grant_type()
+            )));
+        }
 
-    if !client.supports_grant_type("client_credentials") {
-        return Err(OAuth2Error::unauthorized_client(
-            "Client is not allowed to use client_credentials",
-        ));
+        // Client assertions must be addressed to this endpoint's public URL.
+        let token_endpoint = issuer_urls
+            .as_ref()
+            .map(|urls| urls.get_ref().clone())
+            .unwrap_or_default()
+            .for_tenant(tenant_id(tenant.as_ref()))
+            .token_endpoint(&RequestOrigin::from(&req));
+        let grant = GrantContext {
+            storage: storage.map(|storage| storage.get_ref().clone()),
+            token_actor: token_actor.get_ref().clone(),
+            client_actor: client_actor.get_ref().clone(),
+            auth_actor: auth_actor.get_ref().clone(),
+            metrics,
+            tenant,
+            binding: request_context(&req),
+            token_endpoint,
+            security: security.clone(),
+        };
+        handler.handle(request, client, &grant).await
     }
-
-    // Validate client credentials (required for this grant).
-    let client_secret = req
-        .client_secret
-        .ok_or_else(|| OAuth2Error::invalid_client("Missing client_secret"))?;
-    let ok = client_actor
-        .send(ValidateClient {
-            client_id: req.client_id.clone(),
-            client_secret,
-            span: tracing::Span::current(),
-        })
-        .await
-        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
-    if !ok {
-        return Err(OAuth2Error::invalid_client("Invalid client_secret"));
+    .await;
+
+    // Unknown clients, wrong secrets and rejected assertions, from any grant.
+    if let Err(error) = &issued {
+        if error.error == "invalid_client" {
+            security.publish(
+                EventType::ClientAuthenticationFailed,
+                Some(&client_id),
+                None,
+                &[
+                    ("grant_type", grant_type.as_str()),
+                    (
+                        "reason",
+                        error.error_description.as_deref().unwrap_or_default(),
+                    ),
+                ],
+            );
+        }
     }
 
-    let scope = req.scope.unwrap_or_else(|| "read".to_string());
-
-    validate_scope_subset(&scope, &client.scope)?;
-
-    // Create token (no user, client-only)
-    let token = token_actor
-        .send(CreateToken {
-            user_id: None,
-            client_id: req.client_id,
-            scope,
-            include_refresh: false,
-            span: tracing::Span::current(),
-        })
-        .await
-        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
-
-    metrics.oauth_token_issued_total.inc();
+    let mut record = AuditRecord::from_result(AuditAction::TokenIssued, &issued)
+        .with_client(client_id.clone())
+        .with_target(grant_type)
+        .with_request(&req);
+    if let Ok(token) = &issued {
+        // Client-only grants act on the client's own behalf.
+        record = record.with_actor(token.user_id.clone().unwrap_or(client_id));
+    }
+    record_audit(audit.as_ref().map(|audit| audit.get_ref()), record).await;
+    let token = issued?;
 
     Ok(no_store_headers(
         HttpResponse::Ok().json(TokenResponse::from(token)),
@@ -0,0 +1,63 @@
+use actix_session::Session;
+use actix_web::{http::header, web, HttpResponse};
+
+use oauth2_core::{OAuth2Error, SessionInfo, SessionList};
+use oauth2_ports::DynUserSessionStore;
+
+use crate::session::SESSION_ID_KEY;
+
+/// The signed-in user and the id of the current session.
+fn signed_in(session: &Session) -> Result<(String, Option<String>), OAuth2Error> {
+    let user_id = session
+        .get::<String>("user_id")
+        .unwrap_or(None)
+        .ok_or_else(|| OAuth2Error::login_required("Sign in to manage your sessions"))?;
+    let current = session.get::<String>(SESSION_ID_KEY).unwrap_or(None);
+    Ok((user_id, current))
+}
+
+/// `GET /auth/sessions`: the signed-in user's active sessions, newest first.
+pub async fn list_sessions(
+    session: Session,
+    store: web::Data<DynUserSessionStore>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let (user_id, current) = signed_in(&session)?;
+    let sessions = store
+        .list_for_user(&user_id)
+        .await?
+        .into_iter()
+        .map(|record| SessionInfo::new(record, current.as_deref()))
+        .collect();
+
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .json(SessionList { sessions }))
+}
+
+/// `DELETE /auth/sessions/{id}`: sign out one of the user's sessions. Revoking the
+/// current session also clears its cookie.
+pub async fn revoke_session(
+    session: Session,
+    path: web::Path<String>,
+    store: web::Data<DynUserSessionStore>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let (user_id, current) = signed_in(&session)?;
+    let id = path.into_inner();
+    // Other users' sessions are reported as missing, so ids cannot be probed.
+    let owned = store
+        .get(&id)
+        .await?
+        .filter(|target| !target.is_expired())
+        .is_some_and(|target| target.user_id.as_deref() == Some(user_id.as_str()));
+    if !owned {
+        return Err(OAuth2Error::not_found("Session not found"));
+    }
+
+    store.delete(&id).await?;
+    if current.as_deref() == Some(id.as_str()) {
+        session.purge();
+    }
+    tracing::info!(enduser.id = %user_id, "Browser session revoked");
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -1,110 +1,277 @@
 use actix::Addr;
-use actix_web::{web, HttpResponse, Result};
+use actix_web::{http::header, web, HttpRequest, HttpResponse, Result};
+use base64::{engine::general_purpose, Engine as _};
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use crate::actors::{RevokeToken, TokenActor, ValidateToken};
-use oauth2_core::{Claims, IntrospectionResponse, OAuth2Error};
+use crate::actors::{AuthenticateClient, ClientActor, IntrospectToken, RevokeToken, TokenActor};
+use crate::deadline::Deadline;
+use crate::handlers::wellknown::{cacheable_json, MetadataCaching};
+use crate::security_events::SecurityEvents;
+use oauth2_core::{
+    tenant_id, Client, IssuerKeys, OAuth2Error, Redactor, RevocationList, RevokedToken,
+    TenantContext,
+};
+use oauth2_events::EventType;
+use oauth2_observability::Metrics;
+use oauth2_ports::{has_admin_scope, DynStorage, Introspection};
 
 #[derive(Debug, Deserialize)]
 pub struct IntrospectRequest {
     token: String,
     #[allow(dead_code)] // OAuth2 spec field, can be used for optimization
     token_type_hint: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
 }
 
-/// Token introspection endpoint
-/// Returns information about a token
+/// Client credentials from `Authorization: Basic` (RFC 6749 §2.3.1, form-urlencoded
+/// id and secret), if present.
+fn basic_credentials(req: &HttpRequest) -> Result<Option<(String, String)>, OAuth2Error> {
+    let Some(value) = req.headers().get(header::AUTHORIZATION) else {
+        return Ok(None);
+    };
+    let Some(encoded) = value.to_str().ok().and_then(|v| v.strip_prefix("Basic ")) else {
+        return Ok(None);
+    };
+
+    let invalid = || OAuth2Error::invalid_client("Malformed Basic credentials");
+    let decoded = general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|_| invalid())?;
+    let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+    let (id, secret) = decoded.split_once(':').ok_or_else(invalid)?;
+    let unescape = |s: &str| {
+        url::form_urlencoded::parse(format!("v={s}").as_bytes())
+            .next()
+            .map(|(_, v)| v.into_owned())
+            .unwrap_or_default()
+    };
+    Ok(Some((unescape(id), unescape(secret))))
+}
+
+/// Authenticate the calling client via HTTP Basic or `client_id`/`client_secret` form
+/// parameters. Using both methods at once is rejected (RFC 6749 §2.3). The client must
+/// belong to `tenant`.
+async fn authenticate_client(
+    req: &HttpRequest,
+    form_client_id: Option<&str>,
+    form_client_secret: Option<&str>,
+    tenant: Option<&TenantContext>,
+    client_actor: &Addr<ClientActor>,
+) -> Result<Client, OAuth2Error> {
+    let (client_id, client_secret) = match (basic_credentials(req)?, form_client_secret) {
+        (Some(_), Some(_)) => {
+            return Err(OAuth2Error::invalid_request(
+                "Use only one client authentication method",
+            ))
+        }
+        (Some(credentials), None) => credentials,
+        (None, Some(secret)) => {
+            let client_id =
+                form_client_id.ok_or_else(|| OAuth2Error::invalid_client("Missing client_id"))?;
+            (client_id.to_string(), secret.to_string())
+        }
+        (None, None) => {
+            return Err(OAuth2Error::invalid_client(
+                "Client authentication is required",
+            ))
+        }
+    };
+
+    client_actor
+        .send(AuthenticateClient {
+            client_id,
+            client_secret,
+            tenant_id: tenant_id(tenant).map(str::to_string),
+            security: SecurityEvents::for_request(req).into_inner(),
+            endpoint: req.path().to_string(),
+            span: tracing::Span::current(),
+            deadline: Deadline::current(),
+        })
+        .await
+        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))?
+}
+
+/// Per-caller quota for token introspection, counted in fixed one-minute windows.
+///
+/// Counters live in memory, so each replica enforces the quota separately.
+#[derive(Clone)]
+pub struct IntrospectionRateLimiter {
+    limit: u32,
+    window: Duration,
+    windows: Arc<Mutex<HashMap<String, (Instant, u32)>>>,
+}
+
+impl IntrospectionRateLimiter {
+    pub fn per_minute(limit: u32) -> Self {
+        Self {
+            limit,
+            window: Duration::from_secs(60),
+            windows: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Count a request by `client_id`. Over quota, returns how long until the window resets.
+    pub fn check(&self, client_id: &str) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        if windows.len() >= 10_000 {
+            windows.retain(|_, (started, _)| now.duration_since(*started) < self.window);
+        }
+
+        let (started, count) = windows.entry(client_id.to_string()).or_insert((now, 0));
+        if now.duration_since(*started) >= self.window {
+            *started = now;
+            *count = 0;
+        }
+        if *count >= self.limit {
+            return Err(self.window - now.duration_since(*started));
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+/// Token introspection endpoint (RFC 7662)
+/// Returns information about a token; unknown, expired and revoked tokens are
+/// always reported as `{"active": false}` with HTTP 200.
+///
+/// Callers must authenticate as a client. Tokens issued to other clients are reported as
+/// inactive unless the caller is a resource server or holds the admin scope. Callers over
+/// their introspection quota get `429 rate_limit_exceeded`. Under `/t/{tenant}`, only that
+/// tenant's clients may call and only its tokens are active.
+#[allow(clippy::too_many_arguments)] // actix extractors
 pub async fn introspect(
+    req: HttpRequest,
     form: web::Form<IntrospectRequest>,
     token_actor: web::Data<Addr<TokenActor>>,
-    jwt_secret: web::Data<String>,
+    client_actor: web::Data<Addr<ClientActor>>,
+    metrics: web::Data<Metrics>,
+    rate_limiter: Option<web::Data<IntrospectionRateLimiter>>,
+    tenant: Option<web::ReqData<TenantContext>>,
 ) -> Result<HttpResponse, OAuth2Error> {
-    let token_prefix = form.token.chars().take(20).collect::<String>();
+    let tenant = tenant.map(web::ReqData::into_inner);
+    let caller = authenticate_client(
+        &req,
+        form.client_id.as_deref(),
+        form.client_secret.as_deref(),
+        tenant.as_ref(),
+        &client_actor,
+    )
+    .await?;
+
+    // Resource servers are labelled individually; other callers share one series.
+    let caller_label = if caller.is_resource_server() {
+        caller.client_id.as_str()
+    } else {
+        "client"
+    };
+    if let Some(Err(retry_after)) = rate_limiter.map(|limiter| limiter.check(&caller.client_id)) {
+        metrics
+            .oauth_introspection_requests_total
+            .with_label_values(&[caller_label, "rate_limited"])
+            .inc();
+        tracing::warn!(caller = %caller.client_id, "Introspection quota exceeded");
+        SecurityEvents::for_request(&req).publish(
+            EventType::RateLimitExceeded,
+            Some(&caller.client_id),
+            None,
+            &[("limit", "introspection")],
+        );
+        return Ok(HttpResponse::TooManyRequests()
+            .insert_header((
+                header::RETRY_AFTER,
+                retry_after.as_secs().max(1).to_string(),
+            ))
+            .json(OAuth2Error::rate_limit_exceeded(
+                "Introspection quota exceeded; retry later",
+            )));
+    }
+
+    let redacted = Redactor::current().token(&form.token);
     tracing::info!(
         token_len = form.token.len(),
-        token_prefix = %token_prefix,
+        token = %redacted,
         "Token introspection requested"
     );
 
-    // Try to validate the token
-    let token_result = token_actor
-        .send(ValidateToken {
+    let Introspection {
+        response,
+        verification,
+    } = token_actor
+        .send(IntrospectToken {
+            caller: caller.clone(),
             token: form.token.clone(),
+            tenant,
             span: tracing::Span::current(),
+            deadline: Deadline::current(),
         })
         .await
-        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))?;
-
-    match token_result {
-        Ok(token) => {
-            // Decode JWT to get claims
-            let claims = Claims::decode(&token.access_token, &jwt_secret).ok();
-
-            let active = token.is_valid();
-            let user_id = token.user_id.clone();
-            let scope = token.scope;
-            let client_id = token.client_id;
-            let token_type = token.token_type;
-
-            let response = IntrospectionResponse {
-                active,
-                scope: Some(scope),
-                client_id: Some(client_id),
-                username: user_id.clone(),
-                token_type: Some(token_type),
-                exp: claims.as_ref().map(|c| c.exp),
-                iat: claims.as_ref().map(|c| c.iat),
-                sub: claims.as_ref().map(|c| c.sub.clone()).or(user_id),
-            };
-
-            Ok(HttpResponse::Ok()
-                .insert_header((actix_web::http::header::CACHE_CONTROL, "no-store"))
-                .insert_header((actix_web::http::header::PRAGMA, "no-cache"))
-                .json(response))
-        }
-        Err(err) => {
-            tracing::warn!(
-                error = %err,
-                token_len = form.token.len(),
-                token_prefix = %token_prefix,
-                "Token introspection failed; returning inactive"
-            );
-            // Token is invalid
-            let response = IntrospectionResponse {
-                active: false,
-                scope: None,
-                client_id: None,
-                username: None,
-                token_type: None,
-                exp: None,
-                iat: None,
-                sub: None,
-            };
-            Ok(HttpResponse::Ok()
-                .insert_header((actix_web::http::header::CACHE_CONTROL, "no-store"))
-                .insert_header((actix_web::http::header::PRAGMA, "no-cache"))
-                .json(response))
-        }
+        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
+    if let Some((generation, result)) = verification {
+        metrics
+            .oauth_token_verifications_by_issuer
+            .with_label_values(&[generation, result])
+            .inc();
     }
+    let outcome = if response.active {
+        "active"
+    } else {
+        "inactive"
+    };
+    metrics
+        .oauth_introspection_requests_total
+        .with_label_values(&[caller_label, outcome])
+        .inc();
+
+    Ok(HttpResponse::Ok()
+        .insert_header((actix_web::http::header::CACHE_CONTROL, "no-store"))
+        .insert_header((actix_web::http::header::PRAGMA, "no-cache"))
+        .json(response))
 }
 
 #[derive(Debug, Deserialize)]
 pub struct RevokeRequest {
     token: String,
-    #[allow(dead_code)] // OAuth2 spec field, can be used for optimization
     token_type_hint: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
 }
 
-/// Token revocation endpoint
+/// Token revocation endpoint (RFC 7009)
 /// Revokes an access or refresh token
+///
+/// Callers must authenticate as a client and may only revoke their own tokens unless
+/// they hold the admin scope. Unknown tokens, and tokens of other tenants, are ignored.
+/// Revoking a refresh token also revokes every access token issued under the same grant.
 pub async fn revoke(
+    req: HttpRequest,
     form: web::Form<RevokeRequest>,
     token_actor: web::Data<Addr<TokenActor>>,
+    client_actor: web::Data<Addr<ClientActor>>,
+    tenant: Option<web::ReqData<TenantContext>>,
 ) -> Result<HttpResponse, OAuth2Error> {
+    let tenant = tenant.map(web::ReqData::into_inner);
+    let caller = authenticate_client(
+        &req,
+        form.client_id.as_deref(),
+        form.client_secret.as_deref(),
+        tenant.as_ref(),
+        &client_actor,
+    )
+    .await?;
+
     token_actor
         .send(RevokeToken {
             token: form.token.clone(),
+            token_type_hint: form.token_type_hint.clone(),
+            client_id: (!has_admin_scope(&caller)).then_some(caller.client_id),
+            tenant_id: tenant_id(tenant.as_ref()).map(str::to_string),
             span: tracing::Span::current(),
+            deadline: Deadline::current(),
         })
         .await
         .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))??;
@@ -114,3 +281,87 @@ pub async fn revoke(
         .insert_header((actix_web::http::header::PRAGMA, "no-cache"))
         .finish())
 }
+
+/// The revocation list served at `GET /oauth/revocations`, rebuilt from storage at most
+/// once per `max_age` and shared by every worker.
+#[derive(Clone)]
+pub struct RevocationListCache {
+    max_age: Duration,
+    current: Arc<tokio::sync::Mutex<Option<(Instant, RevocationList)>>>,
+}
+
+impl Default for RevocationListCache {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(30))
+    }
+}
+
+impl RevocationListCache {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            current: Arc::new(tokio::sync::Mutex::new(None)),
+        }
+    }
+
+    async fn get(
+        &self,
+        db: &DynStorage,
+        issuer_keys: &IssuerKeys,
+    ) -> Result<RevocationList, OAuth2Error> {
+        // Holding the lock while rebuilding lets concurrent requests share one query.
+        let mut current = self.current.lock().await;
+        if let Some((built_at, list)) = current.as_ref() {
+            if built_at.elapsed() < self.max_age {
+                return Ok(list.clone());
+            }
+        }
+
+        let revoked = db
+            .list_revoked_tokens()
+            .await?
+            .into_iter()
+            .filter_map(|token| {
+                // Tokens no current or legacy issuer accepts are rejected anyway.
+                let (claims, _) = issuer_keys
+                    .verify_ignoring_expiry(&token.access_token)
+                    .ok()?;
+                Some(RevokedToken {
+                    jti: claims.jti,
+                    exp: claims.exp,
+                })
+            })
+            .collect();
+        let list = RevocationList { revoked };
+        *current = Some((Instant::now(), list.clone()));
+        Ok(list)
+    }
+}
+
+/// Revocation list for resource servers that validate JWTs locally
+///
+/// Lists the `jti` and `exp` of revoked access tokens until they expire. Responses carry
+/// an `ETag` and `Cache-Control` (see [`cacheable_json`]); see
+/// [`oauth2_core::RevocationChecker`] for the consuming side.
+pub async fn revocation_list(
+    req: HttpRequest,
+    db: web::Data<DynStorage>,
+    issuer_keys: web::Data<IssuerKeys>,
+    cache: Option<web::Data<RevocationListCache>>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let cache = cache
+        .map(|cache| cache.get_ref().clone())
+        .unwrap_or_default();
+    let list = cache.get(&db, &issuer_keys).await?;
+    let document = serde_json::to_value(&list)
+        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))?;
+
+    Ok(cacheable_json(
+        &req,
+        &document,
+        None,
+        MetadataCaching {
+            max_age: cache.max_age,
+        },
+    ))
+}
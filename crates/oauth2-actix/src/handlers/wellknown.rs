@@ -1,29 +1,162 @@
-use actix_web::{HttpResponse, Result};
-use serde_json::json;
+use actix_web::http::header::{self, CacheControl, CacheDirective, EntityTag, Header};
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+use crate::grants::GrantRegistry;
+use oauth2_config::GrantsConfig;
+use oauth2_core::{
+    tenant_id, GrantType, IssuerKeys, IssuerUrls, RequestOrigin, ResponseType, TenantContext,
+};
+
+/// How long clients and shared caches may reuse metadata documents before revalidating.
+#[derive(Debug, Clone, Copy)]
+pub struct MetadataCaching {
+    pub max_age: Duration,
+}
+
+impl Default for MetadataCaching {
+    fn default() -> Self {
+        Self {
+            max_age: Duration::from_secs(3600),
+        }
+    }
+}
+
+/// Serve a metadata document with an `ETag` and `Cache-Control`, answering `304 Not
+/// Modified` when `If-None-Match` already names the current version.
+///
+/// The tag covers the document and the signing keys, so a key rotation invalidates cached
+/// copies even when the document itself is unchanged.
+pub fn cacheable_json(
+    req: &HttpRequest,
+    document: &Value,
+    keys: Option<&IssuerKeys>,
+    caching: MetadataCaching,
+) -> HttpResponse {
+    let body = document.to_string();
+    let mut hasher = Sha256::new();
+    hasher.update(body.as_bytes());
+    if let Some(keys) = keys {
+        hasher.update(keys.key_tag().as_bytes());
+    }
+    let etag = EntityTag::new_strong(general_purpose::URL_SAFE_NO_PAD.encode(hasher.finalize()));
+
+    let cache_control = if caching.max_age.is_zero() {
+        CacheControl(vec![CacheDirective::NoCache])
+    } else {
+        CacheControl(vec![
+            CacheDirective::Public,
+            CacheDirective::MaxAge(caching.max_age.as_secs() as u32),
+        ])
+    };
+
+    let not_modified = match header::IfNoneMatch::parse(req) {
+        Ok(header::IfNoneMatch::Any) => true,
+        Ok(header::IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+        Err(_) => false,
+    };
+    if not_modified {
+        return HttpResponse::NotModified()
+            .insert_header(header::ETag(etag))
+            .insert_header(cache_control)
+            .finish();
+    }
+
+    HttpResponse::Ok()
+        .insert_header(header::ETag(etag))
+        .insert_header(cache_control)
+        .content_type("application/json")
+        .body(body)
+}
 
 /// OAuth2 discovery endpoint
 /// Returns server metadata according to RFC 8414
-pub async fn openid_configuration() -> Result<HttpResponse> {
+///
+/// URLs are absolute, built from `server.issuer` or, when unset, from the request. Under
+/// `/t/{tenant}` they point at the tenant's endpoints.
+///
+/// Responses carry an `ETag` and `Cache-Control` (see [`cacheable_json`]).
+pub async fn openid_configuration(
+    req: HttpRequest,
+    issuer_urls: Option<web::Data<IssuerUrls>>,
+    issuer_keys: Option<web::Data<IssuerKeys>>,
+    caching: Option<web::Data<MetadataCaching>>,
+    grants: Option<web::Data<GrantsConfig>>,
+    registry: Option<web::Data<GrantRegistry>>,
+    tenant: Option<web::ReqData<TenantContext>>,
+) -> Result<HttpResponse> {
+    let tenant = tenant.map(web::ReqData::into_inner);
+    let server_urls = issuer_urls
+        .as_ref()
+        .map(|urls| urls.get_ref())
+        .cloned()
+        .unwrap_or_default();
+    let urls = server_urls.clone().for_tenant(tenant_id(tenant.as_ref()));
+    let origin = RequestOrigin::from(&req);
+    let base = urls.base_url(&origin);
+
     let config = json!({
-        "issuer": "http://localhost:8080",
-        "authorization_endpoint": "http://localhost:8080/oauth/authorize",
-        "token_endpoint": "http://localhost:8080/oauth/token",
-        "token_introspection_endpoint": "http://localhost:8080/oauth/introspect",
-        "token_revocation_endpoint": "http://localhost:8080/oauth/revoke",
-        "registration_endpoint": "http://localhost:8080/clients/register",
+        "issuer": base,
+        "authorization_endpoint": urls.url(&origin, "/oauth/authorize"),
+        "token_endpoint": urls.token_endpoint(&origin),
+        "token_introspection_endpoint": urls.url(&origin, "/oauth/introspect"),
+        "token_revocation_endpoint": urls.url(&origin, "/oauth/revoke"),
+        "registration_endpoint": urls.url(&origin, "/clients/register"),
+        "end_session_endpoint": urls.url(&origin, "/oauth/logout"),
         "scopes_supported": ["read", "write", "admin"],
-        // The server supports Authorization Code + Client Credentials.
-        // Implicit, Password, and Refresh Token grants are intentionally disabled by default
+        // Implicit is never supported; password and refresh_token are opt-in via `grants`
         // (OAuth 2.0 Security Best Current Practice).
-        "response_types_supported": ["code"],
-        "grant_types_supported": ["authorization_code", "client_credentials"],
+        "response_types_supported": ResponseType::ALL,
+        "grant_types_supported": match &registry {
+            Some(registry) => registry.grant_types(),
+            None => grants
+                .as_ref()
+                .map(|grants| grants.get_ref().clone())
+                .unwrap_or_default()
+                .enabled_grant_types()
+                .iter()
+                .map(GrantType::as_str)
+                .collect(),
+        },
         "token_endpoint_auth_methods_supported": [
+            "client_secret_basic",
+            "client_secret_post",
+            "private_key_jwt"
+        ],
+        // `private_key_jwt` is accepted for service accounts with a registered public key.
+        "token_endpoint_auth_signing_alg_values_supported": [
+            "RS256", "RS384", "RS512", "PS256", "PS384", "PS512", "ES256", "ES384", "EdDSA"
+        ],
+        "introspection_endpoint_auth_methods_supported": [
+            "client_secret_basic",
+            "client_secret_post"
+        ],
+        "revocation_endpoint_auth_methods_supported": [
             "client_secret_basic",
             "client_secret_post"
         ],
         "code_challenge_methods_supported": ["S256"],
-        "service_documentation": "http://localhost:8080/docs"
+        // Silent authentication; other `prompt` values are accepted but not acted on.
+        "prompt_values_supported": ["none"],
+        // RFC 9207: authorization responses carry `iss`.
+        "authorization_response_iss_parameter_supported": true,
+        "service_documentation": server_urls.url(&origin, "/docs")
     });
 
-    Ok(HttpResponse::Ok().json(config))
+    let keys = match &tenant {
+        Some(tenant) => Some(&tenant.keys),
+        None => issuer_keys.as_ref().map(|keys| keys.get_ref()),
+    };
+    Ok(cacheable_json(
+        &req,
+        &config,
+        keys,
+        caching
+            .as_ref()
+            .map(|caching| *caching.get_ref())
+            .unwrap_or_default(),
+    ))
 }
@@ -4,5 +4,13 @@
 //! Domain types live in `oauth2-core`, while storage is abstracted behind `oauth2-ports`.
 
 pub mod actors;
+pub mod deadline;
+pub mod grants;
 pub mod handlers;
 pub mod middleware;
+pub mod mount;
+pub mod outbox;
+pub mod security_events;
+pub mod session;
+
+pub use mount::scope;
@@ -0,0 +1,136 @@
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::Method,
+    web, Error, HttpMessage,
+};
+use futures::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+
+use oauth2_core::{AdminRole, IssuerKeys, OAuth2Error, DEFAULT_ADMIN_SCOPE};
+use oauth2_ports::DynStorage;
+
+use crate::handlers::admin::require_admin;
+
+/// Scope granting every admin role, from `security.admin_scope`. Registered as app data;
+/// `admin` when absent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdminScope(pub String);
+
+impl Default for AdminScope {
+    fn default() -> Self {
+        Self(DEFAULT_ADMIN_SCOPE.to_string())
+    }
+}
+
+/// The caller of an admin route, available from request extensions once
+/// [`RequireAdminRole`] has let the request through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdminPrincipal {
+    /// `sub` of the bearer token.
+    pub subject: String,
+    pub role: AdminRole,
+}
+
+/// Admin API authorization: requires a bearer token whose scopes or claims map to at least
+/// the configured [`AdminRole`].
+///
+/// Reads (`GET`, `HEAD`) and writes may require different roles, e.g.
+/// `RequireAdminRole::new(AdminRole::Viewer).writes(AdminRole::Operator)`. Nested
+/// middleware reuse the [`AdminPrincipal`] authenticated by the outer one.
+#[derive(Debug, Clone, Copy)]
+pub struct RequireAdminRole {
+    read: AdminRole,
+    write: AdminRole,
+}
+
+impl RequireAdminRole {
+    pub fn new(role: AdminRole) -> Self {
+        Self {
+            read: role,
+            write: role,
+        }
+    }
+
+    /// Require `role` for requests other than `GET` and `HEAD`.
+    pub fn writes(mut self, role: AdminRole) -> Self {
+        self.write = role;
+        self
+    }
+
+    /// Role required for a request with `method`.
+    pub fn required_for(&self, method: &Method) -> AdminRole {
+        if matches!(*method, Method::GET | Method::HEAD) {
+            self.read
+        } else {
+            self.write
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequireAdminRole
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequireAdminRoleService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireAdminRoleService {
+            service: Rc::new(service),
+            policy: *self,
+        }))
+    }
+}
+
+pub struct RequireAdminRoleService<S> {
+    service: Rc<S>,
+    policy: RequireAdminRole,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireAdminRoleService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+        let required = self.policy.required_for(req.method());
+
+        Box::pin(async move {
+            let (Some(issuer_keys), Some(db)) = (
+                req.app_data::<web::Data<IssuerKeys>>().cloned(),
+                req.app_data::<web::Data<DynStorage>>().cloned(),
+            ) else {
+                let err = OAuth2Error::new(
+                    "server_error",
+                    Some("Admin authorization is not configured"),
+                );
+                return Ok(req.error_response(err).map_into_right_body());
+            };
+
+            match require_admin(req.request(), &issuer_keys, &db, required).await {
+                Ok(principal) => {
+                    req.extensions_mut().insert(principal);
+                    svc.call(req).await.map(ServiceResponse::map_into_left_body)
+                }
+                // Rendered here rather than returned as an error so outer middleware
+                // (logging, CORS) see an ordinary response.
+                Err(err) => Ok(req.error_response(err).map_into_right_body()),
+            }
+        })
+    }
+}
@@ -0,0 +1,88 @@
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::Method,
+    web, Error, HttpMessage,
+};
+use futures::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+
+use oauth2_core::{AuditAction, AuditOutcome, AuditRecord};
+use oauth2_ports::{record_audit, DynAuditSink};
+
+use crate::middleware::admin_rbac::AdminPrincipal;
+
+/// Records every admin API write (anything but `GET` and `HEAD`) as an
+/// [`AuditAction::AdminChange`], with the route as its target.
+///
+/// Wrap it outside [`RequireAdminRole`](crate::middleware::admin_rbac::RequireAdminRole) so
+/// rejected calls are recorded too; the actor is the [`AdminPrincipal`] when the caller
+/// was authenticated. Requests pass through untouched when no [`DynAuditSink`] is
+/// registered as app data.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AuditAdminChanges;
+
+impl<S, B> Transform<S, ServiceRequest> for AuditAdminChanges
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuditAdminChangesService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuditAdminChangesService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct AuditAdminChangesService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for AuditAdminChangesService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+        let sink = req.app_data::<web::Data<DynAuditSink>>().cloned();
+        if sink.is_none() || matches!(*req.method(), Method::GET | Method::HEAD) {
+            return Box::pin(svc.call(req));
+        }
+
+        Box::pin(async move {
+            let res = svc.call(req).await?;
+            let outcome = if res.status().is_success() {
+                AuditOutcome::Success
+            } else {
+                AuditOutcome::Failure
+            };
+            let request = res.request();
+            let mut record = AuditRecord::new(AuditAction::AdminChange, outcome)
+                .with_target(format!("{} {}", request.method(), request.path()))
+                .with_request(request);
+            if let Some(principal) = request.extensions().get::<AdminPrincipal>() {
+                record = record.with_actor(principal.subject.clone());
+            }
+            if outcome == AuditOutcome::Failure {
+                record = record.with_detail(res.status().as_u16().to_string());
+            }
+            record_audit(sink.as_ref().map(|sink| sink.get_ref()), record).await;
+            Ok(res)
+        })
+    }
+}
@@ -0,0 +1,206 @@
+//! Bearer-token protection (RFC 6750) for routes hosted alongside the OAuth2 endpoints.
+//!
+//! Needs the [`IssuerKeys`] and [`DynStorage`] app data that `OAuth2Server::configure`
+//! registers, so a host application's own routes can be guarded with the tokens this server
+//! issues:
+//!
+//! ```ignore
+//! App::new()
+//!     .configure(|cfg| oauth2.configure(cfg))
+//!     .service(web::scope("/api").wrap(RequireScope::new("read")).route("/me", web::get().to(me)))
+//! ```
+
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header,
+    web, Error, FromRequest, HttpMessage, HttpRequest,
+};
+use futures::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+
+use oauth2_core::{tenant_id, Claims, IssuerKeys, OAuth2Error, TenantContext, Token};
+use oauth2_ports::DynStorage;
+
+/// The raw token from an `Authorization: Bearer` header (RFC 6750 section 2.1).
+#[derive(Debug, Clone)]
+pub struct BearerToken(pub String);
+
+impl BearerToken {
+    /// Fails with a bare `Bearer` challenge when the header is absent or empty.
+    pub fn from_request_headers(req: &HttpRequest) -> Result<Self, OAuth2Error> {
+        req.headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .map(|token| Self(token.to_string()))
+            .ok_or_else(OAuth2Error::missing_token)
+    }
+}
+
+impl FromRequest for BearerToken {
+    type Error = OAuth2Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Self::from_request_headers(req))
+    }
+}
+
+/// A bearer token signed by this server that storage still knows as unexpired and
+/// unrevoked, with its verified claims.
+///
+/// Reuses the token validated by [`RequireScope`] when that middleware ran.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedToken {
+    pub token: Token,
+    pub claims: Claims,
+}
+
+impl AuthenticatedToken {
+    /// Verify `raw` against the signing keys of `tenant` (`issuer_keys` for the default
+    /// tenant), then against storage. Tokens of other tenants are invalid.
+    pub async fn validate(
+        raw: &str,
+        issuer_keys: &IssuerKeys,
+        db: &DynStorage,
+        tenant: Option<&TenantContext>,
+    ) -> Result<Self, OAuth2Error> {
+        let issuer_keys = tenant.map_or(issuer_keys, |tenant| &tenant.keys);
+        let (claims, _) = issuer_keys
+            .verify(raw)
+            .map_err(|_| OAuth2Error::invalid_token("Invalid bearer token"))?;
+
+        // A revoked JWT still verifies, so storage has the final say.
+        let token = db
+            .get_token_by_access_token(raw)
+            .await?
+            .filter(|token| token.is_valid() && token.in_tenant(tenant_id(tenant)))
+            .ok_or_else(|| OAuth2Error::invalid_token("Token is revoked or expired"))?;
+        Ok(Self { token, claims })
+    }
+
+    /// Authenticate the bearer token of `req` with the server's registered app data, for
+    /// the tenant the request was addressed to.
+    pub async fn from_http_request(req: &HttpRequest) -> Result<Self, OAuth2Error> {
+        if let Some(authenticated) = req.extensions().get::<Self>() {
+            return Ok(authenticated.clone());
+        }
+        let BearerToken(raw) = BearerToken::from_request_headers(req)?;
+        let (Some(issuer_keys), Some(db)) = (
+            req.app_data::<web::Data<IssuerKeys>>(),
+            req.app_data::<web::Data<DynStorage>>(),
+        ) else {
+            return Err(OAuth2Error::new(
+                "server_error",
+                Some("Bearer authentication is not configured"),
+            ));
+        };
+        let tenant = req.extensions().get::<TenantContext>().cloned();
+        Self::validate(&raw, issuer_keys, db, tenant.as_ref()).await
+    }
+
+    /// Whether the token was granted `scope`.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.token.scope.split_whitespace().any(|s| s == scope)
+    }
+
+    /// Fail with `insufficient_scope`, naming `scope` in the challenge, unless the token
+    /// was granted it.
+    pub fn require_scope(&self, scope: &str) -> Result<(), OAuth2Error> {
+        if self.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(
+                OAuth2Error::insufficient_scope(&format!("Token lacks the '{scope}' scope"))
+                    .with_required_scope(scope),
+            )
+        }
+    }
+}
+
+impl FromRequest for AuthenticatedToken {
+    type Error = OAuth2Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move { Self::from_http_request(&req).await })
+    }
+}
+
+/// Resource-server middleware: requires a valid bearer token granted `scope`, answering
+/// `401 invalid_token` or `403 insufficient_scope` with a `WWW-Authenticate` challenge.
+///
+/// The [`AuthenticatedToken`] is stored in the request extensions, where the extractor (or
+/// `web::ReqData<AuthenticatedToken>`) picks it up.
+#[derive(Debug, Clone)]
+pub struct RequireScope {
+    scope: Rc<str>,
+}
+
+impl RequireScope {
+    pub fn new(scope: impl Into<String>) -> Self {
+        Self {
+            scope: scope.into().into(),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequireScope
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequireScopeService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireScopeService {
+            service: Rc::new(service),
+            scope: self.scope.clone(),
+        }))
+    }
+}
+
+pub struct RequireScopeService<S> {
+    service: Rc<S>,
+    scope: Rc<str>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireScopeService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+        let scope = self.scope.clone();
+
+        Box::pin(async move {
+            let authenticated = match AuthenticatedToken::from_http_request(req.request()).await {
+                Ok(authenticated) => authenticated,
+                Err(err) => return Ok(req.error_response(err).map_into_right_body()),
+            };
+            if let Err(err) = authenticated.require_scope(&scope) {
+                return Ok(req.error_response(err).map_into_right_body());
+            }
+            req.extensions_mut().insert(authenticated);
+            svc.call(req).await.map(ServiceResponse::map_into_left_body)
+        })
+    }
+}
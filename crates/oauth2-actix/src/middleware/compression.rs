@@ -0,0 +1,243 @@
+use actix_http::encoding::Encoder;
+use actix_web::{
+    body::{BodySize, MessageBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{self, AcceptEncoding, ContentEncoding, Encoding, Header},
+    Error,
+};
+use futures::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use super::timeout::matches_prefix;
+
+/// Response compression limited to JSON bodies.
+///
+/// The encoding is negotiated from `Accept-Encoding` among the configured encodings, with
+/// identity always acceptable so negotiation never fails. Responses are only encoded
+/// when they are JSON (`application/json` or a `+json` type), have a known length of at
+/// least `min_size` bytes, and their path is not under an excluded prefix. Excluded
+/// paths are for responses that mix secrets with attacker-influenced input, where the
+/// compressed length leaks the secret (BREACH).
+///
+/// Compressed request bodies need no middleware: the payload extractors decode them.
+#[derive(Clone)]
+pub struct ResponseCompression {
+    /// Always contains identity; empty otherwise disables compression entirely.
+    encodings: Arc<Vec<Encoding>>,
+    min_size: u64,
+    excluded: Arc<Vec<String>>,
+}
+
+impl ResponseCompression {
+    /// An empty `encodings` disables compression.
+    pub fn new(encodings: impl IntoIterator<Item = ContentEncoding>) -> Self {
+        let mut offered: Vec<Encoding> = encodings.into_iter().map(Encoding::Known).collect();
+        if !offered.is_empty() {
+            offered.push(Encoding::identity());
+        }
+        Self {
+            encodings: Arc::new(offered),
+            min_size: 0,
+            excluded: Arc::new(Vec::new()),
+        }
+    }
+
+    pub fn with_min_size(mut self, bytes: u64) -> Self {
+        self.min_size = bytes;
+        self
+    }
+
+    pub fn exclude_path(mut self, path_prefix: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.excluded).push(path_prefix.into());
+        self
+    }
+
+    /// Encoding to use for a response to `path`, before looking at the response itself.
+    fn negotiate(&self, path: &str, accept: Option<&AcceptEncoding>) -> ContentEncoding {
+        if self.encodings.is_empty()
+            || self
+                .excluded
+                .iter()
+                .any(|prefix| matches_prefix(path, prefix))
+        {
+            return ContentEncoding::Identity;
+        }
+        match accept.and_then(|accept| accept.negotiate(self.encodings.iter())) {
+            Some(Encoding::Known(encoding)) => encoding,
+            _ => ContentEncoding::Identity,
+        }
+    }
+
+    fn should_encode(&self, head: &actix_web::dev::ResponseHead, size: BodySize) -> bool {
+        let is_json = head
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .map(|mime| {
+                let mime = mime.trim();
+                mime.eq_ignore_ascii_case("application/json")
+                    || mime.to_ascii_lowercase().ends_with("+json")
+            })
+            .unwrap_or(false);
+
+        // Streams (e.g. server-sent events) have no known size and are left alone.
+        is_json && matches!(size, BodySize::Sized(len) if len >= self.min_size)
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ResponseCompression
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<Encoder<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ResponseCompressionService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ResponseCompressionService {
+            service: Rc::new(service),
+            compression: self.clone(),
+        }))
+    }
+}
+
+pub struct ResponseCompressionService<S> {
+    service: Rc<S>,
+    compression: ResponseCompression,
+}
+
+impl<S, B> Service<ServiceRequest> for ResponseCompressionService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<Encoder<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let accept = AcceptEncoding::parse(req.request()).ok();
+        let encoding = self.compression.negotiate(req.path(), accept.as_ref());
+        let compression = self.compression.clone();
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let res = fut.await?;
+            Ok(res.map_body(move |head, body| {
+                let encoding = if encoding != ContentEncoding::Identity
+                    && compression.should_encode(head, body.size())
+                {
+                    encoding
+                } else {
+                    ContentEncoding::Identity
+                };
+                Encoder::response(encoding, head, body)
+            }))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test as actix_test, web, App, HttpResponse};
+
+    fn accept(value: &str) -> AcceptEncoding {
+        let req = actix_test::TestRequest::default()
+            .insert_header((header::ACCEPT_ENCODING, value))
+            .to_http_request();
+        AcceptEncoding::parse(&req).expect("accept-encoding")
+    }
+
+    #[test]
+    fn negotiates_only_offered_encodings_outside_excluded_paths() {
+        let compression =
+            ResponseCompression::new([ContentEncoding::Gzip]).exclude_path("/oauth/token");
+
+        assert_eq!(
+            compression.negotiate("/jwks", Some(&accept("br, gzip"))),
+            ContentEncoding::Gzip
+        );
+        assert_eq!(
+            compression.negotiate("/jwks", Some(&accept("br"))),
+            ContentEncoding::Identity
+        );
+        assert_eq!(
+            compression.negotiate("/jwks", None),
+            ContentEncoding::Identity
+        );
+        assert_eq!(
+            compression.negotiate("/oauth/token", Some(&accept("gzip"))),
+            ContentEncoding::Identity
+        );
+        assert_eq!(
+            compression.negotiate("/oauth/tokenx", Some(&accept("gzip"))),
+            ContentEncoding::Gzip
+        );
+
+        let disabled = ResponseCompression::new([]);
+        assert_eq!(
+            disabled.negotiate("/jwks", Some(&accept("gzip"))),
+            ContentEncoding::Identity
+        );
+    }
+
+    #[actix_web::test]
+    async fn only_large_enough_json_is_encoded() {
+        let large = "x".repeat(2048);
+        let json_body = serde_json::json!({ "data": large }).to_string();
+        let text_body = large.clone();
+
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(ResponseCompression::new([ContentEncoding::Gzip]).with_min_size(1024))
+                .route(
+                    "/json",
+                    web::get().to(move || {
+                        let body = json_body.clone();
+                        async move {
+                            HttpResponse::Ok()
+                                .content_type("application/json")
+                                .body(body)
+                        }
+                    }),
+                )
+                .route(
+                    "/small",
+                    web::get().to(|| async { HttpResponse::Ok().json(serde_json::json!({})) }),
+                )
+                .route(
+                    "/text",
+                    web::get().to(move || {
+                        let body = text_body.clone();
+                        async move { HttpResponse::Ok().content_type("text/plain").body(body) }
+                    }),
+                ),
+        )
+        .await;
+
+        for (uri, encoded) in [("/json", true), ("/small", false), ("/text", false)] {
+            let req = actix_test::TestRequest::get()
+                .uri(uri)
+                .insert_header((header::ACCEPT_ENCODING, "gzip"))
+                .to_request();
+            let resp = actix_test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 200);
+            assert_eq!(
+                resp.headers().get(header::CONTENT_ENCODING).is_some(),
+                encoded,
+                "{uri}"
+            );
+        }
+    }
+}
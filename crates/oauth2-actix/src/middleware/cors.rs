@@ -0,0 +1,494 @@
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{
+        header::{self, HeaderValue},
+        Method,
+    },
+    Error, HttpMessage, HttpRequest, HttpResponse,
+};
+use futures::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use oauth2_core::Client;
+
+use super::timeout::matches_prefix;
+
+/// Cross-origin access for browser-based clients.
+///
+/// Routes fall into three kinds, by path prefix:
+/// - public paths (discovery metadata) may be read by any origin;
+/// - client paths (the token endpoint) may be called from the origins registered by the
+///   requesting client, which the handler reports with [`allow_client_origins`] once it
+///   has loaded the client;
+/// - everything else only from the origins configured here.
+///
+/// Configured origins are allowed on every route; `*` allows any origin. Tenant routes
+/// (`/t/{tenant}/...`) are matched like their default-tenant counterparts. Preflights on
+/// client paths are answered for any origin, since the client is only known from the
+/// actual request; its response carries `Access-Control-Allow-Origin` only for that
+/// client's origins, so other pages cannot read it.
+#[derive(Clone)]
+pub struct CorsPolicy {
+    origins: Arc<Vec<String>>,
+    methods: Arc<Vec<String>>,
+    headers: Arc<Vec<String>>,
+    max_age: Duration,
+    public_paths: Arc<Vec<String>>,
+    client_paths: Arc<Vec<String>>,
+}
+
+/// Origins the client of the current request registered; see [`allow_client_origins`].
+#[derive(Debug, Clone)]
+pub struct ClientOrigins(pub Vec<String>);
+
+/// Let the origins registered by `client` read the response to `req` on client paths.
+pub fn allow_client_origins(req: &HttpRequest, client: &Client) {
+    req.extensions_mut()
+        .insert(ClientOrigins(client.get_allowed_origins()));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Route {
+    Public,
+    Client,
+    Other,
+}
+
+impl CorsPolicy {
+    /// Allow `origins` on every route; `*` allows any origin.
+    pub fn new<I, S>(origins: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            origins: Arc::new(
+                origins
+                    .into_iter()
+                    .map(|origin| normalize_origin(&origin.into()))
+                    .collect(),
+            ),
+            methods: Arc::new(
+                ["GET", "POST", "PUT", "DELETE", "OPTIONS"]
+                    .map(String::from)
+                    .to_vec(),
+            ),
+            headers: Arc::new(["authorization", "content-type"].map(String::from).to_vec()),
+            max_age: Duration::from_secs(3600),
+            public_paths: Arc::new(Vec::new()),
+            client_paths: Arc::new(Vec::new()),
+        }
+    }
+
+    /// `server.cors`.
+    pub fn from_config(cors: &oauth2_config::CorsConfig) -> Self {
+        let policy = Self::new(cors.allowed_origins.clone())
+            .with_methods(cors.allowed_methods.clone())
+            .with_headers(cors.allowed_headers.clone())
+            .with_max_age(Duration::from_secs(cors.max_age_secs));
+        let policy = cors
+            .public_paths
+            .iter()
+            .fold(policy, |policy, prefix| policy.public_path(prefix.clone()));
+        cors.client_paths
+            .iter()
+            .fold(policy, |policy, prefix| policy.client_path(prefix.clone()))
+    }
+
+    /// Methods allowed by preflights outside public paths, which only allow `GET`.
+    pub fn with_methods<I, S>(mut self, methods: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.methods = Arc::new(methods.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Request headers allowed by preflights; `*` allows whatever the preflight asks for.
+    pub fn with_headers<I, S>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.headers = Arc::new(headers.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// How long browsers may cache a preflight result.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Any origin may `GET` paths under `prefix`.
+    pub fn public_path(mut self, prefix: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.public_paths).push(prefix.into());
+        self
+    }
+
+    /// The requesting client's registered origins may call paths under `prefix`.
+    pub fn client_path(mut self, prefix: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.client_paths).push(prefix.into());
+        self
+    }
+
+    fn route(&self, path: &str) -> Route {
+        let path = without_tenant(path);
+        let under = |prefixes: &[String]| prefixes.iter().any(|p| matches_prefix(path, p));
+        if under(&self.public_paths) {
+            Route::Public
+        } else if under(&self.client_paths) {
+            Route::Client
+        } else {
+            Route::Other
+        }
+    }
+
+    /// `Access-Control-Allow-Origin` for `origin` from the configured origins alone.
+    fn configured(&self, origin: &str) -> Option<String> {
+        if self.origins.iter().any(|allowed| allowed == "*") {
+            Some("*".to_string())
+        } else {
+            self.origins
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(origin))
+                .then(|| origin.to_string())
+        }
+    }
+
+    /// `Access-Control-Allow-Origin` for a preflight from `origin`, if it is allowed.
+    fn preflight_origin(&self, route: Route, origin: &str) -> Option<String> {
+        match route {
+            Route::Public => Some("*".to_string()),
+            Route::Client => Some(
+                self.configured(origin)
+                    .unwrap_or_else(|| origin.to_string()),
+            ),
+            Route::Other => self.configured(origin),
+        }
+    }
+
+    fn preflight(&self, route: Route, allow_origin: &str, req: &HttpRequest) -> HttpResponse {
+        let methods = match route {
+            Route::Public => "GET".to_string(),
+            _ => self.methods.join(", "),
+        };
+        let headers = if self.headers.iter().any(|h| h == "*") {
+            req.headers()
+                .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+                .to_string()
+        } else {
+            self.headers.join(", ")
+        };
+
+        let mut res = HttpResponse::Ok();
+        res.insert_header((header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin))
+            .insert_header((header::ACCESS_CONTROL_ALLOW_METHODS, methods))
+            .insert_header((
+                header::ACCESS_CONTROL_MAX_AGE,
+                self.max_age.as_secs().to_string(),
+            ));
+        if !headers.is_empty() {
+            res.insert_header((header::ACCESS_CONTROL_ALLOW_HEADERS, headers));
+        }
+        if allow_origin != "*" {
+            res.insert_header((header::VARY, "Origin"));
+        }
+        res.finish()
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for CorsPolicy
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CorsPolicyService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CorsPolicyService {
+            service: Rc::new(service),
+            policy: self.clone(),
+        }))
+    }
+}
+
+pub struct CorsPolicyService<S> {
+    service: Rc<S>,
+    policy: CorsPolicy,
+}
+
+impl<S, B> Service<ServiceRequest> for CorsPolicyService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let origin = req
+            .headers()
+            .get(header::ORIGIN)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let service = self.service.clone();
+        let Some(origin) = origin else {
+            return Box::pin(async move { Ok(service.call(req).await?.map_into_left_body()) });
+        };
+
+        let route = self.policy.route(req.path());
+        let is_preflight = req.method() == Method::OPTIONS
+            && req
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+        if is_preflight {
+            if let Some(allow_origin) = self.policy.preflight_origin(route, &origin) {
+                let res = self.policy.preflight(route, &allow_origin, req.request());
+                return Box::pin(ready(Ok(req.into_response(res).map_into_right_body())));
+            }
+        }
+
+        let policy = self.policy.clone();
+        Box::pin(async move {
+            let mut res = service.call(req).await?;
+            let allow_origin = match route {
+                Route::Public => Some("*".to_string()),
+                Route::Client => policy.configured(&origin).or_else(|| {
+                    res.request()
+                        .extensions()
+                        .get::<ClientOrigins>()
+                        .filter(|client| {
+                            client
+                                .0
+                                .iter()
+                                .any(|allowed| allowed.eq_ignore_ascii_case(&origin))
+                        })
+                        .map(|_| origin.clone())
+                }),
+                Route::Other => policy.configured(&origin),
+            };
+
+            if let Some(allow_origin) = allow_origin {
+                let headers = res.headers_mut();
+                if allow_origin != "*" {
+                    headers.append(header::VARY, HeaderValue::from_static("Origin"));
+                }
+                if let Ok(value) = HeaderValue::from_str(&allow_origin) {
+                    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, value);
+                }
+            }
+            Ok(res.map_into_left_body())
+        })
+    }
+}
+
+/// `path` with a leading `/t/{tenant}` removed.
+fn without_tenant(path: &str) -> &str {
+    path.strip_prefix("/t/")
+        .and_then(|rest| rest.find('/').map(|slash| &rest[slash..]))
+        .unwrap_or(path)
+}
+
+fn normalize_origin(origin: &str) -> String {
+    origin.trim().trim_end_matches('/').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test as actix_test, web, App};
+
+    const SPA: &str = "https://spa.example";
+
+    async fn token(req: HttpRequest) -> HttpResponse {
+        let mut client = Client::new(
+            "spa".to_string(),
+            String::new(),
+            vec![format!("{SPA}/cb")],
+            vec!["authorization_code".to_string()],
+            "read".to_string(),
+            "SPA".to_string(),
+        );
+        client.set_allowed_origins(&[SPA.to_string()]);
+        allow_client_origins(&req, &client);
+        HttpResponse::Ok().finish()
+    }
+
+    fn allow_origin(res: &ServiceResponse<impl actix_web::body::MessageBody>) -> Option<&str> {
+        res.headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .and_then(|v| v.to_str().ok())
+    }
+
+    macro_rules! init_app {
+        ($policy:expr) => {
+            actix_test::init_service(
+                App::new()
+                    .wrap($policy)
+                    .route("/oauth/token", web::post().to(token))
+                    .route("/t/{tenant}/oauth/token", web::post().to(token))
+                    .route(
+                        "/.well-known/openid-configuration",
+                        web::get().to(HttpResponse::Ok),
+                    )
+                    .route("/admin/clients", web::get().to(HttpResponse::Ok)),
+            )
+            .await
+        };
+    }
+
+    fn policy() -> CorsPolicy {
+        CorsPolicy::new(["https://admin.example/"])
+            .public_path("/.well-known")
+            .client_path("/oauth/token")
+    }
+
+    fn request(method: Method, path: &str, origin: &str) -> actix_test::TestRequest {
+        actix_test::TestRequest::default()
+            .method(method)
+            .uri(path)
+            .insert_header((header::ORIGIN, origin))
+    }
+
+    #[test]
+    fn tenant_routes_match_like_default_routes() {
+        let policy = policy();
+        assert_eq!(policy.route("/oauth/token"), Route::Client);
+        assert_eq!(policy.route("/t/acme/oauth/token"), Route::Client);
+        assert_eq!(
+            policy.route("/t/acme/.well-known/openid-configuration"),
+            Route::Public
+        );
+        assert_eq!(policy.route("/oauth/tokenx"), Route::Other);
+        assert_eq!(policy.route("/t/acme"), Route::Other);
+    }
+
+    #[actix_web::test]
+    async fn discovery_is_readable_from_any_origin() {
+        let app = init_app!(policy());
+        let res = actix_test::call_service(
+            &app,
+            request(
+                Method::GET,
+                "/.well-known/openid-configuration",
+                "https://anyone.example",
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(allow_origin(&res), Some("*"));
+
+        let res = actix_test::call_service(
+            &app,
+            request(
+                Method::OPTIONS,
+                "/.well-known/openid-configuration",
+                "https://anyone.example",
+            )
+            .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "GET"))
+            .to_request(),
+        )
+        .await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(allow_origin(&res), Some("*"));
+        assert_eq!(
+            res.headers().get(header::ACCESS_CONTROL_ALLOW_METHODS),
+            Some(&HeaderValue::from_static("GET"))
+        );
+    }
+
+    #[actix_web::test]
+    async fn token_responses_are_readable_from_the_clients_origins_only() {
+        let app = init_app!(policy());
+        for path in ["/oauth/token", "/t/acme/oauth/token"] {
+            let res =
+                actix_test::call_service(&app, request(Method::POST, path, SPA).to_request()).await;
+            assert_eq!(allow_origin(&res), Some(SPA));
+            assert_eq!(
+                res.headers().get(header::VARY),
+                Some(&HeaderValue::from_static("Origin"))
+            );
+
+            let res = actix_test::call_service(
+                &app,
+                request(Method::POST, path, "https://evil.example").to_request(),
+            )
+            .await;
+            assert_eq!(allow_origin(&res), None);
+        }
+
+        // Configured origins are allowed for every client.
+        let res = actix_test::call_service(
+            &app,
+            request(Method::POST, "/oauth/token", "https://admin.example").to_request(),
+        )
+        .await;
+        assert_eq!(allow_origin(&res), Some("https://admin.example"));
+
+        let res = actix_test::call_service(
+            &app,
+            request(Method::OPTIONS, "/oauth/token", "https://evil.example")
+                .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "POST"))
+                .insert_header((header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(allow_origin(&res), Some("https://evil.example"));
+        assert_eq!(
+            res.headers().get(header::ACCESS_CONTROL_ALLOW_HEADERS),
+            Some(&HeaderValue::from_static("authorization, content-type"))
+        );
+    }
+
+    #[actix_web::test]
+    async fn other_routes_only_allow_configured_origins() {
+        let app = init_app!(policy());
+        let res = actix_test::call_service(
+            &app,
+            request(Method::GET, "/admin/clients", SPA).to_request(),
+        )
+        .await;
+        assert_eq!(allow_origin(&res), None);
+
+        let res = actix_test::call_service(
+            &app,
+            request(Method::OPTIONS, "/admin/clients", SPA)
+                .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "GET"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(allow_origin(&res), None);
+
+        let res = actix_test::call_service(
+            &app,
+            request(Method::GET, "/admin/clients", "https://admin.example").to_request(),
+        )
+        .await;
+        assert_eq!(allow_origin(&res), Some("https://admin.example"));
+
+        let app = init_app!(CorsPolicy::new(["*"]));
+        let res = actix_test::call_service(
+            &app,
+            request(Method::GET, "/admin/clients", SPA).to_request(),
+        )
+        .await;
+        assert_eq!(allow_origin(&res), Some("*"));
+    }
+}
@@ -1 +1,8 @@
+pub mod admin_rbac;
+pub mod audit;
 pub mod auth_middleware;
+pub mod bearer;
+pub mod compression;
+pub mod cors;
+pub mod tenant;
+pub mod timeout;
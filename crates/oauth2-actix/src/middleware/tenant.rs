@@ -0,0 +1,86 @@
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    web, Error, HttpMessage,
+};
+use futures::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+
+use oauth2_core::{IssuerKeys, OAuth2Error, TenantContext};
+use oauth2_ports::DynStorage;
+
+/// Resolves the `{tenant}` path segment of a `/t/{tenant}/...` scope and makes the
+/// [`TenantContext`] available to handlers through request extensions.
+///
+/// Unknown and disabled tenants are answered with `404` before any handler runs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ResolveTenant;
+
+impl<S, B> Transform<S, ServiceRequest> for ResolveTenant
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ResolveTenantService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ResolveTenantService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct ResolveTenantService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for ResolveTenantService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+
+        Box::pin(async move {
+            let (Some(issuer_keys), Some(db)) = (
+                req.app_data::<web::Data<IssuerKeys>>().cloned(),
+                req.app_data::<web::Data<DynStorage>>().cloned(),
+            ) else {
+                let err = OAuth2Error::new("server_error", Some("Tenancy is not configured"));
+                return Ok(req.error_response(err).map_into_right_body());
+            };
+            let tenant_id = req
+                .match_info()
+                .get("tenant")
+                .unwrap_or_default()
+                .to_string();
+
+            let tenant = match db.get_tenant(&tenant_id).await {
+                Ok(Some(tenant)) if tenant.enabled => tenant,
+                Ok(_) => {
+                    let err = OAuth2Error::not_found("Unknown tenant");
+                    return Ok(req.error_response(err).map_into_right_body());
+                }
+                Err(err) => return Ok(req.error_response(err).map_into_right_body()),
+            };
+
+            req.extensions_mut()
+                .insert(TenantContext::new(tenant, &issuer_keys));
+            svc.call(req).await.map(ServiceResponse::map_into_left_body)
+        })
+    }
+}
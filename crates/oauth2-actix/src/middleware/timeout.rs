@@ -0,0 +1,253 @@
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error,
+};
+use futures::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use oauth2_core::OAuth2Error;
+
+use crate::deadline::Deadline;
+
+/// Per-route request timeouts.
+///
+/// The timeout for a request is taken from the longest configured path prefix that
+/// matches (on `/` boundaries), falling back to the default. When it elapses, the inner
+/// future is dropped (cancelling in-flight work) and the client receives
+/// `503 temporarily_unavailable`. A zero duration disables the timeout for that route.
+///
+/// The timeout is also set as the request's [`Deadline`], which handlers pass on to the
+/// actors so that work detached from the request stops at the same time.
+#[derive(Clone)]
+pub struct RequestTimeout {
+    default: Duration,
+    /// Sorted longest prefix first so the first match is the most specific.
+    routes: Arc<Vec<(String, Duration)>>,
+}
+
+impl RequestTimeout {
+    pub fn new(default: Duration) -> Self {
+        Self {
+            default,
+            routes: Arc::new(Vec::new()),
+        }
+    }
+
+    /// `server.timeouts`: the default plus per-route overrides.
+    pub fn from_config(timeouts: &oauth2_config::TimeoutConfig) -> Self {
+        timeouts.routes.iter().fold(
+            Self::new(Duration::from_millis(timeouts.default_ms)),
+            |timeout, (prefix, ms)| timeout.with_route(prefix.clone(), Duration::from_millis(*ms)),
+        )
+    }
+
+    pub fn with_route(mut self, path_prefix: impl Into<String>, timeout: Duration) -> Self {
+        let prefix = path_prefix.into();
+        let routes = Arc::make_mut(&mut self.routes);
+        routes.retain(|(p, _)| *p != prefix);
+        routes.push((prefix, timeout));
+        routes.sort_by_key(|(p, _)| std::cmp::Reverse(p.len()));
+        self
+    }
+
+    /// Timeout for `path`, or `None` if disabled.
+    pub fn timeout_for(&self, path: &str) -> Option<Duration> {
+        let timeout = self
+            .routes
+            .iter()
+            .find(|(prefix, _)| matches_prefix(path, prefix))
+            .map(|(_, timeout)| *timeout)
+            .unwrap_or(self.default);
+
+        (!timeout.is_zero()).then_some(timeout)
+    }
+}
+
+/// Whether `path` is `prefix` or below it; `/oauth/tokenx` is not under `/oauth/token`.
+pub(crate) fn matches_prefix(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequestTimeout
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestTimeoutService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestTimeoutService {
+            service: Rc::new(service),
+            timeouts: self.clone(),
+        }))
+    }
+}
+
+pub struct RequestTimeoutService<S> {
+    service: Rc<S>,
+    timeouts: RequestTimeout,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestTimeoutService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let Some(timeout) = self.timeouts.timeout_for(req.path()) else {
+            return Box::pin(self.service.call(req));
+        };
+
+        // The request itself cannot be retained here: routing needs exclusive access
+        // to it. The error is rendered into a 503 response by the dispatcher.
+        let path = req.path().to_string();
+        let deadline = Deadline::after(timeout);
+        let fut = deadline.scope(self.service.call(req));
+
+        Box::pin(async move {
+            match actix_web::rt::time::timeout(timeout, fut).await {
+                Ok(res) => res,
+                Err(_) => {
+                    tracing::warn!(
+                        path = %path,
+                        timeout_ms = timeout.as_millis() as u64,
+                        "request timed out"
+                    );
+                    Err(OAuth2Error::temporarily_unavailable("Request timed out").into())
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test as actix_test, web, App, HttpResponse};
+
+    #[test]
+    fn longest_prefix_wins_on_segment_boundaries() {
+        let timeouts = RequestTimeout::new(Duration::from_secs(30))
+            .with_route("/oauth", Duration::from_secs(10))
+            .with_route("/oauth/token", Duration::from_secs(2))
+            .with_route("/metrics", Duration::ZERO);
+
+        assert_eq!(
+            timeouts.timeout_for("/oauth/token"),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            timeouts.timeout_for("/oauth/authorize"),
+            Some(Duration::from_secs(10))
+        );
+        // `/oauth/tokenx` is not under `/oauth/token`.
+        assert_eq!(
+            timeouts.timeout_for("/oauth/tokenx"),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            timeouts.timeout_for("/health"),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(timeouts.timeout_for("/metrics"), None);
+    }
+
+    #[actix_web::test]
+    async fn slow_requests_get_temporarily_unavailable() {
+        async fn slow() -> HttpResponse {
+            actix_web::rt::time::sleep(Duration::from_secs(5)).await;
+            HttpResponse::Ok().finish()
+        }
+
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(
+                    RequestTimeout::new(Duration::from_secs(30))
+                        .with_route("/slow", Duration::from_millis(50)),
+                )
+                .route("/slow", web::get().to(slow))
+                .route("/fast", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let resp = actix_test::call_service(
+            &app,
+            actix_test::TestRequest::get().uri("/fast").to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+
+        let err = actix_test::try_call_service(
+            &app,
+            actix_test::TestRequest::get().uri("/slow").to_request(),
+        )
+        .await
+        .expect_err("slow request should time out");
+        let resp = err.error_response();
+        assert_eq!(resp.status(), 503);
+        let body = actix_test::read_body(actix_web::dev::ServiceResponse::new(
+            actix_test::TestRequest::default().to_http_request(),
+            resp,
+        ))
+        .await;
+        let body: serde_json::Value = serde_json::from_slice(&body).expect("json body");
+        assert_eq!(body["error"], "temporarily_unavailable");
+    }
+
+    #[actix_web::test]
+    async fn handlers_see_the_route_deadline() {
+        async fn remaining() -> HttpResponse {
+            let remaining = Deadline::current()
+                .remaining()
+                .map(|d| d.as_millis() as u64);
+            HttpResponse::Ok().json(remaining)
+        }
+
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(
+                    RequestTimeout::new(Duration::from_secs(30))
+                        .with_route("/unbounded", Duration::ZERO),
+                )
+                .route("/bounded", web::get().to(remaining))
+                .route("/unbounded", web::get().to(remaining)),
+        )
+        .await;
+
+        let remaining: Option<u64> = actix_test::call_and_read_body_json(
+            &app,
+            actix_test::TestRequest::get().uri("/bounded").to_request(),
+        )
+        .await;
+        let remaining = remaining.expect("deadline set");
+        assert!(remaining > 25_000 && remaining <= 30_000, "{remaining}");
+
+        let remaining: Option<u64> = actix_test::call_and_read_body_json(
+            &app,
+            actix_test::TestRequest::get()
+                .uri("/unbounded")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(remaining, None);
+    }
+}
@@ -0,0 +1,177 @@
+//! The OAuth2 endpoints as one mountable [`Scope`], for applications that add them to an
+//! existing actix `App` instead of running the server binary.
+
+use actix::Actor;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::{web, Scope};
+use oauth2_config::{Config, JwtConfig};
+use oauth2_core::{IssuerKey, IssuerKeys, IssuerUrls, JwtValidation, LegacyIssuer, TrustedProxies};
+use oauth2_observability::Metrics;
+use oauth2_ports::DynStorage;
+
+use crate::actors::{AuthActor, ClientActor, TokenActor};
+use crate::handlers::client::registration_json_config;
+use crate::middleware::cors::CorsPolicy;
+use crate::middleware::timeout::RequestTimeout;
+
+/// `/oauth/*` (authorize, token, introspect, revoke, revocation list, logout and client
+/// registration) with the state and middleware those routes need:
+///
+/// ```no_run
+/// # fn example(config: oauth2_config::Config, storage: oauth2_ports::DynStorage) {
+/// use actix_web::{App, HttpServer};
+/// use oauth2_observability::Metrics;
+///
+/// let metrics = Metrics::new().expect("metrics");
+/// HttpServer::new(move || {
+///     App::new().service(oauth2_actix::scope(&config, storage.clone(), metrics.clone()))
+/// });
+/// # }
+/// ```
+///
+/// Call it in the `HttpServer` factory: the actors start on each worker. Request
+/// timeouts, CORS and metrics follow `config.server`. `/oauth/authorize` reads the signed-in
+/// user from the `user_id` key of the `App`'s own session, so the `App` must wrap a
+/// `SessionMiddleware` and handle login itself. Discovery is served at the root; register
+/// [`crate::handlers::wellknown::openid_configuration`] at
+/// `/.well-known/openid-configuration` to expose it. Events are not published; use
+/// `oauth2_server::ServerBuilder` for that and the remaining endpoint groups.
+///
+/// # Panics
+///
+/// If `jwt.validation`, `jwt.legacy.accept_until` or `server.trusted_proxies` is invalid.
+pub fn scope(
+    config: &Config,
+    storage: DynStorage,
+    metrics: Metrics,
+) -> Scope<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    let issuer_keys = issuer_keys_from_config(&config.jwt).unwrap_or_else(|err| panic!("{err}"));
+    let trusted_proxies = if config.server.trust_forwarded_headers {
+        TrustedProxies::any()
+    } else {
+        TrustedProxies::parse(&config.server.trusted_proxies)
+            .unwrap_or_else(|err| panic!("invalid server.trusted_proxies: {err}"))
+    };
+    let issuer_urls =
+        IssuerUrls::new(config.server.issuer.clone()).with_trusted_proxies(trusted_proxies.clone());
+
+    let token_actor = TokenActor::new(storage.clone(), config.jwt.secret.clone())
+        .with_issuer_keys(issuer_keys.clone())
+        .with_revocation_cascade(config.security.cascade_token_revocation)
+        .with_context_binding(
+            config.security.bind_authorization_codes,
+            config.security.context_binding_tolerance,
+        )
+        .start();
+    let client_actor = ClientActor::new(storage.clone())
+        .with_redirect_uri_changes(
+            config.security.redirect_uri_changes,
+            config.security.redirect_uri_change_delay_secs,
+        )
+        .with_admin_scope(config.security.admin_scope.clone())
+        .start();
+    let auth_actor = AuthActor::new(storage.clone())
+        .with_context_binding(
+            config.security.bind_authorization_codes,
+            config.security.context_binding_tolerance,
+        )
+        .start();
+
+    web::scope("/oauth")
+        .app_data(web::Data::new(token_actor))
+        .app_data(web::Data::new(client_actor))
+        .app_data(web::Data::new(auth_actor))
+        .app_data(web::Data::new(storage))
+        .app_data(web::Data::new(metrics.clone()))
+        .app_data(web::Data::new(issuer_keys))
+        .app_data(web::Data::new(issuer_urls))
+        .app_data(web::Data::new(trusted_proxies))
+        .app_data(web::Data::new(config.grants.clone()))
+        .app_data(registration_json_config())
+        .configure(oauth_routes)
+        .wrap(RequestTimeout::from_config(&config.server.timeouts))
+        .wrap(oauth2_observability::actix::MetricsMiddleware::new(metrics))
+        .wrap(CorsPolicy::from_config(&config.server.cors))
+}
+
+/// The routes under `/oauth`, relative to it.
+pub fn oauth_routes(cfg: &mut web::ServiceConfig) {
+    use crate::handlers::{client, oauth, token};
+
+    cfg.route("/authorize", web::get().to(oauth::authorize))
+        .route("/authorize", web::post().to(oauth::authorize_decision))
+        .route("/token", web::post().to(oauth::token))
+        .route("/introspect", web::post().to(token::introspect))
+        .route("/revoke", web::post().to(token::revoke))
+        .route("/revocations", web::get().to(token::revocation_list))
+        // OIDC RP-Initiated Logout
+        .route("/logout", web::get().to(oauth::end_session))
+        .route("/logout", web::post().to(oauth::end_session))
+        // Dynamic client registration + management (RFC 7591 / RFC 7592)
+        .route("/register", web::post().to(client::register_client))
+        .route(
+            "/register/{client_id}",
+            web::get().to(client::get_client_configuration),
+        )
+        .route(
+            "/register/{client_id}",
+            web::put().to(client::update_client_configuration),
+        )
+        .route(
+            "/register/{client_id}",
+            web::delete().to(client::delete_client_configuration),
+        );
+}
+
+/// JWT signing/verification keys, including the legacy issuer during a migration.
+///
+/// Fails, naming the setting, if `jwt.validation` names an unknown claim or algorithm, or
+/// `jwt.legacy.accept_until` is not an RFC 3339 timestamp.
+pub fn issuer_keys_from_config(jwt: &JwtConfig) -> Result<IssuerKeys, String> {
+    let validation = JwtValidation::new(
+        jwt.validation.leeway_secs,
+        &jwt.validation.required_claims,
+        &jwt.validation.algorithms,
+    )
+    .map_err(|e| format!("invalid jwt.validation: {e}"))?;
+    let keys = IssuerKeys::new(IssuerKey::new(jwt.issuer.clone(), jwt.secret.clone()))
+        .with_validation(validation);
+    let Some(legacy) = &jwt.legacy else {
+        return Ok(keys);
+    };
+
+    let accept_until = legacy
+        .accept_until
+        .as_deref()
+        .map(|s| {
+            chrono::DateTime::parse_from_rfc3339(s)
+                .map(|until| until.with_timezone(&chrono::Utc))
+                .map_err(|e| {
+                    format!(
+                        "jwt.legacy.accept_until of legacy issuer '{}' is not an RFC 3339 \
+                         timestamp: '{s}' ({e})",
+                        legacy.issuer
+                    )
+                })
+        })
+        .transpose()?;
+    tracing::info!(
+        legacy_issuer = %legacy.issuer,
+        accept_until = ?accept_until,
+        "Accepting tokens from legacy JWT issuer"
+    );
+
+    Ok(keys.with_legacy(LegacyIssuer::new(
+        IssuerKey::new(legacy.issuer.clone(), legacy.secret.clone()),
+        accept_until,
+    )))
+}
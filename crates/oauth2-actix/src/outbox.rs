@@ -0,0 +1,142 @@
+//! Durable event delivery: [`StorageOutbox`] queues events in storage and an
+//! [`OutboxRelay`] publishes them to the event backends, retrying until they are accepted.
+//!
+//! Token issuance queues its events in the transaction that saves the token (see
+//! [`EventBusHandle::is_outbox`](oauth2_events::EventBusHandle::is_outbox)); other events
+//! are queued just after their change. Delivery is at least once: a relay that crashes
+//! after publishing, or a backend that fails after another accepted the event, leads to
+//! redelivery, so consumers deduplicate by event id.
+
+use async_trait::async_trait;
+use chrono::Utc;
+use std::time::Duration;
+
+use oauth2_config::OutboxConfig;
+use oauth2_core::OAuth2Error;
+use oauth2_events::{DynEventBus, EventBus, EventBusError, EventEnvelope};
+use oauth2_ports::DynStorage;
+
+pub use oauth2_ports::outbox_message;
+
+/// An [`EventBus`] that queues events in storage for an [`OutboxRelay`] to publish.
+pub struct StorageOutbox {
+    storage: DynStorage,
+}
+
+impl StorageOutbox {
+    pub fn new(storage: DynStorage) -> Self {
+        Self { storage }
+    }
+}
+
+#[async_trait]
+impl EventBus for StorageOutbox {
+    async fn publish(&self, envelope: EventEnvelope) -> Result<(), EventBusError> {
+        let message = outbox_message(&envelope).map_err(|e| EventBusError::Other(e.to_string()))?;
+        self.storage
+            .enqueue_outbox_message(&message)
+            .await
+            .map_err(|e| EventBusError::Other(format!("queueing event: {e}")))
+    }
+}
+
+/// Publishes queued events to `bus`, which must report failures (e.g.
+/// [`ActixEventBus::acknowledged`](oauth2_events::ActixEventBus::acknowledged)).
+///
+/// Envelopes are published as queued, so ones signed when they were produced keep their
+/// signature. Replicas sharing storage can each run a relay: claimed events are leased,
+/// and a lease that runs out (the relay died) makes the event due again.
+pub struct OutboxRelay {
+    storage: DynStorage,
+    bus: DynEventBus,
+    batch_size: u32,
+    lease: Duration,
+    max_backoff: Duration,
+}
+
+impl OutboxRelay {
+    pub fn new(storage: DynStorage, bus: DynEventBus, config: &OutboxConfig) -> Self {
+        Self {
+            storage,
+            bus,
+            batch_size: config.batch_size.max(1),
+            lease: Duration::from_secs(config.lease_secs.max(1)),
+            max_backoff: Duration::from_secs(config.max_backoff_secs.max(1)),
+        }
+    }
+
+    /// Publish one batch of due events, returning how many were published. Failed events
+    /// are retried after a delay that doubles with each attempt.
+    pub async fn relay_due(&self) -> Result<usize, OAuth2Error> {
+        let now = Utc::now();
+        let messages = self
+            .storage
+            .claim_outbox_messages(now, now + self.lease, self.batch_size)
+            .await?;
+
+        let mut published = 0;
+        for message in messages {
+            let result = match serde_json::from_str::<EventEnvelope>(&message.payload) {
+                Ok(envelope) => self.bus.publish(envelope).await.map_err(|e| e.to_string()),
+                Err(e) => Err(format!("invalid payload: {e}")),
+            };
+            match result {
+                Ok(()) => {
+                    self.storage.delete_outbox_message(&message.id).await?;
+                    published += 1;
+                }
+                Err(error) => {
+                    let delay = backoff(message.attempts, self.max_backoff);
+                    tracing::warn!(
+                        event_id = %message.id,
+                        event_type = %message.event_type,
+                        attempts = message.attempts + 1,
+                        retry_in_secs = delay.as_secs(),
+                        error = %error,
+                        "Event publish failed; will retry"
+                    );
+                    self.storage
+                        .reschedule_outbox_message(&message.id, Utc::now() + delay, &error)
+                        .await?;
+                }
+            }
+        }
+        Ok(published)
+    }
+
+    /// Relay due events every `poll_interval` in the background. Must be called from
+    /// within an actix system.
+    pub fn start(self, poll_interval: Duration) {
+        actix::spawn(async move {
+            let mut interval = actix::clock::interval(poll_interval);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.relay_due().await {
+                    tracing::error!(error = %e, "Event outbox relay failed");
+                }
+            }
+        });
+    }
+}
+
+/// Delay before retrying an event that has now failed `attempts + 1` times: one second,
+/// doubling per attempt, up to `max`.
+fn backoff(attempts: i32, max: Duration) -> Duration {
+    let exponent = u32::try_from(attempts).unwrap_or(0).min(63);
+    Duration::from_secs(1u64.checked_shl(exponent).unwrap_or(u64::MAX)).min(max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let max = Duration::from_secs(300);
+        let delays: Vec<u64> = [0, 1, 2, 8, 9, 40]
+            .into_iter()
+            .map(|attempts| backoff(attempts, max).as_secs())
+            .collect();
+        assert_eq!(delays, [1, 2, 4, 256, 300, 300]);
+    }
+}
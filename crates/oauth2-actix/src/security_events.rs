@@ -0,0 +1,53 @@
+//! Security events for SIEMs: failed client authentication, abuse of the authorization
+//! code flow and exhausted quotas, tagged with where the request came from.
+
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+use std::convert::Infallible;
+use std::future::{ready, Ready};
+use std::ops::Deref;
+
+use oauth2_core::TrustedProxies;
+use oauth2_events::EventBusHandle;
+
+/// [`oauth2_ports::SecurityEvents`] for the actix request being handled.
+///
+/// Extract it in a handler, or build it with [`SecurityEvents::for_request`]. Events are
+/// dropped when the application registered no [`EventBusHandle`].
+#[derive(Clone, Default)]
+pub struct SecurityEvents(oauth2_ports::SecurityEvents);
+
+impl SecurityEvents {
+    pub fn for_request(req: &HttpRequest) -> Self {
+        Self(oauth2_ports::SecurityEvents::new(
+            req.app_data::<web::Data<EventBusHandle>>()
+                .map(|bus| bus.get_ref().clone()),
+            TrustedProxies::client_ip_of(req).map(|ip| ip.to_string()),
+            req.headers()
+                .get(actix_web::http::header::USER_AGENT)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string),
+        ))
+    }
+
+    /// The framework-free events, for handing to the service layer.
+    pub fn into_inner(self) -> oauth2_ports::SecurityEvents {
+        self.0
+    }
+}
+
+impl Deref for SecurityEvents {
+    type Target = oauth2_ports::SecurityEvents;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl FromRequest for SecurityEvents {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Ok(Self::for_request(req)))
+    }
+}
@@ -0,0 +1,371 @@
+//! Server-side browser sessions for `actix-session`.
+//!
+//! [`ServerSessionStore`] keeps session state in a [`UserSessionStore`]; the cookie only
+//! carries a random key. Sessions end after the middleware's state TTL without requests
+//! (the idle timeout) or a fixed time after they started (the absolute timeout).
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use actix_session::storage::{LoadError, SaveError, SessionKey, SessionStore, UpdateError};
+use actix_web::cookie::time::Duration;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use oauth2_core::{generate_session_key, hash_session_key, OAuth2Error, UserSession};
+use oauth2_ports::{DynStorage, DynUserSessionStore, UserSessionStore};
+
+/// Session entry holding the current session's id, for handlers that list or revoke
+/// sessions. Added when a session is loaded and never stored.
+pub const SESSION_ID_KEY: &str = "_session_id";
+
+/// Session entry naming the signed-in user, as a JSON string.
+const USER_ID_KEY: &str = "user_id";
+
+/// Keeps sessions in the server's storage (the `user_sessions` table), shared by every
+/// replica using the same database. The default.
+pub struct StorageSessionStore {
+    storage: DynStorage,
+}
+
+impl StorageSessionStore {
+    pub fn new(storage: DynStorage) -> Self {
+        Self { storage }
+    }
+}
+
+#[async_trait]
+impl UserSessionStore for StorageSessionStore {
+    fn backend_name(&self) -> &'static str {
+        "database"
+    }
+
+    async fn save(&self, session: &UserSession) -> Result<(), OAuth2Error> {
+        self.storage.save_user_session(session).await
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<UserSession>, OAuth2Error> {
+        self.storage.get_user_session(id).await
+    }
+
+    async fn touch(
+        &self,
+        id: &str,
+        last_seen_at: DateTime<Utc>,
+        idle_expires_at: DateTime<Utc>,
+    ) -> Result<(), OAuth2Error> {
+        self.storage
+            .touch_user_session(id, last_seen_at, idle_expires_at)
+            .await
+    }
+
+    async fn list_for_user(&self, user_id: &str) -> Result<Vec<UserSession>, OAuth2Error> {
+        self.storage.list_user_sessions(user_id).await
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), OAuth2Error> {
+        self.storage.delete_user_session(id).await
+    }
+}
+
+/// Keeps sessions in process memory. Only for a single replica; everyone is signed out
+/// on restart.
+#[derive(Default)]
+pub struct InMemorySessionStore {
+    sessions: Mutex<HashMap<String, UserSession>>,
+}
+
+impl InMemorySessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl UserSessionStore for InMemorySessionStore {
+    fn backend_name(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn save(&self, session: &UserSession) -> Result<(), OAuth2Error> {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        // Abandoned sessions are never deleted by their browser; drop them here.
+        sessions.retain(|_, existing| !existing.is_expired());
+        sessions.insert(session.id.clone(), session.clone());
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<UserSession>, OAuth2Error> {
+        Ok(self
+            .sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(id)
+            .cloned())
+    }
+
+    async fn touch(
+        &self,
+        id: &str,
+        last_seen_at: DateTime<Utc>,
+        idle_expires_at: DateTime<Utc>,
+    ) -> Result<(), OAuth2Error> {
+        if let Some(session) = self
+            .sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_mut(id)
+        {
+            session.last_seen_at = last_seen_at;
+            session.idle_expires_at = idle_expires_at;
+        }
+        Ok(())
+    }
+
+    async fn list_for_user(&self, user_id: &str) -> Result<Vec<UserSession>, OAuth2Error> {
+        let mut sessions: Vec<UserSession> = self
+            .sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .filter(|session| session.user_id.as_deref() == Some(user_id))
+            .filter(|session| !session.is_expired())
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.created_at));
+        Ok(sessions)
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), OAuth2Error> {
+        self.sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(id);
+        Ok(())
+    }
+}
+
+/// `actix-session` backend over a [`UserSessionStore`].
+///
+/// The middleware's state TTL is the idle timeout; use it with
+/// `TtlExtensionPolicy::OnEveryRequest` so every request moves the timeout forward.
+#[derive(Clone)]
+pub struct ServerSessionStore {
+    store: DynUserSessionStore,
+    absolute_timeout: chrono::Duration,
+}
+
+impl ServerSessionStore {
+    pub fn new(store: DynUserSessionStore, absolute_timeout: std::time::Duration) -> Self {
+        Self {
+            store,
+            absolute_timeout: chrono::Duration::from_std(absolute_timeout)
+                .unwrap_or(chrono::Duration::MAX),
+        }
+    }
+
+    pub fn store(&self) -> &DynUserSessionStore {
+        &self.store
+    }
+
+    /// The unexpired session for a cookie key.
+    async fn live_session(&self, key: &SessionKey) -> Result<Option<UserSession>, OAuth2Error> {
+        let id = hash_session_key(key.as_ref());
+        match self.store.get(&id).await? {
+            Some(session) if session.is_expired() => {
+                self.store.delete(&id).await?;
+                Ok(None)
+            }
+            session => Ok(session),
+        }
+    }
+
+    async fn create(
+        &self,
+        mut state: HashMap<String, String>,
+        ttl: &Duration,
+    ) -> Result<SessionKey, anyhow::Error> {
+        let key = generate_session_key();
+        let now = Utc::now();
+        state.remove(SESSION_ID_KEY);
+        let session = UserSession {
+            id: hash_session_key(&key),
+            user_id: signed_in_user(&state),
+            state: serde_json::to_string(&state)?,
+            created_at: now,
+            last_seen_at: now,
+            idle_expires_at: now + idle_timeout(ttl),
+            expires_at: now + self.absolute_timeout,
+        };
+        self.store.save(&session).await.map_err(store_error)?;
+        Ok(SessionKey::try_from(key)?)
+    }
+}
+
+impl SessionStore for ServerSessionStore {
+    async fn load(
+        &self,
+        session_key: &SessionKey,
+    ) -> Result<Option<HashMap<String, String>>, LoadError> {
+        let Some(session) = self
+            .live_session(session_key)
+            .await
+            .map_err(|e| LoadError::Other(store_error(e)))?
+        else {
+            return Ok(None);
+        };
+        let mut state: HashMap<String, String> = serde_json::from_str(&session.state)
+            .map_err(|e| LoadError::Deserialization(e.into()))?;
+        state.insert(
+            SESSION_ID_KEY.to_string(),
+            serde_json::to_string(&session.id).map_err(|e| LoadError::Other(e.into()))?,
+        );
+        Ok(Some(state))
+    }
+
+    async fn save(
+        &self,
+        session_state: HashMap<String, String>,
+        ttl: &Duration,
+    ) -> Result<SessionKey, SaveError> {
+        self.create(session_state, ttl)
+            .await
+            .map_err(SaveError::Other)
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        mut session_state: HashMap<String, String>,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        let existing = self
+            .live_session(&session_key)
+            .await
+            .map_err(|e| UpdateError::Other(store_error(e)))?;
+        // Ended meanwhile (expired or revoked): start a new session instead.
+        let Some(mut session) = existing else {
+            return self
+                .create(session_state, ttl)
+                .await
+                .map_err(UpdateError::Other);
+        };
+
+        session_state.remove(SESSION_ID_KEY);
+        let now = Utc::now();
+        session.user_id = signed_in_user(&session_state);
+        session.state = serde_json::to_string(&session_state)
+            .map_err(|e| UpdateError::Serialization(e.into()))?;
+        session.last_seen_at = now;
+        session.idle_expires_at = now + idle_timeout(ttl);
+        self.store
+            .save(&session)
+            .await
+            .map_err(|e| UpdateError::Other(store_error(e)))?;
+        Ok(session_key)
+    }
+
+    async fn update_ttl(&self, session_key: &SessionKey, ttl: &Duration) -> anyhow::Result<()> {
+        let now = Utc::now();
+        self.store
+            .touch(
+                &hash_session_key(session_key.as_ref()),
+                now,
+                now + idle_timeout(ttl),
+            )
+            .await
+            .map_err(store_error)
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> anyhow::Result<()> {
+        self.store
+            .delete(&hash_session_key(session_key.as_ref()))
+            .await
+            .map_err(store_error)
+    }
+}
+
+/// The user a session belongs to, from its JSON-encoded `user_id` entry.
+fn signed_in_user(state: &HashMap<String, String>) -> Option<String> {
+    state
+        .get(USER_ID_KEY)
+        .and_then(|value| serde_json::from_str(value).ok())
+}
+
+fn idle_timeout(ttl: &Duration) -> chrono::Duration {
+    chrono::Duration::try_seconds(ttl.whole_seconds()).unwrap_or(chrono::Duration::MAX)
+}
+
+fn store_error(e: OAuth2Error) -> anyhow::Error {
+    anyhow::anyhow!("session store: {e}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn state(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), serde_json::to_string(value).unwrap()))
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn sessions_are_stored_under_the_key_hash_and_end_at_the_absolute_timeout() {
+        let store = Arc::new(InMemorySessionStore::new());
+        let sessions = ServerSessionStore::new(store.clone(), std::time::Duration::from_secs(60));
+        let ttl = Duration::minutes(30);
+
+        let key = SessionStore::save(&sessions, state(&[("user_id", "alice")]), &ttl)
+            .await
+            .unwrap();
+        let id = hash_session_key(key.as_ref());
+        let stored = store.get(&id).await.unwrap().unwrap();
+        assert_eq!(stored.user_id.as_deref(), Some("alice"));
+        assert_eq!(
+            stored.expires_at - stored.created_at,
+            chrono::Duration::seconds(60)
+        );
+        assert!(!stored.state.contains(&id));
+
+        let loaded = sessions.load(&key).await.unwrap().unwrap();
+        assert_eq!(loaded[SESSION_ID_KEY], serde_json::to_string(&id).unwrap());
+
+        let mut ended = stored.clone();
+        ended.expires_at = Utc::now() - chrono::Duration::seconds(1);
+        store.save(&ended).await.unwrap();
+        assert!(sessions.load(&key).await.unwrap().is_none());
+        assert!(store.get(&id).await.unwrap().is_none());
+    }
+
+    #[actix_web::test]
+    async fn updating_an_ended_session_starts_a_new_one() {
+        let store = Arc::new(InMemorySessionStore::new());
+        let sessions = ServerSessionStore::new(store.clone(), std::time::Duration::from_secs(60));
+        let ttl = Duration::minutes(30);
+
+        let key = SessionStore::save(&sessions, state(&[("theme", "dark")]), &ttl)
+            .await
+            .unwrap();
+        let id = hash_session_key(key.as_ref());
+        let mut signed_in = sessions.load(&key).await.unwrap().unwrap();
+        signed_in.insert("user_id".to_string(), "\"alice\"".to_string());
+        let same = sessions
+            .update(
+                SessionKey::try_from(key.as_ref().to_string()).unwrap(),
+                signed_in.clone(),
+                &ttl,
+            )
+            .await
+            .unwrap();
+        assert_eq!(same, key);
+        assert_eq!(store.list_for_user("alice").await.unwrap()[0].id, id);
+
+        SessionStore::delete(&sessions, &key).await.unwrap();
+        let renewed = sessions.update(key, signed_in, &ttl).await.unwrap();
+        assert_ne!(hash_session_key(renewed.as_ref()), id);
+        assert!(store.get(&id).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,78 @@
+//! Request extractors for handlers hosted alongside the OAuth2 endpoints.
+
+use axum::extract::FromRequestParts;
+use axum::http::{header, request::Parts};
+
+use crate::OAuth2State;
+use oauth2_core::{OAuth2Error, Token};
+
+/// The authenticated end user an authorization request is made for.
+///
+/// The host application's login layer inserts this into the request extensions before
+/// `/oauth/authorize` runs; the authorize handler never authenticates users itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceOwner(pub String);
+
+/// The id of the [`oauth2_core::UserSession`] the request was made from, placed in the
+/// request extensions by the host application's login layer alongside [`ResourceOwner`].
+/// `/auth/sessions` uses it to mark the current session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CurrentSession(pub String);
+
+/// The raw token from an `Authorization: Bearer` header (RFC 6750 section 2.1).
+#[derive(Debug, Clone)]
+pub struct BearerToken(pub String);
+
+impl<S: Send + Sync> FromRequestParts<S> for BearerToken {
+    type Rejection = OAuth2Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .map(|token| Self(token.to_string()))
+            .ok_or_else(OAuth2Error::missing_token)
+    }
+}
+
+/// A bearer token that is known, unexpired and unrevoked.
+///
+/// Reuses the token validated by [`crate::middleware::require_token`] when that layer ran.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedToken(pub Token);
+
+impl FromRequestParts<OAuth2State> for AuthenticatedToken {
+    type Rejection = OAuth2Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &OAuth2State,
+    ) -> Result<Self, Self::Rejection> {
+        if let Some(token) = parts.extensions.get::<Token>() {
+            return Ok(Self(token.clone()));
+        }
+        let BearerToken(raw) = BearerToken::from_request_parts(parts, state).await?;
+        let token = state
+            .validate_token(&raw)
+            .await
+            .map_err(|_| OAuth2Error::invalid_token("Token is invalid, expired or revoked"))?;
+        Ok(Self(token))
+    }
+}
+
+impl AuthenticatedToken {
+    /// Fail with `insufficient_scope` unless the token was granted `scope`.
+    pub fn require_scope(&self, scope: &str) -> Result<(), OAuth2Error> {
+        if self.0.scope.split_whitespace().any(|s| s == scope) {
+            Ok(())
+        } else {
+            Err(OAuth2Error::insufficient_scope(&format!(
+                "Token lacks the '{scope}' scope"
+            )))
+        }
+    }
+}
@@ -0,0 +1,114 @@
+pub mod oauth;
+pub mod sessions;
+pub mod token;
+pub mod wellknown;
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use axum::extract::ConnectInfo;
+use axum::http::{header, request::Parts, HeaderValue};
+use axum::response::Response;
+
+use oauth2_core::{
+    AuditRecord, ContextBinding, IssuerUrls, OAuth2Error, RequestOrigin, TenantContext,
+};
+use oauth2_ports::SecurityEvents;
+
+use crate::OAuth2State;
+
+/// Decode query or form parameters, rejecting duplicates (prevents parser differentials).
+pub(crate) fn parse_params(input: &[u8]) -> Result<HashMap<String, String>, OAuth2Error> {
+    let mut params = HashMap::new();
+    for (name, value) in url::form_urlencoded::parse(input) {
+        if params
+            .insert(name.into_owned(), value.into_owned())
+            .is_some()
+        {
+            return Err(OAuth2Error::invalid_request(
+                "Duplicate parameters are not allowed",
+            ));
+        }
+    }
+    Ok(params)
+}
+
+/// Where the request was addressed, for deriving absolute URLs when no issuer is set.
+pub(crate) fn request_origin(parts: &Parts) -> RequestOrigin<'_> {
+    let header = |name: &str| parts.headers.get(name).and_then(|v| v.to_str().ok());
+    RequestOrigin {
+        scheme: parts.uri.scheme_str().unwrap_or("http"),
+        peer: parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip()),
+        host: header("host").or_else(|| parts.uri.authority().map(|a| a.as_str())),
+        forwarded: header("forwarded"),
+        x_forwarded_proto: header("x-forwarded-proto"),
+        x_forwarded_host: header("x-forwarded-host"),
+    }
+}
+
+/// Context of the calling user agent, for authorization code binding. The peer address
+/// is only known when the app is served with `into_make_service_with_connect_info`.
+pub(crate) fn request_context(parts: &Parts) -> ContextBinding {
+    let user_agent = parts
+        .headers
+        .get(header::USER_AGENT)
+        .and_then(|v| v.to_str().ok());
+    let ip = parts
+        .extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string());
+    ContextBinding::new(user_agent, ip.as_deref())
+}
+
+/// The tenant resolved by [`crate::middleware::resolve_tenant`] for `/t/{tenant}` routes.
+pub(crate) fn request_tenant(parts: &Parts) -> Option<TenantContext> {
+    parts.extensions.get::<TenantContext>().cloned()
+}
+
+/// Issuer URLs for the request's tenant.
+pub(crate) fn tenant_urls(state: &OAuth2State, tenant: Option<&TenantContext>) -> IssuerUrls {
+    state
+        .issuer_urls
+        .clone()
+        .for_tenant(oauth2_core::tenant_id(tenant))
+}
+
+/// Peer address of the caller; only known when the app is served with
+/// `into_make_service_with_connect_info`.
+fn peer_ip(parts: &Parts) -> Option<String> {
+    parts
+        .extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+}
+
+fn user_agent(parts: &Parts) -> Option<String> {
+    parts
+        .headers
+        .get(header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+/// `record` tagged with the caller's address and user agent.
+pub(crate) fn audit_record(mut record: AuditRecord, parts: &Parts) -> AuditRecord {
+    record.ip_address = peer_ip(parts);
+    record.user_agent = user_agent(parts);
+    record
+}
+
+/// Security events tagged with the calling user agent and its peer address.
+pub(crate) fn security_events(state: &OAuth2State, parts: &Parts) -> SecurityEvents {
+    SecurityEvents::new(state.event_bus.clone(), peer_ip(parts), user_agent(parts))
+}
+
+/// Token responses must never be cached (RFC 6749 section 5.1).
+pub(crate) fn no_store(mut response: Response) -> Response {
+    let headers = response.headers_mut();
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    headers.insert(header::PRAGMA, HeaderValue::from_static("no-cache"));
+    response
+}
@@ -0,0 +1,437 @@
+use std::collections::HashMap;
+
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{header, request::Parts, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use url::Url;
+
+use super::{
+    audit_record, no_store, parse_params, request_context, request_origin, request_tenant,
+    security_events, tenant_urls,
+};
+use crate::extract::ResourceOwner;
+use crate::OAuth2State;
+use oauth2_core::{
+    is_silent_prompt, tenant_id, AuditAction, AuditRecord, Client, GrantType, OAuth2Error,
+    ResponseType, Token, TokenMetadata, TokenResponse,
+};
+use oauth2_events::EventType;
+use oauth2_ports::{
+    record_audit, validate_scope_subset, ClientCredentials, CodeRedemption, IssueToken,
+    NewAuthorizationCode, RefreshTokenRequest, SecurityEvents,
+};
+
+/// Redirect the user agent back to the client with `params`, `state` (if any) and `iss`
+/// (RFC 9207) appended to the already-verified redirect URI.
+fn authorization_redirect(
+    mut redirect_uri: Url,
+    params: &[(&str, &str)],
+    state: Option<&str>,
+    issuer: &str,
+) -> Response {
+    {
+        let mut qp = redirect_uri.query_pairs_mut();
+        for (name, value) in params {
+            qp.append_pair(name, value);
+        }
+        if let Some(state) = state {
+            qp.append_pair("state", state);
+        }
+        qp.append_pair("iss", issuer);
+    }
+
+    let mut response = StatusCode::FOUND.into_response();
+    let headers = response.headers_mut();
+    if let Ok(location) = HeaderValue::from_str(redirect_uri.as_str()) {
+        headers.insert(header::LOCATION, location);
+    }
+    // Same clickjacking/referrer protections as the actix endpoint.
+    headers.insert(
+        header::REFERRER_POLICY,
+        HeaderValue::from_static("no-referrer"),
+    );
+    headers.insert(header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+    headers.insert(
+        header::CONTENT_SECURITY_POLICY,
+        HeaderValue::from_static("frame-ancestors 'none'"),
+    );
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+    no_store(response)
+}
+
+/// RFC 6749 section 4.1.2.1 error response. Internal error details stay in the logs.
+fn authorization_error_redirect(
+    redirect_uri: Url,
+    error: &OAuth2Error,
+    state: Option<&str>,
+    issuer: &str,
+) -> Response {
+    let description = match error.error.as_str() {
+        "server_error" => Some("The authorization server encountered an unexpected error"),
+        _ => error.error_description.as_deref(),
+    };
+    let mut params = vec![("error", error.error.as_str())];
+    if let Some(description) = description {
+        params.push(("error_description", description));
+    }
+    authorization_redirect(redirect_uri, &params, state, issuer)
+}
+
+/// OAuth2 authorize endpoint
+/// Initiates the authorization code flow for the [`ResourceOwner`] in the request
+/// extensions; without one the client receives `access_denied`, or `login_required` for
+/// `prompt=none`. Consent and account checks for silent requests (`consent_required`,
+/// `interaction_required`) are left to the host's login layer.
+///
+/// Until the client and its redirect URI are verified, errors are returned to the user
+/// agent as JSON; redirecting to an unverified URI would make this an open redirector.
+pub async fn authorize(
+    State(state): State<OAuth2State>,
+    parts: Parts,
+) -> Result<Response, OAuth2Error> {
+    let query = parse_params(parts.uri.query().unwrap_or_default().as_bytes())?;
+    let client_id = query
+        .get("client_id")
+        .ok_or_else(|| OAuth2Error::invalid_request("Missing client_id"))?;
+    let requested_redirect_uri = query
+        .get("redirect_uri")
+        .ok_or_else(|| OAuth2Error::invalid_request("Missing redirect_uri"))?;
+
+    let tenant = request_tenant(&parts);
+    let client = state
+        .clients
+        .get(client_id, tenant_id(tenant.as_ref()))
+        .await?;
+    if !client.validate_redirect_uri(requested_redirect_uri) {
+        return Err(OAuth2Error::invalid_request("Invalid redirect_uri"));
+    }
+    let redirect_uri = Url::parse(requested_redirect_uri)
+        .map_err(|_| OAuth2Error::invalid_request("Invalid redirect_uri"))?;
+    if redirect_uri.fragment().is_some() {
+        return Err(OAuth2Error::invalid_request(
+            "redirect_uri must not contain a fragment",
+        ));
+    }
+
+    // Must match the `issuer` advertised by discovery.
+    let issuer = tenant_urls(&state, tenant.as_ref()).base_url(&request_origin(&parts));
+    let client_state = query.get("state").map(String::as_str);
+
+    let issued = issue_authorization_code(&state, &parts, &query, &client).await;
+    let mut record = AuditRecord::from_result(AuditAction::Consent, &issued)
+        .with_client(client.client_id.clone());
+    if let Some(ResourceOwner(user_id)) = parts.extensions.get::<ResourceOwner>() {
+        record = record.with_actor(user_id.clone());
+    }
+    record_audit(state.audit.as_ref(), audit_record(record, &parts)).await;
+
+    match issued {
+        Ok(code) => {
+            state.metrics.oauth_authorization_codes_issued.inc();
+            Ok(authorization_redirect(
+                redirect_uri,
+                &[("code", &code)],
+                client_state,
+                &issuer,
+            ))
+        }
+        Err(error) => {
+            tracing::debug!(
+                client_id = %client.client_id,
+                error = %error,
+                "Authorization request rejected; redirecting to client"
+            );
+            Ok(authorization_error_redirect(
+                redirect_uri,
+                &error,
+                client_state,
+                &issuer,
+            ))
+        }
+    }
+}
+
+/// Validate the rest of an authorization request for a verified client and issue a code.
+async fn issue_authorization_code(
+    state: &OAuth2State,
+    parts: &Parts,
+    query: &HashMap<String, String>,
+    client: &Client,
+) -> Result<String, OAuth2Error> {
+    let silent = is_silent_prompt(query.get("prompt").map(String::as_str))?;
+    let response_type: ResponseType = query
+        .get("response_type")
+        .ok_or_else(|| OAuth2Error::invalid_request("Missing response_type"))?
+        .parse()?;
+
+    if !client.supports_grant_type(response_type.grant_type()) {
+        return Err(OAuth2Error::unauthorized_client(
+            "Client is not allowed to use authorization_code",
+        ));
+    }
+
+    // Require PKCE (S256 only), per the OAuth 2.0 Security BCP.
+    let code_challenge = query
+        .get("code_challenge")
+        .ok_or_else(|| OAuth2Error::invalid_request("Missing code_challenge"))?;
+    let code_challenge_method = query
+        .get("code_challenge_method")
+        .ok_or_else(|| OAuth2Error::invalid_request("Missing code_challenge_method"))?;
+    if code_challenge_method != "S256" {
+        return Err(OAuth2Error::invalid_request(
+            "Only S256 code_challenge_method is supported",
+        ));
+    }
+    if code_challenge.trim().is_empty() {
+        return Err(OAuth2Error::invalid_request(
+            "code_challenge must not be empty",
+        ));
+    }
+
+    let ResourceOwner(user_id) = parts
+        .extensions
+        .get::<ResourceOwner>()
+        .cloned()
+        .ok_or_else(|| {
+            if silent {
+                OAuth2Error::login_required("No authenticated resource owner")
+            } else {
+                OAuth2Error::access_denied("No authenticated resource owner")
+            }
+        })?;
+
+    let scope = query.get("scope").map(String::as_str).unwrap_or("read");
+    validate_scope_subset(scope, &client.scope)?;
+
+    let auth_code = state
+        .auth
+        .create_authorization_code(NewAuthorizationCode {
+            client_id: client.client_id.clone(),
+            user_id,
+            redirect_uri: query["redirect_uri"].clone(),
+            scope: scope.to_string(),
+            code_challenge: Some(code_challenge.clone()),
+            code_challenge_method: Some("S256".to_string()),
+            context: request_context(parts),
+            // The client was looked up in the request's tenant.
+            tenant_id: client.tenant_id.clone(),
+        })
+        .await?;
+    Ok(auth_code.code)
+}
+
+/// OAuth2 token endpoint
+/// Issues tokens for the grants enabled with [`OAuth2State::with_grants`]: authorization
+/// code and client credentials by default, password and refresh_token when enabled.
+pub async fn token(
+    State(state): State<OAuth2State>,
+    parts: Parts,
+    body: Bytes,
+) -> Result<Response, OAuth2Error> {
+    parse_params(parts.uri.query().unwrap_or_default().as_bytes())?;
+    let form = parse_params(&body)?;
+
+    let grant_type = form
+        .get("grant_type")
+        .ok_or_else(|| OAuth2Error::invalid_request("Missing grant_type"))?
+        .clone();
+    let client_id = form
+        .get("client_id")
+        .ok_or_else(|| OAuth2Error::invalid_request("Missing client_id"))?
+        .clone();
+    let security = security_events(&state, &parts);
+
+    let issued = issue_token(&state, &parts, &form, &grant_type, &security).await;
+
+    // Unknown clients, wrong secrets and rejected assertions, from any grant.
+    if let Err(error) = &issued {
+        if error.error == "invalid_client" {
+            security.publish(
+                EventType::ClientAuthenticationFailed,
+                Some(&client_id),
+                None,
+                &[
+                    ("grant_type", grant_type.as_str()),
+                    (
+                        "reason",
+                        error.error_description.as_deref().unwrap_or_default(),
+                    ),
+                ],
+            );
+        }
+    }
+
+    let mut record = AuditRecord::from_result(AuditAction::TokenIssued, &issued)
+        .with_client(client_id.clone())
+        .with_target(grant_type);
+    if let Ok(token) = &issued {
+        // Client-only grants act on the client's own behalf.
+        record = record.with_actor(token.user_id.clone().unwrap_or(client_id));
+    }
+    record_audit(state.audit.as_ref(), audit_record(record, &parts)).await;
+    let token = issued?;
+
+    state.metrics.oauth_token_issued_total.inc();
+    Ok(no_store(Json(TokenResponse::from(token)).into_response()))
+}
+
+/// Issue a token through the grant named by `grant_type`.
+async fn issue_token(
+    state: &OAuth2State,
+    parts: &Parts,
+    form: &HashMap<String, String>,
+    grant_type: &str,
+    security: &SecurityEvents,
+) -> Result<Token, OAuth2Error> {
+    let grant_type: GrantType = grant_type.parse()?;
+    // Password and refresh_token are off unless enabled (OAuth 2.0 Security BCP).
+    if !state.grants.is_enabled(grant_type) {
+        return Err(OAuth2Error::unsupported_grant_type("Grant type disabled"));
+    }
+    let metadata = form
+        .get("metadata")
+        .map(|json| TokenMetadata::from_json(json))
+        .transpose()?
+        .unwrap_or_default();
+    let param = |name: &str| form.get(name).cloned();
+    let tenant = request_tenant(parts);
+
+    let client = state
+        .clients
+        .get(&form["client_id"], tenant_id(tenant.as_ref()))
+        .await?;
+    if !client.supports_grant_type(grant_type) {
+        return Err(OAuth2Error::unauthorized_client(&format!(
+            "Client is not allowed to use {grant_type}"
+        )));
+    }
+    // Refresh tokens go to user grants of clients registered for them.
+    let include_refresh =
+        state.grants.refresh_token && client.supports_grant_type(GrantType::RefreshToken);
+
+    match grant_type {
+        GrantType::ClientCredentials => {
+            // Client assertions must be addressed to this endpoint's public URL.
+            let token_endpoint =
+                tenant_urls(state, tenant.as_ref()).token_endpoint(&request_origin(parts));
+            let credentials = ClientCredentials {
+                client_secret: param("client_secret"),
+                client_assertion_type: param("client_assertion_type"),
+                client_assertion: param("client_assertion"),
+                scope: param("scope"),
+            };
+            state
+                .issue_client_credentials(&client, credentials, metadata, &token_endpoint, tenant)
+                .await
+        }
+        GrantType::AuthorizationCode => {
+            let code = param("code").ok_or_else(|| OAuth2Error::invalid_request("Missing code"))?;
+            if param("redirect_uri").as_deref() == Some("") {
+                return Err(OAuth2Error::invalid_request(
+                    "redirect_uri must not be empty",
+                ));
+            }
+            let auth_code = state
+                .auth
+                .validate_authorization_code(
+                    CodeRedemption {
+                        code: code.clone(),
+                        client_id: client.client_id.clone(),
+                        redirect_uri: param("redirect_uri"),
+                        code_verifier: param("code_verifier"),
+                        context: request_context(parts),
+                        tenant_id: tenant_id(tenant.as_ref()).map(str::to_string),
+                    },
+                    security,
+                )
+                .await?;
+
+            // Public clients have no secret; PKCE, required at /oauth/authorize, binds the
+            // code.
+            state
+                .clients
+                .authenticate_secret(&client, form.get("client_secret").map(String::as_str))
+                .await?;
+
+            // Only burn the code once the client is authenticated, so invalid_client
+            // errors cannot exhaust valid codes. It is burned with the token save, so
+            // concurrent exchanges cannot both succeed.
+            state
+                .tokens
+                .issue(IssueToken {
+                    include_refresh,
+                    metadata,
+                    consume_code: Some(code),
+                    tenant,
+                    context: Some(request_context(parts)),
+                    ..IssueToken::new(
+                        grant_type.as_str(),
+                        auth_code.client_id,
+                        Some(auth_code.user_id),
+                        auth_code.scope,
+                    )
+                })
+                .await
+        }
+        GrantType::Password => {
+            let (Some(username), Some(password)) = (param("username"), param("password")) else {
+                return Err(OAuth2Error::invalid_request(
+                    "username and password are required",
+                ));
+            };
+            state
+                .clients
+                .authenticate_secret(&client, form.get("client_secret").map(String::as_str))
+                .await?;
+
+            let scope = param("scope").unwrap_or_else(|| client.scope.clone());
+            validate_scope_subset(&scope, &client.scope)?;
+
+            let user = state
+                .auth
+                .authenticate_user(
+                    &username,
+                    &password,
+                    &client.client_id,
+                    tenant_id(tenant.as_ref()),
+                )
+                .await?;
+            state
+                .tokens
+                .issue(IssueToken {
+                    include_refresh,
+                    metadata,
+                    tenant,
+                    context: Some(request_context(parts)),
+                    ..IssueToken::new(grant_type.as_str(), client.client_id, Some(user.id), scope)
+                })
+                .await
+        }
+        GrantType::RefreshToken => {
+            let refresh_token = param("refresh_token")
+                .ok_or_else(|| OAuth2Error::invalid_request("Missing refresh_token"))?;
+            state
+                .clients
+                .authenticate_secret(&client, form.get("client_secret").map(String::as_str))
+                .await?;
+
+            // Rotated: the presented refresh token is revoked in the transaction that
+            // saves its replacement.
+            state
+                .tokens
+                .refresh(RefreshTokenRequest {
+                    refresh_token,
+                    client_id: client.client_id,
+                    scope: param("scope"),
+                    tenant,
+                    context: request_context(parts),
+                })
+                .await
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use axum::extract::{Path, State};
+use axum::http::{request::Parts, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+use super::no_store;
+use crate::extract::{CurrentSession, ResourceOwner};
+use crate::OAuth2State;
+use oauth2_core::{OAuth2Error, SessionInfo, SessionList};
+use oauth2_ports::DynUserSessionStore;
+
+/// The signed-in user, the id of the current session and the session store.
+fn signed_in(
+    state: &OAuth2State,
+    parts: &Parts,
+) -> Result<(String, Option<String>, DynUserSessionStore), OAuth2Error> {
+    let store = state
+        .sessions
+        .clone()
+        .ok_or_else(|| OAuth2Error::not_found("Sessions are not enabled"))?;
+    let ResourceOwner(user_id) = parts
+        .extensions
+        .get::<ResourceOwner>()
+        .cloned()
+        .ok_or_else(|| OAuth2Error::login_required("Sign in to manage your sessions"))?;
+    let current = parts
+        .extensions
+        .get::<CurrentSession>()
+        .map(|CurrentSession(id)| id.clone());
+    Ok((user_id, current, store))
+}
+
+/// `GET /auth/sessions`: the [`ResourceOwner`]'s active sessions, newest first.
+pub async fn list_sessions(
+    State(state): State<OAuth2State>,
+    parts: Parts,
+) -> Result<Response, OAuth2Error> {
+    let (user_id, current, store) = signed_in(&state, &parts)?;
+    let sessions = store
+        .list_for_user(&user_id)
+        .await?
+        .into_iter()
+        .map(|record| SessionInfo::new(record, current.as_deref()))
+        .collect();
+
+    Ok(no_store(Json(SessionList { sessions }).into_response()))
+}
+
+/// `DELETE /auth/sessions/{id}`: sign out one of the [`ResourceOwner`]'s sessions. Ending
+/// the current session's cookie is left to the host's login layer.
+pub async fn revoke_session(
+    State(state): State<OAuth2State>,
+    Path(id): Path<String>,
+    parts: Parts,
+) -> Result<StatusCode, OAuth2Error> {
+    let (user_id, _, store) = signed_in(&state, &parts)?;
+    // Other users' sessions are reported as missing, so ids cannot be probed.
+    let owned = store
+        .get(&id)
+        .await?
+        .filter(|target| !target.is_expired())
+        .is_some_and(|target| target.user_id.as_deref() == Some(user_id.as_str()));
+    if !owned {
+        return Err(OAuth2Error::not_found("Session not found"));
+    }
+
+    store.delete(&id).await?;
+    tracing::info!(enduser.id = %user_id, "Browser session revoked");
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,125 @@
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{header, request::Parts, HeaderMap};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use base64::{engine::general_purpose, Engine as _};
+
+use super::{no_store, parse_params, request_tenant, security_events};
+use crate::OAuth2State;
+use oauth2_core::{Client, OAuth2Error};
+
+/// Client credentials from `Authorization: Basic` (RFC 6749 §2.3.1, form-urlencoded
+/// id and secret), if present.
+fn basic_credentials(headers: &HeaderMap) -> Result<Option<(String, String)>, OAuth2Error> {
+    let Some(value) = headers.get(header::AUTHORIZATION) else {
+        return Ok(None);
+    };
+    let Some(encoded) = value.to_str().ok().and_then(|v| v.strip_prefix("Basic ")) else {
+        return Ok(None);
+    };
+
+    let invalid = || OAuth2Error::invalid_client("Malformed Basic credentials");
+    let decoded = general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|_| invalid())?;
+    let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+    let (id, secret) = decoded.split_once(':').ok_or_else(invalid)?;
+    let unescape = |s: &str| {
+        url::form_urlencoded::parse(format!("v={s}").as_bytes())
+            .next()
+            .map(|(_, v)| v.into_owned())
+            .unwrap_or_default()
+    };
+    Ok(Some((unescape(id), unescape(secret))))
+}
+
+/// Authenticate the calling client via HTTP Basic or `client_id`/`client_secret` form
+/// parameters. Using both methods at once is rejected (RFC 6749 §2.3).
+async fn authenticate_client(
+    state: &OAuth2State,
+    parts: &Parts,
+    form_client_id: Option<&str>,
+    form_client_secret: Option<&str>,
+) -> Result<Client, OAuth2Error> {
+    let (client_id, client_secret) = match (basic_credentials(&parts.headers)?, form_client_secret)
+    {
+        (Some(_), Some(_)) => {
+            return Err(OAuth2Error::invalid_request(
+                "Use only one client authentication method",
+            ))
+        }
+        (Some(credentials), None) => credentials,
+        (None, Some(secret)) => {
+            let client_id =
+                form_client_id.ok_or_else(|| OAuth2Error::invalid_client("Missing client_id"))?;
+            (client_id.to_string(), secret.to_string())
+        }
+        (None, None) => {
+            return Err(OAuth2Error::invalid_client(
+                "Client authentication is required",
+            ))
+        }
+    };
+
+    state
+        .authenticate_client(
+            &client_id,
+            &client_secret,
+            request_tenant(parts).as_ref(),
+            &security_events(state, parts),
+            parts.uri.path(),
+        )
+        .await
+}
+
+/// Token introspection endpoint (RFC 7662)
+/// Unknown, expired and revoked tokens are always reported as `{"active": false}`.
+///
+/// Callers must authenticate as a client. Tokens issued to other clients are reported as
+/// inactive unless the caller is a resource server or holds the admin scope. Under
+/// `/t/{tenant}`, only that tenant's clients may call and only its tokens are active.
+pub async fn introspect(
+    State(state): State<OAuth2State>,
+    parts: Parts,
+    body: Bytes,
+) -> Result<Response, OAuth2Error> {
+    let form = parse_params(&body)?;
+    let param = |name: &str| form.get(name).map(String::as_str);
+    let caller =
+        authenticate_client(&state, &parts, param("client_id"), param("client_secret")).await?;
+    let token = param("token").ok_or_else(|| OAuth2Error::invalid_request("Missing token"))?;
+
+    let response = state
+        .introspect(&caller, token, request_tenant(&parts).as_ref())
+        .await;
+    Ok(no_store(Json(response).into_response()))
+}
+
+/// Token revocation endpoint (RFC 7009)
+///
+/// Callers must authenticate as a client and may only revoke their own tokens unless
+/// they hold the admin scope. Unknown tokens, and tokens of other tenants, are ignored.
+/// Revoking a refresh token also revokes every access token issued under the same grant.
+pub async fn revoke(
+    State(state): State<OAuth2State>,
+    parts: Parts,
+    body: Bytes,
+) -> Result<Response, OAuth2Error> {
+    let form = parse_params(&body)?;
+    let param = |name: &str| form.get(name).map(String::as_str);
+    let caller =
+        authenticate_client(&state, &parts, param("client_id"), param("client_secret")).await?;
+    let token = param("token").ok_or_else(|| OAuth2Error::invalid_request("Missing token"))?;
+
+    state
+        .revoke(
+            &caller,
+            token,
+            param("token_type_hint"),
+            request_tenant(&parts).as_ref(),
+        )
+        .await?;
+
+    Ok(no_store(().into_response()))
+}
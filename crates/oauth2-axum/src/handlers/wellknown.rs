@@ -0,0 +1,52 @@
+use axum::extract::State;
+use axum::http::request::Parts;
+use axum::Json;
+use serde_json::{json, Value};
+
+use oauth2_core::{GrantType, ResponseType};
+
+use super::{request_origin, request_tenant, tenant_urls};
+use crate::OAuth2State;
+
+/// OAuth2 discovery endpoint (RFC 8414).
+///
+/// Advertises what this router serves; URLs are absolute, built from the configured
+/// issuer or, when unset, from the request, and under `/t/{tenant}` for tenants.
+pub async fn openid_configuration(State(state): State<OAuth2State>, parts: Parts) -> Json<Value> {
+    let urls = tenant_urls(&state, request_tenant(&parts).as_ref());
+    let origin = request_origin(&parts);
+
+    Json(json!({
+        "issuer": urls.base_url(&origin),
+        "authorization_endpoint": urls.url(&origin, "/oauth/authorize"),
+        "token_endpoint": urls.token_endpoint(&origin),
+        "token_introspection_endpoint": urls.url(&origin, "/oauth/introspect"),
+        "token_revocation_endpoint": urls.url(&origin, "/oauth/revoke"),
+        "response_types_supported": ResponseType::ALL,
+        "grant_types_supported": state
+            .grants
+            .enabled_grant_types()
+            .iter()
+            .map(GrantType::as_str)
+            .collect::<Vec<_>>(),
+        "token_endpoint_auth_methods_supported": [
+            "client_secret_basic",
+            "client_secret_post",
+            "private_key_jwt"
+        ],
+        "token_endpoint_auth_signing_alg_values_supported": [
+            "RS256", "RS384", "RS512", "PS256", "PS384", "PS512", "ES256", "ES384", "EdDSA"
+        ],
+        "introspection_endpoint_auth_methods_supported": [
+            "client_secret_basic",
+            "client_secret_post"
+        ],
+        "revocation_endpoint_auth_methods_supported": [
+            "client_secret_basic",
+            "client_secret_post"
+        ],
+        "code_challenge_methods_supported": ["S256"],
+        "prompt_values_supported": ["none"],
+        "authorization_response_iss_parameter_supported": true
+    }))
+}
@@ -0,0 +1,63 @@
+//! Axum HTTP surface for the OAuth2 server.
+//!
+//! The axum counterpart of `oauth2-actix`: the same endpoints, backed by the same storage
+//! port, signing keys and event bus, without an actix runtime. Mount [`router`] into an
+//! existing axum application:
+//!
+//! ```ignore
+//! let state = OAuth2State::new(storage, IssuerKeys::from_secret(secret), Metrics::new()?);
+//! let app = axum::Router::new()
+//!     .merge(oauth2_axum::router(state.clone()))
+//!     .route("/api/me", get(me).layer(from_fn_with_state(state, require_token)));
+//! ```
+//!
+//! `/oauth/authorize` issues codes for the [`extract::ResourceOwner`] placed in the request
+//! extensions by the host application's login layer.
+//!
+//! Tokens, codes and clients are handled by the `oauth2-ports` services the actix server
+//! runs, so grants, tenants, issuance policies and claims behave the same on both.
+
+pub mod extract;
+pub mod handlers;
+pub mod middleware;
+mod service;
+mod state;
+
+pub use oauth2_ports::ClientCredentials;
+pub use state::OAuth2State;
+
+use axum::middleware::from_fn_with_state;
+use axum::routing::{delete, get, post};
+use axum::Router;
+
+/// Routes for every OAuth2 endpoint, at the same paths as the actix server: at the root
+/// for the default tenant and under `/t/{tenant}` for the others. `/auth/sessions` is
+/// only served with a session store ([`OAuth2State::with_session_store`]).
+pub fn router(state: OAuth2State) -> Router {
+    let tenant_routes = oauth2_routes().layer(from_fn_with_state(
+        state.clone(),
+        middleware::resolve_tenant,
+    ));
+    let mut router = oauth2_routes().nest("/t/{tenant}", tenant_routes);
+    if state.sessions.is_some() {
+        router = router
+            .route("/auth/sessions", get(handlers::sessions::list_sessions))
+            .route(
+                "/auth/sessions/{id}",
+                delete(handlers::sessions::revoke_session),
+            );
+    }
+    router.with_state(state)
+}
+
+fn oauth2_routes() -> Router<OAuth2State> {
+    Router::new()
+        .route("/oauth/authorize", get(handlers::oauth::authorize))
+        .route("/oauth/token", post(handlers::oauth::token))
+        .route("/oauth/introspect", post(handlers::token::introspect))
+        .route("/oauth/revoke", post(handlers::token::revoke))
+        .route(
+            "/.well-known/openid-configuration",
+            get(handlers::wellknown::openid_configuration),
+        )
+}
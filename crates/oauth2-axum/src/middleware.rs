@@ -0,0 +1,49 @@
+//! Middleware for routes protected by tokens from this server.
+
+use axum::extract::{FromRequestParts, Path, Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use crate::extract::AuthenticatedToken;
+use crate::OAuth2State;
+use oauth2_core::{OAuth2Error, TenantContext};
+
+/// Reject requests without a valid bearer token (`401 invalid_token`).
+///
+/// Use with `axum::middleware::from_fn_with_state`. The validated [`oauth2_core::Token`]
+/// is stored in the request extensions, where [`AuthenticatedToken`] picks it up.
+pub async fn require_token(
+    State(state): State<OAuth2State>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (mut parts, body) = request.into_parts();
+    match AuthenticatedToken::from_request_parts(&mut parts, &state).await {
+        Ok(AuthenticatedToken(token)) => {
+            parts.extensions.insert(token);
+            next.run(Request::from_parts(parts, body)).await
+        }
+        Err(error) => error.into_response(),
+    }
+}
+
+/// Resolve the `{tenant}` path segment of a `/t/{tenant}/...` route into a
+/// [`TenantContext`] in the request extensions, where the handlers pick it up.
+///
+/// Unknown and disabled tenants are answered with `404` before any handler runs.
+pub async fn resolve_tenant(
+    State(state): State<OAuth2State>,
+    Path(tenant_id): Path<String>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let tenant = match state.storage.get_tenant(&tenant_id).await {
+        Ok(Some(tenant)) if tenant.enabled => tenant,
+        Ok(_) => return OAuth2Error::not_found("Unknown tenant").into_response(),
+        Err(error) => return error.into_response(),
+    };
+    request
+        .extensions_mut()
+        .insert(TenantContext::new(tenant, state.tokens.keys()));
+    next.run(request).await
+}
@@ -0,0 +1,172 @@
+//! Endpoint operations the handlers and other front ends (e.g. `oauth2-grpc`) share.
+//!
+//! These compose the [`oauth2_ports`] services held by [`OAuth2State`] and record the
+//! endpoint metrics; the checks, errors and events live in the services.
+
+use oauth2_core::{
+    tenant_id, Claims, Client, GrantType, IntrospectionResponse, OAuth2Error, TenantContext, Token,
+    TokenMetadata,
+};
+use oauth2_ports::{
+    has_admin_scope, AuthorizedClientCredentials, ClientCredentials, Introspection, IssueToken,
+    SecurityEvents,
+};
+
+use crate::OAuth2State;
+
+impl OAuth2State {
+    /// Issue an access token for the client credentials grant, for a client of `tenant`
+    /// (`None` for the default tenant).
+    ///
+    /// Client assertions must be addressed to `token_endpoint`.
+    pub async fn client_credentials_grant(
+        &self,
+        client_id: &str,
+        credentials: ClientCredentials,
+        metadata: TokenMetadata,
+        token_endpoint: &str,
+        tenant: Option<TenantContext>,
+    ) -> Result<Token, OAuth2Error> {
+        let client = self
+            .clients
+            .get(client_id, tenant_id(tenant.as_ref()))
+            .await?;
+        self.issue_client_credentials(&client, credentials, metadata, token_endpoint, tenant)
+            .await
+    }
+
+    /// [`client_credentials_grant`](Self::client_credentials_grant) for a client already
+    /// looked up.
+    pub(crate) async fn issue_client_credentials(
+        &self,
+        client: &Client,
+        credentials: ClientCredentials,
+        metadata: TokenMetadata,
+        token_endpoint: &str,
+        tenant: Option<TenantContext>,
+    ) -> Result<Token, OAuth2Error> {
+        let AuthorizedClientCredentials {
+            scope,
+            service_account,
+        } = self
+            .clients
+            .authorize_client_credentials(client, credentials, token_endpoint)
+            .await?;
+
+        let token = self
+            .tokens
+            .issue(IssueToken {
+                metadata,
+                tenant,
+                ..IssueToken::new(
+                    GrantType::ClientCredentials.as_str(),
+                    client.client_id.clone(),
+                    None,
+                    scope,
+                )
+            })
+            .await?;
+
+        if let Some(account) = &service_account {
+            self.metrics
+                .oauth_service_account_tokens_issued_total
+                .with_label_values(&[account.name.as_str()])
+                .inc();
+        }
+        Ok(token)
+    }
+
+    /// The stored token for `access_token`, if it is still valid.
+    pub(crate) async fn validate_token(&self, access_token: &str) -> Result<Token, OAuth2Error> {
+        self.tokens.validate(access_token).await
+    }
+
+    /// A bearer token signed by this server for `tenant` that storage still knows as
+    /// unexpired and unrevoked, with its verified claims. Failures are `invalid_token`.
+    pub async fn authenticate_bearer(
+        &self,
+        raw: &str,
+        tenant: Option<&TenantContext>,
+    ) -> Result<(Token, Claims), OAuth2Error> {
+        self.tokens.authenticate_bearer(raw, tenant).await
+    }
+
+    /// The client of `tenant` identified by `client_id` and `client_secret`. Unknown
+    /// clients and wrong secrets both fail with the same `invalid_client` error; the
+    /// reason goes to `security`, tagged with `endpoint`.
+    pub async fn authenticate_client(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        tenant: Option<&TenantContext>,
+        security: &SecurityEvents,
+        endpoint: &str,
+    ) -> Result<Client, OAuth2Error> {
+        self.clients
+            .authenticate(
+                client_id,
+                client_secret,
+                tenant_id(tenant),
+                security,
+                endpoint,
+            )
+            .await
+    }
+
+    /// Token introspection (RFC 7662) on behalf of the authenticated `caller`; see
+    /// [`TokenService::introspect`](oauth2_ports::TokenService::introspect).
+    pub async fn introspect(
+        &self,
+        caller: &Client,
+        token: &str,
+        tenant: Option<&TenantContext>,
+    ) -> IntrospectionResponse {
+        let Introspection {
+            response,
+            verification,
+        } = self.tokens.introspect(caller, token, tenant).await;
+        if let Some((generation, result)) = verification {
+            self.metrics
+                .oauth_token_verifications_by_issuer
+                .with_label_values(&[generation, result])
+                .inc();
+        }
+
+        // Resource servers are labelled individually; other callers share one series.
+        let caller_label = if caller.is_resource_server() {
+            caller.client_id.as_str()
+        } else {
+            "client"
+        };
+        let outcome = if response.active {
+            "active"
+        } else {
+            "inactive"
+        };
+        self.metrics
+            .oauth_introspection_requests_total
+            .with_label_values(&[caller_label, outcome])
+            .inc();
+        response
+    }
+
+    /// Token revocation (RFC 7009) on behalf of the authenticated `caller`, who may only
+    /// revoke their own tokens unless they hold the admin scope. Tokens of other tenants
+    /// than `tenant`'s are ignored.
+    pub async fn revoke(
+        &self,
+        caller: &Client,
+        token: &str,
+        token_type_hint: Option<&str>,
+        tenant: Option<&TenantContext>,
+    ) -> Result<(), OAuth2Error> {
+        self.tokens
+            .revoke(
+                token,
+                token_type_hint,
+                (!has_admin_scope(caller)).then_some(caller.client_id.as_str()),
+                tenant_id(tenant),
+            )
+            .await
+    }
+}
@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use oauth2_config::GrantsConfig;
+use oauth2_core::{ContextTolerance, IssuerKeys, IssuerUrls, OAuth2Error};
+use oauth2_events::EventBusHandle;
+use oauth2_observability::Metrics;
+use oauth2_ports::{
+    AuthService, ClientService, DynAuditSink, DynClaimsEnricher, DynStorage,
+    DynTokenIssuancePolicy, DynUserAuthenticator, DynUserSessionStore, RoleClaimsEnricher,
+    TokenService,
+};
+
+/// Shared state for the axum handlers.
+///
+/// Holds the same [`oauth2_ports`] services the actix actors run, so both servers behave
+/// the same for the same configuration.
+#[derive(Clone)]
+pub struct OAuth2State {
+    pub(crate) storage: DynStorage,
+    pub(crate) tokens: TokenService,
+    pub(crate) auth: AuthService,
+    pub(crate) clients: ClientService,
+    pub(crate) issuer_urls: IssuerUrls,
+    pub(crate) metrics: Metrics,
+    pub(crate) event_bus: Option<EventBusHandle>,
+    pub(crate) grants: GrantsConfig,
+    pub(crate) claims_enrichers: Vec<DynClaimsEnricher>,
+    pub(crate) audit: Option<DynAuditSink>,
+    pub(crate) sessions: Option<DynUserSessionStore>,
+}
+
+impl OAuth2State {
+    pub fn new(storage: DynStorage, keys: IssuerKeys, metrics: Metrics) -> Self {
+        Self {
+            tokens: TokenService::new(storage.clone(), keys),
+            auth: AuthService::new(storage.clone()),
+            clients: ClientService::new(storage.clone()),
+            storage,
+            issuer_urls: IssuerUrls::default(),
+            metrics,
+            event_bus: None,
+            grants: GrantsConfig::default(),
+            claims_enrichers: Vec::new(),
+            audit: None,
+            sessions: None,
+        }
+    }
+
+    /// Serve the grants enabled in `grants`; password and refresh_token are off by
+    /// default (OAuth 2.0 Security BCP).
+    pub fn with_grants(mut self, grants: GrantsConfig) -> Self {
+        self.grants = grants;
+        self
+    }
+
+    /// Build absolute URLs (discovery, `iss`, client assertion audience) from `urls`.
+    pub fn with_issuer_urls(mut self, urls: IssuerUrls) -> Self {
+        self.issuer_urls = urls;
+        self
+    }
+
+    pub fn with_events(mut self, event_bus: EventBusHandle) -> Self {
+        self.tokens = self.tokens.with_events(event_bus.clone());
+        self.auth = self.auth.with_events(event_bus.clone());
+        self.clients = self.clients.with_events(event_bus.clone());
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Run `enrichers`, in order, on every access token before it is signed.
+    pub fn with_claims_enrichers(mut self, enrichers: Vec<DynClaimsEnricher>) -> Self {
+        self.tokens = self.tokens.with_claims_enrichers(enrichers.clone());
+        self.claims_enrichers = enrichers;
+        self
+    }
+
+    /// Add the subject's roles and groups to access tokens as `roles_claim` and
+    /// `groups_claim` (see [`RoleClaimsEnricher`]), after the other enrichers.
+    pub fn with_role_claims(
+        self,
+        roles_claim: Option<String>,
+        groups_claim: Option<String>,
+    ) -> Result<Self, OAuth2Error> {
+        let enricher = RoleClaimsEnricher::new(self.storage.clone(), roles_claim, groups_claim)?;
+        let mut enrichers = self.claims_enrichers.clone();
+        enrichers.push(Arc::new(enricher));
+        Ok(self.with_claims_enrichers(enrichers))
+    }
+
+    /// Check `policies`, in order, before every token is issued; the first error denies it.
+    pub fn with_issuance_policies(mut self, policies: Vec<DynTokenIssuancePolicy>) -> Self {
+        self.tokens = self.tokens.with_issuance_policies(policies);
+        self
+    }
+
+    /// Check passwords for the password grant with `authenticator` instead of the hashes
+    /// in storage.
+    pub fn with_authenticator(mut self, authenticator: DynUserAuthenticator) -> Self {
+        self.auth = self.auth.with_authenticator(authenticator);
+        self
+    }
+
+    /// Record consent and token issuance in `audit`.
+    pub fn with_audit(mut self, audit: DynAuditSink) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Serve `/auth/sessions`, where the [`ResourceOwner`](crate::extract::ResourceOwner)
+    /// lists and revokes their sessions in `sessions`.
+    pub fn with_session_store(mut self, sessions: DynUserSessionStore) -> Self {
+        self.sessions = Some(sessions);
+        self
+    }
+
+    /// Revoke the whole grant when an access token is revoked, not just that token.
+    pub fn with_revocation_cascade(mut self, enabled: bool) -> Self {
+        self.tokens = self.tokens.with_revocation_cascade(enabled);
+        self
+    }
+
+    /// Bind authorization codes and refresh tokens to the requesting network; see
+    /// [`ContextTolerance`].
+    pub fn with_context_binding(mut self, enabled: bool, tolerance: ContextTolerance) -> Self {
+        self.tokens = self.tokens.with_context_binding(enabled, tolerance);
+        self.auth = self.auth.with_context_binding(enabled, tolerance);
+        self
+    }
+
+    pub fn storage(&self) -> &DynStorage {
+        &self.storage
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    pub fn issuer_urls(&self) -> &IssuerUrls {
+        &self.issuer_urls
+    }
+
+    /// Token issuance, validation and revocation.
+    pub fn tokens(&self) -> &TokenService {
+        &self.tokens
+    }
+
+    /// Authorization codes and end-user authentication.
+    pub fn auth(&self) -> &AuthService {
+        &self.auth
+    }
+
+    /// Client lookup and authentication.
+    pub fn clients(&self) -> &ClientService {
+        &self.clients
+    }
+
+    pub fn event_bus(&self) -> Option<&EventBusHandle> {
+        self.event_bus.as_ref()
+    }
+}
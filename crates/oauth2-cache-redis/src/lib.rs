@@ -0,0 +1,438 @@
+//! Redis adapters for the [`oauth2_ports::Cache`],
+//! [`oauth2_ports::IdempotencyBackend`], [`oauth2_ports::InvalidationChannel`],
+//! [`oauth2_ports::StateStore`] and [`oauth2_ports::UserSessionStore`] ports.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::stream::{BoxStream, StreamExt};
+use oauth2_core::{OAuth2Error, SocialLoginState, UserSession};
+use oauth2_ports::{
+    Cache, IdempotencyBackend, Invalidation, InvalidationChannel, InvalidationHandler, StateStore,
+    UserSessionStore,
+};
+use redis::aio::ConnectionManager;
+use std::time::Duration;
+
+/// Redis-backed [`Cache`].
+///
+/// Keys are namespaced with a prefix so several deployments can share one Redis. Every
+/// command is bounded by a short timeout so callers can fall back quickly when Redis is
+/// unreachable instead of stalling request handling.
+pub struct RedisCache {
+    prefix: String,
+    op_timeout: Duration,
+    conn: ConnectionManager,
+}
+
+impl RedisCache {
+    pub async fn connect(url: &str, prefix: impl Into<String>) -> Result<Self, String> {
+        let client = redis::Client::open(url).map_err(|e| format!("redis client: {e}"))?;
+        Self::from_client(&client, prefix).await
+    }
+
+    async fn from_client(
+        client: &redis::Client,
+        prefix: impl Into<String>,
+    ) -> Result<Self, String> {
+        let conn = client
+            .get_connection_manager()
+            .await
+            .map_err(|e| format!("redis connect: {e}"))?;
+
+        Ok(Self {
+            prefix: prefix.into(),
+            op_timeout: default_op_timeout(),
+            conn,
+        })
+    }
+
+    pub fn with_op_timeout(mut self, op_timeout: Duration) -> Self {
+        self.op_timeout = op_timeout;
+        self
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key)
+    }
+
+    async fn query<T: redis::FromRedisValue>(&self, cmd: redis::Cmd) -> Result<T, OAuth2Error> {
+        // ConnectionManager is a cheap handle over a shared multiplexed connection.
+        let mut conn = self.conn.clone();
+        match tokio::time::timeout(self.op_timeout, cmd.query_async::<_, T>(&mut conn)).await {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(e)) => Err(cache_error(&format!("redis: {e}"))),
+            Err(_) => Err(cache_error("redis: operation timed out")),
+        }
+    }
+}
+
+#[async_trait]
+impl Cache for RedisCache {
+    fn backend_name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn set_if_absent(
+        &self,
+        key: &str,
+        value: &str,
+        ttl: Duration,
+    ) -> Result<bool, OAuth2Error> {
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(self.key(key))
+            .arg(value)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl.as_millis().max(1) as u64);
+
+        // SET ... NX replies OK when stored and nil when the key already exists.
+        let reply: Option<String> = self.query(cmd).await?;
+        Ok(reply.is_some())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>, OAuth2Error> {
+        let mut cmd = redis::cmd("GET");
+        cmd.arg(self.key(key));
+        self.query(cmd).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), OAuth2Error> {
+        let mut cmd = redis::cmd("DEL");
+        cmd.arg(self.key(key));
+        let _removed: i64 = self.query(cmd).await?;
+        Ok(())
+    }
+}
+
+/// Redis-backed [`IdempotencyBackend`].
+///
+/// Each key is a `SET NX` with the requested TTL, so exactly one replica records it and
+/// every other one sees a duplicate until it expires.
+pub struct RedisIdempotencyBackend {
+    redis: RedisCache,
+}
+
+impl RedisIdempotencyBackend {
+    pub async fn connect(url: &str, prefix: impl Into<String>) -> Result<Self, String> {
+        Ok(Self {
+            redis: RedisCache::connect(url, prefix).await?,
+        })
+    }
+
+    pub fn with_op_timeout(mut self, op_timeout: Duration) -> Self {
+        self.redis = self.redis.with_op_timeout(op_timeout);
+        self
+    }
+}
+
+#[async_trait]
+impl IdempotencyBackend for RedisIdempotencyBackend {
+    fn backend_name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn check_and_record(&self, key: &str, ttl: Duration) -> Result<bool, OAuth2Error> {
+        let stored = self
+            .redis
+            .set_if_absent(&format!("idempotency:{key}"), "1", ttl)
+            .await?;
+        Ok(!stored)
+    }
+}
+
+/// Redis pub/sub [`InvalidationChannel`].
+///
+/// The channel name is namespaced with the key prefix. Publishing shares the bounded
+/// command timeout of [`RedisCache`]; the subscriber holds its own connection and, when
+/// it drops, reconnects with backoff and reports [`Invalidation::Resync`], since anything
+/// published in between was lost.
+pub struct RedisInvalidationChannel {
+    client: redis::Client,
+    channel: String,
+    publisher: RedisCache,
+}
+
+impl RedisInvalidationChannel {
+    pub async fn connect(
+        url: &str,
+        prefix: impl Into<String>,
+        channel: &str,
+    ) -> Result<Self, String> {
+        let client = redis::Client::open(url).map_err(|e| format!("redis client: {e}"))?;
+        let publisher = RedisCache::from_client(&client, prefix).await?;
+        Ok(Self {
+            channel: publisher.key(channel),
+            client,
+            publisher,
+        })
+    }
+
+    pub fn with_op_timeout(mut self, op_timeout: Duration) -> Self {
+        self.publisher = self.publisher.with_op_timeout(op_timeout);
+        self
+    }
+}
+
+async fn subscribe_messages(
+    client: &redis::Client,
+    channel: &str,
+) -> redis::RedisResult<BoxStream<'static, redis::Msg>> {
+    let mut pubsub = client.get_async_pubsub().await?;
+    pubsub.subscribe(channel).await?;
+    Ok(pubsub.into_on_message().boxed())
+}
+
+#[async_trait]
+impl InvalidationChannel for RedisInvalidationChannel {
+    fn backend_name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn publish(&self, message: &str) -> Result<(), OAuth2Error> {
+        let mut cmd = redis::cmd("PUBLISH");
+        cmd.arg(&self.channel).arg(message);
+        let _receivers: i64 = self.publisher.query(cmd).await?;
+        Ok(())
+    }
+
+    async fn subscribe(&self, handler: InvalidationHandler) -> Result<(), OAuth2Error> {
+        let mut messages = subscribe_messages(&self.client, &self.channel)
+            .await
+            .map_err(|e| cache_error(&format!("redis subscribe: {e}")))?;
+        let client = self.client.clone();
+        let channel = self.channel.clone();
+
+        tokio::spawn(async move {
+            loop {
+                while let Some(msg) = messages.next().await {
+                    match msg.get_payload::<String>() {
+                        Ok(payload) => handler(Invalidation::Message(payload)),
+                        Err(_) => handler(Invalidation::Resync),
+                    }
+                }
+
+                // The connection dropped; resubscribe, then report the gap.
+                let mut backoff = Duration::from_millis(100);
+                messages = loop {
+                    tokio::time::sleep(backoff).await;
+                    match subscribe_messages(&client, &channel).await {
+                        Ok(messages) => break messages,
+                        Err(_) => backoff = (backoff * 2).min(Duration::from_secs(10)),
+                    }
+                };
+                handler(Invalidation::Resync);
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Redis-backed social login [`StateStore`].
+///
+/// Records expire with the login attempt and are read with `GETDEL` (Redis 6.2+), so a
+/// state is consumed once across every replica.
+pub struct RedisStateStore {
+    redis: RedisCache,
+}
+
+impl RedisStateStore {
+    pub async fn connect(url: &str, prefix: impl Into<String>) -> Result<Self, String> {
+        Ok(Self {
+            redis: RedisCache::connect(url, prefix).await?,
+        })
+    }
+
+    pub fn with_op_timeout(mut self, op_timeout: Duration) -> Self {
+        self.redis = self.redis.with_op_timeout(op_timeout);
+        self
+    }
+
+    fn key(&self, state: &str) -> String {
+        self.redis.key(&format!("social_login_state:{state}"))
+    }
+}
+
+#[async_trait]
+impl StateStore for RedisStateStore {
+    fn backend_name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn save(&self, state: &SocialLoginState) -> Result<(), OAuth2Error> {
+        let value = serde_json::to_string(state)
+            .map_err(|e| cache_error(&format!("serialize login state: {e}")))?;
+        let ttl = (state.expires_at - chrono::Utc::now())
+            .num_milliseconds()
+            .max(1);
+
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(self.key(&state.state))
+            .arg(value)
+            .arg("PX")
+            .arg(ttl);
+        let _: String = self.redis.query(cmd).await?;
+        Ok(())
+    }
+
+    async fn take(&self, state: &str) -> Result<Option<SocialLoginState>, OAuth2Error> {
+        let mut cmd = redis::cmd("GETDEL");
+        cmd.arg(self.key(state));
+        let value: Option<String> = self.redis.query(cmd).await?;
+        value
+            .map(|value| {
+                serde_json::from_str(&value)
+                    .map_err(|e| cache_error(&format!("deserialize login state: {e}")))
+            })
+            .transpose()
+    }
+}
+
+/// Redis-backed [`UserSessionStore`].
+///
+/// Each session is a JSON record expiring at its idle or absolute timeout, whichever
+/// comes first. A set per user indexes their sessions; members whose record is gone are
+/// pruned when the user's sessions are listed.
+pub struct RedisSessionStore {
+    redis: RedisCache,
+}
+
+impl RedisSessionStore {
+    pub async fn connect(url: &str, prefix: impl Into<String>) -> Result<Self, String> {
+        Ok(Self {
+            redis: RedisCache::connect(url, prefix).await?,
+        })
+    }
+
+    pub fn with_op_timeout(mut self, op_timeout: Duration) -> Self {
+        self.redis = self.redis.with_op_timeout(op_timeout);
+        self
+    }
+
+    fn key(&self, id: &str) -> String {
+        self.redis.key(&format!("session:{id}"))
+    }
+
+    fn user_key(&self, user_id: &str) -> String {
+        self.redis.key(&format!("user_sessions:{user_id}"))
+    }
+
+    /// Store `session` until it expires. With `existing_only`, a session deleted meanwhile
+    /// is not recreated.
+    async fn put(&self, session: &UserSession, existing_only: bool) -> Result<(), OAuth2Error> {
+        let value = serde_json::to_string(session)
+            .map_err(|e| cache_error(&format!("serialize session: {e}")))?;
+        let ttl = (session.idle_expires_at.min(session.expires_at) - Utc::now())
+            .num_milliseconds()
+            .max(1);
+
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(self.key(&session.id)).arg(value).arg("PX").arg(ttl);
+        if existing_only {
+            cmd.arg("XX");
+        }
+        // SET replies OK when stored and nil when XX found no record.
+        let _: Option<String> = self.redis.query(cmd).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UserSessionStore for RedisSessionStore {
+    fn backend_name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn save(&self, session: &UserSession) -> Result<(), OAuth2Error> {
+        self.put(session, false).await?;
+        if let Some(user_id) = &session.user_id {
+            let mut cmd = redis::cmd("SADD");
+            cmd.arg(self.user_key(user_id)).arg(&session.id);
+            let _added: i64 = self.redis.query(cmd).await?;
+        }
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<UserSession>, OAuth2Error> {
+        let mut cmd = redis::cmd("GET");
+        cmd.arg(self.key(id));
+        let value: Option<String> = self.redis.query(cmd).await?;
+        value
+            .map(|value| {
+                serde_json::from_str(&value)
+                    .map_err(|e| cache_error(&format!("deserialize session: {e}")))
+            })
+            .transpose()
+    }
+
+    async fn touch(
+        &self,
+        id: &str,
+        last_seen_at: DateTime<Utc>,
+        idle_expires_at: DateTime<Utc>,
+    ) -> Result<(), OAuth2Error> {
+        let Some(mut session) = self.get(id).await? else {
+            return Ok(());
+        };
+        session.last_seen_at = last_seen_at;
+        session.idle_expires_at = idle_expires_at;
+        self.put(&session, true).await
+    }
+
+    async fn list_for_user(&self, user_id: &str) -> Result<Vec<UserSession>, OAuth2Error> {
+        let mut cmd = redis::cmd("SMEMBERS");
+        cmd.arg(self.user_key(user_id));
+        let ids: Vec<String> = self.redis.query(cmd).await?;
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut cmd = redis::cmd("MGET");
+        for id in &ids {
+            cmd.arg(self.key(id));
+        }
+        let values: Vec<Option<String>> = self.redis.query(cmd).await?;
+
+        let mut sessions = Vec::new();
+        let mut stale = Vec::new();
+        for (id, value) in ids.into_iter().zip(values) {
+            let session = value.and_then(|value| serde_json::from_str::<UserSession>(&value).ok());
+            match session {
+                Some(session)
+                    if session.user_id.as_deref() == Some(user_id) && !session.is_expired() =>
+                {
+                    sessions.push(session)
+                }
+                _ => stale.push(id),
+            }
+        }
+        if !stale.is_empty() {
+            let mut cmd = redis::cmd("SREM");
+            cmd.arg(self.user_key(user_id)).arg(stale);
+            let _removed: i64 = self.redis.query(cmd).await?;
+        }
+
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.created_at));
+        Ok(sessions)
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), OAuth2Error> {
+        // The user's index is pruned on the next listing.
+        let mut cmd = redis::cmd("DEL");
+        cmd.arg(self.key(id));
+        let _removed: i64 = self.redis.query(cmd).await?;
+        Ok(())
+    }
+}
+
+fn cache_error(description: &str) -> OAuth2Error {
+    OAuth2Error::new("server_error", Some(description))
+}
+
+/// Conservative defaults used when not configured.
+pub fn default_key_prefix() -> String {
+    "oauth2:".to_string()
+}
+
+pub fn default_op_timeout() -> Duration {
+    Duration::from_millis(250)
+}
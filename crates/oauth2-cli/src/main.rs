@@ -0,0 +1,541 @@
+//! `oauth2-cli`: operational tasks run directly against the server's storage, for
+//! bootstrapping a deployment before any admin credentials exist.
+//!
+//! Configuration is read the way the server reads it (`application.conf`, the active
+//! profile, `OAUTH2_*` overrides and external secret stores), so the CLI reaches the
+//! same database. Servers that cache storage lookups (`database.cache`) notice deleted
+//! clients and revoked tokens once their cache entries expire.
+
+use clap::{Args, Parser, Subcommand};
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use oauth2_config::{config_layers, Config, JwtConfig};
+use oauth2_core::{hash_password, validate_password, Client, User};
+use oauth2_ports::{DynStorage, PageRequest};
+use oauth2_server::seed::{self, SeedData};
+
+/// A failure reported on stderr before exiting non-zero.
+#[derive(Debug)]
+struct CliError(String);
+
+impl<E: fmt::Display> From<E> for CliError {
+    fn from(e: E) -> Self {
+        CliError(e.to_string())
+    }
+}
+
+type CliResult<T = ()> = Result<T, CliError>;
+
+/// Length of generated client secrets, passwords and JWT secrets.
+const GENERATED_SECRET_LENGTH: usize = 48;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "oauth2-cli",
+    version,
+    about = "Administer the OAuth2 server's storage"
+)]
+struct Cli {
+    /// Configuration file; the active profile's file is merged over it.
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Database to use instead of `database.url`.
+    #[arg(long, global = true, value_name = "URL")]
+    database_url: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Apply pending schema migrations and create missing tables.
+    Migrate,
+    #[command(subcommand)]
+    Clients(ClientsCommand),
+    #[command(subcommand)]
+    Users(UsersCommand),
+    #[command(subcommand)]
+    Tokens(TokensCommand),
+    #[command(subcommand)]
+    Jwt(JwtCommand),
+    #[command(subcommand)]
+    Seed(SeedCommand),
+}
+
+/// Manage OAuth clients.
+#[derive(Debug, Subcommand)]
+enum ClientsCommand {
+    /// List clients as `client_id  name  grant_types  scope`.
+    List,
+    /// Register a confidential client and print its secret.
+    Create(CreateClient),
+    /// Delete a client.
+    Delete { client_id: String },
+}
+
+#[derive(Debug, Args)]
+struct CreateClient {
+    #[arg(long)]
+    client_id: String,
+    /// Display name; defaults to the client id.
+    #[arg(long)]
+    name: Option<String>,
+    /// Repeat for several URIs.
+    #[arg(long = "redirect-uri", value_name = "URI")]
+    redirect_uris: Vec<String>,
+    /// Repeat for several grant types.
+    #[arg(long = "grant-type", value_name = "GRANT", required = true)]
+    grant_types: Vec<String>,
+    /// Space-separated scopes the client may request.
+    #[arg(long, default_value = "")]
+    scope: String,
+    /// Read the secret from stdin instead of generating one.
+    #[arg(long)]
+    secret_stdin: bool,
+}
+
+/// Manage users.
+#[derive(Debug, Subcommand)]
+enum UsersCommand {
+    /// Create a user with an Argon2-hashed password.
+    Create(CreateUser),
+}
+
+#[derive(Debug, Args)]
+struct CreateUser {
+    #[arg(long)]
+    username: String,
+    #[arg(long)]
+    email: String,
+    /// Read the password from stdin instead of generating one.
+    #[arg(long)]
+    password_stdin: bool,
+}
+
+/// Revoke tokens.
+#[derive(Debug, Subcommand)]
+enum TokensCommand {
+    /// Revoke access or refresh tokens.
+    Revoke {
+        #[arg(required = true)]
+        tokens: Vec<String>,
+    },
+    /// Revoke every token issued to a client.
+    RevokeClient { client_id: String },
+}
+
+/// Rotate JWT signing secrets.
+#[derive(Debug, Subcommand)]
+enum JwtCommand {
+    /// Print a `jwt` config block with a new secret; the current one is kept as the
+    /// legacy secret so tokens already issued stay valid for the grace period.
+    Rotate {
+        #[arg(long, default_value_t = 24)]
+        grace_hours: i64,
+    },
+}
+
+/// Export or import roles, groups, clients and users.
+#[derive(Debug, Subcommand)]
+enum SeedCommand {
+    /// Write the stored data as a seed file (JSON), with secrets as hashes.
+    Export {
+        /// Defaults to stdout.
+        #[arg(long, short, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+    /// Create what a seed file declares that storage does not have yet.
+    Import { path: PathBuf },
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e.0);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> CliResult {
+    let mut config = load_config(cli.config.as_deref())?;
+    if let Some(url) = cli.database_url {
+        config.database.url = url;
+    }
+    oauth2_server::resolve_secrets(&mut config).await?;
+
+    let mut out = std::io::stdout().lock();
+    match cli.command {
+        Command::Migrate => {
+            let applied = oauth2_storage_factory::run_migrations(&config.database.url).await?;
+            connect(&config.database.url).await?;
+            if applied.is_empty() {
+                writeln!(out, "Schema is up to date")?;
+            } else {
+                writeln!(out, "Applied migrations: {applied:?}")?;
+            }
+            Ok(())
+        }
+        Command::Jwt(JwtCommand::Rotate { grace_hours }) => {
+            let accept_until = chrono::Utc::now() + chrono::Duration::hours(grace_hours);
+            write!(
+                out,
+                "{}",
+                rotated_jwt_config(&config.jwt, &generate_secret(), accept_until)
+            )?;
+            Ok(())
+        }
+        command => {
+            let storage = connect(&config.database.url).await?;
+            let input = std::io::stdin().lock();
+            execute(command, &storage, input, &mut out).await
+        }
+    }
+}
+
+/// `path` with its profile layer, or the server's default lookup.
+fn load_config(path: Option<&Path>) -> CliResult<Config> {
+    match path {
+        Some(path) => Ok(Config::from_hocon_layers(&config_layers(path))?),
+        None => Ok(Config::load().0),
+    }
+}
+
+async fn connect(database_url: &str) -> CliResult<DynStorage> {
+    let storage = oauth2_storage_factory::create_storage(database_url).await?;
+    storage.init().await?;
+    Ok(storage)
+}
+
+/// Run a storage command, reading secrets from `input` and writing results to `out`.
+async fn execute(
+    command: Command,
+    storage: &DynStorage,
+    mut input: impl BufRead,
+    out: &mut impl Write,
+) -> CliResult {
+    match command {
+        Command::Clients(ClientsCommand::List) => {
+            let mut page = PageRequest::new(1, PageRequest::MAX_PER_PAGE);
+            loop {
+                let clients = storage.list_clients(page).await?;
+                let last_page = (clients.len() as u32) < page.per_page;
+                for client in clients {
+                    writeln!(
+                        out,
+                        "{}\t{}\t{}\t{}",
+                        client.client_id,
+                        client.name,
+                        client.get_grant_types().join(","),
+                        client.scope
+                    )?;
+                }
+                if last_page {
+                    return Ok(());
+                }
+                page = PageRequest::new(page.page + 1, page.per_page);
+            }
+        }
+        Command::Clients(ClientsCommand::Create(args)) => {
+            if storage.get_client(&args.client_id, None).await?.is_some() {
+                return Err(format!("client {} already exists", args.client_id).into());
+            }
+            let secret = if args.secret_stdin {
+                read_secret(&mut input)?
+            } else {
+                generate_secret()
+            };
+            let mut client = Client::new(
+                args.client_id.clone(),
+                String::new(),
+                args.redirect_uris,
+                args.grant_types,
+                args.scope,
+                args.name.unwrap_or_else(|| args.client_id.clone()),
+            );
+            client.set_client_secret(&secret)?;
+            storage.save_client(&client).await?;
+            writeln!(out, "Created client {}", client.client_id)?;
+            if !args.secret_stdin {
+                writeln!(out, "client_secret: {secret}")?;
+            }
+            Ok(())
+        }
+        Command::Clients(ClientsCommand::Delete { client_id }) => {
+            if storage.get_client(&client_id, None).await?.is_none() {
+                return Err(format!("client {client_id} not found").into());
+            }
+            storage.delete_client(&client_id, None).await?;
+            writeln!(out, "Deleted client {client_id}")?;
+            Ok(())
+        }
+        Command::Users(UsersCommand::Create(args)) => {
+            let username = args.username.trim();
+            if username.is_empty() {
+                return Err("username must not be empty".into());
+            }
+            if !args.email.contains('@') {
+                return Err("email must be a valid address".into());
+            }
+            if storage
+                .get_user_by_username(username, None)
+                .await?
+                .is_some()
+            {
+                return Err(format!("user {username} already exists").into());
+            }
+            let password = if args.password_stdin {
+                read_secret(&mut input)?
+            } else {
+                generate_secret()
+            };
+            validate_password(&password)?;
+            let user = User::new(
+                username.to_string(),
+                hash_password(&password)?,
+                args.email.trim().to_string(),
+            );
+            storage.save_user(&user).await?;
+            writeln!(out, "Created user {} ({})", user.username, user.id)?;
+            if !args.password_stdin {
+                writeln!(out, "password: {password}")?;
+            }
+            Ok(())
+        }
+        Command::Tokens(TokensCommand::Revoke { tokens }) => {
+            for token in &tokens {
+                storage.revoke_token(token).await?;
+            }
+            writeln!(out, "Revoked {} token(s)", tokens.len())?;
+            Ok(())
+        }
+        Command::Tokens(TokensCommand::RevokeClient { client_id }) => {
+            let mut revoked = 0;
+            let mut page = PageRequest::new(1, PageRequest::MAX_PER_PAGE);
+            loop {
+                let tokens = storage.list_tokens_by_client(&client_id, page).await?;
+                let last_page = (tokens.len() as u32) < page.per_page;
+                for token in tokens.into_iter().filter(|token| !token.revoked) {
+                    storage.revoke_token(&token.access_token).await?;
+                    revoked += 1;
+                }
+                if last_page {
+                    break;
+                }
+                page = PageRequest::new(page.page + 1, page.per_page);
+            }
+            writeln!(out, "Revoked {revoked} token(s) of client {client_id}")?;
+            Ok(())
+        }
+        Command::Seed(SeedCommand::Export { output }) => {
+            let data = seed::export(storage).await?;
+            let json = serde_json::to_string_pretty(&data)?;
+            match output {
+                Some(path) => std::fs::write(&path, json + "\n")?,
+                None => writeln!(out, "{json}")?,
+            }
+            Ok(())
+        }
+        Command::Seed(SeedCommand::Import { path }) => {
+            let report = seed::apply(storage, &SeedData::load(&path)?).await?;
+            for created in &report.created {
+                writeln!(out, "Created {created}")?;
+            }
+            writeln!(
+                out,
+                "{} created, {} already present",
+                report.created.len(),
+                report.unchanged
+            )?;
+            Ok(())
+        }
+        Command::Migrate | Command::Jwt(_) => unreachable!("handled before connecting"),
+    }
+}
+
+/// The first line of `input`, without its line ending.
+fn read_secret(input: &mut impl BufRead) -> CliResult<String> {
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    let secret = line.trim_end_matches(['\r', '\n']).to_string();
+    if secret.is_empty() {
+        return Err("expected a secret on stdin".into());
+    }
+    Ok(secret)
+}
+
+fn generate_secret() -> String {
+    use rand::Rng;
+    rand::rng()
+        .sample_iter(rand::distr::Alphanumeric)
+        .take(GENERATED_SECRET_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// HOCON for a `jwt` block that signs with `secret` and keeps accepting tokens signed
+/// with the current secret until `accept_until`.
+fn rotated_jwt_config(
+    jwt: &JwtConfig,
+    secret: &str,
+    accept_until: chrono::DateTime<chrono::Utc>,
+) -> String {
+    format!(
+        "# Replace the jwt block with this, then restart the servers.\n\
+         jwt {{\n  \
+           secret = \"{secret}\"\n  \
+           issuer = \"{issuer}\"\n  \
+           legacy {{\n    \
+             issuer = \"{issuer}\"\n    \
+             secret = \"{current}\"\n    \
+             accept_until = \"{until}\"\n  \
+           }}\n\
+         }}\n",
+        issuer = jwt.issuer,
+        current = jwt.secret,
+        until = accept_until.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    async fn storage() -> DynStorage {
+        connect("sqlite::memory:").await.expect("storage")
+    }
+
+    async fn run_command(storage: &DynStorage, args: &[&str], stdin: &str) -> String {
+        let cli = Cli::try_parse_from(std::iter::once("oauth2-cli").chain(args.iter().copied()))
+            .expect("parse");
+        let mut out = Vec::new();
+        execute(cli.command, storage, stdin.as_bytes(), &mut out)
+            .await
+            .expect("execute");
+        String::from_utf8(out).expect("utf-8")
+    }
+
+    #[test]
+    fn cli_definition_is_valid() {
+        Cli::command().debug_assert();
+    }
+
+    #[tokio::test]
+    async fn creates_lists_and_deletes_clients() {
+        let storage = storage().await;
+
+        let out = run_command(
+            &storage,
+            &[
+                "clients",
+                "create",
+                "--client-id",
+                "billing",
+                "--grant-type",
+                "client_credentials",
+                "--scope",
+                "billing.read",
+                "--secret-stdin",
+            ],
+            "s3cret-from-stdin\n",
+        )
+        .await;
+        assert_eq!(out, "Created client billing\n");
+        let client = storage.get_client("billing", None).await.unwrap().unwrap();
+        assert!(client.verify_client_secret("s3cret-from-stdin"));
+
+        let out = run_command(&storage, &["clients", "list"], "").await;
+        assert!(out.contains("billing\tbilling\tclient_credentials\tbilling.read"));
+
+        run_command(&storage, &["clients", "delete", "billing"], "").await;
+        assert!(storage.get_client("billing", None).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn created_users_get_a_hashed_password() {
+        let storage = storage().await;
+
+        let out = run_command(
+            &storage,
+            &[
+                "users",
+                "create",
+                "--username",
+                "admin",
+                "--email",
+                "admin@example.com",
+            ],
+            "",
+        )
+        .await;
+        let password = out
+            .lines()
+            .find_map(|line| line.strip_prefix("password: "))
+            .expect("generated password");
+
+        let user = storage
+            .get_user_by_username("admin", None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(user.password_hash, password);
+        assert!(oauth2_core::verify_password(password, &user.password_hash));
+    }
+
+    #[tokio::test]
+    async fn exported_seed_imports_into_another_database() {
+        let source = storage().await;
+        run_command(
+            &source,
+            &[
+                "clients",
+                "create",
+                "--client-id",
+                "web",
+                "--redirect-uri",
+                "https://web.example/cb",
+                "--grant-type",
+                "authorization_code",
+                "--secret-stdin",
+            ],
+            "web-secret\n",
+        )
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("seed.json");
+        let path_arg = path.to_str().unwrap();
+        run_command(&source, &["seed", "export", "--output", path_arg], "").await;
+
+        let target = storage().await;
+        let out = run_command(&target, &["seed", "import", path_arg], "").await;
+        assert!(out.contains("Created client:web"), "{out}");
+        let client = target.get_client("web", None).await.unwrap().unwrap();
+        assert!(client.verify_client_secret("web-secret"));
+
+        let out = run_command(&target, &["seed", "import", path_arg], "").await;
+        assert!(out.ends_with("0 created, 1 already present\n"), "{out}");
+    }
+
+    #[test]
+    fn rotation_keeps_the_current_secret_as_legacy() {
+        let jwt: JwtConfig = serde_json::from_value(serde_json::json!({
+            "secret": "old-secret",
+            "issuer": "https://auth.example",
+        }))
+        .unwrap();
+        let until = chrono::DateTime::parse_from_rfc3339("2030-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+
+        let hocon = rotated_jwt_config(&jwt, "new-secret", until);
+        assert!(hocon.contains("  secret = \"new-secret\"\n"));
+        assert!(hocon.contains("    secret = \"old-secret\"\n"));
+        assert!(hocon.contains("accept_until = \"2030-01-01T00:00:00Z\""));
+    }
+}
@@ -0,0 +1,390 @@
+use base64::{engine::general_purpose, Engine as _};
+use rand::Rng;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use oauth2_core::OAuth2Error;
+
+use crate::id_token::{self, Expected, IdTokenClaims, JwksCache};
+use crate::{provider_error, ProviderMetadata};
+
+/// A started authorization code flow. Keep it (e.g. in the session) until the callback;
+/// it holds the PKCE verifier, `state` and `nonce` the response is checked against.
+#[derive(Debug, Clone)]
+pub struct AuthorizationRequest {
+    /// Where to send the user agent.
+    pub url: Url,
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+}
+
+/// The query parameters of the redirect back from the authorization endpoint.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuthorizationResponse {
+    pub code: String,
+    pub state: Option<String>,
+    /// RFC 9207 issuer identifier.
+    pub iss: Option<String>,
+}
+
+impl AuthorizationResponse {
+    /// Parse a callback query string. An `error` response becomes that error.
+    pub fn from_query(query: &str) -> Result<Self, OAuth2Error> {
+        let mut response = Self::default();
+        let (mut error, mut error_description) = (None, None);
+        for (name, value) in url::form_urlencoded::parse(query.as_bytes()) {
+            match name.as_ref() {
+                "code" => response.code = value.into_owned(),
+                "state" => response.state = Some(value.into_owned()),
+                "iss" => response.iss = Some(value.into_owned()),
+                "error" => error = Some(value.into_owned()),
+                "error_description" => error_description = Some(value.into_owned()),
+                _ => {}
+            }
+        }
+        if let Some(error) = error {
+            return Err(OAuth2Error::new(&error, error_description.as_deref()));
+        }
+        if response.code.is_empty() {
+            return Err(OAuth2Error::invalid_request(
+                "Authorization response has no code",
+            ));
+        }
+        Ok(response)
+    }
+}
+
+/// A token endpoint response (RFC 6749 section 5.1), plus the verified ID token claims
+/// when the provider returned an ID token.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenSet {
+    pub access_token: String,
+    pub token_type: String,
+    #[serde(default)]
+    pub expires_in: Option<i64>,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    #[serde(default)]
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub id_token: Option<String>,
+    #[serde(skip)]
+    pub id_token_claims: Option<IdTokenClaims>,
+}
+
+/// An OAuth2/OpenID Connect relying party for one provider.
+///
+/// Cheap to clone; clones share the HTTP connection pool and cached signing keys.
+#[derive(Clone)]
+pub struct OAuth2Client {
+    http: reqwest::Client,
+    metadata: ProviderMetadata,
+    client_id: String,
+    client_secret: Option<String>,
+    redirect_uri: Option<String>,
+    jwks: JwksCache,
+}
+
+impl OAuth2Client {
+    pub fn new(metadata: ProviderMetadata, client_id: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            metadata,
+            client_id: client_id.into(),
+            client_secret: None,
+            redirect_uri: None,
+            jwks: JwksCache::default(),
+        }
+    }
+
+    /// Build a client from the provider's discovery document.
+    pub async fn discover(issuer: &str, client_id: impl Into<String>) -> Result<Self, OAuth2Error> {
+        let http = reqwest::Client::new();
+        let metadata = ProviderMetadata::discover(&http, issuer).await?;
+        Ok(Self::new(metadata, client_id).with_http_client(http))
+    }
+
+    /// Confidential clients authenticate to the token endpoint with this secret
+    /// (`client_secret_post`).
+    pub fn with_client_secret(mut self, secret: impl Into<String>) -> Self {
+        self.client_secret = Some(secret.into());
+        self
+    }
+
+    pub fn with_redirect_uri(mut self, redirect_uri: impl Into<String>) -> Self {
+        self.redirect_uri = Some(redirect_uri.into());
+        self
+    }
+
+    /// Use `http` (timeouts, proxies, TLS roots) for every request.
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn metadata(&self) -> &ProviderMetadata {
+        &self.metadata
+    }
+
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    /// Start an authorization code flow for `scope` with PKCE (S256), `state` and `nonce`.
+    pub fn authorize_url(&self, scope: &str) -> Result<AuthorizationRequest, OAuth2Error> {
+        let mut url = Url::parse(&self.metadata.authorization_endpoint).map_err(|e| {
+            OAuth2Error::new(
+                "invalid_configuration",
+                Some(&format!("Invalid authorization_endpoint: {e}")),
+            )
+        })?;
+        let (state, nonce, code_verifier) = (random_token(), random_token(), random_token());
+        let challenge = general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(&code_verifier));
+        {
+            let mut query = url.query_pairs_mut();
+            query
+                .append_pair("response_type", "code")
+                .append_pair("client_id", &self.client_id)
+                .append_pair("scope", scope)
+                .append_pair("state", &state)
+                .append_pair("nonce", &nonce)
+                .append_pair("code_challenge", &challenge)
+                .append_pair("code_challenge_method", "S256");
+            if let Some(redirect_uri) = &self.redirect_uri {
+                query.append_pair("redirect_uri", redirect_uri);
+            }
+        }
+        Ok(AuthorizationRequest {
+            url,
+            state,
+            nonce,
+            code_verifier,
+        })
+    }
+
+    /// Finish the flow started by `request`: check `state` and `iss`, redeem the code and
+    /// verify any ID token against the request's nonce.
+    pub async fn exchange_code(
+        &self,
+        request: &AuthorizationRequest,
+        response: &AuthorizationResponse,
+    ) -> Result<TokenSet, OAuth2Error> {
+        if response.state.as_deref() != Some(request.state.as_str()) {
+            return Err(OAuth2Error::invalid_request(
+                "Authorization response state mismatch",
+            ));
+        }
+        // RFC 9207: reject responses from another issuer (mix-up attacks).
+        match &response.iss {
+            Some(iss) if *iss != self.metadata.issuer => {
+                return Err(OAuth2Error::invalid_request(
+                    "Authorization response issuer mismatch",
+                ))
+            }
+            None if self.metadata.authorization_response_iss_parameter_supported => {
+                return Err(OAuth2Error::invalid_request(
+                    "Authorization response is missing iss",
+                ))
+            }
+            _ => {}
+        }
+
+        self.redeem_code(
+            &response.code,
+            Some(&request.code_verifier),
+            Some(&request.nonce),
+        )
+        .await
+    }
+
+    /// Redeem `code` for a flow whose `state` the caller has already checked, e.g. one
+    /// tracked in server-side storage rather than an [`AuthorizationRequest`]. Any ID
+    /// token is verified, against `nonce` when given.
+    pub async fn redeem_code(
+        &self,
+        code: &str,
+        code_verifier: Option<&str>,
+        nonce: Option<&str>,
+    ) -> Result<TokenSet, OAuth2Error> {
+        let mut params = vec![("grant_type", "authorization_code"), ("code", code)];
+        if let Some(code_verifier) = code_verifier {
+            params.push(("code_verifier", code_verifier));
+        }
+        if let Some(redirect_uri) = &self.redirect_uri {
+            params.push(("redirect_uri", redirect_uri));
+        }
+        let mut tokens = self.token_request(&params).await?;
+        if let Some(id_token) = &tokens.id_token {
+            tokens.id_token_claims = Some(self.verify_id_token(id_token, nonce).await?);
+        }
+        Ok(tokens)
+    }
+
+    /// Redeem a refresh token, optionally narrowing `scope`.
+    pub async fn refresh(
+        &self,
+        refresh_token: &str,
+        scope: Option<&str>,
+    ) -> Result<TokenSet, OAuth2Error> {
+        let mut params = vec![
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+        ];
+        if let Some(scope) = scope {
+            params.push(("scope", scope));
+        }
+        let mut tokens = self.token_request(&params).await?;
+        if let Some(id_token) = &tokens.id_token {
+            tokens.id_token_claims = Some(self.verify_id_token(id_token, None).await?);
+        }
+        Ok(tokens)
+    }
+
+    /// Client credentials grant, for service-to-service calls.
+    pub async fn client_credentials(&self, scope: Option<&str>) -> Result<TokenSet, OAuth2Error> {
+        let mut params = vec![("grant_type", "client_credentials")];
+        if let Some(scope) = scope {
+            params.push(("scope", scope));
+        }
+        self.token_request(&params).await
+    }
+
+    /// Verify an ID token's signature (against `jwks_uri`, or the client secret for HMAC
+    /// algorithms) and its `iss`, `aud`, `exp` and, when given, `nonce`.
+    pub async fn verify_id_token(
+        &self,
+        id_token: &str,
+        nonce: Option<&str>,
+    ) -> Result<IdTokenClaims, OAuth2Error> {
+        let header = jsonwebtoken::decode_header(id_token)
+            .map_err(|e| OAuth2Error::invalid_token(&format!("Invalid ID token: {e}")))?;
+        let key = if id_token::is_hmac(header.alg) {
+            id_token::hmac_key(self.client_secret.as_deref())?
+        } else {
+            let jwks_uri =
+                self.metadata.jwks_uri.as_deref().ok_or_else(|| {
+                    OAuth2Error::invalid_token("Provider metadata has no jwks_uri")
+                })?;
+            self.jwks
+                .decoding_key(&self.http, jwks_uri, header.kid.as_deref())
+                .await?
+        };
+        id_token::verify(
+            id_token,
+            header.alg,
+            &key,
+            &Expected {
+                issuer: &self.metadata.issuer,
+                client_id: &self.client_id,
+                nonce,
+            },
+        )
+    }
+
+    async fn token_request(&self, params: &[(&str, &str)]) -> Result<TokenSet, OAuth2Error> {
+        let mut form = params.to_vec();
+        form.push(("client_id", &self.client_id));
+        if let Some(secret) = &self.client_secret {
+            form.push(("client_secret", secret));
+        }
+
+        let response = self
+            .http
+            .post(&self.metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .map_err(provider_error)?;
+        let status = response.status();
+        if !status.is_success() {
+            // RFC 6749 section 5.2 error body, when the provider sent one.
+            let body = response.text().await.map_err(provider_error)?;
+            return Err(
+                serde_json::from_str::<OAuth2Error>(&body).unwrap_or_else(|_| {
+                    OAuth2Error::new(
+                        "provider_error",
+                        Some(&format!("Token endpoint returned {status}")),
+                    )
+                }),
+            );
+        }
+        response.json().await.map_err(provider_error)
+    }
+}
+
+/// 256 bits of randomness, base64url-encoded: 43 characters, a valid PKCE verifier.
+fn random_token() -> String {
+    let bytes: [u8; 32] = rand::rng().random();
+    general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> OAuth2Client {
+        let metadata: ProviderMetadata = serde_json::from_value(serde_json::json!({
+            "issuer": "https://idp.example",
+            "authorization_endpoint": "https://idp.example/oauth/authorize",
+            "token_endpoint": "https://idp.example/oauth/token",
+            "token_introspection_endpoint": "https://idp.example/oauth/introspect",
+            "authorization_response_iss_parameter_supported": true,
+        }))
+        .unwrap();
+        OAuth2Client::new(metadata, "app").with_redirect_uri("https://app.example/cb")
+    }
+
+    #[test]
+    fn authorize_urls_carry_pkce_state_and_nonce() {
+        let client = client();
+        assert_eq!(
+            client.metadata().introspection_endpoint.as_deref(),
+            Some("https://idp.example/oauth/introspect")
+        );
+
+        let request = client.authorize_url("openid email").unwrap();
+        let query: std::collections::HashMap<_, _> = request.url.query_pairs().collect();
+        assert_eq!(query["client_id"], "app");
+        assert_eq!(query["scope"], "openid email");
+        assert_eq!(query["state"], request.state.as_str());
+        assert_eq!(query["nonce"], request.nonce.as_str());
+        assert_eq!(query["code_challenge_method"], "S256");
+        assert_eq!(
+            query["code_challenge"],
+            general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(&request.code_verifier))
+        );
+        assert_eq!(request.code_verifier.len(), 43);
+        assert_ne!(request.state, client.authorize_url("openid").unwrap().state);
+    }
+
+    #[tokio::test]
+    async fn callbacks_are_checked_before_the_code_is_redeemed() {
+        let client = client();
+        let request = client.authorize_url("openid").unwrap();
+
+        let err = AuthorizationResponse::from_query("error=access_denied&state=x").unwrap_err();
+        assert_eq!(err.error, "access_denied");
+
+        // Neither reaches the token endpoint.
+        let forged =
+            AuthorizationResponse::from_query("code=c&state=other&iss=https%3A%2F%2Fidp.example")
+                .unwrap();
+        let err = client.exchange_code(&request, &forged).await.unwrap_err();
+        assert_eq!(
+            err.error_description.as_deref(),
+            Some("Authorization response state mismatch")
+        );
+        let mixed_up = AuthorizationResponse {
+            code: "c".to_string(),
+            state: Some(request.state.clone()),
+            iss: Some("https://evil.example".to_string()),
+        };
+        let err = client.exchange_code(&request, &mixed_up).await.unwrap_err();
+        assert_eq!(
+            err.error_description.as_deref(),
+            Some("Authorization response issuer mismatch")
+        );
+    }
+}
@@ -0,0 +1,222 @@
+use std::sync::{Arc, RwLock};
+
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+
+use oauth2_core::OAuth2Error;
+
+use crate::provider_error;
+
+/// Clock-skew tolerance for `exp`, `nbf` and `iat`.
+const LEEWAY_SECS: u64 = 60;
+
+/// `aud` is a single string or an array (RFC 7519 section 4.1.3).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    pub fn contains(&self, client_id: &str) -> bool {
+        match self {
+            Self::One(aud) => aud == client_id,
+            Self::Many(auds) => auds.iter().any(|aud| aud == client_id),
+        }
+    }
+
+    fn is_multiple(&self) -> bool {
+        matches!(self, Self::Many(auds) if auds.len() > 1)
+    }
+}
+
+/// Verified ID token claims (OpenID Connect Core section 2).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: Audience,
+    pub exp: i64,
+    pub iat: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub azp: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Every other claim, e.g. provider-specific profile fields.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// What an ID token is checked against.
+pub(crate) struct Expected<'a> {
+    pub issuer: &'a str,
+    pub client_id: &'a str,
+    pub nonce: Option<&'a str>,
+}
+
+/// Signing keys from the provider's `jwks_uri`, fetched on first use and again when a
+/// token names a key the cached set lacks (key rotation).
+#[derive(Clone, Default)]
+pub(crate) struct JwksCache {
+    keys: Arc<RwLock<Option<Arc<JwkSet>>>>,
+}
+
+impl JwksCache {
+    async fn fetch(&self, http: &reqwest::Client, jwks_uri: &str) -> Result<(), OAuth2Error> {
+        let set: JwkSet = http
+            .get(jwks_uri)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)?;
+        *self.keys.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(set));
+        Ok(())
+    }
+
+    fn lookup(&self, kid: Option<&str>) -> Option<DecodingKey> {
+        let keys = self
+            .keys
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()?;
+        let jwk = match kid {
+            Some(kid) => keys.find(kid)?,
+            // Without a `kid` the set must be unambiguous.
+            None if keys.keys.len() == 1 => &keys.keys[0],
+            None => return None,
+        };
+        DecodingKey::from_jwk(jwk).ok()
+    }
+
+    pub(crate) async fn decoding_key(
+        &self,
+        http: &reqwest::Client,
+        jwks_uri: &str,
+        kid: Option<&str>,
+    ) -> Result<DecodingKey, OAuth2Error> {
+        if let Some(key) = self.lookup(kid) {
+            return Ok(key);
+        }
+        self.fetch(http, jwks_uri).await?;
+        self.lookup(kid)
+            .ok_or_else(|| OAuth2Error::invalid_token("ID token signing key not found"))
+    }
+}
+
+/// Key for an HMAC-signed ID token: the client secret (OpenID Connect Core section 10.1).
+pub(crate) fn hmac_key(client_secret: Option<&str>) -> Result<DecodingKey, OAuth2Error> {
+    client_secret
+        .map(|secret| DecodingKey::from_secret(secret.as_bytes()))
+        .ok_or_else(|| OAuth2Error::invalid_token("HMAC-signed ID token needs a client secret"))
+}
+
+pub(crate) fn is_hmac(alg: Algorithm) -> bool {
+    matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512)
+}
+
+/// Verify the signature with `key` and the claims against `expected` (OpenID Connect Core
+/// section 3.1.3.7).
+pub(crate) fn verify(
+    id_token: &str,
+    alg: Algorithm,
+    key: &DecodingKey,
+    expected: &Expected<'_>,
+) -> Result<IdTokenClaims, OAuth2Error> {
+    let mut validation = Validation::new(alg);
+    validation.leeway = LEEWAY_SECS;
+    validation.set_issuer(&[expected.issuer]);
+    validation.set_audience(&[expected.client_id]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+    let claims = jsonwebtoken::decode::<IdTokenClaims>(id_token, key, &validation)
+        .map_err(|e| OAuth2Error::invalid_token(&format!("Invalid ID token: {e}")))?
+        .claims;
+
+    if claims.aud.is_multiple() && claims.azp.as_deref() != Some(expected.client_id) {
+        return Err(OAuth2Error::invalid_token(
+            "ID token has several audiences but was not authorized for this client",
+        ));
+    }
+    if let Some(nonce) = expected.nonce {
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(OAuth2Error::invalid_token("ID token nonce mismatch"));
+        }
+    }
+    Ok(claims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{EncodingKey, Header};
+    use serde_json::json;
+
+    const SECRET: &str = "client_secret";
+
+    fn sign(claims: serde_json::Value) -> String {
+        jsonwebtoken::encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(SECRET.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    fn claims(aud: serde_json::Value, nonce: &str) -> serde_json::Value {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        json!({
+            "iss": "https://idp.example",
+            "sub": "alice",
+            "aud": aud,
+            "exp": now + 300,
+            "iat": now,
+            "nonce": nonce,
+            "groups": ["admins"],
+        })
+    }
+
+    fn check(id_token: &str) -> Result<IdTokenClaims, OAuth2Error> {
+        verify(
+            id_token,
+            Algorithm::HS256,
+            &hmac_key(Some(SECRET)).unwrap(),
+            &Expected {
+                issuer: "https://idp.example",
+                client_id: "app",
+                nonce: Some("n-1"),
+            },
+        )
+    }
+
+    #[test]
+    fn id_tokens_are_checked_against_issuer_audience_and_nonce() {
+        let verified = check(&sign(claims(json!("app"), "n-1"))).unwrap();
+        assert_eq!(verified.sub, "alice");
+        assert_eq!(verified.extra["groups"], json!(["admins"]));
+
+        let err = check(&sign(claims(json!("app"), "n-2"))).unwrap_err();
+        assert_eq!(
+            err.error_description.as_deref(),
+            Some("ID token nonce mismatch")
+        );
+        assert!(check(&sign(claims(json!("other"), "n-1"))).is_err());
+
+        // Several audiences need `azp` naming this client.
+        assert!(check(&sign(claims(json!(["app", "other"]), "n-1"))).is_err());
+        let mut with_azp = claims(json!(["app", "other"]), "n-1");
+        with_azp["azp"] = json!("app");
+        assert!(check(&sign(with_azp)).is_ok());
+    }
+}
@@ -0,0 +1,32 @@
+//! OAuth2/OpenID Connect relying party.
+//!
+//! Talks to this server or any compliant identity provider: discovery, authorization URLs
+//! with PKCE, `state` and `nonce`, code exchange, refresh, client credentials and ID token
+//! validation against the provider's JWKS.
+//!
+//! ```ignore
+//! let client = OAuth2Client::discover("https://auth.example.com", "my_app")
+//!     .await?
+//!     .with_client_secret(secret)
+//!     .with_redirect_uri("https://app.example.com/callback");
+//!
+//! let request = client.authorize_url("openid email")?;
+//! // redirect to request.url, keep `request` in the session, then on the callback:
+//! let response = AuthorizationResponse::from_query(callback_query)?;
+//! let tokens = client.exchange_code(&request, &response).await?;
+//! ```
+
+mod client;
+mod id_token;
+mod metadata;
+
+pub use client::{AuthorizationRequest, AuthorizationResponse, OAuth2Client, TokenSet};
+pub use id_token::{Audience, IdTokenClaims};
+pub use metadata::ProviderMetadata;
+
+use oauth2_core::OAuth2Error;
+
+/// The provider could not be reached or sent something unreadable.
+fn provider_error(e: impl std::fmt::Display) -> OAuth2Error {
+    OAuth2Error::new("provider_error", Some(&e.to_string()))
+}
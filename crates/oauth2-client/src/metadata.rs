@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+use oauth2_core::OAuth2Error;
+
+use crate::provider_error;
+
+/// Authorization server metadata (RFC 8414 / OpenID Connect Discovery 1.0).
+///
+/// Only the members the client uses; others are ignored.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwks_uri: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub userinfo_endpoint: Option<String>,
+    // This server advertises the `token_`-prefixed names.
+    #[serde(
+        default,
+        alias = "token_introspection_endpoint",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub introspection_endpoint: Option<String>,
+    #[serde(
+        default,
+        alias = "token_revocation_endpoint",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub revocation_endpoint: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub code_challenge_methods_supported: Vec<String>,
+    /// RFC 9207: the authorization response carries `iss`.
+    #[serde(default)]
+    pub authorization_response_iss_parameter_supported: bool,
+}
+
+impl ProviderMetadata {
+    /// Fetch `{issuer}/.well-known/openid-configuration` and check that it describes
+    /// `issuer` (OpenID Connect Discovery section 4.3).
+    pub async fn discover(http: &reqwest::Client, issuer: &str) -> Result<Self, OAuth2Error> {
+        let issuer = issuer.trim_end_matches('/');
+        let url = format!("{issuer}/.well-known/openid-configuration");
+        let response = http
+            .get(&url)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(provider_error)?;
+        let metadata: Self = response.json().await.map_err(provider_error)?;
+
+        if metadata.issuer.trim_end_matches('/') != issuer {
+            return Err(OAuth2Error::new(
+                "provider_error",
+                Some(&format!(
+                    "Discovery document for {issuer} names issuer {}",
+                    metadata.issuer
+                )),
+            ));
+        }
+        Ok(metadata)
+    }
+}
@@ -0,0 +1,212 @@
+//! Layered loading: `application.conf`, then `application.{profile}.conf`, then the
+//! environment.
+//!
+//! Later layers override earlier ones key by key, so a profile file only needs the
+//! settings that differ from the base file. [`origins`] reports which layer supplied
+//! each setting, for `GET /admin/config`.
+
+use hocon::{Error, Hocon, HoconLoader};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the active profile, e.g. `prod` for `application.prod.conf`.
+pub const PROFILE_ENV: &str = "OAUTH2_PROFILE";
+
+/// List settings that environment variables replace after the files are merged; HOCON
+/// can't substitute arrays from the environment.
+const LIST_OVERRIDES: &[(&str, &str)] = &[
+    ("events.event_types", "OAUTH2_EVENTS_TYPES"),
+    ("server.trusted_proxies", "OAUTH2_SERVER_TRUSTED_PROXIES"),
+    ("server.cors.allowed_origins", "OAUTH2_CORS_ALLOWED_ORIGINS"),
+    ("server.cors.allowed_methods", "OAUTH2_CORS_ALLOWED_METHODS"),
+    ("server.cors.allowed_headers", "OAUTH2_CORS_ALLOWED_HEADERS"),
+];
+
+/// The profile selected with `OAUTH2_PROFILE`, if any.
+pub fn active_profile() -> Option<String> {
+    std::env::var(PROFILE_ENV)
+        .ok()
+        .map(|profile| profile.trim().to_string())
+        .filter(|profile| !profile.is_empty())
+}
+
+/// Files to merge for `base`: the base file, then its profile file when a profile is
+/// active (`application.conf` + `prod` gives `application.prod.conf` next to it).
+pub fn config_layers(base: &Path) -> Vec<PathBuf> {
+    let mut layers = vec![base.to_path_buf()];
+    if let Some(profile) = active_profile() {
+        let stem = base
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("application");
+        layers.push(base.with_file_name(format!("{stem}.{profile}.conf")));
+    }
+    layers
+}
+
+/// Merge `paths` in order, later files overriding earlier ones.
+pub(crate) fn load(paths: &[PathBuf], loader: HoconLoader) -> Result<Hocon, Error> {
+    paths
+        .iter()
+        .try_fold(loader, |loader, path| loader.load_file(path))?
+        .hocon()
+}
+
+/// Which layer supplied each setting of the merged `paths`: a file path, or `env:VAR`
+/// when a `${?VAR}` substitution or list override won. Settings absent from every layer
+/// use built-in defaults and are not listed.
+pub(crate) fn origins(paths: &[PathBuf]) -> BTreeMap<String, String> {
+    let mut origins = BTreeMap::new();
+    let Ok(merged) = load(paths, HoconLoader::new()) else {
+        return origins;
+    };
+    // Without the environment, `${?VAR}` overrides stay unresolved and name their variable.
+    let without_env = load(paths, HoconLoader::new().no_system()).ok();
+    let files: Vec<(String, Option<Hocon>)> = paths
+        .iter()
+        .map(|path| {
+            let file = HoconLoader::new()
+                .no_system()
+                .load_file(path)
+                .and_then(HoconLoader::hocon)
+                .ok();
+            (path.display().to_string(), file)
+        })
+        .collect();
+
+    let mut leaves = Vec::new();
+    collect_leaves(&merged, String::new(), &mut leaves);
+    for key in leaves {
+        let env_var = without_env
+            .as_ref()
+            .and_then(|hocon| match lookup(hocon, &key) {
+                Some(Hocon::BadValue(Error::KeyNotFound { key })) if std::env::var(key).is_ok() => {
+                    Some(key.clone())
+                }
+                _ => None,
+            });
+        let origin = match env_var {
+            Some(var) => format!("env:{var}"),
+            None => files
+                .iter()
+                .rev()
+                .find(|(_, file)| {
+                    // An unset `${?VAR}` in a file leaves the earlier layer's value.
+                    file.as_ref()
+                        .and_then(|file| lookup(file, &key))
+                        .is_some_and(|value| !matches!(value, Hocon::BadValue(_)))
+                })
+                .or(files.first())
+                .map(|(path, _)| path.clone())
+                .unwrap_or_default(),
+        };
+        origins.insert(key, origin);
+    }
+
+    for (key, var) in LIST_OVERRIDES {
+        if std::env::var(var).is_ok() {
+            origins.insert(key.to_string(), format!("env:{var}"));
+        }
+    }
+    origins
+}
+
+/// Dotted paths of every value in `hocon`; arrays count as one value since a later
+/// layer replaces them whole.
+fn collect_leaves(hocon: &Hocon, prefix: String, leaves: &mut Vec<String>) {
+    match hocon {
+        Hocon::Hash(map) => {
+            for (key, value) in map {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                collect_leaves(value, path, leaves);
+            }
+        }
+        Hocon::BadValue(_) => {}
+        _ => leaves.push(prefix),
+    }
+}
+
+/// The value at a path collected by [`collect_leaves`], if the document has it.
+fn lookup<'a>(hocon: &'a Hocon, path: &str) -> Option<&'a Hocon> {
+    // Walk the path segment by segment; keys never contain dots in this config.
+    let mut current = hocon;
+    for segment in path.split('.') {
+        match current {
+            Hocon::Hash(map) => current = map.get(segment)?,
+            _ => return None,
+        }
+    }
+    Some(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    fn write(name: &str, hocon: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "oauth2-config-layers-{}-{name}.conf",
+            std::process::id()
+        ));
+        std::fs::write(&path, hocon).expect("write config");
+        path
+    }
+
+    #[test]
+    fn profile_file_overrides_base_and_origins_name_the_winner() {
+        std::env::set_var("OAUTH2_LAYERS_TEST_ISSUER", "https://env.example");
+        let base = write(
+            "base",
+            r#"
+            server { host = "127.0.0.1", port = 8080 }
+            database { url = "sqlite::memory:" }
+            jwt {
+                secret = "0123456789abcdef0123456789abcdef"
+                issuer = "https://base.example"
+                issuer = ${?OAUTH2_LAYERS_TEST_ISSUER}
+            }
+            events { enabled = false, backend = console, filter_mode = allow_all }
+            "#,
+        );
+        let profile = write(
+            "prod",
+            r#"
+            server.port = 9090
+            server.host = ${?OAUTH2_LAYERS_TEST_UNSET}
+            "#,
+        );
+        let layers = vec![base.clone(), profile.clone()];
+
+        let config = Config::from_hocon_layers(&layers).expect("load layers");
+        assert_eq!(config.server.host, "127.0.0.1");
+        assert_eq!(config.server.port, 9090);
+        assert_eq!(config.jwt.issuer, "https://env.example");
+
+        let origins = origins(&layers);
+        let base_name = base.display().to_string();
+        let profile_name = profile.display().to_string();
+        assert_eq!(origins["server.host"], base_name);
+        assert_eq!(origins["server.port"], profile_name);
+        assert_eq!(origins["database.url"], base_name);
+        assert_eq!(origins["jwt.issuer"], "env:OAUTH2_LAYERS_TEST_ISSUER");
+
+        std::fs::remove_file(base).ok();
+        std::fs::remove_file(profile).ok();
+    }
+
+    #[test]
+    fn missing_profile_file_is_an_error() {
+        let base = write("base-only", r#"server { host = "127.0.0.1", port = 8080 }"#);
+        let missing = base.with_file_name("oauth2-config-layers-missing.staging.conf");
+
+        let err = Config::from_hocon_layers(&[base.clone(), missing]).unwrap_err();
+        assert!(err.contains("not found"), "{err}");
+
+        std::fs::remove_file(base).ok();
+    }
+}
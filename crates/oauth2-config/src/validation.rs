@@ -0,0 +1,710 @@
+//! Checks a HOCON file against the settings [`Config`] understands.
+//!
+//! Loading is forgiving: keys nothing reads are ignored, and a file that fails to
+//! deserialize makes the server fall back to environment variables. This pass reports
+//! both, with the path of every offending key, plus sections an enabled feature needs.
+
+use crate::layers;
+use crate::SocialStateStore;
+use crate::{AwsCredentialsSource, Config, GcpCredentialsSource, SessionStoreBackend};
+use hocon::{Hocon, HoconLoader};
+use serde::de::{self, DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, SeqAccess};
+use serde::de::{value::MapAccessDeserializer, Visitor};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Result of [`Config::validate_file`] and [`Config::validate_layers`].
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub path: String,
+    /// The file does not parse or a value has the wrong type. On startup such a file is
+    /// ignored in favour of environment variables.
+    pub errors: Vec<String>,
+    /// Keys no setting reads, usually typos.
+    pub unknown_keys: Vec<UnknownKey>,
+    /// Settings an enabled feature cannot work without.
+    pub missing: Vec<String>,
+    /// Production readiness findings; reported but not fatal.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKey {
+    /// Dotted path, e.g. `server.prot`.
+    pub path: String,
+    /// A known key at the same level with a similar name.
+    pub did_you_mean: Option<String>,
+}
+
+impl ValidationReport {
+    /// No errors, unknown keys or missing sections.
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty() && self.unknown_keys.is_empty() && self.missing.is_empty()
+    }
+}
+
+impl fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if let Some(suggestion) = &self.did_you_mean {
+            write!(f, " (did you mean `{suggestion}`?)")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Configuration {}:", self.path)?;
+        for error in &self.errors {
+            writeln!(f, "  error: {error}")?;
+        }
+        for key in &self.unknown_keys {
+            writeln!(f, "  unknown key: {key}")?;
+        }
+        for missing in &self.missing {
+            writeln!(f, "  missing: {missing}")?;
+        }
+        for warning in &self.warnings {
+            writeln!(f, "  warning: {warning}")?;
+        }
+        let problems = self.errors.len() + self.unknown_keys.len() + self.missing.len();
+        if problems == 0 {
+            writeln!(f, "OK")
+        } else {
+            writeln!(f, "{problems} problem(s) found")
+        }
+    }
+}
+
+impl Config {
+    /// Validate a HOCON file (with its `${?VAR}` overrides applied) without starting
+    /// anything.
+    pub fn validate_file<P: AsRef<Path>>(path: P) -> ValidationReport {
+        Self::validate_layers(&[path.as_ref().to_path_buf()])
+    }
+
+    /// Validate HOCON files as merged by [`Config::from_hocon_layers`], e.g. a base file and
+    /// its profile file.
+    pub fn validate_layers(paths: &[PathBuf]) -> ValidationReport {
+        let mut report = ValidationReport {
+            path: paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" + "),
+            ..ValidationReport::default()
+        };
+        if let Some(path) = paths.iter().find(|path| !path.exists()) {
+            report
+                .errors
+                .push(format!("Configuration file not found: {}", path.display()));
+            return report;
+        }
+
+        let hocon = match layers::load(paths, HoconLoader::new()) {
+            Ok(hocon) => hocon,
+            Err(e) => {
+                report.errors.push(format!("Failed to parse HOCON: {e}"));
+                return report;
+            }
+        };
+
+        let unknown = RefCell::new(Vec::new());
+        if let Err(e) = Config::deserialize(Tracked::root(&hocon, &unknown)) {
+            report.errors.push(e.to_string());
+        }
+        report.unknown_keys = unknown.into_inner();
+
+        if report.errors.is_empty() {
+            match Self::from_hocon_layers(paths) {
+                Ok(config) => {
+                    report.missing = config.missing_settings();
+                    report.warnings = config.production_violations();
+                }
+                Err(e) => report.errors.push(e),
+            }
+        }
+        report
+    }
+
+    /// Settings the enabled features need but that are not configured.
+    pub fn missing_settings(&self) -> Vec<String> {
+        let mut missing = Vec::new();
+        let events = &self.events;
+
+        if events.enabled {
+            let backend = events.backend.as_str();
+            let section = match backend {
+                "console" | "in_memory" | "both" => None,
+                "redis" | "redis_streams" => events.redis_url.is_none().then_some("redis"),
+                "kafka" => events.kafka_brokers.is_none().then_some("kafka"),
+                "rabbit" | "rabbitmq" => events.rabbit_url.is_none().then_some("rabbit"),
+                "sns" | "sqs" => events.sns.is_none().then_some("sns"),
+                "pubsub" => events.pubsub.is_none().then_some("pubsub"),
+                other => {
+                    missing.push(format!(
+                        "events.backend \"{other}\" is not a known backend (in_memory, console, both, redis, kafka, rabbit, sns, pubsub)"
+                    ));
+                    None
+                }
+            };
+            if let Some(section) = section {
+                missing.push(format!(
+                    "events.{section} is required when events.backend = \"{backend}\""
+                ));
+            }
+
+            if let Some(sns) = events
+                .sns
+                .as_ref()
+                .filter(|_| matches!(backend, "sns" | "sqs"))
+            {
+                if sns.topic_arn.is_none() && sns.queue_url.is_none() {
+                    missing.push("events.sns needs topic_arn or queue_url".to_string());
+                }
+                if sns.credentials == AwsCredentialsSource::Static
+                    && (sns.access_key_id.is_none() || sns.secret_access_key.is_none())
+                {
+                    missing.push(
+                        "events.sns.credentials = \"static\" needs access_key_id and secret_access_key"
+                            .to_string(),
+                    );
+                }
+            }
+            if let Some(pubsub) = events.pubsub.as_ref().filter(|_| backend == "pubsub") {
+                if pubsub.credentials == GcpCredentialsSource::ServiceAccountFile
+                    && pubsub.credentials_file.is_none()
+                {
+                    missing.push(
+                        "events.pubsub.credentials = \"service_account_file\" needs credentials_file"
+                            .to_string(),
+                    );
+                }
+            }
+        }
+
+        let redis_url = self.cache.as_ref().and_then(|c| c.redis_url.as_ref());
+        if redis_url.is_none() {
+            if self
+                .session
+                .as_ref()
+                .is_some_and(|s| s.store == SessionStoreBackend::Redis)
+            {
+                missing
+                    .push("cache.redis_url is required when session.store = \"redis\"".to_string());
+            }
+            if self
+                .social
+                .as_ref()
+                .is_some_and(|s| s.state_store == SocialStateStore::Redis)
+            {
+                missing.push(
+                    "cache.redis_url is required when social.state_store = \"redis\"".to_string(),
+                );
+            }
+        }
+
+        if self.mail.as_ref().is_some_and(|mail| mail.enabled) && self.server.issuer.is_none() {
+            missing.push(
+                "server.issuer is required when mail.enabled, to build the links in mail"
+                    .to_string(),
+            );
+        }
+
+        for (name, provider) in self.enabled_social_providers() {
+            if provider.client_id.is_none() {
+                missing.push(format!(
+                    "social.{name}.client_id is required when the provider is enabled"
+                ));
+            }
+        }
+
+        missing
+    }
+}
+
+/// Deserialization error with the path of the value it is about.
+#[derive(Debug)]
+struct Error {
+    path: Option<String>,
+    message: String,
+}
+
+impl Error {
+    fn at(mut self, path: &str) -> Self {
+        if self.path.is_none() && !path.is_empty() {
+            self.path = Some(path.to_string());
+        }
+        self
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self {
+            path: None,
+            message: msg.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{path}: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Deserializer over a resolved HOCON value that remembers where it is and records the
+/// keys the target type ignores. Scalars are coerced like the `hocon` crate does, since
+/// `${?VAR}` overrides always arrive as strings.
+struct Tracked<'a> {
+    value: &'a Hocon,
+    path: String,
+    /// Fields of the struct this value sits in, for suggestions.
+    siblings: &'static [&'static str],
+    unknown: &'a RefCell<Vec<UnknownKey>>,
+}
+
+impl<'a> Tracked<'a> {
+    fn root(value: &'a Hocon, unknown: &'a RefCell<Vec<UnknownKey>>) -> Self {
+        Self {
+            value,
+            path: String::new(),
+            siblings: &[],
+            unknown,
+        }
+    }
+
+    fn invalid(&self, expected: &str) -> Error {
+        let found = match self.value {
+            Hocon::Real(v) => format!("number {v}"),
+            Hocon::Integer(v) => format!("integer {v}"),
+            Hocon::String(v) => format!("string {v:?}"),
+            Hocon::Boolean(v) => format!("boolean {v}"),
+            Hocon::Array(_) => "a list".to_string(),
+            Hocon::Hash(_) => "an object".to_string(),
+            Hocon::Null | Hocon::BadValue(_) => "nothing".to_string(),
+        };
+        Error {
+            path: Some(self.path.clone()),
+            message: format!("expected {expected}, found {found}"),
+        }
+    }
+
+    fn map(&self, fields: &'static [&'static str]) -> Result<TrackedMap<'a>, Error> {
+        match self.value {
+            Hocon::Hash(hash) => Ok(TrackedMap {
+                entries: hash
+                    .iter()
+                    // Unset optional substitutions leave nothing behind.
+                    .filter(|(_, value)| !matches!(value, Hocon::BadValue(_)))
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+                value: None,
+                path: self.path.clone(),
+                fields,
+                unknown: self.unknown,
+            }),
+            _ => Err(self.invalid("an object")),
+        }
+    }
+
+    fn seq(&self) -> Result<TrackedSeq<'a>, Error> {
+        match self.value {
+            Hocon::Array(items) => Ok(TrackedSeq {
+                items: items.iter().enumerate(),
+                path: self.path.clone(),
+                unknown: self.unknown,
+            }),
+            _ => Err(self.invalid("a list")),
+        }
+    }
+}
+
+macro_rules! deserialize_integer {
+    ($($method:ident)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            match self.value.as_i64() {
+                Some(v) => visitor.visit_i64(v).map_err(|e: Error| e.at(&self.path)),
+                None => Err(self.invalid("an integer")),
+            }
+        }
+    )*};
+}
+
+impl<'de> Deserializer<'de> for Tracked<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let result = match self.value {
+            Hocon::Real(v) => visitor.visit_f64(*v),
+            Hocon::Integer(v) => visitor.visit_i64(*v),
+            Hocon::String(v) => visitor.visit_str(v),
+            Hocon::Boolean(v) => visitor.visit_bool(*v),
+            Hocon::Array(_) => visitor.visit_seq(self.seq()?),
+            Hocon::Hash(_) => visitor.visit_map(self.map(&[])?),
+            Hocon::Null | Hocon::BadValue(_) => visitor.visit_unit(),
+        };
+        result.map_err(|e: Error| e.at(&self.path))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value.as_bool() {
+            Some(v) => visitor.visit_bool(v).map_err(|e: Error| e.at(&self.path)),
+            None => Err(self.invalid("a boolean")),
+        }
+    }
+
+    deserialize_integer! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value.as_f64() {
+            Some(v) => visitor.visit_f64(v).map_err(|e: Error| e.at(&self.path)),
+            None => Err(self.invalid("a number")),
+        }
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value.as_string() {
+            Some(v) => visitor.visit_string(v).map_err(|e: Error| e.at(&self.path)),
+            None => Err(self.invalid("a string")),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Hocon::Null | Hocon::BadValue(_) => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor
+            .visit_seq(self.seq()?)
+            .map_err(|e: Error| e.at(&self.path))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor
+            .visit_map(self.map(&[])?)
+            .map_err(|e: Error| e.at(&self.path))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor
+            .visit_map(self.map(fields)?)
+            .map_err(|e: Error| e.at(&self.path))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let result = match self.value {
+            Hocon::String(v) => visitor.visit_enum(v.as_str().into_deserializer()),
+            Hocon::Hash(_) => visitor.visit_enum(MapAccessDeserializer::new(self.map(&[])?)),
+            _ => return Err(self.invalid("a string")),
+        };
+        result.map_err(|e: Error| e.at(&self.path))
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let key = self.path.rsplit('.').next().unwrap_or_default();
+        let did_you_mean = closest(key, self.siblings).map(str::to_string);
+        self.unknown.borrow_mut().push(UnknownKey {
+            path: self.path,
+            did_you_mean,
+        });
+        visitor.visit_unit()
+    }
+}
+
+struct TrackedMap<'a> {
+    entries: std::vec::IntoIter<(&'a String, &'a Hocon)>,
+    value: Option<(&'a String, &'a Hocon)>,
+    path: String,
+    fields: &'static [&'static str],
+    unknown: &'a RefCell<Vec<UnknownKey>>,
+}
+
+impl<'de> MapAccess<'de> for TrackedMap<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+        self.value = Some((key, value));
+        seed.deserialize(key.as_str().into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (key, value) = self
+            .value
+            .take()
+            .ok_or_else(|| <Error as de::Error>::custom("value requested before key"))?;
+        let path = if self.path.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{key}", self.path)
+        };
+        seed.deserialize(Tracked {
+            value,
+            path,
+            siblings: self.fields,
+            unknown: self.unknown,
+        })
+    }
+}
+
+struct TrackedSeq<'a> {
+    items: std::iter::Enumerate<std::slice::Iter<'a, Hocon>>,
+    path: String,
+    unknown: &'a RefCell<Vec<UnknownKey>>,
+}
+
+impl<'de> SeqAccess<'de> for TrackedSeq<'_> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        let Some((index, item)) = self.items.next() else {
+            return Ok(None);
+        };
+        seed.deserialize(Tracked {
+            value: item,
+            path: format!("{}[{index}]", self.path),
+            siblings: &[],
+            unknown: self.unknown,
+        })
+        .map(Some)
+    }
+}
+
+/// The entry of `candidates` within two edits of `key`, if any.
+fn closest(key: &str, candidates: &[&'static str]) -> Option<&'static str> {
+    candidates
+        .iter()
+        .map(|candidate| (edit_distance(key, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(hocon: &str) -> ValidationReport {
+        let path = std::env::temp_dir().join(format!(
+            "oauth2-config-validation-{}-{:?}.conf",
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        std::fs::write(&path, hocon).expect("write config");
+        let report = Config::validate_file(&path);
+        std::fs::remove_file(&path).ok();
+        report
+    }
+
+    const MINIMAL: &str = r#"
+        server { host = "127.0.0.1", port = 8080 }
+        database { url = "sqlite::memory:" }
+        jwt { secret = "0123456789abcdef0123456789abcdef", issuer = "test" }
+    "#;
+
+    #[test]
+    fn minimal_config_is_valid() {
+        let report = validate(&format!(
+            "{MINIMAL} events {{ enabled = true, backend = console, filter_mode = allow_all }}"
+        ));
+        assert!(report.is_valid(), "{report}");
+    }
+
+    #[test]
+    fn unknown_keys_are_reported_with_suggestions() {
+        let report = validate(&format!(
+            "{MINIMAL} events {{ enabled = true, backend = console, filter_mode = allow_all, backnd = kafka }}
+             server.cors {{ allowed_orgins = [] }}
+             colour = blue"
+        ));
+        assert!(report.errors.is_empty(), "{report}");
+        let unknown: Vec<String> = report.unknown_keys.iter().map(|k| k.to_string()).collect();
+        assert!(
+            unknown.contains(&"events.backnd (did you mean `backend`?)".to_string()),
+            "{unknown:?}"
+        );
+        assert!(
+            unknown.contains(
+                &"server.cors.allowed_orgins (did you mean `allowed_origins`?)".to_string()
+            ),
+            "{unknown:?}"
+        );
+        assert!(unknown.contains(&"colour".to_string()), "{unknown:?}");
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn type_errors_name_the_key() {
+        let report = validate(&format!(
+            "{MINIMAL} server.port = eighty
+             events {{ enabled = true, backend = console, filter_mode = allow_all }}"
+        ));
+        assert_eq!(
+            report.errors,
+            vec![r#"server.port: expected an integer, found string "eighty""#.to_string()]
+        );
+
+        let report = validate(r#"server { host = "127.0.0.1", port = 8080 }"#);
+        assert_eq!(report.errors, vec!["missing field `database`".to_string()]);
+    }
+
+    #[test]
+    fn enabled_backends_need_their_section() {
+        let report = validate(&format!(
+            "{MINIMAL} events {{ enabled = true, backend = kafka, filter_mode = allow_all }}"
+        ));
+        assert_eq!(
+            report.missing,
+            vec![r#"events.kafka is required when events.backend = "kafka""#.to_string()]
+        );
+
+        let report = validate(&format!(
+            r#"{MINIMAL} events {{
+                 enabled = true, backend = kafka, filter_mode = allow_all
+                 kafka {{ brokers = "kafka:9092", topic = "events" }}
+               }}"#
+        ));
+        assert!(report.missing.is_empty(), "{report}");
+
+        let report = validate(&format!(
+            "{MINIMAL} events {{ enabled = false, backend = kafka, filter_mode = allow_all }}"
+        ));
+        assert!(report.missing.is_empty(), "{report}");
+    }
+
+    #[test]
+    fn mail_needs_the_issuer() {
+        let mail = r#"events { enabled = false, backend = console, filter_mode = allow_all }
+                      mail { enabled = true, from = "no-reply@example.com" }"#;
+        let report = validate(&format!("{MINIMAL} {mail}"));
+        assert_eq!(
+            report.missing,
+            vec![
+                "server.issuer is required when mail.enabled, to build the links in mail"
+                    .to_string()
+            ]
+        );
+
+        let with_issuer = MINIMAL.replace(
+            "port = 8080 }",
+            r#"port = 8080, issuer = "https://auth.example.com" }"#,
+        );
+        let report = validate(&format!("{with_issuer} {mail}"));
+        assert!(report.missing.is_empty(), "{report}");
+    }
+
+    #[test]
+    fn suggestions_need_a_close_match() {
+        assert_eq!(closest("prot", &["host", "port"]), Some("port"));
+        assert_eq!(closest("colour", &["host", "port"]), None);
+    }
+}
@@ -4,5 +4,7 @@
 //! fork the main `rust-oauth2-server` repository.
 
 pub mod models;
+pub mod redaction;
 
 pub use models::*;
+pub use redaction::{RedactionPolicy, Redactor, Sensitive};
@@ -0,0 +1,104 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::error::OAuth2Error;
+use super::token::Claims;
+
+/// Custom claim an embedder's claims enricher may set to grant an admin role, as an
+/// alternative to the role scopes.
+pub const ADMIN_ROLE_CLAIM: &str = "admin_role";
+
+/// Scope granting `security_admin` unless a deployment configures another.
+pub const DEFAULT_ADMIN_SCOPE: &str = "admin";
+
+/// Role for the admin API, ordered by privilege: each role can do everything the ones
+/// below it can.
+///
+/// - `viewer`: read-only access to the admin API.
+/// - `operator`: manages users, service accounts and clients.
+/// - `security_admin`: revokes tokens, approves redirect URI changes and manages
+///   resource server credentials.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminRole {
+    Viewer,
+    Operator,
+    SecurityAdmin,
+}
+
+impl AdminRole {
+    pub const ALL: [AdminRole; 3] = [Self::Viewer, Self::Operator, Self::SecurityAdmin];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Viewer => "viewer",
+            Self::Operator => "operator",
+            Self::SecurityAdmin => "security_admin",
+        }
+    }
+
+    /// Scope granting this role. The plain `admin` scope also grants `security_admin`.
+    pub fn scope(&self) -> &'static str {
+        match self {
+            Self::Viewer => "admin:viewer",
+            Self::Operator => "admin:operator",
+            Self::SecurityAdmin => "admin:security_admin",
+        }
+    }
+
+    /// Role granted by a single scope, if any.
+    pub fn from_scope(scope: &str) -> Option<Self> {
+        Self::from_scope_with_admin_scope(scope, DEFAULT_ADMIN_SCOPE)
+    }
+
+    fn from_scope_with_admin_scope(scope: &str, admin_scope: &str) -> Option<Self> {
+        if scope == admin_scope {
+            return Some(Self::SecurityAdmin);
+        }
+        Self::ALL.into_iter().find(|role| role.scope() == scope)
+    }
+
+    /// Highest role granted by a token's scopes or its [`ADMIN_ROLE_CLAIM`].
+    pub fn from_claims(claims: &Claims) -> Option<Self> {
+        Self::from_claims_with_admin_scope(claims, DEFAULT_ADMIN_SCOPE)
+    }
+
+    /// As [`from_claims`](Self::from_claims), with `admin_scope` instead of `admin`
+    /// granting `security_admin`.
+    pub fn from_claims_with_admin_scope(claims: &Claims, admin_scope: &str) -> Option<Self> {
+        let from_claim = claims
+            .claim(ADMIN_ROLE_CLAIM)
+            .and_then(|value| value.as_str())
+            .and_then(|value| value.parse().ok());
+        claims
+            .scope
+            .split_whitespace()
+            .filter_map(|scope| Self::from_scope_with_admin_scope(scope, admin_scope))
+            .chain(from_claim)
+            .max()
+    }
+
+    /// Whether this role may act where `required` is needed.
+    pub fn allows(&self, required: AdminRole) -> bool {
+        *self >= required
+    }
+}
+
+impl fmt::Display for AdminRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AdminRole {
+    type Err = OAuth2Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|role| role.as_str() == s)
+            .ok_or_else(|| OAuth2Error::invalid_request(&format!("Unknown admin role '{s}'")))
+    }
+}
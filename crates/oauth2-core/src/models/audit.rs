@@ -0,0 +1,202 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[cfg(feature = "actix")]
+use actix_web::HttpRequest;
+
+use super::error::OAuth2Error;
+
+/// A security-relevant action recorded in the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    /// A user signed in, e.g. with a magic link or a social or SAML login.
+    Login,
+    /// A user approved a client at the authorization endpoint.
+    Consent,
+    /// The token endpoint issued (or refused) tokens.
+    TokenIssued,
+    /// A write through the admin API.
+    AdminChange,
+}
+
+impl AuditAction {
+    pub const ALL: [AuditAction; 4] = [
+        Self::Login,
+        Self::Consent,
+        Self::TokenIssued,
+        Self::AdminChange,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Login => "login",
+            Self::Consent => "consent",
+            Self::TokenIssued => "token_issued",
+            Self::AdminChange => "admin_change",
+        }
+    }
+}
+
+impl std::str::FromStr for AuditAction {
+    type Err = OAuth2Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|action| action.as_str() == s)
+            .ok_or_else(|| OAuth2Error::invalid_request(&format!("Unknown audit action: {s}")))
+    }
+}
+
+/// Whether an audited action went through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+}
+
+impl AuditOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Failure => "failure",
+        }
+    }
+}
+
+impl std::str::FromStr for AuditOutcome {
+    type Err = OAuth2Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "success" => Ok(Self::Success),
+            "failure" => Ok(Self::Failure),
+            other => Err(OAuth2Error::invalid_request(&format!(
+                "Unknown audit outcome: {other}"
+            ))),
+        }
+    }
+}
+
+// Stored in TEXT columns, so decode through `String` on any database.
+macro_rules! text_column {
+    ($type:ty) => {
+        #[cfg(feature = "sqlx")]
+        impl<DB: sqlx::Database> sqlx::Type<DB> for $type
+        where
+            String: sqlx::Type<DB>,
+        {
+            fn type_info() -> DB::TypeInfo {
+                <String as sqlx::Type<DB>>::type_info()
+            }
+
+            fn compatible(ty: &DB::TypeInfo) -> bool {
+                <String as sqlx::Type<DB>>::compatible(ty)
+            }
+        }
+
+        #[cfg(feature = "sqlx")]
+        impl<'r, DB: sqlx::Database> sqlx::Decode<'r, DB> for $type
+        where
+            String: sqlx::Decode<'r, DB>,
+        {
+            fn decode(value: DB::ValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+                let value = <String as sqlx::Decode<DB>>::decode(value)?;
+                value.parse().map_err(|e: OAuth2Error| e.to_string().into())
+            }
+        }
+    };
+}
+
+text_column!(AuditAction);
+text_column!(AuditOutcome);
+
+/// An entry in the audit log: who did what, from where, and whether it worked.
+///
+/// Unlike events, which are published best-effort, audit records are written to the
+/// server's storage before the response is sent.
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub id: String,
+    pub occurred_at: DateTime<Utc>,
+    pub action: AuditAction,
+    pub outcome: AuditOutcome,
+    /// Who acted: a user id, the client for client-only grants, or the subject of an
+    /// admin token. `None` when the caller could not be identified.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// What was acted on, e.g. the grant type of a token request or the admin route that
+    /// was called.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip_address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// Why the action failed, e.g. the OAuth2 error code.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl AuditRecord {
+    pub fn new(action: AuditAction, outcome: AuditOutcome) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            occurred_at: Utc::now(),
+            action,
+            outcome,
+            actor: None,
+            client_id: None,
+            target: None,
+            ip_address: None,
+            user_agent: None,
+            detail: None,
+        }
+    }
+
+    /// A record of `result`; a failure carries the OAuth2 error code as its detail.
+    pub fn from_result<T>(action: AuditAction, result: &Result<T, OAuth2Error>) -> Self {
+        match result {
+            Ok(_) => Self::new(action, AuditOutcome::Success),
+            Err(e) => Self::new(action, AuditOutcome::Failure).with_detail(&e.error),
+        }
+    }
+
+    pub fn with_actor(mut self, actor: impl Into<String>) -> Self {
+        self.actor = Some(actor.into());
+        self
+    }
+
+    pub fn with_client(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = Some(client_id.into());
+        self
+    }
+
+    pub fn with_target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Take the caller's IP address and user agent from `req`.
+    #[cfg(feature = "actix")]
+    pub fn with_request(mut self, req: &HttpRequest) -> Self {
+        self.ip_address = super::TrustedProxies::client_ip_of(req).map(|ip| ip.to_string());
+        self.user_agent = req
+            .headers()
+            .get(actix_web::http::header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        self
+    }
+}
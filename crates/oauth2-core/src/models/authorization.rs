@@ -4,6 +4,9 @@ use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::error::OAuth2Error;
+use super::grant_type::ResponseType;
+
 #[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthorizationCode {
@@ -20,6 +23,15 @@ pub struct AuthorizationCode {
     pub code_challenge: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub code_challenge_method: Option<String>,
+    /// Encoded [`ContextBinding`](super::ContextBinding) of the authorize request, when
+    /// context binding is enabled.
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_binding: Option<String>,
+    /// [`Tenant`](crate::Tenant) that issued the code; `None` for the default tenant.
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
 }
 
 impl AuthorizationCode {
@@ -47,9 +59,26 @@ impl AuthorizationCode {
             used: false,
             code_challenge,
             code_challenge_method,
+            context_binding: None,
+            tenant_id: None,
         }
     }
 
+    pub fn with_context_binding(mut self, binding: Option<String>) -> Self {
+        self.context_binding = binding;
+        self
+    }
+
+    pub fn with_tenant_id(mut self, tenant_id: Option<String>) -> Self {
+        self.tenant_id = tenant_id;
+        self
+    }
+
+    /// Whether this code was issued by `tenant_id` (`None` is the default tenant).
+    pub fn in_tenant(&self, tenant_id: Option<&str>) -> bool {
+        self.tenant_id.as_deref() == tenant_id
+    }
+
     pub fn is_expired(&self) -> bool {
         Utc::now() > self.expires_at
     }
@@ -61,7 +90,7 @@ impl AuthorizationCode {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AuthorizationRequest {
-    pub response_type: String,
+    pub response_type: ResponseType,
     pub client_id: String,
     pub redirect_uri: String,
     pub scope: Option<String>,
@@ -70,6 +99,22 @@ pub struct AuthorizationRequest {
     pub code_challenge_method: Option<String>,
 }
 
+/// Whether an authorization request's `prompt` parameter (OpenID Connect Core section
+/// 3.1.2.1) asks for no user interaction at all. `none` cannot be combined with other
+/// values; the others are accepted and left to the login page.
+pub fn is_silent_prompt(prompt: Option<&str>) -> Result<bool, OAuth2Error> {
+    let values: Vec<&str> = prompt.unwrap_or_default().split_whitespace().collect();
+    if !values.contains(&"none") {
+        return Ok(false);
+    }
+    if values.len() > 1 {
+        return Err(OAuth2Error::invalid_request(
+            "prompt=none cannot be combined with other values",
+        ));
+    }
+    Ok(true)
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AuthorizationResponse {
     pub code: String,
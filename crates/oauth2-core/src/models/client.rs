@@ -1,9 +1,17 @@
 #![allow(dead_code)]
 
+use base64::{engine::general_purpose, Engine as _};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
+use super::client_metadata::{ClientMetadata, TokenEndpointAuthMethod};
+use super::error::OAuth2Error;
+use super::grant_type::GrantType;
+use super::password::{hash_password, is_password_hash, verify_password};
+use super::redirect_uri::RedirectUri;
+
 #[cfg(feature = "openapi")]
 use utoipa::ToSchema;
 
@@ -13,6 +21,7 @@ use utoipa::ToSchema;
 pub struct Client {
     pub id: String,
     pub client_id: String,
+    /// Argon2 PHC hash of the secret. Rows created before hashing may still hold plaintext.
     #[cfg_attr(feature = "openapi", schema(write_only))]
     pub client_secret: String,
     pub redirect_uris: String, // JSON array stored as string
@@ -21,6 +30,181 @@ pub struct Client {
     pub name: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// SHA-256 hash of the RFC 7592 registration access token (never the token itself).
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[cfg_attr(feature = "openapi", schema(write_only))]
+    #[serde(default)]
+    pub registration_access_token: Option<String>,
+    /// Reconciler that owns this client. Managed clients are converged from a declarative
+    /// source, which overwrites manual edits and deletes them when they are removed there.
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub managed_by: Option<String>,
+    /// Redirect URIs requested by a registration update but not yet in effect (JSON array
+    /// stored as string). See [`Client::stage_redirect_uris`].
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_redirect_uris: Option<String>,
+    /// When `pending_redirect_uris` take effect; `None` while they await approval.
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_redirect_uris_at: Option<DateTime<Utc>>,
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default)]
+    pub client_type: ClientType,
+    /// Owning [`Tenant`](crate::Tenant); `None` for the default tenant.
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    // RFC 7591 client metadata; see [`ClientMetadata`].
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logo_uri: Option<String>,
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_uri: Option<String>,
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_uri: Option<String>,
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tos_uri: Option<String>,
+    /// JSON array stored as string.
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contacts: Option<String>,
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwks_uri: Option<String>,
+    /// `None` means the default for the client type; see [`Client::token_endpoint_auth_method`].
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_endpoint_auth_method: Option<TokenEndpointAuthMethod>,
+    /// Browser origins allowed to call the token endpoint cross-origin (JSON array stored
+    /// as string).
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_origins: Option<String>,
+}
+
+/// What kind of client this is (RFC 6749 section 2.1, RFC 8252).
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientType {
+    /// Can keep a secret and authenticates with it at the token endpoint.
+    #[default]
+    #[serde(alias = "application")]
+    Confidential,
+    /// Runs on the user's device (e.g. a SPA) and has no secret; PKCE protects its codes.
+    Public,
+    /// A public client installed as a native app. May use loopback redirects on any port
+    /// and private-use URI schemes (RFC 8252 section 7).
+    Native,
+    /// An API that only introspects tokens presented to it. It may introspect tokens of
+    /// any client and cannot obtain tokens itself.
+    ResourceServer,
+}
+
+impl ClientType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Confidential => "confidential",
+            Self::Public => "public",
+            Self::Native => "native",
+            Self::ResourceServer => "resource_server",
+        }
+    }
+
+    /// Whether clients of this type have no secret to authenticate with.
+    pub fn is_public(&self) -> bool {
+        matches!(self, Self::Public | Self::Native)
+    }
+
+    /// Check a redirect URI to register: it must parse as a [`RedirectUri`], and only
+    /// native clients may use a private-use scheme.
+    pub fn validate_redirect_uri(&self, uri: &str) -> Result<(), OAuth2Error> {
+        self.check_redirect_uri(&RedirectUri::parse(uri)?)
+    }
+
+    /// Check a parsed redirect URI against this client type.
+    pub fn check_redirect_uri(&self, uri: &RedirectUri) -> Result<(), OAuth2Error> {
+        if uri.is_private_use() && *self != Self::Native {
+            return Err(OAuth2Error::invalid_request(
+                "only native clients may use a private-use redirect_uri scheme",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check the grant types to register. Without a secret, `client_credentials` would
+    /// hand tokens to anyone who knows a public client's id.
+    pub fn validate_grant_types(&self, grant_types: &[String]) -> Result<(), OAuth2Error> {
+        let client_credentials = GrantType::ClientCredentials.as_str();
+        if self.is_public() && grant_types.iter().any(|gt| gt == client_credentials) {
+            return Err(OAuth2Error::invalid_request(
+                "public clients cannot use client_credentials",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check registered metadata against this client type.
+    pub fn validate_metadata(
+        &self,
+        redirect_uris: &[RedirectUri],
+        grant_types: &[String],
+    ) -> Result<(), OAuth2Error> {
+        self.validate_grant_types(grant_types)?;
+        redirect_uris
+            .iter()
+            .try_for_each(|uri| self.check_redirect_uri(uri))
+    }
+}
+
+impl std::str::FromStr for ClientType {
+    type Err = OAuth2Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            // Rows written before public and native clients existed.
+            "confidential" | "application" => Ok(Self::Confidential),
+            "public" => Ok(Self::Public),
+            "native" => Ok(Self::Native),
+            "resource_server" => Ok(Self::ResourceServer),
+            other => Err(OAuth2Error::invalid_request(&format!(
+                "Unknown client type: {other}"
+            ))),
+        }
+    }
+}
+
+// Stored in a TEXT column, so decode through `String` on any database.
+#[cfg(feature = "sqlx")]
+impl<DB: sqlx::Database> sqlx::Type<DB> for ClientType
+where
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <String as sqlx::Type<DB>>::compatible(ty)
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl<'r, DB: sqlx::Database> sqlx::Decode<'r, DB> for ClientType
+where
+    String: sqlx::Decode<'r, DB>,
+{
+    fn decode(value: DB::ValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let client_type = <String as sqlx::Decode<DB>>::decode(value)?;
+        client_type
+            .parse()
+            .map_err(|e: OAuth2Error| e.to_string().into())
+    }
 }
 
 impl Client {
@@ -44,33 +228,333 @@ impl Client {
             name,
             created_at: now,
             updated_at: now,
+            registration_access_token: None,
+            managed_by: None,
+            pending_redirect_uris: None,
+            pending_redirect_uris_at: None,
+            client_type: ClientType::Confidential,
+            tenant_id: None,
+            logo_uri: None,
+            client_uri: None,
+            policy_uri: None,
+            tos_uri: None,
+            contacts: None,
+            jwks_uri: None,
+            token_endpoint_auth_method: None,
+            allowed_origins: None,
+        }
+    }
+
+    /// A client for an API that introspects tokens with its own credentials. It has no
+    /// grant types or redirect URIs.
+    pub fn resource_server(client_id: String, client_secret: String, name: String) -> Self {
+        Self {
+            client_type: ClientType::ResourceServer,
+            ..Self::new(
+                client_id,
+                client_secret,
+                Vec::new(),
+                Vec::new(),
+                String::new(),
+                name,
+            )
+        }
+    }
+
+    /// Whether this client belongs to `tenant_id` (`None` is the default tenant).
+    pub fn in_tenant(&self, tenant_id: Option<&str>) -> bool {
+        self.tenant_id.as_deref() == tenant_id
+    }
+
+    pub fn is_resource_server(&self) -> bool {
+        self.client_type == ClientType::ResourceServer
+    }
+
+    /// Whether this client has no secret (see [`ClientType::is_public`]).
+    pub fn is_public(&self) -> bool {
+        self.client_type.is_public()
+    }
+
+    /// Replace the stored secret with an Argon2 hash of `secret`.
+    pub fn set_client_secret(&mut self, secret: &str) -> Result<(), OAuth2Error> {
+        self.client_secret = hash_password(secret)?;
+        Ok(())
+    }
+
+    /// Whether the stored secret is still plaintext and should be re-hashed.
+    pub fn client_secret_needs_rehash(&self) -> bool {
+        !is_password_hash(&self.client_secret)
+    }
+
+    /// Check a presented client secret against the stored value.
+    ///
+    /// Hashed secrets are verified with Argon2. Legacy plaintext secrets are compared in
+    /// constant time so existing clients keep working until they are re-hashed.
+    pub fn verify_client_secret(&self, secret: &str) -> bool {
+        // Public clients, and clients registered to authenticate with `none`, have no
+        // secret: nothing authenticates as them, an empty `client_secret` included.
+        if self.client_secret.is_empty()
+            || self.token_endpoint_auth_method() == TokenEndpointAuthMethod::None
+        {
+            return false;
+        }
+        if self.client_secret_needs_rehash() {
+            use subtle::ConstantTimeEq;
+            return self
+                .client_secret
+                .as_bytes()
+                .ct_eq(secret.as_bytes())
+                .into();
         }
+        verify_password(secret, &self.client_secret)
     }
 
+    /// Store the hash of a newly issued registration access token.
+    pub fn set_registration_access_token(&mut self, token: &str) {
+        self.registration_access_token = Some(hash_registration_access_token(token));
+    }
+
+    /// Check a presented registration access token against the stored hash.
+    pub fn verify_registration_access_token(&self, token: &str) -> bool {
+        let Some(expected) = &self.registration_access_token else {
+            return false;
+        };
+        let presented = hash_registration_access_token(token);
+
+        use subtle::ConstantTimeEq;
+        expected.as_bytes().ct_eq(presented.as_bytes()).into()
+    }
+
+    /// Redirect URIs in effect: the pending ones once their delay has passed.
     pub fn get_redirect_uris(&self) -> Vec<String> {
-        serde_json::from_str(&self.redirect_uris).unwrap_or_default()
+        match (&self.pending_redirect_uris, self.pending_redirect_uris_at) {
+            (Some(pending), Some(at)) if at <= Utc::now() => {
+                serde_json::from_str(pending).unwrap_or_default()
+            }
+            _ => serde_json::from_str(&self.redirect_uris).unwrap_or_default(),
+        }
+    }
+
+    /// Redirect URIs waiting for their delay to pass or for approval.
+    pub fn get_pending_redirect_uris(&self) -> Option<Vec<String>> {
+        if self
+            .pending_redirect_uris_at
+            .is_some_and(|at| at <= Utc::now())
+        {
+            return None;
+        }
+        self.pending_redirect_uris
+            .as_deref()
+            .map(|pending| serde_json::from_str(pending).unwrap_or_default())
+    }
+
+    /// Replace the redirect URIs immediately, discarding any pending change.
+    pub fn set_redirect_uris(&mut self, redirect_uris: &[String]) {
+        self.redirect_uris =
+            serde_json::to_string(redirect_uris).unwrap_or_else(|_| "[]".to_string());
+        self.pending_redirect_uris = None;
+        self.pending_redirect_uris_at = None;
+    }
+
+    /// Hold `redirect_uris` back until `effective_at`, or until
+    /// [`Client::approve_pending_redirect_uris`] when `None`. Replaces any earlier pending
+    /// change; a change whose delay has already passed is applied first.
+    pub fn stage_redirect_uris(
+        &mut self,
+        redirect_uris: &[String],
+        effective_at: Option<DateTime<Utc>>,
+    ) {
+        self.set_redirect_uris(&self.get_redirect_uris());
+        self.pending_redirect_uris =
+            Some(serde_json::to_string(redirect_uris).unwrap_or_else(|_| "[]".to_string()));
+        self.pending_redirect_uris_at = effective_at;
+    }
+
+    /// Apply the pending redirect URIs now. Returns them, or `None` if nothing was pending.
+    pub fn approve_pending_redirect_uris(&mut self) -> Option<Vec<String>> {
+        let pending = self.get_pending_redirect_uris()?;
+        self.set_redirect_uris(&pending);
+        Some(pending)
+    }
+
+    /// Discard the pending redirect URIs. Returns them, or `None` if nothing was pending.
+    pub fn reject_pending_redirect_uris(&mut self) -> Option<Vec<String>> {
+        let pending = self.get_pending_redirect_uris()?;
+        self.set_redirect_uris(&self.get_redirect_uris());
+        Some(pending)
+    }
+
+    /// Registered RFC 7591 metadata, with the effective `token_endpoint_auth_method`.
+    pub fn metadata(&self) -> ClientMetadata {
+        ClientMetadata {
+            logo_uri: self.logo_uri.clone(),
+            client_uri: self.client_uri.clone(),
+            policy_uri: self.policy_uri.clone(),
+            tos_uri: self.tos_uri.clone(),
+            contacts: self
+                .contacts
+                .as_deref()
+                .map(|contacts| serde_json::from_str(contacts).unwrap_or_default())
+                .unwrap_or_default(),
+            jwks_uri: self.jwks_uri.clone(),
+            token_endpoint_auth_method: Some(self.token_endpoint_auth_method()),
+            allowed_origins: self.get_allowed_origins(),
+        }
+    }
+
+    /// Replace the RFC 7591 metadata.
+    pub fn set_metadata(&mut self, metadata: ClientMetadata) {
+        self.logo_uri = metadata.logo_uri;
+        self.client_uri = metadata.client_uri;
+        self.policy_uri = metadata.policy_uri;
+        self.tos_uri = metadata.tos_uri;
+        self.contacts = (!metadata.contacts.is_empty()).then(|| {
+            serde_json::to_string(&metadata.contacts).unwrap_or_else(|_| "[]".to_string())
+        });
+        self.jwks_uri = metadata.jwks_uri;
+        self.token_endpoint_auth_method = metadata.token_endpoint_auth_method;
+        self.set_allowed_origins(&metadata.allowed_origins);
+    }
+
+    pub fn get_allowed_origins(&self) -> Vec<String> {
+        self.allowed_origins
+            .as_deref()
+            .map(|origins| serde_json::from_str(origins).unwrap_or_default())
+            .unwrap_or_default()
+    }
+
+    pub fn set_allowed_origins(&mut self, origins: &[String]) {
+        self.allowed_origins = (!origins.is_empty())
+            .then(|| serde_json::to_string(origins).unwrap_or_else(|_| "[]".to_string()));
+    }
+
+    pub fn token_endpoint_auth_method(&self) -> TokenEndpointAuthMethod {
+        self.token_endpoint_auth_method
+            .unwrap_or_else(|| TokenEndpointAuthMethod::default_for(self.client_type))
     }
 
     pub fn get_grant_types(&self) -> Vec<String> {
         serde_json::from_str(&self.grant_types).unwrap_or_default()
     }
 
-    pub fn supports_grant_type(&self, grant_type: &str) -> bool {
-        self.get_grant_types().contains(&grant_type.to_string())
+    pub fn supports_grant_type(&self, grant_type: GrantType) -> bool {
+        self.get_grant_types()
+            .iter()
+            .any(|registered| registered == grant_type.as_str())
     }
 
+    /// Whether `redirect_uri` is registered. URIs are compared in their normalized form
+    /// (see [`RedirectUri`]), except that native clients may use any port on a registered
+    /// loopback redirect (RFC 8252 section 7.3).
     pub fn validate_redirect_uri(&self, redirect_uri: &str) -> bool {
-        self.get_redirect_uris().contains(&redirect_uri.to_string())
+        let Ok(requested) = RedirectUri::parse(redirect_uri) else {
+            return false;
+        };
+        let registered: Vec<RedirectUri> = self
+            .get_redirect_uris()
+            .iter()
+            .filter_map(|uri| RedirectUri::parse(uri).ok())
+            .collect();
+        if registered.contains(&requested) {
+            return true;
+        }
+        self.client_type == ClientType::Native
+            && registered
+                .iter()
+                .any(|uri| uri.matches_ignoring_loopback_port(&requested))
     }
 }
 
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ClientRegistration {
+    pub client_name: String,
+    /// Invalid redirect URIs fail deserialization, so they are rejected at registration.
+    pub redirect_uris: Vec<RedirectUri>,
+    pub grant_types: Vec<String>,
+    pub scope: String,
+    /// `confidential` (default), `public` or `native`. Public and native clients get no
+    /// secret.
+    #[serde(default)]
+    pub client_type: ClientType,
+    #[serde(flatten)]
+    pub metadata: ClientMetadata,
+}
+
+/// Hash a registration access token for storage (base64url-encoded SHA-256).
+pub fn hash_registration_access_token(token: &str) -> String {
+    general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Client metadata accepted by `PUT /oauth/register/{client_id}` (RFC 7592 section 2.2).
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClientUpdateRequest {
+    pub client_id: String,
+    /// If present, must match the current secret; secrets cannot be changed this way.
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    pub client_name: String,
+    pub redirect_uris: Vec<RedirectUri>,
+    pub grant_types: Vec<String>,
+    pub scope: String,
+    /// Replaces the registered metadata; omitted fields are cleared.
+    #[serde(flatten)]
+    pub metadata: ClientMetadata,
+}
+
+/// Client information response (RFC 7591 section 3.2.1, RFC 7592 section 3).
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClientInformationResponse {
+    pub client_id: String,
+    /// Only returned once, at registration.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    /// Only returned once, at registration.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registration_access_token: Option<String>,
+    pub registration_client_uri: String,
+    pub client_id_issued_at: i64,
+    /// Always 0: client secrets do not expire.
+    pub client_secret_expires_at: i64,
     pub client_name: String,
     pub redirect_uris: Vec<String>,
+    /// Redirect URIs from an update that are not in effect yet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_redirect_uris: Option<Vec<String>>,
+    /// When `pending_redirect_uris` take effect (Unix time); absent while they await approval.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_redirect_uris_effective_at: Option<i64>,
     pub grant_types: Vec<String>,
     pub scope: String,
+    pub client_type: ClientType,
+    #[serde(flatten)]
+    pub metadata: ClientMetadata,
+}
+
+impl ClientInformationResponse {
+    pub fn from_client(client: &Client, registration_client_uri: String) -> Self {
+        Self {
+            client_id: client.client_id.clone(),
+            client_secret: None,
+            registration_access_token: None,
+            registration_client_uri,
+            client_id_issued_at: client.created_at.timestamp(),
+            client_secret_expires_at: 0,
+            client_name: client.name.clone(),
+            redirect_uris: client.get_redirect_uris(),
+            pending_redirect_uris: client.get_pending_redirect_uris(),
+            pending_redirect_uris_effective_at: client
+                .get_pending_redirect_uris()
+                .and(client.pending_redirect_uris_at)
+                .map(|at| at.timestamp()),
+            grant_types: client.get_grant_types(),
+            scope: client.scope.clone(),
+            client_type: client.client_type,
+            metadata: client.metadata(),
+        }
+    }
 }
 
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
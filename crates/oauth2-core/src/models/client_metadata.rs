@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use super::client::ClientType;
+use super::error::OAuth2Error;
+
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+/// How a client authenticates at the token endpoint (RFC 7591 section 2).
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenEndpointAuthMethod {
+    /// HTTP Basic authentication with the client secret.
+    ClientSecretBasic,
+    /// `client_id` and `client_secret` in the request body.
+    ClientSecretPost,
+    /// No authentication: public and native clients, which have no secret.
+    None,
+}
+
+impl TokenEndpointAuthMethod {
+    pub const ALL: [TokenEndpointAuthMethod; 3] =
+        [Self::ClientSecretBasic, Self::ClientSecretPost, Self::None];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ClientSecretBasic => "client_secret_basic",
+            Self::ClientSecretPost => "client_secret_post",
+            Self::None => "none",
+        }
+    }
+
+    /// What clients of `client_type` use unless they register something else.
+    pub fn default_for(client_type: ClientType) -> Self {
+        if client_type.is_public() {
+            Self::None
+        } else {
+            Self::ClientSecretBasic
+        }
+    }
+}
+
+impl fmt::Display for TokenEndpointAuthMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TokenEndpointAuthMethod {
+    type Err = OAuth2Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|method| method.as_str() == s)
+            .ok_or_else(|| {
+                OAuth2Error::invalid_request(&format!(
+                    "Unsupported token_endpoint_auth_method: {s}"
+                ))
+            })
+    }
+}
+
+// Stored in a TEXT column, so decode through `String` on any database.
+#[cfg(feature = "sqlx")]
+impl<DB: sqlx::Database> sqlx::Type<DB> for TokenEndpointAuthMethod
+where
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <String as sqlx::Type<DB>>::compatible(ty)
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl<'r, DB: sqlx::Database> sqlx::Decode<'r, DB> for TokenEndpointAuthMethod
+where
+    String: sqlx::Decode<'r, DB>,
+{
+    fn decode(value: DB::ValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let method = <String as sqlx::Decode<DB>>::decode(value)?;
+        method
+            .parse()
+            .map_err(|e: OAuth2Error| e.to_string().into())
+    }
+}
+
+/// Descriptive client metadata from RFC 7591 section 2: what the consent screen shows
+/// the user about a client and how the client authenticates. Every field is optional.
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientMetadata {
+    /// Logo shown to the user when asking for consent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logo_uri: Option<String>,
+    /// Home page of the client.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_uri: Option<String>,
+    /// How the client uses the data it is given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_uri: Option<String>,
+    /// Terms of service of the client.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tos_uri: Option<String>,
+    /// People responsible for the client, usually email addresses.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contacts: Vec<String>,
+    /// Where the client publishes its public keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwks_uri: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_endpoint_auth_method: Option<TokenEndpointAuthMethod>,
+    /// Browser origins (`https://app.example.com`) allowed to call the token endpoint
+    /// cross-origin. Not part of RFC 7591.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_origins: Vec<String>,
+}
+
+impl ClientMetadata {
+    /// Check the metadata of a client of `client_type`: URIs must be absolute `http(s)`
+    /// URLs (`https` for `jwks_uri`), allowed origins bare `http(s)` origins, and clients
+    /// without a secret must authenticate with `none` while clients with one must not.
+    pub fn validate(&self, client_type: ClientType) -> Result<(), OAuth2Error> {
+        for (name, uri) in [
+            ("logo_uri", &self.logo_uri),
+            ("client_uri", &self.client_uri),
+            ("policy_uri", &self.policy_uri),
+            ("tos_uri", &self.tos_uri),
+        ] {
+            if let Some(uri) = uri {
+                validate_web_uri(name, uri, false)?;
+            }
+        }
+        if let Some(uri) = &self.jwks_uri {
+            validate_web_uri("jwks_uri", uri, true)?;
+        }
+
+        if self
+            .contacts
+            .iter()
+            .any(|contact| contact.trim().is_empty())
+        {
+            return Err(OAuth2Error::invalid_request("contacts must not be empty"));
+        }
+
+        for origin in &self.allowed_origins {
+            validate_origin(origin)?;
+        }
+
+        if let Some(method) = self.token_endpoint_auth_method {
+            let public = client_type.is_public();
+            if public != (method == TokenEndpointAuthMethod::None) {
+                return Err(OAuth2Error::invalid_request(&format!(
+                    "token_endpoint_auth_method {method} cannot be used by {} clients",
+                    client_type.as_str()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_web_uri(name: &str, uri: &str, https_only: bool) -> Result<(), OAuth2Error> {
+    let rest = uri
+        .strip_prefix("https://")
+        .or_else(|| uri.strip_prefix("http://").filter(|_| !https_only));
+    let valid = rest.is_some_and(|rest| {
+        !rest.is_empty()
+            && !rest.starts_with('/')
+            && !rest.chars().any(|c| c.is_control() || c.is_whitespace())
+    });
+    if valid {
+        return Ok(());
+    }
+    let scheme = if https_only { "https" } else { "http(s)" };
+    Err(OAuth2Error::invalid_request(&format!(
+        "{name} must be an absolute {scheme} URL"
+    )))
+}
+
+/// A serialized origin as browsers send it: scheme, host and optional port, no path.
+fn validate_origin(origin: &str) -> Result<(), OAuth2Error> {
+    let host = origin
+        .strip_prefix("https://")
+        .or_else(|| origin.strip_prefix("http://"));
+    let valid = host.is_some_and(|host| {
+        !host.is_empty()
+            && !host.chars().any(|c| {
+                matches!(c, '/' | '?' | '#' | '@' | '*') || c.is_control() || c.is_whitespace()
+            })
+    });
+    if valid {
+        Ok(())
+    } else {
+        Err(OAuth2Error::invalid_request(&format!(
+            "allowed_origins entry {origin:?} must be an http(s) origin without a path"
+        )))
+    }
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::net::{IpAddr, SocketAddr};
+
+/// Hashed fingerprint of the user agent and network a request came from.
+///
+/// Used to bind authorization codes and refresh tokens to the context they were issued
+/// in. Only hashes are kept, and the IP is reduced to its network (`/24` for IPv4, `/48`
+/// for IPv6) so that ordinary address churn within a network does not count as a
+/// different context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextBinding {
+    user_agent: String,
+    network: String,
+}
+
+impl ContextBinding {
+    pub fn new(user_agent: Option<&str>, ip: Option<&str>) -> Self {
+        Self {
+            user_agent: hash(user_agent.unwrap_or_default().trim()),
+            network: hash(&ip.map(network_of).unwrap_or_default()),
+        }
+    }
+
+    /// Storage form: `<user agent hash>.<network hash>`.
+    pub fn encode(&self) -> String {
+        format!("{}.{}", self.user_agent, self.network)
+    }
+
+    pub fn decode(value: &str) -> Option<Self> {
+        let (user_agent, network) = value.split_once('.')?;
+        Some(Self {
+            user_agent: user_agent.to_string(),
+            network: network.to_string(),
+        })
+    }
+
+    /// Whether `other` may redeem what was issued to this context. The network must
+    /// match unless `tolerance` also accepts a matching user agent.
+    pub fn is_compatible_with(&self, other: &ContextBinding, tolerance: ContextTolerance) -> bool {
+        self.network == other.network
+            || (tolerance == ContextTolerance::NetworkOrUserAgent
+                && self.user_agent == other.user_agent)
+    }
+}
+
+/// How far the context redeeming a bound code or refresh token may drift from the one it
+/// was issued to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextTolerance {
+    /// The network must match; a changed user agent (e.g. a browser update) is accepted.
+    #[default]
+    Network,
+    /// Either the network or the user agent must match, for clients that roam between
+    /// networks. User agent strings are easily copied, so this only stops callers that
+    /// do not bother.
+    NetworkOrUserAgent,
+}
+
+fn network_of(ip: &str) -> String {
+    let ip = ip.trim();
+    let parsed = ip
+        .parse::<IpAddr>()
+        .ok()
+        .or_else(|| ip.parse::<SocketAddr>().ok().map(|addr| addr.ip()));
+
+    match parsed {
+        Some(IpAddr::V4(v4)) => {
+            let [a, b, c, _] = v4.octets();
+            format!("{a}.{b}.{c}.0/24")
+        }
+        Some(IpAddr::V6(v6)) => {
+            let s = v6.segments();
+            format!("{:x}:{:x}:{:x}::/48", s[0], s[1], s[2])
+        }
+        None => ip.to_string(),
+    }
+}
+
+fn hash(value: &str) -> String {
+    let digest = Sha256::digest(value.as_bytes());
+    digest[..16].iter().map(|b| format!("{b:02x}")).collect()
+}
@@ -0,0 +1,124 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::error::OAuth2Error;
+use super::user::User;
+
+/// What an [`EmailToken`] lets its holder do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailTokenPurpose {
+    /// Confirm that the user receives mail at their address.
+    VerifyEmail,
+    /// Set a new password without knowing the current one.
+    ResetPassword,
+    /// Sign in without a password.
+    MagicLink,
+}
+
+impl EmailTokenPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::VerifyEmail => "verify_email",
+            Self::ResetPassword => "reset_password",
+            Self::MagicLink => "magic_link",
+        }
+    }
+}
+
+impl std::str::FromStr for EmailTokenPurpose {
+    type Err = OAuth2Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "verify_email" => Ok(Self::VerifyEmail),
+            "reset_password" => Ok(Self::ResetPassword),
+            "magic_link" => Ok(Self::MagicLink),
+            other => Err(OAuth2Error::invalid_request(&format!(
+                "Unknown email token purpose: {other}"
+            ))),
+        }
+    }
+}
+
+// Stored in a TEXT column, so decode through `String` on any database.
+#[cfg(feature = "sqlx")]
+impl<DB: sqlx::Database> sqlx::Type<DB> for EmailTokenPurpose
+where
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <String as sqlx::Type<DB>>::compatible(ty)
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl<'r, DB: sqlx::Database> sqlx::Decode<'r, DB> for EmailTokenPurpose
+where
+    String: sqlx::Decode<'r, DB>,
+{
+    fn decode(value: DB::ValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let purpose = <String as sqlx::Decode<DB>>::decode(value)?;
+        purpose
+            .parse()
+            .map_err(|e: OAuth2Error| e.to_string().into())
+    }
+}
+
+/// A single-use token mailed to a user, e.g. in a password reset link.
+///
+/// Only the hash of the token is stored; the link carries the token itself. It is bound to
+/// the address it was sent to, so it stops working once the user's email changes.
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmailToken {
+    /// See [`hash_email_token`].
+    pub token_hash: String,
+    pub user_id: String,
+    pub purpose: EmailTokenPurpose,
+    pub email: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Local path to continue at once the token is used, e.g. the authorization request
+    /// that was waiting for a magic link sign-in.
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub return_to: Option<String>,
+}
+
+impl EmailToken {
+    /// A new random token for `user`, valid for `ttl_secs`: the token to mail and the
+    /// record to store.
+    pub fn issue(user: &User, purpose: EmailTokenPurpose, ttl_secs: i64) -> (String, Self) {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        let token = general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+        let now = Utc::now();
+        let record = Self {
+            token_hash: hash_email_token(&token),
+            user_id: user.id.clone(),
+            purpose,
+            email: user.email.clone(),
+            created_at: now,
+            expires_at: now + Duration::seconds(ttl_secs),
+            return_to: None,
+        };
+        (token, record)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        Utc::now() > self.expires_at
+    }
+}
+
+/// Hash an email token for storage (base64url-encoded SHA-256).
+pub fn hash_email_token(token: &str) -> String {
+    general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+}
@@ -9,12 +9,22 @@ use utoipa::ToSchema;
 #[cfg(feature = "actix")]
 use actix_web::{error::ResponseError, http::StatusCode, HttpResponse};
 
+#[cfg(feature = "axum")]
+use axum::response::{IntoResponse, Response};
+
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct OAuth2Error {
     pub error: String,
     pub error_description: Option<String>,
     pub error_uri: Option<String>,
+    /// Named in the `WWW-Authenticate` challenge of an `insufficient_scope` error.
+    #[serde(skip)]
+    required_scope: Option<String>,
+    /// The request carried no credentials, so the challenge names no error (RFC 6750
+    /// section 3.1).
+    #[serde(skip)]
+    credentials_missing: bool,
 }
 
 impl OAuth2Error {
@@ -23,6 +33,8 @@ impl OAuth2Error {
             error: error.to_string(),
             error_description: description.map(|s| s.to_string()),
             error_uri: None,
+            required_scope: None,
+            credentials_missing: false,
         }
     }
 
@@ -46,6 +58,11 @@ impl OAuth2Error {
         Self::new("unsupported_grant_type", Some(description))
     }
 
+    /// RFC 6749 authorization endpoint error for response types other than `code`.
+    pub fn unsupported_response_type(description: &str) -> Self {
+        Self::new("unsupported_response_type", Some(description))
+    }
+
     pub fn invalid_scope(description: &str) -> Self {
         Self::new("invalid_scope", Some(description))
     }
@@ -53,6 +70,91 @@ impl OAuth2Error {
     pub fn access_denied(description: &str) -> Self {
         Self::new("access_denied", Some(description))
     }
+
+    /// RFC 6750 bearer token error (missing, malformed, or unknown token).
+    pub fn invalid_token(description: &str) -> Self {
+        Self::new("invalid_token", Some(description))
+    }
+
+    /// `invalid_token` for a request without a bearer token. The challenge is a bare
+    /// `Bearer`, as RFC 6750 asks when no credentials were sent.
+    pub fn missing_token() -> Self {
+        Self {
+            credentials_missing: true,
+            ..Self::invalid_token("Missing bearer token")
+        }
+    }
+
+    /// RFC 6750 bearer token error: the token is valid but lacks a required scope.
+    pub fn insufficient_scope(description: &str) -> Self {
+        Self::new("insufficient_scope", Some(description))
+    }
+
+    /// Name `scope` in the bearer challenge, e.g. `Bearer error="insufficient_scope",
+    /// scope="admin:operator"`.
+    pub fn with_required_scope(mut self, scope: &str) -> Self {
+        self.required_scope = Some(scope.to_string());
+        self
+    }
+
+    /// RFC 6749 error for transient overload/timeouts (maps to 503).
+    pub fn temporarily_unavailable(description: &str) -> Self {
+        Self::new("temporarily_unavailable", Some(description))
+    }
+
+    /// The caller exceeded its request quota (maps to 429).
+    pub fn rate_limit_exceeded(description: &str) -> Self {
+        Self::new("rate_limit_exceeded", Some(description))
+    }
+
+    /// OpenID Connect error: the request needs a signed-in browser session (maps to 401).
+    pub fn login_required(description: &str) -> Self {
+        Self::new("login_required", Some(description))
+    }
+
+    /// OpenID Connect error: `prompt=none` was requested, but the user has not consented
+    /// to the client and scope yet.
+    pub fn consent_required(description: &str) -> Self {
+        Self::new("consent_required", Some(description))
+    }
+
+    /// OpenID Connect error: `prompt=none` was requested, but the request needs the user
+    /// to interact with the authorization server, e.g. to pick another account.
+    pub fn interaction_required(description: &str) -> Self {
+        Self::new("interaction_required", Some(description))
+    }
+
+    pub fn not_found(description: &str) -> Self {
+        Self::new("not_found", Some(description))
+    }
+
+    /// HTTP status code this error is reported with.
+    pub fn http_status(&self) -> u16 {
+        match self.error.as_str() {
+            "invalid_client" | "invalid_token" | "login_required" => 401,
+            "access_denied" | "insufficient_scope" => 403,
+            "not_found" => 404,
+            "rate_limit_exceeded" => 429,
+            "temporarily_unavailable" => 503,
+            _ => 400,
+        }
+    }
+
+    /// RFC 6750 section 3: bearer challenges carry the error code, and the scope needed
+    /// when it is known.
+    fn bearer_challenge(&self) -> Option<String> {
+        if !matches!(self.error.as_str(), "invalid_token" | "insufficient_scope") {
+            return None;
+        }
+        if self.credentials_missing {
+            return Some("Bearer".to_string());
+        }
+        let mut challenge = format!("Bearer error=\"{}\"", self.error);
+        if let Some(ref scope) = self.required_scope {
+            challenge.push_str(&format!(", scope=\"{scope}\""));
+        }
+        Some(challenge)
+    }
 }
 
 impl fmt::Display for OAuth2Error {
@@ -64,15 +166,33 @@ impl fmt::Display for OAuth2Error {
 #[cfg(feature = "actix")]
 impl ResponseError for OAuth2Error {
     fn status_code(&self) -> StatusCode {
-        match self.error.as_str() {
-            "invalid_client" => StatusCode::UNAUTHORIZED,
-            "access_denied" => StatusCode::FORBIDDEN,
-            _ => StatusCode::BAD_REQUEST,
-        }
+        StatusCode::from_u16(self.http_status()).unwrap_or(StatusCode::BAD_REQUEST)
     }
 
     fn error_response(&self) -> HttpResponse {
-        HttpResponse::build(self.status_code()).json(self)
+        let mut response = HttpResponse::build(self.status_code());
+        if let Some(challenge) = self.bearer_challenge() {
+            response.insert_header((actix_web::http::header::WWW_AUTHENTICATE, challenge));
+        }
+        response.json(self)
+    }
+}
+
+#[cfg(feature = "axum")]
+impl IntoResponse for OAuth2Error {
+    fn into_response(self) -> Response {
+        let status = axum::http::StatusCode::from_u16(self.http_status())
+            .unwrap_or(axum::http::StatusCode::BAD_REQUEST);
+        let mut response = (status, axum::Json(&self)).into_response();
+        if let Some(challenge) = self
+            .bearer_challenge()
+            .and_then(|challenge| challenge.parse().ok())
+        {
+            response
+                .headers_mut()
+                .insert(axum::http::header::WWW_AUTHENTICATE, challenge);
+        }
+        response
     }
 }
 
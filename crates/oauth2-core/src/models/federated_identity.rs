@@ -0,0 +1,40 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A social provider account linked to a local [`User`](crate::User). A login whose
+/// `(provider, provider_user_id)` is linked signs in as that user.
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FederatedIdentity {
+    pub provider: String,
+    /// The provider's stable subject identifier, not the email.
+    pub provider_user_id: String,
+    pub user_id: String,
+    pub created_at: DateTime<Utc>,
+    /// The provider's access token from the latest sign-in, sealed with the server's token
+    /// key; `None` when no key is configured.
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_token: Option<String>,
+    /// The provider's refresh token, sealed like `access_token`.
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_expires_at: Option<DateTime<Utc>>,
+}
+
+impl FederatedIdentity {
+    pub fn new(provider: String, provider_user_id: String, user_id: String) -> Self {
+        Self {
+            provider,
+            provider_user_id,
+            user_id,
+            created_at: Utc::now(),
+            access_token: None,
+            refresh_token: None,
+            token_expires_at: None,
+        }
+    }
+}
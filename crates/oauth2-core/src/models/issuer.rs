@@ -0,0 +1,296 @@
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use std::fmt;
+use std::str::FromStr;
+
+use super::token::{Claims, DEFAULT_ISSUER};
+
+/// An issuer identifier paired with the HMAC secret used to sign its tokens.
+#[derive(Clone)]
+pub struct IssuerKey {
+    pub issuer: String,
+    secret: String,
+}
+
+impl IssuerKey {
+    pub fn new(issuer: impl Into<String>, secret: impl Into<String>) -> Self {
+        Self {
+            issuer: issuer.into(),
+            secret: secret.into(),
+        }
+    }
+
+    pub fn secret(&self) -> &str {
+        &self.secret
+    }
+
+    /// HMAC of the issuer name under the secret: identifies the key without revealing
+    /// more about the secret than any token it signs.
+    fn tag(&self) -> String {
+        jsonwebtoken::crypto::sign(
+            self.issuer.as_bytes(),
+            &EncodingKey::from_secret(self.secret.as_ref()),
+            Algorithm::HS256,
+        )
+        .unwrap_or_default()
+    }
+
+    fn decode(
+        &self,
+        token: &str,
+        rules: &JwtValidation,
+        validate_exp: bool,
+    ) -> Result<Claims, jsonwebtoken::errors::Error> {
+        let mut validation = rules.validation();
+        validation.validate_exp = validate_exp;
+        validation.set_issuer(&[self.issuer.as_str()]);
+
+        let token_data = jsonwebtoken::decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(self.secret.as_ref()),
+            &validation,
+        )?;
+        Ok(token_data.claims)
+    }
+}
+
+impl fmt::Debug for IssuerKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IssuerKey")
+            .field("issuer", &self.issuer)
+            .field("secret", &"***MASKED***")
+            .finish()
+    }
+}
+
+/// Registered claims that can be listed in [`JwtValidation::required_claims`].
+const SPEC_CLAIMS: &[&str] = &["exp", "nbf", "aud", "iss", "sub"];
+
+/// Verification rules applied to every JWT the server accepts.
+///
+/// Shared by [`IssuerKeys::verify`] and [`Claims::decode_with`] so every validation
+/// path applies the same clock skew, required claims and algorithms.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JwtValidation {
+    /// Clock-skew tolerance in seconds for `exp` and `nbf`.
+    pub leeway_secs: u64,
+    /// Registered claims that must be present.
+    pub required_claims: Vec<String>,
+    /// Accepted `alg` values. The first one signs new tokens.
+    pub algorithms: Vec<Algorithm>,
+}
+
+impl Default for JwtValidation {
+    fn default() -> Self {
+        Self {
+            leeway_secs: 60,
+            required_claims: vec!["exp".to_string()],
+            algorithms: vec![Algorithm::HS256],
+        }
+    }
+}
+
+impl JwtValidation {
+    /// Build validation rules from configuration values.
+    ///
+    /// Tokens are signed with shared secrets, so only the HMAC family is accepted.
+    pub fn new(
+        leeway_secs: u64,
+        required_claims: &[String],
+        algorithms: &[String],
+    ) -> Result<Self, String> {
+        if let Some(unknown) = required_claims
+            .iter()
+            .find(|c| !SPEC_CLAIMS.contains(&c.as_str()))
+        {
+            return Err(format!(
+                "unsupported required claim `{unknown}` (expected one of {})",
+                SPEC_CLAIMS.join(", ")
+            ));
+        }
+
+        let algorithms = algorithms
+            .iter()
+            .map(|name| match Algorithm::from_str(name) {
+                Ok(alg @ (Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512)) => Ok(alg),
+                Ok(_) => Err(format!(
+                    "algorithm `{name}` is not supported with HMAC secrets"
+                )),
+                Err(_) => Err(format!("unknown JWT algorithm `{name}`")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if algorithms.is_empty() {
+            return Err("at least one JWT algorithm must be accepted".to_string());
+        }
+
+        Ok(Self {
+            leeway_secs,
+            required_claims: required_claims.to_vec(),
+            algorithms,
+        })
+    }
+
+    /// Algorithm used to sign newly minted tokens.
+    pub fn signing_algorithm(&self) -> Algorithm {
+        self.algorithms[0]
+    }
+
+    pub(crate) fn validation(&self) -> Validation {
+        let mut validation = Validation::new(self.signing_algorithm());
+        validation.algorithms = self.algorithms.clone();
+        validation.leeway = self.leeway_secs;
+        validation.set_required_spec_claims(&self.required_claims);
+        validation.validate_nbf = true;
+        // `aud` carries the requesting client id, which varies per token.
+        validation.validate_aud = false;
+        validation
+    }
+}
+
+/// Issuer from a previous deployment whose tokens are still accepted during a migration.
+#[derive(Debug, Clone)]
+pub struct LegacyIssuer {
+    pub key: IssuerKey,
+    /// End of the migration window. `None` accepts legacy tokens until the issuer is removed.
+    pub accept_until: Option<DateTime<Utc>>,
+}
+
+impl LegacyIssuer {
+    pub fn new(key: IssuerKey, accept_until: Option<DateTime<Utc>>) -> Self {
+        Self { key, accept_until }
+    }
+
+    pub fn is_accepting(&self, now: DateTime<Utc>) -> bool {
+        self.accept_until.is_none_or(|until| now <= until)
+    }
+}
+
+/// Which issuer a verified token was minted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssuerGeneration {
+    Current,
+    Legacy,
+}
+
+impl IssuerGeneration {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IssuerGeneration::Current => "current",
+            IssuerGeneration::Legacy => "legacy",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TokenVerificationError {
+    /// Signature, issuer, or expiry did not validate against any configured issuer.
+    Invalid(jsonwebtoken::errors::Error),
+    /// Token was minted by the legacy issuer after its migration window closed.
+    LegacyWindowClosed,
+}
+
+impl fmt::Display for TokenVerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenVerificationError::Invalid(e) => write!(f, "invalid token: {}", e),
+            TokenVerificationError::LegacyWindowClosed => {
+                write!(f, "legacy issuer is no longer accepted")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TokenVerificationError {}
+
+/// Signing and verification keys for JWTs.
+///
+/// New tokens are always signed by the current issuer. Tokens from the legacy issuer
+/// (if configured) keep verifying until its `accept_until` deadline passes.
+#[derive(Debug, Clone)]
+pub struct IssuerKeys {
+    pub current: IssuerKey,
+    pub legacy: Option<LegacyIssuer>,
+    pub validation: JwtValidation,
+}
+
+impl IssuerKeys {
+    pub fn new(current: IssuerKey) -> Self {
+        Self {
+            current,
+            legacy: None,
+            validation: JwtValidation::default(),
+        }
+    }
+
+    /// Keys for the default issuer signed with `secret`.
+    pub fn from_secret(secret: impl Into<String>) -> Self {
+        Self::new(IssuerKey::new(DEFAULT_ISSUER, secret))
+    }
+
+    pub fn with_legacy(mut self, legacy: LegacyIssuer) -> Self {
+        self.legacy = Some(legacy);
+        self
+    }
+
+    pub fn with_validation(mut self, validation: JwtValidation) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    /// Opaque tag that changes whenever the current or legacy key does, so cache
+    /// validators derived from it are invalidated by a key rotation.
+    pub fn key_tag(&self) -> String {
+        match &self.legacy {
+            Some(legacy) => format!("{}.{}", self.current.tag(), legacy.key.tag()),
+            None => self.current.tag(),
+        }
+    }
+
+    /// Sign `claims` with the current issuer, overriding their `iss`.
+    pub fn sign(&self, claims: Claims) -> Result<String, jsonwebtoken::errors::Error> {
+        let claims = claims.with_issuer(self.current.issuer.clone());
+        jsonwebtoken::encode(
+            &Header::new(self.validation.signing_algorithm()),
+            &claims,
+            &EncodingKey::from_secret(self.current.secret.as_ref()),
+        )
+    }
+
+    /// Verify a token against the current issuer, falling back to the legacy issuer.
+    pub fn verify(
+        &self,
+        token: &str,
+    ) -> Result<(Claims, IssuerGeneration), TokenVerificationError> {
+        self.verify_with(token, true)
+    }
+
+    /// Like [`IssuerKeys::verify`], but expired tokens are accepted. For hints such as
+    /// `id_token_hint`, which only need to prove that this server issued them.
+    pub fn verify_ignoring_expiry(
+        &self,
+        token: &str,
+    ) -> Result<(Claims, IssuerGeneration), TokenVerificationError> {
+        self.verify_with(token, false)
+    }
+
+    fn verify_with(
+        &self,
+        token: &str,
+        validate_exp: bool,
+    ) -> Result<(Claims, IssuerGeneration), TokenVerificationError> {
+        let current_err = match self.current.decode(token, &self.validation, validate_exp) {
+            Ok(claims) => return Ok((claims, IssuerGeneration::Current)),
+            Err(e) => e,
+        };
+
+        let Some(legacy) = &self.legacy else {
+            return Err(TokenVerificationError::Invalid(current_err));
+        };
+
+        match legacy.key.decode(token, &self.validation, validate_exp) {
+            Ok(claims) if legacy.is_accepting(Utc::now()) => Ok((claims, IssuerGeneration::Legacy)),
+            Ok(_) => Err(TokenVerificationError::LegacyWindowClosed),
+            Err(_) => Err(TokenVerificationError::Invalid(current_err)),
+        }
+    }
+}
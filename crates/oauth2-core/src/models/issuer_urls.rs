@@ -0,0 +1,160 @@
+#[cfg(feature = "actix")]
+use actix_web::HttpRequest;
+use std::net::IpAddr;
+
+use super::TrustedProxies;
+
+/// Builds absolute URLs for this server's endpoints.
+///
+/// The base URL is the configured issuer when set. Otherwise it is derived per request:
+/// from `Forwarded` / `X-Forwarded-Proto` / `X-Forwarded-Host` when the request came
+/// from a trusted proxy, else from the `Host` header and the scheme the server itself was
+/// reached on.
+#[derive(Debug, Clone, Default)]
+pub struct IssuerUrls {
+    issuer: Option<String>,
+    trusted_proxies: TrustedProxies,
+    /// Appended to the base URL, e.g. `/t/acme` for a tenant.
+    path_prefix: String,
+}
+
+/// Request data the base URL is derived from when no issuer is configured.
+#[derive(Debug, Clone, Default)]
+pub struct RequestOrigin<'a> {
+    /// Scheme the server was reached on (`http` or `https`).
+    pub scheme: &'a str,
+    /// Address of the connected peer; `None` over a Unix domain socket.
+    pub peer: Option<IpAddr>,
+    pub host: Option<&'a str>,
+    pub forwarded: Option<&'a str>,
+    pub x_forwarded_proto: Option<&'a str>,
+    pub x_forwarded_host: Option<&'a str>,
+}
+
+impl IssuerUrls {
+    /// `issuer` is the public base URL, e.g. `https://auth.example.com`.
+    pub fn new(issuer: Option<String>) -> Self {
+        Self {
+            issuer: issuer
+                .map(|issuer| issuer.trim().trim_end_matches('/').to_string())
+                .filter(|issuer| !issuer.is_empty()),
+            trusted_proxies: TrustedProxies::default(),
+            path_prefix: String::new(),
+        }
+    }
+
+    /// Believe forwarded headers from any peer. Only enable behind a proxy that
+    /// overwrites these headers; clients can set them too.
+    pub fn with_forwarded_headers(mut self, trust: bool) -> Self {
+        self.trusted_proxies = if trust {
+            TrustedProxies::any()
+        } else {
+            TrustedProxies::default()
+        };
+        self
+    }
+
+    /// Believe forwarded headers from `proxies` only.
+    pub fn with_trusted_proxies(mut self, proxies: TrustedProxies) -> Self {
+        self.trusted_proxies = proxies;
+        self
+    }
+
+    /// URLs of a [`Tenant`](crate::Tenant)'s endpoints (`/t/{id}/...`); `None` keeps the
+    /// default tenant's.
+    pub fn for_tenant(mut self, tenant_id: Option<&str>) -> Self {
+        if let Some(tenant_id) = tenant_id {
+            self.path_prefix = format!("/t/{tenant_id}");
+        }
+        self
+    }
+
+    pub fn configured_issuer(&self) -> Option<&str> {
+        self.issuer.as_deref()
+    }
+
+    /// Base URL (no trailing slash) for a request with the given origin.
+    pub fn base_url(&self, origin: &RequestOrigin<'_>) -> String {
+        if let Some(issuer) = &self.issuer {
+            return format!("{issuer}{}", self.path_prefix);
+        }
+
+        let (mut scheme, mut host) = (None, None);
+        if self.trusted_proxies.trusts(origin.peer) {
+            if let Some(forwarded) = origin.forwarded {
+                (scheme, host) = parse_forwarded(forwarded);
+            }
+            scheme = scheme.or_else(|| first_value(origin.x_forwarded_proto));
+            host = host.or_else(|| first_value(origin.x_forwarded_host));
+        }
+
+        let scheme = scheme.unwrap_or(origin.scheme).to_ascii_lowercase();
+        let host = host.or(origin.host).unwrap_or("localhost");
+        format!("{scheme}://{host}{}", self.path_prefix)
+    }
+
+    /// Absolute URL of `path` (which must start with `/`).
+    pub fn url(&self, origin: &RequestOrigin<'_>, path: &str) -> String {
+        format!("{}{}", self.base_url(origin), path)
+    }
+
+    /// Absolute URL of the token endpoint.
+    pub fn token_endpoint(&self, origin: &RequestOrigin<'_>) -> String {
+        self.url(origin, "/oauth/token")
+    }
+
+    /// Default redirect URI registered with a social login provider.
+    pub fn social_callback(&self, origin: &RequestOrigin<'_>, provider: &str) -> String {
+        self.url(origin, &format!("/auth/callback/{provider}"))
+    }
+}
+
+#[cfg(feature = "actix")]
+impl<'a> From<&'a HttpRequest> for RequestOrigin<'a> {
+    fn from(req: &'a HttpRequest) -> Self {
+        let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok());
+        Self {
+            scheme: if req.app_config().secure() {
+                "https"
+            } else {
+                "http"
+            },
+            peer: req.peer_addr().map(|addr| addr.ip()),
+            host: header("host")
+                .or_else(|| req.uri().authority().map(|a| a.as_str()))
+                .or(Some(req.app_config().host())),
+            forwarded: header("forwarded"),
+            x_forwarded_proto: header("x-forwarded-proto"),
+            x_forwarded_host: header("x-forwarded-host"),
+        }
+    }
+}
+
+/// `proto` and `host` of the first (client-most) element of an RFC 7239 `Forwarded` header.
+fn parse_forwarded(value: &str) -> (Option<&str>, Option<&str>) {
+    let (mut proto, mut host) = (None, None);
+    let first = value.split(',').next().unwrap_or_default();
+    for pair in first.split(';') {
+        let Some((name, value)) = pair.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"');
+        if value.is_empty() {
+            continue;
+        }
+        match name.trim().to_ascii_lowercase().as_str() {
+            "proto" => proto = Some(value),
+            "host" => host = Some(value),
+            _ => {}
+        }
+    }
+    (proto, host)
+}
+
+/// First entry of a comma-separated `X-Forwarded-*` header.
+fn first_value(value: Option<&str>) -> Option<&str> {
+    value
+        .and_then(|v| v.split(',').next())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
@@ -1,13 +1,53 @@
+pub mod admin_role;
+pub mod audit;
 pub mod authorization;
 pub mod client;
+pub mod client_metadata;
+pub mod context_binding;
+pub mod email_token;
 pub mod error;
+pub mod federated_identity;
+pub mod grant_type;
+pub mod issuer;
+pub mod issuer_urls;
+pub mod outbox;
+pub mod password;
+pub mod redirect_uri;
+pub mod revocation;
+pub mod role;
 pub mod scope;
+pub mod service_account;
+pub mod social_login_state;
+pub mod tenant;
 pub mod token;
+pub mod token_metadata;
+pub mod trusted_proxies;
 pub mod user;
+pub mod user_session;
 
+pub use admin_role::*;
+pub use audit::*;
 pub use authorization::*;
 pub use client::*;
+pub use client_metadata::*;
+pub use context_binding::*;
+pub use email_token::*;
 pub use error::*;
+pub use federated_identity::*;
+pub use grant_type::*;
+pub use issuer::*;
+pub use issuer_urls::*;
+pub use outbox::*;
+pub use password::*;
+pub use redirect_uri::*;
+pub use revocation::*;
+pub use role::*;
 pub use scope::*;
+pub use service_account::*;
+pub use social_login_state::*;
+pub use tenant::*;
 pub use token::*;
+pub use token_metadata::*;
+pub use trusted_proxies::*;
 pub use user::*;
+pub use user_session::*;
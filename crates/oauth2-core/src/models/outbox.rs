@@ -0,0 +1,43 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// An event waiting in the outbox for the relay to publish it.
+///
+/// Written with the storage change that caused the event, so the event is published once
+/// the change is committed, however long the broker is unavailable, and never without it.
+/// The relay deletes the message once the event backend has accepted it.
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutboxMessage {
+    /// The event's id, which consumers deduplicate redeliveries by.
+    pub id: String,
+    /// The event type, for logs, without decoding the payload.
+    pub event_type: String,
+    /// The event envelope as JSON, signed when event signing is configured.
+    pub payload: String,
+    pub created_at: DateTime<Utc>,
+    /// Failed publish attempts so far.
+    pub attempts: i32,
+    /// When the relay may next try to publish the message. A relay working on the message
+    /// moves it forward, leasing the message until then.
+    pub next_attempt_at: DateTime<Utc>,
+    /// Why the last attempt failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+impl OutboxMessage {
+    /// A message due immediately.
+    pub fn new(id: String, event_type: String, payload: String) -> Self {
+        let now = Utc::now();
+        Self {
+            id,
+            event_type,
+            payload,
+            created_at: now,
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+
+use super::error::OAuth2Error;
+
+/// Shortest password users may set.
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Check a password a user is about to set.
+pub fn validate_password(password: &str) -> Result<(), OAuth2Error> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(OAuth2Error::invalid_request(&format!(
+            "password must be at least {} characters",
+            MIN_PASSWORD_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+/// Hash a password with Argon2id and a random salt (PHC string format).
+pub fn hash_password(password: &str) -> Result<String, OAuth2Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))
+}
+
+/// Verify a password against a PHC-formatted hash. Malformed hashes never verify.
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .map(|parsed| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &parsed)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+/// Whether `value` looks like a PHC-formatted hash rather than a plaintext secret.
+pub fn is_password_hash(value: &str) -> bool {
+    PasswordHash::new(value).is_ok()
+}
@@ -0,0 +1,272 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use super::error::OAuth2Error;
+
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+/// A redirect URI that is safe to register (RFC 6749 section 3.1.2, RFC 8252).
+///
+/// Parsing enforces the registration rules: no fragment, no user credentials or
+/// wildcards, and `https` unless the host is a loopback address (`localhost`,
+/// `127.0.0.0/8`, `[::1]`). Native apps may also use a private-use scheme in reverse
+/// domain name form, such as `com.example.app:/callback`; whether a client may register
+/// one is up to its [`ClientType`](crate::ClientType).
+///
+/// The URI is kept normalized: scheme and host lowercased, default ports dropped and an
+/// empty path written as `/`. Two redirect URIs are the same if their normalized forms
+/// are equal; path and query are compared exactly.
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RedirectUri(String);
+
+impl RedirectUri {
+    pub fn parse(uri: &str) -> Result<Self, OAuth2Error> {
+        if uri.is_empty() {
+            return Err(OAuth2Error::invalid_request(
+                "redirect_uri must not be empty",
+            ));
+        }
+        if uri.chars().any(|c| c.is_control() || c.is_whitespace()) {
+            return Err(OAuth2Error::invalid_request(
+                "redirect_uri contains invalid characters",
+            ));
+        }
+        if uri.contains('#') {
+            return Err(OAuth2Error::invalid_request(
+                "redirect_uri must not contain a fragment",
+            ));
+        }
+
+        let Some((scheme, rest)) = uri.split_once(':') else {
+            return Err(not_absolute());
+        };
+        let scheme = scheme.to_ascii_lowercase();
+        match scheme.as_str() {
+            "https" | "http" => parse_web(&scheme, rest).map(Self),
+            _ if is_private_use_redirect_uri(uri) => Ok(Self(format!("{scheme}:{rest}"))),
+            _ => Err(OAuth2Error::invalid_request(
+                "redirect_uri must use https, a loopback address or a private-use scheme",
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether this is an `http(s)` redirect to a loopback host.
+    pub fn is_loopback(&self) -> bool {
+        web_host(&self.0).is_some_and(is_loopback_host)
+    }
+
+    pub fn is_private_use(&self) -> bool {
+        web_host(&self.0).is_none()
+    }
+
+    /// Whether both are the same loopback IP redirect apart from the port, which native
+    /// apps pick at runtime (RFC 8252 section 7.3). `localhost` does not qualify, as
+    /// RFC 8252 section 8.3 advises against it.
+    pub fn matches_ignoring_loopback_port(&self, other: &RedirectUri) -> bool {
+        loopback_redirect_without_port(&self.0)
+            .is_some_and(|uri| loopback_redirect_without_port(&other.0) == Some(uri))
+    }
+}
+
+fn not_absolute() -> OAuth2Error {
+    OAuth2Error::invalid_request("redirect_uri must be an absolute URI")
+}
+
+/// Normalize the part after `scheme:` of an `http(s)` URI.
+fn parse_web(scheme: &str, rest: &str) -> Result<String, OAuth2Error> {
+    let rest = rest.strip_prefix("//").ok_or_else(not_absolute)?;
+    let (authority, path) = rest.split_at(rest.find(['/', '?']).unwrap_or(rest.len()));
+    if authority.contains('@') {
+        return Err(OAuth2Error::invalid_request(
+            "redirect_uri must not contain user credentials",
+        ));
+    }
+    if authority.contains('*') {
+        return Err(OAuth2Error::invalid_request(
+            "redirect_uri must not contain wildcards",
+        ));
+    }
+
+    let (host, port) = split_host_port(authority)?;
+    if host.is_empty() {
+        return Err(not_absolute());
+    }
+    let host = host.to_ascii_lowercase();
+    if scheme == "http" && !is_loopback_host(&host) {
+        return Err(OAuth2Error::invalid_request(
+            "redirect_uri must use https unless it is a loopback address",
+        ));
+    }
+
+    let default_port = if scheme == "https" { 443 } else { 80 };
+    let port = match port {
+        Some(port) if port != default_port => format!(":{port}"),
+        _ => String::new(),
+    };
+    let path = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("/{path}")
+    };
+    Ok(format!("{scheme}://{host}{port}{path}"))
+}
+
+/// Split `host[:port]` or `[v6][:port]`. An empty port (`host:`) is no port.
+fn split_host_port(authority: &str) -> Result<(&str, Option<u16>), OAuth2Error> {
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(rest) => {
+            let end = rest.find(']').ok_or_else(not_absolute)?;
+            let (host, port) = authority.split_at(end + 2);
+            match port {
+                "" => (host, None),
+                port => (host, Some(port.strip_prefix(':').ok_or_else(not_absolute)?)),
+            }
+        }
+        None => match authority.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+    let port = match port.filter(|port| !port.is_empty()) {
+        Some(port) if port.bytes().all(|b| b.is_ascii_digit()) => Some(
+            port.parse()
+                .map_err(|_| OAuth2Error::invalid_request("redirect_uri has an invalid port"))?,
+        ),
+        Some(_) => {
+            return Err(OAuth2Error::invalid_request(
+                "redirect_uri has an invalid port",
+            ))
+        }
+        None => None,
+    };
+    Ok((host, port))
+}
+
+/// Host of a normalized `http(s)` redirect URI; `None` for private-use schemes.
+fn web_host(uri: &str) -> Option<&str> {
+    let rest = uri
+        .strip_prefix("https://")
+        .or_else(|| uri.strip_prefix("http://"))?;
+    let authority = &rest[..rest.find('/').unwrap_or(rest.len())];
+    let (host, _) = split_host_port(authority).ok()?;
+    Some(host)
+}
+
+fn is_loopback_host(host: &str) -> bool {
+    if host == "localhost" {
+        return true;
+    }
+    if let Some(v6) = host.strip_prefix('[').and_then(|h| h.strip_suffix(']')) {
+        return v6.parse::<Ipv6Addr>().is_ok_and(|ip| ip.is_loopback());
+    }
+    host.parse::<Ipv4Addr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Split `http://127.0.0.1[:port]/path` or `http://[::1][:port]/path` into host and the
+/// part after the port. `None` for anything else, including `localhost`, which RFC 8252
+/// section 8.3 advises against.
+fn loopback_redirect_without_port(uri: &str) -> Option<(&str, &str)> {
+    let rest = uri.strip_prefix("http://")?;
+    let (host, rest) = ["127.0.0.1", "[::1]"]
+        .into_iter()
+        .find_map(|host| rest.strip_prefix(host).map(|rest| (host, rest)))?;
+    let rest = match rest.strip_prefix(':') {
+        Some(port_and_path) => {
+            let port_len = port_and_path
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(port_and_path.len());
+            if port_len == 0 {
+                return None;
+            }
+            &port_and_path[port_len..]
+        }
+        None => rest,
+    };
+    (rest.is_empty() || rest.starts_with('/') || rest.starts_with('?')).then_some((host, rest))
+}
+
+/// Whether `uri` uses a private-use URI scheme in reverse domain name form, such as
+/// `com.example.app:/callback` (RFC 8252 section 7.1).
+pub fn is_private_use_redirect_uri(uri: &str) -> bool {
+    let Some((scheme, rest)) = uri.split_once(':') else {
+        return false;
+    };
+    scheme.contains('.')
+        && !rest.is_empty()
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '+' | '-'))
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+}
+
+impl fmt::Display for RedirectUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for RedirectUri {
+    type Err = OAuth2Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Serialize for RedirectUri {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+/// Rejects invalid URIs with the reason as the error message.
+impl<'de> Deserialize<'de> for RedirectUri {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let uri = String::deserialize(deserializer)?;
+        Self::parse(&uri)
+            .map_err(|e| serde::de::Error::custom(e.error_description.unwrap_or(e.error)))
+    }
+}
+
+impl TryFrom<String> for RedirectUri {
+    type Error = OAuth2Error;
+
+    fn try_from(uri: String) -> Result<Self, Self::Error> {
+        Self::parse(&uri)
+    }
+}
+
+impl From<RedirectUri> for String {
+    fn from(uri: RedirectUri) -> Self {
+        uri.0
+    }
+}
+
+impl AsRef<str> for RedirectUri {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Protection against a leaked registration access token being used to redirect a
+/// client's authorization codes elsewhere.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedirectUriChangePolicy {
+    /// New redirect URIs apply as soon as the update is accepted.
+    #[default]
+    Immediate,
+    /// New redirect URIs are held back for the configured delay, giving the
+    /// owner time to notice the change event and revoke the registration.
+    Delayed,
+    /// New redirect URIs are held back until an administrator approves them.
+    Approval,
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use super::error::OAuth2Error;
+use super::token::Claims;
+
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+/// A revoked access token, identified by its `jti`. Listed until `exp`, after which
+/// resource servers reject the token anyway.
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevokedToken {
+    pub jti: String,
+    pub exp: i64,
+}
+
+/// Revoked, unexpired access tokens as served at `GET /oauth/revocations`.
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevocationList {
+    pub revoked: Vec<RevokedToken>,
+}
+
+/// Revocation awareness for resource servers that validate JWTs locally.
+///
+/// Fetch `GET /oauth/revocations` periodically, sending [`RevocationChecker::etag`] as
+/// `If-None-Match`, pass each response to [`RevocationChecker::apply`], and call
+/// [`RevocationChecker::is_revoked`] after verifying a token's signature and expiry.
+#[derive(Debug, Clone, Default)]
+pub struct RevocationChecker {
+    revoked: HashMap<String, i64>,
+    etag: Option<String>,
+}
+
+impl RevocationChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `ETag` of the list currently held, for the next request's `If-None-Match`.
+    pub fn etag(&self) -> Option<&str> {
+        self.etag.as_deref()
+    }
+
+    /// Take in a response: `304` keeps the current list, `200` replaces it with `body`.
+    /// Returns whether the list changed.
+    pub fn apply(
+        &mut self,
+        status: u16,
+        etag: Option<&str>,
+        body: &[u8],
+    ) -> Result<bool, OAuth2Error> {
+        match status {
+            304 => Ok(false),
+            200 => {
+                let list: RevocationList = serde_json::from_slice(body).map_err(|e| {
+                    OAuth2Error::new(
+                        "server_error",
+                        Some(&format!("Invalid revocation list: {e}")),
+                    )
+                })?;
+                self.replace(list);
+                self.etag = etag.map(str::to_string);
+                Ok(true)
+            }
+            status => Err(OAuth2Error::new(
+                "server_error",
+                Some(&format!(
+                    "Revocation list request failed with HTTP {status}"
+                )),
+            )),
+        }
+    }
+
+    /// Replace the held list, e.g. when fetching it some other way.
+    pub fn replace(&mut self, list: RevocationList) {
+        self.revoked = list
+            .revoked
+            .into_iter()
+            .map(|token| (token.jti, token.exp))
+            .collect();
+    }
+
+    /// Whether a token with these (already verified) claims has been revoked.
+    pub fn is_revoked(&self, claims: &Claims) -> bool {
+        self.is_jti_revoked(&claims.jti)
+    }
+
+    pub fn is_jti_revoked(&self, jti: &str) -> bool {
+        self.revoked
+            .get(jti)
+            .is_some_and(|exp| *exp > Utc::now().timestamp())
+    }
+
+    /// Number of revoked tokens held, including ones that have expired since.
+    pub fn len(&self) -> usize {
+        self.revoked.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.revoked.is_empty()
+    }
+}
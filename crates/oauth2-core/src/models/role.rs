@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::error::OAuth2Error;
+
+/// A role assigned to users for resource servers' access checks. With `roles.enabled`
+/// the subject's roles are issued in the `roles` claim of access tokens.
+///
+/// Unrelated to [`AdminRole`](crate::AdminRole), which guards this server's admin API.
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Role {
+    /// Unique; the value issued in tokens.
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Role {
+    pub fn new(name: String, description: Option<String>) -> Self {
+        Self {
+            name,
+            description,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn validate_name(name: &str) -> Result<(), OAuth2Error> {
+        validate_name("role", name)
+    }
+}
+
+/// A named set of users, issued in the `groups` claim of its members' access tokens.
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Group {
+    /// Unique; the value issued in tokens.
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Group {
+    pub fn new(name: String, description: Option<String>) -> Self {
+        Self {
+            name,
+            description,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn validate_name(name: &str) -> Result<(), OAuth2Error> {
+        validate_name("group", name)
+    }
+}
+
+/// Names are 1-64 letters, digits or `_-.:`, so they read the same in every token and URL.
+fn validate_name(kind: &str, name: &str) -> Result<(), OAuth2Error> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.' | b':'));
+    if !valid {
+        return Err(OAuth2Error::invalid_request(&format!(
+            "{kind} name must be 1-64 letters, digits or '_', '-', '.', ':'"
+        )));
+    }
+    Ok(())
+}
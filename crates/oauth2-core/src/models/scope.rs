@@ -1,6 +1,10 @@
 #![allow(dead_code)]
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeSet;
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
 use uuid::Uuid;
 
 #[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
@@ -21,23 +25,117 @@ impl Scope {
     }
 }
 
-pub fn validate_scopes(requested: &str, available: &str) -> bool {
-    let requested_scopes: Vec<&str> = requested.split_whitespace().collect();
-    let available_scopes: Vec<&str> = available.split_whitespace().collect();
+/// A set of scope tokens (RFC 6749 section 3.3).
+///
+/// Parsed from a space-delimited scope string; duplicates and extra whitespace are
+/// dropped. Tokens are kept sorted, so equal sets compare and print equally regardless
+/// of the order they were requested in. Serialized as a scope string.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ScopeSet(BTreeSet<String>);
+
+impl ScopeSet {
+    pub fn parse(scope: &str) -> Self {
+        Self(scope.split_whitespace().map(str::to_string).collect())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether `scope` is one of the tokens; `read` does not contain `readonly`.
+    pub fn contains(&self, scope: &str) -> bool {
+        self.0.contains(scope)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+
+    /// Whether every token in `self` is also in `other`. The empty set is a subset of
+    /// everything; callers that need a scope must check for it separately.
+    pub fn is_subset(&self, other: &ScopeSet) -> bool {
+        self.0.is_subset(&other.0)
+    }
+
+    pub fn intersection(&self, other: &ScopeSet) -> ScopeSet {
+        Self(self.0.intersection(&other.0).cloned().collect())
+    }
+
+    pub fn union(&self, other: &ScopeSet) -> ScopeSet {
+        Self(self.0.union(&other.0).cloned().collect())
+    }
+
+    /// Add a single scope token. Returns `false` if it was already present, or is empty
+    /// or contains whitespace and so is not a token.
+    pub fn insert(&mut self, scope: impl Into<String>) -> bool {
+        let scope = scope.into();
+        let is_token = !scope.is_empty() && !scope.contains(char::is_whitespace);
+        is_token && self.0.insert(scope)
+    }
+
+    pub fn remove(&mut self, scope: &str) -> bool {
+        self.0.remove(scope)
+    }
+}
 
-    requested_scopes
-        .iter()
-        .all(|s| available_scopes.contains(s))
+impl fmt::Display for ScopeSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, scope) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str(scope)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ScopeSet {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::parse(s))
+    }
+}
+
+impl From<&str> for ScopeSet {
+    fn from(scope: &str) -> Self {
+        Self::parse(scope)
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for ScopeSet {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        let mut set = Self::default();
+        for scope in iter {
+            set.insert(scope);
+        }
+        set
+    }
+}
+
+impl Serialize for ScopeSet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ScopeSet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|scope| Self::parse(&scope))
+    }
+}
+
+pub fn validate_scopes(requested: &str, available: &str) -> bool {
+    ScopeSet::parse(requested).is_subset(&ScopeSet::parse(available))
 }
 
 pub fn intersect_scopes(requested: &str, available: &str) -> String {
-    let requested_scopes: Vec<&str> = requested.split_whitespace().collect();
-    let available_scopes: Vec<&str> = available.split_whitespace().collect();
-
-    requested_scopes
-        .iter()
-        .filter(|s| available_scopes.contains(s))
-        .map(|s| s.to_string())
-        .collect::<Vec<String>>()
-        .join(" ")
+    ScopeSet::parse(requested)
+        .intersection(&ScopeSet::parse(available))
+        .to_string()
 }
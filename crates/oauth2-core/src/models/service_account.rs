@@ -0,0 +1,137 @@
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::error::OAuth2Error;
+
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+/// RFC 7523 `client_assertion_type` for `private_key_jwt` client authentication.
+pub const JWT_BEARER_ASSERTION_TYPE: &str =
+    "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
+
+/// Longest accepted lifetime (`exp - now`) of a client assertion. Assertions are not
+/// tracked for replay, so they must be short-lived.
+pub const MAX_ASSERTION_LIFETIME_SECS: i64 = 300;
+
+/// A machine identity layered on a `client_credentials` client.
+///
+/// The backing client authenticates at the token endpoint; the account narrows what it
+/// may request and can be disabled without touching the client.
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceAccount {
+    pub id: String,
+    /// Backing client; one service account per client.
+    pub client_id: String,
+    /// Unique, human-readable name (also the metrics label).
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Space-separated scopes the account may request; also the default grant.
+    pub scope: String,
+    /// PEM-encoded RSA, EC or Ed25519 public key for `private_key_jwt` authentication.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ServiceAccount {
+    pub fn new(name: String, client_id: String, scope: String) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            client_id,
+            name,
+            description: None,
+            scope,
+            public_key: None,
+            enabled: true,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn with_description(mut self, description: Option<String>) -> Self {
+        self.description = description;
+        self
+    }
+
+    pub fn with_public_key(mut self, public_key: Option<String>) -> Self {
+        self.public_key = public_key;
+        self
+    }
+
+    /// Reject keys that cannot be used to verify assertions.
+    pub fn validate_public_key(pem: &str) -> Result<(), OAuth2Error> {
+        let pem = pem.as_bytes();
+        if DecodingKey::from_rsa_pem(pem).is_ok()
+            || DecodingKey::from_ec_pem(pem).is_ok()
+            || DecodingKey::from_ed_pem(pem).is_ok()
+        {
+            Ok(())
+        } else {
+            Err(OAuth2Error::invalid_request(
+                "public_key must be a PEM-encoded RSA, EC or Ed25519 public key",
+            ))
+        }
+    }
+
+    /// Verify an RFC 7523 client assertion signed with this account's private key.
+    ///
+    /// `iss` and `sub` must be the backing client id, `aud` must be `token_endpoint`
+    /// (absolute URL), and the assertion must expire within [`MAX_ASSERTION_LIFETIME_SECS`].
+    pub fn verify_client_assertion(
+        &self,
+        assertion: &str,
+        token_endpoint: &str,
+    ) -> Result<(), OAuth2Error> {
+        let pem = self.public_key.as_deref().ok_or_else(|| {
+            OAuth2Error::invalid_client("Service account has no public key registered")
+        })?;
+
+        let header = jsonwebtoken::decode_header(assertion)
+            .map_err(|_| OAuth2Error::invalid_client("Malformed client_assertion"))?;
+        let key = match header.alg {
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512 => DecodingKey::from_rsa_pem(pem.as_bytes()),
+            Algorithm::ES256 | Algorithm::ES384 => DecodingKey::from_ec_pem(pem.as_bytes()),
+            Algorithm::EdDSA => DecodingKey::from_ed_pem(pem.as_bytes()),
+            _ => {
+                return Err(OAuth2Error::invalid_client(
+                    "client_assertion must be signed with an asymmetric algorithm",
+                ))
+            }
+        }
+        .map_err(|_| {
+            OAuth2Error::invalid_client("client_assertion algorithm does not match the key")
+        })?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_required_spec_claims(&["exp", "iss", "sub", "aud"]);
+        validation.set_issuer(&[&self.client_id]);
+        validation.sub = Some(self.client_id.clone());
+        validation.set_audience(&[token_endpoint]);
+
+        let data = jsonwebtoken::decode::<serde_json::Value>(assertion, &key, &validation)
+            .map_err(|e| OAuth2Error::invalid_client(&format!("Invalid client_assertion: {e}")))?;
+
+        let exp = data.claims.get("exp").and_then(|v| v.as_i64()).unwrap_or(0);
+        if exp - Utc::now().timestamp() > MAX_ASSERTION_LIFETIME_SECS {
+            return Err(OAuth2Error::invalid_client(
+                "client_assertion lifetime is too long",
+            ));
+        }
+
+        Ok(())
+    }
+}
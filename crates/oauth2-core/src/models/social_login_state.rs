@@ -0,0 +1,85 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// How long a user has to come back from the provider.
+pub const SOCIAL_LOGIN_STATE_TTL_SECS: i64 = 600;
+
+/// Server-side record of an outbound social login, keyed by the `state` sent to the
+/// provider. The callback consumes it exactly once, so a replayed callback is rejected,
+/// and only from the browser that started the login (see `browser_binding`).
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SocialLoginState {
+    pub state: String,
+    pub provider: String,
+    /// PKCE verifier for the code exchange.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pkce_verifier: Option<String>,
+    /// OpenID Connect `nonce` sent to the provider.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// Hash of the browser binding cookie set when the login started; a callback carrying
+    /// another browser's cookie is someone else's login.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub browser_binding: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl SocialLoginState {
+    pub fn new(state: String, provider: String) -> Self {
+        let now = Utc::now();
+        Self {
+            state,
+            provider,
+            pkce_verifier: None,
+            nonce: None,
+            browser_binding: None,
+            created_at: now,
+            expires_at: now + Duration::seconds(SOCIAL_LOGIN_STATE_TTL_SECS),
+        }
+    }
+
+    pub fn with_pkce_verifier(mut self, verifier: String) -> Self {
+        self.pkce_verifier = Some(verifier);
+        self
+    }
+
+    pub fn with_nonce(mut self, nonce: String) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    /// Bind the login to the browser holding the binding cookie `value`.
+    pub fn with_browser_binding(mut self, value: &str) -> Self {
+        self.browser_binding = Some(hash_browser_binding(value));
+        self
+    }
+
+    pub fn is_expired(&self) -> bool {
+        Utc::now() > self.expires_at
+    }
+
+    /// Whether the callback's binding cookie `value` is the one the login started with.
+    /// Records saved without a binding match no cookie.
+    pub fn is_bound_to(&self, value: &str) -> bool {
+        self.browser_binding
+            .as_deref()
+            .is_some_and(|hash| hash == hash_browser_binding(value))
+    }
+}
+
+/// A new random browser binding cookie value.
+pub fn generate_browser_binding() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Hash a browser binding cookie value for storage (base64url-encoded SHA-256).
+pub fn hash_browser_binding(value: &str) -> String {
+    general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(value.as_bytes()))
+}
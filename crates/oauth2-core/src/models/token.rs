@@ -1,13 +1,20 @@
 #![allow(dead_code)]
 
 use chrono::{DateTime, Duration, Utc};
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::error::OAuth2Error;
+use super::issuer::JwtValidation;
+use super::token_metadata::TokenMetadata;
+
 #[cfg(feature = "openapi")]
 use utoipa::ToSchema;
 
+/// Default `iss` claim for tokens minted by this server.
+pub const DEFAULT_ISSUER: &str = "rust_oauth2_server";
+
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
@@ -18,10 +25,30 @@ pub struct Claims {
     pub iat: i64,      // Issued at
     pub scope: String, // Scopes
     pub jti: String,   // JWT ID
+    // Not before; absent on tokens minted before it was added
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub client_id: Option<String>,
+    /// Custom claims added at issuance (see [`Claims::set_claim`]).
+    #[cfg_attr(feature = "openapi", schema(ignore))]
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
 }
 
+/// Claims owned by the server; custom claims may not overwrite them.
+pub const REGISTERED_CLAIMS: &[&str] = &[
+    "sub",
+    "iss",
+    "aud",
+    "exp",
+    "iat",
+    "scope",
+    "jti",
+    "nbf",
+    "client_id",
+];
+
 impl Claims {
     pub fn new(subject: String, client_id: String, scope: String, duration_seconds: i64) -> Self {
         let now = Utc::now();
@@ -29,14 +56,52 @@ impl Claims {
 
         Self {
             sub: subject,
-            iss: "rust_oauth2_server".to_string(),
+            iss: DEFAULT_ISSUER.to_string(),
             aud: client_id.clone(),
             exp: exp.timestamp(),
             iat: now.timestamp(),
             scope,
             jti: Uuid::new_v4().to_string(),
+            nbf: Some(now.timestamp()),
             client_id: Some(client_id),
+            extra: serde_json::Map::new(),
+        }
+    }
+
+    /// Add (or replace) a custom claim. Registered claims such as `sub` or `exp` are
+    /// rejected; set those through the struct fields.
+    pub fn set_claim(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Result<(), OAuth2Error> {
+        let name = name.into();
+        if REGISTERED_CLAIMS.contains(&name.as_str()) {
+            return Err(OAuth2Error::invalid_request(&format!(
+                "'{name}' is a registered claim and cannot be overridden"
+            )));
         }
+        self.extra.insert(name, value.into());
+        Ok(())
+    }
+
+    /// A custom claim, if present.
+    pub fn claim(&self, name: &str) -> Option<&serde_json::Value> {
+        self.extra.get(name)
+    }
+
+    /// Stamp `iat`, `nbf` and `exp` as if issued at `now`, keeping the lifetime.
+    pub fn issued_at(mut self, now: DateTime<Utc>) -> Self {
+        let lifetime = self.exp - self.iat;
+        self.iat = now.timestamp();
+        self.nbf = Some(self.iat);
+        self.exp = self.iat + lifetime;
+        self
+    }
+
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.iss = issuer.into();
+        self
     }
 
     pub fn encode(&self, secret: &str) -> Result<String, jsonwebtoken::errors::Error> {
@@ -47,11 +112,20 @@ impl Claims {
         )
     }
 
+    /// Decode and verify with the default [`JwtValidation`] rules.
     pub fn decode(token: &str, secret: &str) -> Result<Self, jsonwebtoken::errors::Error> {
+        Self::decode_with(token, secret, &JwtValidation::default())
+    }
+
+    pub fn decode_with(
+        token: &str,
+        secret: &str,
+        rules: &JwtValidation,
+    ) -> Result<Self, jsonwebtoken::errors::Error> {
         let token_data = jsonwebtoken::decode::<Claims>(
             token,
             &DecodingKey::from_secret(secret.as_ref()),
-            &Validation::default(),
+            &rules.validation(),
         )?;
         Ok(token_data.claims)
     }
@@ -73,6 +147,24 @@ pub struct Token {
     pub created_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
     pub revoked: bool,
+    /// Identifier shared by every token issued under the same grant. Revoking a refresh
+    /// token revokes the whole family; rows written before it existed fall back to `id`.
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grant_id: Option<String>,
+    /// Tags attached at issuance (e.g. `deployment=canary`) for targeted lookup/revocation.
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "TokenMetadata::is_empty")]
+    pub metadata: TokenMetadata,
+    /// [`Tenant`](crate::Tenant) that issued the token; `None` for the default tenant.
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    /// Encoded [`ContextBinding`](super::ContextBinding) of the request that obtained the
+    /// refresh token, when context binding is enabled.
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_binding: Option<String>,
 }
 
 impl Token {
@@ -86,9 +178,11 @@ impl Token {
     ) -> Self {
         let now = Utc::now();
         let expires_at = now + Duration::seconds(i64::from(expires_in));
+        let id = Uuid::new_v4().to_string();
 
         Self {
-            id: Uuid::new_v4().to_string(),
+            grant_id: Some(id.clone()),
+            id,
             access_token,
             refresh_token,
             token_type: "Bearer".to_string(),
@@ -99,9 +193,50 @@ impl Token {
             created_at: now,
             expires_at,
             revoked: false,
+            metadata: TokenMetadata::default(),
+            tenant_id: None,
+            context_binding: None,
         }
     }
 
+    /// Attach this token to an existing grant (e.g. one minted from a refresh token).
+    /// Stamp `created_at` and `expires_at` as if issued at `now`.
+    pub fn issued_at(mut self, now: DateTime<Utc>) -> Self {
+        self.created_at = now;
+        self.expires_at = now + Duration::seconds(i64::from(self.expires_in));
+        self
+    }
+
+    pub fn with_grant_id(mut self, grant_id: impl Into<String>) -> Self {
+        self.grant_id = Some(grant_id.into());
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: TokenMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    pub fn with_tenant_id(mut self, tenant_id: Option<String>) -> Self {
+        self.tenant_id = tenant_id;
+        self
+    }
+
+    pub fn with_context_binding(mut self, binding: Option<String>) -> Self {
+        self.context_binding = binding;
+        self
+    }
+
+    /// Whether this token was issued by `tenant_id` (`None` is the default tenant).
+    pub fn in_tenant(&self, tenant_id: Option<&str>) -> bool {
+        self.tenant_id.as_deref() == tenant_id
+    }
+
+    /// The grant this token belongs to.
+    pub fn grant_id(&self) -> &str {
+        self.grant_id.as_deref().unwrap_or(&self.id)
+    }
+
     pub fn is_expired(&self) -> bool {
         Utc::now() > self.expires_at
     }
@@ -135,8 +270,12 @@ impl From<Token> for TokenResponse {
     }
 }
 
+/// RFC 7662 token introspection response.
+///
+/// Inactive tokens (unknown, expired, revoked or failing verification) are reported as
+/// `{"active": false}` with no other members.
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct IntrospectionResponse {
     pub active: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -152,5 +291,38 @@ pub struct IntrospectionResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub iat: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+}
+
+impl IntrospectionResponse {
+    pub fn inactive() -> Self {
+        Self::default()
+    }
+
+    /// Describe an active token from its stored record and verified JWT claims.
+    pub fn active(token: Token, claims: Claims) -> Self {
+        Self {
+            active: true,
+            scope: Some(token.scope),
+            client_id: Some(token.client_id),
+            username: token.user_id,
+            token_type: Some(token.token_type),
+            exp: Some(claims.exp),
+            iat: Some(claims.iat),
+            // Tokens are usable from issuance when no explicit `nbf` was minted.
+            nbf: Some(claims.nbf.unwrap_or(claims.iat)),
+            sub: Some(claims.sub),
+            aud: Some(claims.aud),
+            iss: Some(claims.iss),
+            jti: Some(claims.jti),
+        }
+    }
 }
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::error::OAuth2Error;
+
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+/// Most tags a single token may carry.
+pub const MAX_TOKEN_METADATA_ENTRIES: usize = 16;
+const MAX_KEY_LEN: usize = 64;
+const MAX_VALUE_LEN: usize = 256;
+
+/// Key/value tags attached to a token at issuance, e.g. `deployment=canary`.
+///
+/// Tags are opaque to the server; they exist so operators can find (and revoke) a
+/// targeted set of tokens. Stored as a JSON object.
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TokenMetadata(BTreeMap<String, String>);
+
+impl TokenMetadata {
+    /// Parse the `metadata` token request parameter: a JSON object of string values.
+    pub fn from_json(json: &str) -> Result<Self, OAuth2Error> {
+        let entries: BTreeMap<String, String> = serde_json::from_str(json).map_err(|_| {
+            OAuth2Error::invalid_request("metadata must be a JSON object of string values")
+        })?;
+        if entries.len() > MAX_TOKEN_METADATA_ENTRIES {
+            return Err(Self::too_many_entries());
+        }
+        for (key, value) in &entries {
+            Self::validate_key(key)?;
+            Self::validate_value(key, value)?;
+        }
+        Ok(Self(entries))
+    }
+
+    /// Keys are 1-64 characters of `[A-Za-z0-9_.:-]`, so they can be used in queries as-is.
+    pub fn validate_key(key: &str) -> Result<(), OAuth2Error> {
+        let valid = !key.is_empty()
+            && key.len() <= MAX_KEY_LEN
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | ':' | '-'));
+        if valid {
+            Ok(())
+        } else {
+            Err(OAuth2Error::invalid_request(&format!(
+                "invalid metadata key '{key}': use 1-{MAX_KEY_LEN} characters of [A-Za-z0-9_.:-]"
+            )))
+        }
+    }
+
+    /// Add (or replace) a tag, applying the same limits as [`TokenMetadata::from_json`].
+    pub fn insert(
+        &mut self,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Result<(), OAuth2Error> {
+        let (key, value) = (key.into(), value.into());
+        Self::validate_key(&key)?;
+        Self::validate_value(&key, &value)?;
+        if self.0.len() >= MAX_TOKEN_METADATA_ENTRIES && !self.0.contains_key(&key) {
+            return Err(Self::too_many_entries());
+        }
+        self.0.insert(key, value);
+        Ok(())
+    }
+
+    fn validate_value(key: &str, value: &str) -> Result<(), OAuth2Error> {
+        if value.chars().count() > MAX_VALUE_LEN {
+            return Err(OAuth2Error::invalid_request(&format!(
+                "metadata value for '{key}' must not exceed {MAX_VALUE_LEN} characters"
+            )));
+        }
+        Ok(())
+    }
+
+    fn too_many_entries() -> OAuth2Error {
+        OAuth2Error::invalid_request(&format!(
+            "metadata must not have more than {MAX_TOKEN_METADATA_ENTRIES} entries"
+        ))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.0).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for TokenMetadata {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+// Stored in a TEXT column as JSON, so decode through `String` on any database.
+#[cfg(feature = "sqlx")]
+impl<DB: sqlx::Database> sqlx::Type<DB> for TokenMetadata
+where
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <String as sqlx::Type<DB>>::compatible(ty)
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl<'r, DB: sqlx::Database> sqlx::Decode<'r, DB> for TokenMetadata
+where
+    String: sqlx::Decode<'r, DB>,
+{
+    fn decode(value: DB::ValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let json = <String as sqlx::Decode<DB>>::decode(value)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
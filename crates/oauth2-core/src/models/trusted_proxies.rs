@@ -0,0 +1,143 @@
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
+
+#[cfg(feature = "actix")]
+use actix_web::{web, HttpRequest};
+
+/// Peers whose `Forwarded` / `X-Forwarded-*` headers are believed.
+///
+/// Behind a reverse proxy the TCP peer is the proxy, and the client address and scheme
+/// are only known from the headers it adds. Clients can send the same headers, so they
+/// are ignored unless the request arrived from a trusted proxy.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    networks: Vec<IpNet>,
+    /// Connections without a peer address, i.e. over a Unix domain socket.
+    unix_sockets: bool,
+    any: bool,
+}
+
+impl TrustedProxies {
+    /// Trust forwarded headers from every peer. Only safe when every route to the server
+    /// passes a proxy that overwrites them.
+    pub fn any() -> Self {
+        Self {
+            any: true,
+            ..Self::default()
+        }
+    }
+
+    /// Parse CIDR ranges (`10.0.0.0/8`), single addresses (`192.0.2.1`) and `unix` (peers
+    /// connected over a Unix domain socket).
+    pub fn parse<I, S>(entries: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut proxies = Self::default();
+        for entry in entries {
+            let entry = entry.as_ref().trim();
+            if entry.eq_ignore_ascii_case("unix") {
+                proxies.unix_sockets = true;
+            } else if let Ok(network) = entry.parse::<IpNet>() {
+                proxies.networks.push(network);
+            } else if let Ok(address) = entry.parse::<IpAddr>() {
+                proxies.networks.push(IpNet::from(address));
+            } else {
+                return Err(format!(
+                    "invalid trusted proxy {entry:?}: expected a CIDR range, an IP address or \"unix\""
+                ));
+            }
+        }
+        Ok(proxies)
+    }
+
+    /// Whether forwarded headers from `peer` are believed; `None` is a Unix socket peer.
+    pub fn trusts(&self, peer: Option<IpAddr>) -> bool {
+        if self.any {
+            return true;
+        }
+        match peer {
+            Some(ip) => {
+                let ip = ip.to_canonical();
+                self.networks.iter().any(|network| network.contains(&ip))
+            }
+            None => self.unix_sockets,
+        }
+    }
+
+    /// The address of the client behind any trusted proxies.
+    ///
+    /// The forwarding chain (`Forwarded: for=`, else `X-Forwarded-For`) is walked from the
+    /// peer towards the client, and the first hop not run by a trusted proxy is the
+    /// client. Entries further left were supplied by that client and are ignored.
+    pub fn client_ip(
+        &self,
+        peer: Option<IpAddr>,
+        forwarded: Option<&str>,
+        x_forwarded_for: Option<&str>,
+    ) -> Option<IpAddr> {
+        if !self.trusts(peer) {
+            return peer;
+        }
+        let hops: Vec<&str> = match forwarded {
+            Some(forwarded) => forwarded_for(forwarded).collect(),
+            None => x_forwarded_for
+                .map(|value| value.split(',').map(str::trim).collect())
+                .unwrap_or_default(),
+        };
+
+        let mut client = peer;
+        for hop in hops.iter().rev() {
+            // Obfuscated or unknown hops (`for=_hidden`, `for=unknown`) end the chain.
+            let Some(ip) = parse_hop(hop) else {
+                break;
+            };
+            client = Some(ip);
+            if !self.trusts(Some(ip)) {
+                break;
+            }
+        }
+        client
+    }
+
+    /// The client address of `req`, per the [`TrustedProxies`] registered as app data
+    /// (none trusted when absent).
+    #[cfg(feature = "actix")]
+    pub fn client_ip_of(req: &HttpRequest) -> Option<IpAddr> {
+        let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok());
+        let peer = req.peer_addr().map(|addr| addr.ip());
+        match req.app_data::<web::Data<TrustedProxies>>() {
+            Some(proxies) => {
+                proxies.client_ip(peer, header("forwarded"), header("x-forwarded-for"))
+            }
+            None => peer,
+        }
+    }
+}
+
+/// `for=` values of every element of an RFC 7239 `Forwarded` header, client first.
+fn forwarded_for(value: &str) -> impl Iterator<Item = &str> {
+    value.split(',').filter_map(|element| {
+        element.split(';').find_map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            name.trim()
+                .eq_ignore_ascii_case("for")
+                .then(|| value.trim().trim_matches('"'))
+        })
+    })
+}
+
+/// An address from a forwarding header, with or without port and IPv6 brackets.
+fn parse_hop(hop: &str) -> Option<IpAddr> {
+    hop.parse::<IpAddr>()
+        .ok()
+        .or_else(|| hop.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+        .or_else(|| {
+            hop.strip_prefix('[')?
+                .strip_suffix(']')?
+                .parse::<IpAddr>()
+                .ok()
+        })
+        .map(|ip| ip.to_canonical())
+}
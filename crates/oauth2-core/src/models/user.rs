@@ -11,9 +11,17 @@ pub struct User {
     pub username: String,
     pub password_hash: String,
     pub email: String,
+    /// Whether the user proved they receive mail at `email`.
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default)]
+    pub email_verified: bool,
     pub enabled: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Owning [`Tenant`](crate::Tenant); `None` for the default tenant.
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
 }
 
 impl User {
@@ -24,11 +32,18 @@ impl User {
             username,
             password_hash,
             email,
+            email_verified: false,
             enabled: true,
             created_at: now,
             updated_at: now,
+            tenant_id: None,
         }
     }
+
+    /// Whether this user belongs to `tenant_id` (`None` is the default tenant).
+    pub fn in_tenant(&self, tenant_id: Option<&str>) -> bool {
+        self.tenant_id.as_deref() == tenant_id
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
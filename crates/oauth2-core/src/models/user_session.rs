@@ -0,0 +1,79 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// A browser session kept on the server; the session cookie only carries its key.
+///
+/// A session ends at `idle_expires_at`, which moves forward with every request, or at
+/// `expires_at`, fixed when it starts, whichever comes first.
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserSession {
+    /// Hash of the cookie's key ([`hash_session_key`]), so a stored session cannot be
+    /// replayed as a cookie. Users revoke sessions by this id.
+    pub id: String,
+    /// The signed-in user; `None` until sign-in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// What handlers stored in the session: a JSON object of JSON-encoded values.
+    pub state: String,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub idle_expires_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl UserSession {
+    pub fn is_expired(&self) -> bool {
+        let now = Utc::now();
+        now > self.idle_expires_at || now > self.expires_at
+    }
+}
+
+/// A session as shown to its user, without the stored state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    /// When the session ends unless it is used again.
+    pub idle_expires_at: DateTime<Utc>,
+    /// When the session ends regardless of use.
+    pub expires_at: DateTime<Utc>,
+    /// Whether this is the session making the request.
+    pub current: bool,
+}
+
+impl SessionInfo {
+    /// `session` as shown to a request made from the session `current`, if any.
+    pub fn new(session: UserSession, current: Option<&str>) -> Self {
+        Self {
+            current: current == Some(session.id.as_str()),
+            id: session.id,
+            created_at: session.created_at,
+            last_seen_at: session.last_seen_at,
+            idle_expires_at: session.idle_expires_at,
+            expires_at: session.expires_at,
+        }
+    }
+}
+
+/// A user's active sessions, newest first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionList {
+    pub sessions: Vec<SessionInfo>,
+}
+
+/// A new random session cookie key.
+pub fn generate_session_key() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// The [`UserSession::id`] for a session cookie key (base64url-encoded SHA-256).
+pub fn hash_session_key(key: &str) -> String {
+    general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(key.as_bytes()))
+}
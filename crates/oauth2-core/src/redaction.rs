@@ -0,0 +1,215 @@
+//! Masking of sensitive values (emails, tokens, secrets) before they reach logs, spans
+//! or published events.
+//!
+//! The server installs its configured [`Redactor`] once at startup; log and span fields go
+//! through [`Redactor::current`], and event metadata through
+//! [`Redactor::redact_metadata`].
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Placeholder for dropped values where a field cannot be omitted (e.g. a log field).
+pub const REDACTED: &str = "[redacted]";
+
+/// What happens to a sensitive value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactionPolicy {
+    /// Unchanged.
+    Keep,
+    /// Truncated SHA-256, hex: stays correlatable without revealing the value.
+    Hash,
+    /// The first `truncate_length` characters.
+    Truncate,
+    /// Removed from event metadata; [`REDACTED`] in logs.
+    Drop,
+}
+
+/// Kind of sensitive value, each with its own [`RedactionPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sensitive {
+    Email,
+    /// Access, refresh and ID tokens, and authorization codes.
+    Token,
+    /// Client secrets and passwords.
+    Secret,
+}
+
+impl Sensitive {
+    /// The kind of value an event metadata entry named `key` holds, if sensitive.
+    pub fn of_field(key: &str) -> Option<Self> {
+        match key {
+            "email" => Some(Self::Email),
+            "token" | "access_token" | "refresh_token" | "id_token" | "code" => Some(Self::Token),
+            "secret" | "client_secret" | "password" => Some(Self::Secret),
+            _ => None,
+        }
+    }
+}
+
+/// Policies for each kind of [`Sensitive`] value.
+///
+/// By default emails are kept, tokens truncated to 12 characters and secrets dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Redactor {
+    #[serde(default = "default_email_policy")]
+    pub emails: RedactionPolicy,
+    #[serde(default = "default_token_policy")]
+    pub tokens: RedactionPolicy,
+    #[serde(default = "default_secret_policy")]
+    pub secrets: RedactionPolicy,
+    #[serde(default = "default_truncate_length")]
+    pub truncate_length: usize,
+}
+
+fn default_email_policy() -> RedactionPolicy {
+    Redactor::DEFAULT.emails
+}
+
+fn default_token_policy() -> RedactionPolicy {
+    Redactor::DEFAULT.tokens
+}
+
+fn default_secret_policy() -> RedactionPolicy {
+    Redactor::DEFAULT.secrets
+}
+
+fn default_truncate_length() -> usize {
+    Redactor::DEFAULT.truncate_length
+}
+
+static CURRENT: RwLock<Redactor> = RwLock::new(Redactor::DEFAULT);
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Redactor {
+    const DEFAULT: Self = Self {
+        emails: RedactionPolicy::Keep,
+        tokens: RedactionPolicy::Truncate,
+        secrets: RedactionPolicy::Drop,
+        truncate_length: 12,
+    };
+
+    /// The process-wide redactor, as last [`install`](Self::install)ed.
+    pub fn current() -> Self {
+        *CURRENT.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Make this the process-wide redactor.
+    pub fn install(self) {
+        *CURRENT.write().unwrap_or_else(|e| e.into_inner()) = self;
+    }
+
+    fn policy(&self, kind: Sensitive) -> RedactionPolicy {
+        match kind {
+            Sensitive::Email => self.emails,
+            Sensitive::Token => self.tokens,
+            Sensitive::Secret => self.secrets,
+        }
+    }
+
+    /// `value` redacted as a `kind`; `None` when dropped.
+    pub fn redact(&self, kind: Sensitive, value: &str) -> Option<String> {
+        match self.policy(kind) {
+            RedactionPolicy::Keep => Some(value.to_string()),
+            RedactionPolicy::Hash => Some(hash(value)),
+            RedactionPolicy::Truncate => Some(value.chars().take(self.truncate_length).collect()),
+            RedactionPolicy::Drop => None,
+        }
+    }
+
+    /// `value` redacted as a `kind` for a log or span field.
+    pub fn display(&self, kind: Sensitive, value: &str) -> String {
+        self.redact(kind, value)
+            .unwrap_or_else(|| REDACTED.to_string())
+    }
+
+    pub fn token(&self, token: &str) -> String {
+        self.display(Sensitive::Token, token)
+    }
+
+    pub fn email(&self, email: &str) -> String {
+        self.display(Sensitive::Email, email)
+    }
+
+    /// Redact the entries of event metadata whose name marks them as sensitive (see
+    /// [`Sensitive::of_field`]), removing dropped ones.
+    pub fn redact_metadata(&self, metadata: &mut HashMap<String, String>) {
+        metadata.retain(|key, value| {
+            let Some(kind) = Sensitive::of_field(key) else {
+                return true;
+            };
+            match self.redact(kind, value) {
+                Some(redacted) => {
+                    *value = redacted;
+                    true
+                }
+                None => false,
+            }
+        });
+    }
+}
+
+/// Truncated SHA-256 of `value`, hex: 32 characters.
+pub fn hash(value: &str) -> String {
+    let digest = Sha256::digest(value.as_bytes());
+    digest[..16].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_keep_emails_truncate_tokens_and_drop_secrets() {
+        let redactor = Redactor::default();
+        assert_eq!(redactor.email("bob@example.com"), "bob@example.com");
+        assert_eq!(
+            redactor.token("eyJhbGciOiJIUzI1NiJ9.payload"),
+            "eyJhbGciOiJI"
+        );
+        assert_eq!(redactor.display(Sensitive::Secret, "s3cret"), REDACTED);
+    }
+
+    #[test]
+    fn hashes_are_stable_and_hide_the_value() {
+        let redactor = Redactor {
+            emails: RedactionPolicy::Hash,
+            ..Redactor::default()
+        };
+        let hashed = redactor.email("bob@example.com");
+        assert_eq!(hashed, redactor.email("bob@example.com"));
+        assert_ne!(hashed, redactor.email("alice@example.com"));
+        assert_eq!(hashed.len(), 32);
+        assert!(!hashed.contains("bob"));
+    }
+
+    #[test]
+    fn metadata_is_redacted_by_field_name() {
+        let redactor = Redactor {
+            emails: RedactionPolicy::Hash,
+            truncate_length: 4,
+            ..Redactor::default()
+        };
+        let mut metadata = HashMap::from([
+            ("email".to_string(), "bob@example.com".to_string()),
+            ("refresh_token".to_string(), "rt_0123456789".to_string()),
+            ("client_secret".to_string(), "s3cret".to_string()),
+            ("has_refresh_token".to_string(), "true".to_string()),
+            ("scope".to_string(), "read".to_string()),
+        ]);
+        redactor.redact_metadata(&mut metadata);
+
+        assert_eq!(metadata["email"], hash("bob@example.com"));
+        assert_eq!(metadata["refresh_token"], "rt_0");
+        assert!(!metadata.contains_key("client_secret"));
+        assert_eq!(metadata["has_refresh_token"], "true");
+        assert_eq!(metadata["scope"], "read");
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     bus::{EventBus, EventBusError},
-    event_actor::{EmitEvent, EventActor},
+    event_actor::{DeliverEvent, EmitEvent, EventActor},
     EventEnvelope,
 };
 use actix::prelude::*;
@@ -11,14 +11,27 @@ use async_trait::async_trait;
 /// Phase 1 behavior:
 /// - Best-effort: uses `do_send` (fire-and-forget) so producers never block.
 /// - Errors cannot be observed from `do_send`; failures are logged within `EventActor` plugins.
+///
+/// [`acknowledged`](Self::acknowledged) instead waits for every plugin, for the outbox relay.
 #[derive(Clone)]
 pub struct ActixEventBus {
     addr: Addr<EventActor>,
+    acknowledged: bool,
 }
 
 impl ActixEventBus {
     pub fn new(addr: Addr<EventActor>) -> Self {
-        Self { addr }
+        Self {
+            addr,
+            acknowledged: false,
+        }
+    }
+
+    /// Publish by waiting until every plugin has emitted the event, failing if any of
+    /// them failed. Events the actor's filter drops count as published.
+    pub fn acknowledged(mut self) -> Self {
+        self.acknowledged = true;
+        self
     }
 
     #[allow(dead_code)]
@@ -30,6 +43,14 @@ impl ActixEventBus {
 #[async_trait]
 impl EventBus for ActixEventBus {
     async fn publish(&self, envelope: EventEnvelope) -> Result<(), EventBusError> {
+        if self.acknowledged {
+            return self
+                .addr
+                .send(DeliverEvent { envelope })
+                .await
+                .map_err(|_| EventBusError::Unavailable)?
+                .map_err(EventBusError::Rejected);
+        }
         // Best-effort and non-blocking.
         self.addr.do_send(EmitEvent { envelope });
         Ok(())
@@ -66,4 +87,35 @@ mod tests {
         tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
         assert_eq!(logger.get_events().len(), 1);
     }
+
+    struct FailingPlugin;
+
+    #[async_trait]
+    impl EventPlugin for FailingPlugin {
+        async fn emit(&self, _envelope: &EventEnvelope) -> Result<(), String> {
+            Err("broker unavailable".to_string())
+        }
+
+        fn name(&self) -> &str {
+            "failing"
+        }
+    }
+
+    #[actix::test]
+    async fn acknowledged_bus_reports_plugin_failures() {
+        let logger = Arc::new(InMemoryEventLogger::new(10));
+        let plugins: Vec<Arc<dyn EventPlugin>> = vec![logger.clone(), Arc::new(FailingPlugin)];
+        let actor = EventActor::new(plugins, EventFilter::allow_all()).start();
+        let bus = ActixEventBus::new(actor).acknowledged();
+
+        let event = AuthEvent::new(EventType::TokenCreated, EventSeverity::Info, None, None);
+        let err = bus
+            .publish(EventEnvelope::from_current_span(event, "test"))
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("failing: broker unavailable"));
+        // Delivered to the healthy plugin before the call returned.
+        assert_eq!(logger.get_events().len(), 1);
+    }
 }
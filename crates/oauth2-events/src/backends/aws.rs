@@ -0,0 +1,416 @@
+//! AWS request signing (Signature Version 4, by `aws-sigv4`) and credential sources for
+//! the SNS/SQS publishers, also used by other crates calling AWS APIs.
+
+use aws_sigv4::http_request::{sign, SignableBody, SignableRequest, SigningSettings};
+use aws_sigv4::sign::v4;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::time::{Duration, SystemTime};
+use tokio::sync::Mutex;
+
+/// Access key for signing requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Set for temporary credentials.
+    pub session_token: Option<String>,
+}
+
+/// Where the publisher gets its credentials.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AwsCredentialsSource {
+    /// The environment when `AWS_ACCESS_KEY_ID` is set, else the container credentials
+    /// endpoint when configured, else the instance metadata service.
+    Default,
+    /// `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`.
+    Environment,
+    Static(AwsCredentials),
+    /// The ECS/EKS container credentials endpoint
+    /// (`AWS_CONTAINER_CREDENTIALS_RELATIVE_URI` or `AWS_CONTAINER_CREDENTIALS_FULL_URI`).
+    Container,
+    /// The EC2 instance metadata service (IMDSv2).
+    Instance,
+}
+
+const CONTAINER_HOST: &str = "http://169.254.170.2";
+const IMDS_HOST: &str = "http://169.254.169.254";
+
+/// Temporary credentials are refreshed this long before they expire.
+const REFRESH_MARGIN: chrono::Duration = chrono::Duration::minutes(5);
+
+/// Resolves credentials from a source, caching temporary ones until shortly before they
+/// expire.
+pub(crate) struct AwsCredentialsProvider {
+    source: AwsCredentialsSource,
+    http: reqwest::Client,
+    cached: Mutex<Option<(AwsCredentials, Option<DateTime<Utc>>)>>,
+}
+
+/// Credentials document served by the container and instance metadata endpoints.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TemporaryCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    token: Option<String>,
+    expiration: Option<DateTime<Utc>>,
+}
+
+impl AwsCredentialsProvider {
+    pub(crate) fn new(source: AwsCredentialsSource, http: reqwest::Client) -> Self {
+        Self {
+            source,
+            http,
+            cached: Mutex::new(None),
+        }
+    }
+
+    pub(crate) async fn credentials(&self) -> Result<AwsCredentials, String> {
+        let mut cached = self.cached.lock().await;
+        if let Some((credentials, expires_at)) = cached.as_ref() {
+            if expires_at.is_none_or(|at| Utc::now() + REFRESH_MARGIN < at) {
+                return Ok(credentials.clone());
+            }
+        }
+        let (credentials, expires_at) = self.resolve().await?;
+        *cached = Some((credentials.clone(), expires_at));
+        Ok(credentials)
+    }
+
+    async fn resolve(&self) -> Result<(AwsCredentials, Option<DateTime<Utc>>), String> {
+        match &self.source {
+            AwsCredentialsSource::Static(credentials) => Ok((credentials.clone(), None)),
+            AwsCredentialsSource::Environment => environment_credentials().map(|c| (c, None)),
+            AwsCredentialsSource::Container => self.container_credentials().await,
+            AwsCredentialsSource::Instance => self.instance_credentials().await,
+            AwsCredentialsSource::Default => {
+                if std::env::var("AWS_ACCESS_KEY_ID").is_ok() {
+                    environment_credentials().map(|c| (c, None))
+                } else if std::env::var("AWS_CONTAINER_CREDENTIALS_RELATIVE_URI").is_ok()
+                    || std::env::var("AWS_CONTAINER_CREDENTIALS_FULL_URI").is_ok()
+                {
+                    self.container_credentials().await
+                } else {
+                    self.instance_credentials().await
+                }
+            }
+        }
+    }
+
+    async fn container_credentials(
+        &self,
+    ) -> Result<(AwsCredentials, Option<DateTime<Utc>>), String> {
+        let url = match std::env::var("AWS_CONTAINER_CREDENTIALS_RELATIVE_URI") {
+            Ok(path) => format!("{CONTAINER_HOST}{path}"),
+            Err(_) => std::env::var("AWS_CONTAINER_CREDENTIALS_FULL_URI")
+                .map_err(|_| "container credentials endpoint not configured".to_string())?,
+        };
+        let token = match std::env::var("AWS_CONTAINER_AUTHORIZATION_TOKEN_FILE") {
+            Ok(path) => Some(
+                tokio::fs::read_to_string(&path)
+                    .await
+                    .map_err(|e| format!("read {path}: {e}"))?
+                    .trim()
+                    .to_string(),
+            ),
+            Err(_) => std::env::var("AWS_CONTAINER_AUTHORIZATION_TOKEN").ok(),
+        };
+
+        let mut request = self.http.get(&url);
+        if let Some(token) = token {
+            request = request.header("Authorization", token);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("container credentials: {e}"))?;
+        temporary(response, "container credentials").await
+    }
+
+    async fn instance_credentials(
+        &self,
+    ) -> Result<(AwsCredentials, Option<DateTime<Utc>>), String> {
+        let token = self
+            .http
+            .put(format!("{IMDS_HOST}/latest/api/token"))
+            .header("X-aws-ec2-metadata-token-ttl-seconds", "21600")
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("instance metadata token: {e}"))?
+            .text()
+            .await
+            .map_err(|e| format!("instance metadata token: {e}"))?;
+
+        let base = format!("{IMDS_HOST}/latest/meta-data/iam/security-credentials/");
+        let roles = self
+            .http
+            .get(&base)
+            .header("X-aws-ec2-metadata-token", &token)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("instance role: {e}"))?
+            .text()
+            .await
+            .map_err(|e| format!("instance role: {e}"))?;
+        let role = roles
+            .lines()
+            .next()
+            .filter(|role| !role.is_empty())
+            .ok_or_else(|| "no instance role attached".to_string())?;
+
+        let response = self
+            .http
+            .get(format!("{base}{role}"))
+            .header("X-aws-ec2-metadata-token", &token)
+            .send()
+            .await
+            .map_err(|e| format!("instance credentials: {e}"))?;
+        temporary(response, "instance credentials").await
+    }
+}
+
+fn environment_credentials() -> Result<AwsCredentials, String> {
+    let var = |name: &str| std::env::var(name).map_err(|_| format!("{name} not set"));
+    Ok(AwsCredentials {
+        access_key_id: var("AWS_ACCESS_KEY_ID")?,
+        secret_access_key: var("AWS_SECRET_ACCESS_KEY")?,
+        session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+    })
+}
+
+async fn temporary(
+    response: reqwest::Response,
+    what: &str,
+) -> Result<(AwsCredentials, Option<DateTime<Utc>>), String> {
+    let credentials: TemporaryCredentials = response
+        .error_for_status()
+        .map_err(|e| format!("{what}: {e}"))?
+        .json()
+        .await
+        .map_err(|e| format!("{what}: {e}"))?;
+    Ok((
+        AwsCredentials {
+            access_key_id: credentials.access_key_id,
+            secret_access_key: credentials.secret_access_key,
+            session_token: credentials.token,
+        },
+        credentials.expiration,
+    ))
+}
+
+/// Signed POSTs to an AWS API: form-encoded for query APIs (SNS, SQS), JSON for JSON
+/// APIs (e.g. Secrets Manager).
+pub struct AwsClient {
+    http: reqwest::Client,
+    credentials: AwsCredentialsProvider,
+    region: String,
+    service: &'static str,
+}
+
+const FORM_CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
+const JSON_CONTENT_TYPE: &str = "application/x-amz-json-1.1";
+
+impl AwsClient {
+    pub fn new(
+        source: AwsCredentialsSource,
+        region: String,
+        service: &'static str,
+    ) -> Result<Self, String> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .map_err(|e| format!("http client: {e}"))?;
+        Ok(Self {
+            credentials: AwsCredentialsProvider::new(source, http.clone()),
+            http,
+            region,
+            service,
+        })
+    }
+
+    /// POST `params` to `url`, failing with the response body on an error status.
+    pub async fn post(&self, url: &str, params: &[(&str, &str)]) -> Result<(), String> {
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(params)
+            .finish();
+        self.send(url, FORM_CONTENT_TYPE, None, body)
+            .await
+            .map(drop)
+    }
+
+    /// Call the JSON API operation `target` (e.g. `secretsmanager.GetSecretValue`) at
+    /// `url`, returning the response body.
+    pub async fn call_json(
+        &self,
+        url: &str,
+        target: &str,
+        body: &serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        let response = self
+            .send(url, JSON_CONTENT_TYPE, Some(target), body.to_string())
+            .await?;
+        serde_json::from_str(&response)
+            .map_err(|e| format!("{} response is not JSON: {e}", self.service))
+    }
+
+    async fn send(
+        &self,
+        url: &str,
+        content_type: &str,
+        target: Option<&str>,
+        body: String,
+    ) -> Result<String, String> {
+        let parsed = reqwest::Url::parse(url).map_err(|e| format!("invalid url {url}: {e}"))?;
+        if parsed.host_str().is_none() {
+            return Err(format!("invalid url {url}: no host"));
+        }
+
+        let credentials = self.credentials.credentials().await?;
+        let mut headers = vec![("content-type", content_type)];
+        if let Some(target) = target {
+            headers.push(("x-amz-target", target));
+        }
+        let signed = signing_headers(
+            &credentials,
+            &self.region,
+            self.service,
+            SystemTime::now(),
+            &SignedRequest {
+                method: "POST",
+                url: parsed.as_str(),
+                headers: &headers,
+                body: body.as_bytes(),
+            },
+        )?;
+
+        let mut request = self.http.post(parsed);
+        for (name, value) in headers.into_iter().chain(
+            signed
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str())),
+        ) {
+            request = request.header(name, value);
+        }
+        let response = request
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| format!("{} request: {e}", self.service))?;
+
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        if status.is_success() {
+            return Ok(body);
+        }
+        Err(format!(
+            "{} responded {status}: {}",
+            self.service,
+            body.chars().take(512).collect::<String>()
+        ))
+    }
+
+    pub async fn has_credentials(&self) -> bool {
+        self.credentials.credentials().await.is_ok()
+    }
+}
+
+pub(crate) struct SignedRequest<'a> {
+    pub(crate) method: &'a str,
+    pub(crate) url: &'a str,
+    /// Headers sent with the request, all of which are signed.
+    pub(crate) headers: &'a [(&'a str, &'a str)],
+    pub(crate) body: &'a [u8],
+}
+
+/// The headers to add to `request` to sign it at `time`: `Authorization`, `X-Amz-Date`
+/// and, for temporary credentials, `X-Amz-Security-Token`.
+pub(crate) fn signing_headers(
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    time: SystemTime,
+    request: &SignedRequest<'_>,
+) -> Result<Vec<(String, String)>, String> {
+    let identity = aws_credential_types::Credentials::new(
+        &credentials.access_key_id,
+        &credentials.secret_access_key,
+        credentials.session_token.clone(),
+        None,
+        "oauth2-events",
+    )
+    .into();
+    let params = v4::SigningParams::builder()
+        .identity(&identity)
+        .region(region)
+        .name(service)
+        .time(time)
+        .settings(SigningSettings::default())
+        .build()
+        .map_err(|e| format!("{service} request signing: {e}"))?
+        .into();
+    let signable = SignableRequest::new(
+        request.method,
+        request.url,
+        request.headers.iter().copied(),
+        SignableBody::Bytes(request.body),
+    )
+    .map_err(|e| format!("{service} request signing: {e}"))?;
+    let (instructions, _) = sign(signable, &params)
+        .map_err(|e| format!("{service} request signing: {e}"))?
+        .into_parts();
+    Ok(instructions
+        .headers()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The worked example from the AWS Signature Version 4 documentation.
+    #[test]
+    fn signs_the_documented_request() {
+        let credentials = AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+        };
+        let time = DateTime::parse_from_rfc3339("2015-08-30T12:36:00Z").unwrap();
+        let headers = signing_headers(
+            &credentials,
+            "us-east-1",
+            "iam",
+            time.with_timezone(&Utc).into(),
+            &SignedRequest {
+                method: "GET",
+                url: "https://iam.amazonaws.com/?Action=ListUsers&Version=2010-05-08",
+                headers: &[(
+                    "content-type",
+                    "application/x-www-form-urlencoded; charset=utf-8",
+                )],
+                body: b"",
+            },
+        )
+        .unwrap();
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(header, _)| header.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(header("x-amz-date"), Some("20150830T123600Z"));
+        assert_eq!(
+            header("authorization"),
+            Some(
+                "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, \
+                 SignedHeaders=content-type;host;x-amz-date, \
+                 Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+            )
+        );
+        assert_eq!(header("x-amz-security-token"), None);
+    }
+}
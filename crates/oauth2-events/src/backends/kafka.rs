@@ -1,15 +1,125 @@
-use crate::{EventEnvelope, EventPlugin};
+use crate::{
+    DeadLetter, DeadLetterSink, EventEnvelope, EventPlugin, EventReplaySource, ReplayRange,
+    SchemaFormat,
+};
 use async_trait::async_trait;
 use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{BaseConsumer, Consumer};
+use rdkafka::message::{Header, Message, OwnedHeaders};
 use rdkafka::producer::{FutureProducer, FutureRecord};
-use std::time::Duration;
+use rdkafka::{Offset, TopicPartitionList};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Which event field becomes the message key, and so picks the partition.
+///
+/// Events without the field (e.g. a client credentials grant has no user) are keyed by
+/// their idempotency key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KafkaPartitionKey {
+    /// No ordering between related events; spreads load evenly.
+    #[default]
+    IdempotencyKey,
+    /// Keeps each user's events in order.
+    UserId,
+    /// Keeps each client's events in order.
+    ClientId,
+    EventType,
+}
+
+impl KafkaPartitionKey {
+    /// The message key for `envelope`.
+    pub fn key(&self, envelope: &EventEnvelope) -> String {
+        let event = &envelope.event;
+        let field = match self {
+            KafkaPartitionKey::IdempotencyKey => None,
+            KafkaPartitionKey::UserId => event.user_id.clone(),
+            KafkaPartitionKey::ClientId => event.client_id.clone(),
+            KafkaPartitionKey::EventType => Some(event.event_type.as_str().to_string()),
+        };
+        field
+            .filter(|key| !key.is_empty())
+            .unwrap_or_else(|| envelope.effective_idempotency_key())
+    }
+}
+
+/// Kafka headers carried with each event, so consumers can route and trace without
+/// decoding the payload.
+fn message_headers(envelope: &EventEnvelope) -> OwnedHeaders {
+    let event_type = envelope.event.event_type.as_str();
+    let idempotency_key = envelope.effective_idempotency_key();
+    let mut headers = OwnedHeaders::new()
+        .insert(Header {
+            key: "event_type",
+            value: Some(event_type),
+        })
+        .insert(Header {
+            key: "event_id",
+            value: Some(envelope.event.id.as_str()),
+        })
+        .insert(Header {
+            key: "idempotency_key",
+            value: Some(idempotency_key.as_str()),
+        });
+    if let Some(traceparent) = &envelope.traceparent {
+        headers = headers.insert(Header {
+            key: "traceparent",
+            value: Some(traceparent.as_str()),
+        });
+    }
+    if let Some(tracestate) = envelope.tracestate.as_ref().filter(|s| !s.is_empty()) {
+        headers = headers.insert(Header {
+            key: "tracestate",
+            value: Some(tracestate.as_str()),
+        });
+    }
+    headers
+}
+
+/// Register the envelope schema under `subject` (by convention `<topic>-value`) and
+/// return its id. Registering an identical schema again returns the existing id.
+pub async fn register_schema(
+    registry_url: &str,
+    subject: &str,
+    format: SchemaFormat,
+) -> Result<u32, String> {
+    #[derive(serde::Deserialize)]
+    struct Registered {
+        id: u32,
+    }
+
+    let url = format!(
+        "{}/subjects/{subject}/versions",
+        registry_url.trim_end_matches('/')
+    );
+    let body = serde_json::json!({
+        "schemaType": format.schema_type(),
+        "schema": format.schema().to_string(),
+    });
+    let registered: Registered = reqwest::Client::new()
+        .post(&url)
+        .header("Content-Type", "application/vnd.schemaregistry.v1+json")
+        .timeout(Duration::from_secs(5))
+        .json(&body)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("register schema: {e}"))?
+        .json()
+        .await
+        .map_err(|e| format!("register schema: {e}"))?;
+    Ok(registered.id)
+}
 
 /// Kafka event publisher.
 ///
-/// Publishes envelopes as JSON to a Kafka topic.
+/// Publishes envelopes to a Kafka topic, as JSON or in a schema registry format, with the
+/// event type, ids and trace context as headers.
 pub struct KafkaEventPublisher {
     producer: FutureProducer,
     topic: String,
+    partition_key: KafkaPartitionKey,
+    schema: Option<(SchemaFormat, u32)>,
 }
 
 impl KafkaEventPublisher {
@@ -33,24 +143,45 @@ impl KafkaEventPublisher {
         Ok(Self {
             producer,
             topic: topic.into(),
+            partition_key: KafkaPartitionKey::default(),
+            schema: None,
         })
     }
+
+    pub fn with_partition_key(mut self, partition_key: KafkaPartitionKey) -> Self {
+        self.partition_key = partition_key;
+        self
+    }
+
+    /// Encode envelopes in the schema registry wire format, tagged with `schema_id` (see
+    /// [`register_schema`]).
+    pub fn with_schema(mut self, format: SchemaFormat, schema_id: u32) -> Self {
+        self.schema = Some((format, schema_id));
+        self
+    }
 }
 
 #[async_trait]
 impl EventPlugin for KafkaEventPublisher {
     async fn emit(&self, envelope: &EventEnvelope) -> Result<(), String> {
-        let payload =
-            serde_json::to_vec(envelope).map_err(|e| format!("serialize envelope: {e}"))?;
-        let key = envelope.effective_idempotency_key();
+        let payload = match self.schema {
+            Some((format, schema_id)) => format.encode(schema_id, envelope)?,
+            None => serde_json::to_vec(envelope).map_err(|e| format!("serialize envelope: {e}"))?,
+        };
+        let key = self.partition_key.key(envelope);
 
         // We enqueue and then detach the delivery future to keep the plugin best-effort.
         let delivery = self
             .producer
-            .send_result(FutureRecord::to(&self.topic).payload(&payload).key(&key))
+            .send_result(
+                FutureRecord::to(&self.topic)
+                    .payload(&payload)
+                    .key(&key)
+                    .headers(message_headers(envelope)),
+            )
             .map_err(|(e, _msg)| format!("kafka send: {e}"))?;
 
-        actix_rt::spawn(async move {
+        tokio::spawn(async move {
             // A short wait so we at least surface immediate delivery failures.
             let _ = tokio::time::timeout(Duration::from_secs(2), delivery).await;
         });
@@ -67,3 +198,301 @@ impl EventPlugin for KafkaEventPublisher {
         true
     }
 }
+
+/// Dead-letter sink that publishes to a Kafka topic.
+///
+/// The message is the [`DeadLetter`] as JSON, keyed like the original event. Unlike the
+/// event publisher it waits for delivery, so a dead letter is never dropped silently.
+pub struct KafkaDeadLetterSink {
+    producer: FutureProducer,
+    topic: String,
+}
+
+impl KafkaDeadLetterSink {
+    pub fn new(brokers: &str, topic: impl Into<String>) -> Result<Self, String> {
+        let producer: FutureProducer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("message.timeout.ms", "5000")
+            .create()
+            .map_err(|e| format!("kafka producer create: {e}"))?;
+
+        Ok(Self {
+            producer,
+            topic: topic.into(),
+        })
+    }
+}
+
+#[async_trait]
+impl DeadLetterSink for KafkaDeadLetterSink {
+    async fn send(&self, letter: &DeadLetter) -> Result<(), String> {
+        let payload =
+            serde_json::to_vec(letter).map_err(|e| format!("serialize dead letter: {e}"))?;
+        let key = letter.envelope.effective_idempotency_key();
+
+        self.producer
+            .send(
+                FutureRecord::to(&self.topic).payload(&payload).key(&key),
+                Duration::from_secs(5),
+            )
+            .await
+            .map(|_| ())
+            .map_err(|(e, _msg)| format!("kafka send: {e}"))
+    }
+
+    fn name(&self) -> &str {
+        "kafka"
+    }
+}
+
+/// How long a replay waits on the brokers, in total for reading records.
+const REPLAY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Reads back the topic [`KafkaEventPublisher`] writes, for replay. Time ranges select
+/// records by their Kafka timestamp. Records in the Avro format can't be read back and are
+/// skipped.
+///
+/// Each read uses a fresh consumer group and commits nothing, so it doesn't disturb other
+/// consumers of the topic.
+pub struct KafkaReplaySource {
+    brokers: String,
+    topic: String,
+}
+
+impl KafkaReplaySource {
+    pub fn new(brokers: &str, topic: impl Into<String>) -> Self {
+        Self {
+            brokers: brokers.to_string(),
+            topic: topic.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl EventReplaySource for KafkaReplaySource {
+    async fn read(&self, range: &ReplayRange, limit: usize) -> Result<Vec<EventEnvelope>, String> {
+        let brokers = self.brokers.clone();
+        let topic = self.topic.clone();
+        let range = range.clone();
+        tokio::task::spawn_blocking(move || read_range(&brokers, &topic, &range, limit))
+            .await
+            .map_err(|e| format!("kafka replay: {e}"))?
+    }
+
+    fn name(&self) -> &str {
+        "kafka"
+    }
+}
+
+fn read_range(
+    brokers: &str,
+    topic: &str,
+    range: &ReplayRange,
+    limit: usize,
+) -> Result<Vec<EventEnvelope>, String> {
+    let consumer: BaseConsumer = ClientConfig::new()
+        .set("bootstrap.servers", brokers)
+        .set(
+            "group.id",
+            format!("oauth2-replay-{}", uuid::Uuid::new_v4()),
+        )
+        .set("enable.auto.commit", "false")
+        .create()
+        .map_err(|e| format!("kafka consumer create: {e}"))?;
+
+    // Last offset to read, by partition, and where to start.
+    let mut last_offsets = HashMap::new();
+    let mut assignment = TopicPartitionList::new();
+    for (partition, first, last) in partition_bounds(&consumer, topic, range)? {
+        if first <= last {
+            last_offsets.insert(partition, last);
+            assignment
+                .add_partition_offset(topic, partition, Offset::Offset(first))
+                .map_err(|e| format!("kafka assign: {e}"))?;
+        }
+    }
+    if last_offsets.is_empty() {
+        return Ok(Vec::new());
+    }
+    consumer
+        .assign(&assignment)
+        .map_err(|e| format!("kafka assign: {e}"))?;
+
+    let deadline = Instant::now() + REPLAY_TIMEOUT;
+    let mut envelopes = Vec::new();
+    while !last_offsets.is_empty() && envelopes.len() < limit && Instant::now() < deadline {
+        let message = match consumer.poll(Duration::from_millis(500)) {
+            None => continue,
+            Some(message) => message.map_err(|e| format!("kafka poll: {e}"))?,
+        };
+        let Some(&last) = last_offsets.get(&message.partition()) else {
+            continue;
+        };
+        if message.offset() >= last {
+            last_offsets.remove(&message.partition());
+        }
+        if message.offset() > last {
+            continue;
+        }
+        match message.payload().map(decode_payload) {
+            Some(Ok(envelope)) => envelopes.push(envelope),
+            Some(Err(e)) => tracing::warn!(
+                topic,
+                partition = message.partition(),
+                offset = message.offset(),
+                error = %e,
+                "Skipping undecodable record"
+            ),
+            None => {}
+        }
+    }
+    if !last_offsets.is_empty() && envelopes.len() < limit {
+        tracing::warn!(topic, "Kafka replay timed out before the end of the range");
+    }
+
+    // Partitions are read side by side; replay in the order the events happened.
+    envelopes.sort_by_key(|envelope| envelope.event.timestamp);
+    Ok(envelopes)
+}
+
+/// `(partition, first offset, last offset)` covering `range`.
+fn partition_bounds(
+    consumer: &BaseConsumer,
+    topic: &str,
+    range: &ReplayRange,
+) -> Result<Vec<(i32, i64, i64)>, String> {
+    let (from, to) = match range {
+        ReplayRange::Offsets {
+            partition,
+            from_offset,
+            to_offset,
+        } => return Ok(vec![(*partition, *from_offset, *to_offset)]),
+        ReplayRange::StreamIds { .. } => {
+            return Err("Redis Streams ids do not apply to Kafka".to_string())
+        }
+        ReplayRange::Time { from, to } => (from.timestamp_millis(), to.timestamp_millis()),
+    };
+
+    let metadata = consumer
+        .fetch_metadata(Some(topic), REPLAY_TIMEOUT)
+        .map_err(|e| format!("kafka metadata: {e}"))?;
+    let partitions: Vec<i32> = metadata
+        .topics()
+        .iter()
+        .filter(|t| t.name() == topic)
+        .flat_map(|t| t.partitions().iter().map(|p| p.id()))
+        .collect();
+
+    // The first offset at or after each time; `End` when there is none.
+    let offsets_at = |millis: i64| -> Result<HashMap<i32, Offset>, String> {
+        let mut query = TopicPartitionList::new();
+        for partition in &partitions {
+            query
+                .add_partition_offset(topic, *partition, Offset::Offset(millis))
+                .map_err(|e| format!("kafka offsets for times: {e}"))?;
+        }
+        let found = consumer
+            .offsets_for_times(query, REPLAY_TIMEOUT)
+            .map_err(|e| format!("kafka offsets for times: {e}"))?;
+        Ok(found
+            .elements()
+            .iter()
+            .map(|e| (e.partition(), e.offset()))
+            .collect())
+    };
+    let starts = offsets_at(from)?;
+    let ends = offsets_at(to.saturating_add(1))?;
+
+    let mut bounds = Vec::new();
+    for partition in partitions {
+        let Some(Offset::Offset(first)) = starts.get(&partition) else {
+            continue;
+        };
+        let last = match ends.get(&partition) {
+            Some(Offset::Offset(next)) => next - 1,
+            _ => {
+                let (_, high) = consumer
+                    .fetch_watermarks(topic, partition, REPLAY_TIMEOUT)
+                    .map_err(|e| format!("kafka watermarks: {e}"))?;
+                high - 1
+            }
+        };
+        bounds.push((partition, *first, last));
+    }
+    Ok(bounds)
+}
+
+/// The envelope in a record value, as plain JSON or in the schema registry JSON format.
+fn decode_payload(payload: &[u8]) -> Result<EventEnvelope, String> {
+    let json = match payload {
+        [0, _, _, _, _, rest @ ..] => rest,
+        _ => payload,
+    };
+    serde_json::from_slice(json).map_err(|e| format!("decode envelope: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AuthEvent, EventSeverity, EventType};
+    use rdkafka::message::Headers;
+
+    #[test]
+    fn partition_key_falls_back_to_the_idempotency_key() {
+        let event = AuthEvent::new(
+            EventType::TokenCreated,
+            EventSeverity::Info,
+            None,
+            Some("app".to_string()),
+        );
+        let envelope = EventEnvelope::from_current_span(event, "test");
+
+        assert_eq!(KafkaPartitionKey::ClientId.key(&envelope), "app");
+        assert_eq!(KafkaPartitionKey::EventType.key(&envelope), "token_created");
+        assert_eq!(
+            KafkaPartitionKey::UserId.key(&envelope),
+            envelope.effective_idempotency_key()
+        );
+    }
+
+    #[test]
+    fn headers_carry_the_event_type_and_trace_context() {
+        let event = AuthEvent::new(EventType::TokenRevoked, EventSeverity::Info, None, None);
+        let mut envelope = EventEnvelope::from_current_span(event, "test");
+        envelope.idempotency_key = Some("k1".to_string());
+        envelope.traceparent =
+            Some("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".to_string());
+        envelope.tracestate = None;
+
+        let headers = message_headers(&envelope);
+        let headers: Vec<(&str, &[u8])> = headers
+            .iter()
+            .map(|h| (h.key, h.value.unwrap_or_default()))
+            .collect();
+        assert_eq!(
+            headers,
+            [
+                ("event_type", b"token_revoked".as_slice()),
+                ("event_id", envelope.event.id.as_bytes()),
+                ("idempotency_key", b"k1".as_slice()),
+                (
+                    "traceparent",
+                    b"00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".as_slice()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn replayed_records_decode_with_or_without_a_schema_id() {
+        let event = AuthEvent::new(EventType::TokenCreated, EventSeverity::Info, None, None);
+        let envelope = EventEnvelope::from_current_span(event, "test");
+
+        let plain = serde_json::to_vec(&envelope).unwrap();
+        assert_eq!(decode_payload(&plain).unwrap().event.id, envelope.event.id);
+        let tagged = SchemaFormat::JsonSchema.encode(3, &envelope).unwrap();
+        assert_eq!(decode_payload(&tagged).unwrap().event.id, envelope.event.id);
+        let avro = SchemaFormat::Avro.encode(3, &envelope).unwrap();
+        assert!(decode_payload(&avro).is_err());
+    }
+}
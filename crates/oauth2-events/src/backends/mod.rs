@@ -4,21 +4,47 @@
 //! - `events-redis`
 //! - `events-kafka`
 //! - `events-rabbit`
+//! - `events-sns` (Amazon SNS and SQS)
+//! - `events-pubsub` (Google Cloud Pub/Sub)
 
 #[cfg(feature = "events-redis")]
 pub mod redis_streams;
 
+#[cfg(feature = "events-redis")]
+pub mod redis_consumer;
+
 #[cfg(feature = "events-kafka")]
 pub mod kafka;
 
 #[cfg(feature = "events-rabbit")]
 pub mod rabbit;
 
+#[cfg(feature = "events-sns")]
+mod aws;
+
+#[cfg(feature = "events-sns")]
+pub mod sns;
+
+#[cfg(feature = "events-pubsub")]
+pub mod pubsub;
+
 #[cfg(feature = "events-redis")]
 pub use redis_streams::*;
 
+#[cfg(feature = "events-redis")]
+pub use redis_consumer::*;
+
 #[cfg(feature = "events-kafka")]
 pub use kafka::*;
 
 #[cfg(feature = "events-rabbit")]
 pub use rabbit::*;
+
+#[cfg(feature = "events-sns")]
+pub use aws::{AwsClient, AwsCredentials, AwsCredentialsSource};
+
+#[cfg(feature = "events-sns")]
+pub use sns::*;
+
+#[cfg(feature = "events-pubsub")]
+pub use pubsub::*;
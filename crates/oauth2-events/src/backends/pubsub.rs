@@ -0,0 +1,283 @@
+use crate::{EventEnvelope, EventPlugin};
+use async_trait::async_trait;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Where the publisher gets its access tokens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GcpCredentialsSource {
+    /// The service account key in `GOOGLE_APPLICATION_CREDENTIALS` when set, else the
+    /// metadata server.
+    Default,
+    /// The GCE/GKE metadata server (including Workload Identity).
+    Metadata,
+    /// A service account key file.
+    ServiceAccountFile(PathBuf),
+    /// No credentials, for the Pub/Sub emulator.
+    None,
+}
+
+const METADATA_TOKEN_URL: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+const PUBSUB_SCOPE: &str = "https://www.googleapis.com/auth/pubsub";
+const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+
+/// Tokens are refreshed this long before they expire.
+const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize)]
+struct ServiceAccountKey {
+    client_email: String,
+    private_key: String,
+    #[serde(default)]
+    private_key_id: Option<String>,
+    #[serde(default)]
+    token_uri: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AssertionClaims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Serialize)]
+struct PublishRequest<'a> {
+    messages: [PubsubMessage<'a>; 1],
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PubsubMessage<'a> {
+    data: String,
+    attributes: BTreeMap<&'a str, String>,
+}
+
+/// Google Cloud Pub/Sub event publisher.
+///
+/// Publishes envelopes as JSON to a topic, with the event type, ids and trace context as
+/// message attributes.
+pub struct PubSubEventPublisher {
+    http: reqwest::Client,
+    publish_url: String,
+    credentials: GcpCredentialsSource,
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+impl PubSubEventPublisher {
+    /// `endpoint` overrides `https://pubsub.googleapis.com`; it defaults to
+    /// `PUBSUB_EMULATOR_HOST` when that is set.
+    pub fn new(
+        project_id: &str,
+        topic: &str,
+        endpoint: Option<String>,
+        credentials: GcpCredentialsSource,
+    ) -> Result<Self, String> {
+        let endpoint = endpoint
+            .or_else(|| {
+                std::env::var("PUBSUB_EMULATOR_HOST")
+                    .ok()
+                    .map(|host| format!("http://{host}"))
+            })
+            .unwrap_or_else(|| "https://pubsub.googleapis.com".to_string());
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .map_err(|e| format!("http client: {e}"))?;
+
+        Ok(Self {
+            http,
+            publish_url: format!(
+                "{}/v1/projects/{project_id}/topics/{topic}:publish",
+                endpoint.trim_end_matches('/')
+            ),
+            credentials,
+            token: Mutex::new(None),
+        })
+    }
+
+    /// A current access token, or `None` without credentials.
+    async fn access_token(&self) -> Result<Option<String>, String> {
+        let source = match &self.credentials {
+            GcpCredentialsSource::None => return Ok(None),
+            GcpCredentialsSource::Default => {
+                match std::env::var_os("GOOGLE_APPLICATION_CREDENTIALS") {
+                    Some(path) => GcpCredentialsSource::ServiceAccountFile(path.into()),
+                    None => GcpCredentialsSource::Metadata,
+                }
+            }
+            source => source.clone(),
+        };
+
+        let mut token = self.token.lock().await;
+        if let Some((access_token, expires_at)) = token.as_ref() {
+            if Instant::now() + REFRESH_MARGIN < *expires_at {
+                return Ok(Some(access_token.clone()));
+            }
+        }
+        let fetched = match source {
+            GcpCredentialsSource::ServiceAccountFile(path) => {
+                self.service_account_token(&path).await?
+            }
+            _ => self.metadata_token().await?,
+        };
+        let access_token = fetched.access_token.clone();
+        *token = Some((
+            fetched.access_token,
+            Instant::now() + Duration::from_secs(fetched.expires_in),
+        ));
+        Ok(Some(access_token))
+    }
+
+    async fn metadata_token(&self) -> Result<TokenResponse, String> {
+        self.http
+            .get(METADATA_TOKEN_URL)
+            .header("Metadata-Flavor", "Google")
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("metadata token: {e}"))?
+            .json()
+            .await
+            .map_err(|e| format!("metadata token: {e}"))
+    }
+
+    /// Exchange a self-signed assertion for an access token (RFC 7523).
+    async fn service_account_token(&self, path: &std::path::Path) -> Result<TokenResponse, String> {
+        let key = tokio::fs::read(path)
+            .await
+            .map_err(|e| format!("read {}: {e}", path.display()))?;
+        let key: ServiceAccountKey = serde_json::from_slice(&key)
+            .map_err(|e| format!("{}: not a service account key: {e}", path.display()))?;
+        let token_uri = key.token_uri.as_deref().unwrap_or(DEFAULT_TOKEN_URI);
+
+        let now = chrono::Utc::now().timestamp();
+        let claims = AssertionClaims {
+            iss: &key.client_email,
+            scope: PUBSUB_SCOPE,
+            aud: token_uri,
+            iat: now,
+            exp: now + 3600,
+        };
+        let mut header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256);
+        header.kid = key.private_key_id.clone();
+        let signing_key = jsonwebtoken::EncodingKey::from_rsa_pem(key.private_key.as_bytes())
+            .map_err(|e| format!("service account private key: {e}"))?;
+        let assertion = jsonwebtoken::encode(&header, &claims, &signing_key)
+            .map_err(|e| format!("sign assertion: {e}"))?;
+
+        self.http
+            .post(token_uri)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ])
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("service account token: {e}"))?
+            .json()
+            .await
+            .map_err(|e| format!("service account token: {e}"))
+    }
+}
+
+/// The message for `envelope`: the envelope as JSON, with attributes subscribers can
+/// filter and trace on without decoding it.
+fn pubsub_message(envelope: &EventEnvelope) -> Result<PubsubMessage<'static>, String> {
+    let payload = serde_json::to_vec(envelope).map_err(|e| format!("serialize envelope: {e}"))?;
+
+    let mut attributes = BTreeMap::from([
+        ("event_type", envelope.event.event_type.as_str().to_string()),
+        ("event_id", envelope.event.id.clone()),
+        ("idempotency_key", envelope.effective_idempotency_key()),
+    ]);
+    if let Some(traceparent) = &envelope.traceparent {
+        attributes.insert("traceparent", traceparent.clone());
+    }
+    if let Some(tracestate) = envelope.tracestate.as_ref().filter(|s| !s.is_empty()) {
+        attributes.insert("tracestate", tracestate.clone());
+    }
+
+    Ok(PubsubMessage {
+        data: base64::engine::general_purpose::STANDARD.encode(payload),
+        attributes,
+    })
+}
+
+#[async_trait]
+impl EventPlugin for PubSubEventPublisher {
+    async fn emit(&self, envelope: &EventEnvelope) -> Result<(), String> {
+        let body = PublishRequest {
+            messages: [pubsub_message(envelope)?],
+        };
+
+        let mut request = self.http.post(&self.publish_url).json(&body);
+        if let Some(token) = self.access_token().await? {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("pubsub publish: {e}"))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let body = response.text().await.unwrap_or_default();
+        Err(format!(
+            "pubsub publish: responded {status}: {}",
+            body.chars().take(512).collect::<String>()
+        ))
+    }
+
+    fn name(&self) -> &str {
+        "pubsub"
+    }
+
+    async fn health_check(&self) -> bool {
+        self.access_token().await.is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AuthEvent, EventSeverity, EventType};
+
+    #[test]
+    fn message_carries_the_envelope_and_attributes() {
+        let event = AuthEvent::new(EventType::TokenRevoked, EventSeverity::Info, None, None);
+        let mut envelope = EventEnvelope::from_current_span(event, "test");
+        envelope.traceparent =
+            Some("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".to_string());
+
+        let message = pubsub_message(&envelope).unwrap();
+        assert_eq!(message.attributes["event_type"], "token_revoked");
+        assert_eq!(message.attributes["event_id"], envelope.event.id);
+        assert_eq!(
+            message.attributes["traceparent"],
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+        );
+
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(&message.data)
+            .unwrap();
+        let decoded: EventEnvelope = serde_json::from_slice(&data).unwrap();
+        assert_eq!(decoded.event.id, envelope.event.id);
+    }
+}
@@ -0,0 +1,350 @@
+use crate::{DeadLetter, DynDeadLetterSink, EventEnvelope};
+use async_trait::async_trait;
+use redis::aio::ConnectionManager;
+use redis::streams::{
+    StreamClaimReply, StreamId, StreamPendingCountReply, StreamReadOptions, StreamReadReply,
+};
+use redis::AsyncCommands;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// Handles events read by a [`RedisStreamsEventConsumer`].
+///
+/// An `Ok` acknowledges the entry; an `Err` leaves it pending, so it is redelivered
+/// once it has been idle for [`RedisStreamsConsumerOptions::claim_idle`]. Handlers must
+/// therefore be idempotent; [`EventEnvelope::effective_idempotency_key`] identifies
+/// redeliveries.
+///
+/// Implemented for `async` closures taking the envelope.
+#[async_trait]
+pub trait StreamEventHandler: Send + Sync {
+    async fn handle(&self, envelope: EventEnvelope) -> Result<(), String>;
+}
+
+#[async_trait]
+impl<F, Fut> StreamEventHandler for F
+where
+    F: Fn(EventEnvelope) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<(), String>> + Send,
+{
+    async fn handle(&self, envelope: EventEnvelope) -> Result<(), String> {
+        self(envelope).await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RedisStreamsConsumerOptions {
+    /// Entries read per `XREADGROUP`.
+    pub batch_size: usize,
+    /// How long `XREADGROUP` waits for new entries.
+    pub block: Duration,
+    /// Pending entries idle this long are claimed from their consumer (which is presumed
+    /// dead, or failed to handle them) and handled again.
+    pub claim_idle: Duration,
+    /// Entries delivered this many times are dead-lettered (or dropped) and acknowledged
+    /// instead of handled again.
+    pub max_deliveries: usize,
+    /// Whether a newly created group starts at the beginning of the stream rather than
+    /// only seeing new entries.
+    pub from_beginning: bool,
+}
+
+impl Default for RedisStreamsConsumerOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 16,
+            block: Duration::from_secs(5),
+            claim_idle: Duration::from_secs(60),
+            max_deliveries: 5,
+            from_beginning: false,
+        }
+    }
+}
+
+/// Reads events published by [`super::RedisStreamsEventPublisher`] as a member of a
+/// consumer group.
+///
+/// Each entry is handed to one consumer in the group and stays pending until its handler
+/// succeeds. Entries left pending by a crashed or failing consumer are claimed by the
+/// others, so every event is handled at least once.
+pub struct RedisStreamsEventConsumer {
+    stream: String,
+    group: String,
+    consumer: String,
+    options: RedisStreamsConsumerOptions,
+    conn: ConnectionManager,
+    dead_letter: Option<DynDeadLetterSink>,
+}
+
+impl RedisStreamsEventConsumer {
+    /// Connect, creating the stream and group if they don't exist. `consumer` must be
+    /// unique within the group (e.g. the host name).
+    pub async fn connect(
+        url: &str,
+        stream: impl Into<String>,
+        group: impl Into<String>,
+        consumer: impl Into<String>,
+        options: RedisStreamsConsumerOptions,
+    ) -> Result<Self, String> {
+        let client = redis::Client::open(url).map_err(|e| format!("redis client: {e}"))?;
+        let mut conn = client
+            .get_connection_manager()
+            .await
+            .map_err(|e| format!("redis connect: {e}"))?;
+
+        let stream = stream.into();
+        let group = group.into();
+        let start = if options.from_beginning { "0" } else { "$" };
+        let created: redis::RedisResult<()> =
+            conn.xgroup_create_mkstream(&stream, &group, start).await;
+        match created {
+            Ok(()) => {}
+            Err(e) if e.code() == Some("BUSYGROUP") => {}
+            Err(e) => return Err(format!("redis XGROUP CREATE: {e}")),
+        }
+
+        Ok(Self {
+            stream,
+            group,
+            consumer: consumer.into(),
+            options,
+            conn,
+            dead_letter: None,
+        })
+    }
+
+    /// Send entries that exceed `max_deliveries` here rather than dropping them.
+    pub fn with_dead_letter(mut self, sink: DynDeadLetterSink) -> Self {
+        self.dead_letter = Some(sink);
+        self
+    }
+
+    /// Read and handle one batch of new entries, waiting up to `block` for them. Returns
+    /// the number of entries read.
+    pub async fn poll(&self, handler: &dyn StreamEventHandler) -> Result<usize, String> {
+        let options = StreamReadOptions::default()
+            .group(&self.group, &self.consumer)
+            .count(self.options.batch_size)
+            .block(self.options.block.as_millis() as usize);
+        let mut conn = self.conn.clone();
+        let reply: Option<StreamReadReply> = conn
+            .xread_options(&[&self.stream], &[">"], &options)
+            .await
+            .map_err(|e| format!("redis XREADGROUP: {e}"))?;
+
+        let entries: Vec<StreamId> = reply
+            .into_iter()
+            .flat_map(|reply| reply.keys)
+            .flat_map(|key| key.ids)
+            .collect();
+        let read = entries.len();
+        for entry in entries {
+            self.process(entry, 1, handler).await?;
+        }
+        Ok(read)
+    }
+
+    /// Claim entries pending for longer than `claim_idle`, from any consumer in the group
+    /// including this one, and handle them again. Returns the number of entries claimed.
+    pub async fn recover_pending(&self, handler: &dyn StreamEventHandler) -> Result<usize, String> {
+        let mut conn = self.conn.clone();
+        let min_idle = self.options.claim_idle.as_millis() as usize;
+        let mut start = "-".to_string();
+        let mut claimed_total = 0;
+
+        loop {
+            let pending: StreamPendingCountReply = conn
+                .xpending_count(
+                    &self.stream,
+                    &self.group,
+                    &start,
+                    "+",
+                    self.options.batch_size,
+                )
+                .await
+                .map_err(|e| format!("redis XPENDING: {e}"))?;
+            let Some(last) = pending.ids.last() else {
+                break;
+            };
+            // An exclusive start, so the next page begins after this one.
+            start = format!("({}", last.id);
+            let more = pending.ids.len() == self.options.batch_size;
+
+            let idle: Vec<_> = pending
+                .ids
+                .into_iter()
+                .filter(|p| p.last_delivered_ms >= min_idle)
+                .collect();
+            if !idle.is_empty() {
+                let ids: Vec<&str> = idle.iter().map(|p| p.id.as_str()).collect();
+                // XCLAIM re-checks the idle time, so an entry another consumer claimed
+                // first is skipped rather than handled twice.
+                let claimed: StreamClaimReply = conn
+                    .xclaim(&self.stream, &self.group, &self.consumer, min_idle, &ids)
+                    .await
+                    .map_err(|e| format!("redis XCLAIM: {e}"))?;
+
+                claimed_total += claimed.ids.len();
+                for entry in claimed.ids {
+                    let deliveries = idle
+                        .iter()
+                        .find(|p| p.id == entry.id)
+                        .map_or(1, |p| p.times_delivered + 1);
+                    self.process(entry, deliveries, handler).await?;
+                }
+            }
+
+            if !more {
+                break;
+            }
+        }
+        Ok(claimed_total)
+    }
+
+    /// Recover pending entries, then poll for new ones, until the task is dropped. Redis
+    /// errors are logged and retried after a pause.
+    pub async fn run(&self, handler: &dyn StreamEventHandler) {
+        let mut last_recovery: Option<Instant> = None;
+        loop {
+            let result = async {
+                if last_recovery.is_none_or(|at| at.elapsed() >= self.options.claim_idle) {
+                    last_recovery = Some(Instant::now());
+                    self.recover_pending(handler).await?;
+                }
+                self.poll(handler).await
+            }
+            .await;
+
+            if let Err(e) = result {
+                tracing::warn!(
+                    stream = %self.stream,
+                    group = %self.group,
+                    error = %e,
+                    "Redis Streams consumer failed; retrying"
+                );
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+    }
+
+    /// Handle `entry` on its `deliveries`th delivery, acknowledging it unless the handler
+    /// fails and it may be redelivered.
+    async fn process(
+        &self,
+        entry: StreamId,
+        deliveries: usize,
+        handler: &dyn StreamEventHandler,
+    ) -> Result<(), String> {
+        let envelope = match decode_entry(&entry) {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                tracing::warn!(stream = %self.stream, id = %entry.id, error = %e, "Dropping undecodable stream entry");
+                return self.ack(&entry.id).await;
+            }
+        };
+
+        if deliveries > self.options.max_deliveries {
+            self.give_up(
+                &entry.id,
+                envelope,
+                deliveries - 1,
+                "delivery limit reached",
+            )
+            .await?;
+            return self.ack(&entry.id).await;
+        }
+
+        match handler.handle(envelope.clone()).await {
+            Ok(()) => self.ack(&entry.id).await,
+            Err(e) if deliveries >= self.options.max_deliveries => {
+                self.give_up(&entry.id, envelope, deliveries, &e).await?;
+                self.ack(&entry.id).await
+            }
+            Err(e) => {
+                tracing::debug!(stream = %self.stream, id = %entry.id, error = %e, "Stream event handler failed; leaving pending");
+                Ok(())
+            }
+        }
+    }
+
+    async fn give_up(
+        &self,
+        id: &str,
+        envelope: EventEnvelope,
+        attempts: usize,
+        error: &str,
+    ) -> Result<(), String> {
+        let Some(sink) = &self.dead_letter else {
+            tracing::warn!(stream = %self.stream, id = %id, error = %error, "Dropping stream event after {attempts} deliveries");
+            return Ok(());
+        };
+        let plugin = format!("redis_streams:{}", self.group);
+        let letter = DeadLetter::new(plugin, error, attempts as u32, envelope);
+        sink.send(&letter).await
+    }
+
+    async fn ack(&self, id: &str) -> Result<(), String> {
+        let mut conn = self.conn.clone();
+        let _acked: usize = conn
+            .xack(&self.stream, &self.group, &[id])
+            .await
+            .map_err(|e| format!("redis XACK: {e}"))?;
+        Ok(())
+    }
+}
+
+/// The envelope in an entry's `payload` field.
+pub(super) fn decode_entry(entry: &StreamId) -> Result<EventEnvelope, String> {
+    let payload: String = entry
+        .get("payload")
+        .ok_or_else(|| "no payload field".to_string())?;
+    serde_json::from_str(&payload).map_err(|e| format!("decode envelope: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AuthEvent, EventSeverity, EventType};
+    use std::collections::HashMap;
+
+    #[test]
+    fn entries_decode_from_the_payload_field() {
+        let event = AuthEvent::new(EventType::TokenCreated, EventSeverity::Info, None, None);
+        let envelope = EventEnvelope::from_current_span(event, "test");
+        let payload = serde_json::to_string(&envelope).unwrap();
+
+        let entry = StreamId {
+            id: "1-0".to_string(),
+            map: HashMap::from([
+                (
+                    "event_type".to_string(),
+                    redis::Value::Data(b"token_created".to_vec()),
+                ),
+                (
+                    "payload".to_string(),
+                    redis::Value::Data(payload.into_bytes()),
+                ),
+            ]),
+        };
+        assert_eq!(decode_entry(&entry).unwrap().event.id, envelope.event.id);
+
+        let entry = StreamId {
+            id: "2-0".to_string(),
+            map: HashMap::new(),
+        };
+        assert!(decode_entry(&entry).is_err());
+    }
+
+    #[tokio::test]
+    async fn closures_are_handlers() {
+        let handler: &dyn StreamEventHandler = &|envelope: EventEnvelope| async move {
+            match envelope.event.event_type {
+                EventType::TokenRevoked => Err("rejected".to_string()),
+                _ => Ok(()),
+            }
+        };
+        let event = AuthEvent::new(EventType::TokenRevoked, EventSeverity::Info, None, None);
+        let envelope = EventEnvelope::from_current_span(event, "test");
+        assert!(handler.handle(envelope).await.is_err());
+    }
+}
@@ -0,0 +1,265 @@
+use super::aws::{AwsClient, AwsCredentialsSource};
+use crate::{EventEnvelope, EventPlugin};
+use async_trait::async_trait;
+
+/// Message attributes carried with each event, so subscribers can filter and trace
+/// without decoding the body.
+fn message_attributes(envelope: &EventEnvelope) -> Vec<(&'static str, String)> {
+    let mut attributes = vec![
+        ("event_type", envelope.event.event_type.as_str().to_string()),
+        ("event_id", envelope.event.id.clone()),
+        ("idempotency_key", envelope.effective_idempotency_key()),
+    ];
+    if let Some(traceparent) = &envelope.traceparent {
+        attributes.push(("traceparent", traceparent.clone()));
+    }
+    if let Some(tracestate) = envelope.tracestate.as_ref().filter(|s| !s.is_empty()) {
+        attributes.push(("tracestate", tracestate.clone()));
+    }
+    attributes
+}
+
+/// Form parameters for `attributes`, named `{prefix}.N.Name` and so on.
+fn attribute_params(prefix: &str, attributes: &[(&str, String)]) -> Vec<(String, String)> {
+    attributes
+        .iter()
+        .enumerate()
+        .flat_map(|(i, (name, value))| {
+            let n = i + 1;
+            [
+                (format!("{prefix}.{n}.Name"), name.to_string()),
+                (format!("{prefix}.{n}.Value.DataType"), "String".to_string()),
+                (format!("{prefix}.{n}.Value.StringValue"), value.clone()),
+            ]
+        })
+        .collect()
+}
+
+/// Group and deduplication ids for FIFO topics and queues: events are ordered per event
+/// type and deduplicated by idempotency key (falling back to the event id, which always
+/// fits the 128 character limit).
+fn fifo_params(envelope: &EventEnvelope) -> [(String, String); 2] {
+    let key = envelope.effective_idempotency_key();
+    let deduplication_id = if key.len() <= 128 && key.is_ascii() {
+        key
+    } else {
+        envelope.event.id.clone()
+    };
+    [
+        (
+            "MessageGroupId".to_string(),
+            envelope.event.event_type.as_str().to_string(),
+        ),
+        ("MessageDeduplicationId".to_string(), deduplication_id),
+    ]
+}
+
+/// Region from an ARN (`arn:aws:sns:<region>:<account>:<topic>`).
+fn region_from_arn(arn: &str) -> Option<String> {
+    arn.split(':')
+        .nth(3)
+        .filter(|r| !r.is_empty())
+        .map(str::to_string)
+}
+
+/// Region from an SQS queue URL (`https://sqs.<region>.amazonaws.com/<account>/<queue>`).
+fn region_from_queue_url(url: &str) -> Option<String> {
+    let host = reqwest::Url::parse(url).ok()?.host_str()?.to_string();
+    let mut labels = host.split('.');
+    match (labels.next(), labels.next()) {
+        (Some("sqs"), Some(region)) => Some(region.to_string()),
+        (Some(region), Some("queue")) => Some(region.to_string()),
+        _ => None,
+    }
+}
+
+/// Amazon SNS event publisher.
+///
+/// Publishes envelopes as JSON to a topic, with the event type, ids and trace context as
+/// message attributes.
+pub struct SnsEventPublisher {
+    client: AwsClient,
+    endpoint: String,
+    topic_arn: String,
+    fifo: bool,
+}
+
+impl SnsEventPublisher {
+    /// `region` defaults to the topic's; `endpoint` overrides the regional endpoint (e.g.
+    /// for LocalStack).
+    pub fn new(
+        topic_arn: impl Into<String>,
+        region: Option<String>,
+        endpoint: Option<String>,
+        credentials: AwsCredentialsSource,
+    ) -> Result<Self, String> {
+        let topic_arn = topic_arn.into();
+        let region = region
+            .or_else(|| region_from_arn(&topic_arn))
+            .ok_or_else(|| format!("no region in topic ARN {topic_arn}"))?;
+        let endpoint = endpoint.unwrap_or_else(|| format!("https://sns.{region}.amazonaws.com/"));
+        Ok(Self {
+            client: AwsClient::new(credentials, region, "sns")?,
+            endpoint,
+            fifo: topic_arn.ends_with(".fifo"),
+            topic_arn,
+        })
+    }
+}
+
+#[async_trait]
+impl EventPlugin for SnsEventPublisher {
+    async fn emit(&self, envelope: &EventEnvelope) -> Result<(), String> {
+        let message =
+            serde_json::to_string(envelope).map_err(|e| format!("serialize envelope: {e}"))?;
+
+        let mut params = vec![
+            ("Action".to_string(), "Publish".to_string()),
+            ("Version".to_string(), "2010-03-31".to_string()),
+            ("TopicArn".to_string(), self.topic_arn.clone()),
+            ("Message".to_string(), message),
+        ];
+        params.extend(attribute_params(
+            "MessageAttributes.entry",
+            &message_attributes(envelope),
+        ));
+        if self.fifo {
+            params.extend(fifo_params(envelope));
+        }
+
+        let params: Vec<(&str, &str)> = params
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        self.client
+            .post(&self.endpoint, &params)
+            .await
+            .map_err(|e| format!("sns publish: {e}"))
+    }
+
+    fn name(&self) -> &str {
+        "sns"
+    }
+
+    async fn health_check(&self) -> bool {
+        self.client.has_credentials().await
+    }
+}
+
+/// Amazon SQS event publisher.
+///
+/// Sends envelopes as JSON straight to a queue, with the same message attributes as
+/// [`SnsEventPublisher`].
+pub struct SqsEventPublisher {
+    client: AwsClient,
+    queue_url: String,
+    fifo: bool,
+}
+
+impl SqsEventPublisher {
+    /// `region` defaults to the one in the queue URL.
+    pub fn new(
+        queue_url: impl Into<String>,
+        region: Option<String>,
+        credentials: AwsCredentialsSource,
+    ) -> Result<Self, String> {
+        let queue_url = queue_url.into();
+        let region = region
+            .or_else(|| region_from_queue_url(&queue_url))
+            .ok_or_else(|| format!("no region in queue URL {queue_url}; set it explicitly"))?;
+        Ok(Self {
+            client: AwsClient::new(credentials, region, "sqs")?,
+            fifo: queue_url.ends_with(".fifo"),
+            queue_url,
+        })
+    }
+}
+
+#[async_trait]
+impl EventPlugin for SqsEventPublisher {
+    async fn emit(&self, envelope: &EventEnvelope) -> Result<(), String> {
+        let body =
+            serde_json::to_string(envelope).map_err(|e| format!("serialize envelope: {e}"))?;
+
+        let mut params = vec![
+            ("Action".to_string(), "SendMessage".to_string()),
+            ("Version".to_string(), "2012-11-05".to_string()),
+            ("MessageBody".to_string(), body),
+        ];
+        params.extend(attribute_params(
+            "MessageAttribute",
+            &message_attributes(envelope),
+        ));
+        if self.fifo {
+            params.extend(fifo_params(envelope));
+        }
+
+        let params: Vec<(&str, &str)> = params
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        self.client
+            .post(&self.queue_url, &params)
+            .await
+            .map_err(|e| format!("sqs send: {e}"))
+    }
+
+    fn name(&self) -> &str {
+        "sqs"
+    }
+
+    async fn health_check(&self) -> bool {
+        self.client.has_credentials().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AuthEvent, EventSeverity, EventType};
+
+    #[test]
+    fn regions_come_from_the_target() {
+        assert_eq!(
+            region_from_arn("arn:aws:sns:eu-west-1:123456789012:auth-events").as_deref(),
+            Some("eu-west-1")
+        );
+        assert_eq!(
+            region_from_queue_url("https://sqs.us-east-2.amazonaws.com/123456789012/events")
+                .as_deref(),
+            Some("us-east-2")
+        );
+        assert_eq!(
+            region_from_queue_url("http://localhost:4566/000/events"),
+            None
+        );
+    }
+
+    #[test]
+    fn attributes_carry_the_event_type_and_trace_context() {
+        let event = AuthEvent::new(EventType::TokenCreated, EventSeverity::Info, None, None);
+        let mut envelope = EventEnvelope::from_current_span(event, "test");
+        envelope.traceparent =
+            Some("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".to_string());
+        envelope.tracestate = None;
+
+        let params = attribute_params("MessageAttributes.entry", &message_attributes(&envelope));
+        let value = |name: &str| {
+            params
+                .iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(value("MessageAttributes.entry.1.Name"), Some("event_type"));
+        assert_eq!(
+            value("MessageAttributes.entry.1.Value.StringValue"),
+            Some("token_created")
+        );
+        assert_eq!(value("MessageAttributes.entry.4.Name"), Some("traceparent"));
+        assert_eq!(
+            value("MessageAttributes.entry.4.Value.DataType"),
+            Some("String")
+        );
+        assert_eq!(value("MessageAttributes.entry.5.Name"), None);
+    }
+}
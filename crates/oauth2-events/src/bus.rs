@@ -1,6 +1,8 @@
-use crate::EventEnvelope;
+use crate::{DynSigningKeyProvider, EventEnvelope};
 use async_trait::async_trait;
+use oauth2_core::Redactor;
 use std::sync::Arc;
+use std::time::Instant;
 
 #[derive(Debug, Clone)]
 pub enum EventBusError {
@@ -40,27 +42,99 @@ pub type DynEventBus = Arc<dyn EventBus>;
 #[derive(Clone)]
 pub struct EventBusHandle {
     inner: DynEventBus,
+    signing_keys: Option<DynSigningKeyProvider>,
+    outbox: bool,
 }
 
 impl EventBusHandle {
     pub fn new(inner: DynEventBus) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            signing_keys: None,
+            outbox: false,
+        }
+    }
+
+    /// Mark the bus as an outbox that a relay drains, rather than the backends themselves.
+    /// Producers that change storage in a transaction then enqueue their events in that
+    /// transaction (see [`is_outbox`](Self::is_outbox)), so an event is published if and
+    /// only if its change is committed.
+    pub fn as_outbox(mut self) -> Self {
+        self.outbox = true;
+        self
+    }
+
+    pub fn is_outbox(&self) -> bool {
+        self.outbox
+    }
+
+    /// Sign every published envelope with the provider's current key, so consumers can
+    /// tell events produced by this server from ones forged by other producers.
+    pub fn with_signing_keys(mut self, provider: DynSigningKeyProvider) -> Self {
+        self.signing_keys = Some(provider);
+        self
+    }
+
+    /// The same bus without signing, for relaying envelopes the server did not produce
+    /// (e.g. `/events/ingest`) so they cannot be passed off as its own.
+    pub fn without_signing(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            signing_keys: None,
+            outbox: self.outbox,
+        }
+    }
+
+    /// `envelope` as [`publish`](Self::publish) would send it, i.e. with sensitive metadata
+    /// redacted by the installed [`Redactor`] and signed when the bus has a signing key, for
+    /// producers that enqueue it in an outbox themselves.
+    pub fn seal(&self, mut envelope: EventEnvelope) -> Result<EventEnvelope, EventBusError> {
+        Redactor::current().redact_metadata(&mut envelope.event.metadata);
+        if let Some(key) = self.signing_keys.as_ref().and_then(|p| p.signing_key()) {
+            envelope
+                .sign(&key)
+                .map_err(|e| EventBusError::Other(format!("signing envelope: {e}")))?;
+        }
+        Ok(envelope)
     }
 
     pub async fn publish(&self, envelope: EventEnvelope) -> Result<(), EventBusError> {
+        let envelope = self.seal(envelope)?;
         self.inner.publish(envelope).await
     }
 
     /// Fire-and-forget publish.
     ///
     /// This is the recommended way to publish events from core OAuth2 flows.
-    /// Any publish error is logged but does not affect the caller.
+    /// Any publish error is logged but does not affect the caller. Must be called from
+    /// within a Tokio runtime (actix-web workers included).
     pub fn publish_best_effort(&self, envelope: EventEnvelope) {
         let handle = self.clone();
-        actix_rt::spawn(async move {
+        tokio::spawn(async move {
             if let Err(err) = handle.publish(envelope).await {
                 tracing::warn!(error = %err, "event publish failed (best-effort)");
             }
         });
     }
+
+    /// Fire-and-forget publish that is abandoned if still pending at `deadline`, the
+    /// deadline of the request the event belongs to. `None` behaves like
+    /// [`publish_best_effort`](Self::publish_best_effort).
+    pub fn publish_best_effort_until(&self, envelope: EventEnvelope, deadline: Option<Instant>) {
+        let Some(deadline) = deadline else {
+            return self.publish_best_effort(envelope);
+        };
+        let handle = self.clone();
+        tokio::spawn(async move {
+            match tokio::time::timeout_at(deadline.into(), handle.publish(envelope)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => {
+                    tracing::warn!(error = %err, "event publish failed (best-effort)");
+                }
+                Err(_) => {
+                    tracing::warn!("event publish abandoned at the request deadline");
+                }
+            }
+        });
+    }
 }
@@ -0,0 +1,132 @@
+use crate::EventEnvelope;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// An event a plugin still rejected after its retries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// Name of the plugin that rejected the event.
+    pub plugin: String,
+    /// The last error.
+    pub error: String,
+    pub attempts: u32,
+    pub dead_lettered_at: DateTime<Utc>,
+    pub envelope: EventEnvelope,
+}
+
+impl DeadLetter {
+    pub fn new(
+        plugin: impl Into<String>,
+        error: impl Into<String>,
+        attempts: u32,
+        envelope: EventEnvelope,
+    ) -> Self {
+        Self {
+            plugin: plugin.into(),
+            error: error.into(),
+            attempts,
+            dead_lettered_at: Utc::now(),
+            envelope,
+        }
+    }
+}
+
+/// Where dead letters are kept for inspection and replay.
+#[async_trait]
+pub trait DeadLetterSink: Send + Sync {
+    async fn send(&self, letter: &DeadLetter) -> Result<(), String>;
+
+    /// Destination kind, for logs.
+    fn name(&self) -> &str;
+}
+
+pub type DynDeadLetterSink = Arc<dyn DeadLetterSink>;
+
+/// Appends dead letters to a file, one JSON object per line.
+pub struct FileDeadLetterSink {
+    path: PathBuf,
+    write: Mutex<()>,
+}
+
+impl FileDeadLetterSink {
+    /// The file (and its directory) is created on the first dead letter.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            write: Mutex::new(()),
+        }
+    }
+}
+
+#[async_trait]
+impl DeadLetterSink for FileDeadLetterSink {
+    async fn send(&self, letter: &DeadLetter) -> Result<(), String> {
+        let mut line =
+            serde_json::to_vec(letter).map_err(|e| format!("serialize dead letter: {e}"))?;
+        line.push(b'\n');
+
+        // One writer at a time, so lines from concurrent failures never interleave.
+        let _write = self.write.lock().await;
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .map_err(|e| format!("create {}: {e}", dir.display()))?;
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(|e| format!("open {}: {e}", self.path.display()))?;
+        file.write_all(&line)
+            .await
+            .map_err(|e| format!("write {}: {e}", self.path.display()))?;
+        file.flush()
+            .await
+            .map_err(|e| format!("write {}: {e}", self.path.display()))
+    }
+
+    fn name(&self) -> &str {
+        "file"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AuthEvent, EventSeverity, EventType};
+
+    #[tokio::test]
+    async fn file_sink_appends_json_lines() {
+        let dir = std::env::temp_dir().join(format!("dlq-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("nested").join("dlq.jsonl");
+        let sink = FileDeadLetterSink::new(&path);
+
+        for event_type in [EventType::TokenCreated, EventType::TokenRevoked] {
+            let event = AuthEvent::new(event_type, EventSeverity::Info, None, None);
+            let envelope = EventEnvelope::from_current_span(event, "test");
+            sink.send(&DeadLetter::new("kafka", "broker unavailable", 3, envelope))
+                .await
+                .unwrap();
+        }
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let letters: Vec<DeadLetter> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(letters.len(), 2);
+        assert_eq!(letters[0].plugin, "kafka");
+        assert_eq!(letters[0].attempts, 3);
+        assert_eq!(
+            letters[1].envelope.event.event_type,
+            EventType::TokenRevoked
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,3 +1,4 @@
+use crate::signing::{self, SignatureError, SigningKey, VerificationKey};
 use crate::AuthEvent;
 use chrono::{DateTime, Utc};
 use opentelemetry::propagation::Injector;
@@ -44,6 +45,11 @@ pub struct EventEnvelope {
     /// Optional extension metadata for future backends.
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub attributes: HashMap<String, String>,
+
+    /// Detached JWS over the rest of the envelope, set when the bus has a signing key.
+    /// See [`crate::signing`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
 }
 
 impl EventEnvelope {
@@ -60,6 +66,7 @@ impl EventEnvelope {
             producer: producer.into(),
             produced_at: Utc::now(),
             attributes: HashMap::new(),
+            signature: None,
         }
     }
 
@@ -90,6 +97,18 @@ impl EventEnvelope {
             .filter(|v| !v.trim().is_empty())
             .unwrap_or_else(|| self.event.id.clone())
     }
+
+    /// Sign the envelope with `key`, replacing any existing signature. Any later change
+    /// to the envelope invalidates the signature.
+    pub fn sign(&mut self, key: &SigningKey) -> Result<(), SignatureError> {
+        self.signature = Some(signing::sign(self, key)?);
+        Ok(())
+    }
+
+    /// Verify the envelope's signature against the key in `keys` it names.
+    pub fn verify_signature(&self, keys: &[VerificationKey]) -> Result<(), SignatureError> {
+        signing::verify(self, keys)
+    }
 }
 
 fn extract_w3c_trace_context(span: &Span) -> (Option<String>, Option<String>) {
@@ -1,17 +1,29 @@
-use crate::{EventEnvelope, EventFilter, EventPlugin};
+use crate::{EventEnvelope, EventFilter, EventPlugin, PluginQueue, QueueLimits, QueueMetrics};
 use actix::prelude::*;
 use std::sync::Arc;
 
 /// Event actor that processes and distributes events to plugins
+///
+/// Each plugin gets a bounded [`PluginQueue`], so a slow backend neither holds up the
+/// others nor buffers events without limit.
 pub struct EventActor {
     plugins: Vec<Arc<dyn EventPlugin>>,
     filter: EventFilter,
+    limits: QueueLimits,
+    queue_metrics: Option<QueueMetrics>,
+    queues: Vec<Arc<PluginQueue>>,
 }
 
 impl EventActor {
     /// Create a new event actor with the given plugins and filter
     pub fn new(plugins: Vec<Arc<dyn EventPlugin>>, filter: EventFilter) -> Self {
-        Self { plugins, filter }
+        Self {
+            plugins,
+            filter,
+            limits: QueueLimits::default(),
+            queue_metrics: None,
+            queues: Vec::new(),
+        }
     }
 
     /// Create a new event actor with default plugins
@@ -21,7 +33,18 @@ impl EventActor {
 
         let plugins: Vec<Arc<dyn EventPlugin>> = vec![Arc::new(InMemoryEventLogger::new(1000))];
 
-        Self { plugins, filter }
+        Self::new(plugins, filter)
+    }
+
+    /// Queue capacity, overflow policy and concurrency for each plugin.
+    pub fn with_queue_limits(mut self, limits: QueueLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_queue_metrics(mut self, metrics: QueueMetrics) -> Self {
+        self.queue_metrics = Some(metrics);
+        self
     }
 }
 
@@ -29,6 +52,16 @@ impl Actor for EventActor {
     type Context = Context<Self>;
 
     fn started(&mut self, _ctx: &mut Self::Context) {
+        self.queues = self
+            .plugins
+            .iter()
+            .map(|plugin| {
+                let queue =
+                    PluginQueue::new(plugin.clone(), self.limits, self.queue_metrics.clone());
+                queue.start();
+                queue
+            })
+            .collect();
         tracing::info!("EventActor started with {} plugin(s)", self.plugins.len());
     }
 }
@@ -44,36 +77,124 @@ impl Handler<EmitEvent> for EventActor {
     type Result = ResponseFuture<()>;
 
     fn handle(&mut self, msg: EmitEvent, _: &mut Self::Context) -> Self::Result {
-        // Check if event should be emitted based on filter
-        if !self.filter.should_emit(&msg.envelope.event.event_type) {
-            tracing::trace!("Event {:?} filtered out", msg.envelope.event.event_type);
-            return Box::pin(async {});
-        }
+        let delivery = self.deliver(msg.envelope, false, None);
+        Box::pin(async move {
+            delivery.await;
+        })
+    }
+}
 
-        let plugins = self.plugins.clone();
-        let envelope = msg.envelope;
+/// Message to emit an event and wait for the plugins, failing with every plugin error
+/// joined when any of them failed.
+#[derive(Message)]
+#[rtype(result = "Result<(), String>")]
+pub struct DeliverEvent {
+    pub envelope: EventEnvelope,
+}
+
+impl Handler<DeliverEvent> for EventActor {
+    type Result = ResponseFuture<Result<(), String>>;
+
+    fn handle(&mut self, msg: DeliverEvent, _: &mut Self::Context) -> Self::Result {
+        let delivery = self.deliver(msg.envelope, true, None);
+        Box::pin(async move {
+            let failures = delivery.await;
+            if failures.is_empty() {
+                Ok(())
+            } else {
+                Err(failures.join("; "))
+            }
+        })
+    }
+}
+
+/// Message to deliver a replayed event again to the named plugins (every plugin when
+/// `None`) and wait for them, like [`DeliverEvent`]. The envelope is delivered unchanged,
+/// idempotency key included, so consumers can recognise events they already handled.
+#[derive(Message)]
+#[rtype(result = "Result<(), String>")]
+pub struct ReplayEvent {
+    pub envelope: EventEnvelope,
+    pub plugins: Option<Vec<String>>,
+}
+
+impl Handler<ReplayEvent> for EventActor {
+    type Result = ResponseFuture<Result<(), String>>;
 
+    fn handle(&mut self, msg: ReplayEvent, _: &mut Self::Context) -> Self::Result {
+        let delivery = self.deliver(msg.envelope, true, msg.plugins.as_deref());
         Box::pin(async move {
-            // Emit to all plugins in parallel
-            let futures: Vec<_> = plugins
+            let failures = delivery.await;
+            if failures.is_empty() {
+                Ok(())
+            } else {
+                Err(failures.join("; "))
+            }
+        })
+    }
+}
+
+/// Message to get the names of the plugins, in delivery order.
+#[derive(Message)]
+#[rtype(result = "Vec<String>")]
+pub struct GetPluginNames;
+
+impl Handler<GetPluginNames> for EventActor {
+    type Result = Vec<String>;
+
+    fn handle(&mut self, _msg: GetPluginNames, _: &mut Self::Context) -> Self::Result {
+        self.plugins.iter().map(|p| p.name().to_string()).collect()
+    }
+}
+
+impl EventActor {
+    /// Queue `envelope` for every plugin, or the ones named in `only`, unless the filter
+    /// drops it. When `acknowledged`, wait for the plugins to emit it; either way the
+    /// failures are returned as `plugin: error`, counting a dropped event as a failure.
+    fn deliver(
+        &self,
+        envelope: EventEnvelope,
+        acknowledged: bool,
+        only: Option<&[String]>,
+    ) -> impl std::future::Future<Output = Vec<String>> + 'static {
+        // Check if event should be emitted based on filter
+        let queues = if self.filter.should_emit(&envelope.event.event_type) {
+            self.queues
+                .iter()
+                .filter(|queue| {
+                    only.is_none_or(|names| names.iter().any(|n| n == queue.plugin().name()))
+                })
+                .cloned()
+                .collect()
+        } else {
+            tracing::trace!("Event {:?} filtered out", envelope.event.event_type);
+            Vec::new()
+        };
+
+        async move {
+            let futures: Vec<_> = queues
                 .iter()
-                .map(|plugin| {
-                    let plugin = plugin.clone();
+                .map(|queue| {
                     let envelope = envelope.clone();
                     async move {
-                        if let Err(e) = plugin.emit(&envelope).await {
-                            tracing::error!(
-                                "Failed to emit event to plugin {}: {}",
-                                plugin.name(),
-                                e
-                            );
-                        }
+                        let result = if acknowledged {
+                            queue.deliver(envelope).await
+                        } else if queue.push(envelope).await {
+                            Ok(())
+                        } else {
+                            Err("queue full".to_string())
+                        };
+                        result.map_err(|e| format!("{}: {}", queue.plugin().name(), e))
                     }
                 })
                 .collect();
 
-            futures::future::join_all(futures).await;
-        })
+            futures::future::join_all(futures)
+                .await
+                .into_iter()
+                .filter_map(Result::err)
+                .collect()
+        }
     }
 }
 
@@ -181,4 +302,29 @@ mod tests {
         assert_eq!(health[0].0, "in_memory");
         assert!(health[0].1);
     }
+
+    #[actix::test]
+    async fn test_event_actor_replay_to_named_plugins() {
+        let first = Arc::new(InMemoryEventLogger::new(10));
+        let second = Arc::new(crate::ConsoleEventLogger::new());
+        let plugins: Vec<Arc<dyn EventPlugin>> = vec![first.clone(), second];
+        let actor = EventActor::new(plugins, EventFilter::allow_all()).start();
+
+        assert_eq!(
+            actor.send(GetPluginNames).await.unwrap(),
+            ["in_memory", "console"]
+        );
+
+        let event = AuthEvent::new(EventType::TokenCreated, EventSeverity::Info, None, None);
+        let envelope = EventEnvelope::from_current_span(event, "test");
+        let replay = |plugins: &[&str]| ReplayEvent {
+            envelope: envelope.clone(),
+            plugins: Some(plugins.iter().map(|p| p.to_string()).collect()),
+        };
+        actor.send(replay(&["console"])).await.unwrap().unwrap();
+        assert!(first.get_events().is_empty());
+
+        actor.send(replay(&["in_memory"])).await.unwrap().unwrap();
+        assert_eq!(first.get_events()[0].event.id, envelope.event.id);
+    }
 }
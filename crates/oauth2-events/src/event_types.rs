@@ -10,22 +10,54 @@ pub enum EventType {
     AuthorizationCodeCreated,
     AuthorizationCodeValidated,
     AuthorizationCodeExpired,
+    /// Code redeemed from a context the binding does not accept (see
+    /// `security.context_binding_tolerance`).
+    AuthorizationCodeContextMismatch,
+    /// An already redeemed code was presented again.
+    AuthorizationCodeReplayDetected,
+    /// An authorize request without an S256 code challenge, or a code redeemed without
+    /// its verifier.
+    PkceDowngradeAttempt,
+    /// An authorize or logout request with a redirect URI the client did not register.
+    InvalidRedirectUriAttempt,
 
     // Token events
     TokenCreated,
     TokenValidated,
     TokenRevoked,
     TokenExpired,
+    /// Refresh token presented from a context the binding does not accept.
+    RefreshTokenContextMismatch,
 
     // Client events
     ClientRegistered,
     ClientValidated,
     ClientDeleted,
+    /// Redirect URI update held back by `security.redirect_uri_changes`.
+    ClientRedirectUrisChangeRequested,
+    ClientRedirectUrisChanged,
+    ClientRedirectUrisChangeRejected,
+    /// A client presented unknown or wrong credentials.
+    ClientAuthenticationFailed,
 
     // User events
     UserAuthenticated,
     UserAuthenticationFailed,
+    /// A social or SAML sign-in did not complete.
+    LoginFailed,
     UserLogout,
+    /// A verification link was mailed to the user's address.
+    EmailVerificationSent,
+    EmailVerified,
+    /// A reset link was mailed for a known, enabled user.
+    PasswordResetRequested,
+    PasswordResetCompleted,
+    /// A sign-in link was mailed for a known, enabled user.
+    MagicLinkSent,
+
+    // Abuse events
+    /// A caller went over a request quota.
+    RateLimitExceeded,
 }
 
 impl EventType {
@@ -36,16 +68,32 @@ impl EventType {
             EventType::AuthorizationCodeCreated => "authorization_code_created",
             EventType::AuthorizationCodeValidated => "authorization_code_validated",
             EventType::AuthorizationCodeExpired => "authorization_code_expired",
+            EventType::AuthorizationCodeContextMismatch => "authorization_code_context_mismatch",
+            EventType::AuthorizationCodeReplayDetected => "authorization_code_replay_detected",
+            EventType::PkceDowngradeAttempt => "pkce_downgrade_attempt",
+            EventType::InvalidRedirectUriAttempt => "invalid_redirect_uri_attempt",
             EventType::TokenCreated => "token_created",
             EventType::TokenValidated => "token_validated",
             EventType::TokenRevoked => "token_revoked",
             EventType::TokenExpired => "token_expired",
+            EventType::RefreshTokenContextMismatch => "refresh_token_context_mismatch",
             EventType::ClientRegistered => "client_registered",
             EventType::ClientValidated => "client_validated",
             EventType::ClientDeleted => "client_deleted",
+            EventType::ClientRedirectUrisChangeRequested => "client_redirect_uris_change_requested",
+            EventType::ClientRedirectUrisChanged => "client_redirect_uris_changed",
+            EventType::ClientRedirectUrisChangeRejected => "client_redirect_uris_change_rejected",
+            EventType::ClientAuthenticationFailed => "client_authentication_failed",
             EventType::UserAuthenticated => "user_authenticated",
             EventType::UserAuthenticationFailed => "user_authentication_failed",
+            EventType::LoginFailed => "login_failed",
             EventType::UserLogout => "user_logout",
+            EventType::EmailVerificationSent => "email_verification_sent",
+            EventType::EmailVerified => "email_verified",
+            EventType::PasswordResetRequested => "password_reset_requested",
+            EventType::PasswordResetCompleted => "password_reset_completed",
+            EventType::MagicLinkSent => "magic_link_sent",
+            EventType::RateLimitExceeded => "rate_limit_exceeded",
         }
     }
 }
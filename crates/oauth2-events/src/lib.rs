@@ -1,20 +1,39 @@
+#[cfg(feature = "actix")]
 pub mod actix_bus;
 pub mod backends;
 pub mod bus;
+pub mod dead_letter;
 pub mod envelope;
+#[cfg(feature = "actix")]
 pub mod event_actor;
 pub mod event_types;
 pub mod plugins;
+pub mod queue;
+pub mod replay;
+pub mod retry;
+pub mod schema;
+pub mod signing;
+#[cfg(feature = "testing")]
+pub mod testing;
 
+#[cfg(feature = "actix")]
 pub use actix_bus::*;
 pub use bus::*;
+pub use dead_letter::*;
 pub use envelope::*;
 pub use event_types::*;
 pub use plugins::*;
+pub use queue::*;
+pub use replay::*;
+pub use retry::*;
+pub use schema::*;
+pub use signing::*;
 
 #[cfg(any(
     feature = "events-redis",
     feature = "events-kafka",
-    feature = "events-rabbit"
+    feature = "events-rabbit",
+    feature = "events-sns",
+    feature = "events-pubsub"
 ))]
 pub use backends::*;
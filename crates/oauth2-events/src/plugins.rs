@@ -2,6 +2,7 @@ use crate::{EventEnvelope, EventType};
 use async_trait::async_trait;
 use std::collections::HashSet;
 use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
 
 /// Trait for event backend plugins
 #[async_trait]
@@ -160,6 +161,44 @@ impl EventPlugin for ConsoleEventLogger {
     }
 }
 
+/// Fans events out to live subscribers, such as the admin event stream.
+///
+/// Events are only delivered to current subscribers; nothing is kept. A subscriber that
+/// falls more than `capacity` events behind misses the oldest ones.
+#[derive(Clone)]
+pub struct LiveEventStream {
+    sender: broadcast::Sender<EventEnvelope>,
+}
+
+impl LiveEventStream {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self { sender }
+    }
+
+    /// Receive events published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<EventEnvelope> {
+        self.sender.subscribe()
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+#[async_trait]
+impl EventPlugin for LiveEventStream {
+    async fn emit(&self, envelope: &EventEnvelope) -> Result<(), String> {
+        // No subscribers is not a failure.
+        let _ = self.sender.send(envelope.clone());
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "live_stream"
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -232,4 +271,20 @@ mod tests {
         assert_eq!(events[0].event.user_id, Some("user_2".to_string()));
         assert_eq!(events[2].event.user_id, Some("user_4".to_string()));
     }
+
+    #[tokio::test]
+    async fn test_live_stream_delivers_to_current_subscribers() {
+        let stream = LiveEventStream::new(8);
+        let event = AuthEvent::new(EventType::TokenCreated, EventSeverity::Info, None, None);
+        let env = EventEnvelope::from_current_span(event, "test");
+
+        // Publishing without subscribers succeeds and is not replayed to later ones.
+        stream.emit(&env).await.unwrap();
+        let mut receiver = stream.subscribe();
+        assert_eq!(stream.subscriber_count(), 1);
+
+        stream.emit(&env).await.unwrap();
+        assert_eq!(receiver.recv().await.unwrap().event.id, env.event.id);
+        assert!(receiver.try_recv().is_err());
+    }
 }
@@ -0,0 +1,377 @@
+use crate::{EventEnvelope, EventPlugin};
+use prometheus::{IntCounterVec, IntGaugeVec};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{oneshot, Notify};
+
+/// What to do with an event when a plugin's queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Evict the oldest queued event to make room.
+    DropOldest,
+    /// Drop the incoming event.
+    DropNewest,
+    /// Wait up to the timeout for room, then drop the incoming event.
+    Block(Duration),
+}
+
+/// Limits on the events waiting for, and being emitted to, one plugin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueLimits {
+    /// Events waiting for the plugin.
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+    /// Emits the plugin runs at once. With 1, the plugin sees events in order.
+    pub concurrency: usize,
+}
+
+impl Default for QueueLimits {
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            overflow: OverflowPolicy::DropOldest,
+            concurrency: 4,
+        }
+    }
+}
+
+/// Queue gauges and counters, labeled by `plugin`.
+#[derive(Clone)]
+pub struct QueueMetrics {
+    pub depth: IntGaugeVec,
+    pub dropped: IntCounterVec,
+}
+
+type Ack = oneshot::Sender<Result<(), String>>;
+
+struct Queued {
+    envelope: EventEnvelope,
+    ack: Option<Ack>,
+}
+
+/// A bounded queue in front of one plugin, drained by `concurrency` workers, so a slow
+/// backend holds at most `capacity` events instead of an ever-growing backlog.
+pub struct PluginQueue {
+    plugin: Arc<dyn EventPlugin>,
+    limits: QueueLimits,
+    queue: Mutex<VecDeque<Queued>>,
+    queued: Notify,
+    freed: Notify,
+    metrics: Option<QueueMetrics>,
+}
+
+impl PluginQueue {
+    pub fn new(
+        plugin: Arc<dyn EventPlugin>,
+        limits: QueueLimits,
+        metrics: Option<QueueMetrics>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            plugin,
+            limits: QueueLimits {
+                capacity: limits.capacity.max(1),
+                concurrency: limits.concurrency.max(1),
+                ..limits
+            },
+            queue: Mutex::new(VecDeque::new()),
+            queued: Notify::new(),
+            freed: Notify::new(),
+            metrics,
+        })
+    }
+
+    pub fn plugin(&self) -> &Arc<dyn EventPlugin> {
+        &self.plugin
+    }
+
+    /// Events waiting for the plugin.
+    pub fn depth(&self) -> usize {
+        self.queue.lock().unwrap().len()
+    }
+
+    /// Start the workers. Must be called from within a tokio runtime.
+    pub fn start(self: &Arc<Self>) {
+        for _ in 0..self.limits.concurrency {
+            let queue = self.clone();
+            tokio::spawn(async move {
+                loop {
+                    let queued = queue.next().await;
+                    let result = queue.plugin.emit(&queued.envelope).await;
+                    if let Err(e) = &result {
+                        tracing::error!(
+                            "Failed to emit event to plugin {}: {}",
+                            queue.plugin.name(),
+                            e
+                        );
+                    }
+                    if let Some(ack) = queued.ack {
+                        let _ = ack.send(result);
+                    }
+                }
+            });
+        }
+    }
+
+    async fn next(&self) -> Queued {
+        loop {
+            if let Some(queued) = self.pop() {
+                return queued;
+            }
+            self.queued.notified().await;
+        }
+    }
+
+    /// Queue `envelope` without waiting for the plugin. Returns whether it was queued;
+    /// dropped events are logged and counted.
+    pub async fn push(&self, envelope: EventEnvelope) -> bool {
+        self.enqueue(Queued {
+            envelope,
+            ack: None,
+        })
+        .await
+    }
+
+    /// Queue `envelope` and wait for the plugin to emit it, failing if the event was
+    /// dropped or the plugin failed.
+    pub async fn deliver(&self, envelope: EventEnvelope) -> Result<(), String> {
+        let (ack, done) = oneshot::channel();
+        if !self
+            .enqueue(Queued {
+                envelope,
+                ack: Some(ack),
+            })
+            .await
+        {
+            return Err("queue full".to_string());
+        }
+        done.await
+            .unwrap_or_else(|_| Err("queue closed".to_string()))
+    }
+
+    async fn enqueue(&self, queued: Queued) -> bool {
+        let deadline = match self.limits.overflow {
+            OverflowPolicy::Block(timeout) => Some(tokio::time::Instant::now() + timeout),
+            _ => None,
+        };
+        let mut queued = queued;
+        loop {
+            let freed = self.freed.notified();
+            tokio::pin!(freed);
+            freed.as_mut().enable();
+
+            queued = match self.try_push(queued) {
+                None => {
+                    self.queued.notify_one();
+                    return true;
+                }
+                Some(queued) => queued,
+            };
+            let Some(deadline) = deadline else {
+                self.dropped(&queued.envelope);
+                return false;
+            };
+            if tokio::time::timeout_at(deadline, freed).await.is_err() {
+                self.dropped(&queued.envelope);
+                return false;
+            }
+        }
+    }
+
+    /// Push unless the queue is full, handing `queued` back if it is; with `DropOldest` a
+    /// full queue makes room instead.
+    fn try_push(&self, queued: Queued) -> Option<Queued> {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.len() >= self.limits.capacity {
+            if self.limits.overflow != OverflowPolicy::DropOldest {
+                return Some(queued);
+            }
+            if let Some(evicted) = queue.pop_front() {
+                self.dropped(&evicted.envelope);
+                if let Some(ack) = evicted.ack {
+                    let _ = ack.send(Err("dropped: queue full".to_string()));
+                }
+            }
+        }
+        queue.push_back(queued);
+        self.set_depth(queue.len());
+        None
+    }
+
+    fn pop(&self) -> Option<Queued> {
+        let mut queue = self.queue.lock().unwrap();
+        let queued = queue.pop_front()?;
+        self.set_depth(queue.len());
+        drop(queue);
+        self.freed.notify_waiters();
+        Some(queued)
+    }
+
+    fn set_depth(&self, depth: usize) {
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .depth
+                .with_label_values(&[self.plugin.name()])
+                .set(depth as i64);
+        }
+    }
+
+    fn dropped(&self, envelope: &EventEnvelope) {
+        tracing::warn!(
+            plugin = self.plugin.name(),
+            event_id = %envelope.event.id,
+            event_type = envelope.event.event_type.as_str(),
+            capacity = self.limits.capacity,
+            "Event queue full; event dropped"
+        );
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .dropped
+                .with_label_values(&[self.plugin.name()])
+                .inc();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AuthEvent, EventSeverity, EventType};
+    use async_trait::async_trait;
+    use prometheus::Opts;
+    use tokio::sync::Semaphore;
+
+    /// Emits once a test releases a permit, recording event types in order.
+    struct Gated {
+        gate: Semaphore,
+        emitted: Mutex<Vec<EventType>>,
+    }
+
+    #[async_trait]
+    impl EventPlugin for Gated {
+        async fn emit(&self, envelope: &EventEnvelope) -> Result<(), String> {
+            self.gate.acquire().await.unwrap().forget();
+            self.emitted
+                .lock()
+                .unwrap()
+                .push(envelope.event.event_type.clone());
+            Ok(())
+        }
+
+        fn name(&self) -> &str {
+            "gated"
+        }
+    }
+
+    fn gated() -> Arc<Gated> {
+        Arc::new(Gated {
+            gate: Semaphore::new(0),
+            emitted: Mutex::new(Vec::new()),
+        })
+    }
+
+    fn envelope(event_type: EventType) -> EventEnvelope {
+        let event = AuthEvent::new(event_type, EventSeverity::Info, None, None);
+        EventEnvelope::from_current_span(event, "test")
+    }
+
+    fn metrics() -> QueueMetrics {
+        QueueMetrics {
+            depth: IntGaugeVec::new(Opts::new("depth", "depth"), &["plugin"]).unwrap(),
+            dropped: IntCounterVec::new(Opts::new("dropped", "dropped"), &["plugin"]).unwrap(),
+        }
+    }
+
+    fn limits(overflow: OverflowPolicy) -> QueueLimits {
+        QueueLimits {
+            capacity: 2,
+            overflow,
+            concurrency: 1,
+        }
+    }
+
+    /// Fill a stopped queue with capacity 2 with three events.
+    async fn overflow(policy: OverflowPolicy) -> (Arc<PluginQueue>, Arc<Gated>, QueueMetrics) {
+        let plugin = gated();
+        let metrics = metrics();
+        let queue = PluginQueue::new(plugin.clone(), limits(policy), Some(metrics.clone()));
+        assert!(queue.push(envelope(EventType::TokenCreated)).await);
+        assert!(queue.push(envelope(EventType::TokenRevoked)).await);
+        queue.push(envelope(EventType::TokenExpired)).await;
+        (queue, plugin, metrics)
+    }
+
+    async fn drain(queue: &Arc<PluginQueue>, plugin: &Gated) -> Vec<EventType> {
+        queue.start();
+        plugin.gate.add_permits(10);
+        for _ in 0..100 {
+            if queue.depth() == 0 && plugin.emitted.lock().unwrap().len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        plugin.emitted.lock().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_evicts_the_head() {
+        let (queue, plugin, metrics) = overflow(OverflowPolicy::DropOldest).await;
+        assert_eq!(queue.depth(), 2);
+        assert_eq!(metrics.depth.with_label_values(&["gated"]).get(), 2);
+        assert_eq!(metrics.dropped.with_label_values(&["gated"]).get(), 1);
+        assert_eq!(
+            drain(&queue, &plugin).await,
+            [EventType::TokenRevoked, EventType::TokenExpired]
+        );
+        assert_eq!(metrics.depth.with_label_values(&["gated"]).get(), 0);
+    }
+
+    #[tokio::test]
+    async fn drop_newest_rejects_the_incoming_event() {
+        let (queue, plugin, metrics) = overflow(OverflowPolicy::DropNewest).await;
+        assert_eq!(metrics.dropped.with_label_values(&["gated"]).get(), 1);
+        assert_eq!(
+            drain(&queue, &plugin).await,
+            [EventType::TokenCreated, EventType::TokenRevoked]
+        );
+    }
+
+    #[tokio::test]
+    async fn block_drops_the_event_when_no_room_frees_up() {
+        let (queue, plugin, metrics) =
+            overflow(OverflowPolicy::Block(Duration::from_millis(20))).await;
+        assert_eq!(metrics.dropped.with_label_values(&["gated"]).get(), 1);
+        assert_eq!(
+            drain(&queue, &plugin).await,
+            [EventType::TokenCreated, EventType::TokenRevoked]
+        );
+    }
+
+    #[tokio::test]
+    async fn block_waits_for_room() {
+        let plugin = gated();
+        let queue = PluginQueue::new(
+            plugin.clone(),
+            limits(OverflowPolicy::Block(Duration::from_secs(5))),
+            None,
+        );
+        assert!(queue.push(envelope(EventType::TokenCreated)).await);
+        assert!(queue.push(envelope(EventType::TokenRevoked)).await);
+        queue.start();
+        plugin.gate.add_permits(1);
+        assert!(queue.push(envelope(EventType::TokenExpired)).await);
+    }
+
+    #[tokio::test]
+    async fn deliver_reports_the_plugin_result() {
+        let plugin = gated();
+        let queue = PluginQueue::new(plugin.clone(), limits(OverflowPolicy::DropNewest), None);
+        queue.start();
+        plugin.gate.add_permits(1);
+        queue
+            .deliver(envelope(EventType::TokenCreated))
+            .await
+            .unwrap();
+        assert_eq!(*plugin.emitted.lock().unwrap(), [EventType::TokenCreated]);
+    }
+}
@@ -0,0 +1,89 @@
+//! Re-reading persisted events, for replaying them to plugins.
+
+use crate::EventEnvelope;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Which persisted events to replay. Bounds are inclusive.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum ReplayRange {
+    /// Events written between two instants.
+    Time {
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    },
+    /// Redis Streams entries between two entry ids (e.g. `1700000000000-0`).
+    StreamIds { from_id: String, to_id: String },
+    /// Kafka records between two offsets of one partition.
+    Offsets {
+        partition: i32,
+        from_offset: i64,
+        to_offset: i64,
+    },
+}
+
+/// A backend events can be read back from, oldest first.
+#[async_trait]
+pub trait EventReplaySource: Send + Sync {
+    /// Up to `limit` envelopes in `range`. Fails for ranges the backend has no notion of.
+    async fn read(&self, range: &ReplayRange, limit: usize) -> Result<Vec<EventEnvelope>, String>;
+
+    fn name(&self) -> &str;
+}
+
+pub type DynEventReplaySource = Arc<dyn EventReplaySource>;
+
+/// `envelopes` without the ones repeating an earlier envelope's effective idempotency key,
+/// e.g. a publish the backend accepted twice after a retry.
+pub fn dedupe_by_idempotency_key(envelopes: Vec<EventEnvelope>) -> Vec<EventEnvelope> {
+    let mut seen = HashSet::new();
+    envelopes
+        .into_iter()
+        .filter(|envelope| seen.insert(envelope.effective_idempotency_key()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AuthEvent, EventSeverity, EventType};
+
+    #[test]
+    fn ranges_are_told_apart_by_their_fields() {
+        let range: ReplayRange =
+            serde_json::from_str(r#"{"from":"2024-01-15T10:00:00Z","to":"2024-01-15T11:00:00Z"}"#)
+                .unwrap();
+        assert!(matches!(range, ReplayRange::Time { .. }));
+
+        let range: ReplayRange = serde_json::from_str(r#"{"from_id":"1-0","to_id":"+"}"#).unwrap();
+        assert_eq!(
+            range,
+            ReplayRange::StreamIds {
+                from_id: "1-0".to_string(),
+                to_id: "+".to_string()
+            }
+        );
+
+        let range: ReplayRange =
+            serde_json::from_str(r#"{"partition":2,"from_offset":10,"to_offset":20}"#).unwrap();
+        assert!(matches!(range, ReplayRange::Offsets { partition: 2, .. }));
+    }
+
+    #[test]
+    fn duplicates_keep_the_first_delivery() {
+        let envelope = |key: &str| {
+            let event = AuthEvent::new(EventType::TokenCreated, EventSeverity::Info, None, None);
+            EventEnvelope::from_current_span(event, "test").with_idempotency_key(key)
+        };
+        let envelopes = vec![envelope("a"), envelope("b"), envelope("a")];
+        let first = envelopes[0].event.id.clone();
+
+        let kept = dedupe_by_idempotency_key(envelopes);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].event.id, first);
+    }
+}
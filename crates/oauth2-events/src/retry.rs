@@ -0,0 +1,255 @@
+use crate::{DeadLetter, DynDeadLetterSink, EventEnvelope, EventPlugin};
+use async_trait::async_trait;
+use prometheus::IntCounterVec;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often a failed emit is retried, and how long to wait in between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first.
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Delay after failed attempt `attempt` (1-based): the initial backoff, doubling per
+    /// attempt, up to the maximum.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Retries a plugin's failed emits and, once they are exhausted, hands the event to a
+/// dead-letter sink instead of dropping it.
+///
+/// An event that is dead-lettered counts as emitted; without a sink (or when the sink
+/// fails too) the last error is returned.
+pub struct RetryingPlugin {
+    inner: Arc<dyn EventPlugin>,
+    policy: RetryPolicy,
+    dead_letter: Option<DynDeadLetterSink>,
+    retries: Option<IntCounterVec>,
+    dead_lettered: Option<IntCounterVec>,
+}
+
+impl RetryingPlugin {
+    pub fn new(inner: Arc<dyn EventPlugin>, policy: RetryPolicy) -> Self {
+        Self {
+            inner,
+            policy,
+            dead_letter: None,
+            retries: None,
+            dead_lettered: None,
+        }
+    }
+
+    pub fn with_dead_letter(mut self, sink: DynDeadLetterSink) -> Self {
+        self.dead_letter = Some(sink);
+        self
+    }
+
+    /// Count retries and dead letters; both counters are labeled by `plugin`.
+    pub fn with_metrics(mut self, retries: IntCounterVec, dead_lettered: IntCounterVec) -> Self {
+        self.retries = Some(retries);
+        self.dead_lettered = Some(dead_lettered);
+        self
+    }
+}
+
+#[async_trait]
+impl EventPlugin for RetryingPlugin {
+    async fn emit(&self, envelope: &EventEnvelope) -> Result<(), String> {
+        let max_attempts = self.policy.max_attempts.max(1);
+        let mut attempt = 1;
+        let error = loop {
+            match self.inner.emit(envelope).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt >= max_attempts => break e,
+                Err(e) => {
+                    let delay = self.policy.backoff(attempt);
+                    tracing::debug!(
+                        plugin = self.inner.name(),
+                        attempt,
+                        retry_in_ms = delay.as_millis() as u64,
+                        error = %e,
+                        "Event emit failed; retrying"
+                    );
+                    if let Some(retries) = &self.retries {
+                        retries.with_label_values(&[self.inner.name()]).inc();
+                    }
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+            }
+        };
+
+        let Some(sink) = &self.dead_letter else {
+            return Err(error);
+        };
+        let letter = DeadLetter::new(self.inner.name(), &error, attempt, envelope.clone());
+        match sink.send(&letter).await {
+            Ok(()) => {
+                tracing::warn!(
+                    plugin = self.inner.name(),
+                    event_id = %envelope.event.id,
+                    attempts = attempt,
+                    destination = sink.name(),
+                    error = %error,
+                    "Event dead-lettered"
+                );
+                if let Some(dead_lettered) = &self.dead_lettered {
+                    dead_lettered.with_label_values(&[self.inner.name()]).inc();
+                }
+                Ok(())
+            }
+            Err(e) => Err(format!("{error} (dead-lettering failed: {e})")),
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn health_check(&self) -> bool {
+        self.inner.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AuthEvent, DeadLetterSink, EventSeverity, EventType};
+    use prometheus::Opts;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Mutex;
+
+    /// Fails its first `failures` emits.
+    struct Flaky {
+        failures: u32,
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl EventPlugin for Flaky {
+        async fn emit(&self, _envelope: &EventEnvelope) -> Result<(), String> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            if call <= self.failures {
+                Err(format!("attempt {call} failed"))
+            } else {
+                Ok(())
+            }
+        }
+
+        fn name(&self) -> &str {
+            "flaky"
+        }
+    }
+
+    #[derive(Default)]
+    struct Recorded(Mutex<Vec<DeadLetter>>);
+
+    #[async_trait]
+    impl DeadLetterSink for Recorded {
+        async fn send(&self, letter: &DeadLetter) -> Result<(), String> {
+            self.0.lock().unwrap().push(letter.clone());
+            Ok(())
+        }
+
+        fn name(&self) -> &str {
+            "recorded"
+        }
+    }
+
+    fn counter(name: &str) -> IntCounterVec {
+        IntCounterVec::new(Opts::new(name, name), &["plugin"]).unwrap()
+    }
+
+    fn envelope() -> EventEnvelope {
+        let event = AuthEvent::new(EventType::TokenCreated, EventSeverity::Info, None, None);
+        EventEnvelope::from_current_span(event, "test")
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+        };
+        let delays: Vec<u128> = (1..=5).map(|n| policy.backoff(n).as_millis()).collect();
+        assert_eq!(delays, [100, 200, 400, 500, 500]);
+    }
+
+    #[tokio::test]
+    async fn retries_until_the_plugin_recovers() {
+        let flaky = Arc::new(Flaky {
+            failures: 2,
+            calls: AtomicU32::new(0),
+        });
+        let sink = Arc::new(Recorded::default());
+        let retries = counter("retries");
+        let plugin = RetryingPlugin::new(flaky.clone(), policy())
+            .with_dead_letter(sink.clone())
+            .with_metrics(retries.clone(), counter("dead_lettered"));
+
+        plugin.emit(&envelope()).await.unwrap();
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 3);
+        assert_eq!(retries.with_label_values(&["flaky"]).get(), 2);
+        assert!(sink.0.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn exhausted_events_are_dead_lettered() {
+        let flaky = Arc::new(Flaky {
+            failures: u32::MAX,
+            calls: AtomicU32::new(0),
+        });
+        let sink = Arc::new(Recorded::default());
+        let dead_lettered = counter("dead_lettered");
+        let plugin = RetryingPlugin::new(flaky.clone(), policy())
+            .with_dead_letter(sink.clone())
+            .with_metrics(counter("retries"), dead_lettered.clone());
+
+        let envelope = envelope();
+        plugin.emit(&envelope).await.unwrap();
+        let letters = sink.0.lock().unwrap().clone();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].plugin, "flaky");
+        assert_eq!(letters[0].error, "attempt 3 failed");
+        assert_eq!(letters[0].attempts, 3);
+        assert_eq!(letters[0].envelope.event.id, envelope.event.id);
+        assert_eq!(dead_lettered.with_label_values(&["flaky"]).get(), 1);
+
+        // Without a sink the failure is reported instead.
+        let plugin = RetryingPlugin::new(flaky, policy());
+        assert_eq!(
+            plugin.emit(&envelope).await.unwrap_err(),
+            "attempt 6 failed"
+        );
+    }
+}
@@ -0,0 +1,295 @@
+//! Schema-registry serialization of event envelopes.
+//!
+//! Encodes envelopes in the Confluent wire format (a zero byte, the big-endian schema
+//! id, then the payload), so consumers using a schema registry deserializer can read
+//! them. Signatures ([`crate::signing`]) cover the JSON form of the envelope; consumers
+//! of the Avro form can't verify them.
+
+use crate::EventEnvelope;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use std::collections::HashMap;
+
+/// Payload format registered with the schema registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaFormat {
+    /// The JSON envelope, validated against [`envelope_json_schema`].
+    JsonSchema,
+    /// Avro binary encoding of [`envelope_avro_schema`].
+    Avro,
+}
+
+impl SchemaFormat {
+    /// `schemaType` in schema registry requests.
+    pub fn schema_type(&self) -> &'static str {
+        match self {
+            SchemaFormat::JsonSchema => "JSON",
+            SchemaFormat::Avro => "AVRO",
+        }
+    }
+
+    /// The envelope schema in this format.
+    pub fn schema(&self) -> serde_json::Value {
+        match self {
+            SchemaFormat::JsonSchema => envelope_json_schema(),
+            SchemaFormat::Avro => envelope_avro_schema(),
+        }
+    }
+
+    /// `envelope` in the wire format, tagged with `schema_id`.
+    pub fn encode(&self, schema_id: u32, envelope: &EventEnvelope) -> Result<Vec<u8>, String> {
+        let mut out = vec![0u8];
+        out.extend_from_slice(&schema_id.to_be_bytes());
+        match self {
+            SchemaFormat::JsonSchema => serde_json::to_writer(&mut out, envelope)
+                .map_err(|e| format!("serialize envelope: {e}"))?,
+            SchemaFormat::Avro => avro::envelope(&mut out, envelope),
+        }
+        Ok(out)
+    }
+}
+
+/// JSON Schema (draft 7) of the JSON envelope.
+pub fn envelope_json_schema() -> serde_json::Value {
+    let optional_string = json!({ "type": ["string", "null"] });
+    let string_map = json!({ "type": "object", "additionalProperties": { "type": "string" } });
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "EventEnvelope",
+        "type": "object",
+        "required": ["event", "correlation_id", "producer", "produced_at"],
+        "properties": {
+            "event": {
+                "type": "object",
+                "required": ["id", "event_type", "timestamp", "severity", "metadata"],
+                "properties": {
+                    "id": { "type": "string" },
+                    "event_type": { "type": "string" },
+                    "timestamp": { "type": "string", "format": "date-time" },
+                    "severity": { "type": "string", "enum": ["info", "warning", "error"] },
+                    "user_id": optional_string,
+                    "client_id": optional_string,
+                    "metadata": string_map,
+                    "error": optional_string
+                }
+            },
+            "idempotency_key": { "type": "string" },
+            "traceparent": { "type": "string" },
+            "tracestate": { "type": "string" },
+            "correlation_id": { "type": "string" },
+            "producer": { "type": "string" },
+            "produced_at": { "type": "string", "format": "date-time" },
+            "attributes": string_map,
+            "signature": { "type": "string" }
+        }
+    })
+}
+
+/// Avro schema of the envelope. Timestamps are `timestamp-micros`; the event type and
+/// severity are strings, so new event types don't need a schema change.
+pub fn envelope_avro_schema() -> serde_json::Value {
+    let optional_string =
+        |name: &str| json!({ "name": name, "type": ["null", "string"], "default": null });
+    let string_map = |name: &str| json!({ "name": name, "type": { "type": "map", "values": "string" }, "default": {} });
+    let timestamp = |name: &str| json!({ "name": name, "type": { "type": "long", "logicalType": "timestamp-micros" } });
+    json!({
+        "type": "record",
+        "name": "EventEnvelope",
+        "namespace": "oauth2.events",
+        "fields": [
+            {
+                "name": "event",
+                "type": {
+                    "type": "record",
+                    "name": "AuthEvent",
+                    "fields": [
+                        { "name": "id", "type": "string" },
+                        { "name": "event_type", "type": "string" },
+                        timestamp("timestamp"),
+                        { "name": "severity", "type": "string" },
+                        optional_string("user_id"),
+                        optional_string("client_id"),
+                        string_map("metadata"),
+                        optional_string("error")
+                    ]
+                }
+            },
+            optional_string("idempotency_key"),
+            optional_string("traceparent"),
+            optional_string("tracestate"),
+            { "name": "correlation_id", "type": "string" },
+            { "name": "producer", "type": "string" },
+            timestamp("produced_at"),
+            string_map("attributes"),
+            optional_string("signature")
+        ]
+    })
+}
+
+/// Avro binary encoding, field by field in [`envelope_avro_schema`] order.
+mod avro {
+    use super::*;
+
+    pub(super) fn envelope(out: &mut Vec<u8>, envelope: &EventEnvelope) {
+        let event = &envelope.event;
+        string(out, &event.id);
+        string(out, event.event_type.as_str());
+        timestamp(out, &event.timestamp);
+        string(out, severity(event));
+        optional_string(out, event.user_id.as_deref());
+        optional_string(out, event.client_id.as_deref());
+        string_map(out, &event.metadata);
+        optional_string(out, event.error.as_deref());
+
+        optional_string(out, envelope.idempotency_key.as_deref());
+        optional_string(out, envelope.traceparent.as_deref());
+        optional_string(out, envelope.tracestate.as_deref());
+        string(out, &envelope.correlation_id);
+        string(out, &envelope.producer);
+        timestamp(out, &envelope.produced_at);
+        string_map(out, &envelope.attributes);
+        optional_string(out, envelope.signature.as_deref());
+    }
+
+    fn severity(event: &crate::AuthEvent) -> &'static str {
+        match event.severity {
+            crate::EventSeverity::Info => "info",
+            crate::EventSeverity::Warning => "warning",
+            crate::EventSeverity::Error => "error",
+        }
+    }
+
+    /// Zig-zag variable-length integer.
+    pub(super) fn long(out: &mut Vec<u8>, n: i64) {
+        let mut z = ((n << 1) ^ (n >> 63)) as u64;
+        while z >= 0x80 {
+            out.push((z as u8 & 0x7f) | 0x80);
+            z >>= 7;
+        }
+        out.push(z as u8);
+    }
+
+    pub(super) fn string(out: &mut Vec<u8>, s: &str) {
+        long(out, s.len() as i64);
+        out.extend_from_slice(s.as_bytes());
+    }
+
+    /// `["null", "string"]`: the branch index, then the value.
+    fn optional_string(out: &mut Vec<u8>, s: Option<&str>) {
+        match s {
+            None => long(out, 0),
+            Some(s) => {
+                long(out, 1);
+                string(out, s);
+            }
+        }
+    }
+
+    /// One block with every entry (sorted by key, for stable output), then an empty block.
+    fn string_map(out: &mut Vec<u8>, map: &HashMap<String, String>) {
+        if !map.is_empty() {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort();
+            long(out, entries.len() as i64);
+            for (key, value) in entries {
+                string(out, key);
+                string(out, value);
+            }
+        }
+        long(out, 0);
+    }
+
+    fn timestamp(out: &mut Vec<u8>, at: &DateTime<Utc>) {
+        long(out, at.timestamp_micros());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AuthEvent, EventSeverity, EventType};
+
+    #[test]
+    fn avro_longs_are_zigzag_varints() {
+        let encoded: Vec<Vec<u8>> = [0, -1, 1, 63, -64, 64, 8192]
+            .into_iter()
+            .map(|n| {
+                let mut out = Vec::new();
+                avro::long(&mut out, n);
+                out
+            })
+            .collect();
+        assert_eq!(
+            encoded,
+            [
+                vec![0x00],
+                vec![0x01],
+                vec![0x02],
+                vec![0x7e],
+                vec![0x7f],
+                vec![0x80, 0x01],
+                vec![0x80, 0x80, 0x01],
+            ]
+        );
+    }
+
+    #[test]
+    fn encodings_carry_the_schema_id() {
+        let mut event = AuthEvent::new(
+            EventType::TokenCreated,
+            EventSeverity::Info,
+            None,
+            Some("app".to_string()),
+        );
+        event.id = "e1".to_string();
+        event.timestamp = DateTime::from_timestamp(1, 0).unwrap();
+        let mut envelope = EventEnvelope::from_current_span(event, "p");
+        envelope.traceparent = None;
+        envelope.tracestate = None;
+        envelope.correlation_id = "c".to_string();
+        envelope.produced_at = DateTime::from_timestamp(2, 0).unwrap();
+
+        let json = SchemaFormat::JsonSchema.encode(7, &envelope).unwrap();
+        assert_eq!(json[..5], [0, 0, 0, 0, 7]);
+        let decoded: EventEnvelope = serde_json::from_slice(&json[5..]).unwrap();
+        assert_eq!(decoded.event.id, "e1");
+
+        let avro = SchemaFormat::Avro.encode(258, &envelope).unwrap();
+        let mut expected = vec![0, 0, 0, 1, 2];
+        expected.extend_from_slice(&[4, b'e', b'1']);
+        expected.push(26);
+        expected.extend_from_slice(b"token_created");
+        expected.extend_from_slice(&[0x80, 0x89, 0x7a]); // 1_000_000 µs
+        expected.extend_from_slice(&[8, b'i', b'n', b'f', b'o']);
+        expected.extend_from_slice(&[0, 2, 6, b'a', b'p', b'p', 0, 0]);
+        expected.extend_from_slice(&[0, 0, 0, 2, b'c', 2, b'p']);
+        expected.extend_from_slice(&[0x80, 0x92, 0xf4, 0x01]); // 2_000_000 µs
+        expected.extend_from_slice(&[0, 0]);
+        assert_eq!(avro, expected);
+    }
+
+    #[test]
+    fn avro_schema_lists_every_envelope_field() {
+        let schema = envelope_avro_schema();
+        let fields: Vec<&str> = schema["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                "event",
+                "idempotency_key",
+                "traceparent",
+                "tracestate",
+                "correlation_id",
+                "producer",
+                "produced_at",
+                "attributes",
+                "signature"
+            ]
+        );
+    }
+}
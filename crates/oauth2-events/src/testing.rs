@@ -0,0 +1,99 @@
+//! Event bus fake for integration tests.
+
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+use crate::{EventBus, EventBusError, EventBusHandle, EventEnvelope, EventType};
+
+/// [`EventBus`] that keeps every published envelope, in publish order.
+///
+/// Core flows publish with [`EventBusHandle::publish_best_effort`], which runs on a spawned
+/// task; use [`RecordingEventBus::wait_for`] rather than reading right after the call.
+#[derive(Default)]
+pub struct RecordingEventBus {
+    state: Mutex<RecordingState>,
+    published: Notify,
+}
+
+#[derive(Default)]
+struct RecordingState {
+    events: Vec<EventEnvelope>,
+    failures: VecDeque<EventBusError>,
+}
+
+impl RecordingEventBus {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// A handle publishing to this bus, for actors and handlers.
+    pub fn handle(self: &Arc<Self>) -> EventBusHandle {
+        EventBusHandle::new(self.clone())
+    }
+
+    /// Reject the next publish with `error`; it is not recorded. Repeated calls queue up.
+    pub fn fail_next(&self, error: EventBusError) {
+        self.lock().failures.push_back(error);
+    }
+
+    /// Envelopes published so far.
+    pub fn events(&self) -> Vec<EventEnvelope> {
+        self.lock().events.clone()
+    }
+
+    /// Types of the events published so far.
+    pub fn event_types(&self) -> Vec<EventType> {
+        self.lock()
+            .events
+            .iter()
+            .map(|envelope| envelope.event.event_type.clone())
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.lock().events.clear();
+    }
+
+    /// Wait up to `timeout` until at least `count` events were published, then return
+    /// them all. Returns what was published so far if the timeout elapses first.
+    pub async fn wait_for(&self, count: usize, timeout: Duration) -> Vec<EventEnvelope> {
+        let wait = async {
+            loop {
+                // Registered before the check, so a publish in between is not missed.
+                let published = self.published.notified();
+                tokio::pin!(published);
+                published.as_mut().enable();
+                if self.lock().events.len() >= count {
+                    return;
+                }
+                published.await;
+            }
+        };
+        let _ = tokio::time::timeout(timeout, wait).await;
+        self.events()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, RecordingState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl EventBus for RecordingEventBus {
+    async fn publish(&self, envelope: EventEnvelope) -> Result<(), EventBusError> {
+        {
+            let mut state = self.lock();
+            if let Some(error) = state.failures.pop_front() {
+                return Err(error);
+            }
+            state.events.push(envelope);
+        }
+        self.published.notify_waiters();
+        Ok(())
+    }
+}
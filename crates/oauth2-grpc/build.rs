@@ -0,0 +1,9 @@
+fn main() {
+    // Respect an explicitly configured protoc; fall back to the vendored binary.
+    if std::env::var_os("PROTOC").is_none() {
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("vendored protoc");
+        std::env::set_var("PROTOC", protoc);
+    }
+    tonic_prost_build::compile_protos("proto/oauth2/v1/token.proto")
+        .expect("failed to compile protobuf definitions");
+}
@@ -0,0 +1,246 @@
+//! gRPC surface for the OAuth2 server.
+//!
+//! Serves the `oauth2.v1.TokenService` defined in `proto/oauth2/v1/token.proto` with
+//! tonic: client credentials issuance, bearer token validation, introspection and
+//! revocation, for internal services that prefer gRPC over HTTP form posts. It runs on
+//! the same [`OAuth2State`], and so the same `oauth2-ports` services, as `oauth2-axum`
+//! and the actix server, so all three behave alike:
+//!
+//! ```ignore
+//! let state = OAuth2State::new(storage, IssuerKeys::from_secret(secret), Metrics::new()?);
+//! tonic::transport::Server::builder()
+//!     .add_service(oauth2_grpc::service(state))
+//!     .serve("0.0.0.0:50051".parse()?)
+//!     .await?;
+//! ```
+
+use tonic::metadata::MetadataValue;
+use tonic::{Code, Request, Response, Status};
+
+use oauth2_axum::OAuth2State;
+use oauth2_core::{Client, IntrospectionResponse, OAuth2Error, RequestOrigin, TokenMetadata};
+use oauth2_ports::SecurityEvents;
+
+/// Generated protobuf messages, client and server.
+pub mod proto {
+    tonic::include_proto!("oauth2.v1");
+}
+
+use proto::token_service_server::{TokenService, TokenServiceServer};
+use proto::{
+    ClientCredentials, IntrospectTokenRequest, IntrospectTokenResponse, IssueTokenRequest,
+    IssueTokenResponse, RevokeTokenRequest, RevokeTokenResponse, ValidateTokenRequest,
+    ValidateTokenResponse,
+};
+
+/// The `TokenService` implementation.
+#[derive(Clone)]
+pub struct TokenGrpcService {
+    state: OAuth2State,
+}
+
+impl TokenGrpcService {
+    pub fn new(state: OAuth2State) -> Self {
+        Self { state }
+    }
+
+    /// Authenticate the calling client, reporting failures with the peer address and
+    /// user agent of `request`.
+    async fn authenticate<T>(
+        &self,
+        request: &Request<T>,
+        client: Option<ClientCredentials>,
+        method: &str,
+    ) -> Result<Client, Status> {
+        let client = client
+            .ok_or_else(|| OAuth2Error::invalid_client("Client authentication is required"))
+            .into_status()?;
+        let security = SecurityEvents::new(
+            self.state.event_bus().cloned(),
+            request.remote_addr().map(|addr| addr.ip().to_string()),
+            request
+                .metadata()
+                .get("user-agent")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string),
+        );
+        self.state
+            .authenticate_client(
+                &client.client_id,
+                &client.client_secret,
+                None,
+                &security,
+                method,
+            )
+            .await
+            .into_status()
+    }
+}
+
+/// A tonic service for [`TokenGrpcService`], ready for `Server::add_service`.
+pub fn service(state: OAuth2State) -> TokenServiceServer<TokenGrpcService> {
+    TokenServiceServer::new(TokenGrpcService::new(state))
+}
+
+/// Map an OAuth2 error to the closest gRPC status, keeping the OAuth2 code in the
+/// `oauth2-error` metadata entry.
+pub fn to_status(err: OAuth2Error) -> Status {
+    let code = match err.error.as_str() {
+        "invalid_client" | "invalid_token" => Code::Unauthenticated,
+        "unauthorized_client" | "insufficient_scope" | "access_denied" => Code::PermissionDenied,
+        "temporarily_unavailable" => Code::Unavailable,
+        "server_error" => Code::Internal,
+        _ => Code::InvalidArgument,
+    };
+    let message = err
+        .error_description
+        .clone()
+        .unwrap_or_else(|| err.error.clone());
+    let mut status = Status::new(code, message);
+    if let Ok(value) = MetadataValue::try_from(err.error.as_str()) {
+        status.metadata_mut().insert("oauth2-error", value);
+    }
+    status
+}
+
+// Lets handlers use `?` on OAuth2 results.
+trait IntoStatus<T> {
+    fn into_status(self) -> Result<T, Status>;
+}
+
+impl<T> IntoStatus<T> for Result<T, OAuth2Error> {
+    fn into_status(self) -> Result<T, Status> {
+        self.map_err(to_status)
+    }
+}
+
+#[tonic::async_trait]
+impl TokenService for TokenGrpcService {
+    async fn issue_token(
+        &self,
+        request: Request<IssueTokenRequest>,
+    ) -> Result<Response<IssueTokenResponse>, Status> {
+        let request = request.into_inner();
+        let mut metadata = TokenMetadata::default();
+        for (key, value) in request.metadata {
+            metadata.insert(key, value).into_status()?;
+        }
+        // There is no HTTP request to derive it from, so assertions are addressed to the
+        // configured issuer's token endpoint.
+        let token_endpoint = self
+            .state
+            .issuer_urls()
+            .token_endpoint(&RequestOrigin::default());
+
+        let token = self
+            .state
+            .client_credentials_grant(
+                &request.client_id,
+                oauth2_axum::ClientCredentials {
+                    client_secret: request.client_secret,
+                    client_assertion_type: request.client_assertion_type,
+                    client_assertion: request.client_assertion,
+                    scope: request.scope,
+                },
+                metadata,
+                &token_endpoint,
+                None,
+            )
+            .await
+            .into_status()?;
+
+        self.state.metrics().oauth_token_issued_total.inc();
+        Ok(Response::new(IssueTokenResponse {
+            access_token: token.access_token,
+            token_type: token.token_type,
+            expires_in: i64::from(token.expires_in),
+            scope: token.scope,
+        }))
+    }
+
+    async fn validate_token(
+        &self,
+        request: Request<ValidateTokenRequest>,
+    ) -> Result<Response<ValidateTokenResponse>, Status> {
+        let request = request.into_inner();
+        let (token, claims) = self
+            .state
+            .authenticate_bearer(request.token.trim(), None)
+            .await
+            .into_status()?;
+        if let Some(scope) = request.required_scope.as_deref() {
+            if !token.scope.split_whitespace().any(|s| s == scope) {
+                return Err(to_status(
+                    OAuth2Error::insufficient_scope(&format!("Token lacks the '{scope}' scope"))
+                        .with_required_scope(scope),
+                ));
+            }
+        }
+
+        Ok(Response::new(ValidateTokenResponse {
+            client_id: token.client_id,
+            subject: claims.sub,
+            scope: token.scope,
+            expires_at: token.expires_at.timestamp(),
+        }))
+    }
+
+    async fn introspect_token(
+        &self,
+        request: Request<IntrospectTokenRequest>,
+    ) -> Result<Response<IntrospectTokenResponse>, Status> {
+        let caller = self
+            .authenticate(
+                &request,
+                request.get_ref().client.clone(),
+                "/oauth2.v1.TokenService/IntrospectToken",
+            )
+            .await?;
+        let request = request.into_inner();
+        let response = self.state.introspect(&caller, &request.token, None).await;
+        Ok(Response::new(response.into()))
+    }
+
+    async fn revoke_token(
+        &self,
+        request: Request<RevokeTokenRequest>,
+    ) -> Result<Response<RevokeTokenResponse>, Status> {
+        let caller = self
+            .authenticate(
+                &request,
+                request.get_ref().client.clone(),
+                "/oauth2.v1.TokenService/RevokeToken",
+            )
+            .await?;
+        let request = request.into_inner();
+        self.state
+            .revoke(
+                &caller,
+                &request.token,
+                request.token_type_hint.as_deref(),
+                None,
+            )
+            .await
+            .into_status()?;
+        Ok(Response::new(RevokeTokenResponse {}))
+    }
+}
+
+impl From<IntrospectionResponse> for IntrospectTokenResponse {
+    fn from(response: IntrospectionResponse) -> Self {
+        Self {
+            active: response.active,
+            scope: response.scope,
+            client_id: response.client_id,
+            username: response.username,
+            token_type: response.token_type,
+            exp: response.exp,
+            iat: response.iat,
+            nbf: response.nbf,
+            sub: response.sub,
+            aud: response.aud,
+            iss: response.iss,
+            jti: response.jti,
+        }
+    }
+}
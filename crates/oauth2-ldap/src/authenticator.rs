@@ -0,0 +1,335 @@
+//! [`LdapAuthenticator`]: passwords checked by the directory, users provisioned locally.
+
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use oauth2_config::LdapConfig;
+use oauth2_core::{Claims, FederatedIdentity, OAuth2Error, TokenMetadata, User, REGISTERED_CLAIMS};
+use oauth2_ports::{ClaimsEnricher, DynStorage, LocalUserAuthenticator, UserAuthenticator};
+
+use ldap3::Scope;
+
+use crate::connection::{Connection, Endpoint, Entry};
+use crate::pool::Pool;
+
+/// Provider name of the federated identities linking local users to directory entries; the
+/// provider user id is the entry's DN.
+pub const PROVIDER: &str = "ldap";
+
+/// Checks passwords against an LDAP or Active Directory server.
+///
+/// A user's first successful login creates a local account (without a local password)
+/// linked to their directory entry, so tokens have a stable subject. Mapped directory
+/// attributes are added to every access token of linked users, read at issuance.
+pub struct LdapAuthenticator {
+    pool: Pool,
+    storage: DynStorage,
+    user_dn_template: Option<String>,
+    bind_dn: String,
+    bind_password: String,
+    base_dn: Option<String>,
+    user_filter: String,
+    email_attribute: String,
+    claims: BTreeMap<String, String>,
+    local: Option<LocalUserAuthenticator>,
+}
+
+/// What the directory said about a username and password.
+enum Verification {
+    /// No such user, so the local password may be checked instead.
+    Unknown,
+    Rejected,
+    Verified(Entry),
+}
+
+impl LdapAuthenticator {
+    pub fn from_config(config: &LdapConfig, storage: DynStorage) -> Result<Self, OAuth2Error> {
+        let invalid = |description: &str| {
+            OAuth2Error::new(
+                "invalid_configuration",
+                Some(&format!("Invalid LDAP configuration: {description}")),
+            )
+        };
+        match &config.user_dn_template {
+            Some(template) if !template.contains("{username}") => {
+                return Err(invalid("user_dn_template must contain {username}"))
+            }
+            None if config.base_dn.is_none() => {
+                return Err(invalid(
+                    "set user_dn_template, or base_dn to search for users",
+                ))
+            }
+            _ => {}
+        }
+        ldap3::parse_filter(user_filter(&config.user_filter, "user"))
+            .map_err(|_| invalid("user_filter is not a valid LDAP filter"))?;
+        if let Some(claim) = config
+            .claims
+            .keys()
+            .find(|claim| REGISTERED_CLAIMS.contains(&claim.as_str()))
+        {
+            return Err(invalid(&format!(
+                "claims.{claim} would overwrite a registered claim"
+            )));
+        }
+
+        let timeout = Duration::from_secs(config.timeout_secs.max(1));
+        let endpoint = Endpoint::new(&config.url, config.ca_path.as_deref(), timeout)?;
+        Ok(Self {
+            pool: Pool::new(endpoint, config.pool_size),
+            local: config
+                .local_fallback
+                .then(|| LocalUserAuthenticator::new(storage.clone())),
+            storage,
+            user_dn_template: config.user_dn_template.clone(),
+            bind_dn: config.bind_dn.clone().unwrap_or_default(),
+            bind_password: config.bind_password.clone().unwrap_or_default(),
+            base_dn: config.base_dn.clone(),
+            user_filter: config.user_filter.clone(),
+            email_attribute: config.email_attribute.clone(),
+            claims: config.claims.clone(),
+        })
+    }
+
+    /// Whether access tokens get claims from the directory.
+    pub fn maps_claims(&self) -> bool {
+        !self.claims.is_empty()
+    }
+
+    async fn verify(&self, username: &str, password: &str) -> Result<Verification, OAuth2Error> {
+        let mut connection = self.pool.get().await?;
+        match &self.user_dn_template {
+            Some(template) => {
+                let name = template.replace("{username}", &ldap3::dn_escape(username));
+                // A failed bind does not say whether the user exists.
+                if !connection.bind(&name, password).await? {
+                    return Ok(Verification::Unknown);
+                }
+                let entries = match &self.base_dn {
+                    Some(base) => self.find_user(&mut connection, base, username).await?,
+                    None => self.read_entry(&mut connection, &name).await?,
+                };
+                Ok(match <[Entry; 1]>::try_from(entries) {
+                    Ok([entry]) => Verification::Verified(entry),
+                    // Bound, but the entry cannot be read; the bind name identifies the user.
+                    Err(_) => Verification::Verified(Entry {
+                        dn: name,
+                        ..Entry::default()
+                    }),
+                })
+            }
+            None => {
+                self.bind_service(&mut connection).await?;
+                let base = self.base_dn.as_deref().unwrap_or_default();
+                let entries = self.find_user(&mut connection, base, username).await?;
+                let entry = match <[Entry; 1]>::try_from(entries) {
+                    Ok([entry]) => entry,
+                    Err(entries) if entries.is_empty() => return Ok(Verification::Unknown),
+                    Err(_) => {
+                        tracing::warn!(username, "user_filter matches several LDAP entries");
+                        return Ok(Verification::Rejected);
+                    }
+                };
+                if connection.bind(&entry.dn, password).await? {
+                    Ok(Verification::Verified(entry))
+                } else {
+                    Ok(Verification::Rejected)
+                }
+            }
+        }
+    }
+
+    /// Bind as the service account (anonymously without one), unless already bound so.
+    async fn bind_service(&self, connection: &mut Connection) -> Result<(), OAuth2Error> {
+        if connection.bound_as() == self.bind_dn {
+            return Ok(());
+        }
+        if connection.bind(&self.bind_dn, &self.bind_password).await? {
+            Ok(())
+        } else {
+            tracing::warn!(bind_dn = %self.bind_dn, "LDAP service account was rejected");
+            Err(OAuth2Error::temporarily_unavailable(
+                "The user directory is unavailable",
+            ))
+        }
+    }
+
+    async fn find_user(
+        &self,
+        connection: &mut Connection,
+        base: &str,
+        username: &str,
+    ) -> Result<Vec<Entry>, OAuth2Error> {
+        let filter = user_filter(&self.user_filter, username);
+        connection
+            .search(base, Scope::Subtree, &filter, &self.attributes(), 2)
+            .await
+    }
+
+    async fn read_entry(
+        &self,
+        connection: &mut Connection,
+        dn: &str,
+    ) -> Result<Vec<Entry>, OAuth2Error> {
+        connection
+            .search(dn, Scope::Base, "(objectClass=*)", &self.attributes(), 1)
+            .await
+    }
+
+    /// Attributes read from user entries: the email and the mapped claims.
+    fn attributes(&self) -> Vec<&str> {
+        let mut attributes: Vec<&str> = std::iter::once(self.email_attribute.as_str())
+            .chain(self.claims.values().map(String::as_str))
+            .collect();
+        attributes.sort_unstable();
+        attributes.dedup();
+        attributes
+    }
+
+    /// The local user linked to `entry`, created on first login. A local account that
+    /// already has the username is not taken over.
+    async fn local_user(&self, username: &str, entry: &Entry) -> Result<Option<User>, OAuth2Error> {
+        let email = entry.first(&self.email_attribute).unwrap_or_default();
+        if let Some(identity) = self
+            .storage
+            .get_federated_identity(PROVIDER, &entry.dn)
+            .await?
+        {
+            let Some(mut user) = self.storage.get_user(&identity.user_id).await? else {
+                return Ok(None);
+            };
+            if !email.is_empty() && user.email != email {
+                user.email = email.to_string();
+                user.email_verified = false;
+                user.updated_at = chrono::Utc::now();
+                self.storage.update_user(&user).await?;
+            }
+            return Ok(Some(user).filter(|user| user.enabled));
+        }
+
+        if self
+            .storage
+            .get_user_by_username(username, None)
+            .await?
+            .is_some()
+        {
+            tracing::warn!(
+                username,
+                dn = %entry.dn,
+                "LDAP user has the username of an existing local account; not signing in"
+            );
+            return Ok(None);
+        }
+        let user = User::new(username.to_string(), String::new(), email.to_string());
+        self.storage.save_user(&user).await?;
+        self.storage
+            .save_federated_identity(&FederatedIdentity::new(
+                PROVIDER.to_string(),
+                entry.dn.clone(),
+                user.id.clone(),
+            ))
+            .await?;
+        tracing::info!(user_id = %user.id, dn = %entry.dn, "Provisioned user from LDAP");
+        Ok(Some(user))
+    }
+
+    async fn fall_back(
+        &self,
+        username: &str,
+        password: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<User>, OAuth2Error> {
+        match &self.local {
+            Some(local) => local.authenticate(username, password, tenant_id).await,
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl UserAuthenticator for LdapAuthenticator {
+    fn backend_name(&self) -> &'static str {
+        PROVIDER
+    }
+
+    async fn authenticate(
+        &self,
+        username: &str,
+        password: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<User>, OAuth2Error> {
+        // The directory serves the default tenant only.
+        if tenant_id.is_some() {
+            return self.fall_back(username, password, tenant_id).await;
+        }
+        // An empty password would be an unauthenticated bind, which directories accept.
+        if username.is_empty() || password.is_empty() {
+            return Ok(None);
+        }
+        match self.verify(username, password).await? {
+            Verification::Verified(entry) => self.local_user(username, &entry).await,
+            Verification::Rejected => Ok(None),
+            Verification::Unknown => self.fall_back(username, password, tenant_id).await,
+        }
+    }
+}
+
+#[async_trait]
+impl ClaimsEnricher for LdapAuthenticator {
+    /// Add the mapped attributes of the subject's directory entry. Single values become
+    /// strings, several values arrays.
+    async fn enrich(
+        &self,
+        claims: &mut Claims,
+        _metadata: &mut TokenMetadata,
+    ) -> Result<(), OAuth2Error> {
+        if self.claims.is_empty() {
+            return Ok(());
+        }
+        let identities = self.storage.list_federated_identities(&claims.sub).await?;
+        let Some(identity) = identities.iter().find(|i| i.provider == PROVIDER) else {
+            return Ok(());
+        };
+
+        let mut connection = self.pool.get().await?;
+        self.bind_service(&mut connection).await?;
+        let Some(entry) = self
+            .read_entry(&mut connection, &identity.provider_user_id)
+            .await?
+            .pop()
+        else {
+            return Ok(());
+        };
+        for (claim, attribute) in &self.claims {
+            match entry.values(attribute) {
+                [] => {}
+                [value] => claims.set_claim(claim, value.clone())?,
+                values => claims.set_claim(claim, values.to_vec())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `template` with every `{username}` replaced by the escaped `username`.
+fn user_filter(template: &str, username: &str) -> String {
+    template.replace("{username}", &ldap3::ldap_escape(username))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usernames_cannot_change_the_bind_dn_or_filter() {
+        assert_eq!(ldap3::dn_escape("dana"), "dana");
+        assert_eq!(ldap3::dn_escape("x,ou=admins"), "x\\2cou\\3dadmins");
+        assert_eq!(ldap3::dn_escape(" #a b "), "\\20#a b\\20");
+        assert_eq!(ldap3::dn_escape("#x"), "\\23x");
+        assert_eq!(
+            user_filter("(&(objectClass=person)(uid={username}))", "*)(uid=*"),
+            "(&(objectClass=person)(uid=\\2a\\29\\28uid=\\2a))"
+        );
+    }
+}
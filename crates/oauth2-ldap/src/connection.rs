@@ -0,0 +1,266 @@
+//! Connections to the directory, plain (`ldap://`) or TLS (`ldaps://`), opened with
+//! `ldap3` and used for one operation at a time.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ldap3::{Ldap, LdapConnAsync, LdapConnSettings, LdapResult, Scope, SearchEntry, SearchOptions};
+use rustls::{ClientConfig, OwnedTrustAnchor, RootCertStore};
+
+use oauth2_core::OAuth2Error;
+
+const SUCCESS: u32 = 0;
+const SIZE_LIMIT_EXCEEDED: u32 = 4;
+const NO_SUCH_OBJECT: u32 = 32;
+const INVALID_CREDENTIALS: u32 = 49;
+
+/// Where the directory is and how to reach it.
+#[derive(Clone)]
+pub struct Endpoint {
+    url: String,
+    host: String,
+    settings: LdapConnSettings,
+    timeout: Duration,
+}
+
+impl std::fmt::Debug for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Endpoint")
+            .field("url", &self.url)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Endpoint {
+    /// `url` is `ldap://host[:port]` or `ldaps://host[:port]`; `ca_path` is a PEM file of
+    /// CA certificates trusted for `ldaps`, instead of the public web roots.
+    pub fn new(url: &str, ca_path: Option<&str>, timeout: Duration) -> Result<Self, OAuth2Error> {
+        let parsed = url::Url::parse(url).map_err(|e| invalid_config(&format!("url: {e}")))?;
+        let mut settings = LdapConnSettings::new().set_conn_timeout(timeout);
+        match parsed.scheme() {
+            "ldap" => {}
+            "ldaps" => settings = settings.set_config(tls_config(ca_path)?),
+            scheme => {
+                return Err(invalid_config(&format!(
+                    "url scheme must be ldap or ldaps, not {scheme}"
+                )))
+            }
+        }
+        let host = parsed
+            .host_str()
+            .filter(|host| !host.is_empty())
+            .ok_or_else(|| invalid_config("url has no host"))?;
+        Ok(Self {
+            url: url.to_string(),
+            host: host.to_string(),
+            settings,
+            timeout,
+        })
+    }
+
+    pub async fn connect(&self) -> Result<Connection, OAuth2Error> {
+        let connect = LdapConnAsync::with_settings(self.settings.clone(), &self.url);
+        let (driver, ldap) = tokio::time::timeout(self.timeout, connect)
+            .await
+            .map_err(|_| unavailable(&format!("connecting to {} timed out", self.host)))?
+            .map_err(|e| unavailable(&format!("cannot connect to {}: {e}", self.host)))?;
+        ldap3::drive!(driver);
+        Ok(Connection {
+            ldap,
+            timeout: self.timeout,
+            bound_as: String::new(),
+            broken: false,
+        })
+    }
+}
+
+/// A directory entry from a search. Attribute names are lowercased, since LDAP compares
+/// them case-insensitively.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Entry {
+    pub dn: String,
+    pub attributes: BTreeMap<String, Vec<String>>,
+}
+
+impl Entry {
+    pub fn values(&self, attribute: &str) -> &[String] {
+        self.attributes
+            .get(&attribute.to_ascii_lowercase())
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn first(&self, attribute: &str) -> Option<&str> {
+        self.values(attribute).first().map(String::as_str)
+    }
+}
+
+impl From<SearchEntry> for Entry {
+    fn from(entry: SearchEntry) -> Self {
+        let mut attributes: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (name, values) in entry.attrs {
+            attributes
+                .entry(name.to_ascii_lowercase())
+                .or_default()
+                .extend(values);
+        }
+        Self {
+            dn: entry.dn,
+            attributes,
+        }
+    }
+}
+
+/// An open connection. Any transport or protocol failure marks it broken; it must not be
+/// used again.
+pub struct Connection {
+    ldap: Ldap,
+    timeout: Duration,
+    bound_as: String,
+    broken: bool,
+}
+
+impl std::fmt::Debug for Connection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Connection")
+            .field("bound_as", &self.bound_as)
+            .field("broken", &self.broken)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Connection {
+    /// DN of the last successful bind; empty when anonymous.
+    pub fn bound_as(&self) -> &str {
+        &self.bound_as
+    }
+
+    pub fn is_broken(&mut self) -> bool {
+        self.broken || self.ldap.is_closed()
+    }
+
+    /// Simple bind. `Ok(false)` when the directory rejects the credentials, after which
+    /// the connection is anonymous.
+    pub async fn bind(&mut self, name: &str, password: &str) -> Result<bool, OAuth2Error> {
+        let bind = self.ldap.simple_bind(name, password);
+        let result = run(&mut self.broken, self.timeout, bind).await?;
+        match result.rc {
+            SUCCESS => {
+                self.bound_as = name.to_string();
+                Ok(true)
+            }
+            INVALID_CREDENTIALS => {
+                self.bound_as.clear();
+                Ok(false)
+            }
+            _ => Err(failed("bind", &result)),
+        }
+    }
+
+    /// The entries matching `filter` (RFC 4515 string form). A missing base DN finds
+    /// nothing; when more entries match than `size_limit` allows, those returned so far
+    /// are kept.
+    pub async fn search(
+        &mut self,
+        base: &str,
+        scope: Scope,
+        filter: &str,
+        attributes: &[&str],
+        size_limit: i32,
+    ) -> Result<Vec<Entry>, OAuth2Error> {
+        let options = SearchOptions::new()
+            .sizelimit(size_limit)
+            .timelimit(self.timeout.as_secs().try_into().unwrap_or(i32::MAX));
+        let search =
+            self.ldap
+                .with_search_options(options)
+                .search(base, scope, filter, attributes.to_vec());
+        let ldap3::SearchResult(entries, result) =
+            run(&mut self.broken, self.timeout, search).await?;
+        match result.rc {
+            SUCCESS | SIZE_LIMIT_EXCEEDED => Ok(entries
+                .into_iter()
+                .map(|entry| SearchEntry::construct(entry).into())
+                .collect()),
+            NO_SUCH_OBJECT => Ok(Vec::new()),
+            _ => Err(failed("search", &result)),
+        }
+    }
+
+    /// Tell the server the connection is closing.
+    pub async fn unbind(mut self) {
+        let _ = tokio::time::timeout(self.timeout, self.ldap.unbind()).await;
+    }
+}
+
+/// Run one operation within `timeout`. `broken` stays set if the operation fails or its
+/// future is dropped midway, since a response may be unread.
+async fn run<T>(
+    broken: &mut bool,
+    timeout: Duration,
+    operation: impl Future<Output = ldap3::result::Result<T>>,
+) -> Result<T, OAuth2Error> {
+    if *broken {
+        return Err(unavailable("connection is broken"));
+    }
+    *broken = true;
+    match tokio::time::timeout(timeout, operation).await {
+        Ok(Ok(result)) => {
+            *broken = false;
+            Ok(result)
+        }
+        Ok(Err(e)) => Err(unavailable(&e.to_string())),
+        Err(_) => Err(unavailable("operation timed out")),
+    }
+}
+
+fn failed(operation: &str, result: &LdapResult) -> OAuth2Error {
+    unavailable(&format!(
+        "{operation} failed with result code {}: {}",
+        result.rc, result.text
+    ))
+}
+
+fn tls_config(ca_path: Option<&str>) -> Result<Arc<ClientConfig>, OAuth2Error> {
+    let mut roots = RootCertStore::empty();
+    match ca_path {
+        Some(path) => {
+            let certificates = std::fs::read(path)
+                .and_then(|pem| rustls_pemfile::certs(&mut pem.as_slice()))
+                .map_err(|e| invalid_config(&format!("cannot read ca_path {path}: {e}")))?;
+            let (added, _) = roots.add_parsable_certificates(&certificates);
+            if added == 0 {
+                return Err(invalid_config(&format!(
+                    "no CA certificates in ca_path {path}"
+                )));
+            }
+        }
+        None => roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+            OwnedTrustAnchor::from_subject_spki_name_constraints(
+                anchor.subject,
+                anchor.spki,
+                anchor.name_constraints,
+            )
+        })),
+    }
+    let config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+fn invalid_config(description: &str) -> OAuth2Error {
+    OAuth2Error::new(
+        "invalid_configuration",
+        Some(&format!("Invalid LDAP configuration: {description}")),
+    )
+}
+
+/// The directory could not be used. Details are logged, not returned to clients.
+fn unavailable(detail: &str) -> OAuth2Error {
+    tracing::warn!(detail, "LDAP directory unavailable");
+    OAuth2Error::temporarily_unavailable("The user directory is unavailable")
+}
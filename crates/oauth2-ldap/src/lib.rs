@@ -0,0 +1,17 @@
+//! LDAP and Active Directory user authentication.
+//!
+//! [`LdapAuthenticator`] implements [`UserAuthenticator`](oauth2_ports::UserAuthenticator)
+//! so the password grant (and the login page that uses it) checks passwords with a simple
+//! bind against the directory: either directly as the user (bind-as-user) or as the DN a
+//! service account finds for the username (search+bind). It also implements
+//! [`ClaimsEnricher`](oauth2_ports::ClaimsEnricher) to copy directory attributes into
+//! access tokens.
+//!
+//! The protocol is `ldap3`'s; this crate only uses simple binds and searches over pooled
+//! `ldap://` or `ldaps://` connections. StartTLS and SASL are not supported.
+
+pub mod authenticator;
+pub mod connection;
+pub mod pool;
+
+pub use authenticator::{LdapAuthenticator, PROVIDER};
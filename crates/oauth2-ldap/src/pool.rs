@@ -0,0 +1,106 @@
+//! A bounded pool of directory connections, reused across logins.
+
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use oauth2_core::OAuth2Error;
+
+use crate::connection::{Connection, Endpoint};
+
+/// Idle connections older than this are closed rather than reused, before directories
+/// drop them on their own.
+const MAX_IDLE: Duration = Duration::from_secs(60);
+
+type Idle = Arc<Mutex<Vec<(Connection, Instant)>>>;
+
+/// At most `size` connections exist at once; idle ones are kept for the next checkout.
+/// Broken connections are dropped instead of returned.
+#[derive(Debug, Clone)]
+pub struct Pool {
+    endpoint: Endpoint,
+    idle: Idle,
+    permits: Arc<Semaphore>,
+}
+
+impl Pool {
+    pub fn new(endpoint: Endpoint, size: usize) -> Self {
+        Self {
+            endpoint,
+            idle: Arc::new(Mutex::new(Vec::new())),
+            permits: Arc::new(Semaphore::new(size.max(1))),
+        }
+    }
+
+    /// An idle connection, or a new one once fewer than `size` are in use.
+    pub async fn get(&self) -> Result<PooledConnection, OAuth2Error> {
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| OAuth2Error::temporarily_unavailable("LDAP pool closed"))?;
+        let (connection, stale) = {
+            let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+            // Returned connections are pushed, so the oldest are at the front.
+            let stale = idle
+                .iter()
+                .take_while(|(_, since)| since.elapsed() >= MAX_IDLE)
+                .count();
+            let stale: Vec<Connection> = idle.drain(..stale).map(|(c, _)| c).collect();
+            (idle.pop().map(|(c, _)| c), stale)
+        };
+        for connection in stale {
+            connection.unbind().await;
+        }
+        let connection = match connection {
+            Some(connection) => connection,
+            None => self.endpoint.connect().await?,
+        };
+        Ok(PooledConnection {
+            connection: Some(connection),
+            idle: self.idle.clone(),
+            _permit: permit,
+        })
+    }
+}
+
+/// A checked-out connection, returned to the pool when dropped.
+pub struct PooledConnection {
+    connection: Option<Connection>,
+    idle: Idle,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Deref for PooledConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.connection
+            .as_ref()
+            .expect("connection present until drop")
+    }
+}
+
+impl DerefMut for PooledConnection {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.connection
+            .as_mut()
+            .expect("connection present until drop")
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let Some(mut connection) = self.connection.take() {
+            if !connection.is_broken() {
+                self.idle
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push((connection, Instant::now()));
+            }
+        }
+    }
+}
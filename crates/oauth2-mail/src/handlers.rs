@@ -0,0 +1,278 @@
+use actix_session::{Session, SessionInsertError};
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use serde::Deserialize;
+
+use oauth2_core::{AuditAction, AuditRecord, OAuth2Error, MIN_PASSWORD_LENGTH};
+use oauth2_ports::{record_audit, DynAuditSink};
+
+use crate::service::MailService;
+
+#[derive(Deserialize)]
+pub struct EmailForm {
+    email: String,
+}
+
+#[derive(Deserialize)]
+pub struct TokenQuery {
+    token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordForm {
+    token: String,
+    password: String,
+}
+
+#[derive(Deserialize)]
+pub struct ReturnToQuery {
+    return_to: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct MagicLinkForm {
+    email: String,
+    return_to: Option<String>,
+}
+
+/// Register the mail routes under `/auth`; before the `/auth` scope, which would otherwise
+/// take these paths.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/auth/forgot-password")
+            .route(web::get().to(forgot_password_page))
+            .route(web::post().to(forgot_password)),
+    )
+    .service(
+        web::resource("/auth/reset-password")
+            .route(web::get().to(reset_password_page))
+            .route(web::post().to(reset_password)),
+    )
+    .service(
+        web::resource("/auth/verify-email")
+            .route(web::get().to(verify_email))
+            .route(web::post().to(resend_verification)),
+    );
+}
+
+/// Register the magic link routes; like [`configure`], before the `/auth` scope.
+pub fn configure_magic_link(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/auth/magic-link")
+            .route(web::get().to(magic_link_page))
+            .route(web::post().to(request_magic_link)),
+    )
+    .service(
+        web::resource("/auth/magic-link/sign-in")
+            .route(web::get().to(magic_link_sign_in_page))
+            .route(web::post().to(magic_link_sign_in)),
+    );
+}
+
+/// Ask for the address to send a password reset link to.
+pub async fn forgot_password_page() -> HttpResponse {
+    page(
+        "Forgot Password",
+        r#"<h1>Forgot your password?</h1>
+                <p>Enter the email address of your account and we will send you a link to choose a new password.</p>
+                <form method="post" action="/auth/forgot-password">
+                    <input type="email" name="email" placeholder="Email" required autofocus>
+                    <button type="submit">Send reset link</button>
+                </form>
+                <a href="/auth/login">Back to sign in</a>"#,
+    )
+}
+
+/// Mail a reset link. The response is the same whether or not the address is known.
+pub async fn forgot_password(
+    form: web::Form<EmailForm>,
+    mail: web::Data<MailService>,
+) -> Result<HttpResponse, OAuth2Error> {
+    mail.request_password_reset(&form.email)?;
+    Ok(page(
+        "Check Your Email",
+        r#"<h1>Check your email</h1>
+                <p>If an account uses that address, we sent it a link to reset the password.</p>
+                <a href="/auth/login">Back to sign in</a>"#,
+    ))
+}
+
+/// Ask for a new password; the link's token is posted with it.
+pub async fn reset_password_page(query: web::Query<TokenQuery>) -> HttpResponse {
+    page(
+        "Reset Password",
+        &format!(
+            r#"<h1>Choose a new password</h1>
+                <form method="post" action="/auth/reset-password">
+                    <input type="hidden" name="token" value="{token}">
+                    <input type="password" name="password" placeholder="New password" minlength="{MIN_PASSWORD_LENGTH}" required autofocus>
+                    <button type="submit">Set password</button>
+                </form>"#,
+            token = escape_html(&query.token),
+        ),
+    )
+}
+
+/// Set the new password; the link can only be used once.
+pub async fn reset_password(
+    form: web::Form<ResetPasswordForm>,
+    mail: web::Data<MailService>,
+) -> Result<HttpResponse, OAuth2Error> {
+    mail.reset_password(&form.token, &form.password).await?;
+    Ok(page(
+        "Password Changed",
+        r#"<h1>Password changed</h1>
+                <p>Sign in with your new password.</p>
+                <a href="/auth/login">Sign in</a>"#,
+    ))
+}
+
+/// Verification link target: mark the address verified.
+pub async fn verify_email(
+    query: web::Query<TokenQuery>,
+    mail: web::Data<MailService>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let user = mail.verify_email(&query.token).await?;
+    Ok(page(
+        "Email Verified",
+        &format!(
+            r#"<h1>Email verified</h1>
+                <p>Thanks, {email} is confirmed.</p>
+                <a href="/auth/login">Continue</a>"#,
+            email = escape_html(&user.email),
+        ),
+    ))
+}
+
+/// Send a new verification link. The response is the same whether or not the address is
+/// known or already verified.
+pub async fn resend_verification(
+    form: web::Form<EmailForm>,
+    mail: web::Data<MailService>,
+) -> Result<HttpResponse, OAuth2Error> {
+    mail.resend_verification(&form.email)?;
+    Ok(page(
+        "Check Your Email",
+        r#"<h1>Check your email</h1>
+                <p>If an account uses that address and has not confirmed it yet, we sent it a new verification link.</p>
+                <a href="/auth/login">Back to sign in</a>"#,
+    ))
+}
+
+/// Ask for the address to send a sign-in link to. `return_to` is where the user continues
+/// once signed in, e.g. a pending `/oauth/authorize` request.
+pub async fn magic_link_page(query: web::Query<ReturnToQuery>) -> HttpResponse {
+    page(
+        "Sign In With Email",
+        &format!(
+            r#"<h1>Sign in with email</h1>
+                <p>Enter the email address of your account and we will send you a link that signs you in.</p>
+                <form method="post" action="/auth/magic-link">
+                    <input type="email" name="email" placeholder="Email" required autofocus>
+                    <input type="hidden" name="return_to" value="{return_to}">
+                    <button type="submit">Send sign-in link</button>
+                </form>
+                <a href="/auth/login">Sign in with a password</a>"#,
+            return_to = escape_html(query.return_to.as_deref().unwrap_or_default()),
+        ),
+    )
+}
+
+/// Mail a sign-in link. The response is the same whether or not the address is known;
+/// too many requests for one address get `429`.
+pub async fn request_magic_link(
+    req: HttpRequest,
+    form: web::Form<MagicLinkForm>,
+    mail: web::Data<MailService>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let return_to = form.return_to.as_deref().filter(|path| !path.is_empty());
+    let requested = mail.request_magic_link(&form.email, return_to).await;
+    if matches!(&requested, Err(e) if e.error == "rate_limit_exceeded") {
+        mail.publish_rate_limited("magic_link", &form.email, &req);
+    }
+    requested?;
+    Ok(page(
+        "Check Your Email",
+        r#"<h1>Check your email</h1>
+                <p>If an account uses that address, we sent it a link to sign in.</p>
+                <a href="/auth/login">Back to sign in</a>"#,
+    ))
+}
+
+/// Sign-in link target. Signing in takes a click, so mail scanners that open links do not
+/// use them up.
+pub async fn magic_link_sign_in_page(query: web::Query<TokenQuery>) -> HttpResponse {
+    page(
+        "Sign In",
+        &format!(
+            r#"<h1>Sign in</h1>
+                <form method="post" action="/auth/magic-link/sign-in">
+                    <input type="hidden" name="token" value="{token}">
+                    <button type="submit" autofocus>Continue</button>
+                </form>"#,
+            token = escape_html(&query.token),
+        ),
+    )
+}
+
+/// Start a session for the link's user and continue where they asked for the link, or at
+/// the success page.
+pub async fn magic_link_sign_in(
+    req: HttpRequest,
+    form: web::Form<TokenQuery>,
+    mail: web::Data<MailService>,
+    session: Session,
+    audit: Option<web::Data<DynAuditSink>>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let signed_in = mail.sign_in_with_magic_link(&form.token).await;
+    let mut record = AuditRecord::from_result(AuditAction::Login, &signed_in)
+        .with_target("magic_link")
+        .with_request(&req);
+    if let Ok((user, _)) = &signed_in {
+        record = record.with_actor(user.id.clone());
+    }
+    record_audit(audit.as_ref().map(|audit| audit.get_ref()), record).await;
+    let (user, return_to) = signed_in?;
+
+    let session_error =
+        |e: SessionInsertError| OAuth2Error::new("session_error", Some(&e.to_string()));
+    session.renew();
+    session.insert("user_id", &user.id).map_err(session_error)?;
+    session
+        .insert("authenticated", true)
+        .map_err(session_error)?;
+
+    Ok(HttpResponse::Found()
+        .append_header(("Location", return_to.as_deref().unwrap_or("/auth/success")))
+        .finish())
+}
+
+fn page(title: &str, content: &str) -> HttpResponse {
+    let html = format!(
+        r#"
+        <!DOCTYPE html>
+        <html>
+        <head>
+            <title>{title}</title>
+            <link rel="stylesheet" href="/static/css/admin.css">
+        </head>
+        <body>
+            <div class="container">
+                {content}
+            </div>
+        </body>
+        </html>
+        "#
+    );
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(html)
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#x27;")
+}
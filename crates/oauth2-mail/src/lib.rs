@@ -0,0 +1,22 @@
+//! Outgoing mail: email verification, password reset and magic link sign-in.
+//!
+//! Users ask for a reset link at `/auth/forgot-password` and choose a new password at
+//! `/auth/reset-password`; verification links land on `/auth/verify-email`. With
+//! `mail.magic_link`, `/auth/magic-link` mails a link that signs the user in. Links carry a
+//! random single-use token of which only a hash is stored ([`oauth2_core::EmailToken`]), and
+//! stop working once they expire or the user's address changes.
+//!
+//! Mail goes through a [`Mailer`]: `SmtpMailer` with the `smtp` feature, [`LogMailer`]
+//! for development, or one of your own.
+
+pub mod handlers;
+pub mod mailer;
+pub mod rate_limit;
+pub mod service;
+#[cfg(feature = "smtp")]
+pub mod smtp;
+
+pub use mailer::{DynMailer, Email, LogMailer, Mailer, MemoryMailer};
+pub use service::MailService;
+#[cfg(feature = "smtp")]
+pub use smtp::SmtpMailer;
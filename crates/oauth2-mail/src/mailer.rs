@@ -0,0 +1,71 @@
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+
+use oauth2_core::OAuth2Error;
+
+/// A plain text message to one recipient.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivers mail, e.g. through an SMTP relay or a provider's HTTP API.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    /// Fails when the message could not be handed over for delivery.
+    async fn send(&self, email: Email) -> Result<(), OAuth2Error>;
+}
+
+pub type DynMailer = Arc<dyn Mailer>;
+
+/// Logs messages instead of sending them, for development without a relay. The log
+/// contains the links, so never use it where others can read the logs.
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: Email) -> Result<(), OAuth2Error> {
+        tracing::info!(
+            to = %email.to,
+            subject = %email.subject,
+            body = %email.body,
+            "Mail not sent (no mail.smtp relay configured)"
+        );
+        Ok(())
+    }
+}
+
+/// Keeps sent messages in memory, for tests.
+#[derive(Default)]
+pub struct MemoryMailer {
+    sent: Mutex<Vec<Email>>,
+}
+
+impl MemoryMailer {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Messages sent so far, oldest first.
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// The most recent message sent to `to`.
+    pub fn last_to(&self, to: &str) -> Option<Email> {
+        self.sent().into_iter().rev().find(|email| email.to == to)
+    }
+}
+
+#[async_trait]
+impl Mailer for MemoryMailer {
+    async fn send(&self, email: Email) -> Result<(), OAuth2Error> {
+        self.sent
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(email);
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Per-address quota for mailed links, counted in fixed one-hour windows.
+///
+/// Counters live in memory, so each replica enforces the quota separately.
+#[derive(Clone)]
+pub struct AddressRateLimiter {
+    limit: u32,
+    window: Duration,
+    windows: Arc<Mutex<HashMap<String, (Instant, u32)>>>,
+}
+
+impl AddressRateLimiter {
+    pub fn per_hour(limit: u32) -> Self {
+        Self {
+            limit,
+            window: Duration::from_secs(60 * 60),
+            windows: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Count a request for `email`, ignoring case. Over quota, returns how long until the
+    /// window resets.
+    pub fn check(&self, email: &str) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        if windows.len() >= 10_000 {
+            windows.retain(|_, (started, _)| now.duration_since(*started) < self.window);
+        }
+
+        let (started, count) = windows
+            .entry(email.trim().to_ascii_lowercase())
+            .or_insert((now, 0));
+        if now.duration_since(*started) >= self.window {
+            *started = now;
+            *count = 0;
+        }
+        if *count >= self.limit {
+            return Err(self.window - now.duration_since(*started));
+        }
+        *count += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,427 @@
+use actix_web::HttpRequest;
+use chrono::Utc;
+
+use oauth2_config::MailConfig;
+use oauth2_core::{
+    hash_email_token, hash_password, validate_password, EmailToken, EmailTokenPurpose, IssuerUrls,
+    OAuth2Error, TrustedProxies, User,
+};
+use oauth2_events::{AuthEvent, EventBusHandle, EventEnvelope, EventSeverity, EventType};
+use oauth2_ports::{DynStorage, DynUserSessionStore, PageRequest, UserListQuery};
+
+use crate::mailer::{DynMailer, Email};
+use crate::rate_limit::AddressRateLimiter;
+
+/// Mails single-use links for email verification, password reset and magic link sign-in,
+/// and redeems them.
+#[derive(Clone)]
+pub struct MailService {
+    storage: DynStorage,
+    mailer: DynMailer,
+    verification_ttl_secs: i64,
+    password_reset_ttl_secs: i64,
+    magic_link_enabled: bool,
+    magic_link_ttl_secs: i64,
+    magic_link_limiter: AddressRateLimiter,
+    /// Base URL of the links; nothing is mailed without it.
+    issuer: Option<String>,
+    sessions: Option<DynUserSessionStore>,
+    event_bus: Option<EventBusHandle>,
+}
+
+impl MailService {
+    pub fn new(config: &MailConfig, storage: DynStorage, mailer: DynMailer) -> Self {
+        Self {
+            storage,
+            mailer,
+            verification_ttl_secs: config.verification_ttl_secs as i64,
+            password_reset_ttl_secs: config.password_reset_ttl_secs as i64,
+            magic_link_enabled: config.magic_link.enabled,
+            magic_link_ttl_secs: config.magic_link.ttl_secs as i64,
+            magic_link_limiter: AddressRateLimiter::per_hour(config.magic_link.max_per_hour),
+            issuer: None,
+            sessions: None,
+            event_bus: None,
+        }
+    }
+
+    /// Build links on `issuer` (`server.issuer`). Without it no mail is sent: links built
+    /// from the request's `Host` header would let anyone point a reset link at their own
+    /// server.
+    pub fn with_issuer(mut self, issuer: &str) -> Self {
+        self.issuer = IssuerUrls::new(Some(issuer.to_string()))
+            .configured_issuer()
+            .map(str::to_string);
+        self
+    }
+
+    /// Sign users out of the sessions in `sessions` when they reset their password.
+    pub fn with_session_store(mut self, sessions: DynUserSessionStore) -> Self {
+        self.sessions = Some(sessions);
+        self
+    }
+
+    pub fn with_event_bus(mut self, event_bus: EventBusHandle) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Whether `mail.magic_link` sign-in is on.
+    pub fn magic_link_enabled(&self) -> bool {
+        self.magic_link_enabled
+    }
+
+    /// Mail `user` a link to verify their address.
+    pub async fn send_verification(&self, user: &User) -> Result<(), OAuth2Error> {
+        let link = self.link("/auth/verify-email")?;
+        let (token, record) = EmailToken::issue(
+            user,
+            EmailTokenPurpose::VerifyEmail,
+            self.verification_ttl_secs,
+        );
+        self.storage.save_email_token(&record).await?;
+        self.mailer
+            .send(Email {
+                to: user.email.clone(),
+                subject: "Verify your email address".to_string(),
+                body: format!(
+                    "Hello {},\n\nConfirm that this is your email address by opening:\n\n\
+                     {link}?token={token}\n\nThe link expires in {}.\n",
+                    user.username,
+                    describe_ttl(self.verification_ttl_secs)
+                ),
+            })
+            .await?;
+
+        self.publish(user_event(EventType::EmailVerificationSent, user));
+        Ok(())
+    }
+
+    /// Mail a verification link to the users with address `email` that have not verified
+    /// it yet. Like [`request_password_reset`](Self::request_password_reset), it returns
+    /// before anything is sent.
+    pub fn resend_verification(&self, email: &str) -> Result<(), OAuth2Error> {
+        self.link("/auth/verify-email")?;
+        let service = self.clone();
+        let email = email.to_string();
+        actix_web::rt::spawn(async move {
+            if let Err(e) = service.send_verifications(&email).await {
+                tracing::warn!(error = %e, "Failed to send verification mail");
+            }
+        });
+        Ok(())
+    }
+
+    async fn send_verifications(&self, email: &str) -> Result<(), OAuth2Error> {
+        for user in self.users_by_email(email).await? {
+            if !user.email_verified {
+                self.send_verification(&user).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Mark the address a verification link was sent to as verified.
+    pub async fn verify_email(&self, token: &str) -> Result<User, OAuth2Error> {
+        let (record, mut user) = self.redeem(token, EmailTokenPurpose::VerifyEmail).await?;
+        if !user.email_verified {
+            user.email_verified = true;
+            user.updated_at = Utc::now();
+            self.storage.update_user(&user).await?;
+        }
+        self.storage
+            .delete_email_tokens(&user.id, record.purpose)
+            .await?;
+
+        self.publish(user_event(EventType::EmailVerified, &user));
+        Ok(user)
+    }
+
+    /// Mail a reset link to the enabled users with address `email`. The mail is sent in
+    /// the background, so neither the result nor the response time tells callers whether
+    /// the address has an account.
+    pub fn request_password_reset(&self, email: &str) -> Result<(), OAuth2Error> {
+        let link = self.link("/auth/reset-password")?;
+        let service = self.clone();
+        let email = email.to_string();
+        actix_web::rt::spawn(async move {
+            if let Err(e) = service.send_password_resets(&email, &link).await {
+                tracing::warn!(error = %e, "Failed to send password reset mail");
+            }
+        });
+        Ok(())
+    }
+
+    async fn send_password_resets(&self, email: &str, link: &str) -> Result<(), OAuth2Error> {
+        for user in self.users_by_email(email).await? {
+            let (token, record) = EmailToken::issue(
+                &user,
+                EmailTokenPurpose::ResetPassword,
+                self.password_reset_ttl_secs,
+            );
+            self.storage.save_email_token(&record).await?;
+            self.mailer
+                .send(Email {
+                    to: user.email.clone(),
+                    subject: "Reset your password".to_string(),
+                    body: format!(
+                        "Hello {},\n\nSomeone asked to reset the password of your account. \
+                         Choose a new password at:\n\n{link}?token={token}\n\n\
+                         The link expires in {}. If you did not ask for this, ignore this \
+                         message; your password stays the same.\n",
+                        user.username,
+                        describe_ttl(self.password_reset_ttl_secs)
+                    ),
+                })
+                .await?;
+
+            self.publish(user_event(EventType::PasswordResetRequested, &user));
+        }
+        Ok(())
+    }
+
+    /// Set the password of the user a reset link was sent to. Other reset links sent to
+    /// them stop working, their address counts as verified, and they are signed out
+    /// everywhere: their sessions end and their tokens, refresh tokens included, are
+    /// revoked.
+    pub async fn reset_password(&self, token: &str, password: &str) -> Result<User, OAuth2Error> {
+        // Before the token is used up, so the user can try another password.
+        validate_password(password)?;
+        let (record, mut user) = self.redeem(token, EmailTokenPurpose::ResetPassword).await?;
+        user.password_hash = hash_password(password)?;
+        user.email_verified = true;
+        user.updated_at = Utc::now();
+        self.storage.update_user(&user).await?;
+        self.storage
+            .delete_email_tokens(&user.id, record.purpose)
+            .await?;
+        self.sign_out_everywhere(&user).await?;
+
+        self.publish(user_event(EventType::PasswordResetCompleted, &user));
+        Ok(user)
+    }
+
+    /// End `user`'s sessions and revoke every grant they hold, so whoever knew the old
+    /// password loses access.
+    async fn sign_out_everywhere(&self, user: &User) -> Result<(), OAuth2Error> {
+        if let Some(sessions) = &self.sessions {
+            for session in sessions.list_for_user(&user.id).await? {
+                sessions.delete(&session.id).await?;
+            }
+        }
+
+        let mut grants = Vec::new();
+        for page in 1.. {
+            let page = PageRequest::new(page, PageRequest::MAX_PER_PAGE);
+            let tokens = self.storage.list_tokens_by_user(&user.id, page).await?;
+            grants.extend(
+                tokens
+                    .iter()
+                    .filter(|token| !token.revoked)
+                    .map(|token| token.grant_id().to_string()),
+            );
+            if tokens.len() < page.limit() as usize {
+                break;
+            }
+        }
+        grants.sort();
+        grants.dedup();
+        for grant_id in &grants {
+            self.storage.revoke_token_grant(grant_id).await?;
+        }
+        tracing::info!(
+            enduser.id = %user.id,
+            grants = grants.len(),
+            "Signed out everywhere after password reset"
+        );
+        Ok(())
+    }
+
+    /// Mail a sign-in link to the enabled users with address `email`, without telling
+    /// whether there were any. After signing in, the user continues at `return_to`, a path
+    /// on this server.
+    ///
+    /// Fails with `rate_limit_exceeded` once `mail.magic_link.max_per_hour` links were
+    /// requested for the address, known or not.
+    pub async fn request_magic_link(
+        &self,
+        email: &str,
+        return_to: Option<&str>,
+    ) -> Result<(), OAuth2Error> {
+        let link = self.link("/auth/magic-link/sign-in")?;
+        let return_to = return_to.map(local_path).transpose()?;
+        if self.magic_link_limiter.check(email).is_err() {
+            return Err(OAuth2Error::rate_limit_exceeded(
+                "Too many sign-in links requested for this address, try again later",
+            ));
+        }
+
+        for user in self.users_by_email(email).await? {
+            let (token, mut record) = EmailToken::issue(
+                &user,
+                EmailTokenPurpose::MagicLink,
+                self.magic_link_ttl_secs,
+            );
+            record.return_to = return_to.clone();
+            self.storage.save_email_token(&record).await?;
+            self.mailer
+                .send(Email {
+                    to: user.email.clone(),
+                    subject: "Your sign-in link".to_string(),
+                    body: format!(
+                        "Hello {},\n\nSign in by opening:\n\n{link}?token={token}\n\n\
+                         The link expires in {} and works once. If you did not ask for it, \
+                         ignore this message.\n",
+                        user.username,
+                        describe_ttl(self.magic_link_ttl_secs)
+                    ),
+                })
+                .await?;
+
+            self.publish(user_event(EventType::MagicLinkSent, &user));
+        }
+        Ok(())
+    }
+
+    /// Sign in with a magic link: the user it was sent to and where to continue. Other
+    /// sign-in links sent to them stop working, and their address counts as verified.
+    pub async fn sign_in_with_magic_link(
+        &self,
+        token: &str,
+    ) -> Result<(User, Option<String>), OAuth2Error> {
+        let (record, mut user) = self.redeem(token, EmailTokenPurpose::MagicLink).await?;
+        if !user.email_verified {
+            user.email_verified = true;
+            user.updated_at = Utc::now();
+            self.storage.update_user(&user).await?;
+        }
+        self.storage
+            .delete_email_tokens(&user.id, record.purpose)
+            .await?;
+
+        self.publish(
+            user_event(EventType::UserAuthenticated, &user).with_metadata("method", "magic_link"),
+        );
+        Ok((user, record.return_to))
+    }
+
+    /// Absolute URL of `path` under the configured issuer.
+    fn link(&self, path: &str) -> Result<String, OAuth2Error> {
+        let issuer = self.issuer.as_deref().ok_or_else(|| {
+            OAuth2Error::new(
+                "server_error",
+                Some("server.issuer must be set to send links by mail"),
+            )
+        })?;
+        Ok(format!("{issuer}{path}"))
+    }
+
+    /// Use up `token` and load its user. Fails unless the token is known, meant for
+    /// `purpose`, unexpired, and was sent to the user's current address.
+    async fn redeem(
+        &self,
+        token: &str,
+        purpose: EmailTokenPurpose,
+    ) -> Result<(EmailToken, User), OAuth2Error> {
+        let record = self
+            .storage
+            .take_email_token(&hash_email_token(token))
+            .await?
+            .filter(|record| record.purpose == purpose)
+            .ok_or_else(|| OAuth2Error::invalid_grant("Invalid or already used link"))?;
+        if record.is_expired() {
+            return Err(OAuth2Error::invalid_grant("Link expired"));
+        }
+        let user = self
+            .storage
+            .get_user(&record.user_id)
+            .await?
+            .filter(|user| user.enabled)
+            .ok_or_else(|| OAuth2Error::invalid_grant("Account is unavailable"))?;
+        if user.email != record.email {
+            return Err(OAuth2Error::invalid_grant(
+                "Email address changed since the link was sent",
+            ));
+        }
+        Ok((record, user))
+    }
+
+    /// Enabled users of the default tenant with address `email`, ignoring case.
+    async fn users_by_email(&self, email: &str) -> Result<Vec<User>, OAuth2Error> {
+        let email = email.trim();
+        if email.is_empty() {
+            return Ok(Vec::new());
+        }
+        let users = self
+            .storage
+            .list_users(&UserListQuery {
+                search: Some(email.to_string()),
+                ..UserListQuery::default()
+            })
+            .await?;
+        Ok(users
+            .into_iter()
+            .filter(|user| {
+                user.enabled && user.in_tenant(None) && user.email.eq_ignore_ascii_case(email)
+            })
+            .collect())
+    }
+
+    /// Report a request refused by the `limit` quota for `email`, with the caller's IP
+    /// address and user agent.
+    pub(crate) fn publish_rate_limited(&self, limit: &str, email: &str, req: &HttpRequest) {
+        let mut event = AuthEvent::new(
+            EventType::RateLimitExceeded,
+            EventSeverity::Warning,
+            None,
+            None,
+        )
+        .with_metadata("limit", limit)
+        .with_metadata("email", email);
+        if let Some(ip_address) = TrustedProxies::client_ip_of(req) {
+            event = event.with_metadata("ip_address", ip_address.to_string());
+        }
+        if let Some(user_agent) = req
+            .headers()
+            .get(actix_web::http::header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+        {
+            event = event.with_metadata("user_agent", user_agent);
+        }
+        self.publish(event);
+    }
+
+    fn publish(&self, event: AuthEvent) {
+        if let Some(event_bus) = &self.event_bus {
+            event_bus.publish_best_effort(EventEnvelope::from_current_span(event, "oauth2_server"));
+        }
+    }
+}
+
+fn user_event(event_type: EventType, user: &User) -> AuthEvent {
+    AuthEvent::new(event_type, EventSeverity::Info, Some(user.id.clone()), None)
+        .with_metadata("email", user.email.as_str())
+}
+
+/// `return_to` if it is a path on this server, so links cannot send users elsewhere.
+fn local_path(return_to: &str) -> Result<String, OAuth2Error> {
+    let local = return_to.starts_with('/')
+        && !return_to.starts_with("//")
+        && !return_to.starts_with("/\\")
+        && !return_to.chars().any(char::is_control);
+    if !local {
+        return Err(OAuth2Error::invalid_request(
+            "return_to must be a path on this server",
+        ));
+    }
+    Ok(return_to.to_string())
+}
+
+/// `ttl_secs` for people, e.g. "24 hours".
+fn describe_ttl(ttl_secs: i64) -> String {
+    match ttl_secs {
+        secs if secs >= 7200 && secs % 3600 == 0 => format!("{} hours", secs / 3600),
+        secs if secs >= 120 => format!("{} minutes", secs / 60),
+        secs => format!("{secs} seconds"),
+    }
+}
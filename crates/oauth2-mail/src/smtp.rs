@@ -0,0 +1,80 @@
+use async_trait::async_trait;
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use oauth2_config::{SmtpConfig, SmtpEncryption};
+use oauth2_core::OAuth2Error;
+
+use crate::mailer::{Email, Mailer};
+
+/// Sends mail through an SMTP relay. Connections are pooled.
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    /// Fails on a malformed `from` address or relay host; the relay is not contacted yet.
+    pub fn from_config(config: &SmtpConfig, from: &str) -> Result<Self, OAuth2Error> {
+        let invalid = |description: String| {
+            OAuth2Error::new(
+                "invalid_configuration",
+                Some(&format!("Invalid mail configuration: {description}")),
+            )
+        };
+        let from = from
+            .parse::<Mailbox>()
+            .map_err(|e| invalid(format!("from: {e}")))?;
+        let builder = match config.encryption {
+            SmtpEncryption::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+                    .map_err(|e| invalid(format!("smtp.host: {e}")))?
+            }
+            SmtpEncryption::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)
+                .map_err(|e| invalid(format!("smtp.host: {e}")))?,
+            SmtpEncryption::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
+            }
+        }
+        .port(config.port);
+        let builder = match (&config.username, &config.password) {
+            (Some(username), Some(password)) => {
+                builder.credentials(Credentials::new(username.clone(), password.clone()))
+            }
+            (None, None) => builder,
+            _ => {
+                return Err(invalid(
+                    "set both smtp.username and smtp.password".to_string(),
+                ))
+            }
+        };
+
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: Email) -> Result<(), OAuth2Error> {
+        let to = email.to.parse::<Mailbox>().map_err(|e| {
+            OAuth2Error::invalid_request(&format!("Invalid recipient address: {e}"))
+        })?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(email.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.body)
+            .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))?;
+
+        self.transport.send(message).await.map_err(|e| {
+            tracing::warn!(error = %e, "SMTP relay rejected a message");
+            OAuth2Error::new("temporarily_unavailable", Some("Mail could not be sent"))
+        })?;
+        Ok(())
+    }
+}
@@ -0,0 +1,17 @@
+use serde::Serialize;
+
+/// What was built and from which commit, as served at `GET /version` and embedded in
+/// health reports.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BuildInfo {
+    pub version: String,
+    /// Full commit hash, or `unknown` when built outside a git checkout without `GIT_SHA`.
+    pub git_sha: String,
+    /// RFC 3339 build timestamp.
+    pub built_at: String,
+    pub rustc_version: String,
+    /// Cargo profile, e.g. `release`.
+    pub profile: String,
+    /// Enabled optional cargo features, sorted.
+    pub features: Vec<String>,
+}
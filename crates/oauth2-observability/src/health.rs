@@ -0,0 +1,292 @@
+//! Component health checks behind the `/health/*` probes.
+//!
+//! Components (storage, event backends, key stores, upstream identity providers) register
+//! an async check with a [`HealthRegistry`]. Readiness runs every check concurrently, each
+//! under a timeout, and reports per-component status and latency.
+
+use crate::BuildInfo;
+use async_trait::async_trait;
+use futures::future::join_all;
+use oauth2_ports::DynStorage;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Default upper bound for a single component check.
+pub const DEFAULT_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// An async probe of one component.
+#[async_trait]
+pub trait HealthCheck: Send + Sync {
+    /// `Ok(())` when the component is usable, otherwise a short reason.
+    async fn check(&self) -> Result<(), String>;
+}
+
+/// Checks that the storage backend answers.
+pub struct StorageHealthCheck(pub DynStorage);
+
+#[async_trait]
+impl HealthCheck for StorageHealthCheck {
+    async fn check(&self) -> Result<(), String> {
+        self.0.healthcheck().await.map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Healthy,
+    /// Only non-critical components are failing; the server keeps taking traffic.
+    Degraded,
+    Unhealthy,
+}
+
+impl HealthStatus {
+    /// Whether a probe reporting this status should succeed.
+    pub fn is_available(self) -> bool {
+        self != HealthStatus::Unhealthy
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComponentHealth {
+    pub name: String,
+    pub status: HealthStatus,
+    /// A failing critical component makes the whole report unhealthy.
+    pub critical: bool,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build: Option<BuildInfo>,
+    pub components: Vec<ComponentHealth>,
+}
+
+struct Component {
+    name: String,
+    critical: bool,
+    check: Arc<dyn HealthCheck>,
+}
+
+/// Registered component checks plus the startup flag.
+///
+/// Cheap to clone; clones share the startup flag.
+#[derive(Clone)]
+pub struct HealthRegistry {
+    components: Vec<Arc<Component>>,
+    timeout: Duration,
+    build: Option<BuildInfo>,
+    started: Arc<AtomicBool>,
+}
+
+impl Default for HealthRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HealthRegistry {
+    pub fn new() -> Self {
+        Self {
+            components: Vec::new(),
+            timeout: DEFAULT_CHECK_TIMEOUT,
+            build: None,
+            started: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Upper bound for each check; a check that takes longer counts as failed.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Include `build` in every report.
+    pub fn with_build_info(mut self, build: BuildInfo) -> Self {
+        self.build = Some(build);
+        self
+    }
+
+    /// Register a component whose failure takes the server out of rotation.
+    pub fn register(&mut self, name: impl Into<String>, check: Arc<dyn HealthCheck>) {
+        self.push(name.into(), true, check);
+    }
+
+    /// Register a component whose failure is reported but only degrades readiness.
+    pub fn register_non_critical(&mut self, name: impl Into<String>, check: Arc<dyn HealthCheck>) {
+        self.push(name.into(), false, check);
+    }
+
+    fn push(&mut self, name: String, critical: bool, check: Arc<dyn HealthCheck>) {
+        self.components.push(Arc::new(Component {
+            name,
+            critical,
+            check,
+        }));
+    }
+
+    /// Names of the registered components, in registration order.
+    pub fn component_names(&self) -> impl Iterator<Item = &str> {
+        self.components.iter().map(|c| c.name.as_str())
+    }
+
+    /// Record that startup finished; `/health/startup` succeeds from then on.
+    pub fn mark_started(&self) {
+        self.started.store(true, Ordering::Release);
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.started.load(Ordering::Acquire)
+    }
+
+    /// Liveness: the process is up and serving requests. Runs no checks.
+    pub fn liveness(&self) -> HealthReport {
+        self.report(HealthStatus::Healthy, Vec::new())
+    }
+
+    /// Readiness: run every registered check concurrently.
+    pub async fn readiness(&self) -> HealthReport {
+        let components = join_all(self.components.iter().map(|c| self.run(c))).await;
+        let status = if components
+            .iter()
+            .any(|c| c.critical && c.status == HealthStatus::Unhealthy)
+        {
+            HealthStatus::Unhealthy
+        } else if components
+            .iter()
+            .any(|c| c.status == HealthStatus::Unhealthy)
+        {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Healthy
+        };
+        self.report(status, components)
+    }
+
+    /// Startup: unhealthy until [`mark_started`](Self::mark_started), then the readiness report.
+    pub async fn startup(&self) -> HealthReport {
+        if !self.is_started() {
+            return self.report(HealthStatus::Unhealthy, Vec::new());
+        }
+        self.readiness().await
+    }
+
+    fn report(&self, status: HealthStatus, components: Vec<ComponentHealth>) -> HealthReport {
+        HealthReport {
+            status,
+            build: self.build.clone(),
+            components,
+        }
+    }
+
+    async fn run(&self, component: &Component) -> ComponentHealth {
+        let started = Instant::now();
+        let outcome = match tokio::time::timeout(self.timeout, component.check.check()).await {
+            Ok(outcome) => outcome,
+            Err(_) => Err(format!("timed out after {}ms", self.timeout.as_millis())),
+        };
+        let latency_ms = started.elapsed().as_millis() as u64;
+        if let Err(ref error) = outcome {
+            tracing::warn!(
+                component = %component.name,
+                critical = component.critical,
+                error = %error,
+                "Health check failed"
+            );
+        }
+        ComponentHealth {
+            name: component.name.clone(),
+            status: if outcome.is_ok() {
+                HealthStatus::Healthy
+            } else {
+                HealthStatus::Unhealthy
+            },
+            critical: component.critical,
+            latency_ms,
+            error: outcome.err(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(Result<(), String>);
+
+    #[async_trait]
+    impl HealthCheck for Fixed {
+        async fn check(&self) -> Result<(), String> {
+            self.0.clone()
+        }
+    }
+
+    struct Hangs;
+
+    #[async_trait]
+    impl HealthCheck for Hangs {
+        async fn check(&self) -> Result<(), String> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn critical_failures_make_readiness_unhealthy() {
+        let mut registry = HealthRegistry::new();
+        registry.register("storage", Arc::new(Fixed(Ok(()))));
+        registry.register_non_critical("kafka", Arc::new(Fixed(Err("down".to_string()))));
+
+        let report = registry.readiness().await;
+        assert_eq!(report.status, HealthStatus::Degraded);
+        assert!(report.status.is_available());
+        assert_eq!(report.components[1].error.as_deref(), Some("down"));
+
+        registry.register("keys", Arc::new(Fixed(Err("missing".to_string()))));
+        let report = registry.readiness().await;
+        assert_eq!(report.status, HealthStatus::Unhealthy);
+        assert_eq!(
+            report
+                .components
+                .iter()
+                .map(|c| c.status)
+                .collect::<Vec<_>>(),
+            [
+                HealthStatus::Healthy,
+                HealthStatus::Unhealthy,
+                HealthStatus::Unhealthy
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn slow_checks_time_out() {
+        let mut registry = HealthRegistry::new().with_timeout(Duration::from_millis(50));
+        registry.register("idp", Arc::new(Hangs));
+
+        let report = registry.readiness().await;
+        assert_eq!(report.status, HealthStatus::Unhealthy);
+        assert_eq!(
+            report.components[0].error.as_deref(),
+            Some("timed out after 50ms")
+        );
+    }
+
+    #[tokio::test]
+    async fn startup_waits_for_mark_started() {
+        let mut registry = HealthRegistry::new();
+        registry.register("storage", Arc::new(Fixed(Ok(()))));
+        let shared = registry.clone();
+
+        assert_eq!(registry.startup().await.status, HealthStatus::Unhealthy);
+        shared.mark_started();
+        let report = registry.startup().await;
+        assert_eq!(report.status, HealthStatus::Healthy);
+        assert_eq!(report.components.len(), 1);
+    }
+}
@@ -1,13 +1,23 @@
+pub mod build_info;
+pub mod health;
+pub mod logging;
 pub mod metrics;
+pub mod semconv;
 pub mod storage;
 pub mod telemetry;
 
 #[cfg(feature = "actix")]
 pub mod actix;
 
+pub use build_info::BuildInfo;
+pub use health::{HealthCheck, HealthRegistry, HealthReport, HealthStatus};
+pub use logging::{LogFile, LogFormat, LogOptions, LogRotation};
 pub use metrics::Metrics;
 pub use storage::ObservedStorage;
-pub use telemetry::{annotate_span_with_trace_ids, init_telemetry, shutdown_telemetry};
+pub use telemetry::{
+    annotate_span_with_trace_ids, init_telemetry, init_telemetry_with_logs, shutdown_telemetry,
+    span_trace_id,
+};
 
 /// Encode a Prometheus registry into the text exposition format ("version=0.0.4").
 ///
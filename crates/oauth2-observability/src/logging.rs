@@ -0,0 +1,240 @@
+use chrono::{DateTime, Utc};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+/// How log lines are rendered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// One JSON object per line, for log collectors.
+    #[default]
+    Json,
+    /// Multi-line, human-readable output for local development.
+    Pretty,
+    /// Single-line, human-readable output.
+    Compact,
+}
+
+/// When a [`LogFile`] starts a new file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogRotation {
+    /// Always append to `<prefix>`.
+    Never,
+    /// `<prefix>.YYYY-MM-DD-HH`, in UTC.
+    Hourly,
+    /// `<prefix>.YYYY-MM-DD`, in UTC.
+    #[default]
+    Daily,
+}
+
+/// Log output to files in `directory`.
+#[derive(Debug, Clone)]
+pub struct LogFile {
+    pub directory: PathBuf,
+    pub prefix: String,
+    pub rotation: LogRotation,
+    /// Rotated files to keep, newest first; older ones are deleted on rotation.
+    pub max_files: Option<usize>,
+}
+
+/// Where and how [`init_telemetry_with_logs`](crate::init_telemetry_with_logs) writes logs.
+#[derive(Debug, Clone)]
+pub struct LogOptions {
+    pub format: LogFormat,
+    /// Include the list of entered spans in JSON lines. The current span is always included.
+    pub span_list: bool,
+    pub stdout: bool,
+    pub file: Option<LogFile>,
+}
+
+impl Default for LogOptions {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::Json,
+            span_list: true,
+            stdout: true,
+            file: None,
+        }
+    }
+}
+
+/// Appends to the current file of a [`LogFile`], switching files when its rotation period
+/// ends.
+#[derive(Debug)]
+pub struct RollingFile {
+    config: LogFile,
+    current: Mutex<Option<OpenFile>>,
+}
+
+/// The file being written and the rotation period it covers.
+#[derive(Debug)]
+struct OpenFile {
+    suffix: Option<String>,
+    file: File,
+}
+
+impl RollingFile {
+    /// Create the directory if needed and open the current file.
+    pub fn open(config: LogFile) -> io::Result<Self> {
+        fs::create_dir_all(&config.directory)?;
+        let rolling = Self::new(config);
+        drop(rolling.current_file(Utc::now())?);
+        Ok(rolling)
+    }
+
+    fn new(config: LogFile) -> Self {
+        Self {
+            config,
+            current: Mutex::new(None),
+        }
+    }
+
+    fn suffix(&self, now: DateTime<Utc>) -> Option<String> {
+        match self.config.rotation {
+            LogRotation::Never => None,
+            LogRotation::Hourly => Some(now.format("%Y-%m-%d-%H").to_string()),
+            LogRotation::Daily => Some(now.format("%Y-%m-%d").to_string()),
+        }
+    }
+
+    fn path(&self, suffix: Option<&str>) -> PathBuf {
+        let name = match suffix {
+            Some(suffix) => format!("{}.{suffix}", self.config.prefix),
+            None => self.config.prefix.clone(),
+        };
+        self.config.directory.join(name)
+    }
+
+    fn current_file(&self, now: DateTime<Utc>) -> io::Result<MutexGuard<'_, Option<OpenFile>>> {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        let suffix = self.suffix(now);
+        if current.as_ref().is_none_or(|open| open.suffix != suffix) {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.path(suffix.as_deref()))?;
+            *current = Some(OpenFile { suffix, file });
+            self.prune();
+        }
+        Ok(current)
+    }
+
+    /// Delete the oldest rotated files beyond `max_files`. Best effort.
+    fn prune(&self) {
+        let Some(max_files) = self.config.max_files else {
+            return;
+        };
+        let Ok(entries) = fs::read_dir(&self.config.directory) else {
+            return;
+        };
+        let rotated_prefix = format!("{}.", self.config.prefix);
+        let mut rotated: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(&rotated_prefix))
+            })
+            .collect();
+        // Suffixes are zero-padded timestamps, so names sort oldest first.
+        rotated.sort();
+        let excess = rotated.len().saturating_sub(max_files.max(1));
+        for path in &rotated[..excess] {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    fn write_at(&self, buf: &[u8], now: DateTime<Utc>) -> io::Result<usize> {
+        let mut current = self.current_file(now)?;
+        let open = current.as_mut().expect("current file is open");
+        open.file.write(buf)
+    }
+
+    /// Path of the file written to now.
+    pub fn current_path(&self) -> PathBuf {
+        self.path(self.suffix(Utc::now()).as_deref())
+    }
+}
+
+impl Write for &RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_at(buf, Utc::now())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        match current.as_mut() {
+            Some(open) => open.file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::path::Path;
+
+    fn log_file(directory: &Path, rotation: LogRotation, max_files: Option<usize>) -> LogFile {
+        LogFile {
+            directory: directory.to_path_buf(),
+            prefix: "server.log".to_string(),
+            rotation,
+            max_files,
+        }
+    }
+
+    fn names(directory: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn rotates_when_the_period_ends() {
+        let dir = tempfile::tempdir().unwrap();
+        let rolling = RollingFile::new(log_file(dir.path(), LogRotation::Daily, None));
+
+        let day = |d| Utc.with_ymd_and_hms(2026, 3, d, 12, 0, 0).unwrap();
+        rolling.write_at(b"first\n", day(1)).unwrap();
+        rolling.write_at(b"second\n", day(1)).unwrap();
+        rolling.write_at(b"third\n", day(2)).unwrap();
+
+        let first = fs::read_to_string(dir.path().join("server.log.2026-03-01")).unwrap();
+        assert_eq!(first, "first\nsecond\n");
+        let second = fs::read_to_string(dir.path().join("server.log.2026-03-02")).unwrap();
+        assert_eq!(second, "third\n");
+    }
+
+    #[test]
+    fn keeps_at_most_max_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let rolling = RollingFile::new(log_file(dir.path(), LogRotation::Hourly, Some(2)));
+
+        for hour in 0..4 {
+            let now = Utc.with_ymd_and_hms(2026, 3, 1, hour, 0, 0).unwrap();
+            rolling.write_at(b"line\n", now).unwrap();
+        }
+
+        assert_eq!(
+            names(dir.path()),
+            ["server.log.2026-03-01-02", "server.log.2026-03-01-03"]
+        );
+    }
+
+    #[test]
+    fn never_rotating_appends_to_one_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let rolling = RollingFile::open(log_file(dir.path(), LogRotation::Never, None)).unwrap();
+        (&rolling).write_all(b"line\n").unwrap();
+
+        assert_eq!(rolling.current_path(), dir.path().join("server.log"));
+        assert_eq!(names(dir.path()), ["server.log"]);
+    }
+}
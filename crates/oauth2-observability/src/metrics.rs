@@ -1,6 +1,6 @@
 use prometheus::{
-    Counter, CounterVec, Histogram, HistogramOpts, HistogramVec, IntCounter, IntGauge, Opts,
-    Registry,
+    Counter, CounterVec, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec,
+    IntGauge, IntGaugeVec, Opts, Registry,
 };
 use std::sync::Arc;
 
@@ -38,6 +38,70 @@ pub struct Metrics {
     #[allow(dead_code)]
     pub oauth_failed_authentications: IntCounter,
 
+    /// JWT verifications by issuer generation, used to track an issuer migration.
+    ///
+    /// Labels:
+    /// - generation: current | legacy | unknown
+    /// - outcome: accepted | rejected
+    pub oauth_token_verifications_by_issuer: IntCounterVec,
+
+    /// Tokens issued to service accounts.
+    ///
+    /// Labels:
+    /// - service_account: service account name
+    pub oauth_service_account_tokens_issued_total: IntCounterVec,
+
+    /// Token introspection requests.
+    ///
+    /// Labels:
+    /// - caller: resource server client id, or `client` for any other caller
+    /// - outcome: active | inactive | rate_limited
+    pub oauth_introspection_requests_total: IntCounterVec,
+
+    /// Event-ingest idempotency checks.
+    ///
+    /// Labels:
+    /// - backend: cache backend that answered (e.g. redis) or `local`
+    /// - result: recorded | duplicate
+    pub events_idempotency_checks_total: IntCounterVec,
+
+    /// Idempotency checks that fell back to the local store because the shared cache failed.
+    pub events_idempotency_fallbacks_total: IntCounter,
+
+    /// Entries currently held by the local (per-replica) idempotency store.
+    pub events_idempotency_local_entries: IntGauge,
+
+    /// Event emits retried after a plugin failed.
+    ///
+    /// Labels:
+    /// - plugin: event plugin name
+    pub events_plugin_retries_total: IntCounterVec,
+
+    /// Events sent to the dead-letter destination after a plugin's retries ran out.
+    ///
+    /// Labels:
+    /// - plugin: event plugin name
+    pub events_dead_lettered_total: IntCounterVec,
+
+    /// Events waiting in a plugin's queue.
+    ///
+    /// Labels:
+    /// - plugin: event plugin name
+    pub events_queue_depth: IntGaugeVec,
+
+    /// Events dropped because a plugin's queue was full.
+    ///
+    /// Labels:
+    /// - plugin: event plugin name
+    pub events_queue_dropped_total: IntCounterVec,
+
+    /// Lookups answered by the in-process storage cache.
+    ///
+    /// Labels:
+    /// - cache: client | token
+    /// - outcome: hit | miss
+    pub storage_cache_requests_total: IntCounterVec,
+
     // Client metrics
     #[allow(dead_code)]
     pub oauth_clients_total: IntGauge,
@@ -49,6 +113,27 @@ pub struct Metrics {
     pub db_queries_total: Counter,
     #[allow(dead_code)]
     pub db_query_duration_seconds: Histogram,
+
+    /// Storage call latency, recorded by `ObservedStorage`.
+    ///
+    /// Labels:
+    /// - db_system: sqlite | postgresql | mongodb | ...
+    /// - db_operation: `Storage` method name (e.g. get_client)
+    pub db_operation_duration_seconds: HistogramVec,
+
+    /// Storage calls that returned an error, recorded by `ObservedStorage`.
+    ///
+    /// Labels:
+    /// - db_system: sqlite | postgresql | mongodb | ...
+    /// - db_operation: `Storage` method name (e.g. get_client)
+    pub db_operation_errors_total: IntCounterVec,
+
+    /// Storage calls slower than `database.slow_operation_threshold_ms`.
+    ///
+    /// Labels:
+    /// - db_system: sqlite | postgresql | mongodb | ...
+    /// - db_operation: `Storage` method name (e.g. get_client)
+    pub storage_slow_operations_total: IntCounterVec,
 }
 
 impl Metrics {
@@ -123,6 +208,114 @@ impl Metrics {
         )?;
         registry.register(Box::new(oauth_failed_authentications.clone()))?;
 
+        let oauth_token_verifications_by_issuer = IntCounterVec::new(
+            Opts::new(
+                "oauth_token_verifications_by_issuer",
+                "Total number of JWT verifications (labeled by issuer generation/outcome)",
+            )
+            .namespace("oauth2_server"),
+            &["generation", "outcome"],
+        )?;
+        registry.register(Box::new(oauth_token_verifications_by_issuer.clone()))?;
+
+        let oauth_service_account_tokens_issued_total = IntCounterVec::new(
+            Opts::new(
+                "oauth_service_account_tokens_issued_total",
+                "Total number of tokens issued to service accounts (labeled by account name)",
+            )
+            .namespace("oauth2_server"),
+            &["service_account"],
+        )?;
+        registry.register(Box::new(oauth_service_account_tokens_issued_total.clone()))?;
+
+        let oauth_introspection_requests_total = IntCounterVec::new(
+            Opts::new(
+                "oauth_introspection_requests_total",
+                "Total number of token introspection requests (labeled by caller/outcome)",
+            )
+            .namespace("oauth2_server"),
+            &["caller", "outcome"],
+        )?;
+        registry.register(Box::new(oauth_introspection_requests_total.clone()))?;
+
+        let events_idempotency_checks_total = IntCounterVec::new(
+            Opts::new(
+                "events_idempotency_checks_total",
+                "Total number of event-ingest idempotency checks (labeled by backend/result)",
+            )
+            .namespace("oauth2_server"),
+            &["backend", "result"],
+        )?;
+        registry.register(Box::new(events_idempotency_checks_total.clone()))?;
+
+        let events_idempotency_fallbacks_total = IntCounter::with_opts(
+            Opts::new(
+                "events_idempotency_fallbacks_total",
+                "Total number of idempotency checks served locally because the shared cache failed",
+            )
+            .namespace("oauth2_server"),
+        )?;
+        registry.register(Box::new(events_idempotency_fallbacks_total.clone()))?;
+
+        let events_idempotency_local_entries = IntGauge::with_opts(
+            Opts::new(
+                "events_idempotency_local_entries",
+                "Number of entries in the local idempotency store",
+            )
+            .namespace("oauth2_server"),
+        )?;
+        registry.register(Box::new(events_idempotency_local_entries.clone()))?;
+
+        let events_plugin_retries_total = IntCounterVec::new(
+            Opts::new(
+                "events_plugin_retries_total",
+                "Total number of event emits retried after a plugin failure (labeled by plugin)",
+            )
+            .namespace("oauth2_server"),
+            &["plugin"],
+        )?;
+        registry.register(Box::new(events_plugin_retries_total.clone()))?;
+
+        let events_dead_lettered_total = IntCounterVec::new(
+            Opts::new(
+                "events_dead_lettered_total",
+                "Total number of events dead-lettered after exhausting plugin retries (labeled by plugin)",
+            )
+            .namespace("oauth2_server"),
+            &["plugin"],
+        )?;
+        registry.register(Box::new(events_dead_lettered_total.clone()))?;
+
+        let events_queue_depth = IntGaugeVec::new(
+            Opts::new(
+                "events_queue_depth",
+                "Number of events waiting in a plugin's queue (labeled by plugin)",
+            )
+            .namespace("oauth2_server"),
+            &["plugin"],
+        )?;
+        registry.register(Box::new(events_queue_depth.clone()))?;
+
+        let events_queue_dropped_total = IntCounterVec::new(
+            Opts::new(
+                "events_queue_dropped_total",
+                "Total number of events dropped because a plugin's queue was full (labeled by plugin)",
+            )
+            .namespace("oauth2_server"),
+            &["plugin"],
+        )?;
+        registry.register(Box::new(events_queue_dropped_total.clone()))?;
+
+        let storage_cache_requests_total = IntCounterVec::new(
+            Opts::new(
+                "storage_cache_requests_total",
+                "Total number of storage cache lookups (labeled by cache/outcome)",
+            )
+            .namespace("oauth2_server"),
+            &["cache", "outcome"],
+        )?;
+        registry.register(Box::new(storage_cache_requests_total.clone()))?;
+
         let oauth_clients_total = IntGauge::with_opts(
             Opts::new("oauth_clients_total", "Total number of registered clients")
                 .namespace("oauth2_server"),
@@ -149,6 +342,39 @@ impl Metrics {
         )?;
         registry.register(Box::new(db_query_duration_seconds.clone()))?;
 
+        let db_operation_duration_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "db_operation_duration_seconds",
+                "Storage operation duration in seconds (labeled by db_system/db_operation)",
+            )
+            .namespace("oauth2_server")
+            .buckets(vec![
+                0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+            ]),
+            &["db_system", "db_operation"],
+        )?;
+        registry.register(Box::new(db_operation_duration_seconds.clone()))?;
+
+        let db_operation_errors_total = IntCounterVec::new(
+            Opts::new(
+                "db_operation_errors_total",
+                "Total number of failed storage operations (labeled by db_system/db_operation)",
+            )
+            .namespace("oauth2_server"),
+            &["db_system", "db_operation"],
+        )?;
+        registry.register(Box::new(db_operation_errors_total.clone()))?;
+
+        let storage_slow_operations_total = IntCounterVec::new(
+            Opts::new(
+                "storage_slow_operations_total",
+                "Total number of storage operations over the slow threshold (labeled by db_system/db_operation)",
+            )
+            .namespace("oauth2_server"),
+            &["db_system", "db_operation"],
+        )?;
+        registry.register(Box::new(storage_slow_operations_total.clone()))?;
+
         Ok(Self {
             registry: Arc::new(registry),
             http_requests_total,
@@ -159,10 +385,24 @@ impl Metrics {
             oauth_token_revoked_total,
             oauth_authorization_codes_issued,
             oauth_failed_authentications,
+            oauth_token_verifications_by_issuer,
+            oauth_service_account_tokens_issued_total,
+            oauth_introspection_requests_total,
+            events_idempotency_checks_total,
+            events_idempotency_fallbacks_total,
+            events_idempotency_local_entries,
+            events_plugin_retries_total,
+            events_dead_lettered_total,
+            events_queue_depth,
+            events_queue_dropped_total,
+            storage_cache_requests_total,
             oauth_clients_total,
             oauth_active_tokens,
             db_queries_total,
             db_query_duration_seconds,
+            db_operation_duration_seconds,
+            db_operation_errors_total,
+            storage_slow_operations_total,
         })
     }
 }
@@ -0,0 +1,48 @@
+//! OpenTelemetry semantic convention helpers.
+//!
+//! Span fields use the dotted OTel attribute names (`http.route`, `db.system`,
+//! `db.operation`, `enduser.id`) so APM backends can derive service maps. `otel.name`
+//! and `otel.kind` are interpreted by `tracing-opentelemetry` as the exported span
+//! name and kind.
+
+/// Value for the `enduser.id` attribute.
+///
+/// User identifiers are hashed (truncated SHA-256, hex) so spans stay correlatable per
+/// user without exporting raw identifiers to the tracing backend.
+pub fn enduser_id(user_id: &str) -> String {
+    oauth2_core::redaction::hash(user_id)
+}
+
+/// Span name for an HTTP server span: `"{method} {route}"`.
+///
+/// Falls back to the method alone when no route matched, as the HTTP conventions
+/// recommend, to avoid high-cardinality names built from raw paths.
+pub fn http_span_name(method: &str, route: Option<&str>) -> String {
+    match route {
+        Some(route) => format!("{method} {route}"),
+        None => method.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enduser_id_is_stable_and_not_the_raw_id() {
+        let hashed = enduser_id("user_123");
+        assert_eq!(hashed, enduser_id("user_123"));
+        assert_ne!(hashed, enduser_id("user_124"));
+        assert_eq!(hashed.len(), 32);
+        assert!(!hashed.contains("user_123"));
+    }
+
+    #[test]
+    fn http_span_name_uses_route_template() {
+        assert_eq!(
+            http_span_name("GET", Some("/oauth/register/{client_id}")),
+            "GET /oauth/register/{client_id}"
+        );
+        assert_eq!(http_span_name("POST", None), "POST");
+    }
+}
@@ -1,39 +1,269 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use prometheus::{HistogramVec, IntCounterVec};
 use tracing::{field, Instrument};
 
-use oauth2_core::{AuthorizationCode, Client, OAuth2Error, Token, User};
-use oauth2_ports::{DynStorage, Storage};
+use oauth2_core::{
+    AuditRecord, AuthorizationCode, Client, EmailToken, EmailTokenPurpose, FederatedIdentity,
+    Group, OAuth2Error, OutboxMessage, Redactor, Role, ServiceAccount, SocialLoginState, Tenant,
+    Token, User, UserSession,
+};
+use oauth2_ports::{
+    AuditQuery, DynStorage, PageRequest, Storage, StorageTransaction, TokenMetadataQuery,
+    UserListQuery,
+};
+
+use crate::metrics::Metrics;
+use crate::semconv::enduser_id;
+use crate::telemetry::{annotate_span_with_trace_ids, span_trace_id};
+
+/// Build a client span for a storage operation using the OTel database conventions
+/// (`db.system`, `db.operation`), plus any operation-specific fields.
+macro_rules! db_span {
+    ($storage:expr, $operation:expr $(, $($fields:tt)*)?) => {{
+        let span = tracing::info_span!(
+            "db",
+            otel.name = $operation,
+            otel.kind = "client",
+            trace_id = field::Empty,
+            span_id = field::Empty,
+            db.system = %$storage.db_system,
+            db.operation = $operation
+            $(, $($fields)*)?
+        );
+        annotate_span_with_trace_ids(&span);
+        span
+    }};
+}
+
+/// Per-operation latency, error and slow-call series, labeled by `db_system` and
+/// `db_operation`.
+#[derive(Clone)]
+struct StorageMetrics {
+    duration: HistogramVec,
+    errors: IntCounterVec,
+    slow: IntCounterVec,
+}
+
+/// What to record about each storage call besides its span.
+#[derive(Clone, Default)]
+struct Recorder {
+    metrics: Option<StorageMetrics>,
+    slow_threshold: Option<Duration>,
+}
+
+impl Recorder {
+    /// Run one storage call inside `span`, recording its latency and whether it failed,
+    /// and warning when it took longer than the slow threshold.
+    async fn observe<T>(
+        &self,
+        db_system: &str,
+        operation: &'static str,
+        span: tracing::Span,
+        call: impl Future<Output = Result<T, OAuth2Error>>,
+    ) -> Result<T, OAuth2Error> {
+        let started = Instant::now();
+        let result = call.instrument(span.clone()).await;
+        let elapsed = started.elapsed();
+
+        let slow = self
+            .slow_threshold
+            .is_some_and(|threshold| elapsed >= threshold);
+        if slow {
+            tracing::warn!(
+                parent: &span,
+                db.system = %db_system,
+                db.operation = operation,
+                duration_ms = elapsed.as_millis() as u64,
+                trace_id = %span_trace_id(&span).unwrap_or_default(),
+                "Slow storage operation"
+            );
+        }
 
-use crate::telemetry::annotate_span_with_trace_ids;
+        if let Some(metrics) = &self.metrics {
+            let labels = [db_system, operation];
+            metrics
+                .duration
+                .with_label_values(&labels)
+                .observe(elapsed.as_secs_f64());
+            if result.is_err() {
+                metrics.errors.with_label_values(&labels).inc();
+            }
+            if slow {
+                metrics.slow.with_label_values(&labels).inc();
+            }
+        }
+        result
+    }
+}
 
 /// A thin wrapper around a `DynStorage` that creates a tracing span for each storage call.
 ///
 /// This lets request spans (created by actix middleware) extend naturally through
-/// actors/handlers down into persistence calls.
+/// actors/handlers down into persistence calls. With [`ObservedStorage::with_metrics`],
+/// each call is also timed into Prometheus, so slow storage shows up without a tracing
+/// backend; [`ObservedStorage::with_slow_threshold`] additionally logs the slow calls.
 pub struct ObservedStorage {
     inner: DynStorage,
     db_system: String,
+    recorder: Recorder,
 }
 
 impl ObservedStorage {
     pub fn new(inner: DynStorage, db_system: String) -> Self {
-        Self { inner, db_system }
+        Self {
+            inner,
+            db_system,
+            recorder: Recorder::default(),
+        }
+    }
+
+    /// Record `db_operation_duration_seconds`, `db_operation_errors_total` and
+    /// `storage_slow_operations_total` for every call.
+    pub fn with_metrics(mut self, metrics: &Metrics) -> Self {
+        self.recorder.metrics = Some(StorageMetrics {
+            duration: metrics.db_operation_duration_seconds.clone(),
+            errors: metrics.db_operation_errors_total.clone(),
+            slow: metrics.storage_slow_operations_total.clone(),
+        });
+        self
+    }
+
+    /// Log a warning (with the trace id) for any call taking at least `threshold`.
+    pub fn with_slow_threshold(mut self, threshold: Duration) -> Self {
+        self.recorder.slow_threshold = Some(threshold);
+        self
+    }
+
+    async fn observe<T>(
+        &self,
+        operation: &'static str,
+        span: tracing::Span,
+        call: impl Future<Output = Result<T, OAuth2Error>>,
+    ) -> Result<T, OAuth2Error> {
+        self.recorder
+            .observe(&self.db_system, operation, span, call)
+            .await
     }
 
     fn span(&self, operation: &'static str) -> tracing::Span {
-        let span = tracing::info_span!(
-            "db",
-            trace_id = field::Empty,
-            span_id = field::Empty,
-            db_system = %self.db_system,
-            db_operation = operation
+        db_span!(self, operation)
+    }
+}
+
+/// Traces (and times) each step of a transaction opened through [`ObservedStorage`].
+struct ObservedTransaction {
+    inner: Box<dyn StorageTransaction>,
+    db_system: String,
+    recorder: Recorder,
+}
+
+#[async_trait]
+impl StorageTransaction for ObservedTransaction {
+    async fn consume_authorization_code(&mut self, code: &str) -> Result<(), OAuth2Error> {
+        let redacted = Redactor::current().token(code);
+        let span = db_span!(
+            self,
+            "consume_authorization_code",
+            code = %redacted,
+            code_len = code.len()
         );
-        annotate_span_with_trace_ids(&span);
-        span
+        self.recorder
+            .observe(
+                &self.db_system,
+                "consume_authorization_code",
+                span,
+                self.inner.consume_authorization_code(code),
+            )
+            .await
+    }
+
+    async fn save_token(&mut self, token: &Token) -> Result<(), OAuth2Error> {
+        let redacted = Redactor::current().token(&token.access_token);
+        let span = db_span!(
+            self,
+            "save_token",
+            token = %redacted,
+            client_id = %token.client_id,
+            enduser.id = %token.user_id.as_deref().map(enduser_id).unwrap_or_default()
+        );
+        self.recorder
+            .observe(
+                &self.db_system,
+                "save_token",
+                span,
+                self.inner.save_token(token),
+            )
+            .await
+    }
+
+    async fn revoke_token(&mut self, token: &str) -> Result<(), OAuth2Error> {
+        let redacted = Redactor::current().token(token);
+        let span = db_span!(
+            self,
+            "revoke_token",
+            token = %redacted,
+            token_len = token.len()
+        );
+        self.recorder
+            .observe(
+                &self.db_system,
+                "revoke_token",
+                span,
+                self.inner.revoke_token(token),
+            )
+            .await
+    }
+
+    async fn redeem_refresh_token(&mut self, refresh_token: &str) -> Result<(), OAuth2Error> {
+        let redacted = Redactor::current().token(refresh_token);
+        let span = db_span!(
+            self,
+            "redeem_refresh_token",
+            token = %redacted,
+            token_len = refresh_token.len()
+        );
+        self.recorder
+            .observe(
+                &self.db_system,
+                "redeem_refresh_token",
+                span,
+                self.inner.redeem_refresh_token(refresh_token),
+            )
+            .await
     }
 
-    fn token_prefix(token: &str) -> String {
-        token.chars().take(12).collect::<String>()
+    async fn enqueue_outbox_message(&mut self, message: &OutboxMessage) -> Result<(), OAuth2Error> {
+        let span = db_span!(
+            self,
+            "enqueue_outbox_message",
+            event_type = %message.event_type
+        );
+        self.recorder
+            .observe(
+                &self.db_system,
+                "enqueue_outbox_message",
+                span,
+                self.inner.enqueue_outbox_message(message),
+            )
+            .await
+    }
+
+    async fn commit(self: Box<Self>) -> Result<(), OAuth2Error> {
+        let span = db_span!(self, "commit");
+        self.recorder
+            .observe(&self.db_system, "commit", span, self.inner.commit())
+            .await
+    }
+
+    async fn rollback(self: Box<Self>) -> Result<(), OAuth2Error> {
+        let span = db_span!(self, "rollback");
+        self.recorder
+            .observe(&self.db_system, "rollback", span, self.inner.rollback())
+            .await
     }
 }
 
@@ -41,89 +271,271 @@ impl ObservedStorage {
 impl Storage for ObservedStorage {
     async fn init(&self) -> Result<(), OAuth2Error> {
         let span = self.span("init");
-        async move { self.inner.init().await }
-            .instrument(span)
+        self.observe("init", span, self.inner.init()).await
+    }
+
+    async fn save_tenant(&self, tenant: &Tenant) -> Result<(), OAuth2Error> {
+        let span = db_span!(self, "save_tenant", tenant.id = %tenant.id);
+        self.observe("save_tenant", span, self.inner.save_tenant(tenant))
+            .await
+    }
+
+    async fn get_tenant(&self, id: &str) -> Result<Option<Tenant>, OAuth2Error> {
+        let span = db_span!(self, "get_tenant", tenant.id = %id);
+        self.observe("get_tenant", span, self.inner.get_tenant(id))
+            .await
+    }
+
+    async fn list_tenants(&self) -> Result<Vec<Tenant>, OAuth2Error> {
+        let span = self.span("list_tenants");
+        self.observe("list_tenants", span, self.inner.list_tenants())
             .await
     }
 
     async fn save_client(&self, client: &Client) -> Result<(), OAuth2Error> {
-        let span = tracing::info_span!(
-            "db",
-            trace_id = field::Empty,
-            span_id = field::Empty,
-            db_system = %self.db_system,
-            db_operation = "save_client",
+        let span = db_span!(
+            self,
+            "save_client",
             client_id = %client.client_id
         );
-        annotate_span_with_trace_ids(&span);
-        async move { self.inner.save_client(client).await }
-            .instrument(span)
+        self.observe("save_client", span, self.inner.save_client(client))
             .await
     }
 
-    async fn get_client(&self, client_id: &str) -> Result<Option<Client>, OAuth2Error> {
-        let span = tracing::info_span!(
-            "db",
-            trace_id = field::Empty,
-            span_id = field::Empty,
-            db_system = %self.db_system,
-            db_operation = "get_client",
+    async fn get_client(
+        &self,
+        client_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<Client>, OAuth2Error> {
+        let span = db_span!(
+            self,
+            "get_client",
             client_id = %client_id
         );
-        annotate_span_with_trace_ids(&span);
-        async move { self.inner.get_client(client_id).await }
-            .instrument(span)
+        self.observe(
+            "get_client",
+            span,
+            self.inner.get_client(client_id, tenant_id),
+        )
+        .await
+    }
+
+    async fn list_clients_managed_by(&self, manager: &str) -> Result<Vec<Client>, OAuth2Error> {
+        let span = db_span!(self, "list_clients_managed_by", managed_by = %manager);
+        self.observe(
+            "list_clients_managed_by",
+            span,
+            self.inner.list_clients_managed_by(manager),
+        )
+        .await
+    }
+
+    async fn list_clients(&self, page: PageRequest) -> Result<Vec<Client>, OAuth2Error> {
+        let span = db_span!(
+            self,
+            "list_clients",
+            page = page.page,
+            per_page = page.per_page
+        );
+        self.observe("list_clients", span, self.inner.list_clients(page))
             .await
     }
 
+    async fn count_clients(&self) -> Result<u64, OAuth2Error> {
+        let span = self.span("count_clients");
+        self.observe("count_clients", span, self.inner.count_clients())
+            .await
+    }
+
+    async fn update_client(&self, client: &Client) -> Result<(), OAuth2Error> {
+        let span = db_span!(
+            self,
+            "update_client",
+            client_id = %client.client_id
+        );
+        self.observe("update_client", span, self.inner.update_client(client))
+            .await
+    }
+
+    async fn delete_client(
+        &self,
+        client_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<(), OAuth2Error> {
+        let span = db_span!(
+            self,
+            "delete_client",
+            client_id = %client_id
+        );
+        self.observe(
+            "delete_client",
+            span,
+            self.inner.delete_client(client_id, tenant_id),
+        )
+        .await
+    }
+
+    async fn save_service_account(&self, account: &ServiceAccount) -> Result<(), OAuth2Error> {
+        let span = db_span!(
+            self,
+            "save_service_account",
+            service_account.id = %account.id,
+            client_id = %account.client_id
+        );
+        self.observe(
+            "save_service_account",
+            span,
+            self.inner.save_service_account(account),
+        )
+        .await
+    }
+
+    async fn get_service_account(&self, id: &str) -> Result<Option<ServiceAccount>, OAuth2Error> {
+        let span = db_span!(self, "get_service_account", service_account.id = %id);
+        self.observe(
+            "get_service_account",
+            span,
+            self.inner.get_service_account(id),
+        )
+        .await
+    }
+
+    async fn get_service_account_by_client_id(
+        &self,
+        client_id: &str,
+    ) -> Result<Option<ServiceAccount>, OAuth2Error> {
+        let span = db_span!(
+            self,
+            "get_service_account_by_client_id",
+            client_id = %client_id
+        );
+        self.observe(
+            "get_service_account_by_client_id",
+            span,
+            self.inner.get_service_account_by_client_id(client_id),
+        )
+        .await
+    }
+
+    async fn list_service_accounts(&self) -> Result<Vec<ServiceAccount>, OAuth2Error> {
+        let span = db_span!(self, "list_service_accounts");
+        self.observe(
+            "list_service_accounts",
+            span,
+            self.inner.list_service_accounts(),
+        )
+        .await
+    }
+
+    async fn update_service_account(&self, account: &ServiceAccount) -> Result<(), OAuth2Error> {
+        let span = db_span!(
+            self,
+            "update_service_account",
+            service_account.id = %account.id,
+            client_id = %account.client_id
+        );
+        self.observe(
+            "update_service_account",
+            span,
+            self.inner.update_service_account(account),
+        )
+        .await
+    }
+
     async fn save_user(&self, user: &User) -> Result<(), OAuth2Error> {
-        let span = tracing::info_span!(
-            "db",
-            trace_id = field::Empty,
-            span_id = field::Empty,
-            db_system = %self.db_system,
-            db_operation = "save_user",
-            user_id = %user.id,
-            username = %user.username
+        let span = db_span!(
+            self,
+            "save_user",
+            enduser.id = %enduser_id(&user.id)
         );
-        annotate_span_with_trace_ids(&span);
-        async move { self.inner.save_user(user).await }
-            .instrument(span)
+        self.observe("save_user", span, self.inner.save_user(user))
             .await
     }
 
-    async fn get_user_by_username(&self, username: &str) -> Result<Option<User>, OAuth2Error> {
-        let span = tracing::info_span!(
-            "db",
-            trace_id = field::Empty,
-            span_id = field::Empty,
-            db_system = %self.db_system,
-            db_operation = "get_user_by_username",
-            username = %username
+    async fn get_user_by_username(
+        &self,
+        username: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<User>, OAuth2Error> {
+        let span = db_span!(self, "get_user_by_username");
+        self.observe(
+            "get_user_by_username",
+            span,
+            self.inner.get_user_by_username(username, tenant_id),
+        )
+        .await
+    }
+
+    async fn get_user(&self, user_id: &str) -> Result<Option<User>, OAuth2Error> {
+        let span = db_span!(
+            self,
+            "get_user",
+            enduser.id = %enduser_id(user_id)
         );
-        annotate_span_with_trace_ids(&span);
-        async move { self.inner.get_user_by_username(username).await }
-            .instrument(span)
+        self.observe("get_user", span, self.inner.get_user(user_id))
+            .await
+    }
+
+    async fn list_users_by_email(&self, email: &str) -> Result<Vec<User>, OAuth2Error> {
+        let span = self.span("list_users_by_email");
+        self.observe(
+            "list_users_by_email",
+            span,
+            self.inner.list_users_by_email(email),
+        )
+        .await
+    }
+
+    async fn list_users(&self, query: &UserListQuery) -> Result<Vec<User>, OAuth2Error> {
+        let span = db_span!(
+            self,
+            "list_users",
+            has_search = query.search.is_some(),
+            limit = query.limit,
+            offset = query.offset
+        );
+        self.observe("list_users", span, self.inner.list_users(query))
+            .await
+    }
+
+    async fn count_users(&self) -> Result<u64, OAuth2Error> {
+        let span = self.span("count_users");
+        self.observe("count_users", span, self.inner.count_users())
+            .await
+    }
+
+    async fn update_user(&self, user: &User) -> Result<(), OAuth2Error> {
+        let span = db_span!(
+            self,
+            "update_user",
+            enduser.id = %enduser_id(&user.id)
+        );
+        self.observe("update_user", span, self.inner.update_user(user))
+            .await
+    }
+
+    async fn delete_user(&self, user_id: &str) -> Result<(), OAuth2Error> {
+        let span = db_span!(
+            self,
+            "delete_user",
+            enduser.id = %enduser_id(user_id)
+        );
+        self.observe("delete_user", span, self.inner.delete_user(user_id))
             .await
     }
 
     async fn save_token(&self, token: &Token) -> Result<(), OAuth2Error> {
         // Never log full tokens.
-        let token_prefix = Self::token_prefix(&token.access_token);
-        let span = tracing::info_span!(
-            "db",
-            trace_id = field::Empty,
-            span_id = field::Empty,
-            db_system = %self.db_system,
-            db_operation = "save_token",
-            token_prefix = %token_prefix,
+        let redacted = Redactor::current().token(&token.access_token);
+        let span = db_span!(
+            self,
+            "save_token",
+            token = %redacted,
             client_id = %token.client_id,
-            user_id = %token.user_id.as_deref().unwrap_or(""),
+            enduser.id = %token.user_id.as_deref().map(enduser_id).unwrap_or_default(),
             revoked = token.revoked
         );
-        annotate_span_with_trace_ids(&span);
-        async move { self.inner.save_token(token).await }
-            .instrument(span)
+        self.observe("save_token", span, self.inner.save_token(token))
             .await
     }
 
@@ -131,99 +543,647 @@ impl Storage for ObservedStorage {
         &self,
         access_token: &str,
     ) -> Result<Option<Token>, OAuth2Error> {
-        let token_prefix = Self::token_prefix(access_token);
-        let span = tracing::info_span!(
-            "db",
-            trace_id = field::Empty,
-            span_id = field::Empty,
-            db_system = %self.db_system,
-            db_operation = "get_token_by_access_token",
-            token_prefix = %token_prefix,
+        let redacted = Redactor::current().token(access_token);
+        let span = db_span!(
+            self,
+            "get_token_by_access_token",
+            token = %redacted,
             token_len = access_token.len()
         );
-        annotate_span_with_trace_ids(&span);
-        async move { self.inner.get_token_by_access_token(access_token).await }
-            .instrument(span)
-            .await
+        self.observe(
+            "get_token_by_access_token",
+            span,
+            self.inner.get_token_by_access_token(access_token),
+        )
+        .await
+    }
+
+    async fn get_token_by_refresh_token(
+        &self,
+        refresh_token: &str,
+    ) -> Result<Option<Token>, OAuth2Error> {
+        let redacted = Redactor::current().token(refresh_token);
+        let span = db_span!(
+            self,
+            "get_token_by_refresh_token",
+            token = %redacted,
+            token_len = refresh_token.len()
+        );
+        self.observe(
+            "get_token_by_refresh_token",
+            span,
+            self.inner.get_token_by_refresh_token(refresh_token),
+        )
+        .await
     }
 
     async fn revoke_token(&self, token: &str) -> Result<(), OAuth2Error> {
-        let token_prefix = Self::token_prefix(token);
-        let span = tracing::info_span!(
-            "db",
-            trace_id = field::Empty,
-            span_id = field::Empty,
-            db_system = %self.db_system,
-            db_operation = "revoke_token",
-            token_prefix = %token_prefix,
+        let redacted = Redactor::current().token(token);
+        let span = db_span!(
+            self,
+            "revoke_token",
+            token = %redacted,
             token_len = token.len()
         );
-        annotate_span_with_trace_ids(&span);
-        async move { self.inner.revoke_token(token).await }
-            .instrument(span)
+        self.observe("revoke_token", span, self.inner.revoke_token(token))
+            .await
+    }
+
+    async fn revoke_token_grant(&self, grant_id: &str) -> Result<(), OAuth2Error> {
+        let span = db_span!(self, "revoke_token_grant", grant_id = %grant_id);
+        self.observe(
+            "revoke_token_grant",
+            span,
+            self.inner.revoke_token_grant(grant_id),
+        )
+        .await
+    }
+
+    async fn list_revoked_tokens(&self) -> Result<Vec<Token>, OAuth2Error> {
+        let span = db_span!(self, "list_revoked_tokens");
+        self.observe(
+            "list_revoked_tokens",
+            span,
+            self.inner.list_revoked_tokens(),
+        )
+        .await
+    }
+
+    async fn find_tokens_by_metadata(
+        &self,
+        query: &TokenMetadataQuery,
+    ) -> Result<Vec<Token>, OAuth2Error> {
+        let span = db_span!(
+            self,
+            "find_tokens_by_metadata",
+            metadata.key = %query.key,
+            limit = query.limit,
+            offset = query.offset
+        );
+        self.observe(
+            "find_tokens_by_metadata",
+            span,
+            self.inner.find_tokens_by_metadata(query),
+        )
+        .await
+    }
+
+    async fn revoke_tokens_by_metadata(&self, key: &str, value: &str) -> Result<u64, OAuth2Error> {
+        let span = db_span!(self, "revoke_tokens_by_metadata", metadata.key = %key);
+        self.observe(
+            "revoke_tokens_by_metadata",
+            span,
+            self.inner.revoke_tokens_by_metadata(key, value),
+        )
+        .await
+    }
+
+    async fn list_tokens_by_client(
+        &self,
+        client_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Token>, OAuth2Error> {
+        let span = db_span!(
+            self,
+            "list_tokens_by_client",
+            client_id = %client_id,
+            page = page.page,
+            per_page = page.per_page
+        );
+        self.observe(
+            "list_tokens_by_client",
+            span,
+            self.inner.list_tokens_by_client(client_id, page),
+        )
+        .await
+    }
+
+    async fn list_tokens_by_user(
+        &self,
+        user_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Token>, OAuth2Error> {
+        let span = db_span!(
+            self,
+            "list_tokens_by_user",
+            enduser.id = %enduser_id(user_id),
+            page = page.page,
+            per_page = page.per_page
+        );
+        self.observe(
+            "list_tokens_by_user",
+            span,
+            self.inner.list_tokens_by_user(user_id, page),
+        )
+        .await
+    }
+
+    async fn count_tokens(&self) -> Result<u64, OAuth2Error> {
+        let span = self.span("count_tokens");
+        self.observe("count_tokens", span, self.inner.count_tokens())
             .await
     }
 
+    async fn count_active_tokens(&self) -> Result<u64, OAuth2Error> {
+        let span = self.span("count_active_tokens");
+        self.observe(
+            "count_active_tokens",
+            span,
+            self.inner.count_active_tokens(),
+        )
+        .await
+    }
+
     async fn save_authorization_code(
         &self,
         auth_code: &AuthorizationCode,
     ) -> Result<(), OAuth2Error> {
-        let span = tracing::info_span!(
-            "db",
-            trace_id = field::Empty,
-            span_id = field::Empty,
-            db_system = %self.db_system,
-            db_operation = "save_authorization_code",
+        let span = db_span!(
+            self,
+            "save_authorization_code",
             client_id = %auth_code.client_id,
-            user_id = %auth_code.user_id
+            enduser.id = %enduser_id(&auth_code.user_id)
         );
-        annotate_span_with_trace_ids(&span);
-        async move { self.inner.save_authorization_code(auth_code).await }
-            .instrument(span)
-            .await
+        self.observe(
+            "save_authorization_code",
+            span,
+            self.inner.save_authorization_code(auth_code),
+        )
+        .await
     }
 
     async fn get_authorization_code(
         &self,
         code: &str,
     ) -> Result<Option<AuthorizationCode>, OAuth2Error> {
-        let code_prefix = code.chars().take(12).collect::<String>();
-        let span = tracing::info_span!(
-            "db",
-            trace_id = field::Empty,
-            span_id = field::Empty,
-            db_system = %self.db_system,
-            db_operation = "get_authorization_code",
-            code_prefix = %code_prefix,
+        let redacted = Redactor::current().token(code);
+        let span = db_span!(
+            self,
+            "get_authorization_code",
+            code = %redacted,
             code_len = code.len()
         );
-        annotate_span_with_trace_ids(&span);
-        async move { self.inner.get_authorization_code(code).await }
-            .instrument(span)
-            .await
+        self.observe(
+            "get_authorization_code",
+            span,
+            self.inner.get_authorization_code(code),
+        )
+        .await
     }
 
     async fn mark_authorization_code_used(&self, code: &str) -> Result<(), OAuth2Error> {
-        let code_prefix = code.chars().take(12).collect::<String>();
-        let span = tracing::info_span!(
-            "db",
-            trace_id = field::Empty,
-            span_id = field::Empty,
-            db_system = %self.db_system,
-            db_operation = "mark_authorization_code_used",
-            code_prefix = %code_prefix,
+        let redacted = Redactor::current().token(code);
+        let span = db_span!(
+            self,
+            "mark_authorization_code_used",
+            code = %redacted,
             code_len = code.len()
         );
-        annotate_span_with_trace_ids(&span);
-        async move { self.inner.mark_authorization_code_used(code).await }
-            .instrument(span)
+        self.observe(
+            "mark_authorization_code_used",
+            span,
+            self.inner.mark_authorization_code_used(code),
+        )
+        .await
+    }
+
+    async fn begin_transaction(&self) -> Result<Box<dyn StorageTransaction>, OAuth2Error> {
+        let span = self.span("begin_transaction");
+        let inner = self
+            .observe("begin_transaction", span, self.inner.begin_transaction())
+            .await?;
+        Ok(Box::new(ObservedTransaction {
+            inner,
+            db_system: self.db_system.clone(),
+            recorder: self.recorder.clone(),
+        }))
+    }
+
+    async fn save_social_login_state(&self, state: &SocialLoginState) -> Result<(), OAuth2Error> {
+        let span = db_span!(
+            self,
+            "save_social_login_state",
+            provider = %state.provider
+        );
+        self.observe(
+            "save_social_login_state",
+            span,
+            self.inner.save_social_login_state(state),
+        )
+        .await
+    }
+
+    async fn take_social_login_state(
+        &self,
+        state: &str,
+    ) -> Result<Option<SocialLoginState>, OAuth2Error> {
+        let span = self.span("take_social_login_state");
+        self.observe(
+            "take_social_login_state",
+            span,
+            self.inner.take_social_login_state(state),
+        )
+        .await
+    }
+
+    async fn save_email_token(&self, token: &EmailToken) -> Result<(), OAuth2Error> {
+        let span = db_span!(
+            self,
+            "save_email_token",
+            purpose = token.purpose.as_str(),
+            enduser.id = %enduser_id(&token.user_id)
+        );
+        self.observe("save_email_token", span, self.inner.save_email_token(token))
+            .await
+    }
+
+    async fn take_email_token(&self, token_hash: &str) -> Result<Option<EmailToken>, OAuth2Error> {
+        let span = self.span("take_email_token");
+        self.observe(
+            "take_email_token",
+            span,
+            self.inner.take_email_token(token_hash),
+        )
+        .await
+    }
+
+    async fn delete_email_tokens(
+        &self,
+        user_id: &str,
+        purpose: EmailTokenPurpose,
+    ) -> Result<(), OAuth2Error> {
+        let span = db_span!(
+            self,
+            "delete_email_tokens",
+            purpose = purpose.as_str(),
+            enduser.id = %enduser_id(user_id)
+        );
+        self.observe(
+            "delete_email_tokens",
+            span,
+            self.inner.delete_email_tokens(user_id, purpose),
+        )
+        .await
+    }
+
+    async fn save_user_session(&self, session: &UserSession) -> Result<(), OAuth2Error> {
+        let span = self.span("save_user_session");
+        self.observe(
+            "save_user_session",
+            span,
+            self.inner.save_user_session(session),
+        )
+        .await
+    }
+
+    async fn get_user_session(&self, id: &str) -> Result<Option<UserSession>, OAuth2Error> {
+        let span = self.span("get_user_session");
+        self.observe("get_user_session", span, self.inner.get_user_session(id))
+            .await
+    }
+
+    async fn touch_user_session(
+        &self,
+        id: &str,
+        last_seen_at: DateTime<Utc>,
+        idle_expires_at: DateTime<Utc>,
+    ) -> Result<(), OAuth2Error> {
+        let span = self.span("touch_user_session");
+        self.observe(
+            "touch_user_session",
+            span,
+            self.inner
+                .touch_user_session(id, last_seen_at, idle_expires_at),
+        )
+        .await
+    }
+
+    async fn list_user_sessions(&self, user_id: &str) -> Result<Vec<UserSession>, OAuth2Error> {
+        let span = db_span!(
+            self,
+            "list_user_sessions",
+            enduser.id = %enduser_id(user_id)
+        );
+        self.observe(
+            "list_user_sessions",
+            span,
+            self.inner.list_user_sessions(user_id),
+        )
+        .await
+    }
+
+    async fn delete_user_session(&self, id: &str) -> Result<(), OAuth2Error> {
+        let span = self.span("delete_user_session");
+        self.observe(
+            "delete_user_session",
+            span,
+            self.inner.delete_user_session(id),
+        )
+        .await
+    }
+
+    async fn save_audit_record(&self, record: &AuditRecord) -> Result<(), OAuth2Error> {
+        let span = self.span("save_audit_record");
+        self.observe(
+            "save_audit_record",
+            span,
+            self.inner.save_audit_record(record),
+        )
+        .await
+    }
+
+    async fn list_audit_records(
+        &self,
+        query: &AuditQuery,
+    ) -> Result<Vec<AuditRecord>, OAuth2Error> {
+        let span = self.span("list_audit_records");
+        self.observe(
+            "list_audit_records",
+            span,
+            self.inner.list_audit_records(query),
+        )
+        .await
+    }
+
+    async fn enqueue_outbox_message(&self, message: &OutboxMessage) -> Result<(), OAuth2Error> {
+        let span = db_span!(
+            self,
+            "enqueue_outbox_message",
+            event_type = %message.event_type
+        );
+        self.observe(
+            "enqueue_outbox_message",
+            span,
+            self.inner.enqueue_outbox_message(message),
+        )
+        .await
+    }
+
+    async fn claim_outbox_messages(
+        &self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<OutboxMessage>, OAuth2Error> {
+        let span = self.span("claim_outbox_messages");
+        self.observe(
+            "claim_outbox_messages",
+            span,
+            self.inner.claim_outbox_messages(now, lease_until, limit),
+        )
+        .await
+    }
+
+    async fn delete_outbox_message(&self, id: &str) -> Result<(), OAuth2Error> {
+        let span = self.span("delete_outbox_message");
+        self.observe(
+            "delete_outbox_message",
+            span,
+            self.inner.delete_outbox_message(id),
+        )
+        .await
+    }
+
+    async fn reschedule_outbox_message(
+        &self,
+        id: &str,
+        next_attempt_at: DateTime<Utc>,
+        error: &str,
+    ) -> Result<(), OAuth2Error> {
+        let span = self.span("reschedule_outbox_message");
+        self.observe(
+            "reschedule_outbox_message",
+            span,
+            self.inner
+                .reschedule_outbox_message(id, next_attempt_at, error),
+        )
+        .await
+    }
+
+    async fn save_federated_identity(
+        &self,
+        identity: &FederatedIdentity,
+    ) -> Result<(), OAuth2Error> {
+        let span = db_span!(
+            self,
+            "save_federated_identity",
+            provider = %identity.provider,
+            enduser.id = %enduser_id(&identity.user_id)
+        );
+        self.observe(
+            "save_federated_identity",
+            span,
+            self.inner.save_federated_identity(identity),
+        )
+        .await
+    }
+
+    async fn get_federated_identity(
+        &self,
+        provider: &str,
+        provider_user_id: &str,
+    ) -> Result<Option<FederatedIdentity>, OAuth2Error> {
+        let span = db_span!(self, "get_federated_identity", provider = %provider);
+        self.observe(
+            "get_federated_identity",
+            span,
+            self.inner
+                .get_federated_identity(provider, provider_user_id),
+        )
+        .await
+    }
+
+    async fn list_federated_identities(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<FederatedIdentity>, OAuth2Error> {
+        let span = db_span!(
+            self,
+            "list_federated_identities",
+            enduser.id = %enduser_id(user_id)
+        );
+        self.observe(
+            "list_federated_identities",
+            span,
+            self.inner.list_federated_identities(user_id),
+        )
+        .await
+    }
+
+    async fn update_federated_identity_tokens(
+        &self,
+        identity: &FederatedIdentity,
+    ) -> Result<(), OAuth2Error> {
+        let span = db_span!(
+            self,
+            "update_federated_identity_tokens",
+            provider = %identity.provider,
+            enduser.id = %enduser_id(&identity.user_id)
+        );
+        self.observe(
+            "update_federated_identity_tokens",
+            span,
+            self.inner.update_federated_identity_tokens(identity),
+        )
+        .await
+    }
+
+    async fn delete_federated_identity(
+        &self,
+        provider: &str,
+        provider_user_id: &str,
+    ) -> Result<(), OAuth2Error> {
+        let span = db_span!(self, "delete_federated_identity", provider = %provider);
+        self.observe(
+            "delete_federated_identity",
+            span,
+            self.inner
+                .delete_federated_identity(provider, provider_user_id),
+        )
+        .await
+    }
+
+    async fn save_role(&self, role: &Role) -> Result<(), OAuth2Error> {
+        let span = self.span("save_role");
+        self.observe("save_role", span, self.inner.save_role(role))
+            .await
+    }
+
+    async fn list_roles(&self) -> Result<Vec<Role>, OAuth2Error> {
+        let span = self.span("list_roles");
+        self.observe("list_roles", span, self.inner.list_roles())
+            .await
+    }
+
+    async fn delete_role(&self, name: &str) -> Result<(), OAuth2Error> {
+        let span = self.span("delete_role");
+        self.observe("delete_role", span, self.inner.delete_role(name))
+            .await
+    }
+
+    async fn save_group(&self, group: &Group) -> Result<(), OAuth2Error> {
+        let span = self.span("save_group");
+        self.observe("save_group", span, self.inner.save_group(group))
+            .await
+    }
+
+    async fn list_groups(&self) -> Result<Vec<Group>, OAuth2Error> {
+        let span = self.span("list_groups");
+        self.observe("list_groups", span, self.inner.list_groups())
+            .await
+    }
+
+    async fn delete_group(&self, name: &str) -> Result<(), OAuth2Error> {
+        let span = self.span("delete_group");
+        self.observe("delete_group", span, self.inner.delete_group(name))
             .await
     }
 
+    async fn list_user_roles(&self, user_id: &str) -> Result<Vec<String>, OAuth2Error> {
+        let span = db_span!(self, "list_user_roles", enduser.id = %enduser_id(user_id));
+        self.observe("list_user_roles", span, self.inner.list_user_roles(user_id))
+            .await
+    }
+
+    async fn set_user_roles(&self, user_id: &str, roles: &[String]) -> Result<(), OAuth2Error> {
+        let span = db_span!(self, "set_user_roles", enduser.id = %enduser_id(user_id));
+        self.observe(
+            "set_user_roles",
+            span,
+            self.inner.set_user_roles(user_id, roles),
+        )
+        .await
+    }
+
+    async fn list_user_groups(&self, user_id: &str) -> Result<Vec<String>, OAuth2Error> {
+        let span = db_span!(self, "list_user_groups", enduser.id = %enduser_id(user_id));
+        self.observe(
+            "list_user_groups",
+            span,
+            self.inner.list_user_groups(user_id),
+        )
+        .await
+    }
+
+    async fn set_user_groups(&self, user_id: &str, groups: &[String]) -> Result<(), OAuth2Error> {
+        let span = db_span!(self, "set_user_groups", enduser.id = %enduser_id(user_id));
+        self.observe(
+            "set_user_groups",
+            span,
+            self.inner.set_user_groups(user_id, groups),
+        )
+        .await
+    }
+
     async fn healthcheck(&self) -> Result<(), OAuth2Error> {
         let span = self.span("healthcheck");
-        async move { self.inner.healthcheck().await }
-            .instrument(span)
+        self.observe("healthcheck", span, self.inner.healthcheck())
             .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oauth2_ports::testing::FakeStorage;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn calls_are_timed_and_errors_counted_per_operation() {
+        let fake = Arc::new(FakeStorage::new());
+        let metrics = Metrics::new().unwrap();
+        let storage =
+            ObservedStorage::new(fake.clone(), "sqlite".to_string()).with_metrics(&metrics);
+
+        storage.get_client("missing", None).await.unwrap();
+        fake.failures()
+            .fail_next("get_client", OAuth2Error::temporarily_unavailable("down"));
+        assert!(storage.get_client("missing", None).await.is_err());
+        let tx = storage.begin_transaction().await.unwrap();
+        tx.commit().await.unwrap();
+
+        let samples = |operation: &str| {
+            metrics
+                .db_operation_duration_seconds
+                .with_label_values(&["sqlite", operation])
+                .get_sample_count()
+        };
+        let errors = |operation: &str| {
+            metrics
+                .db_operation_errors_total
+                .with_label_values(&["sqlite", operation])
+                .get()
+        };
+        assert_eq!(samples("get_client"), 2);
+        assert_eq!(errors("get_client"), 1);
+        assert_eq!(samples("begin_transaction"), 1);
+        assert_eq!(samples("commit"), 1);
+        assert_eq!(errors("commit"), 0);
+    }
+
+    #[tokio::test]
+    async fn calls_over_the_slow_threshold_are_counted() {
+        let metrics = Metrics::new().unwrap();
+        let slow = |operation: &str| {
+            metrics
+                .storage_slow_operations_total
+                .with_label_values(&["sqlite", operation])
+                .get()
+        };
+
+        let everything_slow =
+            ObservedStorage::new(Arc::new(FakeStorage::new()), "sqlite".to_string())
+                .with_metrics(&metrics)
+                .with_slow_threshold(Duration::ZERO);
+        everything_slow.get_client("missing", None).await.unwrap();
+        everything_slow.count_clients().await.unwrap();
+        assert_eq!(slow("get_client"), 1);
+        assert_eq!(slow("count_clients"), 1);
+
+        let nothing_slow = ObservedStorage::new(Arc::new(FakeStorage::new()), "sqlite".to_string())
+            .with_metrics(&metrics)
+            .with_slow_threshold(Duration::from_secs(60));
+        nothing_slow.get_client("missing", None).await.unwrap();
+        assert_eq!(slow("get_client"), 1);
+    }
+}
@@ -1,20 +1,33 @@
 use opentelemetry::global;
 use opentelemetry_sdk::{trace as sdktrace, Resource};
-use std::sync::OnceLock;
-use tracing::Span;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use std::sync::{Arc, OnceLock};
+use tracing::{Span, Subscriber};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+
+use crate::logging::{LogFormat, LogOptions, RollingFile};
 
 static TELEMETRY_PROVIDER: OnceLock<sdktrace::SdkTracerProvider> = OnceLock::new();
 
+/// Initialize tracing/logging and (optionally) OpenTelemetry export, with JSON logs to
+/// stdout. See [`init_telemetry_with_logs`].
+pub fn init_telemetry(service_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    init_telemetry_with_logs(service_name, &LogOptions::default())
+}
+
 /// Initialize tracing/logging and (optionally) OpenTelemetry export.
 ///
-/// - Always emits structured JSON logs via `tracing_subscriber`.
+/// - Emits logs via `tracing_subscriber` in the format and to the destinations of `logs`.
 /// - Bridges `log` records into `tracing` so `log::info!` etc. are correlated.
 /// - Enables OpenTelemetry spans:
 ///   - If `OTEL_EXPORTER_OTLP_ENDPOINT` (or `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`) is set,
 ///     traces are exported via OTLP.
 ///   - Otherwise, a local tracer provider is installed to generate trace/span IDs for log correlation.
-pub fn init_telemetry(service_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+pub fn init_telemetry_with_logs(
+    service_name: &str,
+    logs: &LogOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
     // Back-compat / convenience: this repo historically documented `OAUTH2_OTLP_ENDPOINT`.
     // OpenTelemetry SDKs use `OTEL_EXPORTER_OTLP_ENDPOINT` (or `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`).
     // If the standard OTEL vars are not set but the app-specific one is, bridge it.
@@ -43,6 +56,15 @@ pub fn init_telemetry(service_name: &str) -> Result<(), Box<dyn std::error::Erro
 
     let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
 
+    let mut formatting_layers = Vec::new();
+    if logs.stdout {
+        formatting_layers.push(formatting_layer(logs, std::io::stdout, true));
+    }
+    if let Some(file) = &logs.file {
+        let file = Arc::new(RollingFile::open(file.clone())?);
+        formatting_layers.push(formatting_layer(logs, file, false));
+    }
+
     // Use W3C trace-context for propagation (traceparent/tracestate).
     global::set_text_map_propagator(opentelemetry_sdk::propagation::TraceContextPropagator::new());
 
@@ -88,15 +110,10 @@ pub fn init_telemetry(service_name: &str) -> Result<(), Box<dyn std::error::Erro
 
     let otel_layer = tracing_opentelemetry::layer().with_tracer(tracer);
 
-    let formatting_layer = tracing_subscriber::fmt::layer()
-        .json()
-        .with_current_span(true)
-        .with_span_list(true);
-
     tracing_subscriber::registry()
+        .with(formatting_layers)
         .with(env_filter)
         .with(otel_layer)
-        .with(formatting_layer)
         .init();
 
     let _ = tracing_log::LogTracer::init();
@@ -104,6 +121,30 @@ pub fn init_telemetry(service_name: &str) -> Result<(), Box<dyn std::error::Erro
     Ok(())
 }
 
+/// A `tracing_subscriber` formatting layer writing to `writer`; `ansi` colors text formats.
+fn formatting_layer<S, W>(
+    logs: &LogOptions,
+    writer: W,
+    ansi: bool,
+) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi);
+    match logs.format {
+        LogFormat::Json => layer
+            .json()
+            .with_current_span(true)
+            .with_span_list(logs.span_list)
+            .boxed(),
+        LogFormat::Pretty => layer.pretty().boxed(),
+        LogFormat::Compact => layer.compact().boxed(),
+    }
+}
+
 /// Record OpenTelemetry trace/span identifiers onto a span.
 ///
 /// This is primarily used to ensure every JSON log line carries `trace_id` and `span_id`
@@ -120,6 +161,16 @@ pub fn annotate_span_with_trace_ids(span: &Span) {
     }
 }
 
+/// OpenTelemetry trace id of `span`, if it is part of a sampled trace.
+pub fn span_trace_id(span: &Span) -> Option<String> {
+    use opentelemetry::trace::TraceContextExt;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    let cx = span.context();
+    let otel_span = cx.span();
+    let sc = otel_span.span_context();
+    sc.is_valid().then(|| sc.trace_id().to_string())
+}
+
 pub fn shutdown_telemetry() {
     if let Some(provider) = TELEMETRY_PROVIDER.get() {
         let _ = provider.shutdown();
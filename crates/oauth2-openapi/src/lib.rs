@@ -13,6 +13,8 @@ use utoipa::OpenApi;
             oauth2_core::IntrospectionResponse,
             oauth2_core::ClientRegistration,
             oauth2_core::ClientCredentials,
+            oauth2_core::ClientUpdateRequest,
+            oauth2_core::ClientInformationResponse,
             oauth2_core::OAuth2Error,
         )
     ),
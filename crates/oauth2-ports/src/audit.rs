@@ -0,0 +1,106 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use oauth2_core::{AuditAction, AuditRecord, OAuth2Error};
+use std::sync::Arc;
+
+use crate::DynStorage;
+
+/// Filters for reading the audit log. Records come newest first.
+#[derive(Debug, Clone)]
+pub struct AuditQuery {
+    /// Only records at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only records before this time.
+    pub until: Option<DateTime<Utc>>,
+    pub actor: Option<String>,
+    pub action: Option<AuditAction>,
+    pub limit: u32,
+}
+
+impl Default for AuditQuery {
+    fn default() -> Self {
+        Self {
+            since: None,
+            until: None,
+            actor: None,
+            action: None,
+            limit: 100,
+        }
+    }
+}
+
+impl AuditQuery {
+    /// Whether `record` passes the filters (the limit aside).
+    pub fn matches(&self, record: &AuditRecord) -> bool {
+        self.since.is_none_or(|since| record.occurred_at >= since)
+            && self.until.is_none_or(|until| record.occurred_at < until)
+            && self
+                .actor
+                .as_deref()
+                .is_none_or(|actor| record.actor.as_deref() == Some(actor))
+            && self.action.is_none_or(|action| record.action == action)
+    }
+}
+
+/// Durable record of security-relevant actions: sign-ins, consent, token issuance and
+/// admin changes.
+///
+/// Implement this to ship the audit log somewhere other than the server's database, e.g.
+/// an append-only store owned by a security team.
+#[async_trait]
+pub trait AuditSink: Send + Sync {
+    /// Short backend identifier used in logs (e.g. `database`).
+    fn backend_name(&self) -> &'static str;
+
+    async fn record(&self, record: &AuditRecord) -> Result<(), OAuth2Error>;
+
+    /// Records matching `query`, newest first.
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, OAuth2Error>;
+}
+
+pub type DynAuditSink = Arc<dyn AuditSink>;
+
+/// Keeps the audit log in the server's storage (the `audit_records` table). The default.
+#[derive(Clone)]
+pub struct StorageAuditSink {
+    storage: DynStorage,
+}
+
+impl StorageAuditSink {
+    pub fn new(storage: DynStorage) -> Self {
+        Self { storage }
+    }
+}
+
+#[async_trait]
+impl AuditSink for StorageAuditSink {
+    fn backend_name(&self) -> &'static str {
+        "database"
+    }
+
+    async fn record(&self, record: &AuditRecord) -> Result<(), OAuth2Error> {
+        self.storage.save_audit_record(record).await
+    }
+
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, OAuth2Error> {
+        self.storage.list_audit_records(query).await
+    }
+}
+
+/// Write `record` to `sink`, when the server has one. A failed write is logged rather than
+/// returned: the action it describes has already happened.
+pub async fn record_audit(sink: Option<&DynAuditSink>, record: AuditRecord) {
+    let Some(sink) = sink else {
+        return;
+    };
+    if let Err(e) = sink.record(&record).await {
+        tracing::error!(
+            backend = sink.backend_name(),
+            action = record.action.as_str(),
+            outcome = record.outcome.as_str(),
+            actor = record.actor.as_deref().unwrap_or_default(),
+            error = %e,
+            "Failed to write audit record"
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use async_trait::async_trait;
+use std::sync::{Arc, OnceLock};
+
+use oauth2_core::{hash_password, verify_password, OAuth2Error, User};
+
+use crate::DynStorage;
+
+/// Checks user passwords for the Resource Owner Password Credentials grant, which the login
+/// page uses too.
+///
+/// Implement this to verify passwords somewhere other than the `users` table, e.g. an LDAP
+/// directory. The returned user must exist in storage, since tokens are issued for its id.
+#[async_trait]
+pub trait UserAuthenticator: Send + Sync {
+    /// Short backend identifier used in logs and events (e.g. `ldap`).
+    fn backend_name(&self) -> &'static str;
+
+    /// The enabled user `username` of tenant `tenant_id` (`None` is the default tenant), if
+    /// `password` is theirs. Unknown users, wrong passwords and disabled accounts are all
+    /// `Ok(None)`; an error means the password could not be checked.
+    async fn authenticate(
+        &self,
+        username: &str,
+        password: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<User>, OAuth2Error>;
+}
+
+pub type DynUserAuthenticator = Arc<dyn UserAuthenticator>;
+
+/// Checks passwords against the Argon2 hashes of users in storage.
+#[derive(Clone)]
+pub struct LocalUserAuthenticator {
+    storage: DynStorage,
+}
+
+impl LocalUserAuthenticator {
+    pub fn new(storage: DynStorage) -> Self {
+        Self { storage }
+    }
+}
+
+#[async_trait]
+impl UserAuthenticator for LocalUserAuthenticator {
+    fn backend_name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn authenticate(
+        &self,
+        username: &str,
+        password: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<User>, OAuth2Error> {
+        let user = self
+            .storage
+            .get_user_by_username(username, tenant_id)
+            .await?;
+        let authenticated = match &user {
+            Some(user) => verify_password(password, &user.password_hash),
+            None => {
+                // Spend the same hashing work as a real check so response timing does not
+                // reveal which usernames exist.
+                let _ = verify_password(password, unknown_user_hash());
+                false
+            }
+        };
+        Ok(user.filter(|user| authenticated && user.enabled))
+    }
+}
+
+/// Argon2 hash verified against when a username is unknown, to equalize timing.
+fn unknown_user_hash() -> &'static str {
+    static HASH: OnceLock<String> = OnceLock::new();
+    HASH.get_or_init(|| hash_password("unknown user").unwrap_or_default())
+}
@@ -0,0 +1,58 @@
+use async_trait::async_trait;
+use oauth2_core::OAuth2Error;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Shared key/value cache with per-entry expiry (e.g. Redis).
+///
+/// Intended for state that must be visible across replicas but is safe to lose, such as
+/// idempotency keys. Callers should treat errors as "cache unavailable" and degrade.
+#[async_trait]
+pub trait Cache: Send + Sync {
+    /// Short backend identifier used in logs and metric labels (e.g. `redis`).
+    fn backend_name(&self) -> &'static str;
+
+    /// Store `value` under `key` only if the key is absent. Returns `true` if it was stored.
+    async fn set_if_absent(
+        &self,
+        key: &str,
+        value: &str,
+        ttl: Duration,
+    ) -> Result<bool, OAuth2Error>;
+
+    async fn get(&self, key: &str) -> Result<Option<String>, OAuth2Error>;
+
+    async fn delete(&self, key: &str) -> Result<(), OAuth2Error>;
+}
+
+pub type DynCache = Arc<dyn Cache>;
+
+/// What an [`InvalidationChannel`] delivers to its subscriber.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Invalidation {
+    /// A message published by any replica, including this one.
+    Message(String),
+    /// Messages may have been missed (e.g. after a reconnect); drop everything cached.
+    Resync,
+}
+
+/// Handler invoked for every [`Invalidation`]; must not block.
+pub type InvalidationHandler = Box<dyn Fn(Invalidation) + Send + Sync>;
+
+/// Broadcast channel that tells every replica to evict in-process cache entries
+/// (e.g. Redis pub/sub).
+///
+/// Delivery is best effort: a subscriber that may have missed messages receives
+/// [`Invalidation::Resync`] instead.
+#[async_trait]
+pub trait InvalidationChannel: Send + Sync {
+    /// Short backend identifier used in logs (e.g. `redis`).
+    fn backend_name(&self) -> &'static str;
+
+    async fn publish(&self, message: &str) -> Result<(), OAuth2Error>;
+
+    /// Deliver every message published from now on to `handler`, until the process exits.
+    async fn subscribe(&self, handler: InvalidationHandler) -> Result<(), OAuth2Error>;
+}
+
+pub type DynInvalidationChannel = Arc<dyn InvalidationChannel>;
@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use oauth2_core::{Claims, OAuth2Error, TokenMetadata, REGISTERED_CLAIMS};
+
+use crate::DynStorage;
+
+/// Hook run for every access token before it is signed.
+///
+/// Enrichers can add custom claims (e.g. a tenant id looked up from the subject) and tag
+/// the stored token with metadata. Returning an error aborts issuance.
+#[async_trait]
+pub trait ClaimsEnricher: Send + Sync {
+    async fn enrich(
+        &self,
+        claims: &mut Claims,
+        metadata: &mut TokenMetadata,
+    ) -> Result<(), OAuth2Error>;
+}
+
+pub type DynClaimsEnricher = Arc<dyn ClaimsEnricher>;
+
+/// Adds the roles and groups assigned to the subject in storage, as arrays of names.
+/// Subjects without any, such as clients, get no claim.
+#[derive(Clone)]
+pub struct RoleClaimsEnricher {
+    storage: DynStorage,
+    roles_claim: Option<String>,
+    groups_claim: Option<String>,
+}
+
+impl RoleClaimsEnricher {
+    /// A `None` claim is left out. Registered claims such as `scope` are rejected.
+    pub fn new(
+        storage: DynStorage,
+        roles_claim: Option<String>,
+        groups_claim: Option<String>,
+    ) -> Result<Self, OAuth2Error> {
+        if let Some(claim) = [&roles_claim, &groups_claim]
+            .into_iter()
+            .flatten()
+            .find(|claim| REGISTERED_CLAIMS.contains(&claim.as_str()))
+        {
+            return Err(OAuth2Error::invalid_request(&format!(
+                "'{claim}' is a registered claim and cannot hold roles or groups"
+            )));
+        }
+        Ok(Self {
+            storage,
+            roles_claim,
+            groups_claim,
+        })
+    }
+}
+
+#[async_trait]
+impl ClaimsEnricher for RoleClaimsEnricher {
+    async fn enrich(
+        &self,
+        claims: &mut Claims,
+        _metadata: &mut TokenMetadata,
+    ) -> Result<(), OAuth2Error> {
+        if let Some(claim) = &self.roles_claim {
+            let roles = self.storage.list_user_roles(&claims.sub).await?;
+            if !roles.is_empty() {
+                claims.set_claim(claim, roles)?;
+            }
+        }
+        if let Some(claim) = &self.groups_claim {
+            let groups = self.storage.list_user_groups(&claims.sub).await?;
+            if !groups.is_empty() {
+                claims.set_claim(claim, groups)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+use async_trait::async_trait;
+
+use oauth2_core::{Claims, OAuth2Error, ScopeSet, TokenMetadata, User, REGISTERED_CLAIMS};
+
+use crate::{ClaimsEnricher, DynStorage};
+
+/// One custom claim: `template` with its `${...}` placeholders filled in, issued when the
+/// token is granted `scope` (always when `None`).
+///
+/// Placeholders are `user.id`, `user.username`, `user.email`, `user.tenant_id`,
+/// `client_id` and `scope`. A claim whose placeholders cannot all be filled, such as
+/// `user.*` in a token issued to a client, is left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClaimRule {
+    pub claim: String,
+    pub template: String,
+    pub scope: Option<String>,
+}
+
+/// Adds the claims of templated [`ClaimRule`]s to access tokens.
+pub struct ClaimsMapper {
+    storage: DynStorage,
+    rules: Vec<CompiledRule>,
+}
+
+struct CompiledRule {
+    claim: String,
+    segments: Vec<Segment>,
+    scope: Option<String>,
+}
+
+enum Segment {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Placeholder {
+    UserId,
+    Username,
+    UserEmail,
+    UserTenantId,
+    ClientId,
+    Scope,
+}
+
+impl Placeholder {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name.trim() {
+            "user.id" => Self::UserId,
+            "user.username" => Self::Username,
+            "user.email" => Self::UserEmail,
+            "user.tenant_id" => Self::UserTenantId,
+            "client_id" => Self::ClientId,
+            "scope" => Self::Scope,
+            _ => return None,
+        })
+    }
+
+    fn needs_user(self) -> bool {
+        matches!(
+            self,
+            Self::UserId | Self::Username | Self::UserEmail | Self::UserTenantId
+        )
+    }
+
+    fn value<'a>(self, claims: &'a Claims, user: Option<&'a User>) -> Option<&'a str> {
+        match self {
+            Self::UserId => user.map(|u| u.id.as_str()),
+            Self::Username => user.map(|u| u.username.as_str()),
+            Self::UserEmail => user.map(|u| u.email.as_str()),
+            Self::UserTenantId => user.and_then(|u| u.tenant_id.as_deref()),
+            Self::ClientId => claims.client_id.as_deref(),
+            Self::Scope => Some(claims.scope.as_str()),
+        }
+    }
+}
+
+impl ClaimsMapper {
+    /// Fails on registered claims (`sub`, `scope`, ...), unknown placeholders and
+    /// unterminated `${`.
+    pub fn new(storage: DynStorage, rules: Vec<ClaimRule>) -> Result<Self, OAuth2Error> {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                if rule.claim.is_empty() || REGISTERED_CLAIMS.contains(&rule.claim.as_str()) {
+                    return Err(OAuth2Error::invalid_request(&format!(
+                        "claims.{} cannot be mapped",
+                        rule.claim
+                    )));
+                }
+                let segments = parse_template(&rule.template).map_err(|e| {
+                    OAuth2Error::invalid_request(&format!("claims.{}: {e}", rule.claim))
+                })?;
+                Ok(CompiledRule {
+                    claim: rule.claim,
+                    segments,
+                    scope: rule.scope,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { storage, rules })
+    }
+}
+
+fn parse_template(template: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        if start > 0 {
+            segments.push(Segment::Literal(rest[..start].to_string()));
+        }
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| format!("unterminated placeholder in \"{template}\""))?;
+        let name = &after[..end];
+        let placeholder =
+            Placeholder::parse(name).ok_or_else(|| format!("unknown placeholder ${{{name}}}"))?;
+        segments.push(Segment::Placeholder(placeholder));
+        rest = &after[end + 1..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Literal(rest.to_string()));
+    }
+    Ok(segments)
+}
+
+#[async_trait]
+impl ClaimsEnricher for ClaimsMapper {
+    async fn enrich(
+        &self,
+        claims: &mut Claims,
+        _metadata: &mut TokenMetadata,
+    ) -> Result<(), OAuth2Error> {
+        let granted = ScopeSet::parse(&claims.scope);
+        let applicable: Vec<&CompiledRule> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.scope.as_deref().is_none_or(|s| granted.contains(s)))
+            .collect();
+        let needs_user = applicable.iter().any(|rule| {
+            rule.segments
+                .iter()
+                .any(|s| matches!(s, Segment::Placeholder(p) if p.needs_user()))
+        });
+        let user = if needs_user {
+            self.storage.get_user(&claims.sub).await?
+        } else {
+            None
+        };
+
+        let mut values = Vec::with_capacity(applicable.len());
+        for rule in applicable {
+            let value: Option<String> = rule
+                .segments
+                .iter()
+                .map(|segment| match segment {
+                    Segment::Literal(text) => Some(text.as_str()),
+                    Segment::Placeholder(p) => p.value(claims, user.as_ref()),
+                })
+                .collect();
+            if let Some(value) = value {
+                values.push((&rule.claim, value));
+            }
+        }
+        for (claim, value) in values {
+            claims.set_claim(claim, value)?;
+        }
+        Ok(())
+    }
+}
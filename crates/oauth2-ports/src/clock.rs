@@ -0,0 +1,22 @@
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+
+/// Source of the current time.
+///
+/// Components that stamp issued artifacts (e.g. token `iat`/`exp`) take a clock so tests
+/// can pin time instead of asserting on ranges.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system's wall clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+pub type DynClock = Arc<dyn Clock>;
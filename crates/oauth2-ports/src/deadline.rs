@@ -0,0 +1,164 @@
+//! Request deadlines.
+//!
+//! Front ends set a deadline for each request (the actix server's `RequestTimeout`
+//! middleware, for one) and pass it on with [`Deadline::current`]. Work run under
+//! [`Deadline::run`] stops once it has passed: storage calls are dropped and best-effort
+//! event publishes are abandoned. Actor handlers and spawned tasks run detached from the
+//! request, so without this a slow query keeps running after the client has been answered
+//! with a timeout.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use oauth2_core::OAuth2Error;
+
+tokio::task_local! {
+    static CURRENT: Deadline;
+}
+
+/// Point in time after which the result of some work is no longer wanted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Deadline(Option<Instant>);
+
+impl Deadline {
+    /// No deadline: work always runs to completion.
+    pub const fn none() -> Self {
+        Self(None)
+    }
+
+    pub fn at(instant: Instant) -> Self {
+        Self(Some(instant))
+    }
+
+    pub fn after(timeout: Duration) -> Self {
+        Self(Instant::now().checked_add(timeout))
+    }
+
+    /// Deadline of the request being handled; none outside a request, or if its route has
+    /// no timeout.
+    pub fn current() -> Self {
+        CURRENT.try_with(|deadline| *deadline).unwrap_or_default()
+    }
+
+    pub fn instant(&self) -> Option<Instant> {
+        self.0
+    }
+
+    /// Time left, or `None` without a deadline.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.0
+            .map(|instant| instant.saturating_duration_since(Instant::now()))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_some_and(|left| left.is_zero())
+    }
+
+    /// Whichever of the two deadlines comes first.
+    pub fn min(self, other: Self) -> Self {
+        match (self.0, other.0) {
+            (Some(a), Some(b)) => Self(Some(a.min(b))),
+            (a, b) => Self(a.or(b)),
+        }
+    }
+
+    /// Run `fut` with this as the [current](Self::current) deadline. An enclosing deadline
+    /// that comes first is kept.
+    pub async fn scope<F: Future>(self, fut: F) -> F::Output {
+        CURRENT.scope(self.min(Self::current()), fut).await
+    }
+
+    /// Run `fut` until the deadline, as the [current](Self::current) one. After it, `fut`
+    /// is dropped, cancelling whatever it was waiting on, and `temporarily_unavailable` is
+    /// returned. Work is not started at all if the deadline has already passed, e.g. while
+    /// the message sat in an actor's mailbox.
+    pub async fn run<T, F>(self, fut: F) -> Result<T, OAuth2Error>
+    where
+        F: Future<Output = Result<T, OAuth2Error>>,
+    {
+        let fut = self.scope(fut);
+        let Some(instant) = self.0 else {
+            return fut.await;
+        };
+        if self.is_expired() {
+            tracing::debug!("request deadline passed before work started");
+            return Err(deadline_exceeded());
+        }
+
+        match tokio::time::timeout_at(instant.into(), fut).await {
+            Ok(result) => result,
+            Err(_) => {
+                tracing::debug!("request deadline passed; abandoning work");
+                Err(deadline_exceeded())
+            }
+        }
+    }
+}
+
+fn deadline_exceeded() -> OAuth2Error {
+    OAuth2Error::temporarily_unavailable("Request deadline exceeded")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn work_past_the_deadline_is_dropped() {
+        let finished = Arc::new(AtomicBool::new(false));
+        let work = {
+            let finished = finished.clone();
+            async move {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                finished.store(true, Ordering::SeqCst);
+                Ok(())
+            }
+        };
+
+        let err = Deadline::after(Duration::from_millis(20))
+            .run(work)
+            .await
+            .unwrap_err();
+        assert_eq!(err.error, "temporarily_unavailable");
+        assert!(!finished.load(Ordering::SeqCst));
+
+        assert_eq!(Deadline::none().run(async { Ok(1) }).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn expired_deadlines_do_not_start_work() {
+        let deadline = Deadline::at(Instant::now() - Duration::from_secs(1));
+        assert!(deadline.is_expired());
+
+        let started = AtomicBool::new(false);
+        let result = deadline
+            .run(async {
+                started.store(true, Ordering::SeqCst);
+                Ok(())
+            })
+            .await;
+        assert!(result.is_err());
+        assert!(!started.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn scopes_keep_the_earliest_deadline() {
+        assert_eq!(Deadline::current(), Deadline::none());
+
+        let outer = Deadline::after(Duration::from_secs(1));
+        let later = Deadline::after(Duration::from_secs(60));
+        outer
+            .scope(async {
+                assert_eq!(Deadline::current(), outer);
+                later
+                    .scope(async { assert_eq!(Deadline::current(), outer) })
+                    .await;
+            })
+            .await;
+
+        let ran = outer.run(async { Ok(Deadline::current()) }).await.unwrap();
+        assert_eq!(ran, outer);
+    }
+}
@@ -0,0 +1,21 @@
+use async_trait::async_trait;
+use oauth2_core::OAuth2Error;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Where idempotency keys of ingested events are remembered, so a retried delivery is
+/// recognized as a duplicate.
+///
+/// Only a backend shared by every replica (e.g. Redis) detects a retry that lands on a
+/// different replica. Callers should treat errors as "backend unavailable" and degrade.
+#[async_trait]
+pub trait IdempotencyBackend: Send + Sync {
+    /// Short backend identifier used in logs and metric labels (e.g. `redis`).
+    fn backend_name(&self) -> &'static str;
+
+    /// Remember `key` for `ttl` unless it is already remembered. Returns `true` if it was
+    /// (a duplicate), atomically with recording it otherwise.
+    async fn check_and_record(&self, key: &str, ttl: Duration) -> Result<bool, OAuth2Error>;
+}
+
+pub type DynIdempotencyBackend = Arc<dyn IdempotencyBackend>;
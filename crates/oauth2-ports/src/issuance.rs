@@ -0,0 +1,107 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use oauth2_core::{OAuth2Error, ScopeSet, REGISTERED_CLAIMS};
+
+/// Business rules checked before every token is issued, at the token endpoint and on
+/// refresh.
+///
+/// A policy can deny issuance by returning an error (usually
+/// [`OAuth2Error::access_denied`], which the client receives), narrow the scope, or add
+/// access token claims. Claims enrichers run afterwards, on the narrowed scope.
+#[async_trait]
+pub trait TokenIssuancePolicy: Send + Sync {
+    async fn check(&self, issuance: &mut TokenIssuance) -> Result<(), OAuth2Error>;
+}
+
+pub type DynTokenIssuancePolicy = Arc<dyn TokenIssuancePolicy>;
+
+/// A token about to be issued, as seen by a [`TokenIssuancePolicy`].
+#[derive(Debug, Clone)]
+pub struct TokenIssuance {
+    grant_type: String,
+    client_id: String,
+    user_id: Option<String>,
+    tenant_id: Option<String>,
+    scope: String,
+    claims: serde_json::Map<String, serde_json::Value>,
+}
+
+impl TokenIssuance {
+    pub fn new(
+        grant_type: impl Into<String>,
+        client_id: impl Into<String>,
+        user_id: Option<String>,
+        tenant_id: Option<String>,
+        scope: impl Into<String>,
+    ) -> Self {
+        Self {
+            grant_type: grant_type.into(),
+            client_id: client_id.into(),
+            user_id,
+            tenant_id,
+            scope: scope.into(),
+            claims: serde_json::Map::new(),
+        }
+    }
+
+    /// Grant the token is issued under, e.g. `authorization_code` or `refresh_token`.
+    pub fn grant_type(&self) -> &str {
+        &self.grant_type
+    }
+
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    /// Resource owner; `None` for client tokens.
+    pub fn user_id(&self) -> Option<&str> {
+        self.user_id.as_deref()
+    }
+
+    /// Issuing tenant; `None` for the default tenant.
+    pub fn tenant_id(&self) -> Option<&str> {
+        self.tenant_id.as_deref()
+    }
+
+    /// Scope the token will be granted, as already checked against the client.
+    pub fn scope(&self) -> &str {
+        &self.scope
+    }
+
+    /// Keep only the scopes also in `allowed`. Fails with `invalid_scope` when none remain.
+    pub fn narrow_scope(&mut self, allowed: &str) -> Result<(), OAuth2Error> {
+        let current = ScopeSet::parse(&self.scope);
+        let narrowed = current.intersection(&ScopeSet::parse(allowed));
+        if narrowed.is_empty() {
+            return Err(OAuth2Error::invalid_scope(
+                "none of the requested scopes may be granted",
+            ));
+        }
+        if narrowed != current {
+            self.scope = narrowed.to_string();
+        }
+        Ok(())
+    }
+
+    /// Add (or replace) an access token claim. Registered claims such as `sub` are rejected.
+    pub fn set_claim(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Result<(), OAuth2Error> {
+        let name = name.into();
+        if REGISTERED_CLAIMS.contains(&name.as_str()) {
+            return Err(OAuth2Error::invalid_request(&format!(
+                "'{name}' is a registered claim and cannot be overridden"
+            )));
+        }
+        self.claims.insert(name, value.into());
+        Ok(())
+    }
+
+    /// Claims added by policies.
+    pub fn claims(&self) -> &serde_json::Map<String, serde_json::Value> {
+        &self.claims
+    }
+}
@@ -1,8 +1,42 @@
 //! Integration ports for the OAuth2 server.
 //!
 //! Implement these traits in your own crate to plug in custom persistence or other
-//! infrastructure without forking.
+//! infrastructure without forking. [`service`] holds the token, code and client operations
+//! every front end (actix, axum, gRPC) runs on top of them.
+//!
+//! With the `testing` feature, [`testing`] provides deterministic in-memory
+//! implementations for integration tests.
 
+pub mod audit;
+pub mod authenticator;
+pub mod cache;
+pub mod claims;
+pub mod claims_mapper;
+pub mod clock;
+pub mod deadline;
+pub mod idempotency;
+pub mod issuance;
+pub mod secrets;
+pub mod security_events;
+pub mod service;
+pub mod session_store;
+pub mod state_store;
 pub mod storage;
+#[cfg(feature = "testing")]
+pub mod testing;
 
+pub use audit::*;
+pub use authenticator::*;
+pub use cache::*;
+pub use claims::*;
+pub use claims_mapper::*;
+pub use clock::*;
+pub use deadline::*;
+pub use idempotency::*;
+pub use issuance::*;
+pub use secrets::*;
+pub use security_events::*;
+pub use service::*;
+pub use session_store::*;
+pub use state_store::*;
 pub use storage::*;
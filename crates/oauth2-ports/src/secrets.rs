@@ -0,0 +1,19 @@
+use async_trait::async_trait;
+use oauth2_core::OAuth2Error;
+use std::sync::Arc;
+
+/// External secret store (e.g. HashiCorp Vault, AWS Secrets Manager).
+///
+/// A secret setting written as `<scheme>:<reference>` is replaced at startup with what
+/// the provider for `scheme` returns, so the secret itself never appears in the
+/// configuration or the environment.
+#[async_trait]
+pub trait SecretProvider: Send + Sync {
+    /// Prefix of the references this provider resolves (e.g. `vault`).
+    fn scheme(&self) -> &'static str;
+
+    /// The secret `reference` (everything after `<scheme>:`) points to.
+    async fn get_secret(&self, reference: &str) -> Result<String, OAuth2Error>;
+}
+
+pub type DynSecretProvider = Arc<dyn SecretProvider>;
@@ -0,0 +1,66 @@
+//! Security events for SIEMs: failed client authentication, abuse of the authorization
+//! code flow and exhausted quotas, tagged with where the request came from.
+
+use oauth2_events::{AuthEvent, EventBusHandle, EventEnvelope, EventSeverity, EventType};
+
+/// Publishes security events with the caller's IP address and user agent as `ip_address`
+/// and `user_agent` metadata.
+///
+/// Front ends build one per request from their own request type. Events are dropped
+/// without an event bus.
+#[derive(Clone, Default)]
+pub struct SecurityEvents {
+    event_bus: Option<EventBusHandle>,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+}
+
+impl SecurityEvents {
+    pub fn new(
+        event_bus: Option<EventBusHandle>,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+    ) -> Self {
+        Self {
+            event_bus,
+            ip_address,
+            user_agent,
+        }
+    }
+
+    /// Publish a warning-level `event_type` about `client_id`.
+    pub fn publish(
+        &self,
+        event_type: EventType,
+        client_id: Option<&str>,
+        user_id: Option<&str>,
+        metadata: &[(&str, &str)],
+    ) {
+        self.publish_event(
+            AuthEvent::new(
+                event_type,
+                EventSeverity::Warning,
+                user_id.map(str::to_string),
+                client_id.map(str::to_string),
+            ),
+            metadata,
+        );
+    }
+
+    /// Publish `event` as built, e.g. with a higher severity.
+    pub fn publish_event(&self, mut event: AuthEvent, metadata: &[(&str, &str)]) {
+        let Some(event_bus) = &self.event_bus else {
+            return;
+        };
+        for (key, value) in metadata {
+            event = event.with_metadata(*key, *value);
+        }
+        if let Some(ip_address) = &self.ip_address {
+            event = event.with_metadata("ip_address", ip_address.as_str());
+        }
+        if let Some(user_agent) = &self.user_agent {
+            event = event.with_metadata("user_agent", user_agent.as_str());
+        }
+        event_bus.publish_best_effort(EventEnvelope::from_current_span(event, "oauth2_server"));
+    }
+}
@@ -0,0 +1,297 @@
+use std::sync::Arc;
+
+use oauth2_core::{AuthorizationCode, ContextBinding, ContextTolerance, OAuth2Error, User};
+use oauth2_events::{AuthEvent, EventBusHandle, EventSeverity, EventType};
+
+use super::{generate_token, publish};
+use crate::{DynStorage, DynUserAuthenticator, LocalUserAuthenticator, SecurityEvents};
+
+/// Issues and redeems authorization codes and checks end-user passwords.
+#[derive(Clone)]
+pub struct AuthService {
+    storage: DynStorage,
+    event_bus: Option<EventBusHandle>,
+    bind_context: bool,
+    context_tolerance: ContextTolerance,
+    authenticator: DynUserAuthenticator,
+}
+
+impl AuthService {
+    pub fn new(storage: DynStorage) -> Self {
+        Self {
+            authenticator: Arc::new(LocalUserAuthenticator::new(storage.clone())),
+            storage,
+            event_bus: None,
+            bind_context: false,
+            context_tolerance: ContextTolerance::default(),
+        }
+    }
+
+    pub fn with_events(mut self, event_bus: EventBusHandle) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Check passwords with `authenticator` instead of the hashes in storage.
+    pub fn with_authenticator(mut self, authenticator: DynUserAuthenticator) -> Self {
+        self.authenticator = authenticator;
+        self
+    }
+
+    /// Record the requesting context on new codes and reject redemption from a context
+    /// `tolerance` does not accept.
+    pub fn with_context_binding(mut self, enabled: bool, tolerance: ContextTolerance) -> Self {
+        self.bind_context = enabled;
+        self.context_tolerance = tolerance;
+        self
+    }
+
+    /// Issue and save an authorization code.
+    pub async fn create_authorization_code(
+        &self,
+        request: NewAuthorizationCode,
+    ) -> Result<AuthorizationCode, OAuth2Error> {
+        let auth_code = AuthorizationCode::new(
+            generate_token(32),
+            request.client_id.clone(),
+            request.user_id.clone(),
+            request.redirect_uri.clone(),
+            request.scope.clone(),
+            request.code_challenge,
+            request.code_challenge_method,
+        )
+        .with_context_binding(self.bind_context.then(|| request.context.encode()))
+        .with_tenant_id(request.tenant_id);
+
+        self.storage.save_authorization_code(&auth_code).await?;
+
+        publish(
+            self.event_bus.as_ref(),
+            AuthEvent::new(
+                EventType::AuthorizationCodeCreated,
+                EventSeverity::Info,
+                Some(request.user_id),
+                Some(request.client_id),
+            )
+            .with_metadata("scope", request.scope)
+            .with_metadata("redirect_uri", request.redirect_uri),
+        );
+
+        Ok(auth_code)
+    }
+
+    /// Check an authorization code presented at the token endpoint, without consuming it.
+    /// Codes issued by another tenant are not found. Replayed codes and missing verifiers
+    /// are reported through `security`.
+    pub async fn validate_authorization_code(
+        &self,
+        request: CodeRedemption,
+        security: &SecurityEvents,
+    ) -> Result<AuthorizationCode, OAuth2Error> {
+        let auth_code = self
+            .storage
+            .get_authorization_code(&request.code)
+            .await?
+            .filter(|code| code.in_tenant(request.tenant_id.as_deref()))
+            .ok_or_else(|| OAuth2Error::invalid_grant("Authorization code not found"))?;
+
+        if auth_code.used {
+            // A redeemed code presented again was most likely intercepted.
+            security.publish_event(
+                AuthEvent::new(
+                    EventType::AuthorizationCodeReplayDetected,
+                    EventSeverity::Error,
+                    Some(auth_code.user_id.clone()),
+                    Some(auth_code.client_id.clone()),
+                ),
+                &[("presented_by", &request.client_id)],
+            );
+
+            return Err(OAuth2Error::invalid_grant(
+                "Authorization code is expired or used",
+            ));
+        }
+
+        if !auth_code.is_valid() {
+            publish(
+                self.event_bus.as_ref(),
+                AuthEvent::new(
+                    EventType::AuthorizationCodeExpired,
+                    EventSeverity::Warning,
+                    Some(auth_code.user_id.clone()),
+                    Some(auth_code.client_id.clone()),
+                ),
+            );
+
+            return Err(OAuth2Error::invalid_grant(
+                "Authorization code is expired or used",
+            ));
+        }
+
+        if auth_code.client_id != request.client_id {
+            return Err(OAuth2Error::invalid_grant("Client ID mismatch"));
+        }
+
+        // OAuth 2.1 removes redirect_uri from the authorization_code token request. For
+        // backward compatibility (OAuth 2.0 clients), we still accept it and enforce it
+        // when provided.
+        if let Some(redirect_uri) = request.redirect_uri {
+            if auth_code.redirect_uri != redirect_uri {
+                return Err(OAuth2Error::invalid_grant("Redirect URI mismatch"));
+            }
+        }
+
+        // Codes issued before binding was enabled carry no context and are exempt.
+        let issued_context = auth_code
+            .context_binding
+            .as_deref()
+            .and_then(ContextBinding::decode);
+        if let (true, Some(issued)) = (self.bind_context, issued_context) {
+            if !issued.is_compatible_with(&request.context, self.context_tolerance) {
+                tracing::warn!(
+                    client_id = %auth_code.client_id,
+                    "Authorization code redeemed from a different network"
+                );
+                publish(
+                    self.event_bus.as_ref(),
+                    AuthEvent::new(
+                        EventType::AuthorizationCodeContextMismatch,
+                        EventSeverity::Warning,
+                        Some(auth_code.user_id.clone()),
+                        Some(auth_code.client_id.clone()),
+                    ),
+                );
+
+                return Err(OAuth2Error::invalid_grant(
+                    "Authorization code was issued to a different context",
+                ));
+            }
+        }
+
+        if let Some(challenge) = &auth_code.code_challenge {
+            let Some(verifier) = request.code_verifier else {
+                security.publish(
+                    EventType::PkceDowngradeAttempt,
+                    Some(&auth_code.client_id),
+                    Some(&auth_code.user_id),
+                    &[("code_verifier", "none")],
+                );
+                return Err(OAuth2Error::invalid_grant("Code verifier required"));
+            };
+
+            let method = auth_code.code_challenge_method.as_deref().unwrap_or("S256");
+            if !validate_pkce(challenge, &verifier, method) {
+                return Err(OAuth2Error::invalid_grant("Invalid code verifier"));
+            }
+        }
+
+        Ok(auth_code)
+    }
+
+    /// Mark a code used outside of token issuance. Marking an already-used code used
+    /// again is safe.
+    pub async fn mark_authorization_code_used(&self, code: &str) -> Result<(), OAuth2Error> {
+        let auth_code = self
+            .storage
+            .get_authorization_code(code)
+            .await?
+            .ok_or_else(|| OAuth2Error::invalid_grant("Authorization code not found"))?;
+
+        self.storage.mark_authorization_code_used(code).await?;
+
+        publish(
+            self.event_bus.as_ref(),
+            AuthEvent::new(
+                EventType::AuthorizationCodeValidated,
+                EventSeverity::Info,
+                Some(auth_code.user_id),
+                Some(auth_code.client_id),
+            ),
+        );
+        Ok(())
+    }
+
+    /// Check a user's password for the Resource Owner Password Credentials grant. Users
+    /// of tenants other than `tenant_id` are unknown.
+    pub async fn authenticate_user(
+        &self,
+        username: &str,
+        password: &str,
+        client_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<User, OAuth2Error> {
+        let user = self
+            .authenticator
+            .authenticate(username, password, tenant_id)
+            .await?;
+
+        let event = match &user {
+            Some(user) => AuthEvent::new(
+                EventType::UserAuthenticated,
+                EventSeverity::Info,
+                Some(user.id.clone()),
+                Some(client_id.to_string()),
+            ),
+            None => AuthEvent::new(
+                EventType::UserAuthenticationFailed,
+                EventSeverity::Warning,
+                None,
+                Some(client_id.to_string()),
+            ),
+        }
+        .with_metadata("method", "password")
+        .with_metadata("backend", self.authenticator.backend_name());
+        publish(self.event_bus.as_ref(), event);
+
+        // One error for unknown users, wrong passwords and disabled accounts.
+        user.ok_or_else(|| OAuth2Error::invalid_grant("Invalid username or password"))
+    }
+}
+
+/// An authorization code to issue with [`AuthService::create_authorization_code`].
+#[derive(Debug, Clone)]
+pub struct NewAuthorizationCode {
+    pub client_id: String,
+    pub user_id: String,
+    pub redirect_uri: String,
+    pub scope: String,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    /// Context of the authorize request; recorded only when binding is enabled.
+    pub context: ContextBinding,
+    /// Tenant the authorize request was addressed to; only it can redeem the code.
+    pub tenant_id: Option<String>,
+}
+
+/// An authorization code presented at the token endpoint.
+#[derive(Debug, Clone)]
+pub struct CodeRedemption {
+    pub code: String,
+    pub client_id: String,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    /// Context of the token request, compared against the one recorded on the code.
+    pub context: ContextBinding,
+    /// Tenant the token request was addressed to.
+    pub tenant_id: Option<String>,
+}
+
+fn validate_pkce(challenge: &str, verifier: &str, method: &str) -> bool {
+    // RFC 7636: code_verifier length MUST be between 43 and 128 characters.
+    // We validate this early so short verifiers can't be used to weaken PKCE.
+    if verifier.len() < 43 || verifier.len() > 128 {
+        return false;
+    }
+
+    match method {
+        // Only S256 is supported (OAuth 2.0 Security BCP guidance).
+        "S256" => {
+            use base64::{engine::general_purpose, Engine as _};
+            use sha2::{Digest, Sha256};
+
+            let encoded = general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(verifier));
+            challenge == encoded
+        }
+        _ => false,
+    }
+}
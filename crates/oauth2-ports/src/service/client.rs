@@ -0,0 +1,636 @@
+use std::sync::OnceLock;
+
+use chrono::{Duration, Utc};
+use oauth2_core::{
+    hash_password, verify_password, Client, ClientRegistration, ClientUpdateRequest, GrantType,
+    OAuth2Error, RedirectUriChangePolicy, ServiceAccount, DEFAULT_ADMIN_SCOPE,
+    JWT_BEARER_ASSERTION_TYPE,
+};
+use oauth2_events::{AuthEvent, EventBusHandle, EventSeverity, EventType};
+
+use super::{generate_token, publish, reject_reserved_scopes, validate_scope_subset};
+use crate::{DynStorage, SecurityEvents};
+
+/// Length of generated registration access tokens (~285 bits of entropy).
+const REGISTRATION_TOKEN_LENGTH: usize = 48;
+
+/// A new client secret (or other shared secret) to hand out once.
+pub fn generate_secret() -> String {
+    generate_token(32)
+}
+
+/// Registers, authenticates and manages OAuth2 clients.
+#[derive(Clone)]
+pub struct ClientService {
+    storage: DynStorage,
+    event_bus: Option<EventBusHandle>,
+    redirect_uri_changes: RedirectUriChangePolicy,
+    redirect_uri_change_delay: Duration,
+    admin_scope: String,
+}
+
+impl ClientService {
+    pub fn new(storage: DynStorage) -> Self {
+        Self {
+            storage,
+            event_bus: None,
+            redirect_uri_changes: RedirectUriChangePolicy::Immediate,
+            redirect_uri_change_delay: Duration::zero(),
+            admin_scope: DEFAULT_ADMIN_SCOPE.to_string(),
+        }
+    }
+
+    pub fn with_events(mut self, event_bus: EventBusHandle) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Hold back redirect URI changes made through registration updates (see
+    /// [`RedirectUriChangePolicy`]). `delay_secs` only applies to `Delayed`.
+    pub fn with_redirect_uri_changes(
+        mut self,
+        policy: RedirectUriChangePolicy,
+        delay_secs: u64,
+    ) -> Self {
+        self.redirect_uri_changes = policy;
+        self.redirect_uri_change_delay = Duration::seconds(delay_secs as i64);
+        self
+    }
+
+    /// Refuse `admin_scope` (besides `admin`) in registrations, as it grants
+    /// `security_admin` (see `security.admin_scope`).
+    pub fn with_admin_scope(mut self, admin_scope: impl Into<String>) -> Self {
+        self.admin_scope = admin_scope.into();
+        self
+    }
+
+    /// Register a client (RFC 7591), generating its credentials.
+    pub async fn register(
+        &self,
+        registration: ClientRegistration,
+        tenant_id: Option<String>,
+    ) -> Result<RegisteredClient, OAuth2Error> {
+        reject_reserved_scopes(&registration.scope, &self.admin_scope)?;
+
+        let client_id = format!("client_{}", uuid::Uuid::new_v4());
+        let client_type = registration.client_type;
+        let client_secret = (!client_type.is_public()).then(generate_secret);
+        let registration_access_token = generate_token(REGISTRATION_TOKEN_LENGTH);
+
+        let mut client = Client::new(
+            client_id.clone(),
+            String::new(),
+            registration
+                .redirect_uris
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            registration.grant_types,
+            registration.scope.clone(),
+            registration.client_name.clone(),
+        );
+        client.client_type = client_type;
+        client.tenant_id = tenant_id;
+        client.set_metadata(registration.metadata);
+        if let Some(secret) = &client_secret {
+            client.set_client_secret(secret)?;
+        }
+        client.set_registration_access_token(&registration_access_token);
+
+        self.storage.save_client(&client).await?;
+
+        publish(
+            self.event_bus.as_ref(),
+            AuthEvent::new(
+                EventType::ClientRegistered,
+                EventSeverity::Info,
+                None,
+                Some(client_id),
+            )
+            .with_metadata("client_name", registration.client_name)
+            .with_metadata("scope", registration.scope),
+        );
+
+        Ok(RegisteredClient {
+            client,
+            client_secret,
+            registration_access_token,
+        })
+    }
+
+    /// The client `client_id` of the tenant `tenant_id`; clients of other tenants are not
+    /// found.
+    pub async fn get(
+        &self,
+        client_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Client, OAuth2Error> {
+        self.storage
+            .get_client(client_id, tenant_id)
+            .await?
+            .ok_or_else(|| OAuth2Error::invalid_client("Client not found"))
+    }
+
+    /// The service account backed by a client, if any.
+    pub async fn get_service_account(
+        &self,
+        client_id: &str,
+    ) -> Result<Option<ServiceAccount>, OAuth2Error> {
+        self.storage
+            .get_service_account_by_client_id(client_id)
+            .await
+    }
+
+    /// Whether `client_secret` is the secret of the client `client_id` of the tenant
+    /// `tenant_id`. Unknown clients fail with `invalid_client`.
+    pub async fn validate(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<bool, OAuth2Error> {
+        let Some(client) = self.storage.get_client(client_id, tenant_id).await? else {
+            // Spend the same hashing work as a real check so response timing does not
+            // reveal which client ids exist.
+            let _ = verify_password(client_secret, unknown_client_hash());
+            return Err(OAuth2Error::invalid_client("Client not found"));
+        };
+
+        let secret_match = client.verify_client_secret(client_secret);
+
+        // Transparently upgrade secrets stored before hashing was introduced.
+        if secret_match && client.client_secret_needs_rehash() {
+            let mut upgraded = client.clone();
+            match upgraded.set_client_secret(client_secret) {
+                Ok(()) => {
+                    if let Err(e) = self.storage.update_client(&upgraded).await {
+                        tracing::warn!(error = %e, "failed to re-hash legacy client secret");
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "failed to re-hash legacy client secret"),
+            }
+        }
+
+        publish(
+            self.event_bus.as_ref(),
+            AuthEvent::new(
+                EventType::ClientValidated,
+                EventSeverity::Info,
+                None,
+                Some(client_id.to_string()),
+            )
+            .with_metadata("success", if secret_match { "true" } else { "false" }),
+        );
+
+        Ok(secret_match)
+    }
+
+    /// The client calling an endpoint such as introspection, identified by `client_id`
+    /// and `client_secret`. Unknown clients and wrong secrets both fail with the same
+    /// `invalid_client` error; the reason goes to `security`, tagged with `endpoint`.
+    pub async fn authenticate(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        tenant_id: Option<&str>,
+        security: &SecurityEvents,
+        endpoint: &str,
+    ) -> Result<Client, OAuth2Error> {
+        let reason = match self.validate(client_id, client_secret, tenant_id).await {
+            Ok(true) => None,
+            Ok(false) => Some("Invalid client_secret".to_string()),
+            Err(e) if e.error == "server_error" => return Err(e),
+            Err(e) => Some(e.error_description.unwrap_or(e.error)),
+        };
+        if let Some(reason) = reason {
+            security.publish(
+                EventType::ClientAuthenticationFailed,
+                Some(client_id),
+                None,
+                &[("endpoint", endpoint), ("reason", &reason)],
+            );
+            return Err(OAuth2Error::invalid_client("Invalid client credentials"));
+        }
+
+        self.get(client_id, tenant_id).await
+    }
+
+    /// Authenticate a confidential client at the token endpoint by its secret. Public and
+    /// native clients have none and are accepted without one.
+    pub async fn authenticate_secret(
+        &self,
+        client: &Client,
+        client_secret: Option<&str>,
+    ) -> Result<(), OAuth2Error> {
+        if client_secret.is_none() && client.is_public() {
+            return Ok(());
+        }
+        let client_secret =
+            client_secret.ok_or_else(|| OAuth2Error::invalid_client("Missing client_secret"))?;
+        if !self
+            .validate(
+                &client.client_id,
+                client_secret,
+                client.tenant_id.as_deref(),
+            )
+            .await?
+        {
+            return Err(OAuth2Error::invalid_client("Invalid client_secret"));
+        }
+        Ok(())
+    }
+
+    /// Authenticate `client` for the client credentials grant (RFC 6749 section 4.4) and
+    /// settle the scope to issue. The client presents its secret or, for service accounts
+    /// with a registered key, an RFC 7523 client assertion addressed to `token_endpoint`.
+    pub async fn authorize_client_credentials(
+        &self,
+        client: &Client,
+        credentials: ClientCredentials,
+        token_endpoint: &str,
+    ) -> Result<AuthorizedClientCredentials, OAuth2Error> {
+        if !client.supports_grant_type(GrantType::ClientCredentials) {
+            return Err(OAuth2Error::unauthorized_client(
+                "Client is not allowed to use client_credentials",
+            ));
+        }
+        // Service accounts exist only in the default tenant.
+        let service_account = match client.tenant_id {
+            None => self.get_service_account(&client.client_id).await?,
+            Some(_) => None,
+        };
+
+        match (
+            credentials.client_assertion_type,
+            credentials.client_assertion,
+        ) {
+            (Some(assertion_type), Some(assertion)) => {
+                if credentials.client_secret.is_some() {
+                    return Err(OAuth2Error::invalid_request(
+                        "Use only one client authentication method",
+                    ));
+                }
+                if assertion_type != JWT_BEARER_ASSERTION_TYPE {
+                    return Err(OAuth2Error::invalid_client(
+                        "Unsupported client_assertion_type",
+                    ));
+                }
+                service_account
+                    .as_ref()
+                    .ok_or_else(|| {
+                        OAuth2Error::invalid_client("Client does not support client assertions")
+                    })?
+                    .verify_client_assertion(&assertion, token_endpoint)?;
+            }
+            (None, None) => {
+                if service_account
+                    .as_ref()
+                    .is_some_and(|account| account.public_key.is_some())
+                {
+                    return Err(OAuth2Error::invalid_client(
+                        "Service account requires a client_assertion",
+                    ));
+                }
+                let client_secret = credentials
+                    .client_secret
+                    .ok_or_else(|| OAuth2Error::invalid_client("Missing client_secret"))?;
+                if !self
+                    .validate(
+                        &client.client_id,
+                        &client_secret,
+                        client.tenant_id.as_deref(),
+                    )
+                    .await?
+                {
+                    return Err(OAuth2Error::invalid_client("Invalid client_secret"));
+                }
+            }
+            _ => {
+                return Err(OAuth2Error::invalid_request(
+                    "client_assertion and client_assertion_type must be sent together",
+                ))
+            }
+        }
+
+        // Service accounts carry their own scopes, which also form the default grant.
+        let (scope, allowed_scope) = match &service_account {
+            Some(account) => {
+                if !account.enabled {
+                    return Err(OAuth2Error::invalid_client("Service account is disabled"));
+                }
+                (
+                    credentials.scope.unwrap_or_else(|| account.scope.clone()),
+                    account.scope.as_str(),
+                )
+            }
+            None => (
+                credentials.scope.unwrap_or_else(|| "read".to_string()),
+                client.scope.as_str(),
+            ),
+        };
+        validate_scope_subset(&scope, allowed_scope)?;
+
+        Ok(AuthorizedClientCredentials {
+            scope,
+            service_account,
+        })
+    }
+
+    /// Look up a client for RFC 7592 management, authenticating the registration access
+    /// token. Unknown clients and bad tokens produce the same error so callers cannot probe
+    /// for client ids.
+    pub async fn read_registration(
+        &self,
+        client_id: &str,
+        registration_access_token: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Client, OAuth2Error> {
+        self.storage
+            .get_client(client_id, tenant_id)
+            .await?
+            .filter(|client| client.verify_registration_access_token(registration_access_token))
+            .ok_or_else(|| OAuth2Error::invalid_token("Invalid registration access token"))
+    }
+
+    /// Replace a client's registered metadata (RFC 7592 section 2.2). Redirect URI changes
+    /// follow the configured [`RedirectUriChangePolicy`].
+    pub async fn update_registration(
+        &self,
+        client_id: &str,
+        registration_access_token: &str,
+        tenant_id: Option<&str>,
+        update: ClientUpdateRequest,
+    ) -> Result<Client, OAuth2Error> {
+        let mut client = self
+            .read_registration(client_id, registration_access_token, tenant_id)
+            .await?;
+
+        if update.client_id != client.client_id {
+            return Err(OAuth2Error::invalid_request(
+                "client_id in body does not match the registration",
+            ));
+        }
+        if let Some(secret) = &update.client_secret {
+            if !client.verify_client_secret(secret) {
+                return Err(OAuth2Error::invalid_request(
+                    "client_secret does not match the registration",
+                ));
+            }
+        }
+
+        client
+            .client_type
+            .validate_metadata(&update.redirect_uris, &update.grant_types)?;
+        update.metadata.validate(client.client_type)?;
+        // Reserved scopes the client already holds were granted by an administrator.
+        let added_scope = update
+            .scope
+            .split_whitespace()
+            .filter(|s| !client.scope.split_whitespace().any(|held| held == *s))
+            .collect::<Vec<_>>()
+            .join(" ");
+        reject_reserved_scopes(&added_scope, &self.admin_scope)?;
+
+        let previous = client.get_redirect_uris();
+        let requested: Vec<String> = update
+            .redirect_uris
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        let mut event = None;
+        if requested == previous {
+            // Re-submitting the current URIs withdraws a pending change.
+            client.set_redirect_uris(&previous);
+        } else if client.get_pending_redirect_uris().as_ref() != Some(&requested) {
+            // Re-submitting a pending change leaves its delay running.
+            event = Some(match self.redirect_uri_changes {
+                RedirectUriChangePolicy::Immediate => {
+                    client.set_redirect_uris(&requested);
+                    redirect_uri_event(
+                        EventType::ClientRedirectUrisChanged,
+                        &client.client_id,
+                        &previous,
+                        &requested,
+                    )
+                }
+                RedirectUriChangePolicy::Delayed => {
+                    let effective_at = Utc::now() + self.redirect_uri_change_delay;
+                    client.stage_redirect_uris(&requested, Some(effective_at));
+                    redirect_uri_event(
+                        EventType::ClientRedirectUrisChangeRequested,
+                        &client.client_id,
+                        &previous,
+                        &requested,
+                    )
+                    .with_metadata("effective_at", effective_at.to_rfc3339())
+                }
+                RedirectUriChangePolicy::Approval => {
+                    client.stage_redirect_uris(&requested, None);
+                    redirect_uri_event(
+                        EventType::ClientRedirectUrisChangeRequested,
+                        &client.client_id,
+                        &previous,
+                        &requested,
+                    )
+                    .with_metadata("awaiting_approval", "true")
+                }
+            });
+        }
+
+        client.name = update.client_name;
+        client.grant_types =
+            serde_json::to_string(&update.grant_types).unwrap_or_else(|_| "[]".to_string());
+        client.scope = update.scope;
+        client.set_metadata(update.metadata);
+        client.updated_at = Utc::now();
+
+        self.storage.update_client(&client).await?;
+
+        if let Some(event) = event {
+            publish(self.event_bus.as_ref(), event);
+        }
+        Ok(client)
+    }
+
+    /// Apply a client's held-back redirect URIs now (admin approval).
+    pub async fn approve_redirect_uri_change(
+        &self,
+        client_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Client, OAuth2Error> {
+        let mut client = self
+            .pending_redirect_uri_change(client_id, tenant_id)
+            .await?;
+        let previous = client.get_redirect_uris();
+        let approved = client.approve_pending_redirect_uris().unwrap_or_default();
+        client.updated_at = Utc::now();
+        self.storage.update_client(&client).await?;
+
+        publish(
+            self.event_bus.as_ref(),
+            redirect_uri_event(
+                EventType::ClientRedirectUrisChanged,
+                &client.client_id,
+                &previous,
+                &approved,
+            ),
+        );
+        Ok(client)
+    }
+
+    /// Discard a client's held-back redirect URIs (admin rejection).
+    pub async fn reject_redirect_uri_change(
+        &self,
+        client_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Client, OAuth2Error> {
+        let mut client = self
+            .pending_redirect_uri_change(client_id, tenant_id)
+            .await?;
+        let rejected = client.reject_pending_redirect_uris().unwrap_or_default();
+        client.updated_at = Utc::now();
+        self.storage.update_client(&client).await?;
+
+        publish(
+            self.event_bus.as_ref(),
+            redirect_uri_event(
+                EventType::ClientRedirectUrisChangeRejected,
+                &client.client_id,
+                &client.get_redirect_uris(),
+                &rejected,
+            ),
+        );
+        Ok(client)
+    }
+
+    /// Look up a client that has held-back redirect URIs.
+    async fn pending_redirect_uri_change(
+        &self,
+        client_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Client, OAuth2Error> {
+        let client = self
+            .storage
+            .get_client(client_id, tenant_id)
+            .await?
+            .ok_or_else(|| OAuth2Error::invalid_client("Client not found"))?;
+        if client.get_pending_redirect_uris().is_none() {
+            return Err(OAuth2Error::invalid_request(
+                "Client has no pending redirect URI change",
+            ));
+        }
+        Ok(client)
+    }
+
+    /// Deprovision a client (RFC 7592 section 2.3).
+    pub async fn delete_registration(
+        &self,
+        client_id: &str,
+        registration_access_token: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<(), OAuth2Error> {
+        let client = self
+            .read_registration(client_id, registration_access_token, tenant_id)
+            .await?;
+        self.storage
+            .delete_client(&client.client_id, tenant_id)
+            .await?;
+        publish(
+            self.event_bus.as_ref(),
+            client_deleted_event(&client, "registration"),
+        );
+        Ok(())
+    }
+
+    /// Delete a client, with its tokens, authorization codes and service account (admin).
+    pub async fn delete(
+        &self,
+        client_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Client, OAuth2Error> {
+        let client = self
+            .storage
+            .get_client(client_id, tenant_id)
+            .await?
+            .ok_or_else(|| OAuth2Error::not_found("Client not found"))?;
+        self.storage
+            .delete_client(&client.client_id, tenant_id)
+            .await?;
+        publish(
+            self.event_bus.as_ref(),
+            client_deleted_event(&client, "admin"),
+        );
+        Ok(client)
+    }
+}
+
+/// A freshly registered client together with its plaintext credentials.
+///
+/// Only hashes are persisted, so this is the single chance to hand them to the caller.
+#[derive(Debug)]
+pub struct RegisteredClient {
+    pub client: Client,
+    /// `None` for public and native clients.
+    pub client_secret: Option<String>,
+    pub registration_access_token: String,
+}
+
+/// How a client authenticates for the client credentials grant, and the scope it asks
+/// for.
+#[derive(Debug, Clone, Default)]
+pub struct ClientCredentials {
+    pub client_secret: Option<String>,
+    pub client_assertion_type: Option<String>,
+    pub client_assertion: Option<String>,
+    /// Defaults to the service account's scopes, or `read` for other clients.
+    pub scope: Option<String>,
+}
+
+/// A client authenticated for the client credentials grant.
+#[derive(Debug, Clone)]
+pub struct AuthorizedClientCredentials {
+    /// Scope to issue, within what the client or its service account is allowed.
+    pub scope: String,
+    pub service_account: Option<ServiceAccount>,
+}
+
+/// Redirect URI change event carrying the before/after values as JSON arrays.
+fn redirect_uri_event(
+    event_type: EventType,
+    client_id: &str,
+    previous: &[String],
+    requested: &[String],
+) -> AuthEvent {
+    AuthEvent::new(
+        event_type,
+        EventSeverity::Warning,
+        None,
+        Some(client_id.to_string()),
+    )
+    .with_metadata(
+        "previous_redirect_uris",
+        serde_json::to_string(previous).unwrap_or_default(),
+    )
+    .with_metadata(
+        "redirect_uris",
+        serde_json::to_string(requested).unwrap_or_default(),
+    )
+}
+
+/// `ClientDeleted`, recording whether the client deprovisioned itself or an admin removed it.
+fn client_deleted_event(client: &Client, deleted_by: &str) -> AuthEvent {
+    AuthEvent::new(
+        EventType::ClientDeleted,
+        EventSeverity::Info,
+        None,
+        Some(client.client_id.clone()),
+    )
+    .with_metadata("client_name", client.name.clone())
+    .with_metadata("deleted_by", deleted_by)
+}
+
+/// Argon2 hash verified against when a client id is unknown, to equalize timing.
+fn unknown_client_hash() -> &'static str {
+    static HASH: OnceLock<String> = OnceLock::new();
+    HASH.get_or_init(|| hash_password(&generate_secret()).unwrap_or_default())
+}
@@ -0,0 +1,86 @@
+//! Token, code and client operations shared by every front end.
+//!
+//! The actix actors, the axum handlers and the gRPC service all call these, so a request
+//! gets the same checks, errors and events whichever transport it came in on. They are
+//! plain async methods over the ports: front ends add their own spans, metrics and
+//! request deadlines (events are published until [`Deadline::current`]).
+
+mod auth;
+mod client;
+mod token;
+
+pub use auth::*;
+pub use client::*;
+pub use token::*;
+
+use oauth2_core::{Client, OAuth2Error, ScopeSet, DEFAULT_ADMIN_SCOPE};
+use oauth2_events::{AuthEvent, EventBusHandle, EventEnvelope};
+use rand::Rng;
+
+use crate::Deadline;
+
+/// Publish `event` best-effort, giving up at the current request deadline.
+fn publish(event_bus: Option<&EventBusHandle>, event: AuthEvent) {
+    if let Some(event_bus) = event_bus {
+        let envelope = EventEnvelope::from_current_span(event, "oauth2_server");
+        event_bus.publish_best_effort_until(envelope, Deadline::current().instant());
+    }
+}
+
+/// Fail with `invalid_scope` unless `requested` is a non-empty subset of `allowed`.
+pub fn validate_scope_subset(requested: &str, allowed: &str) -> Result<(), OAuth2Error> {
+    let requested = ScopeSet::parse(requested);
+    if requested.is_empty() {
+        return Err(OAuth2Error::invalid_scope("scope must not be empty"));
+    }
+    if !requested.is_subset(&ScopeSet::parse(allowed)) {
+        return Err(OAuth2Error::invalid_scope(
+            "requested scope exceeds client permissions",
+        ));
+    }
+    Ok(())
+}
+
+/// Scope letting a token publish events through the ingest endpoint.
+pub const EVENTS_INGEST_SCOPE: &str = "events:ingest";
+
+/// Fail with `invalid_scope` if `scope` includes a scope that only an administrator may
+/// grant: `admin_scope`, the `admin` and `admin:*` role scopes and
+/// [`EVENTS_INGEST_SCOPE`]. Self-service registration must not hand these out.
+pub fn reject_reserved_scopes(scope: &str, admin_scope: &str) -> Result<(), OAuth2Error> {
+    let reserved = scope.split_whitespace().find(|s| {
+        *s == admin_scope
+            || *s == DEFAULT_ADMIN_SCOPE
+            || s.starts_with("admin:")
+            || *s == EVENTS_INGEST_SCOPE
+    });
+    match reserved {
+        Some(s) => Err(OAuth2Error::invalid_scope(&format!(
+            "scope '{s}' is reserved for clients created by an administrator"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Whether `client` may introspect and revoke tokens issued to other clients.
+pub fn has_admin_scope(client: &Client) -> bool {
+    client
+        .scope
+        .split_whitespace()
+        .any(|s| s == DEFAULT_ADMIN_SCOPE)
+}
+
+/// A random alphanumeric string of `len` characters.
+fn generate_token(len: usize) -> String {
+    let mut rng = rand::rng();
+    (0..len)
+        .map(|_| {
+            let idx = rng.random_range(0..62);
+            match idx {
+                0..=25 => (b'a' + idx) as char,
+                26..=51 => (b'A' + (idx - 26)) as char,
+                _ => (b'0' + (idx - 52)) as char,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,754 @@
+use std::sync::Arc;
+
+use oauth2_core::{
+    tenant_id, Claims, Client, ContextBinding, ContextTolerance, GrantType, IntrospectionResponse,
+    IssuerKeys, OAuth2Error, OutboxMessage, Redactor, ScopeSet, TenantContext, Token,
+    TokenMetadata, TokenVerificationError,
+};
+use oauth2_events::{AuthEvent, EventBusHandle, EventEnvelope, EventSeverity, EventType};
+
+use super::{has_admin_scope, publish};
+use crate::{
+    Deadline, DynClaimsEnricher, DynClock, DynStorage, DynTokenIssuancePolicy, SystemClock,
+    TokenIssuance,
+};
+
+/// Lifetime of issued access tokens.
+pub const ACCESS_TOKEN_TTL_SECS: i64 = 3600;
+/// Lifetime of issued refresh tokens.
+pub const REFRESH_TOKEN_TTL_SECS: i64 = 30 * 24 * 3600;
+
+/// `envelope` as an outbox message, keyed by its event id.
+pub fn outbox_message(envelope: &EventEnvelope) -> Result<OutboxMessage, OAuth2Error> {
+    let payload = serde_json::to_string(envelope)
+        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))?;
+    Ok(OutboxMessage::new(
+        envelope.event.id.clone(),
+        envelope.event.event_type.as_str().to_string(),
+        payload,
+    ))
+}
+
+/// Issues, validates, rotates and revokes tokens.
+#[derive(Clone)]
+pub struct TokenService {
+    storage: DynStorage,
+    keys: IssuerKeys,
+    event_bus: Option<EventBusHandle>,
+    cascade_revocation: bool,
+    claims_enrichers: Vec<DynClaimsEnricher>,
+    issuance_policies: Vec<DynTokenIssuancePolicy>,
+    clock: DynClock,
+    bind_context: bool,
+    context_tolerance: ContextTolerance,
+}
+
+impl TokenService {
+    pub fn new(storage: DynStorage, keys: IssuerKeys) -> Self {
+        Self {
+            storage,
+            keys,
+            event_bus: None,
+            cascade_revocation: false,
+            claims_enrichers: Vec::new(),
+            issuance_policies: Vec::new(),
+            clock: Arc::new(SystemClock),
+            bind_context: false,
+            context_tolerance: ContextTolerance::default(),
+        }
+    }
+
+    pub fn with_events(mut self, event_bus: EventBusHandle) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Replace the signing keys, e.g. to use a custom issuer or accept a legacy one.
+    pub fn with_issuer_keys(mut self, keys: IssuerKeys) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// Revoke the whole grant when an access token is revoked, not just that token.
+    /// Revoking a refresh token always revokes its grant.
+    pub fn with_revocation_cascade(mut self, enabled: bool) -> Self {
+        self.cascade_revocation = enabled;
+        self
+    }
+
+    /// Run `enrichers`, in order, on every access token before it is signed.
+    pub fn with_claims_enrichers(mut self, enrichers: Vec<DynClaimsEnricher>) -> Self {
+        self.claims_enrichers = enrichers;
+        self
+    }
+
+    /// Check `policies`, in order, before every token is issued; the first error denies it.
+    pub fn with_issuance_policies(mut self, policies: Vec<DynTokenIssuancePolicy>) -> Self {
+        self.issuance_policies = policies;
+        self
+    }
+
+    /// Time source for the `iat`/`exp` of issued tokens, e.g. a fixed clock in tests.
+    pub fn with_clock(mut self, clock: DynClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Record the requesting context on new refresh tokens and reject refreshes from a
+    /// context `tolerance` does not accept.
+    pub fn with_context_binding(mut self, enabled: bool, tolerance: ContextTolerance) -> Self {
+        self.bind_context = enabled;
+        self.context_tolerance = tolerance;
+        self
+    }
+
+    pub fn keys(&self) -> &IssuerKeys {
+        &self.keys
+    }
+
+    /// Signing keys for `tenant`; the server's own for the default tenant.
+    fn keys_for<'a>(&'a self, tenant: Option<&'a TenantContext>) -> &'a IssuerKeys {
+        match tenant {
+            Some(tenant) => &tenant.keys,
+            None => &self.keys,
+        }
+    }
+
+    /// Run the issuance policies on `issuance`, which they may narrow or add claims to.
+    async fn check_issuance(
+        &self,
+        mut issuance: TokenIssuance,
+    ) -> Result<TokenIssuance, OAuth2Error> {
+        for policy in &self.issuance_policies {
+            if let Err(error) = policy.check(&mut issuance).await {
+                tracing::info!(
+                    grant_type = issuance.grant_type(),
+                    client_id = issuance.client_id(),
+                    error = %error.error,
+                    "Token issuance denied by policy"
+                );
+                return Err(error);
+            }
+        }
+        Ok(issuance)
+    }
+
+    /// Access token claims for a new token: those added by issuance policies, then the
+    /// claims enrichers'.
+    async fn access_claims(
+        &self,
+        keys: &IssuerKeys,
+        now: chrono::DateTime<chrono::Utc>,
+        issuance: &TokenIssuance,
+        metadata: &mut TokenMetadata,
+    ) -> Result<Claims, OAuth2Error> {
+        let client_id = issuance.client_id();
+        let subject = issuance.user_id().unwrap_or(client_id).to_string();
+        let mut claims = Claims::new(
+            subject,
+            client_id.to_string(),
+            issuance.scope().to_string(),
+            ACCESS_TOKEN_TTL_SECS,
+        )
+        .issued_at(now)
+        .with_issuer(keys.current.issuer.clone());
+        for (name, value) in issuance.claims() {
+            claims.set_claim(name, value.clone())?;
+        }
+        for enricher in &self.claims_enrichers {
+            enricher.enrich(&mut claims, metadata).await?;
+        }
+        Ok(claims)
+    }
+
+    /// Handle a refresh token presented after it was rotated or revoked: it is treated as
+    /// stolen, so every token of its grant is revoked.
+    async fn refresh_token_reused(&self, token: &Token) -> Result<(), OAuth2Error> {
+        tracing::warn!(
+            grant_id = %token.grant_id(),
+            client_id = %token.client_id,
+            "Revoked refresh token presented; revoking its grant"
+        );
+        self.storage.revoke_token_grant(token.grant_id()).await?;
+        publish(
+            self.event_bus.as_ref(),
+            AuthEvent::new(
+                EventType::TokenRevoked,
+                EventSeverity::Warning,
+                token.user_id.clone(),
+                Some(token.client_id.clone()),
+            )
+            .with_metadata("reason", "refresh_token_reuse"),
+        );
+        Ok(())
+    }
+
+    /// Sign and save a token, after the token issuance policies.
+    pub async fn issue(&self, request: IssueToken) -> Result<Token, OAuth2Error> {
+        let keys = self.keys_for(request.tenant.as_ref());
+        let now = self.clock.now();
+        let context_binding = request
+            .context
+            .as_ref()
+            .filter(|_| self.bind_context && request.include_refresh)
+            .map(ContextBinding::encode);
+
+        let issuance = self
+            .check_issuance(TokenIssuance::new(
+                request.grant_type,
+                request.client_id.clone(),
+                request.user_id.clone(),
+                tenant_id(request.tenant.as_ref()).map(str::to_string),
+                request.scope,
+            ))
+            .await?;
+        let scope = issuance.scope().to_string();
+
+        let mut metadata = request.metadata;
+        let access_claims = self
+            .access_claims(keys, now, &issuance, &mut metadata)
+            .await?;
+        let access_token = keys
+            .sign(access_claims)
+            .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))?;
+
+        let refresh_token = if request.include_refresh {
+            let refresh_claims = Claims::new(
+                request
+                    .user_id
+                    .clone()
+                    .unwrap_or_else(|| request.client_id.clone()),
+                request.client_id.clone(),
+                scope.clone(),
+                REFRESH_TOKEN_TTL_SECS,
+            )
+            .issued_at(now);
+            Some(
+                keys.sign(refresh_claims)
+                    .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))?,
+            )
+        } else {
+            None
+        };
+
+        let mut token = Token::new(
+            access_token,
+            refresh_token,
+            request.client_id.clone(),
+            request.user_id.clone(),
+            scope.clone(),
+            ACCESS_TOKEN_TTL_SECS as i32,
+        )
+        .issued_at(now)
+        .with_metadata(metadata)
+        .with_tenant_id(tenant_id(request.tenant.as_ref()).map(str::to_string))
+        .with_context_binding(context_binding);
+        if let Some(grant_id) = request.grant_id {
+            token = token.with_grant_id(grant_id);
+        }
+
+        let mut events = Vec::new();
+        if request.consume_code.is_some() {
+            events.push(AuthEvent::new(
+                EventType::AuthorizationCodeValidated,
+                EventSeverity::Info,
+                request.user_id.clone(),
+                Some(request.client_id.clone()),
+            ));
+        }
+        events.push(
+            AuthEvent::new(
+                EventType::TokenCreated,
+                EventSeverity::Info,
+                request.user_id,
+                Some(request.client_id),
+            )
+            .with_metadata("scope", scope)
+            .with_metadata("has_refresh_token", request.include_refresh.to_string()),
+        );
+        let envelopes: Vec<EventEnvelope> = events
+            .into_iter()
+            .map(|event| EventEnvelope::from_current_span(event, "oauth2_server"))
+            .collect();
+
+        // Events are queued with the token, so they are published if and only if it is
+        // saved.
+        let outbox = self.event_bus.as_ref().filter(|bus| bus.is_outbox());
+        if outbox.is_none()
+            && request.consume_code.is_none()
+            && request.redeem_refresh_token.is_none()
+        {
+            self.storage.save_token(&token).await?;
+        } else {
+            let saved = async {
+                let mut tx = self.storage.begin_transaction().await?;
+                if let Some(code) = &request.consume_code {
+                    tx.consume_authorization_code(code).await?;
+                }
+                if let Some(refresh_token) = &request.redeem_refresh_token {
+                    tx.redeem_refresh_token(refresh_token).await?;
+                }
+                tx.save_token(&token).await?;
+                if let Some(outbox) = outbox {
+                    for envelope in &envelopes {
+                        let envelope = outbox
+                            .seal(envelope.clone())
+                            .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))?;
+                        tx.enqueue_outbox_message(&outbox_message(&envelope)?)
+                            .await?;
+                    }
+                }
+                tx.commit().await
+            }
+            .await;
+            if let Err(error) = saved {
+                // Another request rotated the refresh token first.
+                if request.redeem_refresh_token.is_some() && error.error == "invalid_grant" {
+                    self.refresh_token_reused(&token).await?;
+                }
+                return Err(error);
+            }
+        }
+        if let (None, Some(event_bus)) = (outbox, &self.event_bus) {
+            for envelope in envelopes {
+                event_bus.publish_best_effort_until(envelope, Deadline::current().instant());
+            }
+        }
+
+        Ok(token)
+    }
+
+    /// The access token claims [`issue`](Self::issue) would sign for `request`, without
+    /// signing or saving a token.
+    pub async fn preview_claims(&self, request: IssueToken) -> Result<Claims, OAuth2Error> {
+        let keys = self.keys_for(request.tenant.as_ref());
+        let now = self.clock.now();
+        let issuance = self
+            .check_issuance(TokenIssuance::new(
+                request.grant_type,
+                request.client_id,
+                request.user_id,
+                tenant_id(request.tenant.as_ref()).map(str::to_string),
+                request.scope,
+            ))
+            .await?;
+        let mut metadata = request.metadata;
+        self.access_claims(keys, now, &issuance, &mut metadata)
+            .await
+    }
+
+    /// The stored token for `access_token`, if it is still valid.
+    pub async fn validate(&self, access_token: &str) -> Result<Token, OAuth2Error> {
+        // Be forgiving about whitespace and callers that accidentally include a Bearer prefix.
+        let token_trimmed = access_token.trim();
+        let token_normalized = token_trimmed
+            .strip_prefix("Bearer ")
+            .unwrap_or(token_trimmed)
+            .trim();
+
+        let redacted = Redactor::current().token(token_normalized);
+        tracing::info!(
+            token_len = token_normalized.len(),
+            token = %redacted,
+            "ValidateToken called"
+        );
+
+        let token = self
+            .storage
+            .get_token_by_access_token(token_normalized)
+            .await?
+            .ok_or_else(|| OAuth2Error::invalid_grant("Token not found"))?;
+
+        if !token.is_valid() {
+            tracing::warn!(
+                revoked = token.revoked,
+                expires_at = %token.expires_at,
+                now = %chrono::Utc::now(),
+                token_len = token_normalized.len(),
+                token = %redacted,
+                "Token is not valid (expired or revoked)"
+            );
+            publish(
+                self.event_bus.as_ref(),
+                AuthEvent::new(
+                    EventType::TokenExpired,
+                    EventSeverity::Warning,
+                    token.user_id.clone(),
+                    Some(token.client_id.clone()),
+                ),
+            );
+            return Err(OAuth2Error::invalid_grant("Token is expired or revoked"));
+        }
+
+        publish(
+            self.event_bus.as_ref(),
+            AuthEvent::new(
+                EventType::TokenValidated,
+                EventSeverity::Info,
+                token.user_id.clone(),
+                Some(token.client_id.clone()),
+            ),
+        );
+        Ok(token)
+    }
+
+    /// A bearer token signed by this server for `tenant` that storage still knows as
+    /// unexpired and unrevoked, with its verified claims. Tokens of other tenants, like any
+    /// other failure, are `invalid_token`.
+    pub async fn authenticate_bearer(
+        &self,
+        raw: &str,
+        tenant: Option<&TenantContext>,
+    ) -> Result<(Token, Claims), OAuth2Error> {
+        let (claims, _) = self
+            .keys_for(tenant)
+            .verify(raw)
+            .map_err(|_| OAuth2Error::invalid_token("Invalid bearer token"))?;
+
+        // A revoked JWT still verifies, so storage has the final say.
+        let token = self
+            .storage
+            .get_token_by_access_token(raw)
+            .await?
+            .filter(|token| token.is_valid() && token.in_tenant(tenant_id(tenant)))
+            .ok_or_else(|| OAuth2Error::invalid_token("Token is revoked or expired"))?;
+        Ok((token, claims))
+    }
+
+    /// Check a refresh token presented for rotation, returning its token row so the caller
+    /// can issue the replacement under the same grant. The replacement's [`IssueToken`]
+    /// revokes it through `redeem_refresh_token`.
+    ///
+    /// A refresh token that was already redeemed or revoked is treated as stolen and the
+    /// whole grant is revoked (refresh token reuse detection).
+    pub async fn check_refresh_token(
+        &self,
+        request: &RefreshTokenRequest,
+    ) -> Result<Token, OAuth2Error> {
+        let token = self
+            .storage
+            .get_token_by_refresh_token(&request.refresh_token)
+            .await?
+            .filter(|token| token.in_tenant(tenant_id(request.tenant.as_ref())))
+            .ok_or_else(|| OAuth2Error::invalid_grant("Refresh token not found"))?;
+
+        if token.client_id != request.client_id {
+            return Err(OAuth2Error::invalid_grant(
+                "Refresh token was not issued to this client",
+            ));
+        }
+
+        if token.revoked {
+            self.refresh_token_reused(&token).await?;
+            return Err(OAuth2Error::invalid_grant("Refresh token is revoked"));
+        }
+
+        // Tokens issued before binding was enabled carry no context and are exempt.
+        let issued_context = token
+            .context_binding
+            .as_deref()
+            .and_then(ContextBinding::decode);
+        if let (true, Some(issued)) = (self.bind_context, issued_context) {
+            if !issued.is_compatible_with(&request.context, self.context_tolerance) {
+                tracing::warn!(
+                    grant_id = %token.grant_id(),
+                    client_id = %token.client_id,
+                    "Refresh token presented from a different network"
+                );
+                publish(
+                    self.event_bus.as_ref(),
+                    AuthEvent::new(
+                        EventType::RefreshTokenContextMismatch,
+                        EventSeverity::Warning,
+                        token.user_id.clone(),
+                        Some(token.client_id.clone()),
+                    ),
+                );
+                return Err(OAuth2Error::invalid_grant(
+                    "Refresh token was issued to a different context",
+                ));
+            }
+        }
+
+        // The refresh token's own `exp` bounds the grant, not the access token's.
+        if self
+            .keys_for(request.tenant.as_ref())
+            .verify(&request.refresh_token)
+            .is_err()
+        {
+            return Err(OAuth2Error::invalid_grant("Refresh token is expired"));
+        }
+
+        // Checked before rotating so a bad request does not burn the refresh token.
+        if let Some(scope) = &request.scope {
+            let requested = ScopeSet::parse(scope);
+            if requested.is_empty() || !requested.is_subset(&ScopeSet::parse(&token.scope)) {
+                return Err(OAuth2Error::invalid_scope(
+                    "requested scope exceeds the original grant",
+                ));
+            }
+        }
+
+        Ok(token)
+    }
+
+    /// The refresh token grant: check the presented refresh token, then issue its
+    /// replacement under the same grant, revoking it in the same transaction. A refresh
+    /// may narrow the original scope but never widen it.
+    pub async fn refresh(&self, request: RefreshTokenRequest) -> Result<Token, OAuth2Error> {
+        let previous = self.check_refresh_token(&request).await?;
+        let scope = request.scope.unwrap_or_else(|| previous.scope.clone());
+        // Keep the grant bound where it started, so it cannot drift step by step.
+        let context = previous
+            .context_binding
+            .as_deref()
+            .and_then(ContextBinding::decode)
+            .unwrap_or(request.context);
+
+        self.issue(IssueToken {
+            include_refresh: true,
+            metadata: previous.metadata.clone(),
+            grant_id: Some(previous.grant_id().to_string()),
+            redeem_refresh_token: Some(request.refresh_token),
+            tenant: request.tenant,
+            context: Some(context),
+            ..IssueToken::new(
+                GrantType::RefreshToken.as_str(),
+                request.client_id,
+                previous.user_id.clone(),
+                scope,
+            )
+        })
+        .await
+    }
+
+    /// Revoke `token` (RFC 7009). Unknown tokens, and tokens of tenants other than
+    /// `tenant_id`, are ignored; `client_id` restricts revocation to that client's tokens.
+    pub async fn revoke(
+        &self,
+        token: &str,
+        token_type_hint: Option<&str>,
+        client_id: Option<&str>,
+        tenant_id: Option<&str>,
+    ) -> Result<(), OAuth2Error> {
+        let Some((row, is_refresh)) = self
+            .find_revocable_token(token, token_type_hint)
+            .await?
+            .filter(|(row, _)| row.in_tenant(tenant_id))
+        else {
+            // RFC 7009: unknown tokens are not an error
+            return Ok(());
+        };
+
+        if let Some(owner) = client_id {
+            if row.client_id != owner {
+                tracing::warn!(
+                    caller = %owner,
+                    token_client_id = %row.client_id,
+                    "Refusing to revoke another client's token"
+                );
+                return Err(OAuth2Error::unauthorized_client(
+                    "Token was not issued to this client",
+                ));
+            }
+        }
+
+        if is_refresh || self.cascade_revocation {
+            tracing::info!(
+                grant_id = %row.grant_id(),
+                refresh_token = is_refresh,
+                "Revoking all tokens issued under grant"
+            );
+            self.storage.revoke_token_grant(row.grant_id()).await?;
+        } else {
+            self.storage.revoke_token(token).await?;
+        }
+
+        publish(
+            self.event_bus.as_ref(),
+            AuthEvent::new(
+                EventType::TokenRevoked,
+                EventSeverity::Info,
+                row.user_id,
+                Some(row.client_id),
+            ),
+        );
+        Ok(())
+    }
+
+    /// Find the token row for `token`, honouring the hint's lookup order.
+    /// Returns the row and whether `token` matched its refresh token.
+    async fn find_revocable_token(
+        &self,
+        token: &str,
+        hint: Option<&str>,
+    ) -> Result<Option<(Token, bool)>, OAuth2Error> {
+        let refresh_first = hint == Some("refresh_token");
+        for is_refresh in [refresh_first, !refresh_first] {
+            let found = if is_refresh {
+                self.storage.get_token_by_refresh_token(token).await?
+            } else {
+                self.storage.get_token_by_access_token(token).await?
+            };
+            if let Some(found) = found {
+                return Ok(Some((found, is_refresh)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Token introspection (RFC 7662) on behalf of the authenticated `caller`.
+    ///
+    /// Unknown, expired and revoked tokens are reported as inactive, as are tokens of other
+    /// tenants than `tenant`'s, and tokens issued to other clients unless the caller is a
+    /// resource server or holds the admin scope.
+    pub async fn introspect(
+        &self,
+        caller: &Client,
+        token: &str,
+        tenant: Option<&TenantContext>,
+    ) -> Introspection {
+        let redacted = Redactor::current().token(token);
+        let mut verification = None;
+        let result = self
+            .validate(token)
+            .await
+            .and_then(|token| {
+                if token.in_tenant(tenant_id(tenant)) {
+                    Ok(token)
+                } else {
+                    Err(OAuth2Error::invalid_grant("Token not found"))
+                }
+            })
+            .and_then(|token| {
+                // Verify the JWT against the current issuer (and the legacy issuer during a
+                // migration), or the tenant's own key.
+                match self.keys_for(tenant).verify(&token.access_token) {
+                    Ok((claims, generation)) => {
+                        verification = Some((generation.as_str(), "accepted"));
+                        Ok((token, claims))
+                    }
+                    Err(TokenVerificationError::LegacyWindowClosed) => {
+                        verification = Some(("legacy", "rejected"));
+                        Err(OAuth2Error::invalid_grant(
+                            "Token was issued by a retired issuer",
+                        ))
+                    }
+                    Err(e) => {
+                        verification = Some(("unknown", "rejected"));
+                        Err(OAuth2Error::invalid_grant(&e.to_string()))
+                    }
+                }
+            });
+
+        let response = match result {
+            Ok((token, _))
+                if token.client_id != caller.client_id
+                    && !caller.is_resource_server()
+                    && !has_admin_scope(caller) =>
+            {
+                tracing::warn!(
+                    caller = %caller.client_id,
+                    token_client_id = %token.client_id,
+                    "Introspection of another client's token denied; returning inactive"
+                );
+                IntrospectionResponse::inactive()
+            }
+            Ok((token, claims)) if token.is_valid() => IntrospectionResponse::active(token, claims),
+            Ok((token, _)) => {
+                tracing::info!(
+                    token_len = token.access_token.len(),
+                    token = %redacted,
+                    revoked = token.revoked,
+                    "Token is no longer valid; returning inactive"
+                );
+                IntrospectionResponse::inactive()
+            }
+            Err(err) => {
+                tracing::warn!(
+                    error = %err,
+                    token = %redacted,
+                    "Token introspection failed; returning inactive"
+                );
+                IntrospectionResponse::inactive()
+            }
+        };
+        Introspection {
+            response,
+            verification,
+        }
+    }
+}
+
+/// A token to issue with [`TokenService::issue`].
+#[derive(Debug, Clone)]
+pub struct IssueToken {
+    /// Grant the token is issued under, as seen by issuance policies.
+    pub grant_type: String,
+    pub user_id: Option<String>,
+    pub client_id: String,
+    pub scope: String,
+    pub include_refresh: bool,
+    /// Tags persisted with the token for later lookup.
+    pub metadata: TokenMetadata,
+    /// Grant the token continues (refresh token rotation); `None` starts a new grant.
+    pub grant_id: Option<String>,
+    /// Authorization code to burn in the same transaction that saves the token, so a code
+    /// is exchanged at most once and never burned without issuing a token.
+    pub consume_code: Option<String>,
+    /// Refresh token to revoke in the same transaction that saves the token, so a refresh
+    /// token is rotated at most once. Losing a race for it counts as refresh token reuse.
+    pub redeem_refresh_token: Option<String>,
+    /// Tenant issuing the token, signed with its keys; `None` for the default tenant.
+    pub tenant: Option<TenantContext>,
+    /// Context the refresh token is bound to when binding is enabled.
+    pub context: Option<ContextBinding>,
+}
+
+impl IssueToken {
+    /// A token for the default tenant without a refresh token; set the other fields to
+    /// change that.
+    pub fn new(
+        grant_type: impl Into<String>,
+        client_id: impl Into<String>,
+        user_id: Option<String>,
+        scope: impl Into<String>,
+    ) -> Self {
+        Self {
+            grant_type: grant_type.into(),
+            user_id,
+            client_id: client_id.into(),
+            scope: scope.into(),
+            include_refresh: false,
+            metadata: TokenMetadata::default(),
+            grant_id: None,
+            consume_code: None,
+            redeem_refresh_token: None,
+            tenant: None,
+            context: None,
+        }
+    }
+}
+
+/// A refresh token presented for rotation.
+#[derive(Debug, Clone)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+    pub client_id: String,
+    /// Scope requested for the new token; must be within the original grant.
+    pub scope: Option<String>,
+    /// Tenant the request was addressed to; `None` for the default tenant.
+    pub tenant: Option<TenantContext>,
+    /// Context of the token request, compared against the one recorded on the token.
+    pub context: ContextBinding,
+}
+
+/// Result of [`TokenService::introspect`].
+#[derive(Debug)]
+pub struct Introspection {
+    pub response: IntrospectionResponse,
+    /// Issuer generation that verified the token (`current`, `legacy` or `unknown`) and
+    /// whether it was `accepted` or `rejected`, for metrics; `None` when the token was
+    /// not found or no longer valid.
+    pub verification: Option<(&'static str, &'static str)>,
+}
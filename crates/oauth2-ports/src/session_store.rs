@@ -0,0 +1,38 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use oauth2_core::{OAuth2Error, UserSession};
+use std::sync::Arc;
+
+/// Where browser sessions are kept. The session cookie only carries a key; this store
+/// holds what handlers put in the session, keyed by the key's hash.
+///
+/// Every replica must see the same sessions, so multi-replica deployments need a shared
+/// backend (the database or Redis).
+#[async_trait]
+pub trait UserSessionStore: Send + Sync {
+    /// Short backend identifier used in logs (e.g. `redis`).
+    fn backend_name(&self) -> &'static str;
+
+    /// Save `session`, replacing a stored one with the same id.
+    async fn save(&self, session: &UserSession) -> Result<(), OAuth2Error>;
+
+    /// The session with `id`. Expired sessions may be returned; check
+    /// [`UserSession::is_expired`].
+    async fn get(&self, id: &str) -> Result<Option<UserSession>, OAuth2Error>;
+
+    /// Record a request in session `id`, moving its idle timeout to `idle_expires_at`.
+    async fn touch(
+        &self,
+        id: &str,
+        last_seen_at: DateTime<Utc>,
+        idle_expires_at: DateTime<Utc>,
+    ) -> Result<(), OAuth2Error>;
+
+    /// Unexpired sessions of `user_id`, newest first.
+    async fn list_for_user(&self, user_id: &str) -> Result<Vec<UserSession>, OAuth2Error>;
+
+    /// End session `id`; its cookie stops working.
+    async fn delete(&self, id: &str) -> Result<(), OAuth2Error>;
+}
+
+pub type DynUserSessionStore = Arc<dyn UserSessionStore>;
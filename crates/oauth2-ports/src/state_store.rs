@@ -0,0 +1,24 @@
+use async_trait::async_trait;
+use oauth2_core::{OAuth2Error, SocialLoginState};
+use std::sync::Arc;
+
+/// Where in-flight social logins (`state`, PKCE verifier and `nonce`) are kept between
+/// the redirect to the provider and its callback.
+///
+/// Every replica that can receive a callback must see the records, so multi-replica
+/// deployments need a shared backend (the database or Redis).
+#[async_trait]
+pub trait StateStore: Send + Sync {
+    /// Short backend identifier used in logs (e.g. `redis`).
+    fn backend_name(&self) -> &'static str;
+
+    /// Save the record of an outbound login until its `expires_at`.
+    async fn save(&self, state: &SocialLoginState) -> Result<(), OAuth2Error>;
+
+    /// Remove and return the record for `state`, so each is consumed at most once, even by
+    /// concurrent callbacks. Expired records may be returned; check
+    /// [`SocialLoginState::is_expired`].
+    async fn take(&self, state: &str) -> Result<Option<SocialLoginState>, OAuth2Error>;
+}
+
+pub type DynStateStore = Arc<dyn StateStore>;
@@ -1,7 +1,90 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use std::sync::Arc;
 
-use oauth2_core::{AuthorizationCode, Client, OAuth2Error, Token, User};
+use oauth2_core::{
+    AuditRecord, AuthorizationCode, Client, EmailToken, EmailTokenPurpose, FederatedIdentity,
+    Group, OAuth2Error, OutboxMessage, Role, ServiceAccount, SocialLoginState, Tenant, Token, User,
+    UserSession,
+};
+
+use crate::AuditQuery;
+
+/// Filter and pagination options for listing users.
+#[derive(Debug, Clone)]
+pub struct UserListQuery {
+    /// Case-insensitive substring match on username or email.
+    pub search: Option<String>,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+impl Default for UserListQuery {
+    fn default() -> Self {
+        Self {
+            search: None,
+            limit: 50,
+            offset: 0,
+        }
+    }
+}
+
+/// Tag and pagination options for finding tokens by metadata.
+#[derive(Debug, Clone)]
+pub struct TokenMetadataQuery {
+    pub key: String,
+    pub value: String,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+impl TokenMetadataQuery {
+    pub fn new(key: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            value: value.into(),
+            limit: 50,
+            offset: 0,
+        }
+    }
+}
+
+/// One page of a listing. Pages are numbered from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageRequest {
+    pub page: u32,
+    pub per_page: u32,
+}
+
+impl PageRequest {
+    pub const MAX_PER_PAGE: u32 = 200;
+
+    /// Clamp `page` to at least 1 and `per_page` to `1..=MAX_PER_PAGE`.
+    pub fn new(page: u32, per_page: u32) -> Self {
+        Self {
+            page: page.max(1),
+            per_page: per_page.clamp(1, Self::MAX_PER_PAGE),
+        }
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.per_page
+    }
+
+    /// Number of records before this page.
+    pub fn offset(&self) -> u64 {
+        u64::from(self.page.saturating_sub(1)) * u64::from(self.per_page)
+    }
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self {
+            page: 1,
+            per_page: 50,
+        }
+    }
+}
 
 /// Trait implemented by all persistence backends.
 ///
@@ -11,17 +94,70 @@ pub trait Storage: Send + Sync {
     /// Initialize the backing store (e.g., bootstrap schema / create indexes).
     async fn init(&self) -> Result<(), OAuth2Error>;
 
+    // Tenant operations
+    async fn save_tenant(&self, tenant: &Tenant) -> Result<(), OAuth2Error>;
+    async fn get_tenant(&self, id: &str) -> Result<Option<Tenant>, OAuth2Error>;
+    /// List tenants ordered by id.
+    async fn list_tenants(&self) -> Result<Vec<Tenant>, OAuth2Error>;
+
     // Client operations
+    /// Fails if the client's tenant already has a client with the same `client_id`.
     async fn save_client(&self, client: &Client) -> Result<(), OAuth2Error>;
-    async fn get_client(&self, client_id: &str) -> Result<Option<Client>, OAuth2Error>;
+    /// The client `client_id` of tenant `tenant_id` (`None` for the default tenant).
+    async fn get_client(
+        &self,
+        client_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<Client>, OAuth2Error>;
+    /// Clients whose `managed_by` is `manager`, ordered by `client_id`.
+    async fn list_clients_managed_by(&self, manager: &str) -> Result<Vec<Client>, OAuth2Error>;
+    /// List clients ordered by `client_id`.
+    async fn list_clients(&self, page: PageRequest) -> Result<Vec<Client>, OAuth2Error>;
+    async fn count_clients(&self) -> Result<u64, OAuth2Error>;
+    /// Overwrite a client's mutable fields, keyed by `client_id` and `tenant_id`.
+    async fn update_client(&self, client: &Client) -> Result<(), OAuth2Error>;
+    /// Delete the client `client_id` of tenant `tenant_id` along with its tokens,
+    /// authorization codes and service account.
+    async fn delete_client(
+        &self,
+        client_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<(), OAuth2Error>;
+
+    // Service account operations
+    async fn save_service_account(&self, account: &ServiceAccount) -> Result<(), OAuth2Error>;
+    async fn get_service_account(&self, id: &str) -> Result<Option<ServiceAccount>, OAuth2Error>;
+    async fn get_service_account_by_client_id(
+        &self,
+        client_id: &str,
+    ) -> Result<Option<ServiceAccount>, OAuth2Error>;
+    /// List service accounts ordered by name.
+    async fn list_service_accounts(&self) -> Result<Vec<ServiceAccount>, OAuth2Error>;
+    /// Overwrite a service account's mutable fields (description, scope, key, enabled).
+    async fn update_service_account(&self, account: &ServiceAccount) -> Result<(), OAuth2Error>;
 
     // User operations
-    // NOTE: These methods are implemented by all backends and covered by contract tests,
-    // but the current HTTP flows don't yet wire in real user persistence.
-    #[allow(dead_code)]
+    /// Fails if the user's tenant already has a user with the same `username`.
     async fn save_user(&self, user: &User) -> Result<(), OAuth2Error>;
-    #[allow(dead_code)]
-    async fn get_user_by_username(&self, username: &str) -> Result<Option<User>, OAuth2Error>;
+    async fn get_user(&self, user_id: &str) -> Result<Option<User>, OAuth2Error>;
+    /// The user `username` of tenant `tenant_id` (`None` for the default tenant).
+    async fn get_user_by_username(
+        &self,
+        username: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<User>, OAuth2Error>;
+    /// Users of every tenant whose email is `email`, ignoring ASCII case, ordered by
+    /// username.
+    async fn list_users_by_email(&self, email: &str) -> Result<Vec<User>, OAuth2Error>;
+    /// List users ordered by username.
+    async fn list_users(&self, query: &UserListQuery) -> Result<Vec<User>, OAuth2Error>;
+    async fn count_users(&self) -> Result<u64, OAuth2Error>;
+    /// Overwrite a user's mutable fields (username, password hash, email, email_verified,
+    /// enabled).
+    async fn update_user(&self, user: &User) -> Result<(), OAuth2Error>;
+    /// Delete a user along with their tokens, authorization codes, linked identities, roles,
+    /// group memberships, email tokens and browser sessions.
+    async fn delete_user(&self, user_id: &str) -> Result<(), OAuth2Error>;
 
     // Token operations
     async fn save_token(&self, token: &Token) -> Result<(), OAuth2Error>;
@@ -29,7 +165,39 @@ pub trait Storage: Send + Sync {
         &self,
         access_token: &str,
     ) -> Result<Option<Token>, OAuth2Error>;
+    async fn get_token_by_refresh_token(
+        &self,
+        refresh_token: &str,
+    ) -> Result<Option<Token>, OAuth2Error>;
     async fn revoke_token(&self, token: &str) -> Result<(), OAuth2Error>;
+    /// Revoke every token issued under `grant_id` (see [`Token::grant_id`]).
+    async fn revoke_token_grant(&self, grant_id: &str) -> Result<(), OAuth2Error>;
+    /// Revoked tokens that have not expired yet, soonest expiry first.
+    async fn list_revoked_tokens(&self) -> Result<Vec<Token>, OAuth2Error>;
+    /// Tokens whose metadata has `key` set to `value`, newest first.
+    async fn find_tokens_by_metadata(
+        &self,
+        query: &TokenMetadataQuery,
+    ) -> Result<Vec<Token>, OAuth2Error>;
+    /// Revoke every token whose metadata has `key` set to `value`; returns how many
+    /// tokens were newly revoked.
+    async fn revoke_tokens_by_metadata(&self, key: &str, value: &str) -> Result<u64, OAuth2Error>;
+    /// Tokens issued to `client_id`, newest first.
+    async fn list_tokens_by_client(
+        &self,
+        client_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Token>, OAuth2Error>;
+    /// Tokens issued to `user_id`, newest first.
+    async fn list_tokens_by_user(
+        &self,
+        user_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Token>, OAuth2Error>;
+    /// Every stored token, including revoked and expired ones.
+    async fn count_tokens(&self) -> Result<u64, OAuth2Error>;
+    /// Tokens that are neither revoked nor expired.
+    async fn count_active_tokens(&self) -> Result<u64, OAuth2Error>;
 
     // Authorization code operations
     async fn save_authorization_code(
@@ -42,6 +210,136 @@ pub trait Storage: Send + Sync {
     ) -> Result<Option<AuthorizationCode>, OAuth2Error>;
     async fn mark_authorization_code_used(&self, code: &str) -> Result<(), OAuth2Error>;
 
+    // Transactions
+    /// Start a transaction for writes that must take effect together, such as burning an
+    /// authorization code and saving the token issued for it.
+    async fn begin_transaction(&self) -> Result<Box<dyn StorageTransaction>, OAuth2Error>;
+
+    // Social login state operations
+    /// Save the record of an outbound social login. Implementations may drop expired
+    /// records at the same time.
+    async fn save_social_login_state(&self, state: &SocialLoginState) -> Result<(), OAuth2Error>;
+    /// Remove and return the record for `state`, so each is consumed at most once, even by
+    /// concurrent callbacks. Expired records are returned too; check
+    /// [`SocialLoginState::is_expired`].
+    async fn take_social_login_state(
+        &self,
+        state: &str,
+    ) -> Result<Option<SocialLoginState>, OAuth2Error>;
+
+    // Email token operations
+    /// Save a token mailed to a user. Implementations may drop expired tokens at the same
+    /// time.
+    async fn save_email_token(&self, token: &EmailToken) -> Result<(), OAuth2Error>;
+    /// Remove and return the token with `token_hash`, so each is used at most once.
+    /// Expired tokens are returned too; check [`EmailToken::is_expired`].
+    async fn take_email_token(&self, token_hash: &str) -> Result<Option<EmailToken>, OAuth2Error>;
+    /// Delete every token for `purpose` sent to `user_id`, e.g. once their password was
+    /// reset.
+    async fn delete_email_tokens(
+        &self,
+        user_id: &str,
+        purpose: EmailTokenPurpose,
+    ) -> Result<(), OAuth2Error>;
+
+    // Browser session operations
+    /// Save `session`, replacing a stored one with the same id. Implementations may drop
+    /// expired sessions at the same time.
+    async fn save_user_session(&self, session: &UserSession) -> Result<(), OAuth2Error>;
+    /// The session with `id`. Expired sessions are returned too; check
+    /// [`UserSession::is_expired`].
+    async fn get_user_session(&self, id: &str) -> Result<Option<UserSession>, OAuth2Error>;
+    /// Record a request in session `id`: set `last_seen_at` and `idle_expires_at`. Unknown
+    /// ids are ignored.
+    async fn touch_user_session(
+        &self,
+        id: &str,
+        last_seen_at: DateTime<Utc>,
+        idle_expires_at: DateTime<Utc>,
+    ) -> Result<(), OAuth2Error>;
+    /// Unexpired sessions of `user_id`, newest first.
+    async fn list_user_sessions(&self, user_id: &str) -> Result<Vec<UserSession>, OAuth2Error>;
+    async fn delete_user_session(&self, id: &str) -> Result<(), OAuth2Error>;
+
+    // Audit log operations
+    /// Append `record`. Audit records are kept when the user or client they name is
+    /// deleted.
+    async fn save_audit_record(&self, record: &AuditRecord) -> Result<(), OAuth2Error>;
+    /// Records matching `query`, newest first.
+    async fn list_audit_records(&self, query: &AuditQuery)
+        -> Result<Vec<AuditRecord>, OAuth2Error>;
+
+    // Event outbox operations
+    /// Queue `message` for the relay. Fails if a message with its id is already queued.
+    async fn enqueue_outbox_message(&self, message: &OutboxMessage) -> Result<(), OAuth2Error>;
+    /// Lease up to `limit` messages due at `now` until `lease_until`, oldest first.
+    /// Messages leased by another relay are not returned again before their lease ends.
+    async fn claim_outbox_messages(
+        &self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<OutboxMessage>, OAuth2Error>;
+    /// Remove a published message.
+    async fn delete_outbox_message(&self, id: &str) -> Result<(), OAuth2Error>;
+    /// Record a failed publish attempt; the message is tried again at `next_attempt_at`.
+    async fn reschedule_outbox_message(
+        &self,
+        id: &str,
+        next_attempt_at: DateTime<Utc>,
+        error: &str,
+    ) -> Result<(), OAuth2Error>;
+
+    // Federated identity operations
+    /// Link a provider account to a user. Fails if `(provider, provider_user_id)` is
+    /// already linked.
+    async fn save_federated_identity(
+        &self,
+        identity: &FederatedIdentity,
+    ) -> Result<(), OAuth2Error>;
+    async fn get_federated_identity(
+        &self,
+        provider: &str,
+        provider_user_id: &str,
+    ) -> Result<Option<FederatedIdentity>, OAuth2Error>;
+    /// Every provider account linked to `user_id`.
+    async fn list_federated_identities(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<FederatedIdentity>, OAuth2Error>;
+    /// Replace the stored provider tokens of an existing link.
+    async fn update_federated_identity_tokens(
+        &self,
+        identity: &FederatedIdentity,
+    ) -> Result<(), OAuth2Error>;
+    async fn delete_federated_identity(
+        &self,
+        provider: &str,
+        provider_user_id: &str,
+    ) -> Result<(), OAuth2Error>;
+
+    // Role and group operations
+    /// Fails if a role with the same name exists.
+    async fn save_role(&self, role: &Role) -> Result<(), OAuth2Error>;
+    /// List roles ordered by name.
+    async fn list_roles(&self) -> Result<Vec<Role>, OAuth2Error>;
+    /// Delete a role along with its assignments to users.
+    async fn delete_role(&self, name: &str) -> Result<(), OAuth2Error>;
+    /// Fails if a group with the same name exists.
+    async fn save_group(&self, group: &Group) -> Result<(), OAuth2Error>;
+    /// List groups ordered by name.
+    async fn list_groups(&self) -> Result<Vec<Group>, OAuth2Error>;
+    /// Delete a group along with its memberships.
+    async fn delete_group(&self, name: &str) -> Result<(), OAuth2Error>;
+    /// Names of the roles assigned to `user_id`, sorted.
+    async fn list_user_roles(&self, user_id: &str) -> Result<Vec<String>, OAuth2Error>;
+    /// Replace the roles assigned to `user_id`. Callers check that the roles exist.
+    async fn set_user_roles(&self, user_id: &str, roles: &[String]) -> Result<(), OAuth2Error>;
+    /// Names of the groups `user_id` is a member of, sorted.
+    async fn list_user_groups(&self, user_id: &str) -> Result<Vec<String>, OAuth2Error>;
+    /// Replace the groups `user_id` is a member of. Callers check that the groups exist.
+    async fn set_user_groups(&self, user_id: &str, groups: &[String]) -> Result<(), OAuth2Error>;
+
     /// Lightweight liveness/readiness check.
     ///
     /// Implementations may override to do something cheaper than `init()`.
@@ -51,3 +349,27 @@ pub trait Storage: Send + Sync {
 }
 
 pub type DynStorage = Arc<dyn Storage>;
+
+/// Writes that commit or roll back together; see [`Storage::begin_transaction`].
+///
+/// Reads outside the transaction do not see its writes until [`commit`](Self::commit)
+/// succeeds. Dropping an uncommitted transaction rolls it back.
+#[async_trait]
+pub trait StorageTransaction: Send {
+    /// Mark an unused authorization code used. Fails with `invalid_grant` if the code is
+    /// unknown or already used, so two concurrent exchanges of one code cannot both commit.
+    async fn consume_authorization_code(&mut self, code: &str) -> Result<(), OAuth2Error>;
+    async fn save_token(&mut self, token: &Token) -> Result<(), OAuth2Error>;
+    /// Revoke the token with this access or refresh token.
+    async fn revoke_token(&mut self, token: &str) -> Result<(), OAuth2Error>;
+    /// Revoke the unrevoked token with this refresh token. Fails with `invalid_grant` if
+    /// it is unknown or already revoked, so two concurrent rotations of one refresh token
+    /// cannot both commit.
+    async fn redeem_refresh_token(&mut self, refresh_token: &str) -> Result<(), OAuth2Error>;
+    /// Queue the event describing this transaction's writes; see
+    /// [`Storage::enqueue_outbox_message`].
+    async fn enqueue_outbox_message(&mut self, message: &OutboxMessage) -> Result<(), OAuth2Error>;
+
+    async fn commit(self: Box<Self>) -> Result<(), OAuth2Error>;
+    async fn rollback(self: Box<Self>) -> Result<(), OAuth2Error>;
+}
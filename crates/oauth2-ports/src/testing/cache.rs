@@ -0,0 +1,106 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use oauth2_core::OAuth2Error;
+
+use super::Failures;
+use crate::{Cache, DynClock, SystemClock};
+
+/// In-memory [`Cache`] whose entries expire by the given clock.
+pub struct FakeCache {
+    entries: Mutex<HashMap<String, (String, DateTime<Utc>)>>,
+    clock: DynClock,
+    failures: Failures,
+}
+
+impl FakeCache {
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(clock: DynClock) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            clock,
+            failures: Failures::default(),
+        }
+    }
+
+    pub fn failures(&self) -> &Failures {
+        &self.failures
+    }
+
+    /// Unexpired keys, sorted.
+    pub fn keys(&self) -> Vec<String> {
+        let now = self.clock.now();
+        let mut keys: Vec<String> = self
+            .lock()
+            .iter()
+            .filter(|(_, (_, expires_at))| *expires_at > now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, (String, DateTime<Utc>)>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for FakeCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Cache for FakeCache {
+    fn backend_name(&self) -> &'static str {
+        "fake"
+    }
+
+    async fn set_if_absent(
+        &self,
+        key: &str,
+        value: &str,
+        ttl: Duration,
+    ) -> Result<bool, OAuth2Error> {
+        self.failures.check("set_if_absent")?;
+        let now = self.clock.now();
+        let mut entries = self.lock();
+        if entries
+            .get(key)
+            .is_some_and(|(_, expires_at)| *expires_at > now)
+        {
+            return Ok(false);
+        }
+        let ttl = chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::MAX);
+        let expires_at = now
+            .checked_add_signed(ttl)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        entries.insert(key.to_string(), (value.to_string(), expires_at));
+        Ok(true)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>, OAuth2Error> {
+        self.failures.check("get")?;
+        let now = self.clock.now();
+        Ok(self
+            .lock()
+            .get(key)
+            .filter(|(_, expires_at)| *expires_at > now)
+            .map(|(value, _)| value.clone()))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), OAuth2Error> {
+        self.failures.check("delete")?;
+        self.lock().remove(key);
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+
+use oauth2_core::OAuth2Error;
+
+use super::Failures;
+use crate::{Invalidation, InvalidationChannel, InvalidationHandler};
+
+/// In-process [`InvalidationChannel`] that delivers each message to every subscriber
+/// synchronously, before `publish` returns.
+///
+/// Clones share subscribers, so one clone per simulated replica behaves like a shared bus.
+#[derive(Clone, Default)]
+pub struct FakeInvalidationChannel {
+    subscribers: Arc<Mutex<Vec<InvalidationHandler>>>,
+    failures: Arc<Failures>,
+}
+
+impl FakeInvalidationChannel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn failures(&self) -> &Failures {
+        &self.failures
+    }
+
+    /// Deliver [`Invalidation::Resync`] to every subscriber, as after a reconnect.
+    pub fn resync(&self) {
+        self.deliver(Invalidation::Resync);
+    }
+
+    fn deliver(&self, invalidation: Invalidation) {
+        let subscribers = self
+            .subscribers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for handler in subscribers.iter() {
+            handler(invalidation.clone());
+        }
+    }
+}
+
+#[async_trait]
+impl InvalidationChannel for FakeInvalidationChannel {
+    fn backend_name(&self) -> &'static str {
+        "fake"
+    }
+
+    async fn publish(&self, message: &str) -> Result<(), OAuth2Error> {
+        self.failures.check("publish")?;
+        self.deliver(Invalidation::Message(message.to_string()));
+        Ok(())
+    }
+
+    async fn subscribe(&self, handler: InvalidationHandler) -> Result<(), OAuth2Error> {
+        self.failures.check("subscribe")?;
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(handler);
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+//! Deterministic in-memory implementations of the ports, for integration tests.
+//!
+//! Each fake can be scripted to fail specific operations through [`Failures`], and
+//! exposes what it holds so tests can assert on exact results:
+//!
+//! ```ignore
+//! let clock = Arc::new(FixedClock::default());
+//! let storage = Arc::new(FakeStorage::with_clock(clock.clone()));
+//! storage.failures().fail_next("save_token", OAuth2Error::temporarily_unavailable("down"));
+//! ```
+
+mod cache;
+mod clock;
+mod invalidation;
+mod storage;
+
+pub use cache::FakeCache;
+pub use clock::FixedClock;
+pub use invalidation::FakeInvalidationChannel;
+pub use storage::FakeStorage;
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use oauth2_core::OAuth2Error;
+
+/// Scripted failures and a log of the operations a fake was asked to perform.
+///
+/// Operations are named after the trait method, e.g. `"save_token"` or `"set_if_absent"`.
+#[derive(Debug, Default)]
+pub struct Failures {
+    inner: Mutex<FailureState>,
+}
+
+#[derive(Debug, Default)]
+struct FailureState {
+    next: HashMap<String, VecDeque<OAuth2Error>>,
+    always: HashMap<String, OAuth2Error>,
+    calls: Vec<String>,
+}
+
+impl Failures {
+    /// Fail the next call to `operation` with `error`. Repeated calls queue up.
+    pub fn fail_next(&self, operation: &str, error: OAuth2Error) {
+        self.state()
+            .next
+            .entry(operation.to_string())
+            .or_default()
+            .push_back(error);
+    }
+
+    /// Fail every call to `operation` with `error` until [`Failures::clear`].
+    pub fn fail_always(&self, operation: &str, error: OAuth2Error) {
+        self.state().always.insert(operation.to_string(), error);
+    }
+
+    /// Drop every scripted failure; the call log is kept.
+    pub fn clear(&self) {
+        let mut state = self.state();
+        state.next.clear();
+        state.always.clear();
+    }
+
+    /// Operations performed so far, in order, including those that failed.
+    pub fn calls(&self) -> Vec<String> {
+        self.state().calls.clone()
+    }
+
+    /// Record a call to `operation` and return its scripted failure, if any.
+    pub(crate) fn check(&self, operation: &str) -> Result<(), OAuth2Error> {
+        let mut state = self.state();
+        state.calls.push(operation.to_string());
+        if let Some(error) = state.next.get_mut(operation).and_then(VecDeque::pop_front) {
+            return Err(error);
+        }
+        match state.always.get(operation) {
+            Some(error) => Err(error.clone()),
+            None => Ok(()),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, FailureState> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
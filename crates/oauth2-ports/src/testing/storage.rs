@@ -0,0 +1,1122 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use oauth2_core::{
+    AuditRecord, AuthorizationCode, Client, EmailToken, EmailTokenPurpose, FederatedIdentity,
+    Group, OAuth2Error, OutboxMessage, Role, ServiceAccount, SocialLoginState, Tenant, Token, User,
+    UserSession,
+};
+
+use super::Failures;
+use crate::{
+    AuditQuery, DynClock, PageRequest, Storage, StorageTransaction, SystemClock,
+    TokenMetadataQuery, UserListQuery,
+};
+
+/// In-memory [`Storage`] with the same uniqueness rules and cascades as the real backends.
+///
+/// Records are kept in insertion order, and expiry checks use the given clock.
+pub struct FakeStorage {
+    state: Arc<Mutex<State>>,
+    clock: DynClock,
+    failures: Arc<Failures>,
+}
+
+#[derive(Default)]
+struct State {
+    tenants: Vec<Tenant>,
+    clients: Vec<Client>,
+    service_accounts: Vec<ServiceAccount>,
+    users: Vec<User>,
+    tokens: Vec<Token>,
+    authorization_codes: Vec<AuthorizationCode>,
+    social_login_states: Vec<SocialLoginState>,
+    email_tokens: Vec<EmailToken>,
+    user_sessions: Vec<UserSession>,
+    audit_records: Vec<AuditRecord>,
+    outbox_messages: Vec<OutboxMessage>,
+    federated_identities: Vec<FederatedIdentity>,
+    roles: Vec<Role>,
+    groups: Vec<Group>,
+    /// `(user_id, role)` pairs.
+    user_roles: Vec<(String, String)>,
+    /// `(user_id, group)` pairs.
+    user_groups: Vec<(String, String)>,
+}
+
+impl State {
+    fn insert_token(&mut self, token: &Token) -> Result<(), OAuth2Error> {
+        if self.tokens.iter().any(|t| {
+            t.access_token == token.access_token
+                || (token.refresh_token.is_some() && t.refresh_token == token.refresh_token)
+        }) {
+            return Err(duplicate("token"));
+        }
+        self.tokens.push(token.clone());
+        Ok(())
+    }
+
+    fn insert_outbox_message(&mut self, message: &OutboxMessage) -> Result<(), OAuth2Error> {
+        if self.outbox_messages.iter().any(|m| m.id == message.id) {
+            return Err(duplicate("outbox message"));
+        }
+        self.outbox_messages.push(message.clone());
+        Ok(())
+    }
+
+    fn revoke_token(&mut self, token: &str) {
+        for t in self
+            .tokens
+            .iter_mut()
+            .filter(|t| t.access_token == token || t.refresh_token.as_deref() == Some(token))
+        {
+            t.revoked = true;
+        }
+    }
+
+    fn unrevoked_refresh_token(&mut self, refresh_token: &str) -> Result<&mut Token, OAuth2Error> {
+        self.tokens
+            .iter_mut()
+            .find(|t| t.refresh_token.as_deref() == Some(refresh_token) && !t.revoked)
+            .ok_or_else(|| OAuth2Error::invalid_grant("Refresh token is revoked"))
+    }
+
+    fn unused_code(&mut self, code: &str) -> Result<&mut AuthorizationCode, OAuth2Error> {
+        self.authorization_codes
+            .iter_mut()
+            .find(|c| c.code == code && !c.used)
+            .ok_or_else(|| {
+                OAuth2Error::invalid_grant("Authorization code is invalid or already used")
+            })
+    }
+}
+
+fn lock_state(state: &Mutex<State>) -> std::sync::MutexGuard<'_, State> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn paginate<T>(items: Vec<T>, page: PageRequest) -> Vec<T> {
+    items
+        .into_iter()
+        .skip(usize::try_from(page.offset()).unwrap_or(usize::MAX))
+        .take(page.limit() as usize)
+        .collect()
+}
+
+fn duplicate(what: &str) -> OAuth2Error {
+    OAuth2Error::new("server_error", Some(&format!("duplicate {what}")))
+}
+
+impl FakeStorage {
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(clock: DynClock) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State::default())),
+            clock,
+            failures: Arc::new(Failures::default()),
+        }
+    }
+
+    pub fn failures(&self) -> &Failures {
+        &self.failures
+    }
+
+    /// Every saved client, in insertion order.
+    pub fn clients(&self) -> Vec<Client> {
+        self.lock().clients.clone()
+    }
+
+    /// Every queued outbox message, in insertion order.
+    pub fn outbox_messages(&self) -> Vec<OutboxMessage> {
+        self.lock().outbox_messages.clone()
+    }
+
+    /// Every saved user, in insertion order.
+    pub fn users(&self) -> Vec<User> {
+        self.lock().users.clone()
+    }
+
+    /// Every saved token, in insertion order.
+    pub fn tokens(&self) -> Vec<Token> {
+        self.lock().tokens.clone()
+    }
+
+    /// Every saved authorization code, in insertion order.
+    pub fn authorization_codes(&self) -> Vec<AuthorizationCode> {
+        self.lock().authorization_codes.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        lock_state(&self.state)
+    }
+}
+
+impl Default for FakeStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Storage for FakeStorage {
+    async fn init(&self) -> Result<(), OAuth2Error> {
+        self.failures.check("init")
+    }
+
+    async fn save_tenant(&self, tenant: &Tenant) -> Result<(), OAuth2Error> {
+        self.failures.check("save_tenant")?;
+        let mut state = self.lock();
+        if state.tenants.iter().any(|t| t.id == tenant.id) {
+            return Err(duplicate("tenant id"));
+        }
+        state.tenants.push(tenant.clone());
+        Ok(())
+    }
+
+    async fn get_tenant(&self, id: &str) -> Result<Option<Tenant>, OAuth2Error> {
+        self.failures.check("get_tenant")?;
+        Ok(self.lock().tenants.iter().find(|t| t.id == id).cloned())
+    }
+
+    async fn list_tenants(&self) -> Result<Vec<Tenant>, OAuth2Error> {
+        self.failures.check("list_tenants")?;
+        let mut tenants = self.lock().tenants.clone();
+        tenants.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(tenants)
+    }
+
+    async fn save_client(&self, client: &Client) -> Result<(), OAuth2Error> {
+        self.failures.check("save_client")?;
+        let mut state = self.lock();
+        if state
+            .clients
+            .iter()
+            .any(|c| c.client_id == client.client_id && c.tenant_id == client.tenant_id)
+        {
+            return Err(duplicate("client_id"));
+        }
+        state.clients.push(client.clone());
+        Ok(())
+    }
+
+    async fn get_client(
+        &self,
+        client_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<Client>, OAuth2Error> {
+        self.failures.check("get_client")?;
+        Ok(self
+            .lock()
+            .clients
+            .iter()
+            .find(|c| c.client_id == client_id && c.in_tenant(tenant_id))
+            .cloned())
+    }
+
+    async fn list_clients_managed_by(&self, manager: &str) -> Result<Vec<Client>, OAuth2Error> {
+        self.failures.check("list_clients_managed_by")?;
+        let mut clients: Vec<Client> = self
+            .lock()
+            .clients
+            .iter()
+            .filter(|c| c.managed_by.as_deref() == Some(manager))
+            .cloned()
+            .collect();
+        clients.sort_by(|a, b| a.client_id.cmp(&b.client_id));
+        Ok(clients)
+    }
+
+    async fn list_clients(&self, page: PageRequest) -> Result<Vec<Client>, OAuth2Error> {
+        self.failures.check("list_clients")?;
+        let mut clients = self.lock().clients.clone();
+        clients.sort_by(|a, b| (&a.client_id, &a.id).cmp(&(&b.client_id, &b.id)));
+        Ok(paginate(clients, page))
+    }
+
+    async fn count_clients(&self) -> Result<u64, OAuth2Error> {
+        self.failures.check("count_clients")?;
+        Ok(self.lock().clients.len() as u64)
+    }
+
+    async fn update_client(&self, client: &Client) -> Result<(), OAuth2Error> {
+        self.failures.check("update_client")?;
+        if let Some(existing) = self
+            .lock()
+            .clients
+            .iter_mut()
+            .find(|c| c.client_id == client.client_id && c.tenant_id == client.tenant_id)
+        {
+            *existing = client.clone();
+        }
+        Ok(())
+    }
+
+    async fn delete_client(
+        &self,
+        client_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<(), OAuth2Error> {
+        self.failures.check("delete_client")?;
+        let mut state = self.lock();
+        // Codes carry no tenant; those of other tenants' users stay.
+        let other_tenants_users: HashSet<String> = state
+            .users
+            .iter()
+            .filter(|user| !user.in_tenant(tenant_id))
+            .map(|user| user.id.clone())
+            .collect();
+        state.authorization_codes.retain(|code| {
+            code.client_id != client_id || other_tenants_users.contains(&code.user_id)
+        });
+        state
+            .tokens
+            .retain(|token| token.client_id != client_id || !token.in_tenant(tenant_id));
+        if tenant_id.is_none() {
+            state
+                .service_accounts
+                .retain(|account| account.client_id != client_id);
+        }
+        state
+            .clients
+            .retain(|c| c.client_id != client_id || !c.in_tenant(tenant_id));
+        Ok(())
+    }
+
+    async fn save_service_account(&self, account: &ServiceAccount) -> Result<(), OAuth2Error> {
+        self.failures.check("save_service_account")?;
+        let mut state = self.lock();
+        if state
+            .service_accounts
+            .iter()
+            .any(|a| a.id == account.id || a.client_id == account.client_id)
+        {
+            return Err(duplicate("service account"));
+        }
+        state.service_accounts.push(account.clone());
+        Ok(())
+    }
+
+    async fn get_service_account(&self, id: &str) -> Result<Option<ServiceAccount>, OAuth2Error> {
+        self.failures.check("get_service_account")?;
+        Ok(self
+            .lock()
+            .service_accounts
+            .iter()
+            .find(|a| a.id == id)
+            .cloned())
+    }
+
+    async fn get_service_account_by_client_id(
+        &self,
+        client_id: &str,
+    ) -> Result<Option<ServiceAccount>, OAuth2Error> {
+        self.failures.check("get_service_account_by_client_id")?;
+        Ok(self
+            .lock()
+            .service_accounts
+            .iter()
+            .find(|a| a.client_id == client_id)
+            .cloned())
+    }
+
+    async fn list_service_accounts(&self) -> Result<Vec<ServiceAccount>, OAuth2Error> {
+        self.failures.check("list_service_accounts")?;
+        let mut accounts = self.lock().service_accounts.clone();
+        accounts.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(accounts)
+    }
+
+    async fn update_service_account(&self, account: &ServiceAccount) -> Result<(), OAuth2Error> {
+        self.failures.check("update_service_account")?;
+        if let Some(existing) = self
+            .lock()
+            .service_accounts
+            .iter_mut()
+            .find(|a| a.id == account.id)
+        {
+            *existing = account.clone();
+        }
+        Ok(())
+    }
+
+    async fn save_user(&self, user: &User) -> Result<(), OAuth2Error> {
+        self.failures.check("save_user")?;
+        let mut state = self.lock();
+        if state.users.iter().any(|u| {
+            u.id == user.id || (u.username == user.username && u.tenant_id == user.tenant_id)
+        }) {
+            return Err(duplicate("user"));
+        }
+        state.users.push(user.clone());
+        Ok(())
+    }
+
+    async fn get_user(&self, user_id: &str) -> Result<Option<User>, OAuth2Error> {
+        self.failures.check("get_user")?;
+        Ok(self.lock().users.iter().find(|u| u.id == user_id).cloned())
+    }
+
+    async fn get_user_by_username(
+        &self,
+        username: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<User>, OAuth2Error> {
+        self.failures.check("get_user_by_username")?;
+        Ok(self
+            .lock()
+            .users
+            .iter()
+            .find(|u| u.username == username && u.in_tenant(tenant_id))
+            .cloned())
+    }
+
+    async fn list_users_by_email(&self, email: &str) -> Result<Vec<User>, OAuth2Error> {
+        self.failures.check("list_users_by_email")?;
+        let mut users: Vec<User> = self
+            .lock()
+            .users
+            .iter()
+            .filter(|u| u.email.eq_ignore_ascii_case(email))
+            .cloned()
+            .collect();
+        users.sort_by(|a, b| a.username.cmp(&b.username));
+        Ok(users)
+    }
+
+    async fn list_users(&self, query: &UserListQuery) -> Result<Vec<User>, OAuth2Error> {
+        self.failures.check("list_users")?;
+        let search = query.search.as_deref().map(str::to_lowercase);
+        let mut users: Vec<User> = self
+            .lock()
+            .users
+            .iter()
+            .filter(|u| {
+                search.as_deref().is_none_or(|search| {
+                    u.username.to_lowercase().contains(search)
+                        || u.email.to_lowercase().contains(search)
+                })
+            })
+            .cloned()
+            .collect();
+        users.sort_by(|a, b| (&a.username, &a.id).cmp(&(&b.username, &b.id)));
+        Ok(users
+            .into_iter()
+            .skip(query.offset as usize)
+            .take(query.limit as usize)
+            .collect())
+    }
+
+    async fn count_users(&self) -> Result<u64, OAuth2Error> {
+        self.failures.check("count_users")?;
+        Ok(self.lock().users.len() as u64)
+    }
+
+    async fn update_user(&self, user: &User) -> Result<(), OAuth2Error> {
+        self.failures.check("update_user")?;
+        if let Some(existing) = self.lock().users.iter_mut().find(|u| u.id == user.id) {
+            *existing = user.clone();
+        }
+        Ok(())
+    }
+
+    async fn delete_user(&self, user_id: &str) -> Result<(), OAuth2Error> {
+        self.failures.check("delete_user")?;
+        let mut state = self.lock();
+        state
+            .authorization_codes
+            .retain(|code| code.user_id != user_id);
+        state
+            .tokens
+            .retain(|token| token.user_id.as_deref() != Some(user_id));
+        state
+            .federated_identities
+            .retain(|identity| identity.user_id != user_id);
+        state.user_roles.retain(|(user, _)| user != user_id);
+        state.user_groups.retain(|(user, _)| user != user_id);
+        state.email_tokens.retain(|token| token.user_id != user_id);
+        state
+            .user_sessions
+            .retain(|session| session.user_id.as_deref() != Some(user_id));
+        state.users.retain(|u| u.id != user_id);
+        Ok(())
+    }
+
+    async fn save_token(&self, token: &Token) -> Result<(), OAuth2Error> {
+        self.failures.check("save_token")?;
+        self.lock().insert_token(token)
+    }
+
+    async fn get_token_by_access_token(
+        &self,
+        access_token: &str,
+    ) -> Result<Option<Token>, OAuth2Error> {
+        self.failures.check("get_token_by_access_token")?;
+        Ok(self
+            .lock()
+            .tokens
+            .iter()
+            .find(|t| t.access_token == access_token)
+            .cloned())
+    }
+
+    async fn get_token_by_refresh_token(
+        &self,
+        refresh_token: &str,
+    ) -> Result<Option<Token>, OAuth2Error> {
+        self.failures.check("get_token_by_refresh_token")?;
+        Ok(self
+            .lock()
+            .tokens
+            .iter()
+            .find(|t| t.refresh_token.as_deref() == Some(refresh_token))
+            .cloned())
+    }
+
+    async fn revoke_token(&self, token: &str) -> Result<(), OAuth2Error> {
+        self.failures.check("revoke_token")?;
+        self.lock().revoke_token(token);
+        Ok(())
+    }
+
+    async fn revoke_token_grant(&self, grant_id: &str) -> Result<(), OAuth2Error> {
+        self.failures.check("revoke_token_grant")?;
+        for t in self
+            .lock()
+            .tokens
+            .iter_mut()
+            .filter(|t| t.grant_id() == grant_id)
+        {
+            t.revoked = true;
+        }
+        Ok(())
+    }
+
+    async fn list_revoked_tokens(&self) -> Result<Vec<Token>, OAuth2Error> {
+        self.failures.check("list_revoked_tokens")?;
+        let now = self.clock.now();
+        let mut tokens: Vec<Token> = self
+            .lock()
+            .tokens
+            .iter()
+            .filter(|t| t.revoked && t.expires_at > now)
+            .cloned()
+            .collect();
+        tokens.sort_by_key(|t| t.expires_at);
+        Ok(tokens)
+    }
+
+    async fn find_tokens_by_metadata(
+        &self,
+        query: &TokenMetadataQuery,
+    ) -> Result<Vec<Token>, OAuth2Error> {
+        self.failures.check("find_tokens_by_metadata")?;
+        let mut tokens: Vec<Token> = self
+            .lock()
+            .tokens
+            .iter()
+            .filter(|t| t.metadata.get(&query.key) == Some(query.value.as_str()))
+            .cloned()
+            .collect();
+        tokens.sort_by_key(|t| std::cmp::Reverse(t.created_at));
+        Ok(tokens
+            .into_iter()
+            .skip(query.offset as usize)
+            .take(query.limit as usize)
+            .collect())
+    }
+
+    async fn revoke_tokens_by_metadata(&self, key: &str, value: &str) -> Result<u64, OAuth2Error> {
+        self.failures.check("revoke_tokens_by_metadata")?;
+        let mut revoked = 0;
+        for t in self
+            .lock()
+            .tokens
+            .iter_mut()
+            .filter(|t| !t.revoked && t.metadata.get(key) == Some(value))
+        {
+            t.revoked = true;
+            revoked += 1;
+        }
+        Ok(revoked)
+    }
+
+    async fn list_tokens_by_client(
+        &self,
+        client_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Token>, OAuth2Error> {
+        self.failures.check("list_tokens_by_client")?;
+        let mut tokens: Vec<Token> = self
+            .lock()
+            .tokens
+            .iter()
+            .filter(|t| t.client_id == client_id)
+            .cloned()
+            .collect();
+        tokens.sort_by_key(|t| std::cmp::Reverse(t.created_at));
+        Ok(paginate(tokens, page))
+    }
+
+    async fn list_tokens_by_user(
+        &self,
+        user_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Token>, OAuth2Error> {
+        self.failures.check("list_tokens_by_user")?;
+        let mut tokens: Vec<Token> = self
+            .lock()
+            .tokens
+            .iter()
+            .filter(|t| t.user_id.as_deref() == Some(user_id))
+            .cloned()
+            .collect();
+        tokens.sort_by_key(|t| std::cmp::Reverse(t.created_at));
+        Ok(paginate(tokens, page))
+    }
+
+    async fn count_tokens(&self) -> Result<u64, OAuth2Error> {
+        self.failures.check("count_tokens")?;
+        Ok(self.lock().tokens.len() as u64)
+    }
+
+    async fn count_active_tokens(&self) -> Result<u64, OAuth2Error> {
+        self.failures.check("count_active_tokens")?;
+        let now = self.clock.now();
+        Ok(self
+            .lock()
+            .tokens
+            .iter()
+            .filter(|t| !t.revoked && t.expires_at > now)
+            .count() as u64)
+    }
+
+    async fn save_authorization_code(
+        &self,
+        auth_code: &AuthorizationCode,
+    ) -> Result<(), OAuth2Error> {
+        self.failures.check("save_authorization_code")?;
+        let mut state = self.lock();
+        if state
+            .authorization_codes
+            .iter()
+            .any(|c| c.code == auth_code.code)
+        {
+            return Err(duplicate("authorization code"));
+        }
+        state.authorization_codes.push(auth_code.clone());
+        Ok(())
+    }
+
+    async fn get_authorization_code(
+        &self,
+        code: &str,
+    ) -> Result<Option<AuthorizationCode>, OAuth2Error> {
+        self.failures.check("get_authorization_code")?;
+        Ok(self
+            .lock()
+            .authorization_codes
+            .iter()
+            .find(|c| c.code == code)
+            .cloned())
+    }
+
+    async fn mark_authorization_code_used(&self, code: &str) -> Result<(), OAuth2Error> {
+        self.failures.check("mark_authorization_code_used")?;
+        if let Some(existing) = self
+            .lock()
+            .authorization_codes
+            .iter_mut()
+            .find(|c| c.code == code)
+        {
+            existing.used = true;
+        }
+        Ok(())
+    }
+
+    async fn begin_transaction(&self) -> Result<Box<dyn StorageTransaction>, OAuth2Error> {
+        self.failures.check("begin_transaction")?;
+        Ok(Box::new(FakeTransaction {
+            state: self.state.clone(),
+            failures: self.failures.clone(),
+            writes: Vec::new(),
+        }))
+    }
+
+    async fn save_social_login_state(&self, state: &SocialLoginState) -> Result<(), OAuth2Error> {
+        self.failures.check("save_social_login_state")?;
+        let now = self.clock.now();
+        let mut store = self.lock();
+        store.social_login_states.retain(|s| s.expires_at > now);
+        if store
+            .social_login_states
+            .iter()
+            .any(|s| s.state == state.state)
+        {
+            return Err(duplicate("social login state"));
+        }
+        store.social_login_states.push(state.clone());
+        Ok(())
+    }
+
+    async fn take_social_login_state(
+        &self,
+        state: &str,
+    ) -> Result<Option<SocialLoginState>, OAuth2Error> {
+        self.failures.check("take_social_login_state")?;
+        let mut store = self.lock();
+        let index = store
+            .social_login_states
+            .iter()
+            .position(|s| s.state == state);
+        Ok(index.map(|index| store.social_login_states.remove(index)))
+    }
+
+    async fn save_email_token(&self, token: &EmailToken) -> Result<(), OAuth2Error> {
+        self.failures.check("save_email_token")?;
+        let now = self.clock.now();
+        let mut store = self.lock();
+        store.email_tokens.retain(|t| t.expires_at > now);
+        if store
+            .email_tokens
+            .iter()
+            .any(|t| t.token_hash == token.token_hash)
+        {
+            return Err(duplicate("email token"));
+        }
+        store.email_tokens.push(token.clone());
+        Ok(())
+    }
+
+    async fn take_email_token(&self, token_hash: &str) -> Result<Option<EmailToken>, OAuth2Error> {
+        self.failures.check("take_email_token")?;
+        let mut store = self.lock();
+        let index = store
+            .email_tokens
+            .iter()
+            .position(|t| t.token_hash == token_hash);
+        Ok(index.map(|index| store.email_tokens.remove(index)))
+    }
+
+    async fn delete_email_tokens(
+        &self,
+        user_id: &str,
+        purpose: EmailTokenPurpose,
+    ) -> Result<(), OAuth2Error> {
+        self.failures.check("delete_email_tokens")?;
+        self.lock()
+            .email_tokens
+            .retain(|t| t.user_id != user_id || t.purpose != purpose);
+        Ok(())
+    }
+
+    async fn save_user_session(&self, session: &UserSession) -> Result<(), OAuth2Error> {
+        self.failures.check("save_user_session")?;
+        let now = self.clock.now();
+        let mut store = self.lock();
+        store
+            .user_sessions
+            .retain(|s| s.id != session.id && s.idle_expires_at > now && s.expires_at > now);
+        store.user_sessions.push(session.clone());
+        Ok(())
+    }
+
+    async fn get_user_session(&self, id: &str) -> Result<Option<UserSession>, OAuth2Error> {
+        self.failures.check("get_user_session")?;
+        Ok(self
+            .lock()
+            .user_sessions
+            .iter()
+            .find(|s| s.id == id)
+            .cloned())
+    }
+
+    async fn touch_user_session(
+        &self,
+        id: &str,
+        last_seen_at: DateTime<Utc>,
+        idle_expires_at: DateTime<Utc>,
+    ) -> Result<(), OAuth2Error> {
+        self.failures.check("touch_user_session")?;
+        if let Some(session) = self.lock().user_sessions.iter_mut().find(|s| s.id == id) {
+            session.last_seen_at = last_seen_at;
+            session.idle_expires_at = idle_expires_at;
+        }
+        Ok(())
+    }
+
+    async fn list_user_sessions(&self, user_id: &str) -> Result<Vec<UserSession>, OAuth2Error> {
+        self.failures.check("list_user_sessions")?;
+        let now = self.clock.now();
+        let mut sessions: Vec<UserSession> = self
+            .lock()
+            .user_sessions
+            .iter()
+            .filter(|s| {
+                s.user_id.as_deref() == Some(user_id)
+                    && s.idle_expires_at > now
+                    && s.expires_at > now
+            })
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.created_at));
+        Ok(sessions)
+    }
+
+    async fn delete_user_session(&self, id: &str) -> Result<(), OAuth2Error> {
+        self.failures.check("delete_user_session")?;
+        self.lock().user_sessions.retain(|s| s.id != id);
+        Ok(())
+    }
+
+    async fn save_audit_record(&self, record: &AuditRecord) -> Result<(), OAuth2Error> {
+        self.failures.check("save_audit_record")?;
+        self.lock().audit_records.push(record.clone());
+        Ok(())
+    }
+
+    async fn list_audit_records(
+        &self,
+        query: &AuditQuery,
+    ) -> Result<Vec<AuditRecord>, OAuth2Error> {
+        self.failures.check("list_audit_records")?;
+        let mut records: Vec<AuditRecord> = self
+            .lock()
+            .audit_records
+            .iter()
+            .filter(|record| query.matches(record))
+            .cloned()
+            .collect();
+        records.sort_by_key(|record| std::cmp::Reverse(record.occurred_at));
+        records.truncate(query.limit as usize);
+        Ok(records)
+    }
+
+    async fn enqueue_outbox_message(&self, message: &OutboxMessage) -> Result<(), OAuth2Error> {
+        self.failures.check("enqueue_outbox_message")?;
+        self.lock().insert_outbox_message(message)
+    }
+
+    async fn claim_outbox_messages(
+        &self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<OutboxMessage>, OAuth2Error> {
+        self.failures.check("claim_outbox_messages")?;
+        let mut state = self.lock();
+        let mut due: Vec<&mut OutboxMessage> = state
+            .outbox_messages
+            .iter_mut()
+            .filter(|m| m.next_attempt_at <= now)
+            .collect();
+        due.sort_by_key(|m| m.created_at);
+        Ok(due
+            .into_iter()
+            .take(limit as usize)
+            .map(|m| {
+                m.next_attempt_at = lease_until;
+                m.clone()
+            })
+            .collect())
+    }
+
+    async fn delete_outbox_message(&self, id: &str) -> Result<(), OAuth2Error> {
+        self.failures.check("delete_outbox_message")?;
+        self.lock().outbox_messages.retain(|m| m.id != id);
+        Ok(())
+    }
+
+    async fn reschedule_outbox_message(
+        &self,
+        id: &str,
+        next_attempt_at: DateTime<Utc>,
+        error: &str,
+    ) -> Result<(), OAuth2Error> {
+        self.failures.check("reschedule_outbox_message")?;
+        if let Some(m) = self.lock().outbox_messages.iter_mut().find(|m| m.id == id) {
+            m.attempts += 1;
+            m.next_attempt_at = next_attempt_at;
+            m.last_error = Some(error.to_string());
+        }
+        Ok(())
+    }
+
+    async fn save_federated_identity(
+        &self,
+        identity: &FederatedIdentity,
+    ) -> Result<(), OAuth2Error> {
+        self.failures.check("save_federated_identity")?;
+        let mut state = self.lock();
+        if state.federated_identities.iter().any(|i| {
+            i.provider == identity.provider && i.provider_user_id == identity.provider_user_id
+        }) {
+            return Err(duplicate("federated identity"));
+        }
+        state.federated_identities.push(identity.clone());
+        Ok(())
+    }
+
+    async fn get_federated_identity(
+        &self,
+        provider: &str,
+        provider_user_id: &str,
+    ) -> Result<Option<FederatedIdentity>, OAuth2Error> {
+        self.failures.check("get_federated_identity")?;
+        Ok(self
+            .lock()
+            .federated_identities
+            .iter()
+            .find(|i| i.provider == provider && i.provider_user_id == provider_user_id)
+            .cloned())
+    }
+
+    async fn list_federated_identities(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<FederatedIdentity>, OAuth2Error> {
+        self.failures.check("list_federated_identities")?;
+        let mut identities: Vec<_> = self
+            .lock()
+            .federated_identities
+            .iter()
+            .filter(|i| i.user_id == user_id)
+            .cloned()
+            .collect();
+        identities.sort_by(|a, b| {
+            (&a.provider, &a.provider_user_id).cmp(&(&b.provider, &b.provider_user_id))
+        });
+        Ok(identities)
+    }
+
+    async fn update_federated_identity_tokens(
+        &self,
+        identity: &FederatedIdentity,
+    ) -> Result<(), OAuth2Error> {
+        self.failures.check("update_federated_identity_tokens")?;
+        let mut state = self.lock();
+        if let Some(stored) = state.federated_identities.iter_mut().find(|i| {
+            i.provider == identity.provider && i.provider_user_id == identity.provider_user_id
+        }) {
+            stored.access_token = identity.access_token.clone();
+            stored.refresh_token = identity.refresh_token.clone();
+            stored.token_expires_at = identity.token_expires_at;
+        }
+        Ok(())
+    }
+
+    async fn delete_federated_identity(
+        &self,
+        provider: &str,
+        provider_user_id: &str,
+    ) -> Result<(), OAuth2Error> {
+        self.failures.check("delete_federated_identity")?;
+        self.lock()
+            .federated_identities
+            .retain(|i| !(i.provider == provider && i.provider_user_id == provider_user_id));
+        Ok(())
+    }
+
+    async fn save_role(&self, role: &Role) -> Result<(), OAuth2Error> {
+        self.failures.check("save_role")?;
+        let mut state = self.lock();
+        if state.roles.iter().any(|r| r.name == role.name) {
+            return Err(duplicate("role"));
+        }
+        state.roles.push(role.clone());
+        Ok(())
+    }
+
+    async fn list_roles(&self) -> Result<Vec<Role>, OAuth2Error> {
+        self.failures.check("list_roles")?;
+        let mut roles = self.lock().roles.clone();
+        roles.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(roles)
+    }
+
+    async fn delete_role(&self, name: &str) -> Result<(), OAuth2Error> {
+        self.failures.check("delete_role")?;
+        let mut state = self.lock();
+        state.user_roles.retain(|(_, role)| role != name);
+        state.roles.retain(|r| r.name != name);
+        Ok(())
+    }
+
+    async fn save_group(&self, group: &Group) -> Result<(), OAuth2Error> {
+        self.failures.check("save_group")?;
+        let mut state = self.lock();
+        if state.groups.iter().any(|g| g.name == group.name) {
+            return Err(duplicate("group"));
+        }
+        state.groups.push(group.clone());
+        Ok(())
+    }
+
+    async fn list_groups(&self) -> Result<Vec<Group>, OAuth2Error> {
+        self.failures.check("list_groups")?;
+        let mut groups = self.lock().groups.clone();
+        groups.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(groups)
+    }
+
+    async fn delete_group(&self, name: &str) -> Result<(), OAuth2Error> {
+        self.failures.check("delete_group")?;
+        let mut state = self.lock();
+        state.user_groups.retain(|(_, group)| group != name);
+        state.groups.retain(|g| g.name != name);
+        Ok(())
+    }
+
+    async fn list_user_roles(&self, user_id: &str) -> Result<Vec<String>, OAuth2Error> {
+        self.failures.check("list_user_roles")?;
+        Ok(assigned(&self.lock().user_roles, user_id))
+    }
+
+    async fn set_user_roles(&self, user_id: &str, roles: &[String]) -> Result<(), OAuth2Error> {
+        self.failures.check("set_user_roles")?;
+        assign(&mut self.lock().user_roles, user_id, roles);
+        Ok(())
+    }
+
+    async fn list_user_groups(&self, user_id: &str) -> Result<Vec<String>, OAuth2Error> {
+        self.failures.check("list_user_groups")?;
+        Ok(assigned(&self.lock().user_groups, user_id))
+    }
+
+    async fn set_user_groups(&self, user_id: &str, groups: &[String]) -> Result<(), OAuth2Error> {
+        self.failures.check("set_user_groups")?;
+        assign(&mut self.lock().user_groups, user_id, groups);
+        Ok(())
+    }
+
+    async fn healthcheck(&self) -> Result<(), OAuth2Error> {
+        self.failures.check("healthcheck")
+    }
+}
+
+/// Sorted names paired with `user_id`.
+fn assigned(pairs: &[(String, String)], user_id: &str) -> Vec<String> {
+    let mut names: Vec<String> = pairs
+        .iter()
+        .filter(|(user, _)| user == user_id)
+        .map(|(_, name)| name.clone())
+        .collect();
+    names.sort();
+    names
+}
+
+/// Replace the names paired with `user_id`.
+fn assign(pairs: &mut Vec<(String, String)>, user_id: &str, names: &[String]) {
+    pairs.retain(|(user, _)| user != user_id);
+    for name in names {
+        let pair = (user_id.to_string(), name.clone());
+        if !pairs.contains(&pair) {
+            pairs.push(pair);
+        }
+    }
+}
+
+enum Write {
+    ConsumeCode(String),
+    SaveToken(Box<Token>),
+    RevokeToken(String),
+    RedeemRefreshToken(String),
+    EnqueueOutboxMessage(Box<OutboxMessage>),
+}
+
+/// Buffers writes and applies them under a single lock on commit, as if serialized.
+struct FakeTransaction {
+    state: Arc<Mutex<State>>,
+    failures: Arc<Failures>,
+    writes: Vec<Write>,
+}
+
+#[async_trait]
+impl StorageTransaction for FakeTransaction {
+    async fn consume_authorization_code(&mut self, code: &str) -> Result<(), OAuth2Error> {
+        self.failures.check("consume_authorization_code")?;
+        let consumed = self
+            .writes
+            .iter()
+            .any(|w| matches!(w, Write::ConsumeCode(c) if c == code));
+        if consumed {
+            return Err(OAuth2Error::invalid_grant(
+                "Authorization code is invalid or already used",
+            ));
+        }
+        lock_state(&self.state).unused_code(code)?;
+        self.writes.push(Write::ConsumeCode(code.to_string()));
+        Ok(())
+    }
+
+    async fn save_token(&mut self, token: &Token) -> Result<(), OAuth2Error> {
+        self.failures.check("save_token")?;
+        self.writes.push(Write::SaveToken(Box::new(token.clone())));
+        Ok(())
+    }
+
+    async fn revoke_token(&mut self, token: &str) -> Result<(), OAuth2Error> {
+        self.failures.check("revoke_token")?;
+        self.writes.push(Write::RevokeToken(token.to_string()));
+        Ok(())
+    }
+
+    async fn redeem_refresh_token(&mut self, refresh_token: &str) -> Result<(), OAuth2Error> {
+        self.failures.check("redeem_refresh_token")?;
+        let redeemed = self
+            .writes
+            .iter()
+            .any(|w| matches!(w, Write::RedeemRefreshToken(t) if t == refresh_token));
+        if redeemed {
+            return Err(OAuth2Error::invalid_grant("Refresh token is revoked"));
+        }
+        lock_state(&self.state).unrevoked_refresh_token(refresh_token)?;
+        self.writes
+            .push(Write::RedeemRefreshToken(refresh_token.to_string()));
+        Ok(())
+    }
+
+    async fn enqueue_outbox_message(&mut self, message: &OutboxMessage) -> Result<(), OAuth2Error> {
+        self.failures.check("enqueue_outbox_message")?;
+        self.writes
+            .push(Write::EnqueueOutboxMessage(Box::new(message.clone())));
+        Ok(())
+    }
+
+    async fn commit(self: Box<Self>) -> Result<(), OAuth2Error> {
+        self.failures.check("commit")?;
+        let mut state = lock_state(&self.state);
+        // Apply to a copy so a conflict leaves nothing behind.
+        let mut next = State {
+            tokens: state.tokens.clone(),
+            authorization_codes: state.authorization_codes.clone(),
+            outbox_messages: state.outbox_messages.clone(),
+            ..State::default()
+        };
+        for write in &self.writes {
+            match write {
+                Write::ConsumeCode(code) => next.unused_code(code)?.used = true,
+                Write::SaveToken(token) => next.insert_token(token)?,
+                Write::RevokeToken(token) => next.revoke_token(token),
+                Write::RedeemRefreshToken(token) => {
+                    next.unrevoked_refresh_token(token)?.revoked = true
+                }
+                Write::EnqueueOutboxMessage(message) => next.insert_outbox_message(message)?,
+            }
+        }
+        state.tokens = next.tokens;
+        state.authorization_codes = next.authorization_codes;
+        state.outbox_messages = next.outbox_messages;
+        Ok(())
+    }
+
+    async fn rollback(self: Box<Self>) -> Result<(), OAuth2Error> {
+        self.failures.check("rollback")
+    }
+}
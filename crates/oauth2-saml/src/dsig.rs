@@ -0,0 +1,186 @@
+//! Enveloped XML signatures (XML-DSig) as identity providers put them on SAML responses and
+//! assertions: RSA-SHA256 over exclusive canonicalization, one reference to the signed
+//! element itself. Anything else is rejected rather than half-supported.
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use roxmltree::Node;
+use rsa::pkcs1v15::{Signature, VerifyingKey};
+use rsa::pkcs8::DecodePublicKey;
+use rsa::signature::Verifier;
+use rsa::RsaPublicKey;
+use sha2::{Digest, Sha256};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::prelude::FromDer;
+
+use oauth2_core::OAuth2Error;
+
+use crate::xml::{self, ElementExt};
+
+pub const DSIG_NAMESPACE: &str = "http://www.w3.org/2000/09/xmldsig#";
+const EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+const ENVELOPED_SIGNATURE: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+const RSA_SHA256: &str = "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256";
+const SHA256: &str = "http://www.w3.org/2001/04/xmlenc#sha256";
+
+/// The enveloped signature of `element`, if it has one.
+pub fn signature_of<'a, 'input>(element: Node<'a, 'input>) -> Option<Node<'a, 'input>> {
+    element.child(DSIG_NAMESPACE, "Signature")
+}
+
+/// Verify the enveloped signature of `element` with one of `keys`. `document` is the whole
+/// document, in which the element's `ID` must be unique.
+pub fn verify(document: Node, element: Node, keys: &[RsaPublicKey]) -> Result<(), OAuth2Error> {
+    let mut signatures = element.children_named(DSIG_NAMESPACE, "Signature");
+    let signature = signatures
+        .next()
+        .ok_or_else(|| invalid_signature("element is not signed"))?;
+    if signatures.next().is_some() {
+        return Err(invalid_signature("more than one signature"));
+    }
+
+    let id = element
+        .attribute("ID")
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| invalid_signature("signed element has no ID"))?;
+    let same_id = document
+        .descendants()
+        .filter(|e| e.attribute("ID") == Some(id))
+        .count();
+    if same_id != 1 {
+        return Err(invalid_signature("ID is not unique"));
+    }
+
+    let signed_info = signature
+        .child(DSIG_NAMESPACE, "SignedInfo")
+        .ok_or_else(|| invalid_signature("missing SignedInfo"))?;
+    let canonicalization = signed_info
+        .child(DSIG_NAMESPACE, "CanonicalizationMethod")
+        .ok_or_else(|| invalid_signature("missing CanonicalizationMethod"))?;
+    if canonicalization.attribute("Algorithm") != Some(EXC_C14N) {
+        return Err(invalid_signature(
+            "only exclusive canonicalization is supported",
+        ));
+    }
+    let signature_method = signed_info
+        .child(DSIG_NAMESPACE, "SignatureMethod")
+        .and_then(|method| method.attribute("Algorithm"));
+    if signature_method != Some(RSA_SHA256) {
+        return Err(invalid_signature(
+            "only RSA-SHA256 signatures are supported",
+        ));
+    }
+
+    let mut references = signed_info.children_named(DSIG_NAMESPACE, "Reference");
+    let reference = references
+        .next()
+        .ok_or_else(|| invalid_signature("missing Reference"))?;
+    if references.next().is_some() {
+        return Err(invalid_signature("more than one Reference"));
+    }
+    if reference.attribute("URI") != Some(&format!("#{id}")) {
+        return Err(invalid_signature(
+            "Reference does not point to the signed element",
+        ));
+    }
+
+    let mut transforms_seen = (false, false);
+    let mut reference_prefixes = Vec::new();
+    for transform in reference
+        .child(DSIG_NAMESPACE, "Transforms")
+        .into_iter()
+        .flat_map(|transforms| transforms.children_named(DSIG_NAMESPACE, "Transform"))
+    {
+        match transform.attribute("Algorithm") {
+            Some(ENVELOPED_SIGNATURE) => transforms_seen.0 = true,
+            Some(EXC_C14N) => {
+                transforms_seen.1 = true;
+                reference_prefixes = inclusive_prefixes(transform);
+            }
+            _ => return Err(invalid_signature("unsupported Transform")),
+        }
+    }
+    if transforms_seen != (true, true) {
+        return Err(invalid_signature(
+            "Reference must use the enveloped-signature and exclusive canonicalization transforms",
+        ));
+    }
+    let digest_method = reference
+        .child(DSIG_NAMESPACE, "DigestMethod")
+        .and_then(|method| method.attribute("Algorithm"));
+    if digest_method != Some(SHA256) {
+        return Err(invalid_signature("only SHA-256 digests are supported"));
+    }
+    let expected_digest = reference
+        .child(DSIG_NAMESPACE, "DigestValue")
+        .map(|value| decode_base64(&value.text_content()))
+        .transpose()?
+        .ok_or_else(|| invalid_signature("missing DigestValue"))?;
+
+    let prefixes: Vec<&str> = reference_prefixes.iter().map(String::as_str).collect();
+    let canonical = xml::canonicalize(element, Some(signature), &prefixes);
+    if Sha256::digest(canonical.as_bytes())[..] != expected_digest[..] {
+        return Err(invalid_signature("digest mismatch"));
+    }
+
+    let signature_value = signature
+        .child(DSIG_NAMESPACE, "SignatureValue")
+        .map(|value| decode_base64(&value.text_content()))
+        .transpose()?
+        .ok_or_else(|| invalid_signature("missing SignatureValue"))?;
+    let signature_value = Signature::try_from(signature_value.as_slice())
+        .map_err(|_| invalid_signature("malformed SignatureValue"))?;
+    let signed_info_prefixes = inclusive_prefixes(canonicalization);
+    let prefixes: Vec<&str> = signed_info_prefixes.iter().map(String::as_str).collect();
+    let canonical_signed_info = xml::canonicalize(signed_info, None, &prefixes);
+
+    let verified = keys.iter().any(|key| {
+        VerifyingKey::<Sha256>::new(key.clone())
+            .verify(canonical_signed_info.as_bytes(), &signature_value)
+            .is_ok()
+    });
+    if verified {
+        Ok(())
+    } else {
+        Err(invalid_signature(
+            "not signed by a certificate from the identity provider's metadata",
+        ))
+    }
+}
+
+/// The `PrefixList` of an `InclusiveNamespaces` child.
+fn inclusive_prefixes(method: Node) -> Vec<String> {
+    method
+        .child(EXC_C14N, "InclusiveNamespaces")
+        .and_then(|inclusive| inclusive.attribute("PrefixList"))
+        .map(|list| list.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn decode_base64(value: &str) -> Result<Vec<u8>, OAuth2Error> {
+    let compact: String = value.split_whitespace().collect();
+    STANDARD
+        .decode(compact)
+        .map_err(|_| invalid_signature("malformed base64"))
+}
+
+fn invalid_signature(description: &str) -> OAuth2Error {
+    OAuth2Error::access_denied(&format!("Invalid SAML signature: {description}"))
+}
+
+/// The RSA key of a base64 DER X.509 certificate, as found in `X509Certificate` elements.
+pub fn certificate_key(certificate: &str) -> Result<RsaPublicKey, OAuth2Error> {
+    let invalid = |description: &str| {
+        OAuth2Error::new(
+            "invalid_configuration",
+            Some(&format!("Invalid IdP certificate: {description}")),
+        )
+    };
+    let compact: String = certificate.split_whitespace().collect();
+    let der = STANDARD
+        .decode(compact)
+        .map_err(|_| invalid("not base64"))?;
+    let (_, certificate) =
+        X509Certificate::from_der(&der).map_err(|_| invalid("not an X.509 certificate"))?;
+    RsaPublicKey::from_public_key_der(certificate.public_key().raw)
+        .map_err(|_| invalid("not an RSA key"))
+}
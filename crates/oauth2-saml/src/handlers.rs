@@ -0,0 +1,137 @@
+use actix_session::Session;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use oauth2_core::{OAuth2Error, SocialLoginState};
+use oauth2_ports::{DynAuditSink, DynStateStore, DynStorage};
+use oauth2_social_login::handlers::auth::{
+    audit_login, check_browser_binding, finish_login, save_and_redirect,
+};
+use oauth2_social_login::SocialLoginConfig;
+
+use crate::request::new_request_id;
+use crate::service::SamlServiceProvider;
+
+/// The HTTP-POST binding's form, as posted by the identity provider.
+#[derive(Deserialize)]
+pub struct AcsForm {
+    #[serde(rename = "SAMLResponse")]
+    saml_response: String,
+    #[serde(rename = "RelayState")]
+    relay_state: Option<String>,
+}
+
+/// Register the SAML routes under `/auth/saml/{idp}`; before the `/auth` scope, which
+/// would otherwise take these paths.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/auth/saml/{idp}")
+            .route("/login", web::get().to(login))
+            .route("/acs", web::post().to(acs))
+            .route("/metadata", web::get().to(metadata)),
+    );
+}
+
+/// Send the user to the identity provider. The request `ID` is recorded under a random
+/// `RelayState` so the response can be matched to this login and browser.
+pub async fn login(
+    req: HttpRequest,
+    idp: web::Path<String>,
+    saml: web::Data<SamlServiceProvider>,
+    states: web::Data<DynStateStore>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let request_id = new_request_id();
+    let relay_state = uuid::Uuid::new_v4().simple().to_string();
+    let url = saml.login_url(&idp, &request_id, &relay_state)?;
+
+    let record = SocialLoginState::new(relay_state, idp.into_inner()).with_nonce(request_id);
+    save_and_redirect(&req, record, &url, &states).await
+}
+
+/// Assertion consumer service: validate the posted response and sign in like a social
+/// login, including account linking.
+#[allow(clippy::too_many_arguments)] // actix extractors
+pub async fn acs(
+    req: HttpRequest,
+    idp: web::Path<String>,
+    form: web::Form<AcsForm>,
+    saml: web::Data<SamlServiceProvider>,
+    states: web::Data<DynStateStore>,
+    storage: web::Data<DynStorage>,
+    social: Option<web::Data<Arc<SocialLoginConfig>>>,
+    session: Session,
+    audit: Option<web::Data<DynAuditSink>>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let cookieless_state = social.is_some_and(|social| social.cookieless_state);
+    let result = consume_response(
+        &req,
+        &idp,
+        &form,
+        &saml,
+        &states,
+        &storage,
+        &session,
+        cookieless_state,
+    )
+    .await;
+    let audit = audit.as_ref().map(|audit| audit.get_ref());
+    audit_login(&req, &session, &idp, &result, audit).await;
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn consume_response(
+    req: &HttpRequest,
+    idp: &str,
+    form: &AcsForm,
+    saml: &SamlServiceProvider,
+    states: &DynStateStore,
+    storage: &DynStorage,
+    session: &Session,
+    cookieless_state: bool,
+) -> Result<HttpResponse, OAuth2Error> {
+    let relay_state = form
+        .relay_state
+        .as_deref()
+        .ok_or_else(|| OAuth2Error::access_denied("Missing RelayState"))?;
+    let record = states
+        .take(relay_state)
+        .await?
+        .ok_or_else(|| OAuth2Error::access_denied("Unknown or already used RelayState"))?;
+    check_browser_binding(req, &record, cookieless_state)?;
+    if record.is_expired() {
+        return Err(OAuth2Error::access_denied("Login attempt expired"));
+    }
+    if record.provider != idp {
+        return Err(OAuth2Error::invalid_request("Identity provider mismatch"));
+    }
+    let request_id = record
+        .nonce
+        .as_deref()
+        .ok_or_else(|| OAuth2Error::access_denied("Not a SAML login"))?;
+
+    let assertion = saml
+        .validate_response(idp, &form.saml_response, request_id, Utc::now())
+        .inspect_err(|e| {
+            tracing::warn!(
+                idp = %idp,
+                error = e.error_description.as_deref().unwrap_or(&e.error),
+                "SAML response rejected"
+            )
+        })?;
+    let user_info = saml.user_info(idp, &assertion)?;
+
+    finish_login(storage, session, user_info).await
+}
+
+/// This service provider's metadata for identity provider `idp`.
+pub async fn metadata(
+    idp: web::Path<String>,
+    saml: web::Data<SamlServiceProvider>,
+) -> Result<HttpResponse, OAuth2Error> {
+    Ok(HttpResponse::Ok()
+        .content_type("application/samlmetadata+xml")
+        .body(saml.metadata(&idp)?))
+}
@@ -0,0 +1,22 @@
+//! SAML 2.0 service provider bridge.
+//!
+//! Users of identity providers that only speak SAML sign in through
+//! `/auth/saml/{idp}/login`: this server sends an `AuthnRequest` (HTTP-Redirect binding),
+//! validates the signed response posted back to `/auth/saml/{idp}/acs` against the
+//! certificates in the identity provider's metadata, and completes the login like a social
+//! login, after which the usual authorization flow issues OAuth2/OIDC tokens.
+//!
+//! Responses must be signed with RSA-SHA256 over exclusive canonicalization; encrypted
+//! assertions and IdP-initiated logins are not supported.
+
+pub mod dsig;
+pub mod handlers;
+pub mod metadata;
+pub mod request;
+pub mod response;
+pub mod service;
+pub mod xml;
+
+pub use metadata::IdpMetadata;
+pub use response::SamlAssertion;
+pub use service::{IdentityProvider, SamlServiceProvider};
@@ -0,0 +1,100 @@
+//! SAML metadata: reading an identity provider's `EntityDescriptor` and describing this
+//! service provider to identity providers.
+
+use rsa::RsaPublicKey;
+
+use oauth2_core::OAuth2Error;
+
+use crate::dsig::{self, DSIG_NAMESPACE};
+use crate::xml::{self, escape, ElementExt};
+
+pub const METADATA_NAMESPACE: &str = "urn:oasis:names:tc:SAML:2.0:metadata";
+pub const HTTP_REDIRECT_BINDING: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect";
+pub const HTTP_POST_BINDING: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST";
+
+/// What this service provider needs from an identity provider's metadata.
+#[derive(Debug, Clone)]
+pub struct IdpMetadata {
+    pub entity_id: String,
+    /// `SingleSignOnService` location for the HTTP-Redirect binding.
+    pub sso_url: String,
+    /// Keys of the signing certificates; responses must be signed with one of them.
+    pub signing_keys: Vec<RsaPublicKey>,
+}
+
+impl IdpMetadata {
+    /// Parse an `EntityDescriptor`, or the first identity provider in an
+    /// `EntitiesDescriptor`.
+    pub fn parse(xml: &str) -> Result<Self, OAuth2Error> {
+        let document = xml::parse(xml).map_err(|e| {
+            invalid_metadata(e.error_description.as_deref().unwrap_or("malformed XML"))
+        })?;
+        let root = document.root_element();
+        let entity = if root.is(METADATA_NAMESPACE, "EntitiesDescriptor") {
+            root.children_named(METADATA_NAMESPACE, "EntityDescriptor")
+                .find(|entity| {
+                    entity
+                        .child(METADATA_NAMESPACE, "IDPSSODescriptor")
+                        .is_some()
+                })
+        } else {
+            Some(root).filter(|root| root.is(METADATA_NAMESPACE, "EntityDescriptor"))
+        }
+        .ok_or_else(|| invalid_metadata("no identity provider EntityDescriptor"))?;
+
+        let entity_id = entity
+            .attribute("entityID")
+            .filter(|id| !id.is_empty())
+            .ok_or_else(|| invalid_metadata("EntityDescriptor has no entityID"))?;
+        let idp = entity
+            .child(METADATA_NAMESPACE, "IDPSSODescriptor")
+            .ok_or_else(|| invalid_metadata("no IDPSSODescriptor"))?;
+
+        let sso_url = idp
+            .children_named(METADATA_NAMESPACE, "SingleSignOnService")
+            .find(|service| service.attribute("Binding") == Some(HTTP_REDIRECT_BINDING))
+            .and_then(|service| service.attribute("Location"))
+            .ok_or_else(|| invalid_metadata("no HTTP-Redirect SingleSignOnService"))?;
+
+        let signing_keys = idp
+            .children_named(METADATA_NAMESPACE, "KeyDescriptor")
+            .filter(|key| key.attribute("use").is_none_or(|usage| usage == "signing"))
+            .filter_map(|key| key.child(DSIG_NAMESPACE, "KeyInfo"))
+            .flat_map(|info| info.children_named(DSIG_NAMESPACE, "X509Data"))
+            .flat_map(|data| data.children_named(DSIG_NAMESPACE, "X509Certificate"))
+            .map(|certificate| dsig::certificate_key(&certificate.text_content()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if signing_keys.is_empty() {
+            return Err(invalid_metadata("no signing certificate"));
+        }
+
+        Ok(Self {
+            entity_id: entity_id.to_string(),
+            sso_url: sso_url.to_string(),
+            signing_keys,
+        })
+    }
+}
+
+/// Metadata describing this service provider, for registering it with an identity
+/// provider.
+pub fn service_provider_metadata(entity_id: &str, acs_url: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<md:EntityDescriptor xmlns:md="{METADATA_NAMESPACE}" entityID="{entity_id}">
+  <md:SPSSODescriptor AuthnRequestsSigned="false" WantAssertionsSigned="true" protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">
+    <md:AssertionConsumerService Binding="{HTTP_POST_BINDING}" Location="{acs_url}" index="0" isDefault="true"/>
+  </md:SPSSODescriptor>
+</md:EntityDescriptor>
+"#,
+        entity_id = escape(entity_id),
+        acs_url = escape(acs_url),
+    )
+}
+
+fn invalid_metadata(description: &str) -> OAuth2Error {
+    OAuth2Error::new(
+        "invalid_configuration",
+        Some(&format!("Invalid IdP metadata: {description}")),
+    )
+}
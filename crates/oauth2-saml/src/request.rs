@@ -0,0 +1,62 @@
+//! `AuthnRequest`s, sent to the identity provider with the HTTP-Redirect binding.
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, SecondsFormat, Utc};
+use flate2::{write::DeflateEncoder, Compression};
+use std::io::Write;
+use url::Url;
+
+use oauth2_core::OAuth2Error;
+
+use crate::metadata::HTTP_POST_BINDING;
+use crate::response::{ASSERTION_NAMESPACE, PROTOCOL_NAMESPACE};
+use crate::xml::escape;
+
+/// A fresh request `ID`; XML IDs must not start with a digit.
+pub fn new_request_id() -> String {
+    format!("_{}", uuid::Uuid::new_v4().simple())
+}
+
+/// An `AuthnRequest` asking for the response to be posted to `acs_url`.
+pub fn authn_request(
+    id: &str,
+    issue_instant: DateTime<Utc>,
+    destination: &str,
+    acs_url: &str,
+    sp_entity_id: &str,
+) -> String {
+    format!(
+        r#"<samlp:AuthnRequest xmlns:samlp="{PROTOCOL_NAMESPACE}" xmlns:saml="{ASSERTION_NAMESPACE}" ID="{id}" Version="2.0" IssueInstant="{issue_instant}" Destination="{destination}" AssertionConsumerServiceURL="{acs_url}" ProtocolBinding="{HTTP_POST_BINDING}"><saml:Issuer>{sp_entity_id}</saml:Issuer><samlp:NameIDPolicy AllowCreate="true"/></samlp:AuthnRequest>"#,
+        id = escape(id),
+        issue_instant = issue_instant.to_rfc3339_opts(SecondsFormat::Secs, true),
+        destination = escape(destination),
+        acs_url = escape(acs_url),
+        sp_entity_id = escape(sp_entity_id),
+    )
+}
+
+/// The identity provider's single sign-on URL carrying `request` (deflated and base64
+/// encoded, SAML bindings section 3.4.4) and `relay_state`.
+pub fn redirect_url(
+    sso_url: &str,
+    request: &str,
+    relay_state: &str,
+) -> Result<String, OAuth2Error> {
+    let mut url = Url::parse(sso_url).map_err(|e| cannot_build(&e))?;
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    let deflated = encoder
+        .write_all(request.as_bytes())
+        .and_then(|()| encoder.finish())
+        .map_err(|e| cannot_build(&e))?;
+    url.query_pairs_mut()
+        .append_pair("SAMLRequest", &STANDARD.encode(deflated))
+        .append_pair("RelayState", relay_state);
+    Ok(url.to_string())
+}
+
+fn cannot_build(error: &dyn std::fmt::Display) -> OAuth2Error {
+    OAuth2Error::new(
+        "invalid_configuration",
+        Some(&format!("Cannot build SAML request URL: {error}")),
+    )
+}
@@ -0,0 +1,203 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
+use oauth2_config::{SamlConfig, SamlIdpConfig};
+use oauth2_core::OAuth2Error;
+use oauth2_social_login::SocialUserInfo;
+
+use crate::metadata::{service_provider_metadata, IdpMetadata};
+use crate::request;
+use crate::response::{self, Expected, SamlAssertion};
+
+const EMAIL_FORMAT: &str = "urn:oasis:names:tc:SAML:1.1:nameid-format:emailAddress";
+
+/// Attributes identity providers commonly put the email and display name in: plain names,
+/// the LDAP OIDs (`mail`, `displayName`) and the ADFS claim types.
+const EMAIL_ATTRIBUTES: &[&str] = &[
+    "email",
+    "mail",
+    "urn:oid:0.9.2342.19200300.100.1.3",
+    "http://schemas.xmlsoap.org/ws/2005/05/identity/claims/emailaddress",
+];
+const NAME_ATTRIBUTES: &[&str] = &[
+    "displayName",
+    "name",
+    "cn",
+    "urn:oid:2.16.840.1.113730.3.1.241",
+    "http://schemas.xmlsoap.org/ws/2005/05/identity/claims/name",
+];
+
+/// This server as a SAML service provider for the identity providers under `saml.idps`.
+#[derive(Debug, Clone)]
+pub struct SamlServiceProvider {
+    entity_id: String,
+    base_url: String,
+    idps: BTreeMap<String, IdentityProvider>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IdentityProvider {
+    pub metadata: IdpMetadata,
+    email_attribute: Option<String>,
+    name_attribute: Option<String>,
+}
+
+impl IdentityProvider {
+    pub fn from_config(name: &str, config: &SamlIdpConfig) -> Result<Self, OAuth2Error> {
+        let xml = match (&config.metadata_xml, &config.metadata_path) {
+            (Some(xml), _) => xml.clone(),
+            (None, Some(path)) => std::fs::read_to_string(path).map_err(|e| {
+                OAuth2Error::new(
+                    "invalid_configuration",
+                    Some(&format!(
+                        "Cannot read metadata of SAML IdP {name} from {path}: {e}"
+                    )),
+                )
+            })?,
+            (None, None) => {
+                return Err(OAuth2Error::new(
+                    "invalid_configuration",
+                    Some(&format!(
+                        "SAML IdP {name} needs metadata_xml or metadata_path"
+                    )),
+                ))
+            }
+        };
+        Ok(Self {
+            metadata: IdpMetadata::parse(&xml)?,
+            email_attribute: config.email_attribute.clone(),
+            name_attribute: config.name_attribute.clone(),
+        })
+    }
+}
+
+impl SamlServiceProvider {
+    /// Load the metadata of every enabled identity provider.
+    pub fn from_config(config: &SamlConfig) -> Result<Self, OAuth2Error> {
+        let idps = config
+            .idps
+            .iter()
+            .filter(|(_, idp)| idp.enabled)
+            .map(|(name, idp)| Ok((name.clone(), IdentityProvider::from_config(name, idp)?)))
+            .collect::<Result<_, OAuth2Error>>()?;
+        Ok(Self::new(config.entity_id.clone(), &config.base_url, idps))
+    }
+
+    pub fn new(
+        entity_id: String,
+        base_url: &str,
+        idps: BTreeMap<String, IdentityProvider>,
+    ) -> Self {
+        Self {
+            entity_id,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            idps,
+        }
+    }
+
+    pub fn entity_id(&self) -> &str {
+        &self.entity_id
+    }
+
+    pub fn idp_names(&self) -> impl Iterator<Item = &str> {
+        self.idps.keys().map(String::as_str)
+    }
+
+    pub fn idp(&self, name: &str) -> Result<&IdentityProvider, OAuth2Error> {
+        self.idps.get(name).ok_or_else(|| {
+            OAuth2Error::not_found(&format!("Unknown SAML identity provider {name}"))
+        })
+    }
+
+    /// Where identity provider `name` posts its responses.
+    pub fn acs_url(&self, name: &str) -> String {
+        format!("{}/auth/saml/{name}/acs", self.base_url)
+    }
+
+    /// Metadata to register this service provider with identity provider `name`.
+    pub fn metadata(&self, name: &str) -> Result<String, OAuth2Error> {
+        self.idp(name)?;
+        Ok(service_provider_metadata(
+            &self.entity_id,
+            &self.acs_url(name),
+        ))
+    }
+
+    /// URL sending the user to identity provider `name` with a new `AuthnRequest`.
+    pub fn login_url(
+        &self,
+        name: &str,
+        request_id: &str,
+        relay_state: &str,
+    ) -> Result<String, OAuth2Error> {
+        let idp = self.idp(name)?;
+        let authn_request = request::authn_request(
+            request_id,
+            Utc::now(),
+            &idp.metadata.sso_url,
+            &self.acs_url(name),
+            &self.entity_id,
+        );
+        request::redirect_url(&idp.metadata.sso_url, &authn_request, relay_state)
+    }
+
+    /// Validate the base64 `SAMLResponse` identity provider `name` posted in answer to
+    /// `request_id`.
+    pub fn validate_response(
+        &self,
+        name: &str,
+        saml_response: &str,
+        request_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<SamlAssertion, OAuth2Error> {
+        let idp = self.idp(name)?;
+        let compact: String = saml_response.split_whitespace().collect();
+        let xml = STANDARD
+            .decode(compact)
+            .ok()
+            .and_then(|xml| String::from_utf8(xml).ok())
+            .ok_or_else(|| OAuth2Error::invalid_request("SAMLResponse is not base64 XML"))?;
+        response::validate_response(
+            &xml,
+            &Expected {
+                idp_entity_id: &idp.metadata.entity_id,
+                signing_keys: &idp.metadata.signing_keys,
+                sp_entity_id: &self.entity_id,
+                acs_url: &self.acs_url(name),
+                request_id,
+                now,
+            },
+        )
+    }
+
+    /// The social login identity of an assertion from identity provider `name`. The
+    /// `NameID` is the provider user id, so it should be a persistent one.
+    pub fn user_info(
+        &self,
+        name: &str,
+        assertion: &SamlAssertion,
+    ) -> Result<SocialUserInfo, OAuth2Error> {
+        let idp = self.idp(name)?;
+        let email = match &idp.email_attribute {
+            Some(attribute) => assertion.attribute(&[attribute.as_str()]),
+            None => assertion.attribute(EMAIL_ATTRIBUTES).or_else(|| {
+                (assertion.name_id_format.as_deref() == Some(EMAIL_FORMAT))
+                    .then_some(assertion.name_id.as_str())
+            }),
+        }
+        .ok_or_else(|| OAuth2Error::access_denied("SAML assertion has no email"))?;
+        let name_value = match &idp.name_attribute {
+            Some(attribute) => assertion.attribute(&[attribute.as_str()]),
+            None => assertion.attribute(NAME_ATTRIBUTES),
+        };
+
+        Ok(SocialUserInfo {
+            provider: name.to_string(),
+            provider_user_id: assertion.name_id.clone(),
+            email: email.to_string(),
+            name: name_value.map(str::to_string),
+            picture: None,
+        })
+    }
+}
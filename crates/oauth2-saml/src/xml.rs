@@ -0,0 +1,321 @@
+//! XML for SAML: documents are parsed with `roxmltree`; what is left here is Exclusive XML
+//! Canonicalization (without comments) over its tree, which XML signatures are computed
+//! over and no maintained crate provides.
+//!
+//! Document type declarations are rejected by `roxmltree`, so entity expansion attacks
+//! cannot happen, and processing instructions are rejected here. Comments are skipped when
+//! reading text, so a comment cannot truncate what is read from a signed element.
+
+use roxmltree::{Document, Node, ParsingOptions};
+
+use oauth2_core::OAuth2Error;
+
+const MAX_DEPTH: usize = 64;
+const MAX_NODES: u32 = 100_000;
+
+pub fn parse(input: &str) -> Result<Document<'_>, OAuth2Error> {
+    let options = ParsingOptions {
+        allow_dtd: false,
+        nodes_limit: MAX_NODES,
+        ..ParsingOptions::default()
+    };
+    let document =
+        Document::parse_with_options(input, options).map_err(|e| malformed(&e.to_string()))?;
+    if document.descendants().any(|node| node.is_pi()) {
+        return Err(malformed("processing instructions are not allowed"));
+    }
+    if document
+        .descendants()
+        .filter(|node| node.is_element() && !node.has_children())
+        .any(|leaf| leaf.ancestors().count() > MAX_DEPTH)
+    {
+        return Err(malformed("elements are nested too deeply"));
+    }
+    Ok(document)
+}
+
+/// Namespace-aware lookups on elements.
+pub trait ElementExt<'a, 'input: 'a> {
+    fn is(self, namespace: &str, local_name: &str) -> bool;
+
+    fn children_named(
+        self,
+        namespace: &'a str,
+        local_name: &'a str,
+    ) -> impl Iterator<Item = Node<'a, 'input>> + 'a;
+
+    fn child(self, namespace: &str, local_name: &str) -> Option<Node<'a, 'input>>;
+
+    /// All text content, descendants included and comments left out.
+    fn text_content(self) -> String;
+}
+
+impl<'a, 'input: 'a> ElementExt<'a, 'input> for Node<'a, 'input> {
+    fn is(self, namespace: &str, local_name: &str) -> bool {
+        self.is_element()
+            && self.tag_name().namespace().unwrap_or_default() == namespace
+            && self.tag_name().name() == local_name
+    }
+
+    fn children_named(
+        self,
+        namespace: &'a str,
+        local_name: &'a str,
+    ) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+        self.children()
+            .filter(move |child| child.is(namespace, local_name))
+    }
+
+    fn child(self, namespace: &str, local_name: &str) -> Option<Node<'a, 'input>> {
+        self.children()
+            .find(|child| child.is(namespace, local_name))
+    }
+
+    fn text_content(self) -> String {
+        self.descendants()
+            .filter(|node| node.is_text())
+            .filter_map(|node| node.text())
+            .collect()
+    }
+}
+
+/// Exclusive XML Canonicalization of `element`'s subtree, leaving out `exclude` (the
+/// enveloped signature). `inclusive_prefixes` is the `InclusiveNamespaces PrefixList`,
+/// with `#default` for the default namespace.
+pub fn canonicalize(element: Node, exclude: Option<Node>, inclusive_prefixes: &[&str]) -> String {
+    let mut output = String::new();
+    write_canonical(
+        element,
+        &mut output,
+        &Vec::new(),
+        exclude,
+        inclusive_prefixes,
+    );
+    output
+}
+
+fn write_canonical(
+    element: Node,
+    output: &mut String,
+    rendered: &[(String, String)],
+    exclude: Option<Node>,
+    inclusive_prefixes: &[&str],
+) {
+    let input = element.document().input_text();
+    let name = qualified_name(element);
+    let attributes: Vec<(&str, roxmltree::Attribute)> = element
+        .attributes()
+        .map(|attribute| (&input[attribute.range_qname()], attribute))
+        .collect();
+
+    // Namespaces are output where they are visibly used, unless an output ancestor
+    // already declared the same binding.
+    let mut used: Vec<&str> = vec![split_name(name).0];
+    used.extend(
+        attributes
+            .iter()
+            .map(|(name, _)| split_name(name).0)
+            .filter(|prefix| !prefix.is_empty()),
+    );
+    used.extend(
+        inclusive_prefixes
+            .iter()
+            .map(|prefix| if *prefix == "#default" { "" } else { *prefix })
+            .filter(|prefix| namespace_of(element, prefix).is_some()),
+    );
+    used.sort_unstable();
+    used.dedup();
+
+    let mut rendered = rendered.to_vec();
+    let mut declarations = Vec::new();
+    for prefix in used.into_iter().filter(|prefix| *prefix != "xml") {
+        let uri = namespace_of(element, prefix).unwrap_or("");
+        let previous = rendered
+            .iter()
+            .rev()
+            .find(|(rendered_prefix, _)| *rendered_prefix == prefix)
+            .map(|(_, uri)| uri.as_str());
+        let unchanged = match previous {
+            Some(previous) => previous == uri,
+            // An empty default namespace only needs undeclaring.
+            None => prefix.is_empty() && uri.is_empty(),
+        };
+        if !unchanged {
+            declarations.push((prefix, uri));
+            rendered.push((prefix.to_string(), uri.to_string()));
+        }
+    }
+
+    output.push('<');
+    output.push_str(name);
+    for (prefix, uri) in declarations {
+        if prefix.is_empty() {
+            output.push_str(" xmlns=\"");
+        } else {
+            output.push_str(" xmlns:");
+            output.push_str(prefix);
+            output.push_str("=\"");
+        }
+        escape_attribute(uri, output);
+        output.push('"');
+    }
+    let mut attributes = attributes;
+    attributes.sort_by(|(_, a), (_, b)| {
+        (a.namespace().unwrap_or_default(), a.name())
+            .cmp(&(b.namespace().unwrap_or_default(), b.name()))
+    });
+    for (name, attribute) in attributes {
+        output.push(' ');
+        output.push_str(name);
+        output.push_str("=\"");
+        escape_attribute(attribute.value(), output);
+        output.push('"');
+    }
+    output.push('>');
+
+    for child in element.children() {
+        if child.is_text() {
+            escape_text(child.text().unwrap_or_default(), output);
+        } else if child.is_element() && exclude != Some(child) {
+            write_canonical(child, output, &rendered, exclude, inclusive_prefixes);
+        }
+    }
+
+    output.push_str("</");
+    output.push_str(name);
+    output.push('>');
+}
+
+/// Element name as written (`prefix:local`), which `roxmltree` only keeps as a position.
+fn qualified_name<'input>(element: Node<'_, 'input>) -> &'input str {
+    let input = element.document().input_text();
+    let tag = &input[element.range().start + 1..];
+    let end = tag
+        .find(|c: char| c.is_whitespace() || matches!(c, '/' | '>'))
+        .unwrap_or(tag.len());
+    &tag[..end]
+}
+
+fn namespace_of<'a>(element: Node<'a, '_>, prefix: &str) -> Option<&'a str> {
+    if prefix.is_empty() {
+        element.default_namespace()
+    } else {
+        element.lookup_namespace_uri(Some(prefix))
+    }
+}
+
+fn split_name(name: &str) -> (&str, &str) {
+    name.split_once(':').unwrap_or(("", name))
+}
+
+fn escape_text(text: &str, output: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '\r' => output.push_str("&#xD;"),
+            c => output.push(c),
+        }
+    }
+}
+
+fn escape_attribute(value: &str, output: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '"' => output.push_str("&quot;"),
+            '\t' => output.push_str("&#x9;"),
+            '\n' => output.push_str("&#xA;"),
+            '\r' => output.push_str("&#xD;"),
+            c => output.push(c),
+        }
+    }
+}
+
+/// Escape for building XML documents (text or attribute values).
+pub fn escape(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#x27;"),
+            c => output.push(c),
+        }
+    }
+    output
+}
+
+fn malformed(description: &str) -> OAuth2Error {
+    OAuth2Error::invalid_request(&format!("Malformed XML: {description}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exclusive_canonicalization_matches_xmllint() {
+        // Expected output from `xmllint --exc-c14n` on the same document.
+        let document = parse(
+            "<?xml version=\"1.0\"?>\r\n<a:Root xmlns:a=\"urn:a\" xmlns:b=\"urn:b\" xmlns=\"urn:d\" z=\"1\" b:y='2' a=\"x&#9;&quot;&lt;\n\">\n  \
+             <Child xmlns:unused=\"urn:u\" attr=\"v\"/><!-- gone -->\n  \
+             <a:Leaf xmlns=\"\">text &amp; &gt; <![CDATA[<raw>]]>&#xD;</a:Leaf>\n</a:Root>",
+        )
+        .unwrap();
+        let root = document.root_element();
+        assert_eq!(
+            canonicalize(root, None, &[]),
+            "<a:Root xmlns:a=\"urn:a\" xmlns:b=\"urn:b\" a=\"x&#x9;&quot;&lt; \" z=\"1\" b:y=\"2\">\n  \
+             <Child xmlns=\"urn:d\" attr=\"v\"></Child>\n  \
+             <a:Leaf>text &amp; &gt; &lt;raw&gt;&#xD;</a:Leaf>\n</a:Root>"
+        );
+
+        // Canonicalizing a subtree declares what it uses from its ancestors.
+        let leaf = root.child("urn:a", "Leaf").unwrap();
+        assert_eq!(
+            canonicalize(leaf, None, &[]),
+            "<a:Leaf xmlns:a=\"urn:a\">text &amp; &gt; &lt;raw&gt;&#xD;</a:Leaf>"
+        );
+        assert_eq!(
+            canonicalize(leaf, None, &["b", "#default", "missing"]),
+            "<a:Leaf xmlns:a=\"urn:a\" xmlns:b=\"urn:b\">text &amp; &gt; &lt;raw&gt;&#xD;</a:Leaf>"
+        );
+
+        let child = root.child("urn:d", "Child").unwrap();
+        assert_eq!(
+            canonicalize(root, Some(child), &[]),
+            "<a:Root xmlns:a=\"urn:a\" xmlns:b=\"urn:b\" a=\"x&#x9;&quot;&lt; \" z=\"1\" b:y=\"2\">\n  \n  \
+             <a:Leaf>text &amp; &gt; &lt;raw&gt;&#xD;</a:Leaf>\n</a:Root>"
+        );
+    }
+
+    #[test]
+    fn comments_do_not_split_text() {
+        let document = parse("<NameID>admin@corp.test<!---->.evil.test</NameID>").unwrap();
+        assert_eq!(
+            document.root_element().text_content(),
+            "admin@corp.test.evil.test"
+        );
+    }
+
+    #[test]
+    fn rejects_dtds_and_malformed_documents() {
+        for document in [
+            "<!DOCTYPE r [<!ENTITY x \"y\">]><r>&x;</r>",
+            "<r>&x;</r>",
+            "<r><?pi data?></r>",
+            "<r a=\"1\" a=\"2\"/>",
+            "<p:r/>",
+            "<r></s>",
+            "<r>",
+            "<r/><r/>",
+        ] {
+            assert!(parse(document).is_err(), "{document}");
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use async_trait::async_trait;
+use oauth2_config::{AwsCredentialsSource as Source, AwsSecretsConfig};
+use oauth2_core::OAuth2Error;
+use oauth2_events::{AwsClient, AwsCredentialsSource};
+use oauth2_ports::SecretProvider;
+use serde_json::json;
+
+use crate::{provider_error, split_field, string_field, AWS_SECRETS_MANAGER_SCHEME};
+
+/// Reads secrets from AWS Secrets Manager.
+///
+/// References are `aws-sm:<secret id>` for the whole `SecretString`, or
+/// `aws-sm:<secret id>#<key>` for one key of a JSON secret. The id is a name or an ARN.
+pub struct AwsSecretsManagerProvider {
+    client: AwsClient,
+    endpoint: String,
+}
+
+impl AwsSecretsManagerProvider {
+    /// `endpoint` overrides the regional endpoint (e.g. for LocalStack).
+    pub fn new(
+        region: String,
+        endpoint: Option<String>,
+        credentials: AwsCredentialsSource,
+    ) -> Result<Self, String> {
+        let endpoint =
+            endpoint.unwrap_or_else(|| format!("https://secretsmanager.{region}.amazonaws.com/"));
+        Ok(Self {
+            client: AwsClient::new(credentials, region, "secretsmanager")?,
+            endpoint,
+        })
+    }
+
+    /// The region defaults to `AWS_REGION`, then `AWS_DEFAULT_REGION`.
+    pub fn from_config(config: &AwsSecretsConfig) -> Result<Self, String> {
+        let region = config
+            .region
+            .clone()
+            .or_else(|| std::env::var("AWS_REGION").ok())
+            .or_else(|| std::env::var("AWS_DEFAULT_REGION").ok())
+            .ok_or("secrets.aws.region is not set and AWS_REGION is empty")?;
+        let credentials = match config.credentials {
+            Source::Default => AwsCredentialsSource::Default,
+            Source::Environment => AwsCredentialsSource::Environment,
+            Source::Container => AwsCredentialsSource::Container,
+            Source::Instance => AwsCredentialsSource::Instance,
+            Source::Static => {
+                return Err(
+                    "secrets.aws.credentials = \"static\" is not supported; use environment, container or instance credentials"
+                        .to_string(),
+                )
+            }
+        };
+        Self::new(region, config.endpoint.clone(), credentials)
+    }
+}
+
+#[async_trait]
+impl SecretProvider for AwsSecretsManagerProvider {
+    fn scheme(&self) -> &'static str {
+        AWS_SECRETS_MANAGER_SCHEME
+    }
+
+    async fn get_secret(&self, reference: &str) -> Result<String, OAuth2Error> {
+        let (secret_id, key) = split_field(reference);
+        let response = self
+            .client
+            .call_json(
+                &self.endpoint,
+                "secretsmanager.GetSecretValue",
+                &json!({ "SecretId": secret_id }),
+            )
+            .await
+            .map_err(provider_error)?;
+        secret_value(&response, key).map_err(provider_error)
+    }
+}
+
+/// The `SecretString` of a `GetSecretValue` response, or `key` of it when it is JSON.
+fn secret_value(response: &serde_json::Value, key: Option<&str>) -> Result<String, String> {
+    let secret = response
+        .get("SecretString")
+        .and_then(|s| s.as_str())
+        .ok_or("secret has no SecretString; binary secrets are not supported")?;
+    let Some(key) = key else {
+        return Ok(secret.to_string());
+    };
+    let object: serde_json::Value = serde_json::from_str(secret)
+        .map_err(|_| format!("secret is not JSON, so it has no key {key}"))?;
+    string_field(&object, key).ok_or_else(|| format!("secret has no key {key}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_the_whole_secret_or_one_key() {
+        let response = json!({
+            "ARN": "arn:aws:secretsmanager:eu-west-1:123456789012:secret:oauth2-AbCdEf",
+            "SecretString": "{\"jwt_secret\":\"s3cret\"}"
+        });
+        assert_eq!(
+            secret_value(&response, None).unwrap(),
+            "{\"jwt_secret\":\"s3cret\"}"
+        );
+        assert_eq!(
+            secret_value(&response, Some("jwt_secret")).unwrap(),
+            "s3cret"
+        );
+        assert_eq!(
+            secret_value(&response, Some("other")).unwrap_err(),
+            "secret has no key other"
+        );
+
+        let plain = json!({ "SecretString": "s3cret" });
+        assert!(secret_value(&plain, Some("jwt_secret")).is_err());
+        assert!(secret_value(&json!({ "SecretBinary": "AAAA" }), None).is_err());
+    }
+}
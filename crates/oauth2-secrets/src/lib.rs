@@ -0,0 +1,54 @@
+//! Secrets from external stores for the server configuration.
+//!
+//! A secret setting written as `<scheme>:<reference>` (e.g. `jwt.secret =
+//! "vault:secret/data/oauth2#jwt_secret"`) is replaced at startup by [`SecretResolver`]
+//! with what the [`SecretProvider`](oauth2_ports::SecretProvider) for `scheme` returns.
+//!
+//! Built-in providers, behind features:
+//! - `vault`: [`VaultSecretProvider`] (`vault:`)
+//! - `aws`: [`AwsSecretsManagerProvider`] (`aws-sm:`)
+
+#[cfg(feature = "aws")]
+pub mod aws;
+pub mod resolver;
+#[cfg(feature = "vault")]
+pub mod vault;
+
+#[cfg(feature = "aws")]
+pub use aws::AwsSecretsManagerProvider;
+pub use resolver::SecretResolver;
+#[cfg(feature = "vault")]
+pub use vault::VaultSecretProvider;
+
+/// Reference scheme of [`VaultSecretProvider`].
+pub const VAULT_SCHEME: &str = "vault";
+
+/// Reference scheme of [`AwsSecretsManagerProvider`].
+pub const AWS_SECRETS_MANAGER_SCHEME: &str = "aws-sm";
+
+// Helpers for the built-in providers.
+
+/// `reference` split at its last `#` into the secret and the field within it.
+#[cfg(any(feature = "vault", feature = "aws"))]
+pub(crate) fn split_field(reference: &str) -> (&str, Option<&str>) {
+    match reference.rsplit_once('#') {
+        Some((secret, field)) => (secret, Some(field)),
+        None => (reference, None),
+    }
+}
+
+/// `field` of a JSON object, as a string.
+#[cfg(any(feature = "vault", feature = "aws"))]
+pub(crate) fn string_field(object: &serde_json::Value, field: &str) -> Option<String> {
+    match object.get(field)? {
+        serde_json::Value::String(value) => Some(value.clone()),
+        serde_json::Value::Number(value) => Some(value.to_string()),
+        serde_json::Value::Bool(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(any(feature = "vault", feature = "aws", test))]
+pub(crate) fn provider_error(message: String) -> oauth2_core::OAuth2Error {
+    oauth2_core::OAuth2Error::new("server_error", Some(&message))
+}
@@ -0,0 +1,191 @@
+use oauth2_config::{Config, SecretsConfig};
+use oauth2_ports::DynSecretProvider;
+use std::collections::HashMap;
+
+use crate::{AWS_SECRETS_MANAGER_SCHEME, VAULT_SCHEME};
+
+/// Replaces secret settings that reference an external store with the secrets themselves.
+#[derive(Default, Clone)]
+pub struct SecretResolver {
+    providers: Vec<DynSecretProvider>,
+}
+
+impl SecretResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Providers for the stores configured in `secrets`. Fails when one is configured
+    /// but its feature is not compiled in.
+    pub fn from_config(secrets: &SecretsConfig) -> Result<Self, String> {
+        #[allow(unused_mut)]
+        let mut resolver = Self::new();
+
+        if let Some(vault) = &secrets.vault {
+            #[cfg(feature = "vault")]
+            {
+                resolver = resolver.with_provider(std::sync::Arc::new(
+                    crate::VaultSecretProvider::from_config(vault)?,
+                ));
+            }
+            #[cfg(not(feature = "vault"))]
+            {
+                let _ = vault;
+                return Err(
+                    "secrets.vault is configured but Vault support (feature 'secrets-vault') is not enabled"
+                        .to_string(),
+                );
+            }
+        }
+
+        if let Some(aws) = &secrets.aws {
+            #[cfg(feature = "aws")]
+            {
+                resolver = resolver.with_provider(std::sync::Arc::new(
+                    crate::AwsSecretsManagerProvider::from_config(aws)?,
+                ));
+            }
+            #[cfg(not(feature = "aws"))]
+            {
+                let _ = aws;
+                return Err(
+                    "secrets.aws is configured but AWS Secrets Manager support (feature 'secrets-aws') is not enabled"
+                        .to_string(),
+                );
+            }
+        }
+
+        Ok(resolver)
+    }
+
+    /// Add a provider; it replaces any registered for the same scheme.
+    pub fn with_provider(mut self, provider: DynSecretProvider) -> Self {
+        self.providers.retain(|p| p.scheme() != provider.scheme());
+        self.providers.push(provider);
+        self
+    }
+
+    /// Replace every secret setting of `config` written as `<scheme>:<reference>` for a
+    /// registered scheme, returning the paths of the settings replaced. A reference to a
+    /// built-in store that is not configured is an error rather than being used as the
+    /// secret.
+    pub async fn resolve(&self, config: &mut Config) -> Result<Vec<String>, String> {
+        let mut fetched: HashMap<String, String> = HashMap::new();
+        let mut resolved = Vec::new();
+
+        for (path, value) in config.secret_values_mut() {
+            let Some((scheme, reference)) = value.split_once(':') else {
+                continue;
+            };
+            let Some(provider) = self.providers.iter().find(|p| p.scheme() == scheme) else {
+                if [VAULT_SCHEME, AWS_SECRETS_MANAGER_SCHEME].contains(&scheme) {
+                    return Err(format!(
+                        "{path} references a {scheme} secret but no {scheme} store is configured in secrets"
+                    ));
+                }
+                continue;
+            };
+
+            let secret = match fetched.get(value.as_str()) {
+                Some(secret) => secret.clone(),
+                None => {
+                    let secret = provider.get_secret(reference).await.map_err(|e| {
+                        format!("{path}: {}", e.error_description.unwrap_or(e.error))
+                    })?;
+                    fetched.insert(value.clone(), secret.clone());
+                    secret
+                }
+            };
+            *value = secret;
+            resolved.push(path);
+        }
+        Ok(resolved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use oauth2_core::OAuth2Error;
+    use oauth2_ports::SecretProvider;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct Fixed {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl SecretProvider for Fixed {
+        fn scheme(&self) -> &'static str {
+            "test"
+        }
+
+        async fn get_secret(&self, reference: &str) -> Result<String, OAuth2Error> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match reference {
+                "missing" => Err(crate::provider_error("no such secret".to_string())),
+                other => Ok(format!("resolved-{other}")),
+            }
+        }
+    }
+
+    fn config() -> Config {
+        hocon::HoconLoader::new()
+            .load_str(
+                r#"
+                server { host = "127.0.0.1", port = 8080 }
+                database { url = "sqlite::memory:" }
+                jwt { secret = "test:jwt", issuer = "test" }
+                events { enabled = false, backend = console, filter_mode = allow_all }
+                session { key = "test:jwt" }
+                "#,
+            )
+            .and_then(|loader| loader.resolve())
+            .expect("config")
+    }
+
+    #[tokio::test]
+    async fn references_are_replaced_and_fetched_once() {
+        let provider = Arc::new(Fixed {
+            calls: AtomicUsize::new(0),
+        });
+        let resolver = SecretResolver::new().with_provider(provider.clone());
+        let mut config = config();
+
+        let resolved = resolver.resolve(&mut config).await.expect("resolve");
+        assert_eq!(resolved, vec!["jwt.secret", "session.key"]);
+        assert_eq!(config.jwt.secret, "resolved-jwt");
+        assert_eq!(config.session.unwrap().key.as_deref(), Some("resolved-jwt"));
+        assert_eq!(config.database.url, "sqlite::memory:");
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn failures_name_the_setting() {
+        let resolver = SecretResolver::new().with_provider(Arc::new(Fixed {
+            calls: AtomicUsize::new(0),
+        }));
+        let mut config = config();
+        config.jwt.secret = "test:missing".to_string();
+
+        let err = resolver.resolve(&mut config).await.unwrap_err();
+        assert_eq!(err, "jwt.secret: no such secret");
+    }
+
+    #[tokio::test]
+    async fn unconfigured_builtin_stores_are_an_error() {
+        let mut config = config();
+        config.jwt.secret = "vault:secret/data/oauth2#jwt".to_string();
+
+        let err = SecretResolver::new()
+            .resolve(&mut config)
+            .await
+            .unwrap_err();
+        assert!(
+            err.starts_with("jwt.secret references a vault secret"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use async_trait::async_trait;
+use oauth2_config::VaultConfig;
+use oauth2_core::OAuth2Error;
+use oauth2_ports::SecretProvider;
+use std::time::Duration;
+
+use crate::{provider_error, split_field, string_field, VAULT_SCHEME};
+
+/// Reads secrets from HashiCorp Vault's HTTP API with a token.
+///
+/// References are `<path>#<field>`, where `path` is the API path after `/v1/`: for the
+/// KV v2 engine mounted at `secret`, `vault:secret/data/oauth2#jwt_secret`. KV v1 paths
+/// (`secret/oauth2#jwt_secret`) work too.
+pub struct VaultSecretProvider {
+    http: reqwest::Client,
+    address: String,
+    token: String,
+    namespace: Option<String>,
+}
+
+impl VaultSecretProvider {
+    pub fn new(address: String, token: String, namespace: Option<String>) -> Result<Self, String> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| format!("http client: {e}"))?;
+        Ok(Self {
+            http,
+            address: address.trim_end_matches('/').to_string(),
+            token,
+            namespace,
+        })
+    }
+
+    /// The token defaults to `VAULT_TOKEN`.
+    pub fn from_config(config: &VaultConfig) -> Result<Self, String> {
+        let token = config
+            .token
+            .clone()
+            .or_else(|| std::env::var("VAULT_TOKEN").ok())
+            .ok_or("secrets.vault.token is not set and VAULT_TOKEN is empty")?;
+        Self::new(config.address.clone(), token, config.namespace.clone())
+    }
+}
+
+#[async_trait]
+impl SecretProvider for VaultSecretProvider {
+    fn scheme(&self) -> &'static str {
+        VAULT_SCHEME
+    }
+
+    async fn get_secret(&self, reference: &str) -> Result<String, OAuth2Error> {
+        let (path, Some(field)) = split_field(reference) else {
+            return Err(provider_error(format!(
+                "vault reference {reference} names no field (<path>#<field>)"
+            )));
+        };
+
+        let mut request = self
+            .http
+            .get(format!(
+                "{}/v1/{}",
+                self.address,
+                path.trim_start_matches('/')
+            ))
+            .header("X-Vault-Token", &self.token);
+        if let Some(namespace) = &self.namespace {
+            request = request.header("X-Vault-Namespace", namespace);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| provider_error(format!("vault request: {e}")))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(provider_error(format!(
+                "vault responded {status} for {path}"
+            )));
+        }
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| provider_error(format!("vault response: {e}")))?;
+
+        secret_field(&body, field)
+            .ok_or_else(|| provider_error(format!("vault secret {path} has no field {field}")))
+    }
+}
+
+/// `field` of a KV v2 (`data.data`) or KV v1 (`data`) read response.
+fn secret_field(body: &serde_json::Value, field: &str) -> Option<String> {
+    let data = body.get("data")?;
+    match data.get("data") {
+        Some(kv2) if kv2.is_object() && data.get("metadata").is_some() => string_field(kv2, field),
+        _ => string_field(data, field),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reads_kv_v1_and_v2_responses() {
+        let v2 = json!({
+            "data": {
+                "data": { "jwt_secret": "s3cret", "port": 5432 },
+                "metadata": { "version": 3 }
+            }
+        });
+        assert_eq!(secret_field(&v2, "jwt_secret").as_deref(), Some("s3cret"));
+        assert_eq!(secret_field(&v2, "port").as_deref(), Some("5432"));
+        assert_eq!(secret_field(&v2, "missing"), None);
+
+        let v1 = json!({ "data": { "jwt_secret": "s3cret", "data": "a field named data" } });
+        assert_eq!(secret_field(&v1, "jwt_secret").as_deref(), Some("s3cret"));
+        assert_eq!(
+            secret_field(&v1, "data").as_deref(),
+            Some("a field named data")
+        );
+    }
+}
@@ -0,0 +1,24 @@
+use std::process::Command;
+
+fn main() {
+    built::write_built_file().expect("failed to collect build information");
+
+    // Docker builds don't copy `.git`, so CI passes the commit in `GIT_SHA` instead.
+    let git_sha = std::env::var("GIT_SHA")
+        .ok()
+        .filter(|sha| !sha.is_empty())
+        .or_else(git_head)
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=OAUTH2_GIT_SHA={git_sha}");
+}
+
+fn git_head() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,1652 @@
+use actix::{Actor, Addr};
+use actix_files::Files;
+use actix_session::config::{BrowserSession, CookieContentSecurity, TtlExtensionPolicy};
+use actix_session::SessionMiddleware;
+use actix_web::body::MessageBody;
+use actix_web::dev::Server;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::{
+    cookie::Key, middleware as actix_middleware, web, App, HttpResponse, HttpServer, Scope,
+};
+use oauth2_actix::actors::{AuthActor, ClientActor, TokenActor};
+use oauth2_actix::grants::{DynGrantHandler, GrantRegistry};
+use oauth2_actix::handlers::client::registration_json_config;
+use oauth2_actix::handlers::events::{EventPluginsHealthCheck, IdempotencyStore, IngestAuth};
+use oauth2_actix::handlers::token::{IntrospectionRateLimiter, RevocationListCache};
+use oauth2_actix::handlers::wellknown::MetadataCaching;
+use oauth2_actix::middleware::admin_rbac::{AdminScope, RequireAdminRole};
+use oauth2_actix::middleware::audit::AuditAdminChanges;
+use oauth2_actix::middleware::cors::CorsPolicy;
+use oauth2_actix::middleware::tenant::ResolveTenant;
+use oauth2_actix::middleware::timeout::RequestTimeout;
+use oauth2_actix::outbox::{OutboxRelay, StorageOutbox};
+use oauth2_actix::session::ServerSessionStore;
+use oauth2_config::{
+    Config, ConfigSource, EffectiveConfig, EventOverflowPolicy, EventReadinessPolicy,
+    ListenerConfig, SessionCookieContent,
+};
+use oauth2_core::{AdminRole, IssuerKeys, IssuerUrls, TrustedProxies};
+use oauth2_events::{
+    event_actor::EventActor, ActixEventBus, DynEventReplaySource, EventBusHandle, EventPlugin,
+    LiveEventStream, OverflowPolicy, QueueLimits, QueueMetrics, RetryPolicy, RetryingPlugin,
+};
+use oauth2_mail::{DynMailer, MailService};
+use oauth2_observability::{
+    health::StorageHealthCheck, BuildInfo, HealthCheck, HealthRegistry, Metrics,
+};
+use oauth2_openapi::ApiDoc;
+use oauth2_ports::{
+    DynAuditSink, DynClaimsEnricher, DynStateStore, DynStorage, DynTokenIssuancePolicy,
+    DynUserAuthenticator, DynUserSessionStore, StorageAuditSink,
+};
+use oauth2_social_login::{SocialLoginConfig, TokenVault};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing_actix_web::TracingLogger;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+#[cfg(feature = "ldap")]
+use crate::ldap_from_config;
+#[cfg(feature = "saml")]
+use crate::saml_from_config;
+use crate::{
+    admin_dashboard, claims_mapper_from_config, compiled_features, compression_from_config,
+    dead_letter_sink_from_config, error_page, event_filter_from_config, event_plugins_from_config,
+    event_replay_source_from_config, event_signing_key_from_config,
+    ingest_idempotency_backend_from_config, log_startup_banner, mail_from_config,
+    roles_from_config, seed_tenants_from_config, session_key_from_config,
+    session_store_from_config, social_state_store_from_config,
+    storage_cache_invalidation_from_config, OtelRootSpanBuilder,
+};
+
+/// Events buffered per `GET /admin/events/stream` client before it misses some.
+const LIVE_EVENT_STREAM_CAPACITY: usize = 1024;
+
+/// Route groups that can be mounted independently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EndpointGroup {
+    /// `/oauth/*` (authorize, token, introspect, revoke, registration) and `/clients/register`.
+    OAuth,
+    /// `/.well-known/openid-configuration`.
+    Discovery,
+    /// `/auth/*`: login page, social login, session listing and revocation and, with
+    /// `mail.enabled`, password reset, email verification and magic links. Needs session
+    /// middleware ([`OAuth2Server::session_middleware`]).
+    Login,
+    /// `/admin/*`.
+    Admin,
+    /// `/health`, `/health/{live,ready,startup}`, `/ready`, `/version` and `/metrics`.
+    Observability,
+    /// `/events/*`.
+    Events,
+    /// Swagger UI and `/api-docs/openapi.json`.
+    ApiDocs,
+    /// `/` (redirects to the login page), `/error` and `/static`.
+    Ui,
+}
+
+impl EndpointGroup {
+    pub const ALL: [EndpointGroup; 8] = [
+        EndpointGroup::OAuth,
+        EndpointGroup::Discovery,
+        EndpointGroup::Login,
+        EndpointGroup::Admin,
+        EndpointGroup::Observability,
+        EndpointGroup::Events,
+        EndpointGroup::ApiDocs,
+        EndpointGroup::Ui,
+    ];
+}
+
+/// Assembles an [`OAuth2Server`] from a [`Config`], with optional overrides for embedding.
+///
+/// ```no_run
+/// # async fn example(config: oauth2_config::Config) -> std::io::Result<()> {
+/// use actix_web::{App, HttpServer};
+/// use oauth2_server::{EndpointGroup, ServerBuilder};
+///
+/// let oauth2 = ServerBuilder::new(config)
+///     .with_endpoints([EndpointGroup::OAuth, EndpointGroup::Discovery])
+///     .build()
+///     .await?;
+///
+/// HttpServer::new(move || {
+///     let oauth2 = oauth2.clone();
+///     App::new().configure(move |cfg| oauth2.configure(cfg))
+/// })
+/// .bind("127.0.0.1:8080")?
+/// .run()
+/// .await
+/// # }
+/// ```
+pub struct ServerBuilder {
+    config: Config,
+    config_source: ConfigSource,
+    storage: Option<DynStorage>,
+    event_plugins: Vec<Arc<dyn EventPlugin>>,
+    event_replay_source: Option<DynEventReplaySource>,
+    claims_enrichers: Vec<DynClaimsEnricher>,
+    token_issuance_policies: Vec<DynTokenIssuancePolicy>,
+    grant_handlers: Vec<DynGrantHandler>,
+    user_authenticator: Option<DynUserAuthenticator>,
+    mailer: Option<DynMailer>,
+    audit_sink: Option<DynAuditSink>,
+    health_checks: Vec<(String, Arc<dyn HealthCheck>)>,
+    endpoints: BTreeSet<EndpointGroup>,
+}
+
+impl ServerBuilder {
+    /// Mounts every endpoint group and uses the storage backend from `database.url`.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            config_source: ConfigSource::Programmatic,
+            storage: None,
+            event_plugins: Vec::new(),
+            event_replay_source: None,
+            claims_enrichers: Vec::new(),
+            token_issuance_policies: Vec::new(),
+            grant_handlers: Vec::new(),
+            user_authenticator: None,
+            mailer: None,
+            audit_sink: None,
+            health_checks: Vec::new(),
+            endpoints: EndpointGroup::ALL.into_iter().collect(),
+        }
+    }
+
+    /// Where `config` came from, as reported by `GET /admin/config`.
+    pub fn with_config_source(mut self, source: ConfigSource) -> Self {
+        self.config_source = source;
+        self
+    }
+
+    /// Use `storage` instead of connecting to `database.url`. `build` still calls
+    /// [`Storage::init`](oauth2_ports::Storage::init) on it.
+    pub fn with_storage(mut self, storage: DynStorage) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Publish events to `plugin` as well as the configured backend. Added plugins are
+    /// active even when `events.enabled` is false; the configured filter applies only
+    /// when it is true.
+    pub fn with_event_plugin(mut self, plugin: Arc<dyn EventPlugin>) -> Self {
+        self.event_plugins.push(plugin);
+        self
+    }
+
+    /// Read events for `POST /admin/events/replay` from `source` instead of the configured
+    /// Redis stream or Kafka topic.
+    pub fn with_event_replay_source(mut self, source: DynEventReplaySource) -> Self {
+        self.event_replay_source = Some(source);
+        self
+    }
+
+    /// Run `enricher` on every access token before it is signed, after any added earlier.
+    pub fn with_claims_enricher(mut self, enricher: DynClaimsEnricher) -> Self {
+        self.claims_enrichers.push(enricher);
+        self
+    }
+
+    /// Check `policy` before every token is issued, after any added earlier. It can deny
+    /// issuance, narrow the scope or add claims; claims enrichers run afterwards.
+    pub fn with_token_issuance_policy(mut self, policy: DynTokenIssuancePolicy) -> Self {
+        self.token_issuance_policies.push(policy);
+        self
+    }
+
+    /// Serve `handler`'s grant type at the token endpoint, replacing the built-in handler
+    /// of that name. Clients must be registered for the grant type to use it.
+    pub fn with_grant_handler(mut self, handler: DynGrantHandler) -> Self {
+        self.grant_handlers.push(handler);
+        self
+    }
+
+    /// Check passwords of the password grant with `authenticator` instead of the
+    /// configured `ldap` directory or the local password hashes.
+    pub fn with_user_authenticator(mut self, authenticator: DynUserAuthenticator) -> Self {
+        self.user_authenticator = Some(authenticator);
+        self
+    }
+
+    /// Send verification and password reset mail with `mailer` instead of the `mail.smtp`
+    /// relay. Mail is only sent when `mail.enabled`.
+    pub fn with_mailer(mut self, mailer: DynMailer) -> Self {
+        self.mailer = Some(mailer);
+        self
+    }
+
+    /// Write the audit log to `sink` instead of the server's storage. `GET /admin/audit`
+    /// reads it back from the same sink.
+    pub fn with_audit_sink(mut self, sink: DynAuditSink) -> Self {
+        self.audit_sink = Some(sink);
+        self
+    }
+
+    /// Report `check` under `name` on `/health/ready`; a failure takes the server out of
+    /// rotation. Storage is always checked.
+    pub fn with_health_check(
+        mut self,
+        name: impl Into<String>,
+        check: Arc<dyn HealthCheck>,
+    ) -> Self {
+        self.health_checks.push((name.into(), check));
+        self
+    }
+
+    /// Public base URL of the server, e.g. `https://auth.example.com/oauth2` when it is
+    /// mounted under a prefix. Used for discovery metadata and as the `iss` claim,
+    /// replacing `server.issuer` and `jwt.issuer`.
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        let issuer = issuer.into();
+        self.config.jwt.issuer = issuer.clone();
+        self.config.server.issuer = Some(issuer);
+        self
+    }
+
+    /// Do not mount the admin API and dashboard, for embedders with their own tooling.
+    pub fn disable_admin(self) -> Self {
+        self.without_endpoint(EndpointGroup::Admin)
+    }
+
+    /// Mount only `groups`.
+    pub fn with_endpoints(mut self, groups: impl IntoIterator<Item = EndpointGroup>) -> Self {
+        self.endpoints = groups.into_iter().collect();
+        self
+    }
+
+    /// Do not mount `group`.
+    pub fn without_endpoint(mut self, group: EndpointGroup) -> Self {
+        self.endpoints.remove(&group);
+        self
+    }
+
+    /// Connect storage and the event system and start the actors. Must be called from
+    /// within an actix system.
+    pub async fn build(self) -> std::io::Result<OAuth2Server> {
+        let config = self.config;
+        // Logs and spans are process-wide, so their redaction is too.
+        config.redaction.install();
+
+        // Load social login configuration from HOCON config or environment
+        let social_config = if let Some(ref social) = config.social {
+            Arc::new(SocialLoginConfig::from_config_social(social))
+        } else {
+            Arc::new(SocialLoginConfig::from_env())
+        };
+        tracing::info!("Social login configuration loaded");
+
+        // Initialize metrics
+        let metrics = Metrics::new().map_err(std::io::Error::other)?;
+        tracing::info!("Metrics initialized");
+
+        // Initialize storage backend (SQLx by default, optional MongoDB)
+        let storage: DynStorage = match self.storage {
+            Some(storage) => storage,
+            None => {
+                if config.database.run_migrations {
+                    let applied = oauth2_storage_factory::run_migrations(&config.database.url)
+                        .await
+                        .map_err(|e| {
+                            std::io::Error::other(format!("Failed to run database migrations: {e}"))
+                        })?;
+                    tracing::info!(applied = ?applied, "Database migrations up to date");
+                }
+                tracing::info!(database_url = %config.database.url, "Connecting to storage backend");
+                let inner = oauth2_storage_factory::connect(&config.database.url)
+                    .await
+                    .map_err(|e| {
+                        std::io::Error::other(format!("Failed to create storage backend: {e}"))
+                    })?;
+                let db_system = oauth2_storage_factory::backend_name(&config.database.url);
+                let mut observed =
+                    oauth2_storage_factory::ObservedStorage::new(inner, db_system.to_string())
+                        .with_metrics(&metrics);
+                let threshold_ms = config.database.slow_operation_threshold_ms;
+                if threshold_ms > 0 {
+                    observed = observed.with_slow_threshold(Duration::from_millis(threshold_ms));
+                }
+                Arc::new(observed)
+            }
+        };
+        storage.init().await.map_err(|e| {
+            std::io::Error::other(format!("Failed to initialize storage backend: {e}"))
+        })?;
+        tracing::info!("Storage backend initialized");
+
+        let storage: DynStorage = if config.database.cache.enabled {
+            let cache = &config.database.cache;
+            tracing::info!(
+                capacity = cache.capacity,
+                ttl_secs = cache.ttl_secs,
+                "Caching client and access-token lookups in memory"
+            );
+            let mut cached = oauth2_storage_factory::CachedStorage::new(
+                storage,
+                cache.capacity,
+                Duration::from_secs(cache.ttl_secs),
+            )
+            .with_metrics(metrics.storage_cache_requests_total.clone());
+            if let Some(channel) = storage_cache_invalidation_from_config(&config).await {
+                cached = cached.with_invalidation(channel).await;
+            }
+            Arc::new(cached)
+        } else {
+            storage
+        };
+
+        if config.reconcile.enabled {
+            crate::reconcile::start(storage.clone(), &config.reconcile)
+                .await
+                .map_err(std::io::Error::other)?;
+        }
+
+        if let Some(path) = &config.seed.file {
+            let data = crate::seed::SeedData::load(std::path::Path::new(path))
+                .map_err(std::io::Error::other)?;
+            let report = crate::seed::apply(&storage, &data)
+                .await
+                .map_err(std::io::Error::other)?;
+            tracing::info!(
+                file = %path,
+                created = ?report.created,
+                unchanged = report.unchanged,
+                "Seed data applied"
+            );
+        }
+
+        seed_tenants_from_config(&storage, &config.tenancy).await?;
+        if config.tenancy.enabled {
+            tracing::info!("Tenant endpoints mounted under /t/{{tenant}}");
+        }
+
+        let jwt_secret = config.jwt.secret.clone();
+        let issuer_keys = oauth2_actix::mount::issuer_keys_from_config(&config.jwt)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+        let trusted_proxies = if config.server.trust_forwarded_headers {
+            TrustedProxies::any()
+        } else {
+            TrustedProxies::parse(&config.server.trusted_proxies).map_err(|err| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("server.trusted_proxies: {err}"),
+                )
+            })?
+        };
+        let issuer_urls = IssuerUrls::new(config.server.issuer.clone())
+            .with_trusted_proxies(trusted_proxies.clone());
+        match issuer_urls.configured_issuer() {
+            Some(issuer) => tracing::info!(issuer, "Public issuer URL configured"),
+            None => tracing::info!(
+                trust_forwarded_headers = config.server.trust_forwarded_headers,
+                trusted_proxies = ?config.server.trusted_proxies,
+                "server.issuer not set; deriving public URLs from request headers"
+            ),
+        }
+
+        let session_key = session_key_from_config(&config)?;
+
+        // Initialize event system first
+        let mut plugins = if config.events.enabled {
+            event_plugins_from_config(&config.events).await
+        } else {
+            Vec::new()
+        };
+        plugins.extend(self.event_plugins);
+
+        // Failed emits are retried per plugin, then dead-lettered when a destination is set.
+        let retry = &config.events.retry;
+        let policy = RetryPolicy {
+            max_attempts: retry.max_attempts,
+            initial_backoff: Duration::from_millis(retry.initial_backoff_ms),
+            max_backoff: Duration::from_millis(retry.max_backoff_ms),
+        };
+        let dead_letter = dead_letter_sink_from_config(&config.events).await?;
+        let plugins: Vec<Arc<dyn EventPlugin>> = plugins
+            .into_iter()
+            .map(|plugin| {
+                let plugin = RetryingPlugin::new(plugin, policy).with_metrics(
+                    metrics.events_plugin_retries_total.clone(),
+                    metrics.events_dead_lettered_total.clone(),
+                );
+                let plugin = match &dead_letter {
+                    Some(sink) => plugin.with_dead_letter(sink.clone()),
+                    None => plugin,
+                };
+                Arc::new(plugin) as Arc<dyn EventPlugin>
+            })
+            .collect();
+        let (event_actor, event_backends, live_event_stream) = if plugins.is_empty() {
+            tracing::info!("Event system disabled");
+            (None, Vec::new(), None)
+        } else {
+            let filter = if config.events.enabled {
+                event_filter_from_config(&config.events)
+            } else {
+                oauth2_events::EventFilter::allow_all()
+            };
+            let plugin_names: Vec<String> = plugins.iter().map(|p| p.name().to_string()).collect();
+            // Feeds `GET /admin/events/stream`; not a backend, so not listed as one.
+            let live_event_stream = LiveEventStream::new(LIVE_EVENT_STREAM_CAPACITY);
+            let mut plugins = plugins;
+            plugins.push(Arc::new(live_event_stream.clone()));
+            let queue = &config.events.queue;
+            let limits = QueueLimits {
+                capacity: queue.capacity,
+                overflow: match queue.overflow {
+                    EventOverflowPolicy::DropOldest => OverflowPolicy::DropOldest,
+                    EventOverflowPolicy::DropNewest => OverflowPolicy::DropNewest,
+                    EventOverflowPolicy::Block => {
+                        OverflowPolicy::Block(Duration::from_millis(queue.block_timeout_ms))
+                    }
+                },
+                concurrency: queue.concurrency,
+            };
+            let actor = EventActor::new(plugins, filter)
+                .with_queue_limits(limits)
+                .with_queue_metrics(QueueMetrics {
+                    depth: metrics.events_queue_depth.clone(),
+                    dropped: metrics.events_queue_dropped_total.clone(),
+                })
+                .start();
+            tracing::info!("Event system initialized");
+            (Some(actor), plugin_names, Some(live_event_stream))
+        };
+
+        // Wrap the actor-backed event system behind the stable EventBus contract. With the
+        // outbox, events are queued in storage and the relay hands them to the actor.
+        let event_signing_key = event_signing_key_from_config(&config.events)?;
+        let outbox = &config.events.outbox;
+        if let (true, Some(addr)) = (outbox.enabled, &event_actor) {
+            let bus = ActixEventBus::new(addr.clone()).acknowledged();
+            OutboxRelay::new(storage.clone(), Arc::new(bus), outbox)
+                .start(Duration::from_millis(outbox.poll_interval_ms.max(1)));
+            tracing::info!(
+                poll_interval_ms = outbox.poll_interval_ms,
+                "Event outbox relay started"
+            );
+        }
+        let event_bus = event_actor.as_ref().map(|addr| {
+            let handle = if outbox.enabled {
+                EventBusHandle::new(Arc::new(StorageOutbox::new(storage.clone()))).as_outbox()
+            } else {
+                EventBusHandle::new(Arc::new(ActixEventBus::new(addr.clone())))
+            };
+            match &event_signing_key {
+                Some(key) => handle.with_signing_keys(Arc::new(key.clone())),
+                None => handle,
+            }
+        });
+
+        let event_replay_source = match (&event_actor, self.event_replay_source) {
+            (None, _) => None,
+            (Some(_), Some(source)) => Some(source),
+            (Some(_), None) => event_replay_source_from_config(&config.events).await,
+        };
+
+        // Idempotency cache for ingest: shared via Redis when configured, else per-replica.
+        let mut ingest_idempotency = IdempotencyStore::new(Duration::from_secs(5 * 60))
+            // Explicitly set to default to make it configurable without changing call sites.
+            .with_max_entries(100_000)
+            .with_metrics(metrics.clone());
+        if let Some(backend) = ingest_idempotency_backend_from_config(&config).await {
+            ingest_idempotency = ingest_idempotency.with_backend(backend);
+        }
+        let cache_backend = ingest_idempotency.backend_name();
+        let mut ingest_auth = if config.security.event_endpoints_require_admin {
+            IngestAuth::new()
+        } else {
+            IngestAuth::open()
+        };
+        for producer in &config.events.ingest.producers {
+            ingest_auth = ingest_auth.with_producer(&producer.id, &producer.secret);
+        }
+        if config.events.ingest.rate_limit_per_minute > 0 {
+            ingest_auth =
+                ingest_auth.with_rate_limit_per_minute(config.events.ingest.rate_limit_per_minute);
+        }
+        let social_state_store = social_state_store_from_config(&config, storage.clone()).await;
+        tracing::info!(
+            backend = social_state_store.backend_name(),
+            "Social login state store ready"
+        );
+        let session_store = session_store_from_config(&config, storage.clone()).await;
+        tracing::info!(
+            backend = session_store.backend_name(),
+            "Session store ready"
+        );
+        let audit_sink = self
+            .audit_sink
+            .unwrap_or_else(|| Arc::new(StorageAuditSink::new(storage.clone())));
+        tracing::info!(backend = audit_sink.backend_name(), "Audit log ready");
+
+        let token_vault = config
+            .social
+            .as_ref()
+            .and_then(|social| social.token_encryption_key.as_deref())
+            .map(TokenVault::from_base64_key)
+            .transpose()
+            .map_err(|e| {
+                std::io::Error::other(format!("Invalid social.token_encryption_key: {e}"))
+            })?;
+
+        let mail = mail_from_config(&config, storage.clone(), self.mailer)?.map(|mail| {
+            let mail = mail.with_session_store(session_store.clone());
+            match &event_bus {
+                Some(event_bus) => mail.with_event_bus(event_bus.clone()),
+                None => mail,
+            }
+        });
+
+        #[cfg(feature = "saml")]
+        let saml = saml_from_config(&config)?;
+        #[cfg(not(feature = "saml"))]
+        if config.saml.is_some() {
+            tracing::warn!("saml is configured but feature 'saml' is not enabled; ignoring it");
+        }
+
+        // Configured enrichers run before added ones: directory claims, then roles, then
+        // mapped claims.
+        let mut claims_enrichers = self.claims_enrichers;
+        if let Some(mapper) = claims_mapper_from_config(&config, storage.clone())? {
+            claims_enrichers.insert(0, mapper);
+        }
+        if let Some(roles) = roles_from_config(&config, storage.clone())? {
+            claims_enrichers.insert(0, roles);
+        }
+
+        #[cfg(feature = "ldap")]
+        let (claims_enrichers, user_authenticator) = {
+            let mut claims_enrichers = claims_enrichers;
+            let mut user_authenticator = self.user_authenticator;
+            if let Some(ldap) = ldap_from_config(&config, storage.clone())? {
+                // Directory claims come first so added enrichers can build on them.
+                if ldap.maps_claims() {
+                    claims_enrichers.insert(0, ldap.clone());
+                }
+                user_authenticator.get_or_insert(ldap);
+            }
+            (claims_enrichers, user_authenticator)
+        };
+        #[cfg(not(feature = "ldap"))]
+        let user_authenticator = self.user_authenticator;
+        #[cfg(not(feature = "ldap"))]
+        if config.ldap.as_ref().is_some_and(|ldap| ldap.enabled) {
+            tracing::warn!("ldap is enabled but feature 'ldap' is not enabled; ignoring it");
+        }
+
+        let introspection_rate_limiter = (config.security.introspection_rate_limit_per_minute > 0)
+            .then(|| {
+                IntrospectionRateLimiter::per_minute(
+                    config.security.introspection_rate_limit_per_minute,
+                )
+            });
+
+        let revocation_list = RevocationListCache::new(Duration::from_secs(
+            config.server.revocation_list_max_age_secs,
+        ));
+
+        let effective_config = EffectiveConfig::new(
+            &config,
+            self.config_source,
+            compiled_features(),
+            oauth2_config::ResolvedBackends {
+                storage: oauth2_storage_factory::backend_name(&config.database.url).to_string(),
+                events: event_backends,
+                cache: cache_backend.to_string(),
+            },
+        );
+        log_startup_banner(&effective_config);
+
+        // Start actors with event system
+        let token_actor = match &event_bus {
+            Some(event_bus) => {
+                TokenActor::with_events(storage.clone(), jwt_secret.clone(), event_bus.clone())
+            }
+            None => TokenActor::new(storage.clone(), jwt_secret.clone()),
+        }
+        .with_issuer_keys(issuer_keys.clone())
+        .with_revocation_cascade(config.security.cascade_token_revocation)
+        .with_context_binding(
+            config.security.bind_authorization_codes,
+            config.security.context_binding_tolerance,
+        )
+        .with_claims_enrichers(claims_enrichers)
+        .with_issuance_policies(self.token_issuance_policies)
+        .start();
+
+        let client_actor = if let Some(ref event_bus) = event_bus {
+            ClientActor::with_events(storage.clone(), event_bus.clone())
+        } else {
+            ClientActor::new(storage.clone())
+        }
+        .with_redirect_uri_changes(
+            config.security.redirect_uri_changes,
+            config.security.redirect_uri_change_delay_secs,
+        )
+        .with_admin_scope(config.security.admin_scope.clone())
+        .start();
+
+        let auth_actor = if let Some(ref event_bus) = event_bus {
+            AuthActor::with_events(storage.clone(), event_bus.clone())
+        } else {
+            AuthActor::new(storage.clone())
+        }
+        .with_context_binding(
+            config.security.bind_authorization_codes,
+            config.security.context_binding_tolerance,
+        );
+        let auth_actor = match user_authenticator {
+            Some(authenticator) => auth_actor.with_authenticator(authenticator),
+            None => auth_actor,
+        }
+        .start();
+        if config.security.bind_authorization_codes {
+            tracing::info!(
+                tolerance = ?config.security.context_binding_tolerance,
+                "Authorization codes and refresh tokens are bound to the requesting network"
+            );
+        }
+
+        tracing::info!("Actors started");
+
+        let build_info = crate::build_info();
+        tracing::info!(
+            version = %build_info.version,
+            git_sha = %build_info.git_sha,
+            built_at = %build_info.built_at,
+            "Build information"
+        );
+        let mut health = HealthRegistry::new().with_build_info(build_info.clone());
+        health.register("storage", Arc::new(StorageHealthCheck(storage.clone())));
+        if let Some(ref event_actor) = event_actor {
+            let check = Arc::new(EventPluginsHealthCheck(event_actor.clone()));
+            match config.events.readiness {
+                EventReadinessPolicy::Fail => health.register("events", check),
+                EventReadinessPolicy::Warn => health.register_non_critical("events", check),
+            }
+        }
+        for (name, check) in self.health_checks {
+            health.register(name, check);
+        }
+        health.mark_started();
+
+        let grant_registry = self.grant_handlers.into_iter().fold(
+            GrantRegistry::builtin(&config.grants),
+            GrantRegistry::with_handler,
+        );
+
+        Ok(OAuth2Server {
+            grant_registry,
+            config: Arc::new(config),
+            endpoints: self.endpoints,
+            storage,
+            metrics,
+            health,
+            build_info,
+            token_actor,
+            client_actor,
+            auth_actor,
+            jwt_secret,
+            issuer_keys,
+            issuer_urls,
+            trusted_proxies,
+            effective_config,
+            social_config,
+            social_state_store,
+            token_vault,
+            mail,
+            #[cfg(feature = "saml")]
+            saml,
+            ingest_idempotency,
+            ingest_auth,
+            introspection_rate_limiter,
+            revocation_list,
+            event_actor,
+            event_bus,
+            live_event_stream,
+            event_replay_source,
+            session_key,
+            session_store,
+            audit_sink,
+        })
+    }
+}
+
+/// A built server: shared state plus the selected endpoint groups.
+///
+/// Cheap to clone; clones share actors, storage and metrics.
+#[derive(Clone)]
+pub struct OAuth2Server {
+    config: Arc<Config>,
+    grant_registry: GrantRegistry,
+    endpoints: BTreeSet<EndpointGroup>,
+    storage: DynStorage,
+    metrics: Metrics,
+    health: HealthRegistry,
+    build_info: BuildInfo,
+    token_actor: Addr<TokenActor>,
+    client_actor: Addr<ClientActor>,
+    auth_actor: Addr<AuthActor>,
+    jwt_secret: String,
+    issuer_keys: IssuerKeys,
+    issuer_urls: IssuerUrls,
+    trusted_proxies: TrustedProxies,
+    effective_config: EffectiveConfig,
+    social_config: Arc<SocialLoginConfig>,
+    social_state_store: DynStateStore,
+    token_vault: Option<TokenVault>,
+    mail: Option<MailService>,
+    #[cfg(feature = "saml")]
+    saml: Option<oauth2_saml::SamlServiceProvider>,
+    ingest_idempotency: IdempotencyStore,
+    ingest_auth: IngestAuth,
+    introspection_rate_limiter: Option<IntrospectionRateLimiter>,
+    revocation_list: RevocationListCache,
+    event_actor: Option<Addr<EventActor>>,
+    event_bus: Option<EventBusHandle>,
+    live_event_stream: Option<LiveEventStream>,
+    event_replay_source: Option<DynEventReplaySource>,
+    session_key: Key,
+    session_store: DynUserSessionStore,
+    audit_sink: DynAuditSink,
+}
+
+impl OAuth2Server {
+    pub fn builder(config: Config) -> ServerBuilder {
+        ServerBuilder::new(config)
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn storage(&self) -> &DynStorage {
+        &self.storage
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Component checks behind the `/health/*` probes.
+    pub fn health(&self) -> &HealthRegistry {
+        &self.health
+    }
+
+    /// Sanitized configuration and resolved backends, as served at `GET /admin/config`.
+    pub fn effective_config(&self) -> &EffectiveConfig {
+        &self.effective_config
+    }
+
+    /// Key protecting the session cookie.
+    pub fn session_key(&self) -> &Key {
+        &self.session_key
+    }
+
+    /// Where browser sessions are kept (`session.store`).
+    pub fn session_store(&self) -> &DynUserSessionStore {
+        &self.session_store
+    }
+
+    /// Where logins, consent, token issuance and admin changes are recorded.
+    pub fn audit_sink(&self) -> &DynAuditSink {
+        &self.audit_sink
+    }
+
+    /// Session middleware per the `session` config: the cookie carries only a key and
+    /// sessions live in [`Self::session_store`]. Embedders mounting
+    /// [`EndpointGroup::Login`] must wrap their `App` in it (or in their own
+    /// `SessionMiddleware`, without session listing).
+    pub fn session_middleware(&self) -> SessionMiddleware<ServerSessionStore> {
+        let session = self.config.session.clone().unwrap_or_default();
+        let content_security = match session.cookie_content {
+            SessionCookieContent::Private => CookieContentSecurity::Private,
+            SessionCookieContent::Signed => CookieContentSecurity::Signed,
+        };
+        let idle_timeout = session
+            .idle_timeout_secs
+            .min(session.absolute_timeout_secs)
+            .try_into()
+            .unwrap_or(i64::MAX);
+        let store = ServerSessionStore::new(
+            self.session_store.clone(),
+            Duration::from_secs(session.absolute_timeout_secs),
+        );
+
+        SessionMiddleware::builder(store, self.session_key.clone())
+            .cookie_name(session.cookie_name)
+            .cookie_secure(session.cookie_secure)
+            .cookie_content_security(content_security)
+            .session_lifecycle(
+                BrowserSession::default()
+                    .state_ttl(actix_web::cookie::time::Duration::seconds(idle_timeout))
+                    .state_ttl_extension_policy(TtlExtensionPolicy::OnEveryRequest),
+            )
+            .build()
+    }
+
+    pub fn endpoints(&self) -> &BTreeSet<EndpointGroup> {
+        &self.endpoints
+    }
+
+    /// Register shared state and the selected routes, e.g. via `App::configure`.
+    ///
+    /// Only routes and app data are added; middleware (sessions, tracing, metrics, request
+    /// timeouts, CORS) is left to the embedding application.
+    pub fn configure(&self, cfg: &mut web::ServiceConfig) {
+        cfg.app_data(web::Data::new(self.token_actor.clone()))
+            .app_data(web::Data::new(self.client_actor.clone()))
+            .app_data(web::Data::new(self.auth_actor.clone()))
+            .app_data(web::Data::new(self.jwt_secret.clone()))
+            .app_data(web::Data::new(self.issuer_keys.clone()))
+            .app_data(web::Data::new(self.issuer_urls.clone()))
+            .app_data(web::Data::new(self.trusted_proxies.clone()))
+            .app_data(web::Data::new(self.config.grants.clone()))
+            .app_data(web::Data::new(self.grant_registry.clone()))
+            .app_data(web::Data::new(MetadataCaching {
+                max_age: Duration::from_secs(self.config.server.metadata_max_age_secs),
+            }))
+            .app_data(web::Data::new(self.storage.clone()))
+            .app_data(web::Data::new(self.metrics.clone()))
+            .app_data(web::Data::new(AdminScope(
+                self.config.security.admin_scope.clone(),
+            )))
+            .app_data(web::Data::new(self.health.clone()))
+            .app_data(web::Data::new(self.build_info.clone()))
+            .app_data(web::Data::new(self.effective_config.clone()))
+            .app_data(web::Data::new(self.social_config.clone()))
+            .app_data(web::Data::new(self.social_state_store.clone()))
+            .app_data(web::Data::new(self.session_store.clone()))
+            .app_data(web::Data::new(self.audit_sink.clone()))
+            // Shared, best-effort in-memory idempotency cache for event ingest.
+            .app_data(web::Data::new(self.ingest_idempotency.clone()))
+            .app_data(web::Data::new(self.ingest_auth.clone()))
+            .app_data(web::Data::new(self.revocation_list.clone()));
+
+        if let Some(ref vault) = self.token_vault {
+            cfg.app_data(web::Data::new(vault.clone()));
+        }
+        if let Some(ref mail) = self.mail {
+            cfg.app_data(web::Data::new(mail.clone()));
+        }
+        #[cfg(feature = "saml")]
+        if let Some(ref saml) = self.saml {
+            cfg.app_data(web::Data::new(saml.clone()));
+        }
+        if let Some(ref limiter) = self.introspection_rate_limiter {
+            cfg.app_data(web::Data::new(limiter.clone()));
+        }
+
+        // Add event actor if enabled
+        if let Some(ref event_actor) = self.event_actor {
+            cfg.app_data(web::Data::new(event_actor.clone()));
+        }
+
+        // Add event bus handle if enabled
+        if let Some(ref event_bus) = self.event_bus {
+            cfg.app_data(web::Data::new(event_bus.clone()));
+        }
+        if let Some(ref live_event_stream) = self.live_event_stream {
+            cfg.app_data(web::Data::new(live_event_stream.clone()));
+        }
+        if let Some(ref source) = self.event_replay_source {
+            cfg.app_data(web::Data::new(source.clone()));
+        }
+
+        for group in &self.endpoints {
+            match group {
+                EndpointGroup::OAuth => configure_oauth(cfg),
+                EndpointGroup::Discovery => configure_discovery(cfg),
+                EndpointGroup::Login => {
+                    #[cfg(feature = "saml")]
+                    if self.saml.is_some() {
+                        oauth2_saml::handlers::configure(cfg);
+                    }
+                    if let Some(ref mail) = self.mail {
+                        oauth2_mail::handlers::configure(cfg);
+                        if mail.magic_link_enabled() {
+                            oauth2_mail::handlers::configure_magic_link(cfg);
+                        }
+                    }
+                    configure_login(cfg)
+                }
+                EndpointGroup::Admin => configure_admin(cfg),
+                EndpointGroup::Observability => configure_observability(cfg),
+                EndpointGroup::Events => {
+                    configure_events(cfg, self.config.security.event_endpoints_require_admin)
+                }
+                EndpointGroup::ApiDocs => {
+                    cfg.service(
+                        SwaggerUi::new("/swagger-ui/{_:.*}")
+                            .url("/api-docs/openapi.json", ApiDoc::openapi()),
+                    );
+                }
+                EndpointGroup::Ui => configure_ui(cfg),
+            }
+        }
+
+        let tenant_groups = [EndpointGroup::OAuth, EndpointGroup::Discovery];
+        if self.config.tenancy.enabled && tenant_groups.iter().any(|g| self.endpoints.contains(g)) {
+            configure_tenants(cfg, &self.endpoints);
+        }
+    }
+
+    /// The selected routes and shared state under `path`, to mount into an existing `App`
+    /// with `App::service`.
+    ///
+    /// Unlike [`Self::configure`], this brings the middleware the routes rely on: sessions,
+    /// request timeouts, metrics and CORS. Logging and compression are left to the `App`.
+    /// Set the issuer ([`ServerBuilder::with_issuer`]) to the URL including `path`, so
+    /// discovery metadata and redirects point into the scope.
+    pub fn scope(
+        &self,
+        path: &str,
+    ) -> Scope<
+        impl ServiceFactory<
+            ServiceRequest,
+            Config = (),
+            Response = ServiceResponse<impl MessageBody>,
+            Error = actix_web::Error,
+            InitError = (),
+        >,
+    > {
+        web::scope(path)
+            .configure(|cfg| self.configure(cfg))
+            .wrap(RequestTimeout::from_config(&self.config.server.timeouts))
+            .wrap(self.session_middleware())
+            .wrap(oauth2_observability::actix::MetricsMiddleware::new(
+                self.metrics.clone(),
+            ))
+            .wrap(CorsPolicy::from_config(&self.config.server.cors))
+    }
+
+    /// The `App` [`Self::run`] serves: the selected routes behind the standard middleware
+    /// stack.
+    fn app(
+        &self,
+    ) -> App<
+        impl ServiceFactory<
+            ServiceRequest,
+            Config = (),
+            Response = ServiceResponse<impl MessageBody>,
+            Error = actix_web::Error,
+            InitError = (),
+        >,
+    > {
+        App::new()
+            // Middleware
+            // Innermost, so logging/metrics still observe timed-out requests.
+            .wrap(RequestTimeout::from_config(&self.config.server.timeouts))
+            .wrap(self.session_middleware())
+            .wrap(TracingLogger::<OtelRootSpanBuilder>::new())
+            .wrap(actix_middleware::Logger::default())
+            .wrap(compression_from_config(&self.config.server.compression))
+            .wrap(oauth2_observability::actix::MetricsMiddleware::new(
+                self.metrics.clone(),
+            ))
+            .wrap(CorsPolicy::from_config(&self.config.server.cors))
+            .configure(|cfg| self.configure(cfg))
+    }
+
+    /// This server split into the public one and one serving only [`ADMIN_LISTENER_GROUPS`].
+    fn split_admin_endpoints(self) -> (Self, Self) {
+        let mut admin = self.clone();
+        admin
+            .endpoints
+            .retain(|group| ADMIN_LISTENER_GROUPS.contains(group));
+        let mut public = self;
+        public
+            .endpoints
+            .retain(|group| !ADMIN_LISTENER_GROUPS.contains(group));
+        (public, admin)
+    }
+
+    /// TLS settings for `server.tls`, reloading the certificate when its files change.
+    fn tls_config(&self) -> std::io::Result<Option<rustls::ServerConfig>> {
+        match &self.config.server.tls {
+            Some(tls) => {
+                let (server_config, cert) = crate::tls::server_config(tls)?;
+                cert.watch();
+                Ok(Some(server_config))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Serve on `server.listeners` (or `server.host:server.port`) with the standard
+    /// middleware stack until shutdown, over HTTPS when `server.tls` is set. With
+    /// `server.admin_port`, admin and observability endpoints are served only there.
+    pub async fn run(self) -> std::io::Result<()> {
+        let listeners = self.config.server.effective_listeners();
+        let admin_address = self.config.server.admin_address();
+        let tls = self.tls_config()?;
+        let scheme = if tls.is_some() { "https" } else { "http" };
+        let urls = listeners
+            .iter()
+            .map(|listener| listener_url(listener, tls.is_some()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let admin_url = admin_address
+            .as_ref()
+            .map(|address| format!("{scheme}://{address}"));
+        for url in &urls {
+            tracing::info!("Starting server at {}", url);
+        }
+        if let Some(url) = &admin_url {
+            tracing::info!("Admin, health and metrics endpoints only at {}", url);
+        }
+        let base_url = &urls[0];
+        let admin_base_url = admin_url.as_ref().unwrap_or(base_url);
+        if self.endpoints.contains(&EndpointGroup::Login) {
+            tracing::info!("Login page available at {}/auth/login", base_url);
+        }
+        if self.endpoints.contains(&EndpointGroup::ApiDocs) {
+            tracing::info!("Swagger UI available at {}/swagger-ui", base_url);
+        }
+        if self.endpoints.contains(&EndpointGroup::Admin) {
+            tracing::info!("Admin dashboard at {}/admin", admin_base_url);
+        }
+        if self.endpoints.contains(&EndpointGroup::Observability) {
+            tracing::info!("Metrics endpoint at {}/metrics", admin_base_url);
+        }
+
+        let (public, admin) = match admin_address {
+            Some(address) => {
+                let (public, admin) = self.split_admin_endpoints();
+                (public, Some((admin, address)))
+            }
+            None => (self, None),
+        };
+
+        let admin_server = match admin {
+            Some((admin, address)) => {
+                // Probes, scrapes and operators only.
+                let server = HttpServer::new(move || admin.app()).workers(1);
+                let server = match &tls {
+                    Some(tls) => server
+                        .on_connect(crate::tls::record_peer_certificates)
+                        .bind_rustls_0_23(&address, tls.clone())?,
+                    None => server.bind(&address)?,
+                };
+                Some(server.run())
+            }
+            None => None,
+        };
+
+        let server = public.listen(&listeners, tls)?;
+        let admin_handle = admin_server.map(|admin_server| {
+            let handle = admin_server.handle();
+            actix_web::rt::spawn(async move {
+                if let Err(err) = admin_server.await {
+                    tracing::error!(error = %err, "Admin listener stopped");
+                }
+            });
+            handle
+        });
+        let result = server.await;
+        if let Some(handle) = admin_handle {
+            handle.stop(true).await;
+        }
+        result
+    }
+
+    /// Bind the listeners [`Self::run`] would and return the server without waiting for
+    /// it, e.g. to keep its [`handle`](Server::handle) for a programmatic shutdown.
+    ///
+    /// Every selected endpoint group is served on those listeners, so this refuses a
+    /// config with `server.admin_port`; use [`Self::run`] or
+    /// [`ServerBuilder::disable_admin`] there.
+    pub fn bind(self) -> std::io::Result<Server> {
+        if let Some(address) = self.config.server.admin_address() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("server.admin_port ({address}) is only served by OAuth2Server::run"),
+            ));
+        }
+        let listeners = self.config.server.effective_listeners();
+        for listener in &listeners {
+            listener_url(listener, false)?;
+        }
+        let tls = self.tls_config()?;
+        self.listen(&listeners, tls)
+    }
+
+    /// [`Self::app`] bound to `listeners`, started but not awaited.
+    fn listen(
+        self,
+        listeners: &[ListenerConfig],
+        tls: Option<rustls::ServerConfig>,
+    ) -> std::io::Result<Server> {
+        let server = HttpServer::new(move || self.app());
+        let mut server = match tls {
+            Some(_) => server.on_connect(crate::tls::record_peer_certificates),
+            None => server,
+        };
+        for listener in listeners {
+            server = match (&listener.address, &listener.unix_socket) {
+                (Some(address), None) => match &tls {
+                    Some(tls) if listener.tls => server.bind_rustls_0_23(address, tls.clone())?,
+                    _ => server.bind(address)?,
+                },
+                #[cfg(unix)]
+                (None, Some(path)) => {
+                    let server = server.bind_uds(path)?;
+                    if let Some(mode) = &listener.unix_socket_mode {
+                        use std::os::unix::fs::PermissionsExt;
+                        let mode = unix_socket_mode(mode)?;
+                        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+                    }
+                    server
+                }
+                #[cfg(not(unix))]
+                (None, Some(_)) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::Unsupported,
+                        "server.listeners unix_socket is only supported on Unix",
+                    ))
+                }
+                _ => unreachable!("checked by listener_url"),
+            };
+        }
+
+        Ok(server.run())
+    }
+}
+
+/// Endpoint groups served on `server.admin_port` instead of the public listeners.
+const ADMIN_LISTENER_GROUPS: [EndpointGroup; 2] =
+    [EndpointGroup::Admin, EndpointGroup::Observability];
+
+/// How `listener` is reached, for logs; rejects entries that are not exactly one socket.
+fn listener_url(listener: &ListenerConfig, tls: bool) -> std::io::Result<String> {
+    match (&listener.address, &listener.unix_socket) {
+        (Some(address), None) if tls && listener.tls => Ok(format!("https://{address}")),
+        (Some(address), None) => Ok(format!("http://{address}")),
+        (None, Some(path)) => Ok(format!("unix:{path}")),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "each server.listeners entry needs exactly one of address and unix_socket",
+        )),
+    }
+}
+
+#[cfg(unix)]
+fn unix_socket_mode(mode: &str) -> std::io::Result<u32> {
+    u32::from_str_radix(mode, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o777)
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("server.listeners unix_socket_mode must be octal, e.g. \"660\": {mode}"),
+            )
+        })
+}
+
+fn configure_oauth(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/oauth")
+            .app_data(registration_json_config())
+            .configure(oauth2_actix::mount::oauth_routes),
+    );
+    // Client management endpoints
+    cfg.service(
+        web::scope("/clients")
+            .app_data(registration_json_config())
+            .route(
+                "/register",
+                web::post().to(oauth2_actix::handlers::client::register_client),
+            ),
+    );
+}
+
+/// `/t/{tenant}/...`: the OAuth and discovery endpoints of each tenant.
+///
+/// Revocation lists, registration management and the admin API are not tenant-scoped.
+fn configure_tenants(cfg: &mut web::ServiceConfig, endpoints: &BTreeSet<EndpointGroup>) {
+    let mut scope = web::scope("/t/{tenant}").wrap(ResolveTenant);
+    if endpoints.contains(&EndpointGroup::OAuth) {
+        scope = scope
+            .app_data(registration_json_config())
+            .service(
+                web::scope("/oauth")
+                    .route(
+                        "/authorize",
+                        web::get().to(oauth2_actix::handlers::oauth::authorize),
+                    )
+                    .route(
+                        "/authorize",
+                        web::post().to(oauth2_actix::handlers::oauth::authorize_decision),
+                    )
+                    .route(
+                        "/token",
+                        web::post().to(oauth2_actix::handlers::oauth::token),
+                    )
+                    .route(
+                        "/introspect",
+                        web::post().to(oauth2_actix::handlers::token::introspect),
+                    )
+                    .route(
+                        "/revoke",
+                        web::post().to(oauth2_actix::handlers::token::revoke),
+                    )
+                    .route(
+                        "/logout",
+                        web::get().to(oauth2_actix::handlers::oauth::end_session),
+                    )
+                    .route(
+                        "/logout",
+                        web::post().to(oauth2_actix::handlers::oauth::end_session),
+                    )
+                    .route(
+                        "/register",
+                        web::post().to(oauth2_actix::handlers::client::register_client),
+                    ),
+            )
+            .route(
+                "/clients/register",
+                web::post().to(oauth2_actix::handlers::client::register_client),
+            );
+    }
+    if endpoints.contains(&EndpointGroup::Discovery) {
+        scope = scope.route(
+            "/.well-known/openid-configuration",
+            web::get().to(oauth2_actix::handlers::wellknown::openid_configuration),
+        );
+    }
+    cfg.service(scope);
+}
+
+fn configure_discovery(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/.well-known").route(
+        "/openid-configuration",
+        web::get().to(oauth2_actix::handlers::wellknown::openid_configuration),
+    ));
+}
+
+fn configure_login(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/auth")
+            .route(
+                "/login",
+                web::get().to(oauth2_social_login::handlers::auth::login_page),
+            )
+            .route(
+                "/logout",
+                web::post().to(oauth2_social_login::handlers::auth::logout),
+            )
+            .route(
+                "/success",
+                web::get().to(oauth2_social_login::handlers::auth::auth_success),
+            )
+            .route(
+                "/connections",
+                web::get().to(oauth2_social_login::handlers::link::connections),
+            )
+            .route(
+                "/sessions",
+                web::get().to(oauth2_actix::handlers::sessions::list_sessions),
+            )
+            .route(
+                "/sessions/{id}",
+                web::delete().to(oauth2_actix::handlers::sessions::revoke_session),
+            )
+            .service(
+                web::resource("/link")
+                    .route(web::get().to(oauth2_social_login::handlers::link::link_account_page))
+                    .route(web::post().to(oauth2_social_login::handlers::link::confirm_link)),
+            )
+            .service(
+                web::scope("/login")
+                    .route(
+                        "/google",
+                        web::get().to(oauth2_social_login::handlers::auth::google_login),
+                    )
+                    .route(
+                        "/microsoft",
+                        web::get().to(oauth2_social_login::handlers::auth::microsoft_login),
+                    )
+                    .route(
+                        "/github",
+                        web::get().to(oauth2_social_login::handlers::auth::github_login),
+                    )
+                    .route(
+                        "/azure",
+                        web::get().to(oauth2_social_login::handlers::auth::microsoft_login),
+                    ) // Azure uses Microsoft endpoint
+                    // NOTE: Okta and Auth0 handlers not yet implemented - buttons should be hidden in UI
+                    // or implement proper handlers in handlers::auth module
+                    .route(
+                        "/okta",
+                        web::get().to(|| async {
+                            actix_web::HttpResponse::ServiceUnavailable()
+                                .body("Okta login not yet implemented")
+                        }),
+                    )
+                    .route(
+                        "/auth0",
+                        web::get().to(|| async {
+                            actix_web::HttpResponse::ServiceUnavailable()
+                                .body("Auth0 login not yet implemented")
+                        }),
+                    )
+                    .route(
+                        "/apple",
+                        web::get().to(oauth2_social_login::handlers::auth::apple_login),
+                    )
+                    .route(
+                        "/gitlab",
+                        web::get().to(oauth2_social_login::handlers::auth::gitlab_login),
+                    )
+                    .route(
+                        "/discord",
+                        web::get().to(oauth2_social_login::handlers::auth::discord_login),
+                    )
+                    .route(
+                        "/linkedin",
+                        web::get().to(oauth2_social_login::handlers::auth::linkedin_login),
+                    )
+                    .route(
+                        "/facebook",
+                        web::get().to(oauth2_social_login::handlers::auth::facebook_login),
+                    )
+                    // Providers configured under `social.generic`.
+                    .route(
+                        "/{provider}",
+                        web::get().to(oauth2_social_login::handlers::auth::generic_login),
+                    ),
+            )
+            .service(
+                web::resource("/callback/{provider}")
+                    .route(web::get().to(oauth2_social_login::handlers::auth::auth_callback))
+                    // Apple posts its callback (`response_mode=form_post`).
+                    .route(web::post().to(oauth2_social_login::handlers::auth::auth_callback_form)),
+            ),
+    );
+}
+
+fn configure_admin(cfg: &mut web::ServiceConfig) {
+    let viewer = RequireAdminRole::new(AdminRole::Viewer);
+    let operator = RequireAdminRole::new(AdminRole::Operator);
+    let security_admin = RequireAdminRole::new(AdminRole::SecurityAdmin);
+
+    cfg.service(
+        web::scope("/admin")
+            // Outermost, so writes rejected by the role checks are recorded too.
+            .wrap(AuditAdminChanges)
+            // Static page; the data it shows comes from the role-checked API below.
+            .route("", web::get().to(admin_dashboard))
+            .service(
+                web::resource("/config")
+                    .wrap(viewer)
+                    .route(web::get().to(oauth2_actix::handlers::admin::effective_config)),
+            )
+            .service(
+                web::resource("/audit")
+                    .wrap(security_admin)
+                    .route(web::get().to(oauth2_actix::handlers::audit::list_audit_records)),
+            )
+            .service(
+                web::scope("/events")
+                    .wrap(security_admin)
+                    .route(
+                        "/stream",
+                        web::get().to(oauth2_actix::handlers::events::stream),
+                    )
+                    .route(
+                        "/replay",
+                        web::post().to(oauth2_actix::handlers::events::replay),
+                    ),
+            )
+            .service(
+                web::resource("/diagnose/token")
+                    .wrap(operator)
+                    .route(web::post().to(oauth2_actix::handlers::diagnose::diagnose_token)),
+            )
+            .service(
+                web::scope("/clients/{client_id}/redirect-uris")
+                    .wrap(security_admin)
+                    .route(
+                        "/approve",
+                        web::post().to(oauth2_actix::handlers::admin::approve_redirect_uris),
+                    )
+                    .route(
+                        "/reject",
+                        web::post().to(oauth2_actix::handlers::admin::reject_redirect_uris),
+                    ),
+            )
+            .service(
+                web::resource("/tenants")
+                    .wrap(viewer.writes(AdminRole::SecurityAdmin))
+                    .route(web::get().to(oauth2_actix::handlers::admin::list_tenants))
+                    .route(web::post().to(oauth2_actix::handlers::admin::create_tenant)),
+            )
+            .service(
+                web::scope("/resource-servers")
+                    .wrap(security_admin)
+                    .route(
+                        "",
+                        web::post().to(oauth2_actix::handlers::admin::create_resource_server),
+                    )
+                    .route(
+                        "/{client_id}",
+                        web::delete().to(oauth2_actix::handlers::admin::delete_resource_server),
+                    ),
+            )
+            .service(
+                web::scope("/users")
+                    .wrap(viewer.writes(AdminRole::Operator))
+                    .route("", web::get().to(oauth2_actix::handlers::admin::list_users))
+                    .route(
+                        "",
+                        web::post().to(oauth2_actix::handlers::admin::create_user),
+                    )
+                    .route(
+                        "/{id}",
+                        web::get().to(oauth2_actix::handlers::admin::get_user),
+                    )
+                    .route(
+                        "/{id}",
+                        web::delete().to(oauth2_actix::handlers::admin::delete_user),
+                    )
+                    .route(
+                        "/{id}/enable",
+                        web::post().to(oauth2_actix::handlers::admin::enable_user),
+                    )
+                    .route(
+                        "/{id}/disable",
+                        web::post().to(oauth2_actix::handlers::admin::disable_user),
+                    )
+                    .route(
+                        "/{id}/password",
+                        web::put().to(oauth2_actix::handlers::admin::change_user_password),
+                    )
+                    .route(
+                        "/{id}/tokens",
+                        web::get().to(oauth2_actix::handlers::admin::list_user_tokens),
+                    )
+                    .route(
+                        "/{id}/roles",
+                        web::get().to(oauth2_actix::handlers::admin::get_user_roles),
+                    )
+                    .route(
+                        "/{id}/roles",
+                        web::put().to(oauth2_actix::handlers::admin::set_user_roles),
+                    )
+                    .route(
+                        "/{id}/groups",
+                        web::get().to(oauth2_actix::handlers::admin::get_user_groups),
+                    )
+                    .route(
+                        "/{id}/groups",
+                        web::put().to(oauth2_actix::handlers::admin::set_user_groups),
+                    ),
+            )
+            .service(
+                web::scope("/roles")
+                    .wrap(viewer.writes(AdminRole::Operator))
+                    .route("", web::get().to(oauth2_actix::handlers::admin::list_roles))
+                    .route(
+                        "",
+                        web::post().to(oauth2_actix::handlers::admin::create_role),
+                    )
+                    .route(
+                        "/{name}",
+                        web::delete().to(oauth2_actix::handlers::admin::delete_role),
+                    ),
+            )
+            .service(
+                web::scope("/groups")
+                    .wrap(viewer.writes(AdminRole::Operator))
+                    .route(
+                        "",
+                        web::get().to(oauth2_actix::handlers::admin::list_groups),
+                    )
+                    .route(
+                        "",
+                        web::post().to(oauth2_actix::handlers::admin::create_group),
+                    )
+                    .route(
+                        "/{name}",
+                        web::delete().to(oauth2_actix::handlers::admin::delete_group),
+                    ),
+            )
+            .service(
+                web::scope("/service-accounts")
+                    .wrap(viewer.writes(AdminRole::Operator))
+                    .route(
+                        "",
+                        web::get().to(oauth2_actix::handlers::admin::list_service_accounts),
+                    )
+                    .route(
+                        "",
+                        web::post().to(oauth2_actix::handlers::admin::create_service_account),
+                    )
+                    .route(
+                        "/{id}",
+                        web::get().to(oauth2_actix::handlers::admin::get_service_account),
+                    )
+                    .route(
+                        "/{id}",
+                        web::patch().to(oauth2_actix::handlers::admin::update_service_account),
+                    )
+                    .route(
+                        "/{id}",
+                        web::delete().to(oauth2_actix::handlers::admin::delete_service_account),
+                    )
+                    .route(
+                        "/{id}/enable",
+                        web::post().to(oauth2_actix::handlers::admin::enable_service_account),
+                    )
+                    .route(
+                        "/{id}/disable",
+                        web::post().to(oauth2_actix::handlers::admin::disable_service_account),
+                    ),
+            )
+            .service(
+                web::scope("/api")
+                    .wrap(viewer.writes(AdminRole::Operator))
+                    .route(
+                        "/dashboard",
+                        web::get().to(oauth2_actix::handlers::admin::dashboard),
+                    )
+                    .route(
+                        "/clients",
+                        web::get().to(oauth2_actix::handlers::admin::list_clients),
+                    )
+                    .route(
+                        "/clients/{id}/tokens",
+                        web::get().to(oauth2_actix::handlers::admin::list_client_tokens),
+                    )
+                    .route(
+                        "/tokens",
+                        web::get().to(oauth2_actix::handlers::admin::list_tokens),
+                    )
+                    .service(web::resource("/tokens/revoke").wrap(security_admin).route(
+                        web::post().to(oauth2_actix::handlers::admin::revoke_tokens_by_metadata),
+                    ))
+                    .service(
+                        web::resource("/tokens/{id}/revoke")
+                            .wrap(security_admin)
+                            .route(
+                                web::post().to(oauth2_actix::handlers::admin::admin_revoke_token),
+                            ),
+                    )
+                    .route(
+                        "/clients/{id}",
+                        web::delete().to(oauth2_actix::handlers::admin::delete_client),
+                    ),
+            ),
+    );
+}
+
+fn configure_observability(cfg: &mut web::ServiceConfig) {
+    cfg.route(
+        "/health",
+        web::get().to(oauth2_actix::handlers::admin::health),
+    )
+    .route(
+        "/ready",
+        web::get().to(oauth2_actix::handlers::admin::readiness),
+    )
+    .service(
+        web::scope("/health")
+            .route(
+                "/live",
+                web::get().to(oauth2_actix::handlers::admin::liveness),
+            )
+            .route(
+                "/ready",
+                web::get().to(oauth2_actix::handlers::admin::readiness),
+            )
+            .route(
+                "/startup",
+                web::get().to(oauth2_actix::handlers::admin::startup),
+            ),
+    )
+    .route(
+        "/version",
+        web::get().to(oauth2_actix::handlers::admin::version),
+    )
+    .route(
+        "/metrics",
+        web::get().to(oauth2_actix::handlers::admin::system_metrics),
+    );
+}
+
+fn configure_events(cfg: &mut web::ServiceConfig, require_admin: bool) {
+    cfg.service(
+        web::scope("/events")
+            // Producers are authenticated by the handler, as signatures cover the raw body.
+            .route(
+                "/ingest",
+                web::post().to(oauth2_actix::handlers::events::ingest),
+            )
+            .service(
+                web::resource("/health")
+                    .wrap(actix_middleware::Condition::new(
+                        require_admin,
+                        RequireAdminRole::new(AdminRole::Viewer),
+                    ))
+                    .route(web::get().to(oauth2_actix::handlers::events::health)),
+            ),
+    );
+}
+
+fn configure_ui(cfg: &mut web::ServiceConfig) {
+    cfg.route(
+        "/",
+        web::get().to(|| async {
+            HttpResponse::Found()
+                .append_header(("Location", "/auth/login"))
+                .finish()
+        }),
+    )
+    // Error page
+    .route("/error", web::get().to(error_page))
+    // Static files
+    .service(Files::new("/static", "./static"));
+}
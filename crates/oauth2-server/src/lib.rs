@@ -1,16 +1,27 @@
-use actix::Actor;
-use actix_cors::Cors;
-use actix_files::Files;
-use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+//! Server assembly: configuration, storage, actors, event system and routes.
+//!
+//! [`run`] starts the standalone server. Applications that want to host the endpoints in
+//! their own actix `App` use [`ServerBuilder`] instead.
+
 use actix_web::body::MessageBody;
+use actix_web::cookie::Key;
 use actix_web::dev::{ServiceRequest, ServiceResponse};
-use actix_web::{cookie::Key, middleware as actix_middleware, web, App, HttpResponse, HttpServer};
-use oauth2_openapi::ApiDoc;
+use actix_web::HttpResponse;
+use std::collections::BTreeMap;
 use std::sync::Arc;
-use std::time::Duration;
-use tracing_actix_web::{DefaultRootSpanBuilder, RootSpanBuilder, TracingLogger};
-use utoipa::OpenApi;
-use utoipa_swagger_ui::SwaggerUi;
+use tracing_actix_web::{DefaultRootSpanBuilder, RootSpanBuilder};
+
+mod builder;
+pub mod reconcile;
+pub mod seed;
+mod tls;
+
+mod built_info {
+    include!(concat!(env!("OUT_DIR"), "/built.rs"));
+}
+
+pub use builder::{EndpointGroup, OAuth2Server, ServerBuilder};
+pub use tls::PeerCertificates;
 
 #[derive(Clone, Copy)]
 struct OtelRootSpanBuilder;
@@ -21,6 +32,12 @@ impl RootSpanBuilder for OtelRootSpanBuilder {
         // We then populate both trace_id and span_id using the active OpenTelemetry context.
         let span = tracing_actix_web::root_span!(request, span_id = tracing::field::Empty);
         oauth2_observability::annotate_span_with_trace_ids(&span);
+        // The default believes X-Forwarded-For from any peer; only trusted proxies count.
+        let client_ip = oauth2_core::TrustedProxies::client_ip_of(request.request());
+        span.record(
+            "http.client_ip",
+            client_ip.map(|ip| ip.to_string()).unwrap_or_default(),
+        );
         span
     }
 
@@ -28,6 +45,20 @@ impl RootSpanBuilder for OtelRootSpanBuilder {
         span: tracing::Span,
         outcome: &Result<ServiceResponse<B>, actix_web::Error>,
     ) {
+        // The root span is opened before routing, so `http.route` is only known now.
+        // Record the matched route template so span names stay low-cardinality.
+        if let Ok(response) = outcome {
+            let request = response.request();
+            let route = request.match_pattern();
+            span.record("http.route", route.as_deref().unwrap_or("default"));
+            span.record(
+                "otel.name",
+                oauth2_observability::semconv::http_span_name(
+                    request.method().as_str(),
+                    route.as_deref(),
+                ),
+            );
+        }
         DefaultRootSpanBuilder::on_request_end(span, outcome);
     }
 }
@@ -42,16 +73,40 @@ fn parse_event_types(event_type_strings: &[String]) -> Vec<oauth2_events::EventT
             "authorization_code_created" => Some(EventType::AuthorizationCodeCreated),
             "authorization_code_validated" => Some(EventType::AuthorizationCodeValidated),
             "authorization_code_expired" => Some(EventType::AuthorizationCodeExpired),
+            "authorization_code_context_mismatch" => {
+                Some(EventType::AuthorizationCodeContextMismatch)
+            }
+            "authorization_code_replay_detected" => {
+                Some(EventType::AuthorizationCodeReplayDetected)
+            }
+            "pkce_downgrade_attempt" => Some(EventType::PkceDowngradeAttempt),
+            "invalid_redirect_uri_attempt" => Some(EventType::InvalidRedirectUriAttempt),
             "token_created" => Some(EventType::TokenCreated),
             "token_validated" => Some(EventType::TokenValidated),
             "token_revoked" => Some(EventType::TokenRevoked),
             "token_expired" => Some(EventType::TokenExpired),
+            "refresh_token_context_mismatch" => Some(EventType::RefreshTokenContextMismatch),
             "client_registered" => Some(EventType::ClientRegistered),
             "client_validated" => Some(EventType::ClientValidated),
             "client_deleted" => Some(EventType::ClientDeleted),
+            "client_redirect_uris_change_requested" => {
+                Some(EventType::ClientRedirectUrisChangeRequested)
+            }
+            "client_redirect_uris_changed" => Some(EventType::ClientRedirectUrisChanged),
+            "client_redirect_uris_change_rejected" => {
+                Some(EventType::ClientRedirectUrisChangeRejected)
+            }
+            "client_authentication_failed" => Some(EventType::ClientAuthenticationFailed),
             "user_authenticated" => Some(EventType::UserAuthenticated),
             "user_authentication_failed" => Some(EventType::UserAuthenticationFailed),
+            "login_failed" => Some(EventType::LoginFailed),
             "user_logout" => Some(EventType::UserLogout),
+            "email_verification_sent" => Some(EventType::EmailVerificationSent),
+            "email_verified" => Some(EventType::EmailVerified),
+            "password_reset_requested" => Some(EventType::PasswordResetRequested),
+            "password_reset_completed" => Some(EventType::PasswordResetCompleted),
+            "magic_link_sent" => Some(EventType::MagicLinkSent),
+            "rate_limit_exceeded" => Some(EventType::RateLimitExceeded),
             _ => {
                 tracing::warn!("Unknown event type in config: {}", s);
                 None
@@ -60,494 +115,1004 @@ fn parse_event_types(event_type_strings: &[String]) -> Vec<oauth2_events::EventT
         .collect()
 }
 
-pub async fn run() -> std::io::Result<()> {
-    // Initialize telemetry and tracing
-    oauth2_observability::init_telemetry("oauth2_server").unwrap_or_else(|e| {
-        eprintln!("Failed to initialize telemetry: {}", e);
-        // Fall back to basic logging
-        env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
-    });
+fn compression_from_config(
+    compression: &oauth2_config::CompressionConfig,
+) -> oauth2_actix::middleware::compression::ResponseCompression {
+    use actix_web::http::header::ContentEncoding;
+    use oauth2_config::CompressionAlgorithm;
 
-    tracing::info!("Starting OAuth2 Server...");
+    let encodings = compression
+        .algorithms
+        .iter()
+        .filter(|_| compression.enabled)
+        .map(|algorithm| match algorithm {
+            CompressionAlgorithm::Brotli => ContentEncoding::Brotli,
+            CompressionAlgorithm::Gzip => ContentEncoding::Gzip,
+        });
+    compression.exclude_paths.iter().fold(
+        oauth2_actix::middleware::compression::ResponseCompression::new(encodings)
+            .with_min_size(compression.min_size_bytes as u64),
+        |compression, prefix| compression.exclude_path(prefix.clone()),
+    )
+}
 
-    // Load configuration
-    let config = oauth2_config::Config::default();
+/// Cargo features this server binary was compiled with.
+fn compiled_features() -> BTreeMap<String, bool> {
+    [
+        ("sqlx", cfg!(feature = "sqlx")),
+        ("mongo", cfg!(feature = "mongo")),
+        ("events-redis", cfg!(feature = "events-redis")),
+        ("events-kafka", cfg!(feature = "events-kafka")),
+        ("events-rabbit", cfg!(feature = "events-rabbit")),
+        ("events-sns", cfg!(feature = "events-sns")),
+        ("events-pubsub", cfg!(feature = "events-pubsub")),
+        ("cache-redis", cfg!(feature = "cache-redis")),
+        ("reconcile-kube", cfg!(feature = "reconcile-kube")),
+        ("saml", cfg!(feature = "saml")),
+        ("ldap", cfg!(feature = "ldap")),
+        ("mail-smtp", cfg!(feature = "mail-smtp")),
+        ("secrets-vault", cfg!(feature = "secrets-vault")),
+        ("secrets-aws", cfg!(feature = "secrets-aws")),
+    ]
+    .into_iter()
+    .map(|(name, enabled)| (name.to_string(), enabled))
+    .collect()
+}
 
-    if std::env::var("OAUTH2_DEBUG_CONFIG").ok().as_deref() == Some("1") {
-        if let Ok(cfg_json) = serde_json::to_string_pretty(&config.sanitized()) {
-            tracing::info!(config = %cfg_json, "Loaded configuration (sanitized)");
-        }
+/// Version, commit and build details of this binary, as served at `GET /version`.
+pub fn build_info() -> oauth2_observability::BuildInfo {
+    oauth2_observability::BuildInfo {
+        version: built_info::PKG_VERSION.to_string(),
+        git_sha: env!("OAUTH2_GIT_SHA").to_string(),
+        built_at: chrono::DateTime::parse_from_rfc2822(built_info::BUILT_TIME_UTC)
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_else(|_| built_info::BUILT_TIME_UTC.to_string()),
+        rustc_version: built_info::RUSTC_VERSION.to_string(),
+        profile: built_info::PROFILE.to_string(),
+        features: compiled_features()
+            .into_iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| name)
+            .collect(),
     }
+}
 
-    // Validate configuration for production
-    if let Err(e) = config.validate_for_production() {
-        tracing::warn!("Configuration validation warning: {}", e);
-        tracing::warn!("This configuration should only be used for testing!");
+/// Log which config source won and what was resolved from it, so operators can tell at a glance.
+fn log_startup_banner(effective: &oauth2_config::EffectiveConfig) {
+    let source = match &effective.source {
+        oauth2_config::ConfigSource::Hocon {
+            path,
+            profile: Some(profile),
+            ..
+        } => format!("hocon:{path}+{profile}"),
+        oauth2_config::ConfigSource::Hocon { path, .. } => format!("hocon:{path}"),
+        oauth2_config::ConfigSource::Environment { .. } => "environment".to_string(),
+        oauth2_config::ConfigSource::Programmatic => "programmatic".to_string(),
+    };
+    let enabled_features: Vec<&str> = effective
+        .features
+        .iter()
+        .filter(|(_, enabled)| **enabled)
+        .map(|(name, _)| name.as_str())
+        .collect();
+
+    tracing::info!(
+        config_source = %source,
+        storage = %effective.backends.storage,
+        events = ?effective.backends.events,
+        cache = %effective.backends.cache,
+        features = ?enabled_features,
+        "Effective configuration resolved"
+    );
+    if let Ok(json) = serde_json::to_string(effective) {
+        tracing::info!(effective_config = %json, "Effective configuration (sanitized)");
     }
+}
 
-    tracing::info!("Configuration loaded");
+/// Where `/events/ingest` remembers idempotency keys beyond the local map. Unset, Redis is
+/// used when `cache.redis_url` is set. Any failure degrades to per-replica state.
+async fn ingest_idempotency_backend_from_config(
+    config: &oauth2_config::Config,
+) -> Option<oauth2_ports::DynIdempotencyBackend> {
+    use oauth2_config::IdempotencyStoreBackend;
 
-    // Load social login configuration from HOCON config or environment
-    let social_config = if let Some(ref social) = config.social {
-        Arc::new(oauth2_social_login::SocialLoginConfig::from_config_social(
-            social,
-        ))
-    } else {
-        Arc::new(oauth2_social_login::SocialLoginConfig::from_env())
-    };
-    tracing::info!("Social login configuration loaded");
+    let redis_url = config
+        .cache
+        .as_ref()
+        .and_then(|cache| Some((cache.redis_url.as_deref()?, cache.key_prefix.clone())));
+    let backend = config
+        .events
+        .ingest
+        .idempotency_store
+        .unwrap_or(match redis_url {
+            Some(_) => IdempotencyStoreBackend::Redis,
+            None => IdempotencyStoreBackend::Memory,
+        });
+    match (backend, redis_url) {
+        (IdempotencyStoreBackend::Memory, _) => None,
+        #[cfg(feature = "cache-redis")]
+        (IdempotencyStoreBackend::Redis, Some((url, prefix))) => {
+            match oauth2_cache_redis::RedisIdempotencyBackend::connect(url, prefix).await {
+                Ok(redis) => {
+                    tracing::info!("Redis idempotency store enabled for event ingest");
+                    Some(Arc::new(redis))
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "Redis idempotency store init failed; falling back to local store");
+                    None
+                }
+            }
+        }
+        #[cfg(not(feature = "cache-redis"))]
+        (IdempotencyStoreBackend::Redis, Some(_)) => {
+            tracing::warn!(
+                "events.ingest.idempotency_store = redis but feature 'cache-redis' is not enabled; falling back to local store"
+            );
+            None
+        }
+        (IdempotencyStoreBackend::Redis, None) => {
+            tracing::warn!(
+                "events.ingest.idempotency_store = redis but cache.redis_url is not set; falling back to local store"
+            );
+            None
+        }
+    }
+}
 
-    // Initialize metrics
-    let metrics = oauth2_observability::Metrics::new().expect("Failed to initialize metrics");
-    tracing::info!("Metrics initialized");
+/// Where social logins keep their state until the callback. A Redis store that cannot be
+/// used falls back to the database.
+async fn social_state_store_from_config(
+    config: &oauth2_config::Config,
+    storage: oauth2_ports::DynStorage,
+) -> oauth2_ports::DynStateStore {
+    use oauth2_config::SocialStateStore;
 
-    // Initialize storage backend (SQLx by default, optional MongoDB)
-    tracing::info!(database_url = %config.database.url, "Connecting to storage backend");
-    let storage = oauth2_storage_factory::create_storage(&config.database.url)
-        .await
-        .expect("Failed to create storage backend");
+    let backend = config
+        .social
+        .as_ref()
+        .map(|social| social.state_store)
+        .unwrap_or_default();
+    match backend {
+        SocialStateStore::Database => {}
+        SocialStateStore::Memory => {
+            return Arc::new(oauth2_social_login::InMemoryStateStore::new());
+        }
+        SocialStateStore::Redis => {
+            let redis_url = config
+                .cache
+                .as_ref()
+                .and_then(|cache| Some((cache.redis_url.as_deref()?, cache.key_prefix.clone())));
+            match redis_url {
+                #[cfg(feature = "cache-redis")]
+                Some((url, prefix)) => {
+                    match oauth2_cache_redis::RedisStateStore::connect(url, prefix).await {
+                        Ok(redis) => return Arc::new(redis),
+                        Err(e) => tracing::warn!(
+                            error = %e,
+                            "Redis social login state store init failed; using the database"
+                        ),
+                    }
+                }
+                #[cfg(not(feature = "cache-redis"))]
+                Some(_) => tracing::warn!(
+                    "social.state_store = redis but feature 'cache-redis' is not enabled; using the database"
+                ),
+                None => tracing::warn!(
+                    "social.state_store = redis but cache.redis_url is not set; using the database"
+                ),
+            }
+        }
+    }
+    Arc::new(oauth2_social_login::StorageStateStore::new(storage))
+}
 
-    storage
-        .init()
-        .await
-        .expect("Failed to initialize storage backend");
-    tracing::info!("Storage backend initialized");
-    let jwt_secret = config.jwt.secret.clone();
-
-    // Load session key from environment or generate a new one
-    // In production, OAUTH2_SESSION_KEY should be set to a persistent value
-    let session_key = if let Ok(key_str) = std::env::var("OAUTH2_SESSION_KEY") {
-        if key_str.len() < 64 {
-            panic!("OAUTH2_SESSION_KEY must be at least 64 characters (128 hex digits)");
-        }
-        let key_bytes =
-            hex::decode(&key_str).expect("OAUTH2_SESSION_KEY must be valid hexadecimal");
-        Key::try_from(&key_bytes[..]).expect("OAUTH2_SESSION_KEY must be exactly 64 bytes")
-    } else if let Some(key_str) = config
+async fn session_store_from_config(
+    config: &oauth2_config::Config,
+    storage: oauth2_ports::DynStorage,
+) -> oauth2_ports::DynUserSessionStore {
+    use oauth2_config::SessionStoreBackend;
+
+    let backend = config
         .session
         .as_ref()
-        .and_then(|session| session.key.as_ref())
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty())
-    {
-        if key_str.len() < 64 {
-            panic!("OAUTH2_SESSION_KEY must be at least 64 characters (128 hex digits)");
+        .map(|session| session.store)
+        .unwrap_or_default();
+    match backend {
+        SessionStoreBackend::Database => {}
+        SessionStoreBackend::Memory => {
+            return Arc::new(oauth2_actix::session::InMemorySessionStore::new());
         }
-        let key_bytes =
-            hex::decode(&key_str).expect("OAUTH2_SESSION_KEY must be valid hexadecimal");
-        Key::try_from(&key_bytes[..]).expect("OAUTH2_SESSION_KEY must be exactly 64 bytes")
-    } else {
-        tracing::warn!("OAUTH2_SESSION_KEY not set. Generating random key. Sessions will not persist across restarts!");
-        Key::generate()
+        SessionStoreBackend::Redis => {
+            let redis_url = config
+                .cache
+                .as_ref()
+                .and_then(|cache| Some((cache.redis_url.as_deref()?, cache.key_prefix.clone())));
+            match redis_url {
+                #[cfg(feature = "cache-redis")]
+                Some((url, prefix)) => {
+                    match oauth2_cache_redis::RedisSessionStore::connect(url, prefix).await {
+                        Ok(redis) => return Arc::new(redis),
+                        Err(e) => tracing::warn!(
+                            error = %e,
+                            "Redis session store init failed; using the database"
+                        ),
+                    }
+                }
+                #[cfg(not(feature = "cache-redis"))]
+                Some(_) => tracing::warn!(
+                    "session.store = redis but feature 'cache-redis' is not enabled; using the database"
+                ),
+                None => tracing::warn!(
+                    "session.store = redis but cache.redis_url is not set; using the database"
+                ),
+            }
+        }
+    }
+    Arc::new(oauth2_actix::session::StorageSessionStore::new(storage))
+}
+
+/// The SAML service provider for the enabled `saml.idps`, if there are any. Metadata that
+/// cannot be loaded fails startup.
+#[cfg(feature = "saml")]
+fn saml_from_config(
+    config: &oauth2_config::Config,
+) -> std::io::Result<Option<oauth2_saml::SamlServiceProvider>> {
+    let Some(saml) = config
+        .saml
+        .as_ref()
+        .filter(|saml| saml.idps.values().any(|idp| idp.enabled))
+    else {
+        return Ok(None);
+    };
+    let provider = oauth2_saml::SamlServiceProvider::from_config(saml)
+        .map_err(|e| std::io::Error::other(format!("Invalid SAML configuration: {e}")))?;
+    tracing::info!(
+        idps = ?provider.idp_names().collect::<Vec<_>>(),
+        "SAML identity providers loaded"
+    );
+    Ok(Some(provider))
+}
+
+/// The LDAP authenticator, if `ldap.enabled`. Invalid settings fail startup; an
+/// unreachable directory does not, logins fail until it is back.
+#[cfg(feature = "ldap")]
+fn ldap_from_config(
+    config: &oauth2_config::Config,
+    storage: oauth2_ports::DynStorage,
+) -> std::io::Result<Option<Arc<oauth2_ldap::LdapAuthenticator>>> {
+    let Some(ldap) = config.ldap.as_ref().filter(|ldap| ldap.enabled) else {
+        return Ok(None);
     };
+    let authenticator = oauth2_ldap::LdapAuthenticator::from_config(ldap, storage)
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    tracing::info!(url = %ldap.url, local_fallback = ldap.local_fallback, "LDAP authentication enabled");
+    Ok(Some(Arc::new(authenticator)))
+}
+
+/// The mail service, if `mail.enabled`. Mail goes to `mailer` when given, else the
+/// `mail.smtp` relay, else the log. An invalid relay setting, or no `server.issuer` to
+/// build the links on, fails startup.
+fn mail_from_config(
+    config: &oauth2_config::Config,
+    storage: oauth2_ports::DynStorage,
+    mailer: Option<oauth2_mail::DynMailer>,
+) -> std::io::Result<Option<oauth2_mail::MailService>> {
+    let Some(mail) = config.mail.as_ref().filter(|mail| mail.enabled) else {
+        return Ok(None);
+    };
+    let Some(issuer) = config.server.issuer.as_deref() else {
+        return Err(std::io::Error::other(
+            "mail.enabled needs server.issuer: links in mail are built on it",
+        ));
+    };
+    let mailer = match (mailer, &mail.smtp) {
+        (Some(mailer), _) => mailer,
+        #[cfg(feature = "mail-smtp")]
+        (None, Some(smtp)) => {
+            let mailer = oauth2_mail::SmtpMailer::from_config(smtp, &mail.from)
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            tracing::info!(host = %smtp.host, port = smtp.port, "Sending mail through SMTP");
+            Arc::new(mailer)
+        }
+        #[cfg(not(feature = "mail-smtp"))]
+        (None, Some(_)) => {
+            tracing::warn!(
+                "mail.smtp is configured but feature 'mail-smtp' is not enabled; logging mail instead"
+            );
+            Arc::new(oauth2_mail::LogMailer)
+        }
+        (None, None) => {
+            tracing::warn!("mail.smtp is not set; mail is logged, not sent");
+            Arc::new(oauth2_mail::LogMailer)
+        }
+    };
+    Ok(Some(
+        oauth2_mail::MailService::new(mail, storage, mailer).with_issuer(issuer),
+    ))
+}
 
-    // Initialize event system first
-    let event_actor = if config.events.enabled {
-        use oauth2_events::{ConsoleEventLogger, EventFilter, InMemoryEventLogger};
+/// The enricher issuing assigned roles and groups in access tokens, if `roles.enabled`.
+fn roles_from_config(
+    config: &oauth2_config::Config,
+    storage: oauth2_ports::DynStorage,
+) -> std::io::Result<Option<oauth2_ports::DynClaimsEnricher>> {
+    if !config.roles.enabled {
+        return Ok(None);
+    }
+    let claim = |name: &str| (!name.is_empty()).then(|| name.to_string());
+    let enricher = oauth2_ports::RoleClaimsEnricher::new(
+        storage,
+        claim(&config.roles.roles_claim),
+        claim(&config.roles.groups_claim),
+    )
+    .map_err(|e| std::io::Error::other(format!("Invalid roles configuration: {e}")))?;
+    Ok(Some(Arc::new(enricher)))
+}
 
-        // Parse event filter from config
-        let filter = match config.events.filter_mode.as_str() {
-            "include" => {
-                let event_types = parse_event_types(&config.events.event_types);
-                EventFilter::include_only(event_types)
+/// The enricher issuing the templated `claims`, if any are configured.
+fn claims_mapper_from_config(
+    config: &oauth2_config::Config,
+    storage: oauth2_ports::DynStorage,
+) -> std::io::Result<Option<oauth2_ports::DynClaimsEnricher>> {
+    if config.claims.is_empty() {
+        return Ok(None);
+    }
+    let rules = config
+        .claims
+        .iter()
+        .map(|(claim, mapping)| {
+            let (template, scope) = match mapping {
+                oauth2_config::ClaimMapping::Template(template) => (template, None),
+                oauth2_config::ClaimMapping::Rule { value, scope } => (value, scope.clone()),
+            };
+            oauth2_ports::ClaimRule {
+                claim: claim.clone(),
+                template: template.clone(),
+                scope,
             }
-            "exclude" => {
-                let event_types = parse_event_types(&config.events.event_types);
-                EventFilter::exclude_events(event_types)
+        })
+        .collect();
+    let mapper = oauth2_ports::ClaimsMapper::new(storage, rules)
+        .map_err(|e| std::io::Error::other(format!("Invalid claims configuration: {e}")))?;
+    tracing::info!(claims = ?config.claims.keys().collect::<Vec<_>>(), "Custom claims mapped");
+    Ok(Some(Arc::new(mapper)))
+}
+
+/// Connect the channel that keeps storage caches coherent across replicas, if a shared
+/// Redis is configured. Any failure leaves each replica's cache to expire on its own.
+async fn storage_cache_invalidation_from_config(
+    config: &oauth2_config::Config,
+) -> Option<oauth2_ports::DynInvalidationChannel> {
+    let cache = config.cache.as_ref()?;
+    let url = cache.redis_url.as_deref()?;
+
+    #[cfg(feature = "cache-redis")]
+    {
+        match oauth2_cache_redis::RedisInvalidationChannel::connect(
+            url,
+            cache.key_prefix.clone(),
+            "storage-cache-invalidation",
+        )
+        .await
+        {
+            Ok(channel) => {
+                tracing::info!("Storage cache invalidation over Redis pub/sub enabled");
+                Some(Arc::new(channel))
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "Redis invalidation channel init failed; storage cache entries expire per replica");
+                None
+            }
+        }
+    }
+    #[cfg(not(feature = "cache-redis"))]
+    {
+        let _ = url;
+        tracing::warn!(
+            "Shared cache configured but feature 'cache-redis' is not enabled; storage cache entries expire per replica"
+        );
+        None
+    }
+}
+
+/// Create the tenants declared in `tenancy.tenants` that storage does not know yet.
+///
+/// Existing tenants are left alone, so secrets rotated through storage are not reverted.
+async fn seed_tenants_from_config(
+    storage: &oauth2_ports::DynStorage,
+    tenancy: &oauth2_config::TenancyConfig,
+) -> std::io::Result<()> {
+    for declared in &tenancy.tenants {
+        oauth2_core::Tenant::validate_id(&declared.id).map_err(|e| {
+            std::io::Error::other(format!("tenancy.tenants '{}': {e}", declared.id))
+        })?;
+        let existing = storage
+            .get_tenant(&declared.id)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        if existing.is_none() {
+            let tenant = oauth2_core::Tenant::new(
+                declared.id.clone(),
+                declared.name.clone().unwrap_or_else(|| declared.id.clone()),
+                declared.signing_secret.clone(),
+            );
+            storage
+                .save_tenant(&tenant)
+                .await
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            tracing::info!(tenant = %tenant.id, "Tenant created from configuration");
+        }
+    }
+    Ok(())
+}
+
+/// Kafka publisher with the partition key and serialization from `events.kafka`,
+/// registering the envelope schema when no schema id is configured.
+#[cfg(feature = "events-kafka")]
+async fn kafka_publisher(
+    events: &oauth2_config::EventConfig,
+    brokers: &str,
+    topic: String,
+) -> Result<oauth2_events::KafkaEventPublisher, String> {
+    use oauth2_config::{KafkaPartitionKey, KafkaSerialization};
+    use oauth2_events::SchemaFormat;
+
+    let kafka = events.kafka.as_ref();
+    let partition_key = match kafka.map(|k| k.partition_key).unwrap_or_default() {
+        KafkaPartitionKey::IdempotencyKey => oauth2_events::KafkaPartitionKey::IdempotencyKey,
+        KafkaPartitionKey::UserId => oauth2_events::KafkaPartitionKey::UserId,
+        KafkaPartitionKey::ClientId => oauth2_events::KafkaPartitionKey::ClientId,
+        KafkaPartitionKey::EventType => oauth2_events::KafkaPartitionKey::EventType,
+    };
+    let format = match kafka.map(|k| k.serialization).unwrap_or_default() {
+        KafkaSerialization::Json => None,
+        KafkaSerialization::JsonSchema => Some(SchemaFormat::JsonSchema),
+        KafkaSerialization::Avro => Some(SchemaFormat::Avro),
+    };
+    let subject = format!("{topic}-value");
+
+    let mut publisher =
+        oauth2_events::KafkaEventPublisher::new(brokers, topic, events.kafka_client_id.clone())?
+            .with_partition_key(partition_key);
+    if let Some(format) = format {
+        let schema_id = match kafka.and_then(|k| k.schema_id) {
+            Some(id) => id,
+            None => {
+                let registry_url = kafka.and_then(|k| k.schema_registry_url.as_deref()).ok_or(
+                    "schema_registry_url or schema_id is required for schema serialization",
+                )?;
+                let id = oauth2_events::register_schema(registry_url, &subject, format).await?;
+                tracing::info!(subject = %subject, schema_id = id, "Registered event envelope schema");
+                id
             }
-            _ => EventFilter::allow_all(),
         };
+        publisher = publisher.with_schema(format, schema_id);
+    }
+    Ok(publisher)
+}
 
-        // Create plugins based on backend config
-        let plugins: Vec<Arc<dyn oauth2_events::EventPlugin>> = match config.events.backend.as_str()
-        {
-            "console" => vec![Arc::new(ConsoleEventLogger::new())],
-            "in_memory" => vec![Arc::new(InMemoryEventLogger::new(1000))],
-            "both" => vec![
-                Arc::new(InMemoryEventLogger::new(1000)),
-                Arc::new(ConsoleEventLogger::new()),
-            ],
-            "redis" | "redis_streams" => {
-                #[cfg(feature = "events-redis")]
+/// Event plugins for `events.backend`; unknown or unavailable backends fall back to in-memory.
+async fn event_plugins_from_config(
+    events: &oauth2_config::EventConfig,
+) -> Vec<Arc<dyn oauth2_events::EventPlugin>> {
+    use oauth2_events::{ConsoleEventLogger, InMemoryEventLogger};
+
+    match events.backend.as_str() {
+        "console" => vec![Arc::new(ConsoleEventLogger::new())],
+        "in_memory" => vec![Arc::new(InMemoryEventLogger::new(1000))],
+        "both" => vec![
+            Arc::new(InMemoryEventLogger::new(1000)),
+            Arc::new(ConsoleEventLogger::new()),
+        ],
+        "redis" | "redis_streams" => {
+            #[cfg(feature = "events-redis")]
+            {
+                let url = events
+                    .redis_url
+                    .clone()
+                    .unwrap_or_else(|| "redis://127.0.0.1:6379".to_string());
+
+                let stream = events
+                    .redis_stream
+                    .clone()
+                    .unwrap_or_else(oauth2_events::default_stream_name);
+
+                let maxlen = events.redis_maxlen.or_else(oauth2_events::default_maxlen);
+
+                match oauth2_events::RedisStreamsEventPublisher::connect(&url, stream, maxlen).await
                 {
-                    let url = config
-                        .events
-                        .redis_url
-                        .clone()
-                        .unwrap_or_else(|| "redis://127.0.0.1:6379".to_string());
-
-                    let stream = config
-                        .events
-                        .redis_stream
-                        .clone()
-                        .unwrap_or_else(oauth2_events::default_stream_name);
-
-                    let maxlen = config
-                        .events
-                        .redis_maxlen
-                        .or_else(oauth2_events::default_maxlen);
-
-                    match oauth2_events::RedisStreamsEventPublisher::connect(&url, stream, maxlen)
-                        .await
-                    {
-                        Ok(p) => vec![Arc::new(p)],
-                        Err(e) => {
-                            tracing::warn!(error = %e, "Redis event backend init failed; falling back to in_memory");
-                            vec![Arc::new(InMemoryEventLogger::new(1000))]
-                        }
+                    Ok(p) => vec![Arc::new(p)],
+                    Err(e) => {
+                        tracing::warn!(error = %e, "Redis event backend init failed; falling back to in_memory");
+                        vec![Arc::new(InMemoryEventLogger::new(1000))]
                     }
                 }
-                #[cfg(not(feature = "events-redis"))]
-                {
-                    tracing::warn!(
-                        "Event backend '{}' requested but feature 'events-redis' is not enabled; falling back to in_memory",
-                        config.events.backend
-                    );
-                    vec![Arc::new(InMemoryEventLogger::new(1000))]
-                }
             }
-            "kafka" => {
-                #[cfg(feature = "events-kafka")]
-                {
-                    let brokers = config
-                        .events
-                        .kafka_brokers
-                        .clone()
-                        .unwrap_or_else(|| "127.0.0.1:9092".to_string());
-                    let topic = config
-                        .events
-                        .kafka_topic
-                        .clone()
-                        .unwrap_or_else(|| "oauth2_events".to_string());
-
-                    match oauth2_events::KafkaEventPublisher::new(
-                        &brokers,
-                        topic,
-                        config.events.kafka_client_id.clone(),
-                    ) {
-                        Ok(p) => vec![Arc::new(p)],
-                        Err(e) => {
-                            tracing::warn!(error = %e, "Kafka event backend init failed; falling back to in_memory");
-                            vec![Arc::new(InMemoryEventLogger::new(1000))]
-                        }
+            #[cfg(not(feature = "events-redis"))]
+            {
+                tracing::warn!(
+                    "Event backend '{}' requested but feature 'events-redis' is not enabled; falling back to in_memory",
+                    events.backend
+                );
+                vec![Arc::new(InMemoryEventLogger::new(1000))]
+            }
+        }
+        "kafka" => {
+            #[cfg(feature = "events-kafka")]
+            {
+                let brokers = events
+                    .kafka_brokers
+                    .clone()
+                    .unwrap_or_else(|| "127.0.0.1:9092".to_string());
+                let topic = events
+                    .kafka_topic
+                    .clone()
+                    .unwrap_or_else(|| "oauth2_events".to_string());
+
+                match kafka_publisher(events, &brokers, topic).await {
+                    Ok(p) => vec![Arc::new(p)],
+                    Err(e) => {
+                        tracing::warn!(error = %e, "Kafka event backend init failed; falling back to in_memory");
+                        vec![Arc::new(InMemoryEventLogger::new(1000))]
                     }
                 }
-                #[cfg(not(feature = "events-kafka"))]
+            }
+            #[cfg(not(feature = "events-kafka"))]
+            {
+                tracing::warn!(
+                    "Event backend '{}' requested but feature 'events-kafka' is not enabled; falling back to in_memory",
+                    events.backend
+                );
+                vec![Arc::new(InMemoryEventLogger::new(1000))]
+            }
+        }
+        "rabbit" | "rabbitmq" => {
+            #[cfg(feature = "events-rabbit")]
+            {
+                let url = events
+                    .rabbit_url
+                    .clone()
+                    .unwrap_or_else(|| "amqp://127.0.0.1:5672/%2f".to_string());
+                let exchange = events
+                    .rabbit_exchange
+                    .clone()
+                    .unwrap_or_else(|| "oauth2.events".to_string());
+                let routing_key = events
+                    .rabbit_routing_key
+                    .clone()
+                    .unwrap_or_else(|| "oauth2.event".to_string());
+
+                match oauth2_events::RabbitEventPublisher::connect(&url, exchange, routing_key)
+                    .await
                 {
-                    tracing::warn!(
-                        "Event backend '{}' requested but feature 'events-kafka' is not enabled; falling back to in_memory",
-                        config.events.backend
-                    );
-                    vec![Arc::new(InMemoryEventLogger::new(1000))]
+                    Ok(p) => vec![Arc::new(p)],
+                    Err(e) => {
+                        tracing::warn!(error = %e, "Rabbit event backend init failed; falling back to in_memory");
+                        vec![Arc::new(InMemoryEventLogger::new(1000))]
+                    }
                 }
             }
-            "rabbit" | "rabbitmq" => {
-                #[cfg(feature = "events-rabbit")]
-                {
-                    let url = config
-                        .events
-                        .rabbit_url
-                        .clone()
-                        .unwrap_or_else(|| "amqp://127.0.0.1:5672/%2f".to_string());
-                    let exchange = config
-                        .events
-                        .rabbit_exchange
-                        .clone()
-                        .unwrap_or_else(|| "oauth2.events".to_string());
-                    let routing_key = config
-                        .events
-                        .rabbit_routing_key
-                        .clone()
-                        .unwrap_or_else(|| "oauth2.event".to_string());
-
-                    match oauth2_events::RabbitEventPublisher::connect(&url, exchange, routing_key)
-                        .await
-                    {
-                        Ok(p) => vec![Arc::new(p)],
-                        Err(e) => {
-                            tracing::warn!(error = %e, "Rabbit event backend init failed; falling back to in_memory");
-                            vec![Arc::new(InMemoryEventLogger::new(1000))]
-                        }
+            #[cfg(not(feature = "events-rabbit"))]
+            {
+                tracing::warn!(
+                    "Event backend '{}' requested but feature 'events-rabbit' is not enabled; falling back to in_memory",
+                    events.backend
+                );
+                vec![Arc::new(InMemoryEventLogger::new(1000))]
+            }
+        }
+        "sns" | "sqs" => {
+            #[cfg(feature = "events-sns")]
+            {
+                match sns_publisher_from_config(events.sns.as_ref()) {
+                    Ok(p) => vec![p],
+                    Err(e) => {
+                        tracing::warn!(error = %e, "SNS/SQS event backend init failed; falling back to in_memory");
+                        vec![Arc::new(InMemoryEventLogger::new(1000))]
                     }
                 }
-                #[cfg(not(feature = "events-rabbit"))]
-                {
-                    tracing::warn!(
-                        "Event backend '{}' requested but feature 'events-rabbit' is not enabled; falling back to in_memory",
-                        config.events.backend
-                    );
-                    vec![Arc::new(InMemoryEventLogger::new(1000))]
+            }
+            #[cfg(not(feature = "events-sns"))]
+            {
+                tracing::warn!(
+                    "Event backend '{}' requested but feature 'events-sns' is not enabled; falling back to in_memory",
+                    events.backend
+                );
+                vec![Arc::new(InMemoryEventLogger::new(1000))]
+            }
+        }
+        "pubsub" => {
+            #[cfg(feature = "events-pubsub")]
+            {
+                match pubsub_publisher_from_config(events.pubsub.as_ref()) {
+                    Ok(p) => vec![Arc::new(p)],
+                    Err(e) => {
+                        tracing::warn!(error = %e, "Pub/Sub event backend init failed; falling back to in_memory");
+                        vec![Arc::new(InMemoryEventLogger::new(1000))]
+                    }
                 }
             }
-            _ => {
+            #[cfg(not(feature = "events-pubsub"))]
+            {
                 tracing::warn!(
-                    "Unknown event backend: {}, using in_memory",
-                    config.events.backend
+                    "Event backend '{}' requested but feature 'events-pubsub' is not enabled; falling back to in_memory",
+                    events.backend
                 );
                 vec![Arc::new(InMemoryEventLogger::new(1000))]
             }
-        };
+        }
+        _ => {
+            tracing::warn!("Unknown event backend: {}, using in_memory", events.backend);
+            vec![Arc::new(InMemoryEventLogger::new(1000))]
+        }
+    }
+}
 
-        let actor = oauth2_events::event_actor::EventActor::new(plugins, filter).start();
-        tracing::info!("Event system initialized");
-        Some(actor)
-    } else {
-        tracing::info!("Event system disabled");
-        None
+/// SNS topic or SQS queue publisher from `events.sns`.
+#[cfg(feature = "events-sns")]
+fn sns_publisher_from_config(
+    sns: Option<&oauth2_config::SnsConfig>,
+) -> Result<Arc<dyn oauth2_events::EventPlugin>, String> {
+    use oauth2_config::AwsCredentialsSource as Source;
+    use oauth2_events::{AwsCredentials, AwsCredentialsSource};
+
+    let sns = sns.ok_or("events.sns is not configured")?;
+    let credentials = match sns.credentials {
+        Source::Default => AwsCredentialsSource::Default,
+        Source::Environment => AwsCredentialsSource::Environment,
+        Source::Container => AwsCredentialsSource::Container,
+        Source::Instance => AwsCredentialsSource::Instance,
+        Source::Static => match (&sns.access_key_id, &sns.secret_access_key) {
+            (Some(access_key_id), Some(secret_access_key)) => {
+                AwsCredentialsSource::Static(AwsCredentials {
+                    access_key_id: access_key_id.clone(),
+                    secret_access_key: secret_access_key.clone(),
+                    session_token: sns.session_token.clone(),
+                })
+            }
+            _ => {
+                return Err(
+                    "static credentials need access_key_id and secret_access_key".to_string(),
+                )
+            }
+        },
     };
 
-    // Wrap the actor-backed event system behind the stable EventBus contract.
-    let event_bus = event_actor.as_ref().map(|addr| {
-        let bus = oauth2_events::ActixEventBus::new(addr.clone());
-        oauth2_events::EventBusHandle::new(Arc::new(bus))
-    });
+    match (&sns.topic_arn, &sns.queue_url) {
+        (Some(topic_arn), None) => Ok(Arc::new(oauth2_events::SnsEventPublisher::new(
+            topic_arn.clone(),
+            sns.region.clone(),
+            sns.endpoint.clone(),
+            credentials,
+        )?)),
+        (None, Some(queue_url)) => Ok(Arc::new(oauth2_events::SqsEventPublisher::new(
+            queue_url.clone(),
+            sns.region.clone(),
+            credentials,
+        )?)),
+        _ => Err("events.sns needs exactly one of topic_arn or queue_url".to_string()),
+    }
+}
 
-    // Best-effort Phase 1 in-memory idempotency cache for ingest.
-    let ingest_idempotency =
-        oauth2_actix::handlers::events::IdempotencyStore::new(Duration::from_secs(5 * 60))
-            // Explicitly set to default to make it configurable without changing call sites.
-            .with_max_entries(100_000);
-
-    // Start actors with event system
-    let token_actor = if let Some(ref event_bus) = event_bus {
-        oauth2_actix::actors::TokenActor::with_events(
-            storage.clone(),
-            jwt_secret.clone(),
-            event_bus.clone(),
-        )
-        .start()
-    } else {
-        oauth2_actix::actors::TokenActor::new(storage.clone(), jwt_secret.clone()).start()
+/// Pub/Sub publisher from `events.pubsub`.
+#[cfg(feature = "events-pubsub")]
+fn pubsub_publisher_from_config(
+    pubsub: Option<&oauth2_config::PubSubConfig>,
+) -> Result<oauth2_events::PubSubEventPublisher, String> {
+    use oauth2_config::GcpCredentialsSource as Source;
+    use oauth2_events::GcpCredentialsSource;
+
+    let pubsub = pubsub.ok_or("events.pubsub is not configured")?;
+    let credentials = match pubsub.credentials {
+        Source::Default => GcpCredentialsSource::Default,
+        Source::Metadata => GcpCredentialsSource::Metadata,
+        Source::None => GcpCredentialsSource::None,
+        Source::ServiceAccountFile => GcpCredentialsSource::ServiceAccountFile(
+            pubsub
+                .credentials_file
+                .clone()
+                .ok_or("service_account_file credentials need credentials_file")?
+                .into(),
+        ),
+    };
+    oauth2_events::PubSubEventPublisher::new(
+        &pubsub.project_id,
+        &pubsub.topic,
+        pubsub.endpoint.clone(),
+        credentials,
+    )
+}
+
+/// Dead-letter destination from `events.dead_letter`, if configured. An unavailable
+/// destination is logged and skipped, leaving failed events to the plugin's error path.
+/// Where `POST /admin/events/replay` reads events back from: the configured Redis stream
+/// or Kafka topic. Other backends keep nothing to replay.
+async fn event_replay_source_from_config(
+    events: &oauth2_config::EventConfig,
+) -> Option<oauth2_events::DynEventReplaySource> {
+    if !events.enabled {
+        return None;
+    }
+    match events.backend.as_str() {
+        #[cfg(feature = "events-redis")]
+        "redis" | "redis_streams" => {
+            let url = events
+                .redis_url
+                .clone()
+                .unwrap_or_else(|| "redis://127.0.0.1:6379".to_string());
+            let stream = events
+                .redis_stream
+                .clone()
+                .unwrap_or_else(oauth2_events::default_stream_name);
+            match oauth2_events::RedisStreamsReplaySource::connect(&url, stream).await {
+                Ok(source) => Some(Arc::new(source)),
+                Err(e) => {
+                    tracing::warn!(error = %e, "Redis replay source init failed; event replay disabled");
+                    None
+                }
+            }
+        }
+        #[cfg(feature = "events-kafka")]
+        "kafka" => {
+            let brokers = events
+                .kafka_brokers
+                .clone()
+                .unwrap_or_else(|| "127.0.0.1:9092".to_string());
+            let topic = events
+                .kafka_topic
+                .clone()
+                .unwrap_or_else(|| "oauth2_events".to_string());
+            Some(Arc::new(oauth2_events::KafkaReplaySource::new(
+                &brokers, topic,
+            )))
+        }
+        _ => None,
+    }
+}
+
+async fn dead_letter_sink_from_config(
+    events: &oauth2_config::EventConfig,
+) -> std::io::Result<Option<oauth2_events::DynDeadLetterSink>> {
+    use oauth2_config::DeadLetterDestination;
+
+    let Some(dead_letter) = &events.dead_letter else {
+        return Ok(None);
     };
+    match dead_letter.destination {
+        DeadLetterDestination::File => {
+            let path = dead_letter.path.as_deref().ok_or_else(|| {
+                std::io::Error::other(
+                    "events.dead_letter.path is required for the file destination",
+                )
+            })?;
+            Ok(Some(Arc::new(oauth2_events::FileDeadLetterSink::new(path))))
+        }
+        DeadLetterDestination::Redis => {
+            #[cfg(feature = "events-redis")]
+            {
+                let url = dead_letter
+                    .url
+                    .clone()
+                    .or_else(|| events.redis_url.clone())
+                    .unwrap_or_else(|| "redis://127.0.0.1:6379".to_string());
+                let stream = dead_letter
+                    .stream
+                    .clone()
+                    .unwrap_or_else(oauth2_events::default_dead_letter_stream_name);
+                match oauth2_events::RedisStreamsDeadLetterSink::connect(&url, stream).await {
+                    Ok(sink) => Ok(Some(Arc::new(sink))),
+                    Err(e) => {
+                        tracing::warn!(error = %e, "Redis dead-letter destination init failed; dead-lettering disabled");
+                        Ok(None)
+                    }
+                }
+            }
+            #[cfg(not(feature = "events-redis"))]
+            {
+                tracing::warn!(
+                    "Redis dead-letter destination requested but feature 'events-redis' is not enabled; dead-lettering disabled"
+                );
+                Ok(None)
+            }
+        }
+        DeadLetterDestination::Kafka => {
+            #[cfg(feature = "events-kafka")]
+            {
+                let brokers = dead_letter
+                    .brokers
+                    .clone()
+                    .or_else(|| events.kafka_brokers.clone())
+                    .unwrap_or_else(|| "127.0.0.1:9092".to_string());
+                let topic = dead_letter
+                    .topic
+                    .clone()
+                    .unwrap_or_else(|| "oauth2_events_dlq".to_string());
+                match oauth2_events::KafkaDeadLetterSink::new(&brokers, topic) {
+                    Ok(sink) => Ok(Some(Arc::new(sink))),
+                    Err(e) => {
+                        tracing::warn!(error = %e, "Kafka dead-letter destination init failed; dead-lettering disabled");
+                        Ok(None)
+                    }
+                }
+            }
+            #[cfg(not(feature = "events-kafka"))]
+            {
+                tracing::warn!(
+                    "Kafka dead-letter destination requested but feature 'events-kafka' is not enabled; dead-lettering disabled"
+                );
+                Ok(None)
+            }
+        }
+    }
+}
+
+fn event_filter_from_config(events: &oauth2_config::EventConfig) -> oauth2_events::EventFilter {
+    use oauth2_events::EventFilter;
 
-    let client_actor = if let Some(ref event_bus) = event_bus {
-        oauth2_actix::actors::ClientActor::with_events(storage.clone(), event_bus.clone()).start()
-    } else {
-        oauth2_actix::actors::ClientActor::new(storage.clone()).start()
+    match events.filter_mode.as_str() {
+        "include" => EventFilter::include_only(parse_event_types(&events.event_types)),
+        "exclude" => EventFilter::exclude_events(parse_event_types(&events.event_types)),
+        _ => EventFilter::allow_all(),
+    }
+}
+
+/// Envelope signing key from `events.signing`, if configured.
+fn event_signing_key_from_config(
+    events: &oauth2_config::EventConfig,
+) -> std::io::Result<Option<oauth2_events::SigningKey>> {
+    use oauth2_events::SigningKey;
+
+    let Some(signing) = &events.signing else {
+        return Ok(None);
     };
+    match (&signing.secret, &signing.private_key_pem) {
+        (Some(secret), None) => Ok(Some(SigningKey::hmac(&signing.key_id, secret.as_bytes()))),
+        (None, Some(pem)) => SigningKey::ed25519_pem(&signing.key_id, pem.as_bytes())
+            .map(Some)
+            .map_err(|e| std::io::Error::other(format!("events.signing.private_key_pem: {e}"))),
+        _ => Err(std::io::Error::other(
+            "events.signing needs exactly one of secret or private_key_pem",
+        )),
+    }
+}
 
-    let auth_actor = if let Some(ref event_bus) = event_bus {
-        oauth2_actix::actors::AuthActor::with_events(storage.clone(), event_bus.clone()).start()
-    } else {
-        oauth2_actix::actors::AuthActor::new(storage.clone()).start()
+/// Session cookie key from `OAUTH2_SESSION_KEY` or `session.key`; random when unset.
+fn session_key_from_config(config: &oauth2_config::Config) -> std::io::Result<Key> {
+    let configured = std::env::var("OAUTH2_SESSION_KEY").ok().or_else(|| {
+        config
+            .session
+            .as_ref()
+            .and_then(|session| session.key.as_ref())
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    });
+    let Some(key_str) = configured else {
+        tracing::warn!("OAUTH2_SESSION_KEY not set. Generating random key. Sessions will not persist across restarts!");
+        return Ok(Key::generate());
     };
 
-    tracing::info!("Actors started");
-
-    // OpenAPI documentation
-    let openapi = ApiDoc::openapi();
-
-    let bind_addr = format!("{}:{}", config.server.host, config.server.port);
-    tracing::info!("Starting server at http://{}", bind_addr);
-    tracing::info!("Login page available at http://{}/auth/login", bind_addr);
-    tracing::info!("Swagger UI available at http://{}/swagger-ui", bind_addr);
-    tracing::info!("Admin dashboard at http://{}/admin", bind_addr);
-    tracing::info!("Metrics endpoint at http://{}/metrics", bind_addr);
-
-    // Start HTTP server
-    let server = HttpServer::new(move || {
-        let cors = Cors::default()
-            .allow_any_origin()
-            .allow_any_method()
-            .allow_any_header()
-            .max_age(3600);
-
-        let mut app = App::new()
-            // Middleware
-            .wrap(SessionMiddleware::new(
-                CookieSessionStore::default(),
-                session_key.clone(),
-            ))
-            .wrap(TracingLogger::<OtelRootSpanBuilder>::new())
-            .wrap(actix_middleware::Logger::default())
-            .wrap(actix_middleware::Compress::default())
-            .wrap(oauth2_observability::actix::MetricsMiddleware::new(
-                metrics.clone(),
-            ))
-            .wrap(cors)
-            // Shared state
-            .app_data(web::Data::new(token_actor.clone()))
-            .app_data(web::Data::new(client_actor.clone()))
-            .app_data(web::Data::new(auth_actor.clone()))
-            .app_data(web::Data::new(jwt_secret.clone()))
-            .app_data(web::Data::new(storage.clone()))
-            .app_data(web::Data::new(metrics.clone()))
-            .app_data(web::Data::new(social_config.clone()));
-
-        // Shared, best-effort in-memory idempotency cache for event ingest.
-        app = app.app_data(web::Data::new(ingest_idempotency.clone()));
-
-        // Add event actor if enabled
-        if let Some(ref event_actor) = event_actor {
-            app = app.app_data(web::Data::new(event_actor.clone()));
-        }
-
-        // Add event bus handle if enabled
-        if let Some(ref event_bus) = event_bus {
-            app = app.app_data(web::Data::new(event_bus.clone()));
-        }
-
-        app
-            // Root route
-            .route(
-                "/",
-                web::get().to(|| async {
-                    HttpResponse::Found()
-                        .append_header(("Location", "/auth/login"))
-                        .finish()
-                }),
-            )
-            // Authentication routes
-            .service(
-                web::scope("/auth")
-                    .route(
-                        "/login",
-                        web::get().to(oauth2_social_login::handlers::auth::login_page),
-                    )
-                    .route(
-                        "/logout",
-                        web::post().to(oauth2_social_login::handlers::auth::logout),
-                    )
-                    .route(
-                        "/success",
-                        web::get().to(oauth2_social_login::handlers::auth::auth_success),
-                    )
-                    .service(
-                        web::scope("/login")
-                            .route(
-                                "/google",
-                                web::get().to(oauth2_social_login::handlers::auth::google_login),
-                            )
-                            .route(
-                                "/microsoft",
-                                web::get().to(oauth2_social_login::handlers::auth::microsoft_login),
-                            )
-                            .route(
-                                "/github",
-                                web::get().to(oauth2_social_login::handlers::auth::github_login),
-                            )
-                            .route(
-                                "/azure",
-                                web::get().to(oauth2_social_login::handlers::auth::microsoft_login),
-                            ) // Azure uses Microsoft endpoint
-                            // NOTE: Okta and Auth0 handlers not yet implemented - buttons should be hidden in UI
-                            // or implement proper handlers in handlers::auth module
-                            .route(
-                                "/okta",
-                                web::get().to(|| async {
-                                    actix_web::HttpResponse::ServiceUnavailable()
-                                        .body("Okta login not yet implemented")
-                                }),
-                            )
-                            .route(
-                                "/auth0",
-                                web::get().to(|| async {
-                                    actix_web::HttpResponse::ServiceUnavailable()
-                                        .body("Auth0 login not yet implemented")
-                                }),
-                            ),
-                    )
-                    .route(
-                        "/callback/{provider}",
-                        web::get().to(oauth2_social_login::handlers::auth::auth_callback),
-                    ),
-            )
-            // OAuth2 endpoints
-            .service(
-                web::scope("/oauth")
-                    .route(
-                        "/authorize",
-                        web::get().to(oauth2_actix::handlers::oauth::authorize),
-                    )
-                    .route(
-                        "/token",
-                        web::post().to(oauth2_actix::handlers::oauth::token),
-                    )
-                    .route(
-                        "/introspect",
-                        web::post().to(oauth2_actix::handlers::token::introspect),
-                    )
-                    .route(
-                        "/revoke",
-                        web::post().to(oauth2_actix::handlers::token::revoke),
-                    ),
-            )
-            // Client management endpoints
-            .service(web::scope("/clients").route(
-                "/register",
-                web::post().to(oauth2_actix::handlers::client::register_client),
-            ))
-            // Well-known endpoints
-            .service(web::scope("/.well-known").route(
-                "/openid-configuration",
-                web::get().to(oauth2_actix::handlers::wellknown::openid_configuration),
-            ))
-            // Admin endpoints
-            .service(
-                web::scope("/admin")
-                    .route("", web::get().to(admin_dashboard))
-                    .service(
-                        web::scope("/api")
-                            .route(
-                                "/dashboard",
-                                web::get().to(oauth2_actix::handlers::admin::dashboard),
-                            )
-                            .route(
-                                "/clients",
-                                web::get().to(oauth2_actix::handlers::admin::list_clients),
-                            )
-                            .route(
-                                "/tokens",
-                                web::get().to(oauth2_actix::handlers::admin::list_tokens),
-                            )
-                            .route(
-                                "/tokens/{id}/revoke",
-                                web::post().to(oauth2_actix::handlers::admin::admin_revoke_token),
-                            )
-                            .route(
-                                "/clients/{id}",
-                                web::delete().to(oauth2_actix::handlers::admin::delete_client),
-                            ),
-                    ),
-            )
-            // Error page
-            .route("/error", web::get().to(error_page))
-            // Observability endpoints
-            .route(
-                "/health",
-                web::get().to(oauth2_actix::handlers::admin::health),
-            )
-            .route(
-                "/ready",
-                web::get().to(oauth2_actix::handlers::admin::readiness),
-            )
-            .route(
-                "/metrics",
-                web::get().to(oauth2_actix::handlers::admin::system_metrics),
-            )
-            // Eventing endpoints
-            .service(
-                web::scope("/events")
-                    .route(
-                        "/ingest",
-                        web::post().to(oauth2_actix::handlers::events::ingest),
-                    )
-                    .route(
-                        "/health",
-                        web::get().to(oauth2_actix::handlers::events::health),
-                    ),
-            )
-            // Swagger UI
-            .service(
-                SwaggerUi::new("/swagger-ui/{_:.*}").url("/api-docs/openapi.json", openapi.clone()),
-            )
-            // Static files
-            .service(Files::new("/static", "./static"))
-    })
-    .bind(&bind_addr)?
-    .run();
-
-    server.await?;
+    if key_str.len() < 64 {
+        return Err(std::io::Error::other(
+            "OAUTH2_SESSION_KEY must be at least 64 characters (128 hex digits)",
+        ));
+    }
+    let key_bytes = hex::decode(&key_str)
+        .map_err(|_| std::io::Error::other("OAUTH2_SESSION_KEY must be valid hexadecimal"))?;
+    Key::try_from(&key_bytes[..])
+        .map_err(|_| std::io::Error::other("OAUTH2_SESSION_KEY must be exactly 64 bytes"))
+}
+
+/// Logging settings for [`oauth2_observability::init_telemetry_with_logs`].
+fn log_options(logging: &oauth2_config::LoggingConfig) -> oauth2_observability::LogOptions {
+    use oauth2_config::{LogFormat, LogRotation};
+    use oauth2_observability::LogFile;
+
+    oauth2_observability::LogOptions {
+        format: match logging.format {
+            LogFormat::Json => oauth2_observability::LogFormat::Json,
+            LogFormat::Pretty => oauth2_observability::LogFormat::Pretty,
+            LogFormat::Compact => oauth2_observability::LogFormat::Compact,
+        },
+        span_list: logging.span_list,
+        stdout: logging.stdout,
+        file: logging.file.as_ref().map(|file| LogFile {
+            directory: file.directory.clone().into(),
+            prefix: file.prefix.clone(),
+            rotation: match file.rotation {
+                LogRotation::Never => oauth2_observability::LogRotation::Never,
+                LogRotation::Hourly => oauth2_observability::LogRotation::Hourly,
+                LogRotation::Daily => oauth2_observability::LogRotation::Daily,
+            },
+            max_files: file.max_files,
+        }),
+    }
+}
+
+/// Run the standalone server: configuration from HOCON/environment, every endpoint group.
+pub async fn run() -> std::io::Result<()> {
+    if let Some(path) = validate_config_requested() {
+        let layers = oauth2_config::config_layers(std::path::Path::new(&path));
+        let report = oauth2_config::Config::validate_layers(&layers);
+        if report.is_valid() {
+            print!("{report}");
+            return Ok(());
+        }
+        eprint!("{report}");
+        return Err(std::io::Error::other(format!("{path} is not valid")));
+    }
+
+    // Load configuration first: it decides where logs go.
+    let (mut config, config_source) = oauth2_config::Config::load();
+
+    // Initialize telemetry and tracing
+    oauth2_observability::init_telemetry_with_logs("oauth2_server", &log_options(&config.logging))
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to initialize telemetry: {}", e);
+            // Fall back to basic logging
+            env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+        });
+
+    tracing::info!("Starting OAuth2 Server...");
+    match &config_source {
+        oauth2_config::ConfigSource::Environment { reason } => tracing::warn!(
+            "Failed to load HOCON config: {}. Fell back to environment variables.",
+            reason
+        ),
+        oauth2_config::ConfigSource::Hocon { path, profile, .. } => {
+            if let Some(profile) = profile {
+                tracing::info!("Using configuration profile {}", profile);
+            }
+            let layers = oauth2_config::config_layers(std::path::Path::new(path));
+            let report = oauth2_config::Config::validate_layers(&layers);
+            for key in &report.unknown_keys {
+                tracing::warn!("Unknown configuration key {} is ignored", key);
+            }
+            for missing in &report.missing {
+                tracing::warn!("Incomplete configuration: {}", missing);
+            }
+        }
+        oauth2_config::ConfigSource::Programmatic => {}
+    }
+
+    resolve_secrets(&mut config).await?;
+    if let Some(path) = seed_file_requested() {
+        config.seed.file = Some(path);
+    }
+
+    if std::env::var("OAUTH2_DEBUG_CONFIG").ok().as_deref() == Some("1") {
+        if let Ok(cfg_json) = serde_json::to_string_pretty(&config.sanitized()) {
+            tracing::info!(config = %cfg_json, "Loaded configuration (sanitized)");
+        }
+    }
+
+    // Validate configuration for production; `--strict` makes any violation fatal.
+    let strict = strict_mode_requested();
+    let violations = config.production_violations();
+    if !violations.is_empty() {
+        for violation in &violations {
+            if strict {
+                tracing::error!("Production readiness check failed: {}", violation);
+            } else {
+                tracing::warn!("Configuration validation warning: {}", violation);
+            }
+        }
+        if strict {
+            return Err(std::io::Error::other(format!(
+                "refusing to start in strict mode: {} production readiness violation(s)",
+                violations.len()
+            )));
+        }
+        tracing::warn!("This configuration should only be used for testing!");
+    }
+
+    tracing::info!("Configuration loaded");
+
+    ServerBuilder::new(config)
+        .with_config_source(config_source)
+        .build()
+        .await?
+        .run()
+        .await?;
 
     // Shutdown telemetry
     oauth2_observability::shutdown_telemetry();
@@ -555,6 +1120,60 @@ pub async fn run() -> std::io::Result<()> {
     Ok(())
 }
 
+/// Replace `vault:` and `aws-sm:` secret settings with the secrets from the stores
+/// configured in `secrets`.
+pub async fn resolve_secrets(config: &mut oauth2_config::Config) -> std::io::Result<()> {
+    let resolver = oauth2_secrets::SecretResolver::from_config(&config.secrets)
+        .map_err(std::io::Error::other)?;
+    let resolved = resolver
+        .resolve(config)
+        .await
+        .map_err(|e| std::io::Error::other(format!("failed to resolve secrets: {e}")))?;
+    if !resolved.is_empty() {
+        tracing::info!(settings = ?resolved, "Resolved secrets from external stores");
+    }
+    Ok(())
+}
+
+/// Path after `--validate-config` (`application.conf` when none is given). The active
+/// profile's file is validated along with it.
+fn validate_config_requested() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if let Some(path) = arg.strip_prefix("--validate-config=") {
+            return Some(path.to_string());
+        }
+        if arg == "--validate-config" {
+            let path = args.next().filter(|next| !next.starts_with("--"));
+            return Some(path.unwrap_or_else(|| "application.conf".to_string()));
+        }
+    }
+    None
+}
+
+/// Path after `--seed`, which replaces `seed.file`.
+fn seed_file_requested() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if let Some(path) = arg.strip_prefix("--seed=") {
+            return Some(path.to_string());
+        }
+        if arg == "--seed" {
+            return args.next();
+        }
+    }
+    None
+}
+
+/// `--strict` on the command line or `OAUTH2_STRICT=true` in the environment.
+fn strict_mode_requested() -> bool {
+    std::env::args().skip(1).any(|arg| arg == "--strict")
+        || std::env::var("OAUTH2_STRICT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(false)
+}
+
 // Admin dashboard HTML page
 async fn admin_dashboard() -> HttpResponse {
     let html = std::fs::read_to_string("templates/admin_dashboard.html")
@@ -0,0 +1,114 @@
+//! `OAuth2Client` custom resources as the desired client state.
+//!
+//! `k8s/components/reconcile-kube` installs the CRD (as generated by `OAuth2Client::crd()`) and
+//! the RBAC to watch it. Secrets are only accepted as hashes, so resources never hold
+//! usable credentials.
+
+use futures::StreamExt;
+use kube::api::ListParams;
+use kube::runtime::{reflector, watcher, WatchStreamExt};
+use kube::{Api, CustomResource, ResourceExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{ClientManifest, ReconcileError, Reconciler};
+
+#[derive(CustomResource, Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[kube(
+    group = "oauth2.ianlintner.dev",
+    version = "v1alpha1",
+    kind = "OAuth2Client",
+    plural = "oauth2clients",
+    shortname = "oac",
+    namespaced
+)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuth2ClientSpec {
+    /// Defaults to the resource name.
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Argon2 PHC hash of the client secret.
+    pub client_secret_hash: String,
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    pub grant_types: Vec<String>,
+    pub scope: String,
+}
+
+impl From<&OAuth2Client> for ClientManifest {
+    fn from(resource: &OAuth2Client) -> Self {
+        let spec = &resource.spec;
+        Self {
+            client_id: spec
+                .client_id
+                .clone()
+                .unwrap_or_else(|| resource.name_any()),
+            name: spec.name.clone(),
+            client_secret: None,
+            client_secret_hash: Some(spec.client_secret_hash.clone()),
+            redirect_uris: spec.redirect_uris.clone(),
+            grant_types: spec.grant_types.clone(),
+            scope: spec.scope.clone(),
+        }
+    }
+}
+
+fn desired<'a>(resources: impl IntoIterator<Item = &'a OAuth2Client>) -> Vec<ClientManifest> {
+    resources.into_iter().map(ClientManifest::from).collect()
+}
+
+/// List the resources once and reconcile, then re-reconcile on every change.
+pub(super) async fn start(
+    reconciler: Reconciler,
+    namespace: Option<String>,
+) -> Result<(), ReconcileError> {
+    let client = kube::Client::try_default()
+        .await
+        .map_err(|e| ReconcileError::Source(e.to_string()))?;
+    let api: Api<OAuth2Client> = match &namespace {
+        Some(namespace) => Api::namespaced(client, namespace),
+        None => Api::all(client),
+    };
+
+    let resources = api
+        .list(&ListParams::default())
+        .await
+        .map_err(|e| ReconcileError::Source(e.to_string()))?;
+    reconciler
+        .reconcile(&desired(&resources.items))
+        .await?
+        .log("kubernetes");
+    tracing::info!(
+        namespace = namespace.as_deref().unwrap_or("*"),
+        clients = resources.items.len(),
+        "Watching OAuth2Client resources"
+    );
+
+    let (reader, writer) = reflector::store();
+    let events = reflector(writer, watcher(api, watcher::Config::default())).default_backoff();
+    actix::spawn(async move {
+        let mut events = std::pin::pin!(events);
+        while let Some(event) = events.next().await {
+            match event {
+                // The store is only complete once the (re)list is done.
+                Ok(
+                    watcher::Event::InitDone | watcher::Event::Apply(_) | watcher::Event::Delete(_),
+                ) => {
+                    let state = reader.state();
+                    match reconciler
+                        .reconcile(&desired(state.iter().map(|r| r.as_ref())))
+                        .await
+                    {
+                        Ok(report) => report.log("kubernetes"),
+                        Err(e) => tracing::error!(error = %e, "Client reconciliation failed"),
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!(error = %e, "OAuth2Client watch error"),
+            }
+        }
+    });
+    Ok(())
+}
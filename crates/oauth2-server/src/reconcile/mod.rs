@@ -0,0 +1,357 @@
+//! Declarative client management: converge stored OAuth clients to a desired state.
+//!
+//! Clients created by a [`Reconciler`] record it in [`Client::managed_by`]. Each pass
+//! creates missing clients, overwrites drifted ones and, with pruning on, deletes managed
+//! clients that are no longer declared. Clients registered any other way are never
+//! modified; declaring one is reported as a conflict.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use oauth2_config::{ReconcileConfig, ReconcileSource};
+use oauth2_core::{is_password_hash, Client, OAuth2Error};
+use oauth2_ports::DynStorage;
+
+#[cfg(feature = "reconcile-kube")]
+pub mod kube;
+
+/// Desired state of one client.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ClientManifest {
+    pub client_id: String,
+    /// Display name; defaults to `client_id`.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Plaintext secret, hashed before it is stored. Prefer `client_secret_hash` in
+    /// checked-in files; HOCON manifests can read this from the environment instead.
+    #[serde(default, skip_serializing)]
+    pub client_secret: Option<String>,
+    /// Argon2 PHC hash of the secret, stored as is.
+    #[serde(default)]
+    pub client_secret_hash: Option<String>,
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    pub grant_types: Vec<String>,
+    pub scope: String,
+}
+
+impl ClientManifest {
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if self.client_id.trim().is_empty() {
+            return Err("client_id must not be empty".to_string());
+        }
+        match (&self.client_secret, &self.client_secret_hash) {
+            (Some(_), Some(_)) => Err(format!(
+                "{}: set only one of client_secret and client_secret_hash",
+                self.client_id
+            )),
+            (None, None) => Err(format!(
+                "{}: client_secret or client_secret_hash is required",
+                self.client_id
+            )),
+            (None, Some(hash)) if !is_password_hash(hash) => Err(format!(
+                "{}: client_secret_hash is not an Argon2 PHC hash",
+                self.client_id
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.client_id)
+    }
+
+    /// Whether `client` already has this manifest's secret. Plaintext secrets are
+    /// verified rather than re-hashed, so unchanged clients are not rewritten.
+    fn secret_matches(&self, client: &Client) -> bool {
+        match (&self.client_secret_hash, &self.client_secret) {
+            (Some(hash), _) => &client.client_secret == hash,
+            (None, Some(secret)) => client.verify_client_secret(secret),
+            (None, None) => true,
+        }
+    }
+
+    pub(crate) fn apply_secret(&self, client: &mut Client) -> Result<(), OAuth2Error> {
+        match (&self.client_secret_hash, &self.client_secret) {
+            (Some(hash), _) => client.client_secret = hash.clone(),
+            (None, Some(secret)) => client.set_client_secret(secret)?,
+            (None, None) => {}
+        }
+        Ok(())
+    }
+
+    fn matches(&self, client: &Client) -> bool {
+        client.name == self.name()
+            && client.scope == self.scope
+            && client.get_redirect_uris() == self.redirect_uris
+            && client.get_grant_types() == self.grant_types
+            && self.secret_matches(client)
+    }
+}
+
+/// Top-level shape of a manifest file.
+#[derive(Debug, Deserialize)]
+struct ManifestFile {
+    #[serde(default)]
+    clients: Vec<ClientManifest>,
+}
+
+#[derive(Debug)]
+pub enum ReconcileError {
+    /// A manifest could not be read or parsed.
+    Manifest {
+        path: PathBuf,
+        message: String,
+    },
+    /// The desired state is inconsistent; nothing was applied.
+    Invalid(String),
+    Storage(OAuth2Error),
+    /// Watching the declarative source failed.
+    Source(String),
+}
+
+impl fmt::Display for ReconcileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReconcileError::Manifest { path, message } => {
+                write!(f, "invalid client manifest {}: {}", path.display(), message)
+            }
+            ReconcileError::Invalid(message) => write!(f, "invalid desired clients: {}", message),
+            ReconcileError::Storage(e) => write!(f, "storage error: {}", e),
+            ReconcileError::Source(message) => write!(f, "client source error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ReconcileError {}
+
+impl From<OAuth2Error> for ReconcileError {
+    fn from(e: OAuth2Error) -> Self {
+        ReconcileError::Storage(e)
+    }
+}
+
+/// What a reconciliation pass changed, by `client_id`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ReconcileReport {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub deleted: Vec<String>,
+    pub unchanged: usize,
+    /// Declared clients that exist but are not managed by this reconciler; left untouched.
+    pub conflicts: Vec<String>,
+}
+
+impl ReconcileReport {
+    pub fn has_changes(&self) -> bool {
+        !(self.created.is_empty() && self.updated.is_empty() && self.deleted.is_empty())
+    }
+
+    fn log(&self, source: &str) {
+        if self.has_changes() {
+            tracing::info!(
+                source,
+                created = ?self.created,
+                updated = ?self.updated,
+                deleted = ?self.deleted,
+                unchanged = self.unchanged,
+                "Clients reconciled"
+            );
+        }
+        if !self.conflicts.is_empty() {
+            tracing::warn!(
+                source,
+                conflicts = ?self.conflicts,
+                "Declared clients already exist and are not managed by this reconciler"
+            );
+        }
+    }
+}
+
+/// Converges storage to a list of [`ClientManifest`]s.
+#[derive(Clone)]
+pub struct Reconciler {
+    storage: DynStorage,
+    manager: String,
+    prune: bool,
+}
+
+impl Reconciler {
+    /// Pruning is on: managed clients missing from the desired state are deleted.
+    pub fn new(storage: DynStorage, manager: impl Into<String>) -> Self {
+        Self {
+            storage,
+            manager: manager.into(),
+            prune: true,
+        }
+    }
+
+    pub fn with_prune(mut self, prune: bool) -> Self {
+        self.prune = prune;
+        self
+    }
+
+    /// Apply `desired`. An invalid desired state is rejected before anything changes.
+    pub async fn reconcile(
+        &self,
+        desired: &[ClientManifest],
+    ) -> Result<ReconcileReport, ReconcileError> {
+        let mut declared = HashSet::new();
+        for manifest in desired {
+            manifest.validate().map_err(ReconcileError::Invalid)?;
+            if !declared.insert(manifest.client_id.as_str()) {
+                return Err(ReconcileError::Invalid(format!(
+                    "{} is declared more than once",
+                    manifest.client_id
+                )));
+            }
+        }
+
+        let mut report = ReconcileReport::default();
+        for manifest in desired {
+            match self.storage.get_client(&manifest.client_id, None).await? {
+                None => {
+                    let mut client = Client::new(
+                        manifest.client_id.clone(),
+                        String::new(),
+                        manifest.redirect_uris.clone(),
+                        manifest.grant_types.clone(),
+                        manifest.scope.clone(),
+                        manifest.name().to_string(),
+                    );
+                    manifest.apply_secret(&mut client)?;
+                    client.managed_by = Some(self.manager.clone());
+                    self.storage.save_client(&client).await?;
+                    report.created.push(manifest.client_id.clone());
+                }
+                Some(client) if client.managed_by.as_deref() != Some(self.manager.as_str()) => {
+                    report.conflicts.push(manifest.client_id.clone());
+                }
+                Some(client) if manifest.matches(&client) => report.unchanged += 1,
+                Some(mut client) => {
+                    client.name = manifest.name().to_string();
+                    client.scope = manifest.scope.clone();
+                    client.set_redirect_uris(&manifest.redirect_uris);
+                    client.grant_types = serde_json::to_string(&manifest.grant_types)
+                        .unwrap_or_else(|_| "[]".to_string());
+                    if !manifest.secret_matches(&client) {
+                        manifest.apply_secret(&mut client)?;
+                    }
+                    client.updated_at = chrono::Utc::now();
+                    self.storage.update_client(&client).await?;
+                    report.updated.push(manifest.client_id.clone());
+                }
+            }
+        }
+
+        if self.prune {
+            for client in self.storage.list_clients_managed_by(&self.manager).await? {
+                if !declared.contains(client.client_id.as_str()) {
+                    self.storage
+                        .delete_client(&client.client_id, client.tenant_id.as_deref())
+                        .await?;
+                    report.deleted.push(client.client_id);
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// Read every `*.conf` and `*.json` manifest in `dir`, in file name order. Hidden
+/// entries are skipped, which also skips the `..data` links of mounted ConfigMaps.
+///
+/// Manifests are HOCON (JSON is valid HOCON), so `${?VAR}` can supply secrets:
+///
+/// ```hocon
+/// clients = [
+///   {
+///     client_id = "billing"
+///     client_secret = ${?BILLING_CLIENT_SECRET}
+///     grant_types = ["client_credentials"]
+///     scope = "billing.read"
+///   }
+/// ]
+/// ```
+pub fn load_directory(dir: &Path) -> Result<Vec<ClientManifest>, ReconcileError> {
+    let entries = std::fs::read_dir(dir).map_err(|e| ReconcileError::Manifest {
+        path: dir.to_path_buf(),
+        message: e.to_string(),
+    })?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            let visible = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| !name.starts_with('.'));
+            let manifest = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ext == "conf" || ext == "json");
+            visible && manifest && path.is_file()
+        })
+        .collect();
+    paths.sort();
+
+    let mut clients = Vec::new();
+    for path in paths {
+        let file: ManifestFile = hocon::HoconLoader::new()
+            .load_file(&path)
+            .and_then(|loader| loader.resolve())
+            .map_err(|e| ReconcileError::Manifest {
+                path: path.clone(),
+                message: e.to_string(),
+            })?;
+        clients.extend(file.clients);
+    }
+    Ok(clients)
+}
+
+/// Reconcile once from the configured source, then keep converging in the background.
+/// The first pass runs before this returns, so an invalid desired state fails startup.
+/// Later failures are logged and the previous state is kept. Must be called from within
+/// an actix system.
+pub async fn start(storage: DynStorage, config: &ReconcileConfig) -> Result<(), ReconcileError> {
+    let reconciler = Reconciler::new(storage, config.manager.clone()).with_prune(config.prune);
+    match config.source {
+        ReconcileSource::Directory => {
+            let dir = PathBuf::from(&config.directory);
+            let report = reconciler.reconcile(&load_directory(&dir)?).await?;
+            report.log("directory");
+            tracing::info!(
+                directory = %dir.display(),
+                clients = report.created.len() + report.updated.len() + report.unchanged,
+                "Watching client manifests"
+            );
+
+            let period = Duration::from_secs(config.interval_secs.max(1));
+            actix::spawn(async move {
+                let mut interval = actix::clock::interval(period);
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    let result = match load_directory(&dir) {
+                        Ok(desired) => reconciler.reconcile(&desired).await,
+                        Err(e) => Err(e),
+                    };
+                    match result {
+                        Ok(report) => report.log("directory"),
+                        Err(e) => tracing::error!(error = %e, "Client reconciliation failed"),
+                    }
+                }
+            });
+            Ok(())
+        }
+        #[cfg(feature = "reconcile-kube")]
+        ReconcileSource::Kubernetes => kube::start(reconciler, config.namespace.clone()).await,
+        #[cfg(not(feature = "reconcile-kube"))]
+        ReconcileSource::Kubernetes => Err(ReconcileError::Source(
+            "reconcile.source = \"kubernetes\" requires the 'reconcile-kube' feature".to_string(),
+        )),
+    }
+}
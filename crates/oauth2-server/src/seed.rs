@@ -0,0 +1,419 @@
+//! Bootstrap data: roles, groups, clients and users created when they are missing.
+//!
+//! Unlike [`reconcile`](crate::reconcile), seeding never updates or deletes anything
+//! that already exists, so changes made through the admin API survive restarts.
+//! [`export`] writes the same shape back out, with secrets and passwords as hashes.
+//!
+//! Scopes are not stored on their own: a client's `scope` lists what it may request,
+//! and seeded roles and groups are issued in tokens when `roles.enabled` is on.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use oauth2_core::{
+    hash_password, is_password_hash, validate_password, Client, ClientType, Group, OAuth2Error,
+    Role, User,
+};
+use oauth2_ports::{DynStorage, PageRequest, UserListQuery};
+
+use crate::reconcile::ClientManifest;
+
+/// Everything a seed file declares.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SeedData {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<RoleSeed>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<GroupSeed>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clients: Vec<ClientManifest>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<UserSeed>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RoleSeed {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GroupSeed {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// A user created with the given roles and groups, which must be seeded too or
+/// already exist.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct UserSeed {
+    pub username: String,
+    pub email: String,
+    /// Plaintext password, hashed before it is stored.
+    #[serde(default, skip_serializing)]
+    pub password: Option<String>,
+    /// Argon2 PHC hash of the password, stored as is.
+    #[serde(default)]
+    pub password_hash: Option<String>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub email_verified: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl UserSeed {
+    fn validate(&self) -> Result<(), String> {
+        if self.username.trim().is_empty() {
+            return Err("username must not be empty".to_string());
+        }
+        if !self.email.contains('@') {
+            return Err(format!("{}: email must be a valid address", self.username));
+        }
+        match (&self.password, &self.password_hash) {
+            (Some(_), Some(_)) => Err(format!(
+                "{}: set only one of password and password_hash",
+                self.username
+            )),
+            (None, None) => Err(format!(
+                "{}: password or password_hash is required",
+                self.username
+            )),
+            (Some(password), None) => validate_password(password)
+                .map_err(|e| format!("{}: {}", self.username, describe(e))),
+            (None, Some(hash)) if !is_password_hash(hash) => Err(format!(
+                "{}: password_hash is not an Argon2 PHC hash",
+                self.username
+            )),
+            (None, Some(_)) => Ok(()),
+        }
+    }
+
+    fn password_hash(&self) -> Result<String, OAuth2Error> {
+        match (&self.password_hash, &self.password) {
+            (Some(hash), _) => Ok(hash.clone()),
+            (None, Some(password)) => hash_password(password),
+            (None, None) => Err(OAuth2Error::invalid_request("password is required")),
+        }
+    }
+}
+
+/// The human-readable part of a validation error.
+fn describe(e: OAuth2Error) -> String {
+    e.error_description.unwrap_or(e.error)
+}
+
+#[derive(Debug)]
+pub enum SeedError {
+    /// A seed file could not be read or parsed.
+    File {
+        path: PathBuf,
+        message: String,
+    },
+    /// The seed data is inconsistent; nothing was applied.
+    Invalid(String),
+    Storage(OAuth2Error),
+}
+
+impl fmt::Display for SeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeedError::File { path, message } => {
+                write!(f, "invalid seed file {}: {}", path.display(), message)
+            }
+            SeedError::Invalid(message) => write!(f, "invalid seed data: {}", message),
+            SeedError::Storage(e) => write!(f, "storage error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SeedError {}
+
+impl From<OAuth2Error> for SeedError {
+    fn from(e: OAuth2Error) -> Self {
+        SeedError::Storage(e)
+    }
+}
+
+/// What a seeding pass created, as `kind:name` (`role:admin`, `client:web`, ...).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SeedReport {
+    pub created: Vec<String>,
+    /// Declared entries that already existed and were left alone.
+    pub unchanged: usize,
+}
+
+impl SeedData {
+    /// Read a seed file: YAML for `.yaml` / `.yml`, otherwise HOCON (JSON is valid
+    /// HOCON), where `${?VAR}` can supply passwords and client secrets.
+    pub fn load(path: &Path) -> Result<Self, SeedError> {
+        let file_error = |message: String| SeedError::File {
+            path: path.to_path_buf(),
+            message,
+        };
+        let yaml = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext == "yaml" || ext == "yml");
+        if yaml {
+            return config::Config::builder()
+                .add_source(config::File::from(path).format(config::FileFormat::Yaml))
+                .build()
+                .and_then(config::Config::try_deserialize)
+                .map_err(|e| file_error(e.to_string()));
+        }
+        hocon::HoconLoader::new()
+            .load_file(path)
+            .and_then(|loader| loader.resolve())
+            .map_err(|e| file_error(e.to_string()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roles.is_empty()
+            && self.groups.is_empty()
+            && self.clients.is_empty()
+            && self.users.is_empty()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let mut roles = HashSet::new();
+        for role in &self.roles {
+            Role::validate_name(&role.name).map_err(describe)?;
+            if !roles.insert(role.name.as_str()) {
+                return Err(format!("role {} is declared more than once", role.name));
+            }
+        }
+        let mut groups = HashSet::new();
+        for group in &self.groups {
+            Group::validate_name(&group.name).map_err(describe)?;
+            if !groups.insert(group.name.as_str()) {
+                return Err(format!("group {} is declared more than once", group.name));
+            }
+        }
+        let mut clients = HashSet::new();
+        for client in &self.clients {
+            client.validate()?;
+            if !clients.insert(client.client_id.as_str()) {
+                return Err(format!(
+                    "client {} is declared more than once",
+                    client.client_id
+                ));
+            }
+        }
+        let mut users = HashSet::new();
+        for user in &self.users {
+            user.validate()?;
+            if !users.insert(user.username.as_str()) {
+                return Err(format!("user {} is declared more than once", user.username));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Create whatever `seed` declares that storage does not have yet. Invalid seed data,
+/// including users assigned to unknown roles or groups, is rejected before anything
+/// is written.
+pub async fn apply(storage: &DynStorage, seed: &SeedData) -> Result<SeedReport, SeedError> {
+    seed.validate().map_err(SeedError::Invalid)?;
+
+    let existing_roles: HashSet<String> = storage
+        .list_roles()
+        .await?
+        .into_iter()
+        .map(|role| role.name)
+        .collect();
+    let existing_groups: HashSet<String> = storage
+        .list_groups()
+        .await?
+        .into_iter()
+        .map(|group| group.name)
+        .collect();
+    for user in &seed.users {
+        let unknown_role = user.roles.iter().find(|role| {
+            !existing_roles.contains(*role) && !seed.roles.iter().any(|r| &r.name == *role)
+        });
+        if let Some(role) = unknown_role {
+            return Err(SeedError::Invalid(format!(
+                "{}: unknown role {}",
+                user.username, role
+            )));
+        }
+        let unknown_group = user.groups.iter().find(|group| {
+            !existing_groups.contains(*group) && !seed.groups.iter().any(|g| &g.name == *group)
+        });
+        if let Some(group) = unknown_group {
+            return Err(SeedError::Invalid(format!(
+                "{}: unknown group {}",
+                user.username, group
+            )));
+        }
+    }
+
+    let mut report = SeedReport::default();
+    for role in &seed.roles {
+        if existing_roles.contains(&role.name) {
+            report.unchanged += 1;
+        } else {
+            storage
+                .save_role(&Role::new(role.name.clone(), role.description.clone()))
+                .await?;
+            report.created.push(format!("role:{}", role.name));
+        }
+    }
+    for group in &seed.groups {
+        if existing_groups.contains(&group.name) {
+            report.unchanged += 1;
+        } else {
+            storage
+                .save_group(&Group::new(group.name.clone(), group.description.clone()))
+                .await?;
+            report.created.push(format!("group:{}", group.name));
+        }
+    }
+    for manifest in &seed.clients {
+        if storage
+            .get_client(&manifest.client_id, None)
+            .await?
+            .is_some()
+        {
+            report.unchanged += 1;
+            continue;
+        }
+        let mut client = Client::new(
+            manifest.client_id.clone(),
+            String::new(),
+            manifest.redirect_uris.clone(),
+            manifest.grant_types.clone(),
+            manifest.scope.clone(),
+            manifest.name().to_string(),
+        );
+        manifest.apply_secret(&mut client)?;
+        storage.save_client(&client).await?;
+        report
+            .created
+            .push(format!("client:{}", manifest.client_id));
+    }
+    for seeded in &seed.users {
+        if storage
+            .get_user_by_username(&seeded.username, None)
+            .await?
+            .is_some()
+        {
+            report.unchanged += 1;
+            continue;
+        }
+        let mut user = User::new(
+            seeded.username.clone(),
+            seeded.password_hash()?,
+            seeded.email.clone(),
+        );
+        user.enabled = seeded.enabled;
+        user.email_verified = seeded.email_verified;
+        storage.save_user(&user).await?;
+        if !seeded.roles.is_empty() {
+            storage.set_user_roles(&user.id, &seeded.roles).await?;
+        }
+        if !seeded.groups.is_empty() {
+            storage.set_user_groups(&user.id, &seeded.groups).await?;
+        }
+        report.created.push(format!("user:{}", seeded.username));
+    }
+
+    Ok(report)
+}
+
+/// Roles, groups, confidential clients and users of the default tenant, in a form
+/// [`apply`] accepts. Clients owned by a reconciler and public clients, which have no
+/// secret to carry, are left out.
+pub async fn export(storage: &DynStorage) -> Result<SeedData, OAuth2Error> {
+    let mut seed = SeedData {
+        roles: storage
+            .list_roles()
+            .await?
+            .into_iter()
+            .map(|role| RoleSeed {
+                name: role.name,
+                description: role.description,
+            })
+            .collect(),
+        groups: storage
+            .list_groups()
+            .await?
+            .into_iter()
+            .map(|group| GroupSeed {
+                name: group.name,
+                description: group.description,
+            })
+            .collect(),
+        ..SeedData::default()
+    };
+
+    let mut page = PageRequest::new(1, PageRequest::MAX_PER_PAGE);
+    loop {
+        let clients = storage.list_clients(page).await?;
+        let last_page = (clients.len() as u32) < page.per_page;
+        for client in clients {
+            let exportable = client.tenant_id.is_none()
+                && client.managed_by.is_none()
+                && client.client_type == ClientType::Confidential
+                && is_password_hash(&client.client_secret);
+            if exportable {
+                seed.clients.push(ClientManifest {
+                    name: Some(client.name.clone()),
+                    client_secret: None,
+                    client_secret_hash: Some(client.client_secret.clone()),
+                    redirect_uris: client.get_redirect_uris(),
+                    grant_types: client.get_grant_types(),
+                    scope: client.scope,
+                    client_id: client.client_id,
+                });
+            }
+        }
+        if last_page {
+            break;
+        }
+        page = PageRequest::new(page.page + 1, page.per_page);
+    }
+
+    let mut query = UserListQuery {
+        limit: PageRequest::MAX_PER_PAGE,
+        ..UserListQuery::default()
+    };
+    loop {
+        let users = storage.list_users(&query).await?;
+        let last_page = (users.len() as u32) < query.limit;
+        for user in users {
+            if user.tenant_id.is_some() {
+                continue;
+            }
+            seed.users.push(UserSeed {
+                roles: storage.list_user_roles(&user.id).await?,
+                groups: storage.list_user_groups(&user.id).await?,
+                username: user.username,
+                email: user.email,
+                password: None,
+                password_hash: Some(user.password_hash),
+                enabled: user.enabled,
+                email_verified: user.email_verified,
+            });
+        }
+        if last_page {
+            break;
+        }
+        query.offset += query.limit;
+    }
+
+    Ok(seed)
+}
@@ -0,0 +1,310 @@
+//! HTTPS listener: rustls configuration from [`TlsConfig`].
+//!
+//! The certificate is served through [`ReloadingCert`], which swaps in a new certificate
+//! when the PEM files change or the process receives `SIGHUP`, without dropping
+//! connections. A certificate that fails to load is logged and the previous one kept.
+
+use actix_web::dev::Extensions;
+use oauth2_config::{ClientAuthMode, TlsConfig};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier};
+use rustls::sign::CertifiedKey;
+use rustls::RootCertStore;
+use std::any::Any;
+use std::fs;
+use std::io;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+/// Certificates presented by the client of a TLS connection, end-entity first.
+///
+/// Stored in the connection data of every request on a listener with `client_auth`
+/// enabled and a client that sent a certificate; read with
+/// `HttpRequest::conn_data::<PeerCertificates>()`.
+#[derive(Debug, Clone)]
+pub struct PeerCertificates(pub Vec<CertificateDer<'static>>);
+
+/// The rustls server configuration for `tls`, and the certificate it serves.
+pub(crate) fn server_config(
+    tls: &TlsConfig,
+) -> io::Result<(rustls::ServerConfig, Arc<ReloadingCert>)> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let cert = Arc::new(ReloadingCert::load(tls.clone(), provider.clone())?);
+
+    let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(invalid)?;
+    let builder = match client_verifier(tls, provider)? {
+        Some(verifier) => builder.with_client_cert_verifier(verifier),
+        None => builder.with_no_client_auth(),
+    };
+    Ok((builder.with_cert_resolver(cert.clone()), cert))
+}
+
+fn client_verifier(
+    tls: &TlsConfig,
+    provider: Arc<CryptoProvider>,
+) -> io::Result<Option<Arc<dyn rustls::server::danger::ClientCertVerifier>>> {
+    if tls.client_auth == ClientAuthMode::None {
+        return Ok(None);
+    }
+    let path = tls.client_ca_path.as_deref().ok_or_else(|| {
+        invalid("server.tls.client_ca_path is required when client_auth is enabled")
+    })?;
+    let mut roots = RootCertStore::empty();
+    for cert in CertificateDer::pem_file_iter(path).map_err(|e| pem_error(path, e))? {
+        roots
+            .add(cert.map_err(|e| pem_error(path, e))?)
+            .map_err(invalid)?;
+    }
+
+    let builder = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+    let builder = match tls.client_auth {
+        ClientAuthMode::Request => builder.allow_unauthenticated(),
+        _ => builder,
+    };
+    builder.build().map(Some).map_err(invalid)
+}
+
+/// `HttpServer::on_connect` callback recording [`PeerCertificates`].
+pub(crate) fn record_peer_certificates(connection: &dyn Any, data: &mut Extensions) {
+    type Stream = actix_tls::accept::rustls_0_23::TlsStream<actix_web::rt::net::TcpStream>;
+    let Some(stream) = connection.downcast_ref::<Stream>() else {
+        return;
+    };
+    let (_, session) = stream.get_ref();
+    if let Some(certs) = session.peer_certificates() {
+        data.insert(PeerCertificates(certs.to_vec()));
+    }
+}
+
+/// The served certificate, reloadable from the configured PEM files.
+#[derive(Debug)]
+pub(crate) struct ReloadingCert {
+    tls: TlsConfig,
+    provider: Arc<CryptoProvider>,
+    current: RwLock<Arc<CertifiedKey>>,
+    /// Modification times of the certificate and key files at the last load.
+    modified: Mutex<[Option<SystemTime>; 2]>,
+}
+
+impl ReloadingCert {
+    fn load(tls: TlsConfig, provider: Arc<CryptoProvider>) -> io::Result<Self> {
+        let modified = modified_times(&tls);
+        let key = certified_key(&tls, &provider)?;
+        Ok(Self {
+            tls,
+            provider,
+            current: RwLock::new(Arc::new(key)),
+            modified: Mutex::new(modified),
+        })
+    }
+
+    /// Load the certificate again, keeping the current one if that fails.
+    fn reload(&self) -> io::Result<()> {
+        let modified = modified_times(&self.tls);
+        let key = certified_key(&self.tls, &self.provider)?;
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(key);
+        *self.modified.lock().unwrap_or_else(|e| e.into_inner()) = modified;
+        Ok(())
+    }
+
+    /// Whether a certificate or key file changed since the last load.
+    fn changed(&self) -> bool {
+        *self.modified.lock().unwrap_or_else(|e| e.into_inner()) != modified_times(&self.tls)
+    }
+
+    fn reload_logged(&self, trigger: &'static str) {
+        match self.reload() {
+            Ok(()) => tracing::info!(trigger, "TLS certificate reloaded"),
+            Err(err) => tracing::error!(
+                trigger,
+                error = %err,
+                "Failed to reload TLS certificate; still serving the previous one"
+            ),
+        }
+    }
+
+    /// Reload on `SIGHUP` and, unless `reload_interval_secs` is `0`, whenever the files
+    /// change. Must be called on the server's runtime.
+    pub(crate) fn watch(self: &Arc<Self>) {
+        #[cfg(unix)]
+        {
+            use actix_web::rt::signal::unix::{signal, SignalKind};
+            match signal(SignalKind::hangup()) {
+                Ok(mut hangup) => {
+                    let cert = self.clone();
+                    actix_web::rt::spawn(async move {
+                        while hangup.recv().await.is_some() {
+                            cert.reload_logged("sighup");
+                        }
+                    });
+                }
+                Err(err) => tracing::warn!(error = %err, "Cannot reload TLS certificate on SIGHUP"),
+            }
+        }
+
+        let uses_files = self.tls.cert_path.is_some() || self.tls.key_path.is_some();
+        if self.tls.reload_interval_secs > 0 && uses_files {
+            let cert = self.clone();
+            let period = Duration::from_secs(self.tls.reload_interval_secs);
+            actix_web::rt::spawn(async move {
+                let mut ticker = actix_web::rt::time::interval(period);
+                loop {
+                    ticker.tick().await;
+                    if cert.changed() {
+                        cert.reload_logged("file_change");
+                    }
+                }
+            });
+        }
+    }
+}
+
+impl ResolvesServerCert for ReloadingCert {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(
+            self.current
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+        )
+    }
+}
+
+fn certified_key(tls: &TlsConfig, provider: &CryptoProvider) -> io::Result<CertifiedKey> {
+    let cert_pem = read_pem("cert", tls.cert_path.as_deref(), tls.cert_pem.as_deref())?;
+    let key_pem = read_pem("key", tls.key_path.as_deref(), tls.key_pem.as_deref())?;
+
+    let chain = CertificateDer::pem_slice_iter(&cert_pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| pem_error("server.tls certificate", e))?;
+    if chain.is_empty() {
+        return Err(invalid(
+            "server.tls certificate contains no PEM certificates",
+        ));
+    }
+    let key = PrivateKeyDer::from_pem_slice(&key_pem)
+        .map_err(|e| pem_error("server.tls private key", e))?;
+    CertifiedKey::from_der(chain, key, provider).map_err(invalid)
+}
+
+/// The PEM from `path`, else the inline `pem`.
+fn read_pem(what: &str, path: Option<&str>, pem: Option<&str>) -> io::Result<Vec<u8>> {
+    match (path, pem) {
+        (Some(path), _) => fs::read(path).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("cannot read server.tls.{what}_path {path}: {e}"),
+            )
+        }),
+        (None, Some(pem)) => Ok(pem.as_bytes().to_vec()),
+        (None, None) => Err(invalid(format!(
+            "server.tls needs {what}_path or {what}_pem"
+        ))),
+    }
+}
+
+fn modified_times(tls: &TlsConfig) -> [Option<SystemTime>; 2] {
+    let modified = |path: Option<&String>| {
+        path.and_then(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+    };
+    [
+        modified(tls.cert_path.as_ref()),
+        modified(tls.key_path.as_ref()),
+    ]
+}
+
+fn pem_error(source: impl std::fmt::Display, err: rustls::pki_types::pem::Error) -> io::Error {
+    invalid(format!("invalid PEM in {source}: {err:?}"))
+}
+
+fn invalid(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tls");
+
+    fn fixture(name: &str) -> String {
+        format!("{FIXTURES}/{name}")
+    }
+
+    fn served(cert: &ReloadingCert) -> CertificateDer<'static> {
+        cert.current.read().unwrap().cert[0].clone()
+    }
+
+    fn pem_cert(name: &str) -> CertificateDer<'static> {
+        CertificateDer::from_pem_file(fixture(name)).unwrap()
+    }
+
+    #[test]
+    fn loads_certificates_from_files_or_inline_pem() {
+        let from_files = TlsConfig {
+            cert_path: Some(fixture("server.crt")),
+            key_path: Some(fixture("server.key")),
+            ..TlsConfig::default()
+        };
+        let (_, cert) = server_config(&from_files).unwrap();
+        assert_eq!(served(&cert), pem_cert("server.crt"));
+
+        let inline = TlsConfig {
+            cert_pem: Some(fs::read_to_string(fixture("other.crt")).unwrap()),
+            key_pem: Some(fs::read_to_string(fixture("other.key")).unwrap()),
+            client_auth: ClientAuthMode::Require,
+            client_ca_path: Some(fixture("ca.crt")),
+            ..TlsConfig::default()
+        };
+        let (_, cert) = server_config(&inline).unwrap();
+        assert_eq!(served(&cert), pem_cert("other.crt"));
+    }
+
+    #[test]
+    fn rejects_mismatched_keys_and_missing_client_ca() {
+        let mismatched = TlsConfig {
+            cert_path: Some(fixture("server.crt")),
+            key_path: Some(fixture("other.key")),
+            ..TlsConfig::default()
+        };
+        assert!(server_config(&mismatched).is_err());
+
+        let no_ca = TlsConfig {
+            cert_path: Some(fixture("server.crt")),
+            key_path: Some(fixture("server.key")),
+            client_auth: ClientAuthMode::Request,
+            ..TlsConfig::default()
+        };
+        let err = server_config(&no_ca).unwrap_err();
+        assert!(err.to_string().contains("client_ca_path"), "{err}");
+    }
+
+    #[test]
+    fn reload_swaps_the_certificate_and_keeps_it_on_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let cert_path = dir.path().join("tls.crt");
+        let key_path = dir.path().join("tls.key");
+        fs::copy(fixture("server.crt"), &cert_path).unwrap();
+        fs::copy(fixture("server.key"), &key_path).unwrap();
+        let tls = TlsConfig {
+            cert_path: Some(cert_path.to_string_lossy().into_owned()),
+            key_path: Some(key_path.to_string_lossy().into_owned()),
+            ..TlsConfig::default()
+        };
+        let (_, cert) = server_config(&tls).unwrap();
+
+        // Only the certificate replaced so far: the pair does not match.
+        fs::copy(fixture("other.crt"), &cert_path).unwrap();
+        assert!(cert.reload().is_err());
+        assert_eq!(served(&cert), pem_cert("server.crt"));
+
+        fs::copy(fixture("other.key"), &key_path).unwrap();
+        cert.reload().unwrap();
+        assert_eq!(served(&cert), pem_cert("other.crt"));
+        assert!(!cert.changed());
+    }
+}
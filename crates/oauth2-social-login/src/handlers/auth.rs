@@ -1,26 +1,130 @@
 use actix_session::Session;
-use actix_web::{web, HttpResponse, Result};
-use oauth2::{
-    AuthorizationCode, CsrfToken, PkceCodeChallenge, Scope, TokenResponse as OAuth2TokenResponse,
-};
+use actix_web::cookie::{time::Duration, Cookie, SameSite};
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use oauth2::{AuthorizationCode, CsrfToken, PkceCodeChallenge, PkceCodeVerifier, Scope};
 use serde::Deserialize;
 use std::sync::Arc;
 
-use oauth2_core::OAuth2Error;
+use oauth2_client::TokenSet;
+use oauth2_config::ProviderConfig;
+use oauth2_core::{
+    generate_browser_binding, AuditAction, AuditRecord, IssuerUrls, OAuth2Error, RequestOrigin,
+    SocialLoginState, SOCIAL_LOGIN_STATE_TTL_SECS,
+};
+use oauth2_events::{AuthEvent, EventBusHandle, EventEnvelope, EventSeverity, EventType};
+use oauth2_ports::{record_audit, DynAuditSink, DynStateStore, DynStorage};
 
-use crate::models::{SocialLoginConfig, SocialUserInfo};
-use crate::service::SocialLoginService;
+use super::link;
+use crate::models::{AccountMatch, SocialLoginConfig, SocialUserInfo};
+use crate::service::{ConfiguredClient, IdTokenIssuer, SocialLoginService};
+use crate::token_vault::TokenVault;
 
+/// Callback parameters, from the query string or, for `response_mode=form_post`
+/// providers such as Apple, the form body.
 #[derive(Deserialize)]
 pub struct AuthCallbackQuery {
     code: String,
     state: Option<String>,
+    /// Apple's JSON user profile, only sent on the first authorization.
+    user: Option<String>,
+}
+
+/// Cookie tying each login to the browser that started it. The state store keeps its
+/// hash, so a callback URL handed to someone else's browser is refused.
+pub const BROWSER_BINDING_COOKIE: &str = "__Host-social_login";
+
+/// The browser's binding cookie value, or a new one. Reusing it keeps logins started in
+/// several tabs valid.
+fn browser_binding(req: &HttpRequest) -> String {
+    req.cookie(BROWSER_BINDING_COOKIE)
+        .map(|cookie| cookie.value().to_string())
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(generate_browser_binding)
+}
+
+/// `SameSite=None` so the cookie also comes back on `form_post` callbacks (Apple).
+fn browser_binding_cookie(value: String) -> Cookie<'static> {
+    Cookie::build(BROWSER_BINDING_COOKIE, value)
+        .path("/")
+        .secure(true)
+        .http_only(true)
+        .same_site(SameSite::None)
+        .max_age(Duration::seconds(SOCIAL_LOGIN_STATE_TTL_SECS))
+        .finish()
+}
+
+/// Save `record` bound to the requesting browser and redirect to `location`.
+pub async fn save_and_redirect(
+    req: &HttpRequest,
+    record: SocialLoginState,
+    location: &str,
+    states: &DynStateStore,
+) -> Result<HttpResponse, OAuth2Error> {
+    let binding = browser_binding(req);
+    states.save(&record.with_browser_binding(&binding)).await?;
+
+    Ok(HttpResponse::Found()
+        .append_header(("Location", location))
+        .cookie(browser_binding_cookie(binding))
+        .finish())
+}
+
+/// Redirect to the provider, recording `state`, the PKCE verifier and (for OpenID Connect
+/// providers) a `nonce` in the state store for the callback.
+async fn redirect_to_provider(
+    req: &HttpRequest,
+    client: &ConfiguredClient,
+    provider: &str,
+    scopes: &[&str],
+    openid: bool,
+    states: &DynStateStore,
+) -> Result<HttpResponse, OAuth2Error> {
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+    let nonce = openid.then(|| CsrfToken::new_random().into_secret());
+
+    let mut request = client
+        .authorize_url(CsrfToken::new_random)
+        .add_scopes(scopes.iter().map(|scope| Scope::new(scope.to_string())))
+        .set_pkce_challenge(pkce_challenge);
+    if let Some(nonce) = &nonce {
+        request = request.add_extra_param("nonce", nonce.as_str());
+    }
+    let (auth_url, csrf_token) = request.url();
+
+    let mut record = SocialLoginState::new(csrf_token.into_secret(), provider.to_string())
+        .with_pkce_verifier(pkce_verifier.into_secret());
+    if let Some(nonce) = nonce {
+        record = record.with_nonce(nonce);
+    }
+    save_and_redirect(req, record, auth_url.as_str(), states).await
+}
+
+/// Provider config with `redirect_uri` defaulted to this server's callback URL, so the
+/// login redirect and the code exchange use the same value.
+fn with_default_redirect_uri(
+    provider_config: &ProviderConfig,
+    provider: &str,
+    req: &HttpRequest,
+    issuer_urls: Option<&IssuerUrls>,
+) -> ProviderConfig {
+    let mut provider_config = provider_config.clone();
+    if provider_config.redirect_uri.is_none() {
+        provider_config.redirect_uri = Some(
+            issuer_urls
+                .cloned()
+                .unwrap_or_default()
+                .social_callback(&RequestOrigin::from(req), provider),
+        );
+    }
+    provider_config
 }
 
 /// Initiate Google login
 pub async fn google_login(
+    req: HttpRequest,
     config: web::Data<Arc<SocialLoginConfig>>,
-    session: Session,
+    issuer_urls: Option<web::Data<IssuerUrls>>,
+    states: web::Data<DynStateStore>,
 ) -> Result<HttpResponse, OAuth2Error> {
     let provider_config = config.google.as_ref().ok_or_else(|| {
         OAuth2Error::new(
@@ -29,38 +133,31 @@ pub async fn google_login(
         )
     })?;
 
-    let client = SocialLoginService::get_google_client(provider_config)?;
-
-    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
-
-    let (auth_url, csrf_token) = client
-        .authorize_url(CsrfToken::new_random)
-        .add_scope(Scope::new("openid".to_string()))
-        .add_scope(Scope::new("email".to_string()))
-        .add_scope(Scope::new("profile".to_string()))
-        .set_pkce_challenge(pkce_challenge)
-        .url();
-
-    // Store CSRF token and PKCE verifier in session
-    session
-        .insert("csrf_token", csrf_token.secret())
-        .map_err(|e| OAuth2Error::new("session_error", Some(&e.to_string())))?;
-    session
-        .insert("pkce_verifier", pkce_verifier.secret())
-        .map_err(|e| OAuth2Error::new("session_error", Some(&e.to_string())))?;
-    session
-        .insert("provider", "google")
-        .map_err(|e| OAuth2Error::new("session_error", Some(&e.to_string())))?;
-
-    Ok(HttpResponse::Found()
-        .append_header(("Location", auth_url.to_string()))
-        .finish())
+    let provider_config = with_default_redirect_uri(
+        provider_config,
+        "google",
+        &req,
+        issuer_urls.as_ref().map(|urls| urls.get_ref()),
+    );
+    let client = SocialLoginService::get_google_client(&provider_config)?;
+
+    redirect_to_provider(
+        &req,
+        &client,
+        "google",
+        &["openid", "email", "profile"],
+        true,
+        &states,
+    )
+    .await
 }
 
 /// Initiate Microsoft login
 pub async fn microsoft_login(
+    req: HttpRequest,
     config: web::Data<Arc<SocialLoginConfig>>,
-    session: Session,
+    issuer_urls: Option<web::Data<IssuerUrls>>,
+    states: web::Data<DynStateStore>,
 ) -> Result<HttpResponse, OAuth2Error> {
     let provider_config = config.microsoft.as_ref().ok_or_else(|| {
         OAuth2Error::new(
@@ -69,31 +166,31 @@ pub async fn microsoft_login(
         )
     })?;
 
-    let client = SocialLoginService::get_microsoft_client(provider_config)?;
-
-    let (auth_url, csrf_token) = client
-        .authorize_url(CsrfToken::new_random)
-        .add_scope(Scope::new("openid".to_string()))
-        .add_scope(Scope::new("email".to_string()))
-        .add_scope(Scope::new("profile".to_string()))
-        .url();
-
-    session
-        .insert("csrf_token", csrf_token.secret())
-        .map_err(|e| OAuth2Error::new("session_error", Some(&e.to_string())))?;
-    session
-        .insert("provider", "microsoft")
-        .map_err(|e| OAuth2Error::new("session_error", Some(&e.to_string())))?;
-
-    Ok(HttpResponse::Found()
-        .append_header(("Location", auth_url.to_string()))
-        .finish())
+    let provider_config = with_default_redirect_uri(
+        provider_config,
+        "microsoft",
+        &req,
+        issuer_urls.as_ref().map(|urls| urls.get_ref()),
+    );
+    let client = SocialLoginService::get_microsoft_client(&provider_config)?;
+
+    redirect_to_provider(
+        &req,
+        &client,
+        "microsoft",
+        &["openid", "email", "profile"],
+        true,
+        &states,
+    )
+    .await
 }
 
 /// Initiate GitHub login
 pub async fn github_login(
+    req: HttpRequest,
     config: web::Data<Arc<SocialLoginConfig>>,
-    session: Session,
+    issuer_urls: Option<web::Data<IssuerUrls>>,
+    states: web::Data<DynStateStore>,
 ) -> Result<HttpResponse, OAuth2Error> {
     let provider_config = config.github.as_ref().ok_or_else(|| {
         OAuth2Error::new(
@@ -102,139 +199,717 @@ pub async fn github_login(
         )
     })?;
 
-    let client = SocialLoginService::get_github_client(provider_config)?;
+    let provider_config = with_default_redirect_uri(
+        provider_config,
+        "github",
+        &req,
+        issuer_urls.as_ref().map(|urls| urls.get_ref()),
+    );
+    let client = SocialLoginService::get_github_client(&provider_config)?;
 
-    let (auth_url, csrf_token) = client
-        .authorize_url(CsrfToken::new_random)
-        .add_scope(Scope::new("user:email".to_string()))
-        .url();
+    redirect_to_provider(&req, &client, "github", &["user:email"], false, &states).await
+}
 
-    session
-        .insert("csrf_token", csrf_token.secret())
-        .map_err(|e| OAuth2Error::new("session_error", Some(&e.to_string())))?;
-    session
-        .insert("provider", "github")
-        .map_err(|e| OAuth2Error::new("session_error", Some(&e.to_string())))?;
+/// Initiate GitLab login
+pub async fn gitlab_login(
+    req: HttpRequest,
+    config: web::Data<Arc<SocialLoginConfig>>,
+    issuer_urls: Option<web::Data<IssuerUrls>>,
+    states: web::Data<DynStateStore>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let provider_config = config.gitlab.as_ref().ok_or_else(|| {
+        OAuth2Error::new(
+            "provider_not_configured",
+            Some("GitLab login not configured"),
+        )
+    })?;
 
-    Ok(HttpResponse::Found()
-        .append_header(("Location", auth_url.to_string()))
-        .finish())
+    let provider_config = with_default_redirect_uri(
+        provider_config,
+        "gitlab",
+        &req,
+        issuer_urls.as_ref().map(|urls| urls.get_ref()),
+    );
+    let client = SocialLoginService::get_gitlab_client(&provider_config)?;
+
+    redirect_to_provider(&req, &client, "gitlab", &["read_user"], false, &states).await
+}
+
+/// Initiate Discord login
+pub async fn discord_login(
+    req: HttpRequest,
+    config: web::Data<Arc<SocialLoginConfig>>,
+    issuer_urls: Option<web::Data<IssuerUrls>>,
+    states: web::Data<DynStateStore>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let provider_config = config.discord.as_ref().ok_or_else(|| {
+        OAuth2Error::new(
+            "provider_not_configured",
+            Some("Discord login not configured"),
+        )
+    })?;
+
+    let provider_config = with_default_redirect_uri(
+        provider_config,
+        "discord",
+        &req,
+        issuer_urls.as_ref().map(|urls| urls.get_ref()),
+    );
+    let client = SocialLoginService::get_discord_client(&provider_config)?;
+
+    redirect_to_provider(
+        &req,
+        &client,
+        "discord",
+        &["identify", "email"],
+        false,
+        &states,
+    )
+    .await
+}
+
+/// Initiate LinkedIn login
+pub async fn linkedin_login(
+    req: HttpRequest,
+    config: web::Data<Arc<SocialLoginConfig>>,
+    issuer_urls: Option<web::Data<IssuerUrls>>,
+    states: web::Data<DynStateStore>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let provider_config = config.linkedin.as_ref().ok_or_else(|| {
+        OAuth2Error::new(
+            "provider_not_configured",
+            Some("LinkedIn login not configured"),
+        )
+    })?;
+
+    let provider_config = with_default_redirect_uri(
+        provider_config,
+        "linkedin",
+        &req,
+        issuer_urls.as_ref().map(|urls| urls.get_ref()),
+    );
+    let client = SocialLoginService::get_linkedin_client(&provider_config)?;
+
+    redirect_to_provider(
+        &req,
+        &client,
+        "linkedin",
+        &["openid", "profile", "email"],
+        true,
+        &states,
+    )
+    .await
+}
+
+/// Initiate Facebook login
+pub async fn facebook_login(
+    req: HttpRequest,
+    config: web::Data<Arc<SocialLoginConfig>>,
+    issuer_urls: Option<web::Data<IssuerUrls>>,
+    states: web::Data<DynStateStore>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let provider_config = config.facebook.as_ref().ok_or_else(|| {
+        OAuth2Error::new(
+            "provider_not_configured",
+            Some("Facebook login not configured"),
+        )
+    })?;
+
+    let provider_config = with_default_redirect_uri(
+        provider_config,
+        "facebook",
+        &req,
+        issuer_urls.as_ref().map(|urls| urls.get_ref()),
+    );
+    let client = SocialLoginService::get_facebook_client(&provider_config)?;
+
+    redirect_to_provider(
+        &req,
+        &client,
+        "facebook",
+        &["email", "public_profile"],
+        false,
+        &states,
+    )
+    .await
+}
+
+/// Initiate Sign in with Apple. Apple posts the callback (`response_mode=form_post`)
+/// when the `name` or `email` scope is requested.
+pub async fn apple_login(
+    req: HttpRequest,
+    config: web::Data<Arc<SocialLoginConfig>>,
+    issuer_urls: Option<web::Data<IssuerUrls>>,
+    states: web::Data<DynStateStore>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let provider_config = config.apple.as_ref().ok_or_else(|| {
+        OAuth2Error::new(
+            "provider_not_configured",
+            Some("Apple login not configured"),
+        )
+    })?;
+
+    let provider_config = with_default_redirect_uri(
+        provider_config,
+        "apple",
+        &req,
+        issuer_urls.as_ref().map(|urls| urls.get_ref()),
+    );
+    let client = SocialLoginService::get_apple_client(&provider_config)?;
+
+    let mut request = client.authorize_url("name email")?;
+    request
+        .url
+        .query_pairs_mut()
+        .append_pair("response_mode", "form_post");
+    let record = SocialLoginState::new(request.state, "apple".to_string())
+        .with_pkce_verifier(request.code_verifier)
+        .with_nonce(request.nonce);
+    save_and_redirect(&req, record, request.url.as_str(), &states).await
+}
+
+/// Initiate login with a generic OpenID Connect provider (`social.generic.<provider>`)
+pub async fn generic_login(
+    req: HttpRequest,
+    provider: web::Path<String>,
+    config: web::Data<Arc<SocialLoginConfig>>,
+    issuer_urls: Option<web::Data<IssuerUrls>>,
+    states: web::Data<DynStateStore>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let provider_config = config.generic_provider(&provider).ok_or_else(|| {
+        OAuth2Error::new(
+            "provider_not_configured",
+            Some(&format!("{} login not configured", provider)),
+        )
+    })?;
+
+    let provider_config = with_default_redirect_uri(
+        provider_config,
+        &provider,
+        &req,
+        issuer_urls.as_ref().map(|urls| urls.get_ref()),
+    );
+    let (client, _) = SocialLoginService::get_generic_client(&provider_config, &provider).await?;
+
+    redirect_to_provider(
+        &req,
+        &client,
+        &provider,
+        &["openid", "email", "profile"],
+        true,
+        &states,
+    )
+    .await
 }
 
 /// Handle OAuth callback from providers
+#[allow(clippy::too_many_arguments)] // actix extractors
 pub async fn auth_callback(
+    req: HttpRequest,
     query: web::Query<AuthCallbackQuery>,
     provider: web::Path<String>,
     config: web::Data<Arc<SocialLoginConfig>>,
+    issuer_urls: Option<web::Data<IssuerUrls>>,
+    states: web::Data<DynStateStore>,
+    storage: web::Data<DynStorage>,
+    vault: Option<web::Data<TokenVault>>,
     session: Session,
+    audit: Option<web::Data<DynAuditSink>>,
 ) -> Result<HttpResponse, OAuth2Error> {
-    // Verify CSRF token
-    let stored_csrf: Option<String> = session
-        .get("csrf_token")
-        .map_err(|e| OAuth2Error::new("session_error", Some(&e.to_string())))?;
-
-    if let Some(state) = &query.state {
-        if Some(state.clone()) != stored_csrf {
-            return Err(OAuth2Error::access_denied("CSRF token mismatch"));
-        }
-    }
+    let issuer_urls = issuer_urls.as_ref().map(|urls| urls.get_ref());
+    let result = complete_login(
+        &req,
+        &query,
+        &provider,
+        &config,
+        issuer_urls,
+        &states,
+        &storage,
+        vault.as_ref().map(|vault| vault.get_ref()),
+        session.clone(),
+    )
+    .await;
+    let audit = audit.as_ref().map(|audit| audit.get_ref());
+    audit_login(&req, &session, &provider, &result, audit).await;
+    result
+}
 
-    let stored_provider: Option<String> = session
-        .get("provider")
-        .map_err(|e| OAuth2Error::new("session_error", Some(&e.to_string())))?;
+/// Handle callbacks posted by `response_mode=form_post` providers (Apple)
+#[allow(clippy::too_many_arguments)] // actix extractors
+pub async fn auth_callback_form(
+    req: HttpRequest,
+    form: web::Form<AuthCallbackQuery>,
+    provider: web::Path<String>,
+    config: web::Data<Arc<SocialLoginConfig>>,
+    issuer_urls: Option<web::Data<IssuerUrls>>,
+    states: web::Data<DynStateStore>,
+    storage: web::Data<DynStorage>,
+    vault: Option<web::Data<TokenVault>>,
+    session: Session,
+    audit: Option<web::Data<DynAuditSink>>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let issuer_urls = issuer_urls.as_ref().map(|urls| urls.get_ref());
+    let result = complete_login(
+        &req,
+        &form,
+        &provider,
+        &config,
+        issuer_urls,
+        &states,
+        &storage,
+        vault.as_ref().map(|vault| vault.get_ref()),
+        session.clone(),
+    )
+    .await;
+    let audit = audit.as_ref().map(|audit| audit.get_ref());
+    audit_login(&req, &session, &provider, &result, audit).await;
+    result
+}
 
-    if stored_provider.as_deref() != Some(provider.as_str()) {
+#[allow(clippy::too_many_arguments)]
+async fn complete_login(
+    req: &HttpRequest,
+    query: &AuthCallbackQuery,
+    provider: &str,
+    config: &SocialLoginConfig,
+    issuer_urls: Option<&IssuerUrls>,
+    states: &DynStateStore,
+    storage: &DynStorage,
+    vault: Option<&TokenVault>,
+    session: Session,
+) -> Result<HttpResponse, OAuth2Error> {
+    // The state is looked up server-side and consumed, so the callback cannot be replayed,
+    // and must come from the browser that started the login.
+    let state = query
+        .state
+        .as_deref()
+        .ok_or_else(|| OAuth2Error::access_denied("Missing state"))?;
+    let record = states
+        .take(state)
+        .await?
+        .ok_or_else(|| OAuth2Error::access_denied("Unknown or already used state"))?;
+    check_browser_binding(req, &record, config.cookieless_state)?;
+    if record.is_expired() {
+        return Err(OAuth2Error::access_denied("Login attempt expired"));
+    }
+    if record.provider != provider {
         return Err(OAuth2Error::invalid_request("Provider mismatch"));
     }
 
     // Exchange code for token based on provider
-    let user_info = match provider.as_str() {
-        "google" => handle_google_callback(&query.code, config.as_ref(), &session).await?,
-        "microsoft" => handle_microsoft_callback(&query.code, config.as_ref(), &session).await?,
-        "github" => handle_github_callback(&query.code, config.as_ref(), &session).await?,
-        _ => return Err(OAuth2Error::invalid_request("Unsupported provider")),
+    let code = query.code.as_str();
+    let (user_info, tokens) = match provider {
+        "google" => handle_google_callback(code, &record, config, req, issuer_urls).await?,
+        "microsoft" => handle_microsoft_callback(code, &record, config, req, issuer_urls).await?,
+        "github" => handle_github_callback(code, &record, config, req, issuer_urls).await?,
+        "gitlab" => handle_gitlab_callback(code, &record, config, req, issuer_urls).await?,
+        "discord" => handle_discord_callback(code, &record, config, req, issuer_urls).await?,
+        "linkedin" => handle_linkedin_callback(code, &record, config, req, issuer_urls).await?,
+        "facebook" => handle_facebook_callback(code, &record, config, req, issuer_urls).await?,
+        "apple" => {
+            let user = query.user.as_deref();
+            handle_apple_callback(code, &record, user, config, req, issuer_urls).await?
+        }
+        name => handle_generic_callback(name, code, &record, config, req, issuer_urls).await?,
     };
 
-    // Store user info in session
+    let (provider, provider_user_id) = (
+        user_info.provider.clone(),
+        user_info.provider_user_id.clone(),
+    );
+    let response = finish_login(storage, &session, user_info).await?;
+    // Only stored when the provider account is linked; otherwise there is nowhere to keep them.
+    if let Some(vault) = vault {
+        SocialLoginService::save_upstream_tokens(
+            storage,
+            vault,
+            &provider,
+            &provider_user_id,
+            &tokens,
+        )
+        .await?;
+    }
+    Ok(response)
+}
+
+/// Refuse a callback from a browser other than the one that started the login, which
+/// would sign that browser in as whoever started it. With `cookieless_state`, a callback
+/// without the cookie is accepted.
+pub fn check_browser_binding(
+    req: &HttpRequest,
+    record: &SocialLoginState,
+    cookieless_state: bool,
+) -> Result<(), OAuth2Error> {
+    match req.cookie(BROWSER_BINDING_COOKIE) {
+        Some(cookie) if record.is_bound_to(cookie.value()) => Ok(()),
+        None if cookieless_state => Ok(()),
+        _ => Err(OAuth2Error::access_denied(
+            "Login was started in another browser",
+        )),
+    }
+}
+
+/// Record the outcome of a login finished by `target` (a provider, or account linking). A
+/// login waiting for the user to confirm account linking is recorded once they do.
+pub async fn audit_login(
+    req: &HttpRequest,
+    session: &Session,
+    target: &str,
+    result: &Result<HttpResponse, OAuth2Error>,
+    audit: Option<&DynAuditSink>,
+) {
+    if result.is_ok() && link::is_pending(session) {
+        return;
+    }
+    let mut record = AuditRecord::from_result(AuditAction::Login, result)
+        .with_target(target)
+        .with_request(req);
+    if result.is_ok() {
+        if let Some(user_id) = session.get::<String>("user_id").unwrap_or(None) {
+            record = record.with_actor(user_id);
+        }
+    }
+    if let (Err(error), Some(event_bus)) = (result, req.app_data::<web::Data<EventBusHandle>>()) {
+        event_bus.publish_best_effort(EventEnvelope::from_current_span(
+            login_failed_event(&record, error),
+            "oauth2_server",
+        ));
+    }
+    record_audit(audit, record).await;
+}
+
+/// `LoginFailed` with the audit record's target, IP address and user agent.
+fn login_failed_event(record: &AuditRecord, error: &OAuth2Error) -> AuthEvent {
+    let mut event = AuthEvent::new(EventType::LoginFailed, EventSeverity::Warning, None, None)
+        .with_metadata("error", error.error.as_str());
+    let fields = [
+        ("target", &record.target),
+        ("ip_address", &record.ip_address),
+        ("user_agent", &record.user_agent),
+    ];
+    for (key, value) in fields {
+        if let Some(value) = value {
+            event = event.with_metadata(key, value.as_str());
+        }
+    }
+    event
+}
+
+/// Sign in with an account the provider vouched for: as the linked local user, through
+/// account linking when only the email matches one, or without a local user.
+pub async fn finish_login(
+    storage: &DynStorage,
+    session: &Session,
+    user_info: SocialUserInfo,
+) -> Result<HttpResponse, OAuth2Error> {
+    let user_id = match SocialLoginService::match_account(storage, &user_info).await? {
+        AccountMatch::Linked(user) if !user.enabled => {
+            return Err(OAuth2Error::access_denied("Account is disabled"));
+        }
+        AccountMatch::Linked(user) => Some(user.id),
+        // The provider's say-so is not proof of owning the local account; the user
+        // confirms with its password first.
+        AccountMatch::SameEmail(user) => return link::begin_link(session, user_info, &user),
+        AccountMatch::Unknown => None,
+    };
+
+    sign_in(session, &user_info, user_id.as_deref())
+}
+
+/// Start an authenticated session, as the local `user_id` when the provider account is
+/// linked to one, and redirect to the success page.
+pub(crate) fn sign_in(
+    session: &Session,
+    user_info: &SocialUserInfo,
+    user_id: Option<&str>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let session_error = |e: actix_session::SessionInsertError| {
+        OAuth2Error::new("session_error", Some(&e.to_string()))
+    };
+    let user_info = serde_json::to_string(user_info)
+        .map_err(|e| OAuth2Error::new("server_error", Some(&e.to_string())))?;
+    session.renew();
     session
-        .insert("user_info", serde_json::to_string(&user_info).unwrap())
-        .map_err(|e| OAuth2Error::new("session_error", Some(&e.to_string())))?;
+        .insert("user_info", user_info)
+        .map_err(session_error)?;
+    if let Some(user_id) = user_id {
+        session.insert("user_id", user_id).map_err(session_error)?;
+    }
     session
         .insert("authenticated", true)
-        .map_err(|e| OAuth2Error::new("session_error", Some(&e.to_string())))?;
+        .map_err(session_error)?;
 
-    // Redirect to success page
     Ok(HttpResponse::Found()
         .append_header(("Location", "/auth/success"))
         .finish())
 }
 
-async fn handle_google_callback(
+/// Exchange the provider's authorization code with the login's PKCE verifier, returning
+/// the provider tokens. When the login sent a `nonce`, the provider must return an ID token
+/// from `id_token_issuer` that echoes it.
+async fn exchange_code(
+    client: &ConfiguredClient,
+    provider_config: &ProviderConfig,
+    id_token_issuer: Option<&IdTokenIssuer>,
     code: &str,
-    config: &SocialLoginConfig,
-    _session: &Session,
-) -> Result<SocialUserInfo, OAuth2Error> {
-    let provider_config = config.google.as_ref().ok_or_else(|| {
-        OAuth2Error::new("provider_not_configured", Some("Google not configured"))
-    })?;
-
-    let client = SocialLoginService::get_google_client(provider_config)?;
+    record: &SocialLoginState,
+) -> Result<TokenSet, OAuth2Error> {
+    let mut request = client.exchange_code(AuthorizationCode::new(code.to_string()));
+    if let Some(verifier) = &record.pkce_verifier {
+        request = request.set_pkce_verifier(PkceCodeVerifier::new(verifier.clone()));
+    }
 
     // oauth2 implements its async HTTP client trait for reqwest 0.12.
     // We standardize on reqwest 0.12 (rustls) here to keep cross-compilation (arm64) OpenSSL-free.
     let http_client = reqwest::Client::new();
-    let token_result = client
-        .exchange_code(AuthorizationCode::new(code.to_string()))
+    let token_result = request
         .request_async(&http_client)
         .await
         .map_err(|e| OAuth2Error::new("token_exchange_failed", Some(&e.to_string())))?;
 
-    let access_token = token_result.access_token().secret();
-    SocialLoginService::fetch_google_user_info(access_token).await
+    if let Some(nonce) = &record.nonce {
+        let id_token = token_result
+            .extra_fields()
+            .id_token
+            .as_deref()
+            .ok_or_else(|| OAuth2Error::access_denied("Provider returned no ID token"))?;
+        let issuer = id_token_issuer.ok_or_else(|| {
+            OAuth2Error::new("server_error", Some("Provider has no ID token issuer"))
+        })?;
+        SocialLoginService::verify_id_token(id_token, issuer, provider_config, nonce).await?;
+    }
+
+    Ok(SocialLoginService::token_set(&token_result))
+}
+
+async fn handle_google_callback(
+    code: &str,
+    record: &SocialLoginState,
+    config: &SocialLoginConfig,
+    req: &HttpRequest,
+    issuer_urls: Option<&IssuerUrls>,
+) -> Result<(SocialUserInfo, TokenSet), OAuth2Error> {
+    let provider_config = config.google.as_ref().ok_or_else(|| {
+        OAuth2Error::new("provider_not_configured", Some("Google not configured"))
+    })?;
+
+    let provider_config = with_default_redirect_uri(provider_config, "google", req, issuer_urls);
+    let client = SocialLoginService::get_google_client(&provider_config)?;
+
+    let tokens = exchange_code(
+        &client,
+        &provider_config,
+        Some(&SocialLoginService::google_id_token_issuer()),
+        code,
+        record,
+    )
+    .await?;
+    let user_info = SocialLoginService::fetch_google_user_info(&tokens.access_token).await?;
+    Ok((user_info, tokens))
 }
 
 async fn handle_microsoft_callback(
     code: &str,
+    record: &SocialLoginState,
     config: &SocialLoginConfig,
-    _session: &Session,
-) -> Result<SocialUserInfo, OAuth2Error> {
+    req: &HttpRequest,
+    issuer_urls: Option<&IssuerUrls>,
+) -> Result<(SocialUserInfo, TokenSet), OAuth2Error> {
     let provider_config = config.microsoft.as_ref().ok_or_else(|| {
         OAuth2Error::new("provider_not_configured", Some("Microsoft not configured"))
     })?;
 
-    let client = SocialLoginService::get_microsoft_client(provider_config)?;
-
-    let http_client = reqwest::Client::new();
-    let token_result = client
-        .exchange_code(AuthorizationCode::new(code.to_string()))
-        .request_async(&http_client)
-        .await
-        .map_err(|e| OAuth2Error::new("token_exchange_failed", Some(&e.to_string())))?;
-
-    let access_token = token_result.access_token().secret();
-    SocialLoginService::fetch_microsoft_user_info(access_token).await
+    let provider_config = with_default_redirect_uri(provider_config, "microsoft", req, issuer_urls);
+    let client = SocialLoginService::get_microsoft_client(&provider_config)?;
+
+    let tokens = exchange_code(
+        &client,
+        &provider_config,
+        Some(&SocialLoginService::microsoft_id_token_issuer(
+            &provider_config,
+        )),
+        code,
+        record,
+    )
+    .await?;
+    let user_info = SocialLoginService::fetch_microsoft_user_info(&tokens.access_token).await?;
+    Ok((user_info, tokens))
 }
 
 async fn handle_github_callback(
     code: &str,
+    record: &SocialLoginState,
     config: &SocialLoginConfig,
-    _session: &Session,
-) -> Result<SocialUserInfo, OAuth2Error> {
+    req: &HttpRequest,
+    issuer_urls: Option<&IssuerUrls>,
+) -> Result<(SocialUserInfo, TokenSet), OAuth2Error> {
     let provider_config = config.github.as_ref().ok_or_else(|| {
         OAuth2Error::new("provider_not_configured", Some("GitHub not configured"))
     })?;
 
-    let client = SocialLoginService::get_github_client(provider_config)?;
+    let provider_config = with_default_redirect_uri(provider_config, "github", req, issuer_urls);
+    let client = SocialLoginService::get_github_client(&provider_config)?;
 
-    let http_client = reqwest::Client::new();
-    let token_result = client
-        .exchange_code(AuthorizationCode::new(code.to_string()))
-        .request_async(&http_client)
-        .await
-        .map_err(|e| OAuth2Error::new("token_exchange_failed", Some(&e.to_string())))?;
+    let tokens = exchange_code(&client, &provider_config, None, code, record).await?;
+    let user_info = SocialLoginService::fetch_github_user_info(&tokens.access_token).await?;
+    Ok((user_info, tokens))
+}
+
+async fn handle_gitlab_callback(
+    code: &str,
+    record: &SocialLoginState,
+    config: &SocialLoginConfig,
+    req: &HttpRequest,
+    issuer_urls: Option<&IssuerUrls>,
+) -> Result<(SocialUserInfo, TokenSet), OAuth2Error> {
+    let provider_config = config.gitlab.as_ref().ok_or_else(|| {
+        OAuth2Error::new("provider_not_configured", Some("GitLab not configured"))
+    })?;
+
+    let provider_config = with_default_redirect_uri(provider_config, "gitlab", req, issuer_urls);
+    let client = SocialLoginService::get_gitlab_client(&provider_config)?;
+
+    let tokens = exchange_code(&client, &provider_config, None, code, record).await?;
+    let user_info =
+        SocialLoginService::fetch_gitlab_user_info(&provider_config, &tokens.access_token).await?;
+    Ok((user_info, tokens))
+}
+
+async fn handle_discord_callback(
+    code: &str,
+    record: &SocialLoginState,
+    config: &SocialLoginConfig,
+    req: &HttpRequest,
+    issuer_urls: Option<&IssuerUrls>,
+) -> Result<(SocialUserInfo, TokenSet), OAuth2Error> {
+    let provider_config = config.discord.as_ref().ok_or_else(|| {
+        OAuth2Error::new("provider_not_configured", Some("Discord not configured"))
+    })?;
+
+    let provider_config = with_default_redirect_uri(provider_config, "discord", req, issuer_urls);
+    let client = SocialLoginService::get_discord_client(&provider_config)?;
+
+    let tokens = exchange_code(&client, &provider_config, None, code, record).await?;
+    let user_info = SocialLoginService::fetch_discord_user_info(&tokens.access_token).await?;
+    Ok((user_info, tokens))
+}
+
+async fn handle_linkedin_callback(
+    code: &str,
+    record: &SocialLoginState,
+    config: &SocialLoginConfig,
+    req: &HttpRequest,
+    issuer_urls: Option<&IssuerUrls>,
+) -> Result<(SocialUserInfo, TokenSet), OAuth2Error> {
+    let provider_config = config.linkedin.as_ref().ok_or_else(|| {
+        OAuth2Error::new("provider_not_configured", Some("LinkedIn not configured"))
+    })?;
+
+    let provider_config = with_default_redirect_uri(provider_config, "linkedin", req, issuer_urls);
+    let client = SocialLoginService::get_linkedin_client(&provider_config)?;
+
+    let tokens = exchange_code(
+        &client,
+        &provider_config,
+        Some(&SocialLoginService::linkedin_id_token_issuer()),
+        code,
+        record,
+    )
+    .await?;
+    let user_info = SocialLoginService::fetch_linkedin_user_info(&tokens.access_token).await?;
+    Ok((user_info, tokens))
+}
+
+async fn handle_facebook_callback(
+    code: &str,
+    record: &SocialLoginState,
+    config: &SocialLoginConfig,
+    req: &HttpRequest,
+    issuer_urls: Option<&IssuerUrls>,
+) -> Result<(SocialUserInfo, TokenSet), OAuth2Error> {
+    let provider_config = config.facebook.as_ref().ok_or_else(|| {
+        OAuth2Error::new("provider_not_configured", Some("Facebook not configured"))
+    })?;
+
+    let provider_config = with_default_redirect_uri(provider_config, "facebook", req, issuer_urls);
+    let client = SocialLoginService::get_facebook_client(&provider_config)?;
+
+    let tokens = exchange_code(&client, &provider_config, None, code, record).await?;
+    let user_info = SocialLoginService::fetch_facebook_user_info(&tokens.access_token).await?;
+    Ok((user_info, tokens))
+}
+
+/// Apple identifies the user in the ID token, verified against Apple's keys and the
+/// nonce sent with the login.
+async fn handle_apple_callback(
+    code: &str,
+    record: &SocialLoginState,
+    user: Option<&str>,
+    config: &SocialLoginConfig,
+    req: &HttpRequest,
+    issuer_urls: Option<&IssuerUrls>,
+) -> Result<(SocialUserInfo, TokenSet), OAuth2Error> {
+    let provider_config = config
+        .apple
+        .as_ref()
+        .ok_or_else(|| OAuth2Error::new("provider_not_configured", Some("Apple not configured")))?;
+
+    let provider_config = with_default_redirect_uri(provider_config, "apple", req, issuer_urls);
+    let client = SocialLoginService::get_apple_client(&provider_config)?;
+
+    let tokens = client
+        .redeem_code(
+            code,
+            record.pkce_verifier.as_deref(),
+            record.nonce.as_deref(),
+        )
+        .await?;
+    let claims = tokens
+        .id_token_claims
+        .as_ref()
+        .ok_or_else(|| OAuth2Error::new("provider_error", Some("Apple sent no ID token")))?;
+    let user_info = SocialLoginService::apple_user_info(claims, user)?;
+    Ok((user_info, tokens))
+}
+
+async fn handle_generic_callback(
+    provider: &str,
+    code: &str,
+    record: &SocialLoginState,
+    config: &SocialLoginConfig,
+    req: &HttpRequest,
+    issuer_urls: Option<&IssuerUrls>,
+) -> Result<(SocialUserInfo, TokenSet), OAuth2Error> {
+    let provider_config = config
+        .generic_provider(provider)
+        .ok_or_else(|| OAuth2Error::invalid_request("Unsupported provider"))?;
+
+    let provider_config = with_default_redirect_uri(provider_config, provider, req, issuer_urls);
+    let (client, metadata) =
+        SocialLoginService::get_generic_client(&provider_config, provider).await?;
+    let userinfo_endpoint = metadata.userinfo_endpoint.as_deref().ok_or_else(|| {
+        OAuth2Error::new(
+            "provider_error",
+            Some(&format!(
+                "{} does not advertise a userinfo_endpoint",
+                provider
+            )),
+        )
+    })?;
 
-    let access_token = token_result.access_token().secret();
-    SocialLoginService::fetch_github_user_info(access_token).await
+    let id_token_issuer = SocialLoginService::generic_id_token_issuer(&metadata);
+    let tokens = exchange_code(
+        &client,
+        &provider_config,
+        Some(&id_token_issuer),
+        code,
+        record,
+    )
+    .await?;
+    let user_info =
+        SocialLoginService::fetch_oidc_user_info(provider, userinfo_endpoint, &tokens.access_token)
+            .await?;
+    Ok((user_info, tokens))
 }
 
 /// Display login page
@@ -0,0 +1,162 @@
+use actix_session::Session;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use serde::{Deserialize, Serialize};
+
+use oauth2_core::{verify_password, FederatedIdentity, OAuth2Error, User};
+use oauth2_ports::{DynAuditSink, DynStorage};
+
+use super::auth::{audit_login, sign_in};
+use crate::models::{Connection, SocialUserInfo};
+
+const PENDING_LINK_KEY: &str = "pending_link";
+
+/// A social login waiting for the user to confirm linking it to an existing account.
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingLink {
+    user_id: String,
+    username: String,
+    user_info: SocialUserInfo,
+}
+
+#[derive(Deserialize)]
+pub struct LinkAccountForm {
+    password: String,
+}
+
+/// Remember the login and send the user to the confirmation page.
+pub(crate) fn begin_link(
+    session: &Session,
+    user_info: SocialUserInfo,
+    user: &User,
+) -> Result<HttpResponse, OAuth2Error> {
+    let pending = PendingLink {
+        user_id: user.id.clone(),
+        username: user.username.clone(),
+        user_info,
+    };
+    session
+        .insert(PENDING_LINK_KEY, pending)
+        .map_err(|e| OAuth2Error::new("session_error", Some(&e.to_string())))?;
+
+    Ok(HttpResponse::Found()
+        .append_header(("Location", "/auth/link"))
+        .finish())
+}
+
+/// Whether a login is waiting for the user to confirm account linking.
+pub(crate) fn is_pending(session: &Session) -> bool {
+    session.entries().contains_key(PENDING_LINK_KEY)
+}
+
+/// Ask for the existing account's password before linking the provider account to it
+pub async fn link_account_page(session: Session) -> Result<HttpResponse> {
+    let Some(pending) = session.get::<PendingLink>(PENDING_LINK_KEY).unwrap_or(None) else {
+        return Ok(HttpResponse::Found()
+            .append_header(("Location", "/auth/login"))
+            .finish());
+    };
+
+    let html = format!(
+        r#"
+        <!DOCTYPE html>
+        <html>
+        <head>
+            <title>Link Account</title>
+            <link rel="stylesheet" href="/static/css/admin.css">
+        </head>
+        <body>
+            <div class="container">
+                <h1>Link your {provider} account</h1>
+                <p>The account <strong>{username}</strong> already uses {email}.
+                   Enter its password to sign in with {provider} from now on.</p>
+                <form method="post" action="/auth/link">
+                    <input type="password" name="password" placeholder="Password" required autofocus>
+                    <button type="submit">Link and sign in</button>
+                </form>
+                <a href="/auth/login">Use a different account</a>
+            </div>
+        </body>
+        </html>
+        "#,
+        provider = escape_html(&pending.user_info.provider),
+        username = escape_html(&pending.username),
+        email = escape_html(&pending.user_info.email),
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(html))
+}
+
+/// Link the pending provider account once the password proves the user owns the account,
+/// then sign in. A wrong password ends the attempt; the user starts over at the provider.
+pub async fn confirm_link(
+    req: HttpRequest,
+    form: web::Form<LinkAccountForm>,
+    storage: web::Data<DynStorage>,
+    session: Session,
+    audit: Option<web::Data<DynAuditSink>>,
+) -> Result<HttpResponse, OAuth2Error> {
+    let result = link_pending(&form, &storage, &session).await;
+    let audit = audit.as_ref().map(|audit| audit.get_ref());
+    audit_login(&req, &session, "account_link", &result, audit).await;
+    result
+}
+
+async fn link_pending(
+    form: &LinkAccountForm,
+    storage: &DynStorage,
+    session: &Session,
+) -> Result<HttpResponse, OAuth2Error> {
+    let pending = session
+        .remove_as::<PendingLink>(PENDING_LINK_KEY)
+        .and_then(|pending| pending.ok())
+        .ok_or_else(|| OAuth2Error::access_denied("No account link is pending"))?;
+
+    let user = storage
+        .get_user(&pending.user_id)
+        .await?
+        .filter(|user| user.enabled)
+        .ok_or_else(|| OAuth2Error::access_denied("Account is unavailable"))?;
+    if !verify_password(&form.password, &user.password_hash) {
+        return Err(OAuth2Error::access_denied("Invalid password"));
+    }
+
+    storage
+        .save_federated_identity(&FederatedIdentity::new(
+            pending.user_info.provider.clone(),
+            pending.user_info.provider_user_id.clone(),
+            user.id.clone(),
+        ))
+        .await?;
+
+    sign_in(session, &pending.user_info, Some(&user.id))
+}
+
+/// Provider accounts linked to the signed-in user
+pub async fn connections(
+    storage: web::Data<DynStorage>,
+    session: Session,
+) -> Result<HttpResponse, OAuth2Error> {
+    let user_id = session
+        .get::<String>("user_id")
+        .unwrap_or(None)
+        .ok_or_else(|| OAuth2Error::access_denied("Not signed in to a local account"))?;
+
+    let connections: Vec<Connection> = storage
+        .list_federated_identities(&user_id)
+        .await?
+        .into_iter()
+        .map(Connection::from)
+        .collect();
+    Ok(HttpResponse::Ok().json(connections))
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#x27;")
+}
@@ -1,6 +1,10 @@
 pub mod handlers;
 pub mod models;
 pub mod service;
+pub mod state_store;
+pub mod token_vault;
 
 pub use models::*;
 pub use service::*;
+pub use state_store::*;
+pub use token_vault::TokenVault;
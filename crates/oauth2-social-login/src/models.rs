@@ -1,7 +1,10 @@
+use chrono::{DateTime, Utc};
 use oauth2_config::{ProviderConfig, SocialConfig};
+use oauth2_core::{FederatedIdentity, User};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct SocialLoginConfig {
     pub google: Option<ProviderConfig>,
     pub microsoft: Option<ProviderConfig>,
@@ -9,6 +12,17 @@ pub struct SocialLoginConfig {
     pub azure: Option<ProviderConfig>,
     pub okta: Option<ProviderConfig>,
     pub auth0: Option<ProviderConfig>,
+    pub apple: Option<ProviderConfig>,
+    pub gitlab: Option<ProviderConfig>,
+    pub discord: Option<ProviderConfig>,
+    pub linkedin: Option<ProviderConfig>,
+    pub facebook: Option<ProviderConfig>,
+    /// OpenID Connect providers found through discovery, keyed by provider name.
+    #[serde(default)]
+    pub generic: BTreeMap<String, ProviderConfig>,
+    /// Accept callbacks that arrive without the browser binding cookie.
+    #[serde(default)]
+    pub cookieless_state: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +34,46 @@ pub struct SocialUserInfo {
     pub picture: Option<String>,
 }
 
+/// How a provider account relates to the local users.
+#[derive(Debug, Clone)]
+pub enum AccountMatch {
+    /// Linked to this user through a [`FederatedIdentity`](oauth2_core::FederatedIdentity).
+    Linked(User),
+    /// Not linked, but this user is the only one with the same email. Linking needs the
+    /// user's confirmation.
+    SameEmail(User),
+    /// No local user.
+    Unknown,
+}
+
+/// A provider account linked to the signed-in user, as listed by `GET /auth/connections`.
+/// The tokens themselves are never exposed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Connection {
+    pub provider: String,
+    pub provider_user_id: String,
+    pub linked_at: DateTime<Utc>,
+    /// Whether a provider access token is stored for calls on the user's behalf.
+    pub token_stored: bool,
+    /// Whether it can be refreshed without the user signing in again.
+    pub refreshable: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_expires_at: Option<DateTime<Utc>>,
+}
+
+impl From<FederatedIdentity> for Connection {
+    fn from(identity: FederatedIdentity) -> Self {
+        Self {
+            token_stored: identity.access_token.is_some(),
+            refreshable: identity.refresh_token.is_some(),
+            token_expires_at: identity.token_expires_at,
+            provider: identity.provider,
+            provider_user_id: identity.provider_user_id,
+            linked_at: identity.created_at,
+        }
+    }
+}
+
 impl SocialLoginConfig {
     pub fn from_env() -> Self {
         Self {
@@ -29,6 +83,22 @@ impl SocialLoginConfig {
             azure: Self::provider_from_env("AZURE"),
             okta: Self::provider_from_env("OKTA"),
             auth0: Self::provider_from_env("AUTH0"),
+            apple: Self::provider_from_env("APPLE"),
+            gitlab: Self::provider_from_env("GITLAB"),
+            discord: Self::provider_from_env("DISCORD"),
+            linkedin: Self::provider_from_env("LINKEDIN"),
+            facebook: Self::provider_from_env("FACEBOOK"),
+            generic: Self::provider_from_env("GENERIC")
+                .map(|provider| {
+                    let name = std::env::var("OAUTH2_GENERIC_NAME")
+                        .unwrap_or_else(|_| "generic".to_string());
+                    BTreeMap::from([(name, provider)])
+                })
+                .unwrap_or_default(),
+            cookieless_state: std::env::var("OAUTH2_SOCIAL_COOKIELESS_STATE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(false),
         }
     }
 
@@ -41,23 +111,31 @@ impl SocialLoginConfig {
             azure: social.azure.clone(),
             okta: social.okta.clone(),
             auth0: social.auth0.clone(),
+            apple: social.apple.clone(),
+            gitlab: social.gitlab.clone(),
+            discord: social.discord.clone(),
+            linkedin: social.linkedin.clone(),
+            facebook: social.facebook.clone(),
+            generic: social.generic.clone(),
+            cookieless_state: social.cookieless_state,
         }
     }
 
+    /// The enabled generic provider called `name`.
+    pub fn generic_provider(&self, name: &str) -> Option<&ProviderConfig> {
+        self.generic.get(name).filter(|provider| provider.enabled)
+    }
+
     fn provider_from_env(prefix: &str) -> Option<ProviderConfig> {
         let client_id = std::env::var(format!("OAUTH2_{}_CLIENT_ID", prefix)).ok();
         let client_secret = std::env::var(format!("OAUTH2_{}_CLIENT_SECRET", prefix)).ok();
+        // Apple signs its client secret with this key instead.
+        let private_key = std::env::var(format!("OAUTH2_{}_PRIVATE_KEY", prefix)).ok();
 
-        // Only create config if both client_id and client_secret are set
-        if client_id.is_some() && client_secret.is_some() {
-            let redirect_uri = std::env::var(format!("OAUTH2_{}_REDIRECT_URI", prefix))
-                .ok()
-                .or_else(|| {
-                    Some(format!(
-                        "http://localhost:8080/auth/callback/{}",
-                        prefix.to_lowercase()
-                    ))
-                });
+        // Only create config if client_id and client_secret (or a signing key) are set
+        if client_id.is_some() && (client_secret.is_some() || private_key.is_some()) {
+            // Unset redirect_uri defaults to `/auth/callback/<provider>` under the issuer URL.
+            let redirect_uri = std::env::var(format!("OAUTH2_{}_REDIRECT_URI", prefix)).ok();
 
             Some(ProviderConfig {
                 enabled: true,
@@ -66,6 +144,10 @@ impl SocialLoginConfig {
                 redirect_uri,
                 tenant_id: std::env::var(format!("OAUTH2_{}_TENANT_ID", prefix)).ok(),
                 domain: std::env::var(format!("OAUTH2_{}_DOMAIN", prefix)).ok(),
+                issuer_url: std::env::var(format!("OAUTH2_{}_ISSUER_URL", prefix)).ok(),
+                team_id: std::env::var(format!("OAUTH2_{}_TEAM_ID", prefix)).ok(),
+                key_id: std::env::var(format!("OAUTH2_{}_KEY_ID", prefix)).ok(),
+                private_key,
             })
         } else {
             None
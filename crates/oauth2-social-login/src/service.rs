@@ -1,20 +1,50 @@
+use chrono::{Duration, Utc};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
 use oauth2::{
-    basic::BasicClient, AuthUrl, ClientId, ClientSecret, EndpointNotSet, EndpointSet, RedirectUrl,
-    TokenUrl,
+    AuthUrl, ClientId, ClientSecret, EndpointNotSet, EndpointSet, ExtraTokenFields, RedirectUrl,
+    RefreshToken, TokenResponse, TokenUrl,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
 
+use oauth2_client::{IdTokenClaims, OAuth2Client, ProviderMetadata, TokenSet};
 use oauth2_config::ProviderConfig;
 use oauth2_core::OAuth2Error;
+use oauth2_ports::DynStorage;
 
-use crate::models::SocialUserInfo;
+use crate::models::{AccountMatch, SocialLoginConfig, SocialUserInfo};
+use crate::token_vault::TokenVault;
+
+/// Where an OpenID Connect provider's ID tokens are issued, and the keys they are signed
+/// with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdTokenIssuer {
+    /// Expected `iss`.
+    pub issuer: String,
+    /// Without one, only tokens signed with the client secret (HMAC) are accepted.
+    pub jwks_uri: Option<String>,
+}
+
+/// The OpenID Connect `id_token` member of a token response.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IdTokenFields {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+impl ExtraTokenFields for IdTokenFields {}
+
+/// A provider's token endpoint response.
+pub type ProviderTokenResponse =
+    oauth2::StandardTokenResponse<IdTokenFields, oauth2::basic::BasicTokenType>;
 
 // Type alias for a fully configured OAuth2 client with all required endpoints set.
 // This is necessary due to oauth2 5.0's typestate pattern which tracks endpoint
 // configuration at compile time.
-type ConfiguredClient = oauth2::Client<
+pub type ConfiguredClient = oauth2::Client<
     oauth2::StandardErrorResponse<oauth2::basic::BasicErrorResponseType>,
-    oauth2::StandardTokenResponse<oauth2::EmptyExtraTokenFields, oauth2::basic::BasicTokenType>,
+    ProviderTokenResponse,
     oauth2::StandardTokenIntrospectionResponse<
         oauth2::EmptyExtraTokenFields,
         oauth2::basic::BasicTokenType,
@@ -30,12 +60,34 @@ type ConfiguredClient = oauth2::Client<
 
 pub struct SocialLoginService;
 
+const APPLE_ISSUER: &str = "https://appleid.apple.com";
+/// Apple accepts client secrets valid for up to six months; each one here is minted per
+/// token request, so it only needs to outlive that request.
+const APPLE_CLIENT_SECRET_TTL_SECS: i64 = 300;
+
+const FACEBOOK_API_VERSION: &str = "v19.0";
+const FACEBOOK_GRAPH_URL: &str = "https://graph.facebook.com/v19.0";
+
+/// Upstream access tokens are refreshed this long before they expire.
+const UPSTREAM_REFRESH_MARGIN_SECS: i64 = 60;
+
+/// Stands for the user's tenant in the issuer of Microsoft's multi-tenant endpoints, as
+/// in their discovery documents.
+const TENANT_ID_PLACEHOLDER: &str = "{tenantid}";
+
+/// Discovery documents by issuer URL, so each provider is discovered once per process.
+fn discovered_providers() -> &'static RwLock<HashMap<String, ProviderMetadata>> {
+    static DISCOVERED: OnceLock<RwLock<HashMap<String, ProviderMetadata>>> = OnceLock::new();
+    DISCOVERED.get_or_init(Default::default)
+}
+
 impl SocialLoginService {
-    /// Helper function to validate and extract required provider configuration fields
+    /// Helper function to validate and extract required provider configuration fields.
+    /// The redirect URI is only needed to log in, not to refresh tokens.
     fn validate_provider_config(
         config: &ProviderConfig,
         provider_name: &str,
-    ) -> Result<(String, String, String), OAuth2Error> {
+    ) -> Result<(String, String, Option<String>), OAuth2Error> {
         let client_id = config
             .client_id
             .as_ref()
@@ -56,85 +108,328 @@ impl SocialLoginService {
                 )
             })?
             .clone();
-        let redirect_uri = config
-            .redirect_uri
-            .as_ref()
-            .ok_or_else(|| {
-                OAuth2Error::new(
-                    "invalid_configuration",
-                    Some(&format!("{} redirect_uri not set", provider_name)),
-                )
-            })?
-            .clone();
 
-        Ok((client_id, client_secret, redirect_uri))
+        Ok((client_id, client_secret, config.redirect_uri.clone()))
     }
 
-    pub fn get_google_client(config: &ProviderConfig) -> Result<ConfiguredClient, OAuth2Error> {
+    /// Client for a provider with fixed authorization and token endpoints.
+    fn configured_client(
+        config: &ProviderConfig,
+        provider_name: &str,
+        auth_url: String,
+        token_url: String,
+    ) -> Result<ConfiguredClient, OAuth2Error> {
         let (client_id, client_secret, redirect_uri) =
-            Self::validate_provider_config(config, "Google")?;
+            Self::validate_provider_config(config, provider_name)?;
 
-        Ok(BasicClient::new(ClientId::new(client_id))
+        let redirect_uri = redirect_uri
+            .map(RedirectUrl::new)
+            .transpose()
+            .map_err(|e| OAuth2Error::new("invalid_configuration", Some(&e.to_string())))?;
+
+        let client = oauth2::Client::new(ClientId::new(client_id))
             .set_client_secret(ClientSecret::new(client_secret))
             .set_auth_uri(
-                AuthUrl::new("https://accounts.google.com/o/oauth2/v2/auth".to_string())
+                AuthUrl::new(auth_url)
                     .map_err(|e| OAuth2Error::new("invalid_configuration", Some(&e.to_string())))?,
             )
             .set_token_uri(
-                TokenUrl::new("https://oauth2.googleapis.com/token".to_string())
-                    .map_err(|e| OAuth2Error::new("invalid_configuration", Some(&e.to_string())))?,
-            )
-            .set_redirect_uri(
-                RedirectUrl::new(redirect_uri)
+                TokenUrl::new(token_url)
                     .map_err(|e| OAuth2Error::new("invalid_configuration", Some(&e.to_string())))?,
-            ))
+            );
+        // Only logins need a redirect URI; refreshing tokens works without one.
+        Ok(match redirect_uri {
+            Some(redirect_uri) => client.set_redirect_uri(redirect_uri),
+            None => client,
+        })
     }
 
-    pub fn get_microsoft_client(config: &ProviderConfig) -> Result<ConfiguredClient, OAuth2Error> {
-        let (client_id, client_secret, redirect_uri) =
-            Self::validate_provider_config(config, "Microsoft")?;
+    pub fn get_google_client(config: &ProviderConfig) -> Result<ConfiguredClient, OAuth2Error> {
+        Self::configured_client(
+            config,
+            "Google",
+            "https://accounts.google.com/o/oauth2/v2/auth".to_string(),
+            "https://oauth2.googleapis.com/token".to_string(),
+        )
+    }
 
+    pub fn get_microsoft_client(config: &ProviderConfig) -> Result<ConfiguredClient, OAuth2Error> {
         let tenant = config.tenant_id.as_deref().unwrap_or("common");
-        Ok(BasicClient::new(ClientId::new(client_id))
-            .set_client_secret(ClientSecret::new(client_secret))
-            .set_auth_uri(
-                AuthUrl::new(format!(
-                    "https://login.microsoftonline.com/{}/oauth2/v2.0/authorize",
-                    tenant
-                ))
-                .map_err(|e| OAuth2Error::new("invalid_configuration", Some(&e.to_string())))?,
-            )
-            .set_token_uri(
-                TokenUrl::new(format!(
-                    "https://login.microsoftonline.com/{}/oauth2/v2.0/token",
-                    tenant
-                ))
-                .map_err(|e| OAuth2Error::new("invalid_configuration", Some(&e.to_string())))?,
-            )
-            .set_redirect_uri(
-                RedirectUrl::new(redirect_uri)
-                    .map_err(|e| OAuth2Error::new("invalid_configuration", Some(&e.to_string())))?,
-            ))
+        Self::configured_client(
+            config,
+            "Microsoft",
+            format!(
+                "https://login.microsoftonline.com/{}/oauth2/v2.0/authorize",
+                tenant
+            ),
+            format!(
+                "https://login.microsoftonline.com/{}/oauth2/v2.0/token",
+                tenant
+            ),
+        )
     }
 
     pub fn get_github_client(config: &ProviderConfig) -> Result<ConfiguredClient, OAuth2Error> {
-        let (client_id, client_secret, redirect_uri) =
-            Self::validate_provider_config(config, "GitHub")?;
+        Self::configured_client(
+            config,
+            "GitHub",
+            "https://github.com/login/oauth/authorize".to_string(),
+            "https://github.com/login/oauth/access_token".to_string(),
+        )
+    }
 
-        Ok(BasicClient::new(ClientId::new(client_id))
-            .set_client_secret(ClientSecret::new(client_secret))
-            .set_auth_uri(
-                AuthUrl::new("https://github.com/login/oauth/authorize".to_string())
-                    .map_err(|e| OAuth2Error::new("invalid_configuration", Some(&e.to_string())))?,
-            )
-            .set_token_uri(
-                TokenUrl::new("https://github.com/login/oauth/access_token".to_string())
-                    .map_err(|e| OAuth2Error::new("invalid_configuration", Some(&e.to_string())))?,
-            )
-            .set_redirect_uri(
-                RedirectUrl::new(redirect_uri)
-                    .map_err(|e| OAuth2Error::new("invalid_configuration", Some(&e.to_string())))?,
+    pub fn get_gitlab_client(config: &ProviderConfig) -> Result<ConfiguredClient, OAuth2Error> {
+        let base = Self::gitlab_base_url(config);
+        Self::configured_client(
+            config,
+            "GitLab",
+            format!("{}/oauth/authorize", base),
+            format!("{}/oauth/token", base),
+        )
+    }
+
+    pub fn get_discord_client(config: &ProviderConfig) -> Result<ConfiguredClient, OAuth2Error> {
+        Self::configured_client(
+            config,
+            "Discord",
+            "https://discord.com/oauth2/authorize".to_string(),
+            "https://discord.com/api/oauth2/token".to_string(),
+        )
+    }
+
+    pub fn get_linkedin_client(config: &ProviderConfig) -> Result<ConfiguredClient, OAuth2Error> {
+        Self::configured_client(
+            config,
+            "LinkedIn",
+            "https://www.linkedin.com/oauth/v2/authorization".to_string(),
+            "https://www.linkedin.com/oauth/v2/accessToken".to_string(),
+        )
+    }
+
+    pub fn get_facebook_client(config: &ProviderConfig) -> Result<ConfiguredClient, OAuth2Error> {
+        Self::configured_client(
+            config,
+            "Facebook",
+            format!(
+                "https://www.facebook.com/{}/dialog/oauth",
+                FACEBOOK_API_VERSION
+            ),
+            format!("{}/oauth/access_token", FACEBOOK_GRAPH_URL),
+        )
+    }
+
+    /// GitLab.com, or the self-managed instance named by `domain`.
+    fn gitlab_base_url(config: &ProviderConfig) -> String {
+        format!(
+            "https://{}",
+            config.domain.as_deref().unwrap_or("gitlab.com")
+        )
+    }
+
+    /// Sign in with Apple. Apple has no static client secret: each request carries a
+    /// short-lived ES256 JWT signed with the developer's key, and the user's identity
+    /// comes from the verified ID token rather than a userinfo endpoint.
+    pub fn get_apple_client(config: &ProviderConfig) -> Result<OAuth2Client, OAuth2Error> {
+        let client_id = Self::required(&config.client_id, "Apple", "client_id")?;
+        let metadata = ProviderMetadata {
+            issuer: APPLE_ISSUER.to_string(),
+            authorization_endpoint: format!("{}/auth/authorize", APPLE_ISSUER),
+            token_endpoint: format!("{}/auth/token", APPLE_ISSUER),
+            jwks_uri: Some(format!("{}/auth/keys", APPLE_ISSUER)),
+            userinfo_endpoint: None,
+            introspection_endpoint: None,
+            revocation_endpoint: Some(format!("{}/auth/revoke", APPLE_ISSUER)),
+            code_challenge_methods_supported: Vec::new(),
+            authorization_response_iss_parameter_supported: false,
+        };
+
+        let client = OAuth2Client::new(metadata, client_id)
+            .with_client_secret(Self::apple_client_secret(config)?);
+        Ok(match &config.redirect_uri {
+            Some(redirect_uri) => client.with_redirect_uri(redirect_uri),
+            None => client,
+        })
+    }
+
+    /// The client secret Apple expects: a JWT from the team, about the client, signed
+    /// with the key registered under `key_id`.
+    pub fn apple_client_secret(config: &ProviderConfig) -> Result<String, OAuth2Error> {
+        let client_id = Self::required(&config.client_id, "Apple", "client_id")?;
+        let team_id = Self::required(&config.team_id, "Apple", "team_id")?;
+        let key_id = Self::required(&config.key_id, "Apple", "key_id")?;
+        let private_key = Self::required(&config.private_key, "Apple", "private_key")?;
+
+        #[derive(Serialize)]
+        struct ClientSecretClaims<'a> {
+            iss: &'a str,
+            sub: &'a str,
+            aud: &'a str,
+            iat: i64,
+            exp: i64,
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs() as i64)
+            .unwrap_or_default();
+        let mut header = Header::new(Algorithm::ES256);
+        header.kid = Some(key_id.to_string());
+        let key = EncodingKey::from_ec_pem(private_key.as_bytes())
+            .map_err(|e| OAuth2Error::new("invalid_configuration", Some(&e.to_string())))?;
+
+        jsonwebtoken::encode(
+            &header,
+            &ClientSecretClaims {
+                iss: team_id,
+                sub: client_id,
+                aud: APPLE_ISSUER,
+                iat: now,
+                exp: now + APPLE_CLIENT_SECRET_TTL_SECS,
+            },
+            &key,
+        )
+        .map_err(|e| OAuth2Error::new("invalid_configuration", Some(&e.to_string())))
+    }
+
+    /// Google's ID token issuer.
+    pub fn google_id_token_issuer() -> IdTokenIssuer {
+        IdTokenIssuer {
+            issuer: "https://accounts.google.com".to_string(),
+            jwks_uri: Some("https://www.googleapis.com/oauth2/v3/certs".to_string()),
+        }
+    }
+
+    /// The Microsoft identity platform's ID token issuer for the configured tenant. The
+    /// multi-tenant endpoints issue tokens as the user's own tenant.
+    pub fn microsoft_id_token_issuer(config: &ProviderConfig) -> IdTokenIssuer {
+        let tenant = config.tenant_id.as_deref().unwrap_or("common");
+        let issuer_tenant = match tenant {
+            "common" | "organizations" | "consumers" => TENANT_ID_PLACEHOLDER,
+            tenant => tenant,
+        };
+        IdTokenIssuer {
+            issuer: format!("https://login.microsoftonline.com/{issuer_tenant}/v2.0"),
+            jwks_uri: Some(format!(
+                "https://login.microsoftonline.com/{tenant}/discovery/v2.0/keys"
+            )),
+        }
+    }
+
+    /// LinkedIn's ID token issuer.
+    pub fn linkedin_id_token_issuer() -> IdTokenIssuer {
+        IdTokenIssuer {
+            issuer: "https://www.linkedin.com/oauth".to_string(),
+            jwks_uri: Some("https://www.linkedin.com/oauth/openid/jwks".to_string()),
+        }
+    }
+
+    /// The ID token issuer a generic provider's discovery document describes.
+    pub fn generic_id_token_issuer(metadata: &ProviderMetadata) -> IdTokenIssuer {
+        IdTokenIssuer {
+            issuer: metadata.issuer.clone(),
+            jwks_uri: metadata.jwks_uri.clone(),
+        }
+    }
+
+    /// Verify an ID token from `issuer`: its signature against the provider's keys (or
+    /// the client secret, for HMAC algorithms), its `iss`, `exp`, that it was issued to
+    /// the configured client, and that it is for the login that sent `nonce`.
+    pub async fn verify_id_token(
+        id_token: &str,
+        issuer: &IdTokenIssuer,
+        config: &ProviderConfig,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, OAuth2Error> {
+        let invalid = |e: OAuth2Error| {
+            OAuth2Error::access_denied(&format!(
+                "Invalid ID token: {}",
+                e.error_description.as_deref().unwrap_or(&e.error)
             ))
+        };
+        let client_id = Self::required(&config.client_id, "provider", "client_id")?;
+
+        let mut expected_issuer = issuer.issuer.clone();
+        if expected_issuer.contains(TENANT_ID_PLACEHOLDER) {
+            #[derive(Deserialize)]
+            struct TenantClaim {
+                tid: String,
+            }
+            // Only picks the issuer to expect; the token is verified below.
+            let tenant = jsonwebtoken::dangerous::insecure_decode::<TenantClaim>(id_token)
+                .map_err(|e| OAuth2Error::access_denied(&format!("Invalid ID token: {e}")))?
+                .claims
+                .tid;
+            expected_issuer = expected_issuer.replace(TENANT_ID_PLACEHOLDER, &tenant);
+        }
+
+        let metadata = ProviderMetadata {
+            issuer: expected_issuer,
+            authorization_endpoint: String::new(),
+            token_endpoint: String::new(),
+            jwks_uri: issuer.jwks_uri.clone(),
+            userinfo_endpoint: None,
+            introspection_endpoint: None,
+            revocation_endpoint: None,
+            code_challenge_methods_supported: Vec::new(),
+            authorization_response_iss_parameter_supported: false,
+        };
+        let mut client = OAuth2Client::new(metadata, client_id);
+        if let Some(secret) = &config.client_secret {
+            client = client.with_client_secret(secret);
+        }
+        client
+            .verify_id_token(id_token, Some(nonce))
+            .await
+            .map_err(invalid)
+    }
+
+    /// A required provider setting.
+    fn required<'a>(
+        value: &'a Option<String>,
+        provider_name: &str,
+        field: &str,
+    ) -> Result<&'a str, OAuth2Error> {
+        value.as_deref().ok_or_else(|| {
+            OAuth2Error::new(
+                "invalid_configuration",
+                Some(&format!("{} {} not set", provider_name, field)),
+            )
+        })
+    }
+
+    /// Provider metadata from `{issuer_url}/.well-known/openid-configuration`.
+    pub async fn discover(issuer_url: &str) -> Result<ProviderMetadata, OAuth2Error> {
+        if let Some(metadata) = discovered_providers()
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(issuer_url)
+        {
+            return Ok(metadata.clone());
+        }
+
+        let metadata = ProviderMetadata::discover(&reqwest::Client::new(), issuer_url).await?;
+        discovered_providers()
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(issuer_url.to_string(), metadata.clone());
+        Ok(metadata)
+    }
+
+    /// Client for a generic OpenID Connect provider, with endpoints from discovery.
+    pub async fn get_generic_client(
+        config: &ProviderConfig,
+        provider_name: &str,
+    ) -> Result<(ConfiguredClient, ProviderMetadata), OAuth2Error> {
+        let issuer_url = Self::required(&config.issuer_url, provider_name, "issuer_url")?;
+        let metadata = Self::discover(issuer_url).await?;
+
+        let client = Self::configured_client(
+            config,
+            provider_name,
+            metadata.authorization_endpoint.clone(),
+            metadata.token_endpoint.clone(),
+        )?;
+        Ok((client, metadata))
     }
 
     pub async fn fetch_google_user_info(access_token: &str) -> Result<SocialUserInfo, OAuth2Error> {
@@ -264,4 +559,405 @@ impl SocialLoginService {
             picture: user.avatar_url,
         })
     }
+
+    /// Standard claims from a provider's OpenID Connect userinfo endpoint.
+    pub async fn fetch_oidc_user_info(
+        provider: &str,
+        userinfo_endpoint: &str,
+        access_token: &str,
+    ) -> Result<SocialUserInfo, OAuth2Error> {
+        let client = reqwest::Client::new();
+        let response = client
+            .get(userinfo_endpoint)
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| OAuth2Error::new("provider_error", Some(&e.to_string())))?;
+
+        #[derive(Deserialize)]
+        struct OidcUser {
+            sub: String,
+            email: Option<String>,
+            name: Option<String>,
+            preferred_username: Option<String>,
+            picture: Option<String>,
+        }
+
+        let user: OidcUser = response
+            .json()
+            .await
+            .map_err(|e| OAuth2Error::new("provider_error", Some(&e.to_string())))?;
+
+        Ok(SocialUserInfo {
+            provider: provider.to_string(),
+            provider_user_id: user.sub,
+            email: user
+                .email
+                .ok_or_else(|| OAuth2Error::new("provider_error", Some("No email found")))?,
+            name: user.name.or(user.preferred_username),
+            picture: user.picture,
+        })
+    }
+
+    pub async fn fetch_gitlab_user_info(
+        config: &ProviderConfig,
+        access_token: &str,
+    ) -> Result<SocialUserInfo, OAuth2Error> {
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("{}/api/v4/user", Self::gitlab_base_url(config)))
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .map_err(|e| OAuth2Error::new("provider_error", Some(&e.to_string())))?;
+
+        #[derive(Deserialize)]
+        struct GitLabUser {
+            id: i64,
+            email: Option<String>,
+            name: Option<String>,
+            avatar_url: Option<String>,
+        }
+
+        let user: GitLabUser = response
+            .json()
+            .await
+            .map_err(|e| OAuth2Error::new("provider_error", Some(&e.to_string())))?;
+
+        Ok(SocialUserInfo {
+            provider: "gitlab".to_string(),
+            provider_user_id: user.id.to_string(),
+            email: user
+                .email
+                .ok_or_else(|| OAuth2Error::new("provider_error", Some("No email found")))?,
+            name: user.name,
+            picture: user.avatar_url,
+        })
+    }
+
+    pub async fn fetch_discord_user_info(
+        access_token: &str,
+    ) -> Result<SocialUserInfo, OAuth2Error> {
+        let client = reqwest::Client::new();
+        let response = client
+            .get("https://discord.com/api/users/@me")
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .map_err(|e| OAuth2Error::new("provider_error", Some(&e.to_string())))?;
+
+        #[derive(Deserialize)]
+        struct DiscordUser {
+            id: String,
+            username: String,
+            global_name: Option<String>,
+            email: Option<String>,
+            #[serde(default)]
+            verified: bool,
+            avatar: Option<String>,
+        }
+
+        let user: DiscordUser = response
+            .json()
+            .await
+            .map_err(|e| OAuth2Error::new("provider_error", Some(&e.to_string())))?;
+
+        // Discord accounts can carry an email address nobody has confirmed.
+        let email = user
+            .email
+            .filter(|_| user.verified)
+            .ok_or_else(|| OAuth2Error::new("provider_error", Some("No verified email found")))?;
+        let picture = user.avatar.map(|avatar| {
+            format!(
+                "https://cdn.discordapp.com/avatars/{}/{}.png",
+                user.id, avatar
+            )
+        });
+
+        Ok(SocialUserInfo {
+            provider: "discord".to_string(),
+            provider_user_id: user.id,
+            email,
+            name: user.global_name.or(Some(user.username)),
+            picture,
+        })
+    }
+
+    pub async fn fetch_linkedin_user_info(
+        access_token: &str,
+    ) -> Result<SocialUserInfo, OAuth2Error> {
+        // Sign In with LinkedIn using OpenID Connect.
+        Self::fetch_oidc_user_info(
+            "linkedin",
+            "https://api.linkedin.com/v2/userinfo",
+            access_token,
+        )
+        .await
+    }
+
+    pub async fn fetch_facebook_user_info(
+        access_token: &str,
+    ) -> Result<SocialUserInfo, OAuth2Error> {
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("{}/me", FACEBOOK_GRAPH_URL))
+            .query(&[("fields", "id,name,email,picture")])
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .map_err(|e| OAuth2Error::new("provider_error", Some(&e.to_string())))?;
+
+        #[derive(Deserialize)]
+        struct FacebookPictureData {
+            url: String,
+        }
+
+        #[derive(Deserialize)]
+        struct FacebookPicture {
+            data: FacebookPictureData,
+        }
+
+        #[derive(Deserialize)]
+        struct FacebookUser {
+            id: String,
+            email: Option<String>,
+            name: Option<String>,
+            picture: Option<FacebookPicture>,
+        }
+
+        let user: FacebookUser = response
+            .json()
+            .await
+            .map_err(|e| OAuth2Error::new("provider_error", Some(&e.to_string())))?;
+
+        Ok(SocialUserInfo {
+            provider: "facebook".to_string(),
+            provider_user_id: user.id,
+            email: user
+                .email
+                .ok_or_else(|| OAuth2Error::new("provider_error", Some("No email found")))?,
+            name: user.name,
+            picture: user.picture.map(|picture| picture.data.url),
+        })
+    }
+
+    /// Find the local user a provider account signs in as: the linked one, or else the
+    /// only enabled user with the same email, who still has to confirm the link.
+    pub async fn match_account(
+        storage: &DynStorage,
+        user_info: &SocialUserInfo,
+    ) -> Result<AccountMatch, OAuth2Error> {
+        let linked = storage
+            .get_federated_identity(&user_info.provider, &user_info.provider_user_id)
+            .await?;
+        if let Some(identity) = linked {
+            if let Some(user) = storage.get_user(&identity.user_id).await? {
+                return Ok(AccountMatch::Linked(user));
+            }
+        }
+
+        let mut same_email = storage
+            .list_users_by_email(&user_info.email)
+            .await?
+            .into_iter()
+            .filter(|user| user.enabled);
+        match (same_email.next(), same_email.next()) {
+            (Some(user), None) => Ok(AccountMatch::SameEmail(user)),
+            // Several users share the address; none of them is the obvious owner.
+            _ => Ok(AccountMatch::Unknown),
+        }
+    }
+
+    /// The token endpoint response in the form the relying-party client uses.
+    pub fn token_set(response: &ProviderTokenResponse) -> TokenSet {
+        TokenSet {
+            access_token: response.access_token().secret().clone(),
+            token_type: "Bearer".to_string(),
+            expires_in: response
+                .expires_in()
+                .map(|expires_in| expires_in.as_secs() as i64),
+            refresh_token: response
+                .refresh_token()
+                .map(|refresh_token| refresh_token.secret().clone()),
+            scope: response.scopes().map(|scopes| {
+                scopes
+                    .iter()
+                    .map(|scope| scope.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }),
+            id_token: response.extra_fields().id_token.clone(),
+            id_token_claims: None,
+        }
+    }
+
+    /// Keep the provider tokens of a linked account, sealed, for calls on the user's
+    /// behalf. A response without a refresh token keeps the stored one, since providers
+    /// such as Google only send it on first consent.
+    pub async fn save_upstream_tokens(
+        storage: &DynStorage,
+        vault: &TokenVault,
+        provider: &str,
+        provider_user_id: &str,
+        tokens: &TokenSet,
+    ) -> Result<(), OAuth2Error> {
+        let Some(mut identity) = storage
+            .get_federated_identity(provider, provider_user_id)
+            .await?
+        else {
+            return Ok(());
+        };
+        identity.access_token = Some(vault.seal(&tokens.access_token));
+        if let Some(refresh_token) = &tokens.refresh_token {
+            identity.refresh_token = Some(vault.seal(refresh_token));
+        }
+        identity.token_expires_at = tokens
+            .expires_in
+            .map(|expires_in| Utc::now() + Duration::seconds(expires_in));
+        storage.update_federated_identity_tokens(&identity).await
+    }
+
+    /// An access token for calling `provider` as `user_id`, refreshed first when the
+    /// stored one is about to expire.
+    pub async fn upstream_access_token(
+        storage: &DynStorage,
+        vault: &TokenVault,
+        config: &SocialLoginConfig,
+        user_id: &str,
+        provider: &str,
+    ) -> Result<String, OAuth2Error> {
+        let identity = storage
+            .list_federated_identities(user_id)
+            .await?
+            .into_iter()
+            .find(|identity| identity.provider == provider)
+            .ok_or_else(|| OAuth2Error::not_found(&format!("No {provider} account is linked")))?;
+        let sign_in_again = || {
+            OAuth2Error::access_denied(&format!(
+                "No usable {provider} token is stored; sign in with {provider} again"
+            ))
+        };
+        let access_token = identity.access_token.as_deref().ok_or_else(sign_in_again)?;
+
+        let refresh_at = Utc::now() + Duration::seconds(UPSTREAM_REFRESH_MARGIN_SECS);
+        if identity
+            .token_expires_at
+            .is_none_or(|expires_at| expires_at > refresh_at)
+        {
+            return vault.open(access_token);
+        }
+
+        let refresh_token = identity
+            .refresh_token
+            .as_deref()
+            .ok_or_else(sign_in_again)?;
+        let tokens =
+            Self::refresh_upstream_tokens(config, provider, &vault.open(refresh_token)?).await?;
+        Self::save_upstream_tokens(
+            storage,
+            vault,
+            provider,
+            &identity.provider_user_id,
+            &tokens,
+        )
+        .await?;
+        Ok(tokens.access_token)
+    }
+
+    /// Redeem a provider refresh token at the provider's token endpoint.
+    pub async fn refresh_upstream_tokens(
+        config: &SocialLoginConfig,
+        provider: &str,
+        refresh_token: &str,
+    ) -> Result<TokenSet, OAuth2Error> {
+        let not_configured = || {
+            OAuth2Error::new(
+                "provider_not_configured",
+                Some(&format!("{provider} not configured")),
+            )
+        };
+        let client = match provider {
+            "apple" => {
+                let apple = config.apple.as_ref().ok_or_else(not_configured)?;
+                return Self::get_apple_client(apple)?
+                    .refresh(refresh_token, None)
+                    .await;
+            }
+            "google" => {
+                Self::get_google_client(config.google.as_ref().ok_or_else(not_configured)?)?
+            }
+            "microsoft" => {
+                Self::get_microsoft_client(config.microsoft.as_ref().ok_or_else(not_configured)?)?
+            }
+            "github" => {
+                Self::get_github_client(config.github.as_ref().ok_or_else(not_configured)?)?
+            }
+            "gitlab" => {
+                Self::get_gitlab_client(config.gitlab.as_ref().ok_or_else(not_configured)?)?
+            }
+            "discord" => {
+                Self::get_discord_client(config.discord.as_ref().ok_or_else(not_configured)?)?
+            }
+            "linkedin" => {
+                Self::get_linkedin_client(config.linkedin.as_ref().ok_or_else(not_configured)?)?
+            }
+            "facebook" => {
+                Self::get_facebook_client(config.facebook.as_ref().ok_or_else(not_configured)?)?
+            }
+            name => {
+                let provider_config = config.generic_provider(name).ok_or_else(not_configured)?;
+                Self::get_generic_client(provider_config, name).await?.0
+            }
+        };
+
+        let response = client
+            .exchange_refresh_token(&RefreshToken::new(refresh_token.to_string()))
+            .request_async(&reqwest::Client::new())
+            .await
+            .map_err(|e| OAuth2Error::new("token_refresh_failed", Some(&e.to_string())))?;
+        Ok(Self::token_set(&response))
+    }
+
+    /// Apple user from verified ID token claims. The name is only sent once, in the `user`
+    /// form field of the first authorization.
+    pub fn apple_user_info(
+        claims: &IdTokenClaims,
+        user: Option<&str>,
+    ) -> Result<SocialUserInfo, OAuth2Error> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct AppleName {
+            first_name: Option<String>,
+            last_name: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        struct AppleUser {
+            name: Option<AppleName>,
+        }
+
+        let name = user
+            .and_then(|user| serde_json::from_str::<AppleUser>(user).ok())
+            .and_then(|user| user.name)
+            .map(|name| {
+                [name.first_name, name.last_name]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .filter(|name| !name.is_empty());
+
+        Ok(SocialUserInfo {
+            provider: "apple".to_string(),
+            provider_user_id: claims.sub.clone(),
+            email: claims
+                .email
+                .clone()
+                .ok_or_else(|| OAuth2Error::new("provider_error", Some("No email found")))?,
+            name,
+            picture: None,
+        })
+    }
 }
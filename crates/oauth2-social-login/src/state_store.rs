@@ -0,0 +1,90 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use oauth2_core::{OAuth2Error, SocialLoginState};
+use oauth2_ports::{DynStorage, StateStore};
+
+/// Keeps login state in the server's storage (the `social_login_states` table), shared
+/// by every replica using the same database. The default.
+pub struct StorageStateStore {
+    storage: DynStorage,
+}
+
+impl StorageStateStore {
+    pub fn new(storage: DynStorage) -> Self {
+        Self { storage }
+    }
+}
+
+#[async_trait]
+impl StateStore for StorageStateStore {
+    fn backend_name(&self) -> &'static str {
+        "database"
+    }
+
+    async fn save(&self, state: &SocialLoginState) -> Result<(), OAuth2Error> {
+        self.storage.save_social_login_state(state).await
+    }
+
+    async fn take(&self, state: &str) -> Result<Option<SocialLoginState>, OAuth2Error> {
+        self.storage.take_social_login_state(state).await
+    }
+}
+
+/// Keeps login state in process memory. Only for a single replica: a callback reaching
+/// another replica, or arriving after a restart, fails.
+#[derive(Default)]
+pub struct InMemoryStateStore {
+    states: Mutex<HashMap<String, SocialLoginState>>,
+}
+
+impl InMemoryStateStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl StateStore for InMemoryStateStore {
+    fn backend_name(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn save(&self, state: &SocialLoginState) -> Result<(), OAuth2Error> {
+        let mut states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        // Abandoned logins never reach the callback; drop them here.
+        states.retain(|_, existing| !existing.is_expired());
+        states.insert(state.state.clone(), state.clone());
+        Ok(())
+    }
+
+    async fn take(&self, state: &str) -> Result<Option<SocialLoginState>, OAuth2Error> {
+        Ok(self
+            .states
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn in_memory_states_are_taken_once_and_expired_ones_dropped() {
+        let store = InMemoryStateStore::new();
+        let mut stale = SocialLoginState::new("stale".to_string(), "google".to_string());
+        stale.expires_at = chrono::Utc::now() - chrono::Duration::seconds(1);
+        store.save(&stale).await.unwrap();
+
+        let fresh = SocialLoginState::new("fresh".to_string(), "github".to_string())
+            .with_pkce_verifier("verifier".to_string());
+        store.save(&fresh).await.unwrap();
+
+        assert_eq!(store.take("fresh").await.unwrap(), Some(fresh));
+        assert_eq!(store.take("fresh").await.unwrap(), None);
+        assert_eq!(store.take("stale").await.unwrap(), None);
+    }
+}
@@ -0,0 +1,103 @@
+//! Encryption at rest for upstream provider tokens.
+//!
+//! Tokens are sealed with AES-256-GCM under a random 96-bit nonce and stored as
+//! `base64url(nonce || ciphertext)`, so a database dump alone does not let anyone call
+//! provider APIs as the user.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use std::fmt;
+
+use oauth2_core::OAuth2Error;
+
+const NONCE_LEN: usize = 12;
+
+/// Seals and opens provider tokens with the key from `social.token_encryption_key`.
+#[derive(Clone)]
+pub struct TokenVault {
+    cipher: Aes256Gcm,
+}
+
+impl TokenVault {
+    /// A key of 32 random bytes, base64 (standard or URL-safe) encoded.
+    pub fn from_base64_key(key: &str) -> Result<Self, OAuth2Error> {
+        let key = key.trim();
+        let bytes = URL_SAFE_NO_PAD
+            .decode(key.trim_end_matches('='))
+            .or_else(|_| base64::engine::general_purpose::STANDARD.decode(key))
+            .map_err(|_| invalid_key("token encryption key is not base64"))?;
+        let cipher = Aes256Gcm::new_from_slice(&bytes)
+            .map_err(|_| invalid_key("token encryption key must be 32 bytes"))?;
+        Ok(Self { cipher })
+    }
+
+    pub fn seal(&self, plaintext: &str) -> String {
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let ciphertext = self
+            .cipher
+            .encrypt(&Nonce::from(nonce), plaintext.as_bytes())
+            .expect("AES-GCM encryption of an in-memory buffer does not fail");
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        URL_SAFE_NO_PAD.encode(sealed)
+    }
+
+    /// Fails when `sealed` was tampered with or sealed under another key.
+    pub fn open(&self, sealed: &str) -> Result<String, OAuth2Error> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(sealed)
+            .map_err(|_| unreadable_token())?;
+        let (nonce, ciphertext) = bytes
+            .split_first_chunk::<NONCE_LEN>()
+            .ok_or_else(unreadable_token)?;
+        let plaintext = self
+            .cipher
+            .decrypt(&Nonce::from(*nonce), ciphertext)
+            .map_err(|_| unreadable_token())?;
+        String::from_utf8(plaintext).map_err(|_| unreadable_token())
+    }
+}
+
+impl fmt::Debug for TokenVault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenVault")
+            .field("key", &"***MASKED***")
+            .finish()
+    }
+}
+
+fn invalid_key(description: &str) -> OAuth2Error {
+    OAuth2Error::new("invalid_configuration", Some(description))
+}
+
+fn unreadable_token() -> OAuth2Error {
+    OAuth2Error::new(
+        "server_error",
+        Some("Stored provider token cannot be decrypted"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=";
+
+    #[test]
+    fn sealed_tokens_open_only_with_the_same_key_and_untampered() {
+        let vault = TokenVault::from_base64_key(KEY).unwrap();
+        let sealed = vault.seal("ya29.upstream");
+        assert!(!sealed.contains("ya29"));
+        assert_ne!(sealed, vault.seal("ya29.upstream"));
+        assert_eq!(vault.open(&sealed).unwrap(), "ya29.upstream");
+
+        let mut tampered = URL_SAFE_NO_PAD.decode(&sealed).unwrap();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(vault.open(&URL_SAFE_NO_PAD.encode(tampered)).is_err());
+
+        let other = TokenVault::from_base64_key(&URL_SAFE_NO_PAD.encode([7u8; 32])).unwrap();
+        assert!(other.open(&sealed).is_err());
+        assert!(TokenVault::from_base64_key("c2hvcnQ=").is_err());
+    }
+}
@@ -0,0 +1,1013 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use lru::LruCache;
+use prometheus::IntCounterVec;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, MutexGuard};
+use uuid::Uuid;
+
+use oauth2_core::{
+    AuditRecord, AuthorizationCode, Client, EmailToken, EmailTokenPurpose, FederatedIdentity,
+    Group, OAuth2Error, OutboxMessage, Role, ServiceAccount, SocialLoginState, Tenant, Token, User,
+    UserSession,
+};
+use oauth2_ports::{
+    AuditQuery, DynClock, DynInvalidationChannel, DynStorage, Invalidation, PageRequest, Storage,
+    StorageTransaction, SystemClock, TokenMetadataQuery, UserListQuery,
+};
+
+struct Entry<T> {
+    value: T,
+    expires_at: DateTime<Utc>,
+}
+
+/// An LRU map whose entries also expire after a fixed TTL.
+struct TtlLru<T> {
+    entries: Mutex<LruCache<String, Entry<T>>>,
+    ttl: Duration,
+}
+
+impl<T: Clone> TtlLru<T> {
+    fn new(capacity: NonZeroUsize, ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            ttl,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LruCache<String, Entry<T>>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn get(&self, key: &str, now: DateTime<Utc>) -> Option<T> {
+        let mut entries = self.lock();
+        match entries.get(key) {
+            Some(entry) if entry.expires_at > now => Some(entry.value.clone()),
+            Some(_) => {
+                entries.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn put(&self, key: String, value: T, now: DateTime<Utc>) {
+        let expires_at = now
+            .checked_add_signed(self.ttl)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        self.lock().put(key, Entry { value, expires_at });
+    }
+
+    fn remove(&self, key: &str) {
+        self.lock().pop(key);
+    }
+
+    /// Evict every entry whose value matches.
+    fn remove_where(&self, matches: impl Fn(&T) -> bool) {
+        let mut entries = self.lock();
+        let keys: Vec<String> = entries
+            .iter()
+            .filter(|(_, entry)| matches(&entry.value))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            entries.pop(&key);
+        }
+    }
+
+    fn clear(&self) {
+        self.lock().clear();
+    }
+}
+
+/// What a replica asks its peers to evict.
+///
+/// Tokens travel as a hex SHA-256 digest so raw bearer tokens never leave the process.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "evict", rename_all = "snake_case")]
+enum Eviction {
+    Client {
+        client_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tenant_id: Option<String>,
+    },
+    /// A deleted client and every token issued to it.
+    ClientDeleted {
+        client_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tenant_id: Option<String>,
+    },
+    /// A token named by its access or refresh token.
+    Token {
+        digest: String,
+    },
+    Grant {
+        grant_id: String,
+    },
+    User {
+        user_id: String,
+    },
+    TokenMetadata {
+        key: String,
+        value: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct InvalidationMessage {
+    /// Instance that published the message; it has already evicted locally.
+    origin: String,
+    #[serde(flatten)]
+    eviction: Eviction,
+}
+
+/// Cache key of client `client_id` of tenant `tenant_id`. Tenant ids cannot contain `/`,
+/// so keys of different tenants never collide.
+fn client_key(client_id: &str, tenant_id: Option<&str>) -> String {
+    format!("{}/{client_id}", tenant_id.unwrap_or_default())
+}
+
+fn token_digest(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+struct Caches {
+    clients: TtlLru<Client>,
+    tokens: TtlLru<Token>,
+}
+
+impl Caches {
+    fn evict(&self, eviction: &Eviction) {
+        match eviction {
+            Eviction::Client {
+                client_id,
+                tenant_id,
+            } => self
+                .clients
+                .remove(&client_key(client_id, tenant_id.as_deref())),
+            Eviction::ClientDeleted {
+                client_id,
+                tenant_id,
+            } => {
+                self.clients
+                    .remove(&client_key(client_id, tenant_id.as_deref()));
+                self.tokens.remove_where(|t| {
+                    &t.client_id == client_id && t.in_tenant(tenant_id.as_deref())
+                });
+            }
+            Eviction::Token { digest } => self.tokens.remove_where(|t| {
+                token_digest(&t.access_token) == *digest
+                    || t.refresh_token.as_deref().map(token_digest).as_ref() == Some(digest)
+            }),
+            Eviction::Grant { grant_id } => self.tokens.remove_where(|t| t.grant_id() == grant_id),
+            Eviction::User { user_id } => self
+                .tokens
+                .remove_where(|t| t.user_id.as_ref() == Some(user_id)),
+            Eviction::TokenMetadata { key, value } => self
+                .tokens
+                .remove_where(|t| t.metadata.get(key) == Some(value.as_str())),
+        }
+    }
+
+    /// Evict a token by its raw access or refresh token, without hashing every entry.
+    fn evict_token(&self, token: &str) {
+        self.tokens.remove(token);
+        self.tokens
+            .remove_where(|t| t.refresh_token.as_deref() == Some(token));
+    }
+
+    fn clear(&self) {
+        self.clients.clear();
+        self.tokens.clear();
+    }
+
+    fn on_invalidation(&self, origin: &str, invalidation: Invalidation) {
+        let Invalidation::Message(payload) = invalidation else {
+            tracing::info!("Storage cache invalidations may have been missed; clearing the cache");
+            self.clear();
+            return;
+        };
+        match serde_json::from_str::<InvalidationMessage>(&payload) {
+            Ok(message) if message.origin == origin => {}
+            Ok(message) => self.evict(&message.eviction),
+            Err(e) => {
+                tracing::warn!(error = %e, "Unreadable storage cache invalidation; clearing the cache");
+                self.clear();
+            }
+        }
+    }
+}
+
+/// Ask peers on `channel`, if any, to apply `eviction`.
+async fn publish(channel: Option<&DynInvalidationChannel>, origin: &str, eviction: Eviction) {
+    let Some(channel) = channel else {
+        return;
+    };
+    let message = InvalidationMessage {
+        origin: origin.to_string(),
+        eviction,
+    };
+    let payload = match serde_json::to_string(&message) {
+        Ok(payload) => payload,
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to encode storage cache invalidation");
+            return;
+        }
+    };
+    if let Err(e) = channel.publish(&payload).await {
+        tracing::warn!(
+            backend = channel.backend_name(),
+            error = %e,
+            "Failed to publish storage cache invalidation; peers may serve stale entries until they expire"
+        );
+    }
+}
+
+/// A `DynStorage` decorator that keeps recently read clients and access tokens in memory,
+/// so validating a token does not hit the database on every request.
+///
+/// `get_client` and `get_token_by_access_token` are served from an LRU cache whose entries
+/// expire after `ttl`; only found records are cached. Writes go to the inner storage first
+/// and then update or evict the cached copy, so this process never serves a record it has
+/// changed itself. Other replicas may serve a stale copy for up to `ttl`, unless they share
+/// an invalidation channel (see [`CachedStorage::with_invalidation`]).
+pub struct CachedStorage {
+    inner: DynStorage,
+    caches: Arc<Caches>,
+    clock: DynClock,
+    requests: Option<IntCounterVec>,
+    invalidation: Option<DynInvalidationChannel>,
+    instance_id: String,
+}
+
+impl CachedStorage {
+    /// Cache up to `capacity` clients and `capacity` tokens for `ttl` each.
+    pub fn new(inner: DynStorage, capacity: usize, ttl: std::time::Duration) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        let ttl = Duration::from_std(ttl).unwrap_or(Duration::MAX);
+        Self {
+            inner,
+            caches: Arc::new(Caches {
+                clients: TtlLru::new(capacity, ttl),
+                tokens: TtlLru::new(capacity, ttl),
+            }),
+            clock: Arc::new(SystemClock),
+            requests: None,
+            invalidation: None,
+            instance_id: Uuid::new_v4().to_string(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: DynClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Count lookups by `cache` (`client`, `token`) and `outcome` (`hit`, `miss`).
+    pub fn with_metrics(mut self, requests: IntCounterVec) -> Self {
+        self.requests = Some(requests);
+        self
+    }
+
+    /// Tell every replica subscribed to `channel` what this one evicts, and evict what they
+    /// announce.
+    ///
+    /// Both directions are best effort: if publishing fails, peers serve their copy until it
+    /// expires; if subscribing fails, the cache stays local to this replica.
+    pub async fn with_invalidation(mut self, channel: DynInvalidationChannel) -> Self {
+        let caches = self.caches.clone();
+        let origin = self.instance_id.clone();
+        let subscribed = channel
+            .subscribe(Box::new(move |invalidation| {
+                caches.on_invalidation(&origin, invalidation)
+            }))
+            .await;
+        match subscribed {
+            Ok(()) => self.invalidation = Some(channel),
+            Err(e) => tracing::warn!(
+                backend = channel.backend_name(),
+                error = %e,
+                "Failed to subscribe to storage cache invalidations; the cache stays local"
+            ),
+        }
+        self
+    }
+
+    fn cache_client(&self, client: &Client) {
+        self.caches.clients.put(
+            client_key(&client.client_id, client.tenant_id.as_deref()),
+            client.clone(),
+            self.clock.now(),
+        );
+    }
+
+    fn record(&self, cache: &str, hit: bool) {
+        if let Some(requests) = &self.requests {
+            let outcome = if hit { "hit" } else { "miss" };
+            requests.with_label_values(&[cache, outcome]).inc();
+        }
+    }
+
+    async fn publish(&self, eviction: Eviction) {
+        publish(self.invalidation.as_ref(), &self.instance_id, eviction).await;
+    }
+
+    /// Evict locally, then ask peers to do the same.
+    async fn evict(&self, eviction: Eviction) {
+        self.caches.evict(&eviction);
+        self.publish(eviction).await;
+    }
+}
+
+#[async_trait]
+impl Storage for CachedStorage {
+    async fn init(&self) -> Result<(), OAuth2Error> {
+        self.inner.init().await
+    }
+
+    async fn save_tenant(&self, tenant: &Tenant) -> Result<(), OAuth2Error> {
+        self.inner.save_tenant(tenant).await
+    }
+
+    async fn get_tenant(&self, id: &str) -> Result<Option<Tenant>, OAuth2Error> {
+        self.inner.get_tenant(id).await
+    }
+
+    async fn list_tenants(&self) -> Result<Vec<Tenant>, OAuth2Error> {
+        self.inner.list_tenants().await
+    }
+
+    async fn save_client(&self, client: &Client) -> Result<(), OAuth2Error> {
+        self.inner.save_client(client).await?;
+        self.cache_client(client);
+        Ok(())
+    }
+
+    async fn get_client(
+        &self,
+        client_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<Client>, OAuth2Error> {
+        let key = client_key(client_id, tenant_id);
+        if let Some(client) = self.caches.clients.get(&key, self.clock.now()) {
+            self.record("client", true);
+            return Ok(Some(client));
+        }
+        self.record("client", false);
+
+        let client = self.inner.get_client(client_id, tenant_id).await?;
+        if let Some(client) = &client {
+            self.cache_client(client);
+        }
+        Ok(client)
+    }
+
+    async fn list_clients_managed_by(&self, manager: &str) -> Result<Vec<Client>, OAuth2Error> {
+        self.inner.list_clients_managed_by(manager).await
+    }
+
+    async fn list_clients(&self, page: PageRequest) -> Result<Vec<Client>, OAuth2Error> {
+        self.inner.list_clients(page).await
+    }
+
+    async fn count_clients(&self) -> Result<u64, OAuth2Error> {
+        self.inner.count_clients().await
+    }
+
+    async fn update_client(&self, client: &Client) -> Result<(), OAuth2Error> {
+        // Evict first: if the write fails, the stored record is unknown.
+        self.caches
+            .clients
+            .remove(&client_key(&client.client_id, client.tenant_id.as_deref()));
+        let result = self.inner.update_client(client).await;
+        if result.is_ok() {
+            self.cache_client(client);
+        }
+        self.publish(Eviction::Client {
+            client_id: client.client_id.clone(),
+            tenant_id: client.tenant_id.clone(),
+        })
+        .await;
+        result
+    }
+
+    async fn delete_client(
+        &self,
+        client_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<(), OAuth2Error> {
+        let result = self.inner.delete_client(client_id, tenant_id).await;
+        self.evict(Eviction::ClientDeleted {
+            client_id: client_id.to_string(),
+            tenant_id: tenant_id.map(str::to_string),
+        })
+        .await;
+        result
+    }
+
+    async fn save_service_account(&self, account: &ServiceAccount) -> Result<(), OAuth2Error> {
+        self.inner.save_service_account(account).await
+    }
+
+    async fn get_service_account(&self, id: &str) -> Result<Option<ServiceAccount>, OAuth2Error> {
+        self.inner.get_service_account(id).await
+    }
+
+    async fn get_service_account_by_client_id(
+        &self,
+        client_id: &str,
+    ) -> Result<Option<ServiceAccount>, OAuth2Error> {
+        self.inner.get_service_account_by_client_id(client_id).await
+    }
+
+    async fn list_service_accounts(&self) -> Result<Vec<ServiceAccount>, OAuth2Error> {
+        self.inner.list_service_accounts().await
+    }
+
+    async fn update_service_account(&self, account: &ServiceAccount) -> Result<(), OAuth2Error> {
+        self.inner.update_service_account(account).await
+    }
+
+    async fn save_user(&self, user: &User) -> Result<(), OAuth2Error> {
+        self.inner.save_user(user).await
+    }
+
+    async fn get_user(&self, user_id: &str) -> Result<Option<User>, OAuth2Error> {
+        self.inner.get_user(user_id).await
+    }
+
+    async fn get_user_by_username(
+        &self,
+        username: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<User>, OAuth2Error> {
+        self.inner.get_user_by_username(username, tenant_id).await
+    }
+
+    async fn list_users_by_email(&self, email: &str) -> Result<Vec<User>, OAuth2Error> {
+        self.inner.list_users_by_email(email).await
+    }
+
+    async fn list_users(&self, query: &UserListQuery) -> Result<Vec<User>, OAuth2Error> {
+        self.inner.list_users(query).await
+    }
+
+    async fn count_users(&self) -> Result<u64, OAuth2Error> {
+        self.inner.count_users().await
+    }
+
+    async fn update_user(&self, user: &User) -> Result<(), OAuth2Error> {
+        self.inner.update_user(user).await
+    }
+
+    async fn delete_user(&self, user_id: &str) -> Result<(), OAuth2Error> {
+        let result = self.inner.delete_user(user_id).await;
+        self.evict(Eviction::User {
+            user_id: user_id.to_string(),
+        })
+        .await;
+        result
+    }
+
+    async fn save_token(&self, token: &Token) -> Result<(), OAuth2Error> {
+        self.inner.save_token(token).await?;
+        self.caches
+            .tokens
+            .put(token.access_token.clone(), token.clone(), self.clock.now());
+        Ok(())
+    }
+
+    async fn get_token_by_access_token(
+        &self,
+        access_token: &str,
+    ) -> Result<Option<Token>, OAuth2Error> {
+        if let Some(token) = self.caches.tokens.get(access_token, self.clock.now()) {
+            self.record("token", true);
+            return Ok(Some(token));
+        }
+        self.record("token", false);
+
+        let token = self.inner.get_token_by_access_token(access_token).await?;
+        if let Some(token) = &token {
+            self.caches
+                .tokens
+                .put(token.access_token.clone(), token.clone(), self.clock.now());
+        }
+        Ok(token)
+    }
+
+    async fn get_token_by_refresh_token(
+        &self,
+        refresh_token: &str,
+    ) -> Result<Option<Token>, OAuth2Error> {
+        self.inner.get_token_by_refresh_token(refresh_token).await
+    }
+
+    async fn revoke_token(&self, token: &str) -> Result<(), OAuth2Error> {
+        // Evict even if the write fails: the token may have been revoked anyway.
+        let result = self.inner.revoke_token(token).await;
+        self.caches.evict_token(token);
+        self.publish(Eviction::Token {
+            digest: token_digest(token),
+        })
+        .await;
+        result
+    }
+
+    async fn revoke_token_grant(&self, grant_id: &str) -> Result<(), OAuth2Error> {
+        let result = self.inner.revoke_token_grant(grant_id).await;
+        self.evict(Eviction::Grant {
+            grant_id: grant_id.to_string(),
+        })
+        .await;
+        result
+    }
+
+    async fn list_revoked_tokens(&self) -> Result<Vec<Token>, OAuth2Error> {
+        self.inner.list_revoked_tokens().await
+    }
+
+    async fn find_tokens_by_metadata(
+        &self,
+        query: &TokenMetadataQuery,
+    ) -> Result<Vec<Token>, OAuth2Error> {
+        self.inner.find_tokens_by_metadata(query).await
+    }
+
+    async fn revoke_tokens_by_metadata(&self, key: &str, value: &str) -> Result<u64, OAuth2Error> {
+        let result = self.inner.revoke_tokens_by_metadata(key, value).await;
+        self.evict(Eviction::TokenMetadata {
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+        .await;
+        result
+    }
+
+    async fn list_tokens_by_client(
+        &self,
+        client_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Token>, OAuth2Error> {
+        self.inner.list_tokens_by_client(client_id, page).await
+    }
+
+    async fn list_tokens_by_user(
+        &self,
+        user_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Token>, OAuth2Error> {
+        self.inner.list_tokens_by_user(user_id, page).await
+    }
+
+    async fn count_tokens(&self) -> Result<u64, OAuth2Error> {
+        self.inner.count_tokens().await
+    }
+
+    async fn count_active_tokens(&self) -> Result<u64, OAuth2Error> {
+        self.inner.count_active_tokens().await
+    }
+
+    async fn save_authorization_code(
+        &self,
+        auth_code: &AuthorizationCode,
+    ) -> Result<(), OAuth2Error> {
+        self.inner.save_authorization_code(auth_code).await
+    }
+
+    async fn get_authorization_code(
+        &self,
+        code: &str,
+    ) -> Result<Option<AuthorizationCode>, OAuth2Error> {
+        self.inner.get_authorization_code(code).await
+    }
+
+    async fn mark_authorization_code_used(&self, code: &str) -> Result<(), OAuth2Error> {
+        self.inner.mark_authorization_code_used(code).await
+    }
+
+    async fn begin_transaction(&self) -> Result<Box<dyn StorageTransaction>, OAuth2Error> {
+        Ok(Box::new(CachedTransaction {
+            inner: self.inner.begin_transaction().await?,
+            caches: self.caches.clone(),
+            clock: self.clock.clone(),
+            invalidation: self.invalidation.clone(),
+            instance_id: self.instance_id.clone(),
+            saved: Vec::new(),
+            revoked: Vec::new(),
+        }))
+    }
+
+    async fn save_social_login_state(&self, state: &SocialLoginState) -> Result<(), OAuth2Error> {
+        self.inner.save_social_login_state(state).await
+    }
+
+    async fn take_social_login_state(
+        &self,
+        state: &str,
+    ) -> Result<Option<SocialLoginState>, OAuth2Error> {
+        self.inner.take_social_login_state(state).await
+    }
+
+    async fn save_email_token(&self, token: &EmailToken) -> Result<(), OAuth2Error> {
+        self.inner.save_email_token(token).await
+    }
+
+    async fn take_email_token(&self, token_hash: &str) -> Result<Option<EmailToken>, OAuth2Error> {
+        self.inner.take_email_token(token_hash).await
+    }
+
+    async fn delete_email_tokens(
+        &self,
+        user_id: &str,
+        purpose: EmailTokenPurpose,
+    ) -> Result<(), OAuth2Error> {
+        self.inner.delete_email_tokens(user_id, purpose).await
+    }
+
+    async fn save_user_session(&self, session: &UserSession) -> Result<(), OAuth2Error> {
+        self.inner.save_user_session(session).await
+    }
+
+    async fn get_user_session(&self, id: &str) -> Result<Option<UserSession>, OAuth2Error> {
+        self.inner.get_user_session(id).await
+    }
+
+    async fn touch_user_session(
+        &self,
+        id: &str,
+        last_seen_at: DateTime<Utc>,
+        idle_expires_at: DateTime<Utc>,
+    ) -> Result<(), OAuth2Error> {
+        self.inner
+            .touch_user_session(id, last_seen_at, idle_expires_at)
+            .await
+    }
+
+    async fn list_user_sessions(&self, user_id: &str) -> Result<Vec<UserSession>, OAuth2Error> {
+        self.inner.list_user_sessions(user_id).await
+    }
+
+    async fn delete_user_session(&self, id: &str) -> Result<(), OAuth2Error> {
+        self.inner.delete_user_session(id).await
+    }
+
+    async fn save_audit_record(&self, record: &AuditRecord) -> Result<(), OAuth2Error> {
+        self.inner.save_audit_record(record).await
+    }
+
+    async fn list_audit_records(
+        &self,
+        query: &AuditQuery,
+    ) -> Result<Vec<AuditRecord>, OAuth2Error> {
+        self.inner.list_audit_records(query).await
+    }
+
+    async fn enqueue_outbox_message(&self, message: &OutboxMessage) -> Result<(), OAuth2Error> {
+        self.inner.enqueue_outbox_message(message).await
+    }
+
+    async fn claim_outbox_messages(
+        &self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<OutboxMessage>, OAuth2Error> {
+        self.inner
+            .claim_outbox_messages(now, lease_until, limit)
+            .await
+    }
+
+    async fn delete_outbox_message(&self, id: &str) -> Result<(), OAuth2Error> {
+        self.inner.delete_outbox_message(id).await
+    }
+
+    async fn reschedule_outbox_message(
+        &self,
+        id: &str,
+        next_attempt_at: DateTime<Utc>,
+        error: &str,
+    ) -> Result<(), OAuth2Error> {
+        self.inner
+            .reschedule_outbox_message(id, next_attempt_at, error)
+            .await
+    }
+
+    async fn save_federated_identity(
+        &self,
+        identity: &FederatedIdentity,
+    ) -> Result<(), OAuth2Error> {
+        self.inner.save_federated_identity(identity).await
+    }
+
+    async fn get_federated_identity(
+        &self,
+        provider: &str,
+        provider_user_id: &str,
+    ) -> Result<Option<FederatedIdentity>, OAuth2Error> {
+        self.inner
+            .get_federated_identity(provider, provider_user_id)
+            .await
+    }
+
+    async fn list_federated_identities(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<FederatedIdentity>, OAuth2Error> {
+        self.inner.list_federated_identities(user_id).await
+    }
+
+    async fn update_federated_identity_tokens(
+        &self,
+        identity: &FederatedIdentity,
+    ) -> Result<(), OAuth2Error> {
+        self.inner.update_federated_identity_tokens(identity).await
+    }
+
+    async fn delete_federated_identity(
+        &self,
+        provider: &str,
+        provider_user_id: &str,
+    ) -> Result<(), OAuth2Error> {
+        self.inner
+            .delete_federated_identity(provider, provider_user_id)
+            .await
+    }
+
+    async fn save_role(&self, role: &Role) -> Result<(), OAuth2Error> {
+        self.inner.save_role(role).await
+    }
+
+    async fn list_roles(&self) -> Result<Vec<Role>, OAuth2Error> {
+        self.inner.list_roles().await
+    }
+
+    async fn delete_role(&self, name: &str) -> Result<(), OAuth2Error> {
+        self.inner.delete_role(name).await
+    }
+
+    async fn save_group(&self, group: &Group) -> Result<(), OAuth2Error> {
+        self.inner.save_group(group).await
+    }
+
+    async fn list_groups(&self) -> Result<Vec<Group>, OAuth2Error> {
+        self.inner.list_groups().await
+    }
+
+    async fn delete_group(&self, name: &str) -> Result<(), OAuth2Error> {
+        self.inner.delete_group(name).await
+    }
+
+    async fn list_user_roles(&self, user_id: &str) -> Result<Vec<String>, OAuth2Error> {
+        self.inner.list_user_roles(user_id).await
+    }
+
+    async fn set_user_roles(&self, user_id: &str, roles: &[String]) -> Result<(), OAuth2Error> {
+        self.inner.set_user_roles(user_id, roles).await
+    }
+
+    async fn list_user_groups(&self, user_id: &str) -> Result<Vec<String>, OAuth2Error> {
+        self.inner.list_user_groups(user_id).await
+    }
+
+    async fn set_user_groups(&self, user_id: &str, groups: &[String]) -> Result<(), OAuth2Error> {
+        self.inner.set_user_groups(user_id, groups).await
+    }
+
+    async fn healthcheck(&self) -> Result<(), OAuth2Error> {
+        self.inner.healthcheck().await
+    }
+}
+
+/// Updates the cache only once the inner transaction commits.
+struct CachedTransaction {
+    inner: Box<dyn StorageTransaction>,
+    caches: Arc<Caches>,
+    clock: DynClock,
+    invalidation: Option<DynInvalidationChannel>,
+    instance_id: String,
+    saved: Vec<Token>,
+    revoked: Vec<String>,
+}
+
+#[async_trait]
+impl StorageTransaction for CachedTransaction {
+    async fn consume_authorization_code(&mut self, code: &str) -> Result<(), OAuth2Error> {
+        self.inner.consume_authorization_code(code).await
+    }
+
+    async fn save_token(&mut self, token: &Token) -> Result<(), OAuth2Error> {
+        self.inner.save_token(token).await?;
+        self.saved.push(token.clone());
+        Ok(())
+    }
+
+    async fn revoke_token(&mut self, token: &str) -> Result<(), OAuth2Error> {
+        self.inner.revoke_token(token).await?;
+        self.revoked.push(token.to_string());
+        Ok(())
+    }
+
+    async fn redeem_refresh_token(&mut self, refresh_token: &str) -> Result<(), OAuth2Error> {
+        self.inner.redeem_refresh_token(refresh_token).await?;
+        self.revoked.push(refresh_token.to_string());
+        Ok(())
+    }
+
+    async fn enqueue_outbox_message(&mut self, message: &OutboxMessage) -> Result<(), OAuth2Error> {
+        self.inner.enqueue_outbox_message(message).await
+    }
+
+    async fn commit(self: Box<Self>) -> Result<(), OAuth2Error> {
+        let Self {
+            inner,
+            caches,
+            clock,
+            invalidation,
+            instance_id,
+            saved,
+            revoked,
+        } = *self;
+        inner.commit().await?;
+
+        let now = clock.now();
+        for token in saved {
+            caches.tokens.put(token.access_token.clone(), token, now);
+        }
+        for token in revoked {
+            caches.evict_token(&token);
+            let digest = token_digest(&token);
+            publish(
+                invalidation.as_ref(),
+                &instance_id,
+                Eviction::Token { digest },
+            )
+            .await;
+        }
+        Ok(())
+    }
+
+    async fn rollback(self: Box<Self>) -> Result<(), OAuth2Error> {
+        self.inner.rollback().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oauth2_ports::testing::{FakeInvalidationChannel, FakeStorage, FixedClock};
+    use prometheus::Opts;
+
+    fn requests() -> IntCounterVec {
+        IntCounterVec::new(Opts::new("requests", "requests"), &["cache", "outcome"]).unwrap()
+    }
+
+    fn token(access_token: &str) -> Token {
+        Token::new(
+            access_token.to_string(),
+            Some(format!("{access_token}_refresh")),
+            "app".to_string(),
+            Some("alice".to_string()),
+            "read".to_string(),
+            3600,
+        )
+    }
+
+    #[tokio::test]
+    async fn tokens_are_served_from_cache_until_revoked() {
+        let inner = Arc::new(FakeStorage::new());
+        let requests = requests();
+        let storage = CachedStorage::new(inner.clone(), 10, std::time::Duration::from_secs(60))
+            .with_metrics(requests.clone());
+        inner.save_token(&token("at_1")).await.unwrap();
+
+        for _ in 0..3 {
+            storage.get_token_by_access_token("at_1").await.unwrap();
+        }
+        let lookups = inner
+            .failures()
+            .calls()
+            .into_iter()
+            .filter(|call| call == "get_token_by_access_token")
+            .count();
+        assert_eq!(lookups, 1);
+        assert_eq!(requests.with_label_values(&["token", "miss"]).get(), 1);
+        assert_eq!(requests.with_label_values(&["token", "hit"]).get(), 2);
+
+        // Revoking by refresh token evicts the cached access token too.
+        storage.revoke_token("at_1_refresh").await.unwrap();
+        let token = storage
+            .get_token_by_access_token("at_1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(token.revoked);
+    }
+
+    #[tokio::test]
+    async fn entries_expire_after_the_ttl() {
+        let now = Utc::now();
+        let clock = Arc::new(FixedClock::new(now));
+        let inner = Arc::new(FakeStorage::new());
+        let requests = requests();
+        let storage = CachedStorage::new(inner.clone(), 10, std::time::Duration::from_secs(30))
+            .with_clock(clock.clone())
+            .with_metrics(requests.clone());
+        inner
+            .save_client(&Client::new(
+                "app".to_string(),
+                "secret".to_string(),
+                vec![],
+                vec!["client_credentials".to_string()],
+                "read".to_string(),
+                "app".to_string(),
+            ))
+            .await
+            .unwrap();
+
+        storage.get_client("app", None).await.unwrap().unwrap();
+        clock.advance(Duration::seconds(29));
+        storage.get_client("app", None).await.unwrap().unwrap();
+        clock.advance(Duration::seconds(1));
+        storage.get_client("app", None).await.unwrap().unwrap();
+
+        assert_eq!(requests.with_label_values(&["client", "hit"]).get(), 1);
+        assert_eq!(requests.with_label_values(&["client", "miss"]).get(), 2);
+    }
+
+    #[tokio::test]
+    async fn least_recently_used_entries_are_evicted_at_capacity() {
+        let inner = Arc::new(FakeStorage::new());
+        let requests = requests();
+        let storage = CachedStorage::new(inner.clone(), 2, std::time::Duration::from_secs(60))
+            .with_metrics(requests.clone());
+        for access_token in ["at_1", "at_2", "at_3"] {
+            storage.save_token(&token(access_token)).await.unwrap();
+        }
+
+        storage.get_token_by_access_token("at_1").await.unwrap();
+        storage.get_token_by_access_token("at_3").await.unwrap();
+        assert_eq!(requests.with_label_values(&["token", "miss"]).get(), 1);
+        assert_eq!(requests.with_label_values(&["token", "hit"]).get(), 1);
+    }
+
+    #[tokio::test]
+    async fn revocations_on_one_replica_evict_on_its_peers() {
+        let db = Arc::new(FakeStorage::new());
+        let channel = FakeInvalidationChannel::new();
+        let ttl = std::time::Duration::from_secs(60);
+        let node_a = CachedStorage::new(db.clone(), 10, ttl)
+            .with_invalidation(Arc::new(channel.clone()))
+            .await;
+        let node_b = CachedStorage::new(db.clone(), 10, ttl)
+            .with_invalidation(Arc::new(channel.clone()))
+            .await;
+        for access_token in ["at_1", "at_2"] {
+            db.save_token(&token(access_token)).await.unwrap();
+            node_b
+                .get_token_by_access_token(access_token)
+                .await
+                .unwrap();
+        }
+
+        node_a.revoke_token("at_1_refresh").await.unwrap();
+
+        let revoked = node_b
+            .get_token_by_access_token("at_1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(revoked.revoked);
+        let lookups_before = db.failures().calls().len();
+        node_b.get_token_by_access_token("at_2").await.unwrap();
+        assert_eq!(
+            db.failures().calls().len(),
+            lookups_before,
+            "untouched tokens stay cached"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_resync_clears_the_cache() {
+        let db = Arc::new(FakeStorage::new());
+        let channel = FakeInvalidationChannel::new();
+        let requests = requests();
+        let storage = CachedStorage::new(db.clone(), 10, std::time::Duration::from_secs(60))
+            .with_metrics(requests.clone())
+            .with_invalidation(Arc::new(channel.clone()))
+            .await;
+        storage.save_token(&token("at_1")).await.unwrap();
+
+        channel.resync();
+        storage.get_token_by_access_token("at_1").await.unwrap();
+
+        assert_eq!(requests.with_label_values(&["token", "miss"]).get(), 1);
+    }
+}
@@ -1,12 +1,16 @@
 //! Storage backend selection for the OAuth2 server.
 //!
 //! This crate centralizes URL-based backend selection (SQLx vs Mongo) and wraps
-//! the chosen implementation with `ObservedStorage` for tracing.
+//! the chosen implementation with `ObservedStorage` for tracing. `CachedStorage`
+//! can be layered on top to serve hot client and token lookups from memory.
+
+mod cached;
 
 use std::sync::Arc;
 
 use oauth2_core::OAuth2Error;
 
+pub use cached::CachedStorage;
 pub use oauth2_observability::ObservedStorage;
 pub use oauth2_ports::{DynStorage, Storage};
 
@@ -22,22 +26,41 @@ pub mod mongo {
     pub use oauth2_storage_mongo::MongoStorage;
 }
 
+/// Storage system name for a database URL (`mongodb`, `postgresql`, `sqlite`, or `sql`).
+///
+/// Also used as the `db_system` label on storage spans.
+pub fn backend_name(database_url: &str) -> &'static str {
+    if database_url.starts_with("mongodb://") || database_url.starts_with("mongodb+srv://") {
+        "mongodb"
+    } else if database_url.starts_with("postgres://") || database_url.starts_with("postgresql://") {
+        "postgresql"
+    } else if database_url.starts_with("sqlite:") {
+        "sqlite"
+    } else {
+        "sql"
+    }
+}
+
 /// Create a storage backend based on URL scheme.
 ///
 /// Supported:
 /// - `postgres://...` and `sqlite:...` -> SQLx backend
 /// - `mongodb://...` and `mongodb+srv://...` -> Mongo backend (requires `--features mongo`)
 pub async fn create_storage(database_url: &str) -> Result<DynStorage, OAuth2Error> {
-    let is_mongo =
-        database_url.starts_with("mongodb://") || database_url.starts_with("mongodb+srv://");
+    let inner = connect(database_url).await?;
+    let observed = ObservedStorage::new(inner, backend_name(database_url).to_string());
+    Ok(Arc::new(observed))
+}
 
-    if is_mongo {
+/// Connect the backend selected by `database_url`, without any wrapping.
+///
+/// For callers that configure their own `ObservedStorage` (metrics, slow-call threshold).
+pub async fn connect(database_url: &str) -> Result<DynStorage, OAuth2Error> {
+    if backend_name(database_url) == "mongodb" {
         #[cfg(feature = "mongo")]
         {
             let storage = mongo::MongoStorage::new(database_url).await?;
-            let inner: DynStorage = Arc::new(storage);
-            let observed = ObservedStorage::new(inner, "mongodb".to_string());
-            Ok(Arc::new(observed))
+            Ok(Arc::new(storage))
         }
 
         #[cfg(not(feature = "mongo"))]
@@ -54,19 +77,7 @@ pub async fn create_storage(database_url: &str) -> Result<DynStorage, OAuth2Erro
         #[cfg(feature = "sqlx")]
         {
             let storage = oauth2_storage_sqlx::SqlxStorage::new(database_url).await?;
-            let db_system = if database_url.starts_with("postgres://")
-                || database_url.starts_with("postgresql://")
-            {
-                "postgresql"
-            } else if database_url.starts_with("sqlite:") || database_url.starts_with("sqlite://") {
-                "sqlite"
-            } else {
-                "sql"
-            };
-
-            let inner: DynStorage = Arc::new(storage);
-            let observed = ObservedStorage::new(inner, db_system.to_string());
-            Ok(Arc::new(observed))
+            Ok(Arc::new(storage))
         }
 
         #[cfg(not(feature = "sqlx"))]
@@ -80,3 +91,27 @@ pub async fn create_storage(database_url: &str) -> Result<DynStorage, OAuth2Erro
         }
     }
 }
+
+/// Apply pending schema migrations for the database at `database_url`.
+///
+/// Returns the versions that were applied. Only Postgres has embedded migrations;
+/// SQLite schemas are bootstrapped by `Storage::init` and other backends are a no-op.
+pub async fn run_migrations(database_url: &str) -> Result<Vec<i32>, OAuth2Error> {
+    if backend_name(database_url) != "postgresql" {
+        return Ok(Vec::new());
+    }
+
+    #[cfg(feature = "sqlx")]
+    {
+        let storage = oauth2_storage_sqlx::SqlxStorage::new(database_url).await?;
+        Ok(storage.run_migrations().await?)
+    }
+
+    #[cfg(not(feature = "sqlx"))]
+    {
+        Err(OAuth2Error::new(
+            "server_error",
+            Some("SQL migrations requested but the binary was built without SQL support (feature `sqlx` disabled)"),
+        ))
+    }
+}
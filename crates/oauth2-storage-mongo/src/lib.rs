@@ -1,24 +1,50 @@
 use async_trait::async_trait;
+use futures::TryStreamExt;
 use mongodb::{
-    bson::doc,
-    options::{ClientOptions, IndexOptions},
-    Client as MongoClient, Collection, Database, IndexModel,
+    bson::{doc, Bson, Document, Regex},
+    error::ErrorKind,
+    options::{ClientOptions, FindOptions, IndexOptions, ReplaceOptions},
+    Client as MongoClient, ClientSession, Collection, Database, IndexModel,
 };
 
-use oauth2_core::{AuthorizationCode, Client, OAuth2Error, Token, User};
-use oauth2_ports::Storage;
+use oauth2_core::{
+    AuditRecord, AuthorizationCode, Client, EmailToken, EmailTokenPurpose, FederatedIdentity,
+    Group, OAuth2Error, OutboxMessage, Role, ServiceAccount, SocialLoginState, Tenant, Token, User,
+    UserSession,
+};
+use oauth2_ports::{
+    AuditQuery, PageRequest, Storage, StorageTransaction, TokenMetadataQuery, UserListQuery,
+};
+
+pub mod migrations;
 
 /// MongoDB-backed storage implementation.
 ///
 /// Notes:
 /// - Uses the core models as documents via `serde`.
 /// - Uses unique indexes on the same fields that are unique in SQL.
+/// - `init()` applies pending [`migrations`] and refuses a newer schema version.
 pub struct MongoStorage {
+    client: MongoClient,
     db: Database,
+    tenants: Collection<Tenant>,
     clients: Collection<Client>,
     users: Collection<User>,
+    service_accounts: Collection<ServiceAccount>,
     tokens: Collection<Token>,
     authorization_codes: Collection<AuthorizationCode>,
+    social_login_states: Collection<SocialLoginState>,
+    email_tokens: Collection<EmailToken>,
+    user_sessions: Collection<UserSession>,
+    audit_records: Collection<AuditRecord>,
+    outbox_messages: Collection<OutboxMessage>,
+    federated_identities: Collection<FederatedIdentity>,
+    roles: Collection<Role>,
+    groups: Collection<Group>,
+    /// `{ user_id, role_name }` pairs.
+    user_roles: Collection<Document>,
+    /// `{ user_id, group_name }` pairs.
+    user_groups: Collection<Document>,
 }
 
 impl MongoStorage {
@@ -40,38 +66,56 @@ impl MongoStorage {
 
         let db = client.database(&db_name);
 
+        let tenants = db.collection::<Tenant>("tenants");
         let clients = db.collection::<Client>("clients");
         let users = db.collection::<User>("users");
+        let service_accounts = db.collection::<ServiceAccount>("service_accounts");
         let tokens = db.collection::<Token>("tokens");
         let authorization_codes = db.collection::<AuthorizationCode>("authorization_codes");
+        let social_login_states = db.collection::<SocialLoginState>("social_login_states");
+        let email_tokens = db.collection::<EmailToken>("email_tokens");
+        let user_sessions = db.collection::<UserSession>("user_sessions");
+        let audit_records = db.collection::<AuditRecord>("audit_records");
+        let outbox_messages = db.collection::<OutboxMessage>("outbox_messages");
+        let federated_identities = db.collection::<FederatedIdentity>("federated_identities");
+        let roles = db.collection::<Role>("roles");
+        let groups = db.collection::<Group>("groups");
+        let user_roles = db.collection::<Document>("user_roles");
+        let user_groups = db.collection::<Document>("user_groups");
 
         Ok(Self {
+            client,
             db,
+            tenants,
             clients,
             users,
+            service_accounts,
             tokens,
             authorization_codes,
+            social_login_states,
+            email_tokens,
+            user_sessions,
+            audit_records,
+            outbox_messages,
+            federated_identities,
+            roles,
+            groups,
+            user_roles,
+            user_groups,
         })
     }
 
-    async fn ensure_indexes(&self) -> Result<(), OAuth2Error> {
-        // clients.client_id unique
-        self.clients
-            .create_index(
-                IndexModel::builder()
-                    .keys(doc! { "client_id": 1 })
-                    .options(IndexOptions::builder().unique(true).build())
-                    .build(),
-                None,
-            )
-            .await
-            .map_err(Self::mongo_err_to_oauth)?;
+    /// Schema version recorded in `schema_versions`, or `None` before the first `init()`.
+    pub async fn schema_version(&self) -> Result<Option<i32>, OAuth2Error> {
+        migrations::current_version(self).await
+    }
 
-        // users.username unique
-        self.users
+    async fn ensure_indexes(&self) -> Result<(), OAuth2Error> {
+        // tenants.id unique
+        self.tenants
             .create_index(
                 IndexModel::builder()
-                    .keys(doc! { "username": 1 })
+                    .keys(doc! { "id": 1 })
                     .options(IndexOptions::builder().unique(true).build())
                     .build(),
                 None,
@@ -88,6 +132,20 @@ impl MongoStorage {
             .await
             .map_err(Self::mongo_err_to_oauth)?;
 
+        // service_accounts.client_id and service_accounts.name unique
+        for key in ["client_id", "name"] {
+            self.service_accounts
+                .create_index(
+                    IndexModel::builder()
+                        .keys(doc! { key: 1 })
+                        .options(IndexOptions::builder().unique(true).build())
+                        .build(),
+                    None,
+                )
+                .await
+                .map_err(Self::mongo_err_to_oauth)?;
+        }
+
         // tokens.access_token unique
         self.tokens
             .create_index(
@@ -112,6 +170,30 @@ impl MongoStorage {
             .await
             .map_err(Self::mongo_err_to_oauth)?;
 
+        // tokens by client and by user, newest first
+        for key in ["client_id", "user_id"] {
+            self.tokens
+                .create_index(
+                    IndexModel::builder()
+                        .keys(doc! { key: 1, "created_at": -1 })
+                        .build(),
+                    None,
+                )
+                .await
+                .map_err(Self::mongo_err_to_oauth)?;
+        }
+
+        // tokens.metadata.* for tag lookups
+        self.tokens
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "metadata.$**": 1 })
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
         // authorization_codes.code unique
         self.authorization_codes
             .create_index(
@@ -124,9 +206,335 @@ impl MongoStorage {
             .await
             .map_err(Self::mongo_err_to_oauth)?;
 
+        // social_login_states.state unique
+        self.social_login_states
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "state": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
+        Ok(())
+    }
+
+    async fn ensure_federated_identity_indexes(&self) -> Result<(), OAuth2Error> {
+        // federated_identities.(provider, provider_user_id) unique
+        self.federated_identities
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "provider": 1, "provider_user_id": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
+        // federated_identities.user_id
+        self.federated_identities
+            .create_index(
+                IndexModel::builder().keys(doc! { "user_id": 1 }).build(),
+                None,
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
+        Ok(())
+    }
+
+    async fn ensure_audit_record_indexes(&self) -> Result<(), OAuth2Error> {
+        // audit_records.id unique
+        self.audit_records
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "id": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
+        // audit_records.occurred_at
+        self.audit_records
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "occurred_at": -1 })
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
+        // audit_records (actor, occurred_at)
+        self.audit_records
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "actor": 1, "occurred_at": -1 })
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
+        Ok(())
+    }
+
+    async fn ensure_tenant_scoped_indexes(&self) -> Result<(), OAuth2Error> {
+        // Databases created before client ids and usernames were scoped to tenants have
+        // them unique across all tenants.
+        for (collection, index) in [("clients", "client_id_1"), ("users", "username_1")] {
+            match self
+                .db
+                .collection::<Document>(collection)
+                .drop_index(index, None)
+                .await
+            {
+                Ok(()) => {}
+                // IndexNotFound, or NamespaceNotFound for a collection never written to.
+                Err(e) if matches!(*e.kind, ErrorKind::Command(ref c) if c.code == 26 || c.code == 27) =>
+                    {}
+                Err(e) => return Err(Self::mongo_err_to_oauth(e)),
+            }
+        }
+
+        // clients (tenant_id, client_id) unique; the default tenant indexes as null
+        self.clients
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "tenant_id": 1, "client_id": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
+        // users (tenant_id, username) unique
+        self.users
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "tenant_id": 1, "username": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
+        Ok(())
+    }
+
+    async fn ensure_outbox_message_indexes(&self) -> Result<(), OAuth2Error> {
+        // outbox_messages.id unique
+        self.outbox_messages
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "id": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
+        // outbox_messages.next_attempt_at
+        self.outbox_messages
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "next_attempt_at": 1 })
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
+        Ok(())
+    }
+
+    async fn ensure_role_indexes(&self) -> Result<(), OAuth2Error> {
+        // roles.name / groups.name unique
+        for collection in [self.roles.name(), self.groups.name()] {
+            self.db
+                .collection::<Document>(collection)
+                .create_index(
+                    IndexModel::builder()
+                        .keys(doc! { "name": 1 })
+                        .options(IndexOptions::builder().unique(true).build())
+                        .build(),
+                    None,
+                )
+                .await
+                .map_err(Self::mongo_err_to_oauth)?;
+        }
+
+        // user_roles.(user_id, role_name) unique, and role_name for deleting a role;
+        // likewise for user_groups
+        for (assignments, field) in [
+            (&self.user_roles, "role_name"),
+            (&self.user_groups, "group_name"),
+        ] {
+            assignments
+                .create_index(
+                    IndexModel::builder()
+                        .keys(doc! { "user_id": 1, field: 1 })
+                        .options(IndexOptions::builder().unique(true).build())
+                        .build(),
+                    None,
+                )
+                .await
+                .map_err(Self::mongo_err_to_oauth)?;
+            assignments
+                .create_index(IndexModel::builder().keys(doc! { field: 1 }).build(), None)
+                .await
+                .map_err(Self::mongo_err_to_oauth)?;
+        }
+
+        Ok(())
+    }
+
+    async fn ensure_email_token_indexes(&self) -> Result<(), OAuth2Error> {
+        // email_tokens.token_hash unique
+        self.email_tokens
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "token_hash": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
+        // email_tokens.(user_id, purpose)
+        self.email_tokens
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "user_id": 1, "purpose": 1 })
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
+        Ok(())
+    }
+
+    async fn ensure_user_session_indexes(&self) -> Result<(), OAuth2Error> {
+        // user_sessions.id unique
+        self.user_sessions
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "id": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
+        // user_sessions.user_id
+        self.user_sessions
+            .create_index(
+                IndexModel::builder().keys(doc! { "user_id": 1 }).build(),
+                None,
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
         Ok(())
     }
 
+    /// Names assigned to `user_id` in `assignments`, sorted.
+    async fn list_assigned(
+        assignments: &Collection<Document>,
+        field: &str,
+        user_id: &str,
+    ) -> Result<Vec<String>, OAuth2Error> {
+        let options = FindOptions::builder().sort(doc! { field: 1 }).build();
+        let documents: Vec<Document> = assignments
+            .find(doc! { "user_id": user_id }, options)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?
+            .try_collect()
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+        Ok(documents
+            .iter()
+            .filter_map(|d| d.get_str(field).ok().map(str::to_string))
+            .collect())
+    }
+
+    /// Replace the names assigned to `user_id` in `assignments`.
+    async fn set_assigned(
+        assignments: &Collection<Document>,
+        field: &str,
+        user_id: &str,
+        names: &[String],
+    ) -> Result<(), OAuth2Error> {
+        assignments
+            .delete_many(doc! { "user_id": user_id }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+        let names: std::collections::BTreeSet<&String> = names.iter().collect();
+        if names.is_empty() {
+            return Ok(());
+        }
+        assignments
+            .insert_many(
+                names
+                    .into_iter()
+                    .map(|name| doc! { "user_id": user_id, field: name }),
+                None,
+            )
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    /// Match tokens tagged `key=value`. Keys may contain `.`, which a plain field path
+    /// would treat as nesting, so those go through `$getField` (MongoDB 5.0+).
+    fn metadata_filter(key: &str, value: &str) -> Document {
+        if key.contains('.') {
+            doc! { "$expr": { "$eq": [
+                { "$getField": { "field": { "$literal": key }, "input": "$metadata" } },
+                value,
+            ] } }
+        } else {
+            doc! { format!("metadata.{key}"): value }
+        }
+    }
+
+    fn page_options(page: PageRequest, sort: Document) -> FindOptions {
+        FindOptions::builder()
+            .sort(sort)
+            .skip(page.offset())
+            .limit(i64::from(page.limit()))
+            .build()
+    }
+
+    /// Escape regex metacharacters so a search term matches literally.
+    fn escape_regex(term: &str) -> String {
+        let mut escaped = String::with_capacity(term.len());
+        for c in term.chars() {
+            if "\\.+*?()|[]{}^$".contains(c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    /// Filter value for a `tenant_id` field: null matches the default tenant, whose
+    /// records omit the field.
+    fn tenant(tenant_id: Option<&str>) -> Bson {
+        tenant_id.map_or(Bson::Null, |id| Bson::String(id.to_string()))
+    }
+
     fn duplicate_key_error(err: &mongodb::error::Error) -> bool {
         // Canonical server-side message includes "E11000".
         err.to_string().contains("E11000")
@@ -148,7 +556,7 @@ impl Storage for MongoStorage {
             .run_command(doc! { "ping": 1 }, None)
             .await
             .map_err(Self::mongo_err_to_oauth)?;
-        self.ensure_indexes().await
+        migrations::run(self).await.map(|_| ())
     }
 
     async fn save_client(&self, client: &Client) -> Result<(), OAuth2Error> {
@@ -159,75 +567,479 @@ impl Storage for MongoStorage {
             .map_err(Self::mongo_err_to_oauth)
     }
 
-    async fn get_client(&self, client_id: &str) -> Result<Option<Client>, OAuth2Error> {
+    async fn get_client(
+        &self,
+        client_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<Client>, OAuth2Error> {
         self.clients
-            .find_one(doc! { "client_id": client_id }, None)
+            .find_one(
+                doc! { "client_id": client_id, "tenant_id": Self::tenant(tenant_id) },
+                None,
+            )
             .await
             .map_err(Self::mongo_err_to_oauth)
     }
 
-    async fn save_user(&self, user: &User) -> Result<(), OAuth2Error> {
-        self.users
-            .insert_one(user, None)
+    async fn list_clients_managed_by(&self, manager: &str) -> Result<Vec<Client>, OAuth2Error> {
+        let options = FindOptions::builder().sort(doc! { "client_id": 1 }).build();
+        self.clients
+            .find(doc! { "managed_by": manager }, options)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?
+            .try_collect()
             .await
-            .map(|_| ())
             .map_err(Self::mongo_err_to_oauth)
     }
 
-    async fn get_user_by_username(&self, username: &str) -> Result<Option<User>, OAuth2Error> {
-        self.users
-            .find_one(doc! { "username": username }, None)
+    async fn list_clients(&self, page: PageRequest) -> Result<Vec<Client>, OAuth2Error> {
+        self.clients
+            .find(
+                doc! {},
+                Self::page_options(page, doc! { "client_id": 1, "id": 1 }),
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?
+            .try_collect()
             .await
             .map_err(Self::mongo_err_to_oauth)
     }
 
-    async fn save_token(&self, token: &Token) -> Result<(), OAuth2Error> {
-        self.tokens
-            .insert_one(token, None)
+    async fn count_clients(&self) -> Result<u64, OAuth2Error> {
+        self.clients
+            .count_documents(doc! {}, None)
             .await
-            .map(|_| ())
             .map_err(Self::mongo_err_to_oauth)
     }
 
-    async fn get_token_by_access_token(
-        &self,
-        access_token: &str,
-    ) -> Result<Option<Token>, OAuth2Error> {
-        self.tokens
-            .find_one(doc! { "access_token": access_token }, None)
+    async fn update_client(&self, client: &Client) -> Result<(), OAuth2Error> {
+        self.clients
+            .replace_one(
+                doc! {
+                    "client_id": &client.client_id,
+                    "tenant_id": Self::tenant(client.tenant_id.as_deref()),
+                },
+                client,
+                None,
+            )
             .await
+            .map(|_| ())
             .map_err(Self::mongo_err_to_oauth)
     }
 
-    async fn revoke_token(&self, token: &str) -> Result<(), OAuth2Error> {
+    async fn delete_client(
+        &self,
+        client_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<(), OAuth2Error> {
+        // Keep parity with SQL backends, which remove the client's tokens, codes and (in the
+        // default tenant) service account with it.
+        let tenant = Self::tenant(tenant_id);
+        self.authorization_codes
+            .delete_many(
+                doc! { "client_id": client_id, "tenant_id": tenant.clone() },
+                None,
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
         self.tokens
-            .update_many(
-                doc! { "$or": [ {"access_token": token }, {"refresh_token": token } ] },
-                doc! { "$set": { "revoked": true } },
+            .delete_many(
+                doc! { "client_id": client_id, "tenant_id": tenant.clone() },
                 None,
             )
             .await
+            .map_err(Self::mongo_err_to_oauth)?;
+        if tenant_id.is_none() {
+            self.service_accounts
+                .delete_one(doc! { "client_id": client_id }, None)
+                .await
+                .map_err(Self::mongo_err_to_oauth)?;
+        }
+        self.clients
+            .delete_one(doc! { "client_id": client_id, "tenant_id": tenant }, None)
+            .await
             .map(|_| ())
             .map_err(Self::mongo_err_to_oauth)
     }
 
-    async fn save_authorization_code(
-        &self,
-        auth_code: &AuthorizationCode,
-    ) -> Result<(), OAuth2Error> {
-        self.authorization_codes
-            .insert_one(auth_code, None)
+    async fn save_tenant(&self, tenant: &Tenant) -> Result<(), OAuth2Error> {
+        self.tenants
+            .insert_one(tenant, None)
             .await
             .map(|_| ())
             .map_err(Self::mongo_err_to_oauth)
     }
 
-    async fn get_authorization_code(
-        &self,
-        code: &str,
-    ) -> Result<Option<AuthorizationCode>, OAuth2Error> {
-        self.authorization_codes
-            .find_one(doc! { "code": code }, None)
+    async fn get_tenant(&self, id: &str) -> Result<Option<Tenant>, OAuth2Error> {
+        self.tenants
+            .find_one(doc! { "id": id }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn list_tenants(&self) -> Result<Vec<Tenant>, OAuth2Error> {
+        let options = FindOptions::builder().sort(doc! { "id": 1 }).build();
+        self.tenants
+            .find(doc! {}, options)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?
+            .try_collect()
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn save_service_account(&self, account: &ServiceAccount) -> Result<(), OAuth2Error> {
+        self.service_accounts
+            .insert_one(account, None)
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn get_service_account(&self, id: &str) -> Result<Option<ServiceAccount>, OAuth2Error> {
+        self.service_accounts
+            .find_one(doc! { "id": id }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn get_service_account_by_client_id(
+        &self,
+        client_id: &str,
+    ) -> Result<Option<ServiceAccount>, OAuth2Error> {
+        self.service_accounts
+            .find_one(doc! { "client_id": client_id }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn list_service_accounts(&self) -> Result<Vec<ServiceAccount>, OAuth2Error> {
+        let options = FindOptions::builder().sort(doc! { "name": 1 }).build();
+        self.service_accounts
+            .find(doc! {}, options)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?
+            .try_collect()
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn update_service_account(&self, account: &ServiceAccount) -> Result<(), OAuth2Error> {
+        self.service_accounts
+            .replace_one(doc! { "id": &account.id }, account, None)
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn save_user(&self, user: &User) -> Result<(), OAuth2Error> {
+        self.users
+            .insert_one(user, None)
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn get_user_by_username(
+        &self,
+        username: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<User>, OAuth2Error> {
+        self.users
+            .find_one(
+                doc! { "username": username, "tenant_id": Self::tenant(tenant_id) },
+                None,
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn get_user(&self, user_id: &str) -> Result<Option<User>, OAuth2Error> {
+        self.users
+            .find_one(doc! { "id": user_id }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn list_users_by_email(&self, email: &str) -> Result<Vec<User>, OAuth2Error> {
+        let regex = Regex {
+            pattern: format!("^{}$", Self::escape_regex(email)),
+            options: "i".to_string(),
+        };
+        let options = FindOptions::builder()
+            .sort(doc! { "username": 1, "id": 1 })
+            .build();
+
+        self.users
+            .find(doc! { "email": regex }, options)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?
+            .try_collect()
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn list_users(&self, query: &UserListQuery) -> Result<Vec<User>, OAuth2Error> {
+        let filter = match query.search.as_deref() {
+            Some(search) => {
+                let regex = Regex {
+                    pattern: Self::escape_regex(search),
+                    options: "i".to_string(),
+                };
+                doc! { "$or": [ { "username": regex.clone() }, { "email": regex } ] }
+            }
+            None => doc! {},
+        };
+        let options = FindOptions::builder()
+            .sort(doc! { "username": 1, "id": 1 })
+            .skip(u64::from(query.offset))
+            .limit(i64::from(query.limit))
+            .build();
+
+        self.users
+            .find(filter, options)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?
+            .try_collect()
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn count_users(&self) -> Result<u64, OAuth2Error> {
+        self.users
+            .count_documents(doc! {}, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn update_user(&self, user: &User) -> Result<(), OAuth2Error> {
+        self.users
+            .replace_one(doc! { "id": &user.id }, user, None)
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn delete_user(&self, user_id: &str) -> Result<(), OAuth2Error> {
+        // Keep parity with SQL backends, where tokens/codes reference users(id).
+        self.authorization_codes
+            .delete_many(doc! { "user_id": user_id }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+        self.tokens
+            .delete_many(doc! { "user_id": user_id }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+        self.federated_identities
+            .delete_many(doc! { "user_id": user_id }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+        self.email_tokens
+            .delete_many(doc! { "user_id": user_id }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+        self.user_sessions
+            .delete_many(doc! { "user_id": user_id }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+        for assignments in [&self.user_roles, &self.user_groups] {
+            assignments
+                .delete_many(doc! { "user_id": user_id }, None)
+                .await
+                .map_err(Self::mongo_err_to_oauth)?;
+        }
+        self.users
+            .delete_one(doc! { "id": user_id }, None)
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn save_token(&self, token: &Token) -> Result<(), OAuth2Error> {
+        self.tokens
+            .insert_one(token, None)
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn get_token_by_access_token(
+        &self,
+        access_token: &str,
+    ) -> Result<Option<Token>, OAuth2Error> {
+        self.tokens
+            .find_one(doc! { "access_token": access_token }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn get_token_by_refresh_token(
+        &self,
+        refresh_token: &str,
+    ) -> Result<Option<Token>, OAuth2Error> {
+        self.tokens
+            .find_one(doc! { "refresh_token": refresh_token }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn revoke_token(&self, token: &str) -> Result<(), OAuth2Error> {
+        self.tokens
+            .update_many(
+                doc! { "$or": [ {"access_token": token }, {"refresh_token": token } ] },
+                doc! { "$set": { "revoked": true } },
+                None,
+            )
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn revoke_token_grant(&self, grant_id: &str) -> Result<(), OAuth2Error> {
+        // Legacy documents have no grant_id; their grant is the token's own id.
+        self.tokens
+            .update_many(
+                doc! { "$or": [
+                    { "grant_id": grant_id },
+                    { "grant_id": { "$exists": false }, "id": grant_id },
+                ] },
+                doc! { "$set": { "revoked": true } },
+                None,
+            )
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn list_revoked_tokens(&self) -> Result<Vec<Token>, OAuth2Error> {
+        let mut tokens: Vec<Token> = self
+            .tokens
+            .find(doc! { "revoked": true }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?
+            .try_collect()
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
+        // Timestamps are stored as strings, so expiry is compared here rather than in the query.
+        let now = chrono::Utc::now();
+        tokens.retain(|token| token.expires_at > now);
+        tokens.sort_by_key(|token| token.expires_at);
+        Ok(tokens)
+    }
+
+    async fn find_tokens_by_metadata(
+        &self,
+        query: &TokenMetadataQuery,
+    ) -> Result<Vec<Token>, OAuth2Error> {
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .skip(u64::from(query.offset))
+            .limit(i64::from(query.limit))
+            .build();
+
+        self.tokens
+            .find(Self::metadata_filter(&query.key, &query.value), options)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?
+            .try_collect()
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn revoke_tokens_by_metadata(&self, key: &str, value: &str) -> Result<u64, OAuth2Error> {
+        let mut filter = Self::metadata_filter(key, value);
+        filter.insert("revoked", false);
+        self.tokens
+            .update_many(filter, doc! { "$set": { "revoked": true } }, None)
+            .await
+            .map(|result| result.modified_count)
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn list_tokens_by_client(
+        &self,
+        client_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Token>, OAuth2Error> {
+        self.tokens
+            .find(
+                doc! { "client_id": client_id },
+                Self::page_options(page, doc! { "created_at": -1 }),
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?
+            .try_collect()
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn list_tokens_by_user(
+        &self,
+        user_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Token>, OAuth2Error> {
+        self.tokens
+            .find(
+                doc! { "user_id": user_id },
+                Self::page_options(page, doc! { "created_at": -1 }),
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?
+            .try_collect()
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn count_tokens(&self) -> Result<u64, OAuth2Error> {
+        self.tokens
+            .count_documents(doc! {}, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn count_active_tokens(&self) -> Result<u64, OAuth2Error> {
+        // Timestamps are stored as strings, so expiry is compared here rather than in the
+        // query; only `expires_at` is fetched.
+        let options = FindOptions::builder()
+            .projection(doc! { "_id": 0, "expires_at": 1 })
+            .build();
+        let expiries: Vec<Document> = self
+            .tokens
+            .clone_with_type::<Document>()
+            .find(doc! { "revoked": false }, options)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?
+            .try_collect()
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
+        let now = chrono::Utc::now();
+        Ok(expiries
+            .iter()
+            .filter_map(|doc| doc.get_str("expires_at").ok())
+            .filter_map(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+            .filter(|expires_at| *expires_at > now)
+            .count() as u64)
+    }
+
+    async fn save_authorization_code(
+        &self,
+        auth_code: &AuthorizationCode,
+    ) -> Result<(), OAuth2Error> {
+        self.authorization_codes
+            .insert_one(auth_code, None)
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn get_authorization_code(
+        &self,
+        code: &str,
+    ) -> Result<Option<AuthorizationCode>, OAuth2Error> {
+        self.authorization_codes
+            .find_one(doc! { "code": code }, None)
             .await
             .map_err(Self::mongo_err_to_oauth)
     }
@@ -244,6 +1056,474 @@ impl Storage for MongoStorage {
             .map_err(Self::mongo_err_to_oauth)
     }
 
+    async fn begin_transaction(&self) -> Result<Box<dyn StorageTransaction>, OAuth2Error> {
+        // Multi-document transactions need a replica set or sharded cluster; against a
+        // standalone server the first write fails.
+        let mut session = self
+            .client
+            .start_session(None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+        session
+            .start_transaction(None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+        Ok(Box::new(MongoTransaction {
+            session,
+            tokens: self.tokens.clone(),
+            authorization_codes: self.authorization_codes.clone(),
+            outbox_messages: self.outbox_messages.clone(),
+        }))
+    }
+
+    async fn save_social_login_state(&self, state: &SocialLoginState) -> Result<(), OAuth2Error> {
+        // Timestamps are stored as RFC 3339 strings, which only sort chronologically at
+        // whole-second precision; the margin keeps the comparison safely on one side.
+        let cutoff = (chrono::Utc::now() - chrono::Duration::minutes(1))
+            .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        self.social_login_states
+            .delete_many(doc! { "expires_at": { "$lt": cutoff } }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
+        self.social_login_states
+            .insert_one(state, None)
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn take_social_login_state(
+        &self,
+        state: &str,
+    ) -> Result<Option<SocialLoginState>, OAuth2Error> {
+        self.social_login_states
+            .find_one_and_delete(doc! { "state": state }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn save_email_token(&self, token: &EmailToken) -> Result<(), OAuth2Error> {
+        // See `save_social_login_state` for the margin.
+        let cutoff = (chrono::Utc::now() - chrono::Duration::minutes(1))
+            .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        self.email_tokens
+            .delete_many(doc! { "expires_at": { "$lt": cutoff } }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
+        self.email_tokens
+            .insert_one(token, None)
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn take_email_token(&self, token_hash: &str) -> Result<Option<EmailToken>, OAuth2Error> {
+        self.email_tokens
+            .find_one_and_delete(doc! { "token_hash": token_hash }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn delete_email_tokens(
+        &self,
+        user_id: &str,
+        purpose: EmailTokenPurpose,
+    ) -> Result<(), OAuth2Error> {
+        self.email_tokens
+            .delete_many(
+                doc! { "user_id": user_id, "purpose": purpose.as_str() },
+                None,
+            )
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn save_user_session(&self, session: &UserSession) -> Result<(), OAuth2Error> {
+        // Abandoned sessions are never deleted by their browser; clear them out here. See
+        // `save_social_login_state` for the margin.
+        let cutoff = (chrono::Utc::now() - chrono::Duration::minutes(1))
+            .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        self.user_sessions
+            .delete_many(
+                doc! { "$or": [
+                    { "idle_expires_at": { "$lt": &cutoff } },
+                    { "expires_at": { "$lt": &cutoff } },
+                ] },
+                None,
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
+        self.user_sessions
+            .replace_one(
+                doc! { "id": &session.id },
+                session,
+                ReplaceOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn get_user_session(&self, id: &str) -> Result<Option<UserSession>, OAuth2Error> {
+        self.user_sessions
+            .find_one(doc! { "id": id }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn touch_user_session(
+        &self,
+        id: &str,
+        last_seen_at: chrono::DateTime<chrono::Utc>,
+        idle_expires_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<(), OAuth2Error> {
+        // Same format as serde, so the document still deserializes.
+        let times = doc! {
+            "last_seen_at": last_seen_at.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+            "idle_expires_at": idle_expires_at.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+        };
+        self.user_sessions
+            .update_one(doc! { "id": id }, doc! { "$set": times }, None)
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn list_user_sessions(&self, user_id: &str) -> Result<Vec<UserSession>, OAuth2Error> {
+        // Timestamps are strings, so expiry is checked after loading.
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .build();
+        let sessions: Vec<UserSession> = self
+            .user_sessions
+            .find(doc! { "user_id": user_id }, options)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?
+            .try_collect()
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+        Ok(sessions
+            .into_iter()
+            .filter(|session| !session.is_expired())
+            .collect())
+    }
+
+    async fn delete_user_session(&self, id: &str) -> Result<(), OAuth2Error> {
+        self.user_sessions
+            .delete_one(doc! { "id": id }, None)
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn save_audit_record(&self, record: &AuditRecord) -> Result<(), OAuth2Error> {
+        self.audit_records
+            .insert_one(record, None)
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn list_audit_records(
+        &self,
+        query: &AuditQuery,
+    ) -> Result<Vec<AuditRecord>, OAuth2Error> {
+        let mut filter = doc! {};
+        if let Some(ref actor) = query.actor {
+            filter.insert("actor", actor.as_str());
+        }
+        if let Some(action) = query.action {
+            filter.insert("action", action.as_str());
+        }
+        // Timestamps are strings with varying sub-second digits, so they only compare
+        // reliably at whole seconds: the range is widened by a second on each side here
+        // and checked exactly after loading, and records within one second are re-sorted.
+        let mut occurred_at = doc! {};
+        if let Some(since) = query.since {
+            let since = (since - chrono::Duration::seconds(1))
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+            occurred_at.insert("$gte", since);
+        }
+        if let Some(until) = query.until {
+            let until = (until + chrono::Duration::seconds(1))
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+            occurred_at.insert("$lt", until);
+        }
+        if !occurred_at.is_empty() {
+            filter.insert("occurred_at", occurred_at);
+        }
+        let options = FindOptions::builder()
+            .sort(doc! { "occurred_at": -1 })
+            .limit(i64::from(query.limit))
+            .build();
+        let records: Vec<AuditRecord> = self
+            .audit_records
+            .find(filter, options)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?
+            .try_collect()
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+        let mut records: Vec<AuditRecord> = records
+            .into_iter()
+            .filter(|record| query.matches(record))
+            .collect();
+        records.sort_by_key(|record| std::cmp::Reverse(record.occurred_at));
+        records.truncate(query.limit as usize);
+        Ok(records)
+    }
+
+    async fn enqueue_outbox_message(&self, message: &OutboxMessage) -> Result<(), OAuth2Error> {
+        self.outbox_messages
+            .insert_one(message, None)
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn claim_outbox_messages(
+        &self,
+        now: chrono::DateTime<chrono::Utc>,
+        lease_until: chrono::DateTime<chrono::Utc>,
+        limit: u32,
+    ) -> Result<Vec<OutboxMessage>, OAuth2Error> {
+        // As with audit records, the due time is compared a second wide here and exactly
+        // after loading.
+        let due_by =
+            (now + chrono::Duration::seconds(1)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": 1 })
+            .limit(i64::from(limit))
+            .build();
+        let candidates: Vec<OutboxMessage> = self
+            .outbox_messages
+            .find(doc! { "next_attempt_at": { "$lt": due_by } }, options)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?
+            .try_collect()
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+
+        let lease = lease_until.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true);
+        let mut claimed = Vec::new();
+        for mut message in candidates {
+            if message.next_attempt_at > now {
+                continue;
+            }
+            // Conditional on the lease it was loaded with, so only one relay wins it.
+            let leased_at = message
+                .next_attempt_at
+                .to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true);
+            let result = self
+                .outbox_messages
+                .update_one(
+                    doc! { "id": &message.id, "next_attempt_at": leased_at },
+                    doc! { "$set": { "next_attempt_at": &lease } },
+                    None,
+                )
+                .await
+                .map_err(Self::mongo_err_to_oauth)?;
+            if result.modified_count == 1 {
+                message.next_attempt_at = lease_until;
+                claimed.push(message);
+            }
+        }
+        Ok(claimed)
+    }
+
+    async fn delete_outbox_message(&self, id: &str) -> Result<(), OAuth2Error> {
+        self.outbox_messages
+            .delete_one(doc! { "id": id }, None)
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn reschedule_outbox_message(
+        &self,
+        id: &str,
+        next_attempt_at: chrono::DateTime<chrono::Utc>,
+        error: &str,
+    ) -> Result<(), OAuth2Error> {
+        self.outbox_messages
+            .update_one(
+                doc! { "id": id },
+                doc! {
+                    "$inc": { "attempts": 1 },
+                    "$set": {
+                        "next_attempt_at":
+                            next_attempt_at.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+                        "last_error": error,
+                    },
+                },
+                None,
+            )
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn save_federated_identity(
+        &self,
+        identity: &FederatedIdentity,
+    ) -> Result<(), OAuth2Error> {
+        self.federated_identities
+            .insert_one(identity, None)
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn get_federated_identity(
+        &self,
+        provider: &str,
+        provider_user_id: &str,
+    ) -> Result<Option<FederatedIdentity>, OAuth2Error> {
+        self.federated_identities
+            .find_one(
+                doc! { "provider": provider, "provider_user_id": provider_user_id },
+                None,
+            )
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn list_federated_identities(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<FederatedIdentity>, OAuth2Error> {
+        let options = FindOptions::builder()
+            .sort(doc! { "provider": 1, "provider_user_id": 1 })
+            .build();
+        self.federated_identities
+            .find(doc! { "user_id": user_id }, options)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?
+            .try_collect()
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn update_federated_identity_tokens(
+        &self,
+        identity: &FederatedIdentity,
+    ) -> Result<(), OAuth2Error> {
+        let tokens = doc! {
+            "access_token": identity.access_token.as_deref(),
+            "refresh_token": identity.refresh_token.as_deref(),
+            // Same format as serde, so the document still deserializes.
+            "token_expires_at": identity
+                .token_expires_at
+                .map(|at| at.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)),
+        };
+        self.federated_identities
+            .update_one(
+                doc! { "provider": &identity.provider, "provider_user_id": &identity.provider_user_id },
+                doc! { "$set": tokens },
+                None,
+            )
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn delete_federated_identity(
+        &self,
+        provider: &str,
+        provider_user_id: &str,
+    ) -> Result<(), OAuth2Error> {
+        self.federated_identities
+            .delete_one(
+                doc! { "provider": provider, "provider_user_id": provider_user_id },
+                None,
+            )
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn save_role(&self, role: &Role) -> Result<(), OAuth2Error> {
+        self.roles
+            .insert_one(role, None)
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn list_roles(&self) -> Result<Vec<Role>, OAuth2Error> {
+        let options = FindOptions::builder().sort(doc! { "name": 1 }).build();
+        self.roles
+            .find(None, options)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?
+            .try_collect()
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn delete_role(&self, name: &str) -> Result<(), OAuth2Error> {
+        self.user_roles
+            .delete_many(doc! { "role_name": name }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+        self.roles
+            .delete_one(doc! { "name": name }, None)
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn save_group(&self, group: &Group) -> Result<(), OAuth2Error> {
+        self.groups
+            .insert_one(group, None)
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn list_groups(&self) -> Result<Vec<Group>, OAuth2Error> {
+        let options = FindOptions::builder().sort(doc! { "name": 1 }).build();
+        self.groups
+            .find(None, options)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?
+            .try_collect()
+            .await
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn delete_group(&self, name: &str) -> Result<(), OAuth2Error> {
+        self.user_groups
+            .delete_many(doc! { "group_name": name }, None)
+            .await
+            .map_err(Self::mongo_err_to_oauth)?;
+        self.groups
+            .delete_one(doc! { "name": name }, None)
+            .await
+            .map(|_| ())
+            .map_err(Self::mongo_err_to_oauth)
+    }
+
+    async fn list_user_roles(&self, user_id: &str) -> Result<Vec<String>, OAuth2Error> {
+        Self::list_assigned(&self.user_roles, "role_name", user_id).await
+    }
+
+    async fn set_user_roles(&self, user_id: &str, roles: &[String]) -> Result<(), OAuth2Error> {
+        Self::set_assigned(&self.user_roles, "role_name", user_id, roles).await
+    }
+
+    async fn list_user_groups(&self, user_id: &str) -> Result<Vec<String>, OAuth2Error> {
+        Self::list_assigned(&self.user_groups, "group_name", user_id).await
+    }
+
+    async fn set_user_groups(&self, user_id: &str, groups: &[String]) -> Result<(), OAuth2Error> {
+        Self::set_assigned(&self.user_groups, "group_name", user_id, groups).await
+    }
+
     async fn healthcheck(&self) -> Result<(), OAuth2Error> {
         self.db
             .run_command(doc! { "ping": 1 }, None)
@@ -253,6 +1533,98 @@ impl Storage for MongoStorage {
     }
 }
 
+/// A session-scoped transaction; the server aborts it if the session ends uncommitted.
+struct MongoTransaction {
+    session: ClientSession,
+    tokens: Collection<Token>,
+    authorization_codes: Collection<AuthorizationCode>,
+    outbox_messages: Collection<OutboxMessage>,
+}
+
+#[async_trait]
+impl StorageTransaction for MongoTransaction {
+    async fn consume_authorization_code(&mut self, code: &str) -> Result<(), OAuth2Error> {
+        // Conditional on `used`, so a concurrent exchange of the same code matches nothing.
+        let result = self
+            .authorization_codes
+            .update_one_with_session(
+                doc! { "code": code, "used": false },
+                doc! { "$set": { "used": true } },
+                None,
+                &mut self.session,
+            )
+            .await
+            .map_err(MongoStorage::mongo_err_to_oauth)?;
+        if result.modified_count != 1 {
+            return Err(OAuth2Error::invalid_grant(
+                "Authorization code is invalid or already used",
+            ));
+        }
+        Ok(())
+    }
+
+    async fn save_token(&mut self, token: &Token) -> Result<(), OAuth2Error> {
+        self.tokens
+            .insert_one_with_session(token, None, &mut self.session)
+            .await
+            .map(|_| ())
+            .map_err(MongoStorage::mongo_err_to_oauth)
+    }
+
+    async fn revoke_token(&mut self, token: &str) -> Result<(), OAuth2Error> {
+        self.tokens
+            .update_many_with_session(
+                doc! { "$or": [ {"access_token": token }, {"refresh_token": token } ] },
+                doc! { "$set": { "revoked": true } },
+                None,
+                &mut self.session,
+            )
+            .await
+            .map(|_| ())
+            .map_err(MongoStorage::mongo_err_to_oauth)
+    }
+
+    async fn redeem_refresh_token(&mut self, refresh_token: &str) -> Result<(), OAuth2Error> {
+        // Conditional on `revoked`, so a concurrent rotation of the same token matches nothing.
+        let result = self
+            .tokens
+            .update_one_with_session(
+                doc! { "refresh_token": refresh_token, "revoked": false },
+                doc! { "$set": { "revoked": true } },
+                None,
+                &mut self.session,
+            )
+            .await
+            .map_err(MongoStorage::mongo_err_to_oauth)?;
+        if result.modified_count != 1 {
+            return Err(OAuth2Error::invalid_grant("Refresh token is revoked"));
+        }
+        Ok(())
+    }
+
+    async fn enqueue_outbox_message(&mut self, message: &OutboxMessage) -> Result<(), OAuth2Error> {
+        self.outbox_messages
+            .insert_one_with_session(message, None, &mut self.session)
+            .await
+            .map(|_| ())
+            .map_err(MongoStorage::mongo_err_to_oauth)
+    }
+
+    async fn commit(mut self: Box<Self>) -> Result<(), OAuth2Error> {
+        self.session
+            .commit_transaction()
+            .await
+            .map_err(MongoStorage::mongo_err_to_oauth)
+    }
+
+    async fn rollback(mut self: Box<Self>) -> Result<(), OAuth2Error> {
+        self.session
+            .abort_transaction()
+            .await
+            .map_err(MongoStorage::mongo_err_to_oauth)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -293,4 +1665,25 @@ mod tests {
             "refresh_token should be present when Some"
         );
     }
+
+    #[test]
+    fn metadata_filter_keeps_dotted_keys_literal() {
+        assert_eq!(
+            MongoStorage::metadata_filter("deployment", "canary"),
+            doc! { "metadata.deployment": "canary" }
+        );
+        let dotted = MongoStorage::metadata_filter("app.tier", "web");
+        assert!(dotted.contains_key("$expr"));
+        assert!(!dotted.contains_key("metadata.app.tier"));
+    }
+
+    #[test]
+    fn escape_regex_matches_search_terms_literally() {
+        assert_eq!(MongoStorage::escape_regex("alice"), "alice");
+        assert_eq!(
+            MongoStorage::escape_regex("a.b+c@example.com"),
+            "a\\.b\\+c@example\\.com"
+        );
+        assert_eq!(MongoStorage::escape_regex("(.*)"), "\\(\\.\\*\\)");
+    }
 }
@@ -0,0 +1,223 @@
+//! Versioned schema migrations for `MongoStorage`.
+//!
+//! Applied versions are recorded in the `schema_versions` collection. Migrations run in
+//! order from `init()`; each must be idempotent, since replicas starting together may both
+//! run one before either records it.
+
+use futures::{future::BoxFuture, TryStreamExt};
+use mongodb::{
+    bson::{doc, DateTime, Document},
+    options::{FindOneOptions, IndexOptions},
+    IndexModel,
+};
+
+use oauth2_core::OAuth2Error;
+
+use crate::MongoStorage;
+
+/// One step of the Mongo schema.
+pub struct Migration {
+    pub version: i32,
+    pub description: &'static str,
+    run: for<'a> fn(&'a MongoStorage) -> BoxFuture<'a, Result<(), OAuth2Error>>,
+}
+
+/// All migrations, in the order they are applied. Append only.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create_indexes",
+        run: |storage| Box::pin(storage.ensure_indexes()),
+    },
+    Migration {
+        version: 2,
+        description: "backfill_client_type",
+        run: |storage| Box::pin(backfill_client_type(storage)),
+    },
+    Migration {
+        version: 3,
+        description: "create_federated_identity_indexes",
+        run: |storage| Box::pin(storage.ensure_federated_identity_indexes()),
+    },
+    Migration {
+        version: 4,
+        description: "create_role_indexes",
+        run: |storage| Box::pin(storage.ensure_role_indexes()),
+    },
+    Migration {
+        version: 5,
+        description: "create_email_token_indexes",
+        run: |storage| Box::pin(storage.ensure_email_token_indexes()),
+    },
+    Migration {
+        version: 6,
+        description: "create_user_session_indexes",
+        run: |storage| Box::pin(storage.ensure_user_session_indexes()),
+    },
+    Migration {
+        version: 7,
+        description: "create_audit_record_indexes",
+        run: |storage| Box::pin(storage.ensure_audit_record_indexes()),
+    },
+    Migration {
+        version: 8,
+        description: "create_outbox_message_indexes",
+        run: |storage| Box::pin(storage.ensure_outbox_message_indexes()),
+    },
+    Migration {
+        version: 9,
+        description: "scope_client_ids_and_usernames_to_tenants",
+        run: |storage| Box::pin(storage.ensure_tenant_scoped_indexes()),
+    },
+    Migration {
+        version: 10,
+        description: "backfill_authorization_code_tenants",
+        run: |storage| Box::pin(backfill_authorization_code_tenants(storage)),
+    },
+];
+
+/// Newest schema version this binary understands.
+pub const SCHEMA_VERSION: i32 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+pub(crate) const SCHEMA_VERSIONS_COLLECTION: &str = "schema_versions";
+
+/// Bring the database up to `SCHEMA_VERSION`, returning the versions that were applied.
+///
+/// Refuses to touch a database already migrated past `SCHEMA_VERSION` by a newer release.
+pub(crate) async fn run(storage: &MongoStorage) -> Result<Vec<i32>, OAuth2Error> {
+    let versions = storage
+        .db
+        .collection::<Document>(SCHEMA_VERSIONS_COLLECTION);
+    versions
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "version": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            None,
+        )
+        .await
+        .map_err(MongoStorage::mongo_err_to_oauth)?;
+
+    let current = current_version(storage).await?;
+    check_supported(current)?;
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|m| current.is_none_or(|v| m.version > v))
+    {
+        (migration.run)(storage).await?;
+
+        let record = doc! {
+            "version": migration.version,
+            "description": migration.description,
+            "applied_at": DateTime::now(),
+        };
+        match versions.insert_one(record, None).await {
+            Ok(_) => {}
+            // Another replica finished the same migration first.
+            Err(e) if MongoStorage::duplicate_key_error(&e) => {}
+            Err(e) => return Err(MongoStorage::mongo_err_to_oauth(e)),
+        }
+        applied.push(migration.version);
+    }
+
+    Ok(applied)
+}
+
+/// Highest recorded schema version, or `None` for a database never migrated.
+pub(crate) async fn current_version(storage: &MongoStorage) -> Result<Option<i32>, OAuth2Error> {
+    let options = FindOneOptions::builder()
+        .sort(doc! { "version": -1 })
+        .build();
+    let latest = storage
+        .db
+        .collection::<Document>(SCHEMA_VERSIONS_COLLECTION)
+        .find_one(None, options)
+        .await
+        .map_err(MongoStorage::mongo_err_to_oauth)?;
+
+    Ok(latest.and_then(|d| d.get_i32("version").ok()))
+}
+
+fn check_supported(current: Option<i32>) -> Result<(), OAuth2Error> {
+    match current {
+        Some(version) if version > SCHEMA_VERSION => Err(OAuth2Error::new(
+            "server_error",
+            Some(&format!(
+                "MongoDB schema is at version {version} but this server only understands up to \
+                 {SCHEMA_VERSION}; upgrade the server"
+            )),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Clients stored before `client_type` existed, or with the pre-RFC 8252 `application`
+/// value, become `confidential` (mirrors SQL V14/V15).
+async fn backfill_client_type(storage: &MongoStorage) -> Result<(), OAuth2Error> {
+    storage
+        .db
+        .collection::<Document>("clients")
+        .update_many(
+            doc! { "$or": [
+                { "client_type": { "$exists": false } },
+                { "client_type": "application" },
+            ] },
+            doc! { "$set": { "client_type": "confidential" } },
+            None,
+        )
+        .await
+        .map(|_| ())
+        .map_err(MongoStorage::mongo_err_to_oauth)
+}
+
+/// Authorization codes stored before they carried a tenant take their user's (mirrors SQL
+/// V32).
+async fn backfill_authorization_code_tenants(storage: &MongoStorage) -> Result<(), OAuth2Error> {
+    let users: Vec<Document> = storage
+        .db
+        .collection::<Document>("users")
+        .find(doc! { "tenant_id": { "$ne": null } }, None)
+        .await
+        .map_err(MongoStorage::mongo_err_to_oauth)?
+        .try_collect()
+        .await
+        .map_err(MongoStorage::mongo_err_to_oauth)?;
+    let codes = storage.db.collection::<Document>("authorization_codes");
+    for user in users {
+        let (Ok(user_id), Ok(tenant_id)) = (user.get_str("id"), user.get_str("tenant_id")) else {
+            continue;
+        };
+        codes
+            .update_many(
+                doc! { "user_id": user_id, "tenant_id": { "$exists": false } },
+                doc! { "$set": { "tenant_id": tenant_id } },
+                None,
+            )
+            .await
+            .map_err(MongoStorage::mongo_err_to_oauth)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_contiguous_from_one() {
+        let versions: Vec<i32> = MIGRATIONS.iter().map(|m| m.version).collect();
+        let expected: Vec<i32> = (1..=SCHEMA_VERSION).collect();
+        assert_eq!(versions, expected);
+    }
+
+    #[test]
+    fn newer_schema_versions_are_refused() {
+        assert!(check_supported(None).is_ok());
+        assert!(check_supported(Some(SCHEMA_VERSION)).is_ok());
+        let err = check_supported(Some(SCHEMA_VERSION + 1)).unwrap_err();
+        assert!(err.to_string().contains("upgrade the server"), "{err}");
+    }
+}
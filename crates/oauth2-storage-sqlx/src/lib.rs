@@ -1,3 +1,4 @@
+pub mod migrations;
 pub mod sqlx;
 
 pub use sqlx::SqlxStorage;
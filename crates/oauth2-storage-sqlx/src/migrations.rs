@@ -0,0 +1,190 @@
+//! Embedded Postgres schema migrations.
+//!
+//! The SQL files under `migrations/sql` use Flyway's `V<version>__<description>.sql` naming,
+//! which `sqlx::migrate!` cannot parse, so they are embedded here and applied by a small
+//! runner instead. Databases previously migrated by Flyway are picked up from
+//! `flyway_schema_history`, so switching a deployment over does not re-run anything.
+
+use sqlx::{Connection, PgConnection, Pool, Postgres, Row};
+
+/// A single embedded migration.
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub version: i32,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+macro_rules! migration {
+    ($version:literal, $description:literal) => {
+        Migration {
+            version: $version,
+            description: $description,
+            sql: include_str!(concat!(
+                "../../../migrations/sql/V",
+                stringify!($version),
+                "__",
+                $description,
+                ".sql"
+            )),
+        }
+    };
+}
+
+/// All embedded migrations, in the order they are applied.
+pub const MIGRATIONS: &[Migration] = &[
+    migration!(1, "create_clients_table"),
+    migration!(2, "create_users_table"),
+    migration!(3, "create_tokens_table"),
+    migration!(4, "create_authorization_codes_table"),
+    migration!(5, "insert_default_data"),
+    migration!(6, "make_tokens_user_id_nullable"),
+    migration!(7, "add_client_registration_access_token"),
+    migration!(8, "add_authorization_code_context_binding"),
+    migration!(9, "add_token_grant_id"),
+    migration!(10, "create_service_accounts_table"),
+    migration!(11, "add_token_metadata"),
+    migration!(12, "add_client_managed_by"),
+    migration!(13, "add_client_pending_redirect_uris"),
+    migration!(14, "add_client_type"),
+    migration!(15, "add_public_and_native_client_types"),
+    migration!(16, "create_social_login_states_table"),
+    migration!(17, "create_tenants_table"),
+    migration!(18, "add_client_metadata"),
+    migration!(19, "add_token_listing_indexes"),
+    migration!(20, "create_federated_identities_table"),
+    migration!(21, "add_federated_identity_tokens"),
+    migration!(22, "create_roles_and_groups_tables"),
+    migration!(23, "create_email_tokens_table"),
+    migration!(24, "add_email_token_return_to"),
+    migration!(25, "create_user_sessions_table"),
+    migration!(26, "create_audit_records_table"),
+    migration!(27, "create_outbox_messages_table"),
+    migration!(28, "add_client_allowed_origins"),
+    migration!(29, "add_social_login_state_browser_binding"),
+    migration!(30, "add_token_context_binding"),
+    migration!(31, "scope_client_ids_and_usernames_to_tenants"),
+    migration!(32, "add_authorization_code_tenant_id"),
+];
+
+/// Key for the session-level advisory lock held while migrating, so replicas starting at
+/// the same time apply each migration exactly once.
+const MIGRATION_LOCK_KEY: i64 = 0x6f61_7574_6832_6d67; // "oauth2mg"
+
+/// Apply every pending migration, returning the versions that were applied.
+///
+/// Each migration runs in its own transaction together with its `schema_migrations` row,
+/// so a failure leaves the schema at the last fully applied version.
+pub async fn run_postgres_migrations(pool: &Pool<Postgres>) -> Result<Vec<i32>, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    sqlx::query("SELECT pg_advisory_lock($1)")
+        .bind(MIGRATION_LOCK_KEY)
+        .execute(&mut *conn)
+        .await?;
+
+    let result = apply_pending(&mut conn).await;
+
+    // Release even on failure; the lock is also dropped when the connection closes.
+    let unlock = sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(MIGRATION_LOCK_KEY)
+        .execute(&mut *conn)
+        .await;
+
+    let applied = result?;
+    unlock?;
+    Ok(applied)
+}
+
+async fn apply_pending(conn: &mut PgConnection) -> Result<Vec<i32>, sqlx::Error> {
+    sqlx::raw_sql(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        )
+        "#,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let mut applied: Vec<i32> = sqlx::query("SELECT version FROM schema_migrations")
+        .fetch_all(&mut *conn)
+        .await?
+        .iter()
+        .map(|row| row.get("version"))
+        .collect();
+    applied.extend(flyway_versions(conn).await?);
+
+    let mut newly_applied = Vec::new();
+    for migration in MIGRATIONS {
+        if applied.contains(&migration.version) {
+            continue;
+        }
+
+        let mut tx = conn.begin().await?;
+        sqlx::raw_sql(migration.sql).execute(&mut *tx).await?;
+        sqlx::query("INSERT INTO schema_migrations (version, description) VALUES ($1, $2)")
+            .bind(migration.version)
+            .bind(migration.description)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        newly_applied.push(migration.version);
+    }
+
+    Ok(newly_applied)
+}
+
+/// Versions recorded as successful by Flyway, if it has ever run against this database.
+async fn flyway_versions(conn: &mut PgConnection) -> Result<Vec<i32>, sqlx::Error> {
+    let exists: bool =
+        sqlx::query_scalar("SELECT to_regclass('flyway_schema_history') IS NOT NULL")
+            .fetch_one(&mut *conn)
+            .await?;
+    if !exists {
+        return Ok(Vec::new());
+    }
+
+    let versions: Vec<Option<String>> = sqlx::query_scalar(
+        "SELECT version FROM flyway_schema_history WHERE success AND version IS NOT NULL",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(versions
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.parse().ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn every_migration_file_is_embedded_in_order() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../migrations/sql");
+        let mut on_disk: Vec<(i32, String)> = std::fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|entry| {
+                let name = entry.ok()?.file_name().into_string().ok()?;
+                let (version, rest) = name.strip_prefix('V')?.split_once("__")?;
+                Some((
+                    version.parse().ok()?,
+                    rest.strip_suffix(".sql")?.to_string(),
+                ))
+            })
+            .collect();
+        on_disk.sort();
+
+        let embedded: Vec<(i32, String)> = MIGRATIONS
+            .iter()
+            .map(|m| (m.version, m.description.to_string()))
+            .collect();
+        assert_eq!(embedded, on_disk);
+    }
+}
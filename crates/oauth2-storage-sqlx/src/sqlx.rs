@@ -1,7 +1,16 @@
 use async_trait::async_trait;
-use oauth2_core::{AuthorizationCode, Client, OAuth2Error, Token, User};
-use oauth2_ports::Storage;
-use sqlx::{Pool, Postgres, Sqlite};
+use chrono::{DateTime, Utc};
+use oauth2_core::{
+    AuditRecord, AuthorizationCode, Client, EmailToken, EmailTokenPurpose, FederatedIdentity,
+    Group, OAuth2Error, OutboxMessage, Role, ServiceAccount, SocialLoginState, Tenant, Token, User,
+    UserSession,
+};
+use oauth2_ports::{
+    AuditQuery, PageRequest, Storage, StorageTransaction, TokenMetadataQuery, UserListQuery,
+};
+use sqlx::postgres::PgRow;
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Connection, FromRow, PgExecutor, Pool, Postgres, Sqlite, SqliteExecutor};
 use std::borrow::Cow;
 use std::path::PathBuf;
 
@@ -48,6 +57,16 @@ impl SqlxStorage {
         Ok(Self { pool })
     }
 
+    /// Apply pending embedded schema migrations, returning the versions applied.
+    ///
+    /// Only Postgres is migrated; SQLite schemas are bootstrapped by `init()`.
+    pub async fn run_migrations(&self) -> Result<Vec<i32>, sqlx::Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(_) => Ok(Vec::new()),
+            DatabasePool::Postgres(pool) => crate::migrations::run_postgres_migrations(pool).await,
+        }
+    }
+
     async fn init_sqlx(&self) -> Result<(), sqlx::Error> {
         match &self.pool {
             DatabasePool::Sqlite(pool) => {
@@ -57,7 +76,7 @@ impl SqlxStorage {
                 sqlx::query("SELECT 1").execute(pool).await?;
             }
             DatabasePool::Postgres(pool) => {
-                // Postgres schema is expected to be created by Flyway migrations.
+                // Postgres schema is created by `run_migrations` or out-of-band by Flyway.
                 sqlx::query("SELECT 1").execute(pool).await?;
             }
         }
@@ -66,19 +85,48 @@ impl SqlxStorage {
     }
 
     async fn bootstrap_sqlite_schema(&self, pool: &Pool<Sqlite>) -> Result<(), sqlx::Error> {
+        // Tenants
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS tenants (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                signing_secret TEXT NOT NULL,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
         // Clients
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS clients (
                 id TEXT PRIMARY KEY,
-                client_id TEXT NOT NULL UNIQUE,
+                client_id TEXT NOT NULL,
                 client_secret TEXT NOT NULL,
                 redirect_uris TEXT NOT NULL,
                 grant_types TEXT NOT NULL,
                 scope TEXT NOT NULL,
                 name TEXT NOT NULL,
                 created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
+                updated_at TEXT NOT NULL,
+                registration_access_token TEXT,
+                managed_by TEXT,
+                pending_redirect_uris TEXT,
+                pending_redirect_uris_at TEXT,
+                client_type TEXT NOT NULL DEFAULT 'confidential',
+                logo_uri TEXT,
+                client_uri TEXT,
+                policy_uri TEXT,
+                tos_uri TEXT,
+                contacts TEXT,
+                jwks_uri TEXT,
+                token_endpoint_auth_method TEXT,
+                allowed_origins TEXT
             );
             "#,
         )
@@ -89,12 +137,55 @@ impl SqlxStorage {
             .execute(pool)
             .await?;
 
+        // Columns added after the initial schema (mirrors Flyway V7+ for existing SQLite files).
+        self.ensure_sqlite_column(pool, "clients", "registration_access_token", "TEXT")
+            .await?;
+        self.ensure_sqlite_column(pool, "clients", "managed_by", "TEXT")
+            .await?;
+        sqlx::query(r#"CREATE INDEX IF NOT EXISTS idx_clients_managed_by ON clients(managed_by);"#)
+            .execute(pool)
+            .await?;
+        self.ensure_sqlite_column(pool, "clients", "pending_redirect_uris", "TEXT")
+            .await?;
+        self.ensure_sqlite_column(pool, "clients", "pending_redirect_uris_at", "TEXT")
+            .await?;
+        self.ensure_sqlite_column(
+            pool,
+            "clients",
+            "client_type",
+            "TEXT NOT NULL DEFAULT 'confidential'",
+        )
+        .await?;
+        sqlx::query(
+            r#"UPDATE clients SET client_type = 'confidential' WHERE client_type = 'application';"#,
+        )
+        .execute(pool)
+        .await?;
+        self.ensure_sqlite_column(pool, "clients", "tenant_id", "TEXT REFERENCES tenants(id)")
+            .await?;
+        sqlx::query(r#"CREATE INDEX IF NOT EXISTS idx_clients_tenant_id ON clients(tenant_id);"#)
+            .execute(pool)
+            .await?;
+        for column in [
+            "logo_uri",
+            "client_uri",
+            "policy_uri",
+            "tos_uri",
+            "contacts",
+            "jwks_uri",
+            "token_endpoint_auth_method",
+            "allowed_origins",
+        ] {
+            self.ensure_sqlite_column(pool, "clients", column, "TEXT")
+                .await?;
+        }
+
         // Users
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS users (
                 id TEXT PRIMARY KEY,
-                username TEXT NOT NULL UNIQUE,
+                username TEXT NOT NULL,
                 password_hash TEXT NOT NULL,
                 email TEXT NOT NULL,
                 enabled INTEGER NOT NULL DEFAULT 1,
@@ -112,6 +203,37 @@ impl SqlxStorage {
         sqlx::query(r#"CREATE INDEX IF NOT EXISTS idx_users_email ON users(email);"#)
             .execute(pool)
             .await?;
+        self.ensure_sqlite_column(pool, "users", "tenant_id", "TEXT REFERENCES tenants(id)")
+            .await?;
+        self.ensure_sqlite_column(
+            pool,
+            "users",
+            "email_verified",
+            "INTEGER NOT NULL DEFAULT 0",
+        )
+        .await?;
+        sqlx::query(r#"CREATE INDEX IF NOT EXISTS idx_users_tenant_id ON users(tenant_id);"#)
+            .execute(pool)
+            .await?;
+
+        // Service accounts
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS service_accounts (
+                id TEXT PRIMARY KEY,
+                client_id TEXT NOT NULL UNIQUE,
+                name TEXT NOT NULL UNIQUE,
+                description TEXT,
+                scope TEXT NOT NULL,
+                public_key TEXT,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            "#,
+        )
+        .execute(pool)
+        .await?;
 
         // Tokens
         sqlx::query(
@@ -128,7 +250,8 @@ impl SqlxStorage {
                 created_at TEXT NOT NULL,
                 expires_at TEXT NOT NULL,
                 revoked INTEGER NOT NULL DEFAULT 0,
-                FOREIGN KEY (client_id) REFERENCES clients(client_id),
+                grant_id TEXT,
+                metadata TEXT NOT NULL DEFAULT '{}',
                 FOREIGN KEY (user_id) REFERENCES users(id)
             );
             "#,
@@ -152,6 +275,31 @@ impl SqlxStorage {
         sqlx::query(r#"CREATE INDEX IF NOT EXISTS idx_tokens_user_id ON tokens(user_id);"#)
             .execute(pool)
             .await?;
+        // Newest-first listings per client and per user (mirrors Flyway V19).
+        sqlx::query(
+            r#"CREATE INDEX IF NOT EXISTS idx_tokens_client_id_created_at ON tokens(client_id, created_at);"#,
+        )
+        .execute(pool)
+        .await?;
+        sqlx::query(
+            r#"CREATE INDEX IF NOT EXISTS idx_tokens_user_id_created_at ON tokens(user_id, created_at);"#,
+        )
+        .execute(pool)
+        .await?;
+        self.ensure_sqlite_column(pool, "tokens", "grant_id", "TEXT")
+            .await?;
+        sqlx::query(r#"CREATE INDEX IF NOT EXISTS idx_tokens_grant_id ON tokens(grant_id);"#)
+            .execute(pool)
+            .await?;
+        self.ensure_sqlite_column(pool, "tokens", "metadata", "TEXT NOT NULL DEFAULT '{}'")
+            .await?;
+        self.ensure_sqlite_column(pool, "tokens", "tenant_id", "TEXT REFERENCES tenants(id)")
+            .await?;
+        self.ensure_sqlite_column(pool, "tokens", "context_binding", "TEXT")
+            .await?;
+        sqlx::query(r#"CREATE INDEX IF NOT EXISTS idx_tokens_tenant_id ON tokens(tenant_id);"#)
+            .execute(pool)
+            .await?;
 
         // Authorization codes
         sqlx::query(
@@ -168,7 +316,8 @@ impl SqlxStorage {
                 used INTEGER NOT NULL DEFAULT 0,
                 code_challenge TEXT,
                 code_challenge_method TEXT,
-                FOREIGN KEY (client_id) REFERENCES clients(client_id),
+                context_binding TEXT,
+                tenant_id TEXT REFERENCES tenants(id),
                 FOREIGN KEY (user_id) REFERENCES users(id)
             );
             "#,
@@ -192,37 +341,594 @@ impl SqlxStorage {
         .execute(pool)
         .await?;
 
+        self.ensure_sqlite_column(pool, "authorization_codes", "context_binding", "TEXT")
+            .await?;
+        self.ensure_sqlite_column(
+            pool,
+            "authorization_codes",
+            "tenant_id",
+            "TEXT REFERENCES tenants(id)",
+        )
+        .await?;
+        sqlx::query(
+            r#"CREATE INDEX IF NOT EXISTS idx_authorization_codes_tenant_id ON authorization_codes(tenant_id);"#,
+        )
+        .execute(pool)
+        .await?;
+
+        // Social login state
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS social_login_states (
+                state TEXT PRIMARY KEY,
+                provider TEXT NOT NULL,
+                pkce_verifier TEXT,
+                nonce TEXT,
+                created_at TEXT NOT NULL,
+                expires_at TEXT NOT NULL,
+                browser_binding TEXT
+            );
+            "#,
+        )
+        .execute(pool)
+        .await?;
+        self.ensure_sqlite_column(pool, "social_login_states", "browser_binding", "TEXT")
+            .await?;
+        sqlx::query(
+            r#"CREATE INDEX IF NOT EXISTS idx_social_login_states_expires_at ON social_login_states(expires_at);"#,
+        )
+        .execute(pool)
+        .await?;
+
+        // Tokens mailed to users (email verification, password reset)
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS email_tokens (
+                token_hash TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                purpose TEXT NOT NULL,
+                email TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                expires_at TEXT NOT NULL,
+                FOREIGN KEY (user_id) REFERENCES users(id)
+            );
+            "#,
+        )
+        .execute(pool)
+        .await?;
+        self.ensure_sqlite_column(pool, "email_tokens", "return_to", "TEXT")
+            .await?;
+        sqlx::query(
+            r#"CREATE INDEX IF NOT EXISTS idx_email_tokens_user_id ON email_tokens(user_id);"#,
+        )
+        .execute(pool)
+        .await?;
+        sqlx::query(
+            r#"CREATE INDEX IF NOT EXISTS idx_email_tokens_expires_at ON email_tokens(expires_at);"#,
+        )
+        .execute(pool)
+        .await?;
+
+        // Browser sessions
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS user_sessions (
+                id TEXT PRIMARY KEY,
+                user_id TEXT,
+                state TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                last_seen_at TEXT NOT NULL,
+                idle_expires_at TEXT NOT NULL,
+                expires_at TEXT NOT NULL,
+                FOREIGN KEY (user_id) REFERENCES users(id)
+            );
+            "#,
+        )
+        .execute(pool)
+        .await?;
+        sqlx::query(
+            r#"CREATE INDEX IF NOT EXISTS idx_user_sessions_user_id ON user_sessions(user_id);"#,
+        )
+        .execute(pool)
+        .await?;
+        sqlx::query(
+            r#"CREATE INDEX IF NOT EXISTS idx_user_sessions_expires_at ON user_sessions(expires_at);"#,
+        )
+        .execute(pool)
+        .await?;
+
+        // Audit log; no foreign keys, records outlive the users and clients they name
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS audit_records (
+                id TEXT PRIMARY KEY,
+                occurred_at TEXT NOT NULL,
+                action TEXT NOT NULL,
+                outcome TEXT NOT NULL,
+                actor TEXT,
+                client_id TEXT,
+                target TEXT,
+                ip_address TEXT,
+                user_agent TEXT,
+                detail TEXT
+            );
+            "#,
+        )
+        .execute(pool)
+        .await?;
+        sqlx::query(
+            r#"CREATE INDEX IF NOT EXISTS idx_audit_records_occurred_at ON audit_records(occurred_at);"#,
+        )
+        .execute(pool)
+        .await?;
+        sqlx::query(
+            r#"CREATE INDEX IF NOT EXISTS idx_audit_records_actor ON audit_records(actor, occurred_at);"#,
+        )
+        .execute(pool)
+        .await?;
+
+        // Event outbox
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS outbox_messages (
+                id TEXT PRIMARY KEY,
+                event_type TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                next_attempt_at TEXT NOT NULL,
+                last_error TEXT
+            );
+            "#,
+        )
+        .execute(pool)
+        .await?;
+        sqlx::query(
+            r#"CREATE INDEX IF NOT EXISTS idx_outbox_messages_next_attempt_at ON outbox_messages(next_attempt_at);"#,
+        )
+        .execute(pool)
+        .await?;
+
+        // Federated identities
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS federated_identities (
+                provider TEXT NOT NULL,
+                provider_user_id TEXT NOT NULL,
+                user_id TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                PRIMARY KEY (provider, provider_user_id),
+                FOREIGN KEY (user_id) REFERENCES users(id)
+            );
+            "#,
+        )
+        .execute(pool)
+        .await?;
+        sqlx::query(
+            r#"CREATE INDEX IF NOT EXISTS idx_federated_identities_user_id ON federated_identities(user_id);"#,
+        )
+        .execute(pool)
+        .await?;
+        for column in ["access_token", "refresh_token", "token_expires_at"] {
+            self.ensure_sqlite_column(pool, "federated_identities", column, "TEXT")
+                .await?;
+        }
+
+        // Roles and groups, and their assignment to users
+        for table in ["roles", "groups"] {
+            sqlx::query(&format!(
+                r#"
+                CREATE TABLE IF NOT EXISTS {table} (
+                    name TEXT PRIMARY KEY,
+                    description TEXT,
+                    created_at TEXT NOT NULL
+                );
+                "#
+            ))
+            .execute(pool)
+            .await?;
+        }
+        for (table, column, references) in [
+            ("user_roles", "role_name", "roles"),
+            ("user_groups", "group_name", "groups"),
+        ] {
+            sqlx::query(&format!(
+                r#"
+                CREATE TABLE IF NOT EXISTS {table} (
+                    user_id TEXT NOT NULL,
+                    {column} TEXT NOT NULL,
+                    PRIMARY KEY (user_id, {column}),
+                    FOREIGN KEY (user_id) REFERENCES users(id),
+                    FOREIGN KEY ({column}) REFERENCES {references}(name)
+                );
+                "#
+            ))
+            .execute(pool)
+            .await?;
+            sqlx::query(&format!(
+                "CREATE INDEX IF NOT EXISTS idx_{table}_{column} ON {table}({column});"
+            ))
+            .execute(pool)
+            .await?;
+        }
+
+        // Client ids and usernames are unique per tenant (mirrors Flyway V31). Files created
+        // before that carry global UNIQUE constraints and foreign keys on clients(client_id),
+        // which SQLite can only drop by rebuilding the table.
+        for table in [
+            "tokens",
+            "authorization_codes",
+            "service_accounts",
+            "clients",
+            "users",
+        ] {
+            self.rebuild_sqlite_table(pool, table).await?;
+        }
+        sqlx::query(
+            r#"CREATE UNIQUE INDEX IF NOT EXISTS idx_clients_tenant_client_id ON clients(COALESCE(tenant_id, ''), client_id);"#,
+        )
+        .execute(pool)
+        .await?;
+        sqlx::query(
+            r#"CREATE UNIQUE INDEX IF NOT EXISTS idx_users_tenant_username ON users(COALESCE(tenant_id, ''), username);"#,
+        )
+        .execute(pool)
+        .await?;
+
         Ok(())
     }
-}
 
-#[async_trait]
-impl Storage for SqlxStorage {
-    async fn init(&self) -> Result<(), OAuth2Error> {
-        self.init_sqlx().await.map_err(Into::into)
+    /// Recreate `table` without the global client id/username keys, keeping its rows and
+    /// indexes. Does nothing when its definition has none.
+    async fn rebuild_sqlite_table(
+        &self,
+        pool: &Pool<Sqlite>,
+        table: &str,
+    ) -> Result<(), sqlx::Error> {
+        let Some((definition,)): Option<(String,)> =
+            sqlx::query_as("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?")
+                .bind(table)
+                .fetch_optional(pool)
+                .await?
+        else {
+            return Ok(());
+        };
+        let rewritten = without_global_client_keys(table, &definition);
+        if rewritten == definition {
+            return Ok(());
+        }
+        let Some(columns) = rewritten.find('(') else {
+            return Ok(());
+        };
+        let indexes: Vec<(String,)> = sqlx::query_as(
+            "SELECT sql FROM sqlite_master WHERE type = 'index' AND tbl_name = ? AND sql IS NOT NULL",
+        )
+        .bind(table)
+        .fetch_all(pool)
+        .await?;
+
+        // Foreign key enforcement can only be switched outside a transaction, per connection.
+        let mut conn = pool.acquire().await?;
+        sqlx::query("PRAGMA foreign_keys = OFF")
+            .execute(&mut *conn)
+            .await?;
+        let rebuilt = async {
+            let mut tx = conn.begin().await?;
+            sqlx::query(&format!(
+                "CREATE TABLE {table}__rebuild {}",
+                &rewritten[columns..]
+            ))
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query(&format!(
+                "INSERT INTO {table}__rebuild SELECT * FROM {table}"
+            ))
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query(&format!("DROP TABLE {table}"))
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(&format!("ALTER TABLE {table}__rebuild RENAME TO {table}"))
+                .execute(&mut *tx)
+                .await?;
+            for (index,) in &indexes {
+                sqlx::query(index).execute(&mut *tx).await?;
+            }
+            tx.commit().await
+        }
+        .await;
+        sqlx::query("PRAGMA foreign_keys = ON")
+            .execute(&mut *conn)
+            .await?;
+
+        rebuilt
     }
 
-    async fn healthcheck(&self) -> Result<(), OAuth2Error> {
-        // Keep readiness/liveness cheap: don't run bootstrap or migrations.
+    /// `ALTER TABLE ... ADD COLUMN` unless the column already exists (SQLite has no IF NOT EXISTS).
+    async fn ensure_sqlite_column(
+        &self,
+        pool: &Pool<Sqlite>,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> Result<(), sqlx::Error> {
+        let exists: Option<(String,)> =
+            sqlx::query_as("SELECT name FROM pragma_table_info(?) WHERE name = ?")
+                .bind(table)
+                .bind(column)
+                .fetch_optional(pool)
+                .await?;
+
+        if exists.is_none() {
+            sqlx::query(&format!(
+                "ALTER TABLE {table} ADD COLUMN {column} {definition}"
+            ))
+            .execute(pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Insert a role or group into `table`.
+    async fn insert_named(
+        &self,
+        table: &str,
+        name: &str,
+        description: Option<&str>,
+        created_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
         match &self.pool {
             DatabasePool::Sqlite(pool) => {
-                sqlx::query("SELECT 1").execute(pool).await?;
+                sqlx::query(&format!(
+                    "INSERT INTO {table} (name, description, created_at) VALUES (?, ?, ?)"
+                ))
+                .bind(name)
+                .bind(description)
+                .bind(created_at)
+                .execute(pool)
+                .await?;
             }
             DatabasePool::Postgres(pool) => {
-                sqlx::query("SELECT 1").execute(pool).await?;
+                sqlx::query(&format!(
+                    "INSERT INTO {table} (name, description, created_at) VALUES ($1, $2, $3)"
+                ))
+                .bind(name)
+                .bind(description)
+                .bind(created_at)
+                .execute(pool)
+                .await?;
             }
         }
-
         Ok(())
     }
 
-    async fn save_client(&self, client: &Client) -> Result<(), OAuth2Error> {
+    /// Every role or group in `table`, ordered by name.
+    async fn list_named<T>(&self, table: &str) -> Result<Vec<T>, sqlx::Error>
+    where
+        T: Send + Unpin + for<'r> FromRow<'r, SqliteRow> + for<'r> FromRow<'r, PgRow>,
+    {
+        let query = format!("SELECT * FROM {table} ORDER BY name");
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => sqlx::query_as::<_, T>(&query).fetch_all(pool).await,
+            DatabasePool::Postgres(pool) => sqlx::query_as::<_, T>(&query).fetch_all(pool).await,
+        }
+    }
+
+    /// Delete a role or group from `table` together with its rows in `assignments`.
+    async fn delete_named(
+        &self,
+        table: &str,
+        assignments: &str,
+        column: &str,
+        name: &str,
+    ) -> Result<(), sqlx::Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                let mut tx = pool.begin().await?;
+                sqlx::query(&format!("DELETE FROM {assignments} WHERE {column} = ?"))
+                    .bind(name)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query(&format!("DELETE FROM {table} WHERE name = ?"))
+                    .bind(name)
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                let mut tx = pool.begin().await?;
+                sqlx::query(&format!("DELETE FROM {assignments} WHERE {column} = $1"))
+                    .bind(name)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query(&format!("DELETE FROM {table} WHERE name = $1"))
+                    .bind(name)
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Names in `assignments` for `user_id`, sorted.
+    async fn list_assigned(
+        &self,
+        assignments: &str,
+        column: &str,
+        user_id: &str,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_scalar(&format!(
+                    "SELECT {column} FROM {assignments} WHERE user_id = ? ORDER BY {column}"
+                ))
+                .bind(user_id)
+                .fetch_all(pool)
+                .await
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_scalar(&format!(
+                    "SELECT {column} FROM {assignments} WHERE user_id = $1 ORDER BY {column}"
+                ))
+                .bind(user_id)
+                .fetch_all(pool)
+                .await
+            }
+        }
+    }
+
+    /// Replace the names in `assignments` for `user_id`.
+    async fn set_assigned(
+        &self,
+        assignments: &str,
+        column: &str,
+        user_id: &str,
+        names: &[String],
+    ) -> Result<(), sqlx::Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                let mut tx = pool.begin().await?;
+                sqlx::query(&format!("DELETE FROM {assignments} WHERE user_id = ?"))
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                for name in names {
+                    sqlx::query(&format!(
+                        "INSERT INTO {assignments} (user_id, {column}) VALUES (?, ?) ON CONFLICT DO NOTHING"
+                    ))
+                    .bind(user_id)
+                    .bind(name)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                tx.commit().await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                let mut tx = pool.begin().await?;
+                sqlx::query(&format!("DELETE FROM {assignments} WHERE user_id = $1"))
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                for name in names {
+                    sqlx::query(&format!(
+                        "INSERT INTO {assignments} (user_id, {column}) VALUES ($1, $2) ON CONFLICT DO NOTHING"
+                    ))
+                    .bind(user_id)
+                    .bind(name)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                tx.commit().await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Storage for SqlxStorage {
+    async fn init(&self) -> Result<(), OAuth2Error> {
+        self.init_sqlx().await.map_err(Into::into)
+    }
+
+    async fn healthcheck(&self) -> Result<(), OAuth2Error> {
+        // Keep readiness/liveness cheap: don't run bootstrap or migrations.
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query("SELECT 1").execute(pool).await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query("SELECT 1").execute(pool).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn save_tenant(&self, tenant: &Tenant) -> Result<(), OAuth2Error> {
         match &self.pool {
             DatabasePool::Sqlite(pool) => {
                 sqlx::query(
                     r#"
-                    INSERT INTO clients (id, client_id, client_secret, redirect_uris, grant_types, scope, name, created_at, updated_at)
-                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    INSERT INTO tenants (id, name, signing_secret, enabled, created_at, updated_at)
+                    VALUES (?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(&tenant.id)
+                .bind(&tenant.name)
+                .bind(&tenant.signing_secret)
+                .bind(tenant.enabled)
+                .bind(tenant.created_at)
+                .bind(tenant.updated_at)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO tenants (id, name, signing_secret, enabled, created_at, updated_at)
+                    VALUES ($1, $2, $3, $4, $5, $6)
+                    "#,
+                )
+                .bind(&tenant.id)
+                .bind(&tenant.name)
+                .bind(&tenant.signing_secret)
+                .bind(tenant.enabled)
+                .bind(tenant.created_at)
+                .bind(tenant.updated_at)
+                .execute(pool)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn get_tenant(&self, id: &str) -> Result<Option<Tenant>, OAuth2Error> {
+        let tenant = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, Tenant>("SELECT * FROM tenants WHERE id = ?")
+                    .bind(id)
+                    .fetch_optional(pool)
+                    .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, Tenant>("SELECT * FROM tenants WHERE id = $1")
+                    .bind(id)
+                    .fetch_optional(pool)
+                    .await?
+            }
+        };
+
+        Ok(tenant)
+    }
+
+    async fn list_tenants(&self) -> Result<Vec<Tenant>, OAuth2Error> {
+        let tenants = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, Tenant>("SELECT * FROM tenants ORDER BY id")
+                    .fetch_all(pool)
+                    .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, Tenant>("SELECT * FROM tenants ORDER BY id")
+                    .fetch_all(pool)
+                    .await?
+            }
+        };
+
+        Ok(tenants)
+    }
+
+    async fn save_client(&self, client: &Client) -> Result<(), OAuth2Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO clients (id, client_id, client_secret, redirect_uris, grant_types, scope, name, created_at, updated_at, registration_access_token, managed_by, pending_redirect_uris, pending_redirect_uris_at, client_type, tenant_id, logo_uri, client_uri, policy_uri, tos_uri, contacts, jwks_uri, token_endpoint_auth_method, allowed_origins)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
                     "#,
                 )
                 .bind(&client.id)
@@ -234,14 +940,28 @@ impl Storage for SqlxStorage {
                 .bind(&client.name)
                 .bind(client.created_at)
                 .bind(client.updated_at)
+                .bind(&client.registration_access_token)
+                .bind(&client.managed_by)
+                .bind(&client.pending_redirect_uris)
+                .bind(client.pending_redirect_uris_at)
+                .bind(client.client_type.as_str())
+                .bind(&client.tenant_id)
+                .bind(&client.logo_uri)
+                .bind(&client.client_uri)
+                .bind(&client.policy_uri)
+                .bind(&client.tos_uri)
+                .bind(&client.contacts)
+                .bind(&client.jwks_uri)
+                .bind(client.token_endpoint_auth_method.map(|m| m.as_str()))
+                .bind(&client.allowed_origins)
                 .execute(pool)
                 .await?;
             }
             DatabasePool::Postgres(pool) => {
                 sqlx::query(
                     r#"
-                    INSERT INTO clients (id, client_id, client_secret, redirect_uris, grant_types, scope, name, created_at, updated_at)
-                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                    INSERT INTO clients (id, client_id, client_secret, redirect_uris, grant_types, scope, name, created_at, updated_at, registration_access_token, managed_by, pending_redirect_uris, pending_redirect_uris_at, client_type, tenant_id, logo_uri, client_uri, policy_uri, tos_uri, contacts, jwks_uri, token_endpoint_auth_method, allowed_origins)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23)
                     "#,
                 )
                 .bind(&client.id)
@@ -253,6 +973,20 @@ impl Storage for SqlxStorage {
                 .bind(&client.name)
                 .bind(client.created_at)
                 .bind(client.updated_at)
+                .bind(&client.registration_access_token)
+                .bind(&client.managed_by)
+                .bind(&client.pending_redirect_uris)
+                .bind(client.pending_redirect_uris_at)
+                .bind(client.client_type.as_str())
+                .bind(&client.tenant_id)
+                .bind(&client.logo_uri)
+                .bind(&client.client_uri)
+                .bind(&client.policy_uri)
+                .bind(&client.tos_uri)
+                .bind(&client.contacts)
+                .bind(&client.jwks_uri)
+                .bind(client.token_endpoint_auth_method.map(|m| m.as_str()))
+                .bind(&client.allowed_origins)
                 .execute(pool)
                 .await?;
             }
@@ -261,58 +995,1650 @@ impl Storage for SqlxStorage {
         Ok(())
     }
 
-    async fn get_client(&self, client_id: &str) -> Result<Option<Client>, OAuth2Error> {
+    async fn get_client(
+        &self,
+        client_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<Client>, OAuth2Error> {
         let client = match &self.pool {
             DatabasePool::Sqlite(pool) => {
-                sqlx::query_as::<_, Client>("SELECT * FROM clients WHERE client_id = ?")
+                sqlx::query_as::<_, Client>(
+                    "SELECT * FROM clients WHERE client_id = ? AND tenant_id IS ?",
+                )
+                .bind(client_id)
+                .bind(tenant_id)
+                .fetch_optional(pool)
+                .await?
+            }
+            DatabasePool::Postgres(pool) => sqlx::query_as::<_, Client>(
+                "SELECT * FROM clients WHERE client_id = $1 AND tenant_id IS NOT DISTINCT FROM $2",
+            )
+            .bind(client_id)
+            .bind(tenant_id)
+            .fetch_optional(pool)
+            .await?,
+        };
+
+        Ok(client)
+    }
+
+    async fn list_clients_managed_by(&self, manager: &str) -> Result<Vec<Client>, OAuth2Error> {
+        let clients = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, Client>(
+                    "SELECT * FROM clients WHERE managed_by = ? ORDER BY client_id",
+                )
+                .bind(manager)
+                .fetch_all(pool)
+                .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, Client>(
+                    "SELECT * FROM clients WHERE managed_by = $1 ORDER BY client_id",
+                )
+                .bind(manager)
+                .fetch_all(pool)
+                .await?
+            }
+        };
+
+        Ok(clients)
+    }
+
+    async fn list_clients(&self, page: PageRequest) -> Result<Vec<Client>, OAuth2Error> {
+        let (limit, offset) = limit_offset(page);
+        let clients = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, Client>(
+                    "SELECT * FROM clients ORDER BY client_id, id LIMIT ? OFFSET ?",
+                )
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(pool)
+                .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, Client>(
+                    "SELECT * FROM clients ORDER BY client_id, id LIMIT $1 OFFSET $2",
+                )
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(pool)
+                .await?
+            }
+        };
+
+        Ok(clients)
+    }
+
+    async fn count_clients(&self) -> Result<u64, OAuth2Error> {
+        let count: i64 = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_scalar("SELECT COUNT(*) FROM clients")
+                    .fetch_one(pool)
+                    .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_scalar("SELECT COUNT(*) FROM clients")
+                    .fetch_one(pool)
+                    .await?
+            }
+        };
+
+        Ok(count.max(0) as u64)
+    }
+
+    async fn update_client(&self, client: &Client) -> Result<(), OAuth2Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    UPDATE clients
+                    SET client_secret = ?, redirect_uris = ?, grant_types = ?, scope = ?, name = ?, updated_at = ?, registration_access_token = ?, pending_redirect_uris = ?, pending_redirect_uris_at = ?, logo_uri = ?, client_uri = ?, policy_uri = ?, tos_uri = ?, contacts = ?, jwks_uri = ?, token_endpoint_auth_method = ?, allowed_origins = ?
+                    WHERE client_id = ? AND tenant_id IS ?
+                    "#,
+                )
+                .bind(&client.client_secret)
+                .bind(&client.redirect_uris)
+                .bind(&client.grant_types)
+                .bind(&client.scope)
+                .bind(&client.name)
+                .bind(client.updated_at)
+                .bind(&client.registration_access_token)
+                .bind(&client.pending_redirect_uris)
+                .bind(client.pending_redirect_uris_at)
+                .bind(&client.logo_uri)
+                .bind(&client.client_uri)
+                .bind(&client.policy_uri)
+                .bind(&client.tos_uri)
+                .bind(&client.contacts)
+                .bind(&client.jwks_uri)
+                .bind(client.token_endpoint_auth_method.map(|m| m.as_str()))
+                .bind(&client.allowed_origins)
+                .bind(&client.client_id)
+                .bind(&client.tenant_id)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    UPDATE clients
+                    SET client_secret = $1, redirect_uris = $2, grant_types = $3, scope = $4, name = $5, updated_at = $6, registration_access_token = $7, pending_redirect_uris = $8, pending_redirect_uris_at = $9, logo_uri = $10, client_uri = $11, policy_uri = $12, tos_uri = $13, contacts = $14, jwks_uri = $15, token_endpoint_auth_method = $16, allowed_origins = $17
+                    WHERE client_id = $18 AND tenant_id IS NOT DISTINCT FROM $19
+                    "#,
+                )
+                .bind(&client.client_secret)
+                .bind(&client.redirect_uris)
+                .bind(&client.grant_types)
+                .bind(&client.scope)
+                .bind(&client.name)
+                .bind(client.updated_at)
+                .bind(&client.registration_access_token)
+                .bind(&client.pending_redirect_uris)
+                .bind(client.pending_redirect_uris_at)
+                .bind(&client.logo_uri)
+                .bind(&client.client_uri)
+                .bind(&client.policy_uri)
+                .bind(&client.tos_uri)
+                .bind(&client.contacts)
+                .bind(&client.jwks_uri)
+                .bind(client.token_endpoint_auth_method.map(|m| m.as_str()))
+                .bind(&client.allowed_origins)
+                .bind(&client.client_id)
+                .bind(&client.tenant_id)
+                .execute(pool)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn delete_client(
+        &self,
+        client_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<(), OAuth2Error> {
+        // Tokens, authorization codes and service accounts belong to the client, so remove
+        // them first. Only the default tenant has service accounts.
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                let mut tx = pool.begin().await?;
+                sqlx::query(
+                    "DELETE FROM authorization_codes WHERE client_id = ? AND tenant_id IS ?",
+                )
+                .bind(client_id)
+                .bind(tenant_id)
+                .execute(&mut *tx)
+                .await?;
+                sqlx::query("DELETE FROM tokens WHERE client_id = ? AND tenant_id IS ?")
+                    .bind(client_id)
+                    .bind(tenant_id)
+                    .execute(&mut *tx)
+                    .await?;
+                if tenant_id.is_none() {
+                    sqlx::query("DELETE FROM service_accounts WHERE client_id = ?")
+                        .bind(client_id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                sqlx::query("DELETE FROM clients WHERE client_id = ? AND tenant_id IS ?")
                     .bind(client_id)
+                    .bind(tenant_id)
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                let mut tx = pool.begin().await?;
+                sqlx::query(
+                    "DELETE FROM authorization_codes WHERE client_id = $1 AND tenant_id IS NOT DISTINCT FROM $2",
+                )
+                .bind(client_id)
+                .bind(tenant_id)
+                .execute(&mut *tx)
+                .await?;
+                sqlx::query(
+                    "DELETE FROM tokens WHERE client_id = $1 AND tenant_id IS NOT DISTINCT FROM $2",
+                )
+                .bind(client_id)
+                .bind(tenant_id)
+                .execute(&mut *tx)
+                .await?;
+                if tenant_id.is_none() {
+                    sqlx::query("DELETE FROM service_accounts WHERE client_id = $1")
+                        .bind(client_id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                sqlx::query(
+                    "DELETE FROM clients WHERE client_id = $1 AND tenant_id IS NOT DISTINCT FROM $2",
+                )
+                .bind(client_id)
+                .bind(tenant_id)
+                .execute(&mut *tx)
+                .await?;
+                tx.commit().await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn save_service_account(&self, account: &ServiceAccount) -> Result<(), OAuth2Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO service_accounts (id, client_id, name, description, scope, public_key, enabled, created_at, updated_at)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(&account.id)
+                .bind(&account.client_id)
+                .bind(&account.name)
+                .bind(&account.description)
+                .bind(&account.scope)
+                .bind(&account.public_key)
+                .bind(account.enabled)
+                .bind(account.created_at)
+                .bind(account.updated_at)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO service_accounts (id, client_id, name, description, scope, public_key, enabled, created_at, updated_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                    "#,
+                )
+                .bind(&account.id)
+                .bind(&account.client_id)
+                .bind(&account.name)
+                .bind(&account.description)
+                .bind(&account.scope)
+                .bind(&account.public_key)
+                .bind(account.enabled)
+                .bind(account.created_at)
+                .bind(account.updated_at)
+                .execute(pool)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn get_service_account(&self, id: &str) -> Result<Option<ServiceAccount>, OAuth2Error> {
+        let account = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, ServiceAccount>("SELECT * FROM service_accounts WHERE id = ?")
+                    .bind(id)
                     .fetch_optional(pool)
                     .await?
             }
             DatabasePool::Postgres(pool) => {
-                sqlx::query_as::<_, Client>("SELECT * FROM clients WHERE client_id = $1")
-                    .bind(client_id)
+                sqlx::query_as::<_, ServiceAccount>("SELECT * FROM service_accounts WHERE id = $1")
+                    .bind(id)
                     .fetch_optional(pool)
                     .await?
             }
         };
 
-        Ok(client)
+        Ok(account)
+    }
+
+    async fn get_service_account_by_client_id(
+        &self,
+        client_id: &str,
+    ) -> Result<Option<ServiceAccount>, OAuth2Error> {
+        let account = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, ServiceAccount>(
+                    "SELECT * FROM service_accounts WHERE client_id = ?",
+                )
+                .bind(client_id)
+                .fetch_optional(pool)
+                .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, ServiceAccount>(
+                    "SELECT * FROM service_accounts WHERE client_id = $1",
+                )
+                .bind(client_id)
+                .fetch_optional(pool)
+                .await?
+            }
+        };
+
+        Ok(account)
+    }
+
+    async fn list_service_accounts(&self) -> Result<Vec<ServiceAccount>, OAuth2Error> {
+        let accounts = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, ServiceAccount>("SELECT * FROM service_accounts ORDER BY name")
+                    .fetch_all(pool)
+                    .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, ServiceAccount>("SELECT * FROM service_accounts ORDER BY name")
+                    .fetch_all(pool)
+                    .await?
+            }
+        };
+
+        Ok(accounts)
+    }
+
+    async fn update_service_account(&self, account: &ServiceAccount) -> Result<(), OAuth2Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    UPDATE service_accounts
+                    SET description = ?, scope = ?, public_key = ?, enabled = ?, updated_at = ?
+                    WHERE id = ?
+                    "#,
+                )
+                .bind(&account.description)
+                .bind(&account.scope)
+                .bind(&account.public_key)
+                .bind(account.enabled)
+                .bind(account.updated_at)
+                .bind(&account.id)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    UPDATE service_accounts
+                    SET description = $1, scope = $2, public_key = $3, enabled = $4, updated_at = $5
+                    WHERE id = $6
+                    "#,
+                )
+                .bind(&account.description)
+                .bind(&account.scope)
+                .bind(&account.public_key)
+                .bind(account.enabled)
+                .bind(account.updated_at)
+                .bind(&account.id)
+                .execute(pool)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn save_user(&self, user: &User) -> Result<(), OAuth2Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO users (id, username, password_hash, email, email_verified, enabled, created_at, updated_at, tenant_id)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(&user.id)
+                .bind(&user.username)
+                .bind(&user.password_hash)
+                .bind(&user.email)
+                .bind(user.email_verified)
+                .bind(user.enabled)
+                .bind(user.created_at)
+                .bind(user.updated_at)
+                .bind(&user.tenant_id)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO users (id, username, password_hash, email, email_verified, enabled, created_at, updated_at, tenant_id)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                    "#,
+                )
+                .bind(&user.id)
+                .bind(&user.username)
+                .bind(&user.password_hash)
+                .bind(&user.email)
+                .bind(user.email_verified)
+                .bind(user.enabled)
+                .bind(user.created_at)
+                .bind(user.updated_at)
+                .bind(&user.tenant_id)
+                .execute(pool)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn get_user_by_username(
+        &self,
+        username: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<User>, OAuth2Error> {
+        let user =
+            match &self.pool {
+                DatabasePool::Sqlite(pool) => {
+                    sqlx::query_as::<_, User>(
+                        "SELECT * FROM users WHERE username = ? AND tenant_id IS ?",
+                    )
+                    .bind(username)
+                    .bind(tenant_id)
+                    .fetch_optional(pool)
+                    .await?
+                }
+                DatabasePool::Postgres(pool) => sqlx::query_as::<_, User>(
+                    "SELECT * FROM users WHERE username = $1 AND tenant_id IS NOT DISTINCT FROM $2",
+                )
+                .bind(username)
+                .bind(tenant_id)
+                .fetch_optional(pool)
+                .await?,
+            };
+
+        Ok(user)
+    }
+
+    async fn get_user(&self, user_id: &str) -> Result<Option<User>, OAuth2Error> {
+        let user = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+                    .bind(user_id)
+                    .fetch_optional(pool)
+                    .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+                    .bind(user_id)
+                    .fetch_optional(pool)
+                    .await?
+            }
+        };
+
+        Ok(user)
+    }
+
+    async fn list_users_by_email(&self, email: &str) -> Result<Vec<User>, OAuth2Error> {
+        // SQLite's LOWER folds ASCII only; emails are compared ignoring ASCII case anyway.
+        let email = email.to_ascii_lowercase();
+        let users = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, User>(
+                    "SELECT * FROM users WHERE LOWER(email) = ? ORDER BY username, id",
+                )
+                .bind(email)
+                .fetch_all(pool)
+                .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, User>(
+                    "SELECT * FROM users WHERE LOWER(email) = $1 ORDER BY username, id",
+                )
+                .bind(email)
+                .fetch_all(pool)
+                .await?
+            }
+        };
+
+        Ok(users)
+    }
+
+    async fn list_users(&self, query: &UserListQuery) -> Result<Vec<User>, OAuth2Error> {
+        let pattern = query.search.as_deref().map(like_pattern);
+        let limit = i64::from(query.limit);
+        let offset = i64::from(query.offset);
+
+        let users = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                // SQLite's LIKE is case-insensitive for ASCII.
+                sqlx::query_as::<_, User>(
+                    r#"
+                    SELECT * FROM users
+                    WHERE ? IS NULL OR username LIKE ? ESCAPE '\' OR email LIKE ? ESCAPE '\'
+                    ORDER BY username, id
+                    LIMIT ? OFFSET ?
+                    "#,
+                )
+                .bind(&pattern)
+                .bind(&pattern)
+                .bind(&pattern)
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(pool)
+                .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, User>(
+                    r#"
+                    SELECT * FROM users
+                    WHERE $1::TEXT IS NULL OR username ILIKE $1 ESCAPE '\' OR email ILIKE $1 ESCAPE '\'
+                    ORDER BY username, id
+                    LIMIT $2 OFFSET $3
+                    "#,
+                )
+                .bind(&pattern)
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(pool)
+                .await?
+            }
+        };
+
+        Ok(users)
+    }
+
+    async fn count_users(&self) -> Result<u64, OAuth2Error> {
+        let count: i64 = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_scalar("SELECT COUNT(*) FROM users")
+                    .fetch_one(pool)
+                    .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_scalar("SELECT COUNT(*) FROM users")
+                    .fetch_one(pool)
+                    .await?
+            }
+        };
+
+        Ok(count.max(0) as u64)
+    }
+
+    async fn update_user(&self, user: &User) -> Result<(), OAuth2Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    UPDATE users
+                    SET username = ?, password_hash = ?, email = ?, email_verified = ?, enabled = ?, updated_at = ?
+                    WHERE id = ?
+                    "#,
+                )
+                .bind(&user.username)
+                .bind(&user.password_hash)
+                .bind(&user.email)
+                .bind(user.email_verified)
+                .bind(user.enabled)
+                .bind(user.updated_at)
+                .bind(&user.id)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    UPDATE users
+                    SET username = $1, password_hash = $2, email = $3, email_verified = $4, enabled = $5, updated_at = $6
+                    WHERE id = $7
+                    "#,
+                )
+                .bind(&user.username)
+                .bind(&user.password_hash)
+                .bind(&user.email)
+                .bind(user.email_verified)
+                .bind(user.enabled)
+                .bind(user.updated_at)
+                .bind(&user.id)
+                .execute(pool)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn delete_user(&self, user_id: &str) -> Result<(), OAuth2Error> {
+        // Tokens, authorization codes, linked identities, roles, group memberships, email
+        // tokens and browser sessions reference users(id), so remove them first.
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                let mut tx = pool.begin().await?;
+                sqlx::query("DELETE FROM authorization_codes WHERE user_id = ?")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM tokens WHERE user_id = ?")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM federated_identities WHERE user_id = ?")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM user_roles WHERE user_id = ?")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM user_groups WHERE user_id = ?")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM email_tokens WHERE user_id = ?")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM user_sessions WHERE user_id = ?")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM users WHERE id = ?")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                let mut tx = pool.begin().await?;
+                sqlx::query("DELETE FROM authorization_codes WHERE user_id = $1")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM tokens WHERE user_id = $1")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM federated_identities WHERE user_id = $1")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM user_roles WHERE user_id = $1")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM user_groups WHERE user_id = $1")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM email_tokens WHERE user_id = $1")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM user_sessions WHERE user_id = $1")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM users WHERE id = $1")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn save_token(&self, token: &Token) -> Result<(), OAuth2Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => insert_token_sqlite(pool, token).await?,
+            DatabasePool::Postgres(pool) => insert_token_postgres(pool, token).await?,
+        }
+
+        Ok(())
+    }
+
+    async fn get_token_by_access_token(
+        &self,
+        access_token: &str,
+    ) -> Result<Option<Token>, OAuth2Error> {
+        let token = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, Token>("SELECT * FROM tokens WHERE access_token = ?")
+                    .bind(access_token)
+                    .fetch_optional(pool)
+                    .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, Token>("SELECT * FROM tokens WHERE access_token = $1")
+                    .bind(access_token)
+                    .fetch_optional(pool)
+                    .await?
+            }
+        };
+
+        Ok(token)
+    }
+
+    async fn get_token_by_refresh_token(
+        &self,
+        refresh_token: &str,
+    ) -> Result<Option<Token>, OAuth2Error> {
+        let token = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, Token>("SELECT * FROM tokens WHERE refresh_token = ?")
+                    .bind(refresh_token)
+                    .fetch_optional(pool)
+                    .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, Token>("SELECT * FROM tokens WHERE refresh_token = $1")
+                    .bind(refresh_token)
+                    .fetch_optional(pool)
+                    .await?
+            }
+        };
+
+        Ok(token)
+    }
+
+    async fn revoke_token(&self, token: &str) -> Result<(), OAuth2Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => revoke_token_sqlite(pool, token).await?,
+            DatabasePool::Postgres(pool) => revoke_token_postgres(pool, token).await?,
+        }
+
+        Ok(())
+    }
+
+    async fn revoke_token_grant(&self, grant_id: &str) -> Result<(), OAuth2Error> {
+        // Legacy rows have no grant_id; their grant is the token's own id.
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query(
+                    "UPDATE tokens SET revoked = 1 WHERE grant_id = ? OR (grant_id IS NULL AND id = ?)",
+                )
+                .bind(grant_id)
+                .bind(grant_id)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    "UPDATE tokens SET revoked = true WHERE grant_id = $1 OR (grant_id IS NULL AND id = $2)",
+                )
+                .bind(grant_id)
+                .bind(grant_id)
+                .execute(pool)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn list_revoked_tokens(&self) -> Result<Vec<Token>, OAuth2Error> {
+        let now = chrono::Utc::now();
+        let tokens = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, Token>(
+                    "SELECT * FROM tokens WHERE revoked = 1 AND expires_at > ? ORDER BY expires_at",
+                )
+                .bind(now)
+                .fetch_all(pool)
+                .await?
+            }
+            DatabasePool::Postgres(pool) => sqlx::query_as::<_, Token>(
+                "SELECT * FROM tokens WHERE revoked = true AND expires_at > $1 ORDER BY expires_at",
+            )
+            .bind(now)
+            .fetch_all(pool)
+            .await?,
+        };
+
+        Ok(tokens)
+    }
+
+    async fn find_tokens_by_metadata(
+        &self,
+        query: &TokenMetadataQuery,
+    ) -> Result<Vec<Token>, OAuth2Error> {
+        let limit = i64::from(query.limit);
+        let offset = i64::from(query.offset);
+
+        let tokens = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, Token>(
+                    r#"
+                    SELECT * FROM tokens
+                    WHERE json_extract(metadata, ?) = ?
+                    ORDER BY created_at DESC
+                    LIMIT ? OFFSET ?
+                    "#,
+                )
+                .bind(metadata_json_path(&query.key))
+                .bind(&query.value)
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(pool)
+                .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                // Containment so the GIN index on metadata::jsonb can be used.
+                sqlx::query_as::<_, Token>(
+                    r#"
+                    SELECT * FROM tokens
+                    WHERE metadata::jsonb @> $1::jsonb
+                    ORDER BY created_at DESC
+                    LIMIT $2 OFFSET $3
+                    "#,
+                )
+                .bind(metadata_tag_json(&query.key, &query.value))
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(pool)
+                .await?
+            }
+        };
+
+        Ok(tokens)
+    }
+
+    async fn revoke_tokens_by_metadata(&self, key: &str, value: &str) -> Result<u64, OAuth2Error> {
+        let result = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query(
+                    "UPDATE tokens SET revoked = 1 WHERE revoked = 0 AND json_extract(metadata, ?) = ?",
+                )
+                .bind(metadata_json_path(key))
+                .bind(value)
+                .execute(pool)
+                .await?
+                .rows_affected()
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    "UPDATE tokens SET revoked = true WHERE revoked = false AND metadata::jsonb @> $1::jsonb",
+                )
+                .bind(metadata_tag_json(key, value))
+                .execute(pool)
+                .await?
+                .rows_affected()
+            }
+        };
+
+        Ok(result)
+    }
+
+    async fn list_tokens_by_client(
+        &self,
+        client_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Token>, OAuth2Error> {
+        let (limit, offset) = limit_offset(page);
+        let tokens = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, Token>(
+                    "SELECT * FROM tokens WHERE client_id = ? ORDER BY created_at DESC LIMIT ? OFFSET ?",
+                )
+                .bind(client_id)
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(pool)
+                .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, Token>(
+                    "SELECT * FROM tokens WHERE client_id = $1 ORDER BY created_at DESC LIMIT $2 OFFSET $3",
+                )
+                .bind(client_id)
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(pool)
+                .await?
+            }
+        };
+
+        Ok(tokens)
+    }
+
+    async fn list_tokens_by_user(
+        &self,
+        user_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Token>, OAuth2Error> {
+        let (limit, offset) = limit_offset(page);
+        let tokens = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, Token>(
+                    "SELECT * FROM tokens WHERE user_id = ? ORDER BY created_at DESC LIMIT ? OFFSET ?",
+                )
+                .bind(user_id)
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(pool)
+                .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, Token>(
+                    "SELECT * FROM tokens WHERE user_id = $1 ORDER BY created_at DESC LIMIT $2 OFFSET $3",
+                )
+                .bind(user_id)
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(pool)
+                .await?
+            }
+        };
+
+        Ok(tokens)
+    }
+
+    async fn count_tokens(&self) -> Result<u64, OAuth2Error> {
+        let count: i64 = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_scalar("SELECT COUNT(*) FROM tokens")
+                    .fetch_one(pool)
+                    .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_scalar("SELECT COUNT(*) FROM tokens")
+                    .fetch_one(pool)
+                    .await?
+            }
+        };
+
+        Ok(count.max(0) as u64)
+    }
+
+    async fn count_active_tokens(&self) -> Result<u64, OAuth2Error> {
+        let now = chrono::Utc::now();
+        let count: i64 = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_scalar(
+                    "SELECT COUNT(*) FROM tokens WHERE revoked = 0 AND expires_at > ?",
+                )
+                .bind(now)
+                .fetch_one(pool)
+                .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_scalar(
+                    "SELECT COUNT(*) FROM tokens WHERE revoked = false AND expires_at > $1",
+                )
+                .bind(now)
+                .fetch_one(pool)
+                .await?
+            }
+        };
+
+        Ok(count.max(0) as u64)
+    }
+
+    async fn save_authorization_code(
+        &self,
+        auth_code: &AuthorizationCode,
+    ) -> Result<(), OAuth2Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO authorization_codes (id, code, client_id, user_id, redirect_uri, scope, created_at, expires_at, used, code_challenge, code_challenge_method, context_binding, tenant_id)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(&auth_code.id)
+                .bind(&auth_code.code)
+                .bind(&auth_code.client_id)
+                .bind(&auth_code.user_id)
+                .bind(&auth_code.redirect_uri)
+                .bind(&auth_code.scope)
+                .bind(auth_code.created_at)
+                .bind(auth_code.expires_at)
+                .bind(auth_code.used)
+                .bind(&auth_code.code_challenge)
+                .bind(&auth_code.code_challenge_method)
+                .bind(&auth_code.context_binding)
+                .bind(&auth_code.tenant_id)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO authorization_codes (id, code, client_id, user_id, redirect_uri, scope, created_at, expires_at, used, code_challenge, code_challenge_method, context_binding, tenant_id)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                    "#,
+                )
+                .bind(&auth_code.id)
+                .bind(&auth_code.code)
+                .bind(&auth_code.client_id)
+                .bind(&auth_code.user_id)
+                .bind(&auth_code.redirect_uri)
+                .bind(&auth_code.scope)
+                .bind(auth_code.created_at)
+                .bind(auth_code.expires_at)
+                .bind(auth_code.used)
+                .bind(&auth_code.code_challenge)
+                .bind(&auth_code.code_challenge_method)
+                .bind(&auth_code.context_binding)
+                .bind(&auth_code.tenant_id)
+                .execute(pool)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn get_authorization_code(
+        &self,
+        code: &str,
+    ) -> Result<Option<AuthorizationCode>, OAuth2Error> {
+        let auth_code = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, AuthorizationCode>(
+                    "SELECT * FROM authorization_codes WHERE code = ?",
+                )
+                .bind(code)
+                .fetch_optional(pool)
+                .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, AuthorizationCode>(
+                    "SELECT * FROM authorization_codes WHERE code = $1",
+                )
+                .bind(code)
+                .fetch_optional(pool)
+                .await?
+            }
+        };
+
+        Ok(auth_code)
+    }
+
+    async fn mark_authorization_code_used(&self, code: &str) -> Result<(), OAuth2Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query("UPDATE authorization_codes SET used = 1 WHERE code = ?")
+                    .bind(code)
+                    .execute(pool)
+                    .await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query("UPDATE authorization_codes SET used = true WHERE code = $1")
+                    .bind(code)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn begin_transaction(&self) -> Result<Box<dyn StorageTransaction>, OAuth2Error> {
+        let tx = match &self.pool {
+            DatabasePool::Sqlite(pool) => SqlxTransaction::Sqlite(pool.begin().await?),
+            DatabasePool::Postgres(pool) => SqlxTransaction::Postgres(pool.begin().await?),
+        };
+        Ok(Box::new(tx))
+    }
+
+    async fn save_social_login_state(&self, state: &SocialLoginState) -> Result<(), OAuth2Error> {
+        let now = chrono::Utc::now();
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                // Abandoned logins are never consumed; clear them out as new ones arrive.
+                sqlx::query("DELETE FROM social_login_states WHERE expires_at < ?")
+                    .bind(now)
+                    .execute(pool)
+                    .await?;
+                sqlx::query(
+                    r#"
+                    INSERT INTO social_login_states (state, provider, pkce_verifier, nonce, created_at, expires_at, browser_binding)
+                    VALUES (?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(&state.state)
+                .bind(&state.provider)
+                .bind(&state.pkce_verifier)
+                .bind(&state.nonce)
+                .bind(state.created_at)
+                .bind(state.expires_at)
+                .bind(&state.browser_binding)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query("DELETE FROM social_login_states WHERE expires_at < $1")
+                    .bind(now)
+                    .execute(pool)
+                    .await?;
+                sqlx::query(
+                    r#"
+                    INSERT INTO social_login_states (state, provider, pkce_verifier, nonce, created_at, expires_at, browser_binding)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    "#,
+                )
+                .bind(&state.state)
+                .bind(&state.provider)
+                .bind(&state.pkce_verifier)
+                .bind(&state.nonce)
+                .bind(state.created_at)
+                .bind(state.expires_at)
+                .bind(&state.browser_binding)
+                .execute(pool)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn take_social_login_state(
+        &self,
+        state: &str,
+    ) -> Result<Option<SocialLoginState>, OAuth2Error> {
+        // A single DELETE ... RETURNING, so two concurrent callbacks cannot both get it.
+        let record = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, SocialLoginState>(
+                    "DELETE FROM social_login_states WHERE state = ? RETURNING *",
+                )
+                .bind(state)
+                .fetch_optional(pool)
+                .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, SocialLoginState>(
+                    "DELETE FROM social_login_states WHERE state = $1 RETURNING *",
+                )
+                .bind(state)
+                .fetch_optional(pool)
+                .await?
+            }
+        };
+
+        Ok(record)
+    }
+
+    async fn save_email_token(&self, token: &EmailToken) -> Result<(), OAuth2Error> {
+        let now = chrono::Utc::now();
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                // Unused tokens are never taken; clear them out as new ones are mailed.
+                sqlx::query("DELETE FROM email_tokens WHERE expires_at < ?")
+                    .bind(now)
+                    .execute(pool)
+                    .await?;
+                sqlx::query(
+                    r#"
+                    INSERT INTO email_tokens (token_hash, user_id, purpose, email, created_at, expires_at, return_to)
+                    VALUES (?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(&token.token_hash)
+                .bind(&token.user_id)
+                .bind(token.purpose.as_str())
+                .bind(&token.email)
+                .bind(token.created_at)
+                .bind(token.expires_at)
+                .bind(&token.return_to)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query("DELETE FROM email_tokens WHERE expires_at < $1")
+                    .bind(now)
+                    .execute(pool)
+                    .await?;
+                sqlx::query(
+                    r#"
+                    INSERT INTO email_tokens (token_hash, user_id, purpose, email, created_at, expires_at, return_to)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    "#,
+                )
+                .bind(&token.token_hash)
+                .bind(&token.user_id)
+                .bind(token.purpose.as_str())
+                .bind(&token.email)
+                .bind(token.created_at)
+                .bind(token.expires_at)
+                .bind(&token.return_to)
+                .execute(pool)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn take_email_token(&self, token_hash: &str) -> Result<Option<EmailToken>, OAuth2Error> {
+        // A single DELETE ... RETURNING, so a token cannot be used twice concurrently.
+        let record = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, EmailToken>(
+                    "DELETE FROM email_tokens WHERE token_hash = ? RETURNING *",
+                )
+                .bind(token_hash)
+                .fetch_optional(pool)
+                .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, EmailToken>(
+                    "DELETE FROM email_tokens WHERE token_hash = $1 RETURNING *",
+                )
+                .bind(token_hash)
+                .fetch_optional(pool)
+                .await?
+            }
+        };
+
+        Ok(record)
+    }
+
+    async fn delete_email_tokens(
+        &self,
+        user_id: &str,
+        purpose: EmailTokenPurpose,
+    ) -> Result<(), OAuth2Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query("DELETE FROM email_tokens WHERE user_id = ? AND purpose = ?")
+                    .bind(user_id)
+                    .bind(purpose.as_str())
+                    .execute(pool)
+                    .await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query("DELETE FROM email_tokens WHERE user_id = $1 AND purpose = $2")
+                    .bind(user_id)
+                    .bind(purpose.as_str())
+                    .execute(pool)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn save_user_session(&self, session: &UserSession) -> Result<(), OAuth2Error> {
+        let now = chrono::Utc::now();
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                // Abandoned sessions are never deleted by their browser; clear them out here.
+                sqlx::query(
+                    "DELETE FROM user_sessions WHERE idle_expires_at < ? OR expires_at < ?",
+                )
+                .bind(now)
+                .bind(now)
+                .execute(pool)
+                .await?;
+                sqlx::query(
+                    r#"
+                    INSERT INTO user_sessions (id, user_id, state, created_at, last_seen_at, idle_expires_at, expires_at)
+                    VALUES (?, ?, ?, ?, ?, ?, ?)
+                    ON CONFLICT (id) DO UPDATE SET
+                        user_id = excluded.user_id,
+                        state = excluded.state,
+                        last_seen_at = excluded.last_seen_at,
+                        idle_expires_at = excluded.idle_expires_at,
+                        expires_at = excluded.expires_at
+                    "#,
+                )
+                .bind(&session.id)
+                .bind(&session.user_id)
+                .bind(&session.state)
+                .bind(session.created_at)
+                .bind(session.last_seen_at)
+                .bind(session.idle_expires_at)
+                .bind(session.expires_at)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    "DELETE FROM user_sessions WHERE idle_expires_at < $1 OR expires_at < $1",
+                )
+                .bind(now)
+                .execute(pool)
+                .await?;
+                sqlx::query(
+                    r#"
+                    INSERT INTO user_sessions (id, user_id, state, created_at, last_seen_at, idle_expires_at, expires_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    ON CONFLICT (id) DO UPDATE SET
+                        user_id = excluded.user_id,
+                        state = excluded.state,
+                        last_seen_at = excluded.last_seen_at,
+                        idle_expires_at = excluded.idle_expires_at,
+                        expires_at = excluded.expires_at
+                    "#,
+                )
+                .bind(&session.id)
+                .bind(&session.user_id)
+                .bind(&session.state)
+                .bind(session.created_at)
+                .bind(session.last_seen_at)
+                .bind(session.idle_expires_at)
+                .bind(session.expires_at)
+                .execute(pool)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn get_user_session(&self, id: &str) -> Result<Option<UserSession>, OAuth2Error> {
+        let session = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, UserSession>("SELECT * FROM user_sessions WHERE id = ?")
+                    .bind(id)
+                    .fetch_optional(pool)
+                    .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, UserSession>("SELECT * FROM user_sessions WHERE id = $1")
+                    .bind(id)
+                    .fetch_optional(pool)
+                    .await?
+            }
+        };
+
+        Ok(session)
+    }
+
+    async fn touch_user_session(
+        &self,
+        id: &str,
+        last_seen_at: DateTime<Utc>,
+        idle_expires_at: DateTime<Utc>,
+    ) -> Result<(), OAuth2Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query(
+                    "UPDATE user_sessions SET last_seen_at = ?, idle_expires_at = ? WHERE id = ?",
+                )
+                .bind(last_seen_at)
+                .bind(idle_expires_at)
+                .bind(id)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    "UPDATE user_sessions SET last_seen_at = $1, idle_expires_at = $2 WHERE id = $3",
+                )
+                .bind(last_seen_at)
+                .bind(idle_expires_at)
+                .bind(id)
+                .execute(pool)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn list_user_sessions(&self, user_id: &str) -> Result<Vec<UserSession>, OAuth2Error> {
+        let now = chrono::Utc::now();
+        let sessions = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, UserSession>(
+                    r#"
+                    SELECT * FROM user_sessions
+                    WHERE user_id = ? AND idle_expires_at > ? AND expires_at > ?
+                    ORDER BY created_at DESC
+                    "#,
+                )
+                .bind(user_id)
+                .bind(now)
+                .bind(now)
+                .fetch_all(pool)
+                .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, UserSession>(
+                    r#"
+                    SELECT * FROM user_sessions
+                    WHERE user_id = $1 AND idle_expires_at > $2 AND expires_at > $2
+                    ORDER BY created_at DESC
+                    "#,
+                )
+                .bind(user_id)
+                .bind(now)
+                .fetch_all(pool)
+                .await?
+            }
+        };
+
+        Ok(sessions)
+    }
+
+    async fn delete_user_session(&self, id: &str) -> Result<(), OAuth2Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query("DELETE FROM user_sessions WHERE id = ?")
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query("DELETE FROM user_sessions WHERE id = $1")
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn save_audit_record(&self, record: &AuditRecord) -> Result<(), OAuth2Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO audit_records (id, occurred_at, action, outcome, actor, client_id, target, ip_address, user_agent, detail)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(&record.id)
+                .bind(record.occurred_at)
+                .bind(record.action.as_str())
+                .bind(record.outcome.as_str())
+                .bind(&record.actor)
+                .bind(&record.client_id)
+                .bind(&record.target)
+                .bind(&record.ip_address)
+                .bind(&record.user_agent)
+                .bind(&record.detail)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO audit_records (id, occurred_at, action, outcome, actor, client_id, target, ip_address, user_agent, detail)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                    "#,
+                )
+                .bind(&record.id)
+                .bind(record.occurred_at)
+                .bind(record.action.as_str())
+                .bind(record.outcome.as_str())
+                .bind(&record.actor)
+                .bind(&record.client_id)
+                .bind(&record.target)
+                .bind(&record.ip_address)
+                .bind(&record.user_agent)
+                .bind(&record.detail)
+                .execute(pool)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn list_audit_records(
+        &self,
+        query: &AuditQuery,
+    ) -> Result<Vec<AuditRecord>, OAuth2Error> {
+        let action = query.action.map(|action| action.as_str());
+        let limit = i64::from(query.limit);
+        let records = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, AuditRecord>(
+                    r#"
+                    SELECT * FROM audit_records
+                    WHERE (? IS NULL OR occurred_at >= ?)
+                      AND (? IS NULL OR occurred_at < ?)
+                      AND (? IS NULL OR actor = ?)
+                      AND (? IS NULL OR action = ?)
+                    ORDER BY occurred_at DESC
+                    LIMIT ?
+                    "#,
+                )
+                .bind(query.since)
+                .bind(query.since)
+                .bind(query.until)
+                .bind(query.until)
+                .bind(&query.actor)
+                .bind(&query.actor)
+                .bind(action)
+                .bind(action)
+                .bind(limit)
+                .fetch_all(pool)
+                .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, AuditRecord>(
+                    r#"
+                    SELECT * FROM audit_records
+                    WHERE ($1::TIMESTAMPTZ IS NULL OR occurred_at >= $1)
+                      AND ($2::TIMESTAMPTZ IS NULL OR occurred_at < $2)
+                      AND ($3::TEXT IS NULL OR actor = $3)
+                      AND ($4::TEXT IS NULL OR action = $4)
+                    ORDER BY occurred_at DESC
+                    LIMIT $5
+                    "#,
+                )
+                .bind(query.since)
+                .bind(query.until)
+                .bind(&query.actor)
+                .bind(action)
+                .bind(limit)
+                .fetch_all(pool)
+                .await?
+            }
+        };
+
+        Ok(records)
+    }
+
+    async fn enqueue_outbox_message(&self, message: &OutboxMessage) -> Result<(), OAuth2Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => insert_outbox_message_sqlite(pool, message).await?,
+            DatabasePool::Postgres(pool) => insert_outbox_message_postgres(pool, message).await?,
+        }
+        Ok(())
+    }
+
+    async fn claim_outbox_messages(
+        &self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<OutboxMessage>, OAuth2Error> {
+        let limit = i64::from(limit);
+        // Select and lease in one statement, so concurrent relays never claim the same message.
+        let mut messages = match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as::<_, OutboxMessage>(
+                    r#"
+                    UPDATE outbox_messages SET next_attempt_at = ?
+                    WHERE id IN (
+                        SELECT id FROM outbox_messages
+                        WHERE next_attempt_at <= ?
+                        ORDER BY created_at
+                        LIMIT ?
+                    )
+                    RETURNING *
+                    "#,
+                )
+                .bind(lease_until)
+                .bind(now)
+                .bind(limit)
+                .fetch_all(pool)
+                .await?
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as::<_, OutboxMessage>(
+                    r#"
+                    UPDATE outbox_messages SET next_attempt_at = $1
+                    WHERE id IN (
+                        SELECT id FROM outbox_messages
+                        WHERE next_attempt_at <= $2
+                        ORDER BY created_at
+                        LIMIT $3
+                        FOR UPDATE SKIP LOCKED
+                    )
+                    RETURNING *
+                    "#,
+                )
+                .bind(lease_until)
+                .bind(now)
+                .bind(limit)
+                .fetch_all(pool)
+                .await?
+            }
+        };
+        // RETURNING rows come back in no particular order.
+        messages.sort_by_key(|message| message.created_at);
+
+        Ok(messages)
+    }
+
+    async fn delete_outbox_message(&self, id: &str) -> Result<(), OAuth2Error> {
+        match &self.pool {
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query("DELETE FROM outbox_messages WHERE id = ?")
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+            }
+            DatabasePool::Postgres(pool) => {
+                sqlx::query("DELETE FROM outbox_messages WHERE id = $1")
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+
+        Ok(())
     }
 
-    async fn save_user(&self, user: &User) -> Result<(), OAuth2Error> {
+    async fn reschedule_outbox_message(
+        &self,
+        id: &str,
+        next_attempt_at: DateTime<Utc>,
+        error: &str,
+    ) -> Result<(), OAuth2Error> {
         match &self.pool {
             DatabasePool::Sqlite(pool) => {
                 sqlx::query(
                     r#"
-                    INSERT INTO users (id, username, password_hash, email, enabled, created_at, updated_at)
-                    VALUES (?, ?, ?, ?, ?, ?, ?)
+                    UPDATE outbox_messages
+                    SET attempts = attempts + 1, next_attempt_at = ?, last_error = ?
+                    WHERE id = ?
                     "#,
                 )
-                .bind(&user.id)
-                .bind(&user.username)
-                .bind(&user.password_hash)
-                .bind(&user.email)
-                .bind(user.enabled)
-                .bind(user.created_at)
-                .bind(user.updated_at)
+                .bind(next_attempt_at)
+                .bind(error)
+                .bind(id)
                 .execute(pool)
                 .await?;
             }
             DatabasePool::Postgres(pool) => {
                 sqlx::query(
                     r#"
-                    INSERT INTO users (id, username, password_hash, email, enabled, created_at, updated_at)
-                    VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    UPDATE outbox_messages
+                    SET attempts = attempts + 1, next_attempt_at = $1, last_error = $2
+                    WHERE id = $3
                     "#,
                 )
-                .bind(&user.id)
-                .bind(&user.username)
-                .bind(&user.password_hash)
-                .bind(&user.email)
-                .bind(user.enabled)
-                .bind(user.created_at)
-                .bind(user.updated_at)
+                .bind(next_attempt_at)
+                .bind(error)
+                .bind(id)
                 .execute(pool)
                 .await?;
             }
@@ -321,66 +2647,44 @@ impl Storage for SqlxStorage {
         Ok(())
     }
 
-    async fn get_user_by_username(&self, username: &str) -> Result<Option<User>, OAuth2Error> {
-        let user = match &self.pool {
-            DatabasePool::Sqlite(pool) => {
-                sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ?")
-                    .bind(username)
-                    .fetch_optional(pool)
-                    .await?
-            }
-            DatabasePool::Postgres(pool) => {
-                sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = $1")
-                    .bind(username)
-                    .fetch_optional(pool)
-                    .await?
-            }
-        };
-
-        Ok(user)
-    }
-
-    async fn save_token(&self, token: &Token) -> Result<(), OAuth2Error> {
+    async fn save_federated_identity(
+        &self,
+        identity: &FederatedIdentity,
+    ) -> Result<(), OAuth2Error> {
         match &self.pool {
             DatabasePool::Sqlite(pool) => {
                 sqlx::query(
                     r#"
-                    INSERT INTO tokens (id, access_token, refresh_token, token_type, expires_in, scope, client_id, user_id, created_at, expires_at, revoked)
-                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    INSERT INTO federated_identities
+                        (provider, provider_user_id, user_id, created_at, access_token, refresh_token, token_expires_at)
+                    VALUES (?, ?, ?, ?, ?, ?, ?)
                     "#,
                 )
-                .bind(&token.id)
-                .bind(&token.access_token)
-                .bind(&token.refresh_token)
-                .bind(&token.token_type)
-                .bind(token.expires_in)
-                .bind(&token.scope)
-                .bind(&token.client_id)
-                .bind(&token.user_id)
-                .bind(token.created_at)
-                .bind(token.expires_at)
-                .bind(token.revoked)
+                .bind(&identity.provider)
+                .bind(&identity.provider_user_id)
+                .bind(&identity.user_id)
+                .bind(identity.created_at)
+                .bind(&identity.access_token)
+                .bind(&identity.refresh_token)
+                .bind(identity.token_expires_at)
                 .execute(pool)
                 .await?;
             }
             DatabasePool::Postgres(pool) => {
                 sqlx::query(
                     r#"
-                    INSERT INTO tokens (id, access_token, refresh_token, token_type, expires_in, scope, client_id, user_id, created_at, expires_at, revoked)
-                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                    INSERT INTO federated_identities
+                        (provider, provider_user_id, user_id, created_at, access_token, refresh_token, token_expires_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
                     "#,
                 )
-                .bind(&token.id)
-                .bind(&token.access_token)
-                .bind(&token.refresh_token)
-                .bind(&token.token_type)
-                .bind(token.expires_in)
-                .bind(&token.scope)
-                .bind(&token.client_id)
-                .bind(&token.user_id)
-                .bind(token.created_at)
-                .bind(token.expires_at)
-                .bind(token.revoked)
+                .bind(&identity.provider)
+                .bind(&identity.provider_user_id)
+                .bind(&identity.user_id)
+                .bind(identity.created_at)
+                .bind(&identity.access_token)
+                .bind(&identity.refresh_token)
+                .bind(identity.token_expires_at)
                 .execute(pool)
                 .await?;
             }
@@ -389,45 +2693,91 @@ impl Storage for SqlxStorage {
         Ok(())
     }
 
-    async fn get_token_by_access_token(
+    async fn get_federated_identity(
         &self,
-        access_token: &str,
-    ) -> Result<Option<Token>, OAuth2Error> {
-        let token = match &self.pool {
+        provider: &str,
+        provider_user_id: &str,
+    ) -> Result<Option<FederatedIdentity>, OAuth2Error> {
+        let identity = match &self.pool {
+            DatabasePool::Sqlite(pool) => sqlx::query_as::<_, FederatedIdentity>(
+                "SELECT * FROM federated_identities WHERE provider = ? AND provider_user_id = ?",
+            )
+            .bind(provider)
+            .bind(provider_user_id)
+            .fetch_optional(pool)
+            .await?,
+            DatabasePool::Postgres(pool) => sqlx::query_as::<_, FederatedIdentity>(
+                "SELECT * FROM federated_identities WHERE provider = $1 AND provider_user_id = $2",
+            )
+            .bind(provider)
+            .bind(provider_user_id)
+            .fetch_optional(pool)
+            .await?,
+        };
+
+        Ok(identity)
+    }
+
+    async fn list_federated_identities(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<FederatedIdentity>, OAuth2Error> {
+        let identities = match &self.pool {
             DatabasePool::Sqlite(pool) => {
-                sqlx::query_as::<_, Token>("SELECT * FROM tokens WHERE access_token = ?")
-                    .bind(access_token)
-                    .fetch_optional(pool)
-                    .await?
+                sqlx::query_as::<_, FederatedIdentity>(
+                    "SELECT * FROM federated_identities WHERE user_id = ? ORDER BY provider, provider_user_id",
+                )
+                .bind(user_id)
+                .fetch_all(pool)
+                .await?
             }
             DatabasePool::Postgres(pool) => {
-                sqlx::query_as::<_, Token>("SELECT * FROM tokens WHERE access_token = $1")
-                    .bind(access_token)
-                    .fetch_optional(pool)
-                    .await?
+                sqlx::query_as::<_, FederatedIdentity>(
+                    "SELECT * FROM federated_identities WHERE user_id = $1 ORDER BY provider, provider_user_id",
+                )
+                .bind(user_id)
+                .fetch_all(pool)
+                .await?
             }
         };
 
-        Ok(token)
+        Ok(identities)
     }
 
-    async fn revoke_token(&self, token: &str) -> Result<(), OAuth2Error> {
+    async fn update_federated_identity_tokens(
+        &self,
+        identity: &FederatedIdentity,
+    ) -> Result<(), OAuth2Error> {
         match &self.pool {
             DatabasePool::Sqlite(pool) => {
                 sqlx::query(
-                    "UPDATE tokens SET revoked = 1 WHERE access_token = ? OR refresh_token = ?",
+                    r#"
+                    UPDATE federated_identities
+                    SET access_token = ?, refresh_token = ?, token_expires_at = ?
+                    WHERE provider = ? AND provider_user_id = ?
+                    "#,
                 )
-                .bind(token)
-                .bind(token)
+                .bind(&identity.access_token)
+                .bind(&identity.refresh_token)
+                .bind(identity.token_expires_at)
+                .bind(&identity.provider)
+                .bind(&identity.provider_user_id)
                 .execute(pool)
                 .await?;
             }
             DatabasePool::Postgres(pool) => {
                 sqlx::query(
-                    "UPDATE tokens SET revoked = true WHERE access_token = $1 OR refresh_token = $2",
+                    r#"
+                    UPDATE federated_identities
+                    SET access_token = $1, refresh_token = $2, token_expires_at = $3
+                    WHERE provider = $4 AND provider_user_id = $5
+                    "#,
                 )
-                .bind(token)
-                .bind(token)
+                .bind(&identity.access_token)
+                .bind(&identity.refresh_token)
+                .bind(identity.token_expires_at)
+                .bind(&identity.provider)
+                .bind(&identity.provider_user_id)
                 .execute(pool)
                 .await?;
             }
@@ -436,50 +2786,27 @@ impl Storage for SqlxStorage {
         Ok(())
     }
 
-    async fn save_authorization_code(
+    async fn delete_federated_identity(
         &self,
-        auth_code: &AuthorizationCode,
+        provider: &str,
+        provider_user_id: &str,
     ) -> Result<(), OAuth2Error> {
         match &self.pool {
             DatabasePool::Sqlite(pool) => {
                 sqlx::query(
-                    r#"
-                    INSERT INTO authorization_codes (id, code, client_id, user_id, redirect_uri, scope, created_at, expires_at, used, code_challenge, code_challenge_method)
-                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-                    "#,
+                    "DELETE FROM federated_identities WHERE provider = ? AND provider_user_id = ?",
                 )
-                .bind(&auth_code.id)
-                .bind(&auth_code.code)
-                .bind(&auth_code.client_id)
-                .bind(&auth_code.user_id)
-                .bind(&auth_code.redirect_uri)
-                .bind(&auth_code.scope)
-                .bind(auth_code.created_at)
-                .bind(auth_code.expires_at)
-                .bind(auth_code.used)
-                .bind(&auth_code.code_challenge)
-                .bind(&auth_code.code_challenge_method)
+                .bind(provider)
+                .bind(provider_user_id)
                 .execute(pool)
                 .await?;
             }
             DatabasePool::Postgres(pool) => {
                 sqlx::query(
-                    r#"
-                    INSERT INTO authorization_codes (id, code, client_id, user_id, redirect_uri, scope, created_at, expires_at, used, code_challenge, code_challenge_method)
-                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
-                    "#,
+                    "DELETE FROM federated_identities WHERE provider = $1 AND provider_user_id = $2",
                 )
-                .bind(&auth_code.id)
-                .bind(&auth_code.code)
-                .bind(&auth_code.client_id)
-                .bind(&auth_code.user_id)
-                .bind(&auth_code.redirect_uri)
-                .bind(&auth_code.scope)
-                .bind(auth_code.created_at)
-                .bind(auth_code.expires_at)
-                .bind(auth_code.used)
-                .bind(&auth_code.code_challenge)
-                .bind(&auth_code.code_challenge_method)
+                .bind(provider)
+                .bind(provider_user_id)
                 .execute(pool)
                 .await?;
             }
@@ -488,52 +2815,131 @@ impl Storage for SqlxStorage {
         Ok(())
     }
 
-    async fn get_authorization_code(
-        &self,
-        code: &str,
-    ) -> Result<Option<AuthorizationCode>, OAuth2Error> {
-        let auth_code = match &self.pool {
-            DatabasePool::Sqlite(pool) => {
-                sqlx::query_as::<_, AuthorizationCode>(
-                    "SELECT * FROM authorization_codes WHERE code = ?",
-                )
-                .bind(code)
-                .fetch_optional(pool)
-                .await?
-            }
-            DatabasePool::Postgres(pool) => {
-                sqlx::query_as::<_, AuthorizationCode>(
-                    "SELECT * FROM authorization_codes WHERE code = $1",
-                )
-                .bind(code)
-                .fetch_optional(pool)
-                .await?
-            }
-        };
+    async fn save_role(&self, role: &Role) -> Result<(), OAuth2Error> {
+        self.insert_named(
+            "roles",
+            &role.name,
+            role.description.as_deref(),
+            role.created_at,
+        )
+        .await?;
+        Ok(())
+    }
 
-        Ok(auth_code)
+    async fn list_roles(&self) -> Result<Vec<Role>, OAuth2Error> {
+        Ok(self.list_named("roles").await?)
     }
 
-    async fn mark_authorization_code_used(&self, code: &str) -> Result<(), OAuth2Error> {
-        match &self.pool {
-            DatabasePool::Sqlite(pool) => {
-                sqlx::query("UPDATE authorization_codes SET used = 1 WHERE code = ?")
-                    .bind(code)
-                    .execute(pool)
-                    .await?;
-            }
-            DatabasePool::Postgres(pool) => {
-                sqlx::query("UPDATE authorization_codes SET used = true WHERE code = $1")
-                    .bind(code)
-                    .execute(pool)
-                    .await?;
-            }
-        }
+    async fn delete_role(&self, name: &str) -> Result<(), OAuth2Error> {
+        self.delete_named("roles", "user_roles", "role_name", name)
+            .await?;
+        Ok(())
+    }
+
+    async fn save_group(&self, group: &Group) -> Result<(), OAuth2Error> {
+        self.insert_named(
+            "groups",
+            &group.name,
+            group.description.as_deref(),
+            group.created_at,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn list_groups(&self) -> Result<Vec<Group>, OAuth2Error> {
+        Ok(self.list_named("groups").await?)
+    }
+
+    async fn delete_group(&self, name: &str) -> Result<(), OAuth2Error> {
+        self.delete_named("groups", "user_groups", "group_name", name)
+            .await?;
+        Ok(())
+    }
+
+    async fn list_user_roles(&self, user_id: &str) -> Result<Vec<String>, OAuth2Error> {
+        Ok(self
+            .list_assigned("user_roles", "role_name", user_id)
+            .await?)
+    }
+
+    async fn set_user_roles(&self, user_id: &str, roles: &[String]) -> Result<(), OAuth2Error> {
+        self.set_assigned("user_roles", "role_name", user_id, roles)
+            .await?;
+        Ok(())
+    }
 
+    async fn list_user_groups(&self, user_id: &str) -> Result<Vec<String>, OAuth2Error> {
+        Ok(self
+            .list_assigned("user_groups", "group_name", user_id)
+            .await?)
+    }
+
+    async fn set_user_groups(&self, user_id: &str, groups: &[String]) -> Result<(), OAuth2Error> {
+        self.set_assigned("user_groups", "group_name", user_id, groups)
+            .await?;
         Ok(())
     }
 }
 
+/// `LIMIT` and `OFFSET` for a page.
+/// `definition` (a `CREATE TABLE` statement) without the UNIQUE constraints on
+/// `clients.client_id`/`users.username` and foreign keys to `clients(client_id)`.
+fn without_global_client_keys(table: &str, definition: &str) -> String {
+    const CLIENT_KEY: &str = "FOREIGN KEY (client_id) REFERENCES clients(client_id)";
+
+    let mut definition = match table {
+        "clients" => definition.replacen(
+            "client_id TEXT NOT NULL UNIQUE",
+            "client_id TEXT NOT NULL",
+            1,
+        ),
+        "users" => {
+            definition.replacen("username TEXT NOT NULL UNIQUE", "username TEXT NOT NULL", 1)
+        }
+        _ => definition.to_string(),
+    };
+    if let Some(start) = definition.find(CLIENT_KEY) {
+        let end = start + CLIENT_KEY.len();
+        let rest = &definition[end..];
+        if let Some(after_comma) = rest.trim_start().strip_prefix(',') {
+            definition = format!("{}{}", &definition[..start], after_comma.trim_start());
+        } else {
+            let before = definition[..start].trim_end();
+            let before = before.strip_suffix(',').unwrap_or(before);
+            definition = format!("{before}{rest}");
+        }
+    }
+    definition
+}
+
+fn limit_offset(page: PageRequest) -> (i64, i64) {
+    (
+        i64::from(page.limit()),
+        i64::try_from(page.offset()).unwrap_or(i64::MAX),
+    )
+}
+
+/// Build a `LIKE` substring pattern, escaping wildcard characters in the search term.
+fn like_pattern(search: &str) -> String {
+    let escaped = search
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+/// SQLite JSON path selecting a metadata key. Keys are validated to `[A-Za-z0-9_.:-]`,
+/// and quoting keeps `.` and `:` literal.
+fn metadata_json_path(key: &str) -> String {
+    format!("$.\"{key}\"")
+}
+
+/// Single-entry JSON object for a Postgres `@>` containment match.
+fn metadata_tag_json(key: &str, value: &str) -> String {
+    serde_json::json!({ key: value }).to_string()
+}
+
 fn sqlite_db_path(database_url: &str) -> Option<PathBuf> {
     if !database_url.starts_with("sqlite:") {
         return None;
@@ -578,3 +2984,355 @@ fn sqlite_url_with_create_mode(database_url: &str) -> Cow<'_, str> {
     let sep = if database_url.contains('?') { '&' } else { '?' };
     Cow::Owned(format!("{database_url}{sep}mode=rwc"))
 }
+
+async fn insert_token_sqlite<'e>(
+    executor: impl SqliteExecutor<'e>,
+    token: &Token,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO tokens (id, access_token, refresh_token, token_type, expires_in, scope, client_id, user_id, created_at, expires_at, revoked, grant_id, metadata, tenant_id, context_binding)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&token.id)
+    .bind(&token.access_token)
+    .bind(&token.refresh_token)
+    .bind(&token.token_type)
+    .bind(token.expires_in)
+    .bind(&token.scope)
+    .bind(&token.client_id)
+    .bind(&token.user_id)
+    .bind(token.created_at)
+    .bind(token.expires_at)
+    .bind(token.revoked)
+    .bind(&token.grant_id)
+    .bind(token.metadata.to_json())
+    .bind(&token.tenant_id)
+    .bind(&token.context_binding)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+async fn insert_token_postgres<'e>(
+    executor: impl PgExecutor<'e>,
+    token: &Token,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO tokens (id, access_token, refresh_token, token_type, expires_in, scope, client_id, user_id, created_at, expires_at, revoked, grant_id, metadata, tenant_id, context_binding)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+        "#,
+    )
+    .bind(&token.id)
+    .bind(&token.access_token)
+    .bind(&token.refresh_token)
+    .bind(&token.token_type)
+    .bind(token.expires_in)
+    .bind(&token.scope)
+    .bind(&token.client_id)
+    .bind(&token.user_id)
+    .bind(token.created_at)
+    .bind(token.expires_at)
+    .bind(token.revoked)
+    .bind(&token.grant_id)
+    .bind(token.metadata.to_json())
+    .bind(&token.tenant_id)
+    .bind(&token.context_binding)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+async fn revoke_token_sqlite<'e>(
+    executor: impl SqliteExecutor<'e>,
+    token: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE tokens SET revoked = 1 WHERE access_token = ? OR refresh_token = ?")
+        .bind(token)
+        .bind(token)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+async fn revoke_token_postgres<'e>(
+    executor: impl PgExecutor<'e>,
+    token: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE tokens SET revoked = true WHERE access_token = $1 OR refresh_token = $2")
+        .bind(token)
+        .bind(token)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+async fn insert_outbox_message_sqlite<'e>(
+    executor: impl SqliteExecutor<'e>,
+    message: &OutboxMessage,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO outbox_messages (id, event_type, payload, created_at, attempts, next_attempt_at, last_error)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&message.id)
+    .bind(&message.event_type)
+    .bind(&message.payload)
+    .bind(message.created_at)
+    .bind(message.attempts)
+    .bind(message.next_attempt_at)
+    .bind(&message.last_error)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+async fn insert_outbox_message_postgres<'e>(
+    executor: impl PgExecutor<'e>,
+    message: &OutboxMessage,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO outbox_messages (id, event_type, payload, created_at, attempts, next_attempt_at, last_error)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(&message.id)
+    .bind(&message.event_type)
+    .bind(&message.payload)
+    .bind(message.created_at)
+    .bind(message.attempts)
+    .bind(message.next_attempt_at)
+    .bind(&message.last_error)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// A database transaction; sqlx rolls it back if it is dropped uncommitted.
+enum SqlxTransaction {
+    Sqlite(sqlx::Transaction<'static, Sqlite>),
+    Postgres(sqlx::Transaction<'static, Postgres>),
+}
+
+#[async_trait]
+impl StorageTransaction for SqlxTransaction {
+    async fn consume_authorization_code(&mut self, code: &str) -> Result<(), OAuth2Error> {
+        // Conditional on `used`, so a concurrent exchange of the same code updates no row.
+        let result = match self {
+            SqlxTransaction::Sqlite(tx) => {
+                sqlx::query("UPDATE authorization_codes SET used = 1 WHERE code = ? AND used = 0")
+                    .bind(code)
+                    .execute(&mut **tx)
+                    .await?
+                    .rows_affected()
+            }
+            SqlxTransaction::Postgres(tx) => sqlx::query(
+                "UPDATE authorization_codes SET used = true WHERE code = $1 AND used = false",
+            )
+            .bind(code)
+            .execute(&mut **tx)
+            .await?
+            .rows_affected(),
+        };
+        if result != 1 {
+            return Err(OAuth2Error::invalid_grant(
+                "Authorization code is invalid or already used",
+            ));
+        }
+        Ok(())
+    }
+
+    async fn save_token(&mut self, token: &Token) -> Result<(), OAuth2Error> {
+        match self {
+            SqlxTransaction::Sqlite(tx) => insert_token_sqlite(&mut **tx, token).await?,
+            SqlxTransaction::Postgres(tx) => insert_token_postgres(&mut **tx, token).await?,
+        }
+        Ok(())
+    }
+
+    async fn revoke_token(&mut self, token: &str) -> Result<(), OAuth2Error> {
+        match self {
+            SqlxTransaction::Sqlite(tx) => revoke_token_sqlite(&mut **tx, token).await?,
+            SqlxTransaction::Postgres(tx) => revoke_token_postgres(&mut **tx, token).await?,
+        }
+        Ok(())
+    }
+
+    async fn redeem_refresh_token(&mut self, refresh_token: &str) -> Result<(), OAuth2Error> {
+        // Conditional on `revoked`, so a concurrent rotation of the same token updates no row.
+        let result = match self {
+            SqlxTransaction::Sqlite(tx) => {
+                sqlx::query("UPDATE tokens SET revoked = 1 WHERE refresh_token = ? AND revoked = 0")
+                    .bind(refresh_token)
+                    .execute(&mut **tx)
+                    .await?
+                    .rows_affected()
+            }
+            SqlxTransaction::Postgres(tx) => sqlx::query(
+                "UPDATE tokens SET revoked = true WHERE refresh_token = $1 AND revoked = false",
+            )
+            .bind(refresh_token)
+            .execute(&mut **tx)
+            .await?
+            .rows_affected(),
+        };
+        if result != 1 {
+            return Err(OAuth2Error::invalid_grant("Refresh token is revoked"));
+        }
+        Ok(())
+    }
+
+    async fn enqueue_outbox_message(&mut self, message: &OutboxMessage) -> Result<(), OAuth2Error> {
+        match self {
+            SqlxTransaction::Sqlite(tx) => insert_outbox_message_sqlite(&mut **tx, message).await?,
+            SqlxTransaction::Postgres(tx) => {
+                insert_outbox_message_postgres(&mut **tx, message).await?
+            }
+        }
+        Ok(())
+    }
+
+    async fn commit(self: Box<Self>) -> Result<(), OAuth2Error> {
+        match *self {
+            SqlxTransaction::Sqlite(tx) => tx.commit().await?,
+            SqlxTransaction::Postgres(tx) => tx.commit().await?,
+        }
+        Ok(())
+    }
+
+    async fn rollback(self: Box<Self>) -> Result<(), OAuth2Error> {
+        match *self {
+            SqlxTransaction::Sqlite(tx) => tx.rollback().await?,
+            SqlxTransaction::Postgres(tx) => tx.rollback().await?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn global_client_keys_are_dropped_from_legacy_definitions() {
+        assert_eq!(
+            without_global_client_keys(
+                "clients",
+                "CREATE TABLE clients (id TEXT PRIMARY KEY, client_id TEXT NOT NULL UNIQUE)"
+            ),
+            "CREATE TABLE clients (id TEXT PRIMARY KEY, client_id TEXT NOT NULL)"
+        );
+        assert_eq!(
+            without_global_client_keys(
+                "tokens",
+                "CREATE TABLE tokens (client_id TEXT NOT NULL, user_id TEXT, \
+                 FOREIGN KEY (client_id) REFERENCES clients(client_id), \
+                 FOREIGN KEY (user_id) REFERENCES users(id))"
+            ),
+            "CREATE TABLE tokens (client_id TEXT NOT NULL, user_id TEXT, \
+             FOREIGN KEY (user_id) REFERENCES users(id))"
+        );
+        assert_eq!(
+            without_global_client_keys(
+                "service_accounts",
+                "CREATE TABLE service_accounts (client_id TEXT NOT NULL UNIQUE, \
+                 FOREIGN KEY (client_id) REFERENCES clients(client_id)\n)"
+            ),
+            "CREATE TABLE service_accounts (client_id TEXT NOT NULL UNIQUE\n)"
+        );
+    }
+
+    #[tokio::test]
+    async fn legacy_sqlite_files_get_tenant_scoped_client_ids() {
+        let path = std::env::temp_dir().join(format!(
+            "oauth2_legacy_{}_{}.db",
+            std::process::id(),
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        let url = format!("sqlite://{}?mode=rwc", path.display());
+
+        // The schema as created before client ids were scoped to tenants.
+        let legacy = SqlxStorage::new(&url).await.unwrap();
+        let DatabasePool::Sqlite(pool) = &legacy.pool else {
+            unreachable!("sqlite url");
+        };
+        for statement in [
+            "CREATE TABLE clients (id TEXT PRIMARY KEY, client_id TEXT NOT NULL UNIQUE, client_secret TEXT NOT NULL, redirect_uris TEXT NOT NULL, grant_types TEXT NOT NULL, scope TEXT NOT NULL, name TEXT NOT NULL, created_at TEXT NOT NULL, updated_at TEXT NOT NULL)",
+            "CREATE INDEX idx_clients_client_id ON clients(client_id)",
+            "CREATE TABLE users (id TEXT PRIMARY KEY, username TEXT NOT NULL UNIQUE, password_hash TEXT NOT NULL, email TEXT NOT NULL, enabled INTEGER NOT NULL DEFAULT 1, created_at TEXT NOT NULL, updated_at TEXT NOT NULL)",
+            "CREATE TABLE tokens (id TEXT PRIMARY KEY, access_token TEXT NOT NULL UNIQUE, refresh_token TEXT, token_type TEXT NOT NULL, expires_in INTEGER NOT NULL, scope TEXT NOT NULL, client_id TEXT NOT NULL, user_id TEXT, created_at TEXT NOT NULL, expires_at TEXT NOT NULL, revoked INTEGER NOT NULL DEFAULT 0, FOREIGN KEY (client_id) REFERENCES clients(client_id), FOREIGN KEY (user_id) REFERENCES users(id))",
+            "INSERT INTO clients VALUES ('1', 'web', 'secret', '[]', '[]', 'read', 'web', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+        ] {
+            sqlx::query(statement).execute(pool).await.unwrap();
+        }
+        legacy.init().await.unwrap();
+        legacy.init().await.unwrap();
+
+        let index: Option<(String,)> = sqlx::query_as(
+            "SELECT name FROM sqlite_master WHERE type = 'index' AND name = 'idx_clients_client_id'",
+        )
+        .fetch_optional(pool)
+        .await
+        .unwrap();
+        assert!(index.is_some(), "indexes survive the rebuild");
+
+        legacy
+            .save_tenant(&Tenant::new(
+                "acme".to_string(),
+                "acme".to_string(),
+                "acme_signing_secret".to_string(),
+            ))
+            .await
+            .unwrap();
+        let mut acme_web = Client::new(
+            "web".to_string(),
+            "secret".to_string(),
+            vec![],
+            vec![],
+            "read".to_string(),
+            "acme web".to_string(),
+        );
+        acme_web.tenant_id = Some("acme".to_string());
+        legacy.save_client(&acme_web).await.unwrap();
+        assert!(legacy.save_client(&acme_web).await.is_err());
+
+        let web = legacy.get_client("web", None).await.unwrap().unwrap();
+        assert_eq!(web.name, "web");
+        let acme = legacy
+            .get_client("web", Some("acme"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(acme.name, "acme web");
+
+        // Tokens no longer need a client of their id in the default tenant.
+        legacy
+            .save_token(
+                &Token::new(
+                    "acme_access".to_string(),
+                    None,
+                    "web".to_string(),
+                    None,
+                    "read".to_string(),
+                    3600,
+                )
+                .with_tenant_id(Some("acme".to_string())),
+            )
+            .await
+            .unwrap();
+        legacy.delete_client("web", None).await.unwrap();
+        assert!(legacy
+            .get_token_by_access_token("acme_access")
+            .await
+            .unwrap()
+            .is_some());
+
+        drop(legacy);
+        let _ = std::fs::remove_file(&path);
+    }
+}
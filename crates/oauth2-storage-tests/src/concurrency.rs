@@ -0,0 +1,182 @@
+use futures::future::join_all;
+use oauth2_core::{AuthorizationCode, Client, OAuth2Error, SocialLoginState, Token, User};
+use oauth2_ports::Storage;
+
+use crate::ContractResult;
+
+/// Concurrent attempts to use one authorization code or refresh token.
+const RACERS: usize = 8;
+
+/// Racing callers see single-use records used exactly once.
+pub(crate) async fn run(storage: &dyn Storage) -> ContractResult {
+    let client = Client::new(
+        "concurrency_client".to_string(),
+        "secret".to_string(),
+        vec!["http://localhost/cb".to_string()],
+        vec!["authorization_code".to_string()],
+        "read".to_string(),
+        "concurrency client".to_string(),
+    );
+    storage
+        .save_client(&client)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let user = User::new(
+        "concurrency_user".to_string(),
+        "password_hash".to_string(),
+        "concurrency_user@example.com".to_string(),
+    );
+    storage
+        .save_user(&user)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    // An authorization code is exchanged for at most one token, however many racers try.
+    let code = AuthorizationCode::new(
+        "concurrency_code".to_string(),
+        client.client_id.clone(),
+        user.id.clone(),
+        "http://localhost/cb".to_string(),
+        "read".to_string(),
+        None,
+        None,
+    );
+    storage
+        .save_authorization_code(&code)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let exchange = |i: usize| {
+        let token = Token::new(
+            format!("concurrency_access_{i}"),
+            None,
+            client.client_id.clone(),
+            Some(user.id.clone()),
+            "read".to_string(),
+            3600,
+        );
+        async move {
+            let mut tx = storage.begin_transaction().await?;
+            if let Err(e) = tx.consume_authorization_code("concurrency_code").await {
+                // Best effort: a failed transaction may already be rolled back.
+                let _ = tx.rollback().await;
+                return Err(e);
+            }
+            tx.save_token(&token).await?;
+            tx.commit().await
+        }
+    };
+    let outcomes: Vec<Result<(), OAuth2Error>> = join_all((0..RACERS).map(exchange)).await;
+    let winners: Vec<usize> = outcomes
+        .iter()
+        .enumerate()
+        .filter(|(_, outcome)| outcome.is_ok())
+        .map(|(i, _)| i)
+        .collect();
+    assert_eq!(winners.len(), 1, "outcomes: {outcomes:?}");
+
+    let consumed = storage
+        .get_authorization_code("concurrency_code")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("auth code should exist"))?;
+    assert!(consumed.used);
+    for i in 0..RACERS {
+        let issued = storage
+            .get_token_by_access_token(&format!("concurrency_access_{i}"))
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        assert_eq!(issued.is_some(), winners == [i], "token of racer {i}");
+    }
+
+    // A refresh token is rotated at most once, however many racers try.
+    let refreshable = Token::new(
+        "concurrency_refreshable".to_string(),
+        Some("concurrency_refresh".to_string()),
+        client.client_id.clone(),
+        Some(user.id.clone()),
+        "read".to_string(),
+        3600,
+    );
+    storage
+        .save_token(&refreshable)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let rotate = |i: usize| {
+        let token = Token::new(
+            format!("concurrency_rotated_{i}"),
+            Some(format!("concurrency_rotated_refresh_{i}")),
+            client.client_id.clone(),
+            Some(user.id.clone()),
+            "read".to_string(),
+            3600,
+        );
+        async move {
+            let mut tx = storage.begin_transaction().await?;
+            if let Err(e) = tx.redeem_refresh_token("concurrency_refresh").await {
+                let _ = tx.rollback().await;
+                return Err(e);
+            }
+            tx.save_token(&token).await?;
+            tx.commit().await
+        }
+    };
+    let outcomes: Vec<Result<(), OAuth2Error>> = join_all((0..RACERS).map(rotate)).await;
+    let winners: Vec<usize> = outcomes
+        .iter()
+        .enumerate()
+        .filter(|(_, outcome)| outcome.is_ok())
+        .map(|(i, _)| i)
+        .collect();
+    assert_eq!(winners.len(), 1, "outcomes: {outcomes:?}");
+    let redeemed = storage
+        .get_token_by_refresh_token("concurrency_refresh")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("refreshable token should exist"))?;
+    assert!(redeemed.revoked);
+    for i in 0..RACERS {
+        let issued = storage
+            .get_token_by_access_token(&format!("concurrency_rotated_{i}"))
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        assert_eq!(issued.is_some(), winners == [i], "token of racer {i}");
+    }
+
+    // A social login state is taken by exactly one of several concurrent callbacks.
+    storage
+        .save_social_login_state(&SocialLoginState::new(
+            "concurrency_state".to_string(),
+            "google".to_string(),
+        ))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let taken = join_all((0..RACERS).map(|_| storage.take_social_login_state("concurrency_state")))
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(taken.iter().flatten().count(), 1);
+
+    // Concurrent registrations of one client_id leave exactly one client.
+    let duplicate = |i: usize| {
+        let client = Client::new(
+            "concurrency_duplicate".to_string(),
+            format!("secret_{i}"),
+            vec![],
+            vec!["client_credentials".to_string()],
+            "read".to_string(),
+            format!("duplicate {i}"),
+        );
+        async move { storage.save_client(&client).await }
+    };
+    let saved = join_all((0..RACERS).map(duplicate)).await;
+    assert_eq!(saved.iter().filter(|r| r.is_ok()).count(), 1);
+    assert!(storage
+        .get_client("concurrency_duplicate", None)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_some());
+
+    Ok(())
+}
@@ -0,0 +1,172 @@
+use chrono::{Duration, Utc};
+use oauth2_core::{AuthorizationCode, Client, SocialLoginState, Token, User};
+use oauth2_ports::Storage;
+
+use crate::ContractResult;
+
+/// Expired records are still returned as stored; only counts and the revocation list
+/// leave them out. Callers decide what expiry means.
+pub(crate) async fn run(storage: &dyn Storage) -> ContractResult {
+    let client = Client::new(
+        "expiry_client".to_string(),
+        "secret".to_string(),
+        vec!["http://localhost/cb".to_string()],
+        vec!["authorization_code".to_string()],
+        "read".to_string(),
+        "expiry client".to_string(),
+    );
+    storage
+        .save_client(&client)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let tokens_before = storage
+        .count_tokens()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let active_before = storage
+        .count_active_tokens()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    // Expiry timestamps survive the roundtrip (to the second; backends differ below that).
+    let expired = Token::new(
+        "expiry_expired".to_string(),
+        Some("expiry_expired_refresh".to_string()),
+        client.client_id.clone(),
+        None,
+        "read".to_string(),
+        -1,
+    );
+    let live = Token::new(
+        "expiry_live".to_string(),
+        None,
+        client.client_id.clone(),
+        None,
+        "read".to_string(),
+        3600,
+    );
+    for token in [&expired, &live] {
+        storage
+            .save_token(token)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+    }
+
+    let fetched = storage
+        .get_token_by_access_token("expiry_expired")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("expired token should still be stored"))?;
+    assert_eq!(
+        fetched.expires_at.timestamp(),
+        expired.expires_at.timestamp()
+    );
+    assert!(fetched.is_expired());
+    assert!(!fetched.is_valid());
+    let by_refresh = storage
+        .get_token_by_refresh_token("expiry_expired_refresh")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("expired token should be found by refresh token"))?;
+    assert!(by_refresh.is_expired());
+
+    let fetched = storage
+        .get_token_by_access_token("expiry_live")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("live token should exist"))?;
+    assert_eq!(fetched.expires_at.timestamp(), live.expires_at.timestamp());
+    assert!(fetched.is_valid());
+
+    // Expired tokens count as tokens but not as active ones.
+    assert_eq!(
+        storage
+            .count_tokens()
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?,
+        tokens_before + 2
+    );
+    assert_eq!(
+        storage
+            .count_active_tokens()
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?,
+        active_before + 1
+    );
+
+    // A revoked token stays on the revocation list only while it could still be presented.
+    for access_token in ["expiry_expired", "expiry_live"] {
+        storage
+            .revoke_token(access_token)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+    }
+    let revoked = storage
+        .list_revoked_tokens()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert!(revoked.iter().any(|t| t.access_token == "expiry_live"));
+    assert!(!revoked.iter().any(|t| t.access_token == "expiry_expired"));
+    assert_eq!(
+        storage
+            .count_active_tokens()
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?,
+        active_before
+    );
+
+    // Expired authorization codes are returned unchanged; exchanging them is refused upstream.
+    let user = User::new(
+        "expiry_user".to_string(),
+        "password_hash".to_string(),
+        "expiry_user@example.com".to_string(),
+    );
+    storage
+        .save_user(&user)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let mut code = AuthorizationCode::new(
+        "expiry_code".to_string(),
+        client.client_id.clone(),
+        user.id.clone(),
+        "http://localhost/cb".to_string(),
+        "read".to_string(),
+        None,
+        None,
+    );
+    code.expires_at = Utc::now() - Duration::minutes(1);
+    storage
+        .save_authorization_code(&code)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let fetched = storage
+        .get_authorization_code("expiry_code")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("expired code should still be stored"))?;
+    assert_eq!(fetched.expires_at.timestamp(), code.expires_at.timestamp());
+    assert!(fetched.is_expired());
+    assert!(!fetched.used);
+
+    // Expired social login states are still taken exactly once.
+    let mut state = SocialLoginState::new("expiry_state".to_string(), "github".to_string());
+    state.expires_at = Utc::now() - Duration::minutes(1);
+    storage
+        .save_social_login_state(&state)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let taken = storage
+        .take_social_login_state("expiry_state")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("expired state should still be stored"))?;
+    assert!(taken.is_expired());
+    assert!(storage
+        .take_social_login_state("expiry_state")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+
+    Ok(())
+}
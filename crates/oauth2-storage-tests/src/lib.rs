@@ -0,0 +1,44 @@
+//! Contract tests for `oauth2_ports::Storage` implementations.
+//!
+//! Every backend shipped with the server (SQLx, Mongo, the in-memory fake, and the SQLx
+//! backend behind `CachedStorage`) runs this suite, so a third-party backend that passes
+//! it can be swapped in with the same behavior:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! oauth2-storage-tests = "0.1"
+//! ```
+//!
+//! ```ignore
+//! #[tokio::test]
+//! async fn my_storage_contract() -> Result<(), Box<dyn std::error::Error>> {
+//!     let storage = MyStorage::connect("...").await?;
+//!     storage
+//!         .init()
+//!         .await
+//!         .map_err(|e| std::io::Error::other(e.to_string()))?;
+//!     oauth2_storage_tests::run_storage_contract(&storage).await
+//! }
+//! ```
+
+mod concurrency;
+mod expiry;
+mod pagination;
+mod roundtrip;
+mod unicode;
+
+use oauth2_ports::Storage;
+
+/// Outcome of a contract run; assertion failures panic, storage errors are returned.
+pub type ContractResult = Result<(), Box<dyn std::error::Error>>;
+
+/// Run the full contract suite against `storage`.
+///
+/// `storage` must be initialized and empty: the suite asserts exact listings and counts.
+pub async fn run_storage_contract(storage: &dyn Storage) -> ContractResult {
+    roundtrip::run(storage).await?;
+    expiry::run(storage).await?;
+    pagination::run(storage).await?;
+    unicode::run(storage).await?;
+    concurrency::run(storage).await
+}
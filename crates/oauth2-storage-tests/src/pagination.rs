@@ -0,0 +1,144 @@
+use chrono::{Duration, Utc};
+use oauth2_core::{Client, Token, User};
+use oauth2_ports::{PageRequest, Storage, UserListQuery};
+
+use crate::ContractResult;
+
+/// Walking every page returns each record exactly once, in order, and stops with an
+/// empty page.
+pub(crate) async fn run(storage: &dyn Storage) -> ContractResult {
+    let client = Client::new(
+        "pagination_client".to_string(),
+        "secret".to_string(),
+        vec![],
+        vec!["client_credentials".to_string()],
+        "read".to_string(),
+        "pagination client".to_string(),
+    );
+    storage
+        .save_client(&client)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let user = User::new(
+        "pagination_user".to_string(),
+        "password_hash".to_string(),
+        "pagination_user@example.com".to_string(),
+    );
+    storage
+        .save_user(&user)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    // Clients: pages of one, concatenated, equal the full listing and the count.
+    let all_clients: Vec<String> = storage
+        .list_clients(PageRequest::new(1, PageRequest::MAX_PER_PAGE))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .into_iter()
+        .map(|c| c.client_id)
+        .collect();
+    let mut sorted = all_clients.clone();
+    sorted.sort();
+    assert_eq!(all_clients, sorted, "clients are listed by client_id");
+    assert_eq!(
+        storage
+            .count_clients()
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?,
+        all_clients.len() as u64
+    );
+
+    let mut paged = Vec::new();
+    for page in 1.. {
+        let clients = storage
+            .list_clients(PageRequest::new(page, 1))
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        if clients.is_empty() {
+            break;
+        }
+        assert_eq!(clients.len(), 1);
+        paged.extend(clients.into_iter().map(|c| c.client_id));
+    }
+    assert_eq!(paged, all_clients);
+
+    // Tokens: newest first per client and per user, split across pages without overlap.
+    let issued_at = Utc::now() - Duration::minutes(30);
+    let access_tokens: Vec<String> = (0..5).map(|i| format!("pagination_{i}")).collect();
+    for (i, access_token) in access_tokens.iter().enumerate() {
+        let mut token = Token::new(
+            access_token.clone(),
+            None,
+            client.client_id.clone(),
+            Some(user.id.clone()),
+            "read".to_string(),
+            3600,
+        );
+        token.created_at = issued_at + Duration::seconds(i as i64);
+        storage
+            .save_token(&token)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+    }
+    let newest_first: Vec<&str> = access_tokens.iter().rev().map(String::as_str).collect();
+
+    let mut by_client = Vec::new();
+    let mut by_user = Vec::new();
+    for page in 1..=4 {
+        let request = PageRequest::new(page, 2);
+        let client_page = storage
+            .list_tokens_by_client(&client.client_id, request)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let user_page = storage
+            .list_tokens_by_user(&user.id, request)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let expected = match page {
+            1 | 2 => 2,
+            3 => 1,
+            _ => 0,
+        };
+        assert_eq!(client_page.len(), expected, "client page {page}");
+        assert_eq!(user_page.len(), expected, "user page {page}");
+        by_client.extend(client_page.into_iter().map(|t| t.access_token));
+        by_user.extend(user_page.into_iter().map(|t| t.access_token));
+    }
+    assert_eq!(by_client, newest_first);
+    assert_eq!(by_user, newest_first);
+
+    // Users: pages partition the listing, and the count matches.
+    let all_users: Vec<String> = storage
+        .list_users(&UserListQuery {
+            search: None,
+            limit: PageRequest::MAX_PER_PAGE,
+            offset: 0,
+        })
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .into_iter()
+        .map(|u| u.id)
+        .collect();
+    assert_eq!(
+        storage
+            .count_users()
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?,
+        all_users.len() as u64
+    );
+    let mut paged = Vec::new();
+    for offset in 0..=all_users.len() {
+        let users = storage
+            .list_users(&UserListQuery {
+                search: None,
+                limit: 1,
+                offset: offset as u32,
+            })
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        paged.extend(users.into_iter().map(|u| u.id));
+    }
+    assert_eq!(paged, all_users);
+
+    Ok(())
+}
@@ -0,0 +1,1637 @@
+use oauth2_core::{
+    AuditAction, AuditOutcome, AuditRecord, AuthorizationCode, Client, ClientMetadata, EmailToken,
+    EmailTokenPurpose, FederatedIdentity, Group, OutboxMessage, Role, ServiceAccount, Token,
+    TokenEndpointAuthMethod, TokenMetadata, User, UserSession,
+};
+use oauth2_ports::{AuditQuery, PageRequest, Storage, TokenMetadataQuery, UserListQuery};
+
+use crate::ContractResult;
+
+/// Roundtrips of every record type, uniqueness, listings, cascades and transactions.
+///
+/// Asserts exact counts, so it must run first, against an empty store.
+pub(crate) async fn run(storage: &dyn Storage) -> ContractResult {
+    // Client roundtrip
+    let client = Client::new(
+        "client_1".to_string(),
+        "secret".to_string(),
+        vec!["http://localhost/cb".to_string()],
+        vec!["client_credentials".to_string()],
+        "read".to_string(),
+        "test client".to_string(),
+    );
+
+    storage
+        .save_client(&client)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let fetched = storage
+        .get_client("client_1", None)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("client should exist"))?;
+
+    assert_eq!(fetched.client_id, client.client_id);
+    assert!(!fetched.is_resource_server());
+
+    let resource_server = Client::resource_server(
+        "resource_server_1".to_string(),
+        "rs_secret".to_string(),
+        "orders api".to_string(),
+    );
+    storage
+        .save_client(&resource_server)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert!(storage
+        .get_client("resource_server_1", None)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("resource server should exist"))?
+        .is_resource_server());
+
+    // Uniqueness parity: saving the same client_id twice in a tenant should fail.
+    let dup = storage.save_client(&client).await;
+    assert!(dup.is_err(), "saving the same client_id twice should fail");
+
+    // Managed clients are listed by their reconciler only.
+    let mut managed = Client::new(
+        "managed_client".to_string(),
+        "secret".to_string(),
+        vec![],
+        vec!["client_credentials".to_string()],
+        "read".to_string(),
+        "managed client".to_string(),
+    );
+    managed.managed_by = Some("gitops".to_string());
+    storage
+        .save_client(&managed)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let listed = storage
+        .list_clients_managed_by("gitops")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(
+        listed
+            .iter()
+            .map(|c| c.client_id.as_str())
+            .collect::<Vec<_>>(),
+        ["managed_client"]
+    );
+    assert_eq!(listed[0].managed_by.as_deref(), Some("gitops"));
+    assert!(storage
+        .list_clients_managed_by("someone_else")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_empty());
+
+    // User roundtrip
+    let user = User::new(
+        "user_1".to_string(),
+        "password_hash".to_string(),
+        "user_1@example.com".to_string(),
+    );
+
+    storage
+        .save_user(&user)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let fetched_user = storage
+        .get_user_by_username("user_1", None)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("user should exist"))?;
+
+    assert_eq!(fetched_user.username, user.username);
+
+    // Token roundtrip + revoke
+    let token = Token::new(
+        "access_token_1".to_string(),
+        Some("refresh_token_1".to_string()),
+        client.client_id.clone(),
+        None,
+        "read".to_string(),
+        3600,
+    )
+    .with_context_binding(Some("agent_hash.network_hash".to_string()));
+
+    storage
+        .save_token(&token)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let fetched_token = storage
+        .get_token_by_access_token("access_token_1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("token should exist"))?;
+
+    assert!(!fetched_token.revoked);
+    assert_eq!(
+        fetched_token.context_binding.as_deref(),
+        Some("agent_hash.network_hash")
+    );
+
+    storage
+        .revoke_token("access_token_1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let revoked_token = storage
+        .get_token_by_access_token("access_token_1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("token should still exist"))?;
+
+    assert!(revoked_token.revoked);
+
+    // Revocation list: revoked tokens only, and only until they expire.
+    let expired = Token::new(
+        "access_token_expired".to_string(),
+        None,
+        client.client_id.clone(),
+        None,
+        "read".to_string(),
+        -60,
+    );
+    storage
+        .save_token(&expired)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    storage
+        .revoke_token("access_token_expired")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let revoked = storage
+        .list_revoked_tokens()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(
+        revoked
+            .iter()
+            .map(|t| t.access_token.as_str())
+            .collect::<Vec<_>>(),
+        ["access_token_1"]
+    );
+
+    // Parity check: multiple tokens with no refresh token should be allowed.
+    // (SQL UNIQUE allows multiple NULLs; Mongo requires omitting the field to match that behavior.)
+    let no_refresh_1 = Token::new(
+        "access_token_no_refresh_1".to_string(),
+        None,
+        client.client_id.clone(),
+        None,
+        "read".to_string(),
+        3600,
+    );
+
+    storage
+        .save_token(&no_refresh_1)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let no_refresh_2 = Token::new(
+        "access_token_no_refresh_2".to_string(),
+        None,
+        client.client_id.clone(),
+        None,
+        "read".to_string(),
+        3600,
+    );
+
+    storage
+        .save_token(&no_refresh_2)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    // Uniqueness parity: saving the same access_token twice should fail.
+    let dup_access = storage.save_token(&no_refresh_2).await;
+    assert!(
+        dup_access.is_err(),
+        "saving the same access_token twice should fail"
+    );
+
+    // Grant revocation: lookup by refresh token, then revoke every token of the grant.
+    let grant_root = Token::new(
+        "grant_access_1".to_string(),
+        Some("grant_refresh_1".to_string()),
+        client.client_id.clone(),
+        None,
+        "read".to_string(),
+        3600,
+    );
+    let grant_child = Token::new(
+        "grant_access_2".to_string(),
+        None,
+        client.client_id.clone(),
+        None,
+        "read".to_string(),
+        3600,
+    )
+    .with_grant_id(grant_root.grant_id());
+    let unrelated = Token::new(
+        "grant_access_other".to_string(),
+        None,
+        client.client_id.clone(),
+        None,
+        "read".to_string(),
+        3600,
+    );
+
+    for t in [&grant_root, &grant_child, &unrelated] {
+        storage
+            .save_token(t)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+    }
+
+    let by_refresh = storage
+        .get_token_by_refresh_token("grant_refresh_1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("token should be found by refresh token"))?;
+    assert_eq!(by_refresh.access_token, "grant_access_1");
+    assert_eq!(by_refresh.grant_id(), grant_root.grant_id());
+
+    storage
+        .revoke_token_grant(by_refresh.grant_id())
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    for (access_token, expect_revoked) in [
+        ("grant_access_1", true),
+        ("grant_access_2", true),
+        ("grant_access_other", false),
+    ] {
+        let t = storage
+            .get_token_by_access_token(access_token)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?
+            .ok_or_else(|| std::io::Error::other("token should still exist"))?;
+        assert_eq!(t.revoked, expect_revoked, "{access_token}");
+    }
+
+    // Metadata tags: persisted, queryable per key/value, and revocable as a set.
+    let tagged = |access_token: &str, tags: &[(&str, &str)]| {
+        Token::new(
+            access_token.to_string(),
+            None,
+            client.client_id.clone(),
+            None,
+            "read".to_string(),
+            3600,
+        )
+        .with_metadata(tags.iter().copied().collect::<TokenMetadata>())
+    };
+    for t in [
+        tagged(
+            "tagged_canary_1",
+            &[("deployment", "canary"), ("app.tier", "web")],
+        ),
+        tagged("tagged_canary_2", &[("deployment", "canary")]),
+        tagged(
+            "tagged_stable",
+            &[("deployment", "stable"), ("app.tier", "web")],
+        ),
+    ] {
+        storage
+            .save_token(&t)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+    }
+
+    let roundtrip = storage
+        .get_token_by_access_token("tagged_canary_1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("tagged token should exist"))?;
+    assert_eq!(roundtrip.metadata.get("deployment"), Some("canary"));
+    assert_eq!(roundtrip.metadata.get("app.tier"), Some("web"));
+
+    let canary = storage
+        .find_tokens_by_metadata(&TokenMetadataQuery::new("deployment", "canary"))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let mut found: Vec<_> = canary.iter().map(|t| t.access_token.as_str()).collect();
+    found.sort();
+    assert_eq!(found, ["tagged_canary_1", "tagged_canary_2"]);
+
+    // Dotted keys are matched literally.
+    let web_tier = storage
+        .find_tokens_by_metadata(&TokenMetadataQuery::new("app.tier", "web"))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(web_tier.len(), 2);
+
+    let mut page_query = TokenMetadataQuery::new("deployment", "canary");
+    page_query.limit = 1;
+    page_query.offset = 1;
+    let page = storage
+        .find_tokens_by_metadata(&page_query)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(page.len(), 1);
+
+    let revoked = storage
+        .revoke_tokens_by_metadata("deployment", "canary")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(revoked, 2);
+    let revoked_again = storage
+        .revoke_tokens_by_metadata("deployment", "canary")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(revoked_again, 0, "already revoked tokens are not counted");
+
+    for (access_token, expect_revoked) in [
+        ("tagged_canary_1", true),
+        ("tagged_canary_2", true),
+        ("tagged_stable", false),
+    ] {
+        let t = storage
+            .get_token_by_access_token(access_token)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?
+            .ok_or_else(|| std::io::Error::other("token should still exist"))?;
+        assert_eq!(t.revoked, expect_revoked, "{access_token}");
+    }
+
+    // Listing and counting: clients by client_id, tokens newest first.
+    let all_clients = storage
+        .list_clients(PageRequest::new(1, PageRequest::MAX_PER_PAGE))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let client_ids: Vec<&str> = all_clients.iter().map(|c| c.client_id.as_str()).collect();
+    assert_eq!(
+        client_ids,
+        ["client_1", "managed_client", "resource_server_1"]
+    );
+    assert_eq!(
+        storage
+            .count_clients()
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?,
+        3
+    );
+    let second = storage
+        .list_clients(PageRequest::new(2, 2))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(second.len(), 1);
+    assert_eq!(second[0].client_id, "resource_server_1");
+
+    let tokens_before = storage
+        .count_tokens()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let active_before = storage
+        .count_active_tokens()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let issued_at = chrono::Utc::now() + chrono::Duration::minutes(10);
+    for (i, access_token) in ["listed_older", "listed_newer"].into_iter().enumerate() {
+        let mut t = Token::new(
+            access_token.to_string(),
+            None,
+            "managed_client".to_string(),
+            Some(user.id.clone()),
+            "read".to_string(),
+            3600,
+        );
+        t.created_at = issued_at + chrono::Duration::seconds(i as i64);
+        storage
+            .save_token(&t)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+    }
+    assert_eq!(
+        storage
+            .count_tokens()
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?,
+        tokens_before + 2
+    );
+    assert_eq!(
+        storage
+            .count_active_tokens()
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?,
+        active_before + 2
+    );
+
+    let by_client = storage
+        .list_tokens_by_client("managed_client", PageRequest::default())
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(
+        by_client
+            .iter()
+            .map(|t| t.access_token.as_str())
+            .collect::<Vec<_>>(),
+        ["listed_newer", "listed_older"]
+    );
+    let by_user = storage
+        .list_tokens_by_user(&user.id, PageRequest::new(2, 1))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(
+        by_user
+            .iter()
+            .map(|t| t.access_token.as_str())
+            .collect::<Vec<_>>(),
+        ["listed_older"]
+    );
+
+    storage
+        .revoke_token("listed_older")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(
+        storage
+            .count_active_tokens()
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?,
+        active_before + 1
+    );
+
+    // Service account roundtrip; deleting the backing client removes the account.
+    let sa_client = Client::new(
+        "sa_client_1".to_string(),
+        "secret".to_string(),
+        vec![],
+        vec!["client_credentials".to_string()],
+        "metrics:read".to_string(),
+        "exporter".to_string(),
+    );
+    storage
+        .save_client(&sa_client)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let mut account = ServiceAccount::new(
+        "exporter".to_string(),
+        sa_client.client_id.clone(),
+        "metrics:read".to_string(),
+    )
+    .with_description(Some("metrics exporter".to_string()));
+    storage
+        .save_service_account(&account)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let fetched_account = storage
+        .get_service_account_by_client_id("sa_client_1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("service account should exist"))?;
+    assert_eq!(fetched_account.id, account.id);
+    assert_eq!(
+        fetched_account.description.as_deref(),
+        Some("metrics exporter")
+    );
+    assert!(fetched_account.public_key.is_none());
+
+    account.enabled = false;
+    account.scope = "metrics:read metrics:write".to_string();
+    storage
+        .update_service_account(&account)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let updated_account = storage
+        .get_service_account(&account.id)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("service account should exist"))?;
+    assert!(!updated_account.enabled);
+    assert_eq!(updated_account.scope, "metrics:read metrics:write");
+
+    let listed = storage
+        .list_service_accounts()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(listed.len(), 1);
+
+    storage
+        .delete_client("sa_client_1", None)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let deleted_account = storage
+        .get_service_account(&account.id)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert!(deleted_account.is_none());
+
+    // Authorization code roundtrip + mark used
+    let code = AuthorizationCode::new(
+        "code_1".to_string(),
+        client.client_id.clone(),
+        user.id.clone(),
+        "http://localhost/cb".to_string(),
+        "read".to_string(),
+        None,
+        None,
+    );
+
+    storage
+        .save_authorization_code(&code)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let fetched_code = storage
+        .get_authorization_code("code_1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("auth code should exist"))?;
+
+    assert!(!fetched_code.used);
+
+    storage
+        .mark_authorization_code_used("code_1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let used_code = storage
+        .get_authorization_code("code_1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("auth code should exist"))?;
+
+    assert!(used_code.used);
+
+    // Transactions: consume a code and save its token together, or not at all
+    let code = AuthorizationCode::new(
+        "code_tx".to_string(),
+        client.client_id.clone(),
+        user.id.clone(),
+        "http://localhost/cb".to_string(),
+        "read".to_string(),
+        None,
+        None,
+    );
+    storage
+        .save_authorization_code(&code)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let tx_token = Token::new(
+        "access_tx".to_string(),
+        Some("refresh_tx".to_string()),
+        client.client_id.clone(),
+        Some(user.id.clone()),
+        "read".to_string(),
+        3600,
+    );
+
+    let mut tx = storage
+        .begin_transaction()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    tx.consume_authorization_code("code_tx")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    tx.save_token(&tx_token)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    tx.rollback()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let code_after_rollback = storage
+        .get_authorization_code("code_tx")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("auth code should exist"))?;
+    assert!(!code_after_rollback.used);
+    assert!(storage
+        .get_token_by_access_token("access_tx")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+
+    let mut tx = storage
+        .begin_transaction()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    tx.consume_authorization_code("code_tx")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    tx.save_token(&tx_token)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    tx.commit()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let consumed = storage
+        .get_authorization_code("code_tx")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("auth code should exist"))?;
+    assert!(consumed.used);
+    assert!(storage
+        .get_token_by_access_token("access_tx")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_some());
+
+    // A used (or unknown) code cannot be consumed again.
+    let mut tx = storage
+        .begin_transaction()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let replay = tx.consume_authorization_code("code_tx").await;
+    assert_eq!(
+        replay.map_err(|e| e.error).err().as_deref(),
+        Some("invalid_grant")
+    );
+    tx.rollback()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let mut tx = storage
+        .begin_transaction()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    tx.revoke_token("refresh_tx")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    tx.commit()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let revoked_tx_token = storage
+        .get_token_by_access_token("access_tx")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("token should exist"))?;
+    assert!(revoked_tx_token.revoked);
+
+    // User listing, search, update, and delete
+    let other_user = User::new(
+        "Other_User".to_string(),
+        "password_hash".to_string(),
+        "other@example.org".to_string(),
+    );
+    storage
+        .save_user(&other_user)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let all_users = storage
+        .list_users(&UserListQuery::default())
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(all_users.len(), 2);
+    assert_eq!(
+        storage
+            .count_users()
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?,
+        2
+    );
+
+    let page = storage
+        .list_users(&UserListQuery {
+            search: None,
+            limit: 1,
+            offset: 1,
+        })
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(page.len(), 1);
+
+    // Search is case-insensitive and matches username or email.
+    let by_username = storage
+        .list_users(&UserListQuery {
+            search: Some("other_u".to_string()),
+            ..UserListQuery::default()
+        })
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(by_username.len(), 1);
+    assert_eq!(by_username[0].id, other_user.id);
+
+    let by_email = storage
+        .list_users(&UserListQuery {
+            search: Some("example.com".to_string()),
+            ..UserListQuery::default()
+        })
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(by_email.len(), 1);
+    assert_eq!(by_email[0].id, user.id);
+
+    // Wildcards in the search term are matched literally.
+    let wildcard = storage
+        .list_users(&UserListQuery {
+            search: Some("%".to_string()),
+            ..UserListQuery::default()
+        })
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert!(wildcard.is_empty());
+
+    // Email lookups match the whole address, ignoring case.
+    let same_email = storage
+        .list_users_by_email("OTHER@example.org")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(same_email.len(), 1);
+    assert_eq!(same_email[0].id, other_user.id);
+    assert!(storage
+        .list_users_by_email("example.org")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_empty());
+
+    let mut disabled = other_user.clone();
+    disabled.enabled = false;
+    disabled.email_verified = true;
+    disabled.password_hash = "new_password_hash".to_string();
+    storage
+        .update_user(&disabled)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let updated = storage
+        .get_user(&other_user.id)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("user should exist"))?;
+    assert!(!updated.enabled);
+    assert!(updated.email_verified);
+    assert_eq!(updated.password_hash, "new_password_hash");
+
+    // Email tokens are taken exactly once, and deleted per user and purpose.
+    let (_, verify) = EmailToken::issue(&other_user, EmailTokenPurpose::VerifyEmail, 3600);
+    let (_, reset) = EmailToken::issue(&other_user, EmailTokenPurpose::ResetPassword, 3600);
+    for token in [&verify, &reset] {
+        storage
+            .save_email_token(token)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+    }
+    assert!(storage.save_email_token(&verify).await.is_err());
+    storage
+        .delete_email_tokens(&other_user.id, EmailTokenPurpose::ResetPassword)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert!(storage
+        .take_email_token(&reset.token_hash)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+    let taken = storage
+        .take_email_token(&verify.token_hash)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("email token should exist"))?;
+    assert_eq!(taken.user_id, other_user.id);
+    assert_eq!(taken.purpose, EmailTokenPurpose::VerifyEmail);
+    assert_eq!(taken.email, other_user.email);
+    assert!(!taken.is_expired());
+    assert!(storage
+        .take_email_token(&verify.token_hash)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+    let (_, mut magic_link) = EmailToken::issue(&other_user, EmailTokenPurpose::MagicLink, 600);
+    magic_link.return_to = Some("/oauth/authorize?client_id=app&state=xyz".to_string());
+    storage
+        .save_email_token(&magic_link)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let taken = storage
+        .take_email_token(&magic_link.token_hash)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("email token should exist"))?;
+    assert_eq!(taken.purpose, EmailTokenPurpose::MagicLink);
+    assert_eq!(taken.return_to, magic_link.return_to);
+
+    // Browser sessions are upserted, touched, listed per user while unexpired, and deleted.
+    let started = chrono::Utc::now();
+    let session = |id: &str, user_id: Option<&str>, offset_secs: i64| UserSession {
+        id: id.to_string(),
+        user_id: user_id.map(str::to_string),
+        state: "{}".to_string(),
+        created_at: started + chrono::Duration::seconds(offset_secs),
+        last_seen_at: started,
+        idle_expires_at: started + chrono::Duration::minutes(30),
+        expires_at: started + chrono::Duration::hours(12),
+    };
+    let anonymous = session("session_anonymous", None, 0);
+    let older = session("session_older", Some(&other_user.id), 1);
+    let newer = session("session_newer", Some(&other_user.id), 2);
+    let mut idle = session("session_idle", Some(&other_user.id), 3);
+    idle.idle_expires_at = started - chrono::Duration::minutes(1);
+    for record in [&anonymous, &older, &newer, &idle] {
+        storage
+            .save_user_session(record)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+    }
+
+    let mut signed_in = anonymous.clone();
+    signed_in.user_id = Some(other_user.id.clone());
+    signed_in.state = r#"{"user_id":"\"other_user\""}"#.to_string();
+    storage
+        .save_user_session(&signed_in)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let loaded = storage
+        .get_user_session(&anonymous.id)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("session should exist"))?;
+    assert_eq!(loaded.user_id, signed_in.user_id);
+    assert_eq!(loaded.state, signed_in.state);
+    assert!(!loaded.is_expired());
+
+    let seen_at = started + chrono::Duration::minutes(5);
+    storage
+        .touch_user_session(&older.id, seen_at, seen_at + chrono::Duration::minutes(30))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let touched = storage
+        .get_user_session(&older.id)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("session should exist"))?;
+    assert!(touched.last_seen_at > older.last_seen_at);
+    assert!(touched.idle_expires_at > older.idle_expires_at);
+
+    let listed: Vec<String> = storage
+        .list_user_sessions(&other_user.id)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .into_iter()
+        .map(|record| record.id)
+        .collect();
+    assert_eq!(
+        listed,
+        ["session_newer", "session_older", "session_anonymous"]
+    );
+
+    storage
+        .delete_user_session(&newer.id)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert!(storage
+        .get_user_session(&newer.id)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+    for record in [&older, &signed_in] {
+        storage
+            .delete_user_session(&record.id)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+    }
+
+    // Audit records are listed newest first, filtered by time range, actor and action.
+    let audited_at = chrono::Utc::now() - chrono::Duration::hours(1);
+    let audit = |id: &str, action: AuditAction, actor: &str, offset_mins: i64| AuditRecord {
+        id: id.to_string(),
+        occurred_at: audited_at + chrono::Duration::minutes(offset_mins),
+        ..AuditRecord::new(action, AuditOutcome::Success).with_actor(actor)
+    };
+    let login = audit("audit_login", AuditAction::Login, &user.id, 0);
+    let consent = audit("audit_consent", AuditAction::Consent, &user.id, 10)
+        .with_client(client.client_id.clone());
+    let mut token = audit("audit_token", AuditAction::TokenIssued, &user.id, 20)
+        .with_client(client.client_id.clone())
+        .with_target("authorization_code")
+        .with_detail("invalid_grant");
+    token.outcome = AuditOutcome::Failure;
+    token.ip_address = Some("203.0.113.7".to_string());
+    token.user_agent = Some("contract-test".to_string());
+    let admin = audit("audit_admin", AuditAction::AdminChange, "operator", 30)
+        .with_target("POST /admin/clients");
+    for record in [&login, &consent, &token, &admin] {
+        storage
+            .save_audit_record(record)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+    }
+    let audit_ids = |records: Vec<AuditRecord>| -> Vec<String> {
+        records.into_iter().map(|record| record.id).collect()
+    };
+
+    let all = storage
+        .list_audit_records(&AuditQuery::default())
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let loaded = all
+        .iter()
+        .find(|record| record.id == token.id)
+        .ok_or_else(|| std::io::Error::other("audit record should exist"))?;
+    assert_eq!(loaded.outcome, AuditOutcome::Failure);
+    assert_eq!(loaded.client_id, token.client_id);
+    assert_eq!(loaded.detail, token.detail);
+    assert_eq!(loaded.ip_address, token.ip_address);
+    assert_eq!(loaded.user_agent, token.user_agent);
+    assert_eq!(loaded.target, token.target);
+    assert_eq!(
+        audit_ids(all),
+        ["audit_admin", "audit_token", "audit_consent", "audit_login"]
+    );
+    let by_actor = storage
+        .list_audit_records(&AuditQuery {
+            actor: Some(user.id.clone()),
+            limit: 2,
+            ..AuditQuery::default()
+        })
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(audit_ids(by_actor), ["audit_token", "audit_consent"]);
+    let in_range = storage
+        .list_audit_records(&AuditQuery {
+            since: Some(consent.occurred_at),
+            until: Some(admin.occurred_at),
+            ..AuditQuery::default()
+        })
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(audit_ids(in_range), ["audit_token", "audit_consent"]);
+    let by_action = storage
+        .list_audit_records(&AuditQuery {
+            action: Some(AuditAction::AdminChange),
+            ..AuditQuery::default()
+        })
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(audit_ids(by_action), ["audit_admin"]);
+
+    // Outbox messages are claimed oldest first, leased, retried and deleted; an enqueue
+    // inside a transaction lands with it.
+    let outboxed_at = chrono::Utc::now();
+    let outbox = |id: &str, offset_mins: i64| OutboxMessage {
+        created_at: outboxed_at - chrono::Duration::minutes(offset_mins),
+        next_attempt_at: outboxed_at - chrono::Duration::minutes(offset_mins),
+        ..OutboxMessage::new(
+            id.to_string(),
+            "token_created".to_string(),
+            format!(r#"{{"id":"{id}"}}"#),
+        )
+    };
+    storage
+        .enqueue_outbox_message(&outbox("outbox_1", 2))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert!(storage
+        .enqueue_outbox_message(&outbox("outbox_1", 2))
+        .await
+        .is_err());
+    let mut tx = storage
+        .begin_transaction()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    tx.enqueue_outbox_message(&outbox("outbox_rolled_back", 3))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    tx.rollback()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let mut tx = storage
+        .begin_transaction()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    tx.enqueue_outbox_message(&outbox("outbox_2", 1))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    tx.commit()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let lease_until = outboxed_at + chrono::Duration::minutes(1);
+    let outbox_ids = |messages: Vec<OutboxMessage>| -> Vec<String> {
+        messages.into_iter().map(|message| message.id).collect()
+    };
+    let claimed = storage
+        .claim_outbox_messages(outboxed_at, lease_until, 10)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(claimed[0].payload, r#"{"id":"outbox_1"}"#);
+    assert_eq!(outbox_ids(claimed), ["outbox_1", "outbox_2"]);
+    // Leased messages are not claimed again until the lease runs out.
+    assert!(storage
+        .claim_outbox_messages(outboxed_at, lease_until, 10)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_empty());
+
+    storage
+        .reschedule_outbox_message(
+            "outbox_1",
+            outboxed_at - chrono::Duration::seconds(5),
+            "broker unavailable",
+        )
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let retried = storage
+        .claim_outbox_messages(outboxed_at, lease_until, 10)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(retried.len(), 1);
+    assert_eq!(retried[0].id, "outbox_1");
+    assert_eq!(retried[0].attempts, 1);
+    assert_eq!(retried[0].last_error.as_deref(), Some("broker unavailable"));
+
+    let expired = outboxed_at + chrono::Duration::minutes(2);
+    let reclaimed = storage
+        .claim_outbox_messages(expired, expired + chrono::Duration::minutes(1), 1)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(outbox_ids(reclaimed), ["outbox_1"]);
+    for id in ["outbox_1", "outbox_2"] {
+        storage
+            .delete_outbox_message(id)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+    }
+    assert!(storage
+        .claim_outbox_messages(expired + chrono::Duration::hours(1), expired, 10)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_empty());
+
+    // Federated identities are unique per provider account and listed per user.
+    for (provider, subject) in [("google", "g-1"), ("github", "gh-1")] {
+        storage
+            .save_federated_identity(&FederatedIdentity::new(
+                provider.to_string(),
+                subject.to_string(),
+                user.id.clone(),
+            ))
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+    }
+    assert!(storage
+        .save_federated_identity(&FederatedIdentity::new(
+            "github".to_string(),
+            "gh-1".to_string(),
+            other_user.id.clone(),
+        ))
+        .await
+        .is_err());
+    let linked = storage
+        .get_federated_identity("github", "gh-1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("federated identity should exist"))?;
+    assert_eq!(linked.user_id, user.id);
+    assert!(storage
+        .get_federated_identity("gitlab", "gh-1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+    let identities = storage
+        .list_federated_identities(&user.id)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let providers: Vec<_> = identities.iter().map(|i| i.provider.as_str()).collect();
+    assert_eq!(providers, ["github", "google"]);
+
+    // Provider tokens of a link are replaced in place.
+    let mut with_tokens = linked.clone();
+    with_tokens.access_token = Some("sealed-access".to_string());
+    with_tokens.refresh_token = Some("sealed-refresh".to_string());
+    with_tokens.token_expires_at = Some(chrono::Utc::now() + chrono::Duration::hours(1));
+    storage
+        .update_federated_identity_tokens(&with_tokens)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let refetched = storage
+        .get_federated_identity("github", "gh-1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("federated identity should exist"))?;
+    assert_eq!(refetched.user_id, user.id);
+    assert_eq!(refetched.access_token.as_deref(), Some("sealed-access"));
+    assert_eq!(refetched.refresh_token.as_deref(), Some("sealed-refresh"));
+    assert_eq!(
+        refetched.token_expires_at.map(|at| at.timestamp()),
+        with_tokens.token_expires_at.map(|at| at.timestamp())
+    );
+    storage
+        .delete_federated_identity("google", "g-1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(
+        storage
+            .list_federated_identities(&user.id)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?
+            .len(),
+        1
+    );
+
+    // Roles and groups are unique by name; assignments are replaced as a whole.
+    for name in ["editor", "admin"] {
+        storage
+            .save_role(&Role::new(name.to_string(), Some(format!("{name} role"))))
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+    }
+    assert!(storage
+        .save_role(&Role::new("admin".to_string(), None))
+        .await
+        .is_err());
+    let roles = storage
+        .list_roles()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let names: Vec<_> = roles.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(names, ["admin", "editor"]);
+    assert_eq!(roles[0].description.as_deref(), Some("admin role"));
+
+    storage
+        .save_group(&Group::new("staff".to_string(), None))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert!(storage
+        .save_group(&Group::new("staff".to_string(), None))
+        .await
+        .is_err());
+
+    let assigned = ["editor".to_string(), "admin".to_string()];
+    storage
+        .set_user_roles(&user.id, &assigned)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    storage
+        .set_user_roles(&other_user.id, &assigned[..1])
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    storage
+        .set_user_groups(&user.id, &["staff".to_string()])
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(
+        storage
+            .list_user_roles(&user.id)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?,
+        ["admin", "editor"]
+    );
+    assert_eq!(
+        storage
+            .list_user_groups(&user.id)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?,
+        ["staff"]
+    );
+
+    // Deleting a role removes it from every user.
+    storage
+        .delete_role("editor")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(
+        storage
+            .list_user_roles(&user.id)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?,
+        ["admin"]
+    );
+    assert!(storage
+        .list_user_roles(&other_user.id)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_empty());
+
+    let (_, user_reset) = EmailToken::issue(&user, EmailTokenPurpose::ResetPassword, 3600);
+    storage
+        .save_email_token(&user_reset)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let now = chrono::Utc::now();
+    let user_session = UserSession {
+        id: "session_user".to_string(),
+        user_id: Some(user.id.clone()),
+        state: "{}".to_string(),
+        created_at: now,
+        last_seen_at: now,
+        idle_expires_at: now + chrono::Duration::minutes(30),
+        expires_at: now + chrono::Duration::hours(12),
+    };
+    storage
+        .save_user_session(&user_session)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    // Deleting a user also removes the authorization codes, identities, roles, group
+    // memberships, email tokens and browser sessions that reference it.
+    storage
+        .delete_user(&user.id)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    assert!(storage
+        .get_user(&user.id)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+    assert!(storage
+        .get_authorization_code("code_1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+    assert!(storage
+        .get_federated_identity("github", "gh-1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+    assert!(storage
+        .take_email_token(&user_reset.token_hash)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+    assert!(storage
+        .get_user_session(&user_session.id)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+    assert!(storage
+        .list_user_roles(&user.id)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_empty());
+    assert!(storage
+        .list_user_groups(&user.id)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_empty());
+    // The audit log outlives the user it names.
+    assert_eq!(
+        storage
+            .list_audit_records(&AuditQuery {
+                actor: Some(user.id.clone()),
+                ..AuditQuery::default()
+            })
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?
+            .len(),
+        3
+    );
+
+    // Deleting a group keeps the roles.
+    storage
+        .delete_group("staff")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert!(storage
+        .list_groups()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_empty());
+    assert_eq!(
+        storage
+            .list_roles()
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?
+            .len(),
+        1
+    );
+
+    // Client update persists mutable metadata and the registration token hash.
+    let mut updated_client = fetched.clone();
+    updated_client.name = "renamed client".to_string();
+    updated_client.scope = "read write".to_string();
+    updated_client.set_registration_access_token("registration_token");
+    let effective_at = chrono::Utc::now() + chrono::Duration::hours(1);
+    updated_client.stage_redirect_uris(&["https://new.example/cb".to_string()], Some(effective_at));
+    let metadata = ClientMetadata {
+        logo_uri: Some("https://client.example/logo.png".to_string()),
+        client_uri: Some("https://client.example".to_string()),
+        policy_uri: Some("https://client.example/privacy".to_string()),
+        tos_uri: Some("https://client.example/tos".to_string()),
+        contacts: vec!["ops@client.example".to_string()],
+        jwks_uri: Some("https://client.example/jwks.json".to_string()),
+        token_endpoint_auth_method: Some(TokenEndpointAuthMethod::ClientSecretPost),
+        allowed_origins: vec!["https://client.example".to_string()],
+    };
+    updated_client.set_metadata(metadata.clone());
+    storage
+        .update_client(&updated_client)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let refetched = storage
+        .get_client("client_1", None)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("client should exist"))?;
+    assert_eq!(refetched.name, "renamed client");
+    assert_eq!(refetched.scope, "read write");
+    assert!(refetched.verify_registration_access_token("registration_token"));
+    assert_eq!(
+        refetched.get_pending_redirect_uris(),
+        Some(vec!["https://new.example/cb".to_string()])
+    );
+    assert_eq!(
+        refetched.pending_redirect_uris_at.map(|at| at.timestamp()),
+        Some(effective_at.timestamp())
+    );
+    assert_eq!(refetched.metadata(), metadata);
+
+    // Social login state is consumed exactly once.
+    let login_state =
+        oauth2_core::SocialLoginState::new("state_1".to_string(), "google".to_string())
+            .with_pkce_verifier("verifier".to_string())
+            .with_nonce("nonce".to_string())
+            .with_browser_binding("browser");
+    storage
+        .save_social_login_state(&login_state)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let taken = storage
+        .take_social_login_state("state_1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("social login state should exist"))?;
+    assert_eq!(taken.provider, "google");
+    assert_eq!(taken.pkce_verifier.as_deref(), Some("verifier"));
+    assert_eq!(taken.nonce.as_deref(), Some("nonce"));
+    assert!(taken.is_bound_to("browser"));
+    assert!(!taken.is_bound_to("another-browser"));
+    assert!(!taken.is_expired());
+    assert!(storage
+        .take_social_login_state("state_1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+
+    // Tenants are listed by id, and tenant-scoped records keep their tenant.
+    for id in ["beta", "acme"] {
+        storage
+            .save_tenant(&oauth2_core::Tenant::new(
+                id.to_string(),
+                format!("{id} corp"),
+                format!("{id}_signing_secret"),
+            ))
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+    }
+    let tenant = storage
+        .get_tenant("acme")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("tenant should exist"))?;
+    assert_eq!(tenant.name, "acme corp");
+    assert_eq!(tenant.signing_secret, "acme_signing_secret");
+    assert!(tenant.enabled);
+    assert!(storage
+        .get_tenant("missing")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+    assert_eq!(
+        storage
+            .list_tenants()
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?
+            .iter()
+            .map(|t| t.id.as_str())
+            .collect::<Vec<_>>(),
+        ["acme", "beta"]
+    );
+
+    let mut tenant_client = Client::new(
+        "tenant_client".to_string(),
+        "secret".to_string(),
+        vec![],
+        vec!["client_credentials".to_string()],
+        "read".to_string(),
+        "tenant client".to_string(),
+    );
+    tenant_client.tenant_id = Some("acme".to_string());
+    storage
+        .save_client(&tenant_client)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let fetched = storage
+        .get_client("tenant_client", Some("acme"))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("tenant client should exist"))?;
+    assert_eq!(fetched.tenant_id.as_deref(), Some("acme"));
+    assert!(fetched.in_tenant(Some("acme")));
+    assert!(!fetched.in_tenant(None));
+
+    let mut tenant_user = User::new(
+        "tenant_user".to_string(),
+        "password_hash".to_string(),
+        "tenant_user@example.com".to_string(),
+    );
+    tenant_user.tenant_id = Some("acme".to_string());
+    storage
+        .save_user(&tenant_user)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let fetched = storage
+        .get_user_by_username("tenant_user", Some("acme"))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("tenant user should exist"))?;
+    assert_eq!(fetched.tenant_id.as_deref(), Some("acme"));
+
+    let tenant_token = Token::new(
+        "tenant_access_token".to_string(),
+        None,
+        tenant_client.client_id.clone(),
+        None,
+        "read".to_string(),
+        3600,
+    )
+    .with_tenant_id(Some("acme".to_string()));
+    storage
+        .save_token(&tenant_token)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let fetched = storage
+        .get_token_by_access_token("tenant_access_token")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("tenant token should exist"))?;
+    assert_eq!(fetched.tenant_id.as_deref(), Some("acme"));
+
+    // Client ids and usernames are unique per tenant: another tenant may reuse them.
+    let acme_client = |name: &str| {
+        let mut client = Client::new(
+            "client_1".to_string(),
+            "secret".to_string(),
+            vec![],
+            vec!["client_credentials".to_string()],
+            "read".to_string(),
+            name.to_string(),
+        );
+        client.tenant_id = Some("acme".to_string());
+        client
+    };
+    storage
+        .save_client(&acme_client("acme client"))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert!(
+        storage
+            .save_client(&acme_client("acme copy"))
+            .await
+            .is_err(),
+        "a tenant cannot have the same client_id twice"
+    );
+    let fetched = storage
+        .get_client("client_1", Some("acme"))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("acme client_1 should exist"))?;
+    assert_eq!(fetched.name, "acme client");
+    let fetched = storage
+        .get_client("client_1", None)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("default client_1 should exist"))?;
+    assert_eq!(fetched.tenant_id, None);
+    assert!(storage
+        .get_client("tenant_client", None)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+
+    let tenant_user_named = |tenant_id: Option<&str>| {
+        let mut user = User::new(
+            "tenant_user".to_string(),
+            "password_hash".to_string(),
+            "tenant_user@example.com".to_string(),
+        );
+        user.tenant_id = tenant_id.map(str::to_string);
+        user
+    };
+    let default_tenant_user = tenant_user_named(None);
+    storage
+        .save_user(&default_tenant_user)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert!(
+        storage
+            .save_user(&tenant_user_named(Some("acme")))
+            .await
+            .is_err(),
+        "a tenant cannot have the same username twice"
+    );
+    let fetched = storage
+        .get_user_by_username("tenant_user", Some("acme"))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("acme tenant_user should exist"))?;
+    assert_eq!(fetched.id, tenant_user.id);
+    let fetched = storage
+        .get_user_by_username("tenant_user", None)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("default tenant_user should exist"))?;
+    assert_eq!(fetched.id, default_tenant_user.id);
+    assert!(storage
+        .get_user_by_username("tenant_user", Some("beta"))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+
+    // Deleting a tenant's client leaves the other tenants' client of that id alone.
+    storage
+        .save_token(
+            &Token::new(
+                "acme_access_token_1".to_string(),
+                None,
+                "client_1".to_string(),
+                None,
+                "read".to_string(),
+                3600,
+            )
+            .with_tenant_id(Some("acme".to_string())),
+        )
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let code_of = |code: &str, user_id: &str, tenant_id: Option<&str>| {
+        AuthorizationCode::new(
+            code.to_string(),
+            "client_1".to_string(),
+            user_id.to_string(),
+            "http://localhost/cb".to_string(),
+            "read".to_string(),
+            None,
+            None,
+        )
+        .with_tenant_id(tenant_id.map(str::to_string))
+    };
+    for code in [
+        code_of("acme_code_1", &tenant_user.id, Some("acme")),
+        code_of("default_code_1", &default_tenant_user.id, None),
+    ] {
+        storage
+            .save_authorization_code(&code)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+    }
+    let fetched = storage
+        .get_authorization_code("acme_code_1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("acme code should exist"))?;
+    assert_eq!(fetched.tenant_id.as_deref(), Some("acme"));
+    assert!(fetched.in_tenant(Some("acme")));
+    storage
+        .delete_client("client_1", Some("acme"))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert!(storage
+        .get_client("client_1", Some("acme"))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+    assert!(storage
+        .get_token_by_access_token("acme_access_token_1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+    assert!(storage
+        .get_authorization_code("acme_code_1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+    assert!(storage
+        .get_authorization_code("default_code_1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_some());
+    assert!(storage
+        .get_client("client_1", None)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_some());
+    storage
+        .delete_user(&default_tenant_user.id)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    // Deleting a client also removes the tokens issued to it.
+    storage
+        .delete_client("client_1", None)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    assert!(storage
+        .get_client("client_1", None)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+    assert!(storage
+        .get_token_by_access_token("access_token_1")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use oauth2_core::{Client, Token, TokenMetadata, User};
+use oauth2_ports::{Storage, TokenMetadataQuery, UserListQuery};
+
+use crate::ContractResult;
+
+/// Non-ASCII text, including characters outside the Basic Multilingual Plane, is stored
+/// and matched byte for byte.
+pub(crate) async fn run(storage: &dyn Storage) -> ContractResult {
+    let client = Client::new(
+        "unicode_client_ü".to_string(),
+        "secret".to_string(),
+        vec!["https://例え.jp/回调".to_string()],
+        vec!["authorization_code".to_string()],
+        "read".to_string(),
+        "Café ☕ — 日本語クライアント 🚀".to_string(),
+    );
+    storage
+        .save_client(&client)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let fetched = storage
+        .get_client("unicode_client_ü", None)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("unicode client should exist"))?;
+    assert_eq!(fetched.name, "Café ☕ — 日本語クライアント 🚀");
+    assert_eq!(fetched.get_redirect_uris(), ["https://例え.jp/回调"]);
+    assert!(storage
+        .get_client("unicode_client_u", None)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .is_none());
+
+    let user = User::new(
+        "ユーザー_zoë".to_string(),
+        "password_hash".to_string(),
+        "zoë@例え.jp".to_string(),
+    );
+    storage
+        .save_user(&user)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let fetched = storage
+        .get_user_by_username("ユーザー_zoë", None)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("unicode user should exist"))?;
+    assert_eq!(fetched.id, user.id);
+    assert_eq!(fetched.email, "zoë@例え.jp");
+
+    // Search on scripts without case is an exact substring match on every backend.
+    let found = storage
+        .list_users(&UserListQuery {
+            search: Some("ユーザー".to_string()),
+            ..UserListQuery::default()
+        })
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(
+        found.iter().map(|u| u.id.as_str()).collect::<Vec<_>>(),
+        [user.id.as_str()]
+    );
+    let found = storage
+        .list_users(&UserListQuery {
+            search: Some("例え.jp".to_string()),
+            ..UserListQuery::default()
+        })
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(found.len(), 1);
+
+    let token = Token::new(
+        "unicode_access_🔑".to_string(),
+        None,
+        client.client_id.clone(),
+        Some(user.id.clone()),
+        "read".to_string(),
+        3600,
+    )
+    .with_metadata(
+        [("region", "señal 📡"), ("department", "研究開発")]
+            .into_iter()
+            .collect::<TokenMetadata>(),
+    );
+    storage
+        .save_token(&token)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let fetched = storage
+        .get_token_by_access_token("unicode_access_🔑")
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+        .ok_or_else(|| std::io::Error::other("unicode token should exist"))?;
+    assert_eq!(fetched.client_id, "unicode_client_ü");
+    assert_eq!(fetched.metadata.get("region"), Some("señal 📡"));
+    assert_eq!(fetched.metadata.get("department"), Some("研究開発"));
+
+    let tagged = storage
+        .find_tokens_by_metadata(&TokenMetadataQuery::new("department", "研究開発"))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    assert_eq!(
+        tagged
+            .iter()
+            .map(|t| t.access_token.as_str())
+            .collect::<Vec<_>>(),
+        ["unicode_access_🔑"]
+    );
+
+    Ok(())
+}
@@ -0,0 +1,4 @@
+-- Key/value tags attached to tokens at issuance (JSON object), e.g. {"deployment": "canary"}
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS metadata TEXT NOT NULL DEFAULT '{}';
+
+CREATE INDEX IF NOT EXISTS idx_tokens_metadata ON tokens USING GIN ((metadata::jsonb));
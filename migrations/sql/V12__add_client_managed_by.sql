@@ -0,0 +1,3 @@
+-- Clients converged from declarative sources record which reconciler owns them
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS managed_by TEXT;
+CREATE INDEX IF NOT EXISTS idx_clients_managed_by ON clients(managed_by);
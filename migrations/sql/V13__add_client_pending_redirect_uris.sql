@@ -0,0 +1,3 @@
+-- Redirect URI changes held back by security.redirect_uri_changes
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS pending_redirect_uris TEXT;
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS pending_redirect_uris_at TIMESTAMPTZ;
@@ -0,0 +1,2 @@
+-- Resource servers introspect tokens with their own credentials instead of obtaining tokens
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS client_type TEXT NOT NULL DEFAULT 'application';
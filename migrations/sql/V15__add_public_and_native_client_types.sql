@@ -0,0 +1,3 @@
+-- Client types now distinguish confidential, public and native (RFC 8252) clients
+ALTER TABLE clients ALTER COLUMN client_type SET DEFAULT 'confidential';
+UPDATE clients SET client_type = 'confidential' WHERE client_type = 'application';
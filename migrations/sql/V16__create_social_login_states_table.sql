@@ -0,0 +1,11 @@
+-- Outbound social login state, consumed once by the provider callback
+CREATE TABLE IF NOT EXISTS social_login_states (
+    state TEXT PRIMARY KEY,
+    provider TEXT NOT NULL,
+    pkce_verifier TEXT,
+    nonce TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_social_login_states_expires_at ON social_login_states(expires_at);
@@ -0,0 +1,18 @@
+-- Tenants: isolated issuers served under /t/{id}, each with its own signing secret
+CREATE TABLE IF NOT EXISTS tenants (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    signing_secret TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+-- Existing rows (NULL tenant_id) belong to the default tenant
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS tenant_id TEXT REFERENCES tenants(id);
+ALTER TABLE users ADD COLUMN IF NOT EXISTS tenant_id TEXT REFERENCES tenants(id);
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS tenant_id TEXT REFERENCES tenants(id);
+
+CREATE INDEX IF NOT EXISTS idx_clients_tenant_id ON clients(tenant_id);
+CREATE INDEX IF NOT EXISTS idx_users_tenant_id ON users(tenant_id);
+CREATE INDEX IF NOT EXISTS idx_tokens_tenant_id ON tokens(tenant_id);
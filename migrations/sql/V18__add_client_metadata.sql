@@ -0,0 +1,10 @@
+-- RFC 7591 client metadata shown to users and used for token endpoint authentication
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS logo_uri TEXT;
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS client_uri TEXT;
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS policy_uri TEXT;
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS tos_uri TEXT;
+-- JSON array of contact addresses
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS contacts TEXT;
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS jwks_uri TEXT;
+-- NULL means the default for the client type
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS token_endpoint_auth_method TEXT;
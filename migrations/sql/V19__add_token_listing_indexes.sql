@@ -0,0 +1,3 @@
+-- Newest-first token listings per client and per user (admin API)
+CREATE INDEX IF NOT EXISTS idx_tokens_client_id_created_at ON tokens(client_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_tokens_user_id_created_at ON tokens(user_id, created_at);
@@ -0,0 +1,4 @@
+-- Upstream provider tokens, sealed by the server before they are stored
+ALTER TABLE federated_identities ADD COLUMN IF NOT EXISTS access_token TEXT;
+ALTER TABLE federated_identities ADD COLUMN IF NOT EXISTS refresh_token TEXT;
+ALTER TABLE federated_identities ADD COLUMN IF NOT EXISTS token_expires_at TIMESTAMPTZ;
@@ -0,0 +1,27 @@
+-- Roles and groups issued in access tokens, and their assignment to users
+CREATE TABLE IF NOT EXISTS roles (
+    name TEXT PRIMARY KEY,
+    description TEXT,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS groups (
+    name TEXT PRIMARY KEY,
+    description TEXT,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS user_roles (
+    user_id TEXT NOT NULL REFERENCES users(id),
+    role_name TEXT NOT NULL REFERENCES roles(name),
+    PRIMARY KEY (user_id, role_name)
+);
+
+CREATE TABLE IF NOT EXISTS user_groups (
+    user_id TEXT NOT NULL REFERENCES users(id),
+    group_name TEXT NOT NULL REFERENCES groups(name),
+    PRIMARY KEY (user_id, group_name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_roles_role_name ON user_roles(role_name);
+CREATE INDEX IF NOT EXISTS idx_user_groups_group_name ON user_groups(group_name);
@@ -0,0 +1,2 @@
+-- Where a magic link sign-in continues, e.g. a pending authorization request
+ALTER TABLE email_tokens ADD COLUMN IF NOT EXISTS return_to TEXT;
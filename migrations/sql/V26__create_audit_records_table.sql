@@ -0,0 +1,16 @@
+-- Audit log of sign-ins, consent, token issuance and admin changes; records outlive the users and clients they name
+CREATE TABLE IF NOT EXISTS audit_records (
+    id TEXT PRIMARY KEY,
+    occurred_at TIMESTAMPTZ NOT NULL,
+    action TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    actor TEXT,
+    client_id TEXT,
+    target TEXT,
+    ip_address TEXT,
+    user_agent TEXT,
+    detail TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_records_occurred_at ON audit_records(occurred_at);
+CREATE INDEX IF NOT EXISTS idx_audit_records_actor ON audit_records(actor, occurred_at);
@@ -0,0 +1,2 @@
+-- Browser origins allowed to call the token endpoint cross-origin (JSON array)
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS allowed_origins TEXT;
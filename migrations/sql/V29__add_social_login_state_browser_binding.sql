@@ -0,0 +1,2 @@
+-- Hash of the cookie binding a social login to the browser that started it
+ALTER TABLE social_login_states ADD COLUMN IF NOT EXISTS browser_binding TEXT;
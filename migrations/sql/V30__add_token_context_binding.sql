@@ -0,0 +1,2 @@
+-- Optional binding of refresh tokens to the user agent/network that obtained them
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS context_binding TEXT;
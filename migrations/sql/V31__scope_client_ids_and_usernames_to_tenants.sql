@@ -0,0 +1,9 @@
+-- Client ids and usernames are unique per tenant rather than globally
+ALTER TABLE tokens DROP CONSTRAINT IF EXISTS tokens_client_id_fkey;
+ALTER TABLE authorization_codes DROP CONSTRAINT IF EXISTS authorization_codes_client_id_fkey;
+ALTER TABLE service_accounts DROP CONSTRAINT IF EXISTS service_accounts_client_id_fkey;
+ALTER TABLE clients DROP CONSTRAINT IF EXISTS clients_client_id_key;
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_username_key;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_clients_tenant_client_id ON clients((COALESCE(tenant_id, '')), client_id);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_tenant_username ON users((COALESCE(tenant_id, '')), username);
@@ -0,0 +1,5 @@
+-- Authorization codes belong to the tenant that issued them; existing codes take their user's
+ALTER TABLE authorization_codes ADD COLUMN IF NOT EXISTS tenant_id TEXT REFERENCES tenants(id);
+UPDATE authorization_codes SET tenant_id = users.tenant_id FROM users WHERE users.id = authorization_codes.user_id;
+
+CREATE INDEX IF NOT EXISTS idx_authorization_codes_tenant_id ON authorization_codes(tenant_id);
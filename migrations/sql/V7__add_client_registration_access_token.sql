@@ -0,0 +1,2 @@
+-- RFC 7592: store the hash of each client's registration access token
+ALTER TABLE clients ADD COLUMN IF NOT EXISTS registration_access_token TEXT;
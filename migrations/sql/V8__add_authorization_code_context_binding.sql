@@ -0,0 +1,2 @@
+-- Optional binding of authorization codes to the user agent/network that requested them
+ALTER TABLE authorization_codes ADD COLUMN IF NOT EXISTS context_binding TEXT;
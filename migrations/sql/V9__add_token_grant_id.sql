@@ -0,0 +1,3 @@
+-- Link tokens issued under the same grant so revocation can cascade
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS grant_id TEXT;
+CREATE INDEX IF NOT EXISTS idx_tokens_grant_id ON tokens(grant_id);
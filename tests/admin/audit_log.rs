@@ -0,0 +1,191 @@
+use actix_web::{test, App};
+use serde_json::{json, Value};
+
+use oauth2_config::Config;
+use oauth2_core::Client;
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+fn client(client_id: &str, scope: &str) -> Client {
+    support::client(
+        client_id,
+        "https://unused.example/cb",
+        &["client_credentials"],
+        scope,
+    )
+}
+
+/// Client credentials request for `$client_id`: the response status and body.
+macro_rules! token {
+    ($app:expr, $client_id:expr, $secret:expr, $scope:expr) => {{
+        let req = test::TestRequest::post()
+            .uri("/oauth/token")
+            .insert_header(("User-Agent", "audit-test/1.0"))
+            .set_form([
+                ("grant_type", "client_credentials"),
+                ("client_id", $client_id),
+                ("client_secret", $secret),
+                ("scope", $scope),
+            ])
+            .to_request();
+        let resp = test::call_service($app, req).await;
+        let status = resp.status().as_u16();
+        let body: Value = test::read_body_json(resp).await;
+        (status, body)
+    }};
+}
+
+/// `GET /admin/audit` with `$query` as `$bearer`: the response status and body.
+macro_rules! audit {
+    ($app:expr, $bearer:expr, $query:expr) => {{
+        let req = test::TestRequest::get()
+            .uri(&format!("/admin/audit{}", $query))
+            .insert_header(("Authorization", format!("Bearer {}", $bearer)))
+            .to_request();
+        let resp = test::call_service($app, req).await;
+        let status = resp.status().as_u16();
+        let cache_control = resp
+            .headers()
+            .get("Cache-Control")
+            .map(|v| v.to_str().unwrap().to_string());
+        let body: Value = test::read_body_json(resp).await;
+        (status, cache_control, body)
+    }};
+}
+
+fn actions(body: &Value) -> Vec<String> {
+    body["records"]
+        .as_array()
+        .expect("records")
+        .iter()
+        .map(|record| record["action"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[actix_web::test]
+async fn token_issuance_and_admin_changes_are_recorded_and_queryable() {
+    let storage = support::memory_storage().await;
+    for client in [
+        client("app", "read"),
+        client("operator", "admin:operator"),
+        client("security", "admin:security_admin"),
+    ] {
+        support::save_client(&storage, &client).await;
+    }
+
+    let mut config = Config::default();
+    config.events.enabled = false;
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage.clone())
+        .with_endpoints([EndpointGroup::OAuth, EndpointGroup::Admin])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    let started = chrono::Utc::now();
+    let (status, body) = token!(&app, "app", "wrong", "read");
+    assert_eq!(status, 401);
+    assert_eq!(body["error"], "invalid_client");
+    let (status, body) = token!(&app, "app", "app_secret", "read");
+    assert_eq!(status, 200);
+    let app_token = body["access_token"].as_str().unwrap().to_string();
+    let (_, body) = token!(&app, "operator", "operator_secret", "admin:operator");
+    let operator = body["access_token"].as_str().unwrap().to_string();
+    let (_, body) = token!(&app, "security", "security_secret", "admin:security_admin");
+    let security = body["access_token"].as_str().unwrap().to_string();
+
+    // Writes through the admin API are recorded with the caller, whether or not they pass.
+    let req = test::TestRequest::post()
+        .uri("/admin/users")
+        .insert_header(("Authorization", format!("Bearer {operator}")))
+        .set_json(json!({
+            "username": "dana",
+            "email": "dana@example.com",
+            "password": "correct horse battery staple",
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 201);
+    let req = test::TestRequest::delete()
+        .uri("/admin/users/unknown")
+        .insert_header(("Authorization", format!("Bearer {app_token}")))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+    // Reads are not.
+    let req = test::TestRequest::get()
+        .uri("/admin/users")
+        .insert_header(("Authorization", format!("Bearer {operator}")))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    // Only security admins read the audit log.
+    let (status, _, _) = audit!(&app, operator, "");
+    assert_eq!(status, 403);
+
+    let (status, cache_control, body) = audit!(&app, security, "");
+    assert_eq!(status, 200);
+    assert_eq!(cache_control.as_deref(), Some("no-store"));
+    assert_eq!(
+        actions(&body),
+        [
+            "admin_change",
+            "admin_change",
+            "token_issued",
+            "token_issued",
+            "token_issued",
+            "token_issued",
+        ]
+    );
+
+    let (_, _, body) = audit!(&app, security, "?action=token_issued&actor=app");
+    let records = body["records"].as_array().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["outcome"], "success");
+    assert_eq!(records[0]["client_id"], "app");
+    assert_eq!(records[0]["target"], "client_credentials");
+    assert_eq!(records[0]["user_agent"], "audit-test/1.0");
+
+    let (_, _, body) = audit!(&app, security, "?action=token_issued&limit=10");
+    let failed: Vec<&Value> = body["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|record| record["outcome"] == "failure")
+        .collect();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0]["client_id"], "app");
+    assert_eq!(failed[0]["detail"], "invalid_client");
+    assert!(failed[0].get("actor").is_none());
+
+    let (_, _, body) = audit!(&app, security, "?action=admin_change");
+    let records = body["records"].as_array().unwrap();
+    assert_eq!(records[0]["outcome"], "failure");
+    assert_eq!(records[0]["target"], "DELETE /admin/users/unknown");
+    assert_eq!(records[0]["detail"], "403");
+    assert!(records[0].get("actor").is_none());
+    assert_eq!(records[1]["outcome"], "success");
+    assert_eq!(records[1]["actor"], "operator");
+    assert_eq!(records[1]["target"], "POST /admin/users");
+
+    let (_, _, body) = audit!(&app, security, "?actor=operator&limit=1");
+    assert_eq!(actions(&body), ["admin_change"]);
+
+    // Time ranges are RFC 3339 and half-open.
+    let since =
+        url::form_urlencoded::byte_serialize(started.to_rfc3339().as_bytes()).collect::<String>();
+    let (_, _, body) = audit!(&app, security, format!("?since={since}&limit=1000"));
+    assert_eq!(body["records"].as_array().unwrap().len(), 6);
+    let (_, _, body) = audit!(&app, security, format!("?until={since}"));
+    assert!(body["records"].as_array().unwrap().is_empty());
+
+    for query in [
+        "?action=unknown",
+        "?since=yesterday",
+        "?since=2024-01-02T00:00:00Z&until=2024-01-01T00:00:00Z",
+    ] {
+        let (status, _, body) = audit!(&app, security, query);
+        assert_eq!(status, 400, "{query}");
+        assert_eq!(body["error"], "invalid_request");
+    }
+}
@@ -0,0 +1,118 @@
+use actix::Actor;
+use actix_web::{test, web, App};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+
+use oauth2_actix::actors::ClientActor;
+use oauth2_core::{AuthorizationCode, Client, Token};
+use oauth2_events::testing::RecordingEventBus;
+use oauth2_events::EventType;
+use oauth2_ports::testing::FakeStorage;
+use oauth2_ports::{DynStorage, Storage};
+
+#[actix_web::test]
+async fn admin_client_deletion_cascades_and_emits_an_event() {
+    let storage = Arc::new(FakeStorage::new());
+    for client_id in ["doomed", "bystander"] {
+        storage
+            .save_client(&Client::new(
+                client_id.to_string(),
+                "secret".to_string(),
+                vec!["https://rp.example/cb".to_string()],
+                vec!["authorization_code".to_string()],
+                "read".to_string(),
+                format!("{client_id} app"),
+            ))
+            .await
+            .unwrap();
+        storage
+            .save_token(&Token::new(
+                format!("{client_id}_access"),
+                Some(format!("{client_id}_refresh")),
+                client_id.to_string(),
+                Some("alice".to_string()),
+                "read".to_string(),
+                3600,
+            ))
+            .await
+            .unwrap();
+        storage
+            .save_authorization_code(&AuthorizationCode::new(
+                format!("{client_id}_code"),
+                client_id.to_string(),
+                "alice".to_string(),
+                "https://rp.example/cb".to_string(),
+                "read".to_string(),
+                None,
+                None,
+            ))
+            .await
+            .unwrap();
+    }
+
+    let bus = RecordingEventBus::new();
+    let db: DynStorage = storage.clone();
+    let client_actor = ClientActor::with_events(db.clone(), bus.handle()).start();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(db))
+            .app_data(web::Data::new(client_actor))
+            .route(
+                "/admin/api/clients/{id}",
+                web::delete().to(oauth2_actix::handlers::admin::delete_client),
+            ),
+    )
+    .await;
+
+    let req = test::TestRequest::delete()
+        .uri("/admin/api/clients/doomed")
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+
+    assert!(storage.get_client("doomed", None).await.unwrap().is_none());
+    assert!(storage
+        .get_token_by_access_token("doomed_access")
+        .await
+        .unwrap()
+        .is_none());
+    assert!(storage
+        .get_token_by_refresh_token("doomed_refresh")
+        .await
+        .unwrap()
+        .is_none());
+    assert!(storage
+        .get_authorization_code("doomed_code")
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        storage
+            .tokens()
+            .iter()
+            .map(|t| t.client_id.as_str())
+            .collect::<Vec<_>>(),
+        ["bystander"]
+    );
+    assert_eq!(storage.authorization_codes().len(), 1);
+
+    let events = bus.wait_for(1, Duration::from_secs(5)).await;
+    assert_eq!(bus.event_types(), [EventType::ClientDeleted]);
+    assert_eq!(events[0].event.client_id.as_deref(), Some("doomed"));
+    assert_eq!(
+        events[0]
+            .event
+            .metadata
+            .get("deleted_by")
+            .map(String::as_str),
+        Some("admin")
+    );
+
+    let req = test::TestRequest::delete()
+        .uri("/admin/api/clients/doomed")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 404);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "not_found");
+}
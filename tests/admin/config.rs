@@ -0,0 +1,153 @@
+use actix_web::{test, web, App};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use oauth2_config::{Config, EffectiveConfig, ResolvedBackends};
+use oauth2_core::{Claims, IssuerKeys, Token};
+use oauth2_ports::DynStorage;
+
+use crate::support;
+
+async fn setup_storage() -> DynStorage {
+    let storage = support::memory_storage().await;
+    support::save_client(
+        &storage,
+        &support::client(
+            "admin_client",
+            "https://admin.example/cb",
+            &["client_credentials"],
+            "read admin",
+        ),
+    )
+    .await;
+    storage
+}
+
+/// Sign and persist an access token with `scope`, as the token endpoint would.
+async fn issue_token(storage: &DynStorage, keys: &IssuerKeys, scope: &str) -> String {
+    let claims = Claims::new(
+        "admin_client".to_string(),
+        "admin_client".to_string(),
+        scope.to_string(),
+        3600,
+    );
+    let access_token = keys.sign(claims).expect("sign");
+    let token = Token::new(
+        access_token.clone(),
+        None,
+        "admin_client".to_string(),
+        None,
+        scope.to_string(),
+        3600,
+    );
+    storage.save_token(&token).await.expect("save token");
+    access_token
+}
+
+fn effective_config() -> EffectiveConfig {
+    let (mut config, source) = Config::load();
+    config.jwt.secret = "super-secret-jwt-key".to_string();
+    config.database.url = "postgres://oauth:hunter2@db:5432/oauth2".to_string();
+
+    EffectiveConfig::new(
+        &config,
+        source,
+        BTreeMap::from([("sqlx".to_string(), true), ("mongo".to_string(), false)]),
+        ResolvedBackends {
+            storage: "postgresql".to_string(),
+            events: vec!["in_memory".to_string()],
+            cache: "local".to_string(),
+        },
+    )
+}
+
+#[actix_web::test]
+async fn admin_config_requires_admin_token() {
+    let storage = setup_storage().await;
+    let keys = IssuerKeys::from_secret("test_jwt_secret");
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(storage.clone()))
+            .app_data(web::Data::new(keys.clone()))
+            .app_data(web::Data::new(effective_config()))
+            .route(
+                "/admin/config",
+                web::get().to(oauth2_actix::handlers::admin::effective_config),
+            ),
+    )
+    .await;
+
+    // No token
+    let req = test::TestRequest::get().uri("/admin/config").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 401);
+    assert!(resp.headers().contains_key("WWW-Authenticate"));
+
+    // Valid token without the admin scope
+    let read_only = issue_token(&storage, &keys, "read").await;
+    let req = test::TestRequest::get()
+        .uri("/admin/config")
+        .insert_header(("Authorization", format!("Bearer {read_only}")))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 403);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "insufficient_scope");
+
+    // Revoked admin token
+    let revoked = issue_token(&storage, &keys, "read admin").await;
+    storage.revoke_token(&revoked).await.expect("revoke");
+    let req = test::TestRequest::get()
+        .uri("/admin/config")
+        .insert_header(("Authorization", format!("Bearer {revoked}")))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 401);
+}
+
+#[actix_web::test]
+async fn admin_config_returns_sanitized_effective_config() {
+    let storage = setup_storage().await;
+    let keys = IssuerKeys::from_secret("test_jwt_secret");
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(storage.clone()))
+            .app_data(web::Data::new(keys.clone()))
+            .app_data(web::Data::new(effective_config()))
+            .route(
+                "/admin/config",
+                web::get().to(oauth2_actix::handlers::admin::effective_config),
+            ),
+    )
+    .await;
+
+    let admin = issue_token(&storage, &keys, "read admin").await;
+    let req = test::TestRequest::get()
+        .uri("/admin/config")
+        .insert_header(("Authorization", format!("Bearer {admin}")))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+
+    assert!(body["source"]["kind"].is_string());
+    if body["source"]["kind"] == "hocon" {
+        // Origins name the winning layer, never the value.
+        let origin = body["source"]["origins"]["jwt.secret"].as_str().unwrap();
+        assert!(
+            origin == "application.conf" || origin == "env:OAUTH2_JWT_SECRET",
+            "{origin}"
+        );
+    }
+    assert_eq!(body["features"]["sqlx"], true);
+    assert_eq!(body["backends"]["storage"], "postgresql");
+    assert_eq!(body["backends"]["cache"], "local");
+
+    let raw = body.to_string();
+    assert!(!raw.contains("super-secret-jwt-key"));
+    assert!(!raw.contains("hunter2"));
+    assert_eq!(
+        body["config"]["database"]["url"],
+        "postgres://oauth:***MASKED***@db:5432/oauth2"
+    );
+}
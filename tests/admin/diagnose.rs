@@ -0,0 +1,179 @@
+use actix::Actor;
+use actix_web::{test, web, App};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use oauth2_config::GrantsConfig;
+use oauth2_core::{Claims, IssuerKeys, OAuth2Error, Token, TokenMetadata};
+use oauth2_ports::{ClaimsEnricher, DynStorage, TokenMetadataQuery};
+
+use crate::support;
+
+/// Adds a tenant claim so the preview shows enricher output.
+struct TenantEnricher;
+
+#[async_trait]
+impl ClaimsEnricher for TenantEnricher {
+    async fn enrich(
+        &self,
+        claims: &mut Claims,
+        _metadata: &mut TokenMetadata,
+    ) -> Result<(), OAuth2Error> {
+        claims.set_claim("tenant", "acme")
+    }
+}
+
+async fn setup_storage() -> DynStorage {
+    let storage = support::memory_storage().await;
+    for (client_id, grant_types, scope) in [
+        ("admin_client", &["client_credentials"][..], "read admin"),
+        (
+            "web_app",
+            &["authorization_code", "refresh_token"][..],
+            "read write",
+        ),
+    ] {
+        support::save_client(
+            &storage,
+            &support::client(client_id, "https://app.example/cb", grant_types, scope),
+        )
+        .await;
+    }
+    support::save_user_with_id(&storage, "alice", "not_used", true).await;
+    storage
+}
+
+/// Sign and persist an admin access token, as the token endpoint would.
+async fn admin_token(storage: &DynStorage, keys: &IssuerKeys) -> String {
+    let claims = Claims::new(
+        "admin_client".to_string(),
+        "admin_client".to_string(),
+        "read admin".to_string(),
+        3600,
+    );
+    let access_token = keys.sign(claims).expect("sign");
+    let token = Token::new(
+        access_token.clone(),
+        None,
+        "admin_client".to_string(),
+        None,
+        "read admin".to_string(),
+        3600,
+    );
+    storage.save_token(&token).await.expect("save token");
+    access_token
+}
+
+#[actix_web::test]
+async fn diagnose_token_traces_the_decision_without_issuing() {
+    let storage = setup_storage().await;
+    let keys = IssuerKeys::from_secret(support::JWT_SECRET);
+    let token_actor = support::token_actor(&storage)
+        .with_claims_enrichers(vec![Arc::new(TenantEnricher)])
+        .start();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(storage.clone()))
+            .app_data(web::Data::new(keys.clone()))
+            .app_data(web::Data::new(token_actor))
+            .app_data(web::Data::new(GrantsConfig {
+                refresh_token: true,
+                ..GrantsConfig::default()
+            }))
+            .route(
+                "/admin/diagnose/token",
+                web::post().to(oauth2_actix::handlers::diagnose::diagnose_token),
+            ),
+    )
+    .await;
+    let bearer = format!("Bearer {}", admin_token(&storage, &keys).await);
+    let diagnose = |body: Value| {
+        test::TestRequest::post()
+            .uri("/admin/diagnose/token")
+            .insert_header(("Authorization", bearer.clone()))
+            .set_json(body)
+            .to_request()
+    };
+
+    let req = test::TestRequest::post()
+        .uri("/admin/diagnose/token")
+        .set_json(json!({ "client_id": "web_app", "grant_type": "authorization_code" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+    let body: Value = test::call_and_read_body_json(
+        &app,
+        diagnose(json!({
+            "client_id": "web_app",
+            "grant_type": "authorization_code",
+            "scope": "read write",
+            "user_id": "alice",
+            "metadata": { "deployment": "diagnose" }
+        })),
+    )
+    .await;
+    assert_eq!(body["allowed"], true, "{body}");
+    assert!(body["checks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|check| check["passed"] == true));
+    assert_eq!(body["scope"], "read write");
+    assert_eq!(body["access_token_ttl_secs"], 3600);
+    assert_eq!(body["refresh_token_ttl_secs"], 2592000);
+    assert_eq!(body["claims"]["sub"], "alice");
+    assert_eq!(body["claims"]["tenant"], "acme");
+    // Nothing was stored.
+    let tokens = storage
+        .find_tokens_by_metadata(&TokenMetadataQuery::new("deployment", "diagnose"))
+        .await
+        .unwrap();
+    assert!(tokens.is_empty());
+
+    // The trace stops at the first check the token endpoint would reject.
+    let body: Value = test::call_and_read_body_json(
+        &app,
+        diagnose(json!({
+            "client_id": "web_app",
+            "grant_type": "authorization_code",
+            "scope": "read admin",
+            "user_id": "alice"
+        })),
+    )
+    .await;
+    assert_eq!(body["allowed"], false);
+    let failed = body["checks"].as_array().unwrap().last().unwrap();
+    assert_eq!(failed["name"], "scope");
+    assert_eq!(failed["error"], "invalid_scope");
+    assert!(body.get("claims").is_none());
+
+    for (request, check, error) in [
+        (
+            json!({ "client_id": "web_app", "grant_type": "password", "user_id": "alice" }),
+            "grant_type",
+            "unsupported_grant_type",
+        ),
+        (
+            json!({ "client_id": "nobody", "grant_type": "client_credentials" }),
+            "client",
+            "invalid_client",
+        ),
+        (
+            json!({ "client_id": "web_app", "grant_type": "client_credentials" }),
+            "client_grant_type",
+            "unauthorized_client",
+        ),
+        (
+            json!({ "client_id": "web_app", "grant_type": "authorization_code", "user_id": "bob" }),
+            "user",
+            "invalid_grant",
+        ),
+    ] {
+        let body: Value = test::call_and_read_body_json(&app, diagnose(request)).await;
+        assert_eq!(body["allowed"], false);
+        let failed = body["checks"].as_array().unwrap().last().unwrap();
+        assert_eq!(failed["name"], check, "{body}");
+        assert_eq!(failed["error"], error, "{body}");
+    }
+}
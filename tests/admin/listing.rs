@@ -0,0 +1,139 @@
+use actix_web::{test, web, App};
+use serde_json::{json, Value};
+
+use oauth2_core::Token;
+
+use crate::support;
+
+fn admin_scope() -> actix_web::Scope {
+    web::scope("/admin")
+        .route(
+            "/api/dashboard",
+            web::get().to(oauth2_actix::handlers::admin::dashboard),
+        )
+        .route(
+            "/api/clients",
+            web::get().to(oauth2_actix::handlers::admin::list_clients),
+        )
+        .route(
+            "/api/clients/{id}/tokens",
+            web::get().to(oauth2_actix::handlers::admin::list_client_tokens),
+        )
+        .route(
+            "/users/{id}/tokens",
+            web::get().to(oauth2_actix::handlers::admin::list_user_tokens),
+        )
+}
+
+#[actix_web::test]
+async fn admin_lists_clients_and_tokens_from_storage() {
+    let storage = support::memory_storage().await;
+    for client_id in ["client_b", "client_a", "client_c"] {
+        support::save_client(
+            &storage,
+            &support::client(
+                client_id,
+                "https://unused.example/cb",
+                &["client_credentials"],
+                "read",
+            ),
+        )
+        .await;
+    }
+    let user = support::save_user(&storage, "alice", "password").await;
+    let issued_at = chrono::Utc::now();
+    for (i, access_token) in ["first", "second", "third"].into_iter().enumerate() {
+        let mut token = Token::new(
+            access_token.to_string(),
+            None,
+            "client_a".to_string(),
+            (access_token != "third").then(|| user.id.clone()),
+            "read".to_string(),
+            3600,
+        );
+        token.created_at = issued_at + chrono::Duration::seconds(i as i64);
+        storage.save_token(&token).await.unwrap();
+    }
+    storage.revoke_token("first").await.unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(storage.clone()))
+            .service(admin_scope()),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/admin/api/dashboard")
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        body,
+        json!({
+            "total_clients": 3,
+            "total_users": 1,
+            "total_tokens": 3,
+            "active_tokens": 2
+        })
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/admin/api/clients?page=1&per_page=2")
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["total"], 3);
+    assert_eq!(body["page"], 1);
+    assert_eq!(body["per_page"], 2);
+    let client_ids: Vec<&str> = body["clients"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["client_id"].as_str().unwrap())
+        .collect();
+    assert_eq!(client_ids, ["client_a", "client_b"]);
+    assert_eq!(
+        body["clients"][0]["token_endpoint_auth_method"],
+        "client_secret_basic"
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/admin/api/clients?page=2&per_page=2")
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["clients"][0]["client_id"], "client_c");
+
+    let req = test::TestRequest::get()
+        .uri("/admin/api/clients/client_a/tokens?per_page=2")
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let token_ids: Vec<&str> = body["tokens"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(token_ids.len(), 2);
+    assert!(body["tokens"][0].get("access_token").is_none());
+    assert_eq!(body["tokens"][0]["revoked"], false);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/admin/users/{}/tokens", user.id))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let revoked: Vec<bool> = body["tokens"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["revoked"].as_bool().unwrap())
+        .collect();
+    assert_eq!(revoked, [false, true], "newest first");
+
+    for uri in [
+        "/admin/api/clients/unknown/tokens",
+        "/admin/users/unknown/tokens",
+    ] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404, "{uri}");
+    }
+}
@@ -0,0 +1,15 @@
+//! Admin API: users, clients, tokens, roles, configuration and audit.
+
+#[path = "../support/mod.rs"]
+mod support;
+
+mod audit_log;
+mod client_deletion;
+mod config;
+mod diagnose;
+mod listing;
+mod rbac;
+mod redirect_uri_change;
+mod roles;
+mod token_metadata;
+mod users;
@@ -0,0 +1,235 @@
+use actix_web::{test, App};
+use serde_json::{json, Value};
+
+use oauth2_config::Config;
+use oauth2_core::Client;
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+fn client(client_id: &str, scope: &str) -> Client {
+    support::client(
+        client_id,
+        "https://unused.example/cb",
+        &["client_credentials"],
+        scope,
+    )
+}
+
+#[actix_web::test]
+async fn admin_roles_bound_what_each_token_can_do() {
+    let storage = support::memory_storage().await;
+    for client in [
+        client("app", "read"),
+        client("viewer", "admin:viewer"),
+        client("operator", "admin:operator"),
+        client("security", "admin:security_admin"),
+        client("legacy_admin", "admin"),
+    ] {
+        support::save_client(&storage, &client).await;
+    }
+
+    let mut config = Config::default();
+    config.events.enabled = false;
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage.clone())
+        .with_endpoints([EndpointGroup::OAuth, EndpointGroup::Admin])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    let mut bearer = std::collections::HashMap::new();
+    for (client_id, scope) in [
+        ("app", "read"),
+        ("viewer", "admin:viewer"),
+        ("operator", "admin:operator"),
+        ("security", "admin:security_admin"),
+        ("legacy_admin", "admin"),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/oauth/token")
+            .set_form([
+                ("grant_type", "client_credentials"),
+                ("client_id", client_id),
+                ("client_secret", &format!("{client_id}_secret")),
+                ("scope", scope),
+            ])
+            .to_request();
+        let token: Value = test::call_and_read_body_json(&app, req).await;
+        let access_token = token["access_token"].as_str().expect("access token");
+        bearer.insert(client_id, format!("Bearer {access_token}"));
+    }
+    let token_id = storage
+        .get_token_by_access_token(bearer["app"].strip_prefix("Bearer ").unwrap())
+        .await
+        .unwrap()
+        .unwrap()
+        .id;
+
+    let request = |method: &str, uri: &str, caller: Option<&str>| {
+        let req = match method {
+            "GET" => test::TestRequest::get(),
+            "POST" => test::TestRequest::post(),
+            "DELETE" => test::TestRequest::delete(),
+            _ => unreachable!(),
+        }
+        .uri(uri);
+        let req = match caller {
+            Some(caller) => req.insert_header(("Authorization", bearer[caller].clone())),
+            None => req,
+        };
+        let req = if uri == "/admin/resource-servers" {
+            req.set_json(json!({ "name": "orders-api" }))
+        } else {
+            req
+        };
+        req.to_request()
+    };
+    let revoke_uri = format!("/admin/api/tokens/{token_id}/revoke");
+
+    // (method, uri, lowest role allowed, highest role denied)
+    let cases = [
+        ("GET", "/admin/users", "viewer", "app"),
+        ("GET", "/admin/config", "viewer", "app"),
+        ("GET", "/admin/api/dashboard", "viewer", "app"),
+        ("DELETE", "/admin/api/clients/app", "operator", "viewer"),
+        ("DELETE", "/admin/users/unknown", "operator", "viewer"),
+        ("POST", "/admin/resource-servers", "security", "operator"),
+        ("POST", revoke_uri.as_str(), "security", "operator"),
+        ("GET", "/admin/events/stream", "security", "operator"),
+        ("POST", "/admin/events/replay", "security", "operator"),
+    ];
+    for (method, uri, allowed, denied) in cases {
+        let resp = test::call_service(&app, request(method, uri, None)).await;
+        assert_eq!(resp.status(), 401, "{method} {uri} without a token");
+
+        let resp = test::call_service(&app, request(method, uri, Some(denied))).await;
+        assert_eq!(resp.status(), 403, "{method} {uri} as {denied}");
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], "insufficient_scope");
+
+        let resp = test::call_service(&app, request(method, uri, Some(allowed))).await;
+        assert!(
+            !matches!(resp.status().as_u16(), 401 | 403),
+            "{method} {uri} as {allowed}: {}",
+            resp.status()
+        );
+    }
+
+    // The plain admin scope keeps full access.
+    let resp = test::call_service(
+        &app,
+        request("POST", "/admin/resource-servers", Some("legacy_admin")),
+    )
+    .await;
+    assert_eq!(resp.status(), 201);
+
+    // Roles end with the token.
+    let req = test::TestRequest::post()
+        .uri("/oauth/revoke")
+        .set_form([
+            ("token", bearer["security"].strip_prefix("Bearer ").unwrap()),
+            ("client_id", "security"),
+            ("client_secret", "security_secret"),
+        ])
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    let resp = test::call_service(&app, request("GET", "/admin/users", Some("security"))).await;
+    assert_eq!(resp.status(), 401);
+}
+
+#[actix_web::test]
+async fn configured_admin_scope_guards_admin_and_event_routes_with_bearer_challenges() {
+    let storage = support::memory_storage().await;
+    for client in [
+        client("app", "read"),
+        client("ops", "ops:admin"),
+        client("legacy_admin", "admin"),
+    ] {
+        support::save_client(&storage, &client).await;
+    }
+
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.security.admin_scope = "ops:admin".to_string();
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage.clone())
+        .with_endpoints([
+            EndpointGroup::OAuth,
+            EndpointGroup::Admin,
+            EndpointGroup::Events,
+        ])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    let mut bearer = std::collections::HashMap::new();
+    for (client_id, scope) in [
+        ("app", "read"),
+        ("ops", "ops:admin"),
+        ("legacy_admin", "admin"),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/oauth/token")
+            .set_form([
+                ("grant_type", "client_credentials"),
+                ("client_id", client_id),
+                ("client_secret", &format!("{client_id}_secret")),
+                ("scope", scope),
+            ])
+            .to_request();
+        let token: Value = test::call_and_read_body_json(&app, req).await;
+        let access_token = token["access_token"].as_str().expect("access token");
+        bearer.insert(client_id, format!("Bearer {access_token}"));
+    }
+    let get = |uri: &str, caller: Option<&str>| {
+        let req = test::TestRequest::get().uri(uri);
+        match caller {
+            Some(caller) => req.insert_header(("Authorization", bearer[caller].clone())),
+            None => req,
+        }
+        .to_request()
+    };
+
+    for uri in ["/admin/users", "/events/health"] {
+        // No credentials: a bare challenge without an error code (RFC 6750 section 3.1).
+        let resp = test::call_service(&app, get(uri, None)).await;
+        assert_eq!(resp.status(), 401, "{uri}");
+        assert_eq!(
+            resp.headers().get("WWW-Authenticate").unwrap(),
+            "Bearer",
+            "{uri}"
+        );
+
+        // A token without an admin scope is told which scope it needs.
+        let resp = test::call_service(&app, get(uri, Some("app"))).await;
+        assert_eq!(resp.status(), 403, "{uri}");
+        assert_eq!(
+            resp.headers().get("WWW-Authenticate").unwrap(),
+            "Bearer error=\"insufficient_scope\", scope=\"admin:viewer\"",
+            "{uri}"
+        );
+
+        // The configured scope replaces the plain `admin` scope.
+        let resp = test::call_service(&app, get(uri, Some("legacy_admin"))).await;
+        assert_eq!(resp.status(), 403, "{uri}");
+        let resp = test::call_service(&app, get(uri, Some("ops"))).await;
+        assert_eq!(resp.status(), 200, "{uri}");
+    }
+
+    // Event routes can be opened up explicitly.
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.security.event_endpoints_require_admin = false;
+    let open = ServerBuilder::new(config)
+        .with_storage(storage)
+        .with_endpoints([EndpointGroup::Events])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| open.configure(cfg))).await;
+    let req = test::TestRequest::get().uri("/events/health").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+}
@@ -0,0 +1,167 @@
+use actix_web::{test, App};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+use oauth2_config::{Config, RedirectUriChangePolicy};
+use oauth2_events::{EventEnvelope, EventType, InMemoryEventLogger};
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+/// Wait for the best-effort publisher to deliver an event of `event_type`.
+async fn wait_for_event(events: &InMemoryEventLogger, event_type: EventType) -> EventEnvelope {
+    for _ in 0..50 {
+        if let Some(envelope) = events
+            .get_events()
+            .into_iter()
+            .find(|e| e.event.event_type == event_type)
+        {
+            return envelope;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("{event_type:?} was not published");
+}
+
+#[actix_web::test]
+async fn redirect_uri_changes_wait_for_admin_approval() {
+    let storage = support::memory_storage().await;
+    support::save_client(
+        &storage,
+        &support::client(
+            "admin_client",
+            "https://unused.example/cb",
+            &["client_credentials"],
+            "admin",
+        ),
+    )
+    .await;
+
+    let events = Arc::new(InMemoryEventLogger::new(100));
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.security.redirect_uri_changes = RedirectUriChangePolicy::Approval;
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage.clone())
+        .with_event_plugin(events.clone())
+        .with_endpoints([EndpointGroup::OAuth, EndpointGroup::Admin])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/oauth/register")
+        .set_json(json!({
+            "client_name": "web",
+            "redirect_uris": ["https://app.example/cb"],
+            "grant_types": ["authorization_code"],
+            "scope": "read"
+        }))
+        .to_request();
+    let registered: Value = test::call_and_read_body_json(&app, req).await;
+    let client_id = registered["client_id"].as_str().unwrap().to_string();
+    let registration_token = format!(
+        "Bearer {}",
+        registered["registration_access_token"].as_str().unwrap()
+    );
+    let update = |redirect_uri: &str| {
+        test::TestRequest::put()
+            .uri(&format!("/oauth/register/{client_id}"))
+            .insert_header(("Authorization", registration_token.clone()))
+            .set_json(json!({
+                "client_id": client_id,
+                "client_name": "web",
+                "redirect_uris": [redirect_uri],
+                "grant_types": ["authorization_code"],
+                "scope": "read"
+            }))
+            .to_request()
+    };
+
+    // A registration token alone cannot move the redirect URIs.
+    let body: Value = test::call_and_read_body_json(&app, update("https://evil.example/cb")).await;
+    assert_eq!(body["redirect_uris"], json!(["https://app.example/cb"]));
+    assert_eq!(
+        body["pending_redirect_uris"],
+        json!(["https://evil.example/cb"])
+    );
+    assert!(body.get("pending_redirect_uris_effective_at").is_none());
+
+    let requested = wait_for_event(&events, EventType::ClientRedirectUrisChangeRequested).await;
+    let metadata = &requested.event.metadata;
+    assert_eq!(
+        metadata["previous_redirect_uris"],
+        r#"["https://app.example/cb"]"#
+    );
+    assert_eq!(metadata["redirect_uris"], r#"["https://evil.example/cb"]"#);
+    assert_eq!(metadata["awaiting_approval"], "true");
+
+    let req = test::TestRequest::get()
+        .uri(&format!(
+            "/oauth/authorize?response_type=code&client_id={client_id}&redirect_uri=https%3A%2F%2Fevil.example%2Fcb"
+        ))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    // Approval and rejection require an admin token.
+    let req = test::TestRequest::post()
+        .uri(&format!("/admin/clients/{client_id}/redirect-uris/reject"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+    let req = test::TestRequest::post()
+        .uri("/oauth/token")
+        .set_form([
+            ("grant_type", "client_credentials"),
+            ("client_id", "admin_client"),
+            ("client_secret", "admin_client_secret"),
+            ("scope", "admin"),
+        ])
+        .to_request();
+    let token: Value = test::call_and_read_body_json(&app, req).await;
+    let admin = format!("Bearer {}", token["access_token"].as_str().unwrap());
+    let admin_call = |action: &str| {
+        test::TestRequest::post()
+            .uri(&format!(
+                "/admin/clients/{client_id}/redirect-uris/{action}"
+            ))
+            .insert_header(("Authorization", admin.clone()))
+            .to_request()
+    };
+
+    let body: Value = test::call_and_read_body_json(&app, admin_call("reject")).await;
+    assert_eq!(body["redirect_uris"], json!(["https://app.example/cb"]));
+    wait_for_event(&events, EventType::ClientRedirectUrisChangeRejected).await;
+    assert_eq!(
+        test::call_service(&app, admin_call("approve"))
+            .await
+            .status(),
+        400
+    );
+
+    // An approved change takes effect and is recorded with its before/after values.
+    let resp = test::call_service(&app, update("https://app.example/new-cb")).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::call_and_read_body_json(&app, admin_call("approve")).await;
+    assert_eq!(body["redirect_uris"], json!(["https://app.example/new-cb"]));
+
+    let changed = wait_for_event(&events, EventType::ClientRedirectUrisChanged).await;
+    assert_eq!(
+        changed.event.metadata["previous_redirect_uris"],
+        r#"["https://app.example/cb"]"#
+    );
+    assert_eq!(
+        changed.event.metadata["redirect_uris"],
+        r#"["https://app.example/new-cb"]"#
+    );
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/oauth/register/{client_id}"))
+        .insert_header(("Authorization", registration_token.clone()))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["redirect_uris"], json!(["https://app.example/new-cb"]));
+    assert!(body.get("pending_redirect_uris").is_none());
+}
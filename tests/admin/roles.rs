@@ -0,0 +1,227 @@
+use std::sync::Arc;
+
+use actix_web::{test, web, App};
+use base64::Engine;
+use serde_json::{json, Value};
+
+use oauth2_config::GrantsConfig;
+use oauth2_core::User;
+use oauth2_ports::{DynStorage, RoleClaimsEnricher};
+
+use crate::support;
+
+async fn new_storage() -> (DynStorage, User) {
+    let storage = support::first_party_storage(&["password", "client_credentials"], "read").await;
+    let user = support::save_user(&storage, "alice", "correct-horse").await;
+    (storage, user)
+}
+
+macro_rules! init_app {
+    ($storage:expr, $groups_claim:expr) => {{
+        let enricher =
+            RoleClaimsEnricher::new($storage.clone(), Some("roles".to_string()), $groups_claim)
+                .expect("roles enricher");
+        let token_actor =
+            support::token_actor(&$storage).with_claims_enrichers(vec![Arc::new(enricher)]);
+        let auth_actor = oauth2_actix::actors::AuthActor::new($storage.clone());
+
+        test::init_service(
+            App::new()
+                .configure(support::oauth_data(&$storage, token_actor, auth_actor))
+                .app_data(web::Data::new(GrantsConfig {
+                    password: true,
+                    ..GrantsConfig::default()
+                }))
+                .route(
+                    "/oauth/token",
+                    web::post().to(oauth2_actix::handlers::oauth::token),
+                )
+                .service(
+                    web::scope("/admin")
+                        .route(
+                            "/roles",
+                            web::get().to(oauth2_actix::handlers::admin::list_roles),
+                        )
+                        .route(
+                            "/roles",
+                            web::post().to(oauth2_actix::handlers::admin::create_role),
+                        )
+                        .route(
+                            "/roles/{name}",
+                            web::delete().to(oauth2_actix::handlers::admin::delete_role),
+                        )
+                        .route(
+                            "/groups",
+                            web::post().to(oauth2_actix::handlers::admin::create_group),
+                        )
+                        .route(
+                            "/users/{id}/roles",
+                            web::get().to(oauth2_actix::handlers::admin::get_user_roles),
+                        )
+                        .route(
+                            "/users/{id}/roles",
+                            web::put().to(oauth2_actix::handlers::admin::set_user_roles),
+                        )
+                        .route(
+                            "/users/{id}/groups",
+                            web::put().to(oauth2_actix::handlers::admin::set_user_groups),
+                        ),
+                ),
+        )
+        .await
+    }};
+}
+
+fn password_grant() -> test::TestRequest {
+    test::TestRequest::post().uri("/oauth/token").set_form([
+        ("client_id", "first_party"),
+        ("client_secret", "first_party_secret"),
+        ("grant_type", "password"),
+        ("username", "alice"),
+        ("password", "correct-horse"),
+    ])
+}
+
+fn jwt_payload(token: &str) -> Value {
+    let payload = token.split('.').nth(1).expect("jwt payload");
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload)
+        .expect("base64 payload");
+    serde_json::from_slice(&bytes).expect("json payload")
+}
+
+#[actix_web::test]
+async fn admins_define_and_assign_roles() {
+    let (storage, user) = new_storage().await;
+    let app = init_app!(storage, Some("groups".to_string()));
+
+    for (body, status) in [
+        (
+            json!({ "name": "editor", "description": "Edits articles" }),
+            201,
+        ),
+        (json!({ "name": "viewer" }), 201),
+        (json!({ "name": "editor" }), 400),
+        (json!({ "name": "has space" }), 400),
+        (json!({ "name": "" }), 400),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/admin/roles")
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), status, "{body}");
+    }
+
+    let req = test::TestRequest::get().uri("/admin/roles").to_request();
+    let roles: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(roles[0]["name"], "editor");
+    assert_eq!(roles[0]["description"], "Edits articles");
+    assert_eq!(roles[1]["name"], "viewer");
+
+    let uri = format!("/admin/users/{}/roles", user.id);
+    let req = test::TestRequest::put()
+        .uri(&uri)
+        .set_json(json!({ "roles": ["viewer", "editor", "viewer"] }))
+        .to_request();
+    let assigned: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(assigned, json!({ "roles": ["editor", "viewer"] }));
+
+    // Unknown roles are rejected and the assignment is left as it was.
+    let req = test::TestRequest::put()
+        .uri(&uri)
+        .set_json(json!({ "roles": ["editor", "owner"] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    let req = test::TestRequest::put()
+        .uri("/admin/users/missing/roles")
+        .set_json(json!({ "roles": [] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+    // Deleting a role removes it from its users.
+    let req = test::TestRequest::delete()
+        .uri("/admin/roles/editor")
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    let req = test::TestRequest::delete()
+        .uri("/admin/roles/editor")
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+    let req = test::TestRequest::get().uri(&uri).to_request();
+    let assigned: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(assigned, json!({ "roles": ["viewer"] }));
+}
+
+#[actix_web::test]
+async fn access_tokens_carry_assigned_roles_and_groups() {
+    let (storage, user) = new_storage().await;
+    let app = init_app!(storage, Some("groups".to_string()));
+
+    // Users without roles or groups get no claims.
+    let body: Value = test::call_and_read_body_json(&app, password_grant().to_request()).await;
+    let claims = jwt_payload(body["access_token"].as_str().expect("access token"));
+    assert!(claims.get("roles").is_none());
+    assert!(claims.get("groups").is_none());
+
+    for (uri, name) in [("/admin/roles", "editor"), ("/admin/groups", "staff")] {
+        let req = test::TestRequest::post()
+            .uri(uri)
+            .set_json(json!({ "name": name }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+    }
+    for (kind, names) in [("roles", ["editor"]), ("groups", ["staff"])] {
+        let req = test::TestRequest::put()
+            .uri(&format!("/admin/users/{}/{kind}", user.id))
+            .set_json(json!({ kind: names }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+    }
+
+    let body: Value = test::call_and_read_body_json(&app, password_grant().to_request()).await;
+    let claims = jwt_payload(body["access_token"].as_str().expect("access token"));
+    assert_eq!(claims["sub"], user.id.as_str());
+    assert_eq!(claims["roles"], json!(["editor"]));
+    assert_eq!(claims["groups"], json!(["staff"]));
+
+    // Client tokens have no user behind them.
+    let req = test::TestRequest::post()
+        .uri("/oauth/token")
+        .set_form([
+            ("client_id", "first_party"),
+            ("client_secret", "first_party_secret"),
+            ("grant_type", "client_credentials"),
+        ])
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let claims = jwt_payload(body["access_token"].as_str().expect("access token"));
+    assert!(claims.get("roles").is_none());
+}
+
+#[actix_web::test]
+async fn disabled_claims_are_left_out() {
+    let (storage, user) = new_storage().await;
+    let app = init_app!(storage, None);
+
+    storage
+        .save_group(&oauth2_core::Group::new("staff".to_string(), None))
+        .await
+        .expect("save group");
+    storage
+        .set_user_groups(&user.id, &["staff".to_string()])
+        .await
+        .expect("assign group");
+
+    let body: Value = test::call_and_read_body_json(&app, password_grant().to_request()).await;
+    let claims = jwt_payload(body["access_token"].as_str().expect("access token"));
+    assert!(claims.get("groups").is_none());
+}
+
+#[actix_web::test]
+async fn registered_claims_cannot_hold_roles() {
+    let storage: DynStorage = Arc::new(oauth2_ports::testing::FakeStorage::new());
+    assert!(RoleClaimsEnricher::new(storage, Some("scope".to_string()), None).is_err());
+}
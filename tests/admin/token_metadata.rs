@@ -0,0 +1,144 @@
+use actix_web::{test, web, App};
+use serde_json::{json, Value};
+
+use oauth2_ports::DynStorage;
+
+use crate::support;
+
+async fn setup_storage() -> DynStorage {
+    let storage = support::memory_storage().await;
+    support::save_client(
+        &storage,
+        &support::client(
+            "worker",
+            "https://unused.example/cb",
+            &["client_credentials"],
+            "read",
+        ),
+    )
+    .await;
+    storage
+}
+
+macro_rules! init_app {
+    ($storage:expr) => {{
+        test::init_service(
+            App::new()
+                .configure(support::default_oauth_data(&$storage))
+                .route(
+                    "/oauth/token",
+                    web::post().to(oauth2_actix::handlers::oauth::token),
+                )
+                .route(
+                    "/admin/api/tokens",
+                    web::get().to(oauth2_actix::handlers::admin::list_tokens),
+                )
+                .route(
+                    "/admin/api/tokens/revoke",
+                    web::post().to(oauth2_actix::handlers::admin::revoke_tokens_by_metadata),
+                ),
+        )
+        .await
+    }};
+}
+
+fn token_request(metadata: Option<&str>) -> test::TestRequest {
+    let mut form = vec![
+        ("grant_type", "client_credentials"),
+        ("client_id", "worker"),
+        ("client_secret", "worker_secret"),
+    ];
+    if let Some(metadata) = metadata {
+        form.push(("metadata", metadata));
+    }
+    test::TestRequest::post().uri("/oauth/token").set_form(form)
+}
+
+#[actix_web::test]
+async fn tokens_tagged_at_issuance_can_be_found_and_revoked() {
+    let storage = setup_storage().await;
+    let app = init_app!(storage);
+
+    let mut canary_tokens = Vec::new();
+    for _ in 0..2 {
+        let resp = test::call_service(
+            &app,
+            token_request(Some(r#"{"deployment":"canary","region":"eu-west-1"}"#)).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+        let body: Value = test::read_body_json(resp).await;
+        canary_tokens.push(body["access_token"].as_str().unwrap().to_string());
+    }
+    let resp = test::call_service(
+        &app,
+        token_request(Some(r#"{"deployment":"stable"}"#)).to_request(),
+    )
+    .await;
+    let body: Value = test::read_body_json(resp).await;
+    let stable_token = body["access_token"].as_str().unwrap().to_string();
+    let resp = test::call_service(&app, token_request(None).to_request()).await;
+    assert_eq!(resp.status(), 200);
+
+    let req = test::TestRequest::get()
+        .uri("/admin/api/tokens?key=deployment&value=canary")
+        .to_request();
+    let page: Value = test::call_and_read_body_json(&app, req).await;
+    let tokens = page["tokens"].as_array().unwrap();
+    assert_eq!(tokens.len(), 2);
+    assert_eq!(tokens[0]["metadata"]["region"], "eu-west-1");
+    // Token values are never exposed.
+    assert!(tokens[0].get("access_token").is_none());
+
+    let req = test::TestRequest::post()
+        .uri("/admin/api/tokens/revoke")
+        .set_json(json!({ "key": "deployment", "value": "canary" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["revoked"], 2);
+
+    for access_token in &canary_tokens {
+        let token = storage
+            .get_token_by_access_token(access_token)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(token.revoked);
+    }
+    let stable = storage
+        .get_token_by_access_token(&stable_token)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!stable.revoked);
+    assert_eq!(stable.metadata.get("deployment"), Some("stable"));
+}
+
+#[actix_web::test]
+async fn invalid_metadata_is_rejected() {
+    let storage = setup_storage().await;
+    let app = init_app!(storage);
+
+    for bad in [
+        r#"not json"#,
+        r#"{"deployment":["canary"]}"#,
+        r#"{"a b":"c"}"#,
+    ] {
+        let resp = test::call_service(&app, token_request(Some(bad)).to_request()).await;
+        assert_eq!(resp.status(), 400, "{bad}");
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], "invalid_request");
+    }
+
+    // Lookups need a full tag.
+    let req = test::TestRequest::get()
+        .uri("/admin/api/tokens?key=deployment")
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+    let req = test::TestRequest::get()
+        .uri("/admin/api/tokens?key=bad%20key&value=x")
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+}
@@ -0,0 +1,162 @@
+use actix_web::{test, web, App};
+use serde_json::{json, Value};
+
+use oauth2_core::verify_password;
+
+use crate::support;
+
+fn users_scope() -> actix_web::Scope {
+    web::scope("/admin/users")
+        .route("", web::get().to(oauth2_actix::handlers::admin::list_users))
+        .route(
+            "",
+            web::post().to(oauth2_actix::handlers::admin::create_user),
+        )
+        .route(
+            "/{id}",
+            web::get().to(oauth2_actix::handlers::admin::get_user),
+        )
+        .route(
+            "/{id}",
+            web::delete().to(oauth2_actix::handlers::admin::delete_user),
+        )
+        .route(
+            "/{id}/enable",
+            web::post().to(oauth2_actix::handlers::admin::enable_user),
+        )
+        .route(
+            "/{id}/disable",
+            web::post().to(oauth2_actix::handlers::admin::disable_user),
+        )
+        .route(
+            "/{id}/password",
+            web::put().to(oauth2_actix::handlers::admin::change_user_password),
+        )
+}
+
+#[actix_web::test]
+async fn admin_user_lifecycle() {
+    let storage = support::memory_storage().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(storage.clone()))
+            .service(users_scope()),
+    )
+    .await;
+
+    // Create
+    let req = test::TestRequest::post()
+        .uri("/admin/users")
+        .set_json(json!({
+            "username": "alice",
+            "password": "correct-horse",
+            "email": "alice@example.com"
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 201);
+    let created: Value = test::read_body_json(resp).await;
+    assert!(
+        created.get("password_hash").is_none(),
+        "password hash must never be returned"
+    );
+    let user_id = created["id"].as_str().expect("id").to_string();
+
+    let stored = storage
+        .get_user(&user_id)
+        .await
+        .expect("get user")
+        .expect("user exists");
+    assert!(verify_password("correct-horse", &stored.password_hash));
+
+    // Duplicate usernames are rejected.
+    let req = test::TestRequest::post()
+        .uri("/admin/users")
+        .set_json(json!({
+            "username": "alice",
+            "password": "another-password",
+            "email": "alice2@example.com"
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+
+    // Search by email
+    let req = test::TestRequest::get()
+        .uri("/admin/users?search=EXAMPLE.COM&limit=10")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let page: Value = test::read_body_json(resp).await;
+    assert_eq!(page["users"].as_array().expect("users").len(), 1);
+    assert_eq!(page["limit"], 10);
+
+    // Disable, then enable
+    let req = test::TestRequest::post()
+        .uri(&format!("/admin/users/{user_id}/disable"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["enabled"], false);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/admin/users/{user_id}/enable"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["enabled"], true);
+
+    // Change password
+    let req = test::TestRequest::put()
+        .uri(&format!("/admin/users/{user_id}/password"))
+        .set_json(json!({ "password": "battery-staple" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 204);
+
+    let stored = storage
+        .get_user(&user_id)
+        .await
+        .expect("get user")
+        .expect("user exists");
+    assert!(verify_password("battery-staple", &stored.password_hash));
+    assert!(!verify_password("correct-horse", &stored.password_hash));
+
+    // Delete
+    let req = test::TestRequest::delete()
+        .uri(&format!("/admin/users/{user_id}"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 204);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/admin/users/{user_id}"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 404);
+}
+
+#[actix_web::test]
+async fn admin_create_user_rejects_short_password() {
+    let storage = support::memory_storage().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(storage))
+            .service(users_scope()),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/admin/users")
+        .set_json(json!({
+            "username": "bob",
+            "password": "short",
+            "email": "bob@example.com"
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "invalid_request");
+}
@@ -0,0 +1,97 @@
+use actix_web::http::header;
+use actix_web::{test, web, App, HttpResponse};
+use base64::Engine;
+use serde_json::Value;
+
+use oauth2_config::Config;
+use oauth2_observability::Metrics;
+use oauth2_ports::DynStorage;
+
+use crate::support;
+
+async fn setup_storage() -> DynStorage {
+    let storage = support::memory_storage().await;
+    support::save_client(
+        &storage,
+        &support::client(
+            "mounted_client",
+            "https://unused.example/cb",
+            &["client_credentials"],
+            "read",
+        ),
+    )
+    .await;
+    storage
+}
+
+#[actix_web::test]
+async fn scope_serves_the_oauth_endpoints_inside_an_existing_app() {
+    let storage = setup_storage().await;
+    let mut config = Config::default();
+    config.jwt.issuer = "https://app.example".to_string();
+    config.server.cors.allowed_origins = vec!["https://console.example".to_string()];
+
+    let app = test::init_service(
+        App::new()
+            .service(oauth2_actix::scope(
+                &config,
+                storage,
+                Metrics::new().expect("metrics"),
+            ))
+            .route("/app/ping", web::get().to(HttpResponse::Ok)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/app/ping").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::post()
+        .uri("/oauth/token")
+        .insert_header((header::ORIGIN, "https://console.example"))
+        .set_form([
+            ("grant_type", "client_credentials"),
+            ("client_id", "mounted_client"),
+            ("client_secret", "mounted_client_secret"),
+        ])
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    // CORS and the other middleware come with the scope.
+    assert_eq!(
+        resp.headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .unwrap(),
+        "https://console.example"
+    );
+    let body: Value = test::read_body_json(resp).await;
+    let access_token = body["access_token"].as_str().unwrap().to_string();
+    let payload = access_token.split('.').nth(1).unwrap();
+    let claims: Value = serde_json::from_slice(
+        &base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(payload)
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(claims["iss"], "https://app.example");
+
+    let req = test::TestRequest::post()
+        .uri("/oauth/introspect")
+        .set_form([
+            ("token", access_token.as_str()),
+            ("client_id", "mounted_client"),
+            ("client_secret", "mounted_client_secret"),
+        ])
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["active"], true);
+
+    // Only the OAuth endpoints are mounted.
+    for uri in [
+        "/admin/config",
+        "/health",
+        "/.well-known/openid-configuration",
+    ] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404, "{uri}");
+    }
+}
@@ -0,0 +1,535 @@
+use async_trait::async_trait;
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::middleware::from_fn_with_state;
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use base64::{engine::general_purpose, Engine as _};
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use oauth2_actix::session::InMemorySessionStore;
+use oauth2_axum::extract::{AuthenticatedToken, CurrentSession, ResourceOwner};
+use oauth2_axum::middleware::require_token;
+use oauth2_axum::OAuth2State;
+use oauth2_config::GrantsConfig;
+use oauth2_core::{
+    hash_password, AuditAction, Client, IssuerKeys, IssuerUrls, OAuth2Error, Tenant, User,
+    UserSession,
+};
+use oauth2_observability::Metrics;
+use oauth2_ports::{
+    AuditQuery, StorageAuditSink, TokenIssuance, TokenIssuancePolicy, UserSessionStore,
+};
+
+use crate::support;
+
+const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+
+async fn setup_state() -> OAuth2State {
+    let storage = support::memory_storage().await;
+    support::save_client(
+        &storage,
+        &support::client(
+            "axum_client",
+            "https://app.example/cb",
+            &["authorization_code", "client_credentials"],
+            "read write",
+        ),
+    )
+    .await;
+    // Authorization codes reference an existing user.
+    support::save_user_with_id(&storage, "alice", "not_used", true).await;
+    OAuth2State::new(
+        storage,
+        IssuerKeys::from_secret(support::JWT_SECRET),
+        Metrics::new().expect("metrics"),
+    )
+    .with_issuer_urls(IssuerUrls::new(Some(
+        "https://auth.example.com".to_string(),
+    )))
+}
+
+async fn me(token: AuthenticatedToken) -> Result<Json<Value>, oauth2_core::OAuth2Error> {
+    token.require_scope("read")?;
+    Ok(Json(json!({ "sub": token.0.user_id })))
+}
+
+fn app(state: OAuth2State, owner: Option<&str>) -> Router {
+    let mut app = Router::new()
+        .merge(oauth2_axum::router(state.clone()))
+        .route(
+            "/api/me",
+            get(me)
+                .layer(from_fn_with_state(state.clone(), require_token))
+                .with_state(state),
+        );
+    if let Some(owner) = owner {
+        app = app.layer(Extension(ResourceOwner(owner.to_string())));
+    }
+    app
+}
+
+fn form(uri: &str, params: &[(&str, &str)]) -> Request<Body> {
+    let body = url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(params)
+        .finish();
+    Request::post(uri)
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn get_request(uri: &str) -> Request<Body> {
+    Request::get(uri).body(Body::empty()).unwrap()
+}
+
+async fn json_body(response: axum::response::Response) -> Value {
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+fn query_param(location: &str, name: &str) -> Option<String> {
+    url::Url::parse(location)
+        .ok()?
+        .query_pairs()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.into_owned())
+}
+
+fn authorize_uri() -> String {
+    let challenge = general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(VERIFIER));
+    format!(
+        "/oauth/authorize?response_type=code&client_id=axum_client&redirect_uri=https%3A%2F%2Fapp.example%2Fcb&scope=read&state=xyz&code_challenge={challenge}&code_challenge_method=S256"
+    )
+}
+
+#[tokio::test]
+async fn axum_router_runs_the_authorization_code_flow() {
+    let app = app(setup_state().await, Some("alice"));
+
+    let resp = app
+        .clone()
+        .oneshot(get_request(&authorize_uri()))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FOUND);
+    let location = resp.headers()[header::LOCATION]
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(query_param(&location, "state").as_deref(), Some("xyz"));
+    assert_eq!(
+        query_param(&location, "iss").as_deref(),
+        Some("https://auth.example.com")
+    );
+    let code = query_param(&location, "code").expect("code");
+
+    let exchange = [
+        ("grant_type", "authorization_code"),
+        ("client_id", "axum_client"),
+        ("client_secret", "axum_client_secret"),
+        ("code", code.as_str()),
+        ("code_verifier", VERIFIER),
+    ];
+    let resp = app
+        .clone()
+        .oneshot(form("/oauth/token", &exchange))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()[header::CACHE_CONTROL], "no-store");
+    let body = json_body(resp).await;
+    let access_token = body["access_token"].as_str().unwrap().to_string();
+
+    // Codes are single use.
+    let resp = app
+        .clone()
+        .oneshot(form("/oauth/token", &exchange))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(json_body(resp).await["error"], "invalid_grant");
+
+    let resp = app
+        .clone()
+        .oneshot(form(
+            "/oauth/introspect",
+            &[
+                ("token", access_token.as_str()),
+                ("client_id", "axum_client"),
+                ("client_secret", "axum_client_secret"),
+            ],
+        ))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    assert_eq!(body["active"], true);
+    assert_eq!(body["sub"], "alice");
+
+    let bearer = || {
+        Request::get("/api/me")
+            .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+    let resp = app.clone().oneshot(bearer()).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(json_body(resp).await["sub"], "alice");
+
+    let resp = app
+        .clone()
+        .oneshot(form(
+            "/oauth/revoke",
+            &[
+                ("token", access_token.as_str()),
+                ("client_id", "axum_client"),
+                ("client_secret", "axum_client_secret"),
+            ],
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = app.clone().oneshot(bearer()).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        resp.headers()[header::WWW_AUTHENTICATE],
+        "Bearer error=\"invalid_token\""
+    );
+    let resp = app.oneshot(get_request("/api/me")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn axum_router_matches_the_actix_error_handling() {
+    let state = setup_state().await;
+
+    // Without a resource owner from the host's login layer, the client gets access_denied.
+    let resp = app(state.clone(), None)
+        .oneshot(get_request(&authorize_uri()))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FOUND);
+    let location = resp.headers()[header::LOCATION].to_str().unwrap();
+    assert_eq!(
+        query_param(location, "error").as_deref(),
+        Some("access_denied")
+    );
+    // Silent requests are told to sign in instead.
+    let resp = app(state.clone(), None)
+        .oneshot(get_request(&format!("{}&prompt=none", authorize_uri())))
+        .await
+        .unwrap();
+    let location = resp.headers()[header::LOCATION].to_str().unwrap();
+    assert_eq!(
+        query_param(location, "error").as_deref(),
+        Some("login_required")
+    );
+
+    let app = app(state, Some("alice"));
+
+    // Unregistered redirect URIs are never redirected to.
+    let resp = app
+        .clone()
+        .oneshot(get_request(
+            "/oauth/authorize?response_type=code&client_id=axum_client&redirect_uri=https%3A%2F%2Fevil.example%2Fcb",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert!(resp.headers().get(header::LOCATION).is_none());
+
+    // Duplicate parameters are ambiguous.
+    let resp = app
+        .clone()
+        .oneshot(form(
+            "/oauth/token",
+            &[
+                ("grant_type", "client_credentials"),
+                ("client_id", "axum_client"),
+                ("client_id", "other"),
+            ],
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let credentials = [
+        ("grant_type", "client_credentials"),
+        ("client_id", "axum_client"),
+        ("client_secret", "wrong"),
+    ];
+    let resp = app
+        .clone()
+        .oneshot(form("/oauth/token", &credentials))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(json_body(resp).await["error"], "invalid_client");
+
+    let resp = app
+        .clone()
+        .oneshot(form(
+            "/oauth/token",
+            &[
+                ("grant_type", "client_credentials"),
+                ("client_id", "axum_client"),
+                ("client_secret", "axum_client_secret"),
+                ("scope", "write"),
+            ],
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(json_body(resp).await["scope"], "write");
+
+    let resp = app
+        .oneshot(get_request("/.well-known/openid-configuration"))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    assert_eq!(body["issuer"], "https://auth.example.com");
+    assert_eq!(
+        body["token_endpoint"],
+        "https://auth.example.com/oauth/token"
+    );
+}
+
+/// Denies tokens to the `blocked` client.
+struct BlockClient;
+
+#[async_trait]
+impl TokenIssuancePolicy for BlockClient {
+    async fn check(&self, issuance: &mut TokenIssuance) -> Result<(), OAuth2Error> {
+        if issuance.client_id() == "blocked" {
+            return Err(OAuth2Error::access_denied("client is suspended"));
+        }
+        Ok(())
+    }
+}
+
+fn first_party(client_id: &str, tenant_id: Option<&str>) -> Client {
+    let mut client = Client::new(
+        client_id.to_string(),
+        "first_party_secret".to_string(),
+        vec!["https://app.example/cb".to_string()],
+        vec!["password".to_string(), "refresh_token".to_string()],
+        "read write".to_string(),
+        client_id.to_string(),
+    );
+    client.tenant_id = tenant_id.map(str::to_string);
+    client
+}
+
+fn password_grant<'a>(client_id: &'a str, username: &'a str) -> [(&'a str, &'a str); 5] {
+    [
+        ("grant_type", "password"),
+        ("client_id", client_id),
+        ("client_secret", "first_party_secret"),
+        ("username", username),
+        ("password", "user_password"),
+    ]
+}
+
+#[tokio::test]
+async fn axum_router_serves_password_refresh_tenants_and_sessions() {
+    let state = setup_state().await;
+    let storage = state.storage().clone();
+    let mut tenant = Tenant::new(
+        "acme".to_string(),
+        "Acme".to_string(),
+        "acme_signing_secret".to_string(),
+    );
+    storage.save_tenant(&tenant).await.expect("save tenant");
+    tenant.id = "closed".to_string();
+    tenant.enabled = false;
+    storage.save_tenant(&tenant).await.expect("save tenant");
+    for client in [
+        first_party("first_party", None),
+        first_party("blocked", None),
+        first_party("acme_app", Some("acme")),
+    ] {
+        storage.save_client(&client).await.expect("save client");
+    }
+    let now = chrono::Utc::now();
+    for (username, tenant_id) in [("bob", None), ("carol", Some("acme"))] {
+        storage
+            .save_user(&User {
+                id: username.to_string(),
+                username: username.to_string(),
+                password_hash: hash_password("user_password").unwrap(),
+                email: format!("{username}@example.test"),
+                email_verified: true,
+                enabled: true,
+                created_at: now,
+                updated_at: now,
+                tenant_id: tenant_id.map(str::to_string),
+            })
+            .await
+            .expect("save user");
+    }
+    let sessions = Arc::new(InMemorySessionStore::new());
+    for (id, user) in [("current", "alice"), ("laptop", "alice"), ("other", "bob")] {
+        sessions
+            .save(&UserSession {
+                id: id.to_string(),
+                user_id: Some(user.to_string()),
+                state: "{}".to_string(),
+                created_at: now,
+                last_seen_at: now,
+                idle_expires_at: now + chrono::Duration::hours(1),
+                expires_at: now + chrono::Duration::hours(8),
+            })
+            .await
+            .unwrap();
+    }
+    let state = state
+        .with_grants(GrantsConfig {
+            password: true,
+            refresh_token: true,
+            ..Default::default()
+        })
+        .with_issuance_policies(vec![Arc::new(BlockClient)])
+        .with_audit(Arc::new(StorageAuditSink::new(storage.clone())))
+        .with_session_store(sessions.clone());
+    let app = app(state, Some("alice")).layer(Extension(CurrentSession("current".to_string())));
+
+    let resp = app
+        .clone()
+        .oneshot(form("/oauth/token", &password_grant("first_party", "bob")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = json_body(resp).await;
+    let refresh_token = body["refresh_token"].as_str().expect("refresh token");
+
+    // Refresh tokens rotate; the old one stops working.
+    let refresh = [
+        ("grant_type", "refresh_token"),
+        ("client_id", "first_party"),
+        ("client_secret", "first_party_secret"),
+        ("refresh_token", refresh_token),
+    ];
+    let resp = app
+        .clone()
+        .oneshot(form("/oauth/token", &refresh))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let rotated = json_body(resp).await;
+    assert_ne!(rotated["refresh_token"].as_str(), Some(refresh_token));
+    let resp = app
+        .clone()
+        .oneshot(form("/oauth/token", &refresh))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(json_body(resp).await["error"], "invalid_grant");
+
+    // Issuance policies run for axum too.
+    let resp = app
+        .clone()
+        .oneshot(form("/oauth/token", &password_grant("blocked", "bob")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    assert_eq!(json_body(resp).await["error"], "access_denied");
+
+    // Tenant clients only exist under their tenant's prefix.
+    let resp = app
+        .clone()
+        .oneshot(form("/oauth/token", &password_grant("acme_app", "carol")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let resp = app
+        .clone()
+        .oneshot(form(
+            "/t/acme/oauth/token",
+            &password_grant("acme_app", "carol"),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let access_token = json_body(resp).await["access_token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let resp = app
+        .clone()
+        .oneshot(form(
+            "/t/acme/oauth/introspect",
+            &[
+                ("token", access_token.as_str()),
+                ("client_id", "acme_app"),
+                ("client_secret", "first_party_secret"),
+            ],
+        ))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    assert_eq!(body["active"], true);
+    assert_eq!(body["sub"], "carol");
+    for unknown in ["/t/nobody/oauth/token", "/t/closed/oauth/token"] {
+        let resp = app
+            .clone()
+            .oneshot(form(unknown, &password_grant("acme_app", "carol")))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+    let resp = app
+        .clone()
+        .oneshot(get_request("/t/acme/.well-known/openid-configuration"))
+        .await
+        .unwrap();
+    let body = json_body(resp).await;
+    assert_eq!(body["issuer"], "https://auth.example.com/t/acme");
+    assert!(body["grant_types_supported"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("password")));
+
+    let records = storage
+        .list_audit_records(&AuditQuery {
+            action: Some(AuditAction::TokenIssued),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(records
+        .iter()
+        .any(|r| r.client_id.as_deref() == Some("blocked")
+            && r.detail.as_deref() == Some("access_denied")));
+    assert!(records.iter().any(|r| r.actor.as_deref() == Some("carol")));
+
+    let resp = app
+        .clone()
+        .oneshot(get_request("/auth/sessions"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let listed = json_body(resp).await;
+    let listed = listed["sessions"].as_array().unwrap();
+    assert_eq!(listed.len(), 2);
+    assert!(listed
+        .iter()
+        .any(|s| s["id"] == "current" && s["current"] == true));
+
+    // Other users' sessions look missing.
+    let delete = |id: &str| {
+        Request::delete(format!("/auth/sessions/{id}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+    let resp = app.clone().oneshot(delete("other")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let resp = app.oneshot(delete("laptop")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    assert!(sessions.get("laptop").await.unwrap().is_none());
+    assert!(sessions.get("other").await.unwrap().is_some());
+}
@@ -0,0 +1,144 @@
+use std::path::Path;
+
+use oauth2_core::{hash_password, Client};
+use oauth2_server::reconcile::{load_directory, ReconcileError, Reconciler};
+
+use crate::support;
+
+fn write(dir: &Path, name: &str, contents: &str) {
+    std::fs::write(dir.join(name), contents).expect("write manifest");
+}
+
+#[actix_web::test]
+async fn directory_manifests_converge_storage() {
+    let storage = support::memory_storage().await;
+    let dir = tempfile::tempdir().expect("tempdir");
+    let hash = hash_password("billing-secret").expect("hash");
+
+    // Registered by hand: never touched, even when declared.
+    storage
+        .save_client(&Client::new(
+            "legacy".to_string(),
+            "secret".to_string(),
+            vec![],
+            vec!["client_credentials".to_string()],
+            "read".to_string(),
+            "legacy".to_string(),
+        ))
+        .await
+        .unwrap();
+
+    write(
+        dir.path(),
+        "billing.conf",
+        &format!(
+            r#"clients = [
+  {{
+    client_id = "billing"
+    client_secret_hash = "{hash}"
+    grant_types = ["client_credentials"]
+    scope = "billing.read"
+  }}
+]"#
+        ),
+    );
+    write(
+        dir.path(),
+        "web.json",
+        r#"{ "clients": [
+  { "client_id": "web", "name": "Web", "client_secret": "web-secret",
+    "redirect_uris": ["https://web.example/cb"], "grant_types": ["authorization_code"], "scope": "read" },
+  { "client_id": "legacy", "client_secret": "x", "grant_types": ["client_credentials"], "scope": "admin" }
+] }"#,
+    );
+    write(dir.path(), "README.md", "ignored");
+    write(dir.path(), ".hidden.conf", "not even HOCON {");
+
+    let reconciler = Reconciler::new(storage.clone(), "gitops");
+    let report = reconciler
+        .reconcile(&load_directory(dir.path()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(report.created, ["billing", "web"]);
+    assert_eq!(report.conflicts, ["legacy"]);
+
+    let billing = storage.get_client("billing", None).await.unwrap().unwrap();
+    assert_eq!(billing.managed_by.as_deref(), Some("gitops"));
+    assert!(billing.verify_client_secret("billing-secret"));
+    let web = storage.get_client("web", None).await.unwrap().unwrap();
+    assert!(web.verify_client_secret("web-secret"));
+    assert_eq!(web.get_redirect_uris(), ["https://web.example/cb"]);
+    let legacy = storage.get_client("legacy", None).await.unwrap().unwrap();
+    assert_eq!(legacy.scope, "read");
+    assert_eq!(legacy.managed_by, None);
+
+    // Re-running is a no-op; plaintext secrets are verified, not re-hashed.
+    let report = reconciler
+        .reconcile(&load_directory(dir.path()).unwrap())
+        .await
+        .unwrap();
+    assert!(!report.has_changes());
+    assert_eq!(report.unchanged, 2);
+
+    // Drift in storage is repaired, and undeclared managed clients are pruned.
+    let mut drifted = storage.get_client("billing", None).await.unwrap().unwrap();
+    drifted.scope = "billing.read billing.admin".to_string();
+    storage.update_client(&drifted).await.unwrap();
+    std::fs::remove_file(dir.path().join("web.json")).unwrap();
+
+    let report = reconciler
+        .reconcile(&load_directory(dir.path()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(report.updated, ["billing"]);
+    assert_eq!(report.deleted, ["web"]);
+    assert_eq!(
+        storage
+            .get_client("billing", None)
+            .await
+            .unwrap()
+            .unwrap()
+            .scope,
+        "billing.read"
+    );
+    assert!(storage.get_client("web", None).await.unwrap().is_none());
+    assert!(storage.get_client("legacy", None).await.unwrap().is_some());
+}
+
+#[actix_web::test]
+async fn invalid_desired_state_changes_nothing() {
+    let storage = support::memory_storage().await;
+    let dir = tempfile::tempdir().expect("tempdir");
+    write(
+        dir.path(),
+        "a.conf",
+        r#"clients = [{ client_id = "dup", client_secret = "s", grant_types = [], scope = "read" }]"#,
+    );
+    write(
+        dir.path(),
+        "b.conf",
+        r#"clients = [{ client_id = "dup", client_secret = "s", grant_types = [], scope = "read" }]"#,
+    );
+
+    let reconciler = Reconciler::new(storage.clone(), "gitops");
+    let result = reconciler
+        .reconcile(&load_directory(dir.path()).unwrap())
+        .await;
+    assert!(matches!(result, Err(ReconcileError::Invalid(_))));
+    assert!(storage.get_client("dup", None).await.unwrap().is_none());
+
+    // A malformed file fails the whole load rather than pruning its clients.
+    write(dir.path(), "b.conf", r#"clients = [{ client_id = "dup" }]"#);
+    assert!(matches!(
+        load_directory(dir.path()),
+        Err(ReconcileError::Manifest { .. })
+    ));
+
+    let hashless = r#"clients = [{ client_id = "c", client_secret_hash = "plain", grant_types = [], scope = "read" }]"#;
+    write(dir.path(), "b.conf", hashless);
+    std::fs::remove_file(dir.path().join("a.conf")).unwrap();
+    let result = reconciler
+        .reconcile(&load_directory(dir.path()).unwrap())
+        .await;
+    assert!(matches!(result, Err(ReconcileError::Invalid(_))));
+}
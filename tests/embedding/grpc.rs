@@ -0,0 +1,168 @@
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::transport::{Channel, Server};
+use tonic::Code;
+
+use oauth2_axum::OAuth2State;
+use oauth2_core::IssuerKeys;
+use oauth2_grpc::proto::token_service_client::TokenServiceClient;
+use oauth2_grpc::proto::{
+    ClientCredentials, IntrospectTokenRequest, IssueTokenRequest, RevokeTokenRequest,
+    ValidateTokenRequest,
+};
+use oauth2_observability::Metrics;
+
+use crate::support;
+
+async fn serve() -> TokenServiceClient<Channel> {
+    let storage = support::memory_storage().await;
+    support::save_client(
+        &storage,
+        &support::client(
+            "grpc_client",
+            "https://unused.example/cb",
+            &["client_credentials"],
+            "read write",
+        ),
+    )
+    .await;
+    let state = OAuth2State::new(
+        storage,
+        IssuerKeys::from_secret(support::JWT_SECRET),
+        Metrics::new().expect("metrics"),
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let addr = listener.local_addr().expect("local addr");
+    tokio::spawn(
+        Server::builder()
+            .add_service(oauth2_grpc::service(state))
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+    TokenServiceClient::connect(format!("http://{addr}"))
+        .await
+        .expect("connect")
+}
+
+fn credentials() -> Option<ClientCredentials> {
+    Some(ClientCredentials {
+        client_id: "grpc_client".to_string(),
+        client_secret: "grpc_client_secret".to_string(),
+    })
+}
+
+#[tokio::test]
+async fn token_service_issues_validates_introspects_and_revokes() {
+    let mut client = serve().await;
+
+    let err = client
+        .issue_token(IssueTokenRequest {
+            client_id: "grpc_client".to_string(),
+            client_secret: Some("wrong".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::Unauthenticated);
+    assert_eq!(
+        err.metadata().get("oauth2-error").unwrap(),
+        "invalid_client"
+    );
+
+    let issued = client
+        .issue_token(IssueTokenRequest {
+            client_id: "grpc_client".to_string(),
+            client_secret: Some("grpc_client_secret".to_string()),
+            scope: Some("read".to_string()),
+            metadata: [("service".to_string(), "billing".to_string())].into(),
+            ..Default::default()
+        })
+        .await
+        .expect("issue token")
+        .into_inner();
+    assert_eq!(issued.token_type, "Bearer");
+    assert_eq!(issued.scope, "read");
+    let token = issued.access_token;
+
+    let validated = client
+        .validate_token(ValidateTokenRequest {
+            token: token.clone(),
+            required_scope: Some("read".to_string()),
+        })
+        .await
+        .expect("validate token")
+        .into_inner();
+    assert_eq!(validated.client_id, "grpc_client");
+    assert_eq!(validated.subject, "grpc_client");
+
+    let err = client
+        .validate_token(ValidateTokenRequest {
+            token: token.clone(),
+            required_scope: Some("write".to_string()),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::PermissionDenied);
+    assert_eq!(
+        err.metadata().get("oauth2-error").unwrap(),
+        "insufficient_scope"
+    );
+
+    let err = client
+        .validate_token(ValidateTokenRequest {
+            token: "not-a-jwt".to_string(),
+            required_scope: None,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::Unauthenticated);
+
+    let introspection = client
+        .introspect_token(IntrospectTokenRequest {
+            client: credentials(),
+            token: token.clone(),
+        })
+        .await
+        .expect("introspect")
+        .into_inner();
+    assert!(introspection.active);
+    assert_eq!(introspection.client_id.as_deref(), Some("grpc_client"));
+    assert_eq!(introspection.scope.as_deref(), Some("read"));
+
+    let err = client
+        .introspect_token(IntrospectTokenRequest {
+            client: None,
+            token: token.clone(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::Unauthenticated);
+
+    client
+        .revoke_token(RevokeTokenRequest {
+            client: credentials(),
+            token: token.clone(),
+            token_type_hint: None,
+        })
+        .await
+        .expect("revoke");
+
+    let err = client
+        .validate_token(ValidateTokenRequest {
+            token: token.clone(),
+            required_scope: None,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::Unauthenticated);
+    assert_eq!(err.message(), "Token is revoked or expired");
+    let introspection = client
+        .introspect_token(IntrospectTokenRequest {
+            client: credentials(),
+            token,
+        })
+        .await
+        .expect("introspect")
+        .into_inner();
+    assert!(!introspection.active);
+}
@@ -0,0 +1,13 @@
+//! Embedding the server: builder, scopes, axum, gRPC, relying party and seeding.
+
+#[path = "../support/mod.rs"]
+mod support;
+
+mod actix_scope;
+mod axum;
+mod client_reconcile;
+mod grpc;
+mod oauth2_client;
+mod seed_data;
+mod server_builder;
+mod testing_fakes;
@@ -0,0 +1,97 @@
+use axum::{Extension, Router};
+use tokio::net::TcpListener;
+
+use oauth2_axum::extract::ResourceOwner;
+use oauth2_axum::OAuth2State;
+use oauth2_client::{AuthorizationResponse, OAuth2Client};
+use oauth2_core::IssuerKeys;
+use oauth2_observability::Metrics;
+
+use crate::support;
+
+const REDIRECT_URI: &str = "https://app.example/cb";
+
+/// Serve the axum endpoints on a local port; returns the issuer URL.
+async fn serve() -> String {
+    let storage = support::memory_storage().await;
+    support::save_client(
+        &storage,
+        &support::client(
+            "rp",
+            REDIRECT_URI,
+            &["authorization_code", "client_credentials"],
+            "read write",
+        ),
+    )
+    .await;
+    support::save_user_with_id(&storage, "alice", "not_used", true).await;
+    let state = OAuth2State::new(
+        storage,
+        IssuerKeys::from_secret(support::JWT_SECRET),
+        Metrics::new().expect("metrics"),
+    );
+    let app: Router = oauth2_axum::router(state).layer(Extension(ResourceOwner("alice".into())));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let addr = listener.local_addr().expect("local addr");
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn client_runs_the_code_flow_and_client_credentials_against_the_server() {
+    let issuer = serve().await;
+    let client = OAuth2Client::discover(&issuer, "rp")
+        .await
+        .expect("discover")
+        .with_client_secret("rp_secret")
+        .with_redirect_uri(REDIRECT_URI);
+    assert_eq!(
+        client.metadata().token_endpoint,
+        format!("{issuer}/oauth/token")
+    );
+
+    let request = client.authorize_url("read").expect("authorize url");
+    let redirect = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+        .get(request.url.clone())
+        .send()
+        .await
+        .expect("authorize");
+    let location = url::Url::parse(
+        redirect.headers()["location"]
+            .to_str()
+            .expect("location header"),
+    )
+    .expect("callback url");
+    let response = AuthorizationResponse::from_query(location.query().unwrap_or_default())
+        .expect("authorization response");
+    assert_eq!(response.iss.as_deref(), Some(issuer.as_str()));
+
+    let tokens = client
+        .exchange_code(&request, &response)
+        .await
+        .expect("exchange code");
+    assert_eq!(tokens.token_type, "Bearer");
+    assert_eq!(tokens.scope.as_deref(), Some("read"));
+
+    // The code is single use; the server's error comes back as is.
+    let err = client.exchange_code(&request, &response).await.unwrap_err();
+    assert_eq!(err.error, "invalid_grant");
+
+    let tokens = client
+        .client_credentials(Some("write"))
+        .await
+        .expect("client credentials");
+    assert_eq!(tokens.scope.as_deref(), Some("write"));
+
+    let err = client
+        .clone()
+        .with_client_secret("wrong")
+        .client_credentials(None)
+        .await
+        .unwrap_err();
+    assert_eq!(err.error, "invalid_client");
+}
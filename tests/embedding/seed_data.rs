@@ -0,0 +1,217 @@
+use oauth2_config::Config;
+use oauth2_core::{verify_password, Role};
+use oauth2_server::seed::{self, SeedData, SeedError};
+use oauth2_server::ServerBuilder;
+
+use crate::support;
+
+fn seed_data(json: serde_json::Value) -> SeedData {
+    serde_json::from_value(json).expect("seed data")
+}
+
+#[actix_web::test]
+async fn seeding_creates_missing_entries_once() {
+    let storage = support::memory_storage().await;
+    storage
+        .save_role(&Role::new("auditor".to_string(), None))
+        .await
+        .unwrap();
+    let data = seed_data(serde_json::json!({
+        "roles": [{ "name": "admin", "description": "Administrators" }],
+        "groups": [{ "name": "staff" }],
+        "clients": [{
+            "client_id": "web",
+            "client_secret": "web-secret",
+            "redirect_uris": ["https://web.example/cb"],
+            "grant_types": ["authorization_code"],
+            "scope": "openid"
+        }],
+        "users": [{
+            "username": "alice",
+            "email": "alice@example.com",
+            "password": "correct horse battery",
+            "roles": ["admin", "auditor"],
+            "groups": ["staff"]
+        }]
+    }));
+
+    let report = seed::apply(&storage, &data).await.expect("apply");
+    assert_eq!(
+        report.created,
+        vec!["role:admin", "group:staff", "client:web", "user:alice"]
+    );
+
+    let alice = storage
+        .get_user_by_username("alice", None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(verify_password(
+        "correct horse battery",
+        &alice.password_hash
+    ));
+    assert_eq!(
+        storage.list_user_roles(&alice.id).await.unwrap(),
+        vec!["admin", "auditor"]
+    );
+    assert_eq!(
+        storage.list_user_groups(&alice.id).await.unwrap(),
+        vec!["staff"]
+    );
+    let web = storage.get_client("web", None).await.unwrap().unwrap();
+    assert!(web.verify_client_secret("web-secret"));
+    assert_eq!(web.managed_by, None);
+
+    // Changes made after seeding survive a second pass.
+    storage.delete_role("admin").await.unwrap();
+    storage
+        .save_role(&Role::new("admin".to_string(), Some("Renamed".to_string())))
+        .await
+        .unwrap();
+    let report = seed::apply(&storage, &data).await.expect("apply again");
+    assert!(report.created.is_empty());
+    assert_eq!(report.unchanged, 4);
+    let roles = storage.list_roles().await.unwrap();
+    let admin = roles.iter().find(|role| role.name == "admin").unwrap();
+    assert_eq!(admin.description.as_deref(), Some("Renamed"));
+}
+
+#[actix_web::test]
+async fn unknown_roles_reject_the_whole_seed() {
+    let storage = support::memory_storage().await;
+    let data = seed_data(serde_json::json!({
+        "clients": [{
+            "client_id": "web",
+            "client_secret": "web-secret",
+            "grant_types": ["client_credentials"],
+            "scope": "read"
+        }],
+        "users": [{
+            "username": "bob",
+            "email": "bob@example.com",
+            "password": "correct horse battery",
+            "roles": ["missing"]
+        }]
+    }));
+
+    let err = seed::apply(&storage, &data).await.unwrap_err();
+    assert!(matches!(err, SeedError::Invalid(ref message) if message.contains("missing")));
+    assert!(storage.get_client("web", None).await.unwrap().is_none());
+}
+
+#[actix_web::test]
+async fn export_round_trips_through_apply() {
+    let source = support::memory_storage().await;
+    let data = seed_data(serde_json::json!({
+        "roles": [{ "name": "admin" }],
+        "users": [{
+            "username": "carol",
+            "email": "carol@example.com",
+            "password": "correct horse battery",
+            "email_verified": true,
+            "roles": ["admin"]
+        }]
+    }));
+    seed::apply(&source, &data).await.expect("apply");
+
+    let exported = seed::export(&source).await.expect("export");
+    let carol = &exported.users[0];
+    assert_eq!(carol.password, None);
+    assert!(carol.password_hash.is_some());
+    assert!(carol.email_verified);
+
+    let json = serde_json::to_value(&exported).unwrap();
+    let target = support::memory_storage().await;
+    seed::apply(&target, &seed_data(json))
+        .await
+        .expect("import");
+    let imported = target
+        .get_user_by_username("carol", None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(verify_password(
+        "correct horse battery",
+        &imported.password_hash
+    ));
+    assert_eq!(
+        target.list_user_roles(&imported.id).await.unwrap(),
+        vec!["admin"]
+    );
+}
+
+const SEED_YAML: &str = r#"
+roles:
+  - name: admin
+clients:
+  - client_id: demo
+    client_secret: demo-secret
+    redirect_uris: ["http://localhost:3000/callback"]
+    grant_types: [authorization_code, client_credentials]
+    scope: openid read
+users:
+  - username: demo
+    email: demo@example.com
+    password: demo-password-123
+    email_verified: true
+    roles: [admin]
+"#;
+
+#[actix_web::test]
+async fn seed_file_is_applied_when_the_server_starts() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("seed.yaml");
+    std::fs::write(&path, SEED_YAML).expect("write seed");
+    let storage = support::memory_storage().await;
+
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.seed.file = Some(path.display().to_string());
+    for _ in 0..2 {
+        ServerBuilder::new(config.clone())
+            .with_storage(storage.clone())
+            .build()
+            .await
+            .expect("build server");
+    }
+
+    let demo = storage.get_client("demo", None).await.unwrap().unwrap();
+    assert!(demo.verify_client_secret("demo-secret"));
+    assert_eq!(
+        demo.get_grant_types(),
+        vec!["authorization_code", "client_credentials"]
+    );
+    let user = storage
+        .get_user_by_username("demo", None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(user.email_verified);
+    assert!(verify_password("demo-password-123", &user.password_hash));
+    assert_eq!(
+        storage.list_user_roles(&user.id).await.unwrap(),
+        vec!["admin"]
+    );
+}
+
+#[actix_web::test]
+async fn invalid_seed_file_stops_startup() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("seed.yml");
+    std::fs::write(
+        &path,
+        "users:\n  - username: nopass\n    email: a@example.com\n",
+    )
+    .expect("write seed");
+
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.seed.file = Some(path.display().to_string());
+    let err = ServerBuilder::new(config)
+        .with_storage(support::memory_storage().await)
+        .build()
+        .await
+        .err()
+        .expect("startup fails");
+    assert!(err.to_string().contains("password"), "{err}");
+}
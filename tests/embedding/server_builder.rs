@@ -0,0 +1,516 @@
+use actix_web::{test, web, App, HttpResponse};
+use async_trait::async_trait;
+use base64::Engine;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+
+use oauth2_config::{Config, ConfigSource, EventReadinessPolicy, LegacyJwtConfig};
+use oauth2_core::{Claims, OAuth2Error, TokenMetadata};
+use oauth2_events::{EventType, InMemoryEventLogger};
+use oauth2_ports::{ClaimsEnricher, DynStorage};
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+/// Adds a tenant claim and tags the stored token with where it was issued.
+struct TenantEnricher;
+
+#[async_trait]
+impl ClaimsEnricher for TenantEnricher {
+    async fn enrich(
+        &self,
+        claims: &mut Claims,
+        metadata: &mut TokenMetadata,
+    ) -> Result<(), OAuth2Error> {
+        claims.set_claim("tenant", "acme")?;
+        metadata.insert("issued_by", "embedded")
+    }
+}
+
+async fn setup_storage() -> DynStorage {
+    let storage = support::memory_storage().await;
+    support::save_client(
+        &storage,
+        &support::client(
+            "embedded_client",
+            "https://unused.example/cb",
+            &["client_credentials"],
+            "read",
+        ),
+    )
+    .await;
+    storage
+}
+
+fn jwt_payload(token: &str) -> Value {
+    let payload = token.split('.').nth(1).expect("jwt payload");
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload)
+        .expect("base64 payload");
+    serde_json::from_slice(&bytes).expect("json payload")
+}
+
+#[actix_web::test]
+async fn embedded_server_mounts_selected_groups_with_injected_extensions() {
+    let storage = setup_storage().await;
+    let events = Arc::new(InMemoryEventLogger::new(100));
+    let mut config = Config::default();
+    config.events.enabled = false;
+
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage.clone())
+        .with_event_plugin(events.clone())
+        .with_claims_enricher(Arc::new(TenantEnricher))
+        .with_endpoints([EndpointGroup::OAuth, EndpointGroup::Observability])
+        .build()
+        .await
+        .expect("build server");
+    assert_eq!(
+        oauth2.endpoints().iter().copied().collect::<Vec<_>>(),
+        [EndpointGroup::OAuth, EndpointGroup::Observability]
+    );
+
+    let app = test::init_service(
+        App::new()
+            .configure(|cfg| oauth2.configure(cfg))
+            .route("/app/ping", web::get().to(HttpResponse::Ok)),
+    )
+    .await;
+
+    // The embedder's own routes live alongside the mounted groups.
+    let req = test::TestRequest::get().uri("/app/ping").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    let req = test::TestRequest::get().uri("/health").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    // Groups that were not selected are not mounted.
+    for uri in ["/.well-known/openid-configuration", "/admin/users", "/"] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404, "{uri}");
+    }
+
+    let req = test::TestRequest::post()
+        .uri("/oauth/token")
+        .set_form([
+            ("grant_type", "client_credentials"),
+            ("client_id", "embedded_client"),
+            ("client_secret", "embedded_client_secret"),
+        ])
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    let access_token = body["access_token"].as_str().unwrap();
+
+    assert_eq!(jwt_payload(access_token)["tenant"], "acme");
+    let stored = storage
+        .get_token_by_access_token(access_token)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.metadata.get("issued_by"), Some("embedded"));
+
+    // Injected plugins receive events even though `events.enabled` is false.
+    let mut delivered = false;
+    for _ in 0..50 {
+        if events
+            .get_events()
+            .iter()
+            .any(|e| e.event.event_type == EventType::TokenCreated)
+        {
+            delivered = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(delivered, "TokenCreated should reach the injected plugin");
+}
+
+#[actix_web::test]
+async fn builder_defaults_to_every_group_and_a_programmatic_source() {
+    let storage = setup_storage().await;
+    let oauth2 = ServerBuilder::new(Config::default())
+        .with_storage(storage)
+        .without_endpoint(EndpointGroup::Login)
+        .build()
+        .await
+        .expect("build server");
+    assert!(!oauth2.endpoints().contains(&EndpointGroup::Login));
+    assert_eq!(oauth2.effective_config().source, ConfigSource::Programmatic);
+
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+    let req = test::TestRequest::get().uri("/auth/login").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+    let req = test::TestRequest::get()
+        .uri("/.well-known/openid-configuration")
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+}
+
+#[actix_web::test]
+async fn malformed_legacy_key_deadline_fails_the_build_naming_the_setting() {
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.jwt.legacy = Some(LegacyJwtConfig {
+        issuer: "old_issuer".to_string(),
+        secret: "old_secret".to_string(),
+        accept_until: Some("next tuesday".to_string()),
+    });
+    let err = ServerBuilder::new(config)
+        .with_storage(setup_storage().await)
+        .build()
+        .await
+        .err()
+        .expect("build fails");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    let message = err.to_string();
+    assert!(message.contains("jwt.legacy.accept_until"), "{message}");
+    assert!(message.contains("old_issuer"), "{message}");
+}
+
+/// The entry for `name` in a health report.
+fn component<'a>(report: &'a Value, name: &str) -> &'a Value {
+    report["components"]
+        .as_array()
+        .and_then(|components| components.iter().find(|c| c["name"] == name))
+        .unwrap_or_else(|| panic!("no {name} component in {report}"))
+}
+
+/// An upstream dependency that is always down.
+struct Unreachable;
+
+#[async_trait]
+impl oauth2_observability::HealthCheck for Unreachable {
+    async fn check(&self) -> Result<(), String> {
+        Err("connection refused".to_string())
+    }
+}
+
+#[actix_web::test]
+async fn health_probes_report_registered_components() {
+    let healthy = ServerBuilder::new(Config::default())
+        .with_storage(setup_storage().await)
+        .with_endpoints([EndpointGroup::Observability])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| healthy.configure(cfg))).await;
+    for uri in ["/health/live", "/health/startup", "/ready"] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200, "{uri}");
+    }
+    let req = test::TestRequest::get().uri("/health/ready").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["status"], "healthy");
+    assert_eq!(body["components"][0]["name"], "storage");
+    assert_eq!(body["components"][0]["status"], "healthy");
+    assert!(body["components"][0]["latency_ms"].is_u64());
+
+    let failing = ServerBuilder::new(Config::default())
+        .with_storage(setup_storage().await)
+        .with_health_check("upstream_idp", Arc::new(Unreachable))
+        .with_endpoints([EndpointGroup::Observability])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| failing.configure(cfg))).await;
+    let req = test::TestRequest::get().uri("/health/live").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    let req = test::TestRequest::get().uri("/health/ready").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 503);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["status"], "unhealthy");
+    assert_eq!(
+        component(&body, "upstream_idp")["error"],
+        "connection refused"
+    );
+}
+
+/// An event backend whose broker is unreachable.
+struct UnreachableBroker;
+
+#[async_trait]
+impl oauth2_events::EventPlugin for UnreachableBroker {
+    async fn emit(&self, _envelope: &oauth2_events::EventEnvelope) -> Result<(), String> {
+        Err("broker unreachable".to_string())
+    }
+
+    fn name(&self) -> &str {
+        "broker"
+    }
+
+    async fn health_check(&self) -> bool {
+        false
+    }
+}
+
+#[actix_web::test]
+async fn unhealthy_event_backends_warn_or_fail_readiness_per_config() {
+    for (policy, status, report) in [
+        (EventReadinessPolicy::Warn, 200, "degraded"),
+        (EventReadinessPolicy::Fail, 503, "unhealthy"),
+    ] {
+        let mut config = Config::default();
+        config.events.enabled = false;
+        config.events.readiness = policy;
+        let oauth2 = ServerBuilder::new(config)
+            .with_storage(setup_storage().await)
+            .with_event_plugin(Arc::new(UnreachableBroker))
+            .with_endpoints([EndpointGroup::Observability])
+            .build()
+            .await
+            .expect("build server");
+        let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+        let req = test::TestRequest::get().uri("/health/ready").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), status, "{policy:?}");
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["status"], report);
+        assert_eq!(
+            component(&body, "events")["error"],
+            "unhealthy event plugins: broker"
+        );
+    }
+}
+
+#[actix_web::test]
+async fn version_endpoint_and_health_reports_share_build_info() {
+    let oauth2 = ServerBuilder::new(Config::default())
+        .with_storage(setup_storage().await)
+        .with_endpoints([EndpointGroup::Observability])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    let req = test::TestRequest::get().uri("/version").to_request();
+    let version: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        version["version"],
+        oauth2_server::build_info().version.as_str()
+    );
+    assert!(!version["git_sha"].as_str().unwrap().is_empty());
+    assert!(chrono::DateTime::parse_from_rfc3339(version["built_at"].as_str().unwrap()).is_ok());
+    assert!(version["features"].is_array());
+
+    for uri in ["/health/live", "/health/ready"] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let report: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(report["build"], version, "{uri}");
+    }
+}
+
+/// Status line of `GET path` over a fresh connection.
+async fn get_status<S>(mut stream: S, path: &str) -> String
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let request = format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n");
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response.lines().next().unwrap_or_default().to_string()
+}
+
+fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// Connect to `address`, waiting for the server to start listening.
+async fn connect_tcp(
+    address: &str,
+    server: &actix_web::rt::task::JoinHandle<std::io::Result<()>>,
+) -> tokio::net::TcpStream {
+    loop {
+        match tokio::net::TcpStream::connect(address).await {
+            Ok(stream) => return stream,
+            Err(_) if !server.is_finished() => {
+                actix_web::rt::time::sleep(Duration::from_millis(20)).await
+            }
+            Err(err) => panic!("server stopped: {err}"),
+        }
+    }
+}
+
+#[cfg(unix)]
+#[actix_web::test]
+async fn run_serves_every_configured_listener() {
+    use oauth2_config::ListenerConfig;
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("oauth2.sock");
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.server.listeners = vec![
+        ListenerConfig {
+            unix_socket: Some(socket.to_string_lossy().into_owned()),
+            unix_socket_mode: Some("660".to_string()),
+            ..ListenerConfig::default()
+        },
+        ListenerConfig {
+            address: Some("127.0.0.1:0".to_string()),
+            ..ListenerConfig::default()
+        },
+    ];
+
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(setup_storage().await)
+        .with_endpoints([EndpointGroup::Observability])
+        .build()
+        .await
+        .expect("build server");
+    let server = actix_web::rt::spawn(oauth2.run());
+
+    let stream = loop {
+        match tokio::net::UnixStream::connect(&socket).await {
+            Ok(stream) => break stream,
+            Err(_) if !server.is_finished() => {
+                actix_web::rt::time::sleep(Duration::from_millis(20)).await
+            }
+            Err(err) => panic!("server stopped: {err}"),
+        }
+    };
+    let mode = std::fs::metadata(&socket).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o660);
+
+    assert_eq!(get_status(stream, "/health/live").await, "HTTP/1.1 200 OK");
+    server.abort();
+}
+
+#[actix_web::test]
+async fn admin_port_serves_admin_and_observability_endpoints_only() {
+    let public = format!("127.0.0.1:{}", free_port());
+    let admin_port = free_port();
+    let admin = format!("127.0.0.1:{admin_port}");
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.server.host = "127.0.0.1".to_string();
+    config.server.port = public.rsplit(':').next().unwrap().parse().unwrap();
+    config.server.admin_port = Some(admin_port);
+
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(setup_storage().await)
+        .with_endpoints([
+            EndpointGroup::Discovery,
+            EndpointGroup::Admin,
+            EndpointGroup::Observability,
+        ])
+        .build()
+        .await
+        .expect("build server");
+    let server = actix_web::rt::spawn(oauth2.run());
+
+    let discovery = "/.well-known/openid-configuration";
+    for (address, path, status) in [
+        (&public, discovery, "HTTP/1.1 200 OK"),
+        (&public, "/health/live", "HTTP/1.1 404 Not Found"),
+        (&public, "/metrics", "HTTP/1.1 404 Not Found"),
+        (&public, "/admin/config", "HTTP/1.1 404 Not Found"),
+        (&admin, "/health/live", "HTTP/1.1 200 OK"),
+        (&admin, "/metrics", "HTTP/1.1 200 OK"),
+        (&admin, "/admin/config", "HTTP/1.1 401 Unauthorized"),
+        (&admin, discovery, "HTTP/1.1 404 Not Found"),
+    ] {
+        let stream = connect_tcp(address, &server).await;
+        assert_eq!(get_status(stream, path).await, status, "{address}{path}");
+    }
+    server.abort();
+}
+
+#[actix_web::test]
+async fn scope_mounts_the_server_under_a_prefix() {
+    let storage = setup_storage().await;
+    let mut config = Config::default();
+    config.events.enabled = false;
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage)
+        .with_issuer("https://app.example/sso")
+        .disable_admin()
+        .build()
+        .await
+        .expect("build server");
+    assert!(!oauth2.endpoints().contains(&EndpointGroup::Admin));
+    assert_eq!(oauth2.config().jwt.issuer, "https://app.example/sso");
+
+    let app = test::init_service(
+        App::new()
+            .service(oauth2.scope("/sso"))
+            .route("/app/ping", web::get().to(HttpResponse::Ok)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/app/ping").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    for uri in ["/sso/admin/config", "/oauth/token", "/sso/sso/oauth/token"] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404, "{uri}");
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/sso/.well-known/openid-configuration")
+        .to_request();
+    let discovery: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(discovery["issuer"], "https://app.example/sso");
+    assert_eq!(
+        discovery["token_endpoint"],
+        "https://app.example/sso/oauth/token"
+    );
+
+    let req = test::TestRequest::post()
+        .uri("/sso/oauth/token")
+        .set_form([
+            ("grant_type", "client_credentials"),
+            ("client_id", "embedded_client"),
+            ("client_secret", "embedded_client_secret"),
+        ])
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    let access_token = body["access_token"].as_str().unwrap();
+    assert_eq!(jwt_payload(access_token)["iss"], "https://app.example/sso");
+}
+
+#[actix_web::test]
+async fn bind_returns_a_running_server_with_a_handle() {
+    let address = format!("127.0.0.1:{}", free_port());
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.server.host = "127.0.0.1".to_string();
+    config.server.port = address.rsplit(':').next().unwrap().parse().unwrap();
+
+    let oauth2 = ServerBuilder::new(config.clone())
+        .with_storage(setup_storage().await)
+        .with_endpoints([EndpointGroup::Observability])
+        .build()
+        .await
+        .expect("build server");
+    let server = oauth2.bind().expect("bind");
+    let handle = server.handle();
+    let server = actix_web::rt::spawn(server);
+
+    let stream = connect_tcp(&address, &server).await;
+    assert_eq!(get_status(stream, "/health/live").await, "HTTP/1.1 200 OK");
+    handle.stop(true).await;
+    server.await.unwrap().expect("clean shutdown");
+
+    // A separate admin listener needs `run`, which serves both.
+    config.server.admin_port = Some(free_port());
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(setup_storage().await)
+        .build()
+        .await
+        .expect("build server");
+    let err = oauth2.bind().err().expect("admin_port is refused");
+    assert!(err.to_string().contains("server.admin_port"), "{err}");
+}
@@ -0,0 +1,204 @@
+use actix::Actor;
+use chrono::{Duration, TimeZone, Utc};
+use std::sync::Arc;
+
+use oauth2_actix::actors::{CreateToken, TokenActor, ACCESS_TOKEN_TTL_SECS};
+use oauth2_actix::deadline::Deadline;
+use oauth2_core::{AuthorizationCode, IssuerKeys, OAuth2Error, TokenMetadata};
+use oauth2_events::testing::RecordingEventBus;
+use oauth2_events::EventType;
+use oauth2_ports::testing::{FakeCache, FakeStorage, FixedClock};
+use oauth2_ports::{Cache, Storage};
+
+/// The fake must behave like the real backends, or tests built on it prove nothing.
+#[tokio::test]
+async fn fake_storage_satisfies_the_storage_contract() -> Result<(), Box<dyn std::error::Error>> {
+    oauth2_storage_tests::run_storage_contract(&FakeStorage::new()).await
+}
+
+fn create_token() -> CreateToken {
+    CreateToken {
+        grant_type: "password".to_string(),
+        user_id: Some("alice".to_string()),
+        client_id: "app".to_string(),
+        scope: "read".to_string(),
+        include_refresh: false,
+        metadata: TokenMetadata::default(),
+        grant_id: None,
+        consume_code: None,
+        redeem_refresh_token: None,
+        tenant: None,
+        context: None,
+        span: tracing::Span::none(),
+        deadline: Deadline::none(),
+    }
+}
+
+#[actix_rt::test]
+async fn issued_tokens_and_events_are_deterministic() {
+    let issued_at = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+    let clock = Arc::new(FixedClock::new(issued_at));
+    let storage = Arc::new(FakeStorage::with_clock(clock.clone()));
+    let bus = RecordingEventBus::new();
+    let token_actor = TokenActor::with_events(storage.clone(), "secret".to_string(), bus.handle())
+        .with_clock(clock.clone())
+        .start();
+
+    let token = token_actor.send(create_token()).await.unwrap().unwrap();
+    assert_eq!(token.created_at, issued_at);
+    assert_eq!(
+        token.expires_at,
+        issued_at + Duration::seconds(ACCESS_TOKEN_TTL_SECS)
+    );
+    let (claims, _) = IssuerKeys::from_secret("secret")
+        .verify_ignoring_expiry(&token.access_token)
+        .unwrap();
+    assert_eq!(claims.iat, issued_at.timestamp());
+    assert_eq!(claims.exp, issued_at.timestamp() + ACCESS_TOKEN_TTL_SECS);
+
+    let stored = storage.tokens();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].access_token, token.access_token);
+
+    let events = bus.wait_for(1, std::time::Duration::from_secs(5)).await;
+    assert_eq!(bus.event_types(), [EventType::TokenCreated]);
+    assert_eq!(events[0].event.client_id.as_deref(), Some("app"));
+    assert_eq!(events[0].event.user_id.as_deref(), Some("alice"));
+}
+
+#[actix_rt::test]
+async fn scripted_storage_failures_surface_to_callers() {
+    let storage = Arc::new(FakeStorage::new());
+    let bus = RecordingEventBus::new();
+    let token_actor =
+        TokenActor::with_events(storage.clone(), "secret".to_string(), bus.handle()).start();
+
+    storage.failures().fail_next(
+        "save_token",
+        OAuth2Error::temporarily_unavailable("storage is down"),
+    );
+    let err = token_actor.send(create_token()).await.unwrap().unwrap_err();
+    assert_eq!(err.error, "temporarily_unavailable");
+    assert!(storage.tokens().is_empty());
+
+    // Only the next call fails.
+    token_actor.send(create_token()).await.unwrap().unwrap();
+    assert_eq!(storage.tokens().len(), 1);
+    assert_eq!(storage.failures().calls(), ["save_token", "save_token"]);
+
+    storage.failures().fail_always(
+        "get_client",
+        OAuth2Error::temporarily_unavailable("storage is down"),
+    );
+    assert!(storage.get_client("app", None).await.is_err());
+    assert!(storage.get_client("app", None).await.is_err());
+    storage.failures().clear();
+    assert!(storage.get_client("app", None).await.unwrap().is_none());
+
+    let events = bus.wait_for(1, std::time::Duration::from_secs(5)).await;
+    assert_eq!(events.len(), 1, "failed issuance emits no event");
+}
+
+#[actix_rt::test]
+async fn an_authorization_code_is_exchanged_for_at_most_one_token() {
+    let storage = Arc::new(FakeStorage::new());
+    let bus = RecordingEventBus::new();
+    let token_actor =
+        TokenActor::with_events(storage.clone(), "secret".to_string(), bus.handle()).start();
+    storage
+        .save_authorization_code(&AuthorizationCode::new(
+            "code".to_string(),
+            "app".to_string(),
+            "alice".to_string(),
+            "https://rp.example/cb".to_string(),
+            "read".to_string(),
+            None,
+            None,
+        ))
+        .await
+        .unwrap();
+    let exchange = || CreateToken {
+        consume_code: Some("code".to_string()),
+        ..create_token()
+    };
+
+    // A failed token save leaves the code usable.
+    storage.failures().fail_next(
+        "commit",
+        OAuth2Error::temporarily_unavailable("storage is down"),
+    );
+    let err = token_actor.send(exchange()).await.unwrap().unwrap_err();
+    assert_eq!(err.error, "temporarily_unavailable");
+    assert!(!storage.authorization_codes()[0].used);
+
+    token_actor.send(exchange()).await.unwrap().unwrap();
+    let err = token_actor.send(exchange()).await.unwrap().unwrap_err();
+    assert_eq!(err.error, "invalid_grant");
+    assert!(storage.authorization_codes()[0].used);
+    assert_eq!(storage.tokens().len(), 1);
+
+    bus.wait_for(2, std::time::Duration::from_secs(5)).await;
+    assert_eq!(
+        bus.event_types(),
+        [
+            EventType::AuthorizationCodeValidated,
+            EventType::TokenCreated
+        ]
+    );
+}
+
+#[tokio::test]
+async fn fake_cache_expires_entries_by_its_clock() {
+    let clock = Arc::new(FixedClock::default());
+    let cache = FakeCache::with_clock(clock.clone());
+    let ttl = std::time::Duration::from_secs(60);
+
+    assert!(cache.set_if_absent("key", "first", ttl).await.unwrap());
+    assert!(!cache.set_if_absent("key", "second", ttl).await.unwrap());
+    assert_eq!(cache.get("key").await.unwrap().as_deref(), Some("first"));
+
+    clock.advance(Duration::seconds(60));
+    assert!(cache.get("key").await.unwrap().is_none());
+    assert!(cache.set_if_absent("key", "second", ttl).await.unwrap());
+    assert_eq!(cache.keys(), ["key"]);
+
+    cache
+        .failures()
+        .fail_next("get", OAuth2Error::temporarily_unavailable("cache is down"));
+    assert!(cache.get("key").await.is_err());
+    assert_eq!(cache.get("key").await.unwrap().as_deref(), Some("second"));
+}
+
+#[actix_rt::test]
+async fn messages_past_their_deadline_are_not_processed() {
+    let storage = Arc::new(FakeStorage::new());
+    let bus = RecordingEventBus::new();
+    let token_actor =
+        TokenActor::with_events(storage.clone(), "secret".to_string(), bus.handle()).start();
+
+    let expired = Deadline::at(std::time::Instant::now() - std::time::Duration::from_secs(1));
+    let err = token_actor
+        .send(CreateToken {
+            deadline: expired,
+            ..create_token()
+        })
+        .await
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(err.error, "temporarily_unavailable");
+    assert!(storage.failures().calls().is_empty());
+
+    let deadline = Deadline::after(std::time::Duration::from_secs(30));
+    token_actor
+        .send(CreateToken {
+            deadline,
+            ..create_token()
+        })
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(storage.tokens().len(), 1);
+
+    let events = bus.wait_for(1, std::time::Duration::from_secs(5)).await;
+    assert_eq!(events.len(), 1, "only the processed message emits an event");
+}
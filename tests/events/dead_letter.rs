@@ -0,0 +1,126 @@
+use actix_web::{test, App};
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use oauth2_config::{Config, DeadLetterConfig, DeadLetterDestination};
+use oauth2_events::{DeadLetter, EventEnvelope, EventPlugin, EventType};
+use oauth2_ports::testing::FakeStorage;
+use oauth2_ports::Storage;
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+/// A backend that rejects every publish.
+#[derive(Default)]
+struct DownBroker {
+    attempts: AtomicU32,
+}
+
+#[async_trait]
+impl EventPlugin for DownBroker {
+    async fn emit(&self, _envelope: &EventEnvelope) -> Result<(), String> {
+        self.attempts.fetch_add(1, Ordering::SeqCst);
+        Err("broker unavailable".to_string())
+    }
+
+    fn name(&self) -> &str {
+        "broker"
+    }
+}
+
+/// Dead letters written to `path` so far.
+fn dead_letters(path: &std::path::Path) -> Vec<DeadLetter> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("dead letter json"))
+        .collect()
+}
+
+#[actix_web::test]
+async fn events_a_backend_keeps_rejecting_are_retried_then_dead_lettered() {
+    let storage = Arc::new(FakeStorage::new());
+    storage
+        .save_client(&support::client(
+            "app",
+            "https://unused.example/cb",
+            &["client_credentials"],
+            "read",
+        ))
+        .await
+        .expect("save client");
+
+    let dir = std::env::temp_dir().join(format!("dlq-{}", uuid::Uuid::new_v4()));
+    let path = dir.join("events.jsonl");
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.events.retry.max_attempts = 3;
+    config.events.retry.initial_backoff_ms = 10;
+    config.events.dead_letter = Some(DeadLetterConfig {
+        destination: DeadLetterDestination::File,
+        path: Some(path.to_string_lossy().into_owned()),
+        url: None,
+        stream: None,
+        brokers: None,
+        topic: None,
+    });
+
+    let broker = Arc::new(DownBroker::default());
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage)
+        .with_event_plugin(broker.clone())
+        .with_endpoints([EndpointGroup::OAuth])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/oauth/token")
+        .set_form([
+            ("grant_type", "client_credentials"),
+            ("client_id", "app"),
+            ("client_secret", "app_secret"),
+            ("scope", "read"),
+        ])
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+
+    // Client validation and token creation both end up in the dead-letter file.
+    let mut letters = Vec::new();
+    for _ in 0..100 {
+        letters = dead_letters(&path);
+        if letters.len() == 2 {
+            break;
+        }
+        actix_web::rt::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(letters.len(), 2);
+    let token_created = letters
+        .iter()
+        .find(|letter| letter.envelope.event.event_type == EventType::TokenCreated)
+        .expect("token_created dead letter");
+    assert_eq!(token_created.plugin, "broker");
+    assert_eq!(token_created.error, "broker unavailable");
+    assert_eq!(token_created.attempts, 3);
+    assert_eq!(broker.attempts.load(Ordering::SeqCst), 6);
+
+    let metrics = oauth2.metrics();
+    assert_eq!(
+        metrics
+            .events_plugin_retries_total
+            .with_label_values(&["broker"])
+            .get(),
+        4
+    );
+    assert_eq!(
+        metrics
+            .events_dead_lettered_total
+            .with_label_values(&["broker"])
+            .get(),
+        2
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
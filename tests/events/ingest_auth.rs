@@ -0,0 +1,224 @@
+use actix_web::{test, App};
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
+
+use oauth2_config::{Config, EventProducerConfig};
+use oauth2_core::Client;
+use oauth2_events::{AuthEvent, EventEnvelope, EventSeverity, EventType, InMemoryEventLogger};
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+const BILLING_SECRET: &str = "billing_ingest_secret_of_32_chars";
+
+fn client(client_id: &str, scope: &str) -> Client {
+    support::client(
+        client_id,
+        "https://unused.example/cb",
+        &["client_credentials"],
+        scope,
+    )
+}
+
+fn envelope() -> Vec<u8> {
+    let event = AuthEvent::new(
+        EventType::UserAuthenticated,
+        EventSeverity::Info,
+        Some("user_1".to_string()),
+        None,
+    );
+    serde_json::to_vec(&EventEnvelope::from_current_span(event, "billing")).unwrap()
+}
+
+/// `X-Event-Signature` value for `body` signed at `timestamp` with `secret`.
+fn signature(producer: &str, secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(format!("{timestamp}.").as_bytes());
+    mac.update(body);
+    let v1 = hex::encode(mac.finalize().into_bytes());
+    format!("producer={producer},t={timestamp},v1={v1}")
+}
+
+#[actix_web::test]
+async fn ingest_requires_an_ingest_token_or_a_producer_signature() {
+    let storage = support::memory_storage().await;
+    for client in [
+        client("app", "read"),
+        client("collector", "events:ingest"),
+        client("ops", "admin:operator"),
+    ] {
+        storage.save_client(&client).await.expect("save client");
+    }
+
+    let events = Arc::new(InMemoryEventLogger::new(100));
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.events.ingest.producers = vec![EventProducerConfig {
+        id: "billing".to_string(),
+        secret: BILLING_SECRET.to_string(),
+    }];
+    config.events.ingest.rate_limit_per_minute = 3;
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage)
+        .with_event_plugin(events.clone())
+        .with_endpoints([EndpointGroup::OAuth, EndpointGroup::Events])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    let mut bearer = std::collections::HashMap::new();
+    for (client_id, scope) in [
+        ("app", "read"),
+        ("collector", "events:ingest"),
+        ("ops", "admin:operator"),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/oauth/token")
+            .set_form([
+                ("grant_type", "client_credentials"),
+                ("client_id", client_id),
+                ("client_secret", &format!("{client_id}_secret")),
+                ("scope", scope),
+            ])
+            .to_request();
+        let token: Value = test::call_and_read_body_json(&app, req).await;
+        let access_token = token["access_token"].as_str().expect("access token");
+        bearer.insert(client_id, format!("Bearer {access_token}"));
+    }
+    let ingest = |header: Option<(&str, String)>, body: Vec<u8>| {
+        let req = test::TestRequest::post()
+            .uri("/events/ingest")
+            .insert_header(("Content-Type", "application/json"))
+            .set_payload(body);
+        match header {
+            Some(header) => req.insert_header(header),
+            None => req,
+        }
+        .to_request()
+    };
+
+    let resp = test::call_service(&app, ingest(None, envelope())).await;
+    assert_eq!(resp.status(), 401);
+    assert_eq!(resp.headers().get("WWW-Authenticate").unwrap(), "Bearer");
+
+    let resp = test::call_service(
+        &app,
+        ingest(Some(("Authorization", bearer["app"].clone())), envelope()),
+    )
+    .await;
+    assert_eq!(resp.status(), 403);
+    assert_eq!(
+        resp.headers().get("WWW-Authenticate").unwrap(),
+        "Bearer error=\"insufficient_scope\", scope=\"events:ingest\""
+    );
+
+    for caller in ["collector", "ops"] {
+        let resp = test::call_service(
+            &app,
+            ingest(Some(("Authorization", bearer[caller].clone())), envelope()),
+        )
+        .await;
+        assert_eq!(resp.status(), 202, "{caller}");
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let body = envelope();
+    let rejected = [
+        signature("billing", "not_the_billing_secret", now, &body),
+        signature("crm", BILLING_SECRET, now, &body),
+        signature("billing", BILLING_SECRET, now - 3600, &body),
+        signature("billing", BILLING_SECRET, now, b"{}"),
+        "producer=billing".to_string(),
+    ];
+    for header in rejected {
+        let resp = test::call_service(
+            &app,
+            ingest(Some(("X-Event-Signature", header.clone())), body.clone()),
+        )
+        .await;
+        assert_eq!(resp.status(), 401, "{header}");
+        let error: Value = test::read_body_json(resp).await;
+        assert_eq!(error["error"], "invalid_client");
+    }
+
+    // Each producer has its own quota.
+    for _ in 0..3 {
+        let body = envelope();
+        let header = signature("billing", BILLING_SECRET, now, &body);
+        let resp =
+            test::call_service(&app, ingest(Some(("X-Event-Signature", header)), body)).await;
+        assert_eq!(resp.status(), 202);
+    }
+    let body = envelope();
+    let header = signature("billing", BILLING_SECRET, now, &body);
+    let resp = test::call_service(&app, ingest(Some(("X-Event-Signature", header)), body)).await;
+    assert_eq!(resp.status(), 429);
+    assert!(resp.headers().contains_key("Retry-After"));
+    let resp = test::call_service(
+        &app,
+        ingest(
+            Some(("Authorization", bearer["collector"].clone())),
+            envelope(),
+        ),
+    )
+    .await;
+    assert_eq!(resp.status(), 202);
+
+    // Rejected signatures and exhausted quotas are reported as security events.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let reported = |event_type: EventType| {
+        events
+            .get_events()
+            .into_iter()
+            .filter(|e| e.event.event_type == event_type)
+            .collect::<Vec<_>>()
+    };
+    let failures = reported(EventType::ClientAuthenticationFailed);
+    assert_eq!(failures.len(), 5);
+    assert_eq!(
+        failures[0].event.metadata.get("reason").map(String::as_str),
+        Some("invalid_signature")
+    );
+    let limited = reported(EventType::RateLimitExceeded);
+    assert_eq!(limited.len(), 1);
+    assert_eq!(limited[0].event.client_id.as_deref(), Some("billing"));
+    assert_eq!(
+        reported(EventType::UserAuthenticated).len(),
+        6,
+        "only accepted envelopes are relayed"
+    );
+}
+
+#[actix_web::test]
+async fn ingest_can_be_opened_up() {
+    let storage = support::memory_storage().await;
+
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.security.event_endpoints_require_admin = false;
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage)
+        .with_event_plugin(Arc::new(InMemoryEventLogger::new(10)))
+        .with_endpoints([EndpointGroup::Events])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/events/ingest")
+        .insert_header(("Content-Type", "application/json"))
+        .set_payload(envelope())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 202);
+
+    let req = test::TestRequest::post()
+        .uri("/events/ingest")
+        .set_payload("not json")
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+}
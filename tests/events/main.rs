@@ -0,0 +1,13 @@
+//! Events: signing, ingest, outbox, retries, queues, streaming and replay.
+
+#[path = "../support/mod.rs"]
+mod support;
+
+mod dead_letter;
+mod ingest_auth;
+mod outbox;
+mod queue;
+mod replay;
+mod security_events;
+mod signing;
+mod stream;
@@ -0,0 +1,164 @@
+use actix_web::{test, App};
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use oauth2_config::Config;
+use oauth2_core::{OAuth2Error, OutboxMessage};
+use oauth2_events::{EventEnvelope, EventPlugin, EventType};
+use oauth2_ports::testing::FakeStorage;
+use oauth2_ports::Storage;
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+/// A backend that rejects every publish while it is down.
+struct FlakyBroker {
+    down: AtomicBool,
+    received: Mutex<Vec<EventEnvelope>>,
+}
+
+impl FlakyBroker {
+    fn new(down: bool) -> Self {
+        Self {
+            down: AtomicBool::new(down),
+            received: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn received(&self, event_type: EventType) -> usize {
+        self.received
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|envelope| envelope.event.event_type == event_type)
+            .count()
+    }
+}
+
+#[async_trait]
+impl EventPlugin for FlakyBroker {
+    async fn emit(&self, envelope: &EventEnvelope) -> Result<(), String> {
+        if self.down.load(Ordering::SeqCst) {
+            return Err("broker unavailable".to_string());
+        }
+        self.received.lock().unwrap().push(envelope.clone());
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "broker"
+    }
+}
+
+/// Wait up to five seconds for `condition`.
+async fn eventually(condition: impl Fn() -> bool) -> bool {
+    for _ in 0..100 {
+        if condition() {
+            return true;
+        }
+        actix_web::rt::time::sleep(Duration::from_millis(50)).await;
+    }
+    condition()
+}
+
+/// Client credentials request for `app`: the response status.
+macro_rules! token {
+    ($app:expr) => {{
+        let req = test::TestRequest::post()
+            .uri("/oauth/token")
+            .set_form([
+                ("grant_type", "client_credentials"),
+                ("client_id", "app"),
+                ("client_secret", "app_secret"),
+                ("scope", "read"),
+            ])
+            .to_request();
+        test::call_service($app, req).await.status().as_u16()
+    }};
+}
+
+async fn setup(broker: Arc<FlakyBroker>) -> (Arc<FakeStorage>, oauth2_server::OAuth2Server) {
+    let storage = Arc::new(FakeStorage::new());
+    storage
+        .save_client(&support::client(
+            "app",
+            "https://unused.example/cb",
+            &["client_credentials"],
+            "read",
+        ))
+        .await
+        .expect("save client");
+
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.events.outbox.enabled = true;
+    config.events.outbox.poll_interval_ms = 20;
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage.clone())
+        .with_event_plugin(broker)
+        .with_endpoints([EndpointGroup::OAuth])
+        .build()
+        .await
+        .expect("build server");
+    (storage, oauth2)
+}
+
+fn token_created(storage: &FakeStorage) -> Vec<OutboxMessage> {
+    storage
+        .outbox_messages()
+        .into_iter()
+        .filter(|message| message.event_type == "token_created")
+        .collect()
+}
+
+#[actix_web::test]
+async fn events_survive_a_broker_outage_and_are_delivered_once_it_recovers() {
+    let broker = Arc::new(FlakyBroker::new(true));
+    let (storage, oauth2) = setup(broker.clone()).await;
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    assert_eq!(token!(&app), 200);
+
+    // Failed attempts leave the event queued with the error, for a later retry.
+    assert!(
+        eventually(|| token_created(&storage)
+            .first()
+            .is_some_and(|m| m.attempts > 0))
+        .await
+    );
+    let queued = token_created(&storage);
+    assert_eq!(queued.len(), 1);
+    assert_eq!(
+        queued[0].last_error.as_deref(),
+        Some("event bus rejected publish: broker: broker unavailable")
+    );
+    assert_eq!(broker.received(EventType::TokenCreated), 0);
+
+    // Once the broker is back, the retry delivers it and empties the outbox.
+    broker.down.store(false, Ordering::SeqCst);
+    assert!(eventually(|| storage.outbox_messages().is_empty()).await);
+    assert_eq!(broker.received(EventType::TokenCreated), 1);
+    assert_eq!(broker.received(EventType::ClientValidated), 1);
+}
+
+#[actix_web::test]
+async fn token_and_its_event_are_saved_together() {
+    let broker = Arc::new(FlakyBroker::new(false));
+    let (storage, oauth2) = setup(broker.clone()).await;
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    storage.failures().fail_always(
+        "commit",
+        OAuth2Error::temporarily_unavailable("database unavailable"),
+    );
+    assert_eq!(token!(&app), 503);
+    assert!(storage.tokens().is_empty());
+    assert!(token_created(&storage).is_empty());
+
+    storage.failures().clear();
+    assert_eq!(token!(&app), 200);
+    assert_eq!(storage.tokens().len(), 1);
+    assert!(eventually(|| broker.received(EventType::TokenCreated) == 1).await);
+}
@@ -0,0 +1,112 @@
+use actix_web::{test, App};
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+use oauth2_config::{Config, EventOverflowPolicy};
+use oauth2_events::{EventEnvelope, EventPlugin, EventType};
+use oauth2_ports::testing::FakeStorage;
+use oauth2_ports::Storage;
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+/// A backend that accepts one event per permit and stalls otherwise.
+struct StalledBroker {
+    permits: Semaphore,
+    received: Mutex<Vec<EventType>>,
+}
+
+#[async_trait]
+impl EventPlugin for StalledBroker {
+    async fn emit(&self, envelope: &EventEnvelope) -> Result<(), String> {
+        self.permits.acquire().await.unwrap().forget();
+        self.received
+            .lock()
+            .unwrap()
+            .push(envelope.event.event_type.clone());
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "broker"
+    }
+}
+
+/// Wait up to five seconds for `condition`.
+async fn eventually(condition: impl Fn() -> bool) -> bool {
+    for _ in 0..100 {
+        if condition() {
+            return true;
+        }
+        actix_web::rt::time::sleep(Duration::from_millis(50)).await;
+    }
+    condition()
+}
+
+#[actix_web::test]
+async fn a_stalled_backend_holds_at_most_the_queue_capacity() {
+    let storage = Arc::new(FakeStorage::new());
+    storage
+        .save_client(&support::client(
+            "app",
+            "https://unused.example/cb",
+            &["client_credentials"],
+            "read",
+        ))
+        .await
+        .expect("save client");
+
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.events.queue.capacity = 1;
+    config.events.queue.concurrency = 1;
+    config.events.queue.overflow = EventOverflowPolicy::DropNewest;
+
+    let broker = Arc::new(StalledBroker {
+        permits: Semaphore::new(0),
+        received: Mutex::new(Vec::new()),
+    });
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage)
+        .with_event_plugin(broker.clone())
+        .with_endpoints([EndpointGroup::OAuth])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+    let depth = oauth2
+        .metrics()
+        .events_queue_depth
+        .with_label_values(&["broker"]);
+    let dropped = oauth2
+        .metrics()
+        .events_queue_dropped_total
+        .with_label_values(&["broker"]);
+
+    // Each request emits client_validated and token_created. The worker takes the first
+    // event and stalls on it, the second fills the queue and the rest are dropped.
+    for _ in 0..2 {
+        let req = test::TestRequest::post()
+            .uri("/oauth/token")
+            .set_form([
+                ("grant_type", "client_credentials"),
+                ("client_id", "app"),
+                ("client_secret", "app_secret"),
+                ("scope", "read"),
+            ])
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+    }
+    assert!(eventually(|| dropped.get() == 2).await);
+    assert_eq!(depth.get(), 1);
+
+    broker.permits.add_permits(10);
+    assert!(eventually(|| broker.received.lock().unwrap().len() == 2).await);
+    assert_eq!(
+        *broker.received.lock().unwrap(),
+        [EventType::ClientValidated, EventType::TokenCreated]
+    );
+    assert_eq!(depth.get(), 0);
+}
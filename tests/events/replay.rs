@@ -0,0 +1,173 @@
+use actix_web::{test, App};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+use oauth2_config::Config;
+use oauth2_events::{
+    AuthEvent, EventEnvelope, EventPlugin, EventReplaySource, EventSeverity, EventType, ReplayRange,
+};
+use oauth2_ports::testing::FakeStorage;
+use oauth2_ports::Storage;
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+/// A persisted stream of three events, the last a duplicate delivery of the first.
+struct StoredEvents {
+    envelopes: Vec<EventEnvelope>,
+    requested: Mutex<Vec<(ReplayRange, usize)>>,
+}
+
+#[async_trait]
+impl EventReplaySource for StoredEvents {
+    async fn read(&self, range: &ReplayRange, limit: usize) -> Result<Vec<EventEnvelope>, String> {
+        self.requested.lock().unwrap().push((range.clone(), limit));
+        Ok(self.envelopes.clone())
+    }
+
+    fn name(&self) -> &str {
+        "stored"
+    }
+}
+
+/// Records the ids of replayed events only, ignoring the ones the requests below emit.
+struct Recorder {
+    name: &'static str,
+    replayed: Mutex<Vec<String>>,
+}
+
+#[async_trait]
+impl EventPlugin for Recorder {
+    async fn emit(&self, envelope: &EventEnvelope) -> Result<(), String> {
+        if envelope.producer == "archive" {
+            self.replayed
+                .lock()
+                .unwrap()
+                .push(envelope.event.id.clone());
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        self.name
+    }
+}
+
+#[actix_web::test]
+async fn admins_replay_stored_events_to_chosen_plugins() {
+    let storage = Arc::new(FakeStorage::new());
+    storage
+        .save_client(&support::client(
+            "security",
+            "https://unused.example/cb",
+            &["client_credentials"],
+            "admin:security_admin",
+        ))
+        .await
+        .expect("save client");
+
+    let envelope = |event_type: EventType, key: &str| {
+        let event = AuthEvent::new(event_type, EventSeverity::Info, None, None);
+        EventEnvelope::from_current_span(event, "archive").with_idempotency_key(key)
+    };
+    let envelopes = vec![
+        envelope(EventType::TokenCreated, "k1"),
+        envelope(EventType::TokenRevoked, "k2"),
+        envelope(EventType::TokenCreated, "k1"),
+    ];
+    let source = Arc::new(StoredEvents {
+        envelopes: envelopes.clone(),
+        requested: Mutex::new(Vec::new()),
+    });
+    let siem = Arc::new(Recorder {
+        name: "siem",
+        replayed: Mutex::new(Vec::new()),
+    });
+    let warehouse = Arc::new(Recorder {
+        name: "warehouse",
+        replayed: Mutex::new(Vec::new()),
+    });
+
+    let mut config = Config::default();
+    config.events.enabled = false;
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage)
+        .with_event_plugin(siem.clone())
+        .with_event_plugin(warehouse.clone())
+        .with_event_replay_source(source.clone())
+        .with_endpoints([EndpointGroup::OAuth, EndpointGroup::Admin])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/oauth/token")
+        .set_form([
+            ("grant_type", "client_credentials"),
+            ("client_id", "security"),
+            ("client_secret", "security_secret"),
+            ("scope", "admin:security_admin"),
+        ])
+        .to_request();
+    let token: Value = test::call_and_read_body_json(&app, req).await;
+    let bearer = format!("Bearer {}", token["access_token"].as_str().unwrap());
+    let replay = |body: Value| {
+        test::TestRequest::post()
+            .uri("/admin/events/replay")
+            .insert_header(("Authorization", bearer.clone()))
+            .set_json(body)
+            .to_request()
+    };
+
+    let resp = test::call_service(
+        &app,
+        replay(json!({ "from_id": "0-0", "to_id": "+", "plugins": ["nowhere"] })),
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+
+    let resp = test::call_service(
+        &app,
+        replay(json!({
+            "from": "2024-01-15T11:00:00Z",
+            "to": "2024-01-15T10:00:00Z"
+        })),
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+    assert!(source.requested.lock().unwrap().is_empty());
+
+    let body: Value = test::call_and_read_body_json(
+        &app,
+        replay(json!({
+            "from": "2024-01-15T10:00:00Z",
+            "to": "2024-01-15T11:00:00Z",
+            "plugins": ["siem"],
+            "limit": 50
+        })),
+    )
+    .await;
+    assert_eq!(
+        body,
+        json!({
+            "source": "stored",
+            "read": 3,
+            "duplicates": 1,
+            "replayed": 2,
+            "failed": 0,
+            "errors": []
+        })
+    );
+
+    let (range, limit) = source.requested.lock().unwrap()[0].clone();
+    assert!(matches!(range, ReplayRange::Time { .. }));
+    assert_eq!(limit, 50);
+    // Delivery is acknowledged, so the events are there when the response is.
+    assert_eq!(
+        *siem.replayed.lock().unwrap(),
+        [envelopes[0].event.id.clone(), envelopes[1].event.id.clone()]
+    );
+    assert!(warehouse.replayed.lock().unwrap().is_empty());
+}
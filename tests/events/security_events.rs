@@ -0,0 +1,227 @@
+use actix_web::{test, web, App};
+use std::sync::Arc;
+use std::time::Duration;
+
+use oauth2_core::{IssuerKeys, TrustedProxies};
+use oauth2_events::testing::RecordingEventBus;
+use oauth2_events::{EventEnvelope, EventSeverity, EventType};
+
+use crate::support;
+
+const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+const USER_AGENT: &str = "python-requests/2.31";
+const PEER: &str = "198.51.100.7:6000";
+
+fn s256_challenge(verifier: &str) -> String {
+    use base64::{engine::general_purpose, Engine as _};
+    use sha2::{Digest, Sha256};
+    general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+macro_rules! init_app {
+    ($bus:expr) => {{
+        let storage = support::memory_storage().await;
+        support::save_client(
+            &storage,
+            &support::client(
+                "spa",
+                "https://spa.example/cb",
+                &["authorization_code"],
+                "read",
+            ),
+        )
+        .await;
+        support::save_user_with_id(&storage, "alice", "unused", true).await;
+
+        let issuer_keys = IssuerKeys::from_secret(support::JWT_SECRET);
+        let token_actor = support::token_actor(&storage).with_issuer_keys(issuer_keys.clone());
+        let auth_actor = oauth2_actix::actors::AuthActor::new(storage.clone());
+
+        test::init_service(
+            App::new()
+                .wrap_fn(support::signed_in("alice", &[("spa", "read")]))
+                .wrap(support::session_middleware())
+                .configure(support::oauth_data(&storage, token_actor, auth_actor))
+                .app_data(web::Data::new(issuer_keys))
+                .app_data(web::Data::new($bus.handle()))
+                .app_data(web::Data::new(
+                    TrustedProxies::parse(["10.0.0.0/8"]).expect("trusted proxies"),
+                ))
+                .service(
+                    web::scope("/oauth")
+                        .route(
+                            "/authorize",
+                            web::get().to(oauth2_actix::handlers::oauth::authorize),
+                        )
+                        .route(
+                            "/token",
+                            web::post().to(oauth2_actix::handlers::oauth::token),
+                        ),
+                ),
+        )
+        .await
+    }};
+}
+
+macro_rules! authorize {
+    ($app:expr, $query:expr) => {{
+        let req = test::TestRequest::get()
+            .uri(&format!("/oauth/authorize?{}", $query))
+            .insert_header(("User-Agent", USER_AGENT))
+            .peer_addr(PEER.parse().expect("socket addr"))
+            .to_request();
+        test::call_service(&$app, req).await
+    }};
+}
+
+macro_rules! token {
+    ($app:expr, $form:expr) => {{
+        let req = test::TestRequest::post()
+            .uri("/oauth/token")
+            .insert_header(("User-Agent", USER_AGENT))
+            .peer_addr(PEER.parse().expect("socket addr"))
+            .set_form($form)
+            .to_request();
+        test::call_service(&$app, req).await
+    }};
+}
+
+fn pkce_query() -> String {
+    format!(
+        "response_type=code&client_id=spa&redirect_uri=https%3A%2F%2Fspa.example%2Fcb&scope=read&code_challenge={}&code_challenge_method=S256",
+        s256_challenge(VERIFIER)
+    )
+}
+
+fn metadata<'a>(envelope: &'a EventEnvelope, key: &str) -> Option<&'a str> {
+    envelope.event.metadata.get(key).map(String::as_str)
+}
+
+async fn only_event(bus: &Arc<RecordingEventBus>, event_type: EventType) -> EventEnvelope {
+    bus.wait_for(1, Duration::from_secs(5)).await;
+    let events = bus.events();
+    assert_eq!(bus.event_types(), [event_type]);
+    bus.clear();
+    let envelope = events.into_iter().next().expect("event");
+    assert_eq!(envelope.event.client_id.as_deref(), Some("spa"));
+    assert_eq!(metadata(&envelope, "ip_address"), Some("198.51.100.7"));
+    assert_eq!(metadata(&envelope, "user_agent"), Some(USER_AGENT));
+    envelope
+}
+
+#[actix_web::test]
+async fn authorization_requests_report_bad_redirects_and_missing_pkce() {
+    let bus = RecordingEventBus::new();
+    let app = init_app!(bus);
+
+    let resp = authorize!(
+        app,
+        "response_type=code&client_id=spa&redirect_uri=https%3A%2F%2Fevil.example%2Fcb&scope=read"
+    );
+    assert_eq!(resp.status(), 400);
+    let event = only_event(&bus, EventType::InvalidRedirectUriAttempt).await;
+    assert_eq!(
+        metadata(&event, "redirect_uri"),
+        Some("https://evil.example/cb")
+    );
+
+    authorize!(
+        app,
+        "response_type=code&client_id=spa&redirect_uri=https%3A%2F%2Fspa.example%2Fcb&scope=read&code_challenge=abc&code_challenge_method=plain"
+    );
+    let event = only_event(&bus, EventType::PkceDowngradeAttempt).await;
+    assert_eq!(metadata(&event, "code_challenge_method"), Some("plain"));
+
+    // A well-formed request reports nothing.
+    let resp = authorize!(app, pkce_query());
+    assert_eq!(resp.status(), 302);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(bus.events().is_empty());
+}
+
+#[actix_web::test]
+async fn token_requests_report_bad_client_credentials_and_code_replay() {
+    let bus = RecordingEventBus::new();
+    let app = init_app!(bus);
+
+    let resp = authorize!(app, pkce_query());
+    let location = resp
+        .headers()
+        .get("Location")
+        .and_then(|h| h.to_str().ok())
+        .expect("location")
+        .to_string();
+    let (_, query) = location.split_once('?').expect("query");
+    let code = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("code="))
+        .expect("code")
+        .to_string();
+
+    let resp = token!(
+        app,
+        [
+            ("grant_type", "authorization_code"),
+            ("client_id", "spa"),
+            ("client_secret", "wrong"),
+            ("code", code.as_str()),
+            ("code_verifier", VERIFIER),
+        ]
+    );
+    assert_eq!(resp.status(), 401);
+    let event = only_event(&bus, EventType::ClientAuthenticationFailed).await;
+    assert_eq!(metadata(&event, "grant_type"), Some("authorization_code"));
+
+    // The failed attempt did not use up the code.
+    let form = [
+        ("grant_type", "authorization_code"),
+        ("client_id", "spa"),
+        ("client_secret", "spa_secret"),
+        ("code", code.as_str()),
+        ("code_verifier", VERIFIER),
+    ];
+    assert_eq!(token!(app, form).status(), 200);
+
+    let resp = token!(app, form);
+    assert_eq!(resp.status(), 400);
+    let event = only_event(&bus, EventType::AuthorizationCodeReplayDetected).await;
+    assert_eq!(event.event.severity, EventSeverity::Error);
+    assert_eq!(event.event.user_id.as_deref(), Some("alice"));
+}
+
+#[actix_web::test]
+async fn client_ip_is_taken_from_forwarded_headers_of_trusted_proxies_only() {
+    let bus = RecordingEventBus::new();
+    let app = init_app!(bus);
+    let bad_redirect =
+        "/oauth/authorize?response_type=code&client_id=spa&redirect_uri=https%3A%2F%2Fevil.example%2Fcb&scope=read";
+
+    // Via two trusted hops; the client-supplied leftmost entry is ignored.
+    let req = test::TestRequest::get()
+        .uri(bad_redirect)
+        .insert_header(("User-Agent", USER_AGENT))
+        .insert_header(("X-Forwarded-For", "203.0.113.66, 198.51.100.7, 10.1.2.3"))
+        .peer_addr("10.0.0.2:443".parse().expect("socket addr"))
+        .to_request();
+    test::call_service(&app, req).await;
+    only_event(&bus, EventType::InvalidRedirectUriAttempt).await;
+
+    let req = test::TestRequest::get()
+        .uri(bad_redirect)
+        .insert_header(("User-Agent", USER_AGENT))
+        .insert_header(("Forwarded", "for=\"198.51.100.7:1234\";proto=https"))
+        .peer_addr("10.0.0.2:443".parse().expect("socket addr"))
+        .to_request();
+    test::call_service(&app, req).await;
+    only_event(&bus, EventType::InvalidRedirectUriAttempt).await;
+
+    // Headers from an untrusted peer are ignored.
+    let req = test::TestRequest::get()
+        .uri(bad_redirect)
+        .insert_header(("User-Agent", USER_AGENT))
+        .insert_header(("X-Forwarded-For", "203.0.113.66"))
+        .peer_addr(PEER.parse().expect("socket addr"))
+        .to_request();
+    test::call_service(&app, req).await;
+    only_event(&bus, EventType::InvalidRedirectUriAttempt).await;
+}
@@ -0,0 +1,108 @@
+use actix_web::{test, App};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+use oauth2_config::{Config, EventSigningConfig};
+use oauth2_events::{
+    AuthEvent, EventEnvelope, EventSeverity, EventType, InMemoryEventLogger, SignatureError,
+    SigningKey, VerificationKey,
+};
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+const SIGNING_SECRET: &str = "event_signing_secret_of_32_chars!";
+
+async fn wait_for_event(events: &InMemoryEventLogger, event_type: EventType) -> EventEnvelope {
+    for _ in 0..50 {
+        if let Some(envelope) = events
+            .get_events()
+            .into_iter()
+            .find(|e| e.event.event_type == event_type)
+        {
+            return envelope;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("{event_type:?} was not published");
+}
+
+#[actix_web::test]
+async fn published_events_are_signed_and_ingested_events_are_not() {
+    let storage = support::memory_storage().await;
+    support::save_client(
+        &storage,
+        &support::client(
+            "service",
+            "https://unused.example/cb",
+            &["client_credentials"],
+            "read admin:operator",
+        ),
+    )
+    .await;
+
+    let events = Arc::new(InMemoryEventLogger::new(100));
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.events.signing = Some(EventSigningConfig {
+        key_id: "events-1".to_string(),
+        secret: Some(SIGNING_SECRET.to_string()),
+        private_key_pem: None,
+    });
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage)
+        .with_event_plugin(events.clone())
+        .with_endpoints([EndpointGroup::OAuth, EndpointGroup::Events])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+    let keys = [VerificationKey::hmac("events-1", SIGNING_SECRET.as_bytes())];
+
+    let req = test::TestRequest::post()
+        .uri("/oauth/token")
+        .set_form([
+            ("grant_type", "client_credentials"),
+            ("client_id", "service"),
+            ("client_secret", "service_secret"),
+            ("scope", "read admin:operator"),
+        ])
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let access_token = body["access_token"].as_str().expect("access token");
+
+    let issued = wait_for_event(&events, EventType::TokenCreated).await;
+    issued
+        .verify_signature(&keys)
+        .expect("server events are signed");
+
+    // A producer holding the key could sign this, but the server relays it unsigned so
+    // it cannot be passed off as one of its own events.
+    let mut forged = EventEnvelope::from_current_span(
+        AuthEvent::new(
+            EventType::TokenRevoked,
+            EventSeverity::Info,
+            None,
+            Some("service".to_string()),
+        ),
+        "oauth2_server",
+    );
+    forged
+        .sign(&SigningKey::hmac("events-1", SIGNING_SECRET.as_bytes()))
+        .unwrap();
+    let req = test::TestRequest::post()
+        .uri("/events/ingest")
+        .insert_header(("Authorization", format!("Bearer {access_token}")))
+        .set_json(json!(forged))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["status"], "accepted");
+
+    let relayed = wait_for_event(&events, EventType::TokenRevoked).await;
+    assert_eq!(relayed.signature, None);
+    assert_eq!(
+        relayed.verify_signature(&keys),
+        Err(SignatureError::Missing)
+    );
+}
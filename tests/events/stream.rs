@@ -0,0 +1,109 @@
+use actix_web::body::MessageBody;
+use actix_web::{test, App};
+use serde_json::Value;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use oauth2_config::Config;
+use oauth2_events::InMemoryEventLogger;
+use oauth2_ports::testing::FakeStorage;
+use oauth2_ports::Storage;
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+/// The next chunk of a streaming body, failing after five seconds.
+async fn next_chunk<B: MessageBody + Unpin>(body: &mut B) -> String {
+    let chunk = tokio::time::timeout(
+        Duration::from_secs(5),
+        std::future::poll_fn(|cx| Pin::new(&mut *body).poll_next(cx)),
+    )
+    .await
+    .expect("chunk before timeout")
+    .expect("stream still open");
+    String::from_utf8(chunk.ok().expect("chunk").to_vec()).expect("utf-8")
+}
+
+#[actix_web::test]
+async fn admins_watch_filtered_events_live() {
+    let storage = Arc::new(FakeStorage::new());
+    for (client_id, scope) in [("app", "read"), ("security", "admin:security_admin")] {
+        storage
+            .save_client(&support::client(
+                client_id,
+                "https://unused.example/cb",
+                &["client_credentials"],
+                scope,
+            ))
+            .await
+            .expect("save client");
+    }
+
+    let mut config = Config::default();
+    config.events.enabled = false;
+    let logger = Arc::new(InMemoryEventLogger::new(100));
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage)
+        .with_event_plugin(logger.clone())
+        .with_endpoints([EndpointGroup::OAuth, EndpointGroup::Admin])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    let token = |client_id: &str, scope: &str| {
+        test::TestRequest::post()
+            .uri("/oauth/token")
+            .set_form([
+                ("grant_type", "client_credentials"),
+                ("client_id", client_id),
+                ("client_secret", &format!("{client_id}_secret")),
+                ("scope", scope),
+            ])
+            .to_request()
+    };
+    let admin: Value =
+        test::call_and_read_body_json(&app, token("security", "admin:security_admin")).await;
+    let bearer = format!("Bearer {}", admin["access_token"].as_str().unwrap());
+    // Let the admin's own events go by, so the stream only sees the ones below.
+    for _ in 0..100 {
+        if logger.get_events().len() == 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let req = test::TestRequest::get()
+        .uri("/admin/events/stream?event_types=token_created,unknown_event")
+        .insert_header(("Authorization", bearer.clone()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+
+    let req = test::TestRequest::get()
+        .uri("/admin/events/stream?event_types=token_created")
+        .insert_header(("Authorization", bearer))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers().get("Content-Type").unwrap(),
+        "text/event-stream"
+    );
+    let mut body = resp.into_body();
+
+    // The token request also emits client_validated, which is filtered out.
+    let issued: Value = test::call_and_read_body_json(&app, token("app", "read")).await;
+    assert!(issued["access_token"].is_string());
+
+    let chunk = next_chunk(&mut body).await;
+    let mut lines = chunk.lines();
+    assert!(lines.next().unwrap().starts_with("id: "));
+    assert_eq!(lines.next(), Some("event: token_created"));
+    let data: Value =
+        serde_json::from_str(lines.next().unwrap().strip_prefix("data: ").unwrap()).unwrap();
+    assert_eq!(data["event"]["event_type"], "token_created");
+    assert_eq!(data["event"]["client_id"], "app");
+}
@@ -0,0 +1,472 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use actix_web::{test, web, App};
+use base64::Engine;
+use bytes::BytesMut;
+use lber::common::TagClass;
+use lber::parse::parse_tag;
+use lber::structure::{StructureTag, PL};
+use lber::structures::{ASNTag, Enumerated, Integer, OctetString, Sequence, Set, Tag};
+use lber::write;
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use oauth2_config::{GrantsConfig, LdapConfig};
+use oauth2_ldap::LdapAuthenticator;
+use oauth2_ports::DynStorage;
+
+use crate::support;
+
+const SERVICE_DN: &str = "cn=svc,dc=example,dc=test";
+const DANA_DN: &str = "uid=dana,ou=people,dc=example,dc=test";
+
+struct DirectoryEntry {
+    dn: &'static str,
+    password: &'static str,
+    attributes: Vec<(&'static str, Vec<&'static str>)>,
+}
+
+fn directory() -> Vec<DirectoryEntry> {
+    vec![
+        DirectoryEntry {
+            dn: SERVICE_DN,
+            password: "service secret",
+            attributes: vec![("cn", vec!["svc"])],
+        },
+        DirectoryEntry {
+            dn: DANA_DN,
+            password: "directory pw",
+            attributes: vec![
+                ("uid", vec!["dana"]),
+                ("mail", vec!["dana@example.test"]),
+                ("departmentNumber", vec!["42"]),
+                (
+                    "memberOf",
+                    vec![
+                        "cn=admins,dc=example,dc=test",
+                        "cn=staff,dc=example,dc=test",
+                    ],
+                ),
+            ],
+        },
+        DirectoryEntry {
+            dn: "uid=erin,ou=people,dc=example,dc=test",
+            password: "directory pw",
+            attributes: vec![("uid", vec!["erin"])],
+        },
+    ]
+}
+
+/// A minimal LDAP server: simple binds, and searches by anyone bound. Returns its URL and
+/// the number of connections accepted.
+async fn spawn_directory() -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let url = format!("ldap://{}", listener.local_addr().expect("addr"));
+    let accepted = Arc::new(AtomicUsize::new(0));
+    let counter = accepted.clone();
+    let entries = Arc::new(directory());
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            counter.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(serve(stream, entries.clone()));
+        }
+    });
+    (url, accepted)
+}
+
+/// The next LDAP message on `stream`, `None` once the client hangs up.
+async fn read_message(
+    stream: &mut tokio::net::TcpStream,
+    buffer: &mut Vec<u8>,
+) -> Option<StructureTag> {
+    loop {
+        if let Ok((rest, message)) = parse_tag(buffer) {
+            let consumed = buffer.len() - rest.len();
+            buffer.drain(..consumed);
+            return Some(message);
+        }
+        let mut chunk = [0u8; 4096];
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return None,
+            Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+        }
+    }
+}
+
+async fn serve(mut stream: tokio::net::TcpStream, entries: Arc<Vec<DirectoryEntry>>) {
+    let mut bound_as = String::new();
+    let mut buffer = Vec::new();
+    while let Some(message) = read_message(&mut stream, &mut buffer).await {
+        let mut parts = children(message).into_iter();
+        let id = integer(&parts.next().expect("id"));
+        let operation = parts.next().expect("operation");
+        let operation_id = operation.id;
+        let mut fields = children(operation).into_iter();
+        let responses = match operation_id {
+            0 => {
+                fields.next(); // version
+                let name = text(&fields.next().expect("name"));
+                let password = text(&fields.next().expect("password"));
+                let accepted = (name.is_empty() && password.is_empty())
+                    || entries
+                        .iter()
+                        .any(|entry| entry.dn == name && entry.password == password);
+                bound_as = if accepted { name } else { String::new() };
+                vec![result(1, if accepted { 0 } else { 49 })]
+            }
+            3 if bound_as.is_empty() => vec![result(5, 50)],
+            3 => {
+                let base = text(&fields.next().expect("base"));
+                let scope = integer(&fields.next().expect("scope"));
+                let filter = fields.nth(4).expect("filter");
+                let mut responses: Vec<StructureTag> = entries
+                    .iter()
+                    .filter(|entry| match scope {
+                        0 => entry.dn.eq_ignore_ascii_case(&base),
+                        _ => entry.dn.to_lowercase().ends_with(&base.to_lowercase()),
+                    })
+                    .filter(|entry| matches(&filter, entry))
+                    .map(search_entry)
+                    .collect();
+                responses.push(result(5, 0));
+                responses
+            }
+            _ => return,
+        };
+        for response in responses {
+            let message = sequence(vec![
+                Tag::Integer(Integer {
+                    inner: id,
+                    ..Default::default()
+                })
+                .into_structure(),
+                response,
+            ]);
+            let mut bytes = BytesMut::new();
+            write::encode_into(&mut bytes, message).expect("encode");
+            if stream.write_all(&bytes).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn matches(filter: &StructureTag, entry: &DirectoryEntry) -> bool {
+    let values = |attribute: &str| {
+        entry
+            .attributes
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(attribute))
+            .map(|(_, values)| values.clone())
+    };
+    match filter.id {
+        0 => children(filter.clone())
+            .iter()
+            .all(|filter| matches(filter, entry)),
+        3 => {
+            let parts = children(filter.clone());
+            let attribute = text(&parts[0]);
+            let value = text(&parts[1]);
+            values(&attribute)
+                .is_some_and(|values| values.iter().any(|v| v.eq_ignore_ascii_case(&value)))
+        }
+        7 => {
+            let attribute = text(filter);
+            attribute.eq_ignore_ascii_case("objectClass") || values(&attribute).is_some()
+        }
+        _ => false,
+    }
+}
+
+fn search_entry(entry: &DirectoryEntry) -> StructureTag {
+    let attributes = entry
+        .attributes
+        .iter()
+        .map(|(name, values)| {
+            sequence(vec![
+                octet_string(name),
+                Tag::Set(Set {
+                    inner: values
+                        .iter()
+                        .map(|value| Tag::StructureTag(octet_string(value)))
+                        .collect(),
+                    ..Default::default()
+                })
+                .into_structure(),
+            ])
+        })
+        .collect();
+    application(4, vec![octet_string(entry.dn), sequence(attributes)])
+}
+
+fn result(operation: u64, code: i64) -> StructureTag {
+    application(
+        operation,
+        vec![
+            Tag::Enumerated(Enumerated {
+                inner: code,
+                ..Default::default()
+            })
+            .into_structure(),
+            octet_string(""),
+            octet_string(""),
+        ],
+    )
+}
+
+fn application(id: u64, children: Vec<StructureTag>) -> StructureTag {
+    StructureTag {
+        class: TagClass::Application,
+        id,
+        payload: PL::C(children),
+    }
+}
+
+fn sequence(children: Vec<StructureTag>) -> StructureTag {
+    Tag::Sequence(Sequence {
+        inner: children.into_iter().map(Tag::StructureTag).collect(),
+        ..Default::default()
+    })
+    .into_structure()
+}
+
+fn octet_string(value: &str) -> StructureTag {
+    Tag::OctetString(OctetString {
+        inner: value.as_bytes().to_vec(),
+        ..Default::default()
+    })
+    .into_structure()
+}
+
+fn children(tag: StructureTag) -> Vec<StructureTag> {
+    tag.expect_constructed().expect("constructed")
+}
+
+fn text(tag: &StructureTag) -> String {
+    match &tag.payload {
+        PL::P(bytes) => String::from_utf8(bytes.clone()).expect("utf-8"),
+        PL::C(_) => panic!("expected a primitive"),
+    }
+}
+
+fn integer(tag: &StructureTag) -> i64 {
+    match &tag.payload {
+        PL::P(bytes) => bytes
+            .iter()
+            .fold(0, |value, byte| (value << 8) | i64::from(*byte)),
+        PL::C(_) => panic!("expected a primitive"),
+    }
+}
+
+async fn new_storage() -> DynStorage {
+    let storage = support::first_party_storage(&["password"], "read").await;
+    // carol exists only locally; erin also has a directory entry.
+    for username in ["carol", "erin"] {
+        support::save_user(&storage, username, "local pw").await;
+    }
+    storage
+}
+
+fn ldap_config(url: &str, settings: Value) -> LdapConfig {
+    let mut config = json!({ "enabled": true, "url": url });
+    config
+        .as_object_mut()
+        .unwrap()
+        .extend(settings.as_object().unwrap().clone());
+    serde_json::from_value(config).expect("ldap config")
+}
+
+fn search_and_bind(url: &str) -> LdapConfig {
+    ldap_config(
+        url,
+        json!({
+            "bind_dn": SERVICE_DN,
+            "bind_password": "service secret",
+            "base_dn": "dc=example,dc=test",
+            "claims": { "department": "departmentNumber", "groups": "memberOf" },
+            "pool_size": 2
+        }),
+    )
+}
+
+macro_rules! init_app {
+    ($storage:expr, $config:expr) => {{
+        let ldap = Arc::new(
+            LdapAuthenticator::from_config(&$config, $storage.clone()).expect("ldap authenticator"),
+        );
+        let token_actor = support::token_actor(&$storage).with_claims_enrichers(vec![ldap.clone()]);
+        let auth_actor =
+            oauth2_actix::actors::AuthActor::new($storage.clone()).with_authenticator(ldap);
+
+        test::init_service(
+            App::new()
+                .configure(support::oauth_data(&$storage, token_actor, auth_actor))
+                .app_data(web::Data::new(GrantsConfig {
+                    password: true,
+                    ..GrantsConfig::default()
+                }))
+                .route(
+                    "/oauth/token",
+                    web::post().to(oauth2_actix::handlers::oauth::token),
+                ),
+        )
+        .await
+    }};
+}
+
+fn password_grant(username: &str, password: &str) -> test::TestRequest {
+    test::TestRequest::post().uri("/oauth/token").set_form([
+        ("client_id", "first_party"),
+        ("client_secret", "first_party_secret"),
+        ("grant_type", "password"),
+        ("username", username),
+        ("password", password),
+    ])
+}
+
+fn jwt_payload(token: &str) -> Value {
+    let payload = token.split('.').nth(1).expect("jwt payload");
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload)
+        .expect("base64 payload");
+    serde_json::from_slice(&bytes).expect("json payload")
+}
+
+#[actix_web::test]
+async fn directory_users_sign_in_with_mapped_claims() {
+    let (url, connections) = spawn_directory().await;
+    let storage = new_storage().await;
+    let app = init_app!(storage, search_and_bind(&url));
+
+    let resp = test::call_service(&app, password_grant("dana", "directory pw").to_request()).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    let claims = jwt_payload(body["access_token"].as_str().expect("access token"));
+    assert_eq!(claims["department"], "42");
+    assert_eq!(
+        claims["groups"],
+        json!([
+            "cn=admins,dc=example,dc=test",
+            "cn=staff,dc=example,dc=test"
+        ])
+    );
+
+    // The first login provisions a local user without a password, linked by DN.
+    let user = storage
+        .get_user_by_username("dana", None)
+        .await
+        .unwrap()
+        .expect("provisioned user");
+    assert_eq!(claims["sub"], user.id.as_str());
+    assert_eq!(user.email, "dana@example.test");
+    assert!(user.password_hash.is_empty());
+    let identity = storage
+        .get_federated_identity("ldap", DANA_DN)
+        .await
+        .unwrap()
+        .expect("linked identity");
+    assert_eq!(identity.user_id, user.id);
+
+    // Later logins reuse the user and the pooled connection.
+    let resp = test::call_service(&app, password_grant("dana", "directory pw").to_request()).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    let claims = jwt_payload(body["access_token"].as_str().expect("access token"));
+    assert_eq!(claims["sub"], user.id.as_str());
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+}
+
+#[actix_web::test]
+async fn rejected_directory_passwords_do_not_fall_back() {
+    let (url, _) = spawn_directory().await;
+    let storage = new_storage().await;
+    let app = init_app!(storage, search_and_bind(&url));
+
+    // erin's local password must not sign in to the directory account, nor take over the
+    // local account with the directory password.
+    for (username, password) in [
+        ("dana", "wrong"),
+        ("dana", ""),
+        ("erin", "local pw"),
+        ("erin", "directory pw"),
+        ("nobody", "local pw"),
+    ] {
+        let resp = test::call_service(&app, password_grant(username, password).to_request()).await;
+        assert_eq!(resp.status(), 400, "{username}");
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], "invalid_grant", "{username}");
+    }
+    assert!(storage
+        .get_federated_identity("ldap", "uid=erin,ou=people,dc=example,dc=test")
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[actix_web::test]
+async fn users_missing_from_the_directory_fall_back_to_local_passwords() {
+    let (url, _) = spawn_directory().await;
+    let storage = new_storage().await;
+    let app = init_app!(storage, search_and_bind(&url));
+
+    let resp = test::call_service(&app, password_grant("carol", "local pw").to_request()).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    let claims = jwt_payload(body["access_token"].as_str().expect("access token"));
+    assert!(claims.get("department").is_none());
+
+    let mut config = search_and_bind(&url);
+    config.local_fallback = false;
+    let app = init_app!(storage, config);
+    let resp = test::call_service(&app, password_grant("carol", "local pw").to_request()).await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[actix_web::test]
+async fn bind_as_user_checks_the_templated_dn() {
+    let (url, _) = spawn_directory().await;
+    let storage = new_storage().await;
+    let config = ldap_config(
+        &url,
+        json!({ "user_dn_template": "uid={username},ou=people,dc=example,dc=test" }),
+    );
+    let app = init_app!(storage, config);
+
+    let resp = test::call_service(&app, password_grant("dana", "directory pw").to_request()).await;
+    assert_eq!(resp.status(), 200);
+    let user = storage
+        .get_user_by_username("dana", None)
+        .await
+        .unwrap()
+        .expect("provisioned user");
+    assert_eq!(user.email, "dana@example.test");
+
+    // A username cannot rewrite the DN into another entry.
+    let resp = test::call_service(
+        &app,
+        password_grant("x,uid=dana", "directory pw").to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+
+    // A failed bind cannot tell an unknown user from a wrong password, so local
+    // passwords are tried.
+    let resp = test::call_service(&app, password_grant("carol", "local pw").to_request()).await;
+    assert_eq!(resp.status(), 200);
+}
+
+#[actix_web::test]
+async fn unreachable_directories_fail_closed() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let url = format!("ldap://{}", listener.local_addr().expect("addr"));
+    drop(listener);
+    let storage = new_storage().await;
+    let app = init_app!(storage, search_and_bind(&url));
+
+    let resp = test::call_service(&app, password_grant("carol", "local pw").to_request()).await;
+    assert_eq!(resp.status(), 503);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "temporarily_unavailable");
+}
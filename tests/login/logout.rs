@@ -0,0 +1,142 @@
+use actix::Actor;
+use actix_session::{storage::CookieSessionStore, Session, SessionMiddleware};
+use actix_web::cookie::Key;
+use actix_web::{test, web, App, HttpResponse};
+use serde_json::Value;
+
+use oauth2_core::{Claims, IssuerKey, IssuerKeys, DEFAULT_ISSUER};
+
+use crate::support;
+
+async fn sign_in(session: Session) -> HttpResponse {
+    session
+        .insert("authenticated", true)
+        .expect("session insert");
+    HttpResponse::Ok().finish()
+}
+
+fn id_token_for(keys: &IssuerKeys, client_id: &str, lifetime_secs: i64) -> String {
+    keys.sign(Claims::new(
+        "alice".to_string(),
+        client_id.to_string(),
+        "openid".to_string(),
+        lifetime_secs,
+    ))
+    .expect("sign")
+}
+
+#[actix_web::test]
+async fn rp_initiated_logout_validates_hint_and_redirect() {
+    let storage = support::memory_storage().await;
+    support::save_client(
+        &storage,
+        &support::client(
+            "web_app",
+            "https://app.example/signed-out",
+            &["authorization_code"],
+            "openid",
+        ),
+    )
+    .await;
+
+    let keys = IssuerKeys::from_secret(support::JWT_SECRET);
+    let client_actor = oauth2_actix::actors::ClientActor::new(storage.clone()).start();
+    let app = test::init_service(
+        App::new()
+            .wrap(SessionMiddleware::new(
+                CookieSessionStore::default(),
+                Key::generate(),
+            ))
+            .app_data(web::Data::new(client_actor))
+            .app_data(web::Data::new(keys.clone()))
+            .route("/sign-in", web::post().to(sign_in))
+            .route(
+                "/oauth/logout",
+                web::get().to(oauth2_actix::handlers::oauth::end_session),
+            )
+            .route(
+                "/oauth/logout",
+                web::post().to(oauth2_actix::handlers::oauth::end_session),
+            ),
+    )
+    .await;
+
+    let resp =
+        test::call_service(&app, test::TestRequest::post().uri("/sign-in").to_request()).await;
+    let session_cookie = resp
+        .response()
+        .cookies()
+        .next()
+        .expect("session cookie")
+        .into_owned();
+
+    // An expired hint still identifies the client; the session is destroyed and the user
+    // agent is sent back with `state`.
+    let hint = id_token_for(&keys, "web_app", -3600);
+    let req = test::TestRequest::get()
+        .uri(&format!(
+            "/oauth/logout?id_token_hint={hint}&post_logout_redirect_uri=https%3A%2F%2Fapp.example%2Fsigned-out&state=xyz"
+        ))
+        .cookie(session_cookie.clone())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 302);
+    assert_eq!(
+        resp.headers().get("Location").unwrap(),
+        "https://app.example/signed-out?state=xyz"
+    );
+    let removal = resp
+        .response()
+        .cookies()
+        .find(|cookie| cookie.name() == session_cookie.name())
+        .expect("session cookie removed");
+    assert_eq!(removal.value(), "");
+
+    // Form-encoded POST with client_id and no redirect shows a confirmation page.
+    let req = test::TestRequest::post()
+        .uri("/oauth/logout")
+        .insert_header(("Content-Type", "application/x-www-form-urlencoded"))
+        .set_payload("client_id=web_app")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers().get("Cache-Control").unwrap(), "no-store");
+
+    let forged = id_token_for(
+        &IssuerKeys::new(IssuerKey::new(DEFAULT_ISSUER, "someone_else")),
+        "web_app",
+        3600,
+    );
+    let valid = id_token_for(&keys, "web_app", 3600);
+    for (query, description) in [
+        (format!("id_token_hint={forged}"), "Invalid id_token_hint"),
+        (
+            "client_id=web_app&post_logout_redirect_uri=https%3A%2F%2Fevil.example%2F".to_string(),
+            "Invalid post_logout_redirect_uri",
+        ),
+        (
+            "post_logout_redirect_uri=https%3A%2F%2Fapp.example%2Fsigned-out".to_string(),
+            "post_logout_redirect_uri requires id_token_hint or client_id",
+        ),
+        (
+            format!("id_token_hint={valid}&client_id=other_app"),
+            "client_id does not match id_token_hint",
+        ),
+        (
+            "client_id=web_app&client_id=other_app".to_string(),
+            "Duplicate query parameters are not allowed",
+        ),
+    ] {
+        let req = test::TestRequest::get()
+            .uri(&format!("/oauth/logout?{query}"))
+            .cookie(session_cookie.clone())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400, "{query}");
+        // A rejected request leaves the session alone.
+        assert!(resp.response().cookies().next().is_none(), "{query}");
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], "invalid_request");
+        assert_eq!(body["error_description"], description);
+    }
+}
@@ -0,0 +1,412 @@
+use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+use actix_web::cookie::Key;
+use actix_web::{test, App};
+use std::sync::Arc;
+use std::time::Duration;
+
+use oauth2_config::{Config, MagicLinkConfig, MailConfig};
+use oauth2_core::{verify_password, Token, UserSession};
+use oauth2_events::{EventEnvelope, EventType, InMemoryEventLogger};
+use oauth2_mail::MemoryMailer;
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+/// Wait for the best-effort publisher to deliver an event of `event_type`.
+async fn wait_for_event(events: &InMemoryEventLogger, event_type: EventType) -> EventEnvelope {
+    for _ in 0..50 {
+        if let Some(envelope) = events
+            .get_events()
+            .into_iter()
+            .find(|e| e.event.event_type == event_type)
+        {
+            return envelope;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("{event_type:?} was not published");
+}
+
+/// Wait for the `count`th message to `to`; links are mailed in the background.
+async fn wait_for_mail(mailer: &MemoryMailer, to: &str, count: usize) {
+    for _ in 0..50 {
+        if mailer.sent().iter().filter(|email| email.to == to).count() >= count {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("no mail to {to}");
+}
+
+/// The token of the link in the last message to `to`.
+fn token_sent_to(mailer: &MemoryMailer, to: &str) -> String {
+    let email = mailer.last_to(to).expect("mail sent");
+    let (_, rest) = email.body.split_once("?token=").expect("link in body");
+    rest.split_whitespace().next().unwrap().to_string()
+}
+
+fn mail_config() -> Config {
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.server.issuer = Some("https://auth.example".to_string());
+    config.mail = Some(MailConfig {
+        enabled: true,
+        from: "Example <no-reply@example.com>".to_string(),
+        verification_ttl_secs: 86400,
+        password_reset_ttl_secs: 3600,
+        smtp: None,
+        magic_link: MagicLinkConfig::default(),
+    });
+    config
+}
+
+#[actix_web::test]
+async fn password_reset_links_work_once() {
+    let storage = support::memory_storage().await;
+    let alice = support::save_user(&storage, "alice", "old password").await;
+    support::save_client(
+        &storage,
+        &support::client(
+            "web",
+            "https://app.example/cb",
+            &["authorization_code", "refresh_token"],
+            "openid",
+        ),
+    )
+    .await;
+    let token = Token::new(
+        "alice_access".to_string(),
+        Some("alice_refresh".to_string()),
+        "web".to_string(),
+        Some(alice.id.clone()),
+        "openid".to_string(),
+        3600,
+    );
+    storage.save_token(&token).await.expect("save token");
+
+    let mailer = MemoryMailer::new();
+    let events = Arc::new(InMemoryEventLogger::new(100));
+    let oauth2 = ServerBuilder::new(mail_config())
+        .with_storage(storage.clone())
+        .with_mailer(mailer.clone())
+        .with_event_plugin(events.clone())
+        .with_endpoints([EndpointGroup::Login])
+        .build()
+        .await
+        .expect("build server");
+    let now = chrono::Utc::now();
+    let session = UserSession {
+        id: "alice_laptop".to_string(),
+        user_id: Some(alice.id.clone()),
+        state: "{}".to_string(),
+        created_at: now,
+        last_seen_at: now,
+        idle_expires_at: now + chrono::Duration::hours(1),
+        expires_at: now + chrono::Duration::hours(8),
+    };
+    oauth2.session_store().save(&session).await.unwrap();
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/auth/forgot-password")
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    // Magic links are off unless `mail.magic_link.enabled`.
+    let req = test::TestRequest::get()
+        .uri("/auth/magic-link")
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+    // Unknown addresses get the same answer, and no mail.
+    let req = test::TestRequest::post()
+        .uri("/auth/forgot-password")
+        .set_form([("email", "nobody@example.test")])
+        .to_request();
+    let unknown = test::call_and_read_body(&app, req).await;
+
+    let req = test::TestRequest::post()
+        .uri("/auth/forgot-password")
+        .set_form([("email", "ALICE@example.test")])
+        .to_request();
+    assert_eq!(test::call_and_read_body(&app, req).await, unknown);
+    wait_for_mail(&mailer, "alice@example.test", 1).await;
+    assert_eq!(mailer.sent().len(), 1);
+    let email = mailer.last_to("alice@example.test").expect("reset mail");
+    assert!(email
+        .body
+        .contains("https://auth.example/auth/reset-password?token="));
+    let token = token_sent_to(&mailer, "alice@example.test");
+    let requested = wait_for_event(&events, EventType::PasswordResetRequested).await;
+    assert_eq!(requested.event.user_id.as_deref(), Some(alice.id.as_str()));
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/auth/reset-password?token={token}"))
+        .to_request();
+    let page = test::call_and_read_body(&app, req).await;
+    assert!(String::from_utf8_lossy(&page).contains(&token));
+
+    // A rejected password leaves the link usable.
+    let req = test::TestRequest::post()
+        .uri("/auth/reset-password")
+        .set_form([("token", token.as_str()), ("password", "short")])
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    let req = test::TestRequest::post()
+        .uri("/auth/reset-password")
+        .set_form([("token", token.as_str()), ("password", "new password")])
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    let stored = storage.get_user(&alice.id).await.unwrap().unwrap();
+    assert!(verify_password("new password", &stored.password_hash));
+    assert!(stored.email_verified);
+    wait_for_event(&events, EventType::PasswordResetCompleted).await;
+    // Whoever knew the old password is signed out.
+    let revoked = storage
+        .get_token_by_refresh_token("alice_refresh")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(revoked.revoked);
+    assert!(oauth2
+        .session_store()
+        .get("alice_laptop")
+        .await
+        .unwrap()
+        .is_none());
+
+    let req = test::TestRequest::post()
+        .uri("/auth/reset-password")
+        .set_form([("token", token.as_str()), ("password", "another password")])
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+    let stored = storage.get_user(&alice.id).await.unwrap().unwrap();
+    assert!(verify_password("new password", &stored.password_hash));
+}
+
+#[actix_web::test]
+async fn verification_links_mark_the_address_verified() {
+    let storage = support::memory_storage().await;
+    let bob = support::save_user(&storage, "bob", "bob password").await;
+
+    let mailer = MemoryMailer::new();
+    let events = Arc::new(InMemoryEventLogger::new(100));
+    let oauth2 = ServerBuilder::new(mail_config())
+        .with_storage(storage.clone())
+        .with_mailer(mailer.clone())
+        .with_event_plugin(events.clone())
+        .with_endpoints([EndpointGroup::Login])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/auth/verify-email")
+        .set_form([("email", "bob@example.test")])
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    wait_for_mail(&mailer, "bob@example.test", 1).await;
+    let token = token_sent_to(&mailer, "bob@example.test");
+    wait_for_event(&events, EventType::EmailVerificationSent).await;
+
+    // A verification link is no reset link.
+    let req = test::TestRequest::post()
+        .uri("/auth/reset-password")
+        .set_form([("token", token.as_str()), ("password", "new password")])
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    let req = test::TestRequest::post()
+        .uri("/auth/verify-email")
+        .set_form([("email", "bob@example.test")])
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    wait_for_mail(&mailer, "bob@example.test", 2).await;
+    let token = token_sent_to(&mailer, "bob@example.test");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/auth/verify-email?token={token}"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    assert!(
+        storage
+            .get_user(&bob.id)
+            .await
+            .unwrap()
+            .unwrap()
+            .email_verified
+    );
+    let verified = wait_for_event(&events, EventType::EmailVerified).await;
+    assert_eq!(verified.event.metadata["email"], "bob@example.test");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/auth/verify-email?token={token}"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    // Verified addresses get no more links.
+    let sent = mailer.sent().len();
+    let req = test::TestRequest::post()
+        .uri("/auth/verify-email")
+        .set_form([("email", "bob@example.test")])
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(mailer.sent().len(), sent);
+}
+
+#[actix_web::test]
+async fn magic_links_sign_in_and_resume_the_authorization_request() {
+    let storage = support::memory_storage().await;
+    let carol = support::save_user(&storage, "carol", "carol password").await;
+
+    let mut config = mail_config();
+    let magic_link = &mut config.mail.as_mut().unwrap().magic_link;
+    magic_link.enabled = true;
+    magic_link.max_per_hour = 2;
+    let mailer = MemoryMailer::new();
+    let events = Arc::new(InMemoryEventLogger::new(100));
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage.clone())
+        .with_mailer(mailer.clone())
+        .with_event_plugin(events.clone())
+        .with_endpoints([EndpointGroup::Login])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(
+        App::new()
+            .wrap(SessionMiddleware::new(
+                CookieSessionStore::default(),
+                Key::generate(),
+            ))
+            .configure(|cfg| oauth2.configure(cfg)),
+    )
+    .await;
+
+    let return_to = "/oauth/authorize?client_id=web&state=xyz";
+    let req = test::TestRequest::get()
+        .uri("/auth/magic-link?return_to=%2Foauth%2Fauthorize%3Fclient_id%3Dweb%26state%3Dxyz")
+        .to_request();
+    let page = test::call_and_read_body(&app, req).await;
+    assert!(String::from_utf8_lossy(&page)
+        .contains(r#"value="/oauth/authorize?client_id=web&amp;state=xyz""#));
+
+    // Links only continue on this server.
+    for return_to in [
+        "https://evil.example/",
+        "//evil.example/",
+        "/\\evil.example/",
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/auth/magic-link")
+            .set_form([("email", "carol@example.test"), ("return_to", return_to)])
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+    }
+    assert!(mailer.sent().is_empty());
+
+    // Links are built on server.issuer, never on the request's Host header.
+    let req = test::TestRequest::post()
+        .uri("/auth/magic-link")
+        .insert_header(("Host", "evil.example"))
+        .set_form([("email", "carol@example.test"), ("return_to", return_to)])
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    let email = mailer.last_to("carol@example.test").expect("sign-in mail");
+    assert!(email
+        .body
+        .contains("https://auth.example/auth/magic-link/sign-in?token="));
+    let token = token_sent_to(&mailer, "carol@example.test");
+    wait_for_event(&events, EventType::MagicLinkSent).await;
+
+    // Opening the link does not use it up; the user confirms.
+    let req = test::TestRequest::get()
+        .uri(&format!("/auth/magic-link/sign-in?token={token}"))
+        .to_request();
+    let page = test::call_and_read_body(&app, req).await;
+    assert!(String::from_utf8_lossy(&page).contains(&token));
+
+    let req = test::TestRequest::post()
+        .uri("/auth/magic-link/sign-in")
+        .set_form([("token", token.as_str())])
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 302);
+    assert_eq!(resp.headers().get("Location").unwrap(), return_to);
+    let session_cookie = resp
+        .response()
+        .cookies()
+        .next()
+        .expect("session cookie")
+        .into_owned();
+    let req = test::TestRequest::get()
+        .uri("/auth/success")
+        .cookie(session_cookie)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    let signed_in = wait_for_event(&events, EventType::UserAuthenticated).await;
+    assert_eq!(signed_in.event.user_id.as_deref(), Some(carol.id.as_str()));
+    assert_eq!(signed_in.event.metadata["method"], "magic_link");
+    assert!(
+        storage
+            .get_user(&carol.id)
+            .await
+            .unwrap()
+            .unwrap()
+            .email_verified
+    );
+
+    let req = test::TestRequest::post()
+        .uri("/auth/magic-link/sign-in")
+        .set_form([("token", token.as_str())])
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    // Two links per address and hour; the third request is refused.
+    let request = || {
+        test::TestRequest::post()
+            .uri("/auth/magic-link")
+            .set_form([("email", "carol@example.test")])
+            .to_request()
+    };
+    assert_eq!(test::call_service(&app, request()).await.status(), 200);
+    assert_eq!(test::call_service(&app, request()).await.status(), 429);
+    assert_eq!(mailer.sent().len(), 2);
+}
+
+#[actix_web::test]
+async fn mail_endpoints_need_mail_enabled() {
+    let storage = support::memory_storage().await;
+    let mut config = Config::default();
+    config.events.enabled = false;
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage)
+        .with_mailer(MemoryMailer::new())
+        .with_endpoints([EndpointGroup::Login])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/auth/forgot-password")
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+}
+
+#[actix_web::test]
+async fn mail_needs_the_issuer() {
+    let storage = support::memory_storage().await;
+    let mut config = mail_config();
+    config.server.issuer = None;
+    let built = ServerBuilder::new(config)
+        .with_storage(storage)
+        .with_mailer(MemoryMailer::new())
+        .with_endpoints([EndpointGroup::Login])
+        .build()
+        .await;
+    let error = built.err().expect("mail without server.issuer is refused");
+    assert!(error.to_string().contains("server.issuer"), "{error}");
+}
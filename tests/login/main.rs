@@ -0,0 +1,15 @@
+//! Sign-in: sessions, silent authentication, mail, social, SAML and LDAP logins.
+
+#[path = "../support/mod.rs"]
+mod support;
+
+mod ldap_authentication;
+mod logout;
+mod mail;
+mod saml;
+mod sessions;
+mod silent_authentication;
+mod social_login_account_linking;
+mod social_login_generic_oidc;
+mod social_login_providers;
+mod social_login_state;
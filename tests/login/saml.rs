@@ -0,0 +1,247 @@
+use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+use actix_web::cookie::{Cookie, Key};
+use actix_web::{test, web, App};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{Duration, SecondsFormat, Utc};
+use flate2::read::DeflateDecoder;
+use rsa::pkcs1v15::SigningKey;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::signature::{SignatureEncoding, Signer};
+use rsa::RsaPrivateKey;
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::sync::Arc;
+
+use oauth2_config::SamlIdpConfig;
+use oauth2_core::{hash_password, User};
+use oauth2_ports::DynStateStore;
+use oauth2_saml::{IdentityProvider, SamlServiceProvider};
+use oauth2_social_login::handlers::auth::BROWSER_BINDING_COOKIE;
+use oauth2_social_login::InMemoryStateStore;
+
+use crate::support;
+
+const IDP_ENTITY_ID: &str = "https://idp.example.test/metadata";
+const SP_ENTITY_ID: &str = "https://sp.example.test/saml";
+const ACS_URL: &str = "https://sp.example.test/auth/saml/corp/acs";
+
+fn service_provider() -> SamlServiceProvider {
+    let corp = IdentityProvider::from_config(
+        "corp",
+        &SamlIdpConfig {
+            enabled: true,
+            metadata_xml: Some(
+                include_str!("../../crates/oauth2-saml/tests/fixtures/idp_metadata.xml")
+                    .to_string(),
+            ),
+            ..Default::default()
+        },
+    )
+    .expect("IdP metadata");
+    SamlServiceProvider::new(
+        SP_ENTITY_ID.to_string(),
+        "https://sp.example.test",
+        [("corp".to_string(), corp)].into(),
+    )
+}
+
+/// A base64 `SAMLResponse` from the test IdP for user `u-1001` with `email`, answering
+/// `request_id`, with a signed assertion. The assertion is written in its exclusive
+/// canonical form so the digest can be taken over the literal text.
+fn signed_response(request_id: &str, email: &str) -> String {
+    let now = Utc::now();
+    let issued = now.to_rfc3339_opts(SecondsFormat::Secs, true);
+    let expires = (now + Duration::minutes(5)).to_rfc3339_opts(SecondsFormat::Secs, true);
+
+    let assertion_start = format!(
+        r#"<saml:Assertion xmlns:saml="urn:oasis:names:tc:SAML:2.0:assertion" ID="_assertion1" IssueInstant="{issued}" Version="2.0"><saml:Issuer>{IDP_ENTITY_ID}</saml:Issuer>"#
+    );
+    let assertion_rest = format!(
+        r#"<saml:Subject><saml:NameID Format="urn:oasis:names:tc:SAML:2.0:nameid-format:persistent">u-1001</saml:NameID><saml:SubjectConfirmation Method="urn:oasis:names:tc:SAML:2.0:cm:bearer"><saml:SubjectConfirmationData InResponseTo="{request_id}" NotOnOrAfter="{expires}" Recipient="{ACS_URL}"></saml:SubjectConfirmationData></saml:SubjectConfirmation></saml:Subject><saml:Conditions NotBefore="{issued}" NotOnOrAfter="{expires}"><saml:AudienceRestriction><saml:Audience>{SP_ENTITY_ID}</saml:Audience></saml:AudienceRestriction></saml:Conditions><saml:AttributeStatement><saml:Attribute Name="mail"><saml:AttributeValue>{email}</saml:AttributeValue></saml:Attribute><saml:Attribute Name="displayName"><saml:AttributeValue>Erin</saml:AttributeValue></saml:Attribute></saml:AttributeStatement></saml:Assertion>"#
+    );
+    let digest = STANDARD.encode(Sha256::digest(
+        format!("{assertion_start}{assertion_rest}").as_bytes(),
+    ));
+    let signed_info = format!(
+        r##"<ds:SignedInfo xmlns:ds="http://www.w3.org/2000/09/xmldsig#"><ds:CanonicalizationMethod Algorithm="http://www.w3.org/2001/10/xml-exc-c14n#"></ds:CanonicalizationMethod><ds:SignatureMethod Algorithm="http://www.w3.org/2001/04/xmldsig-more#rsa-sha256"></ds:SignatureMethod><ds:Reference URI="#_assertion1"><ds:Transforms><ds:Transform Algorithm="http://www.w3.org/2000/09/xmldsig#enveloped-signature"></ds:Transform><ds:Transform Algorithm="http://www.w3.org/2001/10/xml-exc-c14n#"></ds:Transform></ds:Transforms><ds:DigestMethod Algorithm="http://www.w3.org/2001/04/xmlenc#sha256"></ds:DigestMethod><ds:DigestValue>{digest}</ds:DigestValue></ds:Reference></ds:SignedInfo>"##
+    );
+    let key = RsaPrivateKey::from_pkcs8_pem(include_str!(
+        "../../crates/oauth2-saml/tests/fixtures/idp_key.pem"
+    ))
+    .expect("IdP key");
+    let signature = STANDARD.encode(
+        SigningKey::<Sha256>::new(key)
+            .sign(signed_info.as_bytes())
+            .to_bytes(),
+    );
+
+    let response = format!(
+        r#"<samlp:Response xmlns:samlp="urn:oasis:names:tc:SAML:2.0:protocol" xmlns:saml="urn:oasis:names:tc:SAML:2.0:assertion" Destination="{ACS_URL}" ID="_response1" InResponseTo="{request_id}" IssueInstant="{issued}" Version="2.0"><saml:Issuer>{IDP_ENTITY_ID}</saml:Issuer><samlp:Status><samlp:StatusCode Value="urn:oasis:names:tc:SAML:2.0:status:Success"/></samlp:Status>{assertion_start}<ds:Signature xmlns:ds="http://www.w3.org/2000/09/xmldsig#">{signed_info}<ds:SignatureValue>{signature}</ds:SignatureValue></ds:Signature>{assertion_rest}</samlp:Response>"#
+    );
+    STANDARD.encode(response)
+}
+
+fn query_param(location: &str, name: &str) -> Option<String> {
+    url::Url::parse(location)
+        .ok()?
+        .query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+/// The `ID` of the deflated, base64 `SAMLRequest` in a redirect to the IdP.
+fn authn_request_id(location: &str) -> String {
+    let deflated = STANDARD
+        .decode(query_param(location, "SAMLRequest").expect("SAMLRequest"))
+        .expect("base64");
+    let mut request = String::new();
+    DeflateDecoder::new(deflated.as_slice())
+        .read_to_string(&mut request)
+        .expect("deflated");
+    let id = request.split(r#" ID=""#).nth(1).expect("request ID");
+    id[..id.find('"').unwrap()].to_string()
+}
+
+#[actix_web::test]
+async fn signed_responses_sign_in_once_and_go_through_account_linking() {
+    let states: DynStateStore = Arc::new(InMemoryStateStore::new());
+    let storage = support::memory_storage().await;
+
+    let app = test::init_service(
+        App::new()
+            .wrap(SessionMiddleware::new(
+                CookieSessionStore::default(),
+                Key::generate(),
+            ))
+            .app_data(web::Data::new(service_provider()))
+            .app_data(web::Data::new(states))
+            .app_data(web::Data::new(storage.clone()))
+            .configure(oauth2_saml::handlers::configure),
+    )
+    .await;
+
+    // The browser's binding cookie, reused by every login it starts.
+    let browser = Cookie::new(BROWSER_BINDING_COOKIE, "erins-browser");
+    // Returns the RelayState and request ID of a fresh login.
+    let start_login = async || {
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/auth/saml/corp/login")
+                .cookie(browser.clone())
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 302);
+        let location = resp
+            .headers()
+            .get("location")
+            .and_then(|value| value.to_str().ok())
+            .expect("location")
+            .to_string();
+        assert!(location.starts_with("https://idp.example.test/sso?"));
+        (
+            query_param(&location, "RelayState").expect("RelayState"),
+            authn_request_id(&location),
+        )
+    };
+    let post_response = |relay_state: &str, saml_response: &str| {
+        test::TestRequest::post()
+            .uri("/auth/saml/corp/acs")
+            .cookie(browser.clone())
+            .set_form([("SAMLResponse", saml_response), ("RelayState", relay_state)])
+            .to_request()
+    };
+
+    // The SP metadata names the assertion consumer service.
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/auth/saml/corp/metadata")
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(
+        body.contains(&format!(r#"entityID="{SP_ENTITY_ID}""#)),
+        "{body}"
+    );
+    assert!(body.contains(&format!(r#"Location="{ACS_URL}""#)), "{body}");
+
+    // A signed response for the outstanding request signs in.
+    let (relay_state, request_id) = start_login().await;
+    let saml_response = signed_response(&request_id, "erin@corp.test");
+    let resp = test::call_service(&app, post_response(&relay_state, &saml_response)).await;
+    assert_eq!(resp.status(), 302);
+    assert_eq!(
+        resp.headers().get("location").unwrap().to_str().unwrap(),
+        "/auth/success"
+    );
+
+    // The RelayState is spent, so the same response cannot be replayed.
+    let resp = test::call_service(&app, post_response(&relay_state, &saml_response)).await;
+    assert_eq!(resp.status(), 403);
+
+    // A response posted from another browser is rejected.
+    let (relay_state, request_id) = start_login().await;
+    let saml_response = signed_response(&request_id, "erin@corp.test");
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/auth/saml/corp/acs")
+            .set_form([
+                ("SAMLResponse", saml_response.as_str()),
+                ("RelayState", relay_state.as_str()),
+            ])
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 403);
+
+    // A response answering another request is rejected, as is a tampered one.
+    let (relay_state, _) = start_login().await;
+    let resp = test::call_service(
+        &app,
+        post_response(&relay_state, &signed_response("_other", "erin@corp.test")),
+    )
+    .await;
+    assert_eq!(resp.status(), 403);
+    let (relay_state, request_id) = start_login().await;
+    let xml = String::from_utf8(
+        STANDARD
+            .decode(signed_response(&request_id, "erin@corp.test"))
+            .unwrap(),
+    )
+    .unwrap();
+    let tampered = STANDARD.encode(xml.replace("erin@corp.test", "admin@corp.test"));
+    let resp = test::call_service(&app, post_response(&relay_state, &tampered)).await;
+    assert_eq!(resp.status(), 403);
+
+    // An existing local account with the asserted email must confirm the link first.
+    let frank = User::new(
+        "frank".to_string(),
+        hash_password("correct horse").unwrap(),
+        "frank@corp.test".to_string(),
+    );
+    storage.save_user(&frank).await.unwrap();
+    let (relay_state, request_id) = start_login().await;
+    let resp = test::call_service(
+        &app,
+        post_response(
+            &relay_state,
+            &signed_response(&request_id, "frank@corp.test"),
+        ),
+    )
+    .await;
+    assert_eq!(resp.status(), 302);
+    assert_eq!(
+        resp.headers().get("location").unwrap().to_str().unwrap(),
+        "/auth/link"
+    );
+    assert!(storage
+        .get_federated_identity("corp", "u-1001")
+        .await
+        .unwrap()
+        .is_none());
+}
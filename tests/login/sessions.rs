@@ -0,0 +1,228 @@
+use actix_web::cookie::Cookie;
+use actix_web::{test, App};
+use serde_json::Value;
+
+use oauth2_config::{Config, MagicLinkConfig, MailConfig, SessionConfig};
+use oauth2_mail::MemoryMailer;
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+fn sessions_config() -> Config {
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.server.issuer = Some("https://auth.example".to_string());
+    config.mail = Some(MailConfig {
+        enabled: true,
+        from: "Example <no-reply@example.com>".to_string(),
+        verification_ttl_secs: 86400,
+        password_reset_ttl_secs: 3600,
+        smtp: None,
+        magic_link: MagicLinkConfig {
+            enabled: true,
+            ..MagicLinkConfig::default()
+        },
+    });
+    config.session = Some(SessionConfig::default());
+    config
+}
+
+/// Sign in with a mailed link, returning the new session's cookie.
+macro_rules! sign_in {
+    ($app:expr, $mailer:expr, $email:expr) => {{
+        let req = test::TestRequest::post()
+            .uri("/auth/magic-link")
+            .set_form([("email", $email)])
+            .to_request();
+        assert_eq!(test::call_service($app, req).await.status(), 200);
+        let body = $mailer.last_to($email).expect("sign-in mail").body;
+        let (_, rest) = body.split_once("?token=").expect("link in body");
+        let token = rest.split_whitespace().next().unwrap();
+
+        let req = test::TestRequest::post()
+            .uri("/auth/magic-link/sign-in")
+            .set_form([("token", token)])
+            .to_request();
+        let resp = test::call_service($app, req).await;
+        assert_eq!(resp.status(), 302);
+        let cookie: Cookie<'static> = resp
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == "oauth2_session")
+            .expect("session cookie")
+            .into_owned();
+        assert!(cookie.secure().unwrap_or(false));
+        cookie
+    }};
+}
+
+/// `GET /auth/sessions` with `$cookie`: the status and JSON body.
+macro_rules! list {
+    ($app:expr, $cookie:expr) => {{
+        let req = test::TestRequest::get()
+            .uri("/auth/sessions")
+            .cookie($cookie.clone())
+            .to_request();
+        let resp = test::call_service($app, req).await;
+        let status = resp.status().as_u16();
+        let body = test::read_body(resp).await;
+        (
+            status,
+            serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null),
+        )
+    }};
+}
+
+macro_rules! revoke {
+    ($app:expr, $cookie:expr, $id:expr) => {{
+        let req = test::TestRequest::delete()
+            .uri(&format!("/auth/sessions/{}", $id))
+            .cookie($cookie.clone())
+            .to_request();
+        test::call_service($app, req).await
+    }};
+}
+
+#[actix_web::test]
+async fn users_list_and_revoke_their_sessions() {
+    let storage = support::memory_storage().await;
+    let alice = support::save_user(&storage, "alice", "password").await;
+    support::save_user(&storage, "bob", "password").await;
+
+    let mailer = MemoryMailer::new();
+    let oauth2 = ServerBuilder::new(sessions_config())
+        .with_storage(storage.clone())
+        .with_mailer(mailer.clone())
+        .with_endpoints([EndpointGroup::Login])
+        .build()
+        .await
+        .expect("build server");
+    assert_eq!(oauth2.session_store().backend_name(), "database");
+    let app = test::init_service(
+        App::new()
+            .wrap(oauth2.session_middleware())
+            .configure(|cfg| oauth2.configure(cfg)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/auth/sessions").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 401);
+
+    let laptop = sign_in!(&app, mailer, "alice@example.test");
+    let phone = sign_in!(&app, mailer, "alice@example.test");
+    let bobs = sign_in!(&app, mailer, "bob@example.test");
+
+    // The cookie carries a key, not the session state; the store holds its hash.
+    let (status, listed) = list!(&app, laptop);
+    assert_eq!(status, 200);
+    let sessions = listed["sessions"].as_array().unwrap();
+    assert_eq!(sessions.len(), 2);
+    assert!(sessions.iter().all(|s| s["id"] != laptop.value()));
+    let current: Vec<&Value> = sessions.iter().filter(|s| s["current"] == true).collect();
+    assert_eq!(current.len(), 1);
+    let laptop_id = current[0]["id"].as_str().unwrap().to_string();
+    let phone_id = sessions.iter().find(|s| s["current"] == false).unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let stored = storage
+        .get_user_session(&laptop_id)
+        .await
+        .unwrap()
+        .expect("stored session");
+    assert_eq!(stored.user_id.as_deref(), Some(alice.id.as_str()));
+    assert_eq!(
+        (stored.expires_at - stored.created_at).num_seconds(),
+        12 * 60 * 60
+    );
+
+    // Other users' sessions cannot be revoked, or told apart from missing ones.
+    let (_, bob_listed) = list!(&app, bobs);
+    let bob_id = bob_listed["sessions"][0]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(revoke!(&app, laptop, &bob_id).status(), 404);
+    assert_eq!(revoke!(&app, laptop, "missing").status(), 404);
+    assert_eq!(list!(&app, bobs).0, 200);
+
+    // Revoking the phone signs it out.
+    assert_eq!(revoke!(&app, laptop, &phone_id).status(), 204);
+    assert_eq!(list!(&app, phone).0, 401);
+    let (_, listed) = list!(&app, laptop);
+    assert_eq!(listed["sessions"].as_array().unwrap().len(), 1);
+
+    // Revoking the current session also clears its cookie.
+    let resp = revoke!(&app, laptop, &laptop_id);
+    assert_eq!(resp.status(), 204);
+    let cleared = resp
+        .response()
+        .cookies()
+        .find(|cookie| cookie.name() == "oauth2_session")
+        .expect("removal cookie");
+    assert_eq!(cleared.value(), "");
+    assert_eq!(list!(&app, laptop).0, 401);
+    assert!(storage
+        .get_user_session(&laptop_id)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[actix_web::test]
+async fn sessions_end_after_the_idle_and_absolute_timeouts() {
+    let storage = support::memory_storage().await;
+    support::save_user(&storage, "alice", "password").await;
+
+    let mut config = sessions_config();
+    let session = config.session.as_mut().unwrap();
+    session.store = oauth2_config::SessionStoreBackend::Memory;
+    session.idle_timeout_secs = 600;
+    let mailer = MemoryMailer::new();
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage.clone())
+        .with_mailer(mailer.clone())
+        .with_endpoints([EndpointGroup::Login])
+        .build()
+        .await
+        .expect("build server");
+    let store = oauth2.session_store().clone();
+    assert_eq!(store.backend_name(), "memory");
+    let app = test::init_service(
+        App::new()
+            .wrap(oauth2.session_middleware())
+            .configure(|cfg| oauth2.configure(cfg)),
+    )
+    .await;
+
+    let idle = sign_in!(&app, mailer, "alice@example.test");
+    let (_, listed) = list!(&app, idle);
+    let idle_id = listed["sessions"][0]["id"].as_str().unwrap().to_string();
+
+    // Every request moves the idle timeout forward.
+    let before = store.get(&idle_id).await.unwrap().unwrap();
+    assert_eq!(
+        (before.idle_expires_at - before.last_seen_at).num_seconds(),
+        600
+    );
+    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    assert_eq!(list!(&app, idle).0, 200);
+    let after = store.get(&idle_id).await.unwrap().unwrap();
+    assert!(after.idle_expires_at > before.idle_expires_at);
+
+    let mut stale = after.clone();
+    stale.idle_expires_at = chrono::Utc::now() - chrono::Duration::seconds(1);
+    store.save(&stale).await.unwrap();
+    assert_eq!(list!(&app, idle).0, 401);
+    assert!(store.get(&idle_id).await.unwrap().is_none());
+
+    // Use does not extend a session past its absolute timeout.
+    let busy = sign_in!(&app, mailer, "alice@example.test");
+    let (_, listed) = list!(&app, busy);
+    let busy_id = listed["sessions"][0]["id"].as_str().unwrap().to_string();
+    let mut ended = store.get(&busy_id).await.unwrap().unwrap();
+    ended.expires_at = chrono::Utc::now() - chrono::Duration::seconds(1);
+    store.save(&ended).await.unwrap();
+    assert_eq!(list!(&app, busy).0, 401);
+}
@@ -0,0 +1,372 @@
+use actix_web::cookie::Cookie;
+use actix_web::{test, App};
+
+use oauth2_config::{Config, MagicLinkConfig, MailConfig, SessionConfig};
+use oauth2_core::{AuditAction, AuditOutcome};
+use oauth2_mail::MemoryMailer;
+use oauth2_ports::AuditQuery;
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+fn silent_config() -> Config {
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.server.issuer = Some("https://auth.example".to_string());
+    config.mail = Some(MailConfig {
+        enabled: true,
+        from: "Example <no-reply@example.com>".to_string(),
+        verification_ttl_secs: 86400,
+        password_reset_ttl_secs: 3600,
+        smtp: None,
+        magic_link: MagicLinkConfig {
+            enabled: true,
+            ..MagicLinkConfig::default()
+        },
+    });
+    config.session = Some(SessionConfig::default());
+    config
+}
+
+fn query_param(location: &str, key: &str) -> Option<String> {
+    url::Url::parse(location)
+        .ok()?
+        .query_pairs()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.into_owned())
+}
+
+/// Sign in with a mailed link, returning the new session's cookie.
+macro_rules! sign_in {
+    ($app:expr, $mailer:expr, $email:expr) => {{
+        let req = test::TestRequest::post()
+            .uri("/auth/magic-link")
+            .set_form([("email", $email)])
+            .to_request();
+        assert_eq!(test::call_service($app, req).await.status(), 200);
+        let body = $mailer.last_to($email).expect("sign-in mail").body;
+        let (_, rest) = body.split_once("?token=").expect("link in body");
+        let token = rest.split_whitespace().next().unwrap();
+
+        let req = test::TestRequest::post()
+            .uri("/auth/magic-link/sign-in")
+            .set_form([("token", token)])
+            .to_request();
+        let resp = test::call_service($app, req).await;
+        assert_eq!(resp.status(), 302);
+        let cookie: Cookie<'static> = resp
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == "oauth2_session")
+            .expect("session cookie")
+            .into_owned();
+        cookie
+    }};
+}
+
+/// Authorize `web` for `$scope` with extra query parameters, optionally with a session
+/// cookie: the `Location` the client is sent back to.
+macro_rules! authorize {
+    ($app:expr, $cookie:expr, $scope:expr, $extra:expr) => {{
+        let mut req = test::TestRequest::get().uri(&format!(
+            "/oauth/authorize?response_type=code&client_id=web&redirect_uri=https%3A%2F%2Fapp.example%2Fcb&scope={}&state=xyz&code_challenge=E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM&code_challenge_method=S256{}",
+            $scope, $extra
+        ));
+        let cookie: Option<&Cookie<'static>> = $cookie;
+        if let Some(cookie) = cookie {
+            req = req.cookie(cookie.clone());
+        }
+        let resp = test::call_service($app, req.to_request()).await;
+        assert_eq!(resp.status(), 302);
+        resp.headers()
+            .get("Location")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string()
+    }};
+}
+
+const AUTHORIZE: &str = "/oauth/authorize?response_type=code&client_id=web&redirect_uri=https%3A%2F%2Fapp.example%2Fcb&scope=read&state=xyz&code_challenge=E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM&code_challenge_method=S256";
+
+/// Fetch the consent page of an interactive authorize request for `read`: the session
+/// cookie holding the pending consent, the consent token and the page.
+macro_rules! consent_page {
+    ($app:expr, $cookie:expr) => {{
+        let cookie: &Cookie<'static> = $cookie;
+        let req = test::TestRequest::get()
+            .uri(AUTHORIZE)
+            .cookie(cookie.clone())
+            .to_request();
+        let resp = test::call_service($app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("Cache-Control").unwrap(), "no-store");
+        let cookie = resp
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == "oauth2_session")
+            .map(|cookie| cookie.into_owned())
+            .unwrap_or_else(|| cookie.clone());
+        let page = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        let (_, rest) = page
+            .split_once(r#"name="consent_token" value=""#)
+            .expect("consent token in page");
+        let token = rest.split('"').next().unwrap().to_string();
+        (cookie, token, page)
+    }};
+}
+
+/// Answer the consent page with `$decision`: the `Location` the client is sent back to.
+macro_rules! decide {
+    ($app:expr, $cookie:expr, $token:expr, $decision:expr) => {{
+        let req = test::TestRequest::post()
+            .uri(AUTHORIZE)
+            .cookie($cookie.clone())
+            .set_form([("decision", $decision), ("consent_token", $token)])
+            .to_request();
+        let resp = test::call_service($app, req).await;
+        assert_eq!(resp.status(), 302);
+        resp.headers()
+            .get("Location")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string()
+    }};
+}
+
+#[actix_web::test]
+async fn prompt_none_issues_codes_only_for_signed_in_users_who_approved_the_client() {
+    let storage = support::memory_storage().await;
+    let mut carol = support::save_user(&storage, "carol", "password").await;
+    let client = support::client(
+        "web",
+        "https://app.example/cb",
+        &["authorization_code"],
+        "read write",
+    );
+    support::save_client(&storage, &client).await;
+
+    let mailer = MemoryMailer::new();
+    let oauth2 = ServerBuilder::new(silent_config())
+        .with_storage(storage.clone())
+        .with_mailer(mailer.clone())
+        .with_endpoints([
+            EndpointGroup::OAuth,
+            EndpointGroup::Discovery,
+            EndpointGroup::Login,
+        ])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(
+        App::new()
+            .wrap(oauth2.session_middleware())
+            .configure(|cfg| oauth2.configure(cfg)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/.well-known/openid-configuration")
+        .to_request();
+    let discovery: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        discovery["prompt_values_supported"],
+        serde_json::json!(["none"])
+    );
+
+    // Without a session there is nobody to issue a code for.
+    let location = authorize!(&app, None, "read", "&prompt=none");
+    assert!(location.starts_with("https://app.example/cb?"));
+    assert_eq!(
+        query_param(&location, "error").as_deref(),
+        Some("login_required")
+    );
+    assert_eq!(query_param(&location, "state").as_deref(), Some("xyz"));
+    assert_eq!(
+        query_param(&location, "iss").as_deref(),
+        Some("https://auth.example")
+    );
+    assert!(query_param(&location, "code").is_none());
+
+    // Signed in, but the client was never approved.
+    let cookie = sign_in!(&app, mailer, "carol@example.test");
+    let location = authorize!(&app, Some(&cookie), "read", "&prompt=none");
+    assert_eq!(
+        query_param(&location, "error").as_deref(),
+        Some("consent_required")
+    );
+
+    // An interactive request asks the signed-in user, and their approval issues a code and
+    // is recorded.
+    let (cookie, token, _) = consent_page!(&app, &cookie);
+    let location = decide!(&app, cookie, token.as_str(), "approve");
+    let code = query_param(&location, "code").expect("code");
+    let issued = storage
+        .get_authorization_code(&code)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(issued.user_id, carol.id);
+
+    let location = authorize!(&app, Some(&cookie), "read", "&prompt=none");
+    let code = query_param(&location, "code").expect("silent code");
+    let issued = storage
+        .get_authorization_code(&code)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(issued.user_id, carol.id);
+    assert_eq!(issued.scope, "read");
+
+    // Approval covers the scopes granted, not more.
+    let location = authorize!(&app, Some(&cookie), "read%20write", "&prompt=none");
+    assert_eq!(
+        query_param(&location, "error").as_deref(),
+        Some("consent_required")
+    );
+
+    // The session belongs to a different account than the client expects.
+    let location = authorize!(
+        &app,
+        Some(&cookie),
+        "read",
+        "&prompt=none&login_hint=dave%40example.test"
+    );
+    assert_eq!(
+        query_param(&location, "error").as_deref(),
+        Some("interaction_required")
+    );
+    let location = authorize!(
+        &app,
+        Some(&cookie),
+        "read",
+        "&prompt=none&login_hint=Carol%40Example.test"
+    );
+    assert!(query_param(&location, "code").is_some());
+
+    // `none` cannot be combined with prompts that need the user.
+    let location = authorize!(&app, Some(&cookie), "read", "&prompt=none%20login");
+    assert_eq!(
+        query_param(&location, "error").as_deref(),
+        Some("invalid_request")
+    );
+
+    // Disabled users have to sign in again, which they cannot do silently.
+    carol.enabled = false;
+    storage.update_user(&carol).await.expect("disable user");
+    let location = authorize!(&app, Some(&cookie), "read", "&prompt=none");
+    assert_eq!(
+        query_param(&location, "error").as_deref(),
+        Some("login_required")
+    );
+
+    // The sign-in and each authorization decision are in the audit log.
+    let audit = |action| AuditQuery {
+        action: Some(action),
+        ..AuditQuery::default()
+    };
+    let logins = storage
+        .list_audit_records(&audit(AuditAction::Login))
+        .await
+        .unwrap();
+    assert_eq!(logins.len(), 1);
+    assert_eq!(logins[0].actor.as_deref(), Some(carol.id.as_str()));
+    assert_eq!(logins[0].target.as_deref(), Some("magic_link"));
+    let consents = storage
+        .list_audit_records(&audit(AuditAction::Consent))
+        .await
+        .unwrap();
+    assert_eq!(consents.len(), 9);
+    assert!(consents
+        .iter()
+        .all(|record| record.client_id.as_deref() == Some("web")));
+    assert_eq!(consents[0].outcome, AuditOutcome::Failure);
+    assert_eq!(consents[0].detail.as_deref(), Some("login_required"));
+    assert_eq!(consents[0].actor.as_deref(), Some(carol.id.as_str()));
+    assert!(consents[8].actor.is_none());
+}
+
+#[actix_web::test]
+async fn the_consent_page_shows_the_client_and_only_an_approval_is_remembered() {
+    let storage = support::memory_storage().await;
+    support::save_user(&storage, "carol", "password").await;
+    let mut client = support::client(
+        "web",
+        "https://app.example/cb",
+        &["authorization_code"],
+        "read write",
+    );
+    client.name = "Photo <Printer>".to_string();
+    client.logo_uri = Some("https://app.example/logo.png".to_string());
+    client.client_uri = Some("https://app.example/".to_string());
+    client.policy_uri = Some("https://app.example/privacy".to_string());
+    client.tos_uri = Some("https://app.example/terms".to_string());
+    support::save_client(&storage, &client).await;
+
+    let mailer = MemoryMailer::new();
+    let oauth2 = ServerBuilder::new(silent_config())
+        .with_storage(storage.clone())
+        .with_mailer(mailer.clone())
+        .with_endpoints([EndpointGroup::OAuth, EndpointGroup::Login])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(
+        App::new()
+            .wrap(oauth2.session_middleware())
+            .configure(|cfg| oauth2.configure(cfg)),
+    )
+    .await;
+    let cookie = sign_in!(&app, mailer, "carol@example.test");
+
+    let (cookie, token, page) = consent_page!(&app, &cookie);
+    assert!(page.contains("Photo &lt;Printer&gt;"));
+    assert!(!page.contains("<Printer>"));
+    assert!(page.contains(r#"<img src="https://app.example/logo.png""#));
+    assert!(page.contains(r#"<a href="https://app.example/">"#));
+    assert!(page.contains(r#"<a href="https://app.example/privacy">Privacy policy</a>"#));
+    assert!(page.contains(r#"<a href="https://app.example/terms">Terms of service</a>"#));
+    assert!(page.contains("<li>read</li>"));
+
+    // Showing the page decides nothing.
+    let consents = storage
+        .list_audit_records(&AuditQuery {
+            action: Some(AuditAction::Consent),
+            ..AuditQuery::default()
+        })
+        .await
+        .unwrap();
+    assert!(consents.is_empty());
+
+    // A denial is sent to the client and not remembered.
+    let location = decide!(&app, cookie, token.as_str(), "deny");
+    assert_eq!(
+        query_param(&location, "error").as_deref(),
+        Some("access_denied")
+    );
+    let location = authorize!(&app, Some(&cookie), "read", "&prompt=none");
+    assert_eq!(
+        query_param(&location, "error").as_deref(),
+        Some("consent_required")
+    );
+
+    // Only the token of the page last shown answers it, once.
+    let (cookie, token, _) = consent_page!(&app, &cookie);
+    let location = decide!(&app, cookie, "forged", "approve");
+    assert_eq!(
+        query_param(&location, "error").as_deref(),
+        Some("access_denied")
+    );
+    let location = decide!(&app, cookie, token.as_str(), "approve");
+    assert_eq!(
+        query_param(&location, "error").as_deref(),
+        Some("access_denied")
+    );
+
+    let (cookie, token, _) = consent_page!(&app, &cookie);
+    let location = decide!(&app, cookie, token.as_str(), "approve");
+    assert!(query_param(&location, "code").is_some());
+    let location = authorize!(&app, Some(&cookie), "read", "&prompt=none");
+    assert!(query_param(&location, "code").is_some());
+}
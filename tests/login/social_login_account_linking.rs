@@ -0,0 +1,444 @@
+use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+use actix_web::cookie::{Cookie, Key};
+use actix_web::dev::ServiceResponse;
+use actix_web::{test, web, App};
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use jsonwebtoken::{EncodingKey, Header};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+
+use oauth2_config::ProviderConfig;
+use oauth2_core::{hash_password, FederatedIdentity, User};
+use oauth2_ports::DynStateStore;
+use oauth2_social_login::handlers::{auth, link};
+use oauth2_social_login::{InMemoryStateStore, SocialLoginConfig, SocialLoginService, TokenVault};
+
+use crate::support;
+
+/// An OpenID Connect provider whose only account is `kc-42` with email `carol@corp.test`.
+/// The ID token is signed with the client secret and carries whatever `nonce` holds; a
+/// refresh grant issues `idp-refreshed-token` without rotating the refresh token. Returns
+/// the issuer URL.
+async fn serve_identity_provider(nonce: Arc<Mutex<String>>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let issuer = format!("http://{}", listener.local_addr().expect("local addr"));
+
+    let discovery = json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{issuer}/auth"),
+        "token_endpoint": format!("{issuer}/token"),
+        "userinfo_endpoint": format!("{issuer}/userinfo"),
+    });
+    let token_issuer = issuer.clone();
+    let app = Router::new()
+        .route(
+            "/.well-known/openid-configuration",
+            get(move || async move { Json(discovery) }),
+        )
+        .route(
+            "/token",
+            post(move |body: String| async move {
+                let token_issuer = token_issuer.clone();
+                if body.contains("grant_type=refresh_token") {
+                    assert!(body.contains("refresh_token=idp-refresh-token"), "{body}");
+                    return Json(json!({
+                        "access_token": "idp-refreshed-token",
+                        "token_type": "Bearer",
+                        "expires_in": 3600,
+                    }));
+                }
+                let id_token = jsonwebtoken::encode(
+                    &Header::default(),
+                    &json!({
+                        "iss": token_issuer,
+                        "sub": "kc-42",
+                        "aud": "auth_server",
+                        "iat": chrono::Utc::now().timestamp(),
+                        "exp": chrono::Utc::now().timestamp() + 300,
+                        "nonce": *nonce.lock().unwrap(),
+                    }),
+                    &EncodingKey::from_secret(b"kc_secret"),
+                )
+                .unwrap();
+                Json(json!({
+                    "access_token": "idp-access-token",
+                    "token_type": "Bearer",
+                    "expires_in": 3600,
+                    "refresh_token": "idp-refresh-token",
+                    "id_token": id_token,
+                }))
+            }),
+        )
+        .route(
+            "/userinfo",
+            get(|headers: HeaderMap| async move {
+                if headers.get("authorization").and_then(|v| v.to_str().ok())
+                    == Some("Bearer idp-access-token")
+                {
+                    Ok(Json(json!({
+                        "sub": "kc-42",
+                        "email": "carol@corp.test",
+                        "preferred_username": "carol",
+                    })))
+                } else {
+                    Err(StatusCode::UNAUTHORIZED)
+                }
+            }),
+        );
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    issuer
+}
+
+fn keycloak_config(issuer: String) -> SocialLoginConfig {
+    SocialLoginConfig {
+        generic: [(
+            "keycloak".to_string(),
+            ProviderConfig {
+                enabled: true,
+                client_id: Some("auth_server".to_string()),
+                client_secret: Some("kc_secret".to_string()),
+                redirect_uri: Some("http://localhost:8080/auth/callback/keycloak".to_string()),
+                issuer_url: Some(issuer),
+                ..Default::default()
+            },
+        )]
+        .into(),
+        ..Default::default()
+    }
+}
+
+fn binding_cookie(resp: &ServiceResponse) -> Cookie<'static> {
+    resp.response()
+        .cookies()
+        .find(|cookie| cookie.name() == auth::BROWSER_BINDING_COOKIE)
+        .expect("browser binding cookie")
+        .into_owned()
+}
+
+fn query_param(location: &str, name: &str) -> Option<String> {
+    url::Url::parse(location)
+        .ok()?
+        .query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+#[actix_web::test]
+async fn matching_email_is_linked_only_after_password_confirmation() {
+    let id_token_nonce = Arc::new(Mutex::new(String::new()));
+    let issuer = serve_identity_provider(id_token_nonce.clone()).await;
+    let states: DynStateStore = Arc::new(InMemoryStateStore::new());
+    let storage = support::memory_storage().await;
+
+    // An existing local account with the provider account's email.
+    let carol = User::new(
+        "carol".to_string(),
+        hash_password("correct horse").unwrap(),
+        "Carol@corp.test".to_string(),
+    );
+    storage.save_user(&carol).await.unwrap();
+
+    let social = keycloak_config(issuer);
+    let app = test::init_service(
+        App::new()
+            .wrap(SessionMiddleware::new(
+                CookieSessionStore::default(),
+                Key::generate(),
+            ))
+            .app_data(web::Data::new(Arc::new(social)))
+            .app_data(web::Data::new(states))
+            .app_data(web::Data::new(storage.clone()))
+            .route("/auth/login/{provider}", web::get().to(auth::generic_login))
+            .route(
+                "/auth/callback/{provider}",
+                web::get().to(auth::auth_callback),
+            )
+            .service(
+                web::resource("/auth/link")
+                    .route(web::get().to(link::link_account_page))
+                    .route(web::post().to(link::confirm_link)),
+            ),
+    )
+    .await;
+
+    // Signs in at the provider and returns the callback response.
+    let provider_callback = async || {
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/auth/login/keycloak")
+                .to_request(),
+        )
+        .await;
+        let location = resp
+            .headers()
+            .get("location")
+            .and_then(|value| value.to_str().ok())
+            .expect("location")
+            .to_string();
+        *id_token_nonce.lock().unwrap() = query_param(&location, "nonce").expect("nonce");
+        let state = query_param(&location, "state").expect("state");
+        test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri(&format!(
+                    "/auth/callback/keycloak?code=idp-code&state={state}"
+                ))
+                .cookie(binding_cookie(&resp))
+                .to_request(),
+        )
+        .await
+    };
+    let confirm = |cookie, password: &str| {
+        test::TestRequest::post()
+            .uri("/auth/link")
+            .cookie(cookie)
+            .set_form([("password", password)])
+            .to_request()
+    };
+
+    // Nothing is pending without a matching login.
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get().uri("/auth/link").to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 302);
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/auth/link")
+            .set_form([("password", "correct horse")])
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 403);
+
+    // The email matches carol, so the login waits for confirmation instead of signing in.
+    let resp = provider_callback().await;
+    assert_eq!(resp.status(), 302);
+    assert_eq!(
+        resp.headers().get("location").unwrap().to_str().unwrap(),
+        "/auth/link"
+    );
+    let cookie = resp
+        .response()
+        .cookies()
+        .next()
+        .expect("session cookie")
+        .into_owned();
+
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/auth/link")
+            .cookie(cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(body.contains("<strong>carol</strong>"), "{body}");
+
+    // A wrong password links nothing.
+    let resp = test::call_service(&app, confirm(cookie, "guess")).await;
+    assert_eq!(resp.status(), 403);
+    assert!(storage
+        .get_federated_identity("keycloak", "kc-42")
+        .await
+        .unwrap()
+        .is_none());
+
+    // The right one links the provider account and signs in as carol.
+    let resp = provider_callback().await;
+    let cookie = resp
+        .response()
+        .cookies()
+        .next()
+        .expect("session cookie")
+        .into_owned();
+    let resp = test::call_service(&app, confirm(cookie, "correct horse")).await;
+    assert_eq!(resp.status(), 302);
+    assert_eq!(
+        resp.headers().get("location").unwrap().to_str().unwrap(),
+        "/auth/success"
+    );
+    let identity = storage
+        .get_federated_identity("keycloak", "kc-42")
+        .await
+        .unwrap()
+        .expect("identity linked");
+    assert_eq!(identity.user_id, carol.id);
+
+    // From now on the provider login signs in directly.
+    let resp = provider_callback().await;
+    assert_eq!(resp.status(), 302);
+    assert_eq!(
+        resp.headers().get("location").unwrap().to_str().unwrap(),
+        "/auth/success"
+    );
+
+    // Unless the linked account has been disabled.
+    let mut disabled = carol.clone();
+    disabled.enabled = false;
+    storage.update_user(&disabled).await.unwrap();
+    let resp = provider_callback().await;
+    assert_eq!(resp.status(), 403);
+}
+
+#[actix_web::test]
+async fn linked_logins_keep_sealed_provider_tokens_and_refresh_them() {
+    let id_token_nonce = Arc::new(Mutex::new(String::new()));
+    let issuer = serve_identity_provider(id_token_nonce.clone()).await;
+    let states: DynStateStore = Arc::new(InMemoryStateStore::new());
+    let storage = support::memory_storage().await;
+    let vault =
+        TokenVault::from_base64_key("MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=").unwrap();
+
+    let carol = User::new(
+        "carol".to_string(),
+        hash_password("correct horse").unwrap(),
+        "carol@corp.test".to_string(),
+    );
+    storage.save_user(&carol).await.unwrap();
+    storage
+        .save_federated_identity(&FederatedIdentity::new(
+            "keycloak".to_string(),
+            "kc-42".to_string(),
+            carol.id.clone(),
+        ))
+        .await
+        .unwrap();
+
+    let social = keycloak_config(issuer);
+    let app = test::init_service(
+        App::new()
+            .wrap(SessionMiddleware::new(
+                CookieSessionStore::default(),
+                Key::generate(),
+            ))
+            .app_data(web::Data::new(Arc::new(social.clone())))
+            .app_data(web::Data::new(states))
+            .app_data(web::Data::new(storage.clone()))
+            .app_data(web::Data::new(vault.clone()))
+            .route("/auth/login/{provider}", web::get().to(auth::generic_login))
+            .route(
+                "/auth/callback/{provider}",
+                web::get().to(auth::auth_callback),
+            )
+            .route("/auth/connections", web::get().to(link::connections)),
+    )
+    .await;
+
+    // Connections belong to a signed-in local account.
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/auth/connections")
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 403);
+
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/auth/login/keycloak")
+            .to_request(),
+    )
+    .await;
+    let location = resp.headers().get("location").unwrap().to_str().unwrap();
+    *id_token_nonce.lock().unwrap() = query_param(location, "nonce").expect("nonce");
+    let state = query_param(location, "state").expect("state");
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/auth/callback/keycloak?code=idp-code&state={state}"
+            ))
+            .cookie(binding_cookie(&resp))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(
+        resp.headers().get("location").unwrap().to_str().unwrap(),
+        "/auth/success"
+    );
+    let cookie = resp
+        .response()
+        .cookies()
+        .next()
+        .expect("session cookie")
+        .into_owned();
+
+    // The provider's tokens are stored, but only sealed.
+    let identity = storage
+        .get_federated_identity("keycloak", "kc-42")
+        .await
+        .unwrap()
+        .unwrap();
+    let sealed_access = identity.access_token.clone().expect("access token stored");
+    assert!(!sealed_access.contains("idp-access-token"));
+    assert_eq!(vault.open(&sealed_access).unwrap(), "idp-access-token");
+    assert!(identity.token_expires_at.unwrap() > chrono::Utc::now());
+
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/auth/connections")
+            .cookie(cookie)
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body[0]["provider"], "keycloak");
+    assert_eq!(body[0]["provider_user_id"], "kc-42");
+    assert_eq!(body[0]["token_stored"], true);
+    assert_eq!(body[0]["refreshable"], true);
+    assert!(!body.to_string().contains(&sealed_access));
+
+    // A token that is still valid is handed out as is.
+    let token =
+        SocialLoginService::upstream_access_token(&storage, &vault, &social, &carol.id, "keycloak")
+            .await
+            .unwrap();
+    assert_eq!(token, "idp-access-token");
+
+    // An expired one is refreshed and the result stored, keeping the refresh token.
+    let mut expired = identity;
+    expired.token_expires_at = Some(chrono::Utc::now() - chrono::Duration::seconds(5));
+    storage
+        .update_federated_identity_tokens(&expired)
+        .await
+        .unwrap();
+    let token =
+        SocialLoginService::upstream_access_token(&storage, &vault, &social, &carol.id, "keycloak")
+            .await
+            .unwrap();
+    assert_eq!(token, "idp-refreshed-token");
+    let refreshed = storage
+        .get_federated_identity("keycloak", "kc-42")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        vault.open(&refreshed.access_token.unwrap()).unwrap(),
+        "idp-refreshed-token"
+    );
+    assert_eq!(
+        vault.open(&refreshed.refresh_token.unwrap()).unwrap(),
+        "idp-refresh-token"
+    );
+    assert!(refreshed.token_expires_at.unwrap() > chrono::Utc::now());
+
+    // Providers that are not linked have no token to give.
+    let err =
+        SocialLoginService::upstream_access_token(&storage, &vault, &social, &carol.id, "gitlab")
+            .await
+            .unwrap_err();
+    assert_eq!(err.error, "not_found");
+}
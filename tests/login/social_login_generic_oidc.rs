@@ -0,0 +1,277 @@
+use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+use actix_web::cookie::{Cookie, Key};
+use actix_web::dev::ServiceResponse;
+use actix_web::{test, web, App};
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use jsonwebtoken::{EncodingKey, Header};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+
+use oauth2_config::ProviderConfig;
+use oauth2_ports::DynStateStore;
+use oauth2_social_login::handlers::auth;
+use oauth2_social_login::{InMemoryStateStore, SocialLoginConfig};
+
+use crate::support;
+
+/// A minimal OpenID Connect provider: discovery, a token endpoint that accepts one code
+/// and answers with an ID token carrying `nonce` signed with `signing_key`, and userinfo
+/// for the access token it issues. Returns the issuer URL.
+async fn serve_identity_provider(
+    nonce: Arc<Mutex<String>>,
+    signing_key: Arc<Mutex<&'static str>>,
+) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let issuer = format!("http://{}", listener.local_addr().expect("local addr"));
+
+    let discovery = json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{issuer}/protocol/openid-connect/auth"),
+        "token_endpoint": format!("{issuer}/protocol/openid-connect/token"),
+        "userinfo_endpoint": format!("{issuer}/protocol/openid-connect/userinfo"),
+    });
+    let token_issuer = issuer.clone();
+    let app = Router::new()
+        .route(
+            "/.well-known/openid-configuration",
+            get(move || async move { Json(discovery) }),
+        )
+        .route(
+            "/protocol/openid-connect/token",
+            post(move |body: String| async move {
+                if body.contains("code=idp-code") && body.contains("code_verifier=") {
+                    let id_token = jsonwebtoken::encode(
+                        &Header::default(),
+                        &json!({
+                            "iss": token_issuer,
+                            "sub": "kc-42",
+                            "aud": "auth_server",
+                            "iat": chrono::Utc::now().timestamp(),
+                            "exp": chrono::Utc::now().timestamp() + 300,
+                            "nonce": *nonce.lock().unwrap(),
+                        }),
+                        &EncodingKey::from_secret(signing_key.lock().unwrap().as_bytes()),
+                    )
+                    .unwrap();
+                    Ok(Json(json!({
+                        "access_token": "idp-access-token",
+                        "token_type": "Bearer",
+                        "id_token": id_token,
+                    })))
+                } else {
+                    Err(StatusCode::BAD_REQUEST)
+                }
+            }),
+        )
+        .route(
+            "/protocol/openid-connect/userinfo",
+            get(|headers: HeaderMap| async move {
+                if headers.get("authorization").and_then(|v| v.to_str().ok())
+                    == Some("Bearer idp-access-token")
+                {
+                    Ok(Json(json!({
+                        "sub": "kc-42",
+                        "email": "carol@corp.test",
+                        "preferred_username": "carol",
+                    })))
+                } else {
+                    Err(StatusCode::UNAUTHORIZED)
+                }
+            }),
+        );
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    issuer
+}
+
+fn binding_cookie(resp: &ServiceResponse) -> Cookie<'static> {
+    resp.response()
+        .cookies()
+        .find(|cookie| cookie.name() == auth::BROWSER_BINDING_COOKIE)
+        .expect("browser binding cookie")
+        .into_owned()
+}
+
+fn query_param(location: &str, name: &str) -> Option<String> {
+    url::Url::parse(location)
+        .ok()?
+        .query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+#[actix_web::test]
+async fn generic_provider_uses_discovered_endpoints() {
+    let id_token_nonce = Arc::new(Mutex::new(String::new()));
+    let signing_key = Arc::new(Mutex::new("kc_secret"));
+    let issuer = serve_identity_provider(id_token_nonce.clone(), signing_key.clone()).await;
+    let states: DynStateStore = Arc::new(InMemoryStateStore::new());
+    let storage = support::memory_storage().await;
+
+    let social = SocialLoginConfig {
+        generic: [(
+            "keycloak".to_string(),
+            ProviderConfig {
+                enabled: true,
+                client_id: Some("auth_server".to_string()),
+                client_secret: Some("kc_secret".to_string()),
+                redirect_uri: Some("http://localhost:8080/auth/callback/keycloak".to_string()),
+                issuer_url: Some(issuer.clone()),
+                ..Default::default()
+            },
+        )]
+        .into(),
+        ..Default::default()
+    };
+    let app = test::init_service(
+        App::new()
+            .wrap(SessionMiddleware::new(
+                CookieSessionStore::default(),
+                Key::generate(),
+            ))
+            .app_data(web::Data::new(Arc::new(social)))
+            .app_data(web::Data::new(states))
+            .app_data(web::Data::new(storage))
+            .route("/auth/login/{provider}", web::get().to(auth::generic_login))
+            .route(
+                "/auth/callback/{provider}",
+                web::get().to(auth::auth_callback),
+            ),
+    )
+    .await;
+
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/auth/login/unknown")
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+
+    // The login redirect goes to the authorization endpoint named by discovery.
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/auth/login/keycloak")
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 302);
+    let location = resp
+        .headers()
+        .get("location")
+        .and_then(|value| value.to_str().ok())
+        .expect("location")
+        .to_string();
+    assert!(
+        location.starts_with(&format!("{issuer}/protocol/openid-connect/auth?")),
+        "{location}"
+    );
+    assert_eq!(
+        query_param(&location, "client_id").as_deref(),
+        Some("auth_server")
+    );
+    assert_eq!(
+        query_param(&location, "scope").as_deref(),
+        Some("openid email profile")
+    );
+    let nonce = query_param(&location, "nonce").expect("nonce");
+    let state = query_param(&location, "state").expect("state");
+    let binding = binding_cookie(&resp);
+
+    // An ID token minted for another login is refused, and the state is spent.
+    *id_token_nonce.lock().unwrap() = "someone-elses-nonce".to_string();
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/auth/callback/keycloak?code=idp-code&state={state}"
+            ))
+            .cookie(binding.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 403);
+
+    // So is one with the right nonce that the provider did not sign.
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/auth/login/keycloak")
+            .cookie(binding.clone())
+            .to_request(),
+    )
+    .await;
+    let location = resp
+        .headers()
+        .get("location")
+        .and_then(|value| value.to_str().ok())
+        .expect("location")
+        .to_string();
+    let state = query_param(&location, "state").expect("state");
+    *id_token_nonce.lock().unwrap() = query_param(&location, "nonce").expect("nonce");
+    *signing_key.lock().unwrap() = "someone-elses-key";
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/auth/callback/keycloak?code=idp-code&state={state}"
+            ))
+            .cookie(binding.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 403);
+    *signing_key.lock().unwrap() = "kc_secret";
+
+    // A second login from the same browser keeps its binding cookie.
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/auth/login/keycloak")
+            .cookie(binding.clone())
+            .to_request(),
+    )
+    .await;
+    let location = resp
+        .headers()
+        .get("location")
+        .and_then(|value| value.to_str().ok())
+        .expect("location")
+        .to_string();
+    let state = query_param(&location, "state").expect("state");
+    assert_eq!(binding_cookie(&resp).value(), binding.value());
+    *id_token_nonce.lock().unwrap() = query_param(&location, "nonce").expect("nonce");
+    assert_ne!(*id_token_nonce.lock().unwrap(), nonce);
+
+    // The callback exchanges the code at the discovered token endpoint and reads userinfo.
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/auth/callback/keycloak?code=idp-code&state={state}"
+            ))
+            .cookie(binding.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 302);
+    assert_eq!(
+        resp.headers().get("location").unwrap().to_str().unwrap(),
+        "/auth/success"
+    );
+
+    let user = oauth2_social_login::SocialLoginService::fetch_oidc_user_info(
+        "keycloak",
+        &format!("{issuer}/protocol/openid-connect/userinfo"),
+        "idp-access-token",
+    )
+    .await
+    .expect("userinfo");
+    assert_eq!(user.provider_user_id, "kc-42");
+    assert_eq!(user.email, "carol@corp.test");
+    assert_eq!(user.name.as_deref(), Some("carol"));
+}
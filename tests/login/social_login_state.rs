@@ -0,0 +1,217 @@
+use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+use actix_web::cookie::{Cookie, Key};
+use actix_web::dev::ServiceResponse;
+use actix_web::{test, web, App};
+use std::sync::Arc;
+
+use oauth2_config::ProviderConfig;
+use oauth2_core::SocialLoginState;
+use oauth2_ports::DynStateStore;
+use oauth2_social_login::handlers::auth;
+use oauth2_social_login::{SocialLoginConfig, StorageStateStore};
+
+use crate::support;
+
+fn provider() -> ProviderConfig {
+    ProviderConfig {
+        enabled: true,
+        client_id: Some("social_client".to_string()),
+        client_secret: Some("social_secret".to_string()),
+        redirect_uri: Some("http://localhost:8080/auth/callback/google".to_string()),
+        ..Default::default()
+    }
+}
+
+fn query_param(location: &str, name: &str) -> Option<String> {
+    url::Url::parse(location)
+        .ok()?
+        .query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+fn binding_cookie(resp: &ServiceResponse) -> Cookie<'static> {
+    resp.response()
+        .cookies()
+        .find(|cookie| cookie.name() == auth::BROWSER_BINDING_COOKIE)
+        .expect("browser binding cookie")
+        .into_owned()
+}
+
+macro_rules! social_app {
+    ($storage:expr, $cookieless_state:expr) => {{
+        let states: DynStateStore = Arc::new(StorageStateStore::new($storage.clone()));
+        let social = SocialLoginConfig {
+            google: Some(provider()),
+            github: Some(provider()),
+            cookieless_state: $cookieless_state,
+            ..Default::default()
+        };
+        test::init_service(
+            App::new()
+                .wrap(SessionMiddleware::new(
+                    CookieSessionStore::default(),
+                    Key::generate(),
+                ))
+                .app_data(web::Data::new(Arc::new(social)))
+                .app_data(web::Data::new(states))
+                .app_data(web::Data::new($storage.clone()))
+                .route("/auth/login/google", web::get().to(auth::google_login))
+                .route("/auth/login/github", web::get().to(auth::github_login))
+                .route(
+                    "/auth/callback/{provider}",
+                    web::get().to(auth::auth_callback),
+                ),
+        )
+        .await
+    }};
+}
+
+/// The `state` and browser binding cookie of a login redirect.
+fn started_login(resp: &ServiceResponse) -> (String, Cookie<'static>) {
+    let location = resp.headers().get("location").unwrap().to_str().unwrap();
+    let state = query_param(location, "state").expect("state");
+    (state, binding_cookie(resp))
+}
+
+fn google_login() -> test::TestRequest {
+    test::TestRequest::get().uri("/auth/login/google")
+}
+
+#[actix_web::test]
+async fn social_login_state_is_single_use_and_expires() {
+    let storage = support::memory_storage().await;
+    let app = social_app!(storage, false);
+
+    // Login records state, PKCE verifier and nonce server-side, bound to this browser.
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/auth/login/google")
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 302);
+    let location = resp
+        .headers()
+        .get("location")
+        .and_then(|value| value.to_str().ok())
+        .expect("location")
+        .to_string();
+    let state = query_param(&location, "state").expect("state");
+    assert!(query_param(&location, "code_challenge").is_some());
+    assert_eq!(
+        query_param(&location, "code_challenge_method").as_deref(),
+        Some("S256")
+    );
+    let nonce = query_param(&location, "nonce").expect("nonce");
+    let binding = binding_cookie(&resp);
+    assert!(binding.secure().unwrap_or(false));
+    assert!(binding.http_only().unwrap_or(false));
+
+    let record = storage
+        .take_social_login_state(&state)
+        .await
+        .unwrap()
+        .expect("state recorded");
+    assert_eq!(record.provider, "google");
+    assert_eq!(record.nonce.as_deref(), Some(nonce.as_str()));
+    assert!(record.pkce_verifier.is_some());
+    assert!(record.is_bound_to(binding.value()));
+    storage.save_social_login_state(&record).await.unwrap();
+
+    // GitHub is not an OpenID Connect provider, so no nonce is sent.
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/auth/login/github")
+            .to_request(),
+    )
+    .await;
+    let location = resp.headers().get("location").unwrap().to_str().unwrap();
+    assert!(query_param(location, "state").is_some());
+    assert!(query_param(location, "nonce").is_none());
+
+    let callback = |provider: &str, state: Option<&str>| {
+        let uri = match state {
+            Some(state) => format!("/auth/callback/{provider}?code=abc&state={state}"),
+            None => format!("/auth/callback/{provider}?code=abc"),
+        };
+        test::TestRequest::get()
+            .uri(&uri)
+            .cookie(binding.clone())
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, callback("google", None)).await;
+    assert_eq!(resp.status(), 403);
+
+    let resp = test::call_service(&app, callback("google", Some("forged"))).await;
+    assert_eq!(resp.status(), 403);
+
+    // A state issued for one provider is rejected by another, and is consumed either way.
+    let resp = test::call_service(&app, callback("github", Some(&state))).await;
+    assert_eq!(resp.status(), 400);
+    let resp = test::call_service(&app, callback("google", Some(&state))).await;
+    assert_eq!(resp.status(), 403);
+
+    // Expired attempts are rejected.
+    let mut expired = SocialLoginState::new("stale".to_string(), "google".to_string())
+        .with_browser_binding(binding.value());
+    expired.expires_at = chrono::Utc::now() - chrono::Duration::seconds(1);
+    storage.save_social_login_state(&expired).await.unwrap();
+    let resp = test::call_service(&app, callback("google", Some("stale"))).await;
+    assert_eq!(resp.status(), 403);
+}
+
+#[actix_web::test]
+async fn callbacks_from_another_browser_are_rejected() {
+    let storage = support::memory_storage().await;
+    let app = social_app!(storage, false);
+
+    // An attacker's callback URL opened in the victim's browser, which has no binding
+    // cookie or its own, does not sign the victim in. The state is spent either way.
+    let (state, attacker) =
+        started_login(&test::call_service(&app, google_login().to_request()).await);
+    let (_, victim) = started_login(&test::call_service(&app, google_login().to_request()).await);
+    assert_ne!(attacker.value(), victim.value());
+    for cookie in [None, Some(victim.clone())] {
+        let mut request = test::TestRequest::get().uri(&format!(
+            "/auth/callback/github?code=attacker-code&state={state}"
+        ));
+        if let Some(cookie) = cookie {
+            request = request.cookie(cookie);
+        }
+        let resp = test::call_service(&app, request.to_request()).await;
+        assert_eq!(resp.status(), 403);
+    }
+    assert!(storage
+        .take_social_login_state(&state)
+        .await
+        .unwrap()
+        .is_none());
+
+    // With `cookieless_state`, a callback without the cookie passes the binding check
+    // (and fails here on the provider mismatch instead); another browser's cookie still
+    // does not.
+    let app = social_app!(storage, true);
+    let (state, _) = started_login(&test::call_service(&app, google_login().to_request()).await);
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!("/auth/callback/github?code=abc&state={state}"))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+    let (state, _) = started_login(&test::call_service(&app, google_login().to_request()).await);
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!("/auth/callback/github?code=abc&state={state}"))
+            .cookie(victim)
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 403);
+}
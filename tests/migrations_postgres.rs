@@ -1,12 +1,12 @@
 use sqlx::{postgres::PgPoolOptions, Executor};
-use std::{fs, path::PathBuf, time::Duration};
+use std::time::Duration;
 use testcontainers::{core::IntoContainerPort, runners::AsyncRunner};
 use testcontainers_modules::postgres::Postgres as TcPostgres;
 
 use oauth2_core::Token;
 use uuid::Uuid;
 
-// This test spins up a disposable Postgres via Testcontainers, applies our SQLx migrations,
+// This test spins up a disposable Postgres via Testcontainers, applies our embedded migrations,
 // and verifies the schema is valid. Skips automatically unless RUN_TESTCONTAINERS=1 is set
 // to avoid breaking environments without Docker (e.g., CI without privileges).
 #[tokio::test]
@@ -44,19 +44,19 @@ async fn migrations_apply_successfully_on_postgres() -> Result<(), Box<dyn std::
         })?
     };
 
-    // Apply Flyway-style migrations manually to avoid sqlx filename parsing expectations
-    let mut entries: Vec<PathBuf> = fs::read_dir("./migrations/sql")?
-        .filter_map(Result::ok)
-        .map(|e| e.path())
-        .filter(|p| p.extension().and_then(|s| s.to_str()) == Some("sql"))
-        .collect();
-
-    entries.sort();
-
-    for path in entries {
-        let sql = fs::read_to_string(&path)?;
-        pool.execute(sql.as_str()).await?;
-    }
+    // Apply the embedded migrations, the same path the server takes with
+    // `database.run_migrations = true`.
+    let applied = oauth2_storage_sqlx::migrations::run_postgres_migrations(&pool).await?;
+    assert_eq!(
+        applied.len(),
+        oauth2_storage_sqlx::migrations::MIGRATIONS.len()
+    );
+    assert!(
+        oauth2_storage_sqlx::migrations::run_postgres_migrations(&pool)
+            .await?
+            .is_empty(),
+        "a second run finds nothing pending"
+    );
 
     // Simple sanity check
     pool.execute("SELECT 1").await?;
@@ -7,8 +7,6 @@ use oauth2_storage_mongo::MongoStorage;
 use testcontainers::{core::IntoContainerPort, runners::AsyncRunner};
 use testcontainers_modules::mongo::Mongo as TcMongo;
 
-mod common;
-
 // Basic CRUD contract tests for the MongoDB storage backend.
 // Skips automatically unless RUN_TESTCONTAINERS=1 is set to avoid requiring Docker everywhere.
 #[tokio::test]
@@ -19,11 +17,13 @@ async fn mongo_storage_roundtrip_smoke_test() -> Result<(), Box<dyn std::error::
     }
 
     // NOTE: MongoDB starts quickly, but we still do a retry loop before asserting readiness.
-    let node = TcMongo::default().start().await?;
+    // A single-node replica set, since the contract exercises multi-document transactions.
+    let node = TcMongo::repl_set().start().await?;
     let host = node.get_host().await?;
     let port = node.get_host_port_ipv4(27017.tcp()).await?;
 
-    let uri = format!("mongodb://{host}:{port}/oauth2_test");
+    // The member advertises its in-container address; connect to it directly.
+    let uri = format!("mongodb://{host}:{port}/oauth2_test?directConnection=true");
 
     // Wait for MongoDB to accept connections.
     let storage = {
@@ -56,5 +56,17 @@ async fn mongo_storage_roundtrip_smoke_test() -> Result<(), Box<dyn std::error::
 
     storage.init().await.expect("mongo init should succeed");
 
-    common::run_storage_contract(&storage).await
+    assert_eq!(
+        storage.schema_version().await.expect("read schema version"),
+        Some(oauth2_storage_mongo::migrations::SCHEMA_VERSION)
+    );
+
+    oauth2_storage_tests::run_storage_contract(&storage).await?;
+
+    // Re-running init on a migrated database is a no-op.
+    storage
+        .init()
+        .await
+        .expect("second mongo init should succeed");
+    Ok(())
 }
@@ -0,0 +1,132 @@
+use actix_web::{test, App};
+use base64::Engine;
+use serde_json::Value;
+
+use oauth2_config::{ClaimMapping, Config};
+use oauth2_ports::DynStorage;
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+async fn setup_storage() -> DynStorage {
+    let storage = support::memory_storage().await;
+    support::save_client(
+        &storage,
+        &support::client(
+            "app",
+            "https://unused.example/cb",
+            &["password", "client_credentials"],
+            "read email",
+        ),
+    )
+    .await;
+    support::save_user(&storage, "alice", "correct-horse").await;
+    storage
+}
+
+fn config(claims: &[(&str, ClaimMapping)]) -> Config {
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.grants.password = true;
+    config.claims = claims
+        .iter()
+        .map(|(name, mapping)| (name.to_string(), mapping.clone()))
+        .collect();
+    config
+}
+
+fn template(value: &str) -> ClaimMapping {
+    ClaimMapping::Template(value.to_string())
+}
+
+fn jwt_payload(token: &str) -> Value {
+    let payload = token.split('.').nth(1).expect("jwt payload");
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload)
+        .expect("base64 payload");
+    serde_json::from_slice(&bytes).expect("json payload")
+}
+
+#[actix_web::test]
+async fn configured_claims_are_templated_into_access_tokens() {
+    let storage = setup_storage().await;
+    let oauth2 = ServerBuilder::new(config(&[
+        ("environment", template("production")),
+        ("handle", template("${user.username}@${client_id}")),
+        (
+            "contact",
+            ClaimMapping::Rule {
+                value: "${user.email}".to_string(),
+                scope: Some("email".to_string()),
+            },
+        ),
+    ]))
+    .with_storage(storage)
+    .with_endpoints([EndpointGroup::OAuth])
+    .build()
+    .await
+    .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    let issue = |form: Vec<(&'static str, &'static str)>| {
+        let req = test::TestRequest::post()
+            .uri("/oauth/token")
+            .set_form(form)
+            .to_request();
+        test::call_and_read_body_json::<_, _, Value>(&app, req)
+    };
+    let password = |scope| {
+        vec![
+            ("grant_type", "password"),
+            ("client_id", "app"),
+            ("client_secret", "app_secret"),
+            ("username", "alice"),
+            ("password", "correct-horse"),
+            ("scope", scope),
+        ]
+    };
+
+    let body = issue(password("read email")).await;
+    let claims = jwt_payload(body["access_token"].as_str().expect("access token"));
+    assert_eq!(claims["environment"], "production");
+    assert_eq!(claims["handle"], "alice@app");
+    assert_eq!(claims["contact"], "alice@example.test");
+
+    // Scope-conditional claims need their scope.
+    let body = issue(password("read")).await;
+    let claims = jwt_payload(body["access_token"].as_str().expect("access token"));
+    assert_eq!(claims["handle"], "alice@app");
+    assert!(claims.get("contact").is_none());
+
+    // Client tokens have no user, so user claims are left out.
+    let body = issue(vec![
+        ("grant_type", "client_credentials"),
+        ("client_id", "app"),
+        ("client_secret", "app_secret"),
+        ("scope", "email"),
+    ])
+    .await;
+    let claims = jwt_payload(body["access_token"].as_str().expect("access token"));
+    assert_eq!(claims["environment"], "production");
+    assert!(claims.get("handle").is_none());
+    assert!(claims.get("contact").is_none());
+}
+
+#[actix_web::test]
+async fn invalid_claim_mappings_fail_startup() {
+    for claims in [
+        [("tenant", template("${user.tenant}"))],
+        [("tenant", template("${user.tenant_id"))],
+        [("sub", template("${user.username}"))],
+    ] {
+        let result = ServerBuilder::new(config(&claims))
+            .with_storage(setup_storage().await)
+            .build()
+            .await;
+        let error = result.err().expect("invalid mapping").to_string();
+        assert!(
+            error.contains("Invalid claims configuration"),
+            "{claims:?}: {error}"
+        );
+    }
+}
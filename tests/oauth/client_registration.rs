@@ -0,0 +1,551 @@
+use actix::Actor;
+use actix_web::{test, web, App};
+use serde_json::{json, Value};
+
+use oauth2_config::GrantsConfig;
+
+use crate::support;
+
+fn register_scope() -> actix_web::Scope {
+    web::scope("/oauth")
+        .app_data(oauth2_actix::handlers::client::registration_json_config())
+        .route(
+            "/register",
+            web::post().to(oauth2_actix::handlers::client::register_client),
+        )
+        .route(
+            "/register/{client_id}",
+            web::get().to(oauth2_actix::handlers::client::get_client_configuration),
+        )
+        .route(
+            "/register/{client_id}",
+            web::put().to(oauth2_actix::handlers::client::update_client_configuration),
+        )
+        .route(
+            "/register/{client_id}",
+            web::delete().to(oauth2_actix::handlers::client::delete_client_configuration),
+        )
+}
+
+#[actix_web::test]
+async fn client_configuration_lifecycle() {
+    let storage = support::memory_storage().await;
+    let client_actor = oauth2_actix::actors::ClientActor::new(storage.clone()).start();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(client_actor))
+            .service(register_scope()),
+    )
+    .await;
+
+    // Register
+    let req = test::TestRequest::post()
+        .uri("/oauth/register")
+        .set_json(json!({
+            "client_name": "rp",
+            "redirect_uris": ["https://rp.example/cb"],
+            "grant_types": ["authorization_code"],
+            "scope": "read"
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 201);
+    let registered: Value = test::read_body_json(resp).await;
+    let client_id = registered["client_id"]
+        .as_str()
+        .expect("client_id")
+        .to_string();
+    let client_secret = registered["client_secret"]
+        .as_str()
+        .expect("client_secret")
+        .to_string();
+    let token = registered["registration_access_token"]
+        .as_str()
+        .expect("registration_access_token")
+        .to_string();
+    assert!(registered["registration_client_uri"]
+        .as_str()
+        .expect("registration_client_uri")
+        .ends_with(&format!("/oauth/register/{client_id}")));
+
+    // Only hashes of the client secret and registration token are persisted.
+    let stored = storage
+        .get_client(&client_id, None)
+        .await
+        .expect("get client")
+        .expect("client exists");
+    assert_ne!(stored.client_secret, client_secret);
+    assert!(stored.verify_client_secret(&client_secret));
+    assert_ne!(
+        stored.registration_access_token.as_deref(),
+        Some(token.as_str())
+    );
+
+    // Read requires the registration access token.
+    let req = test::TestRequest::get()
+        .uri(&format!("/oauth/register/{client_id}"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 401);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/oauth/register/{client_id}"))
+        .insert_header(("Authorization", format!("Bearer {token}")))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["client_name"], "rp");
+    assert!(body.get("client_secret").is_none());
+    assert!(body.get("registration_access_token").is_none());
+
+    // Update
+    let req = test::TestRequest::put()
+        .uri(&format!("/oauth/register/{client_id}"))
+        .insert_header(("Authorization", format!("Bearer {token}")))
+        .set_json(json!({
+            "client_id": client_id,
+            "client_secret": client_secret,
+            "client_name": "rp renamed",
+            "redirect_uris": ["https://rp.example/new-cb"],
+            "grant_types": ["authorization_code", "client_credentials"],
+            "scope": "read write"
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["client_name"], "rp renamed");
+    assert_eq!(body["redirect_uris"], json!(["https://rp.example/new-cb"]));
+
+    let stored = storage
+        .get_client(&client_id, None)
+        .await
+        .expect("get client")
+        .expect("client exists");
+    assert_eq!(stored.scope, "read write");
+    assert!(stored.verify_client_secret(&client_secret));
+
+    // Delete
+    let req = test::TestRequest::delete()
+        .uri(&format!("/oauth/register/{client_id}"))
+        .insert_header(("Authorization", format!("Bearer {token}")))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 204);
+
+    assert!(storage
+        .get_client(&client_id, None)
+        .await
+        .expect("get client")
+        .is_none());
+
+    // Once deleted, the token no longer grants access.
+    let req = test::TestRequest::get()
+        .uri(&format!("/oauth/register/{client_id}"))
+        .insert_header(("Authorization", format!("Bearer {token}")))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 401);
+}
+
+#[actix_web::test]
+async fn client_configuration_rejects_mismatched_updates() {
+    let storage = support::memory_storage().await;
+    let client_actor = oauth2_actix::actors::ClientActor::new(storage).start();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(client_actor))
+            .service(register_scope()),
+    )
+    .await;
+
+    let mut registrations = Vec::new();
+    for name in ["first", "second"] {
+        let req = test::TestRequest::post()
+            .uri("/oauth/register")
+            .set_json(json!({
+                "client_name": name,
+                "redirect_uris": ["https://rp.example/cb"],
+                "grant_types": ["client_credentials"],
+                "scope": "read"
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let body: Value = test::read_body_json(resp).await;
+        registrations.push(body);
+    }
+    let first_id = registrations[0]["client_id"].as_str().expect("client_id");
+    let first_token = registrations[0]["registration_access_token"]
+        .as_str()
+        .expect("token");
+    let second_token = registrations[1]["registration_access_token"]
+        .as_str()
+        .expect("token");
+
+    // Another client's registration token is not accepted.
+    let req = test::TestRequest::get()
+        .uri(&format!("/oauth/register/{first_id}"))
+        .insert_header(("Authorization", format!("Bearer {second_token}")))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 401);
+    assert!(resp.headers().contains_key("WWW-Authenticate"));
+
+    let update = |client_id: &str, client_secret: Option<&str>| {
+        json!({
+            "client_id": client_id,
+            "client_secret": client_secret,
+            "client_name": "renamed",
+            "redirect_uris": ["https://rp.example/cb"],
+            "grant_types": ["client_credentials"],
+            "scope": "read"
+        })
+    };
+
+    // client_id in the body must match the path.
+    let req = test::TestRequest::put()
+        .uri(&format!("/oauth/register/{first_id}"))
+        .insert_header(("Authorization", format!("Bearer {first_token}")))
+        .set_json(update("someone_else", None))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+
+    // A client_secret in the body must match the current secret.
+    let req = test::TestRequest::put()
+        .uri(&format!("/oauth/register/{first_id}"))
+        .insert_header(("Authorization", format!("Bearer {first_token}")))
+        .set_json(update(first_id, Some("not-the-secret")))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+
+    // Registration validation applies to updates too.
+    let req = test::TestRequest::put()
+        .uri(&format!("/oauth/register/{first_id}"))
+        .insert_header(("Authorization", format!("Bearer {first_token}")))
+        .set_json(json!({
+            "client_id": first_id,
+            "client_name": "renamed",
+            "redirect_uris": ["https://rp.example/cb#fragment"],
+            "grant_types": ["client_credentials"],
+            "scope": "read"
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[actix_web::test]
+async fn invalid_redirect_uris_are_rejected_at_registration() {
+    let storage = support::memory_storage().await;
+    let client_actor = oauth2_actix::actors::ClientActor::new(storage.clone()).start();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(client_actor))
+            .service(register_scope()),
+    )
+    .await;
+
+    for (redirect_uri, reason) in [
+        ("http://rp.example/cb", "https"),
+        ("https://rp.example/cb#fragment", "fragment"),
+        ("https://*.rp.example/cb", "wildcards"),
+        ("com.example.app:/cb", "native"),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/oauth/register")
+            .set_json(json!({
+                "client_name": "rp",
+                "redirect_uris": [redirect_uri],
+                "grant_types": ["authorization_code"],
+                "scope": "read"
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400, "{redirect_uri}");
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], "invalid_request");
+        let description = body["error_description"].as_str().unwrap();
+        assert!(
+            description.contains(reason),
+            "{redirect_uri}: {description}"
+        );
+    }
+
+    // Registered URIs are stored normalized.
+    let req = test::TestRequest::post()
+        .uri("/oauth/register")
+        .set_json(json!({
+            "client_name": "rp",
+            "redirect_uris": ["HTTPS://RP.example:443/cb"],
+            "grant_types": ["authorization_code"],
+            "scope": "read"
+        }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["redirect_uris"], json!(["https://rp.example/cb"]));
+}
+
+#[actix_web::test]
+async fn reserved_scopes_are_refused_at_registration() {
+    let storage = support::memory_storage().await;
+    let client_actor = oauth2_actix::actors::ClientActor::new(storage.clone())
+        .with_admin_scope("superuser")
+        .start();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(client_actor))
+            .service(register_scope()),
+    )
+    .await;
+
+    let registration = |scope: &str| {
+        json!({
+            "client_name": "rp",
+            "redirect_uris": ["https://rp.example/cb"],
+            "grant_types": ["client_credentials"],
+            "scope": scope
+        })
+    };
+
+    for scope in ["admin", "read admin:operator", "events:ingest", "superuser"] {
+        let req = test::TestRequest::post()
+            .uri("/oauth/register")
+            .set_json(registration(scope))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400, "{scope}");
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], "invalid_scope", "{scope}");
+    }
+    assert!(storage
+        .list_clients(oauth2_ports::PageRequest::new(1, 10))
+        .await
+        .expect("list clients")
+        .is_empty());
+
+    // Nor can an update add them to a registered client.
+    let req = test::TestRequest::post()
+        .uri("/oauth/register")
+        .set_json(registration("read"))
+        .to_request();
+    let registered: Value = test::call_and_read_body_json(&app, req).await;
+    let client_id = registered["client_id"].as_str().expect("client_id");
+    let token = registered["registration_access_token"]
+        .as_str()
+        .expect("token");
+    let req = test::TestRequest::put()
+        .uri(&format!("/oauth/register/{client_id}"))
+        .insert_header(("Authorization", format!("Bearer {token}")))
+        .set_json(json!({
+            "client_id": client_id,
+            "client_name": "rp",
+            "redirect_uris": ["https://rp.example/cb"],
+            "grant_types": ["client_credentials"],
+            "scope": "read admin"
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let stored = storage
+        .get_client(client_id, None)
+        .await
+        .expect("get client")
+        .expect("client exists");
+    assert_eq!(stored.scope, "read");
+}
+
+#[actix_web::test]
+async fn registrable_grant_types_follow_the_enabled_grants() {
+    let registration = |grant_types: &[&str]| {
+        json!({
+            "client_name": "rp",
+            "redirect_uris": ["https://rp.example/cb"],
+            "grant_types": grant_types,
+            "scope": "read"
+        })
+    };
+    let register = |grants: GrantsConfig| async move {
+        let storage = support::memory_storage().await;
+        let client_actor = oauth2_actix::actors::ClientActor::new(storage).start();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(client_actor))
+                .app_data(web::Data::new(grants))
+                .service(register_scope()),
+        )
+        .await;
+        let mut statuses = Vec::new();
+        for grant_types in [
+            &["authorization_code", "refresh_token"][..],
+            &["password"],
+            &["implicit"],
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/oauth/register")
+                .set_json(registration(grant_types))
+                .to_request();
+            statuses.push(test::call_service(&app, req).await.status().as_u16());
+        }
+        statuses
+    };
+
+    // Disabled grants are refused, and implicit is never a grant type here.
+    assert_eq!(register(GrantsConfig::default()).await, [400, 400, 400]);
+    assert_eq!(
+        register(GrantsConfig {
+            refresh_token: true,
+            ..GrantsConfig::default()
+        })
+        .await,
+        [201, 400, 400]
+    );
+}
+
+#[actix_web::test]
+async fn client_metadata_is_registered_and_validated() {
+    let storage = support::memory_storage().await;
+    let client_actor = oauth2_actix::actors::ClientActor::new(storage.clone()).start();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(client_actor))
+            .service(register_scope()),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/oauth/register")
+        .set_json(json!({
+            "client_name": "rp",
+            "redirect_uris": ["https://rp.example/cb"],
+            "grant_types": ["authorization_code"],
+            "scope": "read",
+            "logo_uri": "https://rp.example/logo.png",
+            "tos_uri": "https://rp.example/tos",
+            "contacts": ["ops@rp.example"],
+            "jwks_uri": "https://rp.example/jwks.json",
+            "token_endpoint_auth_method": "client_secret_post"
+        }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["logo_uri"], "https://rp.example/logo.png");
+    assert_eq!(body["contacts"], json!(["ops@rp.example"]));
+    assert_eq!(body["token_endpoint_auth_method"], "client_secret_post");
+    assert!(body.get("policy_uri").is_none());
+
+    let stored = storage
+        .get_client(body["client_id"].as_str().unwrap(), None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.tos_uri.as_deref(), Some("https://rp.example/tos"));
+    assert_eq!(
+        stored.jwks_uri.as_deref(),
+        Some("https://rp.example/jwks.json")
+    );
+
+    // Public clients default to `none`.
+    let req = test::TestRequest::post()
+        .uri("/oauth/register")
+        .set_json(json!({
+            "client_name": "spa",
+            "client_type": "public",
+            "redirect_uris": ["https://spa.example/cb"],
+            "grant_types": ["authorization_code"],
+            "scope": "read"
+        }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["token_endpoint_auth_method"], "none");
+
+    for (field, value) in [
+        ("jwks_uri", json!("http://rp.example/jwks.json")),
+        ("logo_uri", json!("javascript:alert(1)")),
+        ("token_endpoint_auth_method", json!("none")),
+        ("token_endpoint_auth_method", json!("private_key_jwt")),
+    ] {
+        let mut registration = json!({
+            "client_name": "rp",
+            "redirect_uris": ["https://rp.example/cb"],
+            "grant_types": ["authorization_code"],
+            "scope": "read"
+        });
+        registration[field] = value.clone();
+        let req = test::TestRequest::post()
+            .uri("/oauth/register")
+            .set_json(registration)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400, "{field}: {value}");
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], "invalid_request");
+    }
+}
+
+#[actix_web::test]
+async fn legacy_plaintext_client_secret_is_rehashed_on_validation() {
+    let storage = support::memory_storage().await;
+    let legacy = oauth2_core::Client::new(
+        "legacy_client".to_string(),
+        "legacy_plaintext_secret".to_string(),
+        vec!["https://rp.example/cb".to_string()],
+        vec!["client_credentials".to_string()],
+        "read".to_string(),
+        "legacy".to_string(),
+    );
+    storage.save_client(&legacy).await.expect("save client");
+    let client_actor = oauth2_actix::actors::ClientActor::new(storage.clone()).start();
+
+    let validate = |secret: &str| oauth2_actix::actors::ValidateClient {
+        client_id: "legacy_client".to_string(),
+        client_secret: secret.to_string(),
+        tenant_id: None,
+        span: tracing::Span::none(),
+        deadline: oauth2_actix::deadline::Deadline::none(),
+    };
+
+    // A wrong secret does not trigger the upgrade.
+    let ok = client_actor
+        .send(validate("wrong"))
+        .await
+        .expect("mailbox")
+        .expect("validate");
+    assert!(!ok);
+    let stored = storage
+        .get_client("legacy_client", None)
+        .await
+        .expect("get client")
+        .expect("client exists");
+    assert!(stored.client_secret_needs_rehash());
+
+    let ok = client_actor
+        .send(validate("legacy_plaintext_secret"))
+        .await
+        .expect("mailbox")
+        .expect("validate");
+    assert!(ok);
+
+    let stored = storage
+        .get_client("legacy_client", None)
+        .await
+        .expect("get client")
+        .expect("client exists");
+    assert!(!stored.client_secret_needs_rehash());
+    assert!(stored.verify_client_secret("legacy_plaintext_secret"));
+
+    // Unknown clients are still rejected.
+    let err = client_actor
+        .send(oauth2_actix::actors::ValidateClient {
+            client_id: "missing".to_string(),
+            client_secret: "whatever".to_string(),
+            tenant_id: None,
+            span: tracing::Span::none(),
+            deadline: oauth2_actix::deadline::Deadline::none(),
+        })
+        .await
+        .expect("mailbox");
+    assert!(err.is_err());
+}
@@ -0,0 +1,216 @@
+use actix_web::{test, web, App};
+use serde_json::Value;
+use std::time::Duration;
+
+use oauth2_config::GrantsConfig;
+use oauth2_core::{ContextTolerance, IssuerKeys};
+use oauth2_events::testing::RecordingEventBus;
+use oauth2_events::EventType;
+
+use crate::support;
+
+const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+
+fn s256_challenge(verifier: &str) -> String {
+    use base64::{engine::general_purpose, Engine as _};
+    use sha2::{Digest, Sha256};
+    general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+macro_rules! init_app {
+    ($bind:expr) => {
+        init_app!($bind, ContextTolerance::Network)
+    };
+    ($bind:expr, $tolerance:expr) => {
+        init_app!($bind, $tolerance, RecordingEventBus::new())
+    };
+    ($bind:expr, $tolerance:expr, $bus:expr) => {{
+        let storage = support::memory_storage().await;
+        support::save_client(
+            &storage,
+            &support::client(
+                "spa",
+                "https://spa.example/cb",
+                &["authorization_code", "refresh_token"],
+                "read",
+            ),
+        )
+        .await;
+        support::save_user_with_id(&storage, "alice", "unused", true).await;
+
+        let issuer_keys = IssuerKeys::from_secret(support::JWT_SECRET);
+        let token_actor = oauth2_actix::actors::TokenActor::with_events(
+            storage.clone(),
+            support::JWT_SECRET.to_string(),
+            $bus.handle(),
+        )
+        .with_issuer_keys(issuer_keys.clone())
+        .with_context_binding($bind, $tolerance);
+        let auth_actor = oauth2_actix::actors::AuthActor::new(storage.clone())
+            .with_context_binding($bind, $tolerance);
+
+        test::init_service(
+            App::new()
+                .wrap_fn(support::signed_in("alice", &[("spa", "read")]))
+                .wrap(support::session_middleware())
+                .configure(support::oauth_data(&storage, token_actor, auth_actor))
+                .app_data(web::Data::new(issuer_keys))
+                .app_data(web::Data::new(GrantsConfig {
+                    refresh_token: true,
+                    ..GrantsConfig::default()
+                }))
+                .service(
+                    web::scope("/oauth")
+                        .route(
+                            "/authorize",
+                            web::get().to(oauth2_actix::handlers::oauth::authorize),
+                        )
+                        .route(
+                            "/token",
+                            web::post().to(oauth2_actix::handlers::oauth::token),
+                        ),
+                ),
+        )
+        .await
+    }};
+}
+
+macro_rules! authorize {
+    ($app:expr, $user_agent:expr, $ip:expr) => {{
+        let uri = format!(
+            "/oauth/authorize?response_type=code&client_id=spa&redirect_uri=https%3A%2F%2Fspa.example%2Fcb&scope=read&code_challenge={}&code_challenge_method=S256",
+            s256_challenge(VERIFIER)
+        );
+        let req = test::TestRequest::get()
+            .uri(&uri)
+            .insert_header(("User-Agent", $user_agent))
+            .peer_addr($ip.parse().expect("socket addr"))
+            .to_request();
+        let resp = test::call_service(&$app, req).await;
+        assert_eq!(resp.status(), 302);
+        let location = resp
+            .headers()
+            .get("Location")
+            .and_then(|h| h.to_str().ok())
+            .expect("location")
+            .to_string();
+        let (_, query) = location.split_once('?').expect("query");
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("code="))
+            .expect("code")
+            .to_string()
+    }};
+}
+
+macro_rules! exchange {
+    ($app:expr, $code:expr, $user_agent:expr, $ip:expr) => {{
+        let req = test::TestRequest::post()
+            .uri("/oauth/token")
+            .insert_header(("User-Agent", $user_agent))
+            .peer_addr($ip.parse().expect("socket addr"))
+            .set_form([
+                ("grant_type", "authorization_code"),
+                ("client_id", "spa"),
+                ("client_secret", "spa_secret"),
+                ("code", $code.as_str()),
+                ("code_verifier", VERIFIER),
+            ])
+            .to_request();
+        test::call_service(&$app, req).await
+    }};
+}
+
+macro_rules! refresh {
+    ($app:expr, $refresh_token:expr, $user_agent:expr, $ip:expr) => {{
+        let req = test::TestRequest::post()
+            .uri("/oauth/token")
+            .insert_header(("User-Agent", $user_agent))
+            .peer_addr($ip.parse().expect("socket addr"))
+            .set_form([
+                ("grant_type", "refresh_token"),
+                ("client_id", "spa"),
+                ("client_secret", "spa_secret"),
+                ("refresh_token", $refresh_token),
+            ])
+            .to_request();
+        test::call_service(&$app, req).await
+    }};
+}
+
+#[actix_web::test]
+async fn bound_codes_reject_a_drastically_different_context() {
+    let app = init_app!(true);
+
+    let code = authorize!(app, "Mozilla/5.0 (SPA)", "203.0.113.10:5000");
+    let resp = exchange!(app, code, "python-requests/2.31", "198.51.100.7:6000");
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "invalid_grant");
+
+    // The user agent alone does not vouch for another network.
+    let code = authorize!(app, "Mozilla/5.0 (SPA)", "203.0.113.10:5000");
+    let resp = exchange!(app, code, "Mozilla/5.0 (SPA)", "198.51.100.7:6000");
+    assert_eq!(resp.status(), 400);
+
+    // Another address in the same network is accepted, even with an updated browser.
+    let code = authorize!(app, "Mozilla/5.0 (SPA)", "203.0.113.10:5000");
+    let resp = exchange!(app, code, "Mozilla/6.0 (SPA)", "203.0.113.77:6000");
+    assert_eq!(resp.status(), 200);
+}
+
+#[actix_web::test]
+async fn roaming_tolerance_accepts_the_same_user_agent_elsewhere() {
+    let app = init_app!(true, ContextTolerance::NetworkOrUserAgent);
+
+    let code = authorize!(app, "Mozilla/5.0 (SPA)", "203.0.113.10:5000");
+    let resp = exchange!(app, code, "Mozilla/5.0 (SPA)", "198.51.100.7:6000");
+    assert_eq!(resp.status(), 200);
+
+    let code = authorize!(app, "Mozilla/5.0 (SPA)", "203.0.113.10:5000");
+    let resp = exchange!(app, code, "python-requests/2.31", "198.51.100.7:6000");
+    assert_eq!(resp.status(), 400);
+}
+
+#[actix_web::test]
+async fn bound_refresh_tokens_reject_another_network() {
+    let bus = RecordingEventBus::new();
+    let app = init_app!(true, ContextTolerance::Network, bus);
+
+    let code = authorize!(app, "Mozilla/5.0 (SPA)", "203.0.113.10:5000");
+    let resp = exchange!(app, code, "Mozilla/5.0 (SPA)", "203.0.113.10:5000");
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    let refresh_token = body["refresh_token"].as_str().expect("refresh token");
+
+    // A stolen refresh token replayed from elsewhere is rejected without burning it.
+    let resp = refresh!(app, refresh_token, "Mozilla/5.0 (SPA)", "198.51.100.7:6000");
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "invalid_grant");
+    // Issuing the tokens published two events; the mismatch is the third.
+    let events = bus.wait_for(3, Duration::from_secs(5)).await;
+    assert!(events
+        .iter()
+        .any(|envelope| envelope.event.event_type == EventType::RefreshTokenContextMismatch));
+
+    let resp = refresh!(app, refresh_token, "Mozilla/5.0 (SPA)", "203.0.113.42:5000");
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    let rotated = body["refresh_token"]
+        .as_str()
+        .expect("rotated refresh token");
+
+    // The rotated token stays bound to the network the grant started in.
+    let resp = refresh!(app, rotated, "Mozilla/5.0 (SPA)", "198.51.100.7:6000");
+    assert_eq!(resp.status(), 400);
+}
+
+#[actix_web::test]
+async fn binding_is_off_by_default() {
+    let app = init_app!(false);
+
+    let code = authorize!(app, "Mozilla/5.0 (SPA)", "203.0.113.10:5000");
+    let resp = exchange!(app, code, "python-requests/2.31", "198.51.100.7:6000");
+    assert_eq!(resp.status(), 200);
+}
@@ -0,0 +1,95 @@
+use actix_web::http::header;
+use actix_web::{test, web, App};
+
+use oauth2_actix::middleware::cors::CorsPolicy;
+
+use crate::support;
+
+const SPA_ORIGIN: &str = "https://spa.example";
+
+macro_rules! init_app {
+    () => {{
+        let storage = support::memory_storage().await;
+        let mut client = support::client(
+            "spa",
+            &format!("{SPA_ORIGIN}/cb"),
+            &["client_credentials"],
+            "read",
+        );
+        client.set_allowed_origins(&[SPA_ORIGIN.to_string()]);
+        support::save_client(&storage, &client).await;
+
+        test::init_service(
+            App::new()
+                .wrap(
+                    CorsPolicy::new(Vec::<String>::new())
+                        .public_path("/.well-known")
+                        .client_path("/oauth/token"),
+                )
+                .configure(support::default_oauth_data(&storage))
+                .route(
+                    "/oauth/token",
+                    web::post().to(oauth2_actix::handlers::oauth::token),
+                )
+                .route(
+                    "/.well-known/openid-configuration",
+                    web::get().to(oauth2_actix::handlers::wellknown::openid_configuration),
+                ),
+        )
+        .await
+    }};
+}
+
+fn token_request(origin: &str, secret: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/oauth/token")
+        .insert_header((header::ORIGIN, origin))
+        .set_form([
+            ("grant_type", "client_credentials"),
+            ("client_id", "spa"),
+            ("client_secret", secret),
+            ("scope", "read"),
+        ])
+}
+
+fn allow_origin<B>(resp: &actix_web::dev::ServiceResponse<B>) -> Option<String> {
+    resp.headers()
+        .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+#[actix_web::test]
+async fn token_endpoint_allows_the_clients_registered_origins() {
+    let app = init_app!();
+
+    let resp = test::call_service(&app, token_request(SPA_ORIGIN, "spa_secret").to_request()).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(allow_origin(&resp).as_deref(), Some(SPA_ORIGIN));
+
+    // Errors after the client is known stay readable, so the page can handle them.
+    let resp = test::call_service(&app, token_request(SPA_ORIGIN, "wrong").to_request()).await;
+    assert_eq!(resp.status(), 401);
+    assert_eq!(allow_origin(&resp).as_deref(), Some(SPA_ORIGIN));
+
+    let resp = test::call_service(
+        &app,
+        token_request("https://evil.example", "spa_secret").to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(allow_origin(&resp), None);
+}
+
+#[actix_web::test]
+async fn discovery_is_readable_from_any_origin() {
+    let app = init_app!();
+
+    let req = test::TestRequest::get()
+        .uri("/.well-known/openid-configuration")
+        .insert_header((header::ORIGIN, "https://anyone.example"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(allow_origin(&resp).as_deref(), Some("*"));
+}
@@ -0,0 +1,171 @@
+use actix_web::{test, App};
+use async_trait::async_trait;
+use base64::Engine;
+use serde_json::Value;
+use std::sync::Arc;
+
+use oauth2_actix::actors::CreateToken;
+use oauth2_actix::grants::{GrantContext, GrantHandler, TokenRequest};
+use oauth2_config::Config;
+use oauth2_core::{Client, OAuth2Error, Token, User};
+use oauth2_ports::DynStorage;
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+const KIOSK_GRANT: &str = "urn:example:params:oauth:grant-type:kiosk";
+
+/// Issues read-only tokens for a user a trusted kiosk has identified by username.
+struct KioskGrant;
+
+#[async_trait]
+impl GrantHandler for KioskGrant {
+    fn grant_type(&self) -> &str {
+        KIOSK_GRANT
+    }
+
+    async fn handle(
+        &self,
+        mut request: TokenRequest,
+        client: Client,
+        grant: &GrantContext,
+    ) -> Result<Token, OAuth2Error> {
+        grant
+            .authenticate_client(&client, request.take_param("client_secret"))
+            .await?;
+        let username = request
+            .param("username")
+            .ok_or_else(|| OAuth2Error::invalid_request("Missing username"))?;
+        let user = grant
+            .storage()?
+            .get_user_by_username(username, None)
+            .await?
+            .ok_or_else(|| OAuth2Error::invalid_grant("Unknown user"))?;
+        grant
+            .issue(CreateToken {
+                metadata: request.take_metadata(),
+                ..CreateToken::new(KIOSK_GRANT, client.client_id, Some(user.id), "read")
+            })
+            .await
+    }
+}
+
+async fn setup_storage() -> (DynStorage, User) {
+    let storage = support::memory_storage().await;
+    for (id, grant_type) in [("kiosk", KIOSK_GRANT), ("web", "client_credentials")] {
+        support::save_client(
+            &storage,
+            &support::client(id, "https://unused.example/cb", &[grant_type], "read write"),
+        )
+        .await;
+    }
+    let user = support::save_user(&storage, "alice", "unused").await;
+    (storage, user)
+}
+
+fn jwt_payload(token: &str) -> Value {
+    let payload = token.split('.').nth(1).expect("jwt payload");
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload)
+        .expect("base64 payload");
+    serde_json::from_slice(&bytes).expect("json payload")
+}
+
+fn token_request(form: &[(&str, &str)]) -> test::TestRequest {
+    test::TestRequest::post().uri("/oauth/token").set_form(form)
+}
+
+#[actix_web::test]
+async fn registered_grant_types_are_served_next_to_built_in_ones() {
+    let (storage, user) = setup_storage().await;
+    let mut config = Config::default();
+    config.events.enabled = false;
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage)
+        .with_grant_handler(Arc::new(KioskGrant))
+        .with_endpoints([EndpointGroup::OAuth, EndpointGroup::Discovery])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    let req = token_request(&[
+        ("grant_type", KIOSK_GRANT),
+        ("client_id", "kiosk"),
+        ("client_secret", "kiosk_secret"),
+        ("username", "alice"),
+    ])
+    .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["scope"], "read");
+    let claims = jwt_payload(body["access_token"].as_str().expect("access token"));
+    assert_eq!(claims["sub"], user.id.as_str());
+    assert_eq!(claims["client_id"], "kiosk");
+
+    // Built-in grants still work.
+    let req = token_request(&[
+        ("grant_type", "client_credentials"),
+        ("client_id", "web"),
+        ("client_secret", "web_secret"),
+    ])
+    .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    let req = test::TestRequest::get()
+        .uri("/.well-known/openid-configuration")
+        .to_request();
+    let metadata: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        metadata["grant_types_supported"],
+        serde_json::json!(["authorization_code", "client_credentials", KIOSK_GRANT])
+    );
+}
+
+#[actix_web::test]
+async fn grant_types_need_a_handler_and_a_registered_client() {
+    let (storage, _) = setup_storage().await;
+    let mut config = Config::default();
+    config.events.enabled = false;
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage)
+        .with_grant_handler(Arc::new(KioskGrant))
+        .with_endpoints([EndpointGroup::OAuth])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    for (form, error) in [
+        (
+            [
+                ("grant_type", KIOSK_GRANT),
+                ("client_id", "web"),
+                ("client_secret", "web_secret"),
+            ],
+            "unauthorized_client",
+        ),
+        (
+            [
+                ("grant_type", "urn:example:unknown"),
+                ("client_id", "kiosk"),
+                ("client_secret", "kiosk_secret"),
+            ],
+            "unsupported_grant_type",
+        ),
+        // Disabled unless `grants.password` is set.
+        (
+            [
+                ("grant_type", "password"),
+                ("client_id", "kiosk"),
+                ("client_secret", "kiosk_secret"),
+            ],
+            "unsupported_grant_type",
+        ),
+    ] {
+        let resp = test::call_service(&app, token_request(&form).to_request()).await;
+        assert!(resp.status().is_client_error(), "{form:?}");
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], error, "{form:?}");
+    }
+}
@@ -0,0 +1,297 @@
+use actix_web::{test, web, App};
+use serde_json::Value;
+
+use oauth2_config::GrantsConfig;
+use oauth2_core::hash_password;
+use oauth2_ports::DynStorage;
+
+use crate::support;
+
+async fn new_storage() -> DynStorage {
+    let storage = support::first_party_storage(&["password", "refresh_token"], "read write").await;
+    support::save_client(
+        &storage,
+        &support::client(
+            "third_party",
+            "https://other.example/cb",
+            &["authorization_code"],
+            "read",
+        ),
+    )
+    .await;
+
+    let password_hash = hash_password("correct horse").expect("hash");
+    for (id, enabled) in [("alice", true), ("mallory", false)] {
+        support::save_user_with_id(&storage, id, &password_hash, enabled).await;
+    }
+    storage
+}
+
+macro_rules! init_app {
+    ($storage:expr, $grants:expr) => {
+        test::init_service(
+            App::new()
+                .configure(support::default_oauth_data(&$storage))
+                .app_data(web::Data::new($grants))
+                .route(
+                    "/oauth/token",
+                    web::post().to(oauth2_actix::handlers::oauth::token),
+                )
+                .route(
+                    "/.well-known/openid-configuration",
+                    web::get().to(oauth2_actix::handlers::wellknown::openid_configuration),
+                ),
+        )
+        .await
+    };
+}
+
+fn token_request(client: &str, params: &[(&str, &str)]) -> test::TestRequest {
+    let secret = format!("{client}_secret");
+    let mut form = vec![("client_id", client), ("client_secret", secret.as_str())];
+    form.extend_from_slice(params);
+    test::TestRequest::post().uri("/oauth/token").set_form(form)
+}
+
+fn password_grant(username: &str, password: &str) -> Vec<(&'static str, String)> {
+    vec![
+        ("grant_type", "password".to_string()),
+        ("username", username.to_string()),
+        ("password", password.to_string()),
+    ]
+}
+
+fn as_params<'a>(owned: &'a [(&'static str, String)]) -> Vec<(&'static str, &'a str)> {
+    owned.iter().map(|(k, v)| (*k, v.as_str())).collect()
+}
+
+#[actix_web::test]
+async fn password_and_refresh_grants_are_disabled_by_default() {
+    let storage = new_storage().await;
+    let app = init_app!(storage, GrantsConfig::default());
+
+    let params = password_grant("alice", "correct horse");
+    let resp = test::call_service(
+        &app,
+        token_request("first_party", &as_params(&params)).to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "unsupported_grant_type");
+
+    let resp = test::call_service(
+        &app,
+        token_request(
+            "first_party",
+            &[("grant_type", "refresh_token"), ("refresh_token", "x")],
+        )
+        .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+
+    let req = test::TestRequest::get()
+        .uri("/.well-known/openid-configuration")
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        body["grant_types_supported"],
+        serde_json::json!(["authorization_code", "client_credentials"])
+    );
+}
+
+#[actix_web::test]
+async fn password_grant_authenticates_users_when_enabled() {
+    let storage = new_storage().await;
+    let grants = GrantsConfig {
+        password: true,
+        ..GrantsConfig::default()
+    };
+    let app = init_app!(storage, grants);
+
+    let params = password_grant("alice", "correct horse");
+    let resp = test::call_service(
+        &app,
+        token_request("first_party", &as_params(&params)).to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["scope"], "read write");
+    // Refresh tokens stay off until their grant is enabled too.
+    assert!(body.get("refresh_token").is_none_or(Value::is_null));
+    let stored = storage
+        .get_token_by_access_token(body["access_token"].as_str().unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.user_id.as_deref(), Some("alice"));
+
+    // Wrong passwords, unknown users and disabled accounts look the same.
+    for (username, password) in [
+        ("alice", "wrong"),
+        ("nobody", "correct horse"),
+        ("mallory", "correct horse"),
+    ] {
+        let params = password_grant(username, password);
+        let resp = test::call_service(
+            &app,
+            token_request("first_party", &as_params(&params)).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 400, "{username}");
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], "invalid_grant");
+        assert_eq!(body["error_description"], "Invalid username or password");
+    }
+
+    // Clients must opt in to the grant.
+    let params = password_grant("alice", "correct horse");
+    let resp = test::call_service(
+        &app,
+        token_request("third_party", &as_params(&params)).to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "unauthorized_client");
+}
+
+#[actix_web::test]
+async fn refresh_tokens_rotate_and_reuse_revokes_the_grant() {
+    let storage = new_storage().await;
+    let grants = GrantsConfig {
+        password: true,
+        refresh_token: true,
+        ..GrantsConfig::default()
+    };
+    let app = init_app!(storage, grants);
+
+    let params = password_grant("alice", "correct horse");
+    let body: Value = test::read_body_json(
+        test::call_service(
+            &app,
+            token_request("first_party", &as_params(&params)).to_request(),
+        )
+        .await,
+    )
+    .await;
+    let first_refresh = body["refresh_token"].as_str().expect("refresh").to_string();
+
+    let refresh = |token: &str, scope: Option<&str>| {
+        let mut params = vec![("grant_type", "refresh_token"), ("refresh_token", token)];
+        if let Some(scope) = scope {
+            params.push(("scope", scope));
+        }
+        token_request("first_party", &params)
+    };
+
+    // Widening the scope is rejected without burning the refresh token.
+    let resp = test::call_service(
+        &app,
+        refresh(&first_refresh, Some("read admin")).to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "invalid_scope");
+
+    let resp = test::call_service(&app, refresh(&first_refresh, Some("read")).to_request()).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["scope"], "read");
+    let second_access = body["access_token"].as_str().unwrap().to_string();
+    let second_refresh = body["refresh_token"].as_str().unwrap().to_string();
+    assert_ne!(second_refresh, first_refresh);
+
+    // Replaying the rotated refresh token revokes everything issued under the grant.
+    let resp = test::call_service(&app, refresh(&first_refresh, None).to_request()).await;
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "invalid_grant");
+    let second = storage
+        .get_token_by_access_token(&second_access)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(second.revoked);
+    let resp = test::call_service(&app, refresh(&second_refresh, None).to_request()).await;
+    assert_eq!(resp.status(), 400);
+
+    // Refresh tokens are bound to the client they were issued to.
+    let body: Value = test::read_body_json(
+        test::call_service(
+            &app,
+            token_request("first_party", &as_params(&params)).to_request(),
+        )
+        .await,
+    )
+    .await;
+    let resp = test::call_service(
+        &app,
+        token_request(
+            "third_party",
+            &[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", body["refresh_token"].as_str().unwrap()),
+            ],
+        )
+        .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "unauthorized_client");
+}
+
+#[actix_web::test]
+async fn concurrent_refreshes_rotate_the_refresh_token_once() {
+    let storage = new_storage().await;
+    let grants = GrantsConfig {
+        password: true,
+        refresh_token: true,
+        ..GrantsConfig::default()
+    };
+    let app = init_app!(storage, grants);
+
+    let params = password_grant("alice", "correct horse");
+    let body: Value = test::read_body_json(
+        test::call_service(
+            &app,
+            token_request("first_party", &as_params(&params)).to_request(),
+        )
+        .await,
+    )
+    .await;
+    let refresh_token = body["refresh_token"].as_str().expect("refresh").to_string();
+    let refresh = || {
+        token_request(
+            "first_party",
+            &[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", &refresh_token),
+            ],
+        )
+        .to_request()
+    };
+
+    let (first, second) = futures::join!(
+        test::call_service(&app, refresh()),
+        test::call_service(&app, refresh())
+    );
+    let mut statuses = [first.status().as_u16(), second.status().as_u16()];
+    statuses.sort_unstable();
+    assert_eq!(statuses, [200, 400]);
+
+    // The loser is treated as reuse, so the winner's tokens are revoked too.
+    let winner = if first.status() == 200 { first } else { second };
+    let body: Value = test::read_body_json(winner).await;
+    let access_token = body["access_token"].as_str().unwrap();
+    let issued = storage
+        .get_token_by_access_token(access_token)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(issued.revoked);
+}
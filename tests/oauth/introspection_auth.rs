@@ -0,0 +1,257 @@
+use actix_web::{test, web, App};
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::{json, Value};
+
+use oauth2_core::{Client, ClientType, IssuerKeys, TokenEndpointAuthMethod, TokenResponse};
+
+use crate::support;
+
+fn client(id: &str, scope: &str) -> Client {
+    support::client(
+        id,
+        "https://unused.example/cb",
+        &["client_credentials"],
+        scope,
+    )
+}
+
+fn basic(id: &str) -> String {
+    let credentials = general_purpose::STANDARD.encode(format!("{id}:{id}_secret"));
+    format!("Basic {credentials}")
+}
+
+macro_rules! init_app {
+    () => {{
+        let storage = support::memory_storage().await;
+        for c in [
+            client("client_a", "read"),
+            client("client_b", "read"),
+            client("introspector", "read admin"),
+        ] {
+            support::save_client(&storage, &c).await;
+        }
+
+        let issuer_keys = IssuerKeys::from_secret(support::JWT_SECRET);
+        let token_actor = support::token_actor(&storage).with_issuer_keys(issuer_keys.clone());
+        let auth_actor = oauth2_actix::actors::AuthActor::new(storage.clone());
+
+        test::init_service(
+            App::new()
+                .configure(support::oauth_data(&storage, token_actor, auth_actor))
+                .app_data(web::Data::new(issuer_keys))
+                .service(
+                    web::scope("/oauth")
+                        .route(
+                            "/token",
+                            web::post().to(oauth2_actix::handlers::oauth::token),
+                        )
+                        .route(
+                            "/introspect",
+                            web::post().to(oauth2_actix::handlers::token::introspect),
+                        )
+                        .route(
+                            "/revoke",
+                            web::post().to(oauth2_actix::handlers::token::revoke),
+                        ),
+                ),
+        )
+        .await
+    }};
+}
+
+macro_rules! issue_token {
+    ($app:expr, $id:expr) => {{
+        let secret = format!("{}_secret", $id);
+        let req = test::TestRequest::post()
+            .uri("/oauth/token")
+            .set_form([
+                ("grant_type", "client_credentials"),
+                ("client_id", $id),
+                ("client_secret", secret.as_str()),
+                ("scope", "read"),
+            ])
+            .to_request();
+        let resp = test::call_service(&$app, req).await;
+        assert!(resp.status().is_success());
+        let token: TokenResponse = test::read_body_json(resp).await;
+        token.access_token
+    }};
+}
+
+#[actix_web::test]
+async fn introspection_requires_client_authentication() {
+    let app = init_app!();
+    let token = issue_token!(app, "client_a");
+
+    // Anonymous callers are rejected.
+    let req = test::TestRequest::post()
+        .uri("/oauth/introspect")
+        .set_form([("token", token.as_str())])
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 401);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "invalid_client");
+
+    // Wrong secret.
+    let req = test::TestRequest::post()
+        .uri("/oauth/introspect")
+        .set_form([
+            ("token", token.as_str()),
+            ("client_id", "client_a"),
+            ("client_secret", "wrong"),
+        ])
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 401);
+
+    // HTTP Basic works for the owning client.
+    let req = test::TestRequest::post()
+        .uri("/oauth/introspect")
+        .insert_header(("Authorization", basic("client_a")))
+        .set_form([("token", token.as_str())])
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["active"], true);
+
+    // Mixing Basic and form credentials is ambiguous.
+    let req = test::TestRequest::post()
+        .uri("/oauth/introspect")
+        .insert_header(("Authorization", basic("client_a")))
+        .set_form([
+            ("token", token.as_str()),
+            ("client_id", "client_a"),
+            ("client_secret", "client_a_secret"),
+        ])
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[actix_web::test]
+async fn clients_without_a_secret_cannot_authenticate() {
+    let storage = support::memory_storage().await;
+    let mut public = Client::new(
+        "public_app".to_string(),
+        String::new(),
+        vec!["https://app.example/cb".to_string()],
+        vec!["authorization_code".to_string()],
+        "read".to_string(),
+        "public_app".to_string(),
+    );
+    public.client_type = ClientType::Public;
+    support::save_client(&storage, &public).await;
+    // A secret left over on a client registered to authenticate with `none` is not used.
+    let mut unauthenticated = client("none_app", "read");
+    unauthenticated.token_endpoint_auth_method = Some(TokenEndpointAuthMethod::None);
+    support::save_client(&storage, &unauthenticated).await;
+    let app = test::init_service(
+        App::new()
+            .configure(support::default_oauth_data(&storage))
+            .app_data(web::Data::new(IssuerKeys::from_secret(support::JWT_SECRET)))
+            .route(
+                "/oauth/introspect",
+                web::post().to(oauth2_actix::handlers::token::introspect),
+            ),
+    )
+    .await;
+
+    for (client_id, client_secret) in [
+        ("public_app", ""),
+        ("public_app", "anything"),
+        ("none_app", "none_app_secret"),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/oauth/introspect")
+            .set_form([
+                ("token", "some-token"),
+                ("client_id", client_id),
+                ("client_secret", client_secret),
+            ])
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401, "{client_id}:{client_secret}");
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], "invalid_client");
+    }
+}
+
+#[actix_web::test]
+async fn clients_only_see_their_own_tokens_unless_admin() {
+    let app = init_app!();
+    let token = issue_token!(app, "client_a");
+
+    let introspect_as = |caller: &str| {
+        test::TestRequest::post()
+            .uri("/oauth/introspect")
+            .insert_header(("Authorization", basic(caller)))
+            .set_form([("token", token.as_str())])
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, introspect_as("client_b")).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body, json!({ "active": false }));
+
+    let resp = test::call_service(&app, introspect_as("introspector")).await;
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["active"], true);
+    assert_eq!(body["client_id"], "client_a");
+}
+
+#[actix_web::test]
+async fn revocation_is_limited_to_the_owning_client() {
+    let app = init_app!();
+    let token = issue_token!(app, "client_a");
+
+    let revoke_as = |caller: &str| {
+        test::TestRequest::post()
+            .uri("/oauth/revoke")
+            .insert_header(("Authorization", basic(caller)))
+            .set_form([("token", token.as_str())])
+            .to_request()
+    };
+    let is_active = |caller: &str| {
+        test::TestRequest::post()
+            .uri("/oauth/introspect")
+            .insert_header(("Authorization", basic(caller)))
+            .set_form([("token", token.as_str())])
+            .to_request()
+    };
+
+    // Anonymous revocation is rejected.
+    let req = test::TestRequest::post()
+        .uri("/oauth/revoke")
+        .set_form([("token", token.as_str())])
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 401);
+
+    // Another client cannot revoke the token.
+    let resp = test::call_service(&app, revoke_as("client_b")).await;
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "unauthorized_client");
+    let resp = test::call_service(&app, is_active("client_a")).await;
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["active"], true);
+
+    // Unknown tokens are accepted silently (RFC 7009).
+    let req = test::TestRequest::post()
+        .uri("/oauth/revoke")
+        .insert_header(("Authorization", basic("client_b")))
+        .set_form([("token", "unknown-token")])
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    // The owner can revoke it.
+    let resp = test::call_service(&app, revoke_as("client_a")).await;
+    assert_eq!(resp.status(), 200);
+    let resp = test::call_service(&app, is_active("client_a")).await;
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body, json!({ "active": false }));
+}
@@ -0,0 +1,20 @@
+//! OAuth endpoints: grants, client authentication, registration and revocation.
+
+#[path = "../support/mod.rs"]
+mod support;
+
+mod claims_mapping;
+mod client_registration;
+mod code_binding;
+mod cors;
+mod grant_handler;
+mod grants;
+mod introspection_auth;
+mod multi_tenancy;
+mod native_client;
+mod resource_server_bearer;
+mod resource_server_introspection;
+mod revocation_cascade;
+mod revocation_list;
+mod service_accounts;
+mod token_issuance_policy;
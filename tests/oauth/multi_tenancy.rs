@@ -0,0 +1,282 @@
+use actix_web::{test, web, App, HttpResponse};
+use serde_json::Value;
+
+use oauth2_actix::middleware::bearer::RequireScope;
+use oauth2_actix::middleware::tenant::ResolveTenant;
+use oauth2_config::GrantsConfig;
+use oauth2_core::{Client, ContextBinding, IssuerKeys, Tenant};
+use oauth2_ports::{DynStorage, NewAuthorizationCode};
+
+use crate::support;
+
+const SERVER_SECRET: &str = "server_signing_secret";
+
+async fn new_storage() -> DynStorage {
+    let storage = support::memory_storage().await;
+
+    storage
+        .save_tenant(&Tenant::new(
+            "acme".to_string(),
+            "Acme".to_string(),
+            "acme_signing_secret".to_string(),
+        ))
+        .await
+        .expect("save tenant");
+    let mut suspended = Tenant::new(
+        "suspended".to_string(),
+        "Suspended".to_string(),
+        "suspended_signing_secret".to_string(),
+    );
+    suspended.enabled = false;
+    storage.save_tenant(&suspended).await.expect("save tenant");
+
+    for (client_id, tenant_id) in [("default_app", None), ("acme_app", Some("acme"))] {
+        let mut client = Client::new(
+            client_id.to_string(),
+            format!("{client_id}_secret"),
+            vec![],
+            vec!["client_credentials".to_string()],
+            "read".to_string(),
+            client_id.to_string(),
+        );
+        client.tenant_id = tenant_id.map(str::to_string);
+        support::save_client(&storage, &client).await;
+    }
+    storage
+}
+
+macro_rules! init_app {
+    ($storage:expr) => {{
+        let issuer_keys = IssuerKeys::from_secret(SERVER_SECRET);
+        let token_actor =
+            oauth2_actix::actors::TokenActor::new($storage.clone(), SERVER_SECRET.to_string())
+                .with_issuer_keys(issuer_keys.clone());
+        let auth_actor = oauth2_actix::actors::AuthActor::new($storage.clone());
+
+        let oauth = || {
+            web::scope("/oauth")
+                .route(
+                    "/token",
+                    web::post().to(oauth2_actix::handlers::oauth::token),
+                )
+                .route(
+                    "/introspect",
+                    web::post().to(oauth2_actix::handlers::token::introspect),
+                )
+                .service(
+                    web::resource("/resource")
+                        .wrap(RequireScope::new("read"))
+                        .route(web::get().to(HttpResponse::Ok)),
+                )
+        };
+        test::init_service(
+            App::new()
+                .configure(support::oauth_data(&$storage, token_actor, auth_actor))
+                .app_data(web::Data::new(issuer_keys))
+                .app_data(web::Data::new(GrantsConfig::default()))
+                .service(oauth())
+                .service(
+                    web::scope("/t/{tenant}")
+                        .wrap(ResolveTenant)
+                        .service(oauth())
+                        .route(
+                            "/.well-known/openid-configuration",
+                            web::get().to(oauth2_actix::handlers::wellknown::openid_configuration),
+                        ),
+                ),
+        )
+        .await
+    }};
+}
+
+fn client_credentials(path: &str, client_id: &str) -> test::TestRequest {
+    let secret = format!("{client_id}_secret");
+    test::TestRequest::post().uri(path).set_form([
+        ("grant_type", "client_credentials"),
+        ("client_id", client_id),
+        ("client_secret", secret.as_str()),
+    ])
+}
+
+fn introspect(path: &str, client_id: &str, token: &str) -> test::TestRequest {
+    let secret = format!("{client_id}_secret");
+    test::TestRequest::post().uri(path).set_form([
+        ("token", token),
+        ("client_id", client_id),
+        ("client_secret", secret.as_str()),
+    ])
+}
+
+#[actix_web::test]
+async fn tenant_tokens_are_isolated_and_signed_with_the_tenant_key() {
+    let storage = new_storage().await;
+    let app = init_app!(storage);
+
+    let resp = test::call_service(
+        &app,
+        client_credentials("/t/acme/oauth/token", "acme_app").to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    let access_token = body["access_token"].as_str().expect("access_token");
+
+    let server_keys = IssuerKeys::from_secret(SERVER_SECRET);
+    assert!(server_keys.verify(access_token).is_err());
+    let tenant = storage.get_tenant("acme").await.unwrap().unwrap();
+    let (claims, _) = tenant
+        .issuer_keys(&server_keys)
+        .verify(access_token)
+        .expect("signed with the tenant key");
+    assert_eq!(claims.iss, format!("{}/t/acme", server_keys.current.issuer));
+    let stored = storage
+        .get_token_by_access_token(access_token)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.tenant_id.as_deref(), Some("acme"));
+
+    let body: Value = test::call_and_read_body_json(
+        &app,
+        introspect("/t/acme/oauth/introspect", "acme_app", access_token).to_request(),
+    )
+    .await;
+    assert_eq!(body["active"], true);
+
+    // Clients only exist in their own tenant.
+    let resp = test::call_service(
+        &app,
+        client_credentials("/oauth/token", "acme_app").to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 401);
+    let resp = test::call_service(
+        &app,
+        client_credentials("/t/acme/oauth/token", "default_app").to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 401);
+
+    // The default tenant does not accept the tenant's tokens.
+    let body: Value = test::call_and_read_body_json(
+        &app,
+        introspect("/oauth/introspect", "default_app", access_token).to_request(),
+    )
+    .await;
+    assert_eq!(body["active"], false);
+
+    // Nor do bearer-protected routes, which check tokens against the request's tenant.
+    let body: Value = test::call_and_read_body_json(
+        &app,
+        client_credentials("/oauth/token", "default_app").to_request(),
+    )
+    .await;
+    let default_token = body["access_token"].as_str().expect("access_token");
+    for (path, token, status) in [
+        ("/t/acme/oauth/resource", access_token, 200),
+        ("/oauth/resource", access_token, 401),
+        ("/oauth/resource", default_token, 200),
+        ("/t/acme/oauth/resource", default_token, 401),
+    ] {
+        let req = test::TestRequest::get()
+            .uri(path)
+            .insert_header(("Authorization", format!("Bearer {token}")))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), status, "{path}");
+    }
+}
+
+#[actix_web::test]
+async fn authorization_codes_are_only_redeemed_in_their_tenant() {
+    let storage = new_storage().await;
+    // Client ids are unique per tenant, so both tenants can have a `shared_app`.
+    for tenant_id in [None, Some("acme")] {
+        let mut client = support::client(
+            "shared_app",
+            "https://app.example/cb",
+            &["authorization_code"],
+            "read",
+        );
+        client.tenant_id = tenant_id.map(str::to_string);
+        support::save_client(&storage, &client).await;
+    }
+    let mut user = support::save_user(&storage, "acme_user", "password").await;
+    user.tenant_id = Some("acme".to_string());
+    storage.update_user(&user).await.expect("update user");
+    let app = init_app!(storage);
+
+    let code = oauth2_ports::AuthService::new(storage.clone())
+        .create_authorization_code(NewAuthorizationCode {
+            client_id: "shared_app".to_string(),
+            user_id: user.id.clone(),
+            redirect_uri: "https://app.example/cb".to_string(),
+            scope: "read".to_string(),
+            code_challenge: None,
+            code_challenge_method: None,
+            context: ContextBinding::new(None, None),
+            tenant_id: Some("acme".to_string()),
+        })
+        .await
+        .expect("create code");
+    assert_eq!(code.tenant_id.as_deref(), Some("acme"));
+
+    let redeem = |path: &str| {
+        test::TestRequest::post().uri(path).set_form([
+            ("grant_type", "authorization_code"),
+            ("code", code.code.as_str()),
+            ("client_id", "shared_app"),
+            ("client_secret", "shared_app_secret"),
+        ])
+    };
+
+    // The default tenant's client of the same id cannot redeem the tenant's code.
+    let resp = test::call_service(&app, redeem("/oauth/token").to_request()).await;
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "invalid_grant");
+
+    let resp = test::call_service(&app, redeem("/t/acme/oauth/token").to_request()).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    let stored = storage
+        .get_token_by_access_token(body["access_token"].as_str().expect("access_token"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.tenant_id.as_deref(), Some("acme"));
+    assert_eq!(stored.user_id.as_deref(), Some(user.id.as_str()));
+}
+
+#[actix_web::test]
+async fn unknown_and_disabled_tenants_are_not_found() {
+    let storage = new_storage().await;
+    let app = init_app!(storage);
+
+    for path in ["/t/nobody/oauth/token", "/t/suspended/oauth/token"] {
+        let resp =
+            test::call_service(&app, client_credentials(path, "acme_app").to_request()).await;
+        assert_eq!(resp.status(), 404, "{path}");
+    }
+}
+
+#[actix_web::test]
+async fn tenant_discovery_points_at_tenant_endpoints() {
+    let storage = new_storage().await;
+    let app = init_app!(storage);
+
+    let req = test::TestRequest::get()
+        .uri("/t/acme/.well-known/openid-configuration")
+        .insert_header(("host", "auth.example"))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["issuer"], "http://auth.example/t/acme");
+    assert_eq!(
+        body["token_endpoint"],
+        "http://auth.example/t/acme/oauth/token"
+    );
+    assert_eq!(
+        body["registration_endpoint"],
+        "http://auth.example/t/acme/clients/register"
+    );
+}
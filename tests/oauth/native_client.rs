@@ -0,0 +1,139 @@
+use actix_web::{test, App};
+use serde_json::{json, Value};
+
+use oauth2_config::Config;
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+
+fn s256_challenge(verifier: &str) -> String {
+    use base64::{engine::general_purpose, Engine as _};
+    use sha2::{Digest, Sha256};
+    general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+#[actix_web::test]
+async fn native_clients_use_loopback_redirects_without_a_secret() {
+    let storage = support::memory_storage().await;
+    support::save_user_with_id(&storage, "alice", "unused", true).await;
+
+    let mut config = Config::default();
+    config.events.enabled = false;
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage.clone())
+        .with_endpoints([EndpointGroup::OAuth])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    let register = |client_type: &str, redirect_uri: &str, grant_type: &str| {
+        test::TestRequest::post()
+            .uri("/oauth/register")
+            .set_json(json!({
+                "client_name": "cli",
+                "redirect_uris": [redirect_uri],
+                "grant_types": [grant_type],
+                "scope": "read",
+                "client_type": client_type
+            }))
+            .to_request()
+    };
+
+    // Private-use schemes are reserved for native apps, and public clients cannot use
+    // client_credentials.
+    for (client_type, redirect_uri, grant_type) in [
+        ("public", "com.example.app:/cb", "authorization_code"),
+        ("native", "http://127.0.0.1/cb", "client_credentials"),
+        (
+            "resource_server",
+            "https://rs.example/cb",
+            "authorization_code",
+        ),
+    ] {
+        let resp = test::call_service(&app, register(client_type, redirect_uri, grant_type)).await;
+        assert_eq!(resp.status(), 400, "{client_type} {redirect_uri}");
+    }
+
+    let resp = test::call_service(
+        &app,
+        register("native", "com.example.app:/cb", "authorization_code"),
+    )
+    .await;
+    assert_eq!(resp.status(), 201);
+
+    let registered: Value = test::call_and_read_body_json(
+        &app,
+        register("native", "http://127.0.0.1/cb", "authorization_code"),
+    )
+    .await;
+    assert_eq!(registered["client_type"], "native");
+    assert!(registered.get("client_secret").is_none());
+    let client_id = registered["client_id"].as_str().unwrap().to_string();
+
+    // Alice has approved the app in her browser.
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(support::signed_in("alice", &[(client_id.as_str(), "read")]))
+            .wrap(support::session_middleware())
+            .configure(|cfg| oauth2.configure(cfg)),
+    )
+    .await;
+
+    // Any loopback port is accepted, as the app binds an ephemeral one.
+    let authorize = |redirect_uri: &str| {
+        test::TestRequest::get()
+            .uri(&format!(
+                "/oauth/authorize?response_type=code&client_id={client_id}&redirect_uri={}&scope=read&code_challenge={}&code_challenge_method=S256",
+                redirect_uri.replace(':', "%3A").replace('/', "%2F"),
+                s256_challenge(VERIFIER)
+            ))
+            .to_request()
+    };
+    let resp = test::call_service(&app, authorize("http://localhost:51234/cb")).await;
+    assert_eq!(resp.status(), 400);
+    let resp = test::call_service(&app, authorize("http://127.0.0.1:51234/cb")).await;
+    assert_eq!(resp.status(), 302);
+    let location = resp
+        .headers()
+        .get("location")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(location.starts_with("http://127.0.0.1:51234/cb?"));
+    let code = location
+        .split_once('?')
+        .unwrap()
+        .1
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("code="))
+        .expect("code")
+        .to_string();
+
+    let exchange = |code_verifier: &str| {
+        test::TestRequest::post()
+            .uri("/oauth/token")
+            .set_form([
+                ("grant_type", "authorization_code"),
+                ("client_id", client_id.as_str()),
+                ("code", code.as_str()),
+                ("redirect_uri", "http://127.0.0.1:51234/cb"),
+                ("code_verifier", code_verifier),
+            ])
+            .to_request()
+    };
+    // Without a secret, the PKCE verifier is what proves possession of the code.
+    let resp = test::call_service(
+        &app,
+        exchange("wrong-verifier-wrong-verifier-wrong-verifier"),
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+    let resp = test::call_service(&app, exchange(VERIFIER)).await;
+    assert_eq!(resp.status(), 200);
+    let token: Value = test::read_body_json(resp).await;
+    assert!(token["access_token"].is_string());
+}
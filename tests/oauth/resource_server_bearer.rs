@@ -0,0 +1,113 @@
+use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+use serde_json::{json, Value};
+
+use oauth2_actix::middleware::bearer::{AuthenticatedToken, RequireScope};
+use oauth2_config::Config;
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+async fn me(token: AuthenticatedToken) -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "client_id": token.token.client_id,
+        "sub": token.claims.sub,
+    }))
+}
+
+#[actix_web::test]
+async fn require_scope_guards_host_routes_with_bearer_challenges() {
+    let storage = support::memory_storage().await;
+    support::save_client(
+        &storage,
+        &support::client(
+            "api_client",
+            "https://unused.example/cb",
+            &["client_credentials"],
+            "read write",
+        ),
+    )
+    .await;
+
+    let mut config = Config::default();
+    config.events.enabled = false;
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage.clone())
+        .with_endpoints([EndpointGroup::OAuth])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(
+        App::new()
+            .configure(|cfg| oauth2.configure(cfg))
+            .service(
+                web::scope("/api")
+                    .wrap(RequireScope::new("read"))
+                    .route("/me", web::get().to(me)),
+            )
+            .route("/whoami", web::get().to(me)),
+    )
+    .await;
+
+    let token_for = |scope: &str| {
+        test::TestRequest::post()
+            .uri("/oauth/token")
+            .set_form([
+                ("grant_type", "client_credentials"),
+                ("client_id", "api_client"),
+                ("client_secret", "api_client_secret"),
+                ("scope", scope),
+            ])
+            .to_request()
+    };
+    let token: Value = test::call_and_read_body_json(&app, token_for("read")).await;
+    let read = token["access_token"].as_str().unwrap().to_string();
+    let token: Value = test::call_and_read_body_json(&app, token_for("write")).await;
+    let write = token["access_token"].as_str().unwrap().to_string();
+
+    let get = |uri: &str, token: Option<&str>| {
+        let req = test::TestRequest::get().uri(uri);
+        match token {
+            Some(token) => req.insert_header(("Authorization", format!("Bearer {token}"))),
+            None => req,
+        }
+        .to_request()
+    };
+    let challenge = |resp: &actix_web::dev::ServiceResponse| {
+        resp.headers()
+            .get("WWW-Authenticate")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string()
+    };
+
+    // No credentials: 401 with a bare challenge.
+    let resp = test::call_service(&app, get("/api/me", None)).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(challenge(&resp), "Bearer");
+
+    let resp = test::call_service(&app, get("/api/me", Some("not-a-jwt"))).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(challenge(&resp), "Bearer error=\"invalid_token\"");
+
+    let resp = test::call_service(&app, get("/api/me", Some(&write))).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        challenge(&resp),
+        "Bearer error=\"insufficient_scope\", scope=\"read\""
+    );
+
+    let body: Value = test::call_and_read_body_json(&app, get("/api/me", Some(&read))).await;
+    assert_eq!(body["client_id"], "api_client");
+
+    // The extractor alone authenticates without a scope requirement.
+    let resp = test::call_service(&app, get("/whoami", Some(&write))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    storage.revoke_token(&read).await.expect("revoke");
+    let resp = test::call_service(&app, get("/api/me", Some(&read))).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(challenge(&resp), "Bearer error=\"invalid_token\"");
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error_description"], "Token is revoked or expired");
+}
@@ -0,0 +1,146 @@
+use actix_web::{test, App};
+use serde_json::{json, Value};
+
+use oauth2_config::Config;
+use oauth2_core::Client;
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+fn client(client_id: &str, scope: &str) -> Client {
+    support::client(
+        client_id,
+        "https://unused.example/cb",
+        &["client_credentials"],
+        scope,
+    )
+}
+
+#[actix_web::test]
+async fn resource_servers_introspect_with_their_own_credentials() {
+    let storage = support::memory_storage().await;
+    for client in [
+        client("admin_client", "admin"),
+        client("app", "read"),
+        client("other", "read"),
+    ] {
+        support::save_client(&storage, &client).await;
+    }
+
+    let mut config = Config::default();
+    config.events.enabled = false;
+    config.security.introspection_rate_limit_per_minute = 3;
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage.clone())
+        .with_endpoints([EndpointGroup::OAuth, EndpointGroup::Admin])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    let token_for = |client_id: &str, scope: &str| {
+        test::TestRequest::post()
+            .uri("/oauth/token")
+            .set_form([
+                ("grant_type", "client_credentials"),
+                ("client_id", client_id),
+                ("client_secret", &format!("{client_id}_secret")),
+                ("scope", scope),
+            ])
+            .to_request()
+    };
+    let token: Value =
+        test::call_and_read_body_json(&app, token_for("admin_client", "admin")).await;
+    let admin = format!("Bearer {}", token["access_token"].as_str().unwrap());
+    let token: Value = test::call_and_read_body_json(&app, token_for("app", "read")).await;
+    let app_token = token["access_token"].as_str().unwrap().to_string();
+
+    // Only admins create resource servers.
+    let create = || {
+        test::TestRequest::post()
+            .uri("/admin/resource-servers")
+            .set_json(json!({ "name": "orders-api" }))
+    };
+    let resp = test::call_service(&app, create().to_request()).await;
+    assert_eq!(resp.status(), 401);
+    let resp = test::call_service(
+        &app,
+        create()
+            .insert_header(("Authorization", admin.clone()))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 201);
+    let created: Value = test::read_body_json(resp).await;
+    let rs_id = created["client_id"].as_str().unwrap().to_string();
+    let rs_secret = created["client_secret"].as_str().unwrap().to_string();
+    assert!(rs_id.starts_with("rs_"));
+    assert_eq!(created["name"], "orders-api");
+
+    // A resource server cannot obtain tokens itself.
+    let req = test::TestRequest::post()
+        .uri("/oauth/token")
+        .set_form([
+            ("grant_type", "client_credentials"),
+            ("client_id", rs_id.as_str()),
+            ("client_secret", rs_secret.as_str()),
+        ])
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    let introspect = |client_id: &str, client_secret: &str| {
+        test::TestRequest::post()
+            .uri("/oauth/introspect")
+            .set_form([
+                ("token", app_token.as_str()),
+                ("client_id", client_id),
+                ("client_secret", client_secret),
+            ])
+            .to_request()
+    };
+
+    // Unlike other clients, resource servers see tokens issued to any client.
+    let body: Value = test::call_and_read_body_json(&app, introspect(&rs_id, &rs_secret)).await;
+    assert_eq!(body["active"], true);
+    assert_eq!(body["client_id"], "app");
+    let body: Value =
+        test::call_and_read_body_json(&app, introspect("other", "other_secret")).await;
+    assert_eq!(body, json!({ "active": false }));
+
+    // The quota is tracked per caller.
+    for _ in 0..2 {
+        let resp = test::call_service(&app, introspect(&rs_id, &rs_secret)).await;
+        assert_eq!(resp.status(), 200);
+    }
+    let resp = test::call_service(&app, introspect(&rs_id, &rs_secret)).await;
+    assert_eq!(resp.status(), 429);
+    assert!(resp.headers().contains_key("retry-after"));
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "rate_limit_exceeded");
+    let resp = test::call_service(&app, introspect("other", "other_secret")).await;
+    assert_eq!(resp.status(), 200);
+
+    let requests = &oauth2.metrics().oauth_introspection_requests_total;
+    assert_eq!(requests.with_label_values(&[&rs_id, "active"]).get(), 3);
+    assert_eq!(
+        requests.with_label_values(&[&rs_id, "rate_limited"]).get(),
+        1
+    );
+    assert_eq!(requests.with_label_values(&["client", "inactive"]).get(), 2);
+
+    // Deleting the resource server revokes its credentials.
+    let req = test::TestRequest::delete()
+        .uri(&format!("/admin/resource-servers/{rs_id}"))
+        .insert_header(("Authorization", admin.clone()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    let resp = test::call_service(&app, introspect(&rs_id, &rs_secret)).await;
+    assert_eq!(resp.status(), 401);
+
+    // Only resource servers can be deleted this way.
+    let req = test::TestRequest::delete()
+        .uri("/admin/resource-servers/app")
+        .insert_header(("Authorization", admin))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+}
@@ -0,0 +1,134 @@
+use actix_web::{test, web, App};
+
+use oauth2_core::Token;
+use oauth2_ports::DynStorage;
+
+use crate::support;
+
+async fn new_storage() -> DynStorage {
+    let storage = support::memory_storage().await;
+    support::save_client(
+        &storage,
+        &support::client(
+            "client_a",
+            "https://unused.example/cb",
+            &["client_credentials"],
+            "read",
+        ),
+    )
+    .await;
+    storage
+}
+
+/// Save a grant of two tokens: `{prefix}_access_1` (with `{prefix}_refresh`) and a second
+/// access token `{prefix}_access_2` issued under the same grant.
+async fn seed_grant(storage: &DynStorage, prefix: &str) {
+    let root = Token::new(
+        format!("{prefix}_access_1"),
+        Some(format!("{prefix}_refresh")),
+        "client_a".to_string(),
+        None,
+        "read".to_string(),
+        3600,
+    );
+    let sibling = Token::new(
+        format!("{prefix}_access_2"),
+        None,
+        "client_a".to_string(),
+        None,
+        "read".to_string(),
+        3600,
+    )
+    .with_grant_id(root.grant_id());
+    storage.save_token(&root).await.expect("save token");
+    storage.save_token(&sibling).await.expect("save token");
+}
+
+async fn is_revoked(storage: &DynStorage, access_token: &str) -> bool {
+    storage
+        .get_token_by_access_token(access_token)
+        .await
+        .expect("lookup")
+        .expect("token exists")
+        .revoked
+}
+
+macro_rules! init_app {
+    ($storage:expr, $cascade:expr) => {{
+        let token_actor = support::token_actor(&$storage).with_revocation_cascade($cascade);
+        let auth_actor = oauth2_actix::actors::AuthActor::new($storage.clone());
+
+        test::init_service(
+            App::new()
+                .configure(support::oauth_data(&$storage, token_actor, auth_actor))
+                .route(
+                    "/oauth/revoke",
+                    web::post().to(oauth2_actix::handlers::token::revoke),
+                ),
+        )
+        .await
+    }};
+}
+
+fn revoke(token: &str, hint: Option<&str>) -> test::TestRequest {
+    let mut form = vec![
+        ("token", token),
+        ("client_id", "client_a"),
+        ("client_secret", "client_a_secret"),
+    ];
+    if let Some(hint) = hint {
+        form.push(("token_type_hint", hint));
+    }
+    test::TestRequest::post()
+        .uri("/oauth/revoke")
+        .set_form(form)
+}
+
+#[actix_web::test]
+async fn revoking_a_refresh_token_revokes_its_grant() {
+    let storage = new_storage().await;
+    seed_grant(&storage, "a").await;
+    seed_grant(&storage, "b").await;
+    let app = init_app!(storage, false);
+
+    let resp = test::call_service(
+        &app,
+        revoke("a_refresh", Some("refresh_token")).to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    assert!(is_revoked(&storage, "a_access_1").await);
+    assert!(is_revoked(&storage, "a_access_2").await);
+
+    // A wrong hint only changes the lookup order.
+    let resp =
+        test::call_service(&app, revoke("b_refresh", Some("access_token")).to_request()).await;
+    assert_eq!(resp.status(), 200);
+    assert!(is_revoked(&storage, "b_access_1").await);
+    assert!(is_revoked(&storage, "b_access_2").await);
+}
+
+#[actix_web::test]
+async fn revoking_an_access_token_cascades_only_when_enabled() {
+    let storage = new_storage().await;
+    seed_grant(&storage, "a").await;
+    let app = init_app!(storage, false);
+
+    let resp = test::call_service(
+        &app,
+        revoke("a_access_2", Some("unknown_hint")).to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    assert!(is_revoked(&storage, "a_access_2").await);
+    assert!(!is_revoked(&storage, "a_access_1").await);
+
+    let cascading = new_storage().await;
+    seed_grant(&cascading, "a").await;
+    let app = init_app!(cascading, true);
+
+    let resp = test::call_service(&app, revoke("a_access_2", None).to_request()).await;
+    assert_eq!(resp.status(), 200);
+    assert!(is_revoked(&cascading, "a_access_1").await);
+    assert!(is_revoked(&cascading, "a_access_2").await);
+}
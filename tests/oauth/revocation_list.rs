@@ -0,0 +1,109 @@
+use actix_web::{test, App};
+use serde_json::{json, Value};
+
+use oauth2_config::Config;
+use oauth2_core::{Claims, RevocationChecker};
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+#[actix_web::test]
+async fn revocation_list_tracks_revoked_tokens_with_etags() {
+    let storage = support::memory_storage().await;
+    support::save_client(
+        &storage,
+        &support::client(
+            "app",
+            "https://unused.example/cb",
+            &["client_credentials"],
+            "read",
+        ),
+    )
+    .await;
+
+    let mut config = Config::default();
+    config.events.enabled = false;
+    // Rebuild on every request so the revocation shows up immediately.
+    config.server.revocation_list_max_age_secs = 0;
+    let secret = config.jwt.secret.clone();
+    let oauth2 = ServerBuilder::new(config)
+        .with_storage(storage.clone())
+        .with_endpoints([EndpointGroup::OAuth])
+        .build()
+        .await
+        .expect("build server");
+    let app = test::init_service(App::new().configure(|cfg| oauth2.configure(cfg))).await;
+
+    let fetch = |checker: &RevocationChecker| {
+        let mut req = test::TestRequest::get().uri("/oauth/revocations");
+        if let Some(etag) = checker.etag() {
+            req = req.insert_header(("If-None-Match", etag.to_string()));
+        }
+        req.to_request()
+    };
+    let mut checker = RevocationChecker::new();
+
+    let resp = test::call_service(&app, fetch(&checker)).await;
+    assert_eq!(resp.status(), 200);
+    let etag = resp
+        .headers()
+        .get("etag")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body = test::read_body(resp).await;
+    assert_eq!(
+        serde_json::from_slice::<Value>(&body).unwrap(),
+        json!({ "revoked": [] })
+    );
+    assert!(checker.apply(200, Some(&etag), &body).unwrap());
+
+    let req = test::TestRequest::post()
+        .uri("/oauth/token")
+        .set_form([
+            ("grant_type", "client_credentials"),
+            ("client_id", "app"),
+            ("client_secret", "app_secret"),
+            ("scope", "read"),
+        ])
+        .to_request();
+    let token: Value = test::call_and_read_body_json(&app, req).await;
+    let access_token = token["access_token"].as_str().unwrap();
+    let claims = Claims::decode(access_token, &secret).expect("decode token");
+
+    // Nothing revoked yet: revalidation is answered with 304.
+    let resp = test::call_service(&app, fetch(&checker)).await;
+    assert_eq!(resp.status(), 304);
+    assert!(!checker.apply(304, None, b"").unwrap());
+    assert!(!checker.is_revoked(&claims));
+
+    let req = test::TestRequest::post()
+        .uri("/oauth/revoke")
+        .set_form([
+            ("token", access_token),
+            ("client_id", "app"),
+            ("client_secret", "app_secret"),
+        ])
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let resp = test::call_service(&app, fetch(&checker)).await;
+    assert_eq!(resp.status(), 200);
+    let new_etag = resp
+        .headers()
+        .get("etag")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert_ne!(new_etag, etag);
+    let body = test::read_body(resp).await;
+    assert_eq!(
+        serde_json::from_slice::<Value>(&body).unwrap(),
+        json!({ "revoked": [{ "jti": claims.jti, "exp": claims.exp }] })
+    );
+    assert!(checker.apply(200, Some(&new_etag), &body).unwrap());
+    assert!(checker.is_revoked(&claims));
+    assert_eq!(checker.etag(), Some(new_etag.as_str()));
+}
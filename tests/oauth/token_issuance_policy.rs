@@ -0,0 +1,179 @@
+use actix_web::{test, App};
+use async_trait::async_trait;
+use base64::Engine;
+use serde_json::Value;
+use std::sync::Arc;
+
+use oauth2_config::Config;
+use oauth2_core::{Claims, OAuth2Error, TokenMetadata};
+use oauth2_ports::{ClaimsEnricher, DynStorage, TokenIssuance, TokenIssuancePolicy};
+use oauth2_server::{EndpointGroup, ServerBuilder};
+
+use crate::support;
+
+/// Denies tokens to the `blocked` client and keeps password tokens read-only.
+struct ReadOnlyPasswords;
+
+#[async_trait]
+impl TokenIssuancePolicy for ReadOnlyPasswords {
+    async fn check(&self, issuance: &mut TokenIssuance) -> Result<(), OAuth2Error> {
+        if issuance.client_id() == "blocked" {
+            return Err(OAuth2Error::access_denied("client is suspended"));
+        }
+        if issuance.user_id().is_some() {
+            issuance.narrow_scope("read")?;
+        }
+        let grant_type = issuance.grant_type().to_string();
+        issuance.set_claim("grant", grant_type)
+    }
+}
+
+/// Records the scope enrichers are given.
+struct ScopeEcho;
+
+#[async_trait]
+impl ClaimsEnricher for ScopeEcho {
+    async fn enrich(
+        &self,
+        claims: &mut Claims,
+        _metadata: &mut TokenMetadata,
+    ) -> Result<(), OAuth2Error> {
+        let scope = claims.scope.clone();
+        claims.set_claim("enriched_scope", scope)
+    }
+}
+
+async fn setup_storage() -> DynStorage {
+    let storage = support::memory_storage().await;
+    for id in ["app", "blocked"] {
+        support::save_client(
+            &storage,
+            &support::client(
+                id,
+                "https://unused.example/cb",
+                &["password", "client_credentials", "refresh_token"],
+                "read write",
+            ),
+        )
+        .await;
+    }
+    support::save_user(&storage, "alice", "correct-horse").await;
+    storage
+}
+
+fn jwt_payload(token: &str) -> Value {
+    let payload = token.split('.').nth(1).expect("jwt payload");
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload)
+        .expect("base64 payload");
+    serde_json::from_slice(&bytes).expect("json payload")
+}
+
+macro_rules! init_app {
+    () => {{
+        let mut config = Config::default();
+        config.events.enabled = false;
+        config.grants.password = true;
+        config.grants.refresh_token = true;
+        let oauth2 = ServerBuilder::new(config)
+            .with_storage(setup_storage().await)
+            .with_token_issuance_policy(Arc::new(ReadOnlyPasswords))
+            .with_claims_enricher(Arc::new(ScopeEcho))
+            .with_endpoints([EndpointGroup::OAuth])
+            .build()
+            .await
+            .expect("build server");
+        test::init_service(App::new().configure(move |cfg| oauth2.configure(cfg))).await
+    }};
+}
+
+#[actix_web::test]
+async fn policies_narrow_scope_and_add_claims() {
+    let app = init_app!();
+
+    let req = test::TestRequest::post()
+        .uri("/oauth/token")
+        .set_form([
+            ("grant_type", "password"),
+            ("client_id", "app"),
+            ("client_secret", "app_secret"),
+            ("username", "alice"),
+            ("password", "correct-horse"),
+            ("scope", "read write"),
+        ])
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["scope"], "read");
+    let claims = jwt_payload(body["access_token"].as_str().expect("access token"));
+    assert_eq!(claims["scope"], "read");
+    assert_eq!(claims["enriched_scope"], "read");
+    assert_eq!(claims["grant"], "password");
+
+    // Refreshes are checked too, and the refresh token carries the narrowed scope.
+    let req = test::TestRequest::post()
+        .uri("/oauth/token")
+        .set_form([
+            ("grant_type", "refresh_token"),
+            ("client_id", "app"),
+            ("client_secret", "app_secret"),
+            (
+                "refresh_token",
+                body["refresh_token"].as_str().expect("refresh token"),
+            ),
+        ])
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["scope"], "read");
+    let claims = jwt_payload(body["access_token"].as_str().expect("access token"));
+    assert_eq!(claims["grant"], "refresh_token");
+
+    // Client tokens keep their scope.
+    let req = test::TestRequest::post()
+        .uri("/oauth/token")
+        .set_form([
+            ("grant_type", "client_credentials"),
+            ("client_id", "app"),
+            ("client_secret", "app_secret"),
+            ("scope", "read write"),
+        ])
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["scope"], "read write");
+    let claims = jwt_payload(body["access_token"].as_str().expect("access token"));
+    assert_eq!(claims["grant"], "client_credentials");
+}
+
+#[actix_web::test]
+async fn policies_deny_issuance() {
+    let app = init_app!();
+
+    let req = test::TestRequest::post()
+        .uri("/oauth/token")
+        .set_form([
+            ("grant_type", "client_credentials"),
+            ("client_id", "blocked"),
+            ("client_secret", "blocked_secret"),
+        ])
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_client_error(), "{}", resp.status());
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "access_denied");
+    assert_eq!(body["error_description"], "client is suspended");
+
+    // Scopes that cannot be granted at all are rejected.
+    let req = test::TestRequest::post()
+        .uri("/oauth/token")
+        .set_form([
+            ("grant_type", "password"),
+            ("client_id", "app"),
+            ("client_secret", "app_secret"),
+            ("username", "alice"),
+            ("password", "correct-horse"),
+            ("scope", "write"),
+        ])
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "invalid_scope");
+}
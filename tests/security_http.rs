@@ -1,9 +1,12 @@
 use actix::{Actor, Addr};
 use actix_web::{test, web, App};
 
-use oauth2_core::{Client, OAuth2Error, TokenResponse, User};
+use oauth2_core::{Client, IssuerKeys, IssuerUrls, OAuth2Error, TokenResponse, User};
 use oauth2_observability::Metrics;
 
+#[path = "support/mod.rs"]
+mod support;
+
 fn s256_challenge(verifier: &str) -> String {
     use base64::{engine::general_purpose, Engine as _};
     use sha2::{Digest, Sha256};
@@ -29,7 +32,7 @@ async fn setup_context(
     Addr<oauth2_actix::actors::TokenActor>,
     Addr<oauth2_actix::actors::ClientActor>,
     Addr<oauth2_actix::actors::AuthActor>,
-    String,
+    IssuerKeys,
     Metrics,
 ) {
     let storage = oauth2_storage_factory::create_storage("sqlite::memory:")
@@ -38,30 +41,33 @@ async fn setup_context(
     storage.init().await.expect("init storage");
     storage.save_client(&client).await.expect("save client");
 
-    // The authorize endpoint currently auto-approves with a fixed mock user_id ("user_123").
-    // SQL backends enforce an FK from authorization_codes.user_id -> users.id, so we must ensure
-    // this user exists for authorize() to succeed.
+    // Codes are issued to the signed-in user (see `support::signed_in`). SQL backends enforce
+    // an FK from authorization_codes.user_id -> users.id, so the user must exist.
     let now = chrono::Utc::now();
     let user = User {
-        id: "user_123".to_string(),
-        username: "user_123".to_string(),
+        id: "alice".to_string(),
+        username: "alice".to_string(),
         password_hash: "not_used_in_security_http_tests".to_string(),
-        email: "user_123@example.test".to_string(),
+        email: "alice@example.test".to_string(),
+        email_verified: false,
         enabled: true,
         created_at: now,
         updated_at: now,
+        tenant_id: None,
     };
     storage.save_user(&user).await.expect("save user");
 
     let jwt_secret = "test_jwt_secret".to_string();
+    let issuer_keys = IssuerKeys::from_secret(jwt_secret.clone());
     let metrics = Metrics::new().expect("metrics");
 
-    let token_actor =
-        oauth2_actix::actors::TokenActor::new(storage.clone(), jwt_secret.clone()).start();
+    let token_actor = oauth2_actix::actors::TokenActor::new(storage.clone(), jwt_secret)
+        .with_issuer_keys(issuer_keys.clone())
+        .start();
     let client_actor = oauth2_actix::actors::ClientActor::new(storage.clone()).start();
     let auth_actor = oauth2_actix::actors::AuthActor::new(storage.clone()).start();
 
-    (token_actor, client_actor, auth_actor, jwt_secret, metrics)
+    (token_actor, client_actor, auth_actor, issuer_keys, metrics)
 }
 
 #[actix_web::test]
@@ -75,13 +81,13 @@ async fn authorize_rejects_unregistered_redirect_uri() {
         "test".to_string(),
     );
 
-    let (token_actor, client_actor, auth_actor, jwt_secret, metrics) = setup_context(client).await;
+    let (token_actor, client_actor, auth_actor, issuer_keys, metrics) = setup_context(client).await;
     let app = test::init_service(
         App::new()
             .app_data(web::Data::new(token_actor))
             .app_data(web::Data::new(client_actor))
             .app_data(web::Data::new(auth_actor))
-            .app_data(web::Data::new(jwt_secret))
+            .app_data(web::Data::new(issuer_keys))
             .app_data(web::Data::new(metrics))
             .service(
                 web::scope("/oauth")
@@ -132,13 +138,13 @@ async fn authorize_rejects_implicit_response_type() {
         "test".to_string(),
     );
 
-    let (token_actor, client_actor, auth_actor, jwt_secret, metrics) = setup_context(client).await;
+    let (token_actor, client_actor, auth_actor, issuer_keys, metrics) = setup_context(client).await;
     let app = test::init_service(
         App::new()
             .app_data(web::Data::new(token_actor))
             .app_data(web::Data::new(client_actor))
             .app_data(web::Data::new(auth_actor))
-            .app_data(web::Data::new(jwt_secret))
+            .app_data(web::Data::new(issuer_keys))
             .app_data(web::Data::new(metrics))
             .service(
                 web::scope("/oauth")
@@ -168,12 +174,26 @@ async fn authorize_rejects_implicit_response_type() {
 
     let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
     let challenge = s256_challenge(verifier);
-    let req = test::TestRequest::get().uri(&format!("/oauth/authorize?response_type=token&client_id=client_a&redirect_uri=https%3A%2F%2Fgood.example%2Fcb&scope=read&code_challenge={challenge}&code_challenge_method=S256")).to_request();
+    let req = test::TestRequest::get().uri(&format!("/oauth/authorize?response_type=token&client_id=client_a&redirect_uri=https%3A%2F%2Fgood.example%2Fcb&scope=read&state=xyz&code_challenge={challenge}&code_challenge_method=S256")).to_request();
     let resp = test::call_service(&app, req).await;
 
-    assert_eq!(resp.status(), 400);
-    let body: OAuth2Error = test::read_body_json(resp).await;
-    assert_eq!(body.error, "invalid_request");
+    // The redirect URI is registered, so the error goes back to the client.
+    assert_eq!(resp.status(), 302);
+    let location = resp
+        .headers()
+        .get("Location")
+        .and_then(|v| v.to_str().ok())
+        .expect("Location header");
+    assert!(location.starts_with("https://good.example/cb?"));
+    assert_eq!(
+        extract_query_param(location, "error").as_deref(),
+        Some("unsupported_response_type")
+    );
+    assert_eq!(
+        extract_query_param(location, "state").as_deref(),
+        Some("xyz")
+    );
+    assert_eq!(extract_query_param(location, "code"), None);
 }
 
 #[actix_web::test]
@@ -187,13 +207,13 @@ async fn token_client_credentials_rejects_invalid_secret() {
         "test".to_string(),
     );
 
-    let (token_actor, client_actor, auth_actor, jwt_secret, metrics) = setup_context(client).await;
+    let (token_actor, client_actor, auth_actor, issuer_keys, metrics) = setup_context(client).await;
     let app = test::init_service(
         App::new()
             .app_data(web::Data::new(token_actor))
             .app_data(web::Data::new(client_actor))
             .app_data(web::Data::new(auth_actor))
-            .app_data(web::Data::new(jwt_secret))
+            .app_data(web::Data::new(issuer_keys))
             .app_data(web::Data::new(metrics))
             .service(
                 web::scope("/oauth")
@@ -249,13 +269,13 @@ async fn token_response_has_no_store_headers() {
         "test".to_string(),
     );
 
-    let (token_actor, client_actor, auth_actor, jwt_secret, metrics) = setup_context(client).await;
+    let (token_actor, client_actor, auth_actor, issuer_keys, metrics) = setup_context(client).await;
     let app = test::init_service(
         App::new()
             .app_data(web::Data::new(token_actor))
             .app_data(web::Data::new(client_actor))
             .app_data(web::Data::new(auth_actor))
-            .app_data(web::Data::new(jwt_secret))
+            .app_data(web::Data::new(issuer_keys))
             .app_data(web::Data::new(metrics))
             .service(
                 web::scope("/oauth")
@@ -324,13 +344,13 @@ async fn authorize_requires_pkce_s256() {
         "test".to_string(),
     );
 
-    let (token_actor, client_actor, auth_actor, jwt_secret, metrics) = setup_context(client).await;
+    let (token_actor, client_actor, auth_actor, issuer_keys, metrics) = setup_context(client).await;
     let app = test::init_service(
         App::new()
             .app_data(web::Data::new(token_actor))
             .app_data(web::Data::new(client_actor))
             .app_data(web::Data::new(auth_actor))
-            .app_data(web::Data::new(jwt_secret))
+            .app_data(web::Data::new(issuer_keys))
             .app_data(web::Data::new(metrics))
             .service(
                 web::scope("/oauth")
@@ -361,10 +381,18 @@ async fn authorize_requires_pkce_s256() {
     // Missing PKCE parameters should be rejected.
     let req = test::TestRequest::get().uri("/oauth/authorize?response_type=code&client_id=client_ac&redirect_uri=https%3A%2F%2Fgood.example%2Fcb&scope=read").to_request();
     let resp = test::call_service(&app, req).await;
-    assert_eq!(resp.status(), 400);
+    assert_eq!(resp.status(), 302);
 
-    let body: OAuth2Error = test::read_body_json(resp).await;
-    assert_eq!(body.error, "invalid_request");
+    let location = resp
+        .headers()
+        .get("Location")
+        .and_then(|v| v.to_str().ok())
+        .expect("Location header");
+    assert_eq!(
+        extract_query_param(location, "error").as_deref(),
+        Some("invalid_request")
+    );
+    assert_eq!(extract_query_param(location, "code"), None);
 }
 
 #[actix_web::test]
@@ -378,13 +406,15 @@ async fn pkce_allows_public_exchange_and_prevents_downgrade() {
         "test".to_string(),
     );
 
-    let (token_actor, client_actor, auth_actor, jwt_secret, metrics) = setup_context(client).await;
+    let (token_actor, client_actor, auth_actor, issuer_keys, metrics) = setup_context(client).await;
     let app = test::init_service(
         App::new()
+            .wrap_fn(support::signed_in("alice", &[("client_pkce", "read")]))
+            .wrap(support::session_middleware())
             .app_data(web::Data::new(token_actor))
             .app_data(web::Data::new(client_actor))
             .app_data(web::Data::new(auth_actor))
-            .app_data(web::Data::new(jwt_secret))
+            .app_data(web::Data::new(issuer_keys))
             .app_data(web::Data::new(metrics))
             .service(
                 web::scope("/oauth")
@@ -495,13 +525,15 @@ async fn token_authorization_code_exchange_allows_missing_redirect_uri() {
         "test".to_string(),
     );
 
-    let (token_actor, client_actor, auth_actor, jwt_secret, metrics) = setup_context(client).await;
+    let (token_actor, client_actor, auth_actor, issuer_keys, metrics) = setup_context(client).await;
     let app = test::init_service(
         App::new()
+            .wrap_fn(support::signed_in("alice", &[("client_oauth21", "read")]))
+            .wrap(support::session_middleware())
             .app_data(web::Data::new(token_actor))
             .app_data(web::Data::new(client_actor))
             .app_data(web::Data::new(auth_actor))
-            .app_data(web::Data::new(jwt_secret))
+            .app_data(web::Data::new(issuer_keys))
             .app_data(web::Data::new(metrics))
             .service(
                 web::scope("/oauth")
@@ -579,13 +611,18 @@ async fn token_authorization_code_exchange_rejects_wrong_redirect_uri_when_provi
         "test".to_string(),
     );
 
-    let (token_actor, client_actor, auth_actor, jwt_secret, metrics) = setup_context(client).await;
+    let (token_actor, client_actor, auth_actor, issuer_keys, metrics) = setup_context(client).await;
     let app = test::init_service(
         App::new()
+            .wrap_fn(support::signed_in(
+                "alice",
+                &[("client_redirect_mismatch", "read")],
+            ))
+            .wrap(support::session_middleware())
             .app_data(web::Data::new(token_actor))
             .app_data(web::Data::new(client_actor))
             .app_data(web::Data::new(auth_actor))
-            .app_data(web::Data::new(jwt_secret))
+            .app_data(web::Data::new(issuer_keys))
             .app_data(web::Data::new(metrics))
             .service(
                 web::scope("/oauth")
@@ -667,13 +704,15 @@ async fn authorization_code_cannot_be_reused() {
         "test".to_string(),
     );
 
-    let (token_actor, client_actor, auth_actor, jwt_secret, metrics) = setup_context(client).await;
+    let (token_actor, client_actor, auth_actor, issuer_keys, metrics) = setup_context(client).await;
     let app = test::init_service(
         App::new()
+            .wrap_fn(support::signed_in("alice", &[("client_reuse", "read")]))
+            .wrap(support::session_middleware())
             .app_data(web::Data::new(token_actor))
             .app_data(web::Data::new(client_actor))
             .app_data(web::Data::new(auth_actor))
-            .app_data(web::Data::new(jwt_secret))
+            .app_data(web::Data::new(issuer_keys))
             .app_data(web::Data::new(metrics))
             .service(
                 web::scope("/oauth")
@@ -767,13 +806,13 @@ async fn well_known_metadata_matches_supported_flows() {
         "test".to_string(),
     );
 
-    let (token_actor, client_actor, auth_actor, jwt_secret, metrics) = setup_context(client).await;
+    let (token_actor, client_actor, auth_actor, issuer_keys, metrics) = setup_context(client).await;
     let app = test::init_service(
         App::new()
             .app_data(web::Data::new(token_actor))
             .app_data(web::Data::new(client_actor))
             .app_data(web::Data::new(auth_actor))
-            .app_data(web::Data::new(jwt_secret))
+            .app_data(web::Data::new(issuer_keys))
             .app_data(web::Data::new(metrics))
             .service(
                 web::scope("/oauth")
@@ -845,13 +884,15 @@ async fn authorize_redirect_has_clickjacking_and_referrer_headers() {
         "test".to_string(),
     );
 
-    let (token_actor, client_actor, auth_actor, jwt_secret, metrics) = setup_context(client).await;
+    let (token_actor, client_actor, auth_actor, issuer_keys, metrics) = setup_context(client).await;
     let app = test::init_service(
         App::new()
+            .wrap_fn(support::signed_in("alice", &[("client_hdr", "read")]))
+            .wrap(support::session_middleware())
             .app_data(web::Data::new(token_actor))
             .app_data(web::Data::new(client_actor))
             .app_data(web::Data::new(auth_actor))
-            .app_data(web::Data::new(jwt_secret))
+            .app_data(web::Data::new(issuer_keys))
             .app_data(web::Data::new(metrics))
             .service(
                 web::scope("/oauth")
@@ -918,13 +959,15 @@ async fn pkce_rejects_short_verifier() {
         "test".to_string(),
     );
 
-    let (token_actor, client_actor, auth_actor, jwt_secret, metrics) = setup_context(client).await;
+    let (token_actor, client_actor, auth_actor, issuer_keys, metrics) = setup_context(client).await;
     let app = test::init_service(
         App::new()
+            .wrap_fn(support::signed_in("alice", &[("client_short", "read")]))
+            .wrap(support::session_middleware())
             .app_data(web::Data::new(token_actor))
             .app_data(web::Data::new(client_actor))
             .app_data(web::Data::new(auth_actor))
-            .app_data(web::Data::new(jwt_secret))
+            .app_data(web::Data::new(issuer_keys))
             .app_data(web::Data::new(metrics))
             .service(
                 web::scope("/oauth")
@@ -984,3 +1027,402 @@ async fn pkce_rejects_short_verifier() {
     let body: OAuth2Error = test::read_body_json(resp).await;
     assert_eq!(body.error, "invalid_grant");
 }
+
+#[actix_web::test]
+async fn introspection_returns_rfc7662_fields_and_bare_inactive_responses() {
+    let client = Client::new(
+        "client_cc".to_string(),
+        "secret_cc".to_string(),
+        vec!["https://unused.example/cb".to_string()],
+        vec!["client_credentials".to_string()],
+        "read".to_string(),
+        "test".to_string(),
+    );
+
+    let (token_actor, client_actor, auth_actor, issuer_keys, metrics) = setup_context(client).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(token_actor))
+            .app_data(web::Data::new(client_actor))
+            .app_data(web::Data::new(auth_actor))
+            .app_data(web::Data::new(issuer_keys))
+            .app_data(web::Data::new(metrics))
+            .service(
+                web::scope("/oauth")
+                    .route(
+                        "/token",
+                        web::post().to(oauth2_actix::handlers::oauth::token),
+                    )
+                    .route(
+                        "/introspect",
+                        web::post().to(oauth2_actix::handlers::token::introspect),
+                    )
+                    .route(
+                        "/revoke",
+                        web::post().to(oauth2_actix::handlers::token::revoke),
+                    ),
+            ),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/oauth/token")
+        .set_form([
+            ("grant_type", "client_credentials"),
+            ("client_id", "client_cc"),
+            ("client_secret", "secret_cc"),
+            ("scope", "read"),
+        ])
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    let token: TokenResponse = test::read_body_json(resp).await;
+
+    let req = test::TestRequest::post()
+        .uri("/oauth/introspect")
+        .set_form([
+            ("token", token.access_token.as_str()),
+            ("client_id", "client_cc"),
+            ("client_secret", "secret_cc"),
+        ])
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["active"], true);
+    assert_eq!(body["client_id"], "client_cc");
+    assert_eq!(body["token_type"], "Bearer");
+    assert_eq!(body["aud"], "client_cc");
+    for field in ["exp", "iat", "nbf"] {
+        assert!(body[field].is_i64(), "missing {field}: {body}");
+    }
+    for field in ["sub", "iss", "jti"] {
+        assert!(body[field].is_string(), "missing {field}: {body}");
+    }
+
+    // Unknown tokens are inactive, not errors.
+    let req = test::TestRequest::post()
+        .uri("/oauth/introspect")
+        .set_form([
+            ("token", "not-a-token"),
+            ("client_id", "client_cc"),
+            ("client_secret", "secret_cc"),
+        ])
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body, serde_json::json!({ "active": false }));
+
+    // Revoked tokens expose nothing beyond `active`.
+    let req = test::TestRequest::post()
+        .uri("/oauth/revoke")
+        .set_form([
+            ("token", token.access_token.as_str()),
+            ("client_id", "client_cc"),
+            ("client_secret", "secret_cc"),
+        ])
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::post()
+        .uri("/oauth/introspect")
+        .set_form([
+            ("token", token.access_token.as_str()),
+            ("client_id", "client_cc"),
+            ("client_secret", "secret_cc"),
+        ])
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body, serde_json::json!({ "active": false }));
+}
+
+#[actix_web::test]
+async fn discovery_urls_follow_issuer_configuration() {
+    async fn discover(urls: Option<IssuerUrls>, headers: &[(&str, &str)]) -> serde_json::Value {
+        let mut app = App::new();
+        if let Some(urls) = urls {
+            app = app.app_data(web::Data::new(urls));
+        }
+        let app = test::init_service(app.route(
+            "/.well-known/openid-configuration",
+            web::get().to(oauth2_actix::handlers::wellknown::openid_configuration),
+        ))
+        .await;
+
+        let mut req = test::TestRequest::get().uri("/.well-known/openid-configuration");
+        for header in headers {
+            req = req.insert_header(*header);
+        }
+        test::call_and_read_body_json(&app, req.to_request()).await
+    }
+
+    let spoofed = [
+        ("host", "internal:8080"),
+        ("x-forwarded-proto", "https"),
+        ("x-forwarded-host", "evil.example"),
+    ];
+
+    let body = discover(
+        Some(IssuerUrls::new(Some(
+            "https://auth.example.com".to_string(),
+        ))),
+        &spoofed,
+    )
+    .await;
+    assert_eq!(body["issuer"], "https://auth.example.com");
+    assert_eq!(
+        body["token_endpoint"],
+        "https://auth.example.com/oauth/token"
+    );
+    assert_eq!(
+        body["end_session_endpoint"],
+        "https://auth.example.com/oauth/logout"
+    );
+    assert_eq!(body["authorization_response_iss_parameter_supported"], true);
+
+    // Without an issuer, forwarded headers are only honoured when trusted.
+    let body = discover(None, &spoofed).await;
+    assert_eq!(body["issuer"], "http://internal:8080");
+    assert_eq!(
+        body["authorization_endpoint"],
+        "http://internal:8080/oauth/authorize"
+    );
+
+    let body = discover(
+        Some(IssuerUrls::new(None).with_forwarded_headers(true)),
+        &spoofed,
+    )
+    .await;
+    assert_eq!(body["issuer"], "https://evil.example");
+}
+
+#[actix_web::test]
+async fn discovery_is_cacheable_and_revalidates_on_key_rotation() {
+    use actix_web::http::header;
+    use oauth2_actix::handlers::wellknown::MetadataCaching;
+    use std::time::Duration;
+
+    async fn discover(keys: IssuerKeys, if_none_match: Option<&str>) -> (u16, String, String) {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(keys))
+                .app_data(web::Data::new(MetadataCaching {
+                    max_age: Duration::from_secs(600),
+                }))
+                .route(
+                    "/.well-known/openid-configuration",
+                    web::get().to(oauth2_actix::handlers::wellknown::openid_configuration),
+                ),
+        )
+        .await;
+
+        let mut req = test::TestRequest::get().uri("/.well-known/openid-configuration");
+        if let Some(tag) = if_none_match {
+            req = req.insert_header((header::IF_NONE_MATCH, tag));
+        }
+        let resp = test::call_service(&app, req.to_request()).await;
+        let header = |name| {
+            resp.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+                .to_string()
+        };
+        (
+            resp.status().as_u16(),
+            header(header::ETAG),
+            header(header::CACHE_CONTROL),
+        )
+    }
+
+    let (status, etag, cache_control) = discover(IssuerKeys::from_secret("secret"), None).await;
+    assert_eq!(status, 200);
+    assert!(etag.starts_with('"'), "strong etag: {etag}");
+    assert_eq!(cache_control, "public, max-age=600");
+
+    // A current copy is revalidated without resending the document.
+    let (status, revalidated, _) = discover(IssuerKeys::from_secret("secret"), Some(&etag)).await;
+    assert_eq!(status, 304);
+    assert_eq!(revalidated, etag);
+    let weak = format!("W/{etag}");
+    let (status, _, _) = discover(IssuerKeys::from_secret("secret"), Some(&weak)).await;
+    assert_eq!(status, 304);
+
+    // Rotating the signing key busts cached copies.
+    let (status, rotated, _) = discover(IssuerKeys::from_secret("rotated"), Some(&etag)).await;
+    assert_eq!(status, 200);
+    assert_ne!(rotated, etag);
+}
+
+#[actix_web::test]
+async fn authorize_errors_redirect_only_to_registered_uris() {
+    let client = Client::new(
+        "client_redirect_errors".to_string(),
+        "secret".to_string(),
+        vec!["https://good.example/cb?tenant=acme".to_string()],
+        vec!["authorization_code".to_string()],
+        "read".to_string(),
+        "test".to_string(),
+    );
+    let (_token_actor, client_actor, auth_actor, _issuer_keys, metrics) =
+        setup_context(client).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(client_actor))
+            .app_data(web::Data::new(auth_actor))
+            .app_data(web::Data::new(metrics))
+            .route(
+                "/oauth/authorize",
+                web::get().to(oauth2_actix::handlers::oauth::authorize),
+            ),
+    )
+    .await;
+
+    let challenge = s256_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk");
+    let authorize = |redirect_uri: &str, scope: &str| {
+        test::TestRequest::get()
+            .uri(&format!(
+                "/oauth/authorize?response_type=code&client_id=client_redirect_errors&redirect_uri={redirect_uri}&scope={scope}&state=s%201&code_challenge={challenge}&code_challenge_method=S256"
+            ))
+            .to_request()
+    };
+
+    // Invalid scope: reported to the client, keeping its own query parameters.
+    let resp = test::call_service(
+        &app,
+        authorize("https%3A%2F%2Fgood.example%2Fcb%3Ftenant%3Dacme", "admin"),
+    )
+    .await;
+    assert_eq!(resp.status(), 302);
+    let location = resp
+        .headers()
+        .get("Location")
+        .and_then(|v| v.to_str().ok())
+        .expect("Location header")
+        .to_string();
+    assert!(location.starts_with("https://good.example/cb?tenant=acme&error=invalid_scope"));
+    assert_eq!(
+        extract_query_param(&location, "state").as_deref(),
+        Some("s+1")
+    );
+    assert!(extract_query_param(&location, "error_description").is_some());
+    let cache_control = resp
+        .headers()
+        .get("Cache-Control")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    assert!(cache_control.contains("no-store"));
+
+    // The same error with an unregistered redirect_uri is never redirected.
+    let resp = test::call_service(
+        &app,
+        authorize("https%3A%2F%2Fgood.example%2Fcb%3Ftenant%3Devil", "admin"),
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+    assert!(resp.headers().get("Location").is_none());
+    let body: OAuth2Error = test::read_body_json(resp).await;
+    assert_eq!(body.error, "invalid_request");
+
+    // Unknown clients are never redirected either.
+    let req = test::TestRequest::get()
+        .uri("/oauth/authorize?response_type=code&client_id=nobody&redirect_uri=https%3A%2F%2Fgood.example%2Fcb%3Ftenant%3Dacme&scope=admin")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_client_error());
+    assert!(resp.headers().get("Location").is_none());
+}
+
+#[actix_web::test]
+async fn authorization_responses_identify_the_issuer() {
+    let client = Client::new(
+        "client_iss".to_string(),
+        "secret".to_string(),
+        vec!["https://good.example/cb".to_string()],
+        vec!["authorization_code".to_string()],
+        "read".to_string(),
+        "test".to_string(),
+    );
+    let (_token_actor, client_actor, auth_actor, _issuer_keys, metrics) =
+        setup_context(client).await;
+    let client_actor = web::Data::new(client_actor);
+    let auth_actor = web::Data::new(auth_actor);
+    let metrics = web::Data::new(metrics);
+
+    let challenge = s256_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk");
+    let location = |resp: actix_web::dev::ServiceResponse| {
+        assert_eq!(resp.status(), 302);
+        resp.headers()
+            .get("Location")
+            .and_then(|v| v.to_str().ok())
+            .expect("Location header")
+            .to_string()
+    };
+
+    // With `server.issuer` configured, `iss` is the issuer on success and on error.
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(support::signed_in("alice", &[("client_iss", "read")]))
+            .wrap(support::session_middleware())
+            .app_data(client_actor.clone())
+            .app_data(auth_actor.clone())
+            .app_data(metrics.clone())
+            .app_data(web::Data::new(IssuerUrls::new(Some(
+                "https://auth.example.com".to_string(),
+            ))))
+            .route(
+                "/oauth/authorize",
+                web::get().to(oauth2_actix::handlers::oauth::authorize),
+            ),
+    )
+    .await;
+    for scope in ["read", "admin"] {
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/oauth/authorize?response_type=code&client_id=client_iss&redirect_uri=https%3A%2F%2Fgood.example%2Fcb&scope={scope}&state=xyz&code_challenge={challenge}&code_challenge_method=S256"
+            ))
+            .insert_header(("host", "internal:8080"))
+            .to_request();
+        let location = location(test::call_service(&app, req).await);
+        assert_eq!(
+            extract_query_param(&location, "iss").as_deref(),
+            Some("https%3A%2F%2Fauth.example.com"),
+            "{location}"
+        );
+        assert_eq!(
+            extract_query_param(&location, "state").as_deref(),
+            Some("xyz")
+        );
+    }
+
+    // Otherwise it is derived from the request, exactly as discovery reports it.
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(support::signed_in("alice", &[("client_iss", "read")]))
+            .wrap(support::session_middleware())
+            .app_data(client_actor)
+            .app_data(auth_actor)
+            .app_data(metrics)
+            .route(
+                "/oauth/authorize",
+                web::get().to(oauth2_actix::handlers::oauth::authorize),
+            ),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri(&format!(
+            "/oauth/authorize?response_type=code&client_id=client_iss&redirect_uri=https%3A%2F%2Fgood.example%2Fcb&scope=read&code_challenge={challenge}&code_challenge_method=S256"
+        ))
+        .insert_header(("host", "internal:8080"))
+        .to_request();
+    let location = location(test::call_service(&app, req).await);
+    assert!(extract_query_param(&location, "code").is_some());
+    assert_eq!(
+        extract_query_param(&location, "iss").as_deref(),
+        Some("http%3A%2F%2Finternal%3A8080")
+    );
+}
@@ -1,6 +1,8 @@
-mod common;
+use std::sync::Arc;
+use std::time::Duration;
 
 use oauth2_ports::Storage;
+use oauth2_storage_factory::CachedStorage;
 use oauth2_storage_sqlx::SqlxStorage;
 
 /// Contract tests for the default SQLx backend.
@@ -22,5 +24,22 @@ async fn sqlx_storage_contract() -> Result<(), Box<dyn std::error::Error>> {
         .await
         .map_err(|e| std::io::Error::other(e.to_string()))?;
 
-    common::run_storage_contract(&storage).await
+    oauth2_storage_tests::run_storage_contract(&storage).await
+}
+
+/// The cache must be invisible: every read after a write sees the write.
+#[tokio::test]
+async fn cached_sqlx_storage_contract() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let db_path = dir.path().join("oauth2_test.db");
+    let url = format!("sqlite://{}?mode=rwc", db_path.display());
+
+    let inner = SqlxStorage::new(&url).await?;
+    let storage = CachedStorage::new(Arc::new(inner), 100, Duration::from_secs(60));
+    storage
+        .init()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    oauth2_storage_tests::run_storage_contract(&storage).await
 }
@@ -0,0 +1,153 @@
+//! Fixtures shared by the HTTP integration tests: in-memory storage seeded with the
+//! clients and users a test needs, and the actors and data the OAuth handlers read.
+//!
+//! Each test binary uses a different part of this module.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use actix::Actor;
+use actix_session::storage::CookieSessionStore;
+use actix_session::{SessionExt, SessionMiddleware};
+use actix_web::cookie::Key;
+use actix_web::dev::{Service, ServiceRequest};
+use actix_web::web;
+
+use oauth2_actix::actors::{AuthActor, ClientActor, TokenActor};
+use oauth2_core::{hash_password, Client, User};
+use oauth2_observability::Metrics;
+use oauth2_ports::DynStorage;
+
+/// Secret tokens are signed with unless a test configures issuer keys of its own.
+pub const JWT_SECRET: &str = "test_jwt_secret";
+
+/// Redirect URI of the `first_party` client.
+pub const FIRST_PARTY_REDIRECT_URI: &str = "https://app.example/cb";
+
+/// A fresh, initialized in-memory SQLite storage.
+pub async fn memory_storage() -> DynStorage {
+    let storage = oauth2_storage_factory::create_storage("sqlite::memory:")
+        .await
+        .expect("create storage");
+    storage.init().await.expect("init storage");
+    storage
+}
+
+/// A confidential client named after its id, with the secret `{client_id}_secret`.
+pub fn client(client_id: &str, redirect_uri: &str, grant_types: &[&str], scope: &str) -> Client {
+    Client::new(
+        client_id.to_string(),
+        format!("{client_id}_secret"),
+        vec![redirect_uri.to_string()],
+        grant_types.iter().map(|grant| grant.to_string()).collect(),
+        scope.to_string(),
+        client_id.to_string(),
+    )
+}
+
+pub async fn save_client(storage: &DynStorage, client: &Client) {
+    storage.save_client(client).await.expect("save client");
+}
+
+/// Memory storage with the `first_party` client (secret `first_party_secret`).
+pub async fn first_party_storage(grant_types: &[&str], scope: &str) -> DynStorage {
+    let storage = memory_storage().await;
+    save_client(
+        &storage,
+        &client("first_party", FIRST_PARTY_REDIRECT_URI, grant_types, scope),
+    )
+    .await;
+    storage
+}
+
+/// Save an enabled user with `password`, its email at `example.test`.
+pub async fn save_user(storage: &DynStorage, username: &str, password: &str) -> User {
+    let user = User::new(
+        username.to_string(),
+        hash_password(password).expect("hash"),
+        format!("{username}@example.test"),
+    );
+    storage.save_user(&user).await.expect("save user");
+    user
+}
+
+/// Save a user whose id is its username, for tests that need a fixed user id.
+pub async fn save_user_with_id(storage: &DynStorage, id: &str, password_hash: &str, enabled: bool) {
+    let now = chrono::Utc::now();
+    storage
+        .save_user(&User {
+            id: id.to_string(),
+            username: id.to_string(),
+            password_hash: password_hash.to_string(),
+            email: format!("{id}@example.test"),
+            email_verified: false,
+            enabled,
+            created_at: now,
+            updated_at: now,
+            tenant_id: None,
+        })
+        .await
+        .expect("save user");
+}
+
+/// Cookie sessions for apps mounting the OAuth handlers directly, e.g. to sign in with
+/// [`signed_in`].
+pub fn session_middleware() -> SessionMiddleware<CookieSessionStore> {
+    SessionMiddleware::builder(CookieSessionStore::default(), Key::generate())
+        .cookie_secure(false)
+        .build()
+}
+
+/// Sign every request in as `user_id`, who has already approved each `(client_id, scope)`
+/// on the consent page: `App::new().wrap_fn(signed_in(..)).wrap(session_middleware())`.
+pub fn signed_in<S>(
+    user_id: &str,
+    approvals: &[(&str, &str)],
+) -> impl Fn(ServiceRequest, &S) -> S::Future + Clone
+where
+    S: Service<ServiceRequest>,
+{
+    let user_id = user_id.to_string();
+    let consents: HashMap<String, String> = approvals
+        .iter()
+        .map(|(client_id, scope)| (client_id.to_string(), scope.to_string()))
+        .collect();
+    move |req, service| {
+        let session = req.get_session();
+        session.insert("user_id", &user_id).expect("sign in");
+        session.insert("consents", &consents).expect("approve");
+        service.call(req)
+    }
+}
+
+/// A token actor signing with [`JWT_SECRET`], to be customized and passed to
+/// [`oauth_data`].
+pub fn token_actor(storage: &DynStorage) -> TokenActor {
+    TokenActor::new(storage.clone(), JWT_SECRET.to_string())
+}
+
+/// Starts `token_actor`, `auth_actor` and a client actor, and registers them with the
+/// storage and fresh metrics as app data: `App::new().configure(oauth_data(..))`.
+pub fn oauth_data(
+    storage: &DynStorage,
+    token_actor: TokenActor,
+    auth_actor: AuthActor,
+) -> impl FnOnce(&mut web::ServiceConfig) {
+    let storage = storage.clone();
+    move |cfg| {
+        cfg.app_data(web::Data::new(token_actor.start()))
+            .app_data(web::Data::new(ClientActor::new(storage.clone()).start()))
+            .app_data(web::Data::new(auth_actor.start()))
+            .app_data(web::Data::new(Metrics::new().expect("metrics")))
+            .app_data(web::Data::new(storage));
+    }
+}
+
+/// [`oauth_data`] with the default token and auth actors.
+pub fn default_oauth_data(storage: &DynStorage) -> impl FnOnce(&mut web::ServiceConfig) {
+    oauth_data(
+        storage,
+        token_actor(storage),
+        AuthActor::new(storage.clone()),
+    )
+}